@@ -0,0 +1,16 @@
+/*! Converting <span style="font-variant:small-caps;">OpenMath</span> XML to the JSON encoding
+in the browser; see the README. */
+
+use openmath::{Document, OpenMath};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/// Converts an OMOBJ in XML to the JSON encoding; errors are returned as
+/// `{"error":"…"}`.
+#[wasm_bindgen]
+#[must_use]
+pub fn parse_xml_to_json(s: &str) -> String {
+    Document::<OpenMath>::parse_xml(s)
+        .map_err(|e| e.to_string())
+        .and_then(|doc| doc.to_json().map_err(|e| e.to_string()))
+        .unwrap_or_else(|error| serde_json::json!({ "error": error }).to_string())
+}
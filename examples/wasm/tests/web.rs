@@ -0,0 +1,19 @@
+use openmath_wasm_example::parse_xml_to_json;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[wasm_bindgen_test]
+fn converts() {
+    assert_eq!(
+        parse_xml_to_json(
+            r#"<OMOBJ><OMA><OMS cd="arith1" name="plus"/><OMI>1</OMI><OMV name="x"/></OMA></OMOBJ>"#
+        ),
+        r#"{"kind":"OMOBJ","openmath":"2.0","object":{"kind":"OMA","applicant":{"kind":"OMS","cd":"arith1","name":"plus"},"arguments":[{"kind":"OMI","integer":1},{"kind":"OMV","name":"x"}]}}"#
+    );
+}
+
+#[wasm_bindgen_test]
+fn reports_errors() {
+    let error: serde_json::Value =
+        serde_json::from_str(&parse_xml_to_json("<OMOBJ><OMI>x</OMI></OMOBJ>")).expect("is JSON");
+    assert!(error["error"].is_string());
+}
@@ -0,0 +1,73 @@
+//! Compares [`OpenMath`] with [`OmArena`]: the memory per node, and the time to parse XML
+//! into either.
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use openmath::{OMDeserializable, OpenMath, arena::OmArena, testing::CountingAlloc};
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc::new();
+
+/// The number of items in the generated fixture
+const ITEMS: usize = 50_000;
+
+/// A leaf-heavy term
+const ITEM: &str = r#"
+  <OMA>
+    <OMS cd="arith1" name="plus"/>
+    <OMA><OMS cd="arith1" name="times"/><OMI>2</OMI><OMV name="x"/></OMA>
+    <OMA><OMS cd="arith1" name="power"/><OMV name="y"/><OMI>3</OMI></OMA>
+    <OMF dec="1.5"/>
+    <OMSTR>text</OMSTR>
+    <OMBIND><OMS cd="fns1" name="lambda"/><OMBVAR><OMV name="z"/></OMBVAR><OMV name="z"/></OMBIND>
+  </OMA>"#;
+
+fn fixture() -> String {
+    let mut s = String::with_capacity(ITEMS * ITEM.len() + 64);
+    s.push_str(r#"<OMA><OMS cd="list1" name="list"/>"#);
+    for _ in 0..ITEMS {
+        s.push_str(ITEM);
+    }
+    s.push_str("\n</OMA>");
+    s
+}
+
+/// The bytes allocated by `f` and still live when it returns, along with its result
+fn allocated<R>(f: impl FnOnce() -> R) -> (usize, R) {
+    let (allocations, r) = GLOBAL.measure(f);
+    (allocations.live, r)
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn memory(input: &str) {
+    let (tree, om) = allocated(|| OpenMath::from_openmath_xml(input).expect("is valid"));
+    let (borrowed, arena) = allocated(|| OmArena::from(&om));
+    let nodes = arena.len() as f64;
+    let (read, parsed) = allocated(|| OmArena::from_openmath_xml(input).expect("is valid"));
+    assert_eq!(parsed.0.len(), arena.len());
+    println!("{} nodes; bytes per node:", arena.len());
+    println!("  OpenMath (borrowing strings): {:.1}", tree as f64 / nodes);
+    println!(
+        "  OmArena (borrowing strings):  {:.1}",
+        borrowed as f64 / nodes
+    );
+    println!("  OmArena (parsed, borrowing):  {:.1}", read as f64 / nodes);
+}
+
+fn arena(c: &mut Criterion) {
+    let input = fixture();
+    memory(&input);
+
+    let mut group = c.benchmark_group("arena");
+    group.sample_size(20);
+    group.throughput(Throughput::Bytes(input.len() as u64));
+    group.bench_function("OpenMath", |b| {
+        b.iter(|| OpenMath::from_openmath_xml(&input).expect("is valid"));
+    });
+    group.bench_function("OmArena", |b| {
+        b.iter(|| OmArena::from_openmath_xml(&input).expect("is valid"));
+    });
+    group.finish();
+}
+
+criterion_group!(benches, arena);
+criterion_main!(benches);
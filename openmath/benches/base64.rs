@@ -0,0 +1,85 @@
+//! Throughput of base64 for large [OMB](openmath::OMKind::OMB) payloads: the slice-based
+//! [`decode_slice`]/[`encode_into`], compared with the iterator adapters that the XML
+//! reader and writer used before, and the `base64` crate as a reference.
+
+use base64::Engine;
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use openmath::base64::{Base64Decodable, Base64Encodable, decode_slice, encode_into};
+
+/// The size of the decoded payload
+const SIZE: usize = 4 * 1024 * 1024;
+
+fn payload() -> Vec<u8> {
+    let mut seed = 0x2545_f491_u64;
+    (0..SIZE)
+        .map(|_| {
+            seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+            seed.to_be_bytes()[3]
+        })
+        .collect()
+}
+
+fn decode(c: &mut Criterion) {
+    let bytes = payload();
+    let encoded = base64::engine::general_purpose::STANDARD.encode(&bytes);
+    // as written by encoders that wrap lines
+    let wrapped = encoded
+        .as_bytes()
+        .chunks(76)
+        .map(|line| std::str::from_utf8(line).expect("is ASCII"))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut group = c.benchmark_group("base64_decode");
+    group.sample_size(20);
+    group.throughput(Throughput::Bytes(SIZE as u64));
+    group.bench_function("iterator", |b| {
+        b.iter(|| {
+            encoded
+                .bytes()
+                .decode_base64()
+                .flat()
+                .collect::<Result<Vec<u8>, _>>()
+                .expect("is valid")
+        });
+    });
+    group.bench_function("decode_slice", |b| {
+        b.iter(|| decode_slice(encoded.as_bytes()).expect("is valid"));
+    });
+    group.bench_function("decode_slice_wrapped", |b| {
+        b.iter(|| decode_slice(wrapped.as_bytes()).expect("is valid"));
+    });
+    group.bench_function("base64_crate", |b| {
+        b.iter(|| {
+            base64::engine::general_purpose::STANDARD
+                .decode(&encoded)
+                .expect("is valid")
+        });
+    });
+    group.finish();
+}
+
+fn encode(c: &mut Criterion) {
+    let bytes = payload();
+
+    let mut group = c.benchmark_group("base64_encode");
+    group.sample_size(20);
+    group.throughput(Throughput::Bytes(SIZE as u64));
+    group.bench_function("iterator", |b| {
+        b.iter(|| bytes.iter().copied().base64().into_string());
+    });
+    group.bench_function("encode_into", |b| {
+        b.iter(|| {
+            let mut s = String::new();
+            encode_into(&bytes, &mut s);
+            s
+        });
+    });
+    group.bench_function("base64_crate", |b| {
+        b.iter(|| base64::engine::general_purpose::STANDARD.encode(&bytes));
+    });
+    group.finish();
+}
+
+criterion_group!(benches, decode, encode);
+criterion_main!(benches);
@@ -0,0 +1,148 @@
+//! Compares deserializing a stream of small JSON and XML documents into [`OpenMath`]s
+//! with deserializing them into an [`Arena`]: the throughput, the number of allocations and
+//! the peak heap usage.
+//!
+//! The serde deserializer buffers the fields of every object (whose order JSON does not
+//! fix) in owned values, which dominates its allocations either way; and [`Arena`] reads
+//! JSON as an [`OpenMath`] first and then copies it.
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use openmath::{
+    OMDeserializable, OpenMath,
+    de::{Arena, OMFromSerde, OpenMathIn},
+    testing::CountingAlloc,
+};
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc::new();
+
+/// The number of documents in the generated stream
+const DOCUMENTS: usize = 20_000;
+
+/// The number of documents processed (and kept alive) together, like the payloads of one
+/// request
+const BATCH: usize = 100;
+
+/// A small JSON payload; the string contains an escape, so it can not be borrowed
+fn json(i: usize) -> String {
+    format!(
+        r#"{{"kind":"OMA","applicant":{{"kind":"OMS","cd":"arith1","name":"plus"}},"arguments":[
+          {{"kind":"OMI","integer":{i}}},
+          {{"kind":"OMA","applicant":{{"kind":"OMS","cd":"arith1","name":"times"}},"arguments":[{{"kind":"OMV","name":"x"}},{{"kind":"OMF","float":1.5}}]}},
+          {{"kind":"OMATTR","attributes":[[{{"kind":"OMS","cd":"meta","name":"note"}},{{"kind":"OMSTR","string":"item \"{i}\""}}]],"object":{{"kind":"OMV","name":"y"}}}}
+        ]}}"#
+    )
+}
+
+/// [`json`], as XML
+fn xml(i: usize) -> String {
+    format!(
+        r#"<OMA><OMS cd="arith1" name="plus"/>
+          <OMI>{i}</OMI>
+          <OMA><OMS cd="arith1" name="times"/><OMV name="x"/><OMF dec="1.5"/></OMA>
+          <OMATTR><OMATP><OMS cd="meta" name="note"/><OMSTR>item &quot;{i}&quot;</OMSTR></OMATP><OMV name="y"/></OMATTR>
+        </OMA>"#
+    )
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+    Json,
+    Xml,
+}
+
+/// Parses all `documents` as [`OpenMath`]s, in batches; returns the total number of
+/// arguments, to use the results
+fn openmath(documents: &[String], format: Format) -> usize {
+    let mut arguments = 0;
+    for batch in documents.chunks(BATCH) {
+        let parsed = batch
+            .iter()
+            .map(|d| match format {
+                Format::Json => serde_json::from_str::<OMFromSerde<OpenMath>>(d)
+                    .expect("is valid")
+                    .into_inner(),
+                Format::Xml => OpenMath::from_openmath_xml(d).expect("is valid"),
+            })
+            .collect::<Vec<_>>();
+        arguments += parsed
+            .iter()
+            .map(|o| match o {
+                OpenMath::OMA { arguments, .. } => arguments.len(),
+                _ => 0,
+            })
+            .sum::<usize>();
+    }
+    arguments
+}
+
+/// Like [`openmath`], but parses into `arena`, resetting it after every batch
+fn arena(documents: &[String], format: Format, arena: &mut Arena) -> usize {
+    let mut arguments = 0;
+    for batch in documents.chunks(BATCH) {
+        let parsed = batch
+            .iter()
+            .map(|d| match format {
+                Format::Json => arena
+                    .deserialize(&mut serde_json::Deserializer::from_str(d))
+                    .expect("is valid"),
+                Format::Xml => arena.from_openmath_xml(d).expect("is valid"),
+            })
+            .collect::<Vec<_>>();
+        arguments += parsed
+            .iter()
+            .map(|o| match o {
+                OpenMathIn::OMA { arguments, .. } => arguments.len(),
+                _ => 0,
+            })
+            .sum::<usize>();
+        drop(parsed);
+        arena.reset();
+    }
+    arguments
+}
+
+/// The number of allocations by `f` and the peak of the bytes allocated meanwhile
+fn measure(f: impl FnOnce() -> usize) -> (usize, usize) {
+    let (allocations, r) = GLOBAL.measure(f);
+    std::hint::black_box(r);
+    (allocations.count, allocations.peak)
+}
+
+fn bump(c: &mut Criterion) {
+    for (name, format, document) in [
+        ("json", Format::Json, json as fn(usize) -> String),
+        ("xml", Format::Xml, xml),
+    ] {
+        let documents = (0..DOCUMENTS).map(document).collect::<Vec<_>>();
+        let bytes = documents.iter().map(String::len).sum::<usize>();
+        let mut reused = Arena::new();
+        // warm up the arena, as in a long-running service
+        arena(&documents[..BATCH], format, &mut reused);
+        assert_eq!(
+            arena(&documents, format, &mut reused),
+            openmath(&documents, format)
+        );
+
+        println!(
+            "{DOCUMENTS} {name} documents in batches of {BATCH}; allocations, peak heap bytes:"
+        );
+        let (allocations, peak) = measure(|| openmath(&documents, format));
+        println!("  OpenMath: {allocations}, {peak}");
+        // including the arena's own chunks
+        let (allocations, peak) = measure(|| arena(&documents, format, &mut Arena::new()));
+        println!("  Arena:    {allocations}, {peak}");
+
+        let mut group = c.benchmark_group(format!("bump {name}"));
+        group.sample_size(20);
+        group.throughput(Throughput::Bytes(bytes as u64));
+        group.bench_function("OpenMath", |b| b.iter(|| openmath(&documents, format)));
+        group.bench_function("Arena", |b| {
+            b.iter(|| arena(&documents, format, &mut reused));
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, bump);
+criterion_main!(benches);
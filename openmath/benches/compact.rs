@@ -0,0 +1,137 @@
+//! Compares parsing a symbol-heavy document (only symbols and small integers) into an
+//! [`OpenMath`], into the plain intermediate tree most [`OMDeserializable`]s build, and into a
+//! [`Compact`]: the throughput, the number of allocations and the bytes kept.
+
+use std::fmt::Write;
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use openmath::{
+    OMDeserializable, OpenMath,
+    de::{Compact, OM},
+    testing::CountingAlloc,
+};
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc::new();
+
+/// The plain intermediate tree, with every child boxed
+#[derive(Debug)]
+struct Tree<'de>(#[allow(dead_code)] OM<'de, Box<Self>>);
+impl<'de> OMDeserializable<'de> for Tree<'de> {
+    type Ret = Self;
+    type Err = std::convert::Infallible;
+    fn from_openmath(om: OM<'de, Self>, _: &str) -> Result<Self, Self::Err> {
+        let OM::OMA {
+            applicant,
+            arguments,
+            attrs,
+        } = om
+        else {
+            return Ok(Self(match om {
+                OM::OMS {
+                    cdbase,
+                    cd,
+                    name,
+                    attrs,
+                } if attrs.is_empty() => OM::OMS {
+                    cdbase,
+                    cd,
+                    name,
+                    attrs: Vec::new(),
+                },
+                OM::OMI { int, attrs } if attrs.is_empty() => OM::OMI {
+                    int,
+                    attrs: Vec::new(),
+                },
+                _ => unreachable!("the fixture only has symbols, integers and applications"),
+            }));
+        };
+        assert!(attrs.is_empty(), "the fixture has no attributes");
+        Ok(Self(OM::OMA {
+            applicant: Box::new(applicant),
+            arguments: arguments.into_iter().map(Box::new).collect(),
+            attrs: Vec::new(),
+        }))
+    }
+}
+
+/// The number of items in the generated fixture
+const ITEMS: usize = 50_000;
+
+/// The symbols of the fixture
+const SYMBOLS: [(&str, &str); 8] = [
+    ("arith1", "plus"),
+    ("arith1", "times"),
+    ("arith1", "minus"),
+    ("relation1", "eq"),
+    ("relation1", "lt"),
+    ("nums1", "pi"),
+    ("nums1", "e"),
+    ("logic1", "and"),
+];
+
+fn fixture() -> String {
+    let oms = |i: usize| {
+        let (cd, name) = SYMBOLS[i % SYMBOLS.len()];
+        format!(r#"<OMS cd="{cd}" name="{name}"/>"#)
+    };
+    let mut s = String::with_capacity(ITEMS * 200);
+    s.push_str(r#"<OMA><OMS cd="list1" name="list"/>"#);
+    for i in 0..ITEMS {
+        write!(
+            s,
+            "<OMA>{}<OMA>{}{}<OMI>{}</OMI></OMA>{}<OMI>{}</OMI></OMA>",
+            oms(i),
+            oms(i + 1),
+            oms(i + 5),
+            i % 100,
+            oms(i + 6),
+            i % 7
+        )
+        .expect("works");
+    }
+    s.push_str("</OMA>");
+    s
+}
+
+/// The allocations made by `f`, the bytes allocated by it and still live when it returns,
+/// and its result
+fn allocated<R>(f: impl FnOnce() -> R) -> (usize, usize, R) {
+    let (allocations, r) = GLOBAL.measure(f);
+    (allocations.count, allocations.live, r)
+}
+
+fn compact(c: &mut Criterion) {
+    let input = fixture();
+    let (allocations, bytes, om) =
+        allocated(|| OpenMath::from_openmath_xml(&input).expect("is valid"));
+    println!("parsing {} bytes:", input.len());
+    println!("  OpenMath: {allocations:>8} allocations, {bytes:>9} bytes kept");
+    let (allocations, bytes, tree) =
+        allocated(|| Tree::from_openmath_xml(&input).expect("is valid"));
+    println!("  Tree:     {allocations:>8} allocations, {bytes:>9} bytes kept");
+    let (allocations, bytes, compact) =
+        allocated(|| Compact::from_openmath_xml(&input).expect("is valid"));
+    println!("  Compact:  {allocations:>8} allocations, {bytes:>9} bytes kept");
+    assert_eq!(compact.symbols().len(), SYMBOLS.len() + 1);
+    assert!(compact.boxed().is_empty());
+    assert_eq!(compact.to_openmath(), om);
+    drop((om, tree, compact));
+
+    let mut group = c.benchmark_group("compact");
+    group.sample_size(20);
+    group.throughput(Throughput::Bytes(input.len() as u64));
+    group.bench_function("OpenMath", |b| {
+        b.iter(|| OpenMath::from_openmath_xml(&input).expect("is valid"));
+    });
+    group.bench_function("Tree", |b| {
+        b.iter(|| Tree::from_openmath_xml(&input).expect("is valid"));
+    });
+    group.bench_function("Compact", |b| {
+        b.iter(|| Compact::from_openmath_xml(&input).expect("is valid"));
+    });
+    group.finish();
+}
+
+criterion_group!(benches, compact);
+criterion_main!(benches);
@@ -0,0 +1,97 @@
+//! Exporting big integers whose decimal digits are known to be valid (e.g. rendered by a
+//! bignum library) as XML: validating them with [`Int::new`], compared with
+//! [`Int::from_digits_unchecked`].
+
+use std::borrow::Cow;
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use openmath::{
+    Int, OMSerializable,
+    ser::{AsOMS, OMSerializer, Uri},
+};
+
+/// The number of integers in the generated fixture
+const INTS: usize = 100_000;
+
+/// Decimal renderings of big integers, every second of them negative
+fn renderings() -> Vec<String> {
+    (0..INTS)
+        .map(|i| {
+            let sign = if i % 2 == 0 { "-" } else { "" };
+            format!("{sign}{}{}", i + 1, "1234567890".repeat(6))
+        })
+        .collect()
+}
+
+/// A list of the integers, constructed while serializing
+struct Export<'a> {
+    renderings: &'a [String],
+    unchecked: bool,
+}
+impl OMSerializable for Export<'_> {
+    fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        serializer.oma(
+            Uri {
+                cdbase: None,
+                cd: "list1",
+                name: "list",
+            }
+            .as_oms(),
+            self.renderings.iter().map(|r| {
+                if self.unchecked {
+                    Int::from_digits_unchecked(Cow::Borrowed(r))
+                } else {
+                    Int::new(r).expect("is valid")
+                }
+            }),
+        )
+    }
+}
+
+fn int_export(c: &mut Criterion) {
+    let renderings = renderings();
+    let checked = Export {
+        renderings: &renderings,
+        unchecked: false,
+    };
+    let unchecked = Export {
+        renderings: &renderings,
+        unchecked: true,
+    };
+    assert_eq!(
+        checked.xml(false).to_string(),
+        unchecked.xml(false).to_string()
+    );
+
+    let mut group = c.benchmark_group("int_export");
+    group.sample_size(20);
+    group.throughput(Throughput::Elements(INTS as u64));
+    group.bench_function("construct Int::new", |b| {
+        b.iter(|| {
+            renderings
+                .iter()
+                .map(|r| Int::new(r).expect("is valid"))
+                .filter(Int::is_negative)
+                .count()
+        });
+    });
+    group.bench_function("construct from_digits_unchecked", |b| {
+        b.iter(|| {
+            renderings
+                .iter()
+                .map(|r| Int::from_digits_unchecked(Cow::Borrowed(r)))
+                .filter(Int::is_negative)
+                .count()
+        });
+    });
+    group.bench_function("xml Int::new", |b| {
+        b.iter(|| checked.xml(false).to_string());
+    });
+    group.bench_function("xml from_digits_unchecked", |b| {
+        b.iter(|| unchecked.xml(false).to_string());
+    });
+    group.finish();
+}
+
+criterion_group!(benches, int_export);
+criterion_main!(benches);
@@ -0,0 +1,54 @@
+//! Writing XML with and without [`Memoized`], for a term that contains the same annotated
+//! unit many times.
+
+use std::fmt::Write;
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use openmath::{OMSerializable, OpenMath, ser::Memoized};
+
+/// The number of quantities in the generated fixture
+const ITEMS: usize = 20_000;
+
+/// The unit of every quantity, with an annotation
+const UNIT: &str = r#"
+  (omattr (((oms meta note) (omstr "the SI unit of acceleration"))
+           ((oms meta type) (oma (oms sts mapsto) (oms setname1 R) (oms setname1 R))))
+    (oma (oms arith1 divide)
+      (oms units_metric1 metre)
+      (oma (oms arith1 power) (oms units_time1 second) (omi 2))))"#;
+
+fn fixture() -> OpenMath<'static> {
+    let mut s = String::from("(oma (oms list1 list)");
+    for i in 0..ITEMS {
+        write!(s, "(oma (oms arith1 times) (omi {i}) {UNIT})").expect("writing to a string");
+    }
+    s.push(')');
+    OpenMath::from_sexpr(&s).expect("is valid")
+}
+
+fn memoized(c: &mut Criterion) {
+    let om = fixture();
+    let memoized = Memoized::new(&om);
+    for pretty in [false, true] {
+        assert_eq!(memoized.xml(pretty).to_string(), om.xml(pretty).to_string());
+    }
+
+    let mut group = c.benchmark_group("memoized");
+    group.sample_size(20);
+    group.throughput(Throughput::Bytes(om.xml(false).to_string().len() as u64));
+    group.bench_function("compact", |b| b.iter(|| om.xml(false).to_string()));
+    group.bench_function("compact memoized", |b| {
+        b.iter(|| memoized.xml(false).to_string());
+    });
+    group.bench_function("pretty", |b| b.iter(|| om.xml(true).to_string()));
+    group.bench_function("pretty memoized", |b| {
+        b.iter(|| memoized.xml(true).to_string());
+    });
+    group.bench_function("finding repeated subterms", |b| {
+        b.iter(|| Memoized::new(&om).repeated());
+    });
+    group.finish();
+}
+
+criterion_group!(benches, memoized);
+criterion_main!(benches);
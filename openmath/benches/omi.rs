@@ -0,0 +1,127 @@
+//! Allocations and time of parsing integer-heavy XML: [`Int::parse_bytes`] on the text of
+//! an OMI, compared with going through `&str` and an owned copy, as the XML reader did
+//! before.
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use openmath::{
+    Int,
+    de::{OM, OMDeserializable},
+    testing::CountingAlloc,
+};
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc::new();
+
+/// The number of OMIs in the generated fixtures
+const OMIS: usize = 100_000;
+
+/// The number of allocations made by `f`, along with its result
+fn allocations<R>(f: impl FnOnce() -> R) -> (usize, R) {
+    let (allocations, r) = GLOBAL.measure(f);
+    (allocations.count, r)
+}
+
+/// The literals of the fixture, every fourth of them too large for an `i128`
+fn literals() -> Vec<String> {
+    (0..OMIS)
+        .map(|i| {
+            if i % 4 == 0 {
+                format!("-{i}{}", "9".repeat(40))
+            } else {
+                format!("+{i}")
+            }
+        })
+        .collect()
+}
+
+fn fixture(literals: &[String]) -> String {
+    let mut s = String::from(r#"<OMA><OMS cd="list1" name="list"/>"#);
+    for l in literals {
+        s.push_str("<OMI> ");
+        s.push_str(l);
+        s.push_str(" </OMI>");
+    }
+    s.push_str("</OMA>");
+    s
+}
+
+/// Counts the big integers without keeping the arguments, so that only reading allocates
+#[derive(Debug)]
+struct BigInts(usize);
+impl<'de> OMDeserializable<'de> for BigInts {
+    type Ret = Self;
+    type Err = &'static str;
+    fn from_openmath(om: OM<'de, Self>, _: &str) -> Result<Self, Self::Err> {
+        match om {
+            OM::OMI { int, .. } => Ok(Self(usize::from(int.is_big().is_some()))),
+            OM::OMS { .. } => Ok(Self(0)),
+            OM::OMA { arguments, .. } => Ok(Self(arguments.into_iter().map(|a| a.0).sum())),
+            _ => Err("unexpected"),
+        }
+    }
+}
+
+/// The conversion the XML reader used before
+fn via_str(bytes: &[u8]) -> Int<'static> {
+    let s = std::str::from_utf8(bytes.trim_ascii()).expect("is valid");
+    Int::try_from(s).expect("is valid").into_owned()
+}
+
+fn report(input: &str, literals: &[String]) {
+    let (before, _) = allocations(|| {
+        literals
+            .iter()
+            .map(|l| via_str(l.as_bytes()))
+            .filter(|i| i.is_big().is_some())
+            .count()
+    });
+    let (after, _) = allocations(|| {
+        literals
+            .iter()
+            .map(|l| Int::parse_bytes(l.as_bytes()).expect("is valid"))
+            .filter(|i| i.is_big().is_some())
+            .count()
+    });
+    let (parse, BigInts(big)) =
+        allocations(|| BigInts::from_openmath_xml(input).expect("is valid"));
+    assert_eq!(big, OMIS / 4);
+    println!("allocations for {OMIS} OMIs ({big} big):");
+    println!("  via &str and into_owned: {before}");
+    println!("  Int::parse_bytes:        {after}");
+    println!("  parsing the XML:         {parse}");
+}
+
+fn omi(c: &mut Criterion) {
+    let literals = literals();
+    let input = fixture(&literals);
+    report(&input, &literals);
+
+    let mut group = c.benchmark_group("omi");
+    group.sample_size(20);
+    group.throughput(Throughput::Elements(OMIS as u64));
+    group.bench_function("via_str", |b| {
+        b.iter(|| {
+            literals
+                .iter()
+                .map(|l| via_str(l.as_bytes()))
+                .filter(|i| i.is_big().is_some())
+                .count()
+        });
+    });
+    group.bench_function("parse_bytes", |b| {
+        b.iter(|| {
+            literals
+                .iter()
+                .map(|l| Int::parse_bytes(l.as_bytes()).expect("is valid"))
+                .filter(|i| i.is_big().is_some())
+                .count()
+        });
+    });
+    group.bench_function("xml", |b| {
+        b.iter(|| BigInts::from_openmath_xml(&input).expect("is valid"));
+    });
+    group.finish();
+}
+
+criterion_group!(benches, omi);
+criterion_main!(benches);
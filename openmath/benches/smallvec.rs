@@ -0,0 +1,91 @@
+//! Compares inline capacities for the arguments of [`OM`](openmath::OM)s on a distribution
+//! of argument counts averaging 6 per application.
+//!
+//! `collect` collects arguments into [`SmallVec`]s of every capacity (and a [`Vec`]);
+//! `parse` parses XML with the capacity chosen by the `smallvec-*` features, so compare
+//! runs with different features for it:
+//!
+//! ```sh
+//! cargo bench --bench smallvec
+//! cargo bench --bench smallvec --features smallvec-4
+//! cargo bench --bench smallvec --features smallvec-8
+//! ```
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use openmath::{OMDeserializable, OpenMath, de::INLINE_ARGS};
+use smallvec::SmallVec;
+
+/// Argument counts, cycled through; their mean is 6
+const ARITIES: [usize; 10] = [1, 2, 3, 4, 5, 6, 7, 8, 10, 14];
+
+/// The number of applications in the generated fixture
+const ITEMS: usize = 20_000;
+
+/// Applications of `plus` to the next [`ARITIES`] arguments each, in one list
+fn fixture() -> String {
+    let mut s = String::from(r#"<OMA><OMS cd="list1" name="list"/>"#);
+    for arity in ARITIES.iter().cycle().take(ITEMS) {
+        s.push_str(r#"<OMA><OMS cd="arith1" name="plus"/>"#);
+        for i in 0..*arity {
+            if i % 2 == 0 {
+                s.push_str(r#"<OMV name="x"/>"#);
+            } else {
+                s.push_str("<OMI>2</OMI>");
+            }
+        }
+        s.push_str("</OMA>");
+    }
+    s.push_str("</OMA>");
+    s
+}
+
+/// Collects the arguments of [`ITEMS`] applications into `C`s; returns the total number
+/// of arguments, to use the results
+fn collect_into<C: FromIterator<OpenMath<'static>> + AsRef<[OpenMath<'static>]>>() -> usize {
+    ARITIES
+        .iter()
+        .cycle()
+        .take(ITEMS)
+        .map(|arity| {
+            let args = (0..*arity)
+                .map(|_| OpenMath::OMV {
+                    name: "x".into(),
+                    attributes: Vec::new(),
+                })
+                .collect::<C>();
+            std::hint::black_box(args).as_ref().len()
+        })
+        .sum()
+}
+
+fn collect(c: &mut Criterion) {
+    let mut group = c.benchmark_group("smallvec collect");
+    group.throughput(Throughput::Elements(ITEMS as u64));
+    group.bench_function("inline 1", |b| {
+        b.iter(collect_into::<SmallVec<_, 1>>);
+    });
+    group.bench_function("inline 2", |b| {
+        b.iter(collect_into::<SmallVec<_, 2>>);
+    });
+    group.bench_function("inline 4", |b| {
+        b.iter(collect_into::<SmallVec<_, 4>>);
+    });
+    group.bench_function("inline 8", |b| {
+        b.iter(collect_into::<SmallVec<_, 8>>);
+    });
+    group.bench_function("Vec", |b| b.iter(collect_into::<Vec<_>>));
+    group.finish();
+}
+
+fn parse(c: &mut Criterion) {
+    let xml = fixture();
+    let mut group = c.benchmark_group("smallvec parse");
+    group.throughput(Throughput::Bytes(xml.len() as u64));
+    group.bench_function(format!("inline {INLINE_ARGS}"), |b| {
+        b.iter(|| OpenMath::from_openmath_xml(&xml).expect("is valid"));
+    });
+    group.finish();
+}
+
+criterion_group!(benches, collect, parse);
+criterion_main!(benches);
@@ -0,0 +1,137 @@
+//! Allocations and time of exporting symbol-heavy terms whose symbols are stored as URI
+//! strings: splitting every URI while serializing, compared with splitting them once up
+//! front and writing them with [`OMSerializer::oms_uri`].
+
+use std::fmt::Write;
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use openmath::{
+    OMSerializable, SymbolRef,
+    ser::{AsOMS, OMSerializer, Uri},
+    testing::CountingAlloc,
+};
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc::new();
+
+/// The number of symbols in the generated fixture
+const SYMBOLS: usize = 100_000;
+
+/// The symbols of the fixture; half of them with the default cdbase, the rest with
+/// another one
+const URIS: [&str; 6] = [
+    "http://www.openmath.org/cd?arith1#plus",
+    "http://www.openmath.org/cd?arith1#times",
+    "http://www.openmath.org/cd?relation1#eq",
+    "http://example.org/cd?sets#union",
+    "http://example.org/cd?sets#in",
+    "http://example.org/cd?logic#and",
+];
+
+/// The number of allocations made by `f`, along with its result
+fn allocations<R>(f: impl FnOnce() -> R) -> (usize, R) {
+    let (allocations, r) = GLOBAL.measure(f);
+    (allocations.count, r)
+}
+
+/// How the symbols of an [`Export`] are written
+#[derive(Clone, Copy)]
+enum How {
+    /// Parsing an owned [`SymbolRef`] from the URI, as its [`FromStr`](std::str::FromStr)
+    /// impl does
+    ParseOwned,
+    /// Splitting the URI into a borrowed [`SymbolRef`] and writing it with [`AsOMS`]
+    ParseBorrowed,
+    /// Writing the [`SymbolRef`]s split up front with [`OMSerializer::oms_uri`]
+    Presplit,
+}
+
+/// A list of symbols
+struct Export<'a> {
+    uris: &'a [&'static str],
+    split: &'a [SymbolRef<'static>],
+    how: How,
+}
+struct Symbol<'a>(&'a Export<'a>, usize);
+impl OMSerializable for Symbol<'_> {
+    fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        let Symbol(export, i) = self;
+        match export.how {
+            How::ParseOwned => export.uris[*i]
+                .parse::<SymbolRef>()
+                .expect("is valid")
+                .as_oms()
+                .as_openmath(serializer),
+            How::ParseBorrowed => SymbolRef::parse(export.uris[*i])
+                .expect("is valid")
+                .as_oms()
+                .as_openmath(serializer),
+            How::Presplit => serializer.oms_uri(&export.split[*i]),
+        }
+    }
+}
+impl OMSerializable for Export<'_> {
+    fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        serializer.oma(
+            Uri {
+                cdbase: None,
+                cd: "list1",
+                name: "list",
+            }
+            .as_oms(),
+            (0..self.uris.len()).map(|i| Symbol(self, i)),
+        )
+    }
+}
+
+/// Discards what is written, so that only the serializer allocates
+struct Discard;
+impl Write for Discard {
+    #[inline]
+    fn write_str(&mut self, _: &str) -> std::fmt::Result {
+        Ok(())
+    }
+}
+
+fn write(export: &Export) {
+    write!(Discard, "{}", export.xml(false)).expect("works");
+}
+
+fn symbol_uris(c: &mut Criterion) {
+    let uris = (0..SYMBOLS)
+        .map(|i| URIS[i % URIS.len()])
+        .collect::<Vec<_>>();
+    let split = uris
+        .iter()
+        .map(|u| SymbolRef::parse(u).expect("is valid"))
+        .collect::<Vec<_>>();
+    let export = |how| Export {
+        uris: &uris,
+        split: &split,
+        how,
+    };
+    let [owned, borrowed, presplit] =
+        [How::ParseOwned, How::ParseBorrowed, How::Presplit].map(export);
+    let xml = presplit.xml(false).to_string();
+    assert_eq!(owned.xml(false).to_string(), xml);
+    assert_eq!(borrowed.xml(false).to_string(), xml);
+
+    let (parse_owned, ()) = allocations(|| write(&owned));
+    let (parse_borrowed, ()) = allocations(|| write(&borrowed));
+    let (oms_uri, ()) = allocations(|| write(&presplit));
+    println!("allocations writing {SYMBOLS} symbols:");
+    println!("  parsing owned SymbolRefs:    {parse_owned}");
+    println!("  parsing borrowed SymbolRefs: {parse_borrowed}");
+    println!("  oms_uri on split SymbolRefs: {oms_uri}");
+
+    let mut group = c.benchmark_group("symbol_uris");
+    group.sample_size(20);
+    group.throughput(Throughput::Elements(SYMBOLS as u64));
+    group.bench_function("parse owned", |b| b.iter(|| write(&owned)));
+    group.bench_function("parse borrowed", |b| b.iter(|| write(&borrowed)));
+    group.bench_function("oms_uri", |b| b.iter(|| write(&presplit)));
+    group.finish();
+}
+
+criterion_group!(benches, symbol_uris);
+criterion_main!(benches);
@@ -0,0 +1,108 @@
+//! Compares parsing XML from a string with parsing it from a buffered file reader, and
+//! measures parsing a document consisting mostly of symbols.
+
+use std::{io::Write, path::PathBuf};
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use openmath::de::{OM, OMDeserializable, OMDeserializableOwned};
+
+/// The size of the generated fixture
+const SIZE: usize = 5 * 1024 * 1024;
+
+const ITEM: &str = r#"
+  <OMATTR>
+    <OMATP><OMS cd="meta" name="note"/><OMFOREIGN encoding="text/plain">some <b>note</b></OMFOREIGN></OMATP>
+    <OMA>
+      <OMS cd="arith1" name="plus" cdbase="http://example.org/cd"/>
+      <OMI>1234567890123456789012345678901234567890</OMI>
+      <OMF dec="1.5"/>
+      <OMSTR>some text &amp; an entity</OMSTR>
+      <OMB>AAECAwQFBgcICQ==</OMB>
+      <OMBIND><OMS cd="fns1" name="lambda"/><OMBVAR><OMV name="x"/></OMBVAR><OMV name="x"/></OMBIND>
+    </OMA>
+  </OMATTR>"#;
+
+/// Mostly symbols, with attributes in varying order
+const SYMBOLS_ITEM: &str = r#"
+  <OMA>
+    <OMS cd="arith1" name="plus"/>
+    <OMS name="times" cd="arith1"/>
+    <OMS cdbase="http://example.org/cd" cd="my" name="op"/>
+    <OMA><OMS cd="relation1" name="eq"/><OMV name="x"/><OMS cd="nums1" name="pi"/></OMA>
+    <OME><OMS cd="moreerrors" name="unexpected"/><OMS cd="setname1" name="N"/></OME>
+  </OMA>"#;
+
+/// An owned tree that copies all names and strings, so both paths do comparable work
+#[derive(Debug)]
+#[allow(dead_code)]
+enum Tree {
+    Leaf(String),
+    Node(Vec<Self>),
+}
+impl<'d> OMDeserializable<'d> for Tree {
+    type Ret = Self;
+    type Err = std::convert::Infallible;
+    fn from_openmath(om: OM<'d, Self>, _: &str) -> Result<Self, Self::Err> {
+        Ok(match om {
+            OM::OMA {
+                applicant,
+                arguments,
+                ..
+            } => Self::Node(std::iter::once(applicant).chain(arguments).collect()),
+            OM::OMBIND { binder, object, .. } => Self::Node(vec![binder, object]),
+            OM::OMSTR { string: s, .. }
+            | OM::OMV { name: s, .. }
+            | OM::OMS { name: s, .. }
+            | OM::OME { name: s, .. }
+            | OM::OMR { href: s, .. } => Self::Leaf(s.into_owned()),
+            OM::OMI { int, .. } => Self::Leaf(int.to_string()),
+            _ => Self::Leaf(String::new()),
+        })
+    }
+}
+
+fn fixture(item: &str) -> String {
+    let mut s = String::with_capacity(SIZE + item.len() + 64);
+    s.push_str(r#"<OMA><OMS cd="list1" name="list"/>"#);
+    while s.len() < SIZE {
+        s.push_str(item);
+    }
+    s.push_str("\n</OMA>");
+    s
+}
+
+fn xml_read(c: &mut Criterion) {
+    let input = fixture(ITEM);
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("xml_read_fixture.xml");
+    std::fs::File::create(&path)
+        .and_then(|mut f| f.write_all(input.as_bytes()))
+        .expect("fixture can be written");
+
+    let mut group = c.benchmark_group("xml_read");
+    group.sample_size(20);
+    group.throughput(Throughput::Bytes(input.len() as u64));
+    group.bench_function("FromString", |b| {
+        b.iter(|| Tree::from_openmath_xml(&input).expect("is valid"));
+    });
+    group.bench_function("Reader<BufReader<File>>", |b| {
+        b.iter(|| {
+            let file = std::fs::File::open(&path).expect("fixture exists");
+            Tree::from_openmath_xml_reader(std::io::BufReader::new(file)).expect("is valid")
+        });
+    });
+    group.finish();
+}
+
+fn symbols(c: &mut Criterion) {
+    let input = fixture(SYMBOLS_ITEM);
+    let mut group = c.benchmark_group("xml_read_symbols");
+    group.sample_size(20);
+    group.throughput(Throughput::Bytes(input.len() as u64));
+    group.bench_function("FromString", |b| {
+        b.iter(|| Tree::from_openmath_xml(&input).expect("is valid"));
+    });
+    group.finish();
+}
+
+criterion_group!(benches, xml_read, symbols);
+criterion_main!(benches);
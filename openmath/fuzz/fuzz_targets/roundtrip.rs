@@ -0,0 +1,5 @@
+#![no_main]
+
+use openmath::OpenMath;
+
+libfuzzer_sys::fuzz_target!(|om: OpenMath<'_>| openmath::fuzzing::roundtrip(&om));
@@ -0,0 +1,429 @@
+//! Whole-document statistics and symbol dependencies; see [`analyze`] and [`analyze_xml`]
+
+#[cfg(feature = "xml-read")]
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+
+#[cfg(feature = "xml-read")]
+use crate::de::XmlReadError;
+use crate::{
+    Attr, BoundVariable, OMKind, OMMaybeForeign, OpenMath, Step, SymbolRef, visit::Visitor,
+};
+
+/// Statistics about an <span style="font-variant:small-caps;">OpenMath</span> object, e.g. to
+/// pre-fetch the content dictionaries it references before importing it; see [`analyze`] and
+/// [`analyze_xml`].
+///
+/// Symbols without a cdbase (in scope) count as in [`CD_BASE`](crate::CD_BASE), and cdbases
+/// are [normalized](crate::CdBase::normalize), so all references to the same symbol are
+/// counted together.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Analysis {
+    /// The `(cdbase, cd)` pairs of all content dictionaries referenced by symbols
+    /// (including [OME](OMKind::OME) symbols and attribute keys)
+    pub cds: BTreeSet<(String, String)>,
+    /// The number of occurrences of every symbol, by its URI `cdbase?cd#name`
+    pub symbols: BTreeMap<String, usize>,
+    /// The number of elements of every kind; bound variables count as [OMV](OMKind::OMV)s
+    /// and attributed objects as [OMATTR](OMKind::OMATTR)s (in addition to their own kind)
+    pub kinds: BTreeMap<OMKind, usize>,
+    /// The maximum nesting depth of objects, where a leaf has depth 1; attribute values are
+    /// at the depth of the object they attribute, and [OMFOREIGN](OMKind::OMFOREIGN)s do not
+    /// count
+    pub max_depth: usize,
+    /// The encodings of all [OMFOREIGN](OMKind::OMFOREIGN)s, in document order (`None` for
+    /// those without one)
+    pub foreign: Vec<Option<String>>,
+    /// The total number of bytes in [OMB](OMKind::OMB)s
+    pub bytes: usize,
+}
+
+impl Analysis {
+    /// Records an occurrence of the symbol `cdbase?cd#name`, resolving a missing `cdbase`
+    /// against `current`
+    fn symbol(&mut self, cdbase: Option<&str>, cd: &str, name: &str, current: &str) {
+        let cdbase = crate::CdBase::new_unchecked(cdbase.unwrap_or(current));
+        let cdbase = cdbase.normalize();
+        *self
+            .symbols
+            .entry(format!("{}?{cd}#{name}", cdbase.as_str()))
+            .or_default() += 1;
+        self.cds
+            .insert((cdbase.as_str().to_string(), cd.to_string()));
+    }
+
+    fn kind(&mut self, kind: OMKind) {
+        *self.kinds.entry(kind).or_default() += 1;
+    }
+}
+
+/// Walks an object for [`analyze`]
+struct Analyzer {
+    analysis: Analysis,
+    /// The depths of the objects entered and not yet left
+    depths: Vec<usize>,
+}
+
+impl<'a, 'om: 'a> Visitor<'a, 'om> for Analyzer {
+    fn enter(&mut self, om: &'a OpenMath<'om>, step: Option<Step>) {
+        let parent = self.depths.last().copied().unwrap_or(0);
+        // attribute values are at the depth of the object they attribute
+        let depth = if matches!(step, Some(Step::Attribute(_))) {
+            parent
+        } else {
+            parent + 1
+        };
+        self.depths.push(depth);
+        let a = &mut self.analysis;
+        a.kind(om.kind());
+        a.max_depth = a.max_depth.max(depth);
+        if !om.attributes().is_empty() {
+            a.kind(OMKind::OMATTR);
+        }
+        match om {
+            OpenMath::OMB { bytes, .. } => a.bytes += bytes.len(),
+            OpenMath::OMS {
+                cdbase, cd, name, ..
+            } => a.symbol(cdbase.as_deref(), cd, name, crate::CD_BASE),
+            OpenMath::OME {
+                cdbase, cd, name, ..
+            } => {
+                // the error symbol is the first child of the OME in the XML encoding
+                a.kind(OMKind::OMS);
+                a.max_depth = a.max_depth.max(depth + 1);
+                a.symbol(cdbase.as_deref(), cd, name, crate::CD_BASE);
+            }
+            _ => (),
+        }
+    }
+
+    fn leave(&mut self, _: &'a OpenMath<'om>) {
+        self.depths.pop();
+    }
+
+    fn attribute(&mut self, attr: &'a Attr<'om, OMMaybeForeign<'om, OpenMath<'om>>>) {
+        let SymbolRef { cdbase, cd, name } = &attr.key;
+        // keys are OMSs in the XML encoding
+        self.analysis.kind(OMKind::OMS);
+        self.analysis
+            .symbol(cdbase.as_deref(), cd, name, crate::CD_BASE);
+    }
+
+    fn variable(&mut self, var: &'a BoundVariable<'om>) {
+        let depth = self.depths.last().copied().unwrap_or(0) + 1;
+        let a = &mut self.analysis;
+        a.kind(OMKind::OMV);
+        a.max_depth = a.max_depth.max(depth);
+        if !var.attributes.is_empty() {
+            a.kind(OMKind::OMATTR);
+        }
+    }
+
+    fn foreign(&mut self, encoding: Option<&'a str>, _: &'a str) {
+        self.analysis.kind(OMKind::OMFOREIGN);
+        self.analysis.foreign.push(encoding.map(str::to_string));
+    }
+}
+
+/// Computes the [`Analysis`] of `om`.
+///
+/// # Examples
+/// ```
+/// use openmath::{OMKind, OpenMath, analyze};
+///
+/// let om = OpenMath::from_sexpr("(oma (oms arith1 plus) (omi 1) (oma (oms arith1 plus) (omv x)))")
+///     .expect("is valid");
+/// let analysis = analyze(&om);
+/// assert_eq!(
+///     analysis.cds.into_iter().collect::<Vec<_>>(),
+///     [(openmath::CD_BASE.to_string(), "arith1".to_string())]
+/// );
+/// assert_eq!(analysis.symbols["http://www.openmath.org/cd?arith1#plus"], 2);
+/// assert_eq!(analysis.kinds[&OMKind::OMA], 2);
+/// assert_eq!(analysis.max_depth, 3);
+/// ```
+#[must_use]
+pub fn analyze(om: &OpenMath<'_>) -> Analysis {
+    let mut analyzer = Analyzer {
+        analysis: Analysis::default(),
+        depths: Vec::new(),
+    };
+    om.walk(&mut analyzer);
+    analyzer.analysis
+}
+
+/** Computes the [`Analysis`] of the <span style="font-variant:small-caps;">OpenMath</span> XML
+`input` (an OMOBJ, or a bare object) in a single pass over its events, without building the
+object; so it also works for objects that do not fit into memory as a tree, and counts
+[OMR](OMKind::OMR)s (which [`analyze`] never sees, since they are resolved while reading).
+
+Otherwise, the result agrees with [`analyze`] for the object read from `input`; except that
+an [OMATTR](OMKind::OMATTR) directly wrapping another one counts separately, and that
+attribute keys inheriting a cdbase are counted in that one here, whereas objects do not
+record it (so [`analyze`] counts them in [`CD_BASE`](crate::CD_BASE)).
+
+# Errors
+iff `input` is not well-formed XML, contains an unknown
+<span style="font-variant:small-caps;">OpenMath</span> element, or an
+[OMB](OMKind::OMB) that is not valid base64. The input is not otherwise validated (e.g. the
+number of children of an OMBIND).
+
+# Examples
+```
+use openmath::{OMKind, analyze_xml};
+
+let analysis = analyze_xml(r##"<OMOBJ cdbase="http://example.org/cd">
+  <OMA><OMS cd="units" name="metre"/><OMB>AQID</OMB><OMR href="#x"/></OMA>
+</OMOBJ>"##).expect("is valid");
+assert_eq!(analysis.symbols["http://example.org/cd?units#metre"], 1);
+assert_eq!(analysis.kinds[&OMKind::OMR], 1);
+assert_eq!(analysis.bytes, 3);
+assert_eq!(analysis.max_depth, 2);
+```
+*/
+#[cfg(feature = "xml-read")]
+pub fn analyze_xml(input: &str) -> Result<Analysis, XmlReadError<std::convert::Infallible>> {
+    use quick_xml::events::Event;
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+    let mut reader = quick_xml::Reader::from_str(input);
+    let mut ret = Analysis::default();
+    let mut stack: Vec<Open> = Vec::new();
+    let mut depth = 0;
+    // the depth of the OMFOREIGN we are in, if any; its content is opaque
+    let mut foreign = None;
+    loop {
+        let position = reader.buffer_position();
+        let event = reader
+            .read_event()
+            .map_err(|error| XmlReadError::Xml { error, position })?;
+        match &event {
+            Event::Start(s) | Event::Empty(s) if foreign.is_none() => {
+                let open = ret.start(s, position, stack.last(), depth)?;
+                if matches!(event, Event::Start(_)) {
+                    if open.kind == Some(OMKind::OMFOREIGN) {
+                        foreign = Some(stack.len());
+                    }
+                    depth += usize::from(open.object);
+                    stack.push(open);
+                }
+            }
+            Event::Start(s) => stack.push(Open {
+                name: String::from_utf8_lossy(s.name().as_ref()).into_owned(),
+                kind: None,
+                cdbase: String::new(),
+                object: false,
+                base64: None,
+            }),
+            Event::Text(t) => {
+                if let Some((_, b)) = stack.last_mut().and_then(|o| o.base64.as_mut()) {
+                    b.extend_from_slice(t);
+                }
+            }
+            Event::End(_) => {
+                let Some(open) = stack.pop() else {
+                    return Err(XmlReadError::UnexpectedTag(position));
+                };
+                if foreign == Some(stack.len()) {
+                    foreign = None;
+                }
+                depth -= usize::from(open.object);
+                if let Some((position, b)) = open.base64 {
+                    ret.bytes += crate::base64::decode_slice(&b)
+                        .map_err(|source| XmlReadError::Base64 { source, position })?
+                        .len();
+                }
+            }
+            Event::Eof => {
+                if let Some(open) = stack.pop() {
+                    return Err(XmlReadError::Xml {
+                        error: quick_xml::errors::IllFormedError::MissingEndTag(open.name).into(),
+                        position,
+                    });
+                }
+                break;
+            }
+            _ => (),
+        }
+    }
+    if input.trim_ascii().is_empty() {
+        return Err(XmlReadError::EmptyInput);
+    }
+    if ret.kinds.is_empty() {
+        return Err(XmlReadError::NoObject);
+    }
+    Ok(ret)
+}
+
+/// An open element in [`analyze_xml`]
+#[cfg(feature = "xml-read")]
+struct Open {
+    name: String,
+    kind: Option<OMKind>,
+    /// The cdbase in scope
+    cdbase: String,
+    /// Whether the element counts for the depth
+    object: bool,
+    /// The position and content so far, if this is an OMB
+    base64: Option<(u64, Vec<u8>)>,
+}
+
+#[cfg(feature = "xml-read")]
+impl Analysis {
+    /// Records the start of an element (outside of OMFOREIGNs) at `depth`
+    fn start(
+        &mut self,
+        s: &quick_xml::events::BytesStart<'_>,
+        position: u64,
+        parent: Option<&Open>,
+        depth: usize,
+    ) -> Result<Open, XmlReadError<std::convert::Infallible>> {
+        let name = s.local_name();
+        let current = parent.map_or(crate::CD_BASE, |o| o.cdbase.as_str());
+        let attr = |key: &str| {
+            s.try_get_attribute(key)
+                .ok()
+                .flatten()
+                .and_then(|a| a.unescape_value().ok())
+        };
+        let kind = OMKind::from_name(name.as_ref());
+        let object = match kind {
+            Some(OMKind::OMATTR | OMKind::OMFOREIGN) => false,
+            Some(_) => true,
+            None if matches!(name.as_ref(), b"OMOBJ" | b"OMBVAR" | b"OMATP") => false,
+            None => return Err(XmlReadError::UnexpectedTag(position)),
+        };
+        if let Some(kind) = kind {
+            self.kind(kind);
+        }
+        if object {
+            self.max_depth = self.max_depth.max(depth + 1);
+        }
+        let own = attr("cdbase");
+        match kind {
+            Some(OMKind::OMS) => {
+                let (Some(cd), Some(n)) = (attr("cd"), attr("name")) else {
+                    return Err(XmlReadError::ExpectedAttribute("cd and name"));
+                };
+                self.symbol(own.as_deref(), &cd, &n, current);
+            }
+            Some(OMKind::OMFOREIGN) => {
+                self.foreign.push(attr("encoding").map(Cow::into_owned));
+            }
+            _ => (),
+        }
+        Ok(Open {
+            name: String::from_utf8_lossy(s.name().as_ref()).into_owned(),
+            kind,
+            cdbase: own.map_or_else(|| current.to_string(), Cow::into_owned),
+            object,
+            base64: (kind == Some(OMKind::OMB)).then(|| (position, Vec::new())),
+        })
+    }
+}
+
+#[cfg(all(test, feature = "xml-read"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip_fixture() {
+        use crate::de::OMObject;
+        const XML: &str = include_str!("../doc/roundtrip.xml");
+        let expected = Analysis {
+            cds: [
+                ("http://my.namespace", "utils"),
+                ("http://openmath.org", "error"),
+                ("http://openmath.org/cd", "arith1"),
+                ("http://openmath.org/cd", "fns1"),
+                ("http://openmath.org/cd", "nope"),
+            ]
+            .into_iter()
+            .map(|(b, c)| (b.to_string(), c.to_string()))
+            .collect(),
+            symbols: [
+                ("http://my.namespace?utils#either", 1),
+                ("http://openmath.org?error#unhandled_arithmetics", 1),
+                ("http://openmath.org/cd?arith1#plus", 1),
+                ("http://openmath.org/cd?arith1#real", 1),
+                ("http://openmath.org/cd?fns1#lambda", 2),
+                ("http://openmath.org/cd?nope#type", 4),
+            ]
+            .into_iter()
+            .map(|(s, n)| (s.to_string(), n))
+            .collect(),
+            kinds: [
+                (OMKind::OMI, 2),
+                (OMKind::OMF, 1),
+                (OMKind::OMSTR, 3),
+                (OMKind::OMV, 5),
+                (OMKind::OMS, 10),
+                (OMKind::OMA, 2),
+                (OMKind::OMBIND, 2),
+                (OMKind::OME, 1),
+                (OMKind::OMATTR, 4),
+                (OMKind::OMFOREIGN, 2),
+            ]
+            .into_iter()
+            .collect(),
+            max_depth: 5,
+            foreign: vec![None, Some("application/nonsense".to_string())],
+            bytes: 0,
+        };
+        assert_eq!(analyze_xml(XML).expect("is valid"), expected);
+        let om = OMObject::<OpenMath>::from_openmath_xml(XML).expect("is valid");
+        assert_eq!(analyze(&om), expected);
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn xml() {
+        use crate::de::OMDeserializable;
+        let xml = r#"<OMA cdbase="http://a.org/cd/">
+            <OMS cd="c" name="f"/>
+            <OMATTR><OMATP><OMS cdbase="http://a.org/cd" cd="c" name="k"/><OMB>AQIDBA==</OMB></OMATP><OMB>
+                AQID
+            </OMB></OMATTR>
+            <OMBIND cdbase="http://b.org"><OMS cd="c" name="f"/><OMBVAR><OMV name="x"/></OMBVAR><OMFOREIGN encoding="text/xml"><OMS cd="no" name="no"/></OMFOREIGN></OMBIND>
+        </OMA>"#;
+        let analysis = analyze_xml(xml).expect("is valid");
+        assert_eq!(analysis.symbols.len(), 3);
+        assert_eq!(analysis.symbols["http://a.org/cd?c#f"], 1);
+        assert_eq!(analysis.symbols["http://b.org?c#f"], 1);
+        assert_eq!(analysis.cds.len(), 2);
+        assert_eq!(analysis.bytes, 7);
+        assert_eq!(analysis.max_depth, 3);
+        assert_eq!(analysis.foreign, [Some("text/xml".to_string())]);
+        assert_eq!(analysis.kinds[&OMKind::OMS], 3);
+        assert_eq!(analysis.kinds[&OMKind::OMB], 2);
+
+        assert!(matches!(
+            analyze_xml("<OMA><OMX/></OMA>"),
+            Err(crate::de::XmlReadError::UnexpectedTag(5))
+        ));
+        assert!(analyze_xml("<OMB>A</OMB>").is_err());
+        assert!(analyze_xml("<OMA><OMI>1</OMI>").is_err());
+        assert!(analyze_xml("  ").is_err());
+
+        // agrees with `analyze` on the object
+        let xml = xml.replace(
+            "<OMFOREIGN encoding=\"text/xml\"><OMS cd=\"no\" name=\"no\"/></OMFOREIGN>",
+            "<OMV name=\"x\"/>",
+        );
+        let om = OpenMath::from_openmath_xml(&xml).expect("is valid");
+        assert_eq!(analyze_xml(&xml).expect("is valid"), analyze(&om));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let om =
+            OpenMath::from_sexpr(r#"(ome (oms moreerrors oops) (omb "AQI=") (omforeign "x"))"#);
+        let analysis = analyze(&om.expect("is valid"));
+        let json = serde_json::to_string(&analysis).expect("is serializable");
+        assert_eq!(
+            serde_json::from_str::<Analysis>(&json).expect("is valid"),
+            analysis
+        );
+    }
+}
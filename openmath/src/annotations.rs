@@ -0,0 +1,379 @@
+//! Type annotations across whole objects; see [`OpenMath::type_annotations`] and
+//! [`OpenMath::check_annotation_coverage`]
+
+use crate::{
+    Attr, CD_BASE, OMKind, OMMaybeForeign, OpenMath, Path, Step,
+    ser::{AsOMS, Uri, attr_key},
+    visit::Visitor,
+};
+
+/// The key of type annotations, `sts.type` (in [`CD_BASE`])
+pub const TYPE_ANNOTATION: Uri<'static> = Uri {
+    cdbase: None,
+    cd: "sts",
+    name: "type",
+};
+
+/** Which objects [`OpenMath::check_annotation_coverage`] requires to have a type annotation.
+
+Objects in attribute values (e.g. the types themselves) never require one. A
+[variable](crate::OMKind::OMV) bound by an [OMBIND](crate::OMKind::OMBIND) counts as
+annotated if the bound variable it refers to is (as in `λx:ℕ. x`), in addition to being
+annotated itself; the bound variables themselves are not checked, since no [`Path`] refers
+to them.
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum AnnotationPolicy {
+    /// Every object
+    All,
+    /// Every object without children, i.e. every [OMI](OMKind::OMI),
+    /// [OMF](OMKind::OMF), [OMSTR](OMKind::OMSTR), [OMB](OMKind::OMB),
+    /// [OMV](OMKind::OMV) and [OMS](OMKind::OMS)
+    #[default]
+    Leaves,
+    /// The applicant of every [OMA](OMKind::OMA)
+    Heads,
+    /// Every [OMV](OMKind::OMV)
+    Variables,
+}
+
+/// The resolved URIs of `keys`, to compare attribute keys with
+fn resolve<K: AsOMS>(keys: &[K]) -> Vec<String> {
+    keys.iter()
+        .map(|k| attr_key(k, CD_BASE).resolved_uri(CD_BASE))
+        .collect()
+}
+
+fn is_annotation(attr: &Attr<'_, impl Sized>, keys: &[String]) -> bool {
+    let uri = attr.key.resolved_uri(CD_BASE);
+    keys.contains(&uri)
+}
+
+/// Collects the annotations with the given keys and their paths
+struct Annotations<'a, 'om> {
+    keys: Vec<String>,
+    path: Vec<Step>,
+    /// Whether the attribute whose value comes next is an annotation
+    next: bool,
+    found: Vec<(Path, &'a OpenMath<'om>)>,
+}
+impl<'a, 'om: 'a> Visitor<'a, 'om> for Annotations<'a, 'om> {
+    fn enter(&mut self, om: &'a OpenMath<'om>, step: Option<Step>) {
+        if let Some(step) = step {
+            self.path.push(step);
+        }
+        if std::mem::take(&mut self.next) {
+            self.found.push((Path(self.path.clone()), om));
+        }
+    }
+    fn leave(&mut self, _: &'a OpenMath<'om>) {
+        self.path.pop();
+    }
+    fn attribute(&mut self, attr: &'a Attr<'om, OMMaybeForeign<'om, OpenMath<'om>>>) {
+        self.next = is_annotation(attr, &self.keys);
+    }
+    fn foreign(&mut self, _: Option<&'a str>, _: &'a str) {
+        self.next = false;
+    }
+}
+
+/// Collects the objects lacking a type annotation
+struct Coverage<'a, 'om> {
+    policy: AnnotationPolicy,
+    keys: Vec<String>,
+    /// The objects entered but not left, with the steps to them
+    ancestors: Vec<(&'a OpenMath<'om>, Option<Step>)>,
+    /// How many of the `ancestors` are attribute values
+    in_attributes: usize,
+    missing: Vec<Path>,
+}
+impl<'a, 'om: 'a> Coverage<'a, 'om> {
+    fn annotated(&self, attributes: &[Attr<'_, impl Sized>]) -> bool {
+        attributes.iter().any(|a| is_annotation(a, &self.keys))
+    }
+
+    /// Whether `om` requires an annotation; `step` is how it is reached from its parent
+    fn required(&self, om: &OpenMath<'_>, step: Option<Step>) -> bool {
+        match self.policy {
+            AnnotationPolicy::All => true,
+            AnnotationPolicy::Leaves => !matches!(
+                om.kind(),
+                OMKind::OMA | OMKind::OMBIND | OMKind::OME | OMKind::OMATTR
+            ),
+            AnnotationPolicy::Heads => step == Some(Step::Applicant),
+            AnnotationPolicy::Variables => om.kind() == OMKind::OMV,
+        }
+    }
+
+    /// Whether the innermost variable `name` is bound to is annotated; `None` if `name` is
+    /// free
+    fn bound_annotated(&self, name: &str) -> Option<bool> {
+        // the OMBINDs whose body contains the current object, innermost first
+        self.ancestors
+            .windows(2)
+            .rev()
+            .filter(|w| w[1].1 == Some(Step::Body))
+            .find_map(|w| w[0].0.bound_variable(name))
+            .map(|(_, var)| self.annotated(&var.attributes))
+    }
+}
+impl<'a, 'om: 'a> Visitor<'a, 'om> for Coverage<'a, 'om> {
+    fn enter(&mut self, om: &'a OpenMath<'om>, step: Option<Step>) {
+        if matches!(step, Some(Step::Attribute(_) | Step::VariableAttribute(..))) {
+            self.in_attributes += 1;
+        }
+        self.ancestors.push((om, step));
+        if self.in_attributes > 0 || !self.required(om, step) || self.annotated(om.attributes()) {
+            return;
+        }
+        if let OpenMath::OMV { name, .. } = om
+            && self.bound_annotated(name) == Some(true)
+        {
+            return;
+        }
+        self.missing.push(
+            self.ancestors
+                .iter()
+                .filter_map(|(_, step)| *step)
+                .collect(),
+        );
+    }
+    fn leave(&mut self, _: &'a OpenMath<'om>) {
+        if let Some((_, Some(Step::Attribute(_) | Step::VariableAttribute(..)))) =
+            self.ancestors.pop()
+        {
+            self.in_attributes -= 1;
+        }
+    }
+}
+
+impl<'om> OpenMath<'om> {
+    /** The values of all type annotations (attribution pairs with key
+    [`TYPE_ANNOTATION`]) in this object, with their paths, in document order; including
+    those of bound variables and of attribute values, but not
+    [OMFOREIGN](crate::OMKind::OMFOREIGN) ones. See [`annotations`](Self::annotations).
+
+    # Examples
+    ```rust
+    use openmath::{OpenMath, Path, Step};
+
+    let om = OpenMath::from_sexpr(
+        "(ombind (oms fns1 lambda) (bvar (omattr (((oms sts type) (oms setname1 N))) x)) \
+         (omattr (((oms sts type) (oms setname1 Z))) (omv x)))",
+    )
+    .expect("is valid");
+    let annotations = om
+        .type_annotations()
+        .map(|(path, ty)| (path.to_string(), ty.to_sexpr()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        annotations,
+        [
+            ("/var0@0".to_string(), "(oms setname1 N)".to_string()),
+            ("/body/@0".to_string(), "(oms setname1 Z)".to_string()),
+        ]
+    );
+    ```
+    */
+    pub fn type_annotations(&self) -> impl Iterator<Item = (Path, &Self)> {
+        self.annotations(&[TYPE_ANNOTATION])
+    }
+
+    /// Like [`type_annotations`](Self::type_annotations), but for attribution pairs with
+    /// any of the given `keys`; keys are compared by their
+    /// [resolved URI](crate::SymbolRef::resolved_uri), like in
+    /// [`dedup_attributes`](Self::dedup_attributes).
+    pub fn annotations<'a, K: AsOMS>(
+        &'a self,
+        keys: &[K],
+    ) -> impl Iterator<Item = (Path, &'a Self)> + use<'a, 'om, K> {
+        let mut annotations = Annotations {
+            keys: resolve(keys),
+            path: Vec::new(),
+            next: false,
+            found: Vec::new(),
+        };
+        self.walk(&mut annotations);
+        annotations.found.into_iter()
+    }
+
+    /// Removes all type annotations from this object, including those of bound variables
+    /// and of attribute values; see [`strip_annotations`](Self::strip_annotations).
+    #[inline]
+    pub fn strip_type_annotations(&mut self) {
+        self.strip_annotations(&[TYPE_ANNOTATION]);
+    }
+
+    /// Removes all attribution pairs with any of the given `keys` from this object, compared
+    /// like in [`annotations`](Self::annotations); the order of the other pairs is preserved.
+    pub fn strip_annotations<K: AsOMS>(&mut self, keys: &[K]) {
+        let keys = resolve(keys);
+        self.for_each_attributes(&mut |attributes| {
+            attributes.retain(|a| !is_annotation(a, &keys));
+        });
+    }
+
+    /** The paths of all objects that lack a type annotation, but require one according to
+    `policy`, in document order.
+
+    # Examples
+    ```rust
+    use openmath::{AnnotationPolicy, OpenMath};
+
+    let om = OpenMath::from_sexpr(
+        "(oma (omattr (((oms sts type) (oms sts NumericalValue))) (oms arith1 plus)) \
+         (omattr (((oms sts type) (oms setname1 N))) (omi 1)) (omv x))",
+    )
+    .expect("is valid");
+    let paths = |policy| {
+        om.check_annotation_coverage(policy)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(paths(AnnotationPolicy::Leaves), ["/1"]);
+    assert!(paths(AnnotationPolicy::Heads).is_empty());
+    assert_eq!(paths(AnnotationPolicy::All), ["/", "/1"]);
+    ```
+    */
+    #[must_use]
+    pub fn check_annotation_coverage(&self, policy: AnnotationPolicy) -> Vec<Path> {
+        let mut coverage = Coverage {
+            policy,
+            keys: resolve(&[TYPE_ANNOTATION]),
+            ancestors: Vec::new(),
+            in_attributes: 0,
+            missing: Vec::new(),
+        };
+        self.walk(&mut coverage);
+        coverage.missing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AnnotationPolicy;
+    use crate::test_util::om as parse;
+    use crate::{OpenMath, ser::Uri};
+
+    fn paths(om: &OpenMath<'_>, policy: AnnotationPolicy) -> Vec<String> {
+        om.check_annotation_coverage(policy)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    /// `λx:N. plus(x, y)`, with the binder, the body and `plus` annotated as well
+    const LAMBDA: &str = "(omattr (((oms sts type) (oms test fun))) \
+        (ombind (omattr (((oms sts type) (oms test binder))) (oms fns1 lambda)) \
+        (bvar (omattr (((oms meta note) (omstr \"n\")) ((oms sts type) (oms setname1 N))) x)) \
+        (omattr (((oms sts type) (oms setname1 N))) \
+        (oma (omattr (((oms sts type) (oms test plus))) (oms arith1 plus)) (omv x) (omv y)))))";
+
+    #[test]
+    fn annotations() {
+        let om = parse(LAMBDA);
+        let found = om
+            .type_annotations()
+            .map(|(path, ty)| {
+                assert_eq!(om.at(&path), Some(ty));
+                (path.to_string(), ty.to_sexpr())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                ("/@0", "(oms test fun)"),
+                ("/binder/@0", "(oms test binder)"),
+                ("/var0@1", "(oms setname1 N)"),
+                ("/body/@0", "(oms setname1 N)"),
+                ("/body/applicant/@0", "(oms test plus)"),
+            ]
+            .map(|(p, t)| (p.to_string(), t.to_string()))
+        );
+
+        let note = Uri {
+            cdbase: None,
+            cd: "meta",
+            name: "note",
+        };
+        let notes = om
+            .annotations(&[&note])
+            .map(|(p, _)| p.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(notes, ["/var0@0"]);
+        // keys with the default cdbase given explicitly are the same
+        let explicit = Uri {
+            cdbase: Some(crate::CdBase::OPENMATH),
+            ..note
+        };
+        assert_eq!(om.annotations(&[explicit]).count(), 1);
+
+        // annotations of annotations, but no foreign ones
+        let om = parse(
+            "(omattr (((oms sts type) (omattr (((oms sts type) (oms test kind))) (oms test ty))) \
+             ((oms sts type) (omforeign \"text/plain\" \"t\"))) (omi 1))",
+        );
+        let found = om
+            .type_annotations()
+            .map(|(p, _)| p.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(found, ["/@0", "/@0/@0"]);
+    }
+
+    #[test]
+    fn strip() {
+        let mut om = parse(LAMBDA);
+        om.strip_type_annotations();
+        assert_eq!(om.type_annotations().count(), 0);
+        assert_eq!(
+            om,
+            parse(
+                "(ombind (oms fns1 lambda) (bvar (omattr (((oms meta note) (omstr \"n\"))) x)) \
+                 (oma (oms arith1 plus) (omv x) (omv y)))"
+            )
+        );
+    }
+
+    #[test]
+    fn coverage() {
+        let om = parse(LAMBDA);
+        // `x` is annotated at its binding site, `y` is free
+        assert_eq!(paths(&om, AnnotationPolicy::Leaves), ["/body/1"]);
+        assert_eq!(paths(&om, AnnotationPolicy::Variables), ["/body/1"]);
+        assert!(paths(&om, AnnotationPolicy::Heads).is_empty());
+        assert_eq!(paths(&om, AnnotationPolicy::All), ["/body/1"]);
+
+        // the annotation of the binder or the OMBIND does not cover the variables
+        let om = parse(
+            "(omattr (((oms sts type) (oms test fun))) \
+             (ombind (omattr (((oms sts type) (oms test binder))) (oms fns1 lambda)) \
+             (bvar x) (omv x)))",
+        );
+        assert_eq!(paths(&om, AnnotationPolicy::Leaves), ["/body"]);
+
+        // the innermost binding counts, and only in the body
+        let om = parse(
+            "(ombind (oms fns1 lambda) (bvar (omattr (((oms sts type) (oms setname1 N))) x)) \
+             (ombind (oms fns1 lambda) (bvar x) (omv x)))",
+        );
+        assert_eq!(paths(&om, AnnotationPolicy::Variables), ["/body/body"]);
+        let om = parse(
+            "(ombind (oms fns1 lambda) (bvar x (omattr (((oms sts type) (oms setname1 N))) x)) (omv x))",
+        );
+        assert!(paths(&om, AnnotationPolicy::Variables).is_empty());
+        let om = parse(
+            "(ombind (ombind (oms fns1 lambda) (bvar y) (omv x)) \
+             (bvar (omattr (((oms sts type) (oms setname1 N))) x)) (omv x))",
+        );
+        assert_eq!(paths(&om, AnnotationPolicy::Variables), ["/binder/body"]);
+
+        // heads, and nothing in attribute values
+        let om = parse(
+            "(oma (oms arith1 plus) (oma (omattr (((oms sts type) (oms test f))) (omv f)) \
+             (omattr (((oms sts type) (oma (oms test list) (oms setname1 N)))) (omi 1))))",
+        );
+        assert_eq!(paths(&om, AnnotationPolicy::Heads), ["/applicant"]);
+        assert_eq!(paths(&om, AnnotationPolicy::All), ["/", "/applicant", "/0"]);
+    }
+}
@@ -0,0 +1,1123 @@
+/*! A compact representation of <span style="font-variant:small-caps;">OpenMath</span> objects, with
+all nodes in contiguous arrays; see [`OmArena`].
+
+An [`OpenMath`] node takes 128 bytes (on 64-bit targets), plus its own allocations for
+argument and attribute lists, which wastes a lot of memory for leaf-heavy terms. An
+[`OmArena`] stores a node in 16 bytes plus its payload (e.g. a string) and one [`OmId`]
+(4 bytes) per reference from its parent; variable-sized payloads and child lists live in
+shared vectors, and every distinct symbol is stored once.
+*/
+
+use std::{borrow::Cow, cell::RefCell, collections::HashMap};
+
+use crate::{
+    Attr, BoundVariable, Int, OMMaybeForeign, OMSerializable, OpenMath, SymbolRef,
+    ser::{self, AsOMS, Error as _},
+};
+
+/// The index of a node in an [`OmArena`]; only meaningful for the arena that returned it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct OmId(u32);
+
+impl OmId {
+    /// The position of the node in its arena, in the order nodes were added
+    #[inline]
+    #[must_use]
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A range of entries in one of the vectors of an [`OmArena`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Span {
+    start: u32,
+    len: u32,
+}
+
+impl Span {
+    #[inline]
+    const fn range(self) -> std::ops::Range<usize> {
+        self.start as usize..(self.start + self.len) as usize
+    }
+}
+
+/// A node; 16 bytes, payloads are indices into the vectors of the arena
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Node {
+    Omi(u32),
+    Omf(f64),
+    Omstr(u32),
+    Omb(u32),
+    Omv(u32),
+    Oms {
+        symbol: u32,
+        cdbase_inherited: bool,
+    },
+    /// the applicant, followed by the arguments
+    Oma(Span),
+    Ome {
+        symbol: u32,
+        arguments: Span,
+    },
+    /// the binder and the body, followed by the variables
+    Ombind(Span),
+    Foreign(u32),
+}
+
+/** A compact arena of <span style="font-variant:small-caps;">OpenMath</span> objects: nodes
+live in contiguous vectors, and refer to their children by [`OmId`]s.
+
+Objects are built bottom-up, with one method per kind of node (which returns the id of the
+new node), converted from an [`OpenMath`] via [`insert`](Self::insert), or
+[parsed](Self::from_openmath_xml) directly. Nodes are inspected via [`get`](Self::get) and
+[`attributes`](Self::attributes), [serialized](OMSerializable) via
+[`object`](Self::object), and converted back via [`to_openmath`](Self::to_openmath).
+
+[OMFOREIGN](crate::OMKind::OMFOREIGN)s are nodes, too, but can only occur as arguments of
+[OME](crate::OMKind::OME)s and as attribute values; bound variables are
+[OMV](crate::OMKind::OMV) nodes (possibly with attributes).
+
+# Examples
+```
+use openmath::{OMSerializable, OpenMath, SymbolRef, arena::{OmArena, OmNode}};
+
+let mut arena = OmArena::new();
+let plus = arena.oms(SymbolRef::new("arith1", "plus"));
+let (one, x) = (arena.omi(1), arena.omv("x"));
+let sum = arena.oma(plus, [one, x]);
+assert_eq!(arena.len(), 4);
+assert!(matches!(arena.get(sum), OmNode::OMA { applicant, arguments } if applicant == plus && arguments == [one, x]));
+# #[cfg(feature = "xml-write")]
+# {
+assert_eq!(
+    arena.object(sum).xml(false).to_string(),
+    r#"<OMA><OMS cd="arith1" name="plus"/><OMI>1</OMI><OMV name="x"/></OMA>"#
+);
+# }
+
+let om = OpenMath::from_sexpr("(oma (oms arith1 plus) (omi 1) (omv x))").expect("is valid");
+let mut other = OmArena::new();
+let id = other.insert(&om);
+assert_eq!(other.to_openmath(id), om);
+```
+*/
+#[derive(Debug, Clone, Default)]
+pub struct OmArena<'om> {
+    nodes: Vec<Node>,
+    children: Vec<OmId>,
+    ints: Vec<Int<'om>>,
+    strings: Vec<Cow<'om, str>>,
+    bytes: Vec<Cow<'om, [u8]>>,
+    symbols: Vec<SymbolRef<'om>>,
+    foreign: Vec<(Option<Cow<'om, str>>, Cow<'om, str>)>,
+    /// the indices of the distinct symbols, which are stored only once
+    symbol_indices: HashMap<SymbolRef<'om>, u32>,
+    attributes: Vec<(u32, OmId)>,
+    /// the attributes of the nodes that have any
+    attributed: HashMap<OmId, Span>,
+}
+
+/// A node of an [`OmArena`], with its children as [`OmId`]s; see [`OmArena::get`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OmNode<'a, 'om> {
+    /// An [OMI](crate::OMKind::OMI)
+    OMI(&'a Int<'om>),
+    /// An [OMF](crate::OMKind::OMF)
+    OMF(f64),
+    /// An [OMSTR](crate::OMKind::OMSTR)
+    OMSTR(&'a str),
+    /// An [OMB](crate::OMKind::OMB)
+    OMB(&'a [u8]),
+    /// An [OMV](crate::OMKind::OMV)
+    OMV(&'a str),
+    /// An [OMS](crate::OMKind::OMS); see [`OpenMath::OMS`]
+    OMS {
+        symbol: &'a SymbolRef<'om>,
+        cdbase_inherited: bool,
+    },
+    /// An [OMA](crate::OMKind::OMA)
+    OMA {
+        applicant: OmId,
+        arguments: &'a [OmId],
+    },
+    /// An [OME](crate::OMKind::OME)
+    OME {
+        symbol: &'a SymbolRef<'om>,
+        arguments: &'a [OmId],
+    },
+    /// An [OMBIND](crate::OMKind::OMBIND); the variables are [`OMV`](Self::OMV)s
+    OMBIND {
+        binder: OmId,
+        variables: &'a [OmId],
+        object: OmId,
+    },
+    /// An [OMFOREIGN](crate::OMKind::OMFOREIGN)
+    Foreign {
+        encoding: Option<&'a str>,
+        value: &'a str,
+    },
+}
+
+#[inline]
+fn index(len: usize) -> u32 {
+    u32::try_from(len).expect("an OmArena holds at most u32::MAX entries of each kind")
+}
+
+impl<'om> OmArena<'om> {
+    /// An empty arena
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of nodes in this arena
+    #[inline]
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Whether this arena has no nodes
+    #[inline]
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// The node added last, if any; e.g. the root of the last object
+    /// [inserted](Self::insert) or [parsed](Self::from_openmath_xml).
+    #[inline]
+    #[must_use]
+    pub fn root(&self) -> Option<OmId> {
+        self.nodes.len().checked_sub(1).map(|i| OmId(index(i)))
+    }
+
+    /// The bytes allocated by this arena (approximately), excluding the contents of owned
+    /// strings, byte arrays and big integers
+    #[must_use]
+    pub fn allocated_bytes(&self) -> usize {
+        const fn vec<T>(v: &Vec<T>) -> usize {
+            v.capacity() * std::mem::size_of::<T>()
+        }
+        vec(&self.nodes)
+            + vec(&self.children)
+            + vec(&self.ints)
+            + vec(&self.strings)
+            + vec(&self.bytes)
+            + vec(&self.symbols)
+            + vec(&self.foreign)
+            + vec(&self.attributes)
+            + self.symbol_indices.capacity() * (std::mem::size_of::<(SymbolRef, u32)>() + 1)
+            + self.attributed.capacity() * (std::mem::size_of::<(OmId, Span)>() + 1)
+    }
+
+    fn push(&mut self, node: Node) -> OmId {
+        let id = OmId(index(self.nodes.len()));
+        self.nodes.push(node);
+        id
+    }
+
+    fn push_children(&mut self, children: impl IntoIterator<Item = OmId>) -> Span {
+        let start = index(self.children.len());
+        self.children.extend(children);
+        Span {
+            start,
+            len: index(self.children.len()) - start,
+        }
+    }
+
+    fn push_symbol(&mut self, symbol: SymbolRef<'om>) -> u32 {
+        if let Some(i) = self.symbol_indices.get(&symbol) {
+            return *i;
+        }
+        let i = index(self.symbols.len());
+        self.symbols.push(symbol.clone());
+        self.symbol_indices.insert(symbol, i);
+        i
+    }
+
+    #[inline]
+    fn node(&self, id: OmId) -> Node {
+        self.nodes[id.index()]
+    }
+
+    #[inline]
+    fn is_foreign(&self, id: OmId) -> bool {
+        matches!(self.node(id), Node::Foreign(_))
+    }
+
+    /// Adds an [OMI](crate::OMKind::OMI)
+    pub fn omi(&mut self, int: impl Into<Int<'om>>) -> OmId {
+        self.ints.push(int.into());
+        let i = index(self.ints.len() - 1);
+        self.push(Node::Omi(i))
+    }
+
+    /// Adds an [OMF](crate::OMKind::OMF)
+    pub fn omf(&mut self, float: f64) -> OmId {
+        self.push(Node::Omf(float))
+    }
+
+    /// Adds an [OMSTR](crate::OMKind::OMSTR)
+    pub fn omstr(&mut self, string: impl Into<Cow<'om, str>>) -> OmId {
+        self.strings.push(string.into());
+        let i = index(self.strings.len() - 1);
+        self.push(Node::Omstr(i))
+    }
+
+    /// Adds an [OMB](crate::OMKind::OMB)
+    pub fn omb(&mut self, bytes: impl Into<Cow<'om, [u8]>>) -> OmId {
+        self.bytes.push(bytes.into());
+        let i = index(self.bytes.len() - 1);
+        self.push(Node::Omb(i))
+    }
+
+    /// Adds an [OMV](crate::OMKind::OMV)
+    pub fn omv(&mut self, name: impl Into<Cow<'om, str>>) -> OmId {
+        self.strings.push(name.into());
+        let i = index(self.strings.len() - 1);
+        self.push(Node::Omv(i))
+    }
+
+    /// Adds an [OMS](crate::OMKind::OMS); its cdbase counts as explicitly given
+    pub fn oms(&mut self, symbol: SymbolRef<'om>) -> OmId {
+        self.oms_inherited(symbol, false)
+    }
+
+    fn oms_inherited(&mut self, symbol: SymbolRef<'om>, cdbase_inherited: bool) -> OmId {
+        let symbol = self.push_symbol(symbol);
+        self.push(Node::Oms {
+            symbol,
+            cdbase_inherited,
+        })
+    }
+
+    /// Adds an [OMA](crate::OMKind::OMA)
+    ///
+    /// # Panics
+    /// if the applicant or an argument is an [OMFOREIGN](crate::OMKind::OMFOREIGN)
+    pub fn oma(&mut self, applicant: OmId, arguments: impl IntoIterator<Item = OmId>) -> OmId {
+        let span = self.push_children(std::iter::once(applicant).chain(arguments));
+        assert!(
+            !self.children[span.range()]
+                .iter()
+                .any(|c| self.is_foreign(*c)),
+            "OMFOREIGN is not allowed in an OMA"
+        );
+        self.push(Node::Oma(span))
+    }
+
+    /// Adds an [OME](crate::OMKind::OME); its arguments may be
+    /// [OMFOREIGN](crate::OMKind::OMFOREIGN)s.
+    pub fn ome(
+        &mut self,
+        symbol: SymbolRef<'om>,
+        arguments: impl IntoIterator<Item = OmId>,
+    ) -> OmId {
+        let arguments = self.push_children(arguments);
+        let symbol = self.push_symbol(symbol);
+        self.push(Node::Ome { symbol, arguments })
+    }
+
+    /// Adds an [OMBIND](crate::OMKind::OMBIND)
+    ///
+    /// # Panics
+    /// if a variable is not an [OMV](crate::OMKind::OMV), or the binder or body is an
+    /// [OMFOREIGN](crate::OMKind::OMFOREIGN)
+    pub fn ombind(
+        &mut self,
+        binder: OmId,
+        variables: impl IntoIterator<Item = OmId>,
+        object: OmId,
+    ) -> OmId {
+        let span = self.push_children([binder, object].into_iter().chain(variables));
+        assert!(
+            !self.is_foreign(binder) && !self.is_foreign(object),
+            "OMFOREIGN is not allowed as binder or body of an OMBIND"
+        );
+        assert!(
+            self.children[span.range()][2..]
+                .iter()
+                .all(|v| matches!(self.node(*v), Node::Omv(_))),
+            "bound variables need to be OMVs"
+        );
+        self.push(Node::Ombind(span))
+    }
+
+    /// Adds an [OMFOREIGN](crate::OMKind::OMFOREIGN), for use as an argument of an
+    /// [OME](Self::ome) or as an attribute value
+    pub fn foreign(
+        &mut self,
+        encoding: Option<Cow<'om, str>>,
+        value: impl Into<Cow<'om, str>>,
+    ) -> OmId {
+        self.foreign.push((encoding, value.into()));
+        let i = index(self.foreign.len() - 1);
+        self.push(Node::Foreign(i))
+    }
+
+    /// Replaces the attributes of the node `id`
+    ///
+    /// # Panics
+    /// if `id` is an [OMFOREIGN](crate::OMKind::OMFOREIGN)
+    pub fn set_attributes(
+        &mut self,
+        id: OmId,
+        attributes: impl IntoIterator<Item = (SymbolRef<'om>, OmId)>,
+    ) {
+        assert!(!self.is_foreign(id), "OMFOREIGN can not have attributes");
+        let start = index(self.attributes.len());
+        for (key, value) in attributes {
+            let key = self.push_symbol(key);
+            self.attributes.push((key, value));
+        }
+        let len = index(self.attributes.len()) - start;
+        if len == 0 {
+            self.attributed.remove(&id);
+        } else {
+            self.attributed.insert(id, Span { start, len });
+        }
+    }
+
+    /// The node `id`
+    ///
+    /// # Panics
+    /// if `id` is not a node of this arena
+    #[must_use]
+    pub fn get(&self, id: OmId) -> OmNode<'_, 'om> {
+        match self.node(id) {
+            Node::Omi(i) => OmNode::OMI(&self.ints[i as usize]),
+            Node::Omf(f) => OmNode::OMF(f),
+            Node::Omstr(i) => OmNode::OMSTR(&self.strings[i as usize]),
+            Node::Omb(i) => OmNode::OMB(&self.bytes[i as usize]),
+            Node::Omv(i) => OmNode::OMV(&self.strings[i as usize]),
+            Node::Oms {
+                symbol,
+                cdbase_inherited,
+            } => OmNode::OMS {
+                symbol: &self.symbols[symbol as usize],
+                cdbase_inherited,
+            },
+            Node::Oma(span) => {
+                let children = &self.children[span.range()];
+                OmNode::OMA {
+                    applicant: children[0],
+                    arguments: &children[1..],
+                }
+            }
+            Node::Ome { symbol, arguments } => OmNode::OME {
+                symbol: &self.symbols[symbol as usize],
+                arguments: &self.children[arguments.range()],
+            },
+            Node::Ombind(span) => {
+                let children = &self.children[span.range()];
+                OmNode::OMBIND {
+                    binder: children[0],
+                    variables: &children[2..],
+                    object: children[1],
+                }
+            }
+            Node::Foreign(i) => {
+                let (encoding, value) = &self.foreign[i as usize];
+                OmNode::Foreign {
+                    encoding: encoding.as_deref(),
+                    value,
+                }
+            }
+        }
+    }
+
+    /// The attributes of the node `id`, as pairs of key and value
+    #[must_use]
+    pub fn attributes(
+        &self,
+        id: OmId,
+    ) -> impl ExactSizeIterator<Item = (&SymbolRef<'om>, OmId)> + Clone + '_ {
+        let span = self.attributed.get(&id).copied().unwrap_or_default();
+        self.attributes[span.range()]
+            .iter()
+            .map(|(key, value)| (&self.symbols[*key as usize], *value))
+    }
+
+    /// The children of the node `id` (excluding attributes): the applicant and arguments
+    /// of an [OMA](crate::OMKind::OMA), the arguments of an [OME](crate::OMKind::OME), and
+    /// the binder, body and variables of an [OMBIND](crate::OMKind::OMBIND)
+    #[must_use]
+    pub fn children(&self, id: OmId) -> &[OmId] {
+        match self.node(id) {
+            Node::Oma(span)
+            | Node::Ombind(span)
+            | Node::Ome {
+                arguments: span, ..
+            } => &self.children[span.range()],
+            _ => &[],
+        }
+    }
+
+    /// The node `id`, for [serialization](OMSerializable)
+    #[inline]
+    #[must_use]
+    pub const fn object(&self, id: OmId) -> ArenaObject<'_, 'om> {
+        ArenaObject { arena: self, id }
+    }
+
+    /// Adds `om` (and all its subterms) to this arena; returns the id of its root.
+    pub fn insert(&mut self, om: &OpenMath<'om>) -> OmId {
+        let id = match om {
+            OpenMath::OMI { int, .. } => self.omi(int.clone()),
+            OpenMath::OMF { float, .. } => self.omf(float.0),
+            OpenMath::OMSTR { string, .. } => self.omstr(string.clone()),
+            OpenMath::OMB { bytes, .. } => self.omb(bytes.clone()),
+            OpenMath::OMV { name, .. } => self.omv(name.clone()),
+            OpenMath::OMS {
+                cd,
+                name,
+                cdbase,
+                cdbase_inherited,
+                ..
+            } => self.oms_inherited(
+                SymbolRef {
+                    cdbase: cdbase.clone(),
+                    cd: cd.clone(),
+                    name: name.clone(),
+                },
+                *cdbase_inherited,
+            ),
+            OpenMath::OMA {
+                applicant,
+                arguments,
+                ..
+            } => {
+                let applicant = self.insert(applicant);
+                let arguments = arguments.iter().map(|a| self.insert(a)).collect::<Vec<_>>();
+                let span = self.push_children(std::iter::once(applicant).chain(arguments));
+                self.push(Node::Oma(span))
+            }
+            OpenMath::OME {
+                cd,
+                name,
+                cdbase,
+                arguments,
+                ..
+            } => {
+                let arguments = arguments
+                    .iter()
+                    .map(|a| self.insert_foreign(a))
+                    .collect::<Vec<_>>();
+                self.ome(
+                    SymbolRef {
+                        cdbase: cdbase.clone(),
+                        cd: cd.clone(),
+                        name: name.clone(),
+                    },
+                    arguments,
+                )
+            }
+            OpenMath::OMBIND {
+                binder,
+                variables,
+                object,
+                ..
+            } => {
+                let binder = self.insert(binder);
+                let variables = variables
+                    .iter()
+                    .map(|v| {
+                        let id = self.omv(v.name.clone());
+                        self.insert_attributes(id, &v.attributes);
+                        id
+                    })
+                    .collect::<Vec<_>>();
+                let object = self.insert(object);
+                let span = self.push_children([binder, object].into_iter().chain(variables));
+                self.push(Node::Ombind(span))
+            }
+        };
+        self.insert_attributes(id, om.attributes());
+        id
+    }
+
+    fn insert_foreign(&mut self, o: &OMMaybeForeign<'om, OpenMath<'om>>) -> OmId {
+        match o {
+            OMMaybeForeign::OM(o) => self.insert(o),
+            OMMaybeForeign::Foreign { encoding, value } => {
+                self.foreign(encoding.clone(), value.clone())
+            }
+        }
+    }
+
+    fn insert_attributes(
+        &mut self,
+        id: OmId,
+        attributes: &[Attr<'om, OMMaybeForeign<'om, OpenMath<'om>>>],
+    ) {
+        if attributes.is_empty() {
+            return;
+        }
+        let attributes = attributes
+            .iter()
+            .map(|a| (a.key.clone(), self.insert_foreign(&a.value)))
+            .collect::<Vec<_>>();
+        self.set_attributes(id, attributes);
+    }
+
+    /// Converts the node `id` (and all its subterms) to an [`OpenMath`]
+    ///
+    /// # Panics
+    /// if `id` is an [OMFOREIGN](crate::OMKind::OMFOREIGN)
+    #[must_use]
+    pub fn to_openmath(&self, id: OmId) -> OpenMath<'om> {
+        let attributes = self.to_attributes(id);
+        match self.node(id) {
+            Node::Omi(i) => OpenMath::OMI {
+                int: self.ints[i as usize].clone(),
+                attributes,
+            },
+            Node::Omf(float) => OpenMath::OMF {
+                float: float.into(),
+                attributes,
+            },
+            Node::Omstr(i) => OpenMath::OMSTR {
+                string: self.strings[i as usize].clone(),
+                attributes,
+            },
+            Node::Omb(i) => OpenMath::OMB {
+                bytes: self.bytes[i as usize].clone(),
+                attributes,
+            },
+            Node::Omv(i) => OpenMath::OMV {
+                name: self.strings[i as usize].clone(),
+                attributes,
+            },
+            Node::Oms {
+                symbol,
+                cdbase_inherited,
+            } => {
+                let SymbolRef { cdbase, cd, name } = self.symbols[symbol as usize].clone();
+                OpenMath::OMS {
+                    cd,
+                    name,
+                    cdbase,
+                    cdbase_inherited,
+                    attributes,
+                }
+            }
+            Node::Oma(span) => {
+                let children = &self.children[span.range()];
+                OpenMath::OMA {
+                    applicant: Box::new(self.to_openmath(children[0])),
+                    arguments: children[1..].iter().map(|a| self.to_openmath(*a)).collect(),
+                    attributes,
+                }
+            }
+            Node::Ome { symbol, arguments } => {
+                let SymbolRef { cdbase, cd, name } = self.symbols[symbol as usize].clone();
+                OpenMath::OME {
+                    cd,
+                    name,
+                    cdbase,
+                    arguments: self.children[arguments.range()]
+                        .iter()
+                        .map(|a| self.to_foreign(*a))
+                        .collect(),
+                    attributes,
+                }
+            }
+            Node::Ombind(span) => {
+                let children = &self.children[span.range()];
+                OpenMath::OMBIND {
+                    binder: Box::new(self.to_openmath(children[0])),
+                    variables: children[2..]
+                        .iter()
+                        .map(|v| {
+                            let Node::Omv(name) = self.node(*v) else {
+                                unreachable!("bound variables are OMVs")
+                            };
+                            BoundVariable {
+                                name: self.strings[name as usize].clone(),
+                                attributes: self.to_attributes(*v),
+                            }
+                        })
+                        .collect(),
+                    object: Box::new(self.to_openmath(children[1])),
+                    attributes,
+                }
+            }
+            Node::Foreign(_) => panic!("OMFOREIGN is not an OpenMath object"),
+        }
+    }
+
+    fn to_foreign(&self, id: OmId) -> OMMaybeForeign<'om, OpenMath<'om>> {
+        if let Node::Foreign(i) = self.node(id) {
+            let (encoding, value) = self.foreign[i as usize].clone();
+            OMMaybeForeign::Foreign { encoding, value }
+        } else {
+            OMMaybeForeign::OM(self.to_openmath(id))
+        }
+    }
+
+    fn to_attributes(&self, id: OmId) -> Vec<Attr<'om, OMMaybeForeign<'om, OpenMath<'om>>>> {
+        self.attributes(id)
+            .map(|(key, value)| Attr::new(key.clone(), self.to_foreign(value)))
+            .collect()
+    }
+
+    /// The cdbase of the first symbol below `id` (in document order) that inherited its
+    /// cdbase; see [`OpenMath::OMS`]. Memoized in `memo`, so that every node is visited once
+    /// however often it is asked about.
+    fn inherited_cdbase<'s>(
+        &'s self,
+        id: OmId,
+        memo: &mut HashMap<OmId, Option<&'s str>>,
+    ) -> Option<&'s str> {
+        if let Some(cdbase) = memo.get(&id) {
+            return *cdbase;
+        }
+        let mut cdbase = self
+            .attributes(id)
+            .find_map(|(_, value)| self.inherited_cdbase(value, memo));
+        if cdbase.is_none() {
+            cdbase = match self.node(id) {
+                Node::Oms {
+                    symbol,
+                    cdbase_inherited: true,
+                } => self.symbols[symbol as usize].cdbase(),
+                Node::Oma(span)
+                | Node::Ombind(span)
+                | Node::Ome {
+                    arguments: span, ..
+                } => {
+                    let children = &self.children[span.range()];
+                    // in document order, the body of an OMBIND comes last
+                    let order: Box<dyn Iterator<Item = &OmId>> =
+                        if matches!(self.node(id), Node::Ombind(_)) {
+                            Box::new(
+                                std::iter::once(&children[0])
+                                    .chain(&children[2..])
+                                    .chain(std::iter::once(&children[1])),
+                            )
+                        } else {
+                            Box::new(children.iter())
+                        };
+                    order
+                        .into_iter()
+                        .find_map(|c| self.inherited_cdbase(*c, memo))
+                }
+                _ => None,
+            };
+        }
+        memo.insert(id, cdbase);
+        cdbase
+    }
+}
+
+#[cfg(feature = "xml-read")]
+impl<'om> OmArena<'om> {
+    /** Parses <span style="font-variant:small-caps;">OpenMath</span> XML (like
+    [`OpenMath::from_openmath_xml`](crate::OMDeserializable::from_openmath_xml)) directly
+    into a new arena, without building an [`OpenMath`] first; returns the arena and the id
+    of the root. Strings borrow from the input where possible. [OMR](crate::OMKind::OMR)s are
+    unresolved references; see [`from_openmath_xml_with`](Self::from_openmath_xml_with)
+    for resolving them.
+
+    # Errors
+    if the input is invalid, or contains unresolved references
+
+    # Examples
+    ```
+    use openmath::{OMSerializable, arena::OmArena};
+
+    # #[cfg(all(feature = "xml-read", feature = "xml-write"))]
+    # {
+    let xml = r#"<OMA><OMS cd="arith1" name="plus"/><OMI>1</OMI><OMV name="x"/></OMA>"#;
+    let (arena, root) = OmArena::from_openmath_xml(xml).expect("is valid");
+    assert_eq!(arena.len(), 4);
+    assert_eq!(arena.object(root).xml(false).to_string(), xml);
+    # }
+    ```
+    */
+    #[inline]
+    pub fn from_openmath_xml(
+        input: &'om str,
+    ) -> Result<(Self, OmId), crate::de::XmlReadError<crate::de::ReferenceError>> {
+        Self::from_openmath_xml_with(input, crate::de::Options::new())
+    }
+
+    /** Like [`from_openmath_xml`](Self::from_openmath_xml), but with the given
+    [`Options`](crate::de::Options); e.g. an [`OMRPolicy`](crate::de::OMRPolicy) to resolve
+    [OMR](crate::OMKind::OMR)s with.
+
+    # Errors
+    if the input is invalid, or contains unresolved references
+    */
+    pub fn from_openmath_xml_with(
+        input: &'om str,
+        options: crate::de::Options<'_>,
+    ) -> Result<(Self, OmId), crate::de::XmlReadError<crate::de::ReferenceError>> {
+        let mut arena = Self::new();
+        let root = crate::de::from_openmath_xml_converting(input, &options, |om, cdbase| {
+            arena.build(om, cdbase)
+        })?;
+        Ok((arena, root.0))
+    }
+
+    /// Adds the node `om`, read against `cdbase`, whose children were added before
+    #[allow(clippy::too_many_lines)]
+    fn build(
+        &mut self,
+        om: crate::OM<'om, Built>,
+        cdbase: &str,
+    ) -> Result<Built, crate::de::ReferenceError> {
+        use crate::OM;
+        fn value<'om>(a: OMMaybeForeign<'om, Built>, arena: &mut OmArena<'om>) -> OmId {
+            match a {
+                OMMaybeForeign::OM(Built(id)) => id,
+                OMMaybeForeign::Foreign { encoding, value } => arena.foreign(encoding, value),
+            }
+        }
+        fn set_attributes<'om>(
+            id: OmId,
+            attrs: Vec<crate::de::OMAttr<'om, Built>>,
+            arena: &mut OmArena<'om>,
+        ) {
+            if attrs.is_empty() {
+                return;
+            }
+            let attrs = attrs
+                .into_iter()
+                .map(|a| (a.key, value(a.value, arena)))
+                .collect::<Vec<_>>();
+            arena.set_attributes(id, attrs);
+        }
+        // the default cdbase is borrowed, like in `OpenMath`
+        let in_scope = || {
+            if cdbase == crate::CD_BASE {
+                Cow::Borrowed(crate::CD_BASE)
+            } else {
+                Cow::Owned(cdbase.to_string())
+            }
+        };
+        let (id, attrs) = match om {
+            OM::OMI { int, attrs } => (self.omi(int), attrs),
+            OM::OMF { float, attrs, .. } => (self.omf(float), attrs),
+            OM::OMSTR { string, attrs } => (self.omstr(string), attrs),
+            OM::OMB { bytes, attrs } => (self.omb(bytes), attrs),
+            OM::OMV { name, attrs } => (self.omv(name), attrs),
+            OM::OMS {
+                cdbase: own_cdbase,
+                cd,
+                name,
+                attrs,
+            } => {
+                let inherited = own_cdbase.is_none();
+                let cdbase = own_cdbase.unwrap_or_else(in_scope);
+                let symbol = SymbolRef::with_cdbase(cdbase, cd, name);
+                (self.oms_inherited(symbol, inherited), attrs)
+            }
+            OM::OMA {
+                applicant,
+                arguments,
+                attrs,
+            } => {
+                let span = self.push_children(
+                    std::iter::once(applicant.0).chain(arguments.into_iter().map(|a| a.0)),
+                );
+                (self.push(Node::Oma(span)), attrs)
+            }
+            OM::OMBIND {
+                binder,
+                variables,
+                object,
+                attrs,
+            } => {
+                let variables = variables
+                    .into_iter()
+                    .map(|(name, attrs)| {
+                        let id = self.omv(name);
+                        set_attributes(id, attrs, self);
+                        id
+                    })
+                    .collect::<Vec<_>>();
+                let span = self.push_children([binder.0, object.0].into_iter().chain(variables));
+                (self.push(Node::Ombind(span)), attrs)
+            }
+            OM::OME {
+                cdbase,
+                cd,
+                name,
+                arguments,
+                attrs,
+            } => {
+                let arguments = arguments
+                    .into_iter()
+                    .map(|a| value(a, self))
+                    .collect::<Vec<_>>();
+                let cdbase = cdbase.unwrap_or_else(in_scope);
+                let symbol = SymbolRef::with_cdbase(cdbase, cd, name);
+                (self.ome(symbol, arguments), attrs)
+            }
+            OM::OMR { href, .. } => {
+                return Err(crate::de::UnresolvedReference(href.into_owned()).into());
+            }
+        };
+        set_attributes(id, attrs, self);
+        Ok(Built(id))
+    }
+}
+
+/// A node added to an arena by [`OmArena::build`]
+#[cfg(feature = "xml-read")]
+#[derive(Debug, Clone, Copy)]
+struct Built(OmId);
+
+#[cfg(feature = "xml-read")]
+impl<'de> crate::OMDeserializable<'de> for Built {
+    type Ret = Self;
+    type Err = crate::de::ReferenceError;
+    fn from_openmath(_: crate::OM<'de, Self>, _: &str) -> Result<Self, Self::Err> {
+        // there is no arena to add to here; `OmArena::from_openmath_xml_with`, the only
+        // reader of `Built`s, converts every node itself
+        unreachable!("Builts are only read by OmArena::from_openmath_xml_with")
+    }
+}
+
+impl<'om> From<&OpenMath<'om>> for OmArena<'om> {
+    /// An arena holding `om`, whose [`root`](OmArena::root) is `om`'s root
+    fn from(om: &OpenMath<'om>) -> Self {
+        let mut arena = Self::new();
+        arena.insert(om);
+        arena
+    }
+}
+
+/// A node of an [`OmArena`], for [serialization](OMSerializable); see [`OmArena::object`]
+#[derive(Debug, Clone, Copy)]
+pub struct ArenaObject<'a, 'om> {
+    arena: &'a OmArena<'om>,
+    id: OmId,
+}
+
+impl ArenaObject<'_, '_> {
+    #[inline]
+    const fn with(self, id: OmId) -> Self {
+        Self { id, ..self }
+    }
+}
+
+impl OMSerializable for ArenaObject<'_, '_> {
+    #[inline]
+    fn as_openmath<'s, S: ser::OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        // shared by all nodes, rather than recomputed at every one
+        let inherited = RefCell::default();
+        Serialized(*self, &inherited).as_openmath(serializer)
+    }
+}
+
+/// The [inherited cdbases](OmArena::inherited_cdbase) of the nodes serialized so far
+type Inherited<'a> = RefCell<HashMap<OmId, Option<&'a str>>>;
+
+/// A node of an [`ArenaObject`] being serialized
+#[derive(Clone, Copy)]
+struct Serialized<'a, 'om>(ArenaObject<'a, 'om>, &'a Inherited<'a>);
+
+impl Serialized<'_, '_> {
+    #[inline]
+    const fn with(self, id: OmId) -> Self {
+        Self(self.0.with(id), self.1)
+    }
+}
+
+impl OMSerializable for Serialized<'_, '_> {
+    fn as_openmath<'s, S: ser::OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        struct NoAttrs<'a, 'om>(Serialized<'a, 'om>);
+        impl OMSerializable for NoAttrs<'_, '_> {
+            fn as_openmath<'s, S: ser::OMSerializer<'s>>(
+                &self,
+                serializer: S,
+            ) -> Result<S::Ok, S::Err> {
+                let Self(o) = *self;
+                match o.0.arena.get(o.0.id) {
+                    OmNode::OMI(int) => int.as_openmath(serializer),
+                    OmNode::OMF(float) => float.as_openmath(serializer),
+                    OmNode::OMSTR(string) => string.as_openmath(serializer),
+                    OmNode::OMB(bytes) => bytes.as_openmath(serializer),
+                    OmNode::OMV(name) => ser::Omv(name).as_openmath(serializer),
+                    OmNode::OMS { symbol, .. } => symbol.as_uri().as_oms().as_openmath(serializer),
+                    OmNode::OMA {
+                        applicant,
+                        arguments,
+                    } => serializer.oma(o.with(applicant), arguments.iter().map(|a| o.with(*a))),
+                    OmNode::OME { symbol, arguments } => serializer.ome(
+                        symbol.as_uri(),
+                        arguments.iter().map(|a| MaybeForeign(o.with(*a))),
+                    ),
+                    OmNode::OMBIND {
+                        binder,
+                        variables,
+                        object,
+                    } => serializer.ombind(
+                        o.with(binder),
+                        variables.iter().map(|v| o.with(*v)),
+                        o.with(object),
+                    ),
+                    OmNode::Foreign { .. } => Err(S::Err::invalid_value(
+                        "OMFOREIGN only as an argument of an OME or an attribute value",
+                    )),
+                }
+            }
+        }
+        fn with_attrs<'s, S: ser::OMSerializer<'s>>(
+            o: Serialized<'_, '_>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Err> {
+            let attributes = o.0.arena.attributes(o.0.id);
+            if attributes.len() == 0 {
+                return NoAttrs(o).as_openmath(serializer);
+            }
+            serializer.omattr(
+                attributes.map(|(key, value)| ArenaAttr(key, o.with(value))),
+                NoAttrs(o),
+            )
+        }
+        let Self(ArenaObject { arena, id }, inherited) = *self;
+        // reinstate inherited cdbases on the outermost object they apply to
+        let cdbase =
+            if matches!(arena.node(id), Node::Oms { .. }) && arena.attributes(id).len() == 0 {
+                None
+            } else {
+                arena.inherited_cdbase(id, &mut inherited.borrow_mut())
+            };
+        if let Some(cdbase) = cdbase
+            && !crate::cdbase::eq_normalized(cdbase, serializer.current_cdbase())
+        {
+            return with_attrs(*self, serializer.with_cdbase(cdbase)?);
+        }
+        with_attrs(*self, serializer)
+    }
+}
+
+/// An argument of an [OME](crate::OMKind::OME) or an attribute value
+struct MaybeForeign<'a, 'om>(Serialized<'a, 'om>);
+impl ser::OMOrForeign for MaybeForeign<'_, '_> {
+    fn om_or_foreign(
+        self,
+    ) -> either::Either<impl OMSerializable, (Option<impl std::fmt::Display>, impl std::fmt::Display)>
+    {
+        let Self(o) = self;
+        match o.0.arena.get(o.0.id) {
+            OmNode::Foreign { encoding, value } => either::Either::Right((encoding, value)),
+            _ => either::Either::Left(o),
+        }
+    }
+}
+
+struct ArenaAttr<'a, 'om>(&'a SymbolRef<'om>, Serialized<'a, 'om>);
+impl ser::OMAttr for ArenaAttr<'_, '_> {
+    #[inline]
+    fn symbol(&self) -> impl AsOMS {
+        self.0.as_uri()
+    }
+    #[inline]
+    fn value(self) -> impl ser::OMOrForeign {
+        MaybeForeign(self.1)
+    }
+}
+
+impl ser::BindVar for Serialized<'_, '_> {
+    #[inline]
+    fn name(&self) -> impl std::fmt::Display {
+        match self.0.arena.get(self.0.id) {
+            OmNode::OMV(name) => name,
+            _ => unreachable!("bound variables are OMVs"),
+        }
+    }
+    #[inline]
+    fn attrs(&self) -> impl ExactSizeIterator<Item: ser::OMAttr> {
+        self.0
+            .arena
+            .attributes(self.0.id)
+            .map(|(key, value)| ArenaAttr(key, self.with(value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OmArena, OmNode};
+    #[cfg(feature = "xml-write")]
+    use crate::OMSerializable;
+    use crate::{OpenMath, SymbolRef};
+
+    const SEXPR: &str = r#"(ombind (oms fns1 lambda) (bvar x (omattr (((oms sts type) (oms setname1 Z))) y))
+         (omattr (((oms meta note) (oma (oms list1 list) (omstr "a") (omb "AAEC"))))
+           (oma (oms arith1 plus)
+             (oma (oms arith1 times) (omv x) (omi 2) (omf 3.5))
+             (ome (oms moreerrors unexpected)
+               (oma (oms arith1 minus) (omi 123456789012345678901234567890)) (omforeign "…"))
+             (cdbase "http://example.org" (oma (oms my f) (omi 1) (omv y))))))"#;
+
+    #[test]
+    fn arena_roundtrip() {
+        let om = OpenMath::from_sexpr(SEXPR).expect("is valid");
+        let arena = OmArena::from(&om);
+        let root = arena.root().expect("is not empty");
+        assert_eq!(arena.to_openmath(root), om);
+        #[cfg(feature = "xml-write")]
+        assert_eq!(
+            arena.object(root).xml(false).to_string(),
+            om.xml(false).to_string()
+        );
+        let OmNode::OMBIND {
+            variables, object, ..
+        } = arena.get(root)
+        else {
+            panic!("is an OMBIND")
+        };
+        assert!(matches!(arena.get(variables[1]), OmNode::OMV("y")));
+        assert_eq!(arena.attributes(variables[1]).len(), 1);
+        assert_eq!(arena.attributes(object).len(), 1);
+        assert_eq!(arena.children(root).len(), 4);
+
+        #[cfg(all(feature = "xml-read", feature = "xml-write"))]
+        {
+            use crate::OMDeserializable;
+            let xml = om.xml(false).to_string();
+            let (parsed, root) = OmArena::from_openmath_xml(&xml).expect("is valid");
+            assert_eq!(parsed.len(), arena.len());
+            assert_eq!(
+                parsed.to_openmath(root),
+                OpenMath::from_openmath_xml(&xml).expect("is valid")
+            );
+            assert!(OmArena::from_openmath_xml(r##"<OMR href="#x"/>"##).is_err());
+        }
+    }
+
+    #[test]
+    fn arena_builders() {
+        let mut arena = OmArena::new();
+        let error = SymbolRef::new("moreerrors", "unexpected");
+        let foreign = arena.foreign(Some("text/plain".into()), "<b>bold</b>");
+        let one = arena.omi(1);
+        let e = arena.ome(error.clone(), [one, foreign]);
+        let (lambda, x) = (arena.oms(SymbolRef::new("fns1", "lambda")), arena.omv("x"));
+        let note = arena.foreign(None, "a note");
+        arena.set_attributes(x, [(SymbolRef::new("meta", "note"), note)]);
+        let f = arena.ombind(lambda, [x], e);
+        assert_eq!(arena.children(f).len(), 3);
+        #[cfg(feature = "xml-write")]
+        assert_eq!(
+            arena.object(f).xml(false).to_string(),
+            concat!(
+                r#"<OMBIND><OMS cd="fns1" name="lambda"/><OMBVAR><OMATTR><OMATP><OMS cd="meta" name="note"/>"#,
+                r#"<OMFOREIGN>a note</OMFOREIGN></OMATP><OMV name="x"/></OMATTR></OMBVAR>"#,
+                r#"<OME><OMS cd="moreerrors" name="unexpected"/><OMI>1</OMI>"#,
+                r#"<OMFOREIGN encoding="text/plain"><b>bold</b></OMFOREIGN></OME></OMBIND>"#
+            )
+        );
+        assert!(matches!(arena.get(e), OmNode::OME { symbol, .. } if *symbol == error));
+        arena.set_attributes(x, []);
+        assert_eq!(arena.attributes(x).len(), 0);
+        assert!(std::panic::catch_unwind(move || arena.oma(one, [foreign])).is_err());
+    }
+}
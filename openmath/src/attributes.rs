@@ -0,0 +1,340 @@
+//! Normalizing and comparing attribution pairs; see [`OpenMath::dedup_attributes`]
+
+use std::{borrow::Cow, collections::BTreeMap};
+
+use crate::{
+    Attr, BoundVariable, CD_BASE, OMMaybeForeign, OMSerializable, OpenMath,
+    ser::{AsOMS, BuildError, ErasedOMSerializable, attr_key},
+};
+
+type Attrs<'om> = Vec<Attr<'om, OMMaybeForeign<'om, OpenMath<'om>>>>;
+
+/** Which of several attribution pairs with the same key to keep; see
+[`OpenMath::dedup_attributes`].
+
+The standard leaves the meaning of repeated keys to the content dictionaries, but a later
+pair usually overrides an earlier one; hence the default is [`Last`](Self::Last).
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum KeepPolicy {
+    /// Keep the first pair with a given key
+    First,
+    /// Keep the last pair with a given key
+    #[default]
+    Last,
+}
+
+/// The key of an attribution pair by which duplicates are detected; keys without an explicit
+/// cdbase are resolved against [`CD_BASE`].
+fn key(a: &Attr<'_, impl Sized>) -> String {
+    a.key.resolved_uri(CD_BASE)
+}
+
+fn dedup(attributes: &mut Attrs<'_>, keep: KeepPolicy) {
+    if attributes.len() < 2 {
+        return;
+    }
+    let mut seen = std::collections::HashSet::new();
+    match keep {
+        KeepPolicy::First => attributes.retain(|a| seen.insert(key(a))),
+        KeepPolicy::Last => {
+            attributes.reverse();
+            attributes.retain(|a| seen.insert(key(a)));
+            attributes.reverse();
+        }
+    }
+}
+
+fn attr_map<'a, 'om>(
+    attributes: &'a [Attr<'om, OMMaybeForeign<'om, OpenMath<'om>>>],
+) -> BTreeMap<String, &'a OMMaybeForeign<'om, OpenMath<'om>>> {
+    attributes.iter().map(|a| (key(a), &a.value)).collect()
+}
+
+impl<'om> OpenMath<'om> {
+    /** Removes all but one of the attribution pairs with the same key from every attribute
+    list in this object (including those of bound variables and of attribute values),
+    according to `keep`; otherwise, the order of the pairs is preserved.
+
+    Keys are compared by their [resolved URI](crate::SymbolRef::resolved_uri); keys
+    without an explicit cdbase are resolved against [`CD_BASE`].
+
+    # Examples
+    ```rust
+    use openmath::{KeepPolicy, OpenMath};
+
+    let mut om = OpenMath::from_sexpr(
+        "(omattr (((oms meta note) (omi 1)) ((oms meta id) (omi 2)) ((oms meta note) (omi 3))) (omv x))",
+    )
+    .expect("is valid");
+    om.dedup_attributes(KeepPolicy::Last);
+    let expected = OpenMath::from_sexpr(
+        "(omattr (((oms meta id) (omi 2)) ((oms meta note) (omi 3))) (omv x))",
+    )
+    .expect("is valid");
+    assert_eq!(om, expected);
+    ```
+    */
+    pub fn dedup_attributes(&mut self, keep: KeepPolicy) {
+        self.for_each_attributes(&mut |attributes| dedup(attributes, keep));
+    }
+
+    /// Like [`dedup_attributes`](Self::dedup_attributes), but additionally sorts every
+    /// attribute list by key, so that objects whose attributes only differ in order or
+    /// duplicates become equal.
+    pub fn canonicalize_attributes(&mut self, keep: KeepPolicy) {
+        self.for_each_attributes(&mut |attributes| {
+            dedup(attributes, keep);
+            attributes.sort_by_cached_key(|a| key(a));
+        });
+    }
+
+    /// Compares `self` and `other`, treating every attribute list as a map that keeps one
+    /// pair per key according to `keep`; see
+    /// [`canonicalize_attributes`](Self::canonicalize_attributes).
+    #[must_use]
+    pub fn eq_modulo_attributes(&self, other: &Self, keep: KeepPolicy) -> bool {
+        let (mut a, mut b) = (self.clone(), other.clone());
+        a.canonicalize_attributes(keep);
+        b.canonicalize_attributes(keep);
+        a == b
+    }
+
+    /** Attributes this object with `key` and the object `value` is
+    [built](crate::ser::ErasedOMSerializable::erased_build) into; e.g. to annotate it with
+    a value of a user type.
+
+    Like the keys of built objects, `key` keeps its cdbase only if it differs from
+    [`CD_BASE`].
+
+    # Errors
+    If building `value` fails; then this object is left unchanged.
+
+    # Examples
+    ```rust
+    use openmath::{OpenMath, ser::Uri};
+
+    let mut om = OpenMath::from_sexpr("(omv x)").expect("is valid");
+    let key = Uri { cdbase: None, cd: "meta", name: "note" };
+    om.push_attribute(&key, &42).expect("works");
+    om.push_foreign_attribute(&key, Some("text/plain".into()), "the answer");
+    assert_eq!(om.attributes().len(), 2);
+    assert_eq!(om.attributes()[0].value.as_om().and_then(OpenMath::as_i64), Some(42));
+    ```
+    */
+    pub fn push_attribute<T: OMSerializable + ?Sized>(
+        &mut self,
+        key: impl AsOMS,
+        value: &T,
+    ) -> Result<(), BuildError> {
+        let value = value.erased_build()?;
+        self.attributes_mut().push(Attr::new(
+            attr_key(&key, CD_BASE),
+            OMMaybeForeign::OM(value),
+        ));
+        Ok(())
+    }
+
+    /// Attributes this object with `key` and an [OMFOREIGN](crate::OMKind::OMFOREIGN) with
+    /// the given `encoding` and `value` (which is written verbatim, so it has to be
+    /// well-formed XML content); `key` is treated like in
+    /// [`push_attribute`](Self::push_attribute).
+    pub fn push_foreign_attribute(
+        &mut self,
+        key: impl AsOMS,
+        encoding: Option<Cow<'om, str>>,
+        value: impl Into<Cow<'om, str>>,
+    ) {
+        self.attributes_mut().push(Attr::new(
+            attr_key(&key, CD_BASE),
+            OMMaybeForeign::Foreign {
+                encoding,
+                value: value.into(),
+            },
+        ));
+    }
+
+    /// The attribution pairs of this object, keyed by the
+    /// [resolved URI](crate::SymbolRef::resolved_uri) of their keys; a later pair overrides
+    /// an earlier one with the same key (i.e. [`KeepPolicy::Last`]).
+    #[must_use]
+    pub fn attr_map(&self) -> BTreeMap<String, &OMMaybeForeign<'om, Self>> {
+        attr_map(self.attributes())
+    }
+
+    /// Calls `f` on every attribute list in this object, innermost first
+    pub(crate) fn for_each_attributes(&mut self, f: &mut impl FnMut(&mut Attrs<'om>)) {
+        fn attrs<'om>(attributes: &mut Attrs<'om>, f: &mut impl FnMut(&mut Attrs<'om>)) {
+            for a in attributes.iter_mut() {
+                if let OMMaybeForeign::OM(o) = &mut a.value {
+                    o.for_each_attributes(f);
+                }
+            }
+            f(attributes);
+        }
+        match self {
+            Self::OMI { attributes, .. }
+            | Self::OMF { attributes, .. }
+            | Self::OMSTR { attributes, .. }
+            | Self::OMB { attributes, .. }
+            | Self::OMV { attributes, .. }
+            | Self::OMS { attributes, .. } => attrs(attributes, f),
+            Self::OMA {
+                applicant,
+                arguments,
+                attributes,
+            } => {
+                applicant.for_each_attributes(f);
+                for a in arguments {
+                    a.for_each_attributes(f);
+                }
+                attrs(attributes, f);
+            }
+            Self::OME {
+                arguments,
+                attributes,
+                ..
+            } => {
+                for a in arguments {
+                    if let OMMaybeForeign::OM(o) = a {
+                        o.for_each_attributes(f);
+                    }
+                }
+                attrs(attributes, f);
+            }
+            Self::OMBIND {
+                binder,
+                variables,
+                object,
+                attributes,
+            } => {
+                binder.for_each_attributes(f);
+                for v in variables {
+                    attrs(&mut v.attributes, f);
+                }
+                object.for_each_attributes(f);
+                attrs(attributes, f);
+            }
+        }
+    }
+}
+
+impl<'om> BoundVariable<'om> {
+    /// The attribution pairs of this variable, keyed like [`OpenMath::attr_map`]
+    #[must_use]
+    pub fn attr_map(&self) -> BTreeMap<String, &OMMaybeForeign<'om, OpenMath<'om>>> {
+        attr_map(&self.attributes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KeepPolicy;
+    use crate::OpenMath;
+    use crate::test_util::om as parse;
+
+    #[test]
+    fn duplicate_keys() {
+        let a = parse(
+            r#"(omattr (((oms meta note) (omi 1)) ((oms meta id) (omstr "a")) ((oms meta note) (omi 2)))
+                 (ombind (oms fns1 lambda)
+                   (bvar (omattr (((oms sts type) (oms setname1 N)) ((oms sts type) (oms setname1 Z))) x))
+                   (oma (oms arith1 plus)
+                     (omattr (((oms meta id) (omi 1)) ((oms meta id) (omi 1))) (omv x)))))"#,
+        );
+        let b = parse(
+            r#"(omattr (((oms meta id) (omstr "a")) ((oms meta note) (omi 0)) ((oms meta note) (omi 2)))
+                 (ombind (oms fns1 lambda)
+                   (bvar (omattr (((oms sts type) (oms setname1 Z))) x))
+                   (oma (oms arith1 plus)
+                     (omattr (((oms meta id) (omi 1))) (omv x)))))"#,
+        );
+        assert_ne!(a, b);
+        assert!(a.eq_modulo_attributes(&b, KeepPolicy::Last));
+        assert!(!a.eq_modulo_attributes(&b, KeepPolicy::First));
+
+        let (mut ca, mut cb) = (a.clone(), b);
+        ca.canonicalize_attributes(KeepPolicy::Last);
+        cb.canonicalize_attributes(KeepPolicy::Last);
+        assert_eq!(ca, cb);
+
+        let map = a.attr_map();
+        assert_eq!(
+            map.keys().collect::<Vec<_>>(),
+            [
+                "http://www.openmath.org/cd?meta#id",
+                "http://www.openmath.org/cd?meta#note"
+            ]
+        );
+        assert_eq!(
+            map["http://www.openmath.org/cd?meta#note"].as_om(),
+            Some(&parse("(omi 2)"))
+        );
+
+        let mut first = a;
+        first.dedup_attributes(KeepPolicy::First);
+        let OpenMath::OMBIND { variables, .. } = &first else {
+            panic!("is an OMBIND")
+        };
+        assert_eq!(variables[0].attributes.len(), 1);
+        assert_eq!(
+            variables[0].attr_map()["http://www.openmath.org/cd?sts#type"].as_om(),
+            Some(&parse("(oms setname1 N)"))
+        );
+    }
+
+    #[cfg(feature = "xml")]
+    #[test]
+    fn push_attributes_roundtrip() {
+        use crate::{
+            OMDeserializable, OMSerializable,
+            ser::{AsOMS, OMSerializer, Uri},
+        };
+        /// A closed interval, serialized as `interval1#interval_cc`
+        struct Interval(i64, i64);
+        impl OMSerializable for Interval {
+            fn as_openmath<'s, S: OMSerializer<'s>>(&self, s: S) -> Result<S::Ok, S::Err> {
+                let head = Uri {
+                    cdbase: None,
+                    cd: "interval1",
+                    name: "interval_cc",
+                };
+                s.oma(head.as_oms(), [self.0, self.1].into_iter())
+            }
+        }
+        let mut om = parse("(oma (oms arith1 plus) (omv x) (omi 1))");
+        let range = Uri {
+            cdbase: Some(crate::CdBase::new_unchecked("http://example.org/cd")),
+            cd: "meta",
+            name: "range",
+        };
+        om.push_attribute(range, &Interval(0, 10)).expect("works");
+        om.push_foreign_attribute(
+            Uri {
+                cdbase: None,
+                cd: "meta",
+                name: "note",
+            },
+            Some("text/plain".into()),
+            "a note",
+        );
+        let expected = parse(
+            r#"(omattr (((cdbase "http://example.org/cd" (oms meta range))
+                          (oma (oms interval1 interval_cc) (omi 0) (omi 10))))
+                 (oma (oms arith1 plus) (omv x) (omi 1)))"#,
+        );
+        assert_eq!(om.attributes()[0], expected.attributes()[0]);
+        assert_eq!(om.attributes()[1].value.as_om(), None);
+
+        let xml = om.xml(false).to_string();
+        assert!(
+            xml.contains(r#"<OMS cdbase="http://example.org/cd" cd="meta" name="range"/>"#),
+            "{xml}"
+        );
+        let back = OpenMath::from_openmath_xml(&xml).expect("is valid");
+        assert_eq!(back.xml(false).to_string(), xml);
+        assert_eq!(back.attributes().len(), 2);
+        assert_eq!(back.attributes()[1].value, om.attributes()[1].value);
+        assert!(back.eq_modulo_attributes(&om, KeepPolicy::Last));
+    }
+}
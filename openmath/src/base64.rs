@@ -1,6 +1,10 @@
 /*! Opinionated implementation of Base64 encoding/decoding
 as adapters over [`Iterator`]s, rather than on pre-allocated
-byte slices/[`Vec`]s/string types
+byte slices/[`Vec`]s/string types.
+
+For large payloads that are available in full, [`decode_slice`] and [`encode_into`] process
+whole blocks of 4 (resp. 3) bytes at a time, and are considerably faster than the
+adapters; the adapters remain useful for streaming.
  */
 
 use std::num::NonZeroU8;
@@ -81,24 +85,54 @@ let out = decoded.flat().map(|u| u.unwrap() as char).collect::<String>();
 assert_eq!(out,"DiEs IsT eIn TeSt!!");
 ````
  */
-pub struct Base64Decoder<I: Iterator<Item = u8>>(I);
+pub struct Base64Decoder<I: Iterator<Item = u8>>(I, usize);
 impl<I: Iterator<Item = u8>> Base64Decoder<I> {
-    /// Turns this into a
-    pub fn flat(self) -> Flat<I> {
-        self.flat_map(fltn as _).filter(flter as _)
+    /// Turns this into a <code>[Result]<u8, [Error]></code>-[`Iterator`], without the
+    /// zero bytes that padding stands for
+    pub const fn flat(self) -> Flat<I> {
+        Flat {
+            decoder: self,
+            block: [0; 3],
+            start: 0,
+            end: 0,
+        }
     }
 }
 impl<I: ExactSizeIterator<Item = u8>> ExactSizeIterator for Base64Decoder<I> {}
 
-/// Used in [`Base64Decoder::flat`].
-pub type Flat<I> = std::iter::Filter<
-    std::iter::FlatMap<
-        Base64Decoder<I>,
-        [Result<u8, Error>; 3],
-        fn(Result<[u8; 3], Error>) -> [Result<u8, Error>; 3],
-    >,
-    fn(&Result<u8, Error>) -> bool,
->;
+/// Returned by [`Base64Decoder::flat`].
+pub struct Flat<I: Iterator<Item = u8>> {
+    decoder: Base64Decoder<I>,
+    block: [u8; 3],
+    start: usize,
+    end: usize,
+}
+impl<I: Iterator<Item = u8>> Iterator for Flat<I> {
+    type Item = Result<u8, Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.start == self.end {
+            match self.decoder.next_block()? {
+                Ok((block, len)) => {
+                    self.block = block;
+                    self.start = 0;
+                    self.end = len;
+                }
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        let b = self.block[self.start];
+        self.start += 1;
+        Some(Ok(b))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let (lower, upper) = self.decoder.0.size_hint();
+        let buffered = self.end - self.start;
+        (
+            buffered + lower / 4 * 3,
+            upper.map(|u| buffered + u.div_ceil(4) * 3),
+        )
+    }
+}
 
 /// Trait for [`Iterator`]s that can be base64-decoded.
 /// Blanket implemented for all <code>I: [Iterator]<Item = u8></code>.
@@ -109,7 +143,72 @@ pub trait Base64Decodable: Iterator {
 impl<I: Iterator<Item = u8>> Base64Decodable for I {
     type Inner = Self;
     fn decode_base64(self) -> Base64Decoder<Self::Inner> {
-        Base64Decoder(self)
+        Base64Decoder(self, 0)
+    }
+}
+
+/** Decodes the base64 in `input`, skipping ASCII whitespace (which e.g. the XML encoding
+permits inside [OMB](crate::OMKind::OMB)s, since encoders wrap lines).
+
+## Errors
+If `input` is not valid base64.
+
+## Example
+```
+let out = openmath::base64::decode_slice(b"RGlFcyBJc1Qg\n  ZUluIFRlU3QhIQ==").unwrap();
+assert_eq!(out, b"DiEs IsT eIn TeSt!!");
+```
+ */
+pub fn decode_slice(input: &[u8]) -> Result<Vec<u8>, Error> {
+    if input.iter().any(u8::is_ascii_whitespace) {
+        let compact: Vec<u8> = input
+            .iter()
+            .copied()
+            .filter(|b| !b.is_ascii_whitespace())
+            .collect();
+        return decode_compact(&compact).map_err(|e| match e {
+            // the offset in `input` of the first illegal character, which is the one found
+            Error::IllegalChar { char, .. } => Error::IllegalChar {
+                char,
+                offset: input.iter().position(|&x| x == char).unwrap_or_default(),
+            },
+            e => e,
+        });
+    }
+    decode_compact(input)
+}
+
+/** Appends the base64 encoding of `bytes` to `out`.
+
+## Example
+```
+let mut out = String::from("base64: ");
+openmath::base64::encode_into(b"ThIs Is A tEsT!!", &mut out);
+assert_eq!(out, "base64: VGhJcyBJcyBBIHRFc1QhIQ==");
+```
+ */
+pub fn encode_into(bytes: &[u8], out: &mut String) {
+    out.reserve(bytes.len().div_ceil(3) * 4);
+    let mut blocks = bytes.chunks_exact(3);
+    for block in &mut blocks {
+        let n = u32::from_be_bytes([0, block[0], block[1], block[2]]);
+        for shift in [18, 12, 6, 0] {
+            out.push(TABLE[(n >> shift & 0x3F) as usize].get() as char);
+        }
+    }
+    match *blocks.remainder() {
+        [a] => {
+            out.push(TABLE[(a >> 2) as usize].get() as char);
+            out.push(TABLE[((a << 4) & 0x3F) as usize].get() as char);
+            out.push_str("==");
+        }
+        [a, b] => {
+            out.push(TABLE[(a >> 2) as usize].get() as char);
+            out.push(TABLE[((a << 4 | b >> 4) & 0x3F) as usize].get() as char);
+            out.push(TABLE[((b << 2) & 0x3F) as usize].get() as char);
+            out.push('=');
+        }
+        _ => (),
     }
 }
 
@@ -122,9 +221,10 @@ pub enum Error {
     /// padding character (`=`) may only occur at the end of the string
     #[error("base64 string has characters after padding")]
     NonsensicalPadding,
-    /// Only alpha-numeric ASCII characters, `+`, and `/` are allowed (and `=` for padding)
-    #[error("base64 string contains illegal characters")]
-    IllegalChar(u8),
+    /// Only alpha-numeric ASCII characters, `+`, and `/` are allowed (and `=` for padding);
+    /// `offset` is the byte offset of `char` in the input (including whitespace)
+    #[error("base64 string contains illegal character {:?} at offset {offset}", char::from(*.char))]
+    IllegalChar { char: u8, offset: usize },
 }
 
 // -------------------------------------------------------------------------------------
@@ -132,10 +232,9 @@ pub enum Error {
 const PAD: NonZeroU8 = NonZeroU8::new(b'=').unwrap();
 macro_rules! table{
     ($($c:literal),*) => {
-        // SAFETY: all values are != 0
-        const TABLE: [NonZeroU8; 64] = unsafe{[
-            $(NonZeroU8::new_unchecked($c)),*
-        ]};
+        const TABLE: [NonZeroU8; 64] = [
+            $(NonZeroU8::new($c).unwrap()),*
+        ];
     }
 }
 table![
@@ -212,16 +311,6 @@ impl<I: Iterator<Item = u8>> Iterator for Base64Encoder<I> {
     }
 }
 
-const fn fltn(r: Result<[u8; 3], Error>) -> [Result<u8, Error>; 3] {
-    match r {
-        Ok([a, b, c]) => [Ok(a), Ok(b), Ok(c)],
-        Err(e) => [Err(e), Ok(0), Ok(0)],
-    }
-}
-const fn flter(r: &Result<u8, Error>) -> bool {
-    !matches!(r, Ok(0))
-}
-
 impl<I: Iterator<Item = u8>> Iterator for Base64Decoder<I> {
     type Item = Result<[u8; 3], Error>;
     fn size_hint(&self) -> (usize, Option<usize>) {
@@ -229,14 +318,24 @@ impl<I: Iterator<Item = u8>> Iterator for Base64Decoder<I> {
         (lower / 4, upper.map(|u| u / 4))
     }
 
-    #[allow(unused_assignments)]
+    #[inline]
     fn next(&mut self) -> Option<Self::Item> {
+        self.next_block().map(|r| r.map(|(block, _)| block))
+    }
+}
+
+impl<I: Iterator<Item = u8>> Base64Decoder<I> {
+    /// The next block, along with the number of its bytes that are not padding
+    #[allow(unused_assignments)]
+    fn next_block(&mut self) -> Option<Result<([u8; 3], usize), Error>> {
         let mut in_pad = false;
+        let mut padding = 0;
         macro_rules! get {
             () => {{
                 let Some(n) = self.0.next() else {
                     return Some(Err(Error::IllegalLength));
                 };
+                self.1 += 1;
                 get!(n)
             }};
             ($e:ident) => {{
@@ -245,22 +344,190 @@ impl<I: Iterator<Item = u8>> Iterator for Base64Decoder<I> {
                 }
                 if $e == b'=' {
                     in_pad = true;
+                    padding += 1;
                     0u32
                 } else {
                     let n = INVERSE_TABLE[$e as usize];
                     if n == 255 {
-                        return Some(Err(Error::IllegalChar($e)));
+                        return Some(Err(Error::IllegalChar {
+                            char: $e,
+                            offset: self.1 - 1,
+                        }));
                     }
                     n.into()
                 }
             }}
         }
         let a = self.0.next()?;
+        self.1 += 1;
         let mut r = get!(a) << 26;
         r |= get!() << 20;
         r |= get!() << 14;
         r |= get!() << 8;
+        if padding > 2 {
+            return Some(Err(Error::NonsensicalPadding));
+        }
         let [a, b, c, _] = r.to_be_bytes();
-        Some(Ok([a, b, c]))
+        Some(Ok(([a, b, c], 3 - padding)))
+    }
+}
+
+/// [`decode_slice`] for input without whitespace
+fn decode_compact(input: &[u8]) -> Result<Vec<u8>, Error> {
+    if !input.len().is_multiple_of(4) {
+        // an illegal character is the more helpful error, e.g. for text
+        return Err(match invalid(input, input) {
+            e @ Error::IllegalChar { .. } => e,
+            _ => Error::IllegalLength,
+        });
+    }
+    let (body, last) = input.split_at(input.len().saturating_sub(4));
+    let mut out = vec![0; body.len() / 4 * 3];
+    for (block, target) in body.chunks_exact(4).zip(out.chunks_exact_mut(3)) {
+        let block = [block[0], block[1], block[2], block[3]];
+        let bits = decode_block(block).ok_or_else(|| invalid(input, &block))?;
+        target.copy_from_slice(&bits.to_be_bytes()[1..]);
+    }
+    let &[first, second, third, fourth] = last else {
+        return Ok(out);
+    };
+    if first == b'=' || second == b'=' {
+        return Err(Error::NonsensicalPadding);
+    }
+    let padding = match (third, fourth) {
+        (b'=', b'=') => 2,
+        (_, b'=') => 1,
+        (b'=', _) => return Err(Error::NonsensicalPadding),
+        _ => 0,
+    };
+    let block = [first, second, third, fourth].map(|x| if x == b'=' { b'A' } else { x });
+    let bits = decode_block(block).ok_or_else(|| invalid(input, &block))?;
+    out.extend_from_slice(&bits.to_be_bytes()[1..4 - padding]);
+    Ok(out)
+}
+
+/// The 24 bits encoded by `block`, if it consists of valid characters only
+#[inline]
+fn decode_block(block: [u8; 4]) -> Option<u32> {
+    let [a, b, c, d] = block.map(|x| u32::from(INVERSE_TABLE[x as usize]));
+    // invalid characters map to 255, so any of them sets bits above the lowest 6
+    ((a | b | c | d) <= 0x3F).then_some(a << 18 | b << 12 | c << 6 | d)
+}
+
+/// The error for a `block` of `input` containing an invalid character
+fn invalid(input: &[u8], block: &[u8]) -> Error {
+    block
+        .iter()
+        .find(|&&x| INVERSE_TABLE[x as usize] == 255)
+        .map_or(Error::IllegalLength, |&x| {
+            if x == b'=' {
+                Error::NonsensicalPadding
+            } else {
+                Error::IllegalChar {
+                    char: x,
+                    // blocks before this one are valid
+                    offset: input.iter().position(|&y| y == x).unwrap_or_default(),
+                }
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Base64Decodable, Base64Encodable, Error, decode_slice, encode_into};
+    use base64::Engine;
+
+    #[test]
+    fn slices_agree_with_base64_crate() {
+        let engine = base64::engine::general_purpose::STANDARD;
+        let mut seed = 0x2545_f491_u64;
+        let mut next = || {
+            seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+            seed.to_be_bytes()[3]
+        };
+        // every padding length, and some larger blocks
+        for len in (0..64).chain([1000, 1001, 1002, 65_536]) {
+            let bytes: Vec<u8> = (0..len).map(|_| next()).collect();
+            let expected = engine.encode(&bytes);
+            let mut encoded = String::new();
+            encode_into(&bytes, &mut encoded);
+            assert_eq!(encoded, expected);
+            assert_eq!(bytes.iter().copied().base64().into_string(), expected);
+            assert_eq!(decode_slice(expected.as_bytes()).expect("is valid"), bytes);
+            let flat: Result<Vec<u8>, _> = expected.bytes().decode_base64().flat().collect();
+            assert_eq!(flat.expect("is valid"), bytes);
+
+            // line breaks and indentation, as in wrapped XML
+            let wrapped = expected
+                .as_bytes()
+                .chunks(next() as usize % 76 + 1)
+                .map(|line| std::str::from_utf8(line).expect("is ASCII"))
+                .collect::<Vec<_>>()
+                .join("\n\t  ");
+            assert_eq!(decode_slice(wrapped.as_bytes()).expect("is valid"), bytes);
+        }
+    }
+
+    #[test]
+    fn invalid_slices() {
+        assert!(matches!(decode_slice(b"QUJD"), Ok(v) if v == b"ABC"));
+        assert!(matches!(decode_slice(b"QUJ"), Err(Error::IllegalLength)));
+        assert!(matches!(
+            decode_slice(b"QU=D"),
+            Err(Error::NonsensicalPadding)
+        ));
+        assert!(matches!(
+            decode_slice(b"Q===QUJD"),
+            Err(Error::NonsensicalPadding)
+        ));
+        assert!(matches!(
+            decode_slice(b"Q==="),
+            Err(Error::NonsensicalPadding)
+        ));
+        assert!(matches!(
+            decode_slice(b"QU*D"),
+            Err(Error::IllegalChar {
+                char: b'*',
+                offset: 2
+            })
+        ));
+        assert!(matches!(
+            decode_slice(b"QUJDQU-="),
+            Err(Error::IllegalChar {
+                char: b'-',
+                offset: 6
+            })
+        ));
+        let e = decode_slice(b"QUJD\n  QU-=").expect_err("is invalid");
+        assert!(matches!(
+            e,
+            Error::IllegalChar {
+                char: b'-',
+                offset: 9
+            }
+        ));
+        assert_eq!(
+            e.to_string(),
+            "base64 string contains illegal character '-' at offset 9"
+        );
+        // zero bytes are kept, but not the ones padding stands for
+        assert_eq!(decode_slice(b"AAEC/w==").expect("is valid"), [0, 1, 2, 255]);
+        let flat = |s: &[u8]| {
+            s.iter()
+                .copied()
+                .decode_base64()
+                .flat()
+                .collect::<Result<Vec<_>, _>>()
+        };
+        assert_eq!(flat(b"AAEC/w==").expect("is valid"), [0, 1, 2, 255]);
+        assert_eq!(flat(b"AAAA").expect("is valid"), [0, 0, 0]);
+        assert!(matches!(flat(b"Q==="), Err(Error::NonsensicalPadding)));
+        assert!(matches!(
+            flat(b"AAAAQU-="),
+            Err(Error::IllegalChar {
+                char: b'-',
+                offset: 6
+            })
+        ));
     }
 }
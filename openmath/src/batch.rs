@@ -0,0 +1,674 @@
+/*! Checking whole directories of <span style="font-variant:small-caps;">OpenMath</span> files;
+see [`check_dir`].
+
+Every `*.om` and `*.xml` file (and, if [`BatchOptions::json`] is set, every `*.json` file)
+below a directory is parsed and checked, and the [`Problem`]s found are collected in a
+[`BatchReport`]. With the `rayon` feature, files are checked in parallel; the report is
+ordered by path either way, so reports of different runs can be diffed.
+
+[`check_corpus`] checks a conformance corpus instead: every file roundtrips, or fails to
+parse as annotated by an [`Expectation`].
+
+# Examples
+```no_run
+use openmath::batch::{BatchOptions, check_dir};
+
+let report = check_dir("tests/data", &BatchOptions::default());
+print!("{report}");
+assert!(report.is_ok());
+```
+*/
+
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    OpenMath,
+    de::{OMDeserializable, OMObject, XmlReadError},
+};
+
+/// The encoding of a file, determined by its extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Format {
+    /// `*.om` and `*.xml`: the XML encoding; the object needs to be wrapped in an `OMOBJ`
+    Xml,
+    /// `*.json`: the JSON encoding; reading it requires the `serde` and `serde_json` features
+    Json,
+}
+
+impl Format {
+    /// The format of the file at `path`, if it is to be checked with the given `options`
+    #[must_use]
+    pub fn of(path: &Path, options: &BatchOptions) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "om" | "xml" => Some(Self::Xml),
+            "json" if options.json => Some(Self::Json),
+            _ => None,
+        }
+    }
+}
+
+/// What [`check_dir`] checks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchOptions {
+    /// Whether to check `*.json` files, too (default: `false`)
+    pub json: bool,
+    /// Whether to check [`OpenMath::check_invariants`] (default: `true`)
+    pub invariants: bool,
+    /// Whether to report [`OpenMath::lints`] (default: `false`)
+    pub lints: bool,
+}
+
+impl Default for BatchOptions {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            json: false,
+            invariants: true,
+            lints: false,
+        }
+    }
+}
+
+/// A problem with a file; e.g. a parse error, an [`InvariantError`](crate::InvariantError)
+/// or a [`Lint`](crate::Lint).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Problem {
+    /// A short identifier of the kind of problem, by which [`BatchReport::counts`] are
+    /// aggregated; e.g. `"xml"` or [`InvariantError::code`](crate::InvariantError::code)
+    pub code: Cow<'static, str>,
+    /// A human-readable description
+    pub message: String,
+}
+
+impl Problem {
+    /// A problem with the given `code` and `message`
+    #[inline]
+    pub fn new(code: impl Into<Cow<'static, str>>, message: impl std::fmt::Display) -> Self {
+        Self {
+            code: code.into(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}] {}", self.code, self.message)
+    }
+}
+
+/// The problems found in a single file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileReport {
+    /// The path of the file (or of a directory that could not be read)
+    pub path: PathBuf,
+    /// The problems found, in the order the check reported them
+    pub problems: Vec<Problem>,
+}
+
+/// The result of [`check_dir`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchReport {
+    /// The number of files checked
+    pub checked: usize,
+    /// The files with problems, ordered by path
+    pub files: Vec<FileReport>,
+    /// The number of problems per [`code`](Problem::code)
+    pub counts: BTreeMap<Cow<'static, str>, usize>,
+    /// The total time taken; zero on `wasm32-unknown-unknown`, which has no clock
+    pub elapsed: Duration,
+}
+
+impl BatchReport {
+    /// Whether no problems were found
+    #[inline]
+    #[must_use]
+    pub const fn is_ok(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// The total number of problems found
+    #[must_use]
+    pub fn problems(&self) -> usize {
+        self.counts.values().sum()
+    }
+}
+
+impl std::fmt::Display for BatchReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for file in &self.files {
+            writeln!(f, "{}:", file.path.display())?;
+            for p in &file.problems {
+                writeln!(f, "  {p}")?;
+            }
+        }
+        for (code, n) in &self.counts {
+            writeln!(f, "{code}: {n}")?;
+        }
+        writeln!(
+            f,
+            "{} files checked, {} with problems, in {:?}",
+            self.checked,
+            self.files.len(),
+            self.elapsed
+        )
+    }
+}
+
+/// Checks all files below `path` (or the file at `path`) with [`check_openmath`].
+#[must_use]
+pub fn check_dir(path: impl AsRef<Path>, options: &BatchOptions) -> BatchReport {
+    check_dir_with(path, options, |_, format, input| {
+        check_openmath(format, input, options)
+    })
+}
+
+/** Checks all files below `path` (or the file at `path`) whose [`Format`] is known, by
+calling `check` with the path, format and contents of each; e.g. to exercise custom
+[`OMDeserializable`] targets via [`parse`].
+
+Files and directories that can not be read are reported with code `"io"`.
+
+# Examples
+```
+use openmath::batch::{BatchOptions, Problem, check_dir_with, parse};
+
+let dir = std::env::temp_dir().join(format!("openmath-doc-batch-{}", std::process::id()));
+std::fs::create_dir_all(&dir).expect("can create");
+std::fs::write(dir.join("one.om"), "<OMOBJ><OMI>1</OMI></OMOBJ>").expect("can write");
+std::fs::write(dir.join("big.om"), "<OMOBJ><OMI>300</OMI></OMOBJ>").expect("can write");
+
+let report = check_dir_with(&dir, &BatchOptions::default(), |_, format, input| {
+    parse::<u8>(format, input).err().into_iter().collect()
+});
+assert_eq!(report.checked, 2);
+assert_eq!(report.files.len(), 1);
+assert!(report.files[0].path.ends_with("big.om"));
+assert_eq!(report.counts["conversion"], 1);
+# std::fs::remove_dir_all(&dir).expect("can remove");
+```
+*/
+pub fn check_dir_with<F>(path: impl AsRef<Path>, options: &BatchOptions, check: F) -> BatchReport
+where
+    F: Fn(&Path, Format, &str) -> Vec<Problem> + Sync,
+{
+    let start = crate::de::now();
+    let mut files = Vec::new();
+    let mut failed = Vec::new();
+    collect(path.as_ref(), options, &mut files, &mut failed);
+    files.sort_unstable();
+
+    let run = |(path, format): &(PathBuf, Format)| FileReport {
+        problems: match std::fs::read_to_string(path) {
+            Ok(input) => check(path, *format, &input),
+            Err(e) => vec![Problem::new("io", e)],
+        },
+        path: path.clone(),
+    };
+    #[cfg(feature = "rayon")]
+    let reports: Vec<_> = {
+        use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+        files.par_iter().map(run).collect()
+    };
+    #[cfg(not(feature = "rayon"))]
+    let reports: Vec<_> = files.iter().map(run).collect();
+
+    failed.extend(reports.into_iter().filter(|r| !r.problems.is_empty()));
+    failed.sort_by(|a, b| a.path.cmp(&b.path));
+    let mut counts = BTreeMap::new();
+    for p in failed.iter().flat_map(|f| &f.problems) {
+        *counts.entry(p.code.clone()).or_default() += 1;
+    }
+    BatchReport {
+        checked: files.len(),
+        files: failed,
+        counts,
+        elapsed: start.map(|s| s.elapsed()).unwrap_or_default(),
+    }
+}
+
+/// Recursively collects the files to check below `path`; does not follow symlinks to
+/// directories.
+fn collect(
+    path: &Path,
+    options: &BatchOptions,
+    files: &mut Vec<(PathBuf, Format)>,
+    failed: &mut Vec<FileReport>,
+) {
+    if !path.is_dir() {
+        if let Some(format) = Format::of(path, options) {
+            files.push((path.to_path_buf(), format));
+        }
+        return;
+    }
+    let entries = match std::fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(e) => {
+            failed.push(FileReport {
+                path: path.to_path_buf(),
+                problems: vec![Problem::new("io", e)],
+            });
+            return;
+        }
+    };
+    for entry in entries {
+        match entry.and_then(|e| Ok((e.path(), e.file_type()?))) {
+            Ok((path, ty)) if ty.is_dir() => collect(&path, options, files, failed),
+            Ok((path, _)) => {
+                if let Some(format) = Format::of(&path, options) {
+                    files.push((path, format));
+                }
+            }
+            Err(e) => failed.push(FileReport {
+                path: path.to_path_buf(),
+                problems: vec![Problem::new("io", e)],
+            }),
+        }
+    }
+}
+
+/** Parses `input` in the given `format` as an `O`.
+
+# Errors
+a [`Problem`] with code `"conversion"` if [`from_openmath`](OMDeserializable::from_openmath)
+fails, `"xml"` or `"json"` if the input is malformed, and `"unsupported"` for JSON without
+the `serde` and `serde_json` features.
+*/
+pub fn parse<'de, O>(format: Format, input: &'de str) -> Result<O, Problem>
+where
+    O: OMDeserializable<'de> + 'de,
+{
+    match format {
+        Format::Xml => OMObject::<O>::from_openmath_xml(input).map_err(|e| match e {
+            XmlReadError::Conversion(_) | XmlReadError::SymbolConversion { .. } => {
+                Problem::new("conversion", e)
+            }
+            e => Problem::new("xml", e),
+        }),
+        #[cfg(all(feature = "serde", feature = "serde_json"))]
+        Format::Json => serde_json::from_str::<OMObject<'de, O>>(input)
+            .map(OMObject::into_inner)
+            .map_err(|e| Problem::new("json", e)),
+        #[cfg(not(all(feature = "serde", feature = "serde_json")))]
+        Format::Json => Err(Problem::new(
+            "unsupported",
+            "reading JSON requires the `serde` and `serde_json` features",
+        )),
+    }
+}
+
+/** An expectation about a file of a conformance corpus; see [`check_conformance`].
+
+Files are expected to be [`Valid`](Self::Valid) unless annotated otherwise, by a line
+`expect: <expectation>` either in an XML comment of the file itself, or in a file next to it
+with `.expect` appended to its name (e.g. `foo.json.expect`, for encodings without
+comments). An expectation is either `valid` or `parse-error <text>`, where the error message
+has to contain `<text>`.
+
+# Examples
+```
+use openmath::batch::Expectation;
+
+let xml = "<!-- expect: parse-error unresolved reference --><OMOBJ><OMR href='#x'/></OMOBJ>";
+assert_eq!(
+    Expectation::of("sharing.om".as_ref(), xml),
+    Ok(Expectation::ParseError("unresolved reference".to_string()))
+);
+assert_eq!(Expectation::parse("valid"), Ok(Expectation::Valid));
+assert!(Expectation::parse("fine").is_err());
+```
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Expectation {
+    /// The file parses, and roundtrips
+    #[default]
+    Valid,
+    /// Parsing the file fails with a message containing the given text
+    ParseError(String),
+}
+
+impl Expectation {
+    /// Parses an expectation (without the `expect:` prefix).
+    ///
+    /// # Errors
+    /// a [`Problem`] with code `"annotation"` if `s` is not a known expectation.
+    pub fn parse(s: &str) -> Result<Self, Problem> {
+        match s.trim().split_once(char::is_whitespace) {
+            _ if s.trim() == "valid" => Ok(Self::Valid),
+            _ if s.trim() == "parse-error" => Ok(Self::ParseError(String::new())),
+            Some(("parse-error", text)) => Ok(Self::ParseError(text.trim().to_string())),
+            _ => Err(Problem::new(
+                "annotation",
+                format_args!("unknown expectation {:?}", s.trim()),
+            )),
+        }
+    }
+
+    /// The expectation annotated for the file at `path` with the given contents; i.e. the
+    /// first `expect:` line in an XML comment of `input` or in the `.expect` file next to
+    /// `path`.
+    ///
+    /// # Errors
+    /// a [`Problem`] with code `"annotation"` if the annotation is malformed, or `"io"` if
+    /// the `.expect` file exists, but can not be read.
+    pub fn of(path: &Path, input: &str) -> Result<Self, Problem> {
+        let in_comment = input
+            .split("<!--")
+            .skip(1)
+            .filter_map(|c| c.split_once("-->").map(|(c, _)| c))
+            .flat_map(str::lines)
+            .find_map(|l| l.trim().strip_prefix("expect:"));
+        if let Some(e) = in_comment {
+            return Self::parse(e);
+        }
+        let mut sidecar = path.as_os_str().to_os_string();
+        sidecar.push(".expect");
+        let sidecar = PathBuf::from(sidecar);
+        if !sidecar.is_file() {
+            return Ok(Self::Valid);
+        }
+        let contents = std::fs::read_to_string(&sidecar).map_err(|e| Problem::new("io", e))?;
+        contents
+            .lines()
+            .find_map(|l| l.trim().strip_prefix("expect:"))
+            .map_or_else(
+                || {
+                    Err(Problem::new(
+                        "annotation",
+                        format_args!("no `expect:` line in {}", sidecar.display()),
+                    ))
+                },
+                Self::parse,
+            )
+    }
+}
+
+/** Checks all files below `path` (or the file at `path`) with [`check_conformance`];
+`*.json` files are included if the `serde` and `serde_json` features are enabled.
+
+# Examples
+```
+use openmath::batch::check_corpus;
+
+let dir = std::env::temp_dir().join(format!("openmath-doc-corpus-{}", std::process::id()));
+std::fs::create_dir_all(&dir).expect("can create");
+std::fs::write(
+    dir.join("sin.om"),
+    r#"<OMOBJ><OMA><OMS cd="transc1" name="sin"/><OMV name="x"/></OMA></OMOBJ>"#,
+)
+.expect("can write");
+std::fs::write(
+    dir.join("hex.om"),
+    r#"<!-- expect: parse-error hexadecimal --><OMOBJ><OMF hex="3FF0000000000000"/></OMOBJ>"#,
+)
+.expect("can write");
+
+let report = check_corpus(&dir);
+assert!(report.is_ok(), "{report}");
+assert_eq!(report.checked, 2);
+# std::fs::remove_dir_all(&dir).expect("can remove");
+```
+*/
+#[cfg(feature = "xml-write")]
+#[must_use]
+pub fn check_corpus(path: impl AsRef<Path>) -> BatchReport {
+    let options = BatchOptions {
+        json: cfg!(all(feature = "serde", feature = "serde_json")),
+        ..BatchOptions::default()
+    };
+    check_dir_with(path, &options, check_conformance)
+}
+
+/** The check of [`check_corpus`]: checks the file at `path` against its [`Expectation`].
+
+If the file is expected to be valid, it is parsed as [`OpenMath`], serialized in the same
+encoding and parsed again, which has to yield the same object (code `"roundtrip"`).
+Additionally, a `*.json` file has to agree with an `*.xml` or `*.om` file of the same name
+next to it, if any (code `"cross-encoding"`); for variants like `a.array.json`, that is
+`a.xml` unless there is an `a.array.xml`. Unmet expectations of parse errors are
+reported with code `"expectation"`.
+*/
+#[cfg(feature = "xml-write")]
+#[must_use]
+pub fn check_conformance(path: &Path, format: Format, input: &str) -> Vec<Problem> {
+    use crate::OMSerializable;
+    let expectation = match Expectation::of(path, input) {
+        Ok(e) => e,
+        Err(p) => return vec![p],
+    };
+    match (expectation, parse::<OpenMath>(format, input)) {
+        (Expectation::Valid, Err(p)) => vec![p],
+        (Expectation::Valid, Ok(om)) => {
+            let mut problems: Vec<_> = roundtrip(format, &om).into_iter().collect();
+            if format == Format::Json {
+                problems.extend(cross_encoding(path, &om));
+            }
+            problems
+        }
+        (Expectation::ParseError(text), Err(p)) if p.message.contains(&text) => Vec::new(),
+        (Expectation::ParseError(text), result) => vec![Problem::new(
+            "expectation",
+            format_args!(
+                "expected a parse error containing {text:?}, found {}",
+                result.map_or_else(|p| p.to_string(), |om| om.openmath_display().to_string())
+            ),
+        )],
+    }
+}
+
+/// Serializes `om` in `format`, and checks that parsing the result yields `om` again
+#[cfg(feature = "xml-write")]
+fn roundtrip(format: Format, om: &OpenMath<'_>) -> Option<Problem> {
+    use crate::OMSerializable;
+    let ser = crate::ser::OMObject(om);
+    let output = match format {
+        Format::Xml => ser.xml(false, false).to_string(),
+        #[cfg(all(feature = "serde", feature = "serde_json"))]
+        Format::Json => match serde_json::to_string(&ser) {
+            Ok(s) => s,
+            Err(e) => return Some(Problem::new("roundtrip", e)),
+        },
+        #[cfg(not(all(feature = "serde", feature = "serde_json")))]
+        Format::Json => return None,
+    };
+    match parse::<OpenMath>(format, &output) {
+        Ok(back) if back == *om => None,
+        Ok(back) => Some(Problem::new(
+            "roundtrip",
+            format_args!(
+                "{} reads back as {}",
+                om.openmath_display(),
+                back.openmath_display()
+            ),
+        )),
+        Err(p) => Some(Problem::new(
+            "roundtrip",
+            format_args!("the output {output:?} does not parse: {p}"),
+        )),
+    }
+}
+
+/// Checks that the XML file next to the JSON file at `path`, if any, encodes `om`, too
+#[cfg(feature = "xml-write")]
+fn cross_encoding(path: &Path, om: &OpenMath<'_>) -> Option<Problem> {
+    use crate::OMSerializable;
+    // a variant like `a.array.json` is compared to `a.xml`, unless there is `a.array.xml`
+    let (xml_path, xml) = [path.to_path_buf(), path.with_extension("")]
+        .iter()
+        .flat_map(|stem| ["xml", "om"].map(|ext| stem.with_extension(ext)))
+        .find_map(|p| std::fs::read_to_string(&p).ok().map(|xml| (p, xml)))?;
+    // invalid files are reported on their own
+    if Expectation::of(&xml_path, &xml) != Ok(Expectation::Valid) {
+        return None;
+    }
+    let other = parse::<OpenMath>(Format::Xml, &xml).ok()?;
+    (other != *om).then(|| {
+        Problem::new(
+            "cross-encoding",
+            format_args!(
+                "{} encodes {}, but this is {}",
+                xml_path.display(),
+                other.openmath_display(),
+                om.openmath_display()
+            ),
+        )
+    })
+}
+
+/// The check of [`check_dir`]: parses `input` as [`OpenMath`] and applies the validations
+/// and lints selected in `options`, with their [`code`](crate::InvariantError::code)s.
+#[must_use]
+pub fn check_openmath(format: Format, input: &str, options: &BatchOptions) -> Vec<Problem> {
+    let om = match parse::<OpenMath>(format, input) {
+        Ok(om) => om,
+        Err(p) => return vec![p],
+    };
+    let mut problems = Vec::new();
+    if options.invariants
+        && let Err(errors) = om.check_invariants()
+    {
+        problems.extend(errors.iter().map(|e| Problem::new(e.code(), e)));
+    }
+    if options.lints {
+        problems.extend(om.lints().iter().map(|l| Problem::new(l.code(), l)));
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BatchOptions, check_dir};
+    use std::path::Path;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn check_dir_report() {
+        let dir = std::env::temp_dir().join(format!("openmath-batch-{}", std::process::id()));
+        let files = [
+            ("a.om", r#"<OMOBJ><OMS cd="arith1" name="plus"/></OMOBJ>"#),
+            (
+                "b/c.xml",
+                r#"<OMOBJ><OMS cd="arith1" name="1plus"/></OMOBJ>"#,
+            ),
+            ("b/d.om", "<OMOBJ><OMI>one</OMI></OMOBJ>"),
+            (
+                "b/e/f.om",
+                concat!(
+                    r#"<OMOBJ><OMBIND><OMS cd="fns1" name="lambda"/>"#,
+                    r#"<OMBVAR><OMV name="x"/><OMV name="x"/></OMBVAR><OMV name="x"/></OMBIND></OMOBJ>"#
+                ),
+            ),
+            ("b/g.txt", "not checked"),
+            (
+                "h.json",
+                r#"{ "kind": "OMOBJ", "object": { "kind": "OMI", "integer": 1 } }"#,
+            ),
+        ];
+        for (path, content) in files {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().expect("has parent")).expect("can create");
+            std::fs::write(path, content).expect("can write");
+        }
+
+        let report = check_dir(&dir, &BatchOptions::default());
+        assert_eq!(report.checked, 4);
+        let paths = report
+            .files
+            .iter()
+            .map(|f| f.path.strip_prefix(&dir).expect("is below"));
+        assert!(paths.eq([Path::new("b/c.xml"), Path::new("b/d.om")]));
+        assert_eq!(report.counts["invalid-name"], 1);
+        assert_eq!(report.counts["xml"], 1);
+        assert_eq!(report.problems(), 2);
+
+        let options = BatchOptions {
+            json: true,
+            invariants: false,
+            lints: true,
+        };
+        let report = check_dir(&dir, &options);
+        assert_eq!(report.checked, 5);
+        let codes = report
+            .files
+            .iter()
+            .map(|f| {
+                (
+                    f.path.file_name().expect("is a file"),
+                    f.problems[0].code.as_ref(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let json = if cfg!(all(feature = "serde", feature = "serde_json")) {
+            None
+        } else {
+            Some(("h.json".as_ref(), "unsupported"))
+        };
+        assert_eq!(
+            codes,
+            [
+                ("d.om".as_ref(), "xml"),
+                ("f.om".as_ref(), "duplicate-bound-variable")
+            ]
+            .into_iter()
+            .chain(json)
+            .collect::<Vec<_>>()
+        );
+        assert!(!report.to_string().is_empty());
+        std::fs::remove_dir_all(&dir).expect("can remove");
+    }
+
+    #[cfg(feature = "xml-write")]
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn check_corpus_problems() {
+        let dir = std::env::temp_dir().join(format!("openmath-corpus-{}", std::process::id()));
+        let files = [
+            (
+                "a.xml",
+                "<!-- expect: parse-error unresolved --><OMOBJ><OMI>1</OMI></OMOBJ>",
+            ),
+            ("b.xml", "<!-- expect: fine --><OMOBJ><OMI>1</OMI></OMOBJ>"),
+            ("c.xml", "<OMOBJ><OMI>1</OMI></OMOBJ>"),
+            (
+                "c.json",
+                r#"{ "kind": "OMOBJ", "object": { "kind": "OMI", "integer": 2 } }"#,
+            ),
+            ("d.om", "<OMOBJ><OMI>one</OMI></OMOBJ>"),
+            ("d.om.expect", "expect: parse-error one"),
+        ];
+        std::fs::create_dir_all(&dir).expect("can create");
+        for (path, content) in files {
+            std::fs::write(dir.join(path), content).expect("can write");
+        }
+
+        let report = super::check_corpus(&dir);
+        let codes = report
+            .files
+            .iter()
+            .map(|f| {
+                (
+                    f.path.file_name().expect("is a file"),
+                    f.problems[0].code.as_ref(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let json = cfg!(all(feature = "serde", feature = "serde_json"))
+            .then_some(("c.json".as_ref(), "cross-encoding"));
+        assert_eq!(
+            codes,
+            [
+                ("a.xml".as_ref(), "expectation"),
+                ("b.xml".as_ref(), "annotation")
+            ]
+            .into_iter()
+            .chain(json)
+            .collect::<Vec<_>>()
+        );
+        std::fs::remove_dir_all(&dir).expect("can remove");
+    }
+}
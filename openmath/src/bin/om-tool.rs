@@ -0,0 +1,227 @@
+/*! `om-tool`: converts, validates, analyzes and pretty-prints
+<span style="font-variant:small-caps;">OpenMath</span> files from the command line; requires the
+`cli` feature. Run `om-tool help` for the usage.
+
+Every file (or stdin, if no file or `-` is given) is read as a single `OMOBJ`, in the XML or
+JSON encoding: as given by `--from`, otherwise by the file extension (see
+[`Format::of`](openmath::batch::Format::of)), otherwise by whether the input starts with `<`.
+
+The exit code is `0` on success, `1` if some input could not be read or (for `validate`) has
+problems, and `2` for invalid arguments.
+*/
+#![allow(clippy::doc_markdown)]
+
+use std::{
+    io::{Read, Write},
+    path::Path,
+    process::ExitCode,
+};
+
+use openmath::{
+    OMSerializable, OpenMath,
+    batch::{BatchOptions, Format, Problem},
+    ser::Options,
+};
+
+const USAGE: &str = "\
+Usage: om-tool <command> [options] [file ...]
+
+Commands:
+  convert   converts to the other encoding (or to the one given by --to); compact by default
+  validate  checks the invariants (and, with --lint, the lints) and prints the problems found;
+            exits with 1 if there are any
+  stats     prints an analysis of the symbols and elements used, as JSON
+  fmt       pretty-prints in the same encoding (or in the one given by --to)
+  help      prints this message
+
+Options:
+  --from xml|json  the encoding of the input (default: by extension, or by content)
+  --to xml|json    the encoding of the output, for convert and fmt
+  --pretty         pretty-prints the output of convert
+  --compact        does not pretty-print the output of fmt
+  --lint           also reports lints, for validate
+
+Reads stdin if no file (or `-`) is given.
+";
+
+/// The subcommand to run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Convert,
+    Validate,
+    Stats,
+    Fmt,
+}
+
+/// The parsed command line
+#[derive(Debug)]
+struct Args {
+    command: Command,
+    from: Option<Format>,
+    to: Option<Format>,
+    pretty: Option<bool>,
+    lint: bool,
+    files: Vec<String>,
+}
+
+fn format(s: Option<&str>) -> Result<Format, String> {
+    match s {
+        Some("xml") => Ok(Format::Xml),
+        Some("json") => Ok(Format::Json),
+        Some(s) => Err(format!("unknown format `{s}`; expected `xml` or `json`")),
+        None => Err("missing format; expected `xml` or `json`".to_string()),
+    }
+}
+
+impl Args {
+    /// Parses the arguments (without the program name); `Ok(None)` asks for the usage
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Option<Self>, String> {
+        let command = match args.next().as_deref() {
+            Some("convert") => Command::Convert,
+            Some("validate") => Command::Validate,
+            Some("stats") => Command::Stats,
+            Some("fmt") => Command::Fmt,
+            Some("help" | "-h" | "--help") => return Ok(None),
+            Some(c) => return Err(format!("unknown command `{c}`")),
+            None => return Err("missing command".to_string()),
+        };
+        let mut ret = Self {
+            command,
+            from: None,
+            to: None,
+            pretty: None,
+            lint: false,
+            files: Vec::new(),
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--from" => ret.from = Some(format(args.next().as_deref())?),
+                "--to" => ret.to = Some(format(args.next().as_deref())?),
+                "--pretty" => ret.pretty = Some(true),
+                "--compact" => ret.pretty = Some(false),
+                "--lint" => ret.lint = true,
+                "-h" | "--help" => return Ok(None),
+                s if s.starts_with("--") => return Err(format!("unknown option `{s}`")),
+                _ => ret.files.push(arg),
+            }
+        }
+        if ret.files.is_empty() {
+            ret.files.push("-".to_string());
+        }
+        Ok(Some(ret))
+    }
+
+    /// The encoding of `input`, read from `file`
+    fn from(&self, file: &str, input: &str) -> Format {
+        let options = BatchOptions {
+            json: true,
+            ..BatchOptions::default()
+        };
+        self.from
+            .or_else(|| Format::of(Path::new(file), &options))
+            .unwrap_or_else(|| {
+                if input.trim_start().starts_with('<') {
+                    Format::Xml
+                } else {
+                    Format::Json
+                }
+            })
+    }
+}
+
+fn read(file: &str) -> std::io::Result<String> {
+    if file == "-" {
+        let mut input = String::new();
+        std::io::stdin().read_to_string(&mut input)?;
+        Ok(input)
+    } else {
+        std::fs::read_to_string(file)
+    }
+}
+
+/// Writes `om` in the given encoding
+fn write(om: &OpenMath<'_>, to: Format, pretty: bool, out: &mut impl Write) -> Result<(), Problem> {
+    match to {
+        Format::Xml => om
+            .omobject()
+            .write_xml_with(Options::new().with_pretty(pretty), &mut *out)
+            .map_err(|e| Problem::new("xml", e))?,
+        Format::Json if pretty => serde_json::to_writer_pretty(&mut *out, &om.omobject())
+            .map_err(|e| Problem::new("json", e))?,
+        Format::Json => {
+            serde_json::to_writer(&mut *out, &om.omobject())
+                .map_err(|e| Problem::new("json", e))?;
+        }
+    }
+    writeln!(out).map_err(|e| Problem::new("io", e))
+}
+
+/// Runs the command on a single `input`; returns the problems found
+fn run(args: &Args, from: Format, input: &str, out: &mut impl Write) -> Vec<Problem> {
+    if args.command == Command::Validate {
+        let options = BatchOptions {
+            json: true,
+            invariants: true,
+            lints: args.lint,
+        };
+        return openmath::batch::check_openmath(from, input, &options);
+    }
+    let result =
+        openmath::batch::parse::<OpenMath>(from, input).and_then(|om| match args.command {
+            Command::Convert => {
+                let to = args.to.unwrap_or(match from {
+                    Format::Xml => Format::Json,
+                    Format::Json => Format::Xml,
+                });
+                write(&om, to, args.pretty.unwrap_or(false), out)
+            }
+            Command::Fmt => write(
+                &om,
+                args.to.unwrap_or(from),
+                args.pretty.unwrap_or(true),
+                out,
+            ),
+            Command::Stats => serde_json::to_writer_pretty(&mut *out, &openmath::analyze(&om))
+                .map_err(|e| Problem::new("json", e))
+                .and_then(|()| writeln!(out).map_err(|e| Problem::new("io", e))),
+            Command::Validate => unreachable!(),
+        });
+    result.err().into_iter().collect()
+}
+
+fn main() -> ExitCode {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprint!("om-tool: {e}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    let mut out = std::io::stdout().lock();
+    let mut ok = true;
+    for file in &args.files {
+        let name = if file == "-" { "<stdin>" } else { file };
+        let problems = match read(file) {
+            Ok(input) => run(&args, args.from(file, &input), &input, &mut out),
+            Err(e) => vec![Problem::new("io", e)],
+        };
+        for p in &problems {
+            // problems are the output of validate, and errors otherwise
+            if args.command == Command::Validate && p.code != "io" {
+                let _ = writeln!(out, "{name}: {p}");
+            } else {
+                eprintln!("{name}: {p}");
+            }
+        }
+        ok &= problems.is_empty();
+    }
+    if ok {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
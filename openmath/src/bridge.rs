@@ -0,0 +1,604 @@
+/*! Bridging <span style="font-variant:small-caps;">OpenMath</span> and the expression types of
+other crates (e.g. computer algebra systems); see [`FromOpenMath`] and [`ToOpenMath`].
+
+A [`FromOpenMath`] type is built bottom-up from its subexpressions, like the
+[`Ret`](crate::OMDeserializable::Ret) of an [`OMDeserializable`]; it can be [`import`]ed from
+an [`OpenMath`] object, or parsed from XML or JSON directly by way of [`Bridged`].
+
+Compared to implementing [`OMDeserializable`] and [`OMSerializable`](crate::OMSerializable),
+the bridge trades flexibility for brevity: building an expression can not fail, and
+[OMFOREIGN](crate::OMKind::OMFOREIGN) content as well as the attributes of bound variables are
+dropped.
+
+# Examples
+```
+use openmath::{Int, OMDeserializable, OpenMath, bridge::{Bridged, FromOpenMath, import}};
+
+#[derive(Debug, PartialEq)]
+enum Expr {
+    Num(i128),
+    Real(f64),
+    Text(String),
+    Var(String),
+    Sym(String),
+    App(Box<Expr>, Vec<Expr>),
+    Lambda(Vec<String>, Box<Expr>),
+}
+impl FromOpenMath for Expr {
+    fn integer(int: Int<'_>) -> Self { Self::Num(int.is_i128().unwrap_or_default()) }
+    fn float(float: f64) -> Self { Self::Real(float) }
+    fn string(string: &str) -> Self { Self::Text(string.to_string()) }
+    fn variable(name: &str) -> Self { Self::Var(name.to_string()) }
+    fn symbol(_: &str, cd: &str, name: &str) -> Self { Self::Sym(format!("{cd}.{name}")) }
+    fn apply(head: Self, args: Vec<Self>) -> Self { Self::App(Box::new(head), args) }
+    fn bind(_: Self, vars: Vec<String>, body: Self) -> Self { Self::Lambda(vars, Box::new(body)) }
+}
+
+let expected = Expr::Lambda(
+    vec!["x".to_string()],
+    Box::new(Expr::App(
+        Box::new(Expr::Sym("arith1.plus".to_string())),
+        vec![Expr::Var("x".to_string()), Expr::Num(1)],
+    )),
+);
+let om = OpenMath::from_sexpr("(ombind (oms fns1 lambda) (bvar x) (oma (oms arith1 plus) (omv x) (omi 1)))")
+    .expect("is valid");
+assert_eq!(import::<Expr>(&om), expected);
+
+# #[cfg(feature = "xml-read")]
+# {
+let xml = r#"<OMBIND><OMS cd="fns1" name="lambda"/><OMBVAR><OMV name="x"/></OMBVAR>
+    <OMA><OMS cd="arith1" name="plus"/><OMV name="x"/><OMI>1</OMI></OMA></OMBIND>"#;
+let Bridged(parsed) = Bridged::<Expr>::from_openmath_xml(xml).expect("is valid");
+assert_eq!(parsed, expected);
+# }
+```
+*/
+
+use std::borrow::Cow;
+
+use crate::{
+    Attr, BoundVariable, CD_BASE, Int, OM, OMDeserializable, OMMaybeForeign, OpenMath, SymbolRef,
+    de::UnresolvedReference,
+};
+
+/// An expression type that can be built from the parts of an
+/// <span style="font-variant:small-caps;">OpenMath</span> object, bottom-up; see the
+/// [module documentation](self).
+///
+/// Symbols (including the keys of attributes) are given with their resolved cdbase.
+pub trait FromOpenMath: Sized {
+    /// An [OMI](crate::OMKind::OMI)
+    fn integer(int: Int<'_>) -> Self;
+    /// An [OMF](crate::OMKind::OMF)
+    fn float(float: f64) -> Self;
+    /// An [OMSTR](crate::OMKind::OMSTR)
+    fn string(string: &str) -> Self;
+    /// An [OMV](crate::OMKind::OMV)
+    fn variable(name: &str) -> Self;
+    /// An [OMS](crate::OMKind::OMS)
+    fn symbol(cdbase: &str, cd: &str, name: &str) -> Self;
+    /// An [OMA](crate::OMKind::OMA)
+    fn apply(head: Self, args: Vec<Self>) -> Self;
+    /// An [OMBIND](crate::OMKind::OMBIND), with the names of its variables
+    fn bind(binder: Self, vars: Vec<String>, body: Self) -> Self;
+
+    /// An [OMB](crate::OMKind::OMB); by default, the [`string`](Self::string) of its base64
+    /// encoding.
+    #[must_use]
+    fn bytes(bytes: &[u8]) -> Self {
+        let mut encoded = String::new();
+        crate::base64::encode_into(bytes, &mut encoded);
+        Self::string(&encoded)
+    }
+
+    /// An [OME](crate::OMKind::OME) with the given error `symbol` and the arguments that are
+    /// not foreign; by default, the [application](Self::apply) of `symbol` to `args`.
+    #[inline]
+    fn error(symbol: Self, args: Vec<Self>) -> Self {
+        Self::apply(symbol, args)
+    }
+
+    /// Attributes `object` with the pair `cdbase?cd#name = value`, for every pair (outermost
+    /// first) whose value is not foreign; by default, the pair is dropped.
+    #[inline]
+    fn attribute(object: Self, cdbase: &str, cd: &str, name: &str, value: Self) -> Self {
+        let _ = (cdbase, cd, name, value);
+        object
+    }
+}
+
+/// An expression type that can be turned into an
+/// <span style="font-variant:small-caps;">OpenMath</span> object; see the
+/// [module documentation](self).
+pub trait ToOpenMath {
+    /// This expression as an <span style="font-variant:small-caps;">OpenMath</span> object
+    fn to_openmath(&self) -> OpenMath<'static>;
+}
+
+/// Builds a `T` from `om`; symbols without a cdbase are resolved against
+/// [`CD_BASE`](crate::CD_BASE).
+#[must_use]
+pub fn import<T: FromOpenMath>(om: &OpenMath<'_>) -> T {
+    fn base<'s>(cdbase: Option<&'s Cow<'_, str>>) -> &'s str {
+        cdbase.map_or(CD_BASE, |c| &**c)
+    }
+    fn attributed<T: FromOpenMath>(
+        mut object: T,
+        attributes: &[Attr<'_, OMMaybeForeign<'_, OpenMath<'_>>>],
+    ) -> T {
+        for a in attributes {
+            if let OMMaybeForeign::OM(value) = &a.value {
+                let key = &a.key;
+                object = T::attribute(
+                    object,
+                    base(key.cdbase.as_ref()),
+                    &key.cd,
+                    &key.name,
+                    import(value),
+                );
+            }
+        }
+        object
+    }
+    let object = match om {
+        OpenMath::OMI { int, .. } => T::integer(int.clone()),
+        OpenMath::OMF { float, .. } => T::float(float.0),
+        OpenMath::OMSTR { string, .. } => T::string(string),
+        OpenMath::OMB { bytes, .. } => T::bytes(bytes),
+        OpenMath::OMV { name, .. } => T::variable(name),
+        OpenMath::OMS {
+            cd, name, cdbase, ..
+        } => T::symbol(base(cdbase.as_ref()), cd, name),
+        OpenMath::OMA {
+            applicant,
+            arguments,
+            ..
+        } => T::apply(import(applicant), arguments.iter().map(import).collect()),
+        OpenMath::OME {
+            cd,
+            name,
+            cdbase,
+            arguments,
+            ..
+        } => T::error(
+            T::symbol(base(cdbase.as_ref()), cd, name),
+            arguments
+                .iter()
+                .filter_map(|a| match a {
+                    OMMaybeForeign::OM(a) => Some(import(a)),
+                    OMMaybeForeign::Foreign { .. } => None,
+                })
+                .collect(),
+        ),
+        OpenMath::OMBIND {
+            binder,
+            variables,
+            object,
+            ..
+        } => T::bind(
+            import(binder),
+            variables.iter().map(|v| v.name.to_string()).collect(),
+            import(object),
+        ),
+    };
+    attributed(object, om.attributes())
+}
+
+/// Makes any [`FromOpenMath`] type [`OMDeserializable`], and thus parseable from XML or JSON;
+/// see the [module documentation](self).
+///
+/// [OMR](crate::OMKind::OMR)s can not be bridged and fail with an [`UnresolvedReference`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Bridged<T>(pub T);
+impl<T> Bridged<T> {
+    /// The bridged expression
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<'de, T: FromOpenMath + std::fmt::Debug> OMDeserializable<'de> for Bridged<T> {
+    type Ret = Self;
+    type Err = UnresolvedReference;
+    fn from_openmath(om: OM<'de, Self>, cdbase: &str) -> Result<Self, Self::Err> {
+        fn not_foreign<T>(o: OMMaybeForeign<'_, Bridged<T>>) -> Option<T> {
+            match o {
+                OMMaybeForeign::OM(Bridged(o)) => Some(o),
+                OMMaybeForeign::Foreign { .. } => None,
+            }
+        }
+        let (object, attrs) = match om {
+            OM::OMI { int, attrs } => (T::integer(int), attrs),
+            OM::OMF { float, attrs, .. } => (T::float(float), attrs),
+            OM::OMSTR { string, attrs } => (T::string(&string), attrs),
+            OM::OMB { bytes, attrs } => (T::bytes(&bytes), attrs),
+            OM::OMV { name, attrs } => (T::variable(&name), attrs),
+            OM::OMS {
+                cdbase: own,
+                cd,
+                name,
+                attrs,
+            } => (
+                T::symbol(own.as_deref().unwrap_or(cdbase), &cd, &name),
+                attrs,
+            ),
+            OM::OMA {
+                applicant,
+                arguments,
+                attrs,
+            } => (
+                T::apply(applicant.0, arguments.into_iter().map(|a| a.0).collect()),
+                attrs,
+            ),
+            OM::OME {
+                cdbase: own,
+                cd,
+                name,
+                arguments,
+                attrs,
+            } => (
+                T::error(
+                    T::symbol(own.as_deref().unwrap_or(cdbase), &cd, &name),
+                    arguments.into_iter().filter_map(not_foreign).collect(),
+                ),
+                attrs,
+            ),
+            OM::OMBIND {
+                binder,
+                variables,
+                object,
+                attrs,
+            } => (
+                T::bind(
+                    binder.0,
+                    variables
+                        .into_iter()
+                        .map(|(name, _)| name.into_owned())
+                        .collect(),
+                    object.0,
+                ),
+                attrs,
+            ),
+            OM::OMR { href, .. } => return Err(UnresolvedReference(href.into_owned())),
+        };
+        Ok(Self(attrs.into_iter().fold(object, |object, a| {
+            let key = a.key;
+            match not_foreign(a.value) {
+                Some(value) => T::attribute(
+                    object,
+                    key.cdbase.as_deref().unwrap_or(cdbase),
+                    &key.cd,
+                    &key.name,
+                    value,
+                ),
+                None => object,
+            }
+        })))
+    }
+}
+
+/// The reference implementation; [attributes](FromOpenMath::attribute) are kept, symbols
+/// carry their (resolved) cdbase, which counts as given explicitly unless it is
+/// [`CD_BASE`](crate::CD_BASE).
+impl FromOpenMath for OpenMath<'static> {
+    #[inline]
+    fn integer(int: Int<'_>) -> Self {
+        Self::OMI {
+            int: int.into_owned(),
+            attributes: Vec::new(),
+        }
+    }
+    #[inline]
+    fn float(float: f64) -> Self {
+        Self::OMF {
+            float: float.into(),
+            attributes: Vec::new(),
+        }
+    }
+    #[inline]
+    fn string(string: &str) -> Self {
+        Self::OMSTR {
+            string: Cow::Owned(string.to_string()),
+            attributes: Vec::new(),
+        }
+    }
+    #[inline]
+    fn bytes(bytes: &[u8]) -> Self {
+        Self::OMB {
+            bytes: Cow::Owned(bytes.to_vec()),
+            attributes: Vec::new(),
+        }
+    }
+    #[inline]
+    fn variable(name: &str) -> Self {
+        Self::OMV {
+            name: Cow::Owned(name.to_string()),
+            attributes: Vec::new(),
+        }
+    }
+    #[inline]
+    fn symbol(cdbase: &str, cd: &str, name: &str) -> Self {
+        Self::OMS {
+            cd: Cow::Owned(cd.to_string()),
+            name: Cow::Owned(name.to_string()),
+            cdbase_inherited: crate::cdbase::eq_normalized(cdbase, CD_BASE),
+            cdbase: Some(owned_cdbase(cdbase)),
+            attributes: Vec::new(),
+        }
+    }
+    #[inline]
+    fn apply(head: Self, args: Vec<Self>) -> Self {
+        Self::OMA {
+            applicant: Box::new(head),
+            arguments: args,
+            attributes: Vec::new(),
+        }
+    }
+    #[inline]
+    fn bind(binder: Self, vars: Vec<String>, body: Self) -> Self {
+        Self::OMBIND {
+            binder: Box::new(binder),
+            variables: vars
+                .into_iter()
+                .map(|name| BoundVariable {
+                    name: Cow::Owned(name),
+                    attributes: Vec::new(),
+                })
+                .collect(),
+            object: Box::new(body),
+            attributes: Vec::new(),
+        }
+    }
+    fn error(symbol: Self, args: Vec<Self>) -> Self {
+        let Self::OMS {
+            cd, name, cdbase, ..
+        } = symbol
+        else {
+            unreachable!("errors are built from symbols")
+        };
+        Self::OME {
+            cd,
+            name,
+            cdbase,
+            arguments: args.into_iter().map(OMMaybeForeign::OM).collect(),
+            attributes: Vec::new(),
+        }
+    }
+    fn attribute(mut object: Self, cdbase: &str, cd: &str, name: &str, value: Self) -> Self {
+        object.attributes_mut().push(Attr::new(
+            SymbolRef {
+                cdbase: Some(owned_cdbase(cdbase)),
+                cd: Cow::Owned(cd.to_string()),
+                name: Cow::Owned(name.to_string()),
+            },
+            OMMaybeForeign::OM(value),
+        ));
+        object
+    }
+}
+
+/// The default cdbase is borrowed rather than copied, like in deserialized objects
+fn owned_cdbase(cdbase: &str) -> Cow<'static, str> {
+    if crate::cdbase::eq_normalized(cdbase, CD_BASE) {
+        Cow::Borrowed(CD_BASE)
+    } else {
+        Cow::Owned(cdbase.to_string())
+    }
+}
+
+/// A copy of `s` that no longer borrows
+fn owned(s: &str) -> Cow<'static, str> {
+    Cow::Owned(s.to_string())
+}
+
+fn owned_foreign(
+    o: &OMMaybeForeign<'_, OpenMath<'_>>,
+) -> OMMaybeForeign<'static, OpenMath<'static>> {
+    match o {
+        OMMaybeForeign::OM(o) => OMMaybeForeign::OM(o.to_openmath()),
+        OMMaybeForeign::Foreign { encoding, value } => OMMaybeForeign::Foreign {
+            encoding: encoding.as_deref().map(owned),
+            value: owned(value),
+        },
+    }
+}
+
+fn owned_attributes(
+    a: &[Attr<'_, OMMaybeForeign<'_, OpenMath<'_>>>],
+) -> Vec<Attr<'static, OMMaybeForeign<'static, OpenMath<'static>>>> {
+    a.iter()
+        .map(|a| Attr::new(a.key.clone().into_owned(), owned_foreign(&a.value)))
+        .collect()
+}
+
+/// A deep copy, including foreign content and the attributes of bound variables
+impl ToOpenMath for OpenMath<'_> {
+    fn to_openmath(&self) -> OpenMath<'static> {
+        match self {
+            Self::OMI { int, attributes: a } => OpenMath::OMI {
+                int: int.clone().into_owned(),
+                attributes: owned_attributes(a),
+            },
+            Self::OMF {
+                float,
+                attributes: a,
+            } => OpenMath::OMF {
+                float: *float,
+                attributes: owned_attributes(a),
+            },
+            Self::OMSTR {
+                string,
+                attributes: a,
+            } => OpenMath::OMSTR {
+                string: owned(string),
+                attributes: owned_attributes(a),
+            },
+            Self::OMB {
+                bytes,
+                attributes: a,
+            } => OpenMath::OMB {
+                bytes: Cow::Owned(bytes.to_vec()),
+                attributes: owned_attributes(a),
+            },
+            Self::OMV {
+                name,
+                attributes: a,
+            } => OpenMath::OMV {
+                name: owned(name),
+                attributes: owned_attributes(a),
+            },
+            Self::OMS {
+                cd,
+                name,
+                cdbase,
+                cdbase_inherited,
+                attributes: a,
+            } => OpenMath::OMS {
+                cd: owned(cd),
+                name: owned(name),
+                cdbase: cdbase.as_deref().map(owned),
+                cdbase_inherited: *cdbase_inherited,
+                attributes: owned_attributes(a),
+            },
+            Self::OMA {
+                applicant,
+                arguments,
+                attributes: a,
+            } => OpenMath::OMA {
+                applicant: Box::new(applicant.to_openmath()),
+                arguments: arguments.iter().map(Self::to_openmath).collect(),
+                attributes: owned_attributes(a),
+            },
+            Self::OME {
+                cd,
+                name,
+                cdbase,
+                arguments,
+                attributes: a,
+            } => OpenMath::OME {
+                cd: owned(cd),
+                name: owned(name),
+                cdbase: cdbase.as_deref().map(owned),
+                arguments: arguments.iter().map(owned_foreign).collect(),
+                attributes: owned_attributes(a),
+            },
+            Self::OMBIND {
+                binder,
+                variables,
+                object,
+                attributes: a,
+            } => OpenMath::OMBIND {
+                binder: Box::new(binder.to_openmath()),
+                variables: variables
+                    .iter()
+                    .map(|v| BoundVariable {
+                        name: owned(&v.name),
+                        attributes: owned_attributes(&v.attributes),
+                    })
+                    .collect(),
+                object: Box::new(object.to_openmath()),
+                attributes: owned_attributes(a),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Just the required methods, as a string
+    #[derive(Debug, PartialEq, Eq)]
+    struct Minimal(String);
+    impl FromOpenMath for Minimal {
+        fn integer(int: Int<'_>) -> Self {
+            Self(int.to_string())
+        }
+        fn float(float: f64) -> Self {
+            Self(float.to_string())
+        }
+        fn string(string: &str) -> Self {
+            Self(format!("{string:?}"))
+        }
+        fn variable(name: &str) -> Self {
+            Self(name.to_string())
+        }
+        fn symbol(_: &str, cd: &str, name: &str) -> Self {
+            Self(format!("{cd}.{name}"))
+        }
+        fn apply(head: Self, args: Vec<Self>) -> Self {
+            let args = args.into_iter().map(|a| a.0).collect::<Vec<_>>();
+            Self(format!("{}({})", head.0, args.join(",")))
+        }
+        fn bind(binder: Self, vars: Vec<String>, body: Self) -> Self {
+            Self(format!("{}[{}]{}", binder.0, vars.join(","), body.0))
+        }
+    }
+
+    const SEXPR: &str = r#"(omattr (((oms meta note) (omstr "n")) ((oms meta raw) (omforeign "<a/>")))
+        (ombind (oms fns1 lambda) (bvar x (omattr (((oms sts type) (oms setname1 R))) y))
+          (oma (oms arith1 plus) (omv x) (omi 1) (omf 1.5) (omb "AQI=")
+            (ome (oms error unhandled) (omstr "e") (omforeign "<b/>"))
+            (cdbase "http://example.org" (oms my sym)))))"#;
+
+    #[test]
+    fn defaults() {
+        let om = OpenMath::from_sexpr(SEXPR).expect("is valid");
+        assert_eq!(
+            import::<Minimal>(&om).0,
+            r#"fns1.lambda[x,y]arith1.plus(x,1,1.5,"AQI=",error.unhandled("e"),my.sym)"#
+        );
+    }
+
+    #[test]
+    fn reference() {
+        let om = OpenMath::from_sexpr(SEXPR).expect("is valid");
+        assert_eq!(om.to_openmath(), om);
+
+        // foreign content and the attributes of bound variables are dropped
+        let expected = OpenMath::from_sexpr(
+            r#"(omattr (((oms meta note) (omstr "n")))
+              (ombind (oms fns1 lambda) (bvar x y)
+                (oma (oms arith1 plus) (omv x) (omi 1) (omf 1.5) (omb "AQI=")
+                  (ome (oms error unhandled) (omstr "e"))
+                  (cdbase "http://example.org" (oms my sym)))))"#,
+        )
+        .expect("is valid");
+        let imported = import::<OpenMath>(&om);
+        assert_eq!(imported, expected);
+        assert_eq!(import::<OpenMath>(&expected), expected);
+    }
+
+    #[cfg(all(feature = "xml-read", feature = "xml-write"))]
+    #[test]
+    fn bridged() {
+        use crate::OMSerializable;
+        let om = OpenMath::from_sexpr(SEXPR).expect("is valid");
+        let xml = om.xml(false).to_string();
+        let Bridged(parsed) = Bridged::<Minimal>::from_openmath_xml(&xml).expect("is valid");
+        assert_eq!(parsed, import(&om));
+        let Bridged(parsed) = Bridged::<OpenMath>::from_openmath_xml(&xml).expect("is valid");
+        assert_eq!(parsed, import::<OpenMath>(&om));
+
+        #[cfg(feature = "serde_json")]
+        {
+            use serde::de::DeserializeSeed;
+            let json = serde_json::to_string(&om.openmath_serde()).expect("works");
+            let parsed =
+                crate::de::OMFromSerde::<Bridged<Minimal>>::with_options(crate::de::Options::new())
+                    .deserialize(&mut serde_json::Deserializer::from_str(&json))
+                    .expect("is valid")
+                    .into_inner();
+            assert_eq!(parsed.0, import(&om));
+        }
+
+        let e = Bridged::<Minimal>::from_openmath_xml(r##"<OMR href="#x"/>"##)
+            .expect_err("can not be resolved");
+        assert!(
+            matches!(&e, crate::de::XmlReadError::Conversion(UnresolvedReference(href)) if href == "#x"),
+            "{e}"
+        );
+    }
+}
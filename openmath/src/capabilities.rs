@@ -0,0 +1,207 @@
+/*! What this build of the crate supports, depending on the enabled features; see
+[`capabilities()`](crate::capabilities()).
+
+Every capability is also a constant, e.g. for skipping tests at runtime rather than with
+`#[cfg]`s, or for describing a service (as SCSCP's `get_service_description` does):
+
+```rust
+use openmath::capabilities::{self, Capabilities};
+
+if !capabilities::XML.read {
+    return; // nothing to test
+}
+let caps: Capabilities = openmath::capabilities();
+assert_eq!(caps.openmath_version, "2.0");
+println!("{caps}"); // e.g. "OpenMath 2.0; encodings: XML (read/write), JSON (none), ..."
+```
+*/
+
+/// The version of this crate
+pub const CRATE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Whether an encoding can be read and/or written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Support {
+    pub read: bool,
+    pub write: bool,
+}
+
+impl std::fmt::Display for Support {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match (self.read, self.write) {
+            (true, true) => "read/write",
+            (true, false) => "read",
+            (false, true) => "write",
+            (false, false) => "none",
+        })
+    }
+}
+
+/// The XML encoding (features `xml-read` and `xml-write`)
+pub const XML: Support = Support {
+    read: cfg!(feature = "xml-read"),
+    write: cfg!(feature = "xml-write"),
+};
+
+/// The JSON encoding, via [serde](https://docs.rs/serde) (feature `serde`)
+pub const JSON: Support = Support {
+    read: cfg!(feature = "serde"),
+    write: cfg!(feature = "serde"),
+};
+
+/// The binary encoding, which is not implemented yet
+pub const BINARY: Support = Support {
+    read: false,
+    write: false,
+};
+
+/// The Popcorn syntax (feature `popcorn`, see [`popcorn`](crate::popcorn))
+pub const POPCORN: Support = Support {
+    read: cfg!(feature = "popcorn"),
+    write: cfg!(feature = "popcorn"),
+};
+
+/// Whether SCSCP envelopes and framing are available (feature `scscp`)
+pub const SCSCP: bool = cfg!(feature = "scscp");
+
+macro_rules! features {
+    ($($f:literal),* $(,)?) => {
+        /// The enabled cargo features (other than `default` and `xml`, which only enable
+        /// others), in alphabetical order
+        pub const FEATURES: &[&str] = &[$(#[cfg(feature = $f)] $f),*];
+    };
+}
+features!(
+    "bump",
+    "chrono",
+    "cli",
+    "encoding",
+    "ffi",
+    "fuzzing",
+    "ndarray",
+    "popcorn",
+    "rayon",
+    "scscp",
+    "serde",
+    "serde_json",
+    "smallvec-1",
+    "smallvec-4",
+    "smallvec-8",
+    "stable-hash",
+    "tracing",
+    "uuid",
+    "xml-read",
+    "xml-write",
+);
+
+/// What this build of the crate supports; see [`capabilities()`](crate::capabilities()). The
+/// [`Display`](std::fmt::Display) implementation summarizes it in one line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Capabilities {
+    /// See [`OPENMATH_VERSION`](crate::OPENMATH_VERSION)
+    pub openmath_version: &'static str,
+    /// See [`CRATE_VERSION`]
+    pub crate_version: &'static str,
+    /// See [`XML`]
+    pub xml: Support,
+    /// See [`JSON`]
+    pub json: Support,
+    /// See [`BINARY`]
+    pub binary: Support,
+    /// See [`POPCORN`]
+    pub popcorn: Support,
+    /// See [`SCSCP`]
+    pub scscp: bool,
+    /// See [`FEATURES`]
+    pub features: &'static [&'static str],
+}
+
+impl std::fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "OpenMath {}; encodings: XML ({}), JSON ({}), binary ({}), Popcorn ({}); features: ",
+            self.openmath_version, self.xml, self.json, self.binary, self.popcorn
+        )?;
+        if self.features.is_empty() {
+            f.write_str("none")
+        } else {
+            f.write_str(&self.features.join(", "))
+        }
+    }
+}
+
+/** What this build of the crate supports, depending on the enabled features; the same as the
+constants in [`capabilities`](mod@crate::capabilities).
+
+# Examples
+```rust
+# #[cfg(all(feature = "xml-read", feature = "xml-write"))]
+# {
+let caps = openmath::capabilities();
+// the default features
+assert!(caps.xml.read && caps.xml.write);
+assert!(caps.features.contains(&"xml-read"));
+assert!(!caps.binary.read);
+# }
+```
+*/
+#[must_use]
+pub const fn capabilities() -> Capabilities {
+    Capabilities {
+        openmath_version: crate::OPENMATH_VERSION,
+        crate_version: CRATE_VERSION,
+        xml: XML,
+        json: JSON,
+        binary: BINARY,
+        popcorn: POPCORN,
+        scscp: SCSCP,
+        features: FEATURES,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn features() {
+        let caps = capabilities();
+        assert_eq!(caps.xml.read, FEATURES.contains(&"xml-read"));
+        assert_eq!(caps.xml.write, FEATURES.contains(&"xml-write"));
+        assert_eq!(caps.json.read, FEATURES.contains(&"serde"));
+        assert_eq!(caps.scscp, FEATURES.contains(&"scscp"));
+        assert!(FEATURES.is_sorted());
+        assert_eq!(caps.crate_version, env!("CARGO_PKG_VERSION"));
+    }
+
+    #[test]
+    fn display() {
+        let caps = Capabilities {
+            openmath_version: "2.0",
+            crate_version: "0.0.0",
+            xml: Support {
+                read: true,
+                write: true,
+            },
+            json: Support {
+                read: true,
+                write: false,
+            },
+            binary: BINARY,
+            popcorn: BINARY,
+            scscp: false,
+            features: &["serde", "xml-read", "xml-write"],
+        };
+        assert_eq!(
+            caps.to_string(),
+            "OpenMath 2.0; encodings: XML (read/write), JSON (read), binary (none), Popcorn \
+             (none); features: serde, xml-read, xml-write"
+        );
+        let caps = Capabilities {
+            features: &[],
+            ..caps
+        };
+        assert!(caps.to_string().ends_with("features: none"));
+    }
+}
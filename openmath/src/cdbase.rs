@@ -0,0 +1,674 @@
+use std::borrow::Cow;
+
+/// A cdbase URI; a cheap wrapper around a <code>[Cow]<'_,[str]></code>.
+///
+/// Equality, hashing and ordering are modulo [normalization](CdBase::normalize), i.e. modulo
+/// case of scheme and host, default ports and trailing slashes, like
+/// [`eq_normalized`](CdBase::eq_normalized); the spelling is kept nonetheless (see
+/// [`as_str`](CdBase::as_str)). Serializers use the same comparison to decide whether a
+/// cdbase needs to be (re)declared.
+///
+/// # Examples
+/// ```rust
+/// use openmath::CdBase;
+///
+/// let a = CdBase::parse("HTTP://WWW.OpenMath.org:80/cd/").expect("is valid");
+/// assert_eq!(a.normalize().as_str(), "http://www.openmath.org/cd");
+/// assert!(a.eq_normalized(&CdBase::OPENMATH));
+/// assert_eq!(a, CdBase::OPENMATH);
+/// assert!(CdBase::parse("relative/path").is_err());
+/// assert!(CdBase::parse_relative("relative/path").is_ok());
+/// ```
+#[derive(Debug, Clone)]
+pub struct CdBase<'a>(Cow<'a, str>);
+
+/// Errors when [parsing](CdBase::parse) a [`CdBase`]
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CdBaseError {
+    #[error("empty cdbase")]
+    Empty,
+    #[error("cdbase `{0}` is not an absolute URI")]
+    Relative(String),
+    #[error("invalid scheme in cdbase `{0}`")]
+    InvalidScheme(String),
+    #[error("invalid character {1:?} in cdbase `{0}`")]
+    InvalidChar(String, char),
+}
+
+impl<'a> CdBase<'a> {
+    /// The cdbase of the official <span style="font-variant:small-caps;">OpenMath</span>
+    /// content dictionaries; see [`CD_BASE`](crate::CD_BASE)
+    pub const OPENMATH: CdBase<'static> = CdBase(Cow::Borrowed(crate::CD_BASE));
+
+    /// Wraps `s` without validating it.
+    #[inline]
+    #[must_use]
+    pub const fn new_unchecked(s: &'a str) -> Self {
+        Self(Cow::Borrowed(s))
+    }
+
+    /// Parses an absolute URI (`scheme:…`).
+    ///
+    /// # Errors
+    /// if `s` is empty, relative, has an invalid scheme, or contains characters not
+    /// allowed in URIs (whitespace, control characters, `<>"{}|\^` and backticks).
+    pub fn parse(s: impl Into<Cow<'a, str>>) -> Result<Self, CdBaseError> {
+        let s = s.into();
+        Self::validate(&s)?;
+        if scheme_len(&s).is_none() {
+            return Err(CdBaseError::Relative(s.into_owned()));
+        }
+        Ok(Self(s))
+    }
+
+    /// Like [`parse`](Self::parse), but also accepts relative references.
+    ///
+    /// # Errors
+    /// See [`parse`](Self::parse).
+    pub fn parse_relative(s: impl Into<Cow<'a, str>>) -> Result<Self, CdBaseError> {
+        let s = s.into();
+        Self::validate(&s)?;
+        Ok(Self(s))
+    }
+
+    fn validate(s: &str) -> Result<(), CdBaseError> {
+        if s.is_empty() {
+            return Err(CdBaseError::Empty);
+        }
+        if let Some(c) = s.chars().find(|c| {
+            c.is_whitespace()
+                || c.is_control()
+                || matches!(c, '<' | '>' | '"' | '{' | '}' | '|' | '\\' | '^' | '`')
+        }) {
+            return Err(CdBaseError::InvalidChar(s.to_string(), c));
+        }
+        let before = s.find(['/', '?', '#']).map_or(s, |i| &s[..i]);
+        if let Some(i) = before.find(':') {
+            let scheme = &before[..i];
+            if !scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                || !scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+            {
+                return Err(CdBaseError::InvalidScheme(s.to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether this is an absolute URI (i.e. has a scheme)
+    #[inline]
+    #[must_use]
+    pub fn is_absolute(&self) -> bool {
+        scheme_len(&self.0).is_some()
+    }
+
+    /// Returns the underlying string
+    #[inline]
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns the underlying [`Cow`]
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> Cow<'a, str> {
+        self.0
+    }
+
+    /// Converts this into an owned `CdBase<'static>`
+    #[inline]
+    #[must_use]
+    pub fn into_owned(self) -> CdBase<'static> {
+        CdBase(Cow::Owned(self.0.into_owned()))
+    }
+
+    /// Returns the normalized form of this cdbase: scheme and host are lowercased,
+    /// default ports (`http:80`, `https:443`, `ftp:21`) and a trailing slash are removed.
+    /// Borrows if `self` is already normalized.
+    #[must_use]
+    pub fn normalize(&self) -> CdBase<'_> {
+        let s: &str = &self.0;
+        let parts = Normalized::split(s);
+        if parts.is_normal() {
+            return CdBase(Cow::Borrowed(s));
+        }
+        let mut ret = String::with_capacity(s.len());
+        if let Some(scheme) = parts.scheme {
+            ret.push_str(&scheme.to_ascii_lowercase());
+            ret.push(':');
+        }
+        if let Some((userinfo, host, port)) = parts.authority {
+            ret.push_str("//");
+            if let Some(userinfo) = userinfo {
+                ret.push_str(userinfo);
+                ret.push('@');
+            }
+            ret.push_str(&host.to_ascii_lowercase());
+            if let Some(port) = port {
+                ret.push(':');
+                ret.push_str(port);
+            }
+        }
+        ret.push_str(parts.rest);
+        CdBase(Cow::Owned(ret))
+    }
+
+    /// Resolves the URI reference `reference` (e.g. a relative cdbase) against this as its
+    /// base URI, following [RFC 3986, section 5.2](https://www.rfc-editor.org/rfc/rfc3986#section-5.2).
+    /// Borrows `reference` if it is absolute already.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use openmath::CdBase;
+    ///
+    /// let base = CdBase::new_unchecked("http://example.org/docs/a/index.xml");
+    /// assert_eq!(base.join("../cds").as_str(), "http://example.org/docs/cds");
+    /// assert_eq!(base.join("/cd").as_str(), "http://example.org/cd");
+    /// assert_eq!(base.join("https://other.org/cd").as_str(), "https://other.org/cd");
+    /// ```
+    #[must_use]
+    pub fn join<'r>(&self, reference: &'r str) -> CdBase<'r> {
+        if scheme_len(reference).is_some() {
+            return CdBase(Cow::Borrowed(reference));
+        }
+        let base = UriParts::split(&self.0);
+        let r = UriParts::split(reference);
+        let mut ret = String::with_capacity(self.0.len() + reference.len());
+        if let Some(scheme) = base.scheme {
+            ret.push_str(scheme);
+            ret.push(':');
+        }
+        let (authority, path, query) = if r.authority.is_some() {
+            (r.authority, remove_dot_segments(r.path), r.query)
+        } else if r.path.is_empty() {
+            (
+                base.authority,
+                base.path.to_string(),
+                r.query.or(base.query),
+            )
+        } else if r.path.starts_with('/') {
+            (base.authority, remove_dot_segments(r.path), r.query)
+        } else {
+            let merged = if base.authority.is_some() && base.path.is_empty() {
+                format!("/{}", r.path)
+            } else {
+                let dir = base.path.rfind('/').map_or("", |i| &base.path[..=i]);
+                format!("{dir}{}", r.path)
+            };
+            let mut path = remove_dot_segments(&merged);
+            if base.authority.is_none() && !base.path.starts_with('/') && path.starts_with('/') {
+                // a relative base stays relative
+                path.remove(0);
+            }
+            (base.authority, path, r.query)
+        };
+        if let Some(authority) = authority {
+            ret.push_str("//");
+            ret.push_str(authority);
+        }
+        ret.push_str(&path);
+        if let Some(query) = query {
+            ret.push('?');
+            ret.push_str(query);
+        }
+        if let Some(fragment) = r.fragment {
+            ret.push('#');
+            ret.push_str(fragment);
+        }
+        CdBase(Cow::Owned(ret))
+    }
+
+    /// Compares two cdbases modulo [normalization](Self::normalize)
+    #[must_use]
+    pub fn eq_normalized(&self, other: &CdBase<'_>) -> bool {
+        eq_normalized(&self.0, &other.0)
+    }
+}
+
+impl PartialEq for CdBase<'_> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        eq_normalized(&self.0, &other.0)
+    }
+}
+impl Eq for CdBase<'_> {}
+impl std::hash::Hash for CdBase<'_> {
+    #[inline]
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.normalize().0.hash(state);
+    }
+}
+impl PartialOrd for CdBase<'_> {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for CdBase<'_> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        if self.eq(other) {
+            return std::cmp::Ordering::Equal;
+        }
+        self.normalize().0.cmp(&other.normalize().0)
+    }
+}
+
+/// The components of a cdbase that [normalization](CdBase::normalize) distinguishes, as
+/// slices of the original string; scheme and host still need to be lowercased, default
+/// ports and the trailing slash are removed already. Lets us compare cdbases modulo
+/// normalization without allocating.
+struct Normalized<'s> {
+    scheme: Option<&'s str>,
+    /// userinfo, host and port
+    authority: Option<(Option<&'s str>, &'s str, Option<&'s str>)>,
+    /// everything after the authority (or the scheme, if there is none)
+    rest: &'s str,
+    /// whether a port, an empty userinfo or a trailing slash was removed
+    stripped: bool,
+}
+impl<'s> Normalized<'s> {
+    fn split(s: &'s str) -> Self {
+        let Some(i) = scheme_len(s) else {
+            let rest = strip_trailing_slash(s, s.len() > 1);
+            return Self {
+                scheme: None,
+                authority: None,
+                stripped: rest.len() < s.len(),
+                rest,
+            };
+        };
+        let scheme = &s[..i];
+        let Some(hier) = s[i + 1..].strip_prefix("//") else {
+            let rest = strip_trailing_slash(&s[i + 1..], true);
+            return Self {
+                scheme: Some(scheme),
+                authority: None,
+                stripped: i + 1 + rest.len() < s.len(),
+                rest,
+            };
+        };
+        let end = hier.find(['/', '?', '#']).unwrap_or(hier.len());
+        let (authority, path) = hier.split_at(end);
+        let (userinfo, hostport) = authority
+            .rsplit_once('@')
+            .map_or((None, authority), |(u, h)| (Some(u), h));
+        let (host, port) = match hostport.rfind(':') {
+            Some(i) if !hostport[i + 1..].contains(']') => {
+                (&hostport[..i], Some(&hostport[i + 1..]))
+            }
+            _ => (hostport, None),
+        };
+        let is = |s: &str| scheme.eq_ignore_ascii_case(s);
+        let default_port = |port: &str| {
+            port.is_empty()
+                || (port == "80" && (is("http") || is("ws")))
+                || (port == "443" && (is("https") || is("wss")))
+                || (port == "21" && is("ftp"))
+        };
+        let userinfo = userinfo.filter(|u| !u.is_empty());
+        let port = port.filter(|p| !default_port(p));
+        // `scheme:///` keeps its slash, since `scheme://` would end with `//`
+        let empty = userinfo.is_none() && host.is_empty() && port.is_none();
+        let rest = strip_trailing_slash(path, !empty || path != "/");
+        let len = userinfo.map_or(0, |u| u.len() + 1)
+            + host.len()
+            + port.map_or(0, |p| p.len() + 1)
+            + rest.len();
+        Self {
+            scheme: Some(scheme),
+            authority: Some((userinfo, host, port)),
+            rest,
+            stripped: i + 3 + len < s.len(),
+        }
+    }
+
+    /// Whether the string this was split from is normalized already
+    fn is_normal(&self) -> bool {
+        let lower = |s: &str| !s.bytes().any(|b| b.is_ascii_uppercase());
+        !self.stripped
+            && self.scheme.is_none_or(lower)
+            && self.authority.is_none_or(|(_, host, _)| lower(host))
+    }
+}
+impl PartialEq for Normalized<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        let eq_ci = |a: &str, b: &str| a.eq_ignore_ascii_case(b);
+        self.rest == other.rest
+            && match (self.scheme, other.scheme) {
+                (Some(a), Some(b)) => eq_ci(a, b),
+                (None, None) => true,
+                _ => false,
+            }
+            && match (self.authority, other.authority) {
+                (Some((ua, ha, pa)), Some((ub, hb, pb))) => ua == ub && pa == pb && eq_ci(ha, hb),
+                (None, None) => true,
+                _ => false,
+            }
+    }
+}
+
+/// Removes a single trailing slash from `s` (if `allowed` and it does not end with `//`)
+fn strip_trailing_slash(s: &str, allowed: bool) -> &str {
+    match s.strip_suffix('/') {
+        Some(stripped) if allowed && !stripped.ends_with('/') => stripped,
+        _ => s,
+    }
+}
+
+/// The components of a URI reference, as in
+/// [RFC 3986, appendix B](https://www.rfc-editor.org/rfc/rfc3986#appendix-B)
+struct UriParts<'s> {
+    scheme: Option<&'s str>,
+    authority: Option<&'s str>,
+    path: &'s str,
+    query: Option<&'s str>,
+    fragment: Option<&'s str>,
+}
+impl<'s> UriParts<'s> {
+    fn split(s: &'s str) -> Self {
+        let (s, fragment) = s.split_once('#').map_or((s, None), |(s, f)| (s, Some(f)));
+        let (s, query) = s.split_once('?').map_or((s, None), |(s, q)| (s, Some(q)));
+        let (scheme, s) = scheme_len(s).map_or((None, s), |i| (Some(&s[..i]), &s[i + 1..]));
+        let (authority, path) = s.strip_prefix("//").map_or((None, s), |s| {
+            let end = s.find('/').unwrap_or(s.len());
+            (Some(&s[..end]), &s[end..])
+        });
+        Self {
+            scheme,
+            authority,
+            path,
+            query,
+            fragment,
+        }
+    }
+}
+
+/// Removes the `.` and `..` segments of `path`, as in
+/// [RFC 3986, section 5.2.4](https://www.rfc-editor.org/rfc/rfc3986#section-5.2.4)
+fn remove_dot_segments(path: &str) -> String {
+    let mut out: Vec<&str> = Vec::new();
+    let mut input = path;
+    while !input.is_empty() {
+        if let Some(rest) = input
+            .strip_prefix("../")
+            .or_else(|| input.strip_prefix("./"))
+        {
+            input = rest;
+        } else if input.starts_with("/./") {
+            input = &input[2..];
+        } else if input == "/." {
+            input = "/";
+        } else if input.starts_with("/../") || input == "/.." {
+            input = if input == "/.." { "/" } else { &input[3..] };
+            out.pop();
+        } else if input == "." || input == ".." {
+            input = "";
+        } else {
+            let end = input[1..].find('/').map_or(input.len(), |i| i + 1);
+            out.push(&input[..end]);
+            input = &input[end..];
+        }
+    }
+    out.concat()
+}
+
+/// Length of the scheme of `s`, if it is an absolute URI
+fn scheme_len(s: &str) -> Option<usize> {
+    let before = s.find(['/', '?', '#']).map_or(s, |i| &s[..i]);
+    before.find(':').filter(|i| *i > 0)
+}
+
+/// `a == b` modulo [normalization](CdBase::normalize)
+#[inline]
+pub fn eq_normalized(a: &str, b: &str) -> bool {
+    a == b || Normalized::split(a) == Normalized::split(b)
+}
+
+/// The cdbase in effect given an explicitly declared one and the inherited one. Keeps the
+/// explicit one even if both are equal modulo normalization, so that reading and writing
+/// an object preserves its spelling.
+#[cfg(feature = "serde")]
+#[inline]
+pub fn inherit<'a>(explicit: Option<&'a str>, inherited: &'a str) -> &'a str {
+    explicit.unwrap_or(inherited)
+}
+
+impl std::ops::Deref for CdBase<'_> {
+    type Target = str;
+    #[inline]
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+impl AsRef<str> for CdBase<'_> {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+impl std::fmt::Display for CdBase<'_> {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+impl<'a> From<&'a str> for CdBase<'a> {
+    #[inline]
+    fn from(value: &'a str) -> Self {
+        Self(Cow::Borrowed(value))
+    }
+}
+impl From<String> for CdBase<'_> {
+    #[inline]
+    fn from(value: String) -> Self {
+        Self(Cow::Owned(value))
+    }
+}
+impl<'a> From<Cow<'a, str>> for CdBase<'a> {
+    #[inline]
+    fn from(value: Cow<'a, str>) -> Self {
+        Self(value)
+    }
+}
+impl<'a> From<CdBase<'a>> for Cow<'a, str> {
+    #[inline]
+    fn from(value: CdBase<'a>) -> Self {
+        value.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert!(CdBase::parse("http://www.openmath.org/cd").is_ok());
+        assert!(CdBase::parse("urn:isbn:0451450523").is_ok());
+        assert_eq!(CdBase::parse(""), Err(CdBaseError::Empty));
+        assert!(matches!(
+            CdBase::parse("openmath.org/cd"),
+            Err(CdBaseError::Relative(_))
+        ));
+        assert!(matches!(
+            CdBase::parse("1http://foo"),
+            Err(CdBaseError::InvalidScheme(_))
+        ));
+        assert!(matches!(
+            CdBase::parse("http://foo bar"),
+            Err(CdBaseError::InvalidChar(_, ' '))
+        ));
+        let rel = CdBase::parse_relative("../cds").expect("is valid");
+        assert!(!rel.is_absolute());
+    }
+
+    #[test]
+    fn join() {
+        // the examples of RFC 3986, section 5.4
+        let base = CdBase::new_unchecked("http://a/b/c/d;p?q");
+        for (r, t) in [
+            ("g:h", "g:h"),
+            ("g", "http://a/b/c/g"),
+            ("./g", "http://a/b/c/g"),
+            ("g/", "http://a/b/c/g/"),
+            ("/g", "http://a/g"),
+            ("//g", "http://g"),
+            ("?y", "http://a/b/c/d;p?y"),
+            ("g?y", "http://a/b/c/g?y"),
+            ("#s", "http://a/b/c/d;p?q#s"),
+            ("g#s", "http://a/b/c/g#s"),
+            (";x", "http://a/b/c/;x"),
+            ("", "http://a/b/c/d;p?q"),
+            (".", "http://a/b/c/"),
+            ("./", "http://a/b/c/"),
+            ("..", "http://a/b/"),
+            ("../", "http://a/b/"),
+            ("../g", "http://a/b/g"),
+            ("../..", "http://a/"),
+            ("../../g", "http://a/g"),
+            ("../../../g", "http://a/g"),
+            ("/./g", "http://a/g"),
+            ("/../g", "http://a/g"),
+            ("g.", "http://a/b/c/g."),
+            ("..g", "http://a/b/c/..g"),
+            ("./../g", "http://a/b/g"),
+            ("g/./h", "http://a/b/c/g/h"),
+            ("g/../h", "http://a/b/c/h"),
+        ] {
+            assert_eq!(base.join(r).as_str(), t, "{r}");
+        }
+        assert_eq!(
+            CdBase::new_unchecked("http://a").join("cds").as_str(),
+            "http://a/cds"
+        );
+        assert_eq!(
+            CdBase::new_unchecked("docs/index.xml")
+                .join("../cds")
+                .as_str(),
+            "cds"
+        );
+    }
+
+    #[test]
+    fn normalize() {
+        for (s, n) in [
+            ("http://www.openmath.org/cd", "http://www.openmath.org/cd"),
+            ("HTTP://WWW.OPENMATH.ORG/CD/", "http://www.openmath.org/CD"),
+            (
+                "http://www.openmath.org:80/cd",
+                "http://www.openmath.org/cd",
+            ),
+            ("https://example.org:443/", "https://example.org"),
+            ("https://example.org:8443/", "https://example.org:8443"),
+            ("http://User@Example.org/x", "http://User@example.org/x"),
+            ("http://[::1]:80/x", "http://[::1]/x"),
+            ("file:///", "file:///"),
+            ("cds/", "cds"),
+        ] {
+            assert_eq!(CdBase::new_unchecked(s).normalize().as_str(), n, "{s}");
+        }
+        let n = CdBase::OPENMATH;
+        assert!(matches!(n.normalize().into_inner(), Cow::Borrowed(_)));
+        assert!(eq_normalized("http://www.openmath.org/cd/", crate::CD_BASE));
+        assert!(!eq_normalized("http://www.openmath.org/CD", crate::CD_BASE));
+    }
+
+    #[test]
+    fn eq_normalized_agrees_with_normalize() {
+        let all = [
+            "http://www.openmath.org/cd",
+            "HTTP://WWW.OPENMATH.ORG/cd/",
+            "http://www.openmath.org:80/cd",
+            "http://www.openmath.org:/cd",
+            "http://@www.openmath.org/cd",
+            "http://www.openmath.org:8080/cd",
+            "https://www.openmath.org:80/cd",
+            "http://user@www.openmath.org/cd",
+            "http://User@www.openmath.org/cd",
+            "http://[::1]:80/x",
+            "http://[::1]/x/",
+            "file:///",
+            "file://",
+            "FILE:///",
+            "http://a//",
+            "http://a/",
+            "http://a",
+            "urn:x/",
+            "URN:x",
+            "x:/",
+            "x:",
+            "cds/",
+            "cds",
+            "/",
+            "//",
+        ];
+        for a in all {
+            assert!(
+                Normalized::split(a).is_normal()
+                    == (CdBase::new_unchecked(a).normalize().as_str() == a),
+                "{a}"
+            );
+            for b in all {
+                let na = CdBase::new_unchecked(a);
+                let nb = CdBase::new_unchecked(b);
+                assert_eq!(
+                    eq_normalized(a, b),
+                    na.normalize() == nb.normalize(),
+                    "{a} vs {b}"
+                );
+            }
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn inherited() {
+        assert_eq!(
+            inherit(Some("http://WWW.openmath.org/cd"), crate::CD_BASE),
+            "http://WWW.openmath.org/cd"
+        );
+        assert_eq!(inherit(Some("http://foo"), crate::CD_BASE), "http://foo");
+        assert_eq!(inherit(None, crate::CD_BASE), crate::CD_BASE);
+    }
+
+    #[cfg(all(feature = "xml-read", feature = "xml-write"))]
+    #[test]
+    fn normalization_aware_serialization() {
+        use crate::{
+            OMDeserializable, OMSerializable,
+            ser::{AsOMS, Uri},
+        };
+        let uri = Uri {
+            cdbase: Some(crate::CdBase::new_unchecked("HTTP://www.openmath.org/cd/")),
+            cd: "arith1",
+            name: "plus",
+        };
+        assert_eq!(
+            uri.as_oms().xml(false).to_string(),
+            r#"<OMS cd="arith1" name="plus"/>"#
+        );
+        // explicit cdbases are kept as written, even if equivalent to the inherited one
+        let om = crate::OpenMath::from_openmath_xml(
+            r#"<OMA cdbase="http://example.org/cd"><OMS cd="arith1" name="plus"/><OMS cdbase="HTTP://Example.org:80/cd/" cd="arith1" name="plus"/></OMA>"#,
+        )
+        .expect("is valid");
+        let crate::OpenMath::OMA {
+            applicant,
+            arguments,
+            ..
+        } = om
+        else {
+            panic!("not an OMA")
+        };
+        assert!(matches!(
+            *applicant,
+            crate::OpenMath::OMS { cdbase: Some(c), .. } if c == "http://example.org/cd"
+        ));
+        assert!(matches!(
+            &arguments[0],
+            crate::OpenMath::OMS { cdbase: Some(c), .. } if c == "HTTP://Example.org:80/cd/"
+        ));
+    }
+}
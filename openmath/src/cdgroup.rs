@@ -0,0 +1,249 @@
+//! Content dictionary groups; see [`CdGroup`]
+
+use crate::OpenMath;
+
+/// A member of a [`CdGroup`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CdGroupMember {
+    /// The cdbase of the content dictionary; [`CD_BASE`](crate::CD_BASE) unless given by a
+    /// `<CDBase>` in the member (or the group)
+    pub cdbase: String,
+    /// The name of the content dictionary
+    pub cd: String,
+    /// The version of the content dictionary, if given
+    pub version: Option<String>,
+}
+
+/** A group of content dictionaries, as defined by the
+<span style="font-variant:small-caps;">OpenMath</span> standard (section 4.4); e.g. to check
+which symbols of a document are covered by it (see [`OpenMath::resolve_against`]).
+
+With the `xml-read` feature, groups can be read from the `.cdg` XML format via
+[`from_cdg`](Self::from_cdg).
+
+# Examples
+```
+use openmath::{CdGroup, CdGroupMember, OpenMath};
+
+let group = CdGroup {
+    name: "arith".to_string(),
+    version: None,
+    members: vec![CdGroupMember {
+        cdbase: openmath::CD_BASE.to_string(),
+        cd: "arith1".to_string(),
+        version: Some("3".to_string()),
+    }],
+};
+let om = OpenMath::from_sexpr("(oma (oms arith1 plus) (omi 1) (oma (oms transc1 sin) (omv x)))")
+    .expect("is valid");
+let unresolved = om.resolve_against(&group);
+assert_eq!(unresolved.len(), 1);
+assert_eq!(unresolved[0].to_string(), "http://www.openmath.org/cd?transc1#sin");
+```
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct CdGroup {
+    /// The name of the group (`<CDGroupName>`)
+    pub name: String,
+    /// The version of the group (`<CDGroupVersion>`), if given
+    pub version: Option<String>,
+    /// The content dictionaries in the group, in document order
+    pub members: Vec<CdGroupMember>,
+}
+
+impl CdGroup {
+    /// Whether the content dictionary `cd` at `cdbase` is a member of this group; cdbases
+    /// are compared [normalized](crate::CdBase::normalize)
+    #[must_use]
+    pub fn contains(&self, cdbase: &str, cd: &str) -> bool {
+        self.members
+            .iter()
+            .any(|m| m.cd == cd && crate::cdbase::eq_normalized(&m.cdbase, cdbase))
+    }
+
+    /** Reads a group in the `.cdg` XML format: a `<CDGroup>` with a `<CDGroupName>`, an
+    optional `<CDGroupVersion>` and `<CDGroupMember>`s, each with a `<CDName>` and an
+    optional `<CDVersion>`. Other elements (e.g. `<CDGroupURL>` or `<CDComment>`) are
+    ignored. As an extension, a `<CDBase>` in a member (or in the group, for all members
+    without one) sets the cdbase of the member.
+
+    # Errors
+    if `cdg` is not well-formed XML, or if the group or one of its members has no name.
+    */
+    #[cfg(feature = "xml-read")]
+    pub fn from_cdg(cdg: &str) -> Result<Self, CdGroupError> {
+        use quick_xml::events::Event;
+        let mut reader = quick_xml::Reader::from_str(cdg);
+        let mut ret = Self::default();
+        let (mut name, mut cdbase) = (None, None);
+        // (cdbase, cd, version) of the member being read
+        let mut member: Option<[Option<String>; 3]> = None;
+        let mut current: Option<Vec<u8>> = None;
+        loop {
+            match reader.read_event()? {
+                Event::Start(s) if s.local_name().as_ref() == b"CDGroupMember" => {
+                    member = Some(Default::default());
+                }
+                Event::Start(s) => current = Some(s.local_name().as_ref().to_vec()),
+                Event::End(e) if e.local_name().as_ref() == b"CDGroupMember" => {
+                    let Some([cdbase, cd, version]) = member.take() else {
+                        continue;
+                    };
+                    ret.members.push(CdGroupMember {
+                        cdbase: cdbase.unwrap_or_default(),
+                        cd: cd.ok_or(CdGroupError::MissingName("CDName"))?,
+                        version,
+                    });
+                }
+                Event::End(_) => current = None,
+                Event::Text(t) => {
+                    let slot = match (current.as_deref(), &mut member) {
+                        (Some(b"CDBase"), Some(m)) => &mut m[0],
+                        (Some(b"CDName"), Some(m)) => &mut m[1],
+                        (Some(b"CDVersion"), Some(m)) => &mut m[2],
+                        (Some(b"CDGroupName"), None) => &mut name,
+                        (Some(b"CDGroupVersion"), None) => &mut ret.version,
+                        (Some(b"CDBase"), None) => &mut cdbase,
+                        _ => continue,
+                    };
+                    *slot = Some(t.decode()?.trim().to_string());
+                }
+                Event::Eof => break,
+                _ => (),
+            }
+        }
+        ret.name = name.ok_or(CdGroupError::MissingName("CDGroupName"))?;
+        let cdbase = cdbase.unwrap_or_else(|| crate::CD_BASE.to_string());
+        for m in &mut ret.members {
+            if m.cdbase.is_empty() {
+                m.cdbase.clone_from(&cdbase);
+            }
+        }
+        Ok(ret)
+    }
+}
+
+/// Errors when reading a [`CdGroup`]; see [`CdGroup::from_cdg`]
+#[cfg(feature = "xml-read")]
+#[derive(Debug, thiserror::Error)]
+pub enum CdGroupError {
+    #[error("invalid content dictionary group: {0}")]
+    Xml(#[from] quick_xml::Error),
+    #[error("invalid content dictionary group: {0}")]
+    Encoding(#[from] quick_xml::encoding::EncodingError),
+    #[error("content dictionary group without {0}")]
+    MissingName(&'static str),
+}
+
+/// A symbol not covered by a [`CdGroup`]; see [`OpenMath::resolve_against`]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct UnresolvedSymbol {
+    /// The (effective, [normalized](crate::CdBase::normalize)) cdbase of the symbol
+    pub cdbase: String,
+    /// The content dictionary of the symbol
+    pub cd: String,
+    /// The name of the symbol
+    pub name: String,
+}
+impl std::fmt::Display for UnresolvedSymbol {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}?{}#{}", self.cdbase, self.cd, self.name)
+    }
+}
+
+impl OpenMath<'_> {
+    /// The symbols in this object (including [OME](crate::OMKind::OME) symbols and
+    /// attribute keys) whose content dictionary is not a member of `group`, sorted and
+    /// without duplicates; see [`analyze`](crate::analyze) for how cdbases are resolved.
+    #[must_use]
+    pub fn resolve_against(&self, group: &CdGroup) -> Vec<UnresolvedSymbol> {
+        crate::analyze(self)
+            .symbols
+            .into_keys()
+            .filter_map(|uri| {
+                // cds and names contain neither `?` nor `#`
+                let (rest, name) = uri.rsplit_once('#')?;
+                let (cdbase, cd) = rest.rsplit_once('?')?;
+                (!group.contains(cdbase, cd)).then(|| UnresolvedSymbol {
+                    cdbase: cdbase.to_string(),
+                    cd: cd.to_string(),
+                    name: name.to_string(),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(all(test, feature = "xml-read"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cdg() {
+        const CDG: &str = r#"<CDGroup xmlns="http://www.openmath.org/OpenMathCDG">
+          <CDGroupName>mine</CDGroupName>
+          <CDGroupVersion>2</CDGroupVersion>
+          <CDGroupURL>http://example.org/mine.cdg</CDGroupURL>
+          <CDGroupDescription>Some CDs</CDGroupDescription>
+          <CDGroupMember>
+            <CDComment>arithmetic</CDComment>
+            <CDName>arith1</CDName>
+            <CDVersion>3</CDVersion>
+            <CDURL>http://www.openmath.org/cd/arith1.ocd</CDURL>
+          </CDGroupMember>
+          <CDGroupMember>
+            <CDName>units</CDName>
+            <CDBase>http://example.org/cd/</CDBase>
+          </CDGroupMember>
+        </CDGroup>"#;
+        let group = CdGroup::from_cdg(CDG).expect("is valid");
+        assert_eq!(group.name, "mine");
+        assert_eq!(group.version.as_deref(), Some("2"));
+        assert_eq!(
+            group.members,
+            [
+                CdGroupMember {
+                    cdbase: crate::CD_BASE.to_string(),
+                    cd: "arith1".to_string(),
+                    version: Some("3".to_string()),
+                },
+                CdGroupMember {
+                    cdbase: "http://example.org/cd/".to_string(),
+                    cd: "units".to_string(),
+                    version: None,
+                },
+            ]
+        );
+
+        let om = OpenMath::from_sexpr(
+            "(omattr (((oms meta note) (omstr \"n\"))) (oma (oms arith1 times) \
+             (cdbase \"http://example.org/cd\" (oms units metre)) \
+             (cdbase \"http://other.org\" (oms units metre)) (oms arith1 plus) (oms meta note)))",
+        )
+        .expect("is valid");
+        let unresolved = om
+            .resolve_against(&group)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            unresolved,
+            [
+                "http://other.org?units#metre",
+                "http://www.openmath.org/cd?meta#note"
+            ]
+        );
+
+        assert!(matches!(
+            CdGroup::from_cdg("<CDGroup><CDGroupMember/></CDGroup>"),
+            Err(CdGroupError::MissingName("CDGroupName"))
+        ));
+        assert!(matches!(
+            CdGroup::from_cdg(
+                "<CDGroup><CDGroupName>g</CDGroupName><CDGroupMember></CDGroupMember></CDGroup>"
+            ),
+            Err(CdGroupError::MissingName("CDName"))
+        ));
+    }
+}
@@ -0,0 +1,347 @@
+//! Accessors for the contents of objects of a known kind, and conversions of leaf objects
+//! into Rust values; see [`OpenMath::as_i64`] and [`ConversionError`]
+
+use crate::{Int, OMKind, OpenMath};
+
+/** Errors when converting an [`OpenMath`] into a Rust value via [`TryFrom`]
+
+# Examples
+```rust
+use openmath::{ConversionError, OMKind, OpenMath};
+
+let om = OpenMath::from_sexpr(r#"(omstr "42")"#).expect("is valid");
+assert_eq!(
+    i64::try_from(om),
+    Err(ConversionError::Kind { expected: OMKind::OMI, found: OMKind::OMSTR })
+);
+let om = OpenMath::from_sexpr("(omi 12345678901234567890)").expect("is valid");
+assert_eq!(
+    i64::try_from(om).expect_err("is too large").to_string(),
+    "integer 12345678901234567890 out of range"
+);
+```
+*/
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ConversionError {
+    /// The object is not of the kind the target type is converted from
+    #[error("expected {expected}, found {found}")]
+    Kind { expected: OMKind, found: OMKind },
+    /// The [`OMI`](OpenMath::OMI) does not fit into the target type
+    #[error("integer {0} out of range")]
+    OutOfRange(Int<'static>),
+}
+
+/// Accessors for the contents of objects of a known kind. They ignore
+/// [`attributes`](OpenMath::attributes), as attributions annotate an object without
+/// changing what it is: an attributed [`OMI`](OpenMath::OMI) is still an integer.
+impl OpenMath<'_> {
+    /** The value of an [`OMI`](Self::OMI), if it fits into an [`i64`]
+
+    # Examples
+    ```rust
+    use openmath::OpenMath;
+
+    let om = OpenMath::from_sexpr("(omi -42)").expect("is valid");
+    assert_eq!(om.as_i64(), Some(-42));
+    let om = OpenMath::from_sexpr("(omi 12345678901234567890)").expect("is valid");
+    assert_eq!(om.as_i64(), None);
+    ```
+    */
+    #[must_use]
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::OMI { int, .. } => int.is_i128().and_then(|i| i64::try_from(i).ok()),
+            _ => None,
+        }
+    }
+
+    /** The value of an [`OMF`](Self::OMF). Integers are *not* converted, not even those that
+    are exactly representable; use [`Int::to_f64_lossy`] on [`OMI`](Self::OMI)s for that.
+
+    # Examples
+    ```rust
+    use openmath::OpenMath;
+
+    let om = OpenMath::from_sexpr("(omf 1.5)").expect("is valid");
+    assert_eq!(om.as_f64(), Some(1.5));
+    let om = OpenMath::from_sexpr("(omi 1)").expect("is valid");
+    assert_eq!(om.as_f64(), None);
+    ```
+    */
+    #[must_use]
+    pub const fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::OMF { float, .. } => Some(float.0),
+            _ => None,
+        }
+    }
+
+    /// The string of an [`OMSTR`](Self::OMSTR); not the name of an [`OMV`](Self::OMV).
+    #[must_use]
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::OMSTR { string, .. } => Some(string),
+            _ => None,
+        }
+    }
+
+    /** The content dictionary, name and (explicit or inherited) cdbase of an
+    [`OMS`](Self::OMS); unlike [`symbol_ref`](Self::symbol_ref), `None` for the error
+    symbol of an [`OME`](Self::OME).
+
+    # Examples
+    ```rust
+    use openmath::{CD_BASE, OpenMath};
+
+    // inherited from the top level
+    let om = OpenMath::from_sexpr("(oms arith1 plus)").expect("is valid");
+    assert_eq!(om.as_symbol(), Some(("arith1", "plus", Some(CD_BASE))));
+    ```
+    */
+    #[must_use]
+    pub fn as_symbol(&self) -> Option<(&str, &str, Option<&str>)> {
+        match self {
+            Self::OMS {
+                cd, name, cdbase, ..
+            } => Some((cd, name, cdbase.as_deref())),
+            _ => None,
+        }
+    }
+
+    /** The applicant and arguments of an [`OMA`](Self::OMA); its own attributes are
+    dropped (see [`oma_parts_mut`](Self::oma_parts_mut) to keep them).
+
+    # Errors
+    `self`, if it is not an [`OMA`](Self::OMA)
+
+    # Examples
+    ```rust
+    use openmath::OpenMath;
+
+    let om = OpenMath::from_sexpr("(oma (oms arith1 plus) (omi 1) (omi 2))").expect("is valid");
+    let (applicant, arguments) = om.try_into_arguments().expect("is an OMA");
+    assert_eq!(applicant.as_symbol().map(|(cd, name, _)| (cd, name)), Some(("arith1", "plus")));
+    assert_eq!(arguments.iter().map(OpenMath::as_i64).collect::<Vec<_>>(), [Some(1), Some(2)]);
+    ```
+    */
+    pub fn try_into_arguments(self) -> Result<(Self, Vec<Self>), Self> {
+        match self {
+            Self::OMA {
+                applicant,
+                arguments,
+                ..
+            } => Ok((*applicant, arguments)),
+            _ => Err(self),
+        }
+    }
+
+    /// The [`ConversionError::Kind`] for converting this into a value of kind `expected`
+    const fn kind_error(&self, expected: OMKind) -> ConversionError {
+        ConversionError::Kind {
+            expected,
+            found: self.kind(),
+        }
+    }
+}
+
+/// The value of an [`OMI`](OpenMath::OMI), regardless of its attributes; see
+/// [`OpenMath::as_i64`]
+impl TryFrom<OpenMath<'_>> for i64 {
+    type Error = ConversionError;
+    fn try_from(om: OpenMath<'_>) -> Result<Self, Self::Error> {
+        match om {
+            OpenMath::OMI { int, .. } => int
+                .is_i128()
+                .and_then(|i| Self::try_from(i).ok())
+                .ok_or_else(|| ConversionError::OutOfRange(int.into_owned())),
+            _ => Err(om.kind_error(OMKind::OMI)),
+        }
+    }
+}
+
+/// The value of an [`OMF`](OpenMath::OMF), regardless of its attributes; see
+/// [`OpenMath::as_f64`]
+impl TryFrom<OpenMath<'_>> for f64 {
+    type Error = ConversionError;
+    #[inline]
+    fn try_from(om: OpenMath<'_>) -> Result<Self, Self::Error> {
+        om.as_f64().ok_or_else(|| om.kind_error(OMKind::OMF))
+    }
+}
+
+/// The string of an [`OMSTR`](OpenMath::OMSTR), regardless of its attributes
+impl TryFrom<OpenMath<'_>> for String {
+    type Error = ConversionError;
+    fn try_from(om: OpenMath<'_>) -> Result<Self, Self::Error> {
+        match om {
+            OpenMath::OMSTR { string, .. } => Ok(string.into_owned()),
+            _ => Err(om.kind_error(OMKind::OMSTR)),
+        }
+    }
+}
+
+/// The bytes of an [`OMB`](OpenMath::OMB), regardless of its attributes
+impl TryFrom<OpenMath<'_>> for Vec<u8> {
+    type Error = ConversionError;
+    fn try_from(om: OpenMath<'_>) -> Result<Self, Self::Error> {
+        match om {
+            OpenMath::OMB { bytes, .. } => Ok(bytes.into_owned()),
+            _ => Err(om.kind_error(OMKind::OMB)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One object of every kind, attributed or not
+    fn all(attributed: bool) -> Vec<OpenMath<'static>> {
+        [
+            "(omi 42)",
+            "(omf 1.5)",
+            r#"(omstr "hi")"#,
+            r#"(omb "AAEC")"#,
+            "(omv x)",
+            r#"(cdbase "http://example.org" (oms my f))"#,
+            "(oma (oms arith1 plus) (omi 1) (omv x))",
+            "(ombind (oms fns1 lambda) (bvar x) (omv x))",
+            r#"(ome (oms moreerrors unexpected) (omi 1) (omforeign "…"))"#,
+        ]
+        .into_iter()
+        .map(|s| {
+            let s = if attributed {
+                format!(r#"(omattr (((oms meta note) (omstr "note"))) {s})"#)
+            } else {
+                s.to_string()
+            };
+            OpenMath::from_sexpr(&s).expect("is valid")
+        })
+        .collect()
+    }
+
+    #[test]
+    fn accessors() {
+        for attributed in [false, true] {
+            let all = all(attributed);
+            assert_eq!(
+                all.iter().map(OpenMath::kind).collect::<Vec<_>>(),
+                &OMKind::ALL[..9]
+            );
+            for om in &all {
+                assert_eq!(om.attributes().len(), usize::from(attributed));
+                let kind = om.kind();
+                assert_eq!(om.as_i64(), (kind == OMKind::OMI).then_some(42), "{om:?}");
+                assert_eq!(om.as_f64(), (kind == OMKind::OMF).then_some(1.5), "{om:?}");
+                assert_eq!(
+                    om.as_str(),
+                    (kind == OMKind::OMSTR).then_some("hi"),
+                    "{om:?}"
+                );
+                assert_eq!(
+                    om.as_symbol(),
+                    (kind == OMKind::OMS).then_some(("my", "f", Some("http://example.org"))),
+                    "{om:?}"
+                );
+                match om.clone().try_into_arguments() {
+                    Ok((applicant, arguments)) => {
+                        assert_eq!(kind, OMKind::OMA);
+                        assert_eq!(
+                            applicant.as_symbol(),
+                            Some(("arith1", "plus", Some(crate::CD_BASE)))
+                        );
+                        assert_eq!(arguments.len(), 2);
+                        assert_eq!(arguments[0].as_i64(), Some(1));
+                    }
+                    Err(back) => {
+                        assert_ne!(kind, OMKind::OMA);
+                        assert_eq!(&back, om);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn conversions() {
+        for attributed in [false, true] {
+            for om in all(attributed) {
+                let kind = om.kind();
+                let expect = |expected| ConversionError::Kind {
+                    expected,
+                    found: kind,
+                };
+                assert_eq!(
+                    i64::try_from(om.clone()),
+                    if kind == OMKind::OMI {
+                        Ok(42)
+                    } else {
+                        Err(expect(OMKind::OMI))
+                    }
+                );
+                assert_eq!(
+                    f64::try_from(om.clone()),
+                    if kind == OMKind::OMF {
+                        Ok(1.5)
+                    } else {
+                        Err(expect(OMKind::OMF))
+                    }
+                );
+                assert_eq!(
+                    String::try_from(om.clone()),
+                    if kind == OMKind::OMSTR {
+                        Ok("hi".to_string())
+                    } else {
+                        Err(expect(OMKind::OMSTR))
+                    }
+                );
+                assert_eq!(
+                    Vec::<u8>::try_from(om),
+                    if kind == OMKind::OMB {
+                        Ok(vec![0, 1, 2])
+                    } else {
+                        Err(expect(OMKind::OMB))
+                    }
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn integer_ranges() {
+        let int = |s: &str| OpenMath::OMI {
+            int: Int::new(s).expect("is valid").into_owned(),
+            attributes: Vec::new(),
+        };
+        for (s, fits) in [
+            ("9223372036854775807", true),
+            ("-9223372036854775808", true),
+            ("9223372036854775808", false),
+            ("-9223372036854775809", false),
+            ("123456789012345678901234567890123456789012345", false),
+        ] {
+            let om = int(s);
+            assert_eq!(
+                om.as_i64().map(|i| i.to_string()),
+                fits.then(|| s.to_string())
+            );
+            assert_eq!(
+                i64::try_from(om).map_err(|e| e.to_string()),
+                if fits {
+                    Ok(s.parse().expect("fits"))
+                } else {
+                    Err(format!("integer {s} out of range"))
+                }
+            );
+        }
+        assert_eq!(int("1").as_f64(), None);
+    }
+
+    #[test]
+    fn special_floats() {
+        let om = OpenMath::from_sexpr("(omf NaN)").expect("is valid");
+        assert!(om.as_f64().is_some_and(f64::is_nan));
+        let om = OpenMath::from_sexpr("(omf -0.0)").expect("is valid");
+        assert!(f64::try_from(om).is_ok_and(|f| f == 0.0 && f.is_sign_negative()));
+    }
+}
@@ -0,0 +1,707 @@
+/*! Deserializing into a bump allocator; see [`Arena`]. */
+
+use std::borrow::Cow;
+
+use bumpalo::Bump;
+
+#[cfg(feature = "xml-read")]
+use super::{OM, OMDeserializable, Options, ReferenceError, XmlReadError};
+use crate::{Attr, BoundVariable, Int, OMKind, OMMaybeForeign, OpenMath, SymbolRef, int::I};
+
+/// An attribution pair of an [`OpenMathIn`]
+pub type AttrIn<'a> = Attr<'a, OMMaybeForeign<'a, OpenMathIn<'a>>>;
+
+/** An [`OpenMath`] object allocated in an [`Arena`]; see there.
+
+Argument, variable and attribute lists are slices in the arena, and strings borrow either
+from the input or from the arena; the [`Cow`]s of [`Int`]s, attribute keys and
+[OMFOREIGN](OMKind::OMFOREIGN)s are always [borrowed](Cow::Borrowed). Since the object borrows
+the arena, it can not be used after the arena is [reset](Arena::reset) or dropped.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub enum OpenMathIn<'a> {
+    /// See [`OpenMath::OMI`]
+    OMI {
+        int: Int<'a>,
+        attributes: &'a [AttrIn<'a>],
+    },
+    /// See [`OpenMath::OMF`]
+    OMF {
+        float: f64,
+        attributes: &'a [AttrIn<'a>],
+    },
+    /// See [`OpenMath::OMSTR`]
+    OMSTR {
+        string: &'a str,
+        attributes: &'a [AttrIn<'a>],
+    },
+    /// See [`OpenMath::OMB`]
+    OMB {
+        bytes: &'a [u8],
+        attributes: &'a [AttrIn<'a>],
+    },
+    /// See [`OpenMath::OMV`]
+    OMV {
+        name: &'a str,
+        attributes: &'a [AttrIn<'a>],
+    },
+    /// See [`OpenMath::OMS`]
+    OMS {
+        cd: &'a str,
+        name: &'a str,
+        cdbase: Option<&'a str>,
+        cdbase_inherited: bool,
+        attributes: &'a [AttrIn<'a>],
+    },
+    /// See [`OpenMath::OMA`]
+    OMA {
+        applicant: &'a Self,
+        arguments: &'a [Self],
+        attributes: &'a [AttrIn<'a>],
+    },
+    /// See [`OpenMath::OME`]
+    OME {
+        cd: &'a str,
+        name: &'a str,
+        cdbase: Option<&'a str>,
+        arguments: &'a [OMMaybeForeign<'a, Self>],
+        attributes: &'a [AttrIn<'a>],
+    },
+    /// See [`OpenMath::OMBIND`]
+    OMBIND {
+        binder: &'a Self,
+        variables: &'a [BoundVariableIn<'a>],
+        object: &'a Self,
+        attributes: &'a [AttrIn<'a>],
+    },
+}
+
+/// A bound variable in an [`OpenMathIn::OMBIND`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoundVariableIn<'a> {
+    /// the name of the variable
+    pub name: &'a str,
+    /// (optional) attributes of the variable
+    pub attributes: &'a [AttrIn<'a>],
+}
+
+impl<'a> OpenMathIn<'a> {
+    /// The kind of this object
+    #[must_use]
+    pub const fn kind(&self) -> OMKind {
+        match self {
+            Self::OMI { .. } => OMKind::OMI,
+            Self::OMF { .. } => OMKind::OMF,
+            Self::OMSTR { .. } => OMKind::OMSTR,
+            Self::OMB { .. } => OMKind::OMB,
+            Self::OMV { .. } => OMKind::OMV,
+            Self::OMS { .. } => OMKind::OMS,
+            Self::OMA { .. } => OMKind::OMA,
+            Self::OME { .. } => OMKind::OME,
+            Self::OMBIND { .. } => OMKind::OMBIND,
+        }
+    }
+
+    /// The attributes of this object
+    #[must_use]
+    pub const fn attributes(&self) -> &'a [AttrIn<'a>] {
+        match self {
+            Self::OMI { attributes, .. }
+            | Self::OMF { attributes, .. }
+            | Self::OMSTR { attributes, .. }
+            | Self::OMB { attributes, .. }
+            | Self::OMV { attributes, .. }
+            | Self::OMS { attributes, .. }
+            | Self::OMA { attributes, .. }
+            | Self::OME { attributes, .. }
+            | Self::OMBIND { attributes, .. } => attributes,
+        }
+    }
+
+    /// Converts this into an [`OpenMath`] that borrows all strings from the arena (or the
+    /// input), e.g. to serialize it.
+    #[must_use]
+    pub fn to_openmath(&self) -> OpenMath<'a> {
+        fn attrs<'a>(attributes: &'a [AttrIn<'a>]) -> Vec<crate::de::OMAttr<'a, OpenMath<'a>>> {
+            attributes
+                .iter()
+                .map(|a| Attr::new(a.key.clone(), foreign(&a.value)))
+                .collect()
+        }
+        fn foreign<'a>(o: &OMMaybeForeign<'a, OpenMathIn<'a>>) -> OMMaybeForeign<'a, OpenMath<'a>> {
+            match o {
+                OMMaybeForeign::OM(o) => OMMaybeForeign::OM(o.to_openmath()),
+                OMMaybeForeign::Foreign { encoding, value } => OMMaybeForeign::Foreign {
+                    encoding: encoding.clone(),
+                    value: value.clone(),
+                },
+            }
+        }
+        match *self {
+            Self::OMI {
+                ref int,
+                attributes,
+            } => OpenMath::OMI {
+                int: int.clone(),
+                attributes: attrs(attributes),
+            },
+            Self::OMF { float, attributes } => OpenMath::OMF {
+                float: float.into(),
+                attributes: attrs(attributes),
+            },
+            Self::OMSTR { string, attributes } => OpenMath::OMSTR {
+                string: Cow::Borrowed(string),
+                attributes: attrs(attributes),
+            },
+            Self::OMB { bytes, attributes } => OpenMath::OMB {
+                bytes: Cow::Borrowed(bytes),
+                attributes: attrs(attributes),
+            },
+            Self::OMV { name, attributes } => OpenMath::OMV {
+                name: Cow::Borrowed(name),
+                attributes: attrs(attributes),
+            },
+            Self::OMS {
+                cd,
+                name,
+                cdbase,
+                cdbase_inherited,
+                attributes,
+            } => OpenMath::OMS {
+                cd: Cow::Borrowed(cd),
+                name: Cow::Borrowed(name),
+                cdbase: cdbase.map(Cow::Borrowed),
+                cdbase_inherited,
+                attributes: attrs(attributes),
+            },
+            Self::OMA {
+                applicant,
+                arguments,
+                attributes,
+            } => OpenMath::OMA {
+                applicant: Box::new(applicant.to_openmath()),
+                arguments: arguments.iter().map(Self::to_openmath).collect(),
+                attributes: attrs(attributes),
+            },
+            Self::OME {
+                cd,
+                name,
+                cdbase,
+                arguments,
+                attributes,
+            } => OpenMath::OME {
+                cd: Cow::Borrowed(cd),
+                name: Cow::Borrowed(name),
+                cdbase: cdbase.map(Cow::Borrowed),
+                arguments: arguments.iter().map(foreign).collect(),
+                attributes: attrs(attributes),
+            },
+            Self::OMBIND {
+                binder,
+                variables,
+                object,
+                attributes,
+            } => OpenMath::OMBIND {
+                binder: Box::new(binder.to_openmath()),
+                variables: variables
+                    .iter()
+                    .map(|v| BoundVariable {
+                        name: Cow::Borrowed(v.name),
+                        attributes: attrs(v.attributes),
+                    })
+                    .collect(),
+                object: Box::new(object.to_openmath()),
+                attributes: attrs(attributes),
+            },
+        }
+    }
+}
+
+/** A bump allocator to deserialize <span style="font-variant:small-caps;">OpenMath</span>
+objects into, as [`OpenMathIn`]s; for services that parse many small documents, where
+allocating (and freeing) every node, list and unescaped string of an [`OpenMath`]
+separately dominates.
+
+Deserializing into an arena allocates in a few large chunks instead; strings that can be
+borrowed from the input are not copied at all. [`reset`](Self::reset) frees all objects at
+once, but keeps the memory for the next ones. Since it takes `&mut self`, objects
+deserialized before can not be used afterwards. Nothing allocated in the arena is
+[dropped](Drop); [`OpenMathIn`]s own no memory outside of it.
+
+The XML parser itself still allocates temporaries, so the savings are moderate: in the
+`bump` benchmark, on a stream of small documents, about 20% fewer allocations and a lower
+peak heap usage than [`OpenMath`], at roughly the same throughput. Serde input is read as an
+[`OpenMath`] first and then copied (see [`deserialize`](Self::deserialize)), which only
+lowers the peak heap usage.
+
+# Examples
+```
+use openmath::{OMKind, de::{Arena, OpenMathIn}};
+
+let mut arena = Arena::new();
+for i in 1..=3 {
+    let xml = format!(r#"<OMA><OMS cd="arith1" name="plus"/><OMI>{i}</OMI><OMSTR>a &amp; b</OMSTR></OMA>"#);
+    let om = arena.from_openmath_xml(&xml).expect("is valid");
+    let OpenMathIn::OMA { arguments, .. } = om else { unreachable!() };
+    assert_eq!(arguments[1], OpenMathIn::OMSTR { string: "a & b", attributes: &[] });
+    assert_eq!(om.to_openmath().kind(), OMKind::OMA);
+    // frees `om`, which can not be used anymore
+    arena.reset();
+}
+```
+*/
+#[derive(Debug, Default)]
+pub struct Arena(Bump);
+
+impl Arena {
+    /// An empty arena
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self(Bump::new())
+    }
+
+    /// An arena that can hold `bytes` bytes before allocating further
+    #[inline]
+    #[must_use]
+    pub fn with_capacity(bytes: usize) -> Self {
+        Self(Bump::with_capacity(bytes))
+    }
+
+    /// Frees all objects in the arena, keeping (the largest chunk of) its memory
+    #[inline]
+    pub fn reset(&mut self) {
+        self.0.reset();
+    }
+
+    /// The number of bytes the arena has allocated, including unused ones
+    #[inline]
+    #[must_use]
+    pub fn allocated_bytes(&self) -> usize {
+        self.0.allocated_bytes()
+    }
+
+    /// Copies `om` into the arena
+    #[must_use]
+    pub fn alloc<'a>(&'a self, om: &OpenMath<'a>) -> OpenMathIn<'a> {
+        Alloc(&self.0).copy(om)
+    }
+
+    /** Like [`OpenMath::from_openmath_xml`](OMDeserializable::from_openmath_xml), but
+    allocates the object in this arena.
+
+    # Errors
+    see [`OpenMath::from_openmath_xml`](OMDeserializable::from_openmath_xml)
+    */
+    #[cfg(feature = "xml-read")]
+    #[inline]
+    pub fn from_openmath_xml<'a>(
+        &'a self,
+        input: &'a str,
+    ) -> Result<OpenMathIn<'a>, XmlReadError<ReferenceError>> {
+        self.from_openmath_xml_with(input, Options::new())
+    }
+
+    /** Like [`OpenMath::from_openmath_xml_with`](OMDeserializable::from_openmath_xml_with),
+    but allocates the object in this arena.
+
+    # Errors
+    see [`OpenMath::from_openmath_xml_with`](OMDeserializable::from_openmath_xml_with)
+    */
+    #[cfg(feature = "xml-read")]
+    pub fn from_openmath_xml_with<'a>(
+        &'a self,
+        input: &'a str,
+        options: Options<'_>,
+    ) -> Result<OpenMathIn<'a>, XmlReadError<ReferenceError>> {
+        let alloc = Alloc(&self.0);
+        super::from_openmath_xml_converting(input, &options, |om, cdbase| {
+            alloc.node(om, cdbase).map(InArena)
+        })
+        .map(|InArena(om)| om)
+    }
+
+    /** Deserializes an object from a serde [`Deserializer`](serde::Deserializer) (e.g.
+    JSON) like [`OMFromSerde`](crate::de::OMFromSerde), but allocates it in this arena.
+    Unlike [`from_openmath_xml`](Self::from_openmath_xml), the object is read as an
+    [`OpenMath`] first (borrowing strings from the input where possible), and then copied.
+
+    # Errors
+    if the input is invalid, or contains unresolved references
+
+    # Examples
+    ```
+    use openmath::de::{Arena, OpenMathIn};
+
+    let arena = Arena::new();
+    let json = r#"{ "kind": "OMV", "name": "x" }"#;
+    let om = arena
+        .deserialize(&mut serde_json::Deserializer::from_str(json))
+        .expect("is valid");
+    assert_eq!(om, OpenMathIn::OMV { name: "x", attributes: &[] });
+    ```
+    */
+    #[cfg(feature = "serde")]
+    pub fn deserialize<'a, D: serde::Deserializer<'a>>(
+        &'a self,
+        deserializer: D,
+    ) -> Result<OpenMathIn<'a>, D::Error> {
+        use serde::Deserialize;
+        crate::de::OMFromSerde::<OpenMath<'a>>::deserialize(deserializer)
+            .map(|om| self.alloc(&om.into_inner()))
+    }
+}
+
+/// An object allocated in an arena by [`Alloc::node`]
+#[cfg(feature = "xml-read")]
+#[derive(Debug)]
+struct InArena<'a>(OpenMathIn<'a>);
+
+#[cfg(feature = "xml-read")]
+impl<'a> OMDeserializable<'a> for InArena<'a> {
+    type Ret = Self;
+    type Err = ReferenceError;
+    fn from_openmath(_: OM<'a, Self>, _: &str) -> Result<Self, Self::Err> {
+        // there is no arena to allocate in here; `Arena::from_openmath_xml_with`, the only
+        // reader of `InArena`s, converts every node itself
+        unreachable!("InArenas are only read by Arena::from_openmath_xml_with")
+    }
+}
+
+/// Allocates (the parts of) objects in an arena
+#[derive(Clone, Copy)]
+struct Alloc<'a>(&'a Bump);
+
+impl<'a> Alloc<'a> {
+    fn str(self, s: &Cow<'a, str>) -> &'a str {
+        match s {
+            Cow::Borrowed(s) => s,
+            Cow::Owned(s) => self.0.alloc_str(s),
+        }
+    }
+
+    fn cow(self, s: &Cow<'a, str>) -> Cow<'a, str> {
+        Cow::Borrowed(self.str(s))
+    }
+
+    fn int(self, int: &Int<'a>) -> Int<'a> {
+        match &int.0 {
+            I::Stack(i) => Int(I::Stack(*i)),
+            I::Heap(digits) => Int(I::Heap(self.cow(digits))),
+        }
+    }
+
+    fn symbol(self, s: &SymbolRef<'a>) -> SymbolRef<'a> {
+        SymbolRef {
+            cdbase: s.cdbase.as_ref().map(|c| self.cow(c)),
+            cd: self.cow(&s.cd),
+            name: self.cow(&s.name),
+        }
+    }
+
+    /// `o`, with the object (if any) converted by `om`
+    fn foreign<O>(
+        self,
+        o: &OMMaybeForeign<'a, O>,
+        om: impl FnOnce(&O) -> OpenMathIn<'a>,
+    ) -> OMMaybeForeign<'a, OpenMathIn<'a>> {
+        match o {
+            OMMaybeForeign::OM(o) => OMMaybeForeign::OM(om(o)),
+            OMMaybeForeign::Foreign { encoding, value } => OMMaybeForeign::Foreign {
+                encoding: encoding.as_ref().map(|e| self.cow(e)),
+                value: self.cow(value),
+            },
+        }
+    }
+
+    #[cfg(feature = "xml-read")]
+    fn attrs(self, attrs: Vec<crate::de::OMAttr<'a, InArena<'a>>>) -> &'a [AttrIn<'a>] {
+        self.0.alloc_slice_fill_iter(
+            attrs
+                .into_iter()
+                .map(|a| Attr::new(self.symbol(&a.key), self.foreign(&a.value, |o| o.0.clone()))),
+        )
+    }
+
+    /// The node for `om`
+    #[cfg(feature = "xml-read")]
+    fn node(self, om: OM<'a, InArena<'a>>, cdbase: &str) -> Result<OpenMathIn<'a>, ReferenceError> {
+        // like in `OpenMath`
+        let in_scope = || {
+            if crate::cdbase::eq_normalized(cdbase, crate::CD_BASE) {
+                crate::CD_BASE
+            } else {
+                self.0.alloc_str(cdbase)
+            }
+        };
+        Ok(match om {
+            OM::OMI { int, attrs } => OpenMathIn::OMI {
+                int: self.int(&int),
+                attributes: self.attrs(attrs),
+            },
+            OM::OMF { float, attrs, .. } => OpenMathIn::OMF {
+                float,
+                attributes: self.attrs(attrs),
+            },
+            OM::OMSTR { string, attrs } => OpenMathIn::OMSTR {
+                string: self.str(&string),
+                attributes: self.attrs(attrs),
+            },
+            OM::OMB { bytes, attrs } => OpenMathIn::OMB {
+                bytes: match bytes {
+                    Cow::Borrowed(b) => b,
+                    Cow::Owned(b) => self.0.alloc_slice_copy(&b),
+                },
+                attributes: self.attrs(attrs),
+            },
+            OM::OMV { name, attrs } => OpenMathIn::OMV {
+                name: self.str(&name),
+                attributes: self.attrs(attrs),
+            },
+            OM::OMS {
+                cdbase: own_cdbase,
+                cd,
+                name,
+                attrs,
+            } => OpenMathIn::OMS {
+                cd: self.str(&cd),
+                name: self.str(&name),
+                cdbase_inherited: own_cdbase.is_none(),
+                cdbase: Some(own_cdbase.map_or_else(in_scope, |c| self.str(&c))),
+                attributes: self.attrs(attrs),
+            },
+            OM::OMA {
+                applicant,
+                arguments,
+                attrs,
+            } => OpenMathIn::OMA {
+                applicant: self.0.alloc(applicant.0),
+                arguments: self
+                    .0
+                    .alloc_slice_fill_iter(arguments.into_iter().map(|a| a.0)),
+                attributes: self.attrs(attrs),
+            },
+            OM::OMBIND {
+                binder,
+                variables,
+                object,
+                attrs,
+            } => OpenMathIn::OMBIND {
+                binder: self.0.alloc(binder.0),
+                variables: self.0.alloc_slice_fill_iter(variables.into_iter().map(
+                    |(name, attrs)| BoundVariableIn {
+                        name: self.str(&name),
+                        attributes: self.attrs(attrs),
+                    },
+                )),
+                object: self.0.alloc(object.0),
+                attributes: self.attrs(attrs),
+            },
+            OM::OME {
+                cdbase,
+                cd,
+                name,
+                arguments,
+                attrs,
+            } => OpenMathIn::OME {
+                cd: self.str(&cd),
+                name: self.str(&name),
+                cdbase: Some(cdbase.map_or_else(in_scope, |c| self.str(&c))),
+                arguments: self.0.alloc_slice_fill_iter(
+                    arguments
+                        .into_iter()
+                        .map(|a| self.foreign(&a, |o| o.0.clone())),
+                ),
+                attributes: self.attrs(attrs),
+            },
+            OM::OMR { href, .. } => {
+                return Err(crate::de::UnresolvedReference(href.into_owned()).into());
+            }
+        })
+    }
+
+    /// Copies `om` (and the strings it owns) into the arena
+    fn copy(self, om: &OpenMath<'a>) -> OpenMathIn<'a> {
+        let attrs = |attributes: &[crate::de::OMAttr<'a, OpenMath<'a>>]| -> &'a [AttrIn<'a>] {
+            self.0.alloc_slice_fill_iter(attributes.iter().map(|a| {
+                Attr::new(
+                    self.symbol(&a.key),
+                    self.foreign(&a.value, |o| self.copy(o)),
+                )
+            }))
+        };
+        match om {
+            OpenMath::OMI { int, attributes } => OpenMathIn::OMI {
+                int: self.int(int),
+                attributes: attrs(attributes),
+            },
+            OpenMath::OMF { float, attributes } => OpenMathIn::OMF {
+                float: float.0,
+                attributes: attrs(attributes),
+            },
+            OpenMath::OMSTR { string, attributes } => OpenMathIn::OMSTR {
+                string: self.str(string),
+                attributes: attrs(attributes),
+            },
+            OpenMath::OMB { bytes, attributes } => OpenMathIn::OMB {
+                bytes: match bytes {
+                    Cow::Borrowed(b) => b,
+                    Cow::Owned(b) => self.0.alloc_slice_copy(b),
+                },
+                attributes: attrs(attributes),
+            },
+            OpenMath::OMV { name, attributes } => OpenMathIn::OMV {
+                name: self.str(name),
+                attributes: attrs(attributes),
+            },
+            OpenMath::OMS {
+                cd,
+                name,
+                cdbase,
+                cdbase_inherited,
+                attributes,
+            } => OpenMathIn::OMS {
+                cd: self.str(cd),
+                name: self.str(name),
+                cdbase: cdbase.as_ref().map(|c| self.str(c)),
+                cdbase_inherited: *cdbase_inherited,
+                attributes: attrs(attributes),
+            },
+            OpenMath::OMA {
+                applicant,
+                arguments,
+                attributes,
+            } => OpenMathIn::OMA {
+                applicant: self.0.alloc(self.copy(applicant)),
+                arguments: self
+                    .0
+                    .alloc_slice_fill_iter(arguments.iter().map(|a| self.copy(a))),
+                attributes: attrs(attributes),
+            },
+            OpenMath::OME {
+                cd,
+                name,
+                cdbase,
+                arguments,
+                attributes,
+            } => OpenMathIn::OME {
+                cd: self.str(cd),
+                name: self.str(name),
+                cdbase: cdbase.as_ref().map(|c| self.str(c)),
+                arguments: self.0.alloc_slice_fill_iter(
+                    arguments.iter().map(|a| self.foreign(a, |o| self.copy(o))),
+                ),
+                attributes: attrs(attributes),
+            },
+            OpenMath::OMBIND {
+                binder,
+                variables,
+                object,
+                attributes,
+            } => OpenMathIn::OMBIND {
+                binder: self.0.alloc(self.copy(binder)),
+                variables: self.0.alloc_slice_fill_iter(variables.iter().map(|v| {
+                    BoundVariableIn {
+                        name: self.str(&v.name),
+                        attributes: attrs(&v.attributes),
+                    }
+                })),
+                object: self.0.alloc(self.copy(object)),
+                attributes: attrs(attributes),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Arena, OpenMathIn};
+    use crate::OpenMath;
+
+    const SEXPR: &str = r#"(ombind (oms fns1 lambda) (bvar x (omattr (((oms sts type) (oms setname1 Z))) y))
+         (omattr (((oms meta note) (oma (oms list1 list) (omstr "a") (omb "AAEC"))))
+           (oma (oms arith1 plus)
+             (oma (oms arith1 times) (omv x) (omi 2) (omf 3.5))
+             (ome (oms moreerrors unexpected)
+               (oma (oms arith1 minus) (omi 123456789012345678901234567890)) (omforeign "…"))
+             (cdbase "http://example.org" (oma (oms my f) (omi 1) (omv y))))))"#;
+
+    #[test]
+    fn alloc_roundtrip() {
+        let om = OpenMath::from_sexpr(SEXPR).expect("is valid");
+        let mut arena = Arena::new();
+        let copy = arena.alloc(&om);
+        assert_eq!(copy.to_openmath(), om);
+        let OpenMathIn::OMBIND {
+            variables, object, ..
+        } = copy
+        else {
+            panic!("is an OMBIND")
+        };
+        assert_eq!(variables.len(), 2);
+        assert_eq!(variables[1].attributes.len(), 1);
+        assert_eq!(object.attributes().len(), 1);
+        assert!(arena.allocated_bytes() > 0);
+        arena.reset();
+        assert_eq!(arena.alloc(&om).to_openmath(), om);
+    }
+
+    #[cfg(feature = "xml")]
+    #[test]
+    fn xml_in_arena() {
+        use crate::{OMDeserializable, OMSerializable};
+        let om = OpenMath::from_sexpr(SEXPR).expect("is valid");
+        let xml = om.xml(false).to_string();
+        let mut arena = Arena::new();
+        for _ in 0..3 {
+            let parsed = arena.from_openmath_xml(&xml).expect("is valid");
+            assert_eq!(
+                parsed.to_openmath(),
+                OpenMath::from_openmath_xml(&xml).expect("is valid")
+            );
+            arena.reset();
+        }
+
+        // unescaped strings are borrowed from the input, others copied into the arena
+        let xml = "<OMA><OMS cd=\"c\" name=\"f\"/><OMSTR>a</OMSTR><OMSTR>&lt;</OMSTR></OMA>";
+        let OpenMathIn::OMA { arguments, .. } = arena.from_openmath_xml(xml).expect("is valid")
+        else {
+            panic!("is an OMA")
+        };
+        let [
+            OpenMathIn::OMSTR { string: plain, .. },
+            OpenMathIn::OMSTR {
+                string: escaped, ..
+            },
+        ] = arguments
+        else {
+            panic!("are two strings")
+        };
+        assert!(xml.as_bytes().as_ptr_range().contains(&plain.as_ptr()));
+        assert_eq!(*escaped, "<");
+        assert!(!xml.as_bytes().as_ptr_range().contains(&escaped.as_ptr()));
+
+        assert!(arena.from_openmath_xml("<OMI>one</OMI>").is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_in_arena() {
+        use crate::{OMSerializable, de::OMFromSerde};
+        let om = OpenMath::from_sexpr(SEXPR).expect("is valid");
+        let json = serde_json::to_string(&om.openmath_serde()).expect("serializes");
+        let arena = Arena::new();
+        let parsed = arena
+            .deserialize(&mut serde_json::Deserializer::from_str(&json))
+            .expect("is valid");
+        let expected = serde_json::from_str::<OMFromSerde<OpenMath>>(&json)
+            .expect("is valid")
+            .into_inner();
+        assert_eq!(parsed.to_openmath(), expected);
+        assert!(
+            arena
+                .deserialize(&mut serde_json::Deserializer::from_str(r#"{"kind":"OMI"}"#))
+                .is_err()
+        );
+    }
+}
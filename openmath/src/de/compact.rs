@@ -0,0 +1,562 @@
+/*! Deserializing into a flat representation for objects that consist mostly of symbols and
+small integers; see [`Compact`]. */
+
+use std::{borrow::Cow, collections::HashMap, hash::Hash};
+
+use smallvec::SmallVec;
+
+use crate::{
+    CD_BASE, Int, OMKind, OpenMath, SymbolRef,
+    de::{OM, OMDeserializable, ReferenceError},
+};
+
+/// Side tables up to this length are searched linearly when interning, longer ones are
+/// indexed
+const LINEAR: usize = 16;
+
+/// A node; 16 bytes, payloads are indices into the side tables of the [`Compact`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Node {
+    Int(i64),
+    Float(f64),
+    Symbol {
+        id: u32,
+        cdbase_inherited: bool,
+    },
+    Variable(u32),
+    /// followed by the applicant and the arguments
+    Apply {
+        arguments: u32,
+    },
+    /// anything else
+    Boxed(u32),
+}
+
+#[inline]
+fn index(len: usize) -> u32 {
+    u32::try_from(len).expect("a Compact holds at most u32::MAX entries of each kind")
+}
+
+/** An <span style="font-variant:small-caps;">OpenMath</span> object as a flat vector of
+16-byte nodes, for inputs that consist mostly of [OMS](OMKind::OMS)s and small
+[OMI](OMKind::OMI)s.
+
+Nodes are stored in pre-order. Symbols and variable names are interned: every distinct one
+is stored once in a side table (see [`symbols`](Self::symbols)), and nodes refer to it by a
+`u32` id. Integers that fit into an [`i64`] and floats are stored inline, and applications
+as their number of arguments. Everything else (any node with attributes, big integers,
+strings, byte arrays, bindings and errors) is kept *out of line*, as an [`OpenMath`] of its
+own (see [`boxed`](Self::boxed)).
+
+Like every [`OMDeserializable`], it is read by both front-ends (XML and serde), e.g. via
+[`from_openmath_xml`](OMDeserializable::from_openmath_xml) or
+[`OMFromSerde`](super::OMFromSerde); strings borrow from the input where possible. Use
+[`to_openmath`](Self::to_openmath) (or [`From`]) to convert to and from [`OpenMath`].
+
+# Performance
+An [`OpenMath`] node takes 128 bytes (on 64-bit targets), plus an allocation for the
+children of every application; a [`Compact`] node takes 16 bytes, plus one table entry per
+*distinct* symbol and variable. In the `compact` benchmark, whose document consists of
+symbols, small integers and applications only, a [`Compact`] keeps about a tenth of the
+memory of an [`OpenMath`], with the same number of allocations while reading; the time is
+about the same for both, since reading the XML dominates it.
+
+The more subterms are boxed, the less this pays off: for objects with many bindings,
+attributes or strings, use [`OpenMath`] (or [`OmArena`](crate::arena::OmArena), which
+stores every kind of node compactly, but copies all strings when parsing).
+
+# Examples
+```
+use openmath::{OMDeserializable, OpenMath, de::Compact};
+
+# #[cfg(feature = "xml-read")]
+# {
+let xml = r#"<OMA><OMS cd="arith1" name="plus"/>
+    <OMA><OMS cd="arith1" name="times"/><OMI>2</OMI><OMS cd="nums1" name="pi"/></OMA>
+    <OMA><OMS cd="arith1" name="times"/><OMI>3</OMI><OMS cd="nums1" name="pi"/></OMA>
+    <OMSTR>boxed</OMSTR>
+</OMA>"#;
+let compact = Compact::from_openmath_xml(xml).expect("is valid");
+assert_eq!(compact.len(), 11);
+assert_eq!(compact.symbols().len(), 3);
+assert_eq!(compact.boxed().len(), 1);
+assert_eq!(compact.to_openmath(), OpenMath::from_openmath_xml(xml).expect("is valid"));
+# }
+```
+*/
+#[derive(Debug, Clone)]
+pub struct Compact<'de> {
+    nodes: SmallVec<Node, 1>,
+    symbols: SmallVec<SymbolRef<'de>, 1>,
+    variables: SmallVec<Cow<'de, str>, 1>,
+    boxed: Vec<OpenMath<'de>>,
+}
+
+impl<'de> Compact<'de> {
+    /// The number of nodes; a [boxed](Self::boxed) object counts as one
+    #[inline]
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Always `false`; every object has at least one node
+    #[inline]
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// The kind of the root of this object
+    #[must_use]
+    pub fn kind(&self) -> OMKind {
+        match self.nodes[0] {
+            Node::Int(_) => OMKind::OMI,
+            Node::Float(_) => OMKind::OMF,
+            Node::Symbol { .. } => OMKind::OMS,
+            Node::Variable(_) => OMKind::OMV,
+            Node::Apply { .. } => OMKind::OMA,
+            Node::Boxed(i) => self.boxed[i as usize].kind(),
+        }
+    }
+
+    /// The distinct symbols of this object (outside of [boxed](Self::boxed) ones), in the
+    /// order they first occur; the cdbases of inherited ones are resolved
+    #[inline]
+    #[must_use]
+    pub fn symbols(&self) -> &[SymbolRef<'de>] {
+        &self.symbols
+    }
+
+    /// The subterms of this object that are stored out of line, in order
+    #[inline]
+    #[must_use]
+    pub fn boxed(&self) -> &[OpenMath<'de>] {
+        &self.boxed
+    }
+
+    /// Converts this object into an [`OpenMath`]
+    #[must_use]
+    pub fn to_openmath(&self) -> OpenMath<'de> {
+        self.build(&mut 0, &mut |i| self.boxed[i].clone())
+    }
+
+    fn build(
+        &self,
+        next: &mut usize,
+        boxed: &mut impl FnMut(usize) -> OpenMath<'de>,
+    ) -> OpenMath<'de> {
+        let node = self.nodes[*next];
+        *next += 1;
+        match node {
+            Node::Int(int) => OpenMath::OMI {
+                int: int.into(),
+                attributes: Vec::new(),
+            },
+            Node::Float(float) => OpenMath::OMF {
+                float: float.into(),
+                attributes: Vec::new(),
+            },
+            Node::Symbol {
+                id,
+                cdbase_inherited,
+            } => {
+                let symbol = &self.symbols[id as usize];
+                OpenMath::OMS {
+                    cd: symbol.cd.clone(),
+                    name: symbol.name.clone(),
+                    cdbase: symbol.cdbase.clone(),
+                    cdbase_inherited,
+                    attributes: Vec::new(),
+                }
+            }
+            Node::Variable(id) => OpenMath::OMV {
+                name: self.variables[id as usize].clone(),
+                attributes: Vec::new(),
+            },
+            Node::Apply { arguments } => {
+                let applicant = Box::new(self.build(next, boxed));
+                let arguments = (0..arguments).map(|_| self.build(next, boxed)).collect();
+                OpenMath::OMA {
+                    applicant,
+                    arguments,
+                    attributes: Vec::new(),
+                }
+            }
+            Node::Boxed(i) => boxed(i as usize),
+        }
+    }
+
+    fn leaf(node: Node) -> Self {
+        Self {
+            nodes: SmallVec::from([node]),
+            symbols: SmallVec::new(),
+            variables: SmallVec::new(),
+            boxed: Vec::new(),
+        }
+    }
+
+    fn boxed_leaf(om: OpenMath<'de>) -> Self {
+        Self {
+            boxed: vec![om],
+            ..Self::leaf(Node::Boxed(0))
+        }
+    }
+}
+
+impl<'de> From<Compact<'de>> for OpenMath<'de> {
+    fn from(mut compact: Compact<'de>) -> Self {
+        // boxed objects are stored in the order they occur, so they can be moved out in turn
+        let mut boxed = std::mem::take(&mut compact.boxed).into_iter().enumerate();
+        compact.build(&mut 0, &mut |i| {
+            let (j, om) = boxed.next().expect("every boxed object occurs once");
+            debug_assert_eq!(i, j);
+            om
+        })
+    }
+}
+
+impl<'de> From<&OpenMath<'de>> for Compact<'de> {
+    /// The compact representation of `om`; see [`Compact`] for which subterms are boxed
+    fn from(om: &OpenMath<'de>) -> Self {
+        let mut builder = Builder::default();
+        builder.insert(om);
+        builder.compact
+    }
+}
+
+/// Positions of the entries of a side table, once it is too long to search linearly
+type Index<T> = Option<HashMap<T, u32>>;
+
+/// Appends nodes to a [`Compact`], interning symbols and variable names
+struct Builder<'de> {
+    compact: Compact<'de>,
+    symbol_index: Index<SymbolRef<'de>>,
+    variable_index: Index<Cow<'de, str>>,
+}
+
+impl Default for Builder<'_> {
+    fn default() -> Self {
+        Self {
+            compact: Compact {
+                nodes: SmallVec::new(),
+                symbols: SmallVec::new(),
+                variables: SmallVec::new(),
+                boxed: Vec::new(),
+            },
+            symbol_index: None,
+            variable_index: None,
+        }
+    }
+}
+
+/// The id of `value` in `table`, which it is added to if it is new
+fn intern<T: Eq + Hash + Clone>(table: &mut SmallVec<T, 1>, index: &mut Index<T>, value: T) -> u32 {
+    if let Some(index) = index {
+        if let Some(i) = index.get(&value) {
+            return *i;
+        }
+        let i = self::index(table.len());
+        index.insert(value.clone(), i);
+        table.push(value);
+        return i;
+    }
+    if let Some(i) = table.iter().position(|t| *t == value) {
+        return self::index(i);
+    }
+    let i = self::index(table.len());
+    table.push(value);
+    if table.len() > LINEAR {
+        *index = Some(
+            table
+                .iter()
+                .enumerate()
+                .map(|(i, t)| (t.clone(), self::index(i)))
+                .collect(),
+        );
+    }
+    i
+}
+
+impl<'de> Builder<'de> {
+    fn symbol(&mut self, symbol: SymbolRef<'de>, cdbase_inherited: bool) -> Node {
+        let id = intern(&mut self.compact.symbols, &mut self.symbol_index, symbol);
+        Node::Symbol {
+            id,
+            cdbase_inherited,
+        }
+    }
+
+    fn variable(&mut self, name: Cow<'de, str>) -> Node {
+        Node::Variable(intern(
+            &mut self.compact.variables,
+            &mut self.variable_index,
+            name,
+        ))
+    }
+
+    fn boxed(&mut self, om: OpenMath<'de>) -> Node {
+        let i = index(self.compact.boxed.len());
+        self.compact.boxed.push(om);
+        Node::Boxed(i)
+    }
+
+    /// Appends the nodes of `other`, re-interning its symbols and variable names
+    fn append(&mut self, other: Compact<'de>) {
+        let symbols = other
+            .symbols
+            .into_iter()
+            .map(|s| intern(&mut self.compact.symbols, &mut self.symbol_index, s))
+            .collect::<SmallVec<_, 4>>();
+        let variables = other
+            .variables
+            .into_iter()
+            .map(|v| intern(&mut self.compact.variables, &mut self.variable_index, v))
+            .collect::<SmallVec<_, 4>>();
+        let boxed = index(self.compact.boxed.len());
+        self.compact.boxed.extend(other.boxed);
+        self.compact
+            .nodes
+            .extend(other.nodes.into_iter().map(|node| match node {
+                Node::Symbol {
+                    id,
+                    cdbase_inherited,
+                } => Node::Symbol {
+                    id: symbols[id as usize],
+                    cdbase_inherited,
+                },
+                Node::Variable(id) => Node::Variable(variables[id as usize]),
+                Node::Boxed(i) => Node::Boxed(boxed + i),
+                node => node,
+            }));
+    }
+
+    /// Appends the nodes of `om`
+    fn insert(&mut self, om: &OpenMath<'de>) {
+        let node = match om {
+            OpenMath::OMI { int, attributes } if attributes.is_empty() => {
+                small(int).map_or_else(|| self.boxed(om.clone()), Node::Int)
+            }
+            OpenMath::OMF { float, attributes } if attributes.is_empty() => {
+                Node::Float(float.into_inner())
+            }
+            OpenMath::OMS {
+                cd,
+                name,
+                cdbase,
+                cdbase_inherited,
+                attributes,
+            } if attributes.is_empty() => self.symbol(
+                SymbolRef {
+                    cdbase: cdbase.clone(),
+                    cd: cd.clone(),
+                    name: name.clone(),
+                },
+                *cdbase_inherited,
+            ),
+            OpenMath::OMV { name, attributes } if attributes.is_empty() => {
+                self.variable(name.clone())
+            }
+            OpenMath::OMA {
+                applicant,
+                arguments,
+                attributes,
+            } if attributes.is_empty() => {
+                self.compact.nodes.push(Node::Apply {
+                    arguments: index(arguments.len()),
+                });
+                self.insert(applicant);
+                for a in arguments {
+                    self.insert(a);
+                }
+                return;
+            }
+            om => self.boxed(om.clone()),
+        };
+        self.compact.nodes.push(node);
+    }
+}
+
+/// The value of `int`, if it fits into an [`i64`]
+fn small(int: &Int<'_>) -> Option<i64> {
+    int.is_i128().and_then(|i| i64::try_from(i).ok())
+}
+
+impl<'de> OMDeserializable<'de> for Compact<'de> {
+    type Ret = Self;
+    type Err = ReferenceError;
+    fn from_openmath(om: OM<'de, Self>, cdbase: &str) -> Result<Self, Self::Err> {
+        match om {
+            OM::OMI { int, attrs } if attrs.is_empty() => Ok(small(&int).map_or_else(
+                || {
+                    Self::boxed_leaf(OpenMath::OMI {
+                        int,
+                        attributes: Vec::new(),
+                    })
+                },
+                |i| Self::leaf(Node::Int(i)),
+            )),
+            OM::OMF { float, attrs, .. } if attrs.is_empty() => Ok(Self::leaf(Node::Float(float))),
+            OM::OMS {
+                cdbase: own_cdbase,
+                cd,
+                name,
+                attrs,
+            } if attrs.is_empty() => {
+                // like `OpenMath`, the default cdbase is borrowed
+                let cdbase_inherited = own_cdbase.is_none();
+                let cdbase = own_cdbase.unwrap_or_else(|| {
+                    if crate::cdbase::eq_normalized(cdbase, CD_BASE) {
+                        Cow::Borrowed(CD_BASE)
+                    } else {
+                        Cow::Owned(cdbase.to_string())
+                    }
+                });
+                let symbol = SymbolRef {
+                    cdbase: Some(cdbase),
+                    cd,
+                    name,
+                };
+                Ok(Self {
+                    symbols: SmallVec::from([symbol]),
+                    ..Self::leaf(Node::Symbol {
+                        id: 0,
+                        cdbase_inherited,
+                    })
+                })
+            }
+            OM::OMV { name, attrs } if attrs.is_empty() => Ok(Self {
+                variables: SmallVec::from([name]),
+                ..Self::leaf(Node::Variable(0))
+            }),
+            OM::OMA {
+                applicant,
+                arguments,
+                attrs,
+            } if attrs.is_empty() => {
+                // the tables hold at most as many entries as those of the children, but
+                // usually (many) fewer
+                let children = || std::iter::once(&applicant).chain(&arguments);
+                let mut builder = Builder::default();
+                let compact = &mut builder.compact;
+                compact
+                    .nodes
+                    .reserve_exact(1 + children().map(Self::len).sum::<usize>());
+                compact.symbols.reserve(
+                    children()
+                        .map(|c| c.symbols.len())
+                        .sum::<usize>()
+                        .min(LINEAR),
+                );
+                compact.variables.reserve(
+                    children()
+                        .map(|c| c.variables.len())
+                        .sum::<usize>()
+                        .min(LINEAR),
+                );
+                builder.compact.nodes.push(Node::Apply {
+                    arguments: index(arguments.len()),
+                });
+                builder.append(applicant);
+                for a in arguments {
+                    builder.append(a);
+                }
+                Ok(builder.compact)
+            }
+            om => {
+                let om = om.try_map(|c| Ok::<_, ReferenceError>(OpenMath::from(c)))?;
+                Ok(Self::boxed_leaf(OpenMath::from_openmath(om, cdbase)?))
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "xml-read"))]
+mod tests {
+    use super::Compact;
+    use crate::{OMDeserializable, OMKind, OpenMath};
+
+    /// Every kind of node, boxed or not
+    const XML: &str = r#"<OMOBJ cdbase="http://example.org/cd"><OMA>
+        <OMS cd="list1" name="list"/>
+        <OMI>1</OMI>
+        <OMI>-123456789012345678901234567890</OMI>
+        <OMF dec="1.5"/>
+        <OMV name="x"/>
+        <OMSTR>text</OMSTR>
+        <OMB>AQID</OMB>
+        <OMS cdbase="http://www.openmath.org/cd" cd="arith1" name="plus"/>
+        <OMS cd="arith1" name="plus"/>
+        <OMA><OMS cd="arith1" name="plus"/><OMV name="x"/><OMI>2</OMI></OMA>
+        <OMBIND><OMS cd="fns1" name="lambda"/><OMBVAR><OMV name="x"/></OMBVAR>
+            <OMA><OMS cd="arith1" name="plus"/><OMV name="x"/><OMI>1</OMI></OMA>
+        </OMBIND>
+        <OMATTR><OMATP><OMS cd="sts" name="type"/><OMS cd="setname1" name="N"/></OMATP>
+            <OMV name="y"/>
+        </OMATTR>
+        <OME><OMS cd="aritherror" name="DivisionByZero"/><OMV name="x"/></OME>
+    </OMA></OMOBJ>"#;
+
+    #[test]
+    fn xml() {
+        use crate::de::OMObject;
+        let om = OMObject::<OpenMath>::from_openmath_xml(XML).expect("is valid");
+        let compact = OMObject::<Compact>::from_openmath_xml(XML).expect("is valid");
+        assert_eq!(compact.to_openmath(), om);
+        assert_eq!(OpenMath::from(compact.clone()), om);
+        assert_eq!(compact.kind(), OMKind::OMA);
+        // the big integer, string, bytes, binding, attribution and error
+        assert_eq!(compact.boxed().len(), 6);
+        // list, plus (with two cdbases)
+        assert_eq!(compact.symbols().len(), 3);
+        assert_eq!(compact.len(), 17);
+
+        let from = Compact::from(&om);
+        assert_eq!(from.to_openmath(), om);
+        assert_eq!(from.len(), compact.len());
+        assert_eq!(from.symbols(), compact.symbols());
+
+        let compact = Compact::from_openmath_xml("<OMSTR>x</OMSTR>").expect("is valid");
+        assert_eq!((compact.len(), compact.kind()), (1, OMKind::OMSTR));
+    }
+
+    /// Interning keeps working once the tables are indexed
+    #[test]
+    fn many_symbols() {
+        use std::fmt::Write;
+        let mut xml = r#"<OMA><OMS cd="list1" name="list"/>"#.to_string();
+        for i in 0..1000 {
+            write!(
+                xml,
+                r#"<OMA><OMS cd="cd{}" name="f"/><OMV name="v{}"/><OMI>{i}</OMI></OMA>"#,
+                i % 50,
+                i % 30
+            )
+            .expect("works");
+        }
+        xml.push_str("</OMA>");
+        let compact = Compact::from_openmath_xml(&xml).expect("is valid");
+        assert_eq!(compact.symbols().len(), 51);
+        assert_eq!(compact.len(), 1 + 1 + 1000 * 4);
+        assert!(compact.boxed().is_empty());
+        let om = OpenMath::from_openmath_xml(&xml).expect("is valid");
+        assert_eq!(compact.to_openmath(), om);
+        assert_eq!(Compact::from(&om).symbols(), compact.symbols());
+    }
+
+    #[cfg(all(feature = "serde", feature = "xml-read", feature = "xml-write"))]
+    #[test]
+    fn serde() {
+        use crate::{
+            OMSerializable,
+            de::{OMFromSerde, OMObject},
+        };
+        let om = OMObject::<OpenMath>::from_openmath_xml(XML).expect("is valid");
+        let json = serde_json::to_string(&om.openmath_serde()).expect("serializes");
+        let compact = serde_json::from_str::<OMFromSerde<Compact>>(&json)
+            .expect("is valid")
+            .into_inner();
+        assert_eq!(compact.to_openmath(), om);
+        assert_eq!(compact.boxed().len(), 6);
+    }
+}
@@ -0,0 +1,438 @@
+//! Structural comparison of [`OpenMath`] objects with [`OM`]s
+
+use super::{OM, OMAttr};
+use crate::{Attr, BoundVariable, CD_BASE, OMMaybeForeign, OpenMath, cdbase::eq_normalized};
+
+/// Compares an explicit cdbase of an [`OM`] with a cdbase of an [`OpenMath`]
+fn cdbase_eq(om: Option<&str>, openmath: Option<&str>, inherited: bool) -> bool {
+    match (om, openmath) {
+        (None, _) if inherited => true,
+        (Some(a), Some(b)) => eq_normalized(a, b),
+        (None, None) => true,
+        (Some(a), None) | (None, Some(a)) => eq_normalized(a, CD_BASE),
+    }
+}
+
+fn foreign_eq<'o, I>(a: &OMMaybeForeign<'o, OpenMath<'o>>, b: &OMMaybeForeign<'_, I>) -> bool
+where
+    OpenMath<'o>: PartialEq<I>,
+{
+    match (a, b) {
+        (OMMaybeForeign::OM(a), OMMaybeForeign::OM(b)) => a == b,
+        (
+            OMMaybeForeign::Foreign { encoding, value },
+            OMMaybeForeign::Foreign {
+                encoding: e,
+                value: v,
+            },
+        ) => encoding == e && value == v,
+        _ => false,
+    }
+}
+
+fn attrs_eq<'o, I>(a: &[Attr<'o, OMMaybeForeign<'o, OpenMath<'o>>>], b: &[OMAttr<'_, I>]) -> bool
+where
+    OpenMath<'o>: PartialEq<I>,
+{
+    a.len() == b.len()
+        && a.iter().zip(b).all(|(a, b)| {
+            a.cd() == b.cd()
+                && a.name() == b.name()
+                && cdbase_eq(b.cdbase(), a.cdbase(), false)
+                && foreign_eq(&a.value, &b.value)
+        })
+}
+
+fn vars_eq<'o, I>(
+    a: &[BoundVariable<'o>],
+    b: &[(std::borrow::Cow<'_, str>, Vec<OMAttr<'_, I>>)],
+) -> bool
+where
+    OpenMath<'o>: PartialEq<I>,
+{
+    a.len() == b.len()
+        && a.iter()
+            .zip(b)
+            .all(|(a, (name, attrs))| a.name == *name && attrs_eq(&a.attributes, attrs))
+}
+
+/** Compares structurally, e.g. an expected object with an intermediate result in tests.
+
+Children are compared via `OpenMath: PartialEq<I>`; so for an intermediate tree type `I`
+(or <code>[Box]&lt;I&gt;</code>), implementing `PartialEq<I> for OpenMath` by delegating to this
+implementation suffices.
+
+The two models differ in how they store cdbases: an [`OM`] contains only cdbases given
+*explicitly* on the respective element (the effective one being passed to
+[`from_openmath`](super::OMDeserializable::from_openmath) separately), whereas an
+[`OpenMath::OMS`] always carries its effective cdbase. Hence:
+- A cdbase of `None` (on either side) and one equal to [`CD_BASE`] (modulo
+  [normalization](crate::cdbase::CdBase::normalize)) are considered equal.
+- A cdbase that is `None` in the [`OM`] also matches any
+  [inherited](OpenMath::OMS::cdbase_inherited) cdbase of an [`OpenMath::OMS`].
+- Otherwise, cdbases are compared modulo normalization;
+  [`cdbase_inherited`](OpenMath::OMS::cdbase_inherited) itself is ignored.
+*/
+impl<I> PartialEq<OM<'_, I>> for OpenMath<'_>
+where
+    Self: PartialEq<I>,
+{
+    #[allow(clippy::too_many_lines)]
+    fn eq(&self, other: &OM<'_, I>) -> bool {
+        match (self, other) {
+            (Self::OMI { int, attributes }, OM::OMI { int: i, attrs: a }) => {
+                int == i && attrs_eq(attributes, a)
+            }
+            (
+                Self::OMF { float, attributes },
+                OM::OMF {
+                    float: f, attrs: a, ..
+                },
+            ) => *float == ordered_float::OrderedFloat(*f) && attrs_eq(attributes, a),
+            (
+                Self::OMSTR { string, attributes },
+                OM::OMSTR {
+                    string: s,
+                    attrs: a,
+                },
+            ) => string == s && attrs_eq(attributes, a),
+            (Self::OMB { bytes, attributes }, OM::OMB { bytes: b, attrs: a }) => {
+                bytes == b && attrs_eq(attributes, a)
+            }
+            (Self::OMV { name, attributes }, OM::OMV { name: n, attrs: a }) => {
+                name == n && attrs_eq(attributes, a)
+            }
+            (
+                Self::OMS {
+                    cd,
+                    name,
+                    cdbase,
+                    cdbase_inherited,
+                    attributes,
+                },
+                OM::OMS {
+                    cdbase: b,
+                    cd: c,
+                    name: n,
+                    attrs: a,
+                },
+            ) => {
+                cd == c
+                    && name == n
+                    && cdbase_eq(b.as_deref(), cdbase.as_deref(), *cdbase_inherited)
+                    && attrs_eq(attributes, a)
+            }
+            (
+                Self::OMA {
+                    applicant,
+                    arguments,
+                    attributes,
+                },
+                OM::OMA {
+                    applicant: h,
+                    arguments: args,
+                    attrs: a,
+                },
+            ) => {
+                **applicant == *h
+                    && arguments.len() == args.len()
+                    && arguments.iter().zip(args).all(|(a, b)| a == b)
+                    && attrs_eq(attributes, a)
+            }
+            (
+                Self::OMBIND {
+                    binder,
+                    variables,
+                    object,
+                    attributes,
+                },
+                OM::OMBIND {
+                    binder: b,
+                    variables: v,
+                    object: o,
+                    attrs: a,
+                },
+            ) => {
+                **binder == *b && vars_eq(variables, v) && **object == *o && attrs_eq(attributes, a)
+            }
+            (
+                Self::OME {
+                    cd,
+                    name,
+                    cdbase,
+                    arguments,
+                    attributes,
+                },
+                OM::OME {
+                    cdbase: b,
+                    cd: c,
+                    name: n,
+                    arguments: args,
+                    attrs: a,
+                },
+            ) => {
+                cd == c
+                    && name == n
+                    && cdbase_eq(b.as_deref(), cdbase.as_deref(), true)
+                    && arguments.len() == args.len()
+                    && arguments.iter().zip(args).all(|(a, b)| foreign_eq(a, b))
+                    && attrs_eq(attributes, a)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<'o, I> PartialEq<OpenMath<'o>> for OM<'_, I>
+where
+    OpenMath<'o>: PartialEq<I>,
+{
+    #[inline]
+    fn eq(&self, other: &OpenMath<'o>) -> bool {
+        other == self
+    }
+}
+
+impl<I> PartialEq<Box<I>> for OpenMath<'_>
+where
+    Self: PartialEq<I>,
+{
+    #[inline]
+    fn eq(&self, other: &Box<I>) -> bool {
+        self == &**other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use crate::{Attr, BoundVariable, CD_BASE, Int, OMMaybeForeign, OpenMath, de::OM};
+
+    /// An intermediate tree, as commonly used as [`Ret`](crate::de::OMDeserializable::Ret)
+    #[derive(Debug)]
+    struct Tree<'de>(OM<'de, Box<Self>>);
+    impl PartialEq<Tree<'_>> for OpenMath<'_> {
+        fn eq(&self, other: &Tree<'_>) -> bool {
+            *self == other.0
+        }
+    }
+    impl<'de> crate::de::OMDeserializable<'de> for Tree<'de> {
+        type Ret = Self;
+        type Err = std::convert::Infallible;
+        fn from_openmath(om: OM<'de, Self>, _: &str) -> Result<Self, Self::Err> {
+            Ok(Self(om.try_map(|t| Ok::<_, Self::Err>(Box::new(t)))?))
+        }
+    }
+
+    type Om<'a> = OM<'a, OpenMath<'a>>;
+
+    fn oms(cdbase: Option<&'static str>, inherited: bool) -> OpenMath<'static> {
+        OpenMath::OMS {
+            cd: Cow::Borrowed("arith1"),
+            name: Cow::Borrowed("plus"),
+            cdbase: cdbase.map(Cow::Borrowed),
+            cdbase_inherited: inherited,
+            attributes: Vec::new(),
+        }
+    }
+    fn om_oms(cdbase: Option<&'static str>) -> OM<'static, OpenMath<'static>> {
+        OM::OMS {
+            cdbase: cdbase.map(Cow::Borrowed),
+            cd: Cow::Borrowed("arith1"),
+            name: Cow::Borrowed("plus"),
+            attrs: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn cdbases() {
+        const OTHER: &str = "http://example.org/cd";
+        assert_eq!(oms(None, false), om_oms(None));
+        assert_eq!(oms(Some(CD_BASE), false), om_oms(None));
+        assert_eq!(oms(None, false), om_oms(Some(CD_BASE)));
+        assert_eq!(oms(Some(OTHER), true), om_oms(None));
+        assert_eq!(
+            oms(Some(OTHER), false),
+            om_oms(Some("HTTP://example.org/cd"))
+        );
+        assert_ne!(oms(Some(OTHER), false), om_oms(None));
+        assert_ne!(oms(None, false), om_oms(Some(OTHER)));
+        assert_ne!(oms(Some(CD_BASE), true), om_oms(Some(OTHER)));
+    }
+
+    #[test]
+    #[allow(clippy::too_many_lines)]
+    fn variants() {
+        let attr = |value| Attr {
+            key: crate::SymbolRef {
+                cdbase: None,
+                cd: Cow::Borrowed("meta"),
+                name: Cow::Borrowed("note"),
+            },
+            value,
+        };
+        let foreign = || OMMaybeForeign::Foreign {
+            encoding: Some(Cow::Borrowed("text/plain")),
+            value: Cow::Borrowed("hi"),
+        };
+        let omv = |name: &'static str| OpenMath::OMV {
+            name: Cow::Borrowed(name),
+            attributes: Vec::new(),
+        };
+        let om_omv = |name: &'static str| -> Om<'static> {
+            OM::OMV {
+                name: Cow::Borrowed(name),
+                attrs: Vec::new(),
+            }
+        };
+
+        let omi = OpenMath::OMI {
+            int: Int::from(1),
+            attributes: vec![attr(OMMaybeForeign::OM(omv("x")))],
+        };
+        assert_eq!(
+            omi,
+            Om::OMI {
+                int: Int::from(1),
+                attrs: vec![attr(OMMaybeForeign::OM(omv("x")))],
+            }
+        );
+        assert_ne!(
+            omi,
+            Om::OMI {
+                int: Int::from(1),
+                attrs: vec![attr(OMMaybeForeign::OM(omv("y")))],
+            }
+        );
+        assert_ne!(
+            omi,
+            Om::OMI {
+                int: Int::from(1),
+                attrs: vec![attr(foreign())],
+            }
+        );
+        assert_eq!(
+            OpenMath::OMF {
+                float: f64::NAN.into(),
+                attributes: Vec::new()
+            },
+            Om::OMF {
+                float: f64::NAN,
+                lexical: None,
+                attrs: Vec::new()
+            }
+        );
+        assert_eq!(
+            OpenMath::OMSTR {
+                string: Cow::Borrowed("s"),
+                attributes: vec![attr(foreign())]
+            },
+            Om::OMSTR {
+                string: Cow::Owned("s".to_string()),
+                attrs: vec![attr(foreign())]
+            }
+        );
+        assert_ne!(
+            OpenMath::OMB {
+                bytes: Cow::Borrowed(&[1]),
+                attributes: Vec::new()
+            },
+            Om::OMB {
+                bytes: Cow::Borrowed(&[2]),
+                attrs: Vec::new()
+            }
+        );
+        assert_ne!(omv("x"), om_omv("y"));
+
+        let oma = OpenMath::OMA {
+            applicant: Box::new(oms(None, false)),
+            arguments: vec![omv("x"), omv("y")],
+            attributes: Vec::new(),
+        };
+        assert_eq!(
+            oma,
+            OM::OMA {
+                // children are compared by their own `PartialEq`
+                applicant: oms(None, false),
+                arguments: vec![omv("x"), omv("y")].into(),
+                attrs: Vec::new(),
+            }
+        );
+        assert_ne!(
+            oma,
+            OM::OMA {
+                applicant: oms(None, false),
+                arguments: vec![omv("x")].into(),
+                attrs: Vec::new(),
+            }
+        );
+        assert_ne!(oma, om_omv("x"));
+
+        let ombind = |var_attr| OpenMath::OMBIND {
+            binder: Box::new(oms(None, false)),
+            variables: vec![BoundVariable {
+                name: Cow::Borrowed("x"),
+                attributes: vec![attr(var_attr)],
+            }],
+            object: Box::new(omv("x")),
+            attributes: Vec::new(),
+        };
+        let expected_ombind = OM::OMBIND {
+            binder: oms(None, false),
+            variables: vec![(Cow::Borrowed("x"), vec![attr(foreign())])].into(),
+            object: omv("x"),
+            attrs: Vec::new(),
+        };
+        assert_eq!(ombind(foreign()), expected_ombind);
+        assert_ne!(ombind(OMMaybeForeign::OM(omv("x"))), expected_ombind);
+
+        let ome = |arguments| OpenMath::OME {
+            cd: Cow::Borrowed("moreerrors"),
+            name: Cow::Borrowed("unexpected"),
+            cdbase: None,
+            arguments,
+            attributes: Vec::new(),
+        };
+        let expected_ome = OM::OME {
+            cdbase: Some(Cow::Borrowed(CD_BASE)),
+            cd: Cow::Borrowed("moreerrors"),
+            name: Cow::Borrowed("unexpected"),
+            arguments: vec![OMMaybeForeign::OM(omv("x")), foreign()],
+            attrs: Vec::new(),
+        };
+        assert_eq!(
+            ome(vec![OMMaybeForeign::OM(omv("x")), foreign()]),
+            expected_ome
+        );
+        assert_ne!(
+            ome(vec![foreign(), OMMaybeForeign::OM(omv("x"))]),
+            expected_ome
+        );
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn trees() {
+        use crate::de::OMDeserializable;
+        const XML: &str = r#"<OMATTR cdbase="http://example.org/cd">
+  <OMATP><OMS cd="meta" name="id"/><OMFOREIGN>x</OMFOREIGN></OMATP>
+  <OMA>
+    <OMS cd="arith1" name="plus"/>
+    <OMI>1</OMI><OMF dec="1.5"/><OMSTR>s</OMSTR><OMB>AQI=</OMB>
+    <OMBIND>
+      <OMS cd="fns1" name="lambda" cdbase="http://openmath.org/cd"/>
+      <OMBVAR><OMATTR><OMATP><OMS cd="sts" name="type"/><OMV name="T"/></OMATP><OMV name="x"/></OMATTR></OMBVAR>
+      <OMV name="x"/>
+    </OMBIND>
+    <OME><OMS cd="moreerrors" name="unexpected"/><OMI>2</OMI><OMFOREIGN>y</OMFOREIGN></OME>
+  </OMA>
+</OMATTR>"#;
+        let expected = OpenMath::from_openmath_xml(XML).expect("is valid");
+        let tree = Tree::from_openmath_xml(XML).expect("is valid");
+        assert_eq!(expected, tree);
+        let changed = XML.replace("<OMI>2</OMI>", "<OMI>3</OMI>");
+        let other = OpenMath::from_openmath_xml(&changed).expect("is valid");
+        assert_ne!(other, tree);
+    }
+}
@@ -0,0 +1,427 @@
+//! Reading OMOBJs one at a time from a stream, e.g. a log file; see [`read_framed`]
+
+use super::{OMDeserializable, OMObject, Options, xml::XmlReadError};
+
+/** Reads the next OMOBJ from `reader`, e.g. a log or journal file that
+[`ser::write_framed`](crate::ser::write_framed) appends to; `None` if `reader` is exhausted
+before one starts.
+
+Everything before the next `<OMOBJ` start tag (with any prefix) is skipped and, unless it is
+only whitespace, passed to `on_garbage` (lossily decoded as UTF-8); e.g. log lines, or the
+remains of a truncated object. The object ends with the `</OMOBJ>` matching its start tag, so
+the ones in comments, CDATA sections or processing instructions do not end it; the rest of
+`reader` is not consumed, so the next call continues right after it. Another `OMOBJ` start
+tag before that (other than in an `OMFOREIGN`) means that the object was cut off: it is
+skipped as garbage as well, and the other one is read instead. The object itself is
+read like [`OMObject::from_openmath_xml_reader_with`] with the default [`Options`]; see
+[`read_framed_with`].
+
+If `reader` ends in the middle of an object, what was read of it fails to parse.
+
+# Errors
+if reading fails (as [`XmlReadError::Xml`]), or the object fails to parse.
+
+# Examples
+```
+use openmath::{OpenMath, de::read_framed};
+
+let log = r#"12:00 started
+<OMOBJ><OMI>1</OMI></OMOBJ>
+12:01 <OMOBJ><OMA><OMS cd="arith1" name="pl
+12:02 restarted
+<OMOBJ><OMV name="x"/></OMOBJ>
+"#;
+let mut reader = log.as_bytes();
+let mut garbage = Vec::new();
+let mut objects = Vec::new();
+while let Some(obj) =
+    read_framed::<OpenMath, _>(&mut reader, |g| garbage.push(g.to_string())).expect("is valid")
+{
+    objects.push(obj.into_inner().to_sexpr());
+}
+assert_eq!(objects, ["(omi 1)", "(omv x)"]);
+assert_eq!(garbage.len(), 2);
+assert!(garbage[1].ends_with("12:02 restarted\n"));
+```
+*/
+pub fn read_framed<O, R>(
+    reader: &mut R,
+    on_garbage: impl FnMut(&str),
+) -> Result<Option<OMObject<'static, O>>, XmlReadError<O::Err>>
+where
+    O: OMDeserializable<'static>,
+    R: std::io::BufRead,
+{
+    read_framed_with(reader, Options::new(), on_garbage)
+}
+
+/// Like [`read_framed`], but reads the object with the given [`Options`].
+///
+/// # Errors
+/// see [`read_framed`]
+pub fn read_framed_with<'o, O, R>(
+    reader: &mut R,
+    options: impl Into<Options<'o>>,
+    mut on_garbage: impl FnMut(&str),
+) -> Result<Option<OMObject<'static, O>>, XmlReadError<O::Err>>
+where
+    O: OMDeserializable<'static>,
+    R: std::io::BufRead,
+{
+    let io = |error: std::io::Error, position: usize| XmlReadError::Xml {
+        error: quick_xml::Error::Io(std::sync::Arc::new(error)),
+        position: position as u64,
+    };
+    let mut garbage = Vec::new();
+    let start = skip_to_omobj(reader, &mut garbage).map_err(|e| io(e, garbage.len()))?;
+    let Some(start) = start else {
+        report(&garbage, &mut on_garbage);
+        return Ok(None);
+    };
+    let mut frame = garbage.split_off(start);
+    while let Some(restart) = read_frame(reader, &mut frame).map_err(|e| io(e, frame.len()))? {
+        let rest = frame.split_off(restart);
+        garbage.append(&mut frame);
+        frame = rest;
+    }
+    report(&garbage, &mut on_garbage);
+    OMObject::from_openmath_xml_reader_with(frame.as_slice(), options).map(Some)
+}
+
+/// Passes `garbage` to `on_garbage`, unless it is only whitespace
+fn report(garbage: &[u8], on_garbage: &mut impl FnMut(&str)) {
+    if !garbage.iter().all(u8::is_ascii_whitespace) {
+        on_garbage(&String::from_utf8_lossy(garbage));
+    }
+}
+
+/// Whether `b` ends the name in a tag
+const fn ends_name(b: u8) -> bool {
+    b.is_ascii_whitespace() || b == b'>' || b == b'/'
+}
+
+/// Whether the tag name `name` has the local name `OMOBJ`
+fn is_omobj(name: &[u8]) -> bool {
+    name.rsplit(|b| *b == b':').next() == Some(b"OMOBJ")
+}
+
+/// Consumes `reader` up to and including the first byte after the name of the next `OMOBJ`
+/// start tag, appending it to `buf`; returns the offset of the tag's `<` in `buf`, or `None`
+/// if `reader` ends before
+fn skip_to_omobj(
+    reader: &mut impl std::io::BufRead,
+    buf: &mut Vec<u8>,
+) -> std::io::Result<Option<usize>> {
+    let mut tag = None;
+    loop {
+        let available = reader.fill_buf()?;
+        if available.is_empty() {
+            return Ok(None);
+        }
+        for (i, &b) in available.iter().enumerate() {
+            buf.push(b);
+            match tag {
+                _ if b == b'<' => tag = Some(buf.len() - 1),
+                Some(start) if ends_name(b) => {
+                    tag = None;
+                    if is_omobj(&buf[start + 1..buf.len() - 1]) {
+                        reader.consume(i + 1);
+                        return Ok(Some(start));
+                    }
+                }
+                _ => (),
+            }
+        }
+        let n = available.len();
+        reader.consume(n);
+    }
+}
+
+/// What [`Frame::push`] found
+enum Step {
+    Continue,
+    /// the end of the OMOBJ
+    Done,
+    /// the start tag of another OMOBJ, at the given offset
+    Restart(usize),
+}
+
+/// What the end of a frame is in
+#[derive(Clone, Copy)]
+enum State {
+    Text,
+    /// a `<!` that may start a comment or CDATA section
+    Bang,
+    Comment,
+    Cdata,
+    Pi,
+    /// a start or end tag; in an attribute value if quoted
+    Tag {
+        quote: Option<u8>,
+    },
+}
+
+/// Finds the end of an OMOBJ, byte by byte
+struct Frame {
+    state: State,
+    /// the offset of the `<` of the current markup
+    start: usize,
+    /// the number of OMOBJs open
+    depth: usize,
+    /// the number of OMFOREIGNs open
+    foreign: usize,
+}
+impl Frame {
+    /// Processes the last byte of `frame`
+    fn push(&mut self, frame: &[u8]) -> Step {
+        let len = frame.len();
+        let Some(&b) = frame.last() else {
+            return Step::Continue;
+        };
+        let markup = &frame[self.start..];
+        match self.state {
+            // `<` is not allowed in tags, so the previous one was cut off
+            State::Text | State::Tag { .. } if b == b'<' => {
+                self.start = len - 1;
+                self.state = State::Tag { quote: None };
+            }
+            State::Tag { quote: None } if markup == b"<!" => self.state = State::Bang,
+            State::Tag { quote: None } if markup == b"<?" => self.state = State::Pi,
+            State::Bang if markup == b"<!--" => self.state = State::Comment,
+            State::Bang if markup == b"<![CDATA[" => self.state = State::Cdata,
+            State::Bang if b"<![CDATA[".starts_with(markup) || b"<!--".starts_with(markup) => {}
+            State::Bang => {
+                // a DOCTYPE or the like
+                self.state = State::Tag { quote: None };
+                return self.push(frame);
+            }
+            State::Comment if markup.len() >= 7 && markup.ends_with(b"-->") => {
+                self.state = State::Text;
+            }
+            State::Cdata if markup.len() >= 12 && markup.ends_with(b"]]>") => {
+                self.state = State::Text;
+            }
+            State::Pi if markup.len() >= 4 && markup.ends_with(b"?>") => self.state = State::Text,
+            State::Text | State::Comment | State::Cdata | State::Pi => (),
+            State::Tag { quote: Some(q) } => {
+                if b == q {
+                    self.state = State::Tag { quote: None };
+                }
+            }
+            State::Tag { quote: None } => match b {
+                b'"' | b'\'' => self.state = State::Tag { quote: Some(b) },
+                b'>' => {
+                    self.state = State::Text;
+                    return self.tag(markup);
+                }
+                _ => (),
+            },
+        }
+        Step::Continue
+    }
+
+    /// Processes the complete tag `tag`
+    fn tag(&mut self, tag: &[u8]) -> Step {
+        let end = tag.get(1) == Some(&b'/');
+        let name = &tag[if end { 2 } else { 1 }..];
+        let name = &name[..name
+            .iter()
+            .position(|b| ends_name(*b))
+            .unwrap_or(name.len())];
+        let empty = tag.ends_with(b"/>");
+        if name.rsplit(|b| *b == b':').next() == Some(b"OMFOREIGN") && !empty {
+            if end {
+                self.foreign = self.foreign.saturating_sub(1);
+            } else {
+                self.foreign += 1;
+            }
+        }
+        if !is_omobj(name) {
+            return Step::Continue;
+        }
+        if end {
+            self.depth = self.depth.saturating_sub(1);
+        } else if self.depth > 0 && self.foreign == 0 {
+            return Step::Restart(self.start);
+        } else if !empty {
+            self.depth += 1;
+        }
+        if self.depth == 0 {
+            Step::Done
+        } else {
+            Step::Continue
+        }
+    }
+}
+
+/// Consumes `reader` up to the end of the OMOBJ that `frame` starts, appending it to
+/// `frame`; stops early if `reader` ends. Returns the offset of the start tag of another
+/// OMOBJ that cuts it off, if any.
+fn read_frame(
+    reader: &mut impl std::io::BufRead,
+    frame: &mut Vec<u8>,
+) -> std::io::Result<Option<usize>> {
+    let mut state = Frame {
+        state: State::Text,
+        start: 0,
+        depth: 0,
+        foreign: 0,
+    };
+    let head = std::mem::take(frame);
+    for b in head {
+        frame.push(b);
+        match state.push(frame) {
+            Step::Continue => (),
+            Step::Done => return Ok(None),
+            Step::Restart(start) => return Ok(Some(start)),
+        }
+    }
+    loop {
+        let available = reader.fill_buf()?;
+        if available.is_empty() {
+            return Ok(None);
+        }
+        for (i, &b) in available.iter().enumerate() {
+            frame.push(b);
+            match state.push(frame) {
+                Step::Continue => (),
+                Step::Done => {
+                    reader.consume(i + 1);
+                    return Ok(None);
+                }
+                Step::Restart(start) => {
+                    reader.consume(i + 1);
+                    return Ok(Some(start));
+                }
+            }
+        }
+        let n = available.len();
+        reader.consume(n);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_framed, read_framed_with};
+    use crate::{
+        OpenMath,
+        de::{Options, VersionPolicy},
+    };
+
+    /// Reads all objects of `input` through a buffer of `capacity` bytes, as s-expressions,
+    /// and the garbage between them
+    fn read_all(input: &str, capacity: usize) -> (Vec<String>, Vec<String>) {
+        let mut reader = std::io::BufReader::with_capacity(capacity, input.as_bytes());
+        let mut garbage = Vec::new();
+        let mut objects = Vec::new();
+        while let Some(obj) =
+            read_framed::<OpenMath, _>(&mut reader, |g| garbage.push(g.to_string()))
+                .expect("is valid")
+        {
+            objects.push(obj.into_inner().to_sexpr());
+        }
+        (objects, garbage)
+    }
+
+    #[test]
+    fn boundaries() {
+        let input = concat!(
+            "noise <OMOBJECT> <OMOBJ",
+            "\n><OMSTR><![CDATA[</OMOBJ>]]></OMSTR><!-- </OMOBJ> --></OMOBJ >",
+            "<?pi </OMOBJ>?>",
+            "<om:OMOBJ xmlns:om=\"http://www.openmath.org/OpenMath\" id='a>b'>",
+            "<om:OMV name=\"x\"/></om:OMOBJ>\n\n",
+            "<OMOBJ><OMATTR><OMATP><OMS cd=\"meta\" name=\"source\"/>",
+            "<OMFOREIGN><OMOBJ><OMI>1</OMI></OMOBJ></OMFOREIGN></OMATP>",
+            "<OMV name=\"y\"/></OMATTR></OMOBJ>",
+            "trailing",
+        );
+        for capacity in [1, 2, 7, 64, 4096] {
+            let (objects, garbage) = read_all(input, capacity);
+            assert_eq!(objects.len(), 3, "{capacity}");
+            assert_eq!(objects[0], "(omstr \"</OMOBJ>\")");
+            assert_eq!(objects[1], "(omv x)");
+            assert_eq!(
+                garbage,
+                ["noise <OMOBJECT> ", "<?pi </OMOBJ>?>", "trailing"],
+                "{capacity}"
+            );
+        }
+    }
+
+    #[test]
+    fn truncated() {
+        let mut reader = "<OMOBJ><OMI>1</OMI></OMOBJ><OMOBJ><OMI>2".as_bytes();
+        let first = read_framed::<OpenMath, _>(&mut reader, |_| panic!("no garbage"));
+        assert!(matches!(first, Ok(Some(_))));
+        let second = read_framed::<OpenMath, _>(&mut reader, |_| panic!("no garbage"));
+        assert!(second.is_err());
+        assert!(reader.is_empty());
+        let third = read_framed::<OpenMath, _>(&mut reader, |_| panic!("no garbage"));
+        assert!(matches!(third, Ok(None)));
+    }
+
+    #[test]
+    fn cut_off() {
+        let input = r#"<OMOBJ><OMA><OMS cd="arith1" name="pl<OMOBJ><OMI>3</OMI></OMOBJ><OMOBJ><OMA>
+<OMOBJ><OMI>4</OMI></OMOBJ>"#;
+        for capacity in [1, 5, 4096] {
+            let (objects, garbage) = read_all(input, capacity);
+            assert_eq!(objects, ["(omi 3)", "(omi 4)"], "{capacity}");
+            assert_eq!(
+                garbage,
+                [r#"<OMOBJ><OMA><OMS cd="arith1" name="pl"#, "<OMOBJ><OMA>\n"],
+                "{capacity}"
+            );
+        }
+    }
+
+    #[test]
+    fn with_options() {
+        let mut reader = r#"<OMOBJ version="3.0"><OMI>1</OMI></OMOBJ>"#.as_bytes();
+        let options = Options::new().with_version_policy(VersionPolicy::Lenient);
+        let obj = read_framed_with::<OpenMath, _>(&mut reader, options, |_| ())
+            .expect("is lenient")
+            .expect("is there");
+        assert_eq!(obj.version(), Some("3.0"));
+    }
+
+    #[cfg(feature = "xml-write")]
+    #[test]
+    fn concurrent_writers() {
+        use std::sync::{Arc, Mutex};
+
+        /// A shared log that writes every buffer in one piece, like an `O_APPEND` file
+        #[derive(Clone)]
+        struct Log(Arc<Mutex<Vec<u8>>>);
+        impl std::io::Write for Log {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().expect("not poisoned").extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let log = Log(Arc::default());
+        std::thread::scope(|s| {
+            for t in 0..4 {
+                let mut log = log.clone();
+                s.spawn(move || {
+                    for i in 0..50 {
+                        let om = OpenMath::from_sexpr(&format!(
+                            "(oma (oms arith1 plus) (omi {t}) (omstr \"{i} </OMOBJ>\"))"
+                        ))
+                        .expect("is valid");
+                        crate::ser::write_framed(&mut log, &om).expect("writes");
+                    }
+                });
+            }
+        });
+        let log = log.0.lock().expect("not poisoned");
+        let (objects, garbage) = read_all(std::str::from_utf8(&log).expect("is UTF-8"), 16);
+        assert_eq!(objects.len(), 200);
+        assert!(garbage.is_empty());
+    }
+}
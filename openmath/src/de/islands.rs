@@ -0,0 +1,326 @@
+//! Reading the OMOBJs embedded in other XML documents; see [`extract_openmath_xml`]
+
+use std::{marker::PhantomData, ops::Range};
+
+use quick_xml::{
+    NsReader,
+    events::Event,
+    name::{Namespace, ResolveResult},
+};
+
+use super::{
+    OMDeserializable, OMObject, VersionPolicy,
+    xml::{FromString, Readable, XmlReadError},
+};
+
+/** Reads every OMOBJ embedded in the XML document `input` (e.g. XHTML with
+<span style="font-variant:small-caps;">OpenMath</span> "islands"), in document order,
+together with its byte range in `input`; everything outside of them is skipped.
+
+An island is an `OMOBJ` element in the
+<span style="font-variant:small-caps;">OpenMath</span> [namespace](crate::XML_NS) (with any
+prefix) or in no namespace; note that an unprefixed `OMOBJ` inside an element with a default
+namespace declaration (e.g. `<html xmlns="http://www.w3.org/1999/xhtml">`) is in *that*
+namespace, unless it declares its own. Elements that only look like one, in comments,
+CDATA sections or processing instructions, are not islands.
+
+Islands are read like [`OMObject::from_openmath_xml`]; one that fails to parse yields an
+error, after which the remaining islands are still read. The host document needs to be
+well-formed, and the iterator ends after the first error in it; see
+[`lenient`](Islands::lenient) for documents that are not. Positions in errors are offsets
+in `input`.
+
+# Examples
+```
+use openmath::{OpenMath, de::extract_openmath_xml};
+
+let xhtml = r#"<div xmlns:om="http://www.openmath.org/OpenMath">
+  <p>One: <om:OMOBJ><om:OMI>1</om:OMI></om:OMOBJ></p>
+  <!-- <OMOBJ><OMI>2</OMI></OMOBJ> -->
+  <p>Three: <OMOBJ><OMV name="x"/></OMOBJ></p>
+</div>"#;
+let islands = extract_openmath_xml::<OpenMath>(xhtml)
+    .collect::<Result<Vec<_>, _>>()
+    .expect("is valid");
+assert_eq!(islands.len(), 2);
+let (range, om) = &islands[0];
+assert_eq!(&xhtml[range.clone()], "<om:OMOBJ><om:OMI>1</om:OMI></om:OMOBJ>");
+assert_eq!(om, &OpenMath::from_sexpr("(omi 1)").expect("is valid"));
+```
+*/
+#[must_use]
+pub fn extract_openmath_xml<'de, O: OMDeserializable<'de>>(input: &'de str) -> Islands<'de, O> {
+    Islands {
+        input,
+        scan: Scan::Strict(Box::new(NsReader::from_str(input))),
+        _object: PhantomData,
+    }
+}
+
+/// The OMOBJs embedded in an XML document, with their byte ranges; see
+/// [`extract_openmath_xml`]
+pub struct Islands<'de, O> {
+    input: &'de str,
+    scan: Scan<'de>,
+    _object: PhantomData<fn() -> O>,
+}
+
+enum Scan<'de> {
+    /// Reading the host document as XML, which resolves namespaces and checks that it is
+    /// well-formed
+    Strict(Box<NsReader<&'de [u8]>>),
+    /// Searching the rest of the input, from the given offset, for OMOBJ start tags
+    Lenient(usize),
+    /// After an error in the host document
+    Done,
+}
+
+impl<O> Islands<'_, O> {
+    /** Does not require the host document to be well-formed (e.g. HTML with unclosed
+    elements or undeclared entities), only the islands themselves: any element whose
+    local name is `OMOBJ` is an island, regardless of its namespace, since the namespace
+    declarations of a document that is not well-formed can not be tracked reliably.
+    Comments, CDATA sections, processing instructions and quoted attribute values are still
+    skipped.
+
+    # Examples
+    ```
+    use openmath::{OpenMath, de::extract_openmath_xml};
+
+    let html = "<p>Unclosed &nbsp; <br> <OMOBJ><OMI>1</OMI></OMOBJ></div>";
+    assert!(extract_openmath_xml::<OpenMath>(html).any(|r| r.is_err()));
+    let islands = extract_openmath_xml::<OpenMath>(html)
+        .lenient()
+        .collect::<Result<Vec<_>, _>>()
+        .expect("the island is valid");
+    assert_eq!(&html[islands[0].0.clone()], "<OMOBJ><OMI>1</OMI></OMOBJ>");
+    ```
+    */
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn lenient(mut self) -> Self {
+        if let Scan::Strict(reader) = &self.scan {
+            self.scan = Scan::Lenient(reader.buffer_position() as usize);
+        }
+        self
+    }
+}
+
+impl<'de, O: OMDeserializable<'de>> Iterator for Islands<'de, O> {
+    type Item = Result<(Range<usize>, O), XmlReadError<O::Err>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.scan {
+            Scan::Strict(reader) => loop {
+                let start = usize::try_from(reader.buffer_position()).expect("fits");
+                let (namespace, event) = match reader.read_resolved_event() {
+                    Ok(e) => e,
+                    Err(error) => {
+                        let position = reader.error_position();
+                        self.scan = Scan::Done;
+                        return Some(Err(XmlReadError::Xml { error, position }));
+                    }
+                };
+                match event {
+                    Event::Start(e) if is_island(e.local_name().as_ref(), &namespace) => {
+                        let island = read(self.input, start);
+                        // skips the island in the host document
+                        if let Err(error) = reader.read_to_end(e.name()) {
+                            let position = reader.error_position();
+                            self.scan = Scan::Done;
+                            // an error in the island itself comes first
+                            return Some(island.and(Err(XmlReadError::Xml { error, position })));
+                        }
+                        return Some(island);
+                    }
+                    Event::Empty(e) if is_island(e.local_name().as_ref(), &namespace) => {
+                        return Some(read(self.input, start));
+                    }
+                    Event::Eof => {
+                        self.scan = Scan::Done;
+                        return None;
+                    }
+                    _ => (),
+                }
+            },
+            Scan::Lenient(from) => {
+                let Some(start) = next_start_tag(self.input, *from) else {
+                    self.scan = Scan::Done;
+                    return None;
+                };
+                let island = read(self.input, start);
+                *from = match &island {
+                    Ok((range, _)) => range.end,
+                    // continues after the `<`, in the island
+                    Err(_) => start + 1,
+                };
+                Some(island)
+            }
+            Scan::Done => None,
+        }
+    }
+}
+
+/// Whether an element with the given local name and namespace is an island
+fn is_island(local_name: &[u8], namespace: &ResolveResult<'_>) -> bool {
+    local_name == b"OMOBJ"
+        && match namespace {
+            ResolveResult::Unbound => true,
+            ResolveResult::Bound(Namespace(ns)) => *ns == crate::XML_NS.as_bytes(),
+            ResolveResult::Unknown(_) => false,
+        }
+}
+
+/// Reads the island starting at `start`
+fn read<'de, O: OMDeserializable<'de>>(
+    input: &'de str,
+    start: usize,
+) -> Result<(Range<usize>, O), XmlReadError<O::Err>> {
+    let mut reader = FromString::at(&input[start..], start as u64);
+    let object: OMObject<'de, O> =
+        Readable::<'de, O>::read_obj_prefix(&mut reader, VersionPolicy::default(), crate::CD_BASE)?;
+    Ok((start..reader.consumed(), object.into_inner()))
+}
+
+/// The offset of the next OMOBJ start tag at or after `from`, regardless of its prefix;
+/// skipping comments, CDATA sections, processing instructions, declarations and (the quoted
+/// attribute values of) other tags
+fn next_start_tag(input: &str, mut from: usize) -> Option<usize> {
+    let bytes = input.as_bytes();
+    let after = |from: usize, end: &str| input[from..].find(end).map(|i| from + i + end.len());
+    loop {
+        let start = from + input[from..].find('<')?;
+        let rest = &input[start..];
+        from = if rest.starts_with("<!--") {
+            after(start + 4, "-->")?
+        } else if rest.starts_with("<![CDATA[") {
+            after(start + 9, "]]>")?
+        } else if rest.starts_with("<?") {
+            after(start + 2, "?>")?
+        } else if rest.starts_with("<!") || rest.starts_with("</") {
+            after(start + 2, ">")?
+        } else {
+            let name_end = rest
+                .find(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>')
+                .map_or(input.len(), |i| start + i);
+            let name = &input[start + 1..name_end];
+            if name.rsplit(':').next() == Some("OMOBJ") {
+                return Some(start);
+            }
+            // the end of the tag, outside of quoted attribute values
+            let mut quote = None;
+            let mut end = None;
+            for (i, &b) in bytes.iter().enumerate().skip(name_end) {
+                match (quote, b) {
+                    (None, b'"' | b'\'') => quote = Some(b),
+                    (Some(q), _) if q == b => quote = None,
+                    (None, b'>') => {
+                        end = Some(i + 1);
+                        break;
+                    }
+                    _ => (),
+                }
+            }
+            end?
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::extract_openmath_xml;
+    use crate::test_util::om;
+    use crate::{OpenMath, de::XmlReadError};
+
+    const HOST: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:m="http://www.openmath.org/OpenMath">
+  <body>
+    <p title="1 > 0">One: <m:OMOBJ><m:OMS cdbase="http://example.org" cd="c" name="one"/></m:OMOBJ></p>
+    <!-- <OMOBJ xmlns="http://www.openmath.org/OpenMath"><OMI>0</OMI></OMOBJ> -->
+    <pre><![CDATA[<OMOBJ xmlns="http://www.openmath.org/OpenMath"><OMI>0</OMI></OMOBJ>]]></pre>
+    <?pi <OMOBJ/> ?>
+    <p>XHTML, not an island: <OMOBJ><OMI>0</OMI></OMOBJ></p>
+    <p>Two: <OMOBJ xmlns="http://www.openmath.org/OpenMath">
+      <OMA><OMS cd="arith1" name="plus"/><OMI>2</OMI><OMV name="x"/></OMA>
+    </OMOBJ></p>
+    <x:OMOBJ xmlns:x="http://example.org/other"><OMI>0</OMI></x:OMOBJ>
+  </body>
+</html>"#;
+
+    #[test]
+    fn islands() {
+        let islands = extract_openmath_xml::<OpenMath>(HOST)
+            .collect::<Result<Vec<_>, _>>()
+            .expect("is valid");
+        let (ranges, objects): (Vec<_>, Vec<_>) = islands.into_iter().unzip();
+        assert_eq!(
+            objects,
+            [
+                om(r#"(cdbase "http://example.org" (oms c one))"#),
+                om("(oma (oms arith1 plus) (omi 2) (omv x))"),
+            ]
+        );
+        for range in &ranges {
+            assert!(HOST[range.clone()].starts_with('<'), "{range:?}");
+            assert!(HOST[range.clone()].ends_with("OMOBJ>"), "{range:?}");
+        }
+        assert!(HOST[ranges[0].clone()].starts_with("<m:OMOBJ><m:OMS"));
+
+        // lenient: everything named OMOBJ, outside comments, CDATA, PIs and attributes
+        let lenient = extract_openmath_xml::<OpenMath>(HOST)
+            .lenient()
+            .collect::<Result<Vec<_>, _>>()
+            .expect("is valid");
+        assert_eq!(lenient.len(), 4);
+        assert_eq!(lenient[0].0, ranges[0]);
+        assert_eq!(lenient[1].1, om("(omi 0)"));
+        assert_eq!(lenient[2].0, ranges[1]);
+    }
+
+    #[test]
+    fn errors() {
+        // an invalid island does not stop the others
+        let host = "<div><OMOBJ><OMI>x</OMI></OMOBJ><OMOBJ/><OMOBJ><OMI>1</OMI></OMOBJ></div>";
+        let results = extract_openmath_xml::<OpenMath>(host).collect::<Vec<_>>();
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_err());
+        assert!(matches!(results[1], Err(XmlReadError::UnexpectedTag(_))));
+        let Ok((range, one)) = &results[2] else {
+            panic!("is valid")
+        };
+        assert_eq!(range.start, host.rfind("<OMOBJ>").expect("is there"));
+        assert_eq!(one, &om("(omi 1)"));
+
+        // positions are offsets in the host document
+        let host = "<div>\n<OMOBJ><OMA><OMS cd=\"c\" name=\"f\"/><OMFOREIGN/></OMA></OMOBJ></div>";
+        let Some(Err(XmlReadError::MisplacedForeign(at))) =
+            extract_openmath_xml::<OpenMath>(host).next()
+        else {
+            panic!("is misplaced")
+        };
+        assert!(host[usize::try_from(at).expect("fits")..].starts_with("<OMFOREIGN"));
+
+        // a broken host document ends the iteration, unless reading leniently
+        let host = "<div><p><OMOBJ><OMI>1</OMI></OMOBJ></div><OMOBJ><OMI>2</OMI></OMOBJ>";
+        let strict = extract_openmath_xml::<OpenMath>(host).collect::<Vec<_>>();
+        assert_eq!(strict.len(), 2);
+        assert!(strict[0].is_ok());
+        assert!(matches!(strict[1], Err(XmlReadError::Xml { .. })));
+        let lenient = extract_openmath_xml::<i64>(host)
+            .lenient()
+            .map(|r| r.map(|(_, i)| i))
+            .collect::<Result<Vec<_>, _>>()
+            .expect("the islands are valid");
+        assert_eq!(lenient, [1, 2]);
+
+        // a broken island in a broken document
+        let host =
+            "<p>&nbsp;<OMOBJ><OMI>1</OMI></OMOBJ><OMOBJ><OMI>2</OMOBJ><OMOBJ><OMI>3</OMI></OMOBJ>";
+        let lenient = extract_openmath_xml::<i64>(host)
+            .lenient()
+            .map(|r| r.map(|(_, i)| i).ok())
+            .collect::<Vec<_>>();
+        assert_eq!(lenient, [Some(1), None, Some(3)]);
+    }
+}
@@ -0,0 +1,212 @@
+//! Reading objects directly from memory-mapped files; see [`MmapDocument`]
+
+use std::path::Path;
+
+use super::{Islands, OMDeserializable, OMObject, extract_openmath_xml, xml::XmlReadError};
+
+/// Errors when [opening](MmapDocument::open) a file
+#[derive(Debug, thiserror::Error)]
+pub enum MmapError {
+    /// The file could not be opened or mapped
+    #[error("error mapping file: {0}")]
+    Io(#[from] std::io::Error),
+    /// The file is not valid UTF-8
+    #[error("file is not valid UTF-8: {0}")]
+    Utf8(#[from] std::str::Utf8Error),
+}
+
+/** An XML file mapped into memory, to read objects from it without copying it into a
+[`String`] first; e.g. for corpus files of several gigabytes.
+
+The file is validated as UTF-8 once, when it is [opened](Self::open). Objects
+[parsed](Self::parse) from it borrow from the map like from any `&str` (e.g. the names and
+strings of an [`OpenMath<'_>`](crate::OpenMath)), so they can not outlive it:
+```compile_fail
+# use openmath::{OpenMath, de::MmapDocument};
+let om: OpenMath<'_> = {
+    // SAFETY: the file is not modified while mapped
+    let doc = unsafe { MmapDocument::open("objects.xml") }.expect("exists");
+    doc.parse().expect("is valid")
+};
+```
+
+Like for any memory map, the file must not be modified (in particular, truncated) by other
+processes while it is open, which is why [`open`](Self::open) is `unsafe`.
+
+# Examples
+```no_run
+use openmath::{OpenMath, de::MmapDocument};
+
+// SAFETY: nothing else writes to the corpus while it is read
+let doc = unsafe { MmapDocument::open("corpus.xml") }.expect("is a UTF-8 file");
+for island in doc.objects::<OpenMath>() {
+    let (range, om) = island.expect("is valid");
+    println!("{}..{}: {}", range.start, range.end, om.kind());
+}
+```
+*/
+pub struct MmapDocument {
+    map: memmap2::Mmap,
+}
+
+impl MmapDocument {
+    /// Maps the file at `path` into memory and checks that it is valid UTF-8.
+    ///
+    /// # Safety
+    /// The file must not be modified or truncated (by this or any other process) as long as
+    /// the returned [`MmapDocument`] exists. Otherwise, reading from it may crash the process
+    /// (e.g. with `SIGBUS`), and objects borrowed from it may change underneath.
+    ///
+    /// # Errors
+    /// If the file can not be opened or mapped, or is not valid UTF-8.
+    pub unsafe fn open(path: impl AsRef<Path>) -> Result<Self, MmapError> {
+        let file = std::fs::File::open(path)?;
+        // SAFETY: by the caller
+        let map = unsafe { memmap2::Mmap::map(&file) }?;
+        std::str::from_utf8(&map)?;
+        Ok(Self { map })
+    }
+
+    /// The contents of the file; validated as UTF-8 again on every call, which is fast
+    /// compared to parsing them.
+    ///
+    /// # Panics
+    /// If the file is no longer valid UTF-8, which requires violating the safety
+    /// requirements of [`open`](Self::open).
+    #[inline]
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        std::str::from_utf8(&self.map).expect("checked in `open`, and not modified since")
+    }
+
+    /// Reads the OMOBJ in the file, like [`OMObject::from_openmath_xml`].
+    ///
+    /// # Errors
+    /// If the file does not contain a valid object, or converting it fails.
+    #[inline]
+    pub fn parse<'de, O: OMDeserializable<'de>>(&'de self) -> Result<O, XmlReadError<O::Err>> {
+        OMObject::<O>::from_openmath_xml(self.as_str())
+    }
+
+    /// Reads every OMOBJ in the file, in order, together with its byte range in the file;
+    /// e.g. for files with many concatenated OMOBJs, or OMOBJs embedded in other XML. See
+    /// [`extract_openmath_xml`].
+    #[inline]
+    #[must_use]
+    pub fn objects<'de, O: OMDeserializable<'de>>(&'de self) -> Islands<'de, O> {
+        extract_openmath_xml(self.as_str())
+    }
+}
+
+impl std::fmt::Debug for MmapDocument {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MmapDocument")
+            .field("len", &self.map.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{borrow::Cow, io::Write};
+
+    use super::{MmapDocument, MmapError};
+    use crate::OpenMath;
+
+    /// The number of OMOBJs in the generated fixture
+    const OBJECTS: usize = 20_000;
+
+    fn write(
+        name: &str,
+        contents: impl FnOnce(&mut std::io::BufWriter<std::fs::File>),
+    ) -> std::path::PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("openmath-mmap-{name}-{}.xml", std::process::id()));
+        let mut file = std::io::BufWriter::new(std::fs::File::create(&path).expect("can create"));
+        contents(&mut file);
+        file.flush().expect("can write");
+        path
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn many_objects() {
+        let path = write("many", |f| {
+            for i in 0..OBJECTS {
+                writeln!(
+                    f,
+                    r#"<OMOBJ version="2.0"><OMA><OMS cd="arith1" name="plus"/><OMI>{i}</OMI><OMSTR>item {i}</OMSTR><OMV name="x{i}"/></OMA></OMOBJ>"#
+                )
+                .expect("can write");
+            }
+        });
+        // SAFETY: the file is only removed after `doc` is dropped
+        let doc = unsafe { MmapDocument::open(&path) }.expect("is valid");
+        assert!(doc.as_str().len() > 1_000_000);
+        let mut count = 0;
+        for (i, island) in doc.objects::<OpenMath>().enumerate() {
+            let (range, om) = island.expect("is valid");
+            assert!(doc.as_str()[range].starts_with("<OMOBJ"));
+            let OpenMath::OMA { arguments, .. } = &om else {
+                panic!("is an OMA")
+            };
+            assert_eq!(arguments[0].as_i64(), Some(i64::try_from(i).expect("fits")));
+            // strings borrow from the map
+            assert!(matches!(
+                &arguments[2],
+                OpenMath::OMV { name: Cow::Borrowed(n), .. } if *n == format!("x{i}")
+            ));
+            count += 1;
+        }
+        assert_eq!(count, OBJECTS);
+        // a file with several objects is not a single one
+        assert!(doc.parse::<OpenMath>().is_err());
+        drop(doc);
+        std::fs::remove_file(&path).expect("can remove");
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn single_object() {
+        let path = write("single", |f| {
+            f.write_all("<OMOBJ><OMSTR>café</OMSTR></OMOBJ>".as_bytes())
+                .expect("can write");
+        });
+        // SAFETY: the file is only removed after `doc` is dropped
+        let doc = unsafe { MmapDocument::open(&path) }.expect("is valid");
+        let om = doc.parse::<OpenMath>().expect("is valid");
+        assert!(matches!(
+            om,
+            OpenMath::OMSTR {
+                string: Cow::Borrowed("café"),
+                ..
+            }
+        ));
+        drop(doc);
+        std::fs::remove_file(&path).expect("can remove");
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn errors() {
+        let path = write("latin1", |f| {
+            f.write_all(b"<OMOBJ><OMSTR>caf\xe9</OMSTR></OMOBJ>")
+                .expect("can write");
+        });
+        // SAFETY: the file is not modified
+        let Err(e) = (unsafe { MmapDocument::open(&path) }) else {
+            panic!("is not UTF-8")
+        };
+        assert!(
+            matches!(&e, MmapError::Utf8(e) if e.valid_up_to() == 17),
+            "{e}"
+        );
+        std::fs::remove_file(&path).expect("can remove");
+
+        // SAFETY: the file is not modified
+        let Err(e) = (unsafe { MmapDocument::open(&path) }) else {
+            panic!("does not exist")
+        };
+        assert!(matches!(e, MmapError::Io(_)), "{e}");
+    }
+}
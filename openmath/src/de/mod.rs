@@ -1,24 +1,661 @@
 /*! <span style="font-variant:small-caps;">OpenMath</span> Deserialization; [OMDeserializable] and related types
+
+# Tracing
+With the `tracing` feature, the XML and serde deserializers open a
+[`trace_span!`](https://docs.rs/tracing/latest/tracing/macro.trace_span.html) for every element
+(recording its [kind](OMKind), the cdbase in effect and, for XML, its byte offset) and every
+argument (recording its index), and emit an event for every call to
+[`from_openmath`](OMDeserializable::from_openmath): at `TRACE` level if it succeeds, at `DEBUG`
+level with the error if it fails. Without the feature, or without a subscriber, all of this
+compiles to (almost) nothing.
+
+```
+# #[cfg(feature = "tracing")] {
+use openmath::{OpenMath, de::{OMDeserializable, OMObject}};
+use tracing_subscriber::{filter::LevelFilter, util::SubscriberInitExt};
+
+let _guard = tracing_subscriber::fmt()
+    .with_max_level(LevelFilter::TRACE)
+    .without_time()
+    .set_default();
+let xml = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/doc/roundtrip.xml"));
+let om = OMObject::<OpenMath>::from_openmath_xml(xml).expect("is valid");
+assert!(matches!(om, OpenMath::OMBIND { .. }));
+# }
+```
+
+# Untrusted input
+Deserialization does not panic on any input; malformed documents are rejected with an error
+(the decoders are fuzzed, see the `fuzzing` feature). It does however recurse once per nesting
+level, so untrusted XML should be read with a [maximal depth](Limits::with_max_depth) (and
+possibly a [`Budget`]); serde deserializers usually bound their recursion themselves.
+Duplicate XML attributes are rejected, unless [lenient](AttributePolicy::Lenient).
+
+# Compatibility
+JSON produced by other implementations (e.g. openmath-js, or Java libraries serializing with
+Jackson) deviates from the JSON encoding in a few ways; the serde deserializer handles these
+as follows (see `tests/compat` for hand-written examples of each):
+
+| Quirk | Handling |
+|-------|----------|
+| `OMOBJ` without the `"openmath"` version key | accepted; the [version](OMObject::version) is `None` |
+| `"integer"` given as a JSON string (for big values) | accepted, like `"decimal"` |
+| `"cdbase"` with a trailing slash | cdbases are compared modulo [normalization](crate::CdBase::normalize); symbols inheriting e.g. `http://www.openmath.org/cd/` get the standard [`CD_BASE`](crate::CD_BASE) |
+| `OMB` given only as `"base64"` | accepted |
+| `"bytes"` given as an array of integers | accepted if all are in `0..=255` |
+| `"base64"` wrapped into lines (MIME-style) | accepted; ASCII whitespace in base64 is skipped, as in XML |
+| `"id"` on objects other than `OMOBJ` (in any position) | accepted and ignored; `OMR`s referring to such ids are not resolved |
+| optional fields given as `null` (e.g. `"cdbase": null`) | treated as absent |
+| floats in exponent notation (`1.0E-10`) | accepted |
+
+<span style="font-variant:small-caps;">OpenMath</span> 1.0 XML documents need no option:
+elements are recognized by their local name whether they are in the
+<span style="font-variant:small-caps;">OpenMath</span> namespace or in none (also when
+[extracting islands](extract_openmath_xml) from a host document), and an OMOBJ without
+`version` is accepted by any [`VersionPolicy`]. CD names are read as written.
+
+# JSON array form
+Besides JSON objects, the serde deserializer accepts the array (compact) form, in which every
+object is a positional array starting with its kind; it is what
+[`openmath_serde_compact`](crate::OMSerializable::openmath_serde_compact) and
+[`ser::OMObject::openmath_serde_compact`](crate::ser::OMObject::openmath_serde_compact) write
+(see the `*.array.json` files in `tests/corpus` for examples):
+
+| kind        | array                                                    |
+|-------------|----------------------------------------------------------|
+| `OMOBJ`     | `["OMOBJ", version, cdbase, object]`                     |
+| `OMI`       | `["OMI", id, integer]`                                   |
+| `OMF`       | `["OMF", id, float]`                                     |
+| `OMSTR`     | `["OMSTR", id, string]`                                  |
+| `OMB`       | `["OMB", id, bytes]`                                     |
+| `OMV`       | `["OMV", id, name]`                                      |
+| `OMR`       | `["OMR", id, href]`                                      |
+| `OMS`       | `["OMS", id, cdbase, cd, name]`                          |
+| `OME`       | `["OME", id, cdbase, symbol, arguments]`                 |
+| `OMA`       | `["OMA", id, cdbase, applicant, arguments]`              |
+| `OMBIND`    | `["OMBIND", id, cdbase, binder, variables, object]`      |
+| `OMATTR`    | `["OMATTR", id, cdbase, [[symbol, value], ...], object]` |
+| `OMFOREIGN` | `["OMFOREIGN", id, foreign, encoding]`                   |
+
+- `id` and `cdbase` are positional: they always take up their place, as `null` if absent.
+  The only exception is the `cdbase` of an `OMOBJ`, which may also be left out
+  (`["OMOBJ", version, object]`); its `version` may be `null`.
+- The `symbol` of an error and the keys of attributions are written without `kind`, i.e. as
+  `[id, cdbase, cd, name]`.
+- `integer` is a number, or a decimal string for integers that do not fit into an `i128`;
+  `float` is a number, or a decimal string for values that are not JSON numbers (`NaN`,
+  `inf`, `-inf`); `bytes` is an array of numbers in `0..=255`.
+- The `arguments` of `OME` and `OMA` may be `null` or left out if there are none;
+  the arguments of an `OME` and attribute values may be `OMFOREIGN`s.
+- The `variables` of an `OMBIND` are `OMV`s, or `OMATTR`s around `OMV`s (for attributed
+  variables).
+- The `encoding` of an `OMFOREIGN` may be `null` or left out.
+- Trailing elements of the arrays in the table are ignored.
+
+The serializers write every element, using `null` for absent ones, and never write `OMR`s.
 */
 
+// before the modules, so they can use it, too
+#[allow(rustdoc::redundant_explicit_links)]
+/// Evaluates `$read` with `$reader` bound to a new reader of type `$ty` for `$input`,
+/// configured by `$options`; wrapped in an [`xml::CdBases`] that appends to the
+/// [cdbase origins](Options::with_cdbase_origins) if the options ask for them
+#[cfg(feature = "xml-read")]
+macro_rules! read_configured {
+    ($ty:ty, $input:expr, $options:expr, |$reader:ident| $read:expr) => {{
+        let options: &Options<'_> = $options;
+        if let Some(origins) = options.cdbase_origins {
+            let mut $reader = <xml::CdBases<$ty> as xml::Events<'_>>::new($input);
+            xml::Events::configure(&mut $reader, options);
+            let result = $read;
+            origins.borrow_mut().append(&mut $reader.origins);
+            result
+        } else {
+            let mut $reader = <$ty as xml::Events<'_>>::new($input);
+            xml::Events::configure(&mut $reader, options);
+            $read
+        }
+    }};
+}
+
+/// Reads `input` like [`OMDeserializable::from_openmath_xml_with`], but converts every node
+/// with `convert` instead of [`from_openmath`](OMDeserializable::from_openmath); for readers
+/// that build objects in storage of their own, which `convert` borrows.
+#[cfg(feature = "xml-read")]
+pub(crate) fn from_openmath_xml_converting<'s, O, F>(
+    input: &'s str,
+    options: &Options<'_>,
+    convert: F,
+) -> Result<O, xml::XmlReadError<O::Err>>
+where
+    O: OMDeserializable<'s, Ret = O>,
+    F: FnMut(OM<'s, O>, &str) -> Result<O, O::Err>,
+{
+    use xml::Readable;
+    read_configured!(
+        xml::Converting<xml::FromString<'s>, F>,
+        (input, convert),
+        options,
+        |reader| reader.read(options.default_cdbase)
+    )
+}
+
 //#[cfg(feature = "serde")]
 //pub(crate) mod serde_aux;
+#[cfg(feature = "bump")]
+mod bump;
+mod compact;
+mod eq;
+#[cfg(feature = "xml-read")]
+mod framed;
+#[cfg(feature = "xml-read")]
+mod islands;
+#[cfg(feature = "mmap")]
+mod mmap;
+#[cfg(feature = "xml-read")]
+mod multidoc;
+mod positional;
 #[cfg(feature = "serde")]
 pub(crate) mod serde_impl;
+#[cfg(feature = "xml-read")]
+mod spans;
+#[cfg(feature = "xml-read")]
 pub(crate) mod xml;
-use std::borrow::Cow;
+use std::{borrow::Cow, rc::Rc, sync::Arc};
 
-use crate::{OMKind, OMMaybeForeign};
+use crate::OMMaybeForeign;
+#[cfg(feature = "bump")]
+pub use bump::{Arena, AttrIn, BoundVariableIn, OpenMathIn};
+pub use compact::Compact;
+#[cfg(feature = "xml-read")]
+pub use framed::{read_framed, read_framed_with};
+#[cfg(feature = "xml-read")]
+pub use islands::{Islands, extract_openmath_xml};
+#[cfg(feature = "mmap")]
+pub use mmap::{MmapDocument, MmapError};
+#[cfg(feature = "xml-read")]
+pub use multidoc::MultiDocResolver;
+pub use positional::{Deferred, Headed, Lossy, Partial, PositionalError};
 #[cfg(feature = "serde")]
 pub use serde_impl::OMFromSerde;
+#[cfg(feature = "xml-read")]
+pub use spans::{Spanned, SpannedRet};
+#[cfg(feature = "xml-read")]
+pub use xml::{CdBaseOrigin, PartialParse, PositionedError, XmlReadError};
+
+/// How many arguments of an [OMA](crate::OMKind::OMA) (and variables of an
+/// [OMBIND](crate::OMKind::OMBIND)) an [`OM`] stores inline.
+///
+/// 2 by default, or the largest of the enabled `smallvec-1`, `smallvec-4` and `smallvec-8`
+/// features. Larger capacities save allocations for wide applications, but make every
+/// [`OM`] larger, and so the stack the XML reader needs per level of nesting.
+pub const INLINE_ARGS: usize = if cfg!(feature = "smallvec-8") {
+    8
+} else if cfg!(feature = "smallvec-4") {
+    4
+} else if cfg!(feature = "smallvec-1") {
+    1
+} else {
+    2
+};
+
+/** The arguments of an [`OM::OMA`], with [`INLINE_ARGS`] of them stored inline. Name this
+alias rather than the [`SmallVec`](smallvec::SmallVec) it stands for, whose capacity depends
+on the features enabled.
+
+# Examples
+```rust
+use openmath::de::{Args, OM};
+
+fn arity<I>(om: &OM<'_, I>) -> usize {
+    match om {
+        OM::OMA { arguments, .. } => arguments.len(),
+        _ => 0,
+    }
+}
+fn arguments<I>(om: OM<'_, I>) -> Args<I> {
+    match om {
+        OM::OMA { arguments, .. } => arguments,
+        _ => Args::new(),
+    }
+}
+```
+*/
+pub type Args<T> = smallvec::SmallVec<T, INLINE_ARGS>;
+/// The variables of an [`OM::OMBIND`], with [`INLINE_ARGS`] of them stored inline; see
+/// [`Args`]
+pub type Vars<T> = smallvec::SmallVec<T, INLINE_ARGS>;
+/// The attribution pairs of an [`OM`]
+pub type Attrs<T> = Vec<T>;
+
+/// Calls [`from_openmath`](OMDeserializable::from_openmath); with the `tracing` feature,
+/// emits an event with the outcome.
+#[inline]
+pub(crate) fn convert<'de, O: OMDeserializable<'de>>(
+    om: OM<'de, O::Ret>,
+    cdbase: &str,
+) -> Result<O::Ret, O::Err> {
+    #[cfg(feature = "tracing")]
+    let kind = om.kind();
+    let r = O::from_openmath(om, cdbase);
+    #[cfg(feature = "tracing")]
+    match &r {
+        Ok(_) => tracing::trace!(%kind, cdbase, "from_openmath succeeded"),
+        Err(e) => tracing::debug!(%kind, cdbase, error = %e, "from_openmath failed"),
+    }
+    r
+}
+
+/// The error of deserializing an [`OpenMath`](crate::OpenMath) containing an
+/// [OMR](OM::OMR) that is not resolved (see [`OMRPolicy`]); holds the `href`.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("unresolved reference {0:?}")]
+pub struct UnresolvedReference(pub String);
+
+/// The error of resolving an [OMR](OM::OMR) under the [`OMRPolicy`] of the
+/// [`Options`] it is read with.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum ReferenceError {
+    /// The resolver returns nothing for the reference
+    #[error(transparent)]
+    Unresolved(#[from] UnresolvedReference),
+    /// Resolving `id` requires resolving `id` again; `path` lists the references being
+    /// resolved, outermost first and ending with `id`.
+    #[error("cyclic reference {id:?} (via {})", path.join(" -> "))]
+    CyclicReference { id: String, path: Vec<String> },
+    /// The objects returned by the resolver have more nodes in total than the budget; see
+    /// [`Options::with_omr_budget`].
+    #[error("resolving references exceeds the budget of {0} nodes")]
+    BudgetExceeded(usize),
+    /// Resolving requires resolving more than [`OMRPolicy::MAX_DEPTH`] references within
+    /// each other (which would overflow the stack if resolving parses).
+    #[error("references nested more than {0} deep")]
+    TooDeep(usize),
+}
+
+/** How [OMR](OM::OMR)s are read: either they are passed on to
+[`from_openmath`](OMDeserializable::from_openmath) as they are (the default; e.g.
+[`OpenMath`](crate::OpenMath), which has no variant for references, fails with an
+[`UnresolvedReference`]), or they are replaced by the objects a resolver returns for their
+`href`s (with the attributes of the OMR added to those of the object), which are then
+converted as if they had been read in their place.
+
+The policy is passed with the [`Options`] (see [`Options::with_omr_policy`]). A resolver is
+given the options to read other documents with: they resolve references with the same
+policy, as part of the read that called the resolver. Resolution is guarded against
+malicious documents: a reference whose resolution (e.g. by parsing another document with
+these options) requires resolving the same `href` again fails with
+[`ReferenceError::CyclicReference`], and once the resolved objects amount to more nodes
+than the [budget](Options::with_omr_budget), with [`ReferenceError::BudgetExceeded`]; so
+documents sharing subterms cannot expand to astronomically large objects. References
+nested more than [`MAX_DEPTH`](Self::MAX_DEPTH) deep fail with [`ReferenceError::TooDeep`].
+
+# Examples
+```
+# #[cfg(feature = "xml-read")]
+# {
+use openmath::{OpenMath, de::{OMDeserializable, OMRPolicy, Options}};
+
+let s = r##"<OMA><OMS cd="arith1" name="plus"/><OMR href="#x"/><OMR href="#x"/></OMA>"##;
+assert!(OpenMath::from_openmath_xml(s).is_err());
+
+let two = OpenMath::from_sexpr("(omi 2)").expect("is valid");
+let policy = OMRPolicy::resolve(move |href, _| (href == "#x").then(|| two.clone()));
+let om = OpenMath::from_openmath_xml_with(s, Options::new().with_omr_policy(&policy))
+    .expect("is resolved");
+assert_eq!(om, OpenMath::from_sexpr("(oma (oms arith1 plus) (omi 2) (omi 2))").expect("is valid"));
+# }
+```
+*/
+#[derive(Clone, Default)]
+pub enum OMRPolicy {
+    /// Pass OMRs on to [`from_openmath`](OMDeserializable::from_openmath)
+    #[default]
+    Error,
+    /// Replace an OMR by the object returned for its `href` (and the options to read
+    /// other documents with), or fail with an [`UnresolvedReference`] if there is none
+    #[allow(clippy::type_complexity)]
+    Resolve(std::rc::Rc<dyn Fn(&str, Options<'_>) -> Option<crate::OpenMath<'static>>>),
+}
+
+/// The references being resolved by a read and the reads its resolver started
+#[cfg(any(feature = "xml-read", feature = "serde"))]
+#[derive(Debug)]
+pub(crate) struct Expansion {
+    /// The `href`s currently being resolved, outermost first
+    resolving: Vec<String>,
+    /// The number of nodes resolved so far
+    nodes: usize,
+    budget: usize,
+    /// The error that made a nested resolution fail, to report it instead of an
+    /// [`UnresolvedReference`] if the resolver gives up
+    failure: Option<ReferenceError>,
+}
+#[cfg(any(feature = "xml-read", feature = "serde"))]
+impl Expansion {
+    const fn new(budget: usize) -> Self {
+        Self {
+            resolving: Vec::new(),
+            nodes: 0,
+            budget,
+            failure: None,
+        }
+    }
+}
+
+/// The number of nodes in `om`, counting every object, attribute key and bound variable
+#[cfg(any(feature = "xml-read", feature = "serde"))]
+fn nodes(om: &crate::OpenMath<'_>) -> usize {
+    use crate::OpenMath;
+    fn maybe(o: &OMMaybeForeign<'_, OpenMath<'_>>) -> usize {
+        match o {
+            OMMaybeForeign::OM(o) => nodes(o),
+            OMMaybeForeign::Foreign { .. } => 1,
+        }
+    }
+    fn attrs(a: &[crate::Attr<'_, OMMaybeForeign<'_, OpenMath<'_>>>]) -> usize {
+        a.iter().map(|a| 1 + maybe(&a.value)).sum()
+    }
+    1 + attrs(om.attributes())
+        + match om {
+            OpenMath::OMA {
+                applicant,
+                arguments,
+                ..
+            } => nodes(applicant) + arguments.iter().map(nodes).sum::<usize>(),
+            OpenMath::OME { arguments, .. } => arguments.iter().map(maybe).sum(),
+            OpenMath::OMBIND {
+                binder,
+                variables,
+                object,
+                ..
+            } => {
+                nodes(binder)
+                    + variables
+                        .iter()
+                        .map(|v| 1 + attrs(&v.attributes))
+                        .sum::<usize>()
+                    + nodes(object)
+            }
+            _ => 0,
+        }
+}
+
+impl OMRPolicy {
+    /// The default [budget](Options::with_omr_budget): the number of nodes all objects
+    /// resolved while reading one object may have in total.
+    pub const DEFAULT_BUDGET: usize = 1 << 20;
+
+    /// The maximal number of references resolved within each other, e.g. by a resolver
+    /// parsing a document that refers to another one.
+    pub const MAX_DEPTH: usize = 32;
+
+    /// [`Resolve`](Self::Resolve) references with `resolver`
+    #[inline]
+    pub fn resolve(
+        resolver: impl Fn(&str, Options<'_>) -> Option<crate::OpenMath<'static>> + 'static,
+    ) -> Self {
+        Self::Resolve(std::rc::Rc::new(resolver))
+    }
+}
+
+impl std::fmt::Debug for OMRPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Error => f.write_str("Error"),
+            Self::Resolve(_) => f.write_str("Resolve(..)"),
+        }
+    }
+}
+
+/// The resolver of the [`OMRPolicy`] a front-end reads with, and the [`Expansion`] it
+/// takes part in
+#[cfg(any(feature = "xml-read", feature = "serde"))]
+#[derive(Clone)]
+pub(crate) struct References {
+    #[allow(clippy::type_complexity)]
+    resolver: std::rc::Rc<dyn Fn(&str, Options<'_>) -> Option<crate::OpenMath<'static>>>,
+    expansion: std::rc::Rc<std::cell::RefCell<Expansion>>,
+}
+#[cfg(any(feature = "xml-read", feature = "serde"))]
+impl References {
+    /// The references to resolve with `options`; `None` if OMRs are passed on as they are
+    pub(crate) fn new(options: &Options<'_>) -> Option<Self> {
+        match options.omr_policy? {
+            OMRPolicy::Error => None,
+            OMRPolicy::Resolve(resolver) => Some(Self {
+                resolver: resolver.clone(),
+                expansion: options.expansion.cloned().unwrap_or_else(|| {
+                    std::rc::Rc::new(std::cell::RefCell::new(Expansion::new(options.omr_budget)))
+                }),
+            }),
+        }
+    }
+
+    /// Resolves `href`. The resolver is given the options to read other documents with, so
+    /// that their references continue this resolution: the budget of the outermost read
+    /// applies, and cycles through several documents are detected.
+    fn resolve(&self, href: &str) -> Result<crate::OpenMath<'static>, ReferenceError> {
+        /// Forgets the `href` being resolved, even if the resolver panics
+        struct Resolving<'e>(&'e std::cell::RefCell<Expansion>);
+        impl Drop for Resolving<'_> {
+            fn drop(&mut self) {
+                self.0.borrow_mut().resolving.pop();
+            }
+        }
+        let outer_failure = {
+            let mut e = self.expansion.borrow_mut();
+            if e.resolving.iter().any(|h| h == href) {
+                let mut path = e.resolving.clone();
+                path.push(href.to_string());
+                let err = ReferenceError::CyclicReference {
+                    id: href.to_string(),
+                    path,
+                };
+                e.failure = Some(err.clone());
+                return Err(err);
+            }
+            if e.resolving.len() >= OMRPolicy::MAX_DEPTH {
+                let err = ReferenceError::TooDeep(OMRPolicy::MAX_DEPTH);
+                e.failure = Some(err.clone());
+                return Err(err);
+            }
+            e.resolving.push(href.to_string());
+            e.failure.take()
+        };
+        let resolving = Resolving(&self.expansion);
+        let policy = OMRPolicy::Resolve(self.resolver.clone());
+        let mut options = Options::new().with_omr_policy(&policy);
+        options.expansion = Some(&self.expansion);
+        // the resolver may deserialize, too; so no borrow is held while it runs
+        let om = (self.resolver)(href, options);
+        drop(resolving);
+        let mut e = self.expansion.borrow_mut();
+        let failure = std::mem::replace(&mut e.failure, outer_failure);
+        let Some(om) = om else {
+            // passed on, in case the enclosing resolution fails because of this one
+            e.failure.clone_from(&failure);
+            return Err(failure.unwrap_or_else(|| UnresolvedReference(href.to_string()).into()));
+        };
+        e.nodes = e.nodes.saturating_add(nodes(&om));
+        if e.nodes > e.budget {
+            let err = ReferenceError::BudgetExceeded(e.budget);
+            e.failure = Some(err.clone());
+            return Err(err);
+        }
+        Ok(om)
+    }
+
+    /// Like [`convert`], but resolves `om` first if it is an OMR and `references` resolve
+    /// references; the outer `Err` is that of resolving.
+    #[inline]
+    pub(crate) fn convert<'de, O: OMDeserializable<'de>>(
+        references: Option<&mut Self>,
+        om: OM<'de, O::Ret>,
+        cdbase: &str,
+    ) -> Result<Result<O::Ret, O::Err>, ReferenceError> {
+        Self::convert_with(references, om, cdbase, convert::<O>)
+    }
+
+    /// Like [`convert`](Self::convert), but converts nodes with `convert` instead of
+    /// [`from_openmath`](OMDeserializable::from_openmath)
+    pub(crate) fn convert_with<'de, R, E>(
+        references: Option<&mut Self>,
+        om: OM<'de, R>,
+        cdbase: &str,
+        mut convert: impl FnMut(OM<'de, R>, &str) -> Result<R, E>,
+    ) -> Result<Result<R, E>, ReferenceError> {
+        match (om, references) {
+            (OM::OMR { href, attrs }, Some(references)) => {
+                let resolved = references.resolve(&href)?;
+                Ok(replay(resolved, attrs, cdbase, &mut convert))
+            }
+            (om, _) => Ok(convert(om, cdbase)),
+        }
+    }
+}
+
+/// Converts a node read against a cdbase; see [`replay`]
+#[cfg(any(feature = "xml-read", feature = "serde"))]
+type Convert<'c, 'de, R, E> = &'c mut dyn FnMut(OM<'de, R>, &str) -> Result<R, E>;
 
-type Args<T> = smallvec::SmallVec<T, 2>;
-type Vars<T> = smallvec::SmallVec<T, 2>;
-type Attrs<T> = Vec<T>;
+/// Converts `om`, which was resolved for an OMR with the attributes `extra` read against
+/// `cdbase`, bottom-up with `convert` as if it had been read in its place
+#[cfg(any(feature = "xml-read", feature = "serde"))]
+#[allow(clippy::too_many_lines)]
+fn replay<'de, R, E>(
+    om: crate::OpenMath<'static>,
+    extra: Attrs<OMAttr<'de, R>>,
+    cdbase: &str,
+    convert: Convert<'_, 'de, R, E>,
+) -> Result<R, E> {
+    use crate::OpenMath;
+    type Attributes = Vec<crate::Attr<'static, OMMaybeForeign<'static, OpenMath<'static>>>>;
+    fn maybe<'de, R, E>(
+        o: OMMaybeForeign<'static, OpenMath<'static>>,
+        cdbase: &str,
+        convert: Convert<'_, 'de, R, E>,
+    ) -> Result<OMMaybeForeign<'de, R>, E> {
+        Ok(match o {
+            OMMaybeForeign::OM(o) => OMMaybeForeign::OM(replay(o, Vec::new(), cdbase, convert)?),
+            OMMaybeForeign::Foreign { encoding, value } => {
+                OMMaybeForeign::Foreign { encoding, value }
+            }
+        })
+    }
+    fn attrs<'de, R, E>(
+        attributes: Attributes,
+        cdbase: &str,
+        convert: Convert<'_, 'de, R, E>,
+    ) -> Result<Attrs<OMAttr<'de, R>>, E> {
+        attributes
+            .into_iter()
+            .map(|a| Ok(crate::Attr::new(a.key, maybe(a.value, cdbase, convert)?)))
+            .collect()
+    }
+    let mut own_cdbase = None;
+    let mut om = match om {
+        OpenMath::OMI { int, attributes } => OM::OMI {
+            int,
+            attrs: attrs(attributes, cdbase, convert)?,
+        },
+        OpenMath::OMF { float, attributes } => OM::OMF {
+            float: float.0,
+            lexical: None,
+            attrs: attrs(attributes, cdbase, convert)?,
+        },
+        OpenMath::OMSTR { string, attributes } => OM::OMSTR {
+            string,
+            attrs: attrs(attributes, cdbase, convert)?,
+        },
+        OpenMath::OMB { bytes, attributes } => OM::OMB {
+            bytes,
+            attrs: attrs(attributes, cdbase, convert)?,
+        },
+        OpenMath::OMV { name, attributes } => OM::OMV {
+            name,
+            attrs: attrs(attributes, cdbase, convert)?,
+        },
+        OpenMath::OMS {
+            cd,
+            name,
+            cdbase: symbol_cdbase,
+            cdbase_inherited,
+            attributes,
+        } => {
+            own_cdbase.clone_from(&symbol_cdbase);
+            OM::OMS {
+                cdbase: symbol_cdbase.filter(|_| !cdbase_inherited),
+                cd,
+                name,
+                attrs: attrs(attributes, cdbase, convert)?,
+            }
+        }
+        OpenMath::OMA {
+            applicant,
+            arguments,
+            attributes,
+        } => OM::OMA {
+            applicant: replay(*applicant, Vec::new(), cdbase, convert)?,
+            arguments: arguments
+                .into_iter()
+                .map(|a| replay(a, Vec::new(), cdbase, convert))
+                .collect::<Result<_, _>>()?,
+            attrs: attrs(attributes, cdbase, convert)?,
+        },
+        OpenMath::OMBIND {
+            binder,
+            variables,
+            object,
+            attributes,
+        } => OM::OMBIND {
+            binder: replay(*binder, Vec::new(), cdbase, convert)?,
+            variables: variables
+                .into_iter()
+                .map(|v| Ok((v.name, attrs(v.attributes, cdbase, convert)?)))
+                .collect::<Result<_, _>>()?,
+            object: replay(*object, Vec::new(), cdbase, convert)?,
+            attrs: attrs(attributes, cdbase, convert)?,
+        },
+        OpenMath::OME {
+            cd,
+            name,
+            cdbase: error_cdbase,
+            arguments,
+            attributes,
+        } => {
+            own_cdbase.clone_from(&error_cdbase);
+            OM::OME {
+                cdbase: error_cdbase,
+                cd,
+                name,
+                arguments: arguments
+                    .into_iter()
+                    .map(|a| maybe(a, cdbase, convert))
+                    .collect::<Result<_, _>>()?,
+                attrs: attrs(attributes, cdbase, convert)?,
+            }
+        }
+    };
+    om.attrs_mut().extend(extra);
+    convert(om, own_cdbase.as_deref().unwrap_or(cdbase))
+}
 
+/// An attribute of an [`OM`].
+///
+/// Attribute values are deserialized like every other node; i.e. in the [`OM`] passed to
+/// [`from_openmath`](OMDeserializable::from_openmath), `I` is
+/// [`Ret`](OMDeserializable::Ret), and the value has already gone through
+/// [`from_openmath`](OMDeserializable::from_openmath) itself (unless it is an
+/// [`OMFOREIGN`](crate::OMKind::OMFOREIGN)). Use
+/// [`OMMaybeForeign::try_convert`] to turn it into the final type.
+///
+/// The same type is used for the attributes of objects and of bound
+/// [variables](OM::OMBIND), in both the XML and the serde front-end, so values of either may
+/// be arbitrary objects or [`OMFOREIGN`](crate::OMKind::OMFOREIGN)s.
 pub type OMAttr<'o, I> = crate::Attr<'o, crate::OMMaybeForeign<'o, I>>;
 
-#[allow(rustdoc::redundant_explicit_links)]
 /**  Trait for types that can be deserialized from
 <span style="font-variant:small-caps;">OpenMath</span> objects.
 
@@ -31,6 +668,13 @@ If e.g. the expression is `OMA( OMS(s1), OMA( OMS(s2), OMI(1) ), OMI(3) )`, then
 gets called successively with `OMS(s1)`, `OMS(s2)`, `OMI(1)`, `OMA( OMS(s2), OMI(1) )`, `OMI(3)`,
 and finally `OMA( OMS(s1), OMA( OMS(s2), OMI(1) ), OMI(3) )`
 (See below for an example).
+
+Precisely, every front-end guarantees that the method is called exactly once per node, and
+for every node only after it has been called for all nodes below it: its subterms, its
+[attribute values](#attributes) and those of its bound variables. The order of *siblings*
+(and their subtrees) is only guaranteed to be the document order for XML and the array form
+of JSON; for the object form, it depends on the order of the keys in the input, since values
+may have to be buffered until e.g. the `kind` or `cdbase` of their parent is known.
 Deserialization is considered to have failed (and
 will return an Error), if for the *final* instance `r` of [`Ret`](OMDeserializable::Ret),
 <code>r.[try_into](TryInto::try_into)()</code> errors.
@@ -49,7 +693,9 @@ can be deserialized, and is implemented for any <code>S where for<'a> S:[OMDeser
   from a `&'de str` using [from_openmath_xml](OMDeserializable::from_openmath_xml).
   If `Self` can be deserialized into owned values (i.e. implements <code>for<'a> [OMDeserializable]<'a></code>),
   the [`OMDeserializableOwned`] trait also provides
-  <code>[from_openmath_xml_reader](OMDeserializableOwned::from_openmath_xml_reader)<R: [BufRead](std::io::BufRead)></code>.
+  <code>[from_openmath_xml_reader](OMDeserializableOwned::from_openmath_xml_reader)<R: [BufRead](std::io::BufRead)></code>;
+  otherwise, [`read_into_buffer`] reads from a [BufRead](std::io::BufRead) into a buffer
+  provided by the caller, from which `Self` can then borrow.
 
 # Examples
 
@@ -100,6 +746,7 @@ impl<'d> OMDeserializable<'d> for SimplifiedInt {
                 // => We send it back, so we can take care of it later, if it
                 // occurs as the head of an OMA expression
                 Ok(either::Right(OM::OMS {
+                    cdbase: None,
                     cd,
                     name,
                     attrs: Vec::new(),
@@ -160,6 +807,9 @@ let s = r#"{
 let r = serde_json::from_str::<'_, OMFromSerde<SimplifiedInt>>(s)
     .expect("valid json, openmath, and arithmetic expression");
 assert_eq!(r.into_inner().0, 4);
+// If the xml-read-feature is active:
+# #[cfg(feature = "xml-read")]
+# {
 let s = r#"
 <OMA cdbase="http://www.openmath.org/cd">
   <OMS cd="arith1" name="plus"/>
@@ -170,7 +820,79 @@ let s = r#"
         .expect("valid xml, openmath, and arithmetic expression");
     assert_eq!(r.0, 4);
 # }
+# }
+```
+
+## Attributes
+
+Attribute values (the `attrs` of every [`OM`]) are deserialized bottom-up as well, so by the
+time a node reaches [`from_openmath`](OMDeserializable::from_openmath), its attribute values
+are already [`Ret`](OMDeserializable::Ret)s. E.g. to read an integer together with its
+`sts.type` annotation:
+```rust
+use either::Either;
+use openmath::{OMMaybeForeign, de::{OM, OMDeserializable}};
+
+#[derive(Clone, Debug)]
+struct TypedInt {
+    value: i128,
+    ty: Option<String>,
+}
+impl TryFrom<Either<Self, (String, String)>> for TypedInt {
+    type Error = &'static str;
+    fn try_from(value: Either<Self, (String, String)>) -> Result<Self, Self::Error> {
+        value.left().ok_or("not an integer")
+    }
+}
+impl<'d> OMDeserializable<'d> for TypedInt {
+    // symbols are intermediate results; they only make sense as attribute values
+    type Ret = Either<Self, (String, String)>;
+    type Err = &'static str;
+    fn from_openmath(om: OM<'d, Self::Ret>, _cdbase: &str) -> Result<Self::Ret, Self::Err> {
+        match om {
+            OM::OMS { cd, name, .. } => Ok(Either::Right((cd.into_owned(), name.into_owned()))),
+            OM::OMI { int, attrs } => {
+                let value = int.is_i128().ok_or("Invalid int value")?;
+                let ty = attrs.into_iter().find_map(|a| match a.value {
+                    OMMaybeForeign::OM(Either::Right((cd, name)))
+                        if a.cd() == "sts" && a.name() == "type" =>
+                    {
+                        Some(format!("{cd}.{name}"))
+                    }
+                    _ => None,
+                });
+                Ok(Either::Left(Self { value, ty }))
+            }
+            _ => Err("Not an integer"),
+        }
+    }
+}
+
+# #[cfg(feature = "xml-read")]
+# {
+let s = r#"
+<OMATTR cdbase="http://www.openmath.org/cd">
+  <OMATP>
+    <OMS cd="sts" name="type"/>
+    <OMS cd="setname1" name="N"/>
+  </OMATP>
+  <OMI>3</OMI>
+</OMATTR>"#;
+let r = TypedInt::from_openmath_xml(s).expect("valid xml, openmath, and integer");
+assert_eq!(r.value, 3);
+assert_eq!(r.ty.as_deref(), Some("setname1.N"));
+# }
 ```
+
+## Errors
+
+The same holds for the arguments of an [OME](crate::OMKind::OME): every front-end delivers
+them as <code>[OMMaybeForeign](crate::OMMaybeForeign)<[Ret](OMDeserializable::Ret)></code>s,
+which are either already deserialized objects or [OMFOREIGN](crate::OMKind::OMFOREIGN)s.
+[`OMMaybeForeign::map`](crate::OMMaybeForeign::map),
+[`try_convert`](crate::OMMaybeForeign::try_convert) and
+[`into_owned`](crate::OMMaybeForeign::into_owned) help converting them.
+
 [^1]: <https://openmath.org/standard/om20-2019-07-01/omstd20.html#sec_json-the-json-encoding>
 */
 pub trait OMDeserializable<'de>: std::fmt::Debug {
@@ -199,8 +921,71 @@ pub trait OMDeserializable<'de>: std::fmt::Debug {
     where
         Self: Sized;
 
+    /** Called before the `index`th child of an object of kind `parent` is read (in the
+    scope of `cdbase`), to [`Skip`](Visit::Skip) it if it is not needed. By default, every
+    child is read.
+
+    Children are counted like the fields of [`OM`]: the applicant of an
+    [OMA](OM::OMA) is `0`, followed by the arguments; the binder of an
+    [OMBIND](OM::OMBIND) is `0` and its body `1`; the arguments of an [OME](OM::OME)
+    (including [OMFOREIGN](crate::OMKind::OMFOREIGN)s) are counted from `0`. Attribute
+    values and bound variables are not visited; see
+    [`Options::with_attr_filter`] for the former.
+
+    A skipped child is never converted, and not even turned into an [`OM`]; the XML reader
+    only checks that it is well-formed XML, the serde front-end reads it as
+    [`IgnoredAny`](https://docs.rs/serde/latest/serde/de/struct.IgnoredAny.html). It is
+    therefore *not* checked to be valid <span style="font-variant:small-caps;">OpenMath</span>.
+
+    # Examples
+    ```
+    use openmath::{OMKind, de::{OM, OMDeserializable, Visit}};
+
+    /// The name of the head symbol of an application, whose arguments are never read
+    #[derive(Debug)]
+    struct Head(String);
+    impl<'d> OMDeserializable<'d> for Head {
+        type Ret = Self;
+        type Err = &'static str;
+        fn from_openmath(om: OM<'d, Self>, _: &str) -> Result<Self, Self::Err> {
+            match om {
+                OM::OMS { name, .. } => Ok(Self(name.into_owned())),
+                OM::OMA { applicant, .. } => Ok(applicant),
+                _ => Err("not an application"),
+            }
+        }
+        fn before_child(parent: OMKind, index: usize, _: &str) -> Visit<Self> {
+            if parent == OMKind::OMA && index > 0 {
+                Visit::Skip(Self(String::new()))
+            } else {
+                Visit::Descend
+            }
+        }
+    }
+
+    # #[cfg(feature = "xml-read")]
+    # {
+    let s = r#"<OMA><OMS cd="arith1" name="plus"/><OMI>1</OMI><OMI>not a number</OMI></OMA>"#;
+    assert_eq!(Head::from_openmath_xml(s).expect("arguments are skipped").0, "plus");
+    # }
+    ```
+    */
+    #[inline]
+    #[must_use]
+    fn before_child(parent: crate::OMKind, index: usize, cdbase: &str) -> Visit<Self::Ret>
+    where
+        Self: Sized,
+    {
+        let _ = (parent, index, cdbase);
+        Visit::Descend
+    }
+
     /// Deserializes self from a string of <span style="font-variant:small-caps;">OpenMath</span> XML.
     ///
+    /// Names and strings are borrowed from `s` where possible; content that has to be
+    /// unescaped or is split across several events (e.g. text and CDATA sections) is
+    /// copied into [owned](std::borrow::Cow::Owned) strings instead.
+    ///
     /// # Errors
     /// iff the string provided is invalid XML, or invalid
     /// <span style="font-variant:small-caps;">OpenMath</span>, or
@@ -208,14 +993,213 @@ pub trait OMDeserializable<'de>: std::fmt::Debug {
     ///
     /// # Examples
     /// See [trait documentation](OMDeserializable)
+    #[cfg(feature = "xml-read")]
     fn from_openmath_xml(input: &'de str) -> Result<Self, xml::XmlReadError<Self::Err>>
     where
         Self: Sized,
     {
         use xml::Readable;
-        <xml::FromString<'de> as Readable<'de, Self>>::new(input).read(None)
+        <xml::FromString<'de> as xml::Events<'de>>::new(input).read(None)
+    }
+
+    /** Like [from_openmath_xml](OMDeserializable::from_openmath_xml), but only reads the
+    object at the start of `input`, ignoring whatever follows it; also returns the length of
+    the input up to the end of that object. [from_openmath_xml](OMDeserializable::from_openmath_xml)
+    instead fails with [`TrailingContent`](xml::XmlReadError::TrailingContent) if anything
+    but whitespace, comments and processing instructions follows the object.
+
+    # Errors
+    see [from_openmath_xml](OMDeserializable::from_openmath_xml)
+
+    # Examples
+    ```
+    use openmath::{OMDeserializable, de::XmlReadError};
+
+    let s = "<OMI>2</OMI><OMI>3</OMI>";
+    assert!(matches!(i32::from_openmath_xml(s), Err(XmlReadError::TrailingContent(12))));
+    let (two, len) = i32::from_openmath_xml_prefix(s).expect("is valid");
+    assert_eq!((two, len), (2, 12));
+    assert_eq!(i32::from_openmath_xml(&s[len..]).expect("is valid"), 3);
+    ```
+    */
+    #[cfg(feature = "xml-read")]
+    fn from_openmath_xml_prefix(
+        input: &'de str,
+    ) -> Result<(Self, usize), xml::XmlReadError<Self::Err>>
+    where
+        Self: Sized,
+    {
+        use xml::Readable;
+        let mut reader = <xml::FromString<'de> as xml::Events<'de>>::new(input);
+        let object = reader.read_prefix(None)?;
+        Ok((object, reader.consumed()))
+    }
+
+    /** Like [from_openmath_xml](OMDeserializable::from_openmath_xml), but with the given
+    [`Options`]; the [`version_policy`](Options::version_policy) is irrelevant here, since
+    bare objects have no version.
+
+    # Errors
+    see [from_openmath_xml](OMDeserializable::from_openmath_xml)
+
+    # Examples
+    ```
+    use openmath::{OMDeserializable, OpenMath, de::Options};
+
+    let options = Options::new()
+        .with_base("http://example.org/docs/index.xml")
+        .with_default_cdbase("http://example.org/cd");
+    let om = OpenMath::from_openmath_xml_with(
+        r#"<OMA><OMS cd="units" name="metre"/><OMS cdbase="other" cd="units" name="foot"/></OMA>"#,
+        options,
+    )
+    .expect("is valid");
+    let OpenMath::OMA { applicant, arguments, .. } = om else { unreachable!() };
+    let OpenMath::OMS { cdbase, .. } = *applicant else { unreachable!() };
+    assert_eq!(cdbase.as_deref(), Some("http://example.org/cd"));
+    let OpenMath::OMS { cdbase, .. } = &arguments[0] else { unreachable!() };
+    assert_eq!(cdbase.as_deref(), Some("http://example.org/docs/other"));
+    ```
+    */
+    #[cfg(feature = "xml-read")]
+    fn from_openmath_xml_with(
+        input: &'de str,
+        options: Options<'_>,
+    ) -> Result<Self, xml::XmlReadError<Self::Err>>
+    where
+        Self: Sized,
+    {
+        use xml::Readable;
+        read_configured!(xml::FromString<'de>, input, &options, |reader| reader
+            .read(options.default_cdbase))
+    }
+
+    /** Like [from_openmath_xml](OMDeserializable::from_openmath_xml), but resolves symbols
+    without a cdbase (in scope) against `cdbase` instead of [`CD_BASE`](crate::CD_BASE), e.g.
+    for documents following the conventions of an organization.
+
+    # Errors
+    see [from_openmath_xml](OMDeserializable::from_openmath_xml)
+
+    # Examples
+    ```
+    use openmath::{OMDeserializable, OpenMath};
+
+    let om = OpenMath::from_openmath_xml_with_default_cdbase(
+        r#"<OMS cd="units" name="metre"/>"#,
+        "http://example.org/cd",
+    )
+    .expect("is valid");
+    let OpenMath::OMS { cdbase, .. } = om else { unreachable!() };
+    assert_eq!(cdbase.as_deref(), Some("http://example.org/cd"));
+    ```
+    */
+    #[cfg(feature = "xml-read")]
+    fn from_openmath_xml_with_default_cdbase(
+        input: &'de str,
+        cdbase: &str,
+    ) -> Result<Self, xml::XmlReadError<Self::Err>>
+    where
+        Self: Sized,
+    {
+        Self::from_openmath_xml_with(input, Options::new().with_default_cdbase(cdbase))
+    }
+
+    /** Like [from_openmath_xml](OMDeserializable::from_openmath_xml), but with `base` as
+    the URI of the document itself, against which relative cdbases are resolved.
+
+    Relative cdbases are always resolved against the base URI in scope, which `xml:base`
+    attributes (possibly relative themselves) override for the elements carrying them and
+    their descendants. If there is no base URI in scope (e.g. when reading via
+    [from_openmath_xml](OMDeserializable::from_openmath_xml) without any `xml:base`),
+    relative cdbases are kept as they are.
+
+    # Errors
+    see [from_openmath_xml](OMDeserializable::from_openmath_xml)
+
+    # Examples
+    ```
+    use openmath::{OMDeserializable, OpenMath};
+
+    let s = r#"<OMA xml:base="../shared/"><OMS cdbase="cds" cd="units" name="metre"/><OMV name="x"/></OMA>"#;
+    let om = OpenMath::from_openmath_xml_with_base(s, "http://example.org/docs/index.xml")
+        .expect("is valid");
+    let OpenMath::OMA { applicant, .. } = om else { unreachable!() };
+    let OpenMath::OMS { cdbase, .. } = *applicant else { unreachable!() };
+    assert_eq!(cdbase.as_deref(), Some("http://example.org/shared/cds"));
+    ```
+    */
+    #[cfg(feature = "xml-read")]
+    fn from_openmath_xml_with_base(
+        input: &'de str,
+        base: &str,
+    ) -> Result<Self, xml::XmlReadError<Self::Err>>
+    where
+        Self: Sized,
+    {
+        Self::from_openmath_xml_with(input, Options::new().with_base(base))
+    }
+
+    /** Like [from_openmath_xml](OMDeserializable::from_openmath_xml), but recovers what it
+    can from a broken (e.g. truncated) document: the complete children of its root object
+    that convert successfully, up to the first one that does not; see [`PartialParse`].
+
+    # Errors
+    iff [from_openmath_xml](OMDeserializable::from_openmath_xml) fails.
+
+    # Examples
+    ```
+    use openmath::{OMDeserializable, OpenMath};
+
+    let truncated = r#"<OMOBJ><OMA><OMS cd="arith1" name="plus"/><OMI>1</OMI><OMI>2</OMI><OMI>3"#;
+    let partial = OpenMath::from_openmath_xml_partial(truncated).expect_err("is truncated");
+    assert_eq!(partial.children.len(), 3);
+    assert_eq!(&truncated[..partial.offset as usize], r#"<OMOBJ><OMA><OMS cd="arith1" name="plus"/><OMI>1</OMI><OMI>2</OMI>"#);
+    ```
+    */
+    #[cfg(feature = "xml-read")]
+    fn from_openmath_xml_partial(
+        input: &'de str,
+    ) -> Result<Self, xml::PartialParse<Self, Self::Err>>
+    where
+        Self: Sized,
+    {
+        Self::from_openmath_xml(input).map_err(|e| xml::partial(input, e))
+    }
+
+    /// Like [from_openmath_xml](OMDeserializable::from_openmath_xml), but additionally
+    /// records the formatting of the input, so that it can be reproduced when writing
+    /// the object back via [`xml_preserving`](crate::ser::OMSerializable::xml_preserving).
+    ///
+    /// # Errors
+    /// see [from_openmath_xml](OMDeserializable::from_openmath_xml)
+    ///
+    /// # Examples
+    /// See [`FormatMap`](crate::FormatMap)
+    #[cfg(feature = "xml-read")]
+    fn from_openmath_xml_preserving(
+        input: &'de str,
+    ) -> Result<(Self, crate::FormatMap), xml::XmlReadError<Self::Err>>
+    where
+        Self: Sized,
+    {
+        use xml::Readable;
+        let mut reader = <xml::Recording<xml::FromString<'de>> as xml::Events<'de>>::new(input);
+        let r = reader.read(None)?;
+        Ok((r, reader.map))
     }
 }
+
+/// What to do with a child of an object that is about to be read; see
+/// [`OMDeserializable::before_child`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Visit<R> {
+    /// Read and convert the child as usual
+    Descend,
+    /// Skip the child without reading it, and use the given value in its place
+    Skip(R),
+}
+
 /// Trait for types that can be deserialized as owned values from
 /// <span style="font-variant:small-caps;">OpenMath</span> objects.
 ///
@@ -232,522 +1216,5018 @@ pub trait OMDeserializableOwned: for<'d> OMDeserializable<'d> {
     /// # Examples
     /// See [trait documentation](OMDeserializable)
     #[inline]
+    #[cfg(feature = "xml-read")]
     fn from_openmath_xml_reader<R: std::io::BufRead>(
         reader: R,
     ) -> Result<Self, xml::XmlReadError<<Self as OMDeserializable<'static>>::Err>>
     where
         Self: Sized,
     {
-        use xml::Readable;
-        <xml::Reader<R> as Readable<'static, Self>>::new(reader).read(None)
+        Self::from_openmath_xml_reader_with(reader, Options::new())
     }
-}
 
-/// Blanket implementation to allow owned deserializable types to work with the borrowed trait.
-impl<O> OMDeserializableOwned for O where O: for<'de> OMDeserializable<'de> {}
-
-/// Wrapper to deserialize an OMOBJ value.
-pub struct OMObject<'de, O: OMDeserializable<'de>>(O, std::marker::PhantomData<&'de ()>);
-impl<'de, O: OMDeserializable<'de>> OMObject<'de, O> {
-    /// Returns the deserialized value.
-    #[inline]
-    pub fn into_inner(self) -> O {
-        self.0
-    }
-
-    /** Deserializes an [OMDeserializable] from an XML string starting with `<OMOBJ>`
-     *
-    # Errors
-    iff the string provided is invalid XML, or invalid <span style="font-variant:small-caps;">OpenMath</span>, or [from_openmath](OMDeserializable::from_openmath)
-    errors.
-
-    # Examples
-    ```
-    use openmath::de::{OMDeserializable, OM, OMObject};
-
-    let s = r#"<OMOBJ cdbase="http://www.openmath.org/cd">
-      <OMI>2</OMI>
-    </OMOBJ>"#;
-    assert_eq!(OMObject::<i32>::from_openmath_xml(s).expect("is valid"),2);
-    ```
-    */
-    #[inline]
-    pub fn from_openmath_xml(input: &'de str) -> Result<O, xml::XmlReadError<O::Err>>
+    /// Like [from_openmath_xml_reader](OMDeserializableOwned::from_openmath_xml_reader), but
+    /// with the given [`Options`]; see
+    /// [from_openmath_xml_with](OMDeserializable::from_openmath_xml_with).
+    ///
+    /// # Errors
+    /// see [from_openmath_xml_reader](OMDeserializableOwned::from_openmath_xml_reader)
+    #[cfg(feature = "xml-read")]
+    fn from_openmath_xml_reader_with<R: std::io::BufRead>(
+        reader: R,
+        options: Options<'_>,
+    ) -> Result<Self, xml::XmlReadError<<Self as OMDeserializable<'static>>::Err>>
     where
-        O: Sized,
+        Self: Sized,
     {
         use xml::Readable;
-        <xml::FromString as xml::Readable<'de, O>>::new(input).read_obj()
+        read_configured!(xml::Reader<R>, reader, &options, |reader| reader
+            .read(options.default_cdbase))
     }
 }
 
-/// Enum for deserializing from <span style="font-variant:small-caps;">OpenMath</span>. See
-/// see [OMDeserializable] for documentation and an example.
-///
-/// Note that there is no case for [OMATTR](crate::OMKind::OMATTR) - instead,
-/// every case has a <code>[Vec]<[OMAttr]<'de, I>></code>, which is usually empty.
-/// Otherwise, we'd have to either deal with two separate types, or have the
-/// nonsensical case `OMATTR(OMATTR(OMATTR(...),...),...)`, which would also
-/// require a [`Box`]-indirection (hence allocation), etc. since OMATTR is mostly used
-/// for metadata which the recipient might not even care about, or only care secondarily
-/// (compared to the *actual* [OM]-kind), having OMATTR be a separate case seems
-/// like bad API design.
-/// Also, empty Vecs are cheap.
-#[derive(Debug, Clone)]
-#[repr(u8)]
-pub enum OM<'de, I> {
-    /** <div class="openmath">
-    Integers in the mathematical sense, with no predefined range.
-    They are “infinite precision” integers (also called “bignums” in computer algebra).
-    </div> */
-    OMI {
-        int: crate::Int<'de>,
-        attrs: Attrs<OMAttr<'de, I>>,
-    } = OMKind::OMI as _,
+/// Blanket implementation to allow owned deserializable types to work with the borrowed trait.
+impl<O> OMDeserializableOwned for O where O: for<'de> OMDeserializable<'de> {}
 
-    /** <div class="openmath">
-    Double precision floating-point numbers following the IEEE 754-1985 standard.
-    </div> */
-    OMF {
-        float: f64,
-        attrs: Attrs<OMAttr<'de, I>>,
-    } = OMKind::OMF as _,
+/** Reads all of `reader` into `buf` (after clearing it) and deserializes an `O` from it, like
+[from_openmath_xml](OMDeserializable::from_openmath_xml); so unlike
+[from_openmath_xml_reader](OMDeserializableOwned::from_openmath_xml_reader), `O` may borrow
+from the input (e.g. if it contains <code>[Cow]<'b, str></code>s), for as long as `buf` is
+kept alive.
 
-    /** <div class="openmath">
-    A Unicode Character string. This also corresponds to “characters” in XML.
-    </div> */
-    OMSTR {
-        string: Cow<'de, str>,
-        attrs: Attrs<OMAttr<'de, I>>,
-    } = OMKind::OMSTR as _,
+This holds the whole document in memory at once, but strings and names need not be copied;
+[from_openmath_xml_reader](OMDeserializableOwned::from_openmath_xml_reader) instead reads
+incrementally, but copies all data into owned buffers (and requires `O` to be owned). The
+former is usually preferable for small to medium documents, the latter for large streams.
 
-    /** <div class="openmath">
-    A sequence of bytes.
-    </div> */
-    OMB {
-        bytes: Cow<'de, [u8]>,
-        attrs: Attrs<OMAttr<'de, I>>,
-    } = OMKind::OMB as _,
+UTF-16 input is transcoded into `buf` with the `encoding` feature, and rejected otherwise.
 
-    ///<div class="openmath">
+# Errors
+iff reading fails, the data is invalid UTF8, XML, or
+<span style="font-variant:small-caps;">OpenMath</span>, or
+[from_openmath](OMDeserializable::from_openmath) errors.
+
+# Examples
+```
+use std::borrow::Cow;
+use openmath::de::read_into_buffer;
+
+let mut buf = Vec::new();
+let s: Cow<'_, str> = read_into_buffer("<OMSTR>hello</OMSTR>".as_bytes(), &mut buf)
+    .expect("is valid");
+assert!(matches!(s, Cow::Borrowed("hello")));
+```
+*/
+#[cfg(feature = "xml-read")]
+pub fn read_into_buffer<'b, O: OMDeserializable<'b>>(
+    mut reader: impl std::io::BufRead,
+    buf: &'b mut Vec<u8>,
+) -> Result<O, xml::XmlReadError<O::Err>> {
+    buf.clear();
+    reader
+        .read_to_end(buf)
+        .map_err(|e| xml::XmlReadError::Xml {
+            error: quick_xml::Error::Io(std::sync::Arc::new(e)),
+            position: buf.len() as u64,
+        })?;
+    if let Some(transcoded) = xml::transcode_utf16(buf)? {
+        *buf = transcoded.into_bytes();
+    }
+    let buf: &'b [u8] = buf;
+    let input = std::str::from_utf8(buf).map_err(|source| xml::XmlReadError::Utf8 {
+        source,
+        position: source.valid_up_to() as u64,
+        element: None,
+    })?;
+    O::from_openmath_xml(input)
+}
+
+/// [`OMDeserializable`] types whose intermediate [`Ret`](OMDeserializable::Ret) can stand in
+/// for a subtree that failed to convert.
+///
+/// This allows for deserializing in an error-collecting mode, which reports *all*
+/// conversion errors in independent subtrees (arguments of OMA and OME, attribute
+/// values, etc.) rather than stopping at the first one.
+pub trait OMDeserializableRecover<'de>: OMDeserializable<'de> {
+    /// The value to continue with in place of a subtree for which
+    /// [`from_openmath`](OMDeserializable::from_openmath) failed.
     ///
-    /// A Variable must have a name which is a sequence of characters matching a regular
-    /// expression, as described in [Section 2.3](https://openmath.org/standard/om20-2019-07-01/omstd20.html#sec_names).
+    /// If the parent of a placeholder then fails to convert as well, only the original
+    /// error is reported.
+    fn placeholder() -> Self::Ret
+    where
+        Self: Sized;
+
+    /// Like [from_openmath_xml](OMDeserializable::from_openmath_xml), but collects all
+    /// conversion errors, each paired with the byte offset of the offending element.
     ///
-    ///</div>
+    /// The result is `None` only if the root object itself could not be converted, or if
+    /// the input is not valid XML/<span style="font-variant:small-caps;">OpenMath</span>
+    /// (in which case the last error is the one that stopped parsing).
     ///
-    ///(Note: We do not enforce that names are valid XML names;)
-    OMV {
-        name: Cow<'de, str>,
-        attrs: Attrs<OMAttr<'de, I>>,
-    } = OMKind::OMV as _,
-
-    ///<div class="openmath">
+    /// # Examples
+    /// ```
+    /// use openmath::de::{OM, OMDeserializable, OMDeserializableRecover};
     ///
-    /// A Symbol encodes three fields of information, a symbol name, a Content Dictionary name,
-    /// and (optionally) a Content Dictionary base URI, The name of a symbol is a sequence of
-    /// characters matching the regular expression described in
-    /// [Section 2.3](https://openmath.org/standard/om20-2019-07-01/omstd20.html#sec_names).
-    /// The Content Dictionary is the location of the definition of the symbol, consisting of a
-    /// name (a sequence of characters matching the regular expression described in
-    /// [Section 2.3](https://openmath.org/standard/om20-2019-07-01/omstd20.html#sec_names))
-    /// and, optionally, a unique prefix called a cdbase which is used to disambiguate multiple
-    /// Content Dictionaries of the same name. There are other properties of the symbol that are
-    /// not explicit in these fields but whose values may be obtained by inspecting the Content
-    /// Dictionary specified. These include the symbol definition, formal properties and examples
-    /// and, optionally, a role which is a restriction on where the symbol may appear in an
-    /// <span style="font-variant:small-caps;">OpenMath</span> object. The possible roles are described in
-    /// [Section 2.1.4](https://openmath.org/standard/om20-2019-07-01/omstd20.html#sec_roles).
+    /// /// A list of small integers
+    /// #[derive(Debug)]
+    /// struct Bytes(Vec<u8>);
+    /// impl TryFrom<Option<Vec<u8>>> for Bytes {
+    ///     type Error = ();
+    ///     fn try_from(value: Option<Vec<u8>>) -> Result<Self, ()> {
+    ///         value.map(Self).ok_or(())
+    ///     }
+    /// }
+    /// impl<'d> OMDeserializable<'d> for Bytes {
+    ///     // `None` marks a failed subtree
+    ///     type Ret = Option<Vec<u8>>;
+    ///     type Err = &'static str;
+    ///     fn from_openmath(om: OM<'d, Self::Ret>, _: &str) -> Result<Self::Ret, Self::Err> {
+    ///         match om {
+    ///             OM::OMI { int, .. } => int
+    ///                 .is_i128()
+    ///                 .and_then(|i| u8::try_from(i).ok())
+    ///                 .map(|i| Some(vec![i]))
+    ///                 .ok_or("not a byte"),
+    ///             OM::OMS { name, .. } if name == "list" => Ok(Some(Vec::new())),
+    ///             OM::OMA { applicant: Some(mut v), arguments, .. } => {
+    ///                 for a in arguments {
+    ///                     v.extend(a.ok_or("invalid list element")?);
+    ///                 }
+    ///                 Ok(Some(v))
+    ///             }
+    ///             _ => Err("not a list"),
+    ///         }
+    ///     }
+    /// }
+    /// impl<'d> OMDeserializableRecover<'d> for Bytes {
+    ///     fn placeholder() -> Self::Ret {
+    ///         None
+    ///     }
+    /// }
     ///
-    ///</div>
-    OMS {
-        cd: Cow<'de, str>,
-        name: Cow<'de, str>,
-        attrs: Attrs<OMAttr<'de, I>>,
-    } = OMKind::OMS as _,
+    /// let (result, errors) = Bytes::from_openmath_xml_collect(
+    ///     r#"<OMA><OMS cd="list1" name="list"/><OMI>1</OMI><OMI>256</OMI><OMI>-1</OMI></OMA>"#,
+    /// );
+    /// assert!(result.is_none());
+    /// assert_eq!(errors.len(), 2);
+    /// assert_eq!(errors[0].to_string(), "error converting OpenMath: not a byte (at offset 46)");
+    /// ```
+    #[must_use]
+    #[cfg(feature = "xml-read")]
+    fn from_openmath_xml_collect(
+        input: &'de str,
+    ) -> (Option<Self>, Vec<xml::PositionedError<Self::Err>>)
+    where
+        Self: Sized,
+    {
+        use xml::Events;
+        <xml::Collecting<Self::Err, xml::FromString<'de>> as Events<'de>>::new(input)
+            .read_collect(None)
+    }
+}
 
-    /** <div class="openmath">
-    If $A_1,...,A_n\;(n>0)$ are <span style="font-variant:small-caps;">OpenMath</span> objects, then
-    $\mathrm{application}(A_1,...,A_n)$ is an <span style="font-variant:small-caps;">OpenMath</span> application object.
-    We call $A_1$ the function and $A_2$ to $A_n$ the arguments.
-    </div> */
-    OMA {
-        applicant: I,
-        arguments: Args<I>,
-        attrs: Attrs<OMAttr<'de, I>>,
-    } = OMKind::OMA as _,
+/// Policy for the `version` attribute of an OMOBJ during deserialization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VersionPolicy {
+    /// Accept OMOBJs without a version, or with version [`OPENMATH_VERSION`](crate::OPENMATH_VERSION);
+    /// reject anything else.
+    #[default]
+    Strict,
+    /// Accept any version, but report any other than
+    /// [`OPENMATH_VERSION`](crate::OPENMATH_VERSION): as a
+    /// [`Warning::Version`](crate::document::Warning::Version) in the metadata of a
+    /// [`Document`](crate::Document), and with the `tracing` feature as a warning event.
+    Warn,
+    /// Accept any version silently; it can still be inspected via [`OMObject::version`].
+    Lenient,
+}
+impl VersionPolicy {
+    /// Whether an OMOBJ with the given version is acceptable under this policy.
+    #[must_use]
+    pub fn accepts(self, version: Option<&str>) -> bool {
+        match self {
+            Self::Warn | Self::Lenient => true,
+            Self::Strict => version.is_none_or(|v| v == crate::OPENMATH_VERSION),
+        }
+    }
 
-    /** <div class="openmath">
-    If $B$ and $C$ are <span style="font-variant:small-caps;">OpenMath</span> objects, and $v_1,...,v_n\;(n\geq0)$
-    are <span style="font-variant:small-caps;">OpenMath</span> variables or attributed variables, then
-    $\mathrm{binding}(B,v_1,...,v_n,C)$ is an <span style="font-variant:small-caps;">OpenMath</span> binding object.
-    $B$ is called the binder, $v_1,...,v_n$ are called variable bindings, and
-    $C$ is called the body of the binding object above.
-    </div> */
-    OMBIND {
-        binder: I,
-        variables: Vars<(Cow<'de, str>, Attrs<OMAttr<'de, I>>)>,
-        object: I,
-        attrs: Attrs<OMAttr<'de, I>>,
-    } = OMKind::OMBIND as _,
+    /// Whether an OMOBJ with the given version is accepted under this policy, but should be
+    /// reported.
+    #[must_use]
+    pub fn warns(self, version: Option<&str>) -> bool {
+        self == Self::Warn && !Self::Strict.accepts(version)
+    }
 
-    /** <div class="openmath">
-    If $S$ is an <span style="font-variant:small-caps;">OpenMath</span> symbol and $A_1,...,A_n\;(n\geq0)$ are <span style="font-variant:small-caps;">OpenMath</span> objects or
-    derived <span style="font-variant:small-caps;">OpenMath</span> objects, then $\mathrm{error}(S,A_1,...,A_n)$ is an <span style="font-variant:small-caps;">OpenMath</span> error object.
-    </div> */
-    OME {
-        cdbase: Option<Cow<'de, str>>,
-        cd: Cow<'de, str>,
-        name: Cow<'de, str>,
-        arguments: Vec<OMMaybeForeign<'de, I>>,
-        attrs: Attrs<OMAttr<'de, I>>,
-    } = OMKind::OME as _,
-}
-impl<I> OM<'_, I> {
-    /// Returns the [OMKind] of this [`OM`], which of all practical purposes
-    /// acts as a discriminant.
-    pub fn kind(&self) -> crate::OMKind {
-        // SAFETY: Because `Self` is marked `repr(u8)`, its layout is a `repr(C)` `union`
-        // between `repr(C)` structs, each of which has the `u8` discriminant as its first
-        // field, so we can read the discriminant without offsetting the pointer.
-        unsafe {
-            let u = *<*const _>::from(self).cast::<u8>();
-            crate::OMKind::from_u8(u).unwrap_unchecked()
+    /// Like [`accepts`](Self::accepts), but also emits the warning event for
+    /// [`Warn`](Self::Warn) with the `tracing` feature.
+    #[cfg(any(feature = "xml-read", feature = "serde"))]
+    pub(crate) fn check(self, version: Option<&str>) -> bool {
+        #[cfg(feature = "tracing")]
+        if self.warns(version) {
+            tracing::warn!(version, "unsupported OpenMath version");
         }
+        self.accepts(version)
     }
 }
 
-impl<'d> OMDeserializable<'d> for crate::Int<'d> {
-    type Ret = Self;
-    type Err = &'static str;
-    fn from_openmath(om: OM<'d, Self>, _: &str) -> Result<Self, Self::Err>
-    where
-        Self: Sized,
-    {
-        if let OM::OMI { int, .. } = om {
-            Ok(int)
-        } else {
-            Err("Not an integer")
+/** Policy for XML attributes that occur more than once on the same element, such as
+`<OMS cd="a" cd="b" name="x"/>`; see [`Options::with_attribute_policy`].
+
+Such documents are not well-formed XML, and other parsers disagree on which occurrence
+counts; so by default, they are rejected.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+pub enum AttributePolicy {
+    /// Reject duplicate attributes; with [`XmlReadError::DuplicateAttribute`] for those this
+    /// crate reads (`name`, `cd`, `cdbase`, `dec`, `hex`, `encoding`, `id`, `href` and
+    /// `version`), and with [`XmlReadError::Xml`] for all others.
+    #[default]
+    Strict,
+    /// Accept duplicate attributes; the *first* occurrence wins.
+    Lenient,
+}
+
+/** Whether deserializing via serde (e.g. from JSON) accepts OMATTRs that repeat an
+attribute key; keys are compared by their [resolved URI](crate::SymbolRef::resolved_uri).
+See [`Options::with_duplicate_keys`].
+
+The standard leaves the meaning of repeated keys to the content dictionaries, so they are
+accepted (and kept in document order) by default; see also
+[`OpenMath::dedup_attributes`](crate::OpenMath::dedup_attributes).
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+pub enum DuplicateKeys {
+    /// Keep all pairs, in document order
+    #[default]
+    Allow,
+    /// Fail on the first pair whose key already occurs in the same OMATTR
+    Reject,
+}
+
+/** Policy for decimal floats that no [`f64`] represents exactly, such as
+`<OMF dec="0.1"/>`; see [`Options::with_float_policy`]. It applies to the `dec` attribute
+in XML and to the `decimal` field in JSON; JSON numbers are always rounded.
+
+A decimal is exact if it denotes the very value of the float it is read as, e.g. `0.5`,
+`1.25e2` or `9007199254740992`; but not `0.1` or `9007199254740993`, nor decimals whose
+magnitude is out of range (like `1e400`). The literals `inf`, `infinity` and `NaN` are
+exact.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+pub enum FloatPolicy {
+    /// Read them as the nearest [`f64`]
+    #[default]
+    Nearest,
+    /// Reject them; with [`XmlReadError::InexactFloat`] in XML
+    ErrorOnInexact,
+    /// Read them as the nearest [`f64`], and keep every decimal as written in the
+    /// [`lexical`](OM::OMF::lexical) form of the [`OM::OMF`]. [`OpenMath`](crate::OpenMath)
+    /// does not keep it; but the [`FormatMap`](crate::FormatMap) of
+    /// [`from_openmath_xml_preserving`](OMDeserializable::from_openmath_xml_preserving)
+    /// records the `dec` attributes regardless of the policy, and
+    /// [`xml_preserving`](crate::ser::OMSerializable::xml_preserving) writes them back.
+    PreserveLexical,
+}
+#[cfg(any(feature = "xml-read", feature = "serde"))]
+impl FloatPolicy {
+    /// What to keep of the decimal `s` that has been parsed as `float`: `s` itself if it
+    /// is to be [preserved](Self::PreserveLexical); `Err(s)` if it is to be
+    /// [rejected](Self::ErrorOnInexact).
+    pub(crate) fn apply(
+        self,
+        s: Cow<'_, str>,
+        float: f64,
+    ) -> Result<Option<Cow<'_, str>>, Cow<'_, str>> {
+        match self {
+            Self::Nearest => Ok(None),
+            Self::ErrorOnInexact if crate::floats::is_exact(&s, float) => Ok(None),
+            Self::ErrorOnInexact => Err(s),
+            Self::PreserveLexical => Ok(Some(s)),
         }
     }
 }
 
-impl<'d> OMDeserializable<'d> for f32 {
-    type Ret = Self;
-    type Err = &'static str;
-    #[allow(clippy::cast_possible_truncation)]
-    fn from_openmath(om: OM<'d, Self>, _: &str) -> Result<Self, Self::Err>
-    where
-        Self: Sized,
-    {
-        if let OM::OMF { float, .. } = om {
-            Ok(float as _)
-        } else {
-            Err("Not a float")
+/// What to do with an attribute pair during deserialization; see
+/// [`Options::with_attr_filter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+pub enum AttrAction {
+    /// Deserialize the pair as usual
+    #[default]
+    Keep,
+    /// Drop the pair without materializing its value
+    Skip,
+}
+
+/// Decides which attribute pairs to keep, given the (resolved) cdbase, cd and name of their
+/// key; see [`Options::with_attr_filter`].
+pub type AttrFilter = fn(&str, &str, &str) -> AttrAction;
+
+/// Whether `filter` keeps the attribute pair with key `cdbase?cd#name`
+#[cfg(any(feature = "xml-read", feature = "serde"))]
+fn keeps_attr(filter: Option<AttrFilter>, cdbase: &str, cd: &str, name: &str) -> bool {
+    filter.is_none_or(|f| f(cdbase, cd, name) == AttrAction::Keep)
+}
+
+/// Bounds on the input accepted during deserialization; see [`Options::with_limits`]. By
+/// default, nothing is bounded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+#[non_exhaustive]
+pub struct Limits {
+    /// The maximal length in bytes of the names of variables, symbols and content
+    /// dictionaries (including attribute keys and bound variables); `None` for no bound
+    pub max_name_len: Option<usize>,
+    /// The maximal nesting depth of XML elements (the outermost one being at depth 1), which
+    /// bounds the recursion when reading; `None` for no bound. Serde deserializers usually
+    /// bound their recursion themselves (e.g. serde_json at 128 levels), so this only
+    /// applies to XML.
+    pub max_depth: Option<usize>,
+}
+impl Limits {
+    /// No bounds
+    #[inline]
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            max_name_len: None,
+            max_depth: None,
         }
     }
+
+    /// Bounds the length of names to `max` bytes.
+    #[inline]
+    #[must_use]
+    pub const fn with_max_name_len(mut self, max: usize) -> Self {
+        self.max_name_len = Some(max);
+        self
+    }
+
+    /// Bounds the nesting depth of XML elements to `max`; fails with
+    /// [`XmlReadError::TooDeep`] beyond that.
+    #[inline]
+    #[must_use]
+    pub const fn with_max_depth(mut self, max: usize) -> Self {
+        self.max_depth = Some(max);
+        self
+    }
 }
 
-impl<'d> OMDeserializable<'d> for f64 {
-    type Ret = Self;
-    type Err = &'static str;
-    fn from_openmath(om: OM<'d, Self>, _: &str) -> Result<Self, Self::Err>
-    where
-        Self: Sized,
-    {
-        if let OM::OMF { float, .. } = om {
-            Ok(float)
-        } else {
-            Err("Not a float")
+/** A bound on the work spent deserializing, for untrusted input; see
+[`Options::with_budget`].
+
+Deserialization fails once more than [`max_nodes`](Self::max_nodes) objects (or, in XML, elements
+of any kind) have been read, or once the [`deadline`](Self::deadline) has passed; the latter is
+only checked every [`Budget::CHECK_INTERVAL`] nodes. XML fails with
+[`XmlReadError::BudgetExceeded`]. On `wasm32-unknown-unknown`, which has no clock, the
+deadline is never checked and no time is measured.
+
+Via serde (see [`OMFromSerde::with_options`]), only the number of nodes is bounded, and only
+completed nodes are counted: serde deserializers can not be interrupted, so the deadline is
+ignored there.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Budget {
+    /// The maximal number of nodes to read
+    pub max_nodes: usize,
+    /// The time by which reading has to be done, if any
+    pub deadline: Option<std::time::Instant>,
+}
+impl Budget {
+    /// The number of nodes after which the [`deadline`](Self::deadline) is checked (again)
+    pub const CHECK_INTERVAL: usize = 64;
+
+    /// A budget of `max_nodes` nodes, without deadline
+    #[inline]
+    #[must_use]
+    pub const fn new(max_nodes: usize) -> Self {
+        Self {
+            max_nodes,
+            deadline: None,
         }
     }
+
+    /// Additionally requires reading to be done by `deadline`.
+    #[inline]
+    #[must_use]
+    pub const fn with_deadline(mut self, deadline: std::time::Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
 }
 
-impl<'d> OMDeserializable<'d> for Cow<'d, str> {
-    type Ret = Self;
-    type Err = &'static str;
-    fn from_openmath(om: OM<'d, Self>, _: &str) -> Result<Self, Self::Err>
-    where
-        Self: Sized,
-    {
-        if let OM::OMSTR { string, .. } = om {
-            Ok(string)
-        } else {
-            Err("Not an OMSTR")
-        }
+/// The current time; `None` on `wasm32-unknown-unknown`, where [`Instant::now`](std::time::Instant::now)
+/// panics for lack of a clock
+#[cfg(any(feature = "xml-read", feature = "serde"))]
+#[inline]
+pub(crate) fn now() -> Option<std::time::Instant> {
+    if cfg!(all(target_arch = "wasm32", target_os = "unknown")) {
+        None
+    } else {
+        Some(std::time::Instant::now())
     }
 }
 
-impl<'d> OMDeserializable<'d> for String {
-    type Ret = Self;
-    type Err = &'static str;
-    fn from_openmath(om: OM<'d, Self>, _: &str) -> Result<Self, Self::Err>
-    where
-        Self: Sized,
-    {
-        if let OM::OMSTR { string, .. } = om {
-            Ok(string.into_owned())
+/// The part of a [`Budget`] spent so far; counted by both deserializers
+#[cfg(any(feature = "xml-read", feature = "serde"))]
+#[derive(Debug, Clone, Copy, Default)]
+struct Spending {
+    budget: Option<Budget>,
+    nodes: usize,
+    start: Option<std::time::Instant>,
+}
+#[cfg(any(feature = "xml-read", feature = "serde"))]
+impl Spending {
+    fn new(budget: Option<Budget>) -> Self {
+        Self {
+            budget,
+            nodes: 0,
+            start: budget.and_then(|_| now()),
+        }
+    }
+
+    /// Counts a node; fails with the number of nodes and the time spent if that exceeds the
+    /// budget
+    fn node(&mut self) -> Result<(), (usize, std::time::Duration)> {
+        let Some(budget) = self.budget else {
+            return Ok(());
+        };
+        self.nodes += 1;
+        let exceeded = self.nodes > budget.max_nodes
+            || (self.nodes.is_multiple_of(Budget::CHECK_INTERVAL)
+                && budget
+                    .deadline
+                    .zip(now())
+                    .is_some_and(|(deadline, now)| now >= deadline));
+        if exceeded {
+            Err((
+                self.nodes,
+                self.start.map(|s| s.elapsed()).unwrap_or_default(),
+            ))
         } else {
-            Err("Not an OMSTR")
+            Ok(())
         }
     }
 }
 
-impl<'d> OMDeserializable<'d> for Cow<'d, [u8]> {
-    type Ret = Self;
-    type Err = &'static str;
-    fn from_openmath(om: OM<'d, Self>, _: &str) -> Result<Self, Self::Err>
-    where
-        Self: Sized,
-    {
-        if let OM::OMB { bytes, .. } = om {
-            Ok(bytes)
-        } else {
-            Err("Not an OMB")
+/// A name rejected by the [`Limits`] or [`Options::strict_names`] in effect
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, thiserror::Error)]
+pub enum NameError {
+    /// An empty name of a variable, or an empty name or cd of a symbol, in an object of
+    /// the given kind (only with [`Options::strict_names`])
+    #[error("empty name in {0}")]
+    Empty(crate::OMKind),
+    /// A name of `len` bytes in an object of the given kind, beyond the
+    /// [`max_name_len`](Limits::max_name_len) of `max` bytes
+    #[error("name of {len} bytes in {kind} exceeds the limit of {max} bytes")]
+    TooLong {
+        kind: crate::OMKind,
+        len: usize,
+        max: usize,
+    },
+}
+
+/// The checks on (and renamings of) names that [`Options`] ask for; applied by both
+/// deserializers
+#[cfg(any(feature = "xml-read", feature = "serde"))]
+#[derive(Debug, Clone, Copy, Default)]
+struct NameRules {
+    max_len: Option<usize>,
+    strict: bool,
+}
+#[cfg(any(feature = "xml-read", feature = "serde"))]
+impl NameRules {
+    const fn new(options: &Options<'_>) -> Self {
+        Self {
+            max_len: options.limits.max_name_len,
+            strict: options.strict_names,
         }
     }
-}
-impl<'d> OMDeserializable<'d> for Vec<u8> {
-    type Ret = Self;
-    type Err = &'static str;
-    fn from_openmath(om: OM<'d, Self>, _: &str) -> Result<Self, Self::Err>
-    where
-        Self: Sized,
-    {
-        if let OM::OMB { bytes, .. } = om {
-            Ok(bytes.into_owned())
-        } else {
-            Err("Not an OMB")
+
+    const fn check(self, kind: crate::OMKind, name: &str) -> Result<(), NameError> {
+        if self.strict && name.is_empty() {
+            return Err(NameError::Empty(kind));
+        }
+        match self.max_len {
+            Some(max) if name.len() > max => Err(NameError::TooLong {
+                kind,
+                len: name.len(),
+                max,
+            }),
+            _ => Ok(()),
         }
     }
-}
 
-macro_rules! impl_int_deserializable {
-    ($($t:ty=$err:literal),*) => {
-        $(
-            impl<'d> OMDeserializable<'d> for $t {
-                type Ret = Self;
-                type Err = &'static str;
-                fn from_openmath(
-                    om: OM<'d, Self>,
-                    _: &str
-                ) -> Result<Self, Self::Err>
-                where
-                    Self: Sized,
-                {
-                    if let OM::OMI{int,..} = om {
-                        int.is_i128().map_or(Err($err), |i| {
-                            i.try_into().map_err(|_| $err)
-                        })
-                    } else {
-                        Err("Not an OMI")
-                    }
+    /// Checks the names in `om` itself (not its children): those of variables and symbols,
+    /// bound variables and the keys of its attributes
+    fn check_om<I>(self, om: &OM<'_, I>) -> Result<(), NameError> {
+        use crate::OMKind;
+        if self.max_len.is_none() && !self.strict {
+            return Ok(());
+        }
+        match om {
+            OM::OMV { name, .. } => self.check(OMKind::OMV, name)?,
+            OM::OMS { cd, name, .. } => {
+                self.check(OMKind::OMS, cd)?;
+                self.check(OMKind::OMS, name)?;
+            }
+            OM::OME { cd, name, .. } => {
+                self.check(OMKind::OME, cd)?;
+                self.check(OMKind::OME, name)?;
+            }
+            OM::OMBIND { variables, .. } => {
+                for (name, attrs) in variables {
+                    self.check(OMKind::OMV, name)?;
+                    self.check_keys(attrs)?;
                 }
             }
-        )*
+            _ => (),
+        }
+        self.check_keys(om.attrs())
+    }
+
+    fn check_keys<I>(self, attrs: &[OMAttr<'_, I>]) -> Result<(), NameError> {
+        for a in attrs {
+            self.check(crate::OMKind::OMS, &a.key.cd)?;
+            self.check(crate::OMKind::OMS, &a.key.name)?;
+        }
+        Ok(())
+    }
+}
+
+/** Options for deserializing <span style="font-variant:small-caps;">OpenMath</span>, accepted by
+the `*_with` entry points (e.g. [`OMDeserializable::from_openmath_xml_with`],
+[`OMObject::from_openmath_xml_with`] or [`OMFromSerde::with_options`]).
+
+New knobs are added here rather than as further entry points, so the struct is
+`#[non_exhaustive]`: start from [`Options::new`] (or [`Default`]) and use the builder methods.
+
+# Examples
+```
+# #[cfg(feature = "xml-read")]
+# {
+use openmath::{OpenMath, de::{OMObject, Options, VersionPolicy}};
+
+let options = Options::new()
+    .with_version_policy(VersionPolicy::Lenient)
+    .with_default_cdbase("http://example.org/cd");
+let s = r#"<OMOBJ version="3.0"><OMS cd="units" name="metre"/></OMOBJ>"#;
+let obj = OMObject::<OpenMath>::from_openmath_xml_with(s, options).expect("is valid");
+let OpenMath::OMS { cdbase, .. } = &*obj else { unreachable!() };
+assert_eq!(cdbase.as_deref(), Some("http://example.org/cd"));
+assert_eq!(obj.default_cdbase(), "http://example.org/cd");
+# }
+```
+*/
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct Options<'o> {
+    /// Which `version`s of OMOBJs to accept; irrelevant for bare objects
+    pub version_policy: VersionPolicy,
+    /// The cdbase that symbols without one (in scope) are resolved against; `None` means
+    /// [`CD_BASE`](crate::CD_BASE)
+    pub default_cdbase: Option<&'o str>,
+    /// The URI of the document itself, against which relative cdbases are resolved; see
+    /// [`from_openmath_xml_with_base`](OMDeserializable::from_openmath_xml_with_base). Only
+    /// used for XML
+    pub base: Option<&'o str>,
+    /// Which attribute pairs to keep; `None` keeps all of them
+    pub attr_filter: Option<AttrFilter>,
+    /// Bounds on the input; see [`Limits`]
+    pub limits: Limits,
+    /// Whether empty names of variables and symbols (and empty cds) are an error
+    /// ([`NameError::Empty`]) rather than accepted
+    pub strict_names: bool,
+    /// The work to spend at most; see [`Budget`]
+    pub budget: Option<Budget>,
+    /// How to treat duplicate XML attributes; see [`AttributePolicy`]
+    pub attribute_policy: AttributePolicy,
+    /// How to treat decimal floats that are not exact; see [`FloatPolicy`]
+    pub float_policy: FloatPolicy,
+    /// Whether OMATTRs may repeat an attribute key; see [`DuplicateKeys`]. Only used via
+    /// serde
+    pub duplicate_keys: DuplicateKeys,
+    /// How to read OMRs; `None` means [`OMRPolicy::Error`]
+    pub omr_policy: Option<&'o OMRPolicy>,
+    /// The number of nodes the objects resolved for OMRs may have in total; see
+    /// [`with_omr_budget`](Self::with_omr_budget)
+    pub omr_budget: usize,
+    /// Where to record the `cdbase` attributes of the input; see
+    /// [`with_cdbase_origins`](Self::with_cdbase_origins). Only used for XML
+    #[cfg(feature = "xml-read")]
+    #[allow(clippy::type_complexity)]
+    pub cdbase_origins: Option<&'o std::cell::RefCell<Vec<(crate::Path, CdBaseOrigin)>>>,
+    /// The resolution that the references of this read continue; see [`References::resolve`]
+    #[cfg(any(feature = "xml-read", feature = "serde"))]
+    expansion: Option<&'o std::rc::Rc<std::cell::RefCell<Expansion>>>,
+}
+impl<'o> Options<'o> {
+    /// The default options: [`VersionPolicy::Strict`], [`CD_BASE`](crate::CD_BASE) as the
+    /// default cdbase and no document URI.
+    #[inline]
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            version_policy: VersionPolicy::Strict,
+            default_cdbase: None,
+            base: None,
+            attr_filter: None,
+            limits: Limits::new(),
+            strict_names: false,
+            budget: None,
+            attribute_policy: AttributePolicy::Strict,
+            float_policy: FloatPolicy::Nearest,
+            duplicate_keys: DuplicateKeys::Allow,
+            omr_policy: None,
+            omr_budget: OMRPolicy::DEFAULT_BUDGET,
+            #[cfg(feature = "xml-read")]
+            cdbase_origins: None,
+            #[cfg(any(feature = "xml-read", feature = "serde"))]
+            expansion: None,
+        }
+    }
+
+    /// Sets the [`VersionPolicy`] for OMOBJs.
+    #[inline]
+    #[must_use]
+    pub const fn with_version_policy(mut self, policy: VersionPolicy) -> Self {
+        self.version_policy = policy;
+        self
+    }
+
+    /// Resolves symbols without a cdbase (in scope) against `cdbase` instead of
+    /// [`CD_BASE`](crate::CD_BASE).
+    #[inline]
+    #[must_use]
+    pub const fn with_default_cdbase(mut self, cdbase: &'o str) -> Self {
+        self.default_cdbase = Some(cdbase);
+        self
+    }
+
+    /// Resolves relative cdbases against `base` as the URI of the document (XML only).
+    #[inline]
+    #[must_use]
+    pub const fn with_base(mut self, base: &'o str) -> Self {
+        self.base = Some(base);
+        self
+    }
+
+    /** Drops attribute pairs for which `filter` returns [`AttrAction::Skip`] while
+    reading, without materializing their values; e.g. for large provenance annotations that
+    are never used. The filter gets the resolved cdbase, the cd and the name of the key.
+
+    In XML, skipped values are passed over as raw markup; via serde (see
+    [`OMFromSerde::with_options`]), they are read as
+    [`IgnoredAny`](https://docs.rs/serde/latest/serde/de/struct.IgnoredAny.html). If the
+    `cdbase` of an OMATTR only comes after its attributes, keys are resolved against the
+    inherited cdbase for filtering, and it is an error if the actual one would have kept a
+    skipped pair.
+
+    # Examples
+    ```
+    # #[cfg(feature = "xml-read")]
+    # {
+    use openmath::{OpenMath, de::{AttrAction, Options}, OMDeserializable};
+
+    let s = r#"<OMATTR><OMATP>
+        <OMS cd="meta" name="provenance"/><OMFOREIGN>...</OMFOREIGN>
+        <OMS cd="meta" name="note"/><OMSTR>kept</OMSTR>
+      </OMATP><OMV name="x"/></OMATTR>"#;
+    let options = Options::new().with_attr_filter(|_, cd, name| {
+        if (cd, name) == ("meta", "provenance") { AttrAction::Skip } else { AttrAction::Keep }
+    });
+    let om = OpenMath::from_openmath_xml_with(s, options).expect("is valid");
+    assert_eq!(om.attributes().len(), 1);
+    assert_eq!(om.attributes()[0].key.name, "note");
+    # }
+    ```
+    */
+    #[inline]
+    #[must_use]
+    pub const fn with_attr_filter(mut self, filter: AttrFilter) -> Self {
+        self.attr_filter = Some(filter);
+        self
+    }
+
+    /** Rejects input beyond `limits` (see [`NameError`]); e.g. to bound the memory a single
+    name from an untrusted source can take up.
+
+    # Examples
+    ```
+    # #[cfg(feature = "xml-read")]
+    # {
+    use openmath::{OpenMath, OMDeserializable, de::{Limits, NameError, Options, XmlReadError}};
+
+    let options = Options::new().with_limits(Limits::new().with_max_name_len(8));
+    let s = r#"<OMV name="a_rather_long_name"/>"#;
+    let Err(XmlReadError::InvalidName { error, .. }) =
+        OpenMath::from_openmath_xml_with(s, options)
+    else {
+        panic!("name is too long")
+    };
+    assert_eq!(error, NameError::TooLong { kind: openmath::OMKind::OMV, len: 18, max: 8 });
+    # }
+    ```
+    */
+    #[inline]
+    #[must_use]
+    pub const fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Sets whether empty names of variables and symbols are an error
+    /// ([`NameError::Empty`]); by default, they are accepted.
+    #[inline]
+    #[must_use]
+    pub const fn with_strict_names(mut self, strict: bool) -> Self {
+        self.strict_names = strict;
+        self
+    }
+
+    /// Aborts reading once `budget` is spent; see [`Budget`].
+    #[inline]
+    #[must_use]
+    pub const fn with_budget(mut self, budget: Budget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /** Sets the [`AttributePolicy`] for duplicate XML attributes.
+
+    # Examples
+    ```
+    # #[cfg(feature = "xml-read")]
+    # {
+    use openmath::{OpenMath, OMDeserializable, de::{AttributePolicy, Options, XmlReadError}};
+
+    let s = r#"<OMS cd="arith1" cd="arith2" name="plus"/>"#;
+    let Err(XmlReadError::DuplicateAttribute { attribute, .. }) = OpenMath::from_openmath_xml(s)
+    else {
+        panic!("cd is duplicated")
+    };
+    assert_eq!(attribute, "cd");
+
+    let options = Options::new().with_attribute_policy(AttributePolicy::Lenient);
+    let om = OpenMath::from_openmath_xml_with(s, options).expect("is accepted");
+    assert_eq!(om.to_sexpr(), "(oms arith1 plus)");
+    # }
+    ```
+    */
+    #[inline]
+    #[must_use]
+    pub const fn with_attribute_policy(mut self, policy: AttributePolicy) -> Self {
+        self.attribute_policy = policy;
+        self
+    }
+
+    /** Sets the [`FloatPolicy`] for decimal floats.
+
+    # Examples
+    ```
+    # #[cfg(feature = "xml-read")]
+    # {
+    use openmath::{OMDeserializable, de::{FloatPolicy, Options, XmlReadError}};
+
+    let s = r#"<OMF dec="0.1000000000000000000000001"/>"#;
+    assert_eq!(f64::from_openmath_xml(s).ok(), Some(0.1));
+
+    let options = Options::new().with_float_policy(FloatPolicy::ErrorOnInexact);
+    assert!(matches!(
+        f64::from_openmath_xml_with(s, options),
+        Err(XmlReadError::InexactFloat(_))
+    ));
+    let exact = f64::from_openmath_xml_with(r#"<OMF dec="0.125"/>"#, options);
+    assert_eq!(exact.ok(), Some(0.125));
+    # }
+    ```
+    */
+    #[inline]
+    #[must_use]
+    pub const fn with_float_policy(mut self, policy: FloatPolicy) -> Self {
+        self.float_policy = policy;
+        self
+    }
+
+    /** Sets the [`DuplicateKeys`] policy for OMATTRs read via serde (see
+    [`OMFromSerde::with_options`]).
+
+    # Examples
+    ```
+    # #[cfg(feature = "serde")]
+    # {
+    use openmath::{OpenMath, de::{DuplicateKeys, OMFromSerde, Options}};
+    use serde::de::DeserializeSeed;
+
+    let json = r#"{ "kind":"OMATTR", "attributes":[
+        [{ "kind":"OMS", "cd":"meta", "name":"note" }, { "kind":"OMI", "integer":1 }],
+        [{ "kind":"OMS", "cd":"meta", "name":"note" }, { "kind":"OMI", "integer":2 }]
+      ], "object":{ "kind":"OMV", "name":"x" } }"#;
+    assert!(serde_json::from_str::<OMFromSerde<OpenMath>>(json).is_ok());
+    let e = OMFromSerde::<OpenMath>::with_options(
+        Options::new().with_duplicate_keys(DuplicateKeys::Reject),
+    )
+    .deserialize(&mut serde_json::Deserializer::from_str(json))
+    .map(OMFromSerde::into_inner)
+    .expect_err("repeats a key");
+    assert!(e.to_string().contains("duplicate attribute key http://www.openmath.org/cd?meta#note"));
+    # }
+    ```
+    */
+    #[inline]
+    #[must_use]
+    pub const fn with_duplicate_keys(mut self, policy: DuplicateKeys) -> Self {
+        self.duplicate_keys = policy;
+        self
+    }
+
+    /** Reads OMRs with `policy`; with [`OMRPolicy::Resolve`], they are replaced by the
+    objects the resolver returns (see [`OMRPolicy`] for an example). A resolver may read
+    other documents with the options it is given, to resolve their references, too.
+
+    Unlike [`OMRPolicy::Error`], resolving does not depend on the type read, and also
+    applies via serde (see [`OMFromSerde::with_options`]).
+    */
+    #[inline]
+    #[must_use]
+    pub const fn with_omr_policy(mut self, policy: &'o OMRPolicy) -> Self {
+        self.omr_policy = Some(policy);
+        self
+    }
+
+    /** Fails with [`ReferenceError::BudgetExceeded`] once the objects resolved for OMRs
+    have more than `budget` nodes in total (counting every object, attribute key and bound
+    variable); by default, [`OMRPolicy::DEFAULT_BUDGET`]. References resolved while
+    resolving another one (by a resolver reading another document with the options it is
+    given) count towards the budget of the outermost read.
+
+    # Examples
+    ```
+    # #[cfg(feature = "xml-read")]
+    # {
+    use openmath::{OpenMath, OMDeserializable, de::{OMRPolicy, Options, ReferenceError, XmlReadError}};
+
+    let s = r##"<OMA><OMS cd="arith1" name="plus"/><OMR href="#x"/><OMR href="#x"/></OMA>"##;
+    let x = OpenMath::from_sexpr("(oma (oms arith1 minus) (omi 1) (omi 2))").expect("is valid");
+    let policy = OMRPolicy::resolve(move |_, _| Some(x.clone()));
+    let options = Options::new().with_omr_policy(&policy);
+    assert!(OpenMath::from_openmath_xml_with(s, options.with_omr_budget(8)).is_ok());
+    let Err(XmlReadError::Reference { error, .. }) =
+        OpenMath::from_openmath_xml_with(s, options.with_omr_budget(7))
+    else {
+        panic!("resolves 8 nodes")
     };
+    assert_eq!(error, ReferenceError::BudgetExceeded(7));
+    # }
+    ```
+    */
+    #[inline]
+    #[must_use]
+    pub const fn with_omr_budget(mut self, budget: usize) -> Self {
+        self.omr_budget = budget;
+        self
+    }
+
+    /** Appends every `cdbase` attribute of the input to `origins`, in document order, with
+    the [`Path`](crate::Path) of the object it belongs to (including that of an OMOBJ, at
+    the empty path); e.g. to find out where the cdbase a symbol was resolved against was set,
+    or to flag redundant attributes (see [`CdBaseOrigin::is_redundant`]). The cdbase in
+    effect changes exactly at the attributes that are not redundant.
+
+    The symbols of attribution keys and of OMEs are reported at the path of the object they
+    belong to, and bound variables at that of their OMBIND. Only
+    [`from_openmath_xml_with`](OMDeserializable::from_openmath_xml_with),
+    [`from_openmath_xml_reader_with`](OMDeserializableOwned::from_openmath_xml_reader_with)
+    and their [`OMObject`] counterparts record them; attributes read before an error are
+    recorded as well.
+
+    # Examples
+    ```
+    use std::cell::RefCell;
+    use openmath::{OMDeserializable, OMKind, OpenMath, Path, Step, de::Options};
+
+    let origins = RefCell::new(Vec::new());
+    OpenMath::from_openmath_xml_with(
+        r#"<OMA cdbase="http://a.org"><OMS cd="c" name="f"/><OMS cdbase="http://a.org/" cd="c" name="g"/><OMS cdbase="http://b.org" cd="c" name="h"/></OMA>"#,
+        Options::new().with_cdbase_origins(&origins),
+    )
+    .expect("is valid");
+    let origins = origins.into_inner();
+    assert_eq!(origins.len(), 3);
+    let (path, origin) = &origins[0];
+    assert_eq!((path, origin.element, origin.position), (&Path::new(), Some(OMKind::OMA), 0));
+    assert!(!origin.is_redundant());
+    let (path, origin) = &origins[1];
+    assert_eq!(path, &Path::new().then(Step::Argument(0)));
+    assert!(origin.is_redundant());
+    assert_eq!(origins[2].1.cdbase, "http://b.org");
+    ```
+    */
+    #[cfg(feature = "xml-read")]
+    #[inline]
+    #[must_use]
+    #[allow(clippy::type_complexity)]
+    pub const fn with_cdbase_origins(
+        mut self,
+        origins: &'o std::cell::RefCell<Vec<(crate::Path, CdBaseOrigin)>>,
+    ) -> Self {
+        self.cdbase_origins = Some(origins);
+        self
+    }
+
+    /// The default cdbase as stored in an [`OMObject`], which outlives the options
+    #[cfg(any(feature = "xml-read", feature = "serde"))]
+    fn owned_default_cdbase<'de>(&self) -> Option<Cow<'de, str>> {
+        self.default_cdbase.map(|c| Cow::Owned(c.to_owned()))
+    }
+
+    #[cfg(feature = "xml-read")]
+    fn same_cdbase_origins(&self, other: &Self) -> bool {
+        match (self.cdbase_origins, other.cdbase_origins) {
+            (Some(a), Some(b)) => std::ptr::eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        }
+    }
+    #[cfg(not(feature = "xml-read"))]
+    #[allow(clippy::unused_self)]
+    const fn same_cdbase_origins(&self, _: &Self) -> bool {
+        true
+    }
 }
-impl_int_deserializable! {
-    i8 = "not an i8", u8 = "not a u8",
-    i16 = "not an i16", u16 = "not a u16",
-    u32 = "not a u32", i32 = "not an i32",
-    i64 = "not an i64", u64 = "not a u64",
-    i128 = "not an i128",
-    isize = "not an isize", usize = "not a usize"
+/// Attribute filters (and [OMR policies](Options::with_omr_policy) and where
+/// [cdbase origins](Options::with_cdbase_origins) are recorded) are compared by address, which is only meaningful for the very same function; see
+/// [`std::ptr::fn_addr_eq`].
+impl PartialEq for Options<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.version_policy == other.version_policy
+            && self.default_cdbase == other.default_cdbase
+            && self.base == other.base
+            && self.limits == other.limits
+            && self.strict_names == other.strict_names
+            && self.budget == other.budget
+            && self.attribute_policy == other.attribute_policy
+            && self.float_policy == other.float_policy
+            && self.duplicate_keys == other.duplicate_keys
+            && self.omr_budget == other.omr_budget
+            && match (self.omr_policy, other.omr_policy) {
+                (Some(a), Some(b)) => std::ptr::eq(a, b),
+                (a, b) => a.is_none() && b.is_none(),
+            }
+            && self.same_cdbase_origins(other)
+            && match (self.attr_filter, other.attr_filter) {
+                (Some(a), Some(b)) => std::ptr::fn_addr_eq(a, b),
+                (a, b) => a.is_none() && b.is_none(),
+            }
+    }
+}
+impl Eq for Options<'_> {}
+impl Default for Options<'_> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl From<VersionPolicy> for Options<'_> {
+    #[inline]
+    fn from(version_policy: VersionPolicy) -> Self {
+        Self::new().with_version_policy(version_policy)
+    }
+}
+
+/** Wrapper to deserialize an OMOBJ value, keeping its metadata.
+
+Can also be [constructed](Self::new) directly and [dereferences](std::ops::Deref) to the
+wrapped value. If the value is [`OMSerializable`](crate::OMSerializable), it can be written
+back as an OMOBJ via [`as_ser`](Self::as_ser) or [`xml`](Self::xml), preserving its `id` and
+`version`; [`ser::OMObject`](crate::ser::OMObject) is the borrowing counterpart for
+serialization only.
+
+# Examples
+```
+use openmath::de::{OMObject, Options};
+
+let obj = OMObject::new(2).with_id("two");
+assert_eq!(*obj + 1, 3);
+# #[cfg(feature = "xml")]
+# {
+let xml = obj.xml(false).to_string();
+assert_eq!(xml, r#"<OMOBJ version="2.0" id="two" xmlns="http://www.openmath.org/OpenMath"><OMI>2</OMI></OMOBJ>"#);
+let back = OMObject::<i32>::from_openmath_xml_with(&xml, Options::new()).expect("is valid");
+assert_eq!((*back, back.id()), (2, Some("two")));
+# }
+```
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OMObject<'de, O: OMDeserializable<'de>> {
+    object: O,
+    version: Option<Cow<'de, str>>,
+    id: Option<Cow<'de, str>>,
+    pub(crate) default_cdbase: Option<Cow<'de, str>>,
 }
+impl<'de, O: OMDeserializable<'de>> OMObject<'de, O> {
+    /// Wraps `object`, without `version` or `id`.
+    #[inline]
+    pub const fn new(object: O) -> Self {
+        Self {
+            object,
+            version: None,
+            id: None,
+            default_cdbase: None,
+        }
+    }
+
+    /// Sets the `version` attribute of the OMOBJ.
+    #[inline]
+    #[must_use]
+    pub fn with_version(mut self, version: impl Into<Cow<'de, str>>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+
+    /// Sets the `id` attribute of the OMOBJ.
+    #[inline]
+    #[must_use]
+    pub fn with_id(mut self, id: impl Into<Cow<'de, str>>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Sets the cdbase that symbols without one are resolved against, in place of
+    /// [`CD_BASE`](crate::CD_BASE), when writing this OMOBJ; i.e. symbols in that cdbase
+    /// are written without one.
+    #[inline]
+    #[must_use]
+    pub fn with_default_cdbase(mut self, cdbase: impl Into<Cow<'de, str>>) -> Self {
+        self.default_cdbase = Some(cdbase.into());
+        self
+    }
+
+    /// Returns the deserialized value.
+    #[inline]
+    pub fn into_inner(self) -> O {
+        self.object
+    }
+
+    /// Returns the `version` attribute of the OMOBJ, if present.
+    #[inline]
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// Returns the `id` attribute of the OMOBJ, if present (only in XML).
+    #[inline]
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    /// Returns the cdbase that symbols without one are resolved against; see
+    /// [`with_default_cdbase`](Self::with_default_cdbase).
+    #[inline]
+    pub fn default_cdbase(&self) -> &str {
+        self.default_cdbase.as_deref().unwrap_or(crate::CD_BASE)
+    }
+
+    /// Returns a [`DeserializeSeed`](serde::de::DeserializeSeed) for deserializing an OMOBJ
+    /// with the given [`Options`] (or just a [`VersionPolicy`]); the
+    /// [`Deserialize`](serde::Deserialize)-implementation uses the default options.
+    ///
+    /// # Examples
+    /// ```
+    /// use openmath::de::{OMObject, VersionPolicy};
+    /// use serde::de::DeserializeSeed;
+    ///
+    /// let s = r#"{ "kind":"OMOBJ", "openmath":"3.0", "object":{ "kind":"OMI", "integer":2 } }"#;
+    /// assert!(serde_json::from_str::<OMObject<i32>>(s).is_err());
+    /// let obj = OMObject::<i32>::seed(VersionPolicy::Lenient)
+    ///     .deserialize(&mut serde_json::Deserializer::from_str(s))
+    ///     .expect("is valid");
+    /// assert_eq!(obj.version(), Some("3.0"));
+    /// ```
+    #[cfg(feature = "serde")]
+    #[inline]
+    #[must_use]
+    pub fn seed<'o>(
+        options: impl Into<Options<'o>>,
+    ) -> impl serde::de::DeserializeSeed<'de, Value = Self>
+    where
+        O: 'de,
+    {
+        let options = options.into();
+        serde_impl::OMObjectSeed(
+            options.version_policy,
+            options.owned_default_cdbase(),
+            serde_impl::Scope::new(&options),
+            References::new(&options),
+            std::marker::PhantomData,
+        )
+    }
+
+    /** Deserializes an [OMDeserializable] from an XML string starting with `<OMOBJ>`
+     *
+    Uses the default [`VersionPolicy`]; see [`from_openmath_xml_with`](Self::from_openmath_xml_with).
+
+    # Errors
+    iff the string provided is invalid XML, or invalid <span style="font-variant:small-caps;">OpenMath</span>, or [from_openmath](OMDeserializable::from_openmath)
+    errors.
+
+    # Examples
+    ```
+    use openmath::de::{OMDeserializable, OM, OMObject};
+
+    let s = r#"<OMOBJ cdbase="http://www.openmath.org/cd">
+      <OMI>2</OMI>
+    </OMOBJ>"#;
+    assert_eq!(OMObject::<i32>::from_openmath_xml(s).expect("is valid"),2);
+    ```
+    */
+    #[inline]
+    #[cfg(feature = "xml-read")]
+    pub fn from_openmath_xml(input: &'de str) -> Result<O, xml::XmlReadError<O::Err>>
+    where
+        O: Sized,
+    {
+        Self::from_openmath_xml_with(input, VersionPolicy::default()).map(Self::into_inner)
+    }
+
+    /** Like [`from_openmath_xml`](Self::from_openmath_xml), but with the given [`Options`]
+    (or just a [`VersionPolicy`]), and keeping the OMOBJ's metadata (e.g. its
+    [`version`](Self::version)); the result is written back with the same
+    [default cdbase](Self::with_default_cdbase).
+
+    # Errors
+    iff the string provided is invalid XML, or invalid <span style="font-variant:small-caps;">OpenMath</span>,
+    the `version` is rejected by the [`VersionPolicy`], or
+    [from_openmath](OMDeserializable::from_openmath) errors.
+
+    # Examples
+    ```
+    use openmath::de::{OMObject, VersionPolicy};
+
+    let s = r#"<OMOBJ version="3.0"><OMI>2</OMI></OMOBJ>"#;
+    assert!(OMObject::<i32>::from_openmath_xml(s).is_err());
+    let obj = OMObject::<i32>::from_openmath_xml_with(s, VersionPolicy::Lenient).expect("is valid");
+    assert_eq!(obj.version(), Some("3.0"));
+    assert_eq!(obj.into_inner(), 2);
+    ```
+    */
+    #[inline]
+    #[cfg(feature = "xml-read")]
+    pub fn from_openmath_xml_with<'o>(
+        input: &'de str,
+        options: impl Into<Options<'o>>,
+    ) -> Result<Self, xml::XmlReadError<O::Err>>
+    where
+        O: Sized,
+    {
+        let options = options.into();
+        read_configured!(xml::FromString<'de>, input, &options, |reader| {
+            Self::read_obj_with(&mut reader, &options)
+        })
+    }
+
+    #[cfg(feature = "xml-read")]
+    fn read_obj_with(
+        reader: &mut impl xml::Readable<'de, O>,
+        options: &Options<'_>,
+    ) -> Result<Self, xml::XmlReadError<O::Err>>
+    where
+        O: Sized,
+    {
+        let object = reader.read_obj(
+            options.version_policy,
+            options.default_cdbase.unwrap_or(crate::CD_BASE),
+        )?;
+        Ok(Self {
+            default_cdbase: options.owned_default_cdbase(),
+            ..object
+        })
+    }
+
+    /** Like [`from_openmath_xml_with`](Self::from_openmath_xml_with) with the default
+    [`VersionPolicy`], but resolves symbols without a cdbase (in scope) against `cdbase`
+    instead of [`CD_BASE`](crate::CD_BASE); the result is written back with the same
+    [default](Self::with_default_cdbase).
+
+    # Errors
+    see [`from_openmath_xml_with`](Self::from_openmath_xml_with)
+
+    # Examples
+    ```
+    use openmath::{OpenMath, de::OMObject};
+
+    let s = r#"<OMOBJ><OMA><OMS cd="units" name="metre"/><OMS cdbase="http://www.openmath.org/cd" cd="arith1" name="plus"/></OMA></OMOBJ>"#;
+    let obj = OMObject::<OpenMath>::from_openmath_xml_with_default_cdbase(s, "http://example.org/cd")
+        .expect("is valid");
+    let OpenMath::OMA { applicant, .. } = &*obj else { unreachable!() };
+    let OpenMath::OMS { cdbase, .. } = &**applicant else { unreachable!() };
+    assert_eq!(cdbase.as_deref(), Some("http://example.org/cd"));
+    # #[cfg(feature = "xml-write")]
+    assert_eq!(obj.xml(false).to_string(), s.replace("<OMOBJ>", r#"<OMOBJ version="2.0" xmlns="http://www.openmath.org/OpenMath">"#));
+    ```
+    */
+    #[cfg(feature = "xml-read")]
+    pub fn from_openmath_xml_with_default_cdbase(
+        input: &'de str,
+        cdbase: impl Into<Cow<'de, str>>,
+    ) -> Result<Self, xml::XmlReadError<O::Err>>
+    where
+        O: Sized,
+    {
+        use xml::Readable;
+        let cdbase = cdbase.into();
+        <xml::FromString as xml::Events<'de>>::new(input)
+            .read_obj(VersionPolicy::default(), &cdbase)
+            .map(|o: Self| o.with_default_cdbase(cdbase))
+    }
+
+    /// Like [`from_openmath_xml`](Self::from_openmath_xml), but recovers the complete
+    /// children of the object of a broken (e.g. truncated) OMOBJ; see
+    /// [`OMDeserializable::from_openmath_xml_partial`].
+    ///
+    /// # Errors
+    /// iff [`from_openmath_xml`](Self::from_openmath_xml) fails.
+    #[cfg(feature = "xml-read")]
+    pub fn from_openmath_xml_partial(input: &'de str) -> Result<O, xml::PartialParse<O, O::Err>>
+    where
+        O: Sized,
+    {
+        Self::from_openmath_xml(input).map_err(|e| xml::partial(input, e))
+    }
+
+    /// Like [`from_openmath_xml_with`](Self::from_openmath_xml_with), but additionally
+    /// records the formatting of the input; see
+    /// [`OMDeserializable::from_openmath_xml_preserving`].
+    ///
+    /// # Errors
+    /// see [`from_openmath_xml_with`](Self::from_openmath_xml_with)
+    #[inline]
+    #[cfg(feature = "xml-read")]
+    pub fn from_openmath_xml_preserving<'o>(
+        input: &'de str,
+        options: impl Into<Options<'o>>,
+    ) -> Result<(Self, crate::FormatMap), xml::XmlReadError<O::Err>>
+    where
+        O: Sized,
+    {
+        let options = options.into();
+        let mut reader = <xml::Recording<xml::FromString> as xml::Events<'de>>::new(input);
+        xml::Events::configure(&mut reader, &options);
+        let r = Self::read_obj_with(&mut reader, &options)?;
+        Ok((r, reader.map))
+    }
+}
+
+impl<'de, O: OMDeserializable<'de> + crate::OMSerializable> OMObject<'de, O> {
+    /// Borrows this as a [`ser::OMObject`](crate::ser::OMObject), e.g. to serialize it
+    /// with serde (which has no `id`, and always writes the current version).
+    #[inline]
+    pub const fn as_ser(&self) -> crate::ser::OMObject<'_, O> {
+        crate::ser::OMObject(&self.object)
+    }
+
+    /// Returns something that [`Display`](std::fmt::Display)s as the
+    /// <span style="font-variant:small-caps;">OpenMath</span> XML of this OMOBJ, with its
+    /// `id` and `version` (or the current version, if it has none).
+    #[inline]
+    #[must_use]
+    #[cfg(feature = "xml-write")]
+    pub fn xml(&self, pretty: bool) -> impl std::fmt::Display + '_ {
+        crate::ser::xml::XmlObjDisplay {
+            o: &self.object,
+            options: crate::ser::XmlOptions {
+                pretty,
+                version: Some(self.version().unwrap_or(crate::OPENMATH_VERSION)),
+                id: self.id(),
+                default_cdbase: self.default_cdbase(),
+                ..crate::ser::XmlOptions::default()
+            },
+            format: None,
+        }
+    }
+
+    /// Like [`OMSerializable::openmath_display`](crate::ser::OMSerializable::openmath_display),
+    /// but relative to the [default cdbase](Self::with_default_cdbase).
+    #[inline]
+    pub fn openmath_display(&self) -> impl std::fmt::Display + std::fmt::Debug + use<'_, 'de, O> {
+        crate::ser::OMDisplay(
+            &self.object,
+            self.object.cdbase(),
+            crate::ser::FloatStyle::default(),
+            None,
+            self.default_cdbase(),
+            None,
+        )
+    }
+
+    /// Like [`OMSerializable::openmath_serde`](crate::ser::OMSerializable::openmath_serde),
+    /// but relative to the [default cdbase](Self::with_default_cdbase).
+    #[cfg(feature = "serde")]
+    #[inline]
+    pub fn openmath_serde(&self) -> impl ::serde::Serialize + use<'_, 'de, O> {
+        crate::ser::serde_impl::SerdeSerializer(
+            &self.object,
+            self.object.cdbase(),
+            self.default_cdbase(),
+            crate::ser::serde_impl::Form::Object,
+        )
+    }
+}
+
+impl<'de, O: OMDeserializable<'de>> From<O> for OMObject<'de, O> {
+    #[inline]
+    fn from(object: O) -> Self {
+        Self::new(object)
+    }
+}
+
+impl<'de, O: OMDeserializable<'de>> std::ops::Deref for OMObject<'de, O> {
+    type Target = O;
+    #[inline]
+    fn deref(&self) -> &O {
+        &self.object
+    }
+}
+
+impl<'de, O: OMDeserializable<'de>> std::ops::DerefMut for OMObject<'de, O> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut O {
+        &mut self.object
+    }
+}
+
+impl<O: OMDeserializable<'static>> OMObject<'static, O> {
+    /// Like [`from_openmath_xml_with`](Self::from_openmath_xml_with), but reads
+    /// incrementally from `reader`, like
+    /// [`from_openmath_xml_reader`](OMDeserializableOwned::from_openmath_xml_reader).
+    ///
+    /// # Errors
+    /// see [`from_openmath_xml_with`](Self::from_openmath_xml_with)
+    #[inline]
+    #[cfg(feature = "xml-read")]
+    pub fn from_openmath_xml_reader_with<'o, R: std::io::BufRead>(
+        reader: R,
+        options: impl Into<Options<'o>>,
+    ) -> Result<Self, xml::XmlReadError<O::Err>>
+    where
+        O: Sized,
+    {
+        let options = options.into();
+        read_configured!(xml::Reader<R>, reader, &options, |reader| {
+            Self::read_obj_with(&mut reader, &options)
+        })
+    }
+}
+
+/// Enum for deserializing from <span style="font-variant:small-caps;">OpenMath</span>. See
+/// see [OMDeserializable] for documentation and an example.
+///
+/// Note that there is no case for [OMATTR](crate::OMKind::OMATTR) - instead,
+/// every case has a <code>[Vec]<[OMAttr]<'de, I>></code>, which is usually empty.
+/// Otherwise, we'd have to either deal with two separate types, or have the
+/// nonsensical case `OMATTR(OMATTR(OMATTR(...),...),...)`, which would also
+/// require a [`Box`]-indirection (hence allocation), etc. since OMATTR is mostly used
+/// for metadata which the recipient might not even care about, or only care secondarily
+/// (compared to the *actual* [OM]-kind), having OMATTR be a separate case seems
+/// like bad API design.
+/// Also, empty Vecs are cheap.
+///
+/// This enum is `#[non_exhaustive]`: new variants (like [`OMR`](OM::OMR)) may be added in
+/// minor releases, as the crate learns to read more of the standard; implementations of
+/// [`from_openmath`](OMDeserializable::from_openmath) should have a fallback arm, which
+/// usually returns an error anyway.
+#[derive(Clone)]
+#[non_exhaustive]
+// larger inline capacities make applications larger, which is what they are chosen for
+#[cfg_attr(
+    any(feature = "smallvec-4", feature = "smallvec-8"),
+    allow(clippy::large_enum_variant)
+)]
+pub enum OM<'de, I> {
+    /** <div class="openmath">
+    Integers in the mathematical sense, with no predefined range.
+    They are “infinite precision” integers (also called “bignums” in computer algebra).
+    </div> */
+    OMI {
+        int: crate::Int<'de>,
+        attrs: Attrs<OMAttr<'de, I>>,
+    },
+
+    /** <div class="openmath">
+    Double precision floating-point numbers following the IEEE 754-1985 standard.
+    </div>
+
+    This is a plain [`f64`]; comparing with an [`OpenMath`](crate::OpenMath) follows the
+    semantics documented at [`OpenMath::OMF`](crate::OpenMath::OMF). */
+    OMF {
+        float: f64,
+        /// The decimal as written, under [`FloatPolicy::PreserveLexical`]; `None` otherwise,
+        /// and for JSON numbers
+        lexical: Option<Cow<'de, str>>,
+        attrs: Attrs<OMAttr<'de, I>>,
+    },
+
+    /** <div class="openmath">
+    A Unicode Character string. This also corresponds to “characters” in XML.
+    </div> */
+    OMSTR {
+        string: Cow<'de, str>,
+        attrs: Attrs<OMAttr<'de, I>>,
+    },
+
+    /** <div class="openmath">
+    A sequence of bytes.
+    </div> */
+    OMB {
+        bytes: Cow<'de, [u8]>,
+        attrs: Attrs<OMAttr<'de, I>>,
+    },
+
+    ///<div class="openmath">
+    ///
+    /// A Variable must have a name which is a sequence of characters matching a regular
+    /// expression, as described in [Section 2.3](https://openmath.org/standard/om20-2019-07-01/omstd20.html#sec_names).
+    ///
+    ///</div>
+    ///
+    ///(Note: We do not enforce that names are valid XML names;)
+    OMV {
+        name: Cow<'de, str>,
+        attrs: Attrs<OMAttr<'de, I>>,
+    },
+
+    ///<div class="openmath">
+    ///
+    /// A Symbol encodes three fields of information, a symbol name, a Content Dictionary name,
+    /// and (optionally) a Content Dictionary base URI, The name of a symbol is a sequence of
+    /// characters matching the regular expression described in
+    /// [Section 2.3](https://openmath.org/standard/om20-2019-07-01/omstd20.html#sec_names).
+    /// The Content Dictionary is the location of the definition of the symbol, consisting of a
+    /// name (a sequence of characters matching the regular expression described in
+    /// [Section 2.3](https://openmath.org/standard/om20-2019-07-01/omstd20.html#sec_names))
+    /// and, optionally, a unique prefix called a cdbase which is used to disambiguate multiple
+    /// Content Dictionaries of the same name. There are other properties of the symbol that are
+    /// not explicit in these fields but whose values may be obtained by inspecting the Content
+    /// Dictionary specified. These include the symbol definition, formal properties and examples
+    /// and, optionally, a role which is a restriction on where the symbol may appear in an
+    /// <span style="font-variant:small-caps;">OpenMath</span> object. The possible roles are described in
+    /// [Section 2.1.4](https://openmath.org/standard/om20-2019-07-01/omstd20.html#sec_roles).
+    ///
+    ///</div>
+    OMS {
+        /// the symbol's own `cdbase`, if given explicitly; the effective cdbase is passed
+        /// to [`from_openmath`](OMDeserializable::from_openmath) separately
+        cdbase: Option<Cow<'de, str>>,
+        cd: Cow<'de, str>,
+        name: Cow<'de, str>,
+        attrs: Attrs<OMAttr<'de, I>>,
+    },
+
+    /** <div class="openmath">
+    If $A_1,...,A_n\;(n>0)$ are <span style="font-variant:small-caps;">OpenMath</span> objects, then
+    $\mathrm{application}(A_1,...,A_n)$ is an <span style="font-variant:small-caps;">OpenMath</span> application object.
+    We call $A_1$ the function and $A_2$ to $A_n$ the arguments.
+    </div> */
+    OMA {
+        applicant: I,
+        arguments: Args<I>,
+        attrs: Attrs<OMAttr<'de, I>>,
+    },
+
+    /** <div class="openmath">
+    If $B$ and $C$ are <span style="font-variant:small-caps;">OpenMath</span> objects, and $v_1,...,v_n\;(n\geq0)$
+    are <span style="font-variant:small-caps;">OpenMath</span> variables or attributed variables, then
+    $\mathrm{binding}(B,v_1,...,v_n,C)$ is an <span style="font-variant:small-caps;">OpenMath</span> binding object.
+    $B$ is called the binder, $v_1,...,v_n$ are called variable bindings, and
+    $C$ is called the body of the binding object above.
+    </div> */
+    OMBIND {
+        binder: I,
+        variables: Vars<(Cow<'de, str>, Attrs<OMAttr<'de, I>>)>,
+        object: I,
+        attrs: Attrs<OMAttr<'de, I>>,
+    },
+
+    /** <div class="openmath">
+    If $S$ is an <span style="font-variant:small-caps;">OpenMath</span> symbol and $A_1,...,A_n\;(n\geq0)$ are <span style="font-variant:small-caps;">OpenMath</span> objects or
+    derived <span style="font-variant:small-caps;">OpenMath</span> objects, then $\mathrm{error}(S,A_1,...,A_n)$ is an <span style="font-variant:small-caps;">OpenMath</span> error object.
+    </div> */
+    OME {
+        cdbase: Option<Cow<'de, str>>,
+        cd: Cow<'de, str>,
+        name: Cow<'de, str>,
+        arguments: Vec<OMMaybeForeign<'de, I>>,
+        attrs: Attrs<OMAttr<'de, I>>,
+    },
+
+    /** A reference to the object with the given `id` (see
+    [OMR](crate::OMKind::OMR)), as written: `href` is usually a fragment like `#x`, but
+    may be any URI.
+
+    The front-ends only resolve references when read with an [`OMRPolicy`] that does (see
+    [`Options::with_omr_policy`]); otherwise, an implementation of
+    [`from_openmath`](OMDeserializable::from_openmath) can resolve them itself (e.g.
+    against a global store), or return an error, like [`OpenMath`](crate::OpenMath).
+    */
+    OMR {
+        href: Cow<'de, str>,
+        attrs: Attrs<OMAttr<'de, I>>,
+    },
+}
+impl<'de, I> OM<'de, I> {
+    /// Converts the immediate children of this [`OM`] (arguments, binders, bodies and
+    /// attribute values) using `f`, e.g. to hand it on to a different [`OMDeserializable`].
+    #[allow(clippy::too_many_lines)]
+    pub(crate) fn try_map<J, E>(
+        self,
+        mut f: impl FnMut(I) -> Result<J, E>,
+    ) -> Result<OM<'de, J>, E> {
+        fn foreign<'de, I, J, E>(
+            o: OMMaybeForeign<'de, I>,
+            f: &mut impl FnMut(I) -> Result<J, E>,
+        ) -> Result<OMMaybeForeign<'de, J>, E> {
+            Ok(match o {
+                OMMaybeForeign::OM(i) => OMMaybeForeign::OM(f(i)?),
+                OMMaybeForeign::Foreign { encoding, value } => {
+                    OMMaybeForeign::Foreign { encoding, value }
+                }
+            })
+        }
+        fn attrs<'de, I, J, E>(
+            attrs: Attrs<OMAttr<'de, I>>,
+            f: &mut impl FnMut(I) -> Result<J, E>,
+        ) -> Result<Attrs<OMAttr<'de, J>>, E> {
+            attrs
+                .into_iter()
+                .map(|a| {
+                    Ok(crate::Attr {
+                        key: a.key,
+                        value: foreign(a.value, f)?,
+                    })
+                })
+                .collect()
+        }
+        Ok(match self {
+            Self::OMI { int, attrs: a } => OM::OMI {
+                int,
+                attrs: attrs(a, &mut f)?,
+            },
+            Self::OMF {
+                float,
+                lexical,
+                attrs: a,
+            } => OM::OMF {
+                float,
+                lexical,
+                attrs: attrs(a, &mut f)?,
+            },
+            Self::OMSTR { string, attrs: a } => OM::OMSTR {
+                string,
+                attrs: attrs(a, &mut f)?,
+            },
+            Self::OMB { bytes, attrs: a } => OM::OMB {
+                bytes,
+                attrs: attrs(a, &mut f)?,
+            },
+            Self::OMR { href, attrs: a } => OM::OMR {
+                href,
+                attrs: attrs(a, &mut f)?,
+            },
+            Self::OMV { name, attrs: a } => OM::OMV {
+                name,
+                attrs: attrs(a, &mut f)?,
+            },
+            Self::OMS {
+                cdbase,
+                cd,
+                name,
+                attrs: a,
+            } => OM::OMS {
+                cdbase,
+                cd,
+                name,
+                attrs: attrs(a, &mut f)?,
+            },
+            Self::OMA {
+                applicant,
+                arguments,
+                attrs: a,
+            } => OM::OMA {
+                applicant: f(applicant)?,
+                arguments: arguments
+                    .into_iter()
+                    .map(&mut f)
+                    .collect::<Result<_, _>>()?,
+                attrs: attrs(a, &mut f)?,
+            },
+            Self::OMBIND {
+                binder,
+                variables,
+                object,
+                attrs: a,
+            } => OM::OMBIND {
+                binder: f(binder)?,
+                variables: variables
+                    .into_iter()
+                    .map(|(n, a)| Ok((n, attrs(a, &mut f)?)))
+                    .collect::<Result<_, _>>()?,
+                object: f(object)?,
+                attrs: attrs(a, &mut f)?,
+            },
+            Self::OME {
+                cdbase,
+                cd,
+                name,
+                arguments,
+                attrs: a,
+            } => OM::OME {
+                cdbase,
+                cd,
+                name,
+                arguments: arguments
+                    .into_iter()
+                    .map(|o| foreign(o, &mut f))
+                    .collect::<Result<_, _>>()?,
+                attrs: attrs(a, &mut f)?,
+            },
+        })
+    }
+
+    /// The attributes of this [`OM`]; i.e. the attribution pairs of an enclosing
+    /// [OMATTR](crate::OMKind::OMATTR)
+    #[must_use]
+    pub fn attrs(&self) -> &[OMAttr<'de, I>] {
+        match self {
+            Self::OMI { attrs, .. }
+            | Self::OMF { attrs, .. }
+            | Self::OMSTR { attrs, .. }
+            | Self::OMB { attrs, .. }
+            | Self::OMV { attrs, .. }
+            | Self::OMS { attrs, .. }
+            | Self::OMA { attrs, .. }
+            | Self::OMBIND { attrs, .. }
+            | Self::OME { attrs, .. }
+            | Self::OMR { attrs, .. } => attrs,
+        }
+    }
+
+    /// The attributes of this [`OM`], mutably
+    #[cfg(any(feature = "xml-read", feature = "serde"))]
+    const fn attrs_mut(&mut self) -> &mut Attrs<OMAttr<'de, I>> {
+        match self {
+            Self::OMI { attrs, .. }
+            | Self::OMF { attrs, .. }
+            | Self::OMSTR { attrs, .. }
+            | Self::OMB { attrs, .. }
+            | Self::OMV { attrs, .. }
+            | Self::OMS { attrs, .. }
+            | Self::OMA { attrs, .. }
+            | Self::OMBIND { attrs, .. }
+            | Self::OME { attrs, .. }
+            | Self::OMR { attrs, .. } => attrs,
+        }
+    }
+
+    /// Returns the [OMKind] of this [`OM`], which of all practical purposes
+    /// acts as a discriminant.
+    #[must_use]
+    pub const fn kind(&self) -> crate::OMKind {
+        use crate::OMKind;
+        match self {
+            Self::OMI { .. } => OMKind::OMI,
+            Self::OMF { .. } => OMKind::OMF,
+            Self::OMSTR { .. } => OMKind::OMSTR,
+            Self::OMB { .. } => OMKind::OMB,
+            Self::OMV { .. } => OMKind::OMV,
+            Self::OMS { .. } => OMKind::OMS,
+            Self::OMA { .. } => OMKind::OMA,
+            Self::OMBIND { .. } => OMKind::OMBIND,
+            Self::OME { .. } => OMKind::OME,
+            Self::OMR { .. } => OMKind::OMR,
+        }
+    }
+
+    /// Returns the tag name of this [`OM`]'s [kind](Self::kind) (e.g. `"OMA"`), as used in
+    /// the XML and JSON encodings.
+    #[must_use]
+    pub const fn kind_str(&self) -> &'static str {
+        self.kind().as_str()
+    }
+}
+
+impl<'d> OMDeserializable<'d> for crate::Int<'d> {
+    type Ret = Self;
+    type Err = &'static str;
+    fn from_openmath(om: OM<'d, Self>, _: &str) -> Result<Self, Self::Err>
+    where
+        Self: Sized,
+    {
+        if let OM::OMI { int, .. } = om {
+            Ok(int)
+        } else {
+            Err("Not an integer")
+        }
+    }
+}
+
+impl<'d> OMDeserializable<'d> for f32 {
+    type Ret = Self;
+    type Err = &'static str;
+    #[allow(clippy::cast_possible_truncation)]
+    fn from_openmath(om: OM<'d, Self>, _: &str) -> Result<Self, Self::Err>
+    where
+        Self: Sized,
+    {
+        if let OM::OMF { float, .. } = om {
+            Ok(float as _)
+        } else {
+            Err("Not a float")
+        }
+    }
+}
+
+impl<'d> OMDeserializable<'d> for f64 {
+    type Ret = Self;
+    type Err = &'static str;
+    fn from_openmath(om: OM<'d, Self>, _: &str) -> Result<Self, Self::Err>
+    where
+        Self: Sized,
+    {
+        if let OM::OMF { float, .. } = om {
+            Ok(float)
+        } else {
+            Err("Not a float")
+        }
+    }
+}
+
+impl<'d> OMDeserializable<'d> for Cow<'d, str> {
+    type Ret = Self;
+    type Err = &'static str;
+    fn from_openmath(om: OM<'d, Self>, _: &str) -> Result<Self, Self::Err>
+    where
+        Self: Sized,
+    {
+        if let OM::OMSTR { string, .. } = om {
+            Ok(string)
+        } else {
+            Err("Not an OMSTR")
+        }
+    }
+}
+
+impl<'d> OMDeserializable<'d> for String {
+    type Ret = Self;
+    type Err = &'static str;
+    fn from_openmath(om: OM<'d, Self>, _: &str) -> Result<Self, Self::Err>
+    where
+        Self: Sized,
+    {
+        if let OM::OMSTR { string, .. } = om {
+            Ok(string.into_owned())
+        } else {
+            Err("Not an OMSTR")
+        }
+    }
+}
+
+impl<'d> OMDeserializable<'d> for Cow<'d, [u8]> {
+    type Ret = Self;
+    type Err = &'static str;
+    fn from_openmath(om: OM<'d, Self>, _: &str) -> Result<Self, Self::Err>
+    where
+        Self: Sized,
+    {
+        if let OM::OMB { bytes, .. } = om {
+            Ok(bytes)
+        } else {
+            Err("Not an OMB")
+        }
+    }
+}
+impl<'d> OMDeserializable<'d> for Vec<u8> {
+    type Ret = Self;
+    type Err = &'static str;
+    fn from_openmath(om: OM<'d, Self>, _: &str) -> Result<Self, Self::Err>
+    where
+        Self: Sized,
+    {
+        if let OM::OMB { bytes, .. } = om {
+            Ok(bytes.into_owned())
+        } else {
+            Err("Not an OMB")
+        }
+    }
+}
+
+/// Accepts any object and discards it, e.g. to check that an input is valid without
+/// building anything from it
+impl OMDeserializable<'_> for () {
+    type Ret = Self;
+    type Err = std::convert::Infallible;
+    #[inline]
+    fn from_openmath(_: OM<'_, Self>, _: &str) -> Result<Self, Self::Err>
+    where
+        Self: Sized,
+    {
+        Ok(())
+    }
+}
+
+macro_rules! impl_int_deserializable {
+    ($($t:ty=$err:literal),*) => {
+        $(
+            impl<'d> OMDeserializable<'d> for $t {
+                type Ret = Self;
+                type Err = &'static str;
+                fn from_openmath(
+                    om: OM<'d, Self>,
+                    _: &str
+                ) -> Result<Self, Self::Err>
+                where
+                    Self: Sized,
+                {
+                    if let OM::OMI{int,..} = om {
+                        int.is_i128().map_or(Err($err), |i| {
+                            i.try_into().map_err(|_| $err)
+                        })
+                    } else {
+                        Err("Not an OMI")
+                    }
+                }
+            }
+        )*
+    };
+}
+impl_int_deserializable! {
+    i8 = "not an i8", u8 = "not a u8",
+    i16 = "not an i16", u16 = "not a u16",
+    u32 = "not a u32", i32 = "not an i32",
+    i64 = "not an i64", u64 = "not a u64",
+    i128 = "not an i128",
+    isize = "not an isize", usize = "not a usize"
+}
+
+macro_rules! impl_pointer_deserializable {
+    ($($p:ident),*) => {
+        $(
+            /// Deserializes a `T` and moves it into the pointer; needs `T`'s
+            /// [`Ret`](OMDeserializable::Ret) to convert into the pointer, as it does if it
+            /// is `T` itself.
+            impl<'d, T> OMDeserializable<'d> for $p<T>
+            where
+                T: OMDeserializable<'d>,
+                T::Ret: TryInto<$p<T>, Error: std::fmt::Debug>,
+            {
+                type Ret = T::Ret;
+                type Err = T::Err;
+                #[inline]
+                fn from_openmath(
+                    om: OM<'d, Self::Ret>,
+                    cdbase: &str,
+                ) -> Result<Self::Ret, Self::Err> {
+                    T::from_openmath(om, cdbase)
+                }
+                #[inline]
+                fn before_child(
+                    parent: crate::OMKind,
+                    index: usize,
+                    cdbase: &str,
+                ) -> Visit<Self::Ret> {
+                    T::before_child(parent, index, cdbase)
+                }
+            }
+        )*
+    };
+}
+impl_pointer_deserializable!(Box, Rc, Arc);
+
+#[cfg(all(test, any(feature = "serde", feature = "xml-read")))]
+mod tests {
+    use super::*;
+
+    #[derive(Copy, Clone, Debug)]
+    struct Oma;
+    enum ArgOrOMA {
+        Oms,
+        Omi,
+        Oma,
+    }
+    impl TryFrom<ArgOrOMA> for Oma {
+        type Error = &'static str;
+        fn try_from(value: ArgOrOMA) -> Result<Self, Self::Error> {
+            if matches!(value, ArgOrOMA::Oma) {
+                Ok(Self)
+            } else {
+                Err("nope")
+            }
+        }
+    }
+    impl<'d> OMDeserializable<'d> for Oma {
+        type Ret = ArgOrOMA;
+        type Err = &'static str;
+        fn from_openmath(om: OM<'d, ArgOrOMA>, _cdbase: &str) -> Result<Self::Ret, Self::Err>
+        where
+            Self: Sized,
+        {
+            match om {
+                OM::OMA {
+                    applicant: ArgOrOMA::Oms,
+                    arguments,
+                    ..
+                } if arguments.len() == 2
+                    && arguments.iter().all(|a| matches!(a, ArgOrOMA::Omi)) =>
+                {
+                    Ok(ArgOrOMA::Oma)
+                }
+                OM::OMS { .. } => Ok(ArgOrOMA::Oms),
+                OM::OMI { .. } => Ok(ArgOrOMA::Omi),
+                _ => Err("nope"),
+            }
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_oma_deserialization() {
+        let s = r#"{
+            "cdbase":"http://www.openmath.org/cd",
+            "kind": "OMA",
+            "applicant": {
+                "kind": "OMS",
+                "cd": "arith1",
+                "name": "plus"
+            },
+            "arguments": [
+                { "kind":"OMI", "integer":2 },
+                { "kind":"OMI", "integer":2 }
+            ]
+        }"#;
+        serde_json::from_str::<'_, OMFromSerde<Oma>>(s)
+            .expect("valid json, openmath, and arithmetic expression");
+        serde_json::from_reader::<_, OMFromSerde<Oma>>(s.as_bytes())
+            .expect("valid json, openmath, and arithmetic expression");
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn test_oma_deserialization_xml() {
+        let s = r#"<OMOBJ cdbase="http://www.openmath.org/cd">
+          <OMA>
+            <OMS cd="arith1" name="plus"/>
+            <OMI>2</OMI>
+            <OMI>2</OMI>
+          </OMA>
+        </OMOBJ>"#;
+        OMObject::<Oma>::from_openmath_xml(s).expect("is valid");
+    }
+
+    #[cfg(feature = "serde")]
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn fancy() {
+        use crate as openmath;
+        use either::Either;
+        use openmath::de::{OM, OMDeserializable, OMFromSerde};
+
+        #[derive(Copy, Clone, Debug)]
+        struct SimplifiedInt(i128);
+        impl<'d> TryFrom<Either<Self, OM<'d, Box<Self>>>> for SimplifiedInt {
+            type Error = &'static str;
+            fn try_from(value: Either<Self, OM<'d, Box<Self>>>) -> Result<Self, Self::Error> {
+                if let Either::Left(v) = value {
+                    Ok(v)
+                } else {
+                    Err("nope")
+                }
+            }
+        }
+        impl<'d> OMDeserializable<'d> for SimplifiedInt {
+            type Ret = Either<Self, OM<'d, Box<Self>>>;
+            type Err = &'static str;
+            fn from_openmath(
+                om: OM<'d, Self::Ret>,
+                cdbase: &str,
+            ) -> Result<Either<Self, OM<'d, Box<Self>>>, Self::Err>
+            where
+                Self: Sized,
+            {
+                match om {
+                    // An integer
+                    OM::OMI { int, .. } => {
+                        // ...which fits in an i128
+                        int.is_i128()
+                            .map_or(Err("Invalid int value"), |i| Ok(Either::Left(Self(i))))
+                    }
+                    // Addition or multiplication
+                    OM::OMS { cd, name, .. }
+                        if cd == "arith1"
+                            && (name == "plus" || name == "times")
+                            && cdbase == openmath::CD_BASE =>
+                    {
+                        // works, but without arguments, we can't do anything to it *yet*.
+                        // => We send it back, so we can take care of it later, if it
+                        // occurs as the head of an OMA expression
+                        Ok(either::Right(OM::OMS {
+                            cdbase: None,
+                            cd,
+                            name,
+                            attrs: Attrs::new(),
+                        }))
+                    }
+                    // some operator application to two arguments
+                    OM::OMA {
+                        // still an open math expression:
+                        applicant: either::Right(op),
+                        mut arguments,
+                        ..
+                    } if arguments.iter().all(Either::is_left)
+                        && arguments.len() == 2
+                        && cdbase == openmath::CD_BASE =>
+                    {
+                        // An OMA only ends up here, after both the head and all arguments
+                        // were fed into this method.
+                        // Since "plus" and "times" are the only values for
+                        // which we return `either::Right`, we know the following matches:
+                        let is_times = match op {
+                            OM::OMS { name, .. } => name == "times",
+                            _ => unreachable!(),
+                        };
+                        let Some(Either::Left(arg2)) = arguments.pop() else {
+                            unreachable!()
+                        };
+                        let Some(Either::Left(arg1)) = arguments.pop() else {
+                            unreachable!()
+                        };
+                        let value = if is_times {
+                            arg1.0 * arg2.0
+                        } else {
+                            arg1.0 + arg2.0
+                        };
+                        Ok(Either::Left(Self(value)))
+                    }
+                    // everything else is illegal
+                    _ => Err("Not an arithmetic expression"),
+                }
+            }
+        }
+
+        // 2 + 2
+        let s = r#"{
+            "cdbase":"http://www.openmath.org/cd",
+            "kind": "OMA",
+            "applicant": {
+                "kind": "OMS",
+                "cd": "arith1",
+                "name": "plus"
+            },
+            "arguments": [
+                { "kind":"OMI", "integer":2 },
+                { "kind":"OMI", "integer":2 }
+            ]
+        }"#;
+        let r = serde_json::from_str::<'_, OMFromSerde<SimplifiedInt>>(s)
+            .expect("valid json, openmath, and arithmetic expression");
+        assert_eq!(r.into_inner().0, 4);
+        let s = r#"
+        <OMA cdbase="http://www.openmath.org/cd">
+          <OMS cd="arith1" name="plus"/>
+          <OMI>2</OMI>
+          <OMI>2</OMI>
+        </OMA>"#;
+        let r = SimplifiedInt::from_openmath_xml(s)
+            .expect("valid xml, openmath, and arithmetic expression");
+        assert_eq!(r.0, 4);
+    }
+
+    #[cfg(any(feature = "serde", feature = "xml-write"))]
+    fn empty_lambda() -> crate::OpenMath<'static> {
+        crate::OpenMath::OMBIND {
+            binder: Box::new(crate::OpenMath::OMS {
+                cd: Cow::Borrowed("fns1"),
+                name: Cow::Borrowed("lambda"),
+                cdbase: Some(Cow::Borrowed(crate::CD_BASE)),
+                cdbase_inherited: true,
+                attributes: Vec::new(),
+            }),
+            variables: Vec::new(),
+            object: Box::new(crate::OpenMath::OMI {
+                int: 1.into(),
+                attributes: Vec::new(),
+            }),
+            attributes: Vec::new(),
+        }
+    }
+
+    #[derive(Debug)]
+    struct Annotated {
+        value: i64,
+        ty: String,
+    }
+    #[derive(Debug)]
+    enum Node {
+        Symbol(String),
+        Type(String),
+        Value(Annotated),
+    }
+    impl TryFrom<Node> for Annotated {
+        type Error = &'static str;
+        fn try_from(value: Node) -> Result<Self, Self::Error> {
+            match value {
+                Node::Value(a) => Ok(a),
+                _ => Err("not an annotated value"),
+            }
+        }
+    }
+    impl TryFrom<Node> for String {
+        type Error = &'static str;
+        fn try_from(value: Node) -> Result<Self, Self::Error> {
+            match value {
+                Node::Symbol(s) | Node::Type(s) => Ok(s),
+                Node::Value(_) => Err("not a type"),
+            }
+        }
+    }
+    impl<'d> OMDeserializable<'d> for Annotated {
+        type Ret = Node;
+        type Err = &'static str;
+        fn from_openmath(om: OM<'d, Node>, _cdbase: &str) -> Result<Node, Self::Err> {
+            match om {
+                OM::OMS { cd, name, .. } => Ok(Node::Symbol(format!("{cd}.{name}"))),
+                OM::OMA {
+                    applicant: Node::Symbol(head),
+                    arguments,
+                    ..
+                } if head == "sts.mapsto" => {
+                    let args = arguments
+                        .into_iter()
+                        .map(String::try_from)
+                        .collect::<Result<Vec<_>, _>>()?;
+                    Ok(Node::Type(args.join(" -> ")))
+                }
+                OM::OMI { int, attrs } => {
+                    let value = int
+                        .is_i128()
+                        .and_then(|i| i.try_into().ok())
+                        .ok_or("not an i64")?;
+                    let ty = attrs
+                        .into_iter()
+                        .find(|a| a.cd() == "sts" && a.name() == "type")
+                        .ok_or("missing type")?;
+                    let crate::OMMaybeForeign::OM(ty) = ty.value.try_convert::<String>()? else {
+                        return Err("foreign type");
+                    };
+                    Ok(Node::Value(Self { value, ty }))
+                }
+                _ => Err("unexpected"),
+            }
+        }
+    }
+
+    #[cfg(all(feature = "xml-read", feature = "serde"))]
+    #[test]
+    fn ome_arguments() {
+        /// An integer, or an error report with integers and foreign values as arguments
+        #[derive(Debug, Clone, PartialEq)]
+        enum Report {
+            Int(i64),
+            Error {
+                name: String,
+                args: Vec<crate::OMMaybeForeign<'static, Self>>,
+            },
+        }
+        impl<'d> OMDeserializable<'d> for Report {
+            type Ret = Self;
+            type Err = &'static str;
+            fn from_openmath(om: OM<'d, Self>, _: &str) -> Result<Self, Self::Err> {
+                match om {
+                    OM::OMI { int, .. } => int
+                        .is_i128()
+                        .and_then(|i| i64::try_from(i).ok())
+                        .map(Self::Int)
+                        .ok_or("not an i64"),
+                    OM::OME {
+                        name, arguments, ..
+                    } => Ok(Self::Error {
+                        name: name.into_owned(),
+                        args: arguments
+                            .into_iter()
+                            .map(crate::OMMaybeForeign::into_owned)
+                            .collect(),
+                    }),
+                    _ => Err("unexpected"),
+                }
+            }
+        }
+        let expected = Report::Error {
+            name: "unexpected".to_string(),
+            args: vec![
+                crate::OMMaybeForeign::OM(Report::Int(2)),
+                crate::OMMaybeForeign::Foreign {
+                    encoding: Some("text/plain".into()),
+                    value: "oops".into(),
+                },
+            ],
+        };
+        let xml = r#"<OME>
+            <OMS cd="moreerrors" name="unexpected"/>
+            <OMI>2</OMI>
+            <OMFOREIGN encoding="text/plain">oops</OMFOREIGN>
+        </OME>"#;
+        assert_eq!(Report::from_openmath_xml(xml).expect("is valid"), expected);
+        let json = r#"{
+            "kind": "OME",
+            "error": { "kind": "OMS", "cd": "moreerrors", "name": "unexpected" },
+            "arguments": [
+                { "kind": "OMI", "integer": 2 },
+                { "kind": "OMFOREIGN", "encoding": "text/plain", "foreign": "oops" }
+            ]
+        }"#;
+        assert_eq!(
+            serde_json::from_str::<OMFromSerde<Report>>(json)
+                .expect("is valid")
+                .into_inner(),
+            expected
+        );
+        let Report::Error { args, .. } = expected else {
+            unreachable!()
+        };
+        assert_eq!(args[0].as_om(), Some(&Report::Int(2)));
+        assert!(args[1].as_om().is_none());
+        assert!(matches!(
+            args[0].clone().map(|_| ()),
+            crate::OMMaybeForeign::OM(())
+        ));
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn typed_attribute_values() {
+        let s = r#"<OMATTR cdbase="http://www.openmath.org/cd">
+            <OMATP>
+                <OMS cd="sts" name="type"/>
+                <OMA>
+                    <OMS cd="sts" name="mapsto"/>
+                    <OMS cd="setname1" name="Z"/>
+                    <OMS cd="setname1" name="N"/>
+                </OMA>
+            </OMATP>
+            <OMI>3</OMI>
+        </OMATTR>"#;
+        let r = Annotated::from_openmath_xml(s).expect("valid annotated integer");
+        assert_eq!(r.value, 3);
+        assert_eq!(r.ty, "setname1.Z -> setname1.N");
+
+        let s = r#"<OMATTR>
+            <OMATP>
+                <OMS cd="sts" name="type"/>
+                <OMFOREIGN>Z</OMFOREIGN>
+            </OMATP>
+            <OMI>3</OMI>
+        </OMATTR>"#;
+        assert!(
+            Annotated::from_openmath_xml(s)
+                .expect_err("foreign type")
+                .to_string()
+                .contains("foreign type")
+        );
+        assert!(
+            Annotated::from_openmath_xml("<OMI>3</OMI>")
+                .expect_err("no type")
+                .to_string()
+                .contains("missing type")
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn typed_attribute_values_serde() {
+        let s = r#"{
+            "kind": "OMATTR",
+            "cdbase": "http://www.openmath.org/cd",
+            "attributes": [
+                [ { "kind": "OMS", "cd": "sts", "name": "type" },
+                  { "kind": "OMS", "cd": "setname1", "name": "Z" } ]
+            ],
+            "object": { "kind": "OMI", "integer": -7 }
+        }"#;
+        let r = serde_json::from_str::<OMFromSerde<Annotated>>(s)
+            .expect("valid annotated integer")
+            .into_inner();
+        assert_eq!(r.value, -7);
+        assert_eq!(r.ty, "setname1.Z");
+    }
+
+    #[cfg(all(feature = "xml-read", feature = "serde", feature = "xml-write"))]
+    #[test]
+    fn skipped_children() {
+        use crate::OMSerializable;
+        thread_local! {
+            /// The number of calls of [`First::from_openmath`]
+            static CONVERTED: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+        }
+
+        /// The first argument of an application or error; skips all others
+        #[derive(Debug, PartialEq, Eq)]
+        struct First(Option<i64>);
+        impl<'d> OMDeserializable<'d> for First {
+            type Ret = Self;
+            type Err = &'static str;
+            fn from_openmath(om: OM<'d, Self>, _: &str) -> Result<Self, Self::Err> {
+                CONVERTED.set(CONVERTED.get() + 1);
+                match om {
+                    OM::OMI { int, .. } => Ok(Self(int.is_i128().and_then(|i| i.try_into().ok()))),
+                    OM::OMA { mut arguments, .. } => {
+                        Ok(arguments.drain(..).next().unwrap_or(Self(None)))
+                    }
+                    OM::OME { arguments, .. } => Ok(arguments
+                        .into_iter()
+                        .find_map(|a| match a {
+                            OMMaybeForeign::OM(a) => Some(a),
+                            OMMaybeForeign::Foreign { .. } => None,
+                        })
+                        .unwrap_or(Self(None))),
+                    _ => Ok(Self(None)),
+                }
+            }
+            fn before_child(parent: crate::OMKind, index: usize, _: &str) -> Visit<Self> {
+                match (parent, index) {
+                    (crate::OMKind::OMA, 2..) | (crate::OMKind::OME, 1..) => {
+                        Visit::Skip(Self(None))
+                    }
+                    _ => Visit::Descend,
+                }
+            }
+        }
+
+        let big = format!(
+            r#"<OMA><OMS cd="arith1" name="plus"/>{}</OMA>"#,
+            "<OMI>1</OMI>".repeat(1000)
+        );
+        let xml = format!(
+            r#"<OMA><OMS cd="list1" name="list"/><OMI>7</OMI>{big}<OMSTR>s</OMSTR>
+            <OMBIND><OMS cd="fns1" name="lambda"/><OMBVAR><OMV name="x"/></OMBVAR>{big}</OMBIND>
+            <OME><OMS cd="moreerrors" name="unexpected"/>{big}<OMFOREIGN>…</OMFOREIGN></OME>
+            </OMA>"#
+        );
+        let om = crate::OpenMath::from_openmath_xml(&xml).expect("is valid");
+        let json = serde_json::to_string(&om.openmath_serde()).expect("serializes");
+        let compact = serde_json::to_string(&om.openmath_serde_compact()).expect("serializes");
+        let check = |first: First| {
+            assert_eq!(first, First(Some(7)));
+            // the list symbol, the integer and the list itself
+            assert_eq!(CONVERTED.replace(0), 3);
+        };
+        CONVERTED.set(0);
+        check(First::from_openmath_xml(&xml).expect("is valid"));
+        for json in [json, compact] {
+            check(
+                serde_json::from_str::<OMFromSerde<First>>(&json)
+                    .expect("is valid")
+                    .into_inner(),
+            );
+        }
+
+        // invalid objects are skipped all the same
+        let xml = format!(
+            r#"<OME><OMS cd="moreerrors" name="unexpected"/><OMI>3</OMI>{}<OMI>x</OMI></OME>"#,
+            big.replace("<OMI>1</OMI>", "<OMF>1</OMF>")
+        );
+        assert_eq!(
+            First::from_openmath_xml(&xml).expect("is skipped"),
+            First(Some(3))
+        );
+        assert_eq!(CONVERTED.replace(0), 2);
+        let json = r#"{ "kind": "OME", "error": { "kind": "OMS", "cd": "c", "name": "n" },
+            "arguments": [ { "kind": "OMI", "integer": 3 }, { "kind": "nonsense" } ] }"#;
+        assert_eq!(
+            serde_json::from_str::<OMFromSerde<First>>(json)
+                .expect("is skipped")
+                .into_inner(),
+            First(Some(3))
+        );
+        assert_eq!(CONVERTED.replace(0), 2);
+    }
+
+    #[cfg(all(feature = "xml-read", feature = "xml-write"))]
+    #[test]
+    fn empty_ombvar_xml() {
+        use crate::OMSerializable;
+        let expected = empty_lambda();
+        for s in [
+            r#"<OMBIND><OMS cd="fns1" name="lambda"/><OMBVAR/><OMI>1</OMI></OMBIND>"#,
+            r#"<OMBIND><OMS cd="fns1" name="lambda"/><OMBVAR></OMBVAR><OMI>1</OMI></OMBIND>"#,
+            r#"<OMBIND>
+                <OMS cd="fns1" name="lambda"/>
+                <OMBVAR>
+                </OMBVAR>
+                <OMI>1</OMI>
+            </OMBIND>"#,
+        ] {
+            let om = crate::OpenMath::from_openmath_xml(s).expect("is valid");
+            assert_eq!(om, expected);
+        }
+        for pretty in [true, false] {
+            let xml = expected.xml(pretty).to_string();
+            assert!(xml.contains("<OMBVAR/>"));
+            let om = crate::OpenMath::from_openmath_xml(&xml).expect("is valid");
+            assert_eq!(om, expected);
+        }
+        assert_eq!(
+            expected.openmath_display().to_string(),
+            "OMBIND(OMS(fns1#lambda),[],OMI(1))"
+        );
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn missing_ombvar_xml() {
+        let s = r#"<OMBIND><OMS cd="fns1" name="lambda"/><OMI>1</OMI></OMBIND>"#;
+        let e = crate::OpenMath::from_openmath_xml(s).expect_err("OMBVAR is missing");
+        assert!(matches!(e, xml::XmlReadError::MissingOMBVAR(_)), "{e}");
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn malformed_attributes_xml() {
+        for s in [
+            r#"<OMA><OMS cd="arith1" name="plus"/><OMV name=x/></OMA>"#,
+            r#"<OMATTR><OMATP><OMS cd="meta" name=note/><OMI>1</OMI></OMATP><OMI>2</OMI></OMATTR>"#,
+            r#"<OMV foo="1" foo="2" name="x"/>"#,
+        ] {
+            let e = crate::OpenMath::from_openmath_xml(s).expect_err("is malformed");
+            assert!(matches!(e, xml::XmlReadError::Xml { .. }), "{s}: {e}");
+        }
+        // attributes may come in any order, and unknown ones are ignored
+        let om = crate::OpenMath::from_openmath_xml(
+            r#"<OMS xmlns="http://www.openmath.org/OpenMath" name="plus" cd="arith1"/>"#,
+        )
+        .expect("is valid");
+        assert_eq!(om.to_sexpr(), "(oms arith1 plus)");
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn duplicate_attributes_xml() {
+        let cases = [
+            (
+                r#"<OMS cd="arith1" cd="arith2" name="plus"/>"#,
+                "cd",
+                "(oms arith1 plus)",
+            ),
+            (r#"<OMF dec="1.5" dec="2.5"/>"#, "dec", "(omf 1.5)"),
+            (
+                r#"<OMA><OMS cd="arith1" name="plus" name="times"/><OMV name="x"/></OMA>"#,
+                "name",
+                "(oma (oms arith1 plus) (omv x))",
+            ),
+        ];
+        for (s, attribute, _) in cases {
+            let e = crate::OpenMath::from_openmath_xml(s).expect_err("is duplicated");
+            assert!(
+                matches!(e, xml::XmlReadError::DuplicateAttribute { attribute: a, .. }
+                    if a == attribute),
+                "{s}: {e}"
+            );
+            let e = OMObject::<crate::OpenMath<'static>>::from_openmath_xml_reader_with(
+                format!("<OMOBJ>{s}</OMOBJ>").as_bytes(),
+                Options::new(),
+            )
+            .expect_err("is duplicated");
+            assert!(
+                matches!(e, xml::XmlReadError::DuplicateAttribute { attribute: a, .. }
+                    if a == attribute),
+                "{s}: {e}"
+            );
+        }
+
+        // the first occurrence wins
+        let options = Options::new().with_attribute_policy(AttributePolicy::Lenient);
+        for (s, _, sexpr) in cases {
+            let om = crate::OpenMath::from_openmath_xml_with(s, options).expect("is lenient");
+            assert_eq!(om.to_sexpr(), sexpr, "{s}");
+            let om = OMObject::<crate::OpenMath<'static>>::from_openmath_xml_reader_with(
+                format!("<OMOBJ>{s}</OMOBJ>").as_bytes(),
+                options,
+            )
+            .expect("is lenient");
+            assert_eq!(om.to_sexpr(), sexpr, "{s}");
+        }
+        let om =
+            crate::OpenMath::from_openmath_xml_with(r#"<OMV foo="1" foo="2" name="x"/>"#, options)
+                .expect("is lenient");
+        assert_eq!(om.to_sexpr(), "(omv x)");
+    }
+
+    /// A float and the decimal it was read from
+    #[cfg(any(feature = "xml-read", feature = "serde_json"))]
+    #[derive(Debug, PartialEq)]
+    struct Lexical(f64, Option<String>);
+    #[cfg(any(feature = "xml-read", feature = "serde_json"))]
+    impl<'de> OMDeserializable<'de> for Lexical {
+        type Ret = Self;
+        type Err = &'static str;
+        fn from_openmath(om: OM<'de, Self>, _: &str) -> Result<Self, Self::Err> {
+            match om {
+                OM::OMF { float, lexical, .. } => Ok(Self(float, lexical.map(Cow::into_owned))),
+                _ => Err("not a float"),
+            }
+        }
+    }
+
+    /// Exact, inexact and ridiculous decimals, and whether they are exact
+    #[cfg(any(feature = "xml-read", feature = "serde_json"))]
+    fn float_policy_cases() -> [(String, bool); 3] {
+        [
+            ("0.125".to_string(), true),
+            ("0.1000000000000000000000001".to_string(), false),
+            (format!("0.{}", "1234567890".repeat(100)), false),
+        ]
+    }
+
+    /// Checks `read` against the cases of [`float_policy_cases`] under every [`FloatPolicy`]
+    #[cfg(any(feature = "xml-read", feature = "serde_json"))]
+    fn check_float_policy(read: impl Fn(&str, Options) -> Result<Lexical, String>) {
+        for (s, exact) in float_policy_cases() {
+            let nearest: f64 = s.parse().expect("is valid");
+            let options = Options::new();
+            assert_eq!(read(&s, options), Ok(Lexical(nearest, None)), "{s}");
+            let options = options.with_float_policy(FloatPolicy::ErrorOnInexact);
+            if exact {
+                assert_eq!(read(&s, options), Ok(Lexical(nearest, None)), "{s}");
+            } else {
+                let e = read(&s, options).expect_err("is inexact");
+                assert!(e.contains("inexact"), "{s}: {e}");
+            }
+            let options = options.with_float_policy(FloatPolicy::PreserveLexical);
+            assert_eq!(read(&s, options), Ok(Lexical(nearest, Some(s.clone()))));
+        }
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn float_policy_xml() {
+        check_float_policy(|s, options| {
+            Lexical::from_openmath_xml_with(&format!(r#"<OMF dec="{s}"/>"#), options)
+                .map_err(|e| e.to_string())
+        });
+        check_float_policy(|s, options| {
+            OMObject::<Lexical>::from_openmath_xml_reader_with(
+                format!(r#"<OMOBJ><OMF dec="{s}"/></OMOBJ>"#).as_bytes(),
+                options,
+            )
+            .map(OMObject::into_inner)
+            .map_err(|e| e.to_string())
+        });
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn float_policy_serde() {
+        use serde::de::DeserializeSeed;
+        check_float_policy(|s, options| {
+            OMFromSerde::<Lexical>::with_options(options)
+                .deserialize(&mut serde_json::Deserializer::from_str(&format!(
+                    r#"{{"kind":"OMF","decimal":"{s}"}}"#
+                )))
+                .map(OMFromSerde::into_inner)
+                .map_err(|e| e.to_string())
+        });
+        // numbers are rounded regardless
+        let options = Options::new().with_float_policy(FloatPolicy::ErrorOnInexact);
+        let f = OMFromSerde::<Lexical>::with_options(options)
+            .deserialize(&mut serde_json::Deserializer::from_str(
+                r#"{"kind":"OMF","float":0.1}"#,
+            ))
+            .expect("is a number");
+        assert_eq!(f.into_inner(), Lexical(0.1, None));
+    }
+
+    /// A float, or the float in an OMSTR read as JSON with the default options, i.e. one
+    /// deserialization that starts another while it is running
+    #[cfg(feature = "serde_json")]
+    #[derive(Debug)]
+    struct Nested(f64);
+    #[cfg(feature = "serde_json")]
+    impl<'de> OMDeserializable<'de> for Nested {
+        type Ret = Self;
+        type Err = String;
+        fn from_openmath(om: OM<'de, Self>, _: &str) -> Result<Self, Self::Err> {
+            use serde::de::DeserializeSeed;
+            match om {
+                OM::OMF { float, .. } => Ok(Self(float)),
+                OM::OMSTR { string, .. } => OMFromSerde::<Lexical>::with_options(Options::new())
+                    .deserialize(&mut serde_json::Deserializer::from_str(&string))
+                    .map(|f| Self(f.into_inner().0))
+                    .map_err(|e| e.to_string()),
+                OM::OMA { arguments, .. } => arguments
+                    .into_iter()
+                    .next_back()
+                    .ok_or_else(|| "no arguments".to_string()),
+                _ => Err("not a float".to_string()),
+            }
+        }
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn options_are_per_deserialization() {
+        use serde::de::DeserializeSeed;
+        let options = Options::new().with_float_policy(FloatPolicy::ErrorOnInexact);
+        let read = |last: &str| {
+            OMFromSerde::<Nested>::with_options(options)
+                .deserialize(&mut serde_json::Deserializer::from_str(&format!(
+                    r#"{{"kind":"OMA","applicant":{{"kind":"OMF","decimal":"0.5"}},"arguments":[
+                        {{"kind":"OMSTR","string":"{{\"kind\":\"OMF\",\"decimal\":\"0.1\"}}"}},
+                        {last}
+                    ]}}"#
+                )))
+                .map(|f| f.into_inner().0)
+                .map_err(|e| e.to_string())
+        };
+        // the inner deserialization does not see the policy of the outer one...
+        assert_eq!(read(r#"{"kind":"OMF","decimal":"0.125"}"#), Ok(0.125));
+        // ...and does not reset it either
+        let e = read(r#"{"kind":"OMF","decimal":"0.1"}"#).expect_err("is inexact");
+        assert!(e.contains("inexact"), "{e}");
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn pointers_xml() {
+        let s = r#"<OMA><OMS cd="arith1" name="plus"/><OMI>1</OMI><OMSTR>x</OMSTR></OMA>"#;
+        let om = crate::OpenMath::from_openmath_xml(s).expect("is valid");
+        assert_eq!(
+            *Box::<crate::OpenMath>::from_openmath_xml(s).expect("is valid"),
+            om
+        );
+        assert_eq!(
+            *Rc::<crate::OpenMath>::from_openmath_xml(s).expect("is valid"),
+            om
+        );
+        assert_eq!(
+            *Arc::<crate::OpenMath>::from_openmath_xml(s).expect("is valid"),
+            om
+        );
+        assert_eq!(
+            *Arc::<String>::from_openmath_xml("<OMSTR>x</OMSTR>").expect("is valid"),
+            "x"
+        );
+        assert_eq!(
+            Box::<i64>::from_openmath_xml("<OMSTR>x</OMSTR>")
+                .expect_err("is no integer")
+                .to_string(),
+            i64::from_openmath_xml("<OMSTR>x</OMSTR>")
+                .expect_err("is no integer")
+                .to_string()
+        );
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn empty_omstr_xml() {
+        for s in [
+            "<OMSTR></OMSTR>",
+            "<OMSTR/>",
+            r#"<OMA><OMS cd="list1" name="list"/><OMSTR/><OMSTR></OMSTR></OMA>"#,
+        ] {
+            let om = crate::OpenMath::from_openmath_xml(s).expect("is valid");
+            assert!(om.to_sexpr().contains(r#"(omstr "")"#), "{s}");
+        }
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn empty_omb_xml() {
+        for s in ["<OMB></OMB>", "<OMB/>", "<OMB>  </OMB>"] {
+            let om = crate::OpenMath::from_openmath_xml(s).expect("is valid");
+            assert!(
+                matches!(&om, crate::OpenMath::OMB { bytes, .. } if bytes.is_empty()),
+                "{s}: {om:?}"
+            );
+        }
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn escaped_attributes_xml() {
+        let om = crate::OpenMath::from_openmath_xml(
+            r#"<OMS cd="a&amp;b" name="&lt;&quot;&#x78;&#121;"/>"#,
+        )
+        .expect("is valid");
+        assert_eq!(om.to_sexpr(), r#"(oms a&b "<\"xy")"#);
+        for s in [
+            r#"<OMV name="a&b"/>"#,
+            r#"<OMV name="&bogus;"/>"#,
+            r#"<OMV name="&#0;"/>"#,
+        ] {
+            let e = crate::OpenMath::from_openmath_xml(s).expect_err("is malformed");
+            assert!(matches!(e, xml::XmlReadError::UnknownEntity(_)), "{s}: {e}");
+        }
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn max_depth_xml() {
+        let nested = |n: usize| {
+            format!(
+                "<OMOBJ>{}<OMV name=\"x\"/>{}</OMOBJ>",
+                "<OMA><OMS cd=\"c\" name=\"f\"/>".repeat(n),
+                "</OMA>".repeat(n)
+            )
+        };
+        let options = Options::new().with_limits(Limits::new().with_max_depth(8));
+        // OMOBJ, 6 OMAs and their children
+        OMObject::<crate::OpenMath>::from_openmath_xml_with(&nested(6), options)
+            .expect("is shallow enough");
+        let xml = nested(7);
+        let e = OMObject::<crate::OpenMath>::from_openmath_xml_with(&xml, options)
+            .expect_err("is too deep");
+        // the OMS of the innermost OMA
+        let position = xml.rfind("<OMS").expect("exists") as u64;
+        assert!(
+            matches!(e, xml::XmlReadError::TooDeep { max: 8, position: p } if p == position),
+            "{e}"
+        );
+        let e = OMObject::<crate::OpenMath<'static>>::from_openmath_xml_reader_with(
+            xml.as_bytes(),
+            options,
+        )
+        .expect_err("is too deep");
+        assert!(
+            matches!(e, xml::XmlReadError::TooDeep { max: 8, .. }),
+            "{e}"
+        );
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn trailing_content_xml() {
+        use crate::OpenMath;
+        /// The offset of the trailing content, if that is what `r` failed on
+        fn trailing<T, E: std::fmt::Display>(
+            r: Result<T, XmlReadError<E>>,
+            s: &str,
+        ) -> Option<u64> {
+            match r {
+                Ok(_) => None,
+                Err(XmlReadError::TrailingContent(p)) => Some(p),
+                Err(e) => panic!("{s:?}: {e}"),
+            }
+        }
+        let object = "<OMI>2</OMI>";
+        for (rest, ok) in [
+            ("  \n\t", true),
+            ("<!-- done -->\n", true),
+            ("<?pi data?>", true),
+            ("<OMI>3</OMI>", false),
+            ("<OMV name=\"x\"/>", false),
+            (" garbage", false),
+            ("\u{0}\u{1}", false),
+        ] {
+            for obj in [false, true] {
+                let s = if obj {
+                    format!("<OMOBJ>{object}</OMOBJ>{rest}")
+                } else {
+                    format!("{object}{rest}")
+                };
+                let end = s.len() - rest.len();
+                let results = if obj {
+                    [
+                        trailing(OMObject::<OpenMath>::from_openmath_xml(&s), &s),
+                        trailing(
+                            OMObject::<i32>::from_openmath_xml_reader_with(
+                                s.as_bytes(),
+                                Options::new(),
+                            ),
+                            &s,
+                        ),
+                    ]
+                } else {
+                    let (_, len) = OpenMath::from_openmath_xml_prefix(&s).expect("is valid");
+                    assert_eq!(len, end, "{s:?}");
+                    [
+                        trailing(OpenMath::from_openmath_xml(&s), &s),
+                        trailing(i32::from_openmath_xml_reader(s.as_bytes()), &s),
+                    ]
+                };
+                let expected = (!ok).then_some(end as u64);
+                assert_eq!(results, [expected; 2], "{s:?}");
+            }
+        }
+
+        // within the OMOBJ as well
+        let e = OMObject::<OpenMath>::from_openmath_xml(&format!(
+            "<OMOBJ>{object}<!-- ok --><OMI>3</OMI></OMOBJ>"
+        ))
+        .expect_err("has two objects");
+        assert!(matches!(e, XmlReadError::TrailingContent(_)), "{e}");
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn empty_input_xml() {
+        use crate::OpenMath;
+        /// The errors of reading `s` as an object and as an OMOBJ, from a string and a reader
+        fn errors(s: &str) -> [String; 4] {
+            fn e<T, E: std::fmt::Display + std::fmt::Debug>(
+                r: Result<T, XmlReadError<E>>,
+                s: &str,
+            ) -> String {
+                format!("{:?}", r.map(drop).expect_err(s))
+            }
+            [
+                e(OpenMath::from_openmath_xml(s), s),
+                e(i32::from_openmath_xml_reader(s.as_bytes()), s),
+                e(OMObject::<OpenMath>::from_openmath_xml(s), s),
+                e(
+                    OMObject::<i32>::from_openmath_xml_reader_with(s.as_bytes(), Options::new()),
+                    s,
+                ),
+            ]
+        }
+        for s in ["", " \n\t ", "\u{feff}", "\u{feff}\r\n"] {
+            assert_eq!(errors(s), ["EmptyInput"; 4].map(String::from), "{s:?}");
+            assert!(matches!(
+                crate::analyze_xml(s),
+                Err(XmlReadError::EmptyInput)
+            ));
+        }
+        for s in [
+            r#"<?xml version="1.0"?>"#,
+            "<?xml version=\"1.0\"?>\n<!-- nothing -->\n",
+            "<?pi data?>",
+        ] {
+            let missing = r#"MissingElement("OMOBJ")"#;
+            assert_eq!(
+                errors(s),
+                ["NoObject", "NoObject", missing, missing].map(String::from),
+                "{s:?}"
+            );
+            assert!(matches!(crate::analyze_xml(s), Err(XmlReadError::NoObject)));
+        }
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn cdbase_origins_xml() {
+        use crate::{OMKind, OpenMath, Path, Step};
+        let xml = r#"<OMOBJ cdbase="http://a.org"><OMBIND>
+            <OMS cdbase="http://b.org" cd="c" name="lambda"/>
+            <OMBVAR><OMV name="x"/><OMATTR><OMATP>
+                <OMS cd="c" name="type"/><OMS cdbase="http://c.org" cd="c" name="t"/>
+            </OMATP><OMV name="y"/></OMATTR></OMBVAR>
+            <OMATTR cdbase="http://a.org/"><OMATP>
+                <OMS cdbase="http://d.org" cd="c" name="note"/><OMSTR>a</OMSTR>
+                <OMS cd="c" name="type"/><OME cdbase="http://e.org"><OMS cd="c" name="e"/></OME>
+            </OMATP><OMA><OMS cd="c" name="f"/><OMV name="x"/><OME><OMS cdbase="http://f.org" cd="c" name="e"/><OMA cdbase="http://g.org"><OMS cd="c" name="g"/></OMA></OME></OMA></OMATTR>
+        </OMBIND></OMOBJ>"#;
+        let origins = std::cell::RefCell::new(Vec::new());
+        let om = OMObject::<OpenMath>::from_openmath_xml_with(
+            xml,
+            Options::new().with_cdbase_origins(&origins),
+        )
+        .expect("is valid");
+        assert!(matches!(om.into_inner(), OpenMath::OMBIND { .. }));
+        let origins = origins.into_inner();
+        let path = |steps: &[Step]| Path(steps.to_vec());
+        let found = origins
+            .iter()
+            .map(|(p, o)| (p.clone(), o.element, o.cdbase.as_str(), o.is_redundant()))
+            .collect::<Vec<_>>();
+        let body = [Step::Body];
+        let f = [Step::Body, Step::Argument(1)];
+        assert_eq!(
+            found,
+            [
+                (Path::new(), None, "http://a.org", false),
+                (
+                    path(&[Step::Binder]),
+                    Some(OMKind::OMS),
+                    "http://b.org",
+                    false
+                ),
+                (
+                    path(&[Step::VariableAttribute(1, 0)]),
+                    Some(OMKind::OMS),
+                    "http://c.org",
+                    false
+                ),
+                (path(&body), Some(OMKind::OMATTR), "http://a.org/", true),
+                (path(&body), Some(OMKind::OMS), "http://d.org", false),
+                (
+                    path(&[Step::Body, Step::Attribute(1)]),
+                    Some(OMKind::OME),
+                    "http://e.org",
+                    false
+                ),
+                (path(&f), Some(OMKind::OMS), "http://f.org", false),
+                (
+                    path(&[Step::Body, Step::Argument(1), Step::Argument(0)]),
+                    Some(OMKind::OMA),
+                    "http://g.org",
+                    false
+                ),
+            ]
+        );
+        assert_eq!(origins[0].1.inherited, crate::CD_BASE);
+        assert_eq!(origins[4].1.inherited, "http://a.org/");
+        assert_eq!(origins[7].1.inherited, "http://a.org/");
+        for (_, origin) in &origins {
+            // offsets are those of the preceding whitespace, if any
+            let at = xml[usize::try_from(origin.position).expect("fits")..].trim_start();
+            assert!(at.starts_with('<'), "{origin:?}");
+        }
+
+        // incrementally
+        let read = std::cell::RefCell::new(Vec::new());
+        OMObject::<OpenMath>::from_openmath_xml_reader_with(
+            xml.as_bytes(),
+            Options::new().with_cdbase_origins(&read),
+        )
+        .expect("is valid");
+        assert_eq!(read.into_inner(), origins);
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn misplaced_foreign_xml() {
+        use crate::OpenMath;
+        const FOREIGN: &str = r#"<OMFOREIGN encoding="text/plain">x</OMFOREIGN>"#;
+        for (xml, ok) in [
+            (
+                format!(r#"<OMA><OMS cd="c" name="f"/>{FOREIGN}</OMA>"#),
+                false,
+            ),
+            (format!(r#"<OMA>{FOREIGN}<OMV name="x"/></OMA>"#), false),
+            (
+                format!(r#"<OMBIND><OMS cd="c" name="b"/><OMBVAR/>{FOREIGN}</OMBIND>"#),
+                false,
+            ),
+            (
+                format!(
+                    r#"<OMATTR><OMATP><OMS cd="c" name="k"/><OMI>1</OMI></OMATP>{FOREIGN}</OMATTR>"#
+                ),
+                false,
+            ),
+            (FOREIGN.to_string(), false),
+            (format!("<OMOBJ>{FOREIGN}</OMOBJ>"), false),
+            (
+                format!(r#"<OME><OMS cd="c" name="e"/>{FOREIGN}</OME>"#),
+                true,
+            ),
+            (
+                format!(
+                    r#"<OMATTR><OMATP><OMS cd="c" name="k"/>{FOREIGN}</OMATP><OMV name="x"/></OMATTR>"#
+                ),
+                true,
+            ),
+        ] {
+            let result = if xml.starts_with("<OMOBJ") {
+                OMObject::<OpenMath>::from_openmath_xml(&xml)
+            } else {
+                OpenMath::from_openmath_xml(&xml)
+            };
+            match result {
+                Err(XmlReadError::MisplacedForeign(at)) if !ok => {
+                    let at = usize::try_from(at).expect("fits");
+                    assert!(
+                        xml[at..].trim_start().starts_with("<OMFOREIGN"),
+                        "{xml}: {at}"
+                    );
+                }
+                Ok(_) if ok => (),
+                r => panic!("{xml}: {r:?}"),
+            }
+        }
+    }
+
+    /// Content that can not be borrowed from the input falls back to owned strings
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn owned_fallback_xml() {
+        use std::borrow::Cow;
+        let string = |s: &'static str| match crate::OpenMath::from_openmath_xml(s) {
+            Ok(crate::OpenMath::OMSTR { string, .. }) => string,
+            r => panic!("{s}: {r:?}"),
+        };
+        assert!(matches!(
+            string("<OMSTR>plain</OMSTR>"),
+            Cow::Borrowed("plain")
+        ));
+        assert!(matches!(
+            string("<OMSTR><![CDATA[a < b]]></OMSTR>"),
+            Cow::Borrowed("a < b")
+        ));
+        for (s, expected) in [
+            ("<OMSTR>a &amp; b</OMSTR>", "a & b"),
+            ("<OMSTR>&#x41;</OMSTR>", "A"),
+            ("<OMSTR>a<![CDATA[ < ]]>b</OMSTR>", "a < b"),
+            ("<OMSTR>a<!-- comment -->b</OMSTR>", "ab"),
+        ] {
+            let string = string(s);
+            assert!(
+                matches!(&string, Cow::Owned(o) if o == expected),
+                "{s}: {string:?}"
+            );
+        }
+        let om = crate::OpenMath::from_openmath_xml("<OMI>1&#x32;3</OMI>").expect("is valid");
+        assert!(matches!(om, crate::OpenMath::OMI { int, .. } if int == crate::Int::from(123)));
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn foreign_content_reader_xml() {
+        use crate::{OMMaybeForeign, OpenMath};
+        let xml = "<OMA><OMS cd=\"c\" name=\"f\"/><OMATTR><OMATP><OMS cd=\"c\" name=\"k\"/>\
+            <OMFOREIGN encoding=\"text/xml\">\n  <p> a </p>\n  </OMFOREIGN></OMATP>\
+            <OMV name=\"x\"/></OMATTR></OMA>";
+        let foreign = |om: &OpenMath<'_>| {
+            let OpenMath::OMA { arguments, .. } = om else {
+                panic!("is an OMA")
+            };
+            match &arguments[0].attributes()[0].value {
+                OMMaybeForeign::Foreign { value, .. } => value.to_string(),
+                OMMaybeForeign::OM(o) => panic!("{o:?}"),
+            }
+        };
+        let borrowed = OpenMath::from_openmath_xml(xml).expect("is valid");
+        let owned = OMObject::<OpenMath<'static>>::from_openmath_xml_reader_with(
+            format!("<OMOBJ>{xml}</OMOBJ>").as_bytes(),
+            Options::new(),
+        )
+        .expect("is valid")
+        .into_inner();
+        // trimmed the same way by both readers
+        assert_eq!(foreign(&borrowed), "<p> a </p>");
+        assert_eq!(foreign(&owned), "<p> a </p>");
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn text_content_xml() {
+        let string = |s: &str| match crate::OpenMath::from_openmath_xml(s) {
+            Ok(crate::OpenMath::OMSTR { string, .. }) => string.into_owned(),
+            r => panic!("{s}: {r:?}"),
+        };
+        assert_eq!(
+            string("<OMSTR><![CDATA[<mi>x</mi>]]></OMSTR>"),
+            "<mi>x</mi>"
+        );
+        assert_eq!(
+            string("<OMSTR>&lt;mi&gt;x&lt;/mi&gt;</OMSTR>"),
+            "<mi>x</mi>"
+        );
+        assert_eq!(
+            string("<OMSTR>a &amp; <![CDATA[<b>]]>&#x41;&#66;</OMSTR>"),
+            "a & <b>AB"
+        );
+        let s = "<OMSTR><![CDATA[1 < 2]]> &amp; 3</OMSTR>";
+        assert_eq!(
+            String::from_openmath_xml_reader(s.as_bytes()).expect("is valid"),
+            "1 < 2 & 3"
+        );
+
+        assert_eq!(
+            i64::from_openmath_xml("<OMI><![CDATA[-12]]>3</OMI>").expect("is valid"),
+            -123
+        );
+        let om = crate::OpenMath::from_openmath_xml("<OMB>\n<![CDATA[AQI=]]>\n</OMB>")
+            .expect("is valid");
+        assert!(matches!(om, crate::OpenMath::OMB { bytes, .. } if *bytes == [1, 2]));
+
+        let e = crate::OpenMath::from_openmath_xml("<OMSTR>a<mi>x</mi></OMSTR>")
+            .expect_err("markup in string");
+        assert!(
+            matches!(&e, xml::XmlReadError::MarkupInString { element, position: 8 } if element == "mi"),
+            "{e:?}"
+        );
+        assert!(e.to_string().contains("OMFOREIGN"));
+        assert!(matches!(
+            crate::OpenMath::from_openmath_xml("<OMSTR>&nbsp;</OMSTR>"),
+            Err(xml::XmlReadError::UnknownEntity(e)) if e == "nbsp"
+        ));
+    }
+
+    #[cfg(all(feature = "xml-read", feature = "xml-write"))]
+    #[test]
+    fn wrapped_base64() {
+        use crate::{
+            OpenMath,
+            ser::{OMSerializable, XmlOptions},
+        };
+        let bytes: Vec<u8> = (0..1000_u32).map(|i| (i * 7).to_le_bytes()[0]).collect();
+        let om = OpenMath::OMB {
+            bytes: bytes.as_slice().into(),
+            attributes: Vec::new(),
+        };
+        for pretty in [false, true] {
+            let xml = om
+                .omobject()
+                .xml_with(XmlOptions {
+                    pretty,
+                    wrap_base64: true,
+                    ..XmlOptions::default()
+                })
+                .to_string();
+            let start = xml.find("<OMB>").expect("has an OMB") + 5;
+            let end = xml.find("</OMB>").expect("has an OMB");
+            let lines: Vec<_> = xml[start..end].lines().collect();
+            assert_eq!(lines.len(), 18, "{xml}");
+            assert!(lines[..17].iter().all(|l| l.len() == 76), "{xml}");
+            let read = OMObject::<OpenMath>::from_openmath_xml(&xml).expect("is valid");
+            assert_eq!(read, om);
+        }
+
+        // as wrapped by other tools
+        let om =
+            OpenMath::from_openmath_xml("<OMB>\r\n  AQ\tID\r\n  BA==\n</OMB>").expect("is valid");
+        assert!(matches!(om, OpenMath::OMB { bytes, .. } if *bytes == [1, 2, 3, 4]));
+        // but nothing else is skipped
+        assert!(matches!(
+            OpenMath::from_openmath_xml("<OMB>AQI-BA==</OMB>"),
+            Err(xml::XmlReadError::Base64 {
+                source: crate::base64::Error::IllegalChar {
+                    char: b'-',
+                    offset: 3
+                },
+                position: 0
+            })
+        ));
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn encoding_error_messages() {
+        // text in an OMB
+        let err = crate::OpenMath::from_openmath_xml(
+            "<OMA><OMV name=\"f\"/><OMB>hello, world</OMB></OMA>",
+        )
+        .expect_err("is not base64");
+        assert!(matches!(
+            err,
+            xml::XmlReadError::Base64 {
+                source: crate::base64::Error::IllegalChar {
+                    char: b',',
+                    offset: 5
+                },
+                position: 20
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "invalid base64 in OMB at offset 20: base64 string contains illegal character ',' \
+             at offset 5; OMB expects base64-encoded bytes, use OMSTR for text"
+        );
+
+        // binary data in an OMSTR, or in an attribute
+        let err = String::from_openmath_xml_reader(&b"<OMSTR>\x89PNG</OMSTR>"[..])
+            .expect_err("is not UTF-8");
+        assert!(matches!(
+            err,
+            xml::XmlReadError::Utf8 {
+                position: 0,
+                element: Some(crate::OMKind::OMSTR),
+                ..
+            }
+        ));
+        assert!(
+            err.to_string()
+                .starts_with("invalid UTF-8 in OMSTR at offset 0: invalid utf-8 sequence"),
+            "{err}"
+        );
+        let err = OMObject::<String>::from_openmath_xml_reader_with(
+            &b"<OMOBJ version=\"\xff\"><OMSTR>a</OMSTR></OMOBJ>"[..],
+            Options::new(),
+        )
+        .expect_err("is not UTF-8");
+        assert!(matches!(
+            err,
+            xml::XmlReadError::Utf8 {
+                position: 0,
+                element: None,
+                ..
+            }
+        ));
+        assert!(
+            err.to_string()
+                .starts_with("invalid UTF-8 in element at offset 0"),
+            "{err}"
+        );
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn read_into_buffer_file() {
+        use std::io::Write;
+        let path = std::env::temp_dir().join(format!("openmath-{}.xml", std::process::id()));
+        let mut file = std::fs::File::create(&path).expect("can create file");
+        file.write_all(
+            br#"<OMA><OMS cd="list1" name="list"/><OMSTR>a</OMSTR><OMV name="x"/></OMA>"#,
+        )
+        .expect("can write file");
+        drop(file);
+
+        let mut buf = Vec::new();
+        let reader = std::io::BufReader::new(std::fs::File::open(&path).expect("can open file"));
+        let om: crate::OpenMath<'_> = read_into_buffer(reader, &mut buf).expect("is valid");
+        let crate::OpenMath::OMA { arguments, .. } = &om else {
+            panic!("is an OMA")
+        };
+        assert!(matches!(
+            &arguments[..],
+            [
+                crate::OpenMath::OMSTR {
+                    string: Cow::Borrowed("a"),
+                    ..
+                },
+                crate::OpenMath::OMV {
+                    name: Cow::Borrowed("x"),
+                    ..
+                }
+            ]
+        ));
+        // the buffer is reused
+        let reader = std::io::BufReader::new(std::fs::File::open(&path).expect("can open file"));
+        assert!(read_into_buffer::<Cow<'_, str>>(reader, &mut buf).is_err());
+        std::fs::remove_file(&path).expect("can remove file");
+
+        assert!(matches!(
+            read_into_buffer::<String>(&[b'<', 0xff, 0xff][..], &mut buf),
+            Err(xml::XmlReadError::Utf8 {
+                position: 1,
+                element: None,
+                ..
+            })
+        ));
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    #[allow(clippy::too_many_lines)]
+    fn collect_errors_xml() {
+        #[derive(Debug)]
+        struct Sum(i64);
+        enum SumRet {
+            Int(i64),
+            Plus,
+            Failed,
+        }
+        impl TryFrom<SumRet> for Sum {
+            type Error = ();
+            fn try_from(value: SumRet) -> Result<Self, ()> {
+                if let SumRet::Int(i) = value {
+                    Ok(Self(i))
+                } else {
+                    Err(())
+                }
+            }
+        }
+        impl<'d> OMDeserializable<'d> for Sum {
+            type Ret = SumRet;
+            type Err = &'static str;
+            fn from_openmath(om: OM<'d, SumRet>, _: &str) -> Result<SumRet, Self::Err> {
+                match om {
+                    OM::OMI { int, .. } => int
+                        .is_i128()
+                        .and_then(|i| i64::try_from(i).ok())
+                        .map(SumRet::Int)
+                        .ok_or("too large"),
+                    OM::OMS { name, .. } if name == "plus" => Ok(SumRet::Plus),
+                    OM::OMA {
+                        applicant: SumRet::Plus,
+                        arguments,
+                        ..
+                    } => arguments
+                        .into_iter()
+                        .try_fold(SumRet::Int(0), |s, a| match (s, a) {
+                            (SumRet::Int(s), SumRet::Int(a)) => Ok(SumRet::Int(s + a)),
+                            _ => Err("not an integer"),
+                        }),
+                    _ => Err("unsupported"),
+                }
+            }
+        }
+        impl OMDeserializableRecover<'_> for Sum {
+            fn placeholder() -> SumRet {
+                SumRet::Failed
+            }
+        }
+
+        let s = r#"<OMA>
+            <OMS cd="arith1" name="plus"/>
+            <OMI>1</OMI>
+            <OMF dec="1.5"/>
+            <OMA><OMS cd="arith1" name="plus"/><OMI>99999999999999999999</OMI></OMA>
+            <OMATTR><OMATP><OMS cd="meta" name="note"/><OMSTR>x</OMSTR></OMATP><OMI>2</OMI></OMATTR>
+            <OME><OMS cd="error" name="oops"/><OMV name="y"/></OME>
+        </OMA>"#;
+        assert!(matches!(
+            Sum::from_openmath_xml(s),
+            Err(XmlReadError::Conversion("unsupported"))
+        ));
+        let (r, errors) = Sum::from_openmath_xml_collect(s);
+        assert!(r.is_none());
+        let errors: Vec<_> = errors
+            .into_iter()
+            .map(|e| match e.error {
+                XmlReadError::Conversion(c) => (e.position, c),
+                e => panic!("unexpected error {e}"),
+            })
+            .collect();
+        let at = |needle: &str| s.find(needle).expect("exists") as u64;
+        assert_eq!(
+            errors,
+            [
+                (at("<OMF"), "unsupported"),
+                (at("<OMI>9"), "too large"),
+                (at("<OMSTR"), "unsupported"),
+                (at("<OMV"), "unsupported"),
+            ]
+        );
+
+        // errors in attributes do not prevent the root from being built
+        let s = r#"<OMA><OMS cd="arith1" name="plus"/><OMI>1</OMI>
+            <OMATTR><OMATP><OMS cd="meta" name="note"/><OMSTR>x</OMSTR></OMATP><OMI>2</OMI></OMATTR>
+        </OMA>"#;
+        let (r, errors) = Sum::from_openmath_xml_collect(s);
+        assert_eq!(r.map(|s| s.0), Some(3));
+        assert_eq!(errors.len(), 1);
+
+        let (r, errors) = Sum::from_openmath_xml_collect("<OMSTR>x</OMSTR>");
+        assert!(r.is_none());
+        assert_eq!(errors.len(), 1);
+
+        let (r, errors) = Sum::from_openmath_xml_collect(
+            r#"<OMA><OMS cd="arith1" name="plus"/><OMSTR>x</OMSTR><OMF dec="x"/></OMA>"#,
+        );
+        assert!(r.is_none());
+        assert!(matches!(
+            errors.as_slice(),
+            [
+                PositionedError {
+                    error: XmlReadError::Conversion("unsupported"),
+                    ..
+                },
+                PositionedError {
+                    error: XmlReadError::InvalidFloat(_),
+                    ..
+                }
+            ]
+        ));
+
+        let (r, errors) = Sum::from_openmath_xml_collect("<OMI>1</OMI><OMI>2</OMI>");
+        assert!(r.is_none());
+        assert!(matches!(
+            errors.as_slice(),
+            [PositionedError {
+                error: XmlReadError::TrailingContent(12),
+                ..
+            }]
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn empty_ombvar_serde() {
+        use crate::OMSerializable;
+        let expected = empty_lambda();
+        for s in [
+            r#"{ "kind":"OMBIND",
+                "binder": { "kind":"OMS", "cd":"fns1", "name":"lambda" },
+                "variables": [],
+                "object": { "kind":"OMI", "integer":1 }
+            }"#,
+            r#"{ "kind":"OMBIND",
+                "binder": { "kind":"OMS", "cd":"fns1", "name":"lambda" },
+                "object": { "kind":"OMI", "integer":1 }
+            }"#,
+            r#"{ "binder": { "kind":"OMS", "cd":"fns1", "name":"lambda" },
+                "object": { "kind":"OMI", "integer":1 },
+                "kind":"OMBIND"
+            }"#,
+            r#"["OMBIND",null,null,["OMS",null,null,"fns1","lambda"],[],["OMI",null,1]]"#,
+        ] {
+            let om = serde_json::from_str::<OMFromSerde<crate::OpenMath>>(s)
+                .expect("is valid")
+                .into_inner();
+            assert_eq!(om, expected);
+        }
+        let json = serde_json::to_string(&expected.openmath_serde()).expect("works");
+        assert!(json.contains(r#""variables":[]"#));
+        let om = serde_json::from_str::<OMFromSerde<crate::OpenMath>>(&json)
+            .expect("is valid")
+            .into_inner();
+        assert_eq!(om, expected);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn omattr_cdbase_order_serde() {
+        use crate::{OMSerializable, OpenMath};
+        use serde::de::DeserializeSeed;
+        fn parse(s: &str) -> OpenMath<'_> {
+            serde_json::from_str::<OMFromSerde<OpenMath>>(s)
+                .expect("is valid")
+                .into_inner()
+        }
+        const KEY: &str = r#"{ "kind":"OMS", "cd":"meta", "name":"note" }"#;
+        let reordered = format!(
+            r#"{{ "kind":"OMATTR", "cdbase":"http://example.org",
+                "attributes":[[{KEY}, {{ "kind":"OMS", "cd":"my", "name":"g" }}]],
+                "object":{{ "kind":"OMS", "cd":"my", "name":"f" }} }}"#
+        );
+        let expected = parse(&reordered);
+        // the value inherits the cdbase
+        assert_ne!(
+            expected,
+            parse(&reordered.replace(r#""cdbase":"http://example.org","#, ""))
+        );
+        for s in [
+            format!(
+                r#"{{ "kind":"OMATTR", "attributes":[[{KEY}, {{ "kind":"OMS", "cd":"my", "name":"g" }}]],
+                    "cdbase":"http://example.org",
+                    "object":{{ "kind":"OMS", "cd":"my", "name":"f" }} }}"#
+            ),
+            format!(
+                r#"{{ "kind":"OMATTR", "attributes":[[{KEY}, {{ "kind":"OMS", "cd":"my", "name":"g" }}]],
+                    "object":{{ "kind":"OMS", "cd":"my", "name":"f" }},
+                    "cdbase":"http://example.org" }}"#
+            ),
+            format!(
+                r#"{{ "attributes":[[{KEY}, {{ "kind":"OMS", "cd":"my", "name":"g" }}]], "kind":"OMATTR",
+                    "cdbase":"http://example.org",
+                    "object":{{ "kind":"OMS", "cd":"my", "name":"f" }} }}"#
+            ),
+        ] {
+            let om = parse(&s);
+            assert_eq!(om, expected, "{s}");
+            let json = serde_json::to_string(&om.openmath_serde()).expect("works");
+            assert_eq!(parse(&json), expected, "{json}");
+        }
+
+        // the same for attributed variables
+        let lambda = |var: &str| {
+            format!(
+                r#"{{ "kind":"OMBIND", "binder":{{ "kind":"OMS", "cd":"fns1", "name":"lambda" }},
+                    "variables":[{var}], "object":{{ "kind":"OMV", "name":"x" }} }}"#
+            )
+        };
+        let (reordered, s) = (
+            lambda(&format!(
+                r#"{{ "kind":"OMATTR", "cdbase":"http://example.org",
+                    "attributes":[[{KEY}, {{ "kind":"OMS", "cd":"my", "name":"g" }}]],
+                    "object":{{ "kind":"OMV", "name":"x" }} }}"#
+            )),
+            lambda(&format!(
+                r#"{{ "kind":"OMATTR", "attributes":[[{KEY}, {{ "kind":"OMS", "cd":"my", "name":"g" }}]],
+                    "object":{{ "kind":"OMV", "name":"x" }}, "cdbase":"http://example.org" }}"#
+            )),
+        );
+        let expected = parse(&reordered);
+        assert_eq!(parse(&s), expected);
+        assert_ne!(
+            expected,
+            parse(&reordered.replace(r#""cdbase":"http://example.org","#, ""))
+        );
+
+        // keys are compared by their resolved URIs
+        let duplicates = format!(
+            r#"{{ "kind":"OMATTR", "attributes":[
+                    [{KEY}, {{ "kind":"OMI", "integer":1 }}],
+                    [{{ "kind":"OMS", "cdbase":"http://example.org", "cd":"meta", "name":"note" }},
+                     {{ "kind":"OMI", "integer":2 }}]
+                ], "object":{{ "kind":"OMV", "name":"x" }}, "cdbase":"http://example.org/" }}"#
+        );
+        let var = lambda(&duplicates);
+        let reject = |s: &str| {
+            let options = Options::new().with_duplicate_keys(DuplicateKeys::Reject);
+            OMFromSerde::<OpenMath>::with_options(options)
+                .deserialize(&mut serde_json::Deserializer::from_str(s))
+                .map(drop)
+        };
+        assert_eq!(
+            reject(&var)
+                .expect_err("repeats a key")
+                .to_string()
+                .split(" at line")
+                .next(),
+            Some(
+                "duplicate attribute key http://example.org?meta#note (in variables[0].attributes)"
+            )
+        );
+        assert!(reject(&duplicates.replace(r#", "cdbase":"http://example.org/""#, "")).is_ok());
+        assert_eq!(parse(&duplicates).attributes().len(), 2);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn invalid_keys_paths_serde() {
+        use crate::OpenMath;
+        fn error(s: &str) -> String {
+            serde_json::from_str::<OMFromSerde<OpenMath>>(s)
+                .map(OMFromSerde::into_inner)
+                .expect_err("is invalid")
+                .to_string()
+        }
+        const I: &str = r#"{ "kind":"OMI", "integer":1 }"#;
+        const KEY: &str = r#"{ "kind":"OMS", "cd":"meta", "name":"note" }"#;
+        for bad in [
+            r#"{ "kind":"OMI", "name":"x", "integer":1 }"#,
+            r#"{ "name":"x", "integer":1, "kind":"OMI" }"#,
+        ] {
+            let s = format!(
+                r#"{{ "kind":"OMATTR", "attributes":[[{KEY}, {I}]],
+                    "object":{{ "kind":"OMA", "applicant":{KEY}, "arguments":[{I}, {I},
+                        {{ "kind":"OMATTR", "attributes":[[{KEY}, {bad}]], "object":{I} }}
+                    ] }} }}"#
+            );
+            let e = error(&s);
+            assert!(
+                e.contains("Invalid keys for OMI at object.arguments[2].attributes[0][1]"),
+                "{e}"
+            );
+        }
+        let e = error(r#"{ "kind":"OMI", "name":"x", "integer":1 }"#);
+        assert!(e.starts_with("Invalid keys for OMI: "), "{e}");
+
+        let s = format!(
+            r#"["OMA",null,null,{KEY},[{I},
+                ["OMBIND",null,null,{KEY},[{{ "kind":"OMV", "name":"x", "integer":1 }}],{I}]]]"#
+        );
+        let e = error(&s);
+        assert!(e.contains("Invalid keys for OMV at [4][1][4][0]"), "{e}");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_path_to_error() {
+        use crate::OpenMath;
+        fn error<'de, T: serde::Deserialize<'de>>(s: &'de str) -> (String, String) {
+            let d = &mut serde_json::Deserializer::from_str(s);
+            let e = serde_path_to_error::deserialize::<_, T>(d)
+                .map(|_| ())
+                .expect_err("is invalid");
+            let message = e.inner().to_string();
+            (e.path().to_string(), message)
+        }
+        const S: &str = r#"{ "kind":"OMS", "cd":"arith1", "name":"plus" }"#;
+        const BAD: &str = r#"{ "kind":"OMI", "integer":true }"#;
+        let s = format!(
+            r#"{{ "kind":"OMOBJ", "object":{{ "kind":"OMA", "applicant":{S}, "arguments":[{S}, {BAD}] }} }}"#
+        );
+        assert_eq!(
+            error::<OMObject<OpenMath>>(&s).0,
+            "object.arguments[1].integer"
+        );
+        for (s, path) in [
+            (
+                format!(r#"{{ "kind":"OMA", "applicant":{S}, "arguments":[{S}, {BAD}] }}"#),
+                "arguments[1].integer",
+            ),
+            (
+                format!(r#"["OMA", null, null, {S}, [{S}, ["OMI", null, true]]]"#),
+                "[4][1][2]",
+            ),
+            (
+                format!(r#"{{ "kind":"OMBIND", "binder":{S}, "variables":[], "object":{BAD} }}"#),
+                "object.integer",
+            ),
+            (
+                format!(r#"{{ "kind":"OME", "error":{S}, "arguments":[{S}, {BAD}] }}"#),
+                "arguments[1].integer",
+            ),
+            (
+                r#"{ "kind":"OMS", "cd":"arith1", "name":5 }"#.to_string(),
+                "name",
+            ),
+            (
+                format!(
+                    r#"{{ "kind":"OMATTR", "cdbase":"http://example.org", "attributes":[[{S}, {BAD}]], "object":{S} }}"#
+                ),
+                "attributes[0][1].integer",
+            ),
+        ] {
+            assert_eq!(error::<OMFromSerde<OpenMath>>(&s).0, path, "{s}");
+        }
+
+        // buffered values: the message says where
+        for (s, path, location) in [
+            (
+                format!(r#"{{ "applicant":{S}, "arguments":[{S}, {BAD}], "kind":"OMA" }}"#),
+                ".",
+                "(in arguments)",
+            ),
+            (
+                format!(r#"{{ "kind":"OMATTR", "attributes":[[{S}, {BAD}]], "object":{S} }}"#),
+                ".",
+                "(in attributes)",
+            ),
+            (
+                format!(
+                    r#"{{ "kind":"OMA", "applicant":{S}, "arguments":[{{ "kind":"OMATTR", "attributes":[[{S}, {S}]], "object":{{ "arguments":[{BAD}], "kind":"OMA", "applicant":{S} }} }}] }}"#
+                ),
+                "arguments[0]",
+                "(in arguments[0].object.arguments)",
+            ),
+        ] {
+            let (p, message) = error::<OMFromSerde<OpenMath>>(&s);
+            assert_eq!(p, path, "{s}");
+            assert!(message.contains(location), "{message}");
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn big_omi_serde() {
+        use crate::Int;
+        for (s, expected) in [
+            (
+                r#"{ "kind":"OMI", "integer":18446744073709551615 }"#,
+                Int::from(u64::MAX),
+            ),
+            (
+                r#"{ "integer":18446744073709551615, "kind":"OMI" }"#,
+                Int::from(u64::MAX),
+            ),
+            (
+                r#"{ "kind":"OMI", "decimal":"-9223372036854775808" }"#,
+                Int::from(i64::MIN),
+            ),
+            (
+                r#"["OMI",null,1234567890123456789012345]"#,
+                Int::new("1234567890123456789012345").expect("is an integer"),
+            ),
+        ] {
+            let int = serde_json::from_str::<OMFromSerde<Int>>(s)
+                .expect("is valid")
+                .into_inner();
+            assert_eq!(int, expected, "{s}");
+        }
+    }
+
+    /// Hexadecimal integers and their decimal values
+    #[cfg(any(all(feature = "xml-read", feature = "xml-write"), feature = "serde"))]
+    const HEX_OMIS: [(&str, &str); 4] = [
+        ("x1F", "31"),
+        ("-xff", "-255"),
+        ("x0", "0"),
+        (
+            "x100000000000000000000000000000000",
+            "340282366920938463463374607431768211456",
+        ),
+    ];
+
+    #[cfg(all(feature = "xml-read", feature = "xml-write"))]
+    #[test]
+    fn hex_omi_xml() {
+        use crate::{OMSerializable, OpenMath};
+        for (hex, decimal) in HEX_OMIS {
+            let s = format!("<OMI> {hex} </OMI>");
+            let om = OpenMath::from_openmath_xml(&s).expect("is valid");
+            let xml = om.xml(false).to_string();
+            assert_eq!(xml, format!("<OMI>{decimal}</OMI>"));
+            assert_eq!(OpenMath::from_openmath_xml(&xml).expect("roundtrips"), om);
+        }
+        for invalid in ["x", "xg", "-x", "x-1"] {
+            let e = OpenMath::from_openmath_xml(&format!("<OMI>{invalid}</OMI>"))
+                .expect_err("is not hexadecimal");
+            assert!(
+                matches!(e, XmlReadError::InvalidInteger(_)),
+                "{invalid}: {e}"
+            );
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn hex_omi_serde() {
+        use crate::{OMSerializable, OpenMath};
+        for (hex, decimal) in HEX_OMIS {
+            let s = format!(r#"{{ "kind":"OMI", "hexadecimal":"{hex}" }}"#);
+            let om = serde_json::from_str::<OMFromSerde<OpenMath>>(&s)
+                .expect("is valid")
+                .into_inner();
+            let OpenMath::OMI { int, .. } = &om else {
+                panic!("is an OMI")
+            };
+            assert_eq!(int.to_string(), decimal);
+            let json = serde_json::to_string(&om.openmath_serde()).expect("works");
+            let back = serde_json::from_str::<OMFromSerde<OpenMath>>(&json)
+                .expect("roundtrips")
+                .into_inner();
+            assert_eq!(back, om, "{json}");
+        }
+        let om = serde_json::from_str::<OMFromSerde<OpenMath>>(
+            r#"{ "kind":"OMI", "hexadecimal":"-1F" }"#,
+        )
+        .expect("the x is optional")
+        .into_inner();
+        assert_eq!(om.as_i64(), Some(-31));
+        for invalid in ["x", "-", "1G", "x-1", "xx1"] {
+            let s = format!(r#"{{ "kind":"OMI", "hexadecimal":"{invalid}" }}"#);
+            assert!(
+                serde_json::from_str::<OMFromSerde<OpenMath>>(&s).is_err(),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn omb_bytes_serde() {
+        use crate::OpenMath;
+        const ATTR: &str = r#"[[{"kind":"OMS","cd":"c","name":"n"},{"kind":"OMI","integer":1}]]"#;
+        for s in [
+            r#"{ "kind":"OMB", "bytes":[0, 1, 2, 255] }"#,
+            r#"{ "kind":"OMB", "base64":"AAEC/w==" }"#,
+            r#"["OMB",null,[0,1,2,255]]"#,
+            // buffered until the cdbase of the OMATTR is known
+            &format!(
+                r#"{{"kind":"OMATTR","attributes":{ATTR},"object":{{"kind":"OMB","bytes":[0,1,2,255]}}}}"#
+            ),
+        ] {
+            let om = serde_json::from_str::<OMFromSerde<OpenMath>>(s)
+                .expect("is valid")
+                .into_inner();
+            assert!(
+                matches!(om, OpenMath::OMB { ref bytes, .. } if **bytes == [0, 1, 2, 255]),
+                "{s}: {om:?}"
+            );
+        }
+
+        for (s, err) in [
+            (
+                r#"{ "kind":"OMB", "bytes":[0, 256] }"#,
+                "byte 256 at index 1 of OMB is not in 0..=255",
+            ),
+            (
+                r#"{ "kind":"OMB", "bytes":[-1] }"#,
+                "byte -1 at index 0 of OMB is not in 0..=255",
+            ),
+            (r#"{ "kind":"OMB", "bytes":[1.5] }"#, "expected i64"),
+            (
+                r#"{ "kind":"OMB", "bytes":[1], "base64":"AQ==" }"#,
+                "more than one of the fields",
+            ),
+        ] {
+            let Err(e) = serde_json::from_str::<OMFromSerde<OpenMath>>(s) else {
+                panic!("{s} is invalid")
+            };
+            assert!(e.to_string().contains(err), "{s}: {e}");
+        }
+    }
+
+    #[cfg(all(feature = "xml-read", feature = "xml-write"))]
+    #[test]
+    fn omobj_version_xml() {
+        let s = r#"<OMOBJ version="2.0"><OMI>2</OMI></OMOBJ>"#;
+        let obj =
+            OMObject::<i32>::from_openmath_xml_with(s, VersionPolicy::Strict).expect("is valid");
+        assert_eq!(obj.version(), Some("2.0"));
+        assert_eq!(obj.into_inner(), 2);
+
+        let s = "<OMOBJ><OMI>2</OMI></OMOBJ>";
+        let obj =
+            OMObject::<i32>::from_openmath_xml_with(s, VersionPolicy::Strict).expect("is valid");
+        assert_eq!(obj.version(), None);
+
+        let s = r#"<OMOBJ version="3.0"><OMI>2</OMI></OMOBJ>"#;
+        let e = OMObject::<i32>::from_openmath_xml(s).expect_err("unsupported version");
+        assert!(
+            matches!(&e, xml::XmlReadError::UnsupportedVersion(v) if v == "3.0"),
+            "{e}"
+        );
+        for policy in [VersionPolicy::Warn, VersionPolicy::Lenient] {
+            let obj = OMObject::<i32>::from_openmath_xml_with(s, policy).expect("is valid");
+            assert_eq!(obj.version(), Some("3.0"));
+        }
+        assert!(VersionPolicy::Warn.warns(Some("3.0")));
+        assert!(!VersionPolicy::Warn.warns(Some("2.0")));
+        assert!(!VersionPolicy::Lenient.warns(Some("3.0")));
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn encodings_xml() {
+        use crate::OpenMath;
+        fn read(r: &[u8]) -> Result<OpenMath<'static>, xml::XmlReadError<ReferenceError>> {
+            OMObject::from_openmath_xml_reader_with(r, VersionPolicy::Strict)
+                .map(OMObject::into_inner)
+        }
+        fn utf16(s: &str) -> Vec<u8> {
+            std::iter::once(0xFEFF)
+                .chain(s.replace("UTF-8", "UTF-16").encode_utf16())
+                .flat_map(u16::to_le_bytes)
+                .collect()
+        }
+        let utf8 = r#"<?xml version="1.0" encoding="UTF-8"?>
+<OMOBJ><OME><OMS cd="moreerrors" name="unexpected"/><OMSTR>ä</OMSTR><OMFOREIGN><b>x</b></OMFOREIGN></OME></OMOBJ>"#;
+        let expected = OMObject::<OpenMath<'_>>::from_openmath_xml(utf8).expect("is valid");
+
+        let with_bom = format!("\u{feff}{utf8}");
+        assert_eq!(
+            OMObject::<OpenMath<'_>>::from_openmath_xml(&with_bom).expect("is valid"),
+            expected
+        );
+        assert_eq!(read(with_bom.as_bytes()).expect("is valid"), expected);
+        // positions include the byte order mark
+        let invalid = "\u{feff}<OMOBJ><OMSTR>a<mi>x</mi></OMSTR></OMOBJ>";
+        for e in [
+            OMObject::<OpenMath<'_>>::from_openmath_xml(invalid).expect_err("markup in string"),
+            read(invalid.as_bytes()).expect_err("markup in string"),
+        ] {
+            assert!(
+                matches!(&e, xml::XmlReadError::MarkupInString { position: 18, .. }),
+                "{e:?}"
+            );
+        }
+
+        let latin1 = r#"<?xml version="1.0" encoding="ISO-8859-1"?><OMI>1</OMI>"#;
+        for e in [
+            i32::from_openmath_xml(latin1).expect_err("unsupported encoding"),
+            i32::from_openmath_xml_reader(latin1.as_bytes()).expect_err("unsupported encoding"),
+        ] {
+            assert!(
+                matches!(&e, xml::XmlReadError::UnsupportedEncoding(s) if s == "ISO-8859-1"),
+                "{e:?}"
+            );
+        }
+
+        let from_reader = read(&utf16(utf8));
+        let mut buf = Vec::new();
+        let from_buffer = read_into_buffer::<Cow<'_, str>>(
+            utf16(r#"<?xml version="1.0" encoding="UTF-8"?><OMSTR>ä</OMSTR>"#).as_slice(),
+            &mut buf,
+        );
+        #[cfg(feature = "encoding")]
+        {
+            assert_eq!(from_reader.expect("is transcoded"), expected);
+            assert_eq!(from_buffer.expect("is transcoded"), "ä");
+        }
+        #[cfg(not(feature = "encoding"))]
+        {
+            let e = from_reader.expect_err("is UTF-16");
+            assert!(
+                matches!(&e, xml::XmlReadError::UnsupportedEncoding(s) if s == "UTF-16"),
+                "{e:?}"
+            );
+            let e = from_buffer.expect_err("is UTF-16");
+            assert!(
+                matches!(&e, xml::XmlReadError::UnsupportedEncoding(s) if s == "UTF-16"),
+                "{e:?}"
+            );
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn omobj_version_serde() {
+        use serde::de::DeserializeSeed;
+        let s = r#"{ "kind":"OMOBJ", "openmath":"2.0", "object":{ "kind":"OMI", "integer":2 } }"#;
+        let obj = serde_json::from_str::<OMObject<i32>>(s).expect("is valid");
+        assert_eq!(obj.version(), Some("2.0"));
+        assert_eq!(obj.into_inner(), 2);
+
+        let s = r#"{ "kind":"OMOBJ", "openmath":"3.0", "object":{ "kind":"OMI", "integer":2 } }"#;
+        let e = serde_json::from_str::<OMObject<i32>>(s).expect_err("unsupported version");
+        assert!(
+            e.to_string().contains("unsupported OpenMath version 3.0"),
+            "{e}"
+        );
+        let obj = OMObject::<i32>::seed(VersionPolicy::Lenient)
+            .deserialize(&mut serde_json::Deserializer::from_str(s))
+            .expect("is valid");
+        assert_eq!(obj.version(), Some("3.0"));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn omobj_array_form_serde() {
+        let s = r#"["OMOBJ", "2.0", "http://example.org", ["OMATTR", null, null,
+            [[[null, null, "meta", "note"], ["OMSTR", null, "hi"]]],
+            ["OMS", null, null, "my", "sym"]]]"#;
+        let obj = serde_json::from_str::<OMObject<crate::OpenMath<'_>>>(s).expect("is valid");
+        assert_eq!(obj.version(), Some("2.0"));
+        let crate::OpenMath::OMS {
+            cdbase, attributes, ..
+        } = obj.into_inner()
+        else {
+            panic!("is an OMS")
+        };
+        assert_eq!(cdbase.as_deref(), Some("http://example.org"));
+        assert_eq!(attributes.len(), 1);
+
+        for s in [
+            r#"["OMOBJ", "2.0", ["OMI", null, 2]]"#,
+            r#"["OMOBJ", "2.0", null, ["OMI", null, 2]]"#,
+            r#"["OMOBJ", null, {"kind": "OMI", "integer": 2}]"#,
+        ] {
+            let obj = serde_json::from_str::<OMObject<i32>>(s).expect("is valid");
+            assert_eq!(obj.into_inner(), 2);
+        }
+        assert!(serde_json::from_str::<OMObject<i32>>(r#"["OMOBJ", "2.0", null]"#).is_err());
+    }
+
+    /// Records the order of [`from_openmath`](OMDeserializable::from_openmath) calls; the
+    /// children of a node are its attribute values followed by its subterms, in document
+    /// order
+    #[cfg(all(feature = "xml-read", feature = "xml-write"))]
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Recorded {
+        label: String,
+        call: usize,
+        children: Vec<Self>,
+    }
+    #[cfg(all(feature = "xml-read", feature = "xml-write"))]
+    thread_local! {
+        static CALLS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    }
+    #[cfg(all(feature = "xml-read", feature = "xml-write"))]
+    impl<'de> OMDeserializable<'de> for Recorded {
+        type Ret = Self;
+        type Err = std::convert::Infallible;
+        fn from_openmath(om: OM<'de, Self>, _: &str) -> Result<Self, Self::Err> {
+            fn values<'a>(
+                attrs: impl IntoIterator<Item = OMAttr<'a, Recorded>>,
+            ) -> impl Iterator<Item = Recorded> {
+                attrs.into_iter().filter_map(|a| match a.value {
+                    crate::OMMaybeForeign::OM(r) => Some(r),
+                    crate::OMMaybeForeign::Foreign { .. } => None,
+                })
+            }
+            let (label, children) = match om {
+                OM::OMI { int, attrs } => (int.to_string(), values(attrs).collect()),
+                OM::OMV { name, attrs } => (name.into_owned(), values(attrs).collect()),
+                OM::OMS {
+                    cd, name, attrs, ..
+                } => (format!("{cd}.{name}"), values(attrs).collect()),
+                OM::OMA {
+                    applicant,
+                    arguments,
+                    attrs,
+                } => (
+                    "OMA".to_string(),
+                    values(attrs)
+                        .chain(std::iter::once(applicant))
+                        .chain(arguments)
+                        .collect(),
+                ),
+                OM::OMBIND {
+                    binder,
+                    variables,
+                    object,
+                    attrs,
+                } => (
+                    "OMBIND".to_string(),
+                    values(attrs)
+                        .chain(std::iter::once(binder))
+                        .chain(variables.into_iter().flat_map(|(_, a)| values(a)))
+                        .chain(std::iter::once(object))
+                        .collect(),
+                ),
+                OM::OME {
+                    name,
+                    arguments,
+                    attrs,
+                    ..
+                } => (
+                    format!("OME {name}"),
+                    values(attrs)
+                        .chain(arguments.into_iter().filter_map(|a| match a {
+                            crate::OMMaybeForeign::OM(r) => Some(r),
+                            crate::OMMaybeForeign::Foreign { .. } => None,
+                        }))
+                        .collect(),
+                ),
+                om => (format!("{om:?}"), Vec::new()),
+            };
+            let call = CALLS.get();
+            CALLS.set(call + 1);
+            Ok(Self {
+                label,
+                call,
+                children,
+            })
+        }
+    }
+    #[cfg(all(feature = "xml-read", feature = "xml-write"))]
+    impl Recorded {
+        /// Checks that every node is called after all its descendants; returns the calls in
+        /// the subtree
+        fn check(&self, calls: &mut Vec<usize>) {
+            let start = calls.len();
+            for c in &self.children {
+                c.check(calls);
+            }
+            assert!(
+                calls[start..].iter().all(|c| *c < self.call),
+                "{} is called before one of its descendants",
+                self.label
+            );
+            calls.push(self.call);
+        }
+
+        /// The tree without the calls
+        fn labels(&self) -> String {
+            let children = self.children.iter().map(Self::labels).collect::<Vec<_>>();
+            format!("{}({})", self.label, children.join(","))
+        }
+    }
+
+    /// Writes `v` as JSON with the keys of every object in reverse alphabetical order, e.g.
+    /// with `"kind"` after all the other keys of an `OMATTR`
+    #[cfg(all(feature = "serde", feature = "xml-read", feature = "xml-write"))]
+    fn reverse_keys(v: &serde_json::Value) -> String {
+        match v {
+            serde_json::Value::Object(map) => {
+                let mut entries = map.iter().collect::<Vec<_>>();
+                entries.sort_by(|(a, _), (b, _)| b.cmp(a));
+                let entries = entries
+                    .into_iter()
+                    .map(|(k, v)| format!("{k:?}:{}", reverse_keys(v)))
+                    .collect::<Vec<_>>();
+                format!("{{{}}}", entries.join(","))
+            }
+            serde_json::Value::Array(a) => {
+                format!(
+                    "[{}]",
+                    a.iter().map(reverse_keys).collect::<Vec<_>>().join(",")
+                )
+            }
+            v => v.to_string(),
+        }
+    }
+
+    #[cfg(all(feature = "xml-read", feature = "xml-write"))]
+    #[test]
+    fn callback_order() {
+        use crate::{OMSerializable, OpenMath};
+        let om = OpenMath::from_sexpr(
+            r#"(omattr (((oms meta note) (omattr (((oms meta id) (omi 1))) (omv n))))
+                 (ombind (oms fns1 lambda)
+                   (bvar (omattr (((oms sts type) (oma (oms sts mapsto) (oms setname1 N) (oms setname1 Z)))) x) y)
+                   (oma (oms arith1 plus)
+                     (oma (oms arith1 times) (omv x) (omi 2))
+                     (ome (oms moreerrors unexpected) (omv y) (omforeign "…") (omi 3)))))"#,
+        )
+        .expect("is valid");
+        let nodes = 17;
+        let read = |name: &str, r: Recorded| {
+            assert_eq!(CALLS.replace(0), nodes, "{name}: every node is called once");
+            let mut calls = Vec::new();
+            r.check(&mut calls);
+            calls
+        };
+
+        let xml = om.xml(false).to_string();
+        let from_xml = Recorded::from_openmath_xml(&xml).expect("is valid");
+        let labels = from_xml.labels();
+        assert!(
+            labels.starts_with("OMBIND(n(1()),fns1.lambda()"),
+            "{labels}"
+        );
+        // XML is read in document order
+        assert!(read("xml", from_xml).into_iter().eq(0..nodes));
+
+        #[cfg(feature = "serde")]
+        {
+            use super::OMFromSerde;
+            let json = |s: &str| {
+                serde_json::from_str::<OMFromSerde<Recorded>>(s)
+                    .expect("is valid")
+                    .into_inner()
+            };
+            let compact = serde_json::to_string(&om.openmath_serde_compact()).expect("is valid");
+            assert!(compact.starts_with("[\"OMATTR\""), "{compact}");
+            let r = json(&compact);
+            assert_eq!(r.labels(), labels);
+            // so is the array form
+            assert!(read("compact", r).into_iter().eq(0..nodes));
+
+            let object = serde_json::to_value(om.openmath_serde()).expect("is valid");
+            for (name, s) in [
+                ("object", object.to_string()),
+                ("reversed", reverse_keys(&object)),
+            ] {
+                let r = json(&s);
+                assert_eq!(r.labels(), labels, "{name}");
+                // but siblings may be called in any order
+                let mut calls = read(name, r);
+                calls.sort_unstable();
+                assert!(calls.into_iter().eq(0..nodes), "{name}");
+            }
+        }
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn cyclic_references() {
+        // the nested parses need more than the default test stack in debug builds, the
+        // more so with larger inline capacities (see `INLINE_ARGS`)
+        std::thread::Builder::new()
+            .stack_size(8 << 20)
+            .spawn(check_cyclic_references)
+            .expect("thread spawns")
+            .join()
+            .expect("no panic");
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[allow(clippy::too_many_lines)]
+    fn check_cyclic_references() {
+        use crate::OpenMath;
+        const A: &str = r##"<OMA id="a"><OMS cd="list1" name="list"/><OMR href="#a"/></OMA>"##;
+        const B: &str = r##"<OMA><OMS cd="list1" name="list"/><OMR href="#c"/></OMA>"##;
+        const C: &str = r##"<OMA><OMS cd="list1" name="list"/><OMR href="#b"/></OMA>"##;
+        fn cyclic(e: &xml::XmlReadError<ReferenceError>, expected: &[&str]) -> bool {
+            matches!(e, xml::XmlReadError::Reference { error: ReferenceError::CyclicReference { id, path }, .. }
+                if id == expected[expected.len() - 1] && path == expected)
+        }
+        // the documents read while resolving resolve their references, too
+        fn resolve(href: &str, options: Options<'_>) -> Option<OpenMath<'static>> {
+            match href {
+                "#a" => OpenMath::from_openmath_xml_with(A, options).ok(),
+                "#b" => OpenMath::from_openmath_xml_with(B, options).ok(),
+                "#c" => OpenMath::from_openmath_xml_with(C, options).ok(),
+                _ => None,
+            }
+        }
+        let policy = OMRPolicy::resolve(resolve);
+        let options = Options::new().with_omr_policy(&policy);
+        let e = OpenMath::from_openmath_xml_with(A, options).expect_err("is cyclic");
+        assert!(cyclic(&e, &["#a", "#a"]), "{e}");
+        let e = OpenMath::from_openmath_xml_with(B, options).expect_err("is cyclic");
+        assert!(cyclic(&e, &["#c", "#b", "#c"]), "{e}");
+        assert_eq!(
+            e.to_string(),
+            r##"error resolving reference at offset 34: cyclic reference "#c" (via #c -> #b -> #c)"##
+        );
+        // without the policy, the reference is passed on to `from_openmath`
+        assert!(matches!(
+            OpenMath::from_openmath_xml(B),
+            Err(xml::XmlReadError::Conversion(ReferenceError::Unresolved(_)))
+        ));
+
+        #[cfg(feature = "serde_json")]
+        {
+            use serde::de::DeserializeSeed;
+            const JSON: &str = r##"{ "kind": "OMA", "applicant": { "kind": "OMS", "cd": "list1", "name": "list" },
+              "arguments": [{ "kind": "OMR", "href": "#j" }] }"##;
+            fn read<'s>(
+                json: &'s str,
+                options: Options<'_>,
+            ) -> Result<OpenMath<'s>, serde_json::Error> {
+                OMFromSerde::<OpenMath<'_>>::with_options(options)
+                    .deserialize(&mut serde_json::Deserializer::from_str(json))
+                    .map(OMFromSerde::into_inner)
+            }
+            let policy = OMRPolicy::resolve(|href, options| {
+                (href == "#j").then(|| read(JSON, options).ok()).flatten()
+            });
+            let e = read(JSON, Options::new().with_omr_policy(&policy)).expect_err("is cyclic");
+            assert!(e.to_string().contains(r##"cyclic reference "#j""##), "{e}");
+        }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        // every document refers to the previous one twice
+        let laughs = || -> MultiDocResolver {
+            (0..40)
+                .map(|n| {
+                    let xml = if n == 0 {
+                        "<OMOBJ><OMSTR>lol</OMSTR></OMOBJ>".to_string()
+                    } else {
+                        let r = format!(r#"<OMR href="d{}"/>"#, n - 1);
+                        format!(r#"<OMOBJ><OMA><OMS cd="list1" name="list"/>{r}{r}</OMA></OMOBJ>"#)
+                    };
+                    (format!("d{n}"), xml)
+                })
+                .collect()
+        };
+        let read = |root: &str, budget| {
+            let xml = format!(r#"<OMOBJ><OMR href="{root}"/></OMOBJ>"#);
+            // a fresh resolver, since failed documents are not parsed again
+            let policy = laughs().policy();
+            OMObject::<OpenMath<'static>>::from_openmath_xml_reader_with(
+                xml.as_bytes(),
+                Options::new()
+                    .with_omr_policy(&policy)
+                    .with_omr_budget(budget),
+            )
+        };
+        assert!(read("d3", 100).is_ok());
+        let e = read("d25", 10_000).expect_err("too large");
+        assert!(
+            matches!(
+                e,
+                xml::XmlReadError::Reference {
+                    error: ReferenceError::BudgetExceeded(10_000),
+                    ..
+                }
+            ),
+            "{e}"
+        );
+        // MAX_DEPTH nested documents need more than the default test stack in debug builds
+        let e = std::thread::scope(|s| {
+            std::thread::Builder::new()
+                .stack_size(8 << 20)
+                .spawn_scoped(s, || read("d39", usize::MAX).map(drop))
+                .expect("thread spawns")
+                .join()
+                .expect("no panic")
+        })
+        .expect_err("too deep");
+        assert!(
+            matches!(
+                e,
+                xml::XmlReadError::Reference {
+                    error: ReferenceError::TooDeep(OMRPolicy::MAX_DEPTH),
+                    ..
+                }
+            ),
+            "{e}"
+        );
 
-    #[derive(Copy, Clone, Debug)]
-    struct Oma;
-    enum ArgOrOMA {
-        Oms,
-        Omi,
-        Oma,
+        // random reference graphs: reading always terminates
+        let mut seed = 0x2545_f491_u64;
+        let mut next = |n: u64| {
+            seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+            (seed >> 33) % n
+        };
+        for _ in 0..200 {
+            let docs = next(5) + 1;
+            let resolver = MultiDocResolver::new();
+            for d in 0..docs {
+                let refs = (0..=next(3))
+                    .map(|_| match next(docs + 1) {
+                        r if r == docs => "<OMI>1</OMI>".to_string(),
+                        r => format!(r#"<OMR href="d{r}"/>"#),
+                    })
+                    .collect::<String>();
+                resolver.insert(
+                    format!("d{d}"),
+                    format!(r#"<OMOBJ><OMA><OMS cd="list1" name="list"/>{refs}</OMA></OMOBJ>"#),
+                );
+            }
+            let policy = resolver.policy();
+            let options = Options::new()
+                .with_omr_policy(&policy)
+                .with_omr_budget(1000);
+            match OpenMath::from_openmath_xml_with(r#"<OMR href="d0"/>"#, options) {
+                Ok(_)
+                | Err(xml::XmlReadError::Reference {
+                    error:
+                        ReferenceError::BudgetExceeded(_)
+                        | ReferenceError::TooDeep(_)
+                        | ReferenceError::Unresolved(_),
+                    ..
+                }) => (),
+                Err(xml::XmlReadError::Reference {
+                    error: ReferenceError::CyclicReference { id, path },
+                    ..
+                }) => {
+                    assert_eq!(path.last(), Some(&id));
+                    assert!(path.iter().filter(|h| **h == id).count() == 2, "{path:?}");
+                }
+                Err(e) => panic!("{e}"),
+            }
+        }
     }
-    impl TryFrom<ArgOrOMA> for Oma {
-        type Error = &'static str;
-        fn try_from(value: ArgOrOMA) -> Result<Self, Self::Error> {
-            if matches!(value, ArgOrOMA::Oma) {
-                Ok(Self)
-            } else {
-                Err("nope")
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn truncated_partial() {
+        use crate::OpenMath;
+        const XML: &str = include_str!("../../doc/roundtrip.xml");
+        let full = OMObject::<OpenMath>::from_openmath_xml_partial(XML).expect("is valid");
+        let expected = full.children().collect::<Vec<_>>();
+        let mut last = 0;
+        for cut in (0..XML.trim_end().len()).filter(|i| XML.is_char_boundary(*i)) {
+            let Err(partial) = OMObject::<OpenMath>::from_openmath_xml_partial(&XML[..cut]) else {
+                // the closing `</OMOBJ>` is not required
+                assert!(XML[..cut].contains("</OMBIND>"), "{cut}");
+                continue;
+            };
+            assert!(partial.offset <= cut as u64, "{cut}");
+            assert!(partial.children.len() >= last, "{cut}");
+            last = partial.children.len();
+            for (child, expected) in partial.children.iter().zip(&expected) {
+                assert_eq!(child, *expected, "{cut}");
             }
         }
+        assert_eq!(last, 2);
+
+        // an OMA missing its closing tags, with an invalid argument
+        let xml = r#"<OMOBJ cdbase="http://example.org"><OMA><OMS cd="c" name="f"/><OMV name="x"/><OMI>1.5</OMI><OMI>2</OMI>"#;
+        let partial = OMObject::<OpenMath>::from_openmath_xml_partial(xml).expect_err("truncated");
+        assert!(
+            matches!(partial.error, XmlReadError::InvalidInteger(_)),
+            "{}",
+            partial.error
+        );
+        assert_eq!(partial.children.len(), 2);
+        let complete = format!("{}</OMA></OMOBJ>", xml.replace("1.5", "1"));
+        let complete = OMObject::<OpenMath>::from_openmath_xml(&complete).expect("is valid");
+        assert_eq!(
+            &partial.children[0],
+            complete.children().next().expect("has head")
+        );
+        let offset = usize::try_from(partial.offset).expect("fits");
+        assert!(xml[..offset].ends_with(r#"<OMV name="x"/>"#));
     }
-    impl<'d> OMDeserializable<'d> for Oma {
-        type Ret = ArgOrOMA;
-        type Err = &'static str;
-        fn from_openmath(om: OM<'d, ArgOrOMA>, _cdbase: &str) -> Result<Self::Ret, Self::Err>
-        where
-            Self: Sized,
-        {
+
+    #[cfg(all(feature = "xml", feature = "serde"))]
+    #[test]
+    fn default_cdbase() {
+        use crate::{OpenMath, ser::OMSerializable};
+        use serde::de::DeserializeSeed;
+        const ORG: &str = "http://example.org/cd";
+        let xml = r#"<OMOBJ><OMA><OMS cd="units" name="metre"/><OMS cdbase="http://www.openmath.org/cd" cd="arith1" name="plus"/></OMA></OMOBJ>"#;
+        let obj = OMObject::<OpenMath>::from_openmath_xml_with_default_cdbase(xml, ORG)
+            .expect("is valid");
+        assert_eq!(obj.default_cdbase(), ORG);
+        let standard = OMObject::<OpenMath>::from_openmath_xml(xml).expect("is valid");
+        let cdbases = |om: &OpenMath| {
+            om.children()
+                .map(|c| match c {
+                    OpenMath::OMS { cdbase, .. } => cdbase.as_deref().map(str::to_string),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            cdbases(&obj),
+            [Some(ORG.to_string()), Some(crate::CD_BASE.to_string())]
+        );
+        assert_eq!(
+            cdbases(&standard),
+            [
+                Some(crate::CD_BASE.to_string()),
+                Some(crate::CD_BASE.to_string())
+            ]
+        );
+
+        // written back relative to the same default
+        assert_eq!(
+            obj.xml(false).to_string(),
+            xml.replace(
+                "<OMOBJ>",
+                r#"<OMOBJ version="2.0" xmlns="http://www.openmath.org/OpenMath">"#
+            )
+        );
+        assert_eq!(
+            obj.openmath_display().to_string(),
+            "OMA(OMS(units#metre),OMS(http://www.openmath.org/cd/arith1#plus))"
+        );
+        let json = serde_json::to_string(&obj.openmath_serde()).expect("serializable");
+        let back = OMFromSerde::<OpenMath>::with_default_cdbase(ORG)
+            .deserialize(&mut serde_json::Deserializer::from_str(&json))
+            .expect("is valid")
+            .into_inner();
+        assert_eq!(back, *obj);
+        assert_ne!(
+            serde_json::from_str::<OMFromSerde<OpenMath>>(&json)
+                .expect("is valid")
+                .into_inner(),
+            *obj
+        );
+
+        // without an override, nothing changes
+        let plain = OMObject::new(standard.clone());
+        assert_eq!(
+            plain.openmath_display().to_string(),
+            standard.openmath_display().to_string()
+        );
+        assert_eq!(
+            plain.xml(false).to_string(),
+            crate::ser::OMObject(&standard).xml(false, true).to_string()
+        );
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn xml_base() {
+        use crate::OpenMath;
+        fn cdbases(om: &OpenMath) -> Vec<String> {
+            let mut ret = Vec::new();
             match om {
-                OM::OMA {
-                    applicant: ArgOrOMA::Oms,
-                    arguments,
-                    ..
-                } if arguments.len() == 2
-                    && arguments.iter().all(|a| matches!(a, ArgOrOMA::Omi)) =>
-                {
-                    Ok(ArgOrOMA::Oma)
+                OpenMath::OMS { cdbase, .. } => {
+                    ret.push(cdbase.as_deref().unwrap_or_default().to_string());
+                }
+                _ => {
+                    for c in om.children() {
+                        ret.extend(cdbases(c));
+                    }
                 }
-                OM::OMS { .. } => Ok(ArgOrOMA::Oms),
-                OM::OMI { .. } => Ok(ArgOrOMA::Omi),
-                _ => Err("nope"),
             }
+            ret
+        }
+        let xml = r#"<OMA xml:base="http://a.org/x/y/">
+            <OMS cdbase="../cd" cd="c" name="n"/>
+            <OMA xml:base="z/"><OMS cdbase="cd" cd="c" name="n"/><OME><OMS cd="c" name="e"/><OMFOREIGN><b xml:base="http://d.org/"/></OMFOREIGN></OME><OMS cdbase="./cd" cd="c" name="n"/></OMA>
+            <OMS cdbase="cd" cd="c" name="n" xml:base="http://b.org/w/"/>
+            <OMA cdbase="q"><OMS cd="c" name="n"/></OMA>
+            <OMS cdbase="cd" cd="c" name="n"/>
+            <OMS cdbase="http://c.org/cd" cd="c" name="n"/>
+        </OMA>"#;
+        let expected = [
+            "http://a.org/x/cd",
+            "http://a.org/x/y/z/cd",
+            "http://a.org/x/y/z/cd",
+            "http://b.org/w/cd",
+            "http://a.org/x/y/q",
+            "http://a.org/x/y/cd",
+            "http://c.org/cd",
+        ];
+        let om = OpenMath::from_openmath_xml(xml).expect("is valid");
+        assert_eq!(cdbases(&om), expected);
+        let om = OMObject::<OpenMath<'static>>::from_openmath_xml_reader_with(
+            format!("<OMOBJ>{xml}</OMOBJ>").as_bytes(),
+            VersionPolicy::default(),
+        )
+        .expect("is valid");
+        assert_eq!(cdbases(&om), expected);
+
+        // relative `xml:base`s are resolved against the document's URI
+        let xml = r#"<OMA xml:base="sub/"><OMS cdbase="cd" cd="c" name="n"/><OMS cd="c" name="n"/></OMA>"#;
+        let om = OpenMath::from_openmath_xml_with_base(xml, "file:///docs/a.om").expect("is valid");
+        assert_eq!(cdbases(&om), ["file:///docs/sub/cd", crate::CD_BASE]);
+        // without any base, relative cdbases are kept
+        let om = OpenMath::from_openmath_xml(r#"<OMS cdbase="../cd" cd="c" name="n"/>"#)
+            .expect("is valid");
+        assert_eq!(cdbases(&om), ["../cd"]);
+        let om = OMObject::<OpenMath>::from_openmath_xml(
+            r#"<OMOBJ xml:base="http://a.org/" cdbase="cd"><OMS cd="c" name="n"/></OMOBJ>"#,
+        )
+        .expect("is valid");
+        assert_eq!(cdbases(&om), ["http://a.org/cd"]);
+    }
+
+    /// Records the [kind](OM::kind) of the deserialized object
+    #[derive(Debug)]
+    struct Kind(crate::OMKind);
+    impl<'d> OMDeserializable<'d> for Kind {
+        type Ret = Self;
+        type Err = &'static str;
+        fn from_openmath(om: OM<'d, Self>, _: &str) -> Result<Self, Self::Err> {
+            Ok(Self(om.kind()))
         }
     }
 
-    #[cfg(feature = "serde")]
     #[test]
-    fn test_oma_deserialization() {
-        let s = r#"{
-            "cdbase":"http://www.openmath.org/cd",
-            "kind": "OMA",
-            "applicant": {
-                "kind": "OMS",
-                "cd": "arith1",
-                "name": "plus"
-            },
-            "arguments": [
-                { "kind":"OMI", "integer":2 },
-                { "kind":"OMI", "integer":2 }
-            ]
-        }"#;
-        serde_json::from_str::<'_, OMFromSerde<Oma>>(s)
-            .expect("valid json, openmath, and arithmetic expression");
-        serde_json::from_reader::<_, OMFromSerde<Oma>>(s.as_bytes())
-            .expect("valid json, openmath, and arithmetic expression");
+    fn kinds() {
+        use crate::OMKind;
+        for &kind in OMKind::ALL {
+            assert_eq!(OMKind::from_u8(kind as u8), Some(kind));
+            assert_eq!(OMKind::from_name(kind.as_str().as_bytes()), Some(kind));
+        }
+        assert_eq!(OMKind::from_u8(OMKind::ALL.len().to_le_bytes()[0]), None);
+
+        // every variant of `OM`, in both encodings
+        let samples: [(&str, &str); 10] = [
+            ("<OMI>1</OMI>", r#"{"kind":"OMI","integer":1}"#),
+            (r#"<OMF dec="1.5"/>"#, r#"{"kind":"OMF","float":1.5}"#),
+            ("<OMSTR>s</OMSTR>", r#"{"kind":"OMSTR","string":"s"}"#),
+            ("<OMB>AQI=</OMB>", r#"{"kind":"OMB","base64":"AQI="}"#),
+            (r#"<OMV name="x"/>"#, r#"{"kind":"OMV","name":"x"}"#),
+            (
+                r#"<OMS cd="c" name="n"/>"#,
+                r#"{"kind":"OMS","cd":"c","name":"n"}"#,
+            ),
+            (
+                r#"<OMA><OMV name="f"/></OMA>"#,
+                r#"{"kind":"OMA","applicant":{"kind":"OMV","name":"f"}}"#,
+            ),
+            (
+                r#"<OMBIND><OMV name="b"/><OMBVAR><OMV name="x"/></OMBVAR><OMV name="x"/></OMBIND>"#,
+                r#"{"kind":"OMBIND","binder":{"kind":"OMV","name":"b"},"variables":[{"kind":"OMV","name":"x"}],"object":{"kind":"OMV","name":"x"}}"#,
+            ),
+            (
+                r#"<OME><OMS cd="c" name="n"/></OME>"#,
+                r#"{"kind":"OME","error":{"kind":"OMS","cd":"c","name":"n"}}"#,
+            ),
+            (r##"<OMR href="#x"/>"##, r##"{"kind":"OMR","href":"#x"}"##),
+        ];
+        for (xml, json) in samples {
+            let _ = (xml, json);
+            #[cfg(feature = "xml-read")]
+            {
+                let kind = Kind::from_openmath_xml(xml).expect("is valid").0;
+                assert!(xml[1..].starts_with(kind.as_str()), "{kind} vs. {xml}");
+            }
+            #[cfg(feature = "serde")]
+            {
+                let kind = serde_json::from_str::<OMFromSerde<Kind>>(json)
+                    .expect("is valid")
+                    .into_inner()
+                    .0;
+                assert!(
+                    json.starts_with(&format!(r#"{{"kind":"{kind}""#)),
+                    "{kind} vs. {json}"
+                );
+            }
+        }
+        let om: OM<'_, Kind> = OM::OMV {
+            name: "x".into(),
+            attrs: Vec::new(),
+        };
+        assert_eq!(om.kind_str(), "OMV");
     }
 
+    #[cfg(feature = "xml-read")]
     #[test]
-    fn test_oma_deserialization_xml() {
-        let s = r#"<OMOBJ cdbase="http://www.openmath.org/cd">
-          <OMA>
-            <OMS cd="arith1" name="plus"/>
-            <OMI>2</OMI>
-            <OMI>2</OMI>
-          </OMA>
-        </OMOBJ>"#;
-        OMObject::<Oma>::from_openmath_xml(s).expect("is valid");
+    fn options() {
+        use crate::OpenMath;
+        let options = Options::new()
+            .with_version_policy(VersionPolicy::Lenient)
+            .with_default_cdbase("http://a.org/cd")
+            .with_base("http://b.org/doc.om");
+        let xml = r#"<OMOBJ version="3.0"><OMA><OMS cd="c" name="n"/><OMS cdbase="rel" cd="c" name="n"/></OMA></OMOBJ>"#;
+        let check = |om: &OMObject<OpenMath>| {
+            let OpenMath::OMA {
+                applicant,
+                arguments,
+                ..
+            } = &**om
+            else {
+                panic!("not an OMA: {om:?}");
+            };
+            let OpenMath::OMS { cdbase, .. } = &**applicant else {
+                panic!("not an OMS: {applicant:?}");
+            };
+            assert_eq!(cdbase.as_deref(), Some("http://a.org/cd"));
+            let OpenMath::OMS { cdbase, .. } = &arguments[0] else {
+                panic!("not an OMS: {arguments:?}");
+            };
+            assert_eq!(cdbase.as_deref(), Some("http://b.org/rel"));
+            assert_eq!(om.default_cdbase(), "http://a.org/cd");
+            assert_eq!(om.version(), Some("3.0"));
+        };
+        check(&OMObject::from_openmath_xml_with(xml, options).expect("is valid"));
+        check(&OMObject::from_openmath_xml_reader_with(xml.as_bytes(), options).expect("is valid"));
+        check(
+            &OMObject::from_openmath_xml_preserving(xml, options)
+                .expect("is valid")
+                .0,
+        );
+        assert!(OMObject::<OpenMath>::from_openmath_xml_with(xml, Options::new()).is_err());
+
+        let inner = &xml[xml.find("<OMA>").expect("exists")..xml.find("</OMOBJ>").expect("exists")];
+        let om = OpenMath::from_openmath_xml_with(inner, options).expect("is valid");
+        check(
+            &OMObject::new(om)
+                .with_version("3.0")
+                .with_default_cdbase("http://a.org/cd"),
+        );
     }
 
     #[cfg(feature = "serde")]
     #[test]
-    fn fancy() {
-        use crate as openmath;
-        use either::Either;
-        use openmath::de::{OM, OMDeserializable, OMFromSerde};
+    fn options_serde() {
+        use crate::OpenMath;
+        use serde::de::DeserializeSeed;
+        let json = r#"{ "kind":"OMOBJ", "openmath":"3.0", "object":{ "kind":"OMS", "cd":"c", "name":"n" } }"#;
+        let options = Options::new()
+            .with_version_policy(VersionPolicy::Lenient)
+            .with_default_cdbase("http://a.org/cd");
+        let obj = OMObject::<OpenMath>::seed(options)
+            .deserialize(&mut serde_json::Deserializer::from_str(json))
+            .expect("is valid");
+        let OpenMath::OMS { cdbase, .. } = &*obj else {
+            panic!("not an OMS: {obj:?}");
+        };
+        assert_eq!(cdbase.as_deref(), Some("http://a.org/cd"));
+        assert_eq!(obj.default_cdbase(), "http://a.org/cd");
+        assert!(
+            OMObject::<OpenMath>::seed(Options::new())
+                .deserialize(&mut serde_json::Deserializer::from_str(json))
+                .is_err()
+        );
+    }
 
-        #[derive(Copy, Clone, Debug)]
-        struct SimplifiedInt(i128);
-        impl<'d> TryFrom<Either<Self, OM<'d, Box<Self>>>> for SimplifiedInt {
-            type Error = &'static str;
-            fn try_from(value: Either<Self, OM<'d, Box<Self>>>) -> Result<Self, Self::Error> {
-                if let Either::Left(v) = value {
-                    Ok(v)
-                } else {
-                    Err("nope")
+    /// `OMATTR` is flattened onto the node it directly wraps, never onto a child or parent
+    #[test]
+    fn attribution_placement() {
+        use crate::OpenMath;
+        const NOTE: &str = r#"<OMATP><OMS cd="meta" name="note"/><OMSTR>n</OMSTR></OMATP>"#;
+        const NOTE_JSON: &str =
+            r#"[[{"kind":"OMS","cd":"meta","name":"note"},{"kind":"OMSTR","string":"n"}]]"#;
+        const TYPE: &str = r#"<OMATP><OMS cd="meta" name="type"/><OMSTR>t</OMSTR></OMATP>"#;
+        const TYPE_JSON: &str =
+            r#"[[{"kind":"OMS","cd":"meta","name":"type"},{"kind":"OMSTR","string":"t"}]]"#;
+        let attr = |atp: &str, o: &str| format!("<OMATTR>{atp}{o}</OMATTR>");
+        let attr_json =
+            |atp: &str, o: &str| format!(r#"{{"kind":"OMATTR","attributes":{atp},"object":{o}}}"#);
+        let (f, x) = (r#"<OMV name="f"/>"#, r#"<OMV name="x"/>"#);
+        let (f_json, x_json) = (
+            r#"{"kind":"OMV","name":"f"}"#,
+            r#"{"kind":"OMV","name":"x"}"#,
+        );
+        let oma = |f: &str| format!("<OMA>{f}{x}</OMA>");
+        let oma_json =
+            |f: &str| format!(r#"{{"kind":"OMA","applicant":{f},"arguments":[{x_json}]}}"#);
+        let bind = |v: &str| format!("<OMBIND>{f}<OMBVAR>{v}</OMBVAR>{x}</OMBIND>");
+        let bind_json = |v: &str| {
+            format!(r#"{{"kind":"OMBIND","binder":{f_json},"variables":[{v}],"object":{x_json}}}"#)
+        };
+
+        let cases = [
+            // OMATTR(note, OMA(f, x)): the application is attributed
+            (
+                attr(NOTE, &oma(f)),
+                attr_json(NOTE_JSON, &oma_json(f_json)),
+                "(omattr (((oms meta note) (omstr \"n\"))) (oma (omv f) (omv x)))",
+            ),
+            // OMA(OMATTR(note, f), x): the applicant is attributed
+            (
+                oma(&attr(NOTE, f)),
+                oma_json(&attr_json(NOTE_JSON, f_json)),
+                "(oma (omattr (((oms meta note) (omstr \"n\"))) (omv f)) (omv x))",
+            ),
+            // OMATTR(note, OMBIND(f, [x], x)): the binding is attributed
+            (
+                attr(NOTE, &bind(x)),
+                attr_json(NOTE_JSON, &bind_json(x_json)),
+                "(omattr (((oms meta note) (omstr \"n\"))) (ombind (omv f) (bvar x) (omv x)))",
+            ),
+            // OMBIND(f, [OMATTR(note, x)], x): the bound variable is attributed
+            (
+                bind(&attr(NOTE, x)),
+                bind_json(&attr_json(NOTE_JSON, x_json)),
+                "(ombind (omv f) (bvar (omattr (((oms meta note) (omstr \"n\"))) x)) (omv x))",
+            ),
+            // OMATTR(note, OMATTR(type, x)): outer pairs come first
+            (
+                attr(NOTE, &attr(TYPE, x)),
+                attr_json(NOTE_JSON, &attr_json(TYPE_JSON, x_json)),
+                "(omattr (((oms meta note) (omstr \"n\")) ((oms meta type) (omstr \"t\"))) (omv x))",
+            ),
+        ];
+        for (xml, json, sexpr) in cases {
+            let _ = (&xml, &json, sexpr);
+            #[cfg(feature = "xml-read")]
+            {
+                let om = OpenMath::from_openmath_xml(&xml).expect("is valid");
+                assert_eq!(om.to_sexpr(), sexpr, "{xml}");
+                #[cfg(feature = "xml-write")]
+                {
+                    use crate::OMSerializable;
+                    let written = om.xml(false).to_string();
+                    let reread = OpenMath::from_openmath_xml(&written).expect("is valid");
+                    assert_eq!(reread, om, "{written}");
                 }
             }
-        }
-        impl<'d> OMDeserializable<'d> for SimplifiedInt {
-            type Ret = Either<Self, OM<'d, Box<Self>>>;
-            type Err = &'static str;
-            fn from_openmath(
-                om: OM<'d, Self::Ret>,
-                cdbase: &str,
-            ) -> Result<Either<Self, OM<'d, Box<Self>>>, Self::Err>
-            where
-                Self: Sized,
+            #[cfg(feature = "serde")]
             {
-                match om {
-                    // An integer
-                    OM::OMI { int, .. } => {
-                        // ...which fits in an i128
-                        int.is_i128()
-                            .map_or(Err("Invalid int value"), |i| Ok(Either::Left(Self(i))))
-                    }
-                    // Addition or multiplication
-                    OM::OMS { cd, name, .. }
-                        if cd == "arith1"
-                            && (name == "plus" || name == "times")
-                            && cdbase == openmath::CD_BASE =>
-                    {
-                        // works, but without arguments, we can't do anything to it *yet*.
-                        // => We send it back, so we can take care of it later, if it
-                        // occurs as the head of an OMA expression
-                        Ok(either::Right(OM::OMS {
-                            cd,
-                            name,
-                            attrs: Attrs::new(),
-                        }))
-                    }
-                    // some operator application to two arguments
-                    OM::OMA {
-                        // still an open math expression:
-                        applicant: either::Right(op),
-                        mut arguments,
-                        ..
-                    } if arguments.iter().all(Either::is_left)
-                        && arguments.len() == 2
-                        && cdbase == openmath::CD_BASE =>
-                    {
-                        // An OMA only ends up here, after both the head and all arguments
-                        // were fed into this method.
-                        // Since "plus" and "times" are the only values for
-                        // which we return `either::Right`, we know the following matches:
-                        let is_times = match op {
-                            OM::OMS { name, .. } => name == "times",
-                            _ => unreachable!(),
-                        };
-                        let Some(Either::Left(arg2)) = arguments.pop() else {
-                            unreachable!()
-                        };
-                        let Some(Either::Left(arg1)) = arguments.pop() else {
-                            unreachable!()
-                        };
-                        let value = if is_times {
-                            arg1.0 * arg2.0
-                        } else {
-                            arg1.0 + arg2.0
-                        };
-                        Ok(Either::Left(Self(value)))
-                    }
-                    // everything else is illegal
-                    _ => Err("Not an arithmetic expression"),
-                }
+                use crate::OMSerializable;
+                let om = serde_json::from_str::<OMFromSerde<OpenMath>>(&json)
+                    .expect("is valid")
+                    .into_inner();
+                assert_eq!(om.to_sexpr(), sexpr, "{json}");
+                let written = serde_json::to_string(&om.openmath_serde()).expect("works");
+                let reread = serde_json::from_str::<OMFromSerde<OpenMath>>(&written)
+                    .expect("is valid")
+                    .into_inner();
+                assert_eq!(reread, om, "{written}");
             }
         }
+    }
 
-        // 2 + 2
-        let s = r#"{
-            "cdbase":"http://www.openmath.org/cd",
-            "kind": "OMA",
-            "applicant": {
-                "kind": "OMS",
-                "cd": "arith1",
-                "name": "plus"
+    #[cfg(any(feature = "xml-read", feature = "serde"))]
+    fn skip_provenance(_: &str, cd: &str, name: &str) -> AttrAction {
+        if (cd, name) == ("meta", "provenance") {
+            AttrAction::Skip
+        } else {
+            AttrAction::Keep
+        }
+    }
+
+    #[cfg(any(feature = "xml-read", feature = "serde"))]
+    fn skip_other_cdbase(cdbase: &str, _: &str, _: &str) -> AttrAction {
+        if cdbase == "http://example.org" {
+            AttrAction::Keep
+        } else {
+            AttrAction::Skip
+        }
+    }
+
+    #[cfg(any(feature = "xml-read", feature = "serde"))]
+    /// Asserts that only the `note` and `type` attributes of `om` (or its first variable)
+    /// are left
+    fn check_filtered(om: &crate::OpenMath, var: bool) {
+        use crate::OpenMath;
+        let attributes = if var {
+            let OpenMath::OMBIND { variables, .. } = om else {
+                panic!("not an OMBIND: {om:?}");
+            };
+            &variables[0].attributes
+        } else {
+            om.attributes()
+        };
+        let keys = attributes.iter().map(|a| &*a.key.name).collect::<Vec<_>>();
+        assert_eq!(keys, ["note", "type"], "{om:?}");
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn attr_filter_xml() {
+        use crate::OpenMath;
+        let options = Options::new().with_attr_filter(skip_provenance);
+        assert_eq!(options, Options::new().with_attr_filter(skip_provenance));
+        assert_ne!(options, Options::new());
+        let pairs = r#"<OMATP>
+            <OMS cd="meta" name="provenance"/><OMFOREIGN><a><b/>text</a></OMFOREIGN>
+            <OMS cd="meta" name="note"/><OMSTR>n</OMSTR>
+            <OMS cd="meta" name="provenance"/><OMSTR/>
+            <OMS cd="meta" name="type"/><OMA><OMS cd="meta" name="provenance"/></OMA>
+            <OMS cd="meta" name="provenance"/><OMATTR><OMATP/><OMV name="y"/></OMATTR>
+        </OMATP>"#;
+        let xml = format!("<OMATTR>{pairs}<OMV name=\"x\"/></OMATTR>");
+        check_filtered(
+            &OpenMath::from_openmath_xml_with(&xml, options).expect("is valid"),
+            false,
+        );
+        let obj = format!("<OMOBJ>{xml}</OMOBJ>");
+        let owned =
+            OMObject::<OpenMath<'static>>::from_openmath_xml_reader_with(obj.as_bytes(), options)
+                .expect("is valid");
+        check_filtered(&owned, false);
+        let (preserved, _) =
+            OMObject::<OpenMath>::from_openmath_xml_preserving(&obj, options).expect("is valid");
+        check_filtered(&preserved, false);
+        let var = format!(
+            r#"<OMBIND><OMV name="f"/><OMBVAR><OMATTR>{pairs}<OMV name="x"/></OMATTR></OMBVAR><OMV name="x"/></OMBIND>"#
+        );
+        check_filtered(
+            &OpenMath::from_openmath_xml_with(&var, options).expect("is valid"),
+            true,
+        );
+        // without a filter, everything is kept
+        assert_eq!(
+            OpenMath::from_openmath_xml(&xml)
+                .expect("is valid")
+                .attributes()
+                .len(),
+            5
+        );
+        // the filter gets the resolved cdbase
+        let xml = r#"<OMATTR cdbase="http://example.org"><OMATP>
+            <OMS cd="meta" name="note"/><OMSTR>n</OMSTR>
+            <OMS cdbase="http://other.org" cd="meta" name="provenance"/><OMSTR>p</OMSTR>
+            <OMS cd="meta" name="type"/><OMSTR>t</OMSTR>
+        </OMATP><OMV name="x"/></OMATTR>"#;
+        let options = Options::new().with_attr_filter(skip_other_cdbase);
+        check_filtered(
+            &OpenMath::from_openmath_xml_with(xml, options).expect("is valid"),
+            false,
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn attr_filter_serde() {
+        use crate::OpenMath;
+        use serde::de::DeserializeSeed;
+        fn parse<'s>(
+            json: &'s str,
+            options: Options<'_>,
+        ) -> Result<OpenMath<'s>, serde_json::Error> {
+            OMFromSerde::<OpenMath>::with_options(options)
+                .deserialize(&mut serde_json::Deserializer::from_str(json))
+                .map(OMFromSerde::into_inner)
+        }
+        let options = Options::new().with_attr_filter(skip_provenance);
+        let pairs = r#"[
+            [{"kind":"OMS","cd":"meta","name":"provenance"},{"kind":"OMFOREIGN","foreign":"<a/>"}],
+            [{"kind":"OMS","cd":"meta","name":"note"},{"kind":"OMSTR","string":"n"}],
+            [{"kind":"OMS","cd":"meta","name":"provenance"},{"kind":"OMI","integer":1}],
+            [{"kind":"OMS","cd":"meta","name":"type"},{"kind":"OMS","cd":"meta","name":"provenance"}]
+        ]"#;
+        let x = r#"{"kind":"OMV","name":"x"}"#;
+        for json in [
+            format!(r#"{{"kind":"OMATTR","attributes":{pairs},"object":{x}}}"#),
+            format!(r#"{{"kind":"OMATTR","object":{x},"attributes":{pairs}}}"#),
+            format!(r#"{{"attributes":{pairs},"kind":"OMATTR","object":{x}}}"#),
+            format!(
+                r#"{{"kind":"OMATTR","attributes":{pairs},"object":{x},"cdbase":"http://example.org"}}"#
+            ),
+            format!(r#"["OMATTR",null,null,{pairs},["OMV",null,"x"]]"#),
+        ] {
+            check_filtered(&parse(&json, options).expect("is valid"), false);
+        }
+        let var = format!(
+            r#"{{"kind":"OMBIND","binder":{{"kind":"OMV","name":"f"}},"object":{x},
+                "variables":[{{"kind":"OMATTR","attributes":{pairs},"object":{x}}}]}}"#
+        );
+        check_filtered(&parse(&var, options).expect("is valid"), true);
+        let json = format!(r#"{{"kind":"OMATTR","attributes":{pairs},"object":{x}}}"#);
+        assert_eq!(
+            parse(&json, Options::new())
+                .expect("is valid")
+                .attributes()
+                .len(),
+            4
+        );
+
+        // a late cdbase that would have kept a skipped pair is an error
+        let options = Options::new().with_attr_filter(skip_other_cdbase);
+        let late = r#"{"kind":"OMATTR","attributes":[
+            [{"kind":"OMS","cd":"meta","name":"note"},{"kind":"OMSTR","string":"n"}]
+        ],"object":{"kind":"OMV","name":"x"},"cdbase":"http://example.org"}"#;
+        let e = parse(late, options).expect_err("was skipped");
+        assert!(e.to_string().contains("meta#note was skipped"), "{e}");
+        let early = late.replace(
+            r#"{"kind":"OMATTR","#,
+            r#"{"kind":"OMATTR","cdbase":"http://example.org","#,
+        );
+        let early = early.replace(r#","cdbase":"http://example.org"}"#, "}");
+        assert_eq!(
+            parse(&early, options).expect("is valid").attributes().len(),
+            1
+        );
+    }
+
+    #[cfg(any(feature = "xml-read", feature = "serde_json"))]
+    /// Checks `parse` (returning the [`NameError`] it fails with, if any) on empty, short,
+    /// maximal and too long names in each of `templates`, paired with the kind of object
+    /// the name `NAME` is in
+    fn check_names(
+        templates: &[(&str, crate::OMKind)],
+        parse: impl Fn(&str, Options) -> Option<NameError>,
+    ) {
+        let limited = Options::new().with_limits(Limits::new().with_max_name_len(4));
+        let strict = limited.with_strict_names(true);
+        for &(template, kind) in templates {
+            for name in ["", "a", "abcd", "abcde"] {
+                let input = template.replace("NAME", name);
+                assert_eq!(parse(&input, Options::new()), None, "{input}");
+                let expected = match name.len() {
+                    0 => Some(NameError::Empty(kind)),
+                    5 => Some(NameError::TooLong {
+                        kind,
+                        len: 5,
+                        max: 4,
+                    }),
+                    _ => None,
+                };
+                assert_eq!(parse(&input, strict), expected, "{input}");
+                let expected = expected.filter(|_| !name.is_empty());
+                assert_eq!(parse(&input, limited), expected, "{input}");
+            }
+        }
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn name_limits_xml() {
+        use crate::{OMKind, OpenMath};
+        check_names(
+            &[
+                (r#"<OMV name="NAME"/>"#, OMKind::OMV),
+                (r#"<OMS cd="NAME" name="s"/>"#, OMKind::OMS),
+                (r#"<OMS cd="c" name="NAME"/>"#, OMKind::OMS),
+                (r#"<OME><OMS cd="c" name="NAME"/></OME>"#, OMKind::OME),
+                (
+                    r#"<OMATTR><OMATP><OMS cd="c" name="NAME"/><OMSTR/></OMATP><OMI>1</OMI></OMATTR>"#,
+                    OMKind::OMS,
+                ),
+                (
+                    r#"<OMBIND><OMS cd="c" name="s"/><OMBVAR><OMV name="NAME"/></OMBVAR><OMI>1</OMI></OMBIND>"#,
+                    OMKind::OMV,
+                ),
+            ],
+            |xml, options| match OpenMath::from_openmath_xml_with(xml, options) {
+                Ok(_) => None,
+                Err(XmlReadError::InvalidName { error, .. }) => Some(error),
+                Err(e) => panic!("{e}"),
             },
-            "arguments": [
-                { "kind":"OMI", "integer":2 },
-                { "kind":"OMI", "integer":2 }
-            ]
-        }"#;
-        let r = serde_json::from_str::<'_, OMFromSerde<SimplifiedInt>>(s)
-            .expect("valid json, openmath, and arithmetic expression");
-        assert_eq!(r.into_inner().0, 4);
-        let s = r#"
-        <OMA cdbase="http://www.openmath.org/cd">
-          <OMS cd="arith1" name="plus"/>
-          <OMI>2</OMI>
-          <OMI>2</OMI>
-        </OMA>"#;
-        let r = SimplifiedInt::from_openmath_xml(s)
-            .expect("valid xml, openmath, and arithmetic expression");
-        assert_eq!(r.0, 4);
+        );
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn name_limits_serde() {
+        use crate::{OMKind, OpenMath};
+        use serde::de::DeserializeSeed;
+        check_names(
+            &[
+                (r#"{"kind":"OMV","name":"NAME"}"#, OMKind::OMV),
+                (r#"["OMS",null,null,"NAME","s"]"#, OMKind::OMS),
+                (r#"{"kind":"OMS","cd":"c","name":"NAME"}"#, OMKind::OMS),
+                (
+                    r#"{"kind":"OME","error":{"kind":"OMS","cd":"c","name":"NAME"},"arguments":[]}"#,
+                    OMKind::OME,
+                ),
+                (
+                    r#"{"kind":"OMATTR","attributes":[[{"kind":"OMS","cd":"c","name":"NAME"},{"kind":"OMI","integer":1}]],"object":{"kind":"OMI","integer":1}}"#,
+                    OMKind::OMS,
+                ),
+                (
+                    r#"{"kind":"OMBIND","binder":{"kind":"OMS","cd":"c","name":"s"},"variables":[{"kind":"OMV","name":"NAME"}],"object":{"kind":"OMI","integer":1}}"#,
+                    OMKind::OMV,
+                ),
+            ],
+            |json, options| {
+                let e = OMFromSerde::<OpenMath>::with_options(options)
+                    .deserialize(&mut serde_json::Deserializer::from_str(json))
+                    .err()?
+                    .to_string();
+                [
+                    NameError::Empty(OMKind::OMV),
+                    NameError::Empty(OMKind::OMS),
+                    NameError::Empty(OMKind::OME),
+                ]
+                .into_iter()
+                .chain(
+                    [OMKind::OMV, OMKind::OMS, OMKind::OME].map(|kind| NameError::TooLong {
+                        kind,
+                        len: 5,
+                        max: 4,
+                    }),
+                )
+                .find(|n| e.starts_with(&n.to_string()))
+                .or_else(|| panic!("{e}"))
+            },
+        );
+    }
+
+    /// An application with `fanout` applications as arguments, of `3 * fanout + 2` nodes
+    #[cfg(any(feature = "xml-read", feature = "serde_json"))]
+    fn adversarial(fanout: usize, json: bool) -> String {
+        let oma = |args: &[String]| {
+            if json {
+                format!(
+                    r#"{{"kind":"OMA","applicant":{{"kind":"OMS","cd":"c","name":"f"}},"arguments":[{}]}}"#,
+                    args.join(",")
+                )
+            } else {
+                format!(r#"<OMA><OMS cd="c" name="f"/>{}</OMA>"#, args.concat())
+            }
+        };
+        let leaf = if json {
+            r#"{"kind":"OMI","integer":1}"#
+        } else {
+            "<OMI>1</OMI>"
+        };
+        let inner = oma(&[leaf.to_string()]);
+        oma(&vec![inner; fanout])
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn budget_xml() {
+        use crate::OpenMath;
+        let xml = adversarial(10_000, false);
+        let exceeded = |options: Options<'static>, reader: bool| {
+            let r = if reader {
+                OMObject::<OpenMath>::from_openmath_xml_reader_with(
+                    format!("<OMOBJ>{xml}</OMOBJ>").as_bytes(),
+                    options,
+                )
+                .map(drop)
+            } else {
+                OpenMath::from_openmath_xml_with(&xml, options).map(drop)
+            };
+            match r {
+                Ok(()) => None,
+                Err(XmlReadError::BudgetExceeded { nodes_parsed, .. }) => Some(nodes_parsed),
+                Err(e) => panic!("{e}"),
+            }
+        };
+        for reader in [false, true] {
+            assert_eq!(exceeded(Options::new(), reader), None);
+            assert_eq!(
+                exceeded(Options::new().with_budget(Budget::new(40_000)), reader),
+                None
+            );
+            assert_eq!(
+                exceeded(Options::new().with_budget(Budget::new(100)), reader),
+                Some(101)
+            );
+            let late = Budget::new(usize::MAX).with_deadline(std::time::Instant::now());
+            assert_eq!(
+                exceeded(Options::new().with_budget(late), reader),
+                Some(Budget::CHECK_INTERVAL)
+            );
+        }
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn budget_serde() {
+        use crate::OpenMath;
+        use serde::de::DeserializeSeed;
+        let json = adversarial(10_000, true);
+        let parse = |budget| {
+            OMFromSerde::<OpenMath>::with_options(Options::new().with_budget(budget))
+                .deserialize(&mut serde_json::Deserializer::from_str(&json))
+                .map(OMFromSerde::into_inner)
+                .map_err(|e| e.to_string())
+        };
+        let om = parse(Budget::new(40_000)).expect("within budget");
+        assert!(matches!(om, OpenMath::OMA { ref arguments, .. } if arguments.len() == 10_000));
+        let e = parse(Budget::new(100)).expect_err("exceeds budget");
+        assert!(e.starts_with("budget exceeded after 101 nodes"), "{e}");
+        // the deadline is not enforced
+        let late = Budget::new(usize::MAX).with_deadline(std::time::Instant::now());
+        assert!(parse(late).is_ok());
     }
 }
@@ -0,0 +1,136 @@
+//! Resolving references between documents; see [`MultiDocResolver`]
+
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::OpenMath;
+
+use super::{OMObject, OMRPolicy, Options, ReferenceError, xml::XmlReadError};
+
+/** Resolves [OMR](crate::OMKind::OMR)s that refer to objects in other documents, given as
+named XML strings; e.g. the documents a [`Sharder`](crate::ser::Sharder) produces.
+
+An `href` of the form `name#id` refers to the object of the document `name` if its OMOBJ has
+the `id`; a plain `name` refers to the object regardless of its `id`. References in the
+documents themselves are resolved the same way, so documents can be added in any order;
+every document is parsed at most once, when it is first referred to. References to missing
+documents and documents that fail to parse remain unresolved; cyclic references fail with
+[`CyclicReference`](super::ReferenceError::CyclicReference) when read via
+[`read`](Self::read) or the [`policy`](Self::policy).
+
+# Examples
+```
+# #[cfg(feature = "xml-write")]
+# {
+use openmath::{OpenMath, de::MultiDocResolver, ser::Sharder};
+
+let om = OpenMath::from_sexpr(
+    "(oma (oms arith1 plus) (oma (oms arith1 times) (omi 2) (omi 3)) (omi 4))",
+)
+.expect("is valid");
+let shards = Sharder::by_size(3).shard(&om);
+let resolver: MultiDocResolver = shards.documents.into_iter().collect();
+assert_eq!(resolver.read(&shards.root).expect("is resolved"), om);
+# }
+```
+*/
+#[derive(Clone, Default)]
+pub struct MultiDocResolver(Rc<Documents>);
+
+#[derive(Default)]
+struct Documents {
+    sources: RefCell<HashMap<String, String>>,
+    /// `None` while being parsed, or if parsing failed
+    #[allow(clippy::type_complexity)]
+    parsed: RefCell<HashMap<String, Option<(Option<String>, OpenMath<'static>)>>>,
+}
+
+impl MultiDocResolver {
+    /// An empty resolver
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds (or replaces) the document `name` with the given XML
+    pub fn insert(&self, name: impl Into<String>, xml: impl Into<String>) {
+        self.0.sources.borrow_mut().insert(name.into(), xml.into());
+        // replacing a document may change everything referring to it
+        self.0.parsed.borrow_mut().clear();
+    }
+
+    /// The object `href` refers to, if any
+    #[must_use]
+    pub fn resolve(&self, href: &str) -> Option<OpenMath<'static>> {
+        let policy = self.policy();
+        self.resolve_with(href, Options::new().with_omr_policy(&policy))
+    }
+
+    /// Like [`resolve`](Self::resolve), but parses documents with `options`
+    fn resolve_with(&self, href: &str, options: Options<'_>) -> Option<OpenMath<'static>> {
+        let (name, fragment) = match href.split_once('#') {
+            Some((name, fragment)) => (name, Some(fragment)),
+            None => (href, None),
+        };
+        if let Some(parsed) = self.0.parsed.borrow().get(name) {
+            return parsed
+                .as_ref()
+                .filter(|(id, _)| fragment.is_none_or(|f| id.as_deref() == Some(f)))
+                .map(|(_, om)| om.clone());
+        }
+        let sources = self.0.sources.borrow();
+        let source = sources.get(name)?;
+        // marks the document as being parsed, so cycles are not followed
+        self.0.parsed.borrow_mut().insert(name.to_string(), None);
+        let parsed = OMObject::<OpenMath<'static>>::from_openmath_xml_reader_with(
+            source.as_bytes(),
+            options,
+        )
+        .ok()
+        .map(|obj| (obj.id().map(ToString::to_string), obj.into_inner()));
+        self.0.parsed.borrow_mut().insert(name.to_string(), parsed);
+        drop(sources);
+        self.resolve_with(href, options)
+    }
+
+    /// An [`OMRPolicy`] resolving references with this resolver; e.g. for
+    /// [`Options::with_omr_policy`]
+    #[must_use]
+    pub fn policy(&self) -> OMRPolicy {
+        let this = self.clone();
+        OMRPolicy::resolve(move |href, options| this.resolve_with(href, options))
+    }
+
+    /// Reads the OMOBJ in `xml` (e.g. the root a [`Sharder`](crate::ser::Sharder) produces),
+    /// resolving references with this resolver.
+    ///
+    /// # Errors
+    /// if `xml` is not a valid OMOBJ, or contains unresolved references.
+    pub fn read(&self, xml: &str) -> Result<OpenMath<'static>, XmlReadError<ReferenceError>> {
+        let policy = self.policy();
+        OMObject::<OpenMath<'static>>::from_openmath_xml_reader_with(
+            xml.as_bytes(),
+            Options::new().with_omr_policy(&policy),
+        )
+        .map(OMObject::into_inner)
+    }
+}
+
+impl<N: Into<String>, X: Into<String>> FromIterator<(N, X)> for MultiDocResolver {
+    fn from_iter<T: IntoIterator<Item = (N, X)>>(iter: T) -> Self {
+        let resolver = Self::new();
+        resolver.0.sources.borrow_mut().extend(
+            iter.into_iter()
+                .map(|(name, xml)| (name.into(), xml.into())),
+        );
+        resolver
+    }
+}
+
+impl std::fmt::Debug for MultiDocResolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set()
+            .entries(self.0.sources.borrow().keys())
+            .finish()
+    }
+}
@@ -0,0 +1,600 @@
+//! Deserializing applications argument by argument: fixed-arity ones into tuples (see
+//! [`Headed`]), and others into lists that keep the arguments that convert (see [`Partial`]
+//! and [`Lossy`])
+
+use std::borrow::Cow;
+
+use super::{OM, OMDeserializable};
+#[cfg(feature = "xml-read")]
+use super::{Options, xml::XmlReadError};
+use crate::OMKind;
+
+/// Errors when deserializing a tuple or a [`Headed`] from anything but an application of
+/// the right arity whose head and arguments convert
+///
+/// [`Partial`] and [`Lossy`] only fail with
+/// [`NotAnApplication`](PositionalError::NotAnApplication).
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PositionalError {
+    #[error("expected an OMA, found an {0}")]
+    NotAnApplication(OMKind),
+    #[error("expected {expected} arguments, found {found}")]
+    Arity { expected: usize, found: usize },
+    #[error("invalid head: {0}")]
+    Head(String),
+    /// The argument at `index` (counting from `0`) does not convert
+    #[error("invalid argument {index}: {error}")]
+    Argument { index: usize, error: String },
+}
+
+/** The [`Ret`](OMDeserializable::Ret) of tuples, [`Headed`], [`Partial`] and [`Lossy`]: the object as read, kept
+until its root is known to be the application whose arguments are the elements.
+
+Since an implementation of [`from_openmath`](OMDeserializable::from_openmath) cannot tell
+whether it converts the root or a subterm (which, for `(A, B)`, may have to become an `A`
+or a `B`), the whole object is kept, and its arguments are converted (each exactly once)
+when converting this into the tuple. Elements hence never [skip](OMDeserializable::before_child)
+children.
+*/
+#[derive(Debug, Clone)]
+pub struct Deferred<'de> {
+    om: Box<OM<'de, Self>>,
+    cdbase: Cow<'static, str>,
+}
+
+impl<'de> Deferred<'de> {
+    fn new(om: OM<'de, Self>, cdbase: &str) -> Self {
+        Self {
+            om: Box::new(om),
+            cdbase: if cdbase == crate::CD_BASE {
+                Cow::Borrowed(crate::CD_BASE)
+            } else {
+                Cow::Owned(cdbase.to_string())
+            },
+        }
+    }
+
+    /// The head and the `N` arguments of this application
+    fn application<const N: usize>(self) -> Result<(Self, [Self; N]), PositionalError> {
+        let OM::OMA {
+            applicant,
+            arguments,
+            ..
+        } = *self.om
+        else {
+            return Err(PositionalError::NotAnApplication(self.om.kind()));
+        };
+        let found = arguments.len();
+        let arguments = Vec::from(arguments)
+            .try_into()
+            .map_err(|_| PositionalError::Arity { expected: N, found })?;
+        Ok((applicant, arguments))
+    }
+
+    /// Converts this object into a `T`, bottom-up as if it was read as one
+    fn convert<T: OMDeserializable<'de>>(self) -> Result<T, String> {
+        self.replay::<T>()
+            .map_err(|e| e.to_string())?
+            .try_into()
+            .map_err(|e| format!("{e:?}"))
+    }
+
+    fn replay<T: OMDeserializable<'de>>(self) -> Result<T::Ret, T::Err> {
+        let om = self.om.try_map(Self::replay::<T>)?;
+        super::convert::<T>(om, &self.cdbase)
+    }
+
+    fn argument<T: OMDeserializable<'de>>(self, index: usize) -> Result<T, PositionalError> {
+        self.convert()
+            .map_err(|error| PositionalError::Argument { index, error })
+    }
+}
+
+/// Reads the whole object into a [`Deferred`], so that the reasons conversions fail are
+/// not lost in [`NotFullyConvertible`](XmlReadError::NotFullyConvertible)s
+#[cfg(feature = "xml-read")]
+#[derive(Debug)]
+struct Tree<'de>(Deferred<'de>);
+#[cfg(feature = "xml-read")]
+impl<'de> From<Deferred<'de>> for Tree<'de> {
+    #[inline]
+    fn from(value: Deferred<'de>) -> Self {
+        Self(value)
+    }
+}
+#[cfg(feature = "xml-read")]
+impl<'de> OMDeserializable<'de> for Tree<'de> {
+    type Ret = Deferred<'de>;
+    type Err = std::convert::Infallible;
+    #[inline]
+    fn from_openmath(om: OM<'de, Self::Ret>, cdbase: &str) -> Result<Self::Ret, Self::Err> {
+        Ok(Deferred::new(om, cdbase))
+    }
+}
+#[cfg(feature = "xml-read")]
+fn read_xml<'de, T>(
+    input: &'de str,
+    options: Options<'_>,
+) -> Result<T, XmlReadError<PositionalError>>
+where
+    T: TryFrom<Deferred<'de>, Error = PositionalError>,
+{
+    Tree::from_openmath_xml_with(input, options)
+        .map_err(XmlReadError::widen)?
+        .0
+        .try_into()
+        .map_err(XmlReadError::Conversion)
+}
+
+/** An application `head(arguments…)` of a fixed arity, such as
+`geometry1.point(x, y)`, as its head `H` and its arguments `T`; a tuple with an element
+for every argument. Tuples alone deserialize from applications with any head.
+
+Every position that fails to convert (or the wrong number of arguments) is reported as a
+[`PositionalError`] by [`from_openmath_xml`](OMDeserializable::from_openmath_xml) and
+[`from_openmath_xml_with`](OMDeserializable::from_openmath_xml_with) (as a
+[`Conversion`](XmlReadError::Conversion) error) and, as part of the message, by serde; the
+other entry points (like for [`Matrix`](crate::types::Matrix)) only report that the object
+is [not convertible](XmlReadError::NotFullyConvertible). See [`Deferred`] for how this
+works and what it costs.
+
+# Examples
+```
+use openmath::{OMDeserializable, de::Headed, om_symbols};
+
+om_symbols! {
+    enum Geometry {
+        Point = ("geometry1", "point"),
+    }
+}
+
+# #[cfg(feature = "xml-read")]
+# {
+let xml = r#"<OMA><OMS cd="geometry1" name="point"/><OMF dec="1.5"/><OMF dec="-2"/></OMA>"#;
+let point = Headed::<Geometry, (f64, f64)>::from_openmath_xml(xml).expect("is a point");
+assert_eq!((point.head, point.arguments), (Geometry::Point, (1.5, -2.0)));
+
+let xml = r#"<OMA><OMS cd="geometry1" name="point"/><OMF dec="1.5"/><OMI>2</OMI></OMA>"#;
+let error = <(f64, f64)>::from_openmath_xml(xml).expect_err("is not a float");
+assert_eq!(error.to_string(), "error converting OpenMath: invalid argument 1: Not a float");
+# }
+```
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Headed<H, T> {
+    /// The head of the application
+    pub head: H,
+    /// The arguments of the application; usually a tuple
+    pub arguments: T,
+}
+
+impl<'de, H, T> TryFrom<Deferred<'de>> for Headed<H, T>
+where
+    H: OMDeserializable<'de>,
+    T: TryFrom<Deferred<'de>, Error = PositionalError>,
+{
+    type Error = PositionalError;
+    fn try_from(value: Deferred<'de>) -> Result<Self, Self::Error> {
+        let OM::OMA { applicant, .. } = &*value.om else {
+            return Err(PositionalError::NotAnApplication(value.om.kind()));
+        };
+        let head = applicant.clone().convert().map_err(PositionalError::Head)?;
+        Ok(Self {
+            head,
+            arguments: value.try_into()?,
+        })
+    }
+}
+
+impl<'de, H, T> OMDeserializable<'de> for Headed<H, T>
+where
+    H: OMDeserializable<'de>,
+    T: TryFrom<Deferred<'de>, Error = PositionalError> + std::fmt::Debug,
+{
+    type Ret = Deferred<'de>;
+    type Err = PositionalError;
+    #[inline]
+    fn from_openmath(om: OM<'de, Self::Ret>, cdbase: &str) -> Result<Self::Ret, Self::Err> {
+        Ok(Deferred::new(om, cdbase))
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[inline]
+    fn from_openmath_xml(input: &'de str) -> Result<Self, XmlReadError<Self::Err>> {
+        read_xml(input, Options::new())
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[inline]
+    fn from_openmath_xml_with(
+        input: &'de str,
+        options: Options<'_>,
+    ) -> Result<Self, XmlReadError<Self::Err>> {
+        read_xml(input, options)
+    }
+}
+
+macro_rules! tuples {
+    ($($n:literal: ($($t:ident $v:ident $i:literal),+);)*) => {$(
+        impl<'de, $($t: OMDeserializable<'de>),+> TryFrom<Deferred<'de>> for ($($t,)+) {
+            type Error = PositionalError;
+            fn try_from(value: Deferred<'de>) -> Result<Self, Self::Error> {
+                let (_, [$($v),+]) = value.application::<$n>()?;
+                Ok(($($v.argument::<$t>($i)?,)+))
+            }
+        }
+
+        /// Deserializes an application with as many arguments as the tuple has elements,
+        /// ignoring its head; see [`Headed`]
+        impl<'de, $($t: OMDeserializable<'de>),+> OMDeserializable<'de> for ($($t,)+) {
+            type Ret = Deferred<'de>;
+            type Err = PositionalError;
+            #[inline]
+            fn from_openmath(om: OM<'de, Self::Ret>, cdbase: &str) -> Result<Self::Ret, Self::Err> {
+                Ok(Deferred::new(om, cdbase))
+            }
+
+            #[cfg(feature = "xml-read")]
+            #[inline]
+            fn from_openmath_xml(input: &'de str) -> Result<Self, XmlReadError<Self::Err>> {
+                read_xml(input, Options::new())
+            }
+
+            #[cfg(feature = "xml-read")]
+            #[inline]
+            fn from_openmath_xml_with(
+                input: &'de str,
+                options: Options<'_>,
+            ) -> Result<Self, XmlReadError<Self::Err>> {
+                read_xml(input, options)
+            }
+        }
+    )*};
+}
+tuples! {
+    1: (A a 0);
+    2: (A a 0, B b 1);
+    3: (A a 0, B b 1, C c 2);
+    4: (A a 0, B b 1, C c 2, D d 3);
+    5: (A a 0, B b 1, C c 2, D d 3, E e 4);
+    6: (A a 0, B b 1, C c 2, D d 3, E e 4, F f 5);
+}
+
+/** The arguments of an application with any head, each converted into a `T` if possible;
+an argument that does not convert is kept as its index (counting from `0`) and the reason,
+instead of failing as a whole like a [`Vec`] or a tuple would. See [`Lossy`] to only keep
+the arguments that convert.
+
+Only the object not being an application at all is an error
+([`NotAnApplication`](PositionalError::NotAnApplication)). Like for [`Headed`], the whole
+object is kept as a [`Deferred`] until its arguments are converted, each exactly once.
+
+# Examples
+```
+use openmath::{OMDeserializable, de::Partial};
+
+# #[cfg(feature = "xml-read")]
+# {
+let xml = r#"<OMA><OMS cd="list1" name="list"/>
+    <OMF dec="1.5"/><OMSTR>n/a</OMSTR><OMF dec="-2"/>
+</OMA>"#;
+let floats = Partial::<Vec<f64>>::from_openmath_xml(xml).expect("is an application");
+assert_eq!(floats.results, [Ok(1.5), Err((1, "Not a float".to_string())), Ok(-2.0)]);
+let (floats, errors) = floats.split();
+assert_eq!((floats, errors.len()), (vec![1.5, -2.0], 1));
+# }
+```
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Partial<C: IntoIterator> {
+    /// Every argument, in order; converted, or the index of the argument and why it does not
+    /// convert
+    pub results: Vec<Result<C::Item, (usize, String)>>,
+}
+
+impl<T> Partial<Vec<T>> {
+    /// The arguments that convert, and the indices and errors of those that do not
+    #[must_use]
+    pub fn split(self) -> (Vec<T>, Vec<(usize, String)>) {
+        let mut converted = Vec::with_capacity(self.results.len());
+        let mut errors = Vec::new();
+        for result in self.results {
+            match result {
+                Ok(t) => converted.push(t),
+                Err(e) => errors.push(e),
+            }
+        }
+        (converted, errors)
+    }
+}
+
+impl<'de, T: OMDeserializable<'de>> TryFrom<Deferred<'de>> for Partial<Vec<T>> {
+    type Error = PositionalError;
+    fn try_from(value: Deferred<'de>) -> Result<Self, Self::Error> {
+        let OM::OMA { arguments, .. } = *value.om else {
+            return Err(PositionalError::NotAnApplication(value.om.kind()));
+        };
+        let results = arguments
+            .into_iter()
+            .enumerate()
+            .map(|(index, argument)| argument.convert().map_err(|error| (index, error)))
+            .collect();
+        Ok(Self { results })
+    }
+}
+
+/** The arguments of an application with any head that convert into a `T`, and the number
+of those that do not, which are dropped; see [`Partial`] to also keep why they do not
+convert.
+
+# Examples
+```
+use openmath::{OMDeserializable, de::Lossy};
+
+# #[cfg(feature = "xml-read")]
+# {
+let xml = r#"<OMA><OMS cd="list1" name="list"/>
+    <OMI>1</OMI><OMF dec="1.5"/><OMI>-3</OMI><OMSTR>n/a</OMSTR>
+</OMA>"#;
+let ints = Lossy::<Vec<i64>>::from_openmath_xml(xml).expect("is an application");
+assert_eq!((ints.items, ints.dropped), (vec![1, -3], 2));
+# }
+```
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Lossy<C> {
+    /// The arguments that convert, in order
+    pub items: C,
+    /// The number of arguments that do not convert
+    pub dropped: usize,
+}
+
+impl<'de, T: OMDeserializable<'de>> TryFrom<Deferred<'de>> for Lossy<Vec<T>> {
+    type Error = PositionalError;
+    fn try_from(value: Deferred<'de>) -> Result<Self, Self::Error> {
+        let (items, errors) = Partial::<Vec<T>>::try_from(value)?.split();
+        Ok(Self {
+            items,
+            dropped: errors.len(),
+        })
+    }
+}
+
+macro_rules! collections {
+    ($($c:ident),*) => {$(
+        impl<'de, T: OMDeserializable<'de>> OMDeserializable<'de> for $c<Vec<T>> {
+            type Ret = Deferred<'de>;
+            type Err = PositionalError;
+            #[inline]
+            fn from_openmath(om: OM<'de, Self::Ret>, cdbase: &str) -> Result<Self::Ret, Self::Err> {
+                Ok(Deferred::new(om, cdbase))
+            }
+
+            #[cfg(feature = "xml-read")]
+            #[inline]
+            fn from_openmath_xml(input: &'de str) -> Result<Self, XmlReadError<Self::Err>> {
+                read_xml(input, Options::new())
+            }
+
+            #[cfg(feature = "xml-read")]
+            #[inline]
+            fn from_openmath_xml_with(
+                input: &'de str,
+                options: Options<'_>,
+            ) -> Result<Self, XmlReadError<Self::Err>> {
+                read_xml(input, options)
+            }
+        }
+    )*};
+}
+collections!(Partial, Lossy);
+
+#[cfg(all(test, any(feature = "serde", feature = "xml-read")))]
+mod tests {
+    use super::Headed;
+    #[cfg(feature = "xml-read")]
+    use super::{Lossy, Partial, PositionalError};
+    use crate::om_symbols;
+    #[cfg(feature = "xml-read")]
+    use crate::{Int, OMDeserializable};
+
+    om_symbols! {
+        enum Geometry {
+            Point = ("geometry1", "point"),
+            Line = ("geometry1", "line"),
+        }
+    }
+
+    #[cfg(feature = "xml-read")]
+    const POINT: &str = r#"<OMS cd="geometry1" name="point"/>"#;
+
+    #[cfg(feature = "xml-read")]
+    fn error<'de, T: OMDeserializable<'de, Err = PositionalError>>(
+        xml: &'de str,
+    ) -> PositionalError {
+        match T::from_openmath_xml(xml) {
+            Err(crate::de::XmlReadError::Conversion(e)) => e,
+            Err(e) => panic!("unexpected error {e}"),
+            Ok(t) => panic!("unexpectedly converted into {t:?}"),
+        }
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn tuples_xml() {
+        let xml = format!(r#"<OMA>{POINT}<OMF dec="1.5"/><OMI>2</OMI><OMSTR>x</OMSTR></OMA>"#);
+        let (x, y, s) = <(f64, Int, String)>::from_openmath_xml(&xml).expect("converts");
+        assert_eq!((x, y, s.as_str()), (1.5, Int::from(2), "x"));
+        assert_eq!(
+            <(i32,)>::from_openmath_xml(r#"<OMA><OMV name="f"/><OMI>7</OMI></OMA>"#)
+                .expect("converts"),
+            (7,)
+        );
+
+        assert_eq!(
+            error::<(f64, f64)>(&xml),
+            PositionalError::Arity {
+                expected: 2,
+                found: 3
+            }
+        );
+        assert_eq!(
+            error::<(f64, f64, f64)>(&xml),
+            PositionalError::Argument {
+                index: 1,
+                error: "Not a float".to_string()
+            }
+        );
+        assert_eq!(
+            error::<(f64,)>("<OMI>1</OMI>"),
+            PositionalError::NotAnApplication(crate::OMKind::OMI)
+        );
+        // the same through the other entry points
+        assert!(matches!(
+            <(f64, f64)>::from_openmath_xml_with_default_cdbase(&xml, crate::CD_BASE),
+            Err(crate::de::XmlReadError::Conversion(
+                PositionalError::Arity { .. }
+            ))
+        ));
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn headed_xml() {
+        // arguments that are applications themselves, and nested tuples
+        let xml = format!(
+            r#"<OMA>{POINT}<OMA>{POINT}<OMI>1</OMI><OMI>2</OMI></OMA><OMA><OMV name="f"/><OMSTR>x</OMSTR></OMA></OMA>"#
+        );
+        let point =
+            Headed::<Geometry, (Headed<Geometry, (u8, u8)>, (String,))>::from_openmath_xml(&xml)
+                .expect("converts");
+        assert_eq!(point.head, Geometry::Point);
+        assert_eq!(point.arguments.0.arguments, (1, 2));
+        assert_eq!(point.arguments.1.0, "x");
+
+        let line = xml.replacen("point", "line", 1);
+        let line =
+            Headed::<Geometry, ((u8, u8), (String,))>::from_openmath_xml(&line).expect("converts");
+        assert_eq!(line.head, Geometry::Line);
+
+        let unknown = xml.replacen("point", "circle", 1);
+        assert!(matches!(
+            error::<Headed<Geometry, ((u8, u8), (String,))>>(&unknown),
+            PositionalError::Head(e) if e.contains("found http://www.openmath.org/cd?geometry1#circle")
+        ));
+        let PositionalError::Argument { index: 1, error } =
+            error::<Headed<Geometry, ((u8, u8), (u8,))>>(&xml)
+        else {
+            panic!("argument 1 does not convert");
+        };
+        assert!(error.contains("index: 0"), "{error}");
+    }
+
+    /// Arguments of every kind, of which only some are floats
+    #[cfg(feature = "xml-read")]
+    const MIXED: &str = r#"<OMA><OMS cd="list1" name="list"/>
+        <OMF dec="0.5"/>
+        <OMI>2</OMI>
+        <OMSTR>three</OMSTR>
+        <OMF dec="4e0"/>
+        <OMA><OMS cd="arith1" name="plus"/><OMF dec="1"/><OMF dec="2"/></OMA>
+        <OMV name="x"/>
+        <OMF dec="-7.25"/>
+    </OMA>"#;
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn partial_xml() {
+        let floats = Partial::<Vec<f64>>::from_openmath_xml(MIXED).expect("converts");
+        assert_eq!(floats.results.len(), 7);
+        let (converted, errors) = floats.clone().split();
+        assert_eq!(converted, [0.5, 4.0, -7.25]);
+        assert_eq!(
+            errors.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            [1, 2, 4, 5]
+        );
+        assert_eq!(floats.results[2], Err((2, "Not a float".to_string())));
+
+        // the errors of nested conversions are kept, too
+        let tuples = Partial::<Vec<(f64, f64)>>::from_openmath_xml(MIXED).expect("converts");
+        assert_eq!(tuples.results[4], Ok((1.0, 2.0)));
+        assert!(matches!(
+            &tuples.results[0],
+            Err((0, e)) if e.contains("NotAnApplication(OMF)")
+        ));
+
+        let lossy = Lossy::<Vec<f64>>::from_openmath_xml(MIXED).expect("converts");
+        assert_eq!(
+            lossy,
+            Lossy {
+                items: vec![0.5, 4.0, -7.25],
+                dropped: 4
+            }
+        );
+        let strings = Lossy::<Vec<String>>::from_openmath_xml(MIXED).expect("converts");
+        assert_eq!(
+            (strings.items, strings.dropped),
+            (vec!["three".to_string()], 6)
+        );
+        // no arguments are no errors
+        let empty = r#"<OMA><OMS cd="list1" name="list"/></OMA>"#;
+        let empty = Lossy::<Vec<f64>>::from_openmath_xml(empty).expect("converts");
+        assert_eq!((empty.items.len(), empty.dropped), (0, 0));
+
+        assert_eq!(
+            error::<Partial<Vec<f64>>>(r#"<OMF dec="1"/>"#),
+            PositionalError::NotAnApplication(crate::OMKind::OMF)
+        );
+        assert_eq!(
+            error::<Lossy<Vec<f64>>>("<OMSTR>x</OMSTR>"),
+            PositionalError::NotAnApplication(crate::OMKind::OMSTR)
+        );
+    }
+
+    #[cfg(all(feature = "serde", feature = "xml-read", feature = "xml-write"))]
+    #[test]
+    fn partial_serde() {
+        use crate::{OMSerializable, OpenMath, de::OMFromSerde};
+        // the same object as JSON
+        let json = serde_json::to_string(
+            &OpenMath::from_openmath_xml(MIXED)
+                .expect("is valid")
+                .openmath_serde(),
+        )
+        .expect("serializes");
+        let floats = serde_json::from_str::<OMFromSerde<Partial<Vec<f64>>>>(&json)
+            .expect("converts")
+            .into_inner();
+        assert_eq!(
+            floats,
+            Partial::<Vec<f64>>::from_openmath_xml(MIXED).expect("converts")
+        );
+        let lossy = serde_json::from_str::<OMFromSerde<Lossy<Vec<i64>>>>(&json)
+            .expect("converts")
+            .into_inner();
+        assert_eq!((lossy.items, lossy.dropped), (vec![2], 6));
+
+        let e = serde_json::from_str::<OMFromSerde<Partial<Vec<f64>>>>(
+            r#"{ "kind":"OMSTR", "string":"x" }"#,
+        )
+        .map(OMFromSerde::into_inner)
+        .expect_err("is not an application");
+        assert!(e.to_string().contains("NotAnApplication"), "{e}");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn tuples_serde() {
+        use crate::de::OMFromSerde;
+        let json = r#"{ "kind":"OMA", "applicant":{ "kind":"OMS", "cd":"geometry1", "name":"point" },
+            "arguments":[{ "kind":"OMF", "float":1.5 }, { "kind":"OMI", "integer":2 }] }"#;
+        let point = serde_json::from_str::<OMFromSerde<Headed<Geometry, (f64, i64)>>>(json)
+            .expect("converts")
+            .into_inner();
+        assert_eq!((point.head, point.arguments), (Geometry::Point, (1.5, 2)));
+        let e = serde_json::from_str::<OMFromSerde<(f64, f64)>>(json)
+            .map(OMFromSerde::into_inner)
+            .expect_err("is an OMI");
+        assert!(e.to_string().contains("Argument { index: 1"), "{e}");
+    }
+}
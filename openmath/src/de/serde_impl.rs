@@ -18,105 +18,423 @@
 //! let int_value = wrapper.into_inner();
 //! # }
 //! ```
+//!
+//! ## Error locations
+//!
+//! Children are deserialized through the [`MapAccess`](serde::de::MapAccess) and
+//! [`SeqAccess`](serde::de::SeqAccess) of the deserializer, so wrappers that track the path
+//! to an error (like [serde_path_to_error](https://docs.rs/serde_path_to_error)) report it,
+//! e.g. as `object.arguments[1].integer`. The exceptions are values that have to be buffered
+//! because keys they depend on come after them: the children of an object before its
+//! `kind`, and the `attributes` and `object` of an OMATTR before its `cdbase` (which, if
+//! there is none, means until the end of the OMATTR). Wrappers only see the path to the
+//! buffered value, so the error messages say where in it the error occurred instead, e.g.
+//! `... (in object.attributes)`.
 #![allow(clippy::trait_duplication_in_bounds)]
 #![allow(clippy::upper_case_acronyms)]
 #![allow(clippy::type_complexity)]
 
 use serde::{Deserialize, de::DeserializeSeed};
-use serde_cow::{CowBytes, CowStr};
+use serde_cow::CowStr;
 
 use crate::{
     OMDeserializable, OMKind,
-    de::{Args, Attrs, OM, Vars},
+    cdbase::inherit,
+    de::{Args, Attrs, OM, Vars, Visit},
 };
+use either::Either;
 use std::{borrow::Cow, marker::PhantomData};
 
-type Attr<'e, I> = crate::Attr<'e, OMForeign<'e, I>>;
+type Attr<'e, I> = super::OMAttr<'e, <I as OMDeserializable<'e>>::Ret>;
 type OMForeign<'e, I> = crate::OMMaybeForeign<'e, <I as OMDeserializable<'e>>::Ret>;
 
+/// Where in the document an object is, as the keys and indices leading to it from the root
+/// (e.g. `object.arguments[2]`), for error messages; together with the [`State`] of the
+/// deserialization it belongs to, which every seed thereby passes on to its children
+#[derive(Clone, Copy)]
+struct JsonPath<'p> {
+    state: &'p State,
+    step: Step<'p>,
+}
+#[derive(Clone, Copy)]
+enum Step<'p> {
+    Root,
+    Key(&'p JsonPath<'p>, &'static str),
+    Index(&'p JsonPath<'p>, usize),
+}
+impl<'p> JsonPath<'p> {
+    /// The path of the root object
+    const fn root(state: &'p State) -> Self {
+        Self {
+            state,
+            step: Step::Root,
+        }
+    }
+    const fn key(&self, key: &'static str) -> JsonPath<'_> {
+        JsonPath {
+            state: self.state,
+            step: Step::Key(self, key),
+        }
+    }
+    const fn index(&self, index: usize) -> JsonPath<'_> {
+        JsonPath {
+            state: self.state,
+            step: Step::Index(self, index),
+        }
+    }
+}
+impl std::fmt::Display for JsonPath<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.step {
+            Step::Root => Ok(()),
+            Step::Key(parent, key) if matches!(parent.step, Step::Root) => f.write_str(key),
+            Step::Key(parent, key) => write!(f, "{parent}.{key}"),
+            Step::Index(parent, index) => write!(f, "{parent}[{index}]"),
+        }
+    }
+}
+
+/// The error for the `fields` that an object of the given kind at `path` must not have
+fn invalid_keys<E: serde::de::Error>(
+    kind: impl std::fmt::Display,
+    fields: impl std::fmt::Display,
+    path: &JsonPath<'_>,
+) -> E {
+    let replaying = &path.state.replaying;
+    replaying.set((replaying.get().0, true));
+    if matches!(path.step, Step::Root) {
+        E::custom(format_args!("Invalid keys for {kind}: {fields}"))
+    } else {
+        E::custom(format_args!("Invalid keys for {kind} at {path}: {fields}"))
+    }
+}
+
+/// Deserializes a `value` at `path` that was buffered because it came before the keys it
+/// depends on (`kind`, or the `cdbase` of an OMATTR). Path-tracking wrappers around the
+/// deserializer (like [serde_path_to_error](https://docs.rs/serde_path_to_error)) can not see
+/// into buffered values, so errors in them (other than [`invalid_keys`], which have their
+/// own) say where they occurred themselves, as precisely as the innermost buffered value.
+fn replay<'de, S, E>(seed: S, value: serde_value::Value, path: &JsonPath<'_>) -> Result<S::Value, E>
+where
+    S: serde::de::DeserializeSeed<'de>,
+    E: serde::de::Error,
+{
+    let replaying = &path.state.replaying;
+    let (depth, _) = replaying.get();
+    replaying.set((depth + 1, false));
+    let r = seed.deserialize(serde_value::ValueDeserializer::<E>::new(value));
+    let (_, located) = replaying.get();
+    replaying.set((depth, depth > 0 && r.is_err()));
+    r.map_err(|e| {
+        if located {
+            e
+        } else {
+            E::custom(format_args!("{e} (in {path})"))
+        }
+    })
+}
+
 impl<'de, O: OMDeserializable<'de> + 'de> serde::Deserialize<'de> for super::OMObject<'de, O> {
+    #[inline]
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
-        struct Visitor<'de, O: OMDeserializable<'de>>(PhantomData<&'de O>);
-        impl<'de, O: OMDeserializable<'de>> serde::de::Visitor<'de> for Visitor<'de, O> {
-            type Value = super::OMObject<'de, O>;
-            #[inline]
-            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-                formatter.write_str("an OMOBJ struct")
-            }
-            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
-            where
-                A: serde::de::SeqAccess<'de>,
-            {
-                use serde::de::Error;
-                let Some("OMOBJ") = seq.next_element()? else {
-                    return Err(A::Error::custom("missing kind=\"OMOBJ\""));
-                };
-                let _ = seq.next_element::<serde::de::IgnoredAny>()?;
-                let Some(o) = seq.next_element::<OMFromSerde<O>>()? else {
+        OMObjectSeed(
+            super::VersionPolicy::default(),
+            None,
+            Scope::default(),
+            None,
+            PhantomData,
+        )
+        .deserialize(deserializer)
+    }
+}
+
+/// Deserializes an OMOBJ with the given version policy, default cdbase, [`Scope`] and
+/// [`References`](super::References)
+pub struct OMObjectSeed<'de, O: OMDeserializable<'de>>(
+    pub super::VersionPolicy,
+    pub Option<Cow<'de, str>>,
+    pub Scope,
+    pub Option<super::References>,
+    pub PhantomData<&'de O>,
+);
+impl<'de, O: OMDeserializable<'de> + 'de> OMObjectSeed<'de, O> {
+    fn default_cdbase(&self) -> Cow<'de, str> {
+        self.1.clone().unwrap_or(Cow::Borrowed(crate::CD_BASE))
+    }
+    fn check_version<E: serde::de::Error>(&self, version: Option<&str>) -> Result<(), E> {
+        if self.0.check(version) {
+            Ok(())
+        } else {
+            Err(E::custom(format_args!(
+                "unsupported OpenMath version {}",
+                version.unwrap_or_default()
+            )))
+        }
+    }
+}
+impl<'de, O: OMDeserializable<'de> + 'de> serde::de::DeserializeSeed<'de> for OMObjectSeed<'de, O> {
+    type Value = super::OMObject<'de, O>;
+    #[inline]
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let state = State::new(self.2, self.3.clone());
+        deserializer.deserialize_struct(
+            "OMObject",
+            &["kind", "openmath", "cdbase", "object"],
+            OMObjectVisitor(self, &JsonPath::root(&state)),
+        )
+    }
+}
+
+/// Visits the OMOBJ of an [`OMObjectSeed`] at the root of `path`
+struct OMObjectVisitor<'de, 's, O: OMDeserializable<'de>>(OMObjectSeed<'de, O>, &'s JsonPath<'s>);
+impl<'de, O: OMDeserializable<'de> + 'de> serde::de::Visitor<'de> for OMObjectVisitor<'de, '_, O> {
+    type Value = super::OMObject<'de, O>;
+    #[inline]
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("an OMOBJ struct")
+    }
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::SeqAccess<'de>,
+    {
+        use serde::de::Error;
+        let Some("OMOBJ") = seq.next_element()? else {
+            return Err(A::Error::custom("missing kind=\"OMOBJ\""));
+        };
+        let version = seq
+            .next_element::<Option<CowStr<'de>>>()?
+            .flatten()
+            .map(|v| v.0);
+        self.0.check_version(version.as_deref())?;
+        // `["OMOBJ", version, cdbase, object]`, where `cdbase` may be null or omitted
+        let object = match seq.next_element_seed(CdBaseOrObject::<O>(self.1, PhantomData))? {
+            None => return Err(A::Error::custom("missing object")),
+            Some(Either::Right(object)) => object,
+            Some(Either::Left(cdbase)) => {
+                let cdbase = cdbase.map_or_else(|| self.0.default_cdbase(), |c| c.0);
+                let Some(object) =
+                    seq.next_element_seed(OMDeInner::<O>(cdbase, &self.1.index(3), PhantomData))?
+                else {
                     return Err(A::Error::custom("missing object"));
                 };
-                Ok(super::OMObject(o.into_inner(), PhantomData))
+                object.0
             }
-            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
-            where
-                A: serde::de::MapAccess<'de>,
-            {
-                use serde::de::Error;
-
-                #[derive(serde::Deserialize)]
-                #[allow(non_camel_case_types)]
-                enum Fields {
-                    kind,
-                    openmath,
-                    cdbase,
-                    object,
-                }
-                let mut obj = None;
-                let mut cdbase = None;
-                while let Some(key) = map.next_key()? {
-                    match key {
-                        Fields::kind => {
-                            if map.next_value::<&str>()? != "OMOBJ" {
-                                return Err(A::Error::custom("invalid kind"));
-                            }
-                        }
-                        Fields::openmath => {
-                            map.next_value::<serde::de::IgnoredAny>()?;
-                        }
-                        Fields::cdbase => {
-                            cdbase = Some(map.next_value()?);
-                        }
-                        Fields::object if cdbase.is_some() => {
-                            let cdbase = unsafe { cdbase.take().unwrap_unchecked() };
-                            obj = Some(
-                                map.next_value_seed(OMDeInner::<O>(cdbase, PhantomData))?.0.try_into().map_err(|e| A::Error::custom(format!(
-                                    "OpenMath object does not represent a valid instance of {}: {e:?}",
-                                    std::any::type_name::<O>(),
-                                )))?
-                            );
-                        }
-                        Fields::object => {
-                            obj = Some(map.next_value::<OMFromSerde<O>>()?.0);
-                        }
+        };
+        while seq.next_element::<serde::de::IgnoredAny>()?.is_some() {}
+        let object = object.try_into().map_err(|e| {
+            A::Error::custom(format!(
+                "OpenMath object does not represent a valid instance of {}: {e:?}",
+                std::any::type_name::<O>(),
+            ))
+        })?;
+        Ok(super::OMObject {
+            object,
+            version,
+            id: None,
+            default_cdbase: self.0.1,
+        })
+    }
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        use serde::de::Error;
+
+        #[derive(serde::Deserialize)]
+        #[serde(field_identifier)]
+        #[allow(non_camel_case_types)]
+        enum Fields {
+            kind,
+            openmath,
+            cdbase,
+            object,
+        }
+        let mut obj = None;
+        let mut cdbase = None;
+        let mut version = None;
+        while let Some(key) = map.next_key()? {
+            match key {
+                Fields::kind => {
+                    if map.next_value::<&str>()? != "OMOBJ" {
+                        return Err(A::Error::custom("invalid kind"));
                     }
                 }
-                let Some(obj) = obj else {
-                    return Err(A::Error::custom("missing object field"));
-                };
-                Ok(super::OMObject(obj, PhantomData))
+                Fields::openmath => {
+                    let v = map.next_value::<CowStr<'de>>()?.0;
+                    self.0.check_version(Some(&v))?;
+                    version = Some(v);
+                }
+                Fields::cdbase => {
+                    cdbase = map.next_value()?;
+                }
+                Fields::object => {
+                    let cdbase = cdbase.take().unwrap_or_else(|| self.0.default_cdbase());
+                    obj = Some(
+                        map.next_value_seed(OMDeInner::<O>(
+                            cdbase,
+                            &self.1.key("object"),
+                            PhantomData,
+                        ))?
+                        .0
+                        .try_into()
+                        .map_err(|e| {
+                            A::Error::custom(format!(
+                                "OpenMath object does not represent a valid instance of {}: {e:?}",
+                                std::any::type_name::<O>(),
+                            ))
+                        })?,
+                    );
+                }
             }
         }
-        deserializer.deserialize_struct(
-            "OMObject",
-            &["kind", "openmath", "cdbase", "object"],
-            Visitor(PhantomData),
-        )
+        let Some(object) = obj else {
+            return Err(A::Error::custom("missing object field"));
+        };
+        Ok(super::OMObject {
+            object,
+            version,
+            id: None,
+            default_cdbase: self.0.1,
+        })
+    }
+}
+
+/// The third element of the array form of an OMOBJ: either the (optional) cdbase, or
+/// already the object if the cdbase is omitted
+struct CdBaseOrObject<'de, 's, O: OMDeserializable<'de>>(&'s JsonPath<'s>, PhantomData<&'de O>);
+impl<'de, O: OMDeserializable<'de> + 'de> serde::de::DeserializeSeed<'de>
+    for CdBaseOrObject<'de, '_, O>
+{
+    type Value = Either<Option<CowStr<'de>>, O::Ret>;
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+}
+impl<'de, O: OMDeserializable<'de> + 'de> serde::de::Visitor<'de> for CdBaseOrObject<'de, '_, O> {
+    type Value = Either<Option<CowStr<'de>>, O::Ret>;
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a cdbase or an OpenMath object")
+    }
+    fn visit_borrowed_str<E: serde::de::Error>(self, v: &'de str) -> Result<Self::Value, E> {
+        Ok(Either::Left(Some(CowStr(Cow::Borrowed(v)))))
+    }
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(Either::Left(Some(CowStr(Cow::Owned(v.to_string())))))
+    }
+    fn visit_string<E: serde::de::Error>(self, v: String) -> Result<Self::Value, E> {
+        Ok(Either::Left(Some(CowStr(Cow::Owned(v)))))
+    }
+    fn visit_none<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+        Ok(Either::Left(None))
+    }
+    fn visit_unit<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+        Ok(Either::Left(None))
+    }
+    fn visit_some<D: serde::Deserializer<'de>>(self, d: D) -> Result<Self::Value, D::Error> {
+        d.deserialize_any(self)
+    }
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+        OMVisitor::<O, false>(Cow::Borrowed(crate::CD_BASE), &self.0.index(2), PhantomData)
+            .visit_seq(seq)
+            .map(Either::Right)
+    }
+    fn visit_map<A: serde::de::MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+        OMVisitor::<O, false>(Cow::Borrowed(crate::CD_BASE), &self.0.index(2), PhantomData)
+            .visit_map(map)
+            .map(Either::Right)
+    }
+}
+
+/// The [`Options`](super::Options) that apply to every node, which the seeds pass down in the
+/// [`State`] of a deserialization: the [attribute filter](super::Options::attr_filter), the checks on names, the
+/// [float policy](super::Options::float_policy) and the treatment of
+/// [duplicate keys](super::Options::duplicate_keys)
+#[derive(Clone, Copy, Default)]
+pub struct Scope {
+    attr_filter: Option<super::AttrFilter>,
+    names: super::NameRules,
+    budget: super::Spending,
+    floats: super::FloatPolicy,
+    duplicate_keys: super::DuplicateKeys,
+}
+impl Scope {
+    pub(super) fn new(options: &super::Options<'_>) -> Self {
+        Self {
+            attr_filter: options.attr_filter,
+            names: super::NameRules::new(options),
+            // serde deserializers can not be interrupted, so only nodes are counted
+            budget: super::Spending::new(options.budget.map(|b| super::Budget {
+                deadline: None,
+                ..b
+            })),
+            floats: options.float_policy,
+            duplicate_keys: options.duplicate_keys,
+        }
     }
 }
 
+/// The state of one deserialization that all of its nodes share; passed down with their
+/// [`JsonPath`]s
+struct State {
+    scope: std::cell::Cell<Scope>,
+    references: std::cell::RefCell<Option<super::References>>,
+    /// How many [buffered](replay) values are being deserialized, and whether an error in
+    /// the innermost of them already says where it occurred
+    replaying: std::cell::Cell<(usize, bool)>,
+}
+impl State {
+    const fn new(scope: Scope, references: Option<super::References>) -> Self {
+        Self {
+            scope: std::cell::Cell::new(scope),
+            references: std::cell::RefCell::new(references),
+            replaying: std::cell::Cell::new((0, false)),
+        }
+    }
+}
+
+/// Why the [`Scope`] of a deserialization rejects an object
+#[derive(Debug, thiserror::Error)]
+enum Rejected {
+    #[error(transparent)]
+    Name(#[from] super::NameError),
+    #[error("budget exceeded after {0} nodes")]
+    Budget(usize),
+    #[error(transparent)]
+    Reference(#[from] super::ReferenceError),
+}
+
+/// [`super::convert`], after checking `om` against the [`Scope`] of `state`
+fn convert<'de, OMD: OMDeserializable<'de>>(
+    state: &State,
+    om: OM<'de, OMD::Ret>,
+    cdbase: &str,
+) -> Result<OMD::Ret, Either<Rejected, OMD::Err>> {
+    let mut scope = state.scope.get();
+    scope
+        .names
+        .check_om(&om)
+        .map_err(|e| Either::Left(e.into()))?;
+    if scope.budget.budget.is_some() {
+        let spent = scope.budget.node();
+        state.scope.set(scope);
+        spent.map_err(|(nodes, _)| Either::Left(Rejected::Budget(nodes)))?;
+    }
+    let converted =
+        super::References::convert::<OMD>(state.references.borrow_mut().as_mut(), om, cdbase);
+    converted
+        .map_err(|e| Either::Left(e.into()))?
+        .map_err(Either::Right)
+}
+
 /// Wrapper type for deserializing <span style="font-variant:small-caps;">OpenMath</span> objects via serde.
 ///
 /// This type wraps any `OMDeserializable` type and provides a `serde::Deserialize`
@@ -166,6 +484,83 @@ impl<OMD> OMFromSerde<OMD> {
     pub fn into_inner(self) -> OMD {
         self.0
     }
+
+    /// Returns a [`DeserializeSeed`](serde::de::DeserializeSeed) that deserializes like the
+    /// [`Deserialize`](serde::Deserialize)-implementation, but resolves symbols without a
+    /// cdbase against `cdbase` instead of [`CD_BASE`](crate::CD_BASE).
+    ///
+    /// # Examples
+    /// ```rust
+    /// use openmath::{OpenMath, de::OMFromSerde};
+    /// use serde::de::DeserializeSeed;
+    ///
+    /// let json = r#"{ "kind": "OMS", "cd": "units", "name": "metre" }"#;
+    /// let om = OMFromSerde::<OpenMath>::with_default_cdbase("http://example.org/cd")
+    ///     .deserialize(&mut serde_json::Deserializer::from_str(json))
+    ///     .expect("is valid")
+    ///     .into_inner();
+    /// let OpenMath::OMS { cdbase, .. } = om else { unreachable!() };
+    /// assert_eq!(cdbase.as_deref(), Some("http://example.org/cd"));
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn with_default_cdbase<'de, 's>(
+        cdbase: &'s str,
+    ) -> impl serde::de::DeserializeSeed<'de, Value = Self> + use<'de, 's, OMD>
+    where
+        OMD: OMDeserializable<'de> + 'de,
+    {
+        DefaultCdBase(cdbase, Scope::default(), None, PhantomData)
+    }
+
+    /// Returns a [`DeserializeSeed`](serde::de::DeserializeSeed) that deserializes with the
+    /// given [`Options`](super::Options); like [`with_default_cdbase`](Self::with_default_cdbase)
+    /// if one is set, otherwise like the [`Deserialize`](serde::Deserialize)-implementation.
+    /// The [`base`](super::Options::base) only applies to XML and is ignored here.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use openmath::{OpenMath, de::{OMFromSerde, Options}};
+    /// use serde::de::DeserializeSeed;
+    ///
+    /// let json = r#"{ "kind": "OMS", "cd": "units", "name": "metre" }"#;
+    /// let options = Options::new().with_default_cdbase("http://example.org/cd");
+    /// let om = OMFromSerde::<OpenMath>::with_options(options)
+    ///     .deserialize(&mut serde_json::Deserializer::from_str(json))
+    ///     .expect("is valid")
+    ///     .into_inner();
+    /// let OpenMath::OMS { cdbase, .. } = om else { unreachable!() };
+    /// assert_eq!(cdbase.as_deref(), Some("http://example.org/cd"));
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn with_options<'de, 's>(
+        options: super::Options<'s>,
+    ) -> impl serde::de::DeserializeSeed<'de, Value = Self> + use<'de, 's, OMD>
+    where
+        OMD: OMDeserializable<'de> + 'de,
+    {
+        DefaultCdBase(
+            options.default_cdbase.unwrap_or(crate::CD_BASE),
+            Scope::new(&options),
+            super::References::new(&options),
+            PhantomData,
+        )
+    }
+
+    fn convert<'de, E: serde::de::Error>(r: OMD::Ret) -> Result<Self, E>
+    where
+        OMD: OMDeserializable<'de>,
+    {
+        r.try_into()
+            .map_err(|e| {
+                E::custom(format!(
+                    "OpenMath object does not represent a valid instance of {}: {e:?}",
+                    std::any::type_name::<OMD>()
+                ))
+            })
+            .map(OMFromSerde)
+    }
 }
 
 impl<'de, OMD> serde::Deserialize<'de> for OMFromSerde<OMD>
@@ -176,17 +571,24 @@ where
     where
         D: serde::Deserializer<'de>,
     {
-        use serde::de::Error;
-        OMDe::<'de, OMD>::deserialize(deserializer)?
-            .0
-            .try_into()
-            .map_err(|e| {
-                D::Error::custom(format!(
-                    "OpenMath object does not represent a valid instance of {}: {e:?}",
-                    std::any::type_name::<OMD>()
-                ))
-            })
-            .map(OMFromSerde)
+        Self::convert(OMDe::<'de, OMD>::deserialize(deserializer)?.0)
+    }
+}
+
+struct DefaultCdBase<'s, OMD>(&'s str, Scope, Option<super::References>, PhantomData<OMD>);
+impl<'de, OMD> serde::de::DeserializeSeed<'de> for DefaultCdBase<'_, OMD>
+where
+    OMD: OMDeserializable<'de> + 'de,
+{
+    type Value = OMFromSerde<OMD>;
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let state = State::new(self.1, self.2);
+        let r = OMDeInner::<OMD>(Cow::Borrowed(self.0), &JsonPath::root(&state), PhantomData)
+            .deserialize(deserializer)?;
+        OMFromSerde::convert(r.0)
     }
 }
 
@@ -202,11 +604,17 @@ where
     where
         D: serde::Deserializer<'de>,
     {
-        OMDeInner(Cow::Borrowed(crate::CD_BASE), PhantomData).deserialize(deserializer)
+        let state = State::new(Scope::default(), None);
+        OMDeInner(
+            Cow::Borrowed(crate::CD_BASE),
+            &JsonPath::root(&state),
+            PhantomData,
+        )
+        .deserialize(deserializer)
     }
 }
 
-struct OMDeInner<'de, 's, OMD>(Cow<'s, str>, PhantomData<(&'de (), OMD)>)
+struct OMDeInner<'de, 's, OMD>(Cow<'s, str>, &'s JsonPath<'s>, PhantomData<(&'de (), OMD)>)
 where
     OMD: OMDeserializable<'de>;
 
@@ -223,12 +631,44 @@ where
             .deserialize_struct(
                 "OMObject",
                 &ALL_FIELDS,
-                OMVisitor::<OMD, false>(self.0, PhantomData),
+                OMVisitor::<OMD, false>(self.0, self.1, PhantomData),
             )
             .map(|r| OMDe(r, PhantomData))
     }
 }
 
+/// The `index`th child of an object of the given kind, in the scope of the cdbase; skipped
+/// if [`before_child`](OMDeserializable::before_child) says so
+struct Child<'de, 's, OMD>(
+    &'s str,
+    OMKind,
+    usize,
+    &'s JsonPath<'s>,
+    PhantomData<(&'de (), OMD)>,
+)
+where
+    OMD: OMDeserializable<'de>;
+
+impl<'de, OMD> serde::de::DeserializeSeed<'de> for Child<'de, '_, OMD>
+where
+    OMD: OMDeserializable<'de> + 'de,
+{
+    type Value = OMDe<'de, OMD>;
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        match OMD::before_child(self.1, self.2, self.0) {
+            Visit::Descend => OMDeInner::<OMD>(Cow::Borrowed(self.0), self.3, PhantomData)
+                .deserialize(deserializer),
+            Visit::Skip(r) => {
+                serde::de::IgnoredAny::deserialize(deserializer)?;
+                Ok(OMDe(r, PhantomData))
+            }
+        }
+    }
+}
+
 // -------------------------------------------------------------------------------------
 
 macro_rules! all_fields {
@@ -261,7 +701,7 @@ macro_rules! all_fields {
                 }
             }
         }
-        static ALL_FIELDS: [&str;21] = [$(stringify!($name)),*];
+        static ALL_FIELDS: [&str;22] = [$(stringify!($name)),*];
     }
 }
 
@@ -286,7 +726,8 @@ all_fields! {
     binder,
     variables,
     object,
-    attributes
+    attributes,
+    href
 }
 
 #[derive(Default)]
@@ -297,7 +738,7 @@ struct FieldState<'de> {
     hexadecimal: Option<CowStr<'de>>,
     float: Option<f64>,
     string: Option<CowStr<'de>>,
-    bytes: Option<CowBytes<'de>>,
+    bytes: Option<SeqBytes<'de>>,
     base64: Option<CowStr<'de>>,
     name: Option<CowStr<'de>>,
     cdbase: Option<CowStr<'de>>,
@@ -311,10 +752,12 @@ struct FieldState<'de> {
     binder: Option<serde_value::Value>,
     object: Option<serde_value::Value>,
     attributes: Option<serde_value::Value>,
+    href: Option<CowStr<'de>>,
 }
 
 struct OMVisitor<'de, 's, OMD: OMDeserializable<'de>, const ALLOW_FOREIGN: bool>(
     Cow<'s, str>,
+    &'s JsonPath<'s>,
     PhantomData<(&'de (), OMD)>,
 );
 impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
@@ -334,7 +777,7 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
             return Err(A::Error::custom("missing value in OMI"));
         };
         while seq.next_element::<serde::de::IgnoredAny>()?.is_some() {}
-        OMD::from_openmath(OM::OMI { int, attrs }, &self.0).map_err(A::Error::custom)
+        convert::<OMD>(self.1.state, OM::OMI { int, attrs }, &self.0).map_err(A::Error::custom)
     }
 
     fn visit_seq_omf<A>(
@@ -347,11 +790,20 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
         A: serde::de::SeqAccess<'de>,
     {
         use serde::de::Error;
-        let Some(float) = seq.next_element::<f64>()? else {
+        let Some(SeqFloat(float)) = seq.next_element()? else {
             return Err(A::Error::custom("missing value in OMF"));
         };
         while seq.next_element::<serde::de::IgnoredAny>()?.is_some() {}
-        OMD::from_openmath(OM::OMF { float, attrs }, &self.0).map_err(A::Error::custom)
+        convert::<OMD>(
+            self.1.state,
+            OM::OMF {
+                float,
+                lexical: None,
+                attrs,
+            },
+            &self.0,
+        )
+        .map_err(A::Error::custom)
     }
 
     fn visit_seq_omstr<A>(
@@ -369,7 +821,7 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
         };
         let string = v.0;
         while seq.next_element::<serde::de::IgnoredAny>()?.is_some() {}
-        OMD::from_openmath(OM::OMSTR { string, attrs }, &self.0).map_err(A::Error::custom)
+        convert::<OMD>(self.1.state, OM::OMSTR { string, attrs }, &self.0).map_err(A::Error::custom)
     }
 
     fn visit_seq_omb<A>(
@@ -382,12 +834,11 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
         A: serde::de::SeqAccess<'de>,
     {
         use serde::de::Error;
-        let Some(v) = seq.next_element::<CowBytes<'de>>()? else {
+        let Some(SeqBytes(bytes)) = seq.next_element()? else {
             return Err(A::Error::custom("missing value in OMB"));
         };
-        let bytes = v.0;
         while seq.next_element::<serde::de::IgnoredAny>()?.is_some() {}
-        OMD::from_openmath(OM::OMB { bytes, attrs }, &self.0).map_err(A::Error::custom)
+        convert::<OMD>(self.1.state, OM::OMB { bytes, attrs }, &self.0).map_err(A::Error::custom)
     }
 
     fn visit_seq_omv<A>(
@@ -405,7 +856,25 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
         };
         let name = v.0;
         while seq.next_element::<serde::de::IgnoredAny>()?.is_some() {}
-        OMD::from_openmath(OM::OMV { name, attrs }, &self.0).map_err(A::Error::custom)
+        convert::<OMD>(self.1.state, OM::OMV { name, attrs }, &self.0).map_err(A::Error::custom)
+    }
+
+    fn visit_seq_omr<A>(
+        self,
+        _id: Option<CowStr<'de>>,
+        attrs: Attrs<Attr<'de, OMD>>,
+        mut seq: A,
+    ) -> Result<OMD::Ret, A::Error>
+    where
+        A: serde::de::SeqAccess<'de>,
+    {
+        use serde::de::Error;
+        let Some(v) = seq.next_element::<CowStr<'de>>()? else {
+            return Err(A::Error::custom("missing href in OMR"));
+        };
+        let href = v.0;
+        while seq.next_element::<serde::de::IgnoredAny>()?.is_some() {}
+        convert::<OMD>(self.1.state, OM::OMR { href, attrs }, &self.0).map_err(A::Error::custom)
     }
 
     fn visit_seq_oms<A>(
@@ -418,7 +887,7 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
         A: serde::de::SeqAccess<'de>,
     {
         use serde::de::Error;
-        let Some(cdbase) = seq.next_element::<Option<&'de str>>()? else {
+        let Some(own_cdbase) = seq.next_element::<Option<&'de str>>()? else {
             return Err(A::Error::custom("missing cd in OMS"));
         };
         let Some(cd) = seq.next_element::<CowStr<'de>>()? else {
@@ -429,12 +898,13 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
             return Err(A::Error::custom("missing name in OMS"));
         };
         let name = name.0;
-        let cdbase: &str = cdbase.unwrap_or(&self.0);
-        //cdbase.as_ref().map_or::<&str, _>(&self.0, |s| s.as_ref());
+        let cdbase: &str = inherit(own_cdbase, &self.0);
 
         while seq.next_element::<serde::de::IgnoredAny>()?.is_some() {}
-        OMD::from_openmath(
+        convert::<OMD>(
+            self.1.state,
             OM::OMS {
+                cdbase: own_cdbase.map(Cow::Borrowed),
                 cd: cd_name,
                 name,
                 attrs,
@@ -458,7 +928,7 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
         let Some(cdbase) = seq.next_element::<Option<&'de str>>()? else {
             return Err(A::Error::custom("missing error in OME"));
         };
-        let cdbase_i = cdbase.unwrap_or(&self.0);
+        let cdbase_i = inherit(cdbase, &self.0);
 
         let Some(OMS {
             cdbase,
@@ -470,12 +940,13 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
             return Err(A::Error::custom("missing error in OME"));
         };
         let arguments = seq
-            .next_element_seed(OMForeignSeq::<OMD>(cdbase_i, PhantomData))?
+            .next_element_seed(OMForeignSeq::<OMD>(cdbase_i, &self.1.index(4), PhantomData))?
             .unwrap_or_default();
         //cdbase.as_ref().map_or::<&str, _>(&self.0, |s| s.as_ref());
 
         while seq.next_element::<serde::de::IgnoredAny>()?.is_some() {}
-        OMD::from_openmath(
+        convert::<OMD>(
+            self.1.state,
             OM::OME {
                 cdbase: cdbase.map(|e| e.0),
                 cd: cd_name.0,
@@ -502,10 +973,13 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
         let Some(cdbase) = seq.next_element::<Option<&'de str>>()? else {
             return Err(A::Error::custom("missing applicant in OMA"));
         };
-        let cdbase = cdbase.unwrap_or(&self.0);
+        let cdbase = inherit(cdbase, &self.0);
 
-        let Some(head) = seq.next_element_seed(OMDeInner::<'de, '_, OMD>(
-            Cow::Borrowed(cdbase),
+        let Some(head) = seq.next_element_seed(Child::<OMD>(
+            cdbase,
+            OMKind::OMA,
+            0,
+            &self.1.index(3),
             PhantomData,
         ))?
         else {
@@ -513,11 +987,12 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
         };
 
         let args = seq
-            .next_element_seed(OMSeq::<OMD>(cdbase, PhantomData))?
+            .next_element_seed(OMSeq::<OMD>(cdbase, &self.1.index(4), PhantomData))?
             .unwrap_or_default();
 
         while seq.next_element::<serde::de::IgnoredAny>()?.is_some() {}
-        OMD::from_openmath(
+        convert::<OMD>(
+            self.1.state,
             OM::OMA {
                 applicant: head.0,
                 arguments: args,
@@ -542,22 +1017,30 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
         let Some(cdbase) = seq.next_element::<Option<&'de str>>()? else {
             return Err(A::Error::custom("missing applicant in OMBIND"));
         };
-        let cdbase = cdbase.unwrap_or(&self.0);
+        let cdbase = inherit(cdbase, &self.0);
 
-        let Some(head) = seq.next_element_seed(OMDeInner::<'de, '_, OMD>(
-            Cow::Borrowed(cdbase),
+        let Some(head) = seq.next_element_seed(Child::<OMD>(
+            cdbase,
+            OMKind::OMBIND,
+            0,
+            &self.1.index(3),
             PhantomData,
         ))?
         else {
             return Err(A::Error::custom("missing binder in OMBIND"));
         };
 
-        let Some(context) = seq.next_element_seed(OMVarSeq::<OMD>(cdbase, PhantomData))? else {
+        let Some(context) =
+            seq.next_element_seed(OMVarSeq::<OMD>(cdbase, &self.1.index(4), PhantomData))?
+        else {
             return Err(A::Error::custom("missing variables in OMBIND"));
         };
 
-        let Some(body) = seq.next_element_seed(OMDeInner::<'de, '_, OMD>(
-            Cow::Borrowed(cdbase),
+        let Some(body) = seq.next_element_seed(Child::<OMD>(
+            cdbase,
+            OMKind::OMBIND,
+            1,
+            &self.1.index(5),
             PhantomData,
         ))?
         else {
@@ -565,7 +1048,8 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
         };
 
         while seq.next_element::<serde::de::IgnoredAny>()?.is_some() {}
-        OMD::from_openmath(
+        convert::<OMD>(
+            self.1.state,
             OM::OMBIND {
                 binder: head.0,
                 variables: context,
@@ -590,17 +1074,23 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
         let Some(cdbase) = seq.next_element::<Option<&'de str>>()? else {
             return Err(A::Error::custom("missing attributions in OMATTR"));
         };
-        let cdbase = cdbase.unwrap_or(&self.0);
+        let cdbase = inherit(cdbase, &self.0);
 
-        let Some(()) = seq.next_element_seed(OMAttrSeq::<OMD>(&self.0, &mut attrs))? else {
+        let Some(()) =
+            seq.next_element_seed(OMAttrSeq::<OMD>(cdbase, &mut attrs, &self.1.index(3)))?
+        else {
             return Err(A::Error::custom("missing attributions in OMATTR"));
         };
 
-        let Some(object) =
-            seq.next_element_seed(OMWithAttrs::<'de, '_, OMD>(Cow::Borrowed(cdbase), attrs))?
+        let Some(object) = seq.next_element_seed(OMWithAttrs::<'de, '_, OMD>(
+            Cow::Borrowed(cdbase),
+            attrs,
+            &self.1.index(4),
+        ))?
         else {
             return Err(A::Error::custom("missing object in OMATTR"));
         };
+        while seq.next_element::<serde::de::IgnoredAny>()?.is_some() {}
         Ok(object.0)
     }
 
@@ -641,9 +1131,22 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
     {
         use serde::de::Error;
 
-        let mut had_attrs = if let Some(attributes) = attributes {
-            OMAttrSeq::<OMD>(cdbase.as_ref().map_or(&self.0, |e| &*e.0), &mut attrs)
-                .deserialize(serde_value::ValueDeserializer::new(attributes))?;
+        // the keys of the attributes depend on the cdbase, which may come after them; so
+        // until the cdbase is known, attributes (and the object) are buffered
+        let mut attributes = attributes;
+        let mut skipped = Vec::new();
+        let attributes_path = self.1.key("attributes");
+        let object_path = self.1.key("object");
+        let mut had_attrs = if let Some(a) = attributes.take_if(|_| cdbase.is_some()) {
+            replay(
+                OMAttrSeq::<OMD>(
+                    inherit(cdbase.as_ref().map(|e| &*e.0), &self.0),
+                    &mut attrs,
+                    &attributes_path,
+                ),
+                a,
+                &attributes_path,
+            )?;
             true
         } else {
             false
@@ -651,41 +1154,83 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
 
         while let Some(key) = map.next_key()? {
             match key {
-                AllFields::cdbase => cdbase = Some(map.next_value()?),
-                AllFields::attributes => {
+                AllFields::cdbase if cdbase.is_some() => {
+                    return Err(A::Error::custom("duplicate field \"cdbase\" in OMATTR"));
+                }
+                AllFields::cdbase => {
+                    cdbase = map.next_value()?;
+                    check_skipped(
+                        &skipped,
+                        inherit(cdbase.as_ref().map(|e| &*e.0), &self.0),
+                        self.1.state.scope.get().attr_filter,
+                    )?;
+                    if let Some(a) = attributes.take() {
+                        replay(
+                            OMAttrSeq::<OMD>(
+                                inherit(cdbase.as_ref().map(|e| &*e.0), &self.0),
+                                &mut attrs,
+                                &attributes_path,
+                            ),
+                            a,
+                            &attributes_path,
+                        )?;
+                        had_attrs = true;
+                    }
+                }
+                AllFields::attributes if cdbase.is_some() => {
                     map.next_value_seed(OMAttrSeq::<OMD>(
-                        cdbase.as_ref().map_or(&self.0, |e| &*e.0),
+                        inherit(cdbase.as_ref().map(|e| &*e.0), &self.0),
                         &mut attrs,
+                        &attributes_path,
                     ))?;
                     had_attrs = true;
                 }
+                AllFields::attributes if self.1.state.scope.get().attr_filter.is_some() => {
+                    attributes = Some(map.next_value_seed(AttrBuffer(
+                        &self.0,
+                        self.1.state.scope.get().attr_filter,
+                        &mut skipped,
+                    ))?);
+                }
+                AllFields::attributes => attributes = map.next_value()?,
                 AllFields::object if had_attrs => {
                     return map
                         .next_value_seed(OMWithAttrs::<OMD>(
-                            Cow::Borrowed(cdbase.as_ref().map_or(&self.0, |e| &*e.0)),
+                            Cow::Borrowed(inherit(cdbase.as_ref().map(|e| &*e.0), &self.0)),
                             attrs,
+                            &object_path,
                         ))
                         .map(|e| e.0);
                 }
-                AllFields::object => object = Some(map.next_value()?),
+                AllFields::object => object = map.next_value()?,
+                AllFields::id => {
+                    map.next_value::<serde::de::IgnoredAny>()?;
+                }
                 k => {
-                    return Err(A::Error::custom(format_args!(
-                        "Invalid keys for OMATTR: {k}"
-                    )));
+                    return Err(invalid_keys("OMATTR", k, self.1));
                 }
             }
         }
 
-        if let Some(object) = object {
-            OMWithAttrs::<OMD>(
-                Cow::Borrowed(cdbase.as_ref().map_or(&self.0, |e| &*e.0)),
-                attrs,
-            )
-            .deserialize(serde_value::ValueDeserializer::new(object))
-            .map(|e| e.0)
-        } else {
-            Err(A::Error::custom("Missing object for OMATTR"))
+        let cdbase = inherit(cdbase.as_ref().map(|e| &*e.0), &self.0);
+        if let Some(a) = attributes {
+            replay(
+                OMAttrSeq::<OMD>(cdbase, &mut attrs, &attributes_path),
+                a,
+                &attributes_path,
+            )?;
         }
+        object.map_or_else(
+            || Err(A::Error::custom("Missing object for OMATTR")),
+            |object| {
+                replay(
+                    OMWithAttrs::<OMD>(Cow::Borrowed(cdbase), attrs, &object_path),
+                    object,
+                    &object_path,
+                )
+                .map(|e| e.0)
+            },
+        )
     }
 
     fn visit_map_omi<A>(
@@ -703,10 +1248,13 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
         use serde::de::Error;
         while let Some(key) = map.next_key()? {
             match key {
-                AllFields::integer => integer = Some(map.next_value()?),
-                AllFields::decimal => decimal = Some(map.next_value()?),
-                AllFields::hexadecimal => hexadecimal = Some(map.next_value()?),
-                k => return Err(A::Error::custom(format_args!("Invalid keys for OMI: {k}"))),
+                AllFields::integer => integer = map.next_value()?,
+                AllFields::decimal => decimal = map.next_value()?,
+                AllFields::hexadecimal => hexadecimal = map.next_value()?,
+                AllFields::id => {
+                    map.next_value::<serde::de::IgnoredAny>()?;
+                }
+                k => return Err(invalid_keys("OMI", k, self.1)),
             }
         }
         if let Some(int) = integer {
@@ -715,7 +1263,8 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
                     "OMI can not have more than one of the fields `integer`, `decimal`, `hexadecimal`",
                 ));
             }
-            return OMD::from_openmath(OM::OMI { int, attrs }, &self.0).map_err(A::Error::custom);
+            return convert::<OMD>(self.1.state, OM::OMI { int, attrs }, &self.0)
+                .map_err(A::Error::custom);
         }
         if let Some(d) = decimal {
             if hexadecimal.is_some() {
@@ -723,7 +1272,8 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
                     "OMI can not have more than one of the fields `integer`, `decimal`, `hexadecimal`",
                 ));
             }
-            return OMD::from_openmath(
+            return convert::<OMD>(
+                self.1.state,
                 OM::OMI {
                     int: crate::Int::try_from(d.0)
                         .map_err(|()| A::Error::custom("invalid decimal number"))?,
@@ -734,10 +1284,19 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
             .map_err(A::Error::custom);
         }
         if let Some(h) = hexadecimal {
-            return Err(A::Error::custom(format_args!(
-                "Not yet implemented: hexadecimal in OMI: {}",
-                h.0
-            )));
+            // the field says it is hexadecimal, so the `x` is optional
+            let (sign, digits) = h.0.strip_prefix('-').map_or(("", &*h.0), |d| ("-", d));
+            let digits = digits.strip_prefix('x').unwrap_or(digits);
+            return convert::<OMD>(
+                self.1.state,
+                OM::OMI {
+                    int: crate::Int::from_hex(&format!("{sign}x{digits}"))
+                        .ok_or_else(|| A::Error::custom("invalid hexadecimal number"))?,
+                    attrs,
+                },
+                &self.0,
+            )
+            .map_err(A::Error::custom);
         }
         Err(A::Error::custom("Missing value for OMI"))
     }
@@ -757,10 +1316,13 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
         use serde::de::Error;
         while let Some(key) = map.next_key()? {
             match key {
-                AllFields::float => float = Some(map.next_value()?),
-                AllFields::decimal => decimal = Some(map.next_value()?),
-                AllFields::hexadecimal => hexadecimal = Some(map.next_value()?),
-                k => return Err(A::Error::custom(format_args!("Invalid keys for OMF: {k}"))),
+                AllFields::float => float = map.next_value::<Option<SeqFloat>>()?.map(|f| f.0),
+                AllFields::decimal => decimal = map.next_value()?,
+                AllFields::hexadecimal => hexadecimal = map.next_value()?,
+                AllFields::id => {
+                    map.next_value::<serde::de::IgnoredAny>()?;
+                }
+                k => return Err(invalid_keys("OMF", k, self.1)),
             }
         }
         if let Some(float) = float {
@@ -769,7 +1331,16 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
                     "OMF can not have more than one of the fields `float`, `decimal`, `hexadecimal`",
                 ));
             }
-            return OMD::from_openmath(OM::OMF { float, attrs }, &self.0).map_err(A::Error::custom);
+            return convert::<OMD>(
+                self.1.state,
+                OM::OMF {
+                    float,
+                    lexical: None,
+                    attrs,
+                },
+                &self.0,
+            )
+            .map_err(A::Error::custom);
         }
         if let Some(d) = decimal {
             if hexadecimal.is_some() {
@@ -777,11 +1348,22 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
                     "OMI can not have more than one of the fields `integer`, `decimal`, `hexadecimal`",
                 ));
             }
-            return OMD::from_openmath(
+            let float: f64 =
+                d.0.parse()
+                    .map_err(|e| A::Error::custom(format_args!("invalid decimal number: {e}")))?;
+            let lexical = self
+                .1
+                .state
+                .scope
+                .get()
+                .floats
+                .apply(d.0, float)
+                .map_err(|d| A::Error::custom(format_args!("inexact decimal number: {d}")))?;
+            return convert::<OMD>(
+                self.1.state,
                 OM::OMF {
-                    float: d.0.parse().map_err(|e| {
-                        A::Error::custom(format_args!("invalid decimal number: {e}"))
-                    })?,
+                    float,
+                    lexical,
                     attrs,
                 },
                 &self.0,
@@ -810,16 +1392,17 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
         use serde::de::Error;
         while let Some(key) = map.next_key()? {
             match key {
-                AllFields::string => string = Some(map.next_value()?),
+                AllFields::string => string = map.next_value()?,
+                AllFields::id => {
+                    map.next_value::<serde::de::IgnoredAny>()?;
+                }
                 k => {
-                    return Err(A::Error::custom(format_args!(
-                        "Invalid keys for OMSTR: {k}"
-                    )));
+                    return Err(invalid_keys("OMSTR", k, self.1));
                 }
             }
         }
         if let Some(s) = string {
-            return OMD::from_openmath(OM::OMSTR { string: s.0, attrs }, &self.0)
+            return convert::<OMD>(self.1.state, OM::OMSTR { string: s.0, attrs }, &self.0)
                 .map_err(A::Error::custom);
         }
         Err(A::Error::custom("Missing value for OMSTR"))
@@ -828,7 +1411,7 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
     fn visit_map_omb<A>(
         self,
         _id: Option<&str>,
-        mut bytes: Option<CowBytes<'de>>,
+        mut bytes: Option<SeqBytes<'de>>,
         mut base64: Option<CowStr<'de>>,
         mut map: A,
         attrs: Attrs<Attr<'de, OMD>>,
@@ -836,14 +1419,16 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
     where
         A: serde::de::MapAccess<'de>,
     {
-        use crate::base64::Base64Decodable;
         use serde::de::Error;
         while let Some(key) = map.next_key()? {
             match key {
-                AllFields::bytes => bytes = Some(map.next_value()?),
-                AllFields::base64 => base64 = Some(map.next_value()?),
+                AllFields::bytes => bytes = map.next_value()?,
+                AllFields::base64 => base64 = map.next_value()?,
+                AllFields::id => {
+                    map.next_value::<serde::de::IgnoredAny>()?;
+                }
                 k => {
-                    return Err(A::Error::custom(format_args!("Invalid keys for OMB: {k}")));
+                    return Err(invalid_keys("OMB", k, self.1));
                 }
             }
         }
@@ -855,20 +1440,13 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
             }
             bytes.0
         } else if let Some(base64) = base64 {
-            base64
-                .0
-                .as_bytes()
-                .iter()
-                .copied()
-                .decode_base64()
-                .flat()
-                .collect::<Result<Vec<_>, _>>()
+            crate::base64::decode_slice(base64.0.as_bytes())
                 .map_err(A::Error::custom)?
                 .into()
         } else {
             return Err(A::Error::custom("Missing value for OMB"));
         };
-        OMD::from_openmath(OM::OMB { bytes, attrs }, &self.0).map_err(A::Error::custom)
+        convert::<OMD>(self.1.state, OM::OMB { bytes, attrs }, &self.0).map_err(A::Error::custom)
     }
 
     fn visit_map_omv<A>(
@@ -884,14 +1462,18 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
         use serde::de::Error;
         while let Some(key) = map.next_key()? {
             match key {
-                AllFields::name => name = Some(map.next_value()?),
+                AllFields::name => name = map.next_value()?,
+                AllFields::id => {
+                    map.next_value::<serde::de::IgnoredAny>()?;
+                }
                 k => {
-                    return Err(A::Error::custom(format_args!("Invalid keys for OMV: {k}")));
+                    return Err(invalid_keys("OMV", k, self.1));
                 }
             }
         }
         if let Some(name) = name {
-            return OMD::from_openmath(
+            return convert::<OMD>(
+                self.1.state,
                 OM::OMV {
                     name: name.0,
                     attrs,
@@ -903,6 +1485,42 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
         Err(A::Error::custom("Missing value for OMV"))
     }
 
+    fn visit_map_omr<A>(
+        self,
+        _id: Option<&str>,
+        mut href: Option<CowStr<'de>>,
+        mut map: A,
+        attrs: Attrs<Attr<'de, OMD>>,
+    ) -> Result<OMD::Ret, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        use serde::de::Error;
+        while let Some(key) = map.next_key()? {
+            match key {
+                AllFields::href => href = map.next_value()?,
+                AllFields::id => {
+                    map.next_value::<serde::de::IgnoredAny>()?;
+                }
+                k => {
+                    return Err(invalid_keys("OMR", k, self.1));
+                }
+            }
+        }
+        if let Some(href) = href {
+            return convert::<OMD>(
+                self.1.state,
+                OM::OMR {
+                    href: href.0,
+                    attrs,
+                },
+                &self.0,
+            )
+            .map_err(A::Error::custom);
+        }
+        Err(A::Error::custom("Missing href for OMR"))
+    }
+
     fn visit_map_oms<A>(
         self,
         _id: Option<&str>,
@@ -918,11 +1536,14 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
         use serde::de::Error;
         while let Some(key) = map.next_key()? {
             match key {
-                AllFields::cdbase => cdbase = Some(map.next_value()?),
-                AllFields::cd => cd = Some(map.next_value()?),
-                AllFields::name => name = Some(map.next_value()?),
+                AllFields::cdbase => cdbase = map.next_value()?,
+                AllFields::cd => cd = map.next_value()?,
+                AllFields::name => name = map.next_value()?,
+                AllFields::id => {
+                    map.next_value::<serde::de::IgnoredAny>()?;
+                }
                 k => {
-                    return Err(A::Error::custom(format_args!("Invalid keys for OMS: {k}")));
+                    return Err(invalid_keys("OMS", k, self.1));
                 }
             }
         }
@@ -932,10 +1553,12 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
         let Some(name) = name else {
             return Err(A::Error::custom("Missing name for OMS"));
         };
-        let cdbase = cdbase.map(|e| e.0);
-        let cdbase = cdbase.as_deref().unwrap_or(&self.0);
-        OMD::from_openmath(
+        let own_cdbase = cdbase.map(|e| e.0);
+        let cdbase = inherit(own_cdbase.as_deref(), &self.0);
+        convert::<OMD>(
+            self.1.state,
             OM::OMS {
+                cdbase: own_cdbase.clone(),
                 cd: cd.0,
                 name: name.0,
                 attrs,
@@ -959,48 +1582,59 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
     {
         use serde::de::Error;
         let mut error = if let Some(error) = error {
-            Some(OMS::deserialize(serde_value::ValueDeserializer::new(
-                error,
-            ))?)
+            Some(replay(PhantomData::<OMS>, error, &self.1.key("error"))?)
         } else {
             None
         };
         let mut arguments = if let Some(arguments) = arguments {
-            Some(
-                OMForeignSeq::<OMD>(cdbase.as_ref().map_or(&self.0, |e| &*e.0), PhantomData)
-                    .deserialize(serde_value::ValueDeserializer::new(arguments))?,
-            )
+            Some(replay(
+                OMForeignSeq::<OMD>(
+                    inherit(cdbase.as_ref().map(|e| &*e.0), &self.0),
+                    &self.1.key("arguments"),
+                    PhantomData,
+                ),
+                arguments,
+                &self.1.key("arguments"),
+            )?)
         } else {
             None
         };
         while let Some(key) = map.next_key()? {
             match key {
-                AllFields::cdbase => cdbase = Some(map.next_value()?),
-                AllFields::error => error = Some(map.next_value()?),
+                AllFields::cdbase => cdbase = map.next_value()?,
+                AllFields::error => error = map.next_value()?,
                 AllFields::arguments => {
                     arguments = Some(map.next_value_seed(OMForeignSeq::<OMD>(
-                        cdbase.as_ref().map_or(&self.0, |e| &*e.0),
+                        inherit(cdbase.as_ref().map(|e| &*e.0), &self.0),
+                        &self.1.key("arguments"),
                         PhantomData,
                     ))?);
                 }
+                AllFields::id => {
+                    map.next_value::<serde::de::IgnoredAny>()?;
+                }
                 k => {
-                    return Err(A::Error::custom(format_args!("Invalid keys for OME: {k}")));
+                    return Err(invalid_keys("OME", k, self.1));
                 }
             }
         }
         if let Some(OMS {
-            cdbase, cd, name, ..
+            cdbase: own_cdbase,
+            cd,
+            name,
+            ..
         }) = error
         {
-            return OMD::from_openmath(
+            return convert::<OMD>(
+                self.1.state,
                 OM::OME {
-                    cdbase: cdbase.map(|e| e.0),
+                    cdbase: own_cdbase.map(|e| e.0),
                     cd: cd.0,
                     name: name.0,
                     arguments: arguments.unwrap_or_default(),
                     attrs,
                 },
-                &self.0,
+                inherit(cdbase.as_ref().map(|e| &*e.0), &self.0),
             )
             .map_err(A::Error::custom);
         }
@@ -1021,53 +1655,70 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
     {
         use serde::de::Error;
         let mut applicant = if let Some(applicant) = applicant {
-            Some(
-                OMDeInner::<OMD>(
-                    Cow::Borrowed(cdbase.as_ref().map_or(&self.0, |e| &*e.0)),
+            Some(replay(
+                Child::<OMD>(
+                    inherit(cdbase.as_ref().map(|e| &*e.0), &self.0),
+                    OMKind::OMA,
+                    0,
+                    &self.1.key("applicant"),
                     PhantomData,
-                )
-                .deserialize(serde_value::ValueDeserializer::new(applicant))?,
-            )
+                ),
+                applicant,
+                &self.1.key("applicant"),
+            )?)
         } else {
             None
         };
         let mut arguments = if let Some(arguments) = arguments {
-            Some(
-                OMSeq::<OMD>(cdbase.as_ref().map_or(&self.0, |e| &*e.0), PhantomData)
-                    .deserialize(serde_value::ValueDeserializer::new(arguments))?,
-            )
+            Some(replay(
+                OMSeq::<OMD>(
+                    inherit(cdbase.as_ref().map(|e| &*e.0), &self.0),
+                    &self.1.key("arguments"),
+                    PhantomData,
+                ),
+                arguments,
+                &self.1.key("arguments"),
+            )?)
         } else {
             None
         };
         while let Some(key) = map.next_key()? {
             match key {
-                AllFields::cdbase => cdbase = Some(map.next_value()?),
+                AllFields::cdbase => cdbase = map.next_value()?,
                 AllFields::applicant => {
-                    applicant = Some(map.next_value_seed(OMDeInner(
-                        Cow::Borrowed(cdbase.as_ref().map_or(&self.0, |e| &*e.0)),
+                    applicant = Some(map.next_value_seed(Child::<OMD>(
+                        inherit(cdbase.as_ref().map(|e| &*e.0), &self.0),
+                        OMKind::OMA,
+                        0,
+                        &self.1.key("applicant"),
                         PhantomData,
                     ))?);
                 }
                 AllFields::arguments => {
                     arguments = Some(map.next_value_seed(OMSeq::<OMD>(
-                        cdbase.as_ref().map_or(&self.0, |e| &*e.0),
+                        inherit(cdbase.as_ref().map(|e| &*e.0), &self.0),
+                        &self.1.key("arguments"),
                         PhantomData,
                     ))?);
                 }
+                AllFields::id => {
+                    map.next_value::<serde::de::IgnoredAny>()?;
+                }
                 k => {
-                    return Err(A::Error::custom(format_args!("Invalid keys for OMA: {k}")));
+                    return Err(invalid_keys("OMA", k, self.1));
                 }
             }
         }
         let cdbase = cdbase.map(|e| e.0);
         if let Some(head) = applicant {
-            return OMD::from_openmath(
+            return convert::<OMD>(
+                self.1.state,
                 OM::OMA {
                     applicant: head.0,
                     arguments: arguments.unwrap_or_default(),
                     attrs,
                 },
-                cdbase.as_deref().unwrap_or(&self.0),
+                inherit(cdbase.as_deref(), &self.0),
             )
             .map_err(A::Error::custom);
         }
@@ -1090,61 +1741,82 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
     {
         use serde::de::Error;
         let mut binder = if let Some(binder) = binder {
-            Some(
-                OMDeInner::<OMD>(
-                    Cow::Borrowed(cdbase.as_ref().map_or(&self.0, |e| &*e.0)),
+            Some(replay(
+                Child::<OMD>(
+                    inherit(cdbase.as_ref().map(|e| &*e.0), &self.0),
+                    OMKind::OMBIND,
+                    0,
+                    &self.1.key("binder"),
                     PhantomData,
-                )
-                .deserialize(serde_value::ValueDeserializer::new(binder))?,
-            )
+                ),
+                binder,
+                &self.1.key("binder"),
+            )?)
         } else {
             None
         };
         let mut object = if let Some(object) = object {
-            Some(
-                OMDeInner::<OMD>(
-                    Cow::Borrowed(cdbase.as_ref().map_or(&self.0, |e| &*e.0)),
+            Some(replay(
+                Child::<OMD>(
+                    inherit(cdbase.as_ref().map(|e| &*e.0), &self.0),
+                    OMKind::OMBIND,
+                    1,
+                    &self.1.key("object"),
                     PhantomData,
-                )
-                .deserialize(serde_value::ValueDeserializer::new(object))?,
-            )
+                ),
+                object,
+                &self.1.key("object"),
+            )?)
         } else {
             None
         };
 
         let mut variables = if let Some(variables) = variables {
-            Some(
-                OMVarSeq::<OMD>(cdbase.as_ref().map_or(&self.0, |e| &*e.0), PhantomData)
-                    .deserialize(serde_value::ValueDeserializer::new(variables))?,
-            )
+            Some(replay(
+                OMVarSeq::<OMD>(
+                    inherit(cdbase.as_ref().map(|e| &*e.0), &self.0),
+                    &self.1.key("variables"),
+                    PhantomData,
+                ),
+                variables,
+                &self.1.key("variables"),
+            )?)
         } else {
             None
         };
         while let Some(key) = map.next_key()? {
             match key {
-                AllFields::cdbase => cdbase = Some(map.next_value()?),
+                AllFields::cdbase => cdbase = map.next_value()?,
                 AllFields::binder => {
-                    binder = Some(map.next_value_seed(OMDeInner(
-                        Cow::Borrowed(cdbase.as_ref().map_or(&self.0, |e| &*e.0)),
+                    binder = Some(map.next_value_seed(Child::<OMD>(
+                        inherit(cdbase.as_ref().map(|e| &*e.0), &self.0),
+                        OMKind::OMBIND,
+                        0,
+                        &self.1.key("binder"),
                         PhantomData,
                     ))?);
                 }
                 AllFields::object => {
-                    object = Some(map.next_value_seed(OMDeInner(
-                        Cow::Borrowed(cdbase.as_ref().map_or(&self.0, |e| &*e.0)),
+                    object = Some(map.next_value_seed(Child::<OMD>(
+                        inherit(cdbase.as_ref().map(|e| &*e.0), &self.0),
+                        OMKind::OMBIND,
+                        1,
+                        &self.1.key("object"),
                         PhantomData,
                     ))?);
                 }
                 AllFields::variables => {
                     variables = Some(map.next_value_seed(OMVarSeq::<OMD>(
-                        cdbase.as_ref().map_or(&self.0, |e| &*e.0),
+                        inherit(cdbase.as_ref().map(|e| &*e.0), &self.0),
+                        &self.1.key("variables"),
                         PhantomData,
                     ))?);
                 }
+                AllFields::id => {
+                    map.next_value::<serde::de::IgnoredAny>()?;
+                }
                 k => {
-                    return Err(A::Error::custom(format_args!(
-                        "Invalid keys for OMBIND: {k}"
-                    )));
+                    return Err(invalid_keys("OMBIND", k, self.1));
                 }
             }
         }
@@ -1155,17 +1827,17 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
         let Some(object) = object else {
             return Err(A::Error::custom("Missing object for OMBIND"));
         };
-        let Some(variables) = variables else {
-            return Err(A::Error::custom("Missing variables for OMBIND"));
-        };
-        OMD::from_openmath(
+        // some producers omit the key for zero-variable bindings
+        let variables = variables.unwrap_or_default();
+        convert::<OMD>(
+            self.1.state,
             OM::OMBIND {
                 binder: binder.0,
                 variables,
                 object: object.0,
                 attrs,
             },
-            cdbase.as_deref().unwrap_or(&self.0),
+            inherit(cdbase.as_deref(), &self.0),
         )
         .map_err(A::Error::custom)
     }
@@ -1175,6 +1847,7 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
         mut encoding: Option<CowStr<'de>>,
         mut foreign: Option<CowStr<'de>>,
         mut map: A,
+        path: &JsonPath<'_>,
     ) -> Result<OMForeign<'de, OMD>, A::Error>
     where
         A: serde::de::MapAccess<'de>,
@@ -1182,12 +1855,13 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
         use serde::de::Error;
         while let Some(key) = map.next_key()? {
             match key {
-                AllFields::encoding => encoding = Some(map.next_value()?),
-                AllFields::foreign => foreign = Some(map.next_value()?),
+                AllFields::encoding => encoding = map.next_value()?,
+                AllFields::foreign => foreign = map.next_value()?,
+                AllFields::id => {
+                    map.next_value::<serde::de::IgnoredAny>()?;
+                }
                 k => {
-                    return Err(A::Error::custom(format_args!(
-                        "Invalid keys for OMFOREIGN: {k}"
-                    )));
+                    return Err(invalid_keys("OMFOREIGN", k, path));
                 }
             }
         }
@@ -1212,6 +1886,8 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
         A: serde::de::SeqAccess<'de>,
     {
         use serde::de::Error;
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("element", %kind, cdbase = &*self.0).entered();
         let id = seq
             .next_element::<Option<CowStr<'de>>>()?
             .unwrap_or_default();
@@ -1221,13 +1897,13 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
             OMKind::OMSTR => self.visit_seq_omstr(id, attrs, seq),
             OMKind::OMB => self.visit_seq_omb(id, attrs, seq),
             OMKind::OMV => self.visit_seq_omv(id, attrs, seq),
+            OMKind::OMR => self.visit_seq_omr(id, attrs, seq),
             OMKind::OMS => self.visit_seq_oms(id, attrs, seq),
             OMKind::OME => self.visit_seq_ome(id, attrs, seq),
             OMKind::OMA => self.visit_seq_oma(id, attrs, seq),
             OMKind::OMBIND => self.visit_seq_ombind(id, attrs, seq),
             OMKind::OMATTR => self.visit_seq_omattr(id, attrs, seq),
             OMKind::OMFOREIGN => Err(A::Error::custom("OMFOREIGN is not allowed as an OMObject")),
-            OMKind::OMR => Err(A::Error::custom("OMR not yet supported")),
         }
     }
 
@@ -1240,26 +1916,29 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
         while let Some(key) = map.next_key()? {
             match key {
                 AllFields::kind => return Ok((map.next_value()?, state)),
-                AllFields::id => state.id = Some(map.next_value()?),
-                AllFields::cdbase => state.cdbase = Some(map.next_value()?),
-                AllFields::integer => state.integer = Some(map.next_value()?),
-                AllFields::decimal => state.decimal = Some(map.next_value()?),
-                AllFields::hexadecimal => state.hexadecimal = Some(map.next_value()?),
-                AllFields::float => state.float = Some(map.next_value()?),
-                AllFields::string => state.string = Some(map.next_value()?),
-                AllFields::bytes => state.bytes = Some(map.next_value()?),
-                AllFields::base64 => state.base64 = Some(map.next_value()?),
-                AllFields::name => state.name = Some(map.next_value()?),
-                AllFields::cd => state.cd = Some(map.next_value()?),
-                AllFields::encoding => state.encoding = Some(map.next_value()?),
-                AllFields::foreign => state.foreign = Some(map.next_value()?),
-                AllFields::error => state.error = Some(map.next_value()?),
-                AllFields::arguments => state.arguments = Some(map.next_value()?),
-                AllFields::applicant => state.applicant = Some(map.next_value()?),
-                AllFields::binder => state.binder = Some(map.next_value()?),
-                AllFields::variables => state.variables = Some(map.next_value()?),
-                AllFields::object => state.object = Some(map.next_value()?),
-                AllFields::attributes => state.attributes = Some(map.next_value()?),
+                AllFields::id => state.id = map.next_value()?,
+                AllFields::cdbase => state.cdbase = map.next_value()?,
+                AllFields::integer => state.integer = map.next_value()?,
+                AllFields::decimal => state.decimal = map.next_value()?,
+                AllFields::hexadecimal => state.hexadecimal = map.next_value()?,
+                AllFields::float => {
+                    state.float = map.next_value::<Option<SeqFloat>>()?.map(|f| f.0);
+                }
+                AllFields::string => state.string = map.next_value()?,
+                AllFields::bytes => state.bytes = map.next_value()?,
+                AllFields::base64 => state.base64 = map.next_value()?,
+                AllFields::name => state.name = map.next_value()?,
+                AllFields::cd => state.cd = map.next_value()?,
+                AllFields::encoding => state.encoding = map.next_value()?,
+                AllFields::foreign => state.foreign = map.next_value()?,
+                AllFields::error => state.error = map.next_value()?,
+                AllFields::arguments => state.arguments = map.next_value()?,
+                AllFields::applicant => state.applicant = map.next_value()?,
+                AllFields::binder => state.binder = map.next_value()?,
+                AllFields::variables => state.variables = map.next_value()?,
+                AllFields::object => state.object = map.next_value()?,
+                AllFields::attributes => state.attributes = map.next_value()?,
+                AllFields::href => state.href = map.next_value()?,
                 AllFields::__ignore => {
                     map.next_value::<serde::de::IgnoredAny>()?;
                 }
@@ -1281,6 +1960,8 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
         A: serde::de::MapAccess<'de>,
     {
         use serde::de::Error;
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("element", %kind, cdbase = &*self.0).entered();
 
         macro_rules! ass {
                 ($is:ident != $($id:ident),*) => {{
@@ -1289,7 +1970,7 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
                         if state.$id.is_some() { invalid_fields.push(stringify!($id));}
                     )*
                     if !invalid_fields.is_empty() {
-                        return Err(A::Error::custom(format_args!("Invalid keys for {}: {invalid_fields:?}",stringify!($is),)))
+                        return Err(invalid_keys(stringify!($is), format_args!("{invalid_fields:?}"), self.1))
                     }
                 }}
             }
@@ -1311,7 +1992,8 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
                     arguments,
                     applicant,
                     binder,
-                    variables
+                    variables,
+                    href
                 );
                 self.visit_map_omattr(
                     state.id.as_ref().map(|e| &*e.0),
@@ -1338,7 +2020,8 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
                     binder,
                     variables,
                     object,
-                    attributes
+                    attributes,
+                    href
                 );
                 self.visit_map_omi(
                     state.id.as_ref().map(|e| &*e.0),
@@ -1365,7 +2048,8 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
                     binder,
                     variables,
                     object,
-                    attributes
+                    attributes,
+                    href
                 );
                 self.visit_map_omf(
                     state.id.as_ref().map(|e| &*e.0),
@@ -1394,7 +2078,8 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
                     binder,
                     variables,
                     object,
-                    attributes
+                    attributes,
+                    href
                 );
                 self.visit_map_omstr(state.id.as_ref().map(|e| &*e.0), state.string, map, attrs)
             }
@@ -1415,7 +2100,8 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
                     binder,
                     variables,
                     object,
-                    attributes
+                    attributes,
+                    href
                 );
                 self.visit_map_omb(
                     state.id.as_ref().map(|e| &*e.0),
@@ -1443,10 +2129,34 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
                     binder,
                     variables,
                     object,
-                    attributes
+                    attributes,
+                    href
                 );
                 self.visit_map_omv(state.id.as_ref().map(|e| &*e.0), state.name, map, attrs)
             }
+            OMKind::OMR => {
+                ass!(
+                    OMR != integer,
+                    string,
+                    float,
+                    decimal,
+                    hexadecimal,
+                    bytes,
+                    base64,
+                    name,
+                    cd,
+                    encoding,
+                    foreign,
+                    error,
+                    arguments,
+                    applicant,
+                    binder,
+                    variables,
+                    object,
+                    attributes
+                );
+                self.visit_map_omr(state.id.as_ref().map(|e| &*e.0), state.href, map, attrs)
+            }
             OMKind::OMS => {
                 ass!(
                     OMS != integer,
@@ -1464,7 +2174,8 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
                     binder,
                     variables,
                     object,
-                    attributes
+                    attributes,
+                    href
                 );
                 self.visit_map_oms(
                     state.id.as_ref().map(|e| &*e.0),
@@ -1492,7 +2203,8 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
                     binder,
                     variables,
                     object,
-                    attributes
+                    attributes,
+                    href
                 );
                 self.visit_map_ome(
                     state.id.as_ref().map(|e| &*e.0),
@@ -1520,7 +2232,8 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
                     binder,
                     variables,
                     object,
-                    attributes
+                    attributes,
+                    href
                 );
                 self.visit_map_oma(
                     state.id.as_ref().map(|e| &*e.0),
@@ -1547,7 +2260,8 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
                     error,
                     arguments,
                     applicant,
-                    attributes
+                    attributes,
+                    href
                 );
                 self.visit_map_ombind(
                     state.id.as_ref().map(|e| &*e.0),
@@ -1560,7 +2274,6 @@ impl<'de, OMD: OMDeserializable<'de> + 'de, const ALLOW_FOREIGN: bool>
                 )
             }
             OMKind::OMFOREIGN => Err(A::Error::custom("OMFOREIGN is not allowed as an OMObject")),
-            OMKind::OMR => Err(A::Error::custom("OMR not yet supported")),
         }
     }
 }
@@ -1622,7 +2335,6 @@ impl<'de, OMD: OMDeserializable<'de> + 'de> serde::de::Visitor<'de>
     where
         A: serde::de::MapAccess<'de>,
     {
-        use serde::de::Error;
         let (kind, state) = Self::map_state(&mut map)?;
         if kind == OMKind::OMFOREIGN {
             macro_rules! ass {
@@ -1632,7 +2344,7 @@ impl<'de, OMD: OMDeserializable<'de> + 'de> serde::de::Visitor<'de>
                             if state.$id.is_some() { invalid_fields.push(stringify!($id));}
                         )*
                         if !invalid_fields.is_empty() {
-                            return Err(A::Error::custom(format_args!("Invalid keys for {}: {invalid_fields:?}",stringify!($is),)))
+                            return Err(invalid_keys(stringify!($is), format_args!("{invalid_fields:?}"), self.1))
                         }
                     }}
                 }
@@ -1652,13 +2364,15 @@ impl<'de, OMD: OMDeserializable<'de> + 'de> serde::de::Visitor<'de>
                 binder,
                 variables,
                 object,
-                attributes
+                attributes,
+                href
             );
             return Self::visit_map_omforeign(
                 state.id.as_ref().map(|e| &*e.0),
                 state.encoding,
                 state.foreign,
                 map,
+                self.1,
             );
         }
         self.om_map(kind, state, map, Attrs::new())
@@ -1716,6 +2430,131 @@ impl serde::de::Visitor<'_> for AllFieldsVisitor {
 
 // ------------------------------------------------------------------------------------------
 
+/// The bytes of an [`OMB`](OMKind::OMB); like [`CowBytes`](serde_cow::CowBytes), but also
+/// accepting a sequence of integers in `0..=255`, which is how self-describing formats
+/// without a native byte string type (e.g. JSON) serialize bytes.
+struct SeqBytes<'de>(Cow<'de, [u8]>);
+impl<'de> serde::Deserialize<'de> for SeqBytes<'de> {
+    #[inline]
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_bytes(SeqBytesVisitor)
+    }
+}
+struct SeqBytesVisitor;
+impl<'de> serde::de::Visitor<'de> for SeqBytesVisitor {
+    type Value = SeqBytes<'de>;
+    #[inline]
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a byte string or a sequence of bytes")
+    }
+    #[inline]
+    fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(SeqBytes(Cow::Borrowed(v)))
+    }
+    #[inline]
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(SeqBytes(Cow::Owned(v.to_vec())))
+    }
+    #[inline]
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(SeqBytes(Cow::Owned(v)))
+    }
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::SeqAccess<'de>,
+    {
+        use serde::de::Error;
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+        while let Some(b) = seq.next_element::<i64>()? {
+            let b = u8::try_from(b).map_err(|_| {
+                A::Error::custom(format_args!(
+                    "byte {b} at index {} of OMB is not in 0..=255",
+                    bytes.len()
+                ))
+            })?;
+            bytes.push(b);
+        }
+        Ok(SeqBytes(Cow::Owned(bytes)))
+    }
+}
+
+/// The value of an [`OMF`](OMKind::OMF); either a number or, in compact form for values not
+/// representable as JSON numbers (`NaN`, `inf`, `-inf`), a decimal string. Also accepts the
+/// numbers of `serde_json` with the `arbitrary_precision` feature, even after buffering.
+struct SeqFloat(f64);
+impl<'de> serde::Deserialize<'de> for SeqFloat {
+    #[inline]
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(SeqFloatVisitor)
+    }
+}
+struct SeqFloatVisitor;
+impl<'de> serde::de::Visitor<'de> for SeqFloatVisitor {
+    type Value = SeqFloat;
+    #[inline]
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a number or a decimal string")
+    }
+    #[inline]
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(SeqFloat(v))
+    }
+    #[inline]
+    #[allow(clippy::cast_precision_loss)]
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(SeqFloat(v as f64))
+    }
+    #[inline]
+    #[allow(clippy::cast_precision_loss)]
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(SeqFloat(v as f64))
+    }
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        v.parse()
+            .map(SeqFloat)
+            .map_err(|e| E::custom(format_args!("invalid decimal number: {e}")))
+    }
+    /// numbers from `serde_json` with the `arbitrary_precision` feature
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        use serde::de::Error;
+        const TOKEN: &str = "$serde_json::private::Number";
+        match map.next_entry::<String, String>()? {
+            Some((key, value)) if key == TOKEN => self.visit_str(&value),
+            _ => Err(A::Error::custom("expected a number or a decimal string")),
+        }
+    }
+}
+
 #[derive(serde::Deserialize)]
 #[serde(bound = "'s: 'de,'de:'s")]
 struct OMS<'s> {
@@ -1731,7 +2570,7 @@ struct OMS<'s> {
     name: CowStr<'s>,
 }
 
-struct OMSeq<'de, 's, OMD>(&'s str, PhantomData<(&'de (), OMD)>)
+struct OMSeq<'de, 's, OMD>(&'s str, &'s JsonPath<'s>, PhantomData<(&'de (), OMD)>)
 //()
 where
     OMD: OMDeserializable<'de>;
@@ -1777,16 +2616,26 @@ where
         A: serde::de::SeqAccess<'de>,
     {
         let mut vec = smallvec::SmallVec::new();
-        while let Some(e) =
-            seq.next_element_seed(OMDeInner::<OMD>(Cow::Borrowed(self.0), PhantomData))?
-        {
+        loop {
+            #[cfg(feature = "tracing")]
+            let _span = tracing::trace_span!("argument", index = vec.len()).entered();
+            let index = vec.len() + 1;
+            let Some(e) = seq.next_element_seed(Child::<OMD>(
+                self.0,
+                OMKind::OMA,
+                index,
+                &self.1.index(index - 1),
+                PhantomData,
+            ))?
+            else {
+                return Ok(vec);
+            };
             vec.push(e.0);
         }
-        Ok(vec)
     }
 }
 
-struct OMForeignSeq<'de, 's, OMD>(&'s str, PhantomData<(&'de (), OMD)>)
+struct OMForeignSeq<'de, 's, OMD>(&'s str, &'s JsonPath<'s>, PhantomData<(&'de (), OMD)>)
 //()
 where
     OMD: OMDeserializable<'de>;
@@ -1832,14 +2681,28 @@ where
         A: serde::de::SeqAccess<'de>,
     {
         let mut vec = Vec::new();
-        while let Some(e) = seq.next_element_seed(OMDeForeign::<OMD>(self.0, PhantomData))? {
+        loop {
+            #[cfg(feature = "tracing")]
+            let _span = tracing::trace_span!("argument", index = vec.len()).entered();
+            let e = match OMD::before_child(OMKind::OME, vec.len(), self.0) {
+                Visit::Descend => seq.next_element_seed(OMDeForeign::<OMD>(
+                    self.0,
+                    &self.1.index(vec.len()),
+                    PhantomData,
+                ))?,
+                Visit::Skip(r) => seq
+                    .next_element::<serde::de::IgnoredAny>()?
+                    .map(|_| crate::OMMaybeForeign::OM(r)),
+            };
+            let Some(e) = e else {
+                return Ok(vec);
+            };
             vec.push(e);
         }
-        Ok(vec)
     }
 }
 
-struct OMDeForeign<'de, 's, OMD>(&'s str, PhantomData<(&'de (), OMD)>)
+struct OMDeForeign<'de, 's, OMD>(&'s str, &'s JsonPath<'s>, PhantomData<(&'de (), OMD)>)
 where
     OMD: OMDeserializable<'de>;
 
@@ -1856,12 +2719,12 @@ where
         deserializer.deserialize_struct(
             "OMObject",
             &ALL_FIELDS,
-            OMVisitor::<OMD, true>(Cow::Borrowed(self.0), PhantomData),
+            OMVisitor::<OMD, true>(Cow::Borrowed(self.0), self.1, PhantomData),
         )
     }
 }
 
-struct OMWithAttrs<'de, 's, OMD>(Cow<'s, str>, Attrs<Attr<'de, OMD>>)
+struct OMWithAttrs<'de, 's, OMD>(Cow<'s, str>, Attrs<Attr<'de, OMD>>, &'s JsonPath<'s>)
 where
     OMD: OMDeserializable<'de>;
 
@@ -1899,7 +2762,7 @@ where
         let Some(kind) = seq.next_element::<OMKind>()? else {
             return Err(A::Error::custom("missing kind in OpenMath object"));
         };
-        OMVisitor::<'de, '_, OMD, false>(self.0, PhantomData).seq_om(seq, kind, self.1)
+        OMVisitor::<'de, '_, OMD, false>(self.0, self.2, PhantomData).seq_om(seq, kind, self.1)
     }
 
     fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
@@ -1907,18 +2770,20 @@ where
         A: serde::de::MapAccess<'de>,
     {
         let (kind, state) = OMVisitor::<'de, '_, OMD, false>::map_state(&mut map)?;
-        OMVisitor::<'de, '_, OMD, false>(self.0, PhantomData).om_map(kind, state, map, self.1)
+        OMVisitor::<'de, '_, OMD, false>(self.0, self.2, PhantomData)
+            .om_map(kind, state, map, self.1)
     }
 }
 
-struct OMAttrV<'de, 's, OMD>(&'s str, PhantomData<&'de OMD>)
+struct OMAttrV<'de, 's, OMD>(&'s str, &'s JsonPath<'s>, PhantomData<&'de OMD>)
 where
     OMD: OMDeserializable<'de>;
 impl<'de, OMD> serde::de::DeserializeSeed<'de> for OMAttrV<'de, '_, OMD>
 where
     OMD: OMDeserializable<'de>,
 {
-    type Value = Attr<'de, OMD>;
+    /// `None` if the pair is skipped by the [attribute filter](super::Options::attr_filter)
+    type Value = Option<Attr<'de, OMD>>;
     #[inline]
     fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
     where
@@ -1931,7 +2796,7 @@ impl<'de, OMD> serde::de::Visitor<'de> for OMAttrV<'de, '_, OMD>
 where
     OMD: OMDeserializable<'de>,
 {
-    type Value = Attr<'de, OMD>;
+    type Value = Option<Attr<'de, OMD>>;
 
     #[inline]
     fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -1951,19 +2816,35 @@ where
         else {
             return Err(A::Error::custom("missing OMS in OMATP"));
         };
-        let Some(value) = seq.next_element_seed(OMDeForeign::<OMD>(self.0, PhantomData))? else {
+        let resolved = cdbase.as_ref().map_or(self.0, |c| &c.0);
+        if !super::keeps_attr(
+            self.1.state.scope.get().attr_filter,
+            resolved,
+            &cd.0,
+            &name.0,
+        ) {
+            return match seq.next_element::<serde::de::IgnoredAny>()? {
+                Some(_) => Ok(None),
+                None => Err(A::Error::custom("missing Value in OMATP")),
+            };
+        }
+        let Some(value) =
+            seq.next_element_seed(OMDeForeign::<OMD>(self.0, &self.1.index(1), PhantomData))?
+        else {
             return Err(A::Error::custom("missing Value in OMATP"));
         };
-        Ok(Attr::<OMD> {
-            cdbase: cdbase.map(|e| e.0),
-            cd: cd.0,
-            name: name.0,
+        Ok(Some(Attr::<OMD> {
+            key: crate::SymbolRef {
+                cdbase: cdbase.map(|e| e.0),
+                cd: cd.0,
+                name: name.0,
+            },
             value,
-        })
+        }))
     }
 }
 
-struct OMAttrSeq<'de, 's, OMD>(&'s str, &'s mut Attrs<Attr<'de, OMD>>)
+struct OMAttrSeq<'de, 's, OMD>(&'s str, &'s mut Attrs<Attr<'de, OMD>>, &'s JsonPath<'s>)
 where
     OMD: OMDeserializable<'de>;
 impl<'de, OMD> serde::de::DeserializeSeed<'de> for OMAttrSeq<'de, '_, OMD>
@@ -1993,13 +2874,135 @@ where
     where
         A: serde::de::SeqAccess<'de>,
     {
-        while let Some(v) = seq.next_element_seed(OMAttrV::<OMD>(self.0, PhantomData))? {
+        use serde::de::Error;
+        let reject = self.2.state.scope.get().duplicate_keys == super::DuplicateKeys::Reject;
+        let mut seen = std::collections::HashSet::new();
+        for index in 0.. {
+            let Some(v) =
+                seq.next_element_seed(OMAttrV::<OMD>(self.0, &self.2.index(index), PhantomData))?
+            else {
+                break;
+            };
+            let Some(v) = v else { continue };
+            if reject && !seen.insert(v.key.resolved_uri(self.0)) {
+                return Err(A::Error::custom(format_args!(
+                    "duplicate attribute key {}",
+                    v.key.resolved_uri(self.0)
+                )));
+            }
             self.1.push(v);
         }
         Ok(())
     }
 }
-struct OMVarSeq<'de, 's, OMD>(&'s str, PhantomData<&'de OMD>)
+/// Buffers the attribute pairs of an OMATTR whose cdbase is not known yet, dropping those that
+/// the [attribute filter](super::Options::attr_filter) skips under the inherited cdbase
+/// rather than buffering their values; the keys of skipped pairs without a cdbase of their
+/// own are collected, so that a cdbase given later can be [checked](check_skipped) against them
+struct AttrBuffer<'s, 'v>(
+    &'s str,
+    Option<super::AttrFilter>,
+    &'v mut Vec<(String, String)>,
+);
+impl<'de> serde::de::DeserializeSeed<'de> for AttrBuffer<'_, '_> {
+    type Value = serde_value::Value;
+    #[inline]
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(self)
+    }
+}
+impl<'de> serde::de::Visitor<'de> for AttrBuffer<'_, '_> {
+    type Value = serde_value::Value;
+
+    #[inline]
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a sequence of OMATP pairs")
+    }
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::SeqAccess<'de>,
+    {
+        let mut kept = Vec::new();
+        while let Some(pair) =
+            seq.next_element_seed(AttrPairBuffer(self.0, self.1, &mut *self.2))?
+        {
+            kept.extend(pair);
+        }
+        Ok(serde_value::Value::Seq(kept))
+    }
+}
+
+/// A single pair for [`AttrBuffer`]; `None` if it is skipped
+struct AttrPairBuffer<'s, 'v>(
+    &'s str,
+    Option<super::AttrFilter>,
+    &'v mut Vec<(String, String)>,
+);
+impl<'de> serde::de::DeserializeSeed<'de> for AttrPairBuffer<'_, '_> {
+    type Value = Option<serde_value::Value>;
+    #[inline]
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_tuple(2, self)
+    }
+}
+impl<'de> serde::de::Visitor<'de> for AttrPairBuffer<'_, '_> {
+    type Value = Option<serde_value::Value>;
+
+    #[inline]
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("an OMATP pair")
+    }
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::SeqAccess<'de>,
+    {
+        use serde::de::Error;
+        let Some(key) = seq.next_element::<serde_value::Value>()? else {
+            return Err(A::Error::custom("missing OMS in OMATP"));
+        };
+        let oms = OMS::deserialize(serde_value::ValueDeserializer::<A::Error>::new(key.clone()))?;
+        let resolved = oms.cdbase.as_ref().map_or(self.0, |c| &c.0);
+        if super::keeps_attr(self.1, resolved, &oms.cd.0, &oms.name.0) {
+            let Some(value) = seq.next_element::<serde_value::Value>()? else {
+                return Err(A::Error::custom("missing Value in OMATP"));
+            };
+            return Ok(Some(serde_value::Value::Seq(vec![key, value])));
+        }
+        if seq.next_element::<serde::de::IgnoredAny>()?.is_none() {
+            return Err(A::Error::custom("missing Value in OMATP"));
+        }
+        if oms.cdbase.is_none() {
+            self.2
+                .push((oms.cd.0.into_owned(), oms.name.0.into_owned()));
+        }
+        Ok(None)
+    }
+}
+
+/// Fails if one of the pairs `skipped` by an [`AttrBuffer`] would have been kept under the
+/// `cdbase` of their OMATTR, which was only given after them
+fn check_skipped<E: serde::de::Error>(
+    skipped: &[(String, String)],
+    cdbase: &str,
+    filter: Option<super::AttrFilter>,
+) -> Result<(), E> {
+    skipped
+        .iter()
+        .find(|(cd, name)| super::keeps_attr(filter, cdbase, cd, name))
+        .map_or(Ok(()), |(cd, name)| {
+            Err(E::custom(format_args!(
+                "attribute {cd}#{name} was skipped before the cdbase of its OMATTR was known"
+            )))
+        })
+}
+
+struct OMVarSeq<'de, 's, OMD>(&'s str, &'s JsonPath<'s>, PhantomData<&'de OMD>)
 where
     OMD: OMDeserializable<'de>;
 
@@ -2032,14 +3035,16 @@ where
     {
         let mut ret = Vars::new();
         let mut att = Attrs::new();
-        while let Some(v) = seq.next_element_seed(OMVarA::<OMD>(self.0, &mut att))? {
+        while let Some(v) =
+            seq.next_element_seed(OMVarA::<OMD>(self.0, &mut att, &self.1.index(ret.len())))?
+        {
             ret.push((v, std::mem::take(&mut att)));
         }
         Ok(ret)
     }
 }
 
-struct OMVarA<'de, 's, 'v, OMD>(&'s str, &'v mut Attrs<Attr<'de, OMD>>)
+struct OMVarA<'de, 's, 'v, OMD>(&'s str, &'v mut Attrs<Attr<'de, OMD>>, &'s JsonPath<'s>)
 where
     OMD: OMDeserializable<'de>;
 impl<'de, OMD> serde::de::DeserializeSeed<'de> for OMVarA<'de, '_, '_, OMD>
@@ -2139,13 +3144,16 @@ where
         };
         let cdbase = cdbase.unwrap_or(self.0);
 
-        let Some(()) = seq.next_element_seed(OMAttrSeq::<OMD>(cdbase, self.1))? else {
+        let Some(()) = seq.next_element_seed(OMAttrSeq::<OMD>(cdbase, self.1, &self.2.index(3)))?
+        else {
             return Err(A::Error::custom("missing attributions in OMATTR"));
         };
 
-        let Some(var) = seq.next_element_seed(OMVarA::<OMD>(cdbase, self.1))? else {
+        let Some(var) = seq.next_element_seed(OMVarA::<OMD>(cdbase, self.1, &self.2.index(4)))?
+        else {
             return Err(A::Error::custom("missing object in OMATTR"));
         };
+        while seq.next_element::<serde::de::IgnoredAny>()?.is_some() {}
         Ok(var)
     }
 
@@ -2166,38 +3174,30 @@ where
         while let Some(key) = map.next_key()? {
             match key {
                 AllFields::kind => {
-                    kind = Some(map.next_value()?);
+                    kind = map.next_value()?;
                     break;
                 }
-                AllFields::id => id = Some(map.next_value()?),
-                AllFields::name => name = Some(map.next_value()?),
-                AllFields::cdbase => cdbase = Some(map.next_value()?),
-                AllFields::object => object = Some(map.next_value()?),
-                AllFields::attributes => attributes = Some(map.next_value()?),
+                AllFields::id => id = map.next_value()?,
+                AllFields::name => name = map.next_value()?,
+                AllFields::cdbase => cdbase = map.next_value()?,
+                AllFields::object => object = map.next_value()?,
+                AllFields::attributes => attributes = map.next_value()?,
                 AllFields::__ignore => {
                     map.next_value::<serde::de::IgnoredAny>()?;
                 }
-                o => {
-                    return Err(A::Error::custom(format_args!(
-                        "unexpected field \"{o}\" in OMATP"
-                    )));
-                }
+                o => return Err(invalid_keys("OMATP", o, self.2)),
             }
         }
         match kind {
-            Some(OMKind::OMATTR) if name.is_some() => {
-                Err(A::Error::custom("invalid key \"name\" in OMATTR"))
-            }
+            Some(OMKind::OMATTR) if name.is_some() => Err(invalid_keys("OMATTR", "name", self.2)),
             Some(OMKind::OMV) if attributes.is_some() => {
-                Err(A::Error::custom("invalid key \"attributes\" in OMV"))
-            }
-            Some(OMKind::OMV) if object.is_some() => {
-                Err(A::Error::custom("invalid key \"object\" in OMV"))
+                Err(invalid_keys("OMV", "attributes", self.2))
             }
+            Some(OMKind::OMV) if object.is_some() => Err(invalid_keys("OMV", "object", self.2)),
             Some(OMKind::OMATTR) => {
                 self.visit_map_omattr(id.as_ref().map(|e| &*e.0), cdbase, attributes, object, map)
             }
-            Some(OMKind::OMV) => Self::visit_map_omv(id.as_ref().map(|e| &*e.0), name, map),
+            Some(OMKind::OMV) => Self::visit_map_omv(id.as_ref().map(|e| &*e.0), name, map, self.2),
             Some(k) => Err(A::Error::custom(format_args!(
                 "kind \"{k}\" not allowed in OMATP"
             ))),
@@ -2209,6 +3209,7 @@ where
         _id: Option<&str>,
         mut name: Option<CowStr<'de>>,
         mut map: A,
+        path: &JsonPath<'_>,
     ) -> Result<Cow<'de, str>, A::Error>
     where
         A: serde::de::MapAccess<'de>,
@@ -2216,9 +3217,12 @@ where
         use serde::de::Error;
         while let Some(key) = map.next_key()? {
             match key {
-                AllFields::name => name = Some(map.next_value()?),
+                AllFields::name => name = map.next_value()?,
+                AllFields::id => {
+                    map.next_value::<serde::de::IgnoredAny>()?;
+                }
                 k => {
-                    return Err(A::Error::custom(format_args!("Invalid keys for OMV: {k}")));
+                    return Err(invalid_keys("OMV", k, path));
                 }
             }
         }
@@ -2242,9 +3246,21 @@ where
     {
         use serde::de::Error;
 
-        let mut had_attrs = if let Some(attributes) = attributes {
-            OMAttrSeq::<OMD>(cdbase.as_ref().map_or(self.0, |e| &*e.0), self.1)
-                .deserialize(serde_value::ValueDeserializer::new(attributes))?;
+        // like for OMATTRs of objects, attributes are buffered until the cdbase is known
+        let mut attributes = attributes;
+        let mut skipped = Vec::new();
+        let attributes_path = self.2.key("attributes");
+        let object_path = self.2.key("object");
+        let mut had_attrs = if let Some(a) = attributes.take_if(|_| cdbase.is_some()) {
+            replay(
+                OMAttrSeq::<OMD>(
+                    cdbase.as_ref().map_or(self.0, |e| &*e.0),
+                    self.1,
+                    &attributes_path,
+                ),
+                a,
+                &attributes_path,
+            )?;
             true
         } else {
             false
@@ -2252,32 +3268,77 @@ where
 
         while let Some(key) = map.next_key()? {
             match key {
-                AllFields::cdbase => cdbase = Some(map.next_value()?),
-                AllFields::attributes => {
+                AllFields::cdbase if cdbase.is_some() => {
+                    return Err(A::Error::custom("duplicate field \"cdbase\" in OMATTR"));
+                }
+                AllFields::cdbase => {
+                    cdbase = map.next_value()?;
+                    check_skipped(
+                        &skipped,
+                        cdbase.as_ref().map_or(self.0, |e| &*e.0),
+                        self.2.state.scope.get().attr_filter,
+                    )?;
+                    if let Some(a) = attributes.take() {
+                        replay(
+                            OMAttrSeq::<OMD>(
+                                cdbase.as_ref().map_or(self.0, |e| &*e.0),
+                                self.1,
+                                &attributes_path,
+                            ),
+                            a,
+                            &attributes_path,
+                        )?;
+                        had_attrs = true;
+                    }
+                }
+                AllFields::attributes if cdbase.is_some() => {
                     map.next_value_seed(OMAttrSeq::<OMD>(
                         cdbase.as_ref().map_or(self.0, |e| &*e.0),
                         self.1,
+                        &attributes_path,
                     ))?;
                     had_attrs = true;
                 }
+                AllFields::attributes if self.2.state.scope.get().attr_filter.is_some() => {
+                    attributes = Some(map.next_value_seed(AttrBuffer(
+                        self.0,
+                        self.2.state.scope.get().attr_filter,
+                        &mut skipped,
+                    ))?);
+                }
+                AllFields::attributes => attributes = map.next_value()?,
                 AllFields::object if had_attrs => {
                     let r = map.next_value_seed(OMVarA::<OMD>(
                         cdbase.as_ref().map_or(self.0, |e| &*e.0),
                         self.1,
+                        &object_path,
                     ));
                     return r;
                 }
-                AllFields::object => object = Some(map.next_value()?),
+                AllFields::object => object = map.next_value()?,
+                AllFields::id => {
+                    map.next_value::<serde::de::IgnoredAny>()?;
+                }
                 k => {
-                    return Err(A::Error::custom(format_args!(
-                        "Invalid keys for OMATTR: {k}"
-                    )));
+                    return Err(invalid_keys("OMATTR", k, self.2));
                 }
             }
         }
 
+        let cdbase = cdbase.as_ref().map_or(self.0, |e| &*e.0);
+        if let Some(a) = attributes {
+            replay(
+                OMAttrSeq::<OMD>(cdbase, self.1, &attributes_path),
+                a,
+                &attributes_path,
+            )?;
+        }
         if let Some(object) = object {
-            Self(self.0, self.1).deserialize(serde_value::ValueDeserializer::new(object))
+            replay(
+                OMVarA::<OMD>(cdbase, self.1, &object_path),
+                object,
+                &object_path,
+            )
         } else {
             Err(A::Error::custom("Missing object for OMATTR"))
         }
@@ -0,0 +1,219 @@
+use std::ops::Range;
+
+use super::{OM, OMDeserializable, Visit, xml::XmlReadError};
+
+/** A `T` together with the byte range of the XML element it was deserialized from, and
+the spans of all of its descendants; e.g. for syntax highlighting.
+
+Spans are recorded by [`from_openmath_xml`](OMDeserializable::from_openmath_xml) only;
+they start at the `<` of the opening tag and end after the `>` of the closing tag (or of
+the empty element). The span of an attributed object is the span of the outermost
+[OMATTR](crate::OMKind::OMATTR) element enclosing it. Other entry points (e.g. serde) yield
+empty spans.
+
+[`children`](Spanned::children) are ordered like the fields of [`OM`]: the applicant or
+binder, then the arguments (or the attribute values of the bound variables, followed by the
+body), then the values of the node's own attributes. Nodes that are not deserialized
+themselves (e.g. [OMFOREIGN](crate::OMMaybeForeign::Foreign)s, attribute keys or bound
+variables) have no span.
+
+# Examples
+```
+use openmath::{OpenMath, de::{OMDeserializable, Spanned}};
+
+let input = r#"<OMA><OMS cd="arith1" name="plus"/><OMI>1</OMI><OMV name="x"/></OMA>"#;
+let spanned = Spanned::<OpenMath>::from_openmath_xml(input).expect("is valid");
+assert_eq!(spanned.span, 0..input.len());
+let spans = spanned
+    .children
+    .iter()
+    .map(|c| &input[c.span.clone()])
+    .collect::<Vec<_>>();
+assert_eq!(spans, [r#"<OMS cd="arith1" name="plus"/>"#, "<OMI>1</OMI>", r#"<OMV name="x"/>"#]);
+```
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spanned<T> {
+    /// The byte range of the element in the input
+    pub span: Range<usize>,
+    /// The deserialized value
+    pub inner: T,
+    /// The spans of the (deserialized) children of the element
+    pub children: Vec<Spanned<()>>,
+}
+
+/// The [`Ret`](OMDeserializable::Ret) of [`Spanned<T>`]; i.e. the `Ret` of `T` with its span.
+#[derive(Debug)]
+pub struct SpannedRet<R> {
+    pub(super) span: Range<usize>,
+    inner: R,
+    children: Vec<Spanned<()>>,
+}
+
+impl<T, R: TryInto<T>> TryFrom<SpannedRet<R>> for Spanned<T> {
+    type Error = R::Error;
+    fn try_from(value: SpannedRet<R>) -> Result<Self, Self::Error> {
+        Ok(Self {
+            span: value.span,
+            inner: value.inner.try_into()?,
+            children: value.children,
+        })
+    }
+}
+
+impl<'de, T: OMDeserializable<'de>> OMDeserializable<'de> for Spanned<T> {
+    type Ret = SpannedRet<T::Ret>;
+    type Err = T::Err;
+
+    fn from_openmath(om: OM<'de, Self::Ret>, cdbase: &str) -> Result<Self::Ret, Self::Err>
+    where
+        Self: Sized,
+    {
+        let mut children = Vec::new();
+        let om = om
+            .try_map(|c| {
+                children.push(Spanned {
+                    span: c.span,
+                    inner: (),
+                    children: c.children,
+                });
+                Ok(c.inner)
+            })
+            .map_err(|e: std::convert::Infallible| match e {})?;
+        Ok(SpannedRet {
+            // filled in by the reader, if it tracks spans
+            span: 0..0,
+            inner: T::from_openmath(om, cdbase)?,
+            children,
+        })
+    }
+
+    /// Forwards to `T`; skipped children have empty spans and no children.
+    fn before_child(parent: crate::OMKind, index: usize, cdbase: &str) -> Visit<Self::Ret>
+    where
+        Self: Sized,
+    {
+        match T::before_child(parent, index, cdbase) {
+            Visit::Descend => Visit::Descend,
+            Visit::Skip(inner) => Visit::Skip(SpannedRet {
+                span: 0..0,
+                inner,
+                children: Vec::new(),
+            }),
+        }
+    }
+
+    fn from_openmath_xml(input: &'de str) -> Result<Self, XmlReadError<Self::Err>>
+    where
+        Self: Sized,
+    {
+        use super::xml::Readable;
+        <super::xml::Spanning<'de> as super::xml::Events<'de>>::new(input).read(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Spanned;
+    use crate::{OMMaybeForeign, OpenMath, de::OMDeserializable};
+
+    /// The children of `om` in the order of [`Spanned::children`]
+    fn children<'a>(om: &'a OpenMath<'a>) -> Vec<&'a OpenMath<'a>> {
+        let (mut children, attributes) = match om {
+            OpenMath::OMA {
+                applicant,
+                arguments,
+                attributes,
+            } => (
+                std::iter::once(&**applicant).chain(arguments).collect(),
+                attributes,
+            ),
+            OpenMath::OMBIND {
+                binder,
+                variables,
+                object,
+                attributes,
+            } => (
+                std::iter::once(&**binder)
+                    .chain(
+                        variables
+                            .iter()
+                            .flat_map(|v| &v.attributes)
+                            .filter_map(|a| a.value.as_om()),
+                    )
+                    .chain(std::iter::once(&**object))
+                    .collect(),
+                attributes,
+            ),
+            OpenMath::OME {
+                arguments,
+                attributes,
+                ..
+            } => (
+                arguments.iter().filter_map(OMMaybeForeign::as_om).collect(),
+                attributes,
+            ),
+            OpenMath::OMI { attributes, .. }
+            | OpenMath::OMF { attributes, .. }
+            | OpenMath::OMSTR { attributes, .. }
+            | OpenMath::OMB { attributes, .. }
+            | OpenMath::OMV { attributes, .. }
+            | OpenMath::OMS { attributes, .. } => (Vec::new(), attributes),
+        };
+        children.extend(attributes.iter().filter_map(|a| a.value.as_om()));
+        children
+    }
+
+    /// Checks that every span in `spanned` re-parses to the corresponding subterm of `om`
+    fn check(input: &str, spanned: &Spanned<()>, om: &OpenMath<'_>) {
+        let slice = &input[spanned.span.clone()];
+        let reparsed = OpenMath::from_openmath_xml(slice).expect("slice is valid");
+        assert_eq!(&reparsed, om, "span {:?}: {slice}", spanned.span);
+        let children = children(om);
+        assert_eq!(spanned.children.len(), children.len(), "{slice}");
+        for (s, c) in spanned.children.iter().zip(children) {
+            check(input, s, c);
+        }
+    }
+
+    #[test]
+    fn spans() {
+        const INPUT: &str = r#"
+<OMATTR>
+  <OMATP>
+    <OMS cd="sts" name="type"/> <OMSTR/>
+    <OMS cd="meta" name="note"/> <OMFOREIGN encoding="text/plain">ignored</OMFOREIGN>
+  </OMATP>
+  <OMATTR><OMATP><OMS cd="meta" name="id"/><OMI>7</OMI></OMATP>
+    <OMA>
+      <OMS cd="arith1" name="plus"/>
+      <OMI> 1 </OMI>
+      <OMBIND>
+        <OMS cd="fns1" name="lambda"/>
+        <OMBVAR>
+          <OMATTR><OMATP><OMS cd="sts" name="type"/><OMS cd="setname1" name="N"/></OMATP>
+            <OMV name="x"/>
+          </OMATTR>
+        </OMBVAR>
+        <OMATTR><OMATP><OMS cd="meta" name="id"/><OMSTR>body</OMSTR></OMATP><OMV name="x"/></OMATTR>
+      </OMBIND>
+      <OME><OMS cd="moreerrors" name="unexpected"/><OMB>AAE=</OMB><OMFOREIGN>x</OMFOREIGN></OME>
+    </OMA>
+  </OMATTR >
+</OMATTR>
+"#;
+        let spanned = Spanned::<OpenMath>::from_openmath_xml(INPUT).expect("is valid");
+        assert_eq!(&INPUT[spanned.span.clone()], INPUT.trim());
+        let om = OpenMath::from_openmath_xml(INPUT).expect("is valid");
+        assert_eq!(spanned.inner, om);
+        check(
+            INPUT,
+            &Spanned {
+                span: spanned.span,
+                inner: (),
+                children: spanned.children,
+            },
+            &om,
+        );
+    }
+}
@@ -1,15 +1,40 @@
 #![allow(clippy::trait_duplication_in_bounds)]
 #![allow(clippy::type_complexity)]
-use std::{borrow::Cow, ops::ControlFlow};
+use std::{borrow::Cow, ops::ControlFlow, rc::Rc};
 
-use quick_xml::events::{BytesStart, Event};
+use quick_xml::{events::Event, events::attributes::AttrError};
 
 use crate::{
     OM, OMDeserializable,
-    de::{Args, Attrs, Vars},
+    de::{Args, AttributePolicy, Attrs, Vars, Visit},
 };
-type Attr<'s, O> = crate::Attr<'s, crate::OMMaybeForeign<'s, <O as OMDeserializable<'s>>::Ret>>;
+type Attr<'s, O> = super::OMAttr<'s, <O as OMDeserializable<'s>>::Ret>;
 
+/** Errors when reading <span style="font-variant:small-caps;">OpenMath</span> XML
+
+# Examples
+Input without an object fails with [`EmptyInput`](Self::EmptyInput) if it is empty or
+whitespace only, and with [`NoObject`](Self::NoObject) (or, when reading an
+[`OMObject`](super::OMObject), [`MissingElement`](Self::MissingElement)) if it has other
+content, such as an XML declaration:
+```rust
+use openmath::{OMDeserializable, OpenMath, de::{OMObject, XmlReadError}};
+
+for input in ["", "  \n"] {
+    let error = OpenMath::from_openmath_xml(input).expect_err("is empty");
+    assert!(matches!(error, XmlReadError::EmptyInput));
+}
+let input = r#"<?xml version="1.0"?>"#;
+let error = OpenMath::from_openmath_xml(input).expect_err("has no object");
+assert!(matches!(error, XmlReadError::NoObject));
+let error = OMObject::<OpenMath>::from_openmath_xml(input).expect_err("has no OMOBJ");
+assert_eq!(error.to_string(), "expected an OMOBJ element, but the input ended");
+```
+(For JSON, [`Document::parse_json`](crate::Document::parse_json) reports empty input as
+`JsonReadError::EmptyInput` likewise; with serde directly, the deserializer fails before any
+object is read, e.g. `serde_json` with an error for which
+[`is_eof`](https://docs.rs/serde_json/latest/serde_json/struct.Error.html#method.is_eof) holds.)
+*/
 #[derive(Debug, Clone, thiserror::Error)]
 pub enum XmlReadError<E: std::fmt::Display> {
     #[error("{error} (at offset {position})")]
@@ -21,130 +46,513 @@ pub enum XmlReadError<E: std::fmt::Display> {
     Empty(u64),
     #[error("unknown OpenMath element at {0}")]
     UnexpectedTag(u64),
+    /// The input contains no object, but is not empty; e.g. it only has an XML declaration
+    /// or comments
     #[error("missing OpenMath object")]
     NoObject,
+    /// The input is empty or whitespace only
+    #[error("empty input; expected an OpenMath object")]
+    EmptyInput,
+    /// The input ended before the given element, which it needs to contain; e.g. the
+    /// `OMOBJ` when reading an [`OMObject`](super::OMObject)
+    #[error("expected an {0} element, but the input ended")]
+    MissingElement(&'static str),
     #[error("text node expected in xml element")]
     ExpectedText,
-    #[error("invalid utf8: {0}")]
-    Utf8(#[from] std::str::Utf8Error),
+    #[error(
+        "invalid UTF-8 in {} at offset {position}: {source}",
+        element.map_or("element", crate::OMKind::as_str)
+    )]
+    Utf8 {
+        source: std::str::Utf8Error,
+        position: u64,
+        /// The element containing the invalid text or attribute value; `None` for `OMOBJ`
+        /// and non-<span style="font-variant:small-caps;">OpenMath</span> elements
+        element: Option<crate::OMKind>,
+    },
     #[error("invalid integer {0}")]
     InvalidInteger(String),
     #[error("invalid float {0}")]
     InvalidFloat(String),
+    /// A decimal float that no [`f64`] represents exactly, under
+    /// [`FloatPolicy::ErrorOnInexact`](super::FloatPolicy::ErrorOnInexact)
+    #[error("inexact float {0}")]
+    InexactFloat(String),
     #[error("error converting OpenMath: {0}")]
     Conversion(E),
     #[error("OpenMath not fully convertible to target type")]
     NotFullyConvertible,
     #[error("attribute expected: {0}")]
     ExpectedAttribute(&'static str),
-    #[error("error decoding base64 string: {0}")]
-    Base64(#[from] crate::base64::Error),
+    #[error(
+        "invalid base64 in OMB at offset {position}: {source}; OMB expects base64-encoded \
+         bytes, use OMSTR for text"
+    )]
+    Base64 {
+        source: crate::base64::Error,
+        position: u64,
+    },
     #[error("expected empty tag for {0} at {1}")]
     EmptyExpectedFor(&'static str, u64),
     #[error("expected non-empty tag for {0} at {1}")]
     NonEmptyExpectedFor(&'static str, u64),
-    #[error("xml parsing requires string allocation (can't borrow) at {0}")]
-    RequiresAllocating(u64),
-    #[error("hexadecimal not yet implemented")]
+    #[error("hexadecimal floats not yet implemented")]
     Hex,
     #[error("value for OMATP key-value-pair missing")]
     AttributeValue(u64),
+    #[error("missing OMBVAR in OMBIND at {0}")]
+    MissingOMBVAR(u64),
+    #[error("unsupported OpenMath version {0}")]
+    UnsupportedVersion(String),
+    #[error(
+        "element <{element}> inside OMSTR at {position}; escape its content (or wrap it in \
+         CDATA), or use OMFOREIGN for markup"
+    )]
+    MarkupInString { element: String, position: u64 },
+    #[error("unknown or invalid entity reference &{0};")]
+    UnknownEntity(String),
+    #[error("unsupported encoding {0}; only UTF-8 is supported")]
+    UnsupportedEncoding(String),
+    #[error("input is not valid {0}")]
+    InvalidEncoding(&'static str),
+    #[error("{error} (at offset {position})")]
+    InvalidName {
+        error: super::NameError,
+        position: u64,
+    },
+    #[error("budget exceeded after {nodes_parsed} nodes ({elapsed:?})")]
+    BudgetExceeded {
+        nodes_parsed: usize,
+        elapsed: std::time::Duration,
+    },
+    #[error("elements nested deeper than {max} levels at offset {position}")]
+    TooDeep { max: usize, position: u64 },
+    #[error("duplicate attribute {attribute} at offset {position}")]
+    DuplicateAttribute {
+        attribute: &'static str,
+        position: u64,
+    },
+    /// Content other than whitespace, comments and processing instructions after the
+    /// object (or after the `</OMOBJ>`), at the given offset; see
+    /// [`from_openmath_xml_prefix`](super::OMDeserializable::from_openmath_xml_prefix)
+    /// for reading an object from the start of a longer input
+    #[error("unexpected content after the object at offset {0}")]
+    TrailingContent(u64),
+    /// An `OMFOREIGN` at the given offset, but not as an argument of an `OME` or an
+    /// attribute value, the only places the standard allows it
+    #[error(
+        "OMFOREIGN at offset {0} is only allowed as an argument of an OME or an attribute value"
+    )]
+    MisplacedForeign(u64),
+    /// Like [`Conversion`](Self::Conversion), for an [`OMS`](crate::OMKind::OMS) at
+    /// `position`; `cdbase_origin` is the offset of the element whose `cdbase` attribute
+    /// set the cdbase the symbol was resolved against, or `None` if it is the default
+    #[error(
+        "error converting symbol at offset {position}: {error}{}",
+        cdbase_origin.map_or_else(String::new, |o| format!(" (cdbase set at offset {o})"))
+    )]
+    SymbolConversion {
+        error: E,
+        position: u64,
+        cdbase_origin: Option<u64>,
+    },
+    /// The [OMR](crate::OMKind::OMR) at `position` could not be resolved with the
+    /// [`OMRPolicy`](super::OMRPolicy) of the [`Options`](super::Options)
+    #[error("error resolving reference at offset {position}: {error}")]
+    Reference {
+        error: super::ReferenceError,
+        position: u64,
+    },
 }
 
-pub(super) struct Ev<'e>(Event<'e>);
-pub(super) struct NEv<'e>(Event<'e>);
-
-pub(super) trait E<'e, 's: 'e>: AsRef<Event<'e>> {
-    fn into_ref(self) -> Event<'e>;
-    fn into_empty(self) -> BytesStart<'e>;
-
-    fn as_empty(&self) -> &BytesStart<'e> {
-        // SAFETY: private method; only gets called if known to be an Event::Empty!
-        unsafe {
-            let Event::Empty(s) = self.as_ref() else {
-                std::hint::unreachable_unchecked()
-            };
-            s
+impl<E: std::fmt::Display> XmlReadError<E> {
+    /// The byte offset in the input the error occurred at, if the error has one
+    #[must_use]
+    pub const fn position(&self) -> Option<u64> {
+        match self {
+            Self::Xml { position, .. }
+            | Self::Utf8 { position, .. }
+            | Self::Base64 { position, .. }
+            | Self::MarkupInString { position, .. }
+            | Self::InvalidName { position, .. }
+            | Self::TooDeep { position, .. }
+            | Self::DuplicateAttribute { position, .. }
+            | Self::SymbolConversion { position, .. }
+            | Self::Reference { position, .. }
+            | Self::Empty(position)
+            | Self::UnexpectedTag(position)
+            | Self::EmptyExpectedFor(_, position)
+            | Self::NonEmptyExpectedFor(_, position)
+            | Self::AttributeValue(position)
+            | Self::MissingOMBVAR(position)
+            | Self::TrailingContent(position)
+            | Self::MisplacedForeign(position) => Some(*position),
+            Self::NoObject
+            | Self::EmptyInput
+            | Self::MissingElement(_)
+            | Self::ExpectedText
+            | Self::InvalidInteger(_)
+            | Self::InvalidFloat(_)
+            | Self::InexactFloat(_)
+            | Self::Conversion(_)
+            | Self::NotFullyConvertible
+            | Self::ExpectedAttribute(_)
+            | Self::Hex
+            | Self::UnsupportedVersion(_)
+            | Self::UnknownEntity(_)
+            | Self::UnsupportedEncoding(_)
+            | Self::InvalidEncoding(_)
+            | Self::BudgetExceeded { .. } => None,
         }
     }
-    fn as_start(&self) -> &BytesStart<'e> {
-        // SAFETY: private method; only gets called if known to be an Event::Start!
-        unsafe {
-            let Event::Start(s) = self.as_ref() else {
-                std::hint::unreachable_unchecked()
-            };
-            s
+}
+
+/// The errors of reading the structure of a document, which can not be conversion errors;
+/// see [`Events`]
+pub(super) type StructuralError = XmlReadError<std::convert::Infallible>;
+
+impl StructuralError {
+    /// The same error, as one of reading into a type with conversion errors `E`
+    pub(super) fn widen<E: std::fmt::Display>(self) -> XmlReadError<E> {
+        match self {
+            Self::Xml { error, position } => XmlReadError::Xml { error, position },
+            Self::Empty(position) => XmlReadError::Empty(position),
+            Self::UnexpectedTag(position) => XmlReadError::UnexpectedTag(position),
+            Self::NoObject => XmlReadError::NoObject,
+            Self::EmptyInput => XmlReadError::EmptyInput,
+            Self::MissingElement(element) => XmlReadError::MissingElement(element),
+            Self::ExpectedText => XmlReadError::ExpectedText,
+            Self::Utf8 {
+                source,
+                position,
+                element,
+            } => XmlReadError::Utf8 {
+                source,
+                position,
+                element,
+            },
+            Self::InvalidInteger(s) => XmlReadError::InvalidInteger(s),
+            Self::InvalidFloat(s) => XmlReadError::InvalidFloat(s),
+            Self::InexactFloat(s) => XmlReadError::InexactFloat(s),
+            Self::Conversion(e) | Self::SymbolConversion { error: e, .. } => match e {},
+            Self::NotFullyConvertible => XmlReadError::NotFullyConvertible,
+            Self::ExpectedAttribute(key) => XmlReadError::ExpectedAttribute(key),
+            Self::Base64 { source, position } => XmlReadError::Base64 { source, position },
+            Self::EmptyExpectedFor(element, position) => {
+                XmlReadError::EmptyExpectedFor(element, position)
+            }
+            Self::NonEmptyExpectedFor(element, position) => {
+                XmlReadError::NonEmptyExpectedFor(element, position)
+            }
+            Self::Hex => XmlReadError::Hex,
+            Self::AttributeValue(position) => XmlReadError::AttributeValue(position),
+            Self::MissingOMBVAR(position) => XmlReadError::MissingOMBVAR(position),
+            Self::UnsupportedVersion(v) => XmlReadError::UnsupportedVersion(v),
+            Self::MarkupInString { element, position } => {
+                XmlReadError::MarkupInString { element, position }
+            }
+            Self::UnknownEntity(e) => XmlReadError::UnknownEntity(e),
+            Self::UnsupportedEncoding(e) => XmlReadError::UnsupportedEncoding(e),
+            Self::InvalidEncoding(e) => XmlReadError::InvalidEncoding(e),
+            Self::InvalidName { error, position } => XmlReadError::InvalidName { error, position },
+            Self::BudgetExceeded {
+                nodes_parsed,
+                elapsed,
+            } => XmlReadError::BudgetExceeded {
+                nodes_parsed,
+                elapsed,
+            },
+            Self::TooDeep { max, position } => XmlReadError::TooDeep { max, position },
+            Self::DuplicateAttribute {
+                attribute,
+                position,
+            } => XmlReadError::DuplicateAttribute {
+                attribute,
+                position,
+            },
+            Self::TrailingContent(position) => XmlReadError::TrailingContent(position),
+            Self::MisplacedForeign(position) => XmlReadError::MisplacedForeign(position),
+            Self::Reference { error, position } => XmlReadError::Reference { error, position },
         }
     }
-    fn into_str<Err: std::fmt::Display>(self) -> Result<Cow<'s, [u8]>, XmlReadError<Err>>;
-    fn get_attr_from_empty(&self, name: &str) -> Option<Cow<'s, [u8]>>;
-    fn get_attr_from_start(&self, name: &str) -> Option<Cow<'s, [u8]>>;
+}
 
-    fn borrow_attr<'a>(&'a self, name: &str) -> Option<Cow<'a, [u8]>>
-    where
-        'e: 'a,
-    {
-        let es = self.as_empty();
-        es.attributes().find_map(|a| {
-            a.ok().and_then(|a| {
-                if a.key.as_ref() == name.as_bytes() {
-                    Some(a.value)
-                } else {
-                    None
-                }
-            })
-        })
+/// An [`XmlReadError`] together with the byte offset of the element it occurred at;
+/// see [`OMDeserializableRecover`](super::OMDeserializableRecover).
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("{error} (at offset {position})")]
+pub struct PositionedError<E: std::fmt::Display> {
+    pub position: u64,
+    pub error: XmlReadError<E>,
+}
+
+/// What remains of a broken document; see
+/// [`from_openmath_xml_partial`](super::OMDeserializable::from_openmath_xml_partial).
+#[derive(Debug)]
+pub struct PartialParse<O, E: std::fmt::Display> {
+    /// The complete children of the root object (e.g. the applicant and arguments of an
+    /// [`OMA`](crate::OMKind::OMA)) up to the first one that is incomplete or fails to
+    /// convert, in document order
+    pub children: Vec<O>,
+    /// The error that stopped parsing the whole document
+    pub error: XmlReadError<E>,
+    /// The byte offset just after the last of the [`children`](Self::children); i.e. the
+    /// input up to here is well-formed. `0` if there are none.
+    pub offset: u64,
+}
+
+/// Whether `name` is an element representing an <span style="font-variant:small-caps;">OpenMath</span> object
+fn is_object(name: &[u8]) -> bool {
+    matches!(
+        name,
+        b"OMI"
+            | b"OMF"
+            | b"OMSTR"
+            | b"OMB"
+            | b"OMV"
+            | b"OMS"
+            | b"OMA"
+            | b"OME"
+            | b"OMBIND"
+            | b"OMATTR"
+            | b"OMR"
+    )
+}
+
+/// Whether `event` may occur around the root element: whitespace, a comment or a
+/// processing instruction
+fn is_ignorable(event: &Event<'_>) -> bool {
+    match event {
+        Event::Text(t) => t.iter().all(u8::is_ascii_whitespace),
+        Event::Comment(_) | Event::PI(_) => true,
+        _ => false,
     }
 }
-impl<'e, 's: 'e> E<'e, 's> for Ev<'s> {
-    #[inline]
-    fn into_ref(self) -> Event<'e> {
-        self.0
+
+/// Collects the complete children of the root object of `input` after parsing it as a
+/// whole failed with `error`
+pub(super) fn partial<'s, O: OMDeserializable<'s>>(
+    input: &'s str,
+    error: XmlReadError<O::Err>,
+) -> PartialParse<O, O::Err> {
+    let stripped = input.strip_prefix('\u{feff}').unwrap_or(input);
+    let bom = (input.len() - stripped.len()) as u64;
+    let mut reader = quick_xml::Reader::from_str(stripped);
+    let mut ret = PartialParse {
+        children: Vec::new(),
+        error,
+        offset: 0,
+    };
+    // the cdbases in effect in the open elements
+    let mut cdbases: Vec<String> = Vec::new();
+    let mut root_depth = None;
+    let mut child_start = 0;
+    loop {
+        let start = reader.buffer_position();
+        let Ok(event) = reader.read_event() else {
+            return ret;
+        };
+        let depth = cdbases.len();
+        match &event {
+            Event::Start(s) | Event::Empty(s) => {
+                let name = s.local_name();
+                let is_empty = matches!(event, Event::Empty(_));
+                if root_depth.is_none() && name.as_ref() != b"OMOBJ" {
+                    if is_empty {
+                        return ret;
+                    }
+                    root_depth = Some(depth);
+                } else if root_depth.is_some_and(|d| d + 1 == depth) {
+                    if !is_object(name.as_ref()) {
+                        // OMBVAR, OMATP, OMFOREIGN
+                        if !is_empty && reader.read_to_end(s.name()).is_err() {
+                            return ret;
+                        }
+                        continue;
+                    }
+                    if is_empty {
+                        let end = reader.buffer_position();
+                        if !convert_child(stripped, start..end, &cdbases, bom, &mut ret) {
+                            return ret;
+                        }
+                        continue;
+                    }
+                    child_start = start;
+                }
+                if !is_empty {
+                    let current = cdbases.last().map_or(crate::CD_BASE, String::as_str);
+                    let own = s
+                        .try_get_attribute("cdbase")
+                        .ok()
+                        .flatten()
+                        .and_then(|a| a.unescape_value().ok());
+                    cdbases.push(inherit(own, current).into_owned());
+                }
+            }
+            Event::End(_) => {
+                cdbases.pop();
+                let depth = cdbases.len();
+                if root_depth.is_none_or(|d| depth <= d) {
+                    return ret;
+                }
+                if root_depth.is_some_and(|d| d + 1 == depth) {
+                    let end = reader.buffer_position();
+                    if !convert_child(stripped, child_start..end, &cdbases, bom, &mut ret) {
+                        return ret;
+                    }
+                }
+            }
+            Event::Eof => return ret,
+            _ => (),
+        }
     }
-    fn into_empty(self) -> BytesStart<'e> {
-        // SAFETY: private method; only gets called if known to be an Event::Empty!
-        unsafe {
-            let Event::Empty(s) = self.0 else {
-                std::hint::unreachable_unchecked()
-            };
-            s
+}
+
+/// Converts the child of the root object at `input[start..end]`; returns whether that
+/// succeeded
+#[allow(clippy::cast_possible_truncation)]
+fn convert_child<'s, O: OMDeserializable<'s>>(
+    input: &'s str,
+    range: std::ops::Range<u64>,
+    cdbases: &[String],
+    bom: u64,
+    ret: &mut PartialParse<O, O::Err>,
+) -> bool {
+    let end = range.end;
+    let slice = &input[range.start as usize..range.end as usize];
+    let cdbase = cdbases.last().map_or(crate::CD_BASE, String::as_str);
+    match <FromString<'s> as Events<'s>>::new(slice).read(Some(cdbase)) {
+        Ok(child) => {
+            ret.children.push(child);
+            ret.offset = end + bom;
+            true
         }
+        Err(_) => false,
     }
-    fn into_str<Err: std::fmt::Display>(self) -> Result<Cow<'s, [u8]>, XmlReadError<Err>> {
-        let Event::Text(i) = self.0 else {
-            return Err(XmlReadError::ExpectedText);
+}
+
+/// An event borrowing from the input, with the `xml:base` in scope, the policy for
+/// duplicate attributes and the input itself
+pub(super) struct Ev<'e>(Event<'e>, Option<Rc<str>>, AttributePolicy, &'e [u8]);
+/// An event borrowing from the reader's buffer, with the `xml:base` in scope and the policy
+/// for duplicate attributes
+pub(super) struct NEv<'e>(Event<'e>, Option<Rc<str>>, AttributePolicy);
+
+/// The `xml:base` URIs in scope, each with the depth of the element declaring it
+#[derive(Default)]
+pub(super) struct Bases {
+    stack: Vec<(usize, Rc<str>)>,
+    depth: usize,
+    /// whether the last event was an empty element, whose base ends with the next event
+    empty: bool,
+}
+impl Bases {
+    /// The base URI of the document itself
+    pub(super) fn document(&mut self, base: &str) {
+        self.stack = vec![(0, base.into())];
+    }
+
+    /// The effective base URI of the current element, if any
+    fn current(&self) -> Option<Rc<str>> {
+        self.stack.last().map(|(_, b)| b.clone())
+    }
+
+    /// Updates the bases in scope for the next `event` (at `now`)
+    fn event(&mut self, event: &Event<'_>, now: u64) -> Result<(), StructuralError> {
+        if std::mem::take(&mut self.empty) {
+            self.end();
+        }
+        let (Event::Start(e) | Event::Empty(e)) = event else {
+            if matches!(event, Event::End(_)) {
+                self.end();
+            }
+            return Ok(());
         };
-        Ok(i.into_inner())
-    }
-    fn get_attr_from_empty(&self, name: &str) -> Option<Cow<'s, [u8]>> {
-        let es = self.as_empty();
-        es.attributes().find_map(|a| {
-            a.ok().and_then(|a| {
-                if a.key.as_ref() == name.as_bytes() {
-                    // SAFETY: We know this is a slice of lifetime 's, but quick_xml doesn't
-                    // return the most general applicable lifetime
-                    Some(unsafe { std::mem::transmute::<Cow<'_, _>, Cow<'s, _>>(a.value) })
-                } else {
-                    None
-                }
-            })
+        self.depth += 1;
+        self.empty = matches!(event, Event::Empty(_));
+        // cheap check first; most documents have no `xml:base` at all
+        if !e.windows(8).any(|w| w == b"xml:base") {
+            return Ok(());
+        }
+        for a in e.attributes() {
+            let a = a.map_err(|e| XmlReadError::Xml {
+                error: e.into(),
+                position: now,
+            })?;
+            if a.key.as_ref() == b"xml:base" {
+                let at = At {
+                    element: crate::OMKind::from_name(e.local_name().as_ref()),
+                    position: now,
+                };
+                let base = tryfrombytes(a.value, at)?;
+                let base = match self.stack.last() {
+                    Some((_, current)) => crate::CdBase::new_unchecked(current).join(&base).into(),
+                    None => base,
+                };
+                self.stack.push((self.depth, base.into()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Fails if the element just started (at `now`) is nested deeper than `max`
+    const fn check_depth(&self, max: Option<usize>, now: u64) -> Result<(), StructuralError> {
+        match max {
+            Some(max) if self.depth > max => Err(XmlReadError::TooDeep { max, position: now }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Leaves the current element
+    pub(super) fn end(&mut self) {
+        if self.stack.last().is_some_and(|(d, _)| *d == self.depth) {
+            self.stack.pop();
+        }
+        self.depth = self.depth.saturating_sub(1);
+    }
+}
+
+pub(super) trait E<'e, 's: 'e>: AsRef<Event<'e>> {
+    /// The content of a text, CDATA or entity reference event; `None` for all others
+    fn into_text(self) -> Result<Option<Cow<'s, [u8]>>, StructuralError>;
+    /// The attributes of a start or empty element event (at `now`), collected in a
+    /// single pass
+    fn attrs(&self, now: u64) -> Result<XmlAttrs<'s>, StructuralError>;
+
+    /// The `cdbase` attribute of a start or empty element event (at `now`)
+    fn cdbase(&self, now: u64) -> Result<Option<Cow<'s, str>>, StructuralError> {
+        let attrs = self.attrs(now)?;
+        XmlAttrs::optional(attrs.cdbase, attrs.at)
+    }
+}
+impl<'e, 's: 'e> E<'e, 's> for Ev<'s> {
+    fn into_text(self) -> Result<Option<Cow<'s, [u8]>>, StructuralError> {
+        Ok(match self.0 {
+            Event::Text(t) => Some(t.into_inner()),
+            Event::CData(t) => Some(t.into_inner()),
+            Event::GeneralRef(r) => Some(resolve_ref(&r)?),
+            _ => None,
         })
     }
-    fn get_attr_from_start(&self, name: &str) -> Option<Cow<'s, [u8]>> {
-        let es = self.as_start();
-        es.attributes().find_map(|a| {
-            a.ok().and_then(|a| {
-                if a.key.as_ref() == name.as_bytes() {
-                    // SAFETY: We know this is a slice of lifetime 's, but quick_xml doesn't
-                    // return the most general applicable lifetime
-                    Some(unsafe { std::mem::transmute::<Cow<'_, _>, Cow<'s, _>>(a.value) })
-                } else {
-                    None
-                }
-            })
+    fn attrs(&self, now: u64) -> Result<XmlAttrs<'s>, StructuralError> {
+        let input = self.3;
+        XmlAttrs::collect(&self.0, now, self.2, |v| match v {
+            // quick_xml only lends values for as long as the event is borrowed, but unless
+            // they are unescaped, they are subslices of the input; so borrow them from there
+            Cow::Borrowed(v) => {
+                subslice(input, v).map_or_else(|| Cow::Owned(v.to_vec()), Cow::Borrowed)
+            }
+            Cow::Owned(v) => Cow::Owned(v),
         })
+        .map(|a| a.resolve(self.1.as_deref()))
     }
 }
+/// `part` as a subslice of `whole`, if it is one
+fn subslice<'s>(whole: &'s [u8], part: &[u8]) -> Option<&'s [u8]> {
+    let start = part.as_ptr().addr().checked_sub(whole.as_ptr().addr())?;
+    whole.get(start..start.checked_add(part.len())?)
+}
+
 impl<'e, 's: 'e> AsRef<Event<'e>> for Ev<'s> {
     fn as_ref(&self) -> &Event<'e> {
         &self.0
@@ -152,87 +560,475 @@ impl<'e, 's: 'e> AsRef<Event<'e>> for Ev<'s> {
 }
 
 impl<'e, 's: 'e> E<'e, 's> for NEv<'e> {
-    #[inline]
-    fn into_ref(self) -> Event<'e> {
-        self.0
+    fn into_text(self) -> Result<Option<Cow<'s, [u8]>>, StructuralError> {
+        Ok(match self.0 {
+            Event::Text(t) => Some(Cow::Owned(t.into_inner().into_owned())),
+            Event::CData(t) => Some(Cow::Owned(t.into_inner().into_owned())),
+            Event::GeneralRef(r) => Some(resolve_ref(&r)?),
+            _ => None,
+        })
+    }
+    fn attrs(&self, now: u64) -> Result<XmlAttrs<'s>, StructuralError> {
+        XmlAttrs::collect(&self.0, now, self.2, |v| Cow::Owned(v.into_owned()))
+            .map(|a| a.resolve(self.1.as_deref()))
+    }
+}
+impl<'e> AsRef<Event<'e>> for NEv<'e> {
+    fn as_ref(&self) -> &Event<'e> {
+        &self.0
     }
-    fn into_empty(self) -> BytesStart<'e> {
-        // SAFETY: privae method; only gets called if known to be an Event::Empty!
-        unsafe {
-            let Event::Empty(s) = self.0 else {
-                std::hint::unreachable_unchecked()
+}
+
+/// The attributes of an element this crate knows about; all others are ignored
+#[derive(Default)]
+pub(super) struct XmlAttrs<'s> {
+    name: Option<Cow<'s, [u8]>>,
+    cd: Option<Cow<'s, [u8]>>,
+    cdbase: Option<Cow<'s, [u8]>>,
+    dec: Option<Cow<'s, [u8]>>,
+    hex: Option<Cow<'s, [u8]>>,
+    encoding: Option<Cow<'s, [u8]>>,
+    id: Option<Cow<'s, [u8]>>,
+    href: Option<Cow<'s, [u8]>>,
+    version: Option<Cow<'s, [u8]>>,
+    at: At,
+}
+impl<'s> XmlAttrs<'s> {
+    /// Scans the attributes of `event` (if it is a start or empty element) once; malformed
+    /// attributes are reported as [`XmlReadError::Xml`], and duplicates as `policy` says
+    fn collect<'e>(
+        event: &'e Event<'_>,
+        now: u64,
+        policy: AttributePolicy,
+        mut value: impl FnMut(Cow<'e, [u8]>) -> Cow<'s, [u8]>,
+    ) -> Result<Self, StructuralError> {
+        let mut attrs = Self::default();
+        let (Event::Start(e) | Event::Empty(e)) = event else {
+            return Ok(attrs);
+        };
+        attrs.at = At {
+            element: crate::OMKind::from_name(e.local_name().as_ref()),
+            position: now,
+        };
+        let mut attributes = e.attributes();
+        attributes.with_checks(policy == AttributePolicy::Strict);
+        for a in attributes {
+            let a = a.map_err(|error| match error {
+                AttrError::Duplicated(at, _) => Self::known(&e[at..]).map_or_else(
+                    || XmlReadError::Xml {
+                        error: error.into(),
+                        position: now,
+                    },
+                    |attribute| XmlReadError::DuplicateAttribute {
+                        attribute,
+                        position: now,
+                    },
+                ),
+                error => XmlReadError::Xml {
+                    error: error.into(),
+                    position: now,
+                },
+            })?;
+            let slot = match a.key.as_ref() {
+                b"name" => &mut attrs.name,
+                b"cd" => &mut attrs.cd,
+                b"cdbase" => &mut attrs.cdbase,
+                b"dec" => &mut attrs.dec,
+                b"hex" => &mut attrs.hex,
+                b"encoding" => &mut attrs.encoding,
+                b"id" => &mut attrs.id,
+                b"href" => &mut attrs.href,
+                b"version" => &mut attrs.version,
+                _ => continue,
             };
-            s
+            // only reachable with `AttributePolicy::Lenient`
+            if slot.is_some() {
+                continue;
+            }
+            *slot = Some(value(unescape_attr(a.value)?));
         }
+        Ok(attrs)
     }
 
-    fn into_str<Err: std::fmt::Display>(self) -> Result<Cow<'s, [u8]>, XmlReadError<Err>> {
-        let Event::Text(i) = self.0 else {
-            return Err(XmlReadError::ExpectedText);
-        };
-        Ok(Cow::Owned(i.into_inner().into_owned()))
-    }
-    fn get_attr_from_empty(&self, name: &str) -> Option<Cow<'s, [u8]>> {
-        let es = self.as_empty();
-        es.attributes().find_map(|a| {
-            a.ok().and_then(|a| {
-                if a.key.as_ref() == name.as_bytes() {
-                    Some(Cow::Owned(a.value.into_owned()))
-                } else {
-                    None
-                }
-            })
+    /// The name of the attribute at the start of `rest`, if it is one this crate reads
+    fn known(rest: &[u8]) -> Option<&'static str> {
+        let end = rest
+            .iter()
+            .position(|b| *b == b'=' || b.is_ascii_whitespace())
+            .unwrap_or(rest.len());
+        Some(match &rest[..end] {
+            b"name" => "name",
+            b"cd" => "cd",
+            b"cdbase" => "cdbase",
+            b"dec" => "dec",
+            b"hex" => "hex",
+            b"encoding" => "encoding",
+            b"id" => "id",
+            b"href" => "href",
+            b"version" => "version",
+            _ => return None,
         })
     }
-    fn get_attr_from_start(&self, name: &str) -> Option<Cow<'s, [u8]>> {
-        let es = self.as_start();
-        es.attributes().find_map(|a| {
-            a.ok().and_then(|a| {
-                if a.key.as_ref() == name.as_bytes() {
-                    Some(Cow::Owned(a.value.into_owned()))
-                } else {
-                    None
-                }
-            })
+
+    /// Resolves a relative `cdbase` against the `xml:base` in scope, if any
+    fn resolve(mut self, base: Option<&str>) -> Self {
+        if let (Some(base), Some(cdbase)) = (base, &self.cdbase)
+            && let Ok(cdbase) = std::str::from_utf8(cdbase)
+            && !crate::CdBase::new_unchecked(cdbase).is_absolute()
+        {
+            let joined = crate::CdBase::new_unchecked(base).join(cdbase);
+            self.cdbase = Some(Cow::Owned(joined.as_str().as_bytes().to_vec()));
+        }
+        self
+    }
+
+    /// The value of the attribute `key` in `attr`, failing if it is missing
+    fn required(
+        attr: Option<Cow<'s, [u8]>>,
+        key: &'static str,
+        at: At,
+    ) -> Result<Cow<'s, str>, StructuralError> {
+        attr.map_or(Err(XmlReadError::ExpectedAttribute(key)), |a| {
+            tryfrombytes(a, at)
         })
     }
+
+    /// The value of the attribute in `attr`, if any
+    fn optional(
+        attr: Option<Cow<'s, [u8]>>,
+        at: At,
+    ) -> Result<Option<Cow<'s, str>>, StructuralError> {
+        attr.map(|a| tryfrombytes(a, at)).transpose()
+    }
+
+    /// The cdbase, cd and name of an `OMS`
+    fn symbol(self) -> Result<(Option<Cow<'s, str>>, Cow<'s, str>, Cow<'s, str>), StructuralError> {
+        let name = Self::required(self.name, "name", self.at)?;
+        let cd = Self::required(self.cd, "cd", self.at)?;
+        Ok((Self::optional(self.cdbase, self.at)?, cd, name))
+    }
 }
-impl<'e> AsRef<Event<'e>> for NEv<'e> {
-    fn as_ref(&self) -> &Event<'e> {
-        &self.0
+
+/// The attributes that occur more than once on the same element of `input`, with the
+/// position of that element; which is what [`AttributePolicy::Lenient`] silently accepts.
+/// Stops at the first malformed event.
+pub fn duplicate_attributes(input: &str) -> Vec<(String, u64)> {
+    let mut reader = quick_xml::Reader::from_str(input);
+    let mut found = Vec::new();
+    loop {
+        let position = reader.buffer_position();
+        let (Event::Start(e) | Event::Empty(e)) = (match reader.read_event() {
+            Ok(Event::Eof) | Err(_) => break,
+            Ok(event) => event,
+        }) else {
+            continue;
+        };
+        for a in e.attributes() {
+            if let Err(AttrError::Duplicated(at, _)) = a {
+                let rest = &e[at..];
+                let end = rest
+                    .iter()
+                    .position(|b| *b == b'=' || b.is_ascii_whitespace())
+                    .unwrap_or(rest.len());
+                found.push((String::from_utf8_lossy(&rest[..end]).into_owned(), position));
+            }
+        }
+    }
+    found
+}
+
+/// The replacement text of a character or predefined entity reference
+fn resolve_ref(r: &quick_xml::events::BytesRef<'_>) -> Result<Cow<'static, [u8]>, StructuralError> {
+    // a name that is not UTF-8 is not a known entity either
+    let name = String::from_utf8_lossy(r);
+    if let Ok(Some(c)) = r.resolve_char_ref() {
+        return Ok(Cow::Owned(c.to_string().into_bytes()));
+    }
+    quick_xml::escape::resolve_predefined_entity(&name)
+        .map(|s| Cow::Borrowed(s.as_bytes()))
+        .ok_or_else(|| XmlReadError::UnknownEntity(name.to_string()))
+}
+
+/// Replaces the character and predefined entity references in the attribute value `value`
+fn unescape_attr(value: Cow<'_, [u8]>) -> Result<Cow<'_, [u8]>, StructuralError> {
+    if !value.contains(&b'&') {
+        return Ok(value);
+    }
+    let mut out = Vec::with_capacity(value.len());
+    let mut rest = &*value;
+    while let Some(start) = rest.iter().position(|b| *b == b'&') {
+        out.extend_from_slice(&rest[..start]);
+        rest = &rest[start + 1..];
+        let Some(end) = rest.iter().position(|b| *b == b';') else {
+            return Err(XmlReadError::UnknownEntity(
+                String::from_utf8_lossy(rest).into_owned(),
+            ));
+        };
+        let name = std::str::from_utf8(&rest[..end]).map_err(|_| {
+            XmlReadError::UnknownEntity(String::from_utf8_lossy(&rest[..end]).into_owned())
+        })?;
+        out.extend_from_slice(&resolve_ref(&quick_xml::events::BytesRef::new(name))?);
+        rest = &rest[end + 1..];
     }
+    out.extend_from_slice(rest);
+    Ok(Cow::Owned(out))
 }
 
-fn cowfrombytes(cow: Cow<'_, [u8]>) -> Result<Cow<'_, str>, std::str::Utf8Error> {
-    match cow {
-        Cow::Borrowed(s) => Ok(Cow::Borrowed(std::str::from_utf8(s)?)),
-        Cow::Owned(s) => Ok(Cow::Owned(
-            String::from_utf8(s).map_err(|e| e.utf8_error())?,
-        )),
+/// Where a text or attribute value was read; for [`XmlReadError::Utf8`]
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct At {
+    element: Option<crate::OMKind>,
+    position: u64,
+}
+impl At {
+    const fn error(self, source: std::str::Utf8Error) -> StructuralError {
+        XmlReadError::Utf8 {
+            source,
+            position: self.position,
+            element: self.element,
+        }
     }
 }
 
-fn tryfrombytes<E: std::fmt::Display>(cow: Cow<'_, [u8]>) -> Result<Cow<'_, str>, XmlReadError<E>> {
+fn tryfrombytes(cow: Cow<'_, [u8]>, at: At) -> Result<Cow<'_, str>, StructuralError> {
     Ok(match cow {
-        Cow::Borrowed(s) => Cow::Borrowed(std::str::from_utf8(s)?),
-        Cow::Owned(s) => Cow::Owned(String::from_utf8(s).map_err(|e| e.utf8_error())?),
+        Cow::Borrowed(s) => Cow::Borrowed(std::str::from_utf8(s).map_err(|e| at.error(e))?),
+        Cow::Owned(s) => Cow::Owned(String::from_utf8(s).map_err(|e| at.error(e.utf8_error()))?),
     })
 }
 
-pub(super) trait Readable<'s, O: super::OMDeserializable<'s>> {
+/// The cdbase in effect while reading an element, with the offset of the element whose
+/// `cdbase` attribute introduced it; `None` if none did (e.g. for the
+/// [default](super::Options::default_cdbase))
+#[derive(Debug, Clone, Copy)]
+pub(super) struct CdBaseScope<'c> {
+    pub(super) cdbase: &'c str,
+    origin: Option<u64>,
+}
+impl<'c> CdBaseScope<'c> {
+    const fn new(cdbase: &'c str) -> Self {
+        Self {
+            cdbase,
+            origin: None,
+        }
+    }
+
+    /// The error for a failed conversion of the element at `position` in this scope, which
+    /// is an [`OMS`](crate::OMKind::OMS) iff `symbol`
+    pub(super) const fn conversion_error<E: std::fmt::Display>(
+        self,
+        error: E,
+        symbol: bool,
+        position: u64,
+    ) -> XmlReadError<E> {
+        if symbol {
+            XmlReadError::SymbolConversion {
+                error,
+                position,
+                cdbase_origin: self.origin,
+            }
+        } else {
+            XmlReadError::Conversion(error)
+        }
+    }
+}
+
+/// The cdbase in effect for an element with the (optional) `cdbase` attribute `a`; see
+/// [`crate::cdbase::inherit`]
+fn inherit<'a>(a: Option<Cow<'a, str>>, cdbase: &'a str) -> Cow<'a, str> {
+    a.unwrap_or(Cow::Borrowed(cdbase))
+}
+
+/// A leaf element, read completely but not converted yet; i.e. an [`OM`] without
+/// attributes, which is independent of the type it is converted into
+#[allow(clippy::upper_case_acronyms)]
+pub(super) enum Leaf<'s> {
+    OMI(crate::Int<'s>),
+    OMF {
+        float: f64,
+        lexical: Option<Cow<'s, str>>,
+    },
+    OMSTR(Cow<'s, str>),
+    OMB(Cow<'s, [u8]>),
+    OMV(Cow<'s, str>),
+    OMR(Cow<'s, str>),
+    OMS {
+        cdbase: Option<Cow<'s, str>>,
+        cd: Cow<'s, str>,
+        name: Cow<'s, str>,
+    },
+}
+impl<'s> Leaf<'s> {
+    const fn kind(&self) -> crate::OMKind {
+        match self {
+            Self::OMI(_) => crate::OMKind::OMI,
+            Self::OMF { .. } => crate::OMKind::OMF,
+            Self::OMSTR(_) => crate::OMKind::OMSTR,
+            Self::OMB(_) => crate::OMKind::OMB,
+            Self::OMV(_) => crate::OMKind::OMV,
+            Self::OMR(_) => crate::OMKind::OMR,
+            Self::OMS { .. } => crate::OMKind::OMS,
+        }
+    }
+
+    #[inline]
+    fn into_om<I>(self, attrs: Attrs<super::OMAttr<'s, I>>) -> OM<'s, I> {
+        match self {
+            Self::OMI(int) => OM::OMI { int, attrs },
+            Self::OMF { float, lexical } => OM::OMF {
+                float,
+                lexical,
+                attrs,
+            },
+            Self::OMSTR(string) => OM::OMSTR { string, attrs },
+            Self::OMB(bytes) => OM::OMB { bytes, attrs },
+            Self::OMV(name) => OM::OMV { name, attrs },
+            Self::OMR(href) => OM::OMR { href, attrs },
+            Self::OMS { cdbase, cd, name } => OM::OMS {
+                cdbase,
+                cd,
+                name,
+                attrs,
+            },
+        }
+    }
+}
+
+/// The next node of a document, as read by [`Events::node`]. `now` is the
+/// [position](Events::now) of the reader before reading the node, which its `cdbase`
+/// attribute is recorded at; `position` the one after reading its start tag.
+pub(super) enum Node<'s> {
+    /// A leaf element, with its own `cdbase` attribute if it is an `OMS`
+    Leaf {
+        leaf: Leaf<'s>,
+        cdbase: Option<Cow<'s, str>>,
+        now: u64,
+        position: u64,
+    },
+    /// The start tag of an `OMA`, `OMBIND`, `OME` or `OMATTR`, with its `cdbase` attribute
+    Start {
+        kind: crate::OMKind,
+        cdbase: Option<Cow<'s, str>>,
+        now: u64,
+        position: u64,
+    },
+    /// An `OMFOREIGN`, read completely
+    Foreign {
+        encoding: Option<Cow<'s, str>>,
+        value: Cow<'s, str>,
+    },
+    /// The end tag of the enclosing element
+    End,
+    /// Anything else that is not an error here, e.g. a comment
+    Other,
+}
+
+/// The next bound variable in an `OMBVAR` (or `OMATTR`), as read by [`Events::variable`]
+pub(super) enum Variable<'s> {
+    /// An `OMV` with the given name
+    Name(Cow<'s, str>),
+    /// The start tag of an `OMATTR` (read at `now`) with its `cdbase` attribute
+    Attributed {
+        cdbase: Option<Cow<'s, str>>,
+        now: u64,
+    },
+    /// The end tag of the enclosing element
+    End,
+}
+
+/** The XML events of a document, and the structure of
+<span style="font-variant:small-caps;">OpenMath</span> read from them: everything that does
+not depend on the type objects are deserialized into. [`Readable`] drives
+[`from_openmath`](OMDeserializable::from_openmath) on top of this.
+
+This is instantiated once per kind of input rather than once per
+[`OMDeserializable`] type (and input), which keeps the code generated for crates with many
+such types small; hence errors are [`StructuralError`]s, which [`Readable`]
+[`widen`](XmlReadError::widen)s.
+*/
+pub(super) trait Events<'s> {
     type Input;
     type E<'e>: E<'e, 's>
     where
         's: 'e,
         Self: 'e;
-    //fn clear(&mut self);
     fn now(&self) -> u64;
     fn new(input: Self::Input) -> Self;
-    fn next(&mut self) -> Result<Self::E<'_>, XmlReadError<O::Err>>;
-    fn until(&mut self, tag: quick_xml::name::QName)
-    -> Result<Cow<'s, [u8]>, XmlReadError<O::Err>>;
+    fn next(&mut self) -> Result<Self::E<'_>, StructuralError>;
+    fn until(&mut self, tag: quick_xml::name::QName) -> Result<Cow<'s, [u8]>, StructuralError>;
+
+    /// Applies the [`Options`](super::Options) that concern reading XML
+    fn configure(&mut self, options: &super::Options<'_>);
+
+    /// Skips the rest of the element `tag` whose start tag was just read, like
+    /// [`until`](Events::until) without retaining its content
+    #[inline]
+    fn skip(&mut self, tag: quick_xml::name::QName) -> Result<(), StructuralError> {
+        self.until(tag).map(drop)
+    }
+
+    /// Which attribute pairs to keep; see [`Options::attr_filter`](super::Options::attr_filter)
+    #[inline]
+    fn attr_filter(&self) -> Option<super::AttrFilter> {
+        None
+    }
+
+    /// Which names to reject; see [`Options::limits`](super::Options::limits) and
+    /// [`Options::strict_names`](super::Options::strict_names)
+    #[inline]
+    fn names(&self) -> super::NameRules {
+        super::NameRules::default()
+    }
+
+    /// How to treat inexact decimals; see [`Options::float_policy`](super::Options::float_policy)
+    #[inline]
+    fn float_policy(&self) -> super::FloatPolicy {
+        super::FloatPolicy::Nearest
+    }
+
+    /// The references to resolve; see [`Options::omr_policy`](super::Options::omr_policy)
+    #[inline]
+    fn references(&mut self) -> Option<&mut super::References> {
+        None
+    }
+
+    /// The scope of an element of the given kind (`None` for an OMOBJ) at `position`, with
+    /// the (optional) `cdbase` attribute `cdbase`, within `outer`
+    fn enter<'c>(
+        &mut self,
+        outer: CdBaseScope<'c>,
+        cdbase: Option<&'c str>,
+        element: Option<crate::OMKind>,
+        position: u64,
+    ) -> CdBaseScope<'c> {
+        let Some(cdbase) = cdbase else {
+            return outer;
+        };
+        self.cdbase_attribute(element, position, cdbase, outer.cdbase);
+        // an equivalent cdbase is kept as written, but does not change where it was set
+        let origin = if crate::cdbase::eq_normalized(cdbase, outer.cdbase) {
+            outer.origin
+        } else {
+            Some(position)
+        };
+        CdBaseScope { cdbase, origin }
+    }
+
+    /// Called for every `cdbase` attribute (of an element of the given kind at `position`,
+    /// `None` for an OMOBJ), with the cdbase that would be in effect otherwise; for
+    /// diagnostics
+    #[inline]
+    fn cdbase_attribute(
+        &mut self,
+        element: Option<crate::OMKind>,
+        position: u64,
+        cdbase: &str,
+        inherited: &str,
+    ) {
+        let _ = (element, position, cdbase, inherited);
+    }
 
-    fn need_end(&mut self) -> Result<(), XmlReadError<O::Err>> {
+    fn need_end(&mut self) -> Result<(), StructuralError> {
         self.with_next(|e: Self::E<'_>, now| {
             if matches!(e.as_ref(), Event::End(_)) {
                 Ok(())
@@ -242,10 +1038,48 @@ pub(super) trait Readable<'s, O: super::OMDeserializable<'s>> {
         })
     }
 
+    /// Reads the text content of the current element up to (and including) its end tag,
+    /// concatenating adjacent text, CDATA sections and entity references; `None` if the
+    /// element is empty. Child elements are an error.
+    fn text(&mut self, in_string: bool) -> Result<Option<Cow<'s, [u8]>>, StructuralError> {
+        let mut text: Option<Cow<'s, [u8]>> = None;
+        loop {
+            let n = self.next()?;
+            match n.as_ref() {
+                Event::End(_) => return Ok(text),
+                Event::Start(e) | Event::Empty(e) if in_string => {
+                    let element = String::from_utf8_lossy(e.name().as_ref()).into_owned();
+                    drop(n);
+                    return Err(XmlReadError::MarkupInString {
+                        element,
+                        position: self.now(),
+                    });
+                }
+                Event::Start(_) | Event::Empty(_) | Event::Eof => {
+                    drop(n);
+                    return Err(XmlReadError::UnexpectedTag(self.now()));
+                }
+                _ => (),
+            }
+            let Some(next) = n.into_text()? else {
+                // comments, processing instructions
+                continue;
+            };
+            text = Some(match text {
+                None => next,
+                Some(prev) => {
+                    let mut prev = prev.into_owned();
+                    prev.extend_from_slice(&next);
+                    Cow::Owned(prev)
+                }
+            });
+        }
+    }
+
     fn with_next<R>(
         &mut self,
-        f: impl FnOnce(Self::E<'_>, u64) -> Result<R, XmlReadError<O::Err>>,
-    ) -> Result<R, XmlReadError<O::Err>> {
+        f: impl FnOnce(Self::E<'_>, u64) -> Result<R, StructuralError>,
+    ) -> Result<R, StructuralError> {
         let now = self.now();
         let n = self.next()?;
         match n.as_ref() {
@@ -257,414 +1091,644 @@ pub(super) trait Readable<'s, O: super::OMDeserializable<'s>> {
         }
     }
 
+    /// Reads the next node, skipping whitespace; `OMFOREIGN`s are read if `foreign`, and
+    /// an error otherwise
     #[allow(clippy::too_many_lines)]
-    fn next_omforeign(
-        &mut self,
-        cdbase: &str,
-    ) -> Result<ControlFlow<crate::OMMaybeForeign<'s, O::Ret>, bool>, XmlReadError<O::Err>> {
+    fn node(&mut self, foreign: bool) -> Result<Node<'s>, StructuralError> {
+        let floats = self.float_policy();
         let now = self.now();
         let n = self.next()?;
-        match n.as_ref() {
+        let (leaf, cdbase) = match n.as_ref() {
             Event::Empty(e) => match e.local_name().as_ref() {
-                b"OMF" => Ok(ControlFlow::Break(
-                    Self::omf(n.into_empty(), cdbase, Attrs::new())
-                        .map(crate::OMMaybeForeign::OM)?,
-                )), //next!(@ret Self::omf($event, &$cdbase)?),
-                b"OMV" => Ok(ControlFlow::Break(
-                    Self::omv(n, cdbase, Attrs::new()).map(crate::OMMaybeForeign::OM)?,
-                )),
-                b"OMS" => Ok(ControlFlow::Break(
-                    Self::oms(n, cdbase, Attrs::new()).map(crate::OMMaybeForeign::OM)?,
-                )),
-                b"OMATTR" => Err(XmlReadError::NonEmptyExpectedFor("OMATTR", now)),
-                b"OME" => Err(XmlReadError::NonEmptyExpectedFor("OME", now)),
-                b"OMA" => Err(XmlReadError::NonEmptyExpectedFor("OMA", now)),
-                b"OMBIND" => Err(XmlReadError::NonEmptyExpectedFor("OMBIND", now)),
-                b"OMSTR" => Err(XmlReadError::NonEmptyExpectedFor("OMSTR", now)),
-                b"OMI" => Err(XmlReadError::NonEmptyExpectedFor("OMI", now)),
-                b"OMB" => Err(XmlReadError::NonEmptyExpectedFor("OMB", now)),
-                b"OMFOREIGN" => Err(XmlReadError::NonEmptyExpectedFor("OMFOREIGN", now)),
-                _ => Err(XmlReadError::UnexpectedTag(now)),
-            },
-            Event::Start(e) => match e.local_name().as_ref() {
-                b"OMFOREIGN" => {
-                    let encoding = n
-                        .get_attr_from_start("encoding")
-                        .map(tryfrombytes)
-                        .transpose()?;
-                    let name: smallvec::SmallVec<u8, 12> = e.name().0.into();
-                    drop(n);
-                    let end = quick_xml::name::QName(&name);
-                    let value = tryfrombytes(self.until(end)?)?;
-                    Ok(ControlFlow::Break(crate::OMMaybeForeign::Foreign {
-                        encoding,
-                        value,
-                    }))
-                }
-                b"OMI" => {
-                    drop(n);
-                    Ok(ControlFlow::Break(
-                        self.omi(cdbase, Attrs::new())
-                            .map(crate::OMMaybeForeign::OM)?,
-                    ))
+                b"OMF" => (Self::omf(&n, now, floats)?, None),
+                b"OMV" => (Self::omv(&n, now)?, None),
+                b"OMR" => (Self::omr(&n, now)?, None),
+                b"OMS" => {
+                    let (cdbase, cd, name) = n.attrs(now)?.symbol()?;
+                    (
+                        Leaf::OMS {
+                            cdbase: cdbase.clone(),
+                            cd,
+                            name,
+                        },
+                        cdbase,
+                    )
                 }
-                b"OMB" => {
+                // `<OMSTR/>`
+                b"OMSTR" => (Leaf::OMSTR(Cow::Borrowed("")), None),
+                b"OMB" => (Leaf::OMB(Cow::Borrowed(&[])), None),
+                b"OMFOREIGN" if !foreign => {
                     drop(n);
-                    Ok(ControlFlow::Break(
-                        self.omb(cdbase, Attrs::new())
-                            .map(crate::OMMaybeForeign::OM)?,
-                    ))
+                    return Err(XmlReadError::MisplacedForeign(self.now()));
                 }
-                b"OMSTR" => {
+                b"OMATTR" => return Err(XmlReadError::NonEmptyExpectedFor("OMATTR", now)),
+                b"OME" => return Err(XmlReadError::NonEmptyExpectedFor("OME", now)),
+                b"OMA" => return Err(XmlReadError::NonEmptyExpectedFor("OMA", now)),
+                b"OMBIND" => return Err(XmlReadError::NonEmptyExpectedFor("OMBIND", now)),
+                b"OMI" => return Err(XmlReadError::NonEmptyExpectedFor("OMI", now)),
+                b"OMFOREIGN" => return Err(XmlReadError::NonEmptyExpectedFor("OMFOREIGN", now)),
+                _ => return Err(XmlReadError::UnexpectedTag(now)),
+            },
+            Event::Start(e) => {
+                let kind = match e.local_name().as_ref() {
+                    b"OMFOREIGN" if foreign => {
+                        let attrs = n.attrs(now)?;
+                        let encoding = XmlAttrs::optional(attrs.encoding, attrs.at)?;
+                        let name: smallvec::SmallVec<u8, 12> = e.name().0.into();
+                        drop(n);
+                        let end = quick_xml::name::QName(&name);
+                        let at = At {
+                            element: Some(crate::OMKind::OMFOREIGN),
+                            position: now,
+                        };
+                        let value = tryfrombytes(self.until(end)?, at)?;
+                        return Ok(Node::Foreign { encoding, value });
+                    }
+                    b"OMFOREIGN" => {
+                        drop(n);
+                        return Err(XmlReadError::MisplacedForeign(self.now()));
+                    }
+                    b"OMI" => crate::OMKind::OMI,
+                    b"OMB" => crate::OMKind::OMB,
+                    b"OMSTR" => crate::OMKind::OMSTR,
+                    b"OMA" => crate::OMKind::OMA,
+                    b"OMBIND" => crate::OMKind::OMBIND,
+                    b"OME" => crate::OMKind::OME,
+                    b"OMATTR" => crate::OMKind::OMATTR,
+                    b"OMS" => return Err(XmlReadError::EmptyExpectedFor("OMS", now)),
+                    b"OMF" => return Err(XmlReadError::EmptyExpectedFor("OMF", now)),
+                    b"OMV" => return Err(XmlReadError::EmptyExpectedFor("OMV", now)),
+                    b"OMR" => return Err(XmlReadError::EmptyExpectedFor("OMR", now)),
+                    _ => return Err(XmlReadError::UnexpectedTag(now)),
+                };
+                if matches!(
+                    kind,
+                    crate::OMKind::OMI | crate::OMKind::OMB | crate::OMKind::OMSTR
+                ) {
                     drop(n);
-                    Ok(ControlFlow::Break(
-                        self.omstr(cdbase, Attrs::new())
-                            .map(crate::OMMaybeForeign::OM)?,
-                    ))
+                    let position = self.now();
+                    let leaf = match kind {
+                        crate::OMKind::OMI => self.omi()?,
+                        crate::OMKind::OMB => self.omb(position)?,
+                        _ => self.omstr(position)?,
+                    };
+                    return Ok(Node::Leaf {
+                        leaf,
+                        cdbase: None,
+                        now,
+                        position,
+                    });
                 }
-                b"OMA" => {
-                    let a = n
-                        .get_attr_from_start("cdbase")
-                        .map(cowfrombytes)
-                        .transpose()?;
-                    let cdbase = a.unwrap_or(Cow::Borrowed(cdbase));
-                    drop(n);
-                    Ok(ControlFlow::Break(
-                        self.oma(&cdbase, now, Attrs::new())
-                            .map(crate::OMMaybeForeign::OM)?,
-                    ))
-                }
-                b"OMBIND" => {
-                    let a = n
-                        .get_attr_from_start("cdbase")
-                        .map(cowfrombytes)
-                        .transpose()?;
-                    let cdbase = a.unwrap_or(Cow::Borrowed(cdbase));
-                    drop(n);
-                    Ok(ControlFlow::Break(
-                        self.ombind(&cdbase, now, Attrs::new())
-                            .map(crate::OMMaybeForeign::OM)?,
-                    ))
-                }
-                b"OME" => {
-                    let a = n
-                        .get_attr_from_start("cdbase")
-                        .map(cowfrombytes)
-                        .transpose()?;
-                    let cdbase = a.unwrap_or(Cow::Borrowed(cdbase));
-                    drop(n);
-                    Ok(ControlFlow::Break(
-                        self.ome(&cdbase, now, Attrs::new())
-                            .map(crate::OMMaybeForeign::OM)?,
-                    ))
-                }
-                b"OMATTR" => {
-                    let a = n
-                        .get_attr_from_start("cdbase")
-                        .map(cowfrombytes)
-                        .transpose()?;
-                    let cdbase = a.unwrap_or(Cow::Borrowed(cdbase));
-                    drop(n);
-                    Ok(ControlFlow::Break(
-                        self.omattr(&cdbase, Attrs::new())
-                            .map(crate::OMMaybeForeign::OM)?,
-                    ))
-                }
-                b"OMS" => Err(XmlReadError::EmptyExpectedFor("OMS", now)),
-                b"OMF" => Err(XmlReadError::EmptyExpectedFor("OMF", now)),
-                b"OMV" => Err(XmlReadError::EmptyExpectedFor("OMV", now)),
-                _ => Err(XmlReadError::UnexpectedTag(now)),
-            },
+                let cdbase = n.cdbase(now)?;
+                drop(n);
+                return Ok(Node::Start {
+                    kind,
+                    cdbase,
+                    now,
+                    position: self.now(),
+                });
+            }
             Event::Text(t) if t.as_ref().iter().all(u8::is_ascii_whitespace) => {
                 drop(n);
-                self.next_omforeign(cdbase)
+                return self.node(foreign);
             }
-            Event::Eof => Err(XmlReadError::NoObject),
-            Event::End(_) => Ok(ControlFlow::Continue(true)),
-            _ => Ok(ControlFlow::Continue(false)),
+            Event::Eof => return Err(XmlReadError::NoObject),
+            Event::End(_) => return Ok(Node::End),
+            _ => return Ok(Node::Other),
+        };
+        drop(n);
+        Ok(Node::Leaf {
+            leaf,
+            cdbase,
+            now,
+            position: self.now(),
+        })
+    }
+
+    fn omi(&mut self) -> Result<Leaf<'s>, StructuralError> {
+        let Some(i) = self.text(false)? else {
+            return Err(XmlReadError::ExpectedText);
+        };
+        let invalid =
+            |b: &[u8]| XmlReadError::InvalidInteger(String::from_utf8_lossy(b.trim_ascii()).into());
+        let trimmed = i.trim_ascii();
+        if trimmed.starts_with(b"x") || trimmed.starts_with(b"-x") {
+            // hex digits are ASCII, so anything else is invalid anyway
+            let int = std::str::from_utf8(trimmed)
+                .ok()
+                .and_then(crate::Int::from_hex)
+                .ok_or_else(|| invalid(trimmed))?;
+            return Ok(Leaf::OMI(int));
         }
+        // the borrowed text of the input stays borrowed; only text read from a reader (or
+        // unescaped) needs to be copied, and only if it is a big integer
+        let int = match i {
+            Cow::Borrowed(b) => crate::Int::parse_bytes(b).map_err(|_| invalid(b))?,
+            Cow::Owned(b) => crate::Int::parse_bytes(&b)
+                .map_err(|_| invalid(&b))?
+                .into_owned(),
+        };
+        Ok(Leaf::OMI(int))
     }
 
-    fn handle_next(
+    fn omb(&mut self, now: u64) -> Result<Leaf<'s>, StructuralError> {
+        // `<OMB></OMB>` is how empty bytes are written
+        let i = self.text(false)?.unwrap_or_default();
+        let bytes = crate::base64::decode_slice(&i).map_err(|source| XmlReadError::Base64 {
+            source,
+            position: now,
+        })?;
+        Ok(Leaf::OMB(bytes.into()))
+    }
+
+    fn omf(
+        event: &Self::E<'_>,
+        now: u64,
+        policy: super::FloatPolicy,
+    ) -> Result<Leaf<'s>, StructuralError> {
+        let xml_attrs = event.attrs(now)?;
+        let Some(v) = xml_attrs.dec else {
+            return Err(if xml_attrs.hex.is_some() {
+                XmlReadError::Hex
+            } else {
+                XmlReadError::ExpectedAttribute("dec")
+            });
+        };
+        let s = tryfrombytes(v, xml_attrs.at)?;
+        let float: f64 = s
+            .parse()
+            .map_err(|_| XmlReadError::InvalidFloat(s.to_string()))?;
+        let lexical = policy
+            .apply(s, float)
+            .map_err(|s| XmlReadError::InexactFloat(s.into_owned()))?;
+        Ok(Leaf::OMF { float, lexical })
+    }
+
+    fn omstr(&mut self, now: u64) -> Result<Leaf<'s>, StructuralError> {
+        let string = match self.text(true)? {
+            Some(s) => tryfrombytes(
+                s,
+                At {
+                    element: Some(crate::OMKind::OMSTR),
+                    position: now,
+                },
+            )?,
+            // `<OMSTR></OMSTR>`
+            None => Cow::Borrowed(""),
+        };
+        Ok(Leaf::OMSTR(string))
+    }
+
+    fn omv(event: &Self::E<'_>, now: u64) -> Result<Leaf<'s>, StructuralError> {
+        let xml_attrs = event.attrs(now)?;
+        let name = XmlAttrs::required(xml_attrs.name, "name", xml_attrs.at)?;
+        Ok(Leaf::OMV(name))
+    }
+
+    fn omr(event: &Self::E<'_>, now: u64) -> Result<Leaf<'s>, StructuralError> {
+        let xml_attrs = event.attrs(now)?;
+        let href = XmlAttrs::required(xml_attrs.href, "href", xml_attrs.at)?;
+        Ok(Leaf::OMR(href))
+    }
+
+    /// Reads the start tag of the OMOBJ, skipping whatever may precede it; returns its
+    /// `cdbase` attribute, version and id, and the position it was read at
+    fn omobj(
         &mut self,
-        cdbase: &str,
-        attrs: Attrs<Attr<'s, O>>,
-    ) -> Result<ControlFlow<O::Ret, bool>, XmlReadError<O::Err>> {
-        let now = self.now();
-        let n = self.next()?;
-        match n.as_ref() {
-            Event::Empty(e) => match e.local_name().as_ref() {
-                b"OMF" => Ok(ControlFlow::Break(Self::omf(
-                    n.into_empty(),
-                    cdbase,
-                    attrs,
-                )?)), //next!(@ret Self::omf($event, &$cdbase)?),
-                b"OMV" => Ok(ControlFlow::Break(Self::omv(n, cdbase, attrs)?)),
-                b"OMS" => Ok(ControlFlow::Break(Self::oms(n, cdbase, attrs)?)),
-                b"OME" => Err(XmlReadError::NonEmptyExpectedFor("OME", now)),
-                b"OMA" => Err(XmlReadError::NonEmptyExpectedFor("OMA", now)),
-                b"OMBIND" => Err(XmlReadError::NonEmptyExpectedFor("OMBIND", now)),
-                b"OMSTR" => Err(XmlReadError::NonEmptyExpectedFor("OMSTR", now)),
-                b"OMI" => Err(XmlReadError::NonEmptyExpectedFor("OMI", now)),
-                b"OMB" => Err(XmlReadError::NonEmptyExpectedFor("OMB", now)),
-                b"OMATTR" => Err(XmlReadError::NonEmptyExpectedFor("OMATTR", now)),
-                _ => Err(XmlReadError::UnexpectedTag(now)),
-            },
-            Event::Start(e) => match e.local_name().as_ref() {
-                b"OMI" => {
-                    drop(n);
-                    Ok(ControlFlow::Break(self.omi(cdbase, attrs)?))
-                }
-                b"OMB" => {
-                    drop(n);
-                    Ok(ControlFlow::Break(self.omb(cdbase, attrs)?))
-                }
-                b"OMSTR" => {
-                    drop(n);
-                    Ok(ControlFlow::Break(self.omstr(cdbase, attrs)?))
-                }
-                b"OMA" => {
-                    let a = n
-                        .get_attr_from_start("cdbase")
-                        .map(cowfrombytes)
-                        .transpose()?;
-                    let cdbase = a.unwrap_or(Cow::Borrowed(cdbase));
-                    drop(n);
-                    Ok(ControlFlow::Break(self.oma(&cdbase, now, attrs)?))
-                }
-                b"OMBIND" => {
-                    let a = n
-                        .get_attr_from_start("cdbase")
-                        .map(cowfrombytes)
-                        .transpose()?;
-                    let cdbase = a.unwrap_or(Cow::Borrowed(cdbase));
+        policy: super::VersionPolicy,
+    ) -> Result<
+        (
+            Option<Cow<'s, str>>,
+            Option<Cow<'s, str>>,
+            Option<Cow<'s, str>>,
+            u64,
+        ),
+        StructuralError,
+    > {
+        // whether there was only whitespace so far
+        let mut empty = true;
+        loop {
+            let now = self.now();
+            let n = self.next()?;
+            match n.as_ref() {
+                Event::Start(s) if s.local_name().as_ref() == b"OMOBJ" => {
+                    let attrs = n.attrs(now)?;
+                    let a = XmlAttrs::optional(attrs.cdbase, attrs.at)?;
+                    let version = XmlAttrs::optional(attrs.version, attrs.at)?;
+                    let id = XmlAttrs::optional(attrs.id, attrs.at)?;
                     drop(n);
-                    Ok(ControlFlow::Break(self.ombind(&cdbase, now, attrs)?))
+                    if !policy.check(version.as_deref()) {
+                        return Err(XmlReadError::UnsupportedVersion(
+                            version.map(Cow::into_owned).unwrap_or_default(),
+                        ));
+                    }
+                    return Ok((a, version, id, now));
                 }
-                b"OME" => {
-                    let a = n
-                        .get_attr_from_start("cdbase")
-                        .map(cowfrombytes)
-                        .transpose()?;
-                    let cdbase = a.unwrap_or(Cow::Borrowed(cdbase));
-                    drop(n);
-                    Ok(ControlFlow::Break(self.ome(&cdbase, now, attrs)?))
+                Event::Text(t) if !t.as_ref().iter().all(u8::is_ascii_whitespace) => {
+                    return Err(XmlReadError::UnexpectedTag(now));
                 }
-                b"OMATTR" => {
-                    let a = n
-                        .get_attr_from_start("cdbase")
-                        .map(cowfrombytes)
-                        .transpose()?;
-                    let cdbase = a.unwrap_or(Cow::Borrowed(cdbase));
+                Event::Text(_) => (),
+                Event::Eof if empty => return Err(XmlReadError::EmptyInput),
+                Event::Eof => return Err(XmlReadError::MissingElement("OMOBJ")),
+                Event::End(_) | Event::Empty(_) => return Err(XmlReadError::UnexpectedTag(now)),
+                _ => empty = false,
+            }
+        }
+    }
+
+    /// Skips whitespace, comments and processing instructions up to the end tag of the
+    /// OMOBJ whose object was just read; tolerates a missing end tag at the end of the input
+    fn end_obj(&mut self) -> Result<(), StructuralError> {
+        loop {
+            let n = self.next()?;
+            match n.as_ref() {
+                Event::End(_) | Event::Eof => return Ok(()),
+                e if is_ignorable(e) => (),
+                _ => {
                     drop(n);
-                    Ok(ControlFlow::Break(self.omattr(&cdbase, attrs)?))
+                    return Err(XmlReadError::TrailingContent(self.now()));
                 }
-                b"OMS" => Err(XmlReadError::EmptyExpectedFor("OMS", now)),
-                b"OMF" => Err(XmlReadError::EmptyExpectedFor("OMF", now)),
-                b"OMV" => Err(XmlReadError::EmptyExpectedFor("OMV", now)),
-                _ => Err(XmlReadError::UnexpectedTag(now)),
-            },
-            Event::Text(t) if t.as_ref().iter().all(u8::is_ascii_whitespace) => {
-                drop(n);
-                self.handle_next(cdbase, attrs)
             }
-            Event::Eof => Err(XmlReadError::NoObject),
-            Event::End(_) => Ok(ControlFlow::Continue(true)),
-            _ => Ok(ControlFlow::Continue(false)),
         }
     }
 
-    fn read_obj(mut self) -> Result<O, XmlReadError<O::Err>>
-    where
-        Self: Sized,
-    {
-        let cdbase = crate::CD_BASE;
+    /// Fails with [`XmlReadError::TrailingContent`] unless only whitespace, comments and
+    /// processing instructions remain
+    fn finish(&mut self) -> Result<(), StructuralError> {
         loop {
-            let now = self.now();
             let n = self.next()?;
             match n.as_ref() {
-                Event::Start(s) if s.name().0 == b"OMOBJ" => {
-                    let a = n
-                        .get_attr_from_start("cdbase")
-                        .map(cowfrombytes)
-                        .transpose()?;
-                    let cdbase = a.unwrap_or(Cow::Borrowed(cdbase));
+                Event::Eof => return Ok(()),
+                e if is_ignorable(e) => (),
+                _ => {
                     drop(n);
-                    return self.read(Some(&*cdbase));
-                }
-                Event::Text(t) if !t.as_ref().iter().all(u8::is_ascii_whitespace) => {
-                    return Err(XmlReadError::UnexpectedTag(now));
+                    return Err(XmlReadError::TrailingContent(self.now()));
                 }
-                Event::Eof => return Err(XmlReadError::NoObject),
-                Event::End(_) | Event::Empty(_) => return Err(XmlReadError::UnexpectedTag(now)),
-                _ => (),
             }
         }
     }
 
-    fn read(mut self, cdbase: Option<&str>) -> Result<O, XmlReadError<O::Err>>
-    where
-        Self: Sized,
-    {
-        let cdbase = cdbase.unwrap_or(crate::CD_BASE);
+    /// Reads the symbol heading an `OME` read at `now`; returns its `cdbase` attribute, cd,
+    /// name and the position it was read at
+    fn ome_symbol(
+        &mut self,
+        now: u64,
+    ) -> Result<(Option<Cow<'s, str>>, Cow<'s, str>, Cow<'s, str>, u64), StructuralError> {
+        self.with_next(|event: Self::E<'_>, at| match event.as_ref() {
+            Event::Empty(e) if e.local_name().as_ref() == b"OMS" => {
+                let (ocdbase, cd, name) = event.attrs(now)?.symbol()?;
+                Ok((ocdbase, cd, name, at))
+            }
+            _ => Err(XmlReadError::UnexpectedTag(now)),
+        })
+    }
+
+    /// Reads the `OMATP` of an `OMATTR`; returns whether it has pairs, i.e. is not empty
+    fn omatp(&mut self) -> Result<bool, StructuralError> {
+        self.with_next(|n: Self::E<'_>, now| match n.as_ref() {
+            Event::Empty(e) if e.local_name().as_ref() == b"OMATP" => {
+                drop(n);
+                Ok(false)
+            }
+            Event::Start(e) if e.local_name().as_ref() == b"OMATP" => {
+                drop(n);
+                Ok(true)
+            }
+            _ => Err(XmlReadError::UnexpectedTag(now)),
+        })
+    }
+
+    /// Reads the key of the next pair of an `OMATP`, with the positions of the reader
+    /// before and after; `None` at the end of the `OMATP`
+    fn attribute_key(
+        &mut self,
+    ) -> Result<Option<(crate::SymbolRef<'s>, u64, u64)>, StructuralError> {
         loop {
-            if let ControlFlow::Break(b) = self.handle_next(cdbase, Attrs::new())? {
-                return b.try_into().map_err(|_| XmlReadError::NotFullyConvertible);
+            let now = self.now();
+            let next = self.next()?;
+            match next.as_ref() {
+                Event::End(_) => return Ok(None),
+                Event::Empty(event) if event.local_name().as_ref() == b"OMS" => {
+                    let (cdbase, cd, name) = next.attrs(now)?.symbol()?;
+                    drop(next);
+                    return Ok(Some((
+                        crate::SymbolRef { cdbase, cd, name },
+                        now,
+                        self.now(),
+                    )));
+                }
+                Event::Text(t) if t.as_ref().iter().all(u8::is_ascii_whitespace) => (),
+                _ => return Err(XmlReadError::UnexpectedTag(now)),
+            }
+        }
+    }
+
+    /// Skips the value of an attribute pair
+    fn skip_value(&mut self) -> Result<(), StructuralError> {
+        let tag = self.with_next(|n: Self::E<'_>, now| match n.as_ref() {
+            Event::Start(e) => Ok(Some(e.name().as_ref().to_vec())),
+            Event::Empty(_) => Ok(None),
+            Event::End(_) => Err(XmlReadError::AttributeValue(now)),
+            _ => Err(XmlReadError::UnexpectedTag(now)),
+        })?;
+        if let Some(tag) = tag {
+            self.skip(quick_xml::name::QName(&tag))?;
+        }
+        Ok(())
+    }
+
+    /// Skips the next element without checking it, where [`node`](Events::node) would
+    /// read it
+    fn skip_child(&mut self) -> Result<ControlFlow<(), bool>, StructuralError> {
+        let n = self.next()?;
+        let tag = match n.as_ref() {
+            Event::Start(e) => e.name().as_ref().to_vec(),
+            Event::Empty(_) => return Ok(ControlFlow::Break(())),
+            Event::Text(t) if t.as_ref().iter().all(u8::is_ascii_whitespace) => {
+                drop(n);
+                return self.skip_child();
+            }
+            Event::Eof => return Err(XmlReadError::NoObject),
+            Event::End(_) => return Ok(ControlFlow::Continue(true)),
+            _ => return Ok(ControlFlow::Continue(false)),
+        };
+        drop(n);
+        self.skip(quick_xml::name::QName(&tag))?;
+        Ok(ControlFlow::Break(()))
+    }
+
+    /// Reads the `OMBVAR` of an `OMBIND`; returns whether it has variables, i.e. is not
+    /// empty
+    fn ombvar(&mut self) -> Result<bool, StructuralError> {
+        self.with_next(|n: Self::E<'_>, now| match n.as_ref() {
+            Event::Empty(e) if e.local_name().as_ref() == b"OMBVAR" => {
+                drop(n);
+                Ok(false)
+            }
+            Event::Start(e) if e.local_name().as_ref() == b"OMBVAR" => {
+                drop(n);
+                Ok(true)
+            }
+            _ => Err(XmlReadError::MissingOMBVAR(now)),
+        })
+    }
+
+    /// Reads the next bound variable, skipping whitespace
+    fn variable(&mut self) -> Result<Variable<'s>, StructuralError> {
+        let now = self.now();
+        let next = self.next()?;
+        match next.as_ref() {
+            Event::End(_) => Ok(Variable::End),
+            Event::Start(e) if e.local_name().as_ref() == b"OMATTR" => Ok(Variable::Attributed {
+                cdbase: next.cdbase(now)?,
+                now,
+            }),
+            Event::Empty(e) if e.local_name().as_ref() == b"OMV" => {
+                let xml_attrs = next.attrs(now)?;
+                Ok(Variable::Name(XmlAttrs::required(
+                    xml_attrs.name,
+                    "name",
+                    xml_attrs.at,
+                )?))
+            }
+            Event::Text(t) if t.as_ref().iter().all(u8::is_ascii_whitespace) => {
+                drop(next);
+                self.variable()
             }
+            _ => Err(XmlReadError::UnexpectedTag(now)),
         }
     }
+}
+
+/// Reads objects of type `O` from [`Events`]: only the parts that build [`OM`]s and convert
+/// them, which have to be instantiated for every `O`, are here.
+pub(super) trait Readable<'s, O: super::OMDeserializable<'s>>: Events<'s> {
+    /// The number of conversion errors [`recover`](Readable::recover)ed from so far
+    #[inline]
+    fn recovered(&self) -> usize {
+        0
+    }
 
-    fn omi(
+    /// Called if [`from_openmath`](OMDeserializable::from_openmath) fails (with `error`,
+    /// i.e. [`Conversion`](XmlReadError::Conversion) or
+    /// [`SymbolConversion`](XmlReadError::SymbolConversion)) for the element at `position`;
+    /// `in_failed_subtree` indicates that some child of that element had already been
+    /// recovered from. By default, conversion errors are fatal.
+    #[inline]
+    fn recover(
         &mut self,
-        cdbase: &str,
-        attrs: Attrs<Attr<'s, O>>,
+        error: XmlReadError<O::Err>,
+        position: u64,
+        in_failed_subtree: bool,
     ) -> Result<O::Ret, XmlReadError<O::Err>> {
-        let int = self.with_next(|e: Self::E<'_>, _| {
-            let Event::Text(i) = e.into_ref() else {
-                return Err(XmlReadError::ExpectedText);
-            };
-            let s = std::str::from_utf8(&i)?;
-            if s.starts_with('x') || s.starts_with("-x") {
-                return Err(XmlReadError::Hex);
-            }
-            let int = crate::Int::try_from(s)
-                .map_err(|()| XmlReadError::InvalidInteger(s.to_string()))?
-                .into_owned();
-            Ok(int)
-        })?;
-        self.need_end()?;
+        let _ = (position, in_failed_subtree);
+        Err(error)
+    }
 
-        O::from_openmath(OM::OMI { int, attrs }, cdbase).map_err(XmlReadError::Conversion)
+    /// Converts a leaf element
+    #[inline]
+    fn convert(
+        &mut self,
+        om: OM<'s, O::Ret>,
+        scope: CdBaseScope<'_>,
+        position: u64,
+    ) -> Result<O::Ret, XmlReadError<O::Err>> {
+        let recovered = self.recovered();
+        self.convert_node(om, scope, position, recovered)
     }
 
-    fn omb(
+    /// Converts an element whose children were read after `recovered` many errors had
+    /// already been recovered from
+    fn convert_node(
         &mut self,
-        cdbase: &str,
-        attrs: Attrs<Attr<'s, O>>,
+        om: OM<'s, O::Ret>,
+        scope: CdBaseScope<'_>,
+        position: u64,
+        recovered: usize,
     ) -> Result<O::Ret, XmlReadError<O::Err>> {
-        use crate::base64::Base64Decodable;
-        let bytes = self.with_next(|e: Self::E<'_>, _| {
-            let Event::Text(i) = e.into_ref() else {
-                return Err(XmlReadError::ExpectedText);
-            };
-            let b: Result<Vec<u8>, _> = i.as_ref().iter().copied().decode_base64().flat().collect();
-            Ok(b?)
-        })?;
-        self.need_end()?;
-        O::from_openmath(
-            OM::OMB {
-                bytes: bytes.into(),
-                attrs,
-            },
-            cdbase,
-        )
-        .map_err(XmlReadError::Conversion)
+        self.names()
+            .check_om(&om)
+            .map_err(|error| XmlReadError::InvalidName { error, position })?;
+        let symbol = matches!(om, OM::OMS { .. });
+        let converted = super::References::convert::<O>(self.references(), om, scope.cdbase)
+            .map_err(|error| XmlReadError::Reference { error, position })?;
+        match converted {
+            Ok(r) => Ok(r),
+            Err(error) => {
+                let in_failed_subtree = self.recovered() > recovered;
+                let error = scope.conversion_error(error, symbol, position);
+                self.recover(error, position, in_failed_subtree)
+            }
+        }
     }
 
-    #[allow(clippy::needless_pass_by_value)]
-    fn omf(
-        event: BytesStart<'_>,
-        cdbase: &str,
+    /// Reads the object `node` starts (with the attributes `attrs`) in `scope`
+    fn object(
+        &mut self,
+        node: Node<'s>,
+        scope: CdBaseScope<'_>,
         attrs: Attrs<Attr<'s, O>>,
-    ) -> Result<O::Ret, XmlReadError<O::Err>> {
-        let Some(v) = event.attributes().find_map(|a| {
-            a.ok().and_then(|a| {
-                if a.key.as_ref() == b"hex" {
-                    Some(None)
-                } else if a.key.as_ref() == b"dec" {
-                    Some(Some(a))
-                } else {
-                    None
-                }
-            })
-        }) else {
-            return Err(XmlReadError::ExpectedAttribute("dec"));
-        };
-        let Some(v) = v else {
-            return Err(XmlReadError::Hex);
-        };
-        let s = std::str::from_utf8(&v.value)?;
-        let float: f64 = s
-            .parse()
-            .map_err(|_| XmlReadError::InvalidFloat(s.to_string()))?;
-        O::from_openmath(OM::OMF { float, attrs }, cdbase).map_err(XmlReadError::Conversion)
+    ) -> Result<ControlFlow<O::Ret, bool>, XmlReadError<O::Err>> {
+        match node {
+            Node::Leaf {
+                leaf,
+                cdbase,
+                now,
+                position,
+            } => {
+                #[cfg(feature = "tracing")]
+                let _span = tracing::trace_span!("element", kind = %leaf.kind(), cdbase = scope.cdbase, offset = now).entered();
+                let scope = self.enter(scope, cdbase.as_deref(), Some(leaf.kind()), now);
+                Ok(ControlFlow::Break(self.convert(
+                    leaf.into_om(attrs),
+                    scope,
+                    position,
+                )?))
+            }
+            Node::Start {
+                kind,
+                cdbase,
+                now,
+                position,
+            } => {
+                #[cfg(feature = "tracing")]
+                let _span =
+                    tracing::trace_span!("element", %kind, cdbase = scope.cdbase, offset = now)
+                        .entered();
+                let scope = self.enter(scope, cdbase.as_deref(), Some(kind), now);
+                Ok(ControlFlow::Break(match kind {
+                    crate::OMKind::OMA => self.oma(scope, position, attrs)?,
+                    crate::OMKind::OMBIND => self.ombind(scope, position, attrs)?,
+                    crate::OMKind::OME => self.ome(scope, position, attrs)?,
+                    _ => self.omattr(scope, attrs)?,
+                }))
+            }
+            // only read where allowed
+            Node::Foreign { .. } => Err(XmlReadError::MisplacedForeign(self.now())),
+            Node::End => Ok(ControlFlow::Continue(true)),
+            Node::Other => Ok(ControlFlow::Continue(false)),
+        }
     }
 
-    fn omstr(
+    fn next_omforeign(
         &mut self,
-        cdbase: &str,
+        scope: CdBaseScope<'_>,
+    ) -> Result<ControlFlow<crate::OMMaybeForeign<'s, O::Ret>, bool>, XmlReadError<O::Err>> {
+        match self.node(true).map_err(XmlReadError::widen)? {
+            Node::Foreign { encoding, value } => {
+                Ok(ControlFlow::Break(crate::OMMaybeForeign::Foreign {
+                    encoding,
+                    value,
+                }))
+            }
+            node => Ok(self
+                .object(node, scope, Attrs::new())?
+                .map_break(crate::OMMaybeForeign::OM)),
+        }
+    }
+
+    fn handle_next(
+        &mut self,
+        scope: CdBaseScope<'_>,
         attrs: Attrs<Attr<'s, O>>,
-    ) -> Result<O::Ret, XmlReadError<O::Err>> {
-        let cow = self.next()?.into_str()?;
-        let string = tryfrombytes(cow)?;
-        self.need_end()?;
-        O::from_openmath(OM::OMSTR { string, attrs }, cdbase).map_err(XmlReadError::Conversion)
+    ) -> Result<ControlFlow<O::Ret, bool>, XmlReadError<O::Err>> {
+        let node = self.node(false).map_err(XmlReadError::widen)?;
+        self.object(node, scope, attrs)
     }
 
-    fn omv(
-        event: Self::E<'_>,
+    fn read_obj(
+        &mut self,
+        policy: super::VersionPolicy,
         cdbase: &str,
-        attrs: Attrs<Attr<'s, O>>,
-    ) -> Result<O::Ret, XmlReadError<O::Err>> {
-        let Some(cow) = event.get_attr_from_empty("name") else {
-            return Err(XmlReadError::ExpectedAttribute("name"));
-        };
-        let name = tryfrombytes(cow)?;
-        O::from_openmath(OM::OMV { name, attrs }, cdbase).map_err(XmlReadError::Conversion)
+    ) -> Result<super::OMObject<'s, O>, XmlReadError<O::Err>>
+    where
+        Self: Sized,
+    {
+        let object = self.read_obj_prefix(policy, cdbase)?;
+        self.finish().map_err(XmlReadError::widen)?;
+        Ok(object)
     }
 
-    fn oms(
-        event: Self::E<'_>,
+    /// Like [`read_obj`](Self::read_obj), but leaves whatever follows the OMOBJ unread
+    fn read_obj_prefix(
+        &mut self,
+        policy: super::VersionPolicy,
         cdbase: &str,
-        attrs: Attrs<Attr<'s, O>>,
-    ) -> Result<O::Ret, XmlReadError<O::Err>> {
-        let Some(name) = event.get_attr_from_empty("name") else {
-            return Err(XmlReadError::ExpectedAttribute("name"));
-        };
-        let name = tryfrombytes(name)?;
+    ) -> Result<super::OMObject<'s, O>, XmlReadError<O::Err>>
+    where
+        Self: Sized,
+    {
+        let (a, version, id, now) = self.omobj(policy).map_err(XmlReadError::widen)?;
+        let scope = self.enter(CdBaseScope::new(cdbase), a.as_deref(), None, now);
+        let object = self.read_in(scope)?;
+        self.end_obj().map_err(XmlReadError::widen)?;
+        Ok(super::OMObject {
+            object,
+            version,
+            id,
+            default_cdbase: None,
+        })
+    }
 
-        let Some(cd_name) = event.get_attr_from_empty("cd") else {
-            return Err(XmlReadError::ExpectedAttribute("cd"));
-        };
-        let cd_name = tryfrombytes(cd_name)?;
-
-        if let Some(s) = event.borrow_attr("cdbase") {
-            let s = std::str::from_utf8(s.as_ref())?;
-            O::from_openmath(
-                OM::OMS {
-                    cd: cd_name,
-                    name,
-                    attrs,
-                },
-                s,
-            )
-            .map_err(XmlReadError::Conversion)
-        } else {
-            O::from_openmath(
-                OM::OMS {
-                    cd: cd_name,
-                    name,
-                    attrs,
-                },
-                cdbase,
-            )
-            .map_err(XmlReadError::Conversion)
+    /// Reads an object that makes up the whole (rest of the) input, up to whitespace,
+    /// comments and processing instructions
+    fn read(&mut self, cdbase: Option<&str>) -> Result<O, XmlReadError<O::Err>>
+    where
+        Self: Sized,
+    {
+        let object = self.read_prefix(cdbase)?;
+        self.finish().map_err(XmlReadError::widen)?;
+        Ok(object)
+    }
+
+    /// Reads the next object, leaving whatever follows it unread
+    fn read_prefix(&mut self, cdbase: Option<&str>) -> Result<O, XmlReadError<O::Err>>
+    where
+        Self: Sized,
+    {
+        let scope = CdBaseScope::new(cdbase.unwrap_or(crate::CD_BASE));
+        // whether there was only whitespace so far, which `node` skips by itself
+        let mut empty = true;
+        loop {
+            match self.handle_next(scope, Attrs::new()) {
+                Ok(ControlFlow::Break(b)) => {
+                    return b.try_into().map_err(|_| XmlReadError::NotFullyConvertible);
+                }
+                Ok(ControlFlow::Continue(_)) => empty = false,
+                Err(XmlReadError::NoObject) if empty => return Err(XmlReadError::EmptyInput),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Reads the next object in `scope`, leaving whatever follows it unread
+    fn read_in(&mut self, scope: CdBaseScope<'_>) -> Result<O, XmlReadError<O::Err>>
+    where
+        Self: Sized,
+    {
+        loop {
+            if let ControlFlow::Break(b) = self.handle_next(scope, Attrs::new())? {
+                return b.try_into().map_err(|_| XmlReadError::NotFullyConvertible);
+            }
         }
     }
 
     fn oma(
         &mut self,
-        cdbase: &str,
+        scope: CdBaseScope<'_>,
         off: u64,
         attrs: Attrs<Attr<'s, O>>,
     ) -> Result<O::Ret, XmlReadError<O::Err>> {
-        let ControlFlow::Break(head) = self.handle_next(cdbase, Attrs::new())? else {
+        let recovered = self.recovered();
+        let ControlFlow::Break(head) = self.child(scope, crate::OMKind::OMA, 0)? else {
             return Err(XmlReadError::NonEmptyExpectedFor("OMA Applicant", off));
         };
 
         let mut args = Args::new();
         loop {
-            match self.handle_next(cdbase, Attrs::new())? {
+            #[cfg(feature = "tracing")]
+            let _span = tracing::trace_span!("argument", index = args.len()).entered();
+            match self.child(scope, crate::OMKind::OMA, args.len() + 1)? {
                 ControlFlow::Break(a) => args.push(a),
                 ControlFlow::Continue(true) => break,
                 ControlFlow::Continue(false) => {
@@ -673,52 +1737,47 @@ pub(super) trait Readable<'s, O: super::OMDeserializable<'s>> {
             }
         }
 
-        O::from_openmath(
+        self.convert_node(
             OM::OMA {
                 applicant: head,
                 arguments: args,
                 attrs,
             },
-            cdbase,
+            scope,
+            off,
+            recovered,
         )
-        .map_err(XmlReadError::Conversion)
     }
 
     fn ome(
         &mut self,
-        cdbase: &str,
+        scope: CdBaseScope<'_>,
         now: u64,
         attrs: Attrs<Attr<'s, O>>,
     ) -> Result<O::Ret, XmlReadError<O::Err>> {
-        let (ocdbase, cd, name) = self.with_next(|event: Self::E<'_>, _| match event.as_ref() {
-            Event::Empty(e) if e.local_name().as_ref() == b"OMS" => {
-                let Some(name) = event.get_attr_from_empty("name") else {
-                    return Err(XmlReadError::ExpectedAttribute("name"));
-                };
-                let name = tryfrombytes(name)?;
-                let Some(cd_name) = event.get_attr_from_empty("cd") else {
-                    return Err(XmlReadError::ExpectedAttribute("cd"));
-                };
-                let cd_name = tryfrombytes(cd_name)?;
-                let cdbase = event
-                    .get_attr_from_empty("cdbase")
-                    .map(tryfrombytes)
-                    .transpose()?;
-                Ok((cdbase, cd_name, name))
-            }
-            _ => Err(XmlReadError::UnexpectedTag(now)),
-        })?;
+        let recovered = self.recovered();
+        let (ocdbase, cd, name, at) = self.ome_symbol(now).map_err(XmlReadError::widen)?;
+        self.enter(scope, ocdbase.as_deref(), Some(crate::OMKind::OMS), at);
 
         let mut arguments = Vec::with_capacity(2);
         loop {
-            match self.next_omforeign(cdbase)? {
+            #[cfg(feature = "tracing")]
+            let _span = tracing::trace_span!("argument", index = arguments.len()).entered();
+            let next = match O::before_child(crate::OMKind::OME, arguments.len(), scope.cdbase) {
+                Visit::Descend => self.next_omforeign(scope)?,
+                Visit::Skip(r) => self
+                    .skip_child()
+                    .map_err(XmlReadError::widen)?
+                    .map_break(|()| crate::OMMaybeForeign::OM(r)),
+            };
+            match next {
                 ControlFlow::Break(a) => arguments.push(a),
                 ControlFlow::Continue(true) => break,
                 ControlFlow::Continue(false) => return Err(XmlReadError::UnexpectedTag(now)),
             }
         }
 
-        O::from_openmath(
+        self.convert_node(
             OM::OME {
                 cdbase: ocdbase,
                 cd,
@@ -726,186 +1785,211 @@ pub(super) trait Readable<'s, O: super::OMDeserializable<'s>> {
                 arguments,
                 attrs,
             },
-            cdbase,
+            scope,
+            now,
+            recovered,
         )
-        .map_err(XmlReadError::Conversion)
     }
 
     fn omattr_pairs(
         &mut self,
-        cdbase: &str,
+        scope: CdBaseScope<'_>,
         attrs: &mut Attrs<Attr<'s, O>>,
     ) -> Result<(), XmlReadError<O::Err>> {
-        loop {
-            let now = self.now();
-            let next = self.next()?;
-            match next.as_ref() {
-                Event::End(_) => {
-                    drop(next);
-                    return Ok(());
+        while let Some((key, now, position)) = self.attribute_key().map_err(XmlReadError::widen)? {
+            let key_scope = self.enter(scope, key.cdbase.as_deref(), Some(crate::OMKind::OMS), now);
+            if !super::keeps_attr(self.attr_filter(), key_scope.cdbase, &key.cd, &key.name) {
+                self.skip_value().map_err(XmlReadError::widen)?;
+                continue;
+            }
+            match self.next_omforeign(scope)? {
+                ControlFlow::Continue(true) => {
+                    return Err(XmlReadError::AttributeValue(position));
                 }
-                Event::Empty(event) if event.local_name().as_ref() == b"OMS" => {
-                    let Some(name) = next.get_attr_from_empty("name") else {
-                        return Err(XmlReadError::ExpectedAttribute("name"));
-                    };
-                    let name = tryfrombytes(name)?;
-                    let Some(cd_name) = next.get_attr_from_empty("cd") else {
-                        return Err(XmlReadError::ExpectedAttribute("cd"));
-                    };
-                    let cd_name = tryfrombytes(cd_name)?;
-                    let cdbase_o = next
-                        .get_attr_from_empty("cdbase")
-                        .map(tryfrombytes)
-                        .transpose()?;
-                    drop(next);
-                    let now = self.now();
-                    match self.next_omforeign(cdbase)? {
-                        ControlFlow::Continue(true) => {
-                            return Err(XmlReadError::AttributeValue(now));
-                        }
-                        ControlFlow::Continue(false) => {
-                            return Err(XmlReadError::UnexpectedTag(now));
-                        }
-                        ControlFlow::Break(value) => {
-                            attrs.push(Attr::<O> {
-                                cdbase: cdbase_o,
-                                cd: cd_name,
-                                name,
-                                value,
-                            });
-                        }
-                    }
+                ControlFlow::Continue(false) => {
+                    return Err(XmlReadError::UnexpectedTag(position));
                 }
-                Event::Text(t) if t.as_ref().iter().all(u8::is_ascii_whitespace) => drop(next),
-                _ => return Err(XmlReadError::UnexpectedTag(now)),
+                ControlFlow::Break(value) => attrs.push(Attr::<O> { key, value }),
             }
         }
+        Ok(())
+    }
+
+    /// Reads the `index`th child of an element of kind `parent`, unless
+    /// [`before_child`](OMDeserializable::before_child) skips it
+    fn child(
+        &mut self,
+        scope: CdBaseScope<'_>,
+        parent: crate::OMKind,
+        index: usize,
+    ) -> Result<ControlFlow<O::Ret, bool>, XmlReadError<O::Err>> {
+        match O::before_child(parent, index, scope.cdbase) {
+            Visit::Descend => self.handle_next(scope, Attrs::new()),
+            Visit::Skip(r) => Ok(self
+                .skip_child()
+                .map_err(XmlReadError::widen)?
+                .map_break(|()| r)),
+        }
     }
 
     fn omattr_i<R>(
         &mut self,
-        cdbase: &str,
+        scope: CdBaseScope<'_>,
         mut attrs: Attrs<Attr<'s, O>>,
         cont: impl FnOnce(&mut Self, Attrs<Attr<'s, O>>) -> Result<R, XmlReadError<O::Err>>,
     ) -> Result<R, XmlReadError<O::Err>> {
-        let do_pairs = self.with_next(|n: Self::E<'_>, now| match n.as_ref() {
-            Event::Empty(e) if e.local_name().as_ref() == b"OMATP" => {
-                drop(n);
-                Ok(false)
-            }
-            Event::Start(e) if e.local_name().as_ref() == b"OMATP" => {
-                drop(n);
-                Ok(true)
-            }
-            _ => Err(XmlReadError::UnexpectedTag(now)),
-        })?;
-        if do_pairs {
-            self.omattr_pairs(cdbase, &mut attrs)?;
+        if self.omatp().map_err(XmlReadError::widen)? {
+            self.omattr_pairs(scope, &mut attrs)?;
         }
-        let r = cont(self, attrs)?;
-        Ok(r)
+        cont(self, attrs)
     }
 
     #[inline]
     fn omattr(
         &mut self,
-        cdbase: &str,
+        scope: CdBaseScope<'_>,
         attrs: Attrs<Attr<'s, O>>,
     ) -> Result<O::Ret, XmlReadError<O::Err>> {
-        self.omattr_i(cdbase, attrs, |nslf, attrs| {
+        self.omattr_i(scope, attrs, |nslf, attrs| {
             let now = nslf.now();
-            let ControlFlow::Break(object) = nslf.handle_next(cdbase, attrs)? else {
+            let ControlFlow::Break(object) = nslf.handle_next(scope, attrs)? else {
                 return Err(XmlReadError::NonEmptyExpectedFor("OMATTR", now));
             };
-            nslf.need_end()?;
+            nslf.need_end().map_err(XmlReadError::widen)?;
             Ok(object)
         })
     }
 
     fn omattr_or_var(
         &mut self,
-        cdbase: &str,
+        scope: CdBaseScope<'_>,
         attrs: Attrs<Attr<'s, O>>,
     ) -> Result<Option<(Cow<'s, str>, Attrs<Attr<'s, O>>)>, XmlReadError<O::Err>> {
-        let now = self.now();
-        let next = self.next()?;
-        match next.as_ref() {
-            Event::End(_) => {
-                drop(next);
-                Ok(None)
-            }
-            Event::Start(e) if e.local_name().as_ref() == b"OMATTR" => {
-                let a = next
-                    .get_attr_from_start("cdbase")
-                    .map(cowfrombytes)
-                    .transpose()?;
-                let cdbase = a.as_deref().unwrap_or(cdbase);
-                drop(next);
-                self.omattr_i(cdbase, attrs, |nslf, attrs| {
-                    let r = nslf.omattr_or_var(cdbase, attrs)?;
-                    nslf.need_end()?;
+        match self.variable().map_err(XmlReadError::widen)? {
+            Variable::End => Ok(None),
+            Variable::Attributed { cdbase, now } => {
+                let scope = self.enter(scope, cdbase.as_deref(), Some(crate::OMKind::OMATTR), now);
+                self.omattr_i(scope, attrs, |nslf, attrs| {
+                    let r = nslf.omattr_or_var(scope, attrs)?;
+                    nslf.need_end().map_err(XmlReadError::widen)?;
                     Ok(r)
                 })
             }
-            Event::Empty(e) if e.local_name().as_ref() == b"OMV" => {
-                let Some(cow) = next.get_attr_from_empty("name") else {
-                    return Err(XmlReadError::ExpectedAttribute("name"));
-                };
-                let s = tryfrombytes(cow)?;
-                Ok(Some((s, attrs)))
-            }
-            Event::Text(t) if t.as_ref().iter().all(u8::is_ascii_whitespace) => {
-                drop(next);
-                self.omattr_or_var(cdbase, attrs)
-            }
-            _ => Err(XmlReadError::UnexpectedTag(now)),
+            Variable::Name(name) => Ok(Some((name, attrs))),
         }
     }
 
     fn ombind(
         &mut self,
-        cdbase: &str,
+        scope: CdBaseScope<'_>,
         off: u64,
         attrs: Attrs<Attr<'s, O>>,
     ) -> Result<O::Ret, XmlReadError<O::Err>> {
-        let ControlFlow::Break(head) = self.handle_next(cdbase, Attrs::new())? else {
+        let recovered = self.recovered();
+        let ControlFlow::Break(head) = self.child(scope, crate::OMKind::OMBIND, 0)? else {
             return Err(XmlReadError::NonEmptyExpectedFor("OMBIND", off));
         };
 
         let mut context = Vars::new();
-        let ombvar = self.with_next(|n: Self::E<'_>, now| match n.as_ref() {
-            Event::Empty(e) if e.local_name().as_ref() == b"OMBVAR" => {
-                drop(n);
-                Ok(false)
-            }
-            Event::Start(e) if e.local_name().as_ref() == b"OMBVAR" => {
-                drop(n);
-                Ok(true)
-            }
-            _ => Err(XmlReadError::UnexpectedTag(now)),
-        })?;
-        if ombvar {
-            while let Some(e) = self.omattr_or_var(cdbase, Attrs::new())? {
+        if self.ombvar().map_err(XmlReadError::widen)? {
+            while let Some(e) = self.omattr_or_var(scope, Attrs::new())? {
                 context.push(e);
             }
         }
 
         let now = self.now();
-        let ControlFlow::Break(body) = self.handle_next(cdbase, Attrs::new())? else {
+        let ControlFlow::Break(body) = self.child(scope, crate::OMKind::OMBIND, 1)? else {
             return Err(XmlReadError::NonEmptyExpectedFor("OMBIND", now));
         };
-        self.need_end()?;
+        self.need_end().map_err(XmlReadError::widen)?;
 
-        O::from_openmath(
+        self.convert_node(
             OM::OMBIND {
                 binder: head,
                 variables: context,
                 object: body,
                 attrs,
             },
-            cdbase,
+            scope,
+            off,
+            recovered,
         )
-        .map_err(XmlReadError::Conversion)
+    }
+}
+
+/// Detects UTF-16 input by its byte order mark or a leading `<`; returns whether it is
+/// little endian and the length of the byte order mark.
+const fn detect_utf16(start: &[u8]) -> Option<(bool, usize)> {
+    match start {
+        [0xFF, 0xFE, ..] => Some((true, 2)),
+        [0xFE, 0xFF, ..] => Some((false, 2)),
+        [b'<', 0, ..] => Some((true, 0)),
+        [0, b'<', ..] => Some((false, 0)),
+        _ => None,
+    }
+}
+
+/// Transcodes `input` into UTF-8 if it is UTF-16, or fails with
+/// [`UnsupportedEncoding`](XmlReadError::UnsupportedEncoding) without the `encoding`
+/// feature; returns `None` for any other input.
+pub(super) fn transcode_utf16<E: std::fmt::Display>(
+    input: &[u8],
+) -> Result<Option<String>, XmlReadError<E>> {
+    let Some((little_endian, bom)) = detect_utf16(input) else {
+        return Ok(None);
+    };
+    #[cfg(feature = "encoding")]
+    {
+        let encoding = if little_endian {
+            encoding_rs::UTF_16LE
+        } else {
+            encoding_rs::UTF_16BE
+        };
+        encoding
+            .decode_without_bom_handling_and_without_replacement(&input[bom..])
+            .map(|s| Some(s.into_owned()))
+            .ok_or(XmlReadError::InvalidEncoding("UTF-16"))
+    }
+    #[cfg(not(feature = "encoding"))]
+    {
+        let _ = (little_endian, bom);
+        Err(XmlReadError::UnsupportedEncoding("UTF-16".to_string()))
+    }
+}
+
+/// Checks the `encoding` of an XML declaration: anything but UTF-8 (and its subset ASCII)
+/// is unsupported, except for UTF-16 if the input was `transcoded` from it.
+fn check_declared_encoding(
+    decl: &quick_xml::events::BytesDecl<'_>,
+    transcoded: bool,
+    position: u64,
+) -> Result<(), StructuralError> {
+    let Some(encoding) = decl.encoding() else {
+        return Ok(());
+    };
+    let encoding = encoding.map_err(|e| XmlReadError::Xml {
+        error: e.into(),
+        position,
+    })?;
+    let supported: &[&[u8]] = if transcoded {
+        &[
+            b"utf-8",
+            b"utf8",
+            b"us-ascii",
+            b"ascii",
+            b"utf-16",
+            b"utf-16le",
+            b"utf-16be",
+        ]
+    } else {
+        &[b"utf-8", b"utf8", b"us-ascii", b"ascii"]
+    };
+    if supported.iter().any(|s| s.eq_ignore_ascii_case(&encoding)) {
+        Ok(())
+    } else {
+        Err(XmlReadError::UnsupportedEncoding(
+            String::from_utf8_lossy(&encoding).into_owned(),
+        ))
     }
 }
 
@@ -913,12 +1997,48 @@ pub(super) struct FromString<'s> {
     orig: &'s [u8],
     inner: quick_xml::Reader<&'s [u8]>,
     position: u64,
+    /// The offset of `orig` in the whole input: the length of a stripped byte order mark,
+    /// or the start of an [island](super::Islands); positions in `orig` and `inner` are
+    /// relative to `orig`
+    offset: u64,
+    pub(super) bases: Bases,
+    pub(super) attr_filter: Option<super::AttrFilter>,
+    pub(super) names: super::NameRules,
+    pub(super) budget: super::Spending,
+    pub(super) max_depth: Option<usize>,
+    pub(super) attribute_policy: AttributePolicy,
+    pub(super) float_policy: super::FloatPolicy,
+    pub(super) references: Option<super::References>,
+}
+impl<'s> FromString<'s> {
+    /// Reads `rest`, which starts at `offset` in the whole input
+    pub(super) fn at(rest: &'s str, offset: u64) -> Self {
+        Self {
+            orig: rest.as_bytes(),
+            inner: quick_xml::Reader::from_str(rest),
+            position: 0,
+            offset,
+            bases: Bases::default(),
+            attr_filter: None,
+            names: super::NameRules::default(),
+            budget: super::Spending::default(),
+            max_depth: None,
+            attribute_policy: AttributePolicy::Strict,
+            float_policy: super::FloatPolicy::Nearest,
+            references: None,
+        }
+    }
+
+    /// The length of the input read so far, including a byte order mark (or whatever
+    /// precedes an island)
+    #[allow(clippy::cast_possible_truncation)]
+    pub(super) const fn consumed(&self) -> usize {
+        (self.inner.buffer_position() + self.offset) as usize
+    }
 }
 
-impl<'s, O> Readable<'s, O> for FromString<'s>
-where
-    O: super::OMDeserializable<'s>,
-{
+impl<'s, O: super::OMDeserializable<'s>> Readable<'s, O> for FromString<'s> {}
+impl<'s> Events<'s> for FromString<'s> {
     type Input = &'s str;
     type E<'e>
         = Ev<'s>
@@ -926,96 +2046,322 @@ where
         's: 'e;
 
     #[allow(clippy::cast_possible_truncation)]
-    fn until(
-        &mut self,
-        tag: quick_xml::name::QName,
-    ) -> Result<Cow<'s, [u8]>, XmlReadError<O::Err>> {
+    fn until(&mut self, tag: quick_xml::name::QName) -> Result<Cow<'s, [u8]>, StructuralError> {
         let e = self.inner.read_to_end(tag).map_err(|e| XmlReadError::Xml {
             error: e,
-            position: self.position,
+            position: self.position + self.offset,
         })?;
+        self.bases.end();
         Ok(Cow::Borrowed(
             self.orig[e.start as usize..e.end as usize].trim_ascii(),
         ))
     }
 
     #[inline]
-    fn next(&mut self) -> Result<Self::E<'_>, XmlReadError<O::Err>> {
+    fn next(&mut self) -> Result<Self::E<'_>, StructuralError> {
         self.position = self.inner.buffer_position();
-        self.inner
-            .read_event()
-            .map_err(|e| XmlReadError::Xml {
-                error: e,
-                position: self.inner.error_position(),
-            })
-            .map(Ev)
+        let event = self.inner.read_event().map_err(|e| XmlReadError::Xml {
+            error: e,
+            position: self.inner.error_position() + self.offset,
+        })?;
+        if let Event::Decl(decl) = &event {
+            // a string has been decoded already; possibly by `read_into_buffer`
+            check_declared_encoding(decl, true, self.position + self.offset)?;
+        }
+        self.bases.event(&event, self.position + self.offset)?;
+        if matches!(event, Event::Start(_) | Event::Empty(_)) {
+            self.bases
+                .check_depth(self.max_depth, self.position + self.offset)?;
+            self.budget
+                .node()
+                .map_err(|(nodes_parsed, elapsed)| XmlReadError::BudgetExceeded {
+                    nodes_parsed,
+                    elapsed,
+                })?;
+        }
+        Ok(Ev(
+            event,
+            self.bases.current(),
+            self.attribute_policy,
+            self.orig,
+        ))
     }
 
     /*#[inline]
     fn clear(&mut self) {}
     */
 
+    fn configure(&mut self, options: &super::Options<'_>) {
+        if let Some(base) = options.base {
+            self.bases.document(base);
+        }
+        self.attr_filter = options.attr_filter;
+        self.names = super::NameRules::new(options);
+        self.budget = super::Spending::new(options.budget);
+        self.max_depth = options.limits.max_depth;
+        self.attribute_policy = options.attribute_policy;
+        self.float_policy = options.float_policy;
+        self.references = super::References::new(options);
+    }
+
+    #[inline]
+    fn attr_filter(&self) -> Option<super::AttrFilter> {
+        self.attr_filter
+    }
+
+    #[inline]
+    fn names(&self) -> super::NameRules {
+        self.names
+    }
+    #[inline]
+    fn float_policy(&self) -> super::FloatPolicy {
+        self.float_policy
+    }
+    #[inline]
+    fn references(&mut self) -> Option<&mut super::References> {
+        self.references.as_mut()
+    }
+
     #[inline]
     fn now(&self) -> u64 {
-        self.position
+        self.position + self.offset
     }
     #[inline]
     fn new(input: Self::Input) -> Self {
-        Self {
-            orig: input.as_bytes(),
-            inner: quick_xml::Reader::from_str(input),
-            position: 0,
-        }
+        // quick_xml skips a byte order mark, too, but without counting it in positions
+        let stripped = input.strip_prefix('\u{feff}').unwrap_or(input);
+        Self::at(stripped, (input.len() - stripped.len()) as u64)
     }
 }
 
 pub(super) struct Reader<R: std::io::BufRead> {
     buf: Vec<u8>,
-    inner: quick_xml::Reader<R>,
+    inner: quick_xml::Reader<Source<R>>,
     position: u64,
+    /// Whether the start of the input has been checked for its encoding yet
+    sniffed: bool,
+    /// The length of the skipped UTF-8 byte order mark, which positions include
+    bom: u64,
+    pub(super) bases: Bases,
+    pub(super) attr_filter: Option<super::AttrFilter>,
+    pub(super) names: super::NameRules,
+    pub(super) budget: super::Spending,
+    pub(super) max_depth: Option<usize>,
+    pub(super) attribute_policy: AttributePolicy,
+    pub(super) float_policy: super::FloatPolicy,
+    pub(super) references: Option<super::References>,
     //cdbase: Cow<'static, str>,
 }
-impl<O, R: std::io::BufRead> Readable<'static, O> for Reader<R>
-where
-    O: super::OMDeserializable<'static>,
-{
+
+/// The input of a [`Reader`]: UTF-16 input is transcoded into memory first (with the
+/// `encoding` feature)
+pub(super) enum Source<R> {
+    Direct(R),
+    #[cfg(feature = "encoding")]
+    Transcoded(std::io::Cursor<Vec<u8>>),
+}
+impl<R> Source<R> {
+    /// Whether the input was transcoded from UTF-16
+    const fn is_transcoded(&self) -> bool {
+        match self {
+            Self::Direct(_) => false,
+            #[cfg(feature = "encoding")]
+            Self::Transcoded(_) => true,
+        }
+    }
+}
+impl<R: std::io::BufRead> std::io::Read for Source<R> {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Self::Direct(r) => r.read(buf),
+            #[cfg(feature = "encoding")]
+            Self::Transcoded(r) => r.read(buf),
+        }
+    }
+}
+impl<R: std::io::BufRead> std::io::BufRead for Source<R> {
+    #[inline]
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        match self {
+            Self::Direct(r) => r.fill_buf(),
+            #[cfg(feature = "encoding")]
+            Self::Transcoded(r) => r.fill_buf(),
+        }
+    }
+    #[inline]
+    fn consume(&mut self, amount: usize) {
+        match self {
+            Self::Direct(r) => r.consume(amount),
+            #[cfg(feature = "encoding")]
+            Self::Transcoded(r) => r.consume(amount),
+        }
+    }
+}
+
+impl<R: std::io::BufRead> Reader<R> {
+    /// Skips a UTF-8 byte order mark, and checks the start of the input for UTF-16, which
+    /// is transcoded if supported
+    fn sniff(&mut self) -> Result<(), StructuralError> {
+        use std::io::BufRead;
+        let io_error = |e| XmlReadError::Xml {
+            error: quick_xml::Error::Io(std::sync::Arc::new(e)),
+            position: 0,
+        };
+        self.sniffed = true;
+        let source = self.inner.get_mut();
+        let start = source.fill_buf().map_err(io_error)?;
+        if start.starts_with(&[0xEF, 0xBB, 0xBF]) {
+            source.consume(3);
+            self.bom = 3;
+            return Ok(());
+        }
+        if detect_utf16(start).is_none() {
+            return Ok(());
+        }
+        #[cfg(feature = "encoding")]
+        {
+            use std::io::Read;
+            let mut bytes = Vec::new();
+            source.read_to_end(&mut bytes).map_err(io_error)?;
+            let transcoded = transcode_utf16(&bytes)?.unwrap_or_default();
+            *source = Source::Transcoded(std::io::Cursor::new(transcoded.into_bytes()));
+            Ok(())
+        }
+        #[cfg(not(feature = "encoding"))]
+        Err(XmlReadError::UnsupportedEncoding("UTF-16".to_string()))
+    }
+}
+impl<O: super::OMDeserializable<'static>, R: std::io::BufRead> Readable<'static, O> for Reader<R> {}
+impl<R: std::io::BufRead> Events<'static> for Reader<R> {
     type Input = R;
     type E<'e>
         = NEv<'e>
     where
         Self: 'e;
 
-    fn until(
-        &mut self,
-        tag: quick_xml::name::QName,
-    ) -> Result<Cow<'static, [u8]>, XmlReadError<O::Err>> {
+    fn skip(&mut self, tag: quick_xml::name::QName) -> Result<(), StructuralError> {
         self.buf.clear();
         self.inner
             .read_to_end_into(tag, &mut self.buf)
             .map_err(|e| XmlReadError::Xml {
                 error: e,
+                position: self.inner.error_position() + self.bom,
+            })?;
+        self.buf.clear();
+        self.bases.end();
+        Ok(())
+    }
+
+    fn configure(&mut self, options: &super::Options<'_>) {
+        if let Some(base) = options.base {
+            self.bases.document(base);
+        }
+        self.attr_filter = options.attr_filter;
+        self.names = super::NameRules::new(options);
+        self.budget = super::Spending::new(options.budget);
+        self.max_depth = options.limits.max_depth;
+        self.attribute_policy = options.attribute_policy;
+        self.float_policy = options.float_policy;
+        self.references = super::References::new(options);
+    }
+
+    #[inline]
+    fn attr_filter(&self) -> Option<super::AttrFilter> {
+        self.attr_filter
+    }
+
+    #[inline]
+    fn names(&self) -> super::NameRules {
+        self.names
+    }
+    #[inline]
+    fn float_policy(&self) -> super::FloatPolicy {
+        self.float_policy
+    }
+    #[inline]
+    fn references(&mut self) -> Option<&mut super::References> {
+        self.references.as_mut()
+    }
+
+    fn until(
+        &mut self,
+        tag: quick_xml::name::QName,
+    ) -> Result<Cow<'static, [u8]>, StructuralError> {
+        // `read_to_end_into` does not retain the content, so it is reassembled from the
+        // (raw) events instead
+        let mut content = quick_xml::Writer::new(Vec::new());
+        let mut depth = 0_usize;
+        loop {
+            self.buf.clear();
+            let event =
+                self.inner
+                    .read_event_into(&mut self.buf)
+                    .map_err(|e| XmlReadError::Xml {
+                        error: e,
+                        position: self.inner.error_position() + self.bom,
+                    })?;
+            match &event {
+                Event::Start(s) if s.name() == tag => depth += 1,
+                Event::End(e) if e.name() == tag => {
+                    if depth == 0 {
+                        break;
+                    }
+                    depth -= 1;
+                }
+                Event::Eof => {
+                    return Err(XmlReadError::Xml {
+                        error: quick_xml::errors::IllFormedError::MissingEndTag(
+                            String::from_utf8_lossy(tag.as_ref()).into_owned(),
+                        )
+                        .into(),
+                        position: self.inner.buffer_position() + self.bom,
+                    });
+                }
+                _ => (),
+            }
+            content.write_event(event).map_err(|e| XmlReadError::Xml {
+                error: e.into(),
                 position: self.position,
             })?;
-        self.buf = self
-            .buf
-            .drain(
-                self.buf.len() - self.buf.trim_ascii_start().len()..self.buf.trim_ascii_end().len(),
-            )
-            .collect();
-        Ok(Cow::Owned(std::mem::take(&mut self.buf)))
+        }
+        self.bases.end();
+        // trimmed in place, rather than copying the trimmed content
+        let mut content = content.into_inner();
+        content.truncate(content.trim_ascii_end().len());
+        content.drain(..content.len() - content.trim_ascii_start().len());
+        Ok(Cow::Owned(content))
     }
 
     #[inline]
-    fn next(&mut self) -> Result<Self::E<'_>, XmlReadError<O::Err>> {
+    fn next(&mut self) -> Result<Self::E<'_>, StructuralError> {
+        if !self.sniffed {
+            self.sniff()?;
+        }
+        let transcoded = self.inner.get_ref().is_transcoded();
         self.buf.clear();
-        self.position = self.inner.buffer_position();
-        self.inner
+        self.position = self.inner.buffer_position() + self.bom;
+        let event = self
+            .inner
             .read_event_into(&mut self.buf)
             .map_err(|e| XmlReadError::Xml {
                 error: e,
-                position: self.inner.error_position(),
-            })
-            .map(NEv)
+                position: self.inner.error_position() + self.bom,
+            })?;
+        if let Event::Decl(decl) = &event {
+            check_declared_encoding(decl, transcoded, self.position)?;
+        }
+        self.bases.event(&event, self.position)?;
+        if matches!(event, Event::Start(_) | Event::Empty(_)) {
+            self.bases.check_depth(self.max_depth, self.position)?;
+            self.budget
+                .node()
+                .map_err(|(nodes_parsed, elapsed)| XmlReadError::BudgetExceeded {
+                    nodes_parsed,
+                    elapsed,
+                })?;
+        }
+        Ok(NEv(event, self.bases.current(), self.attribute_policy))
     }
 
     #[inline]
@@ -1025,9 +2371,769 @@ where
     #[inline]
     fn new(input: Self::Input) -> Self {
         Self {
-            inner: quick_xml::Reader::from_reader(input),
+            inner: quick_xml::Reader::from_reader(Source::Direct(input)),
             position: 0,
             buf: Vec::with_capacity(256),
+            sniffed: false,
+            bom: 0,
+            bases: Bases::default(),
+            attr_filter: None,
+            names: super::NameRules::default(),
+            budget: super::Spending::default(),
+            max_depth: None,
+            attribute_policy: AttributePolicy::Strict,
+            float_policy: super::FloatPolicy::Nearest,
+            references: None,
+        }
+    }
+}
+
+/// A `cdbase` attribute read from XML; see
+/// [`Options::with_cdbase_origins`](super::Options::with_cdbase_origins).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CdBaseOrigin {
+    /// The element carrying the attribute; `None` for an OMOBJ
+    pub element: Option<crate::OMKind>,
+    /// The byte offset of that element
+    pub position: u64,
+    /// The value of the attribute, resolved against the `xml:base` in scope (if any)
+    pub cdbase: String,
+    /// The cdbase the element would have inherited without the attribute
+    pub inherited: String,
+}
+impl CdBaseOrigin {
+    /// Whether the attribute does not change the cdbase in effect (modulo
+    /// [normalization](crate::CdBase::normalize)); e.g. to flag redundant attributes
+    #[must_use]
+    pub fn is_redundant(&self) -> bool {
+        crate::cdbase::eq_normalized(&self.cdbase, &self.inherited)
+    }
+}
+
+/// The elements [`CdBases`] tells apart when computing the paths of their children
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Parent {
+    Oma,
+    Ombind,
+    Ombvar,
+    Ome,
+    Omattr,
+    Omatp,
+    Other,
+}
+impl Parent {
+    fn of(name: &[u8]) -> Self {
+        match name {
+            b"OMA" => Self::Oma,
+            b"OMBIND" => Self::Ombind,
+            b"OMBVAR" => Self::Ombvar,
+            b"OME" => Self::Ome,
+            b"OMATTR" => Self::Omattr,
+            b"OMATP" => Self::Omatp,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// An open element, for [`CdBases`]
+struct Frame {
+    parent: Parent,
+    /// The path of the object the element belongs to
+    path: crate::Path,
+    /// The number of children read so far that are objects or OMFOREIGNs
+    children: usize,
+    /// For OMATTRs, the index of the next attribution pair of the attributed object
+    attributes: usize,
+    /// For OMATTRs around bound variables, the index of the variable
+    variable: Option<usize>,
+}
+impl Frame {
+    const fn new(parent: Parent, path: crate::Path) -> Self {
+        Self {
+            parent,
+            path,
+            children: 0,
+            attributes: 0,
+            variable: None,
+        }
+    }
+
+    /// The frame of the element `name` starting within the `open` ones
+    fn child(open: &mut [Self], name: &[u8]) -> Self {
+        use crate::Step;
+        let kind = Parent::of(name);
+        let Some((parent, outer)) = open.split_last_mut() else {
+            return Self::new(kind, crate::Path::new());
+        };
+        let index = parent.children;
+        let object = is_object(name) || name == b"OMFOREIGN";
+        if object {
+            parent.children += 1;
+        }
+        let path = parent.path.clone();
+        match parent.parent {
+            Parent::Oma if object => Self::new(
+                kind,
+                path.then(index.checked_sub(1).map_or(Step::Applicant, Step::Argument)),
+            ),
+            Parent::Ombind if object => Self::new(
+                kind,
+                path.then(if index == 0 { Step::Binder } else { Step::Body }),
+            ),
+            // the first child is the symbol
+            Parent::Ome if object && index > 0 => {
+                Self::new(kind, path.then(Step::Argument(index - 1)))
+            }
+            Parent::Ombvar => Self {
+                variable: Some(index),
+                ..Self::new(kind, path)
+            },
+            // the attributed object
+            Parent::Omattr => Self {
+                attributes: parent.attributes,
+                variable: parent.variable,
+                ..Self::new(kind, path)
+            },
+            // the values of the pairs; keys belong to the attributed object
+            Parent::Omatp if object && index % 2 == 1 => match outer.last_mut() {
+                Some(omattr) => {
+                    let j = omattr.attributes;
+                    omattr.attributes += 1;
+                    let step = omattr
+                        .variable
+                        .map_or(Step::Attribute(j), |v| Step::VariableAttribute(v, j));
+                    Self::new(kind, omattr.path.clone().then(step))
+                }
+                None => Self::new(kind, path),
+            },
+            _ => Self::new(kind, path),
+        }
+    }
+}
+
+/// Reads with `R`, but converts every element with the function `F` instead of
+/// [`from_openmath`](super::OMDeserializable::from_openmath); e.g. to build the object in
+/// storage that `F` borrows.
+pub(super) struct Converting<R, F> {
+    inner: R,
+    convert: F,
+}
+impl<'s, O, R, F> Readable<'s, O> for Converting<R, F>
+where
+    O: super::OMDeserializable<'s, Ret = O>,
+    R: Events<'s>,
+    F: FnMut(OM<'s, O>, &str) -> Result<O, O::Err>,
+{
+    fn convert_node(
+        &mut self,
+        om: OM<'s, O>,
+        scope: CdBaseScope<'_>,
+        position: u64,
+        _: usize,
+    ) -> Result<O, XmlReadError<O::Err>> {
+        self.names()
+            .check_om(&om)
+            .map_err(|error| XmlReadError::InvalidName { error, position })?;
+        let symbol = matches!(om, OM::OMS { .. });
+        let Self { inner, convert } = self;
+        super::References::convert_with(inner.references(), om, scope.cdbase, convert)
+            .map_err(|error| XmlReadError::Reference { error, position })?
+            .map_err(|error| scope.conversion_error(error, symbol, position))
+    }
+}
+impl<'s, R: Events<'s>, F> Events<'s> for Converting<R, F> {
+    type Input = (R::Input, F);
+    type E<'e>
+        = R::E<'e>
+    where
+        's: 'e,
+        Self: 'e;
+
+    #[inline]
+    fn new((input, convert): Self::Input) -> Self {
+        Self {
+            inner: R::new(input),
+            convert,
+        }
+    }
+    #[inline]
+    fn now(&self) -> u64 {
+        self.inner.now()
+    }
+    #[inline]
+    fn next(&mut self) -> Result<Self::E<'_>, StructuralError> {
+        self.inner.next()
+    }
+    #[inline]
+    fn until(&mut self, tag: quick_xml::name::QName) -> Result<Cow<'s, [u8]>, StructuralError> {
+        self.inner.until(tag)
+    }
+    #[inline]
+    fn skip(&mut self, tag: quick_xml::name::QName) -> Result<(), StructuralError> {
+        self.inner.skip(tag)
+    }
+    #[inline]
+    fn configure(&mut self, options: &super::Options<'_>) {
+        self.inner.configure(options);
+    }
+    #[inline]
+    fn attr_filter(&self) -> Option<super::AttrFilter> {
+        self.inner.attr_filter()
+    }
+    #[inline]
+    fn names(&self) -> super::NameRules {
+        self.inner.names()
+    }
+    #[inline]
+    fn float_policy(&self) -> super::FloatPolicy {
+        self.inner.float_policy()
+    }
+    #[inline]
+    fn references(&mut self) -> Option<&mut super::References> {
+        self.inner.references()
+    }
+}
+
+/// Wraps a [`Readable`] such that every `cdbase` attribute is recorded with the
+/// [`Path`](crate::Path) of the object it belongs to.
+pub(super) struct CdBases<R> {
+    inner: R,
+    pub(super) origins: Vec<(crate::Path, CdBaseOrigin)>,
+    open: Vec<Frame>,
+    /// The path of the object the element started last belongs to
+    current: crate::Path,
+}
+impl<'s, O, R> Readable<'s, O> for CdBases<R>
+where
+    O: super::OMDeserializable<'s>,
+    R: Readable<'s, O>,
+{
+    #[inline]
+    fn convert_node(
+        &mut self,
+        om: OM<'s, O::Ret>,
+        scope: CdBaseScope<'_>,
+        position: u64,
+        recovered: usize,
+    ) -> Result<O::Ret, XmlReadError<O::Err>> {
+        self.inner.convert_node(om, scope, position, recovered)
+    }
+}
+impl<'s, R: Events<'s>> Events<'s> for CdBases<R> {
+    type Input = R::Input;
+    type E<'e>
+        = R::E<'e>
+    where
+        's: 'e,
+        Self: 'e;
+
+    #[inline]
+    fn until(&mut self, tag: quick_xml::name::QName) -> Result<Cow<'s, [u8]>, StructuralError> {
+        let r = self.inner.until(tag)?;
+        self.open.pop();
+        Ok(r)
+    }
+    #[inline]
+    fn skip(&mut self, tag: quick_xml::name::QName) -> Result<(), StructuralError> {
+        self.inner.skip(tag)?;
+        self.open.pop();
+        Ok(())
+    }
+    #[inline]
+    fn configure(&mut self, options: &super::Options<'_>) {
+        self.inner.configure(options);
+    }
+    #[inline]
+    fn attr_filter(&self) -> Option<super::AttrFilter> {
+        self.inner.attr_filter()
+    }
+    #[inline]
+    fn names(&self) -> super::NameRules {
+        self.inner.names()
+    }
+    #[inline]
+    fn float_policy(&self) -> super::FloatPolicy {
+        self.inner.float_policy()
+    }
+    #[inline]
+    fn references(&mut self) -> Option<&mut super::References> {
+        self.inner.references()
+    }
+    fn next(&mut self) -> Result<Self::E<'_>, StructuralError> {
+        let Self {
+            inner,
+            open,
+            current,
+            ..
+        } = self;
+        let e = inner.next()?;
+        match e.as_ref() {
+            Event::Start(s) | Event::Empty(s) => {
+                let frame = Frame::child(open, s.local_name().as_ref());
+                current.clone_from(&frame.path);
+                if matches!(e.as_ref(), Event::Start(_)) {
+                    open.push(frame);
+                }
+            }
+            Event::End(_) => {
+                open.pop();
+            }
+            _ => (),
+        }
+        Ok(e)
+    }
+    #[inline]
+    fn now(&self) -> u64 {
+        self.inner.now()
+    }
+    #[inline]
+    fn new(input: Self::Input) -> Self {
+        Self {
+            inner: R::new(input),
+            origins: Vec::new(),
+            open: Vec::new(),
+            current: crate::Path::new(),
+        }
+    }
+    fn cdbase_attribute(
+        &mut self,
+        element: Option<crate::OMKind>,
+        position: u64,
+        cdbase: &str,
+        inherited: &str,
+    ) {
+        self.origins.push((
+            self.current.clone(),
+            CdBaseOrigin {
+                element,
+                position,
+                cdbase: cdbase.to_string(),
+                inherited: inherited.to_string(),
+            },
+        ));
+    }
+}
+
+/// Wraps a [`Readable`] such that the formatting of the document is recorded in a
+/// [`FormatMap`](crate::FormatMap).
+pub(super) struct Recording<R> {
+    pub(super) inner: R,
+    pub(super) map: crate::FormatMap,
+    cursor: crate::format_map::Cursor,
+    depth: usize,
+    whitespace: Option<Box<str>>,
+}
+impl<'s, O, R> Readable<'s, O> for Recording<R>
+where
+    O: super::OMDeserializable<'s>,
+    R: Readable<'s, O>,
+{
+    #[inline]
+    fn recovered(&self) -> usize {
+        self.inner.recovered()
+    }
+    #[inline]
+    fn recover(
+        &mut self,
+        error: XmlReadError<O::Err>,
+        position: u64,
+        in_failed_subtree: bool,
+    ) -> Result<O::Ret, XmlReadError<O::Err>> {
+        self.inner.recover(error, position, in_failed_subtree)
+    }
+}
+impl<'s, R: Events<'s>> Events<'s> for Recording<R> {
+    type Input = R::Input;
+    type E<'e>
+        = R::E<'e>
+    where
+        's: 'e,
+        Self: 'e;
+
+    #[inline]
+    fn until(&mut self, tag: quick_xml::name::QName) -> Result<Cow<'s, [u8]>, StructuralError> {
+        let r = self.inner.until(tag)?;
+        self.depth = self.depth.saturating_sub(1);
+        self.whitespace = None;
+        Ok(r)
+    }
+    #[inline]
+    fn skip(&mut self, tag: quick_xml::name::QName) -> Result<(), StructuralError> {
+        self.inner.skip(tag)?;
+        self.depth = self.depth.saturating_sub(1);
+        self.whitespace = None;
+        Ok(())
+    }
+    #[inline]
+    fn configure(&mut self, options: &super::Options<'_>) {
+        self.inner.configure(options);
+    }
+    #[inline]
+    fn attr_filter(&self) -> Option<super::AttrFilter> {
+        self.inner.attr_filter()
+    }
+    #[inline]
+    fn names(&self) -> super::NameRules {
+        self.inner.names()
+    }
+    #[inline]
+    fn float_policy(&self) -> super::FloatPolicy {
+        self.inner.float_policy()
+    }
+    #[inline]
+    fn references(&mut self) -> Option<&mut super::References> {
+        self.inner.references()
+    }
+    fn next(&mut self) -> Result<Self::E<'_>, StructuralError> {
+        let Self {
+            inner,
+            map,
+            cursor,
+            depth,
+            whitespace,
+        } = self;
+        let e = inner.next()?;
+        match e.as_ref() {
+            Event::Text(t) => {
+                *whitespace = t
+                    .iter()
+                    .all(u8::is_ascii_whitespace)
+                    .then(|| String::from_utf8_lossy(t).into());
+            }
+            Event::Start(s) | Event::Empty(s) => {
+                let name = String::from_utf8_lossy(s.local_name().into_inner());
+                let attributes = s.attributes().filter_map(|a| {
+                    a.ok().map(|a| {
+                        String::from_utf8_lossy(a.key.local_name().into_inner()).into_owned()
+                    })
+                });
+                let path = cursor.start(*depth);
+                map.record_start(
+                    path,
+                    &name,
+                    whitespace.take().as_deref().unwrap_or_default(),
+                    attributes,
+                );
+                if name == "OMF"
+                    && let Some(dec) = s
+                        .attributes()
+                        .flatten()
+                        .find(|a| a.key.local_name().as_ref() == b"dec")
+                        .and_then(|a| a.unescape_value().ok())
+                {
+                    map.record_decimal(path, &dec);
+                }
+                if matches!(e.as_ref(), Event::Start(_)) {
+                    *depth += 1;
+                }
+            }
+            Event::End(s) => {
+                *depth = depth.saturating_sub(1);
+                let name = String::from_utf8_lossy(s.local_name().into_inner());
+                map.record_end(
+                    cursor.current(*depth),
+                    &name,
+                    whitespace.take().as_deref().unwrap_or_default(),
+                );
+            }
+            _ => *whitespace = None,
+        }
+        Ok(e)
+    }
+    #[inline]
+    fn now(&self) -> u64 {
+        self.inner.now()
+    }
+    #[inline]
+    fn new(input: Self::Input) -> Self {
+        Self {
+            inner: R::new(input),
+            map: crate::FormatMap::default(),
+            cursor: crate::format_map::Cursor::default(),
+            depth: 0,
+            whitespace: None,
         }
     }
 }
+
+/// Wraps [`FromString`] such that the byte range of every element read is tracked and
+/// attached to the [`Spanned`](super::Spanned)s it is converted into.
+pub(super) struct Spanning<'s> {
+    inner: FromString<'s>,
+    /// start offsets of the currently open elements, and whether they are OMATTRs
+    open: Vec<(usize, bool)>,
+    /// the span of the last element closed
+    last: std::ops::Range<usize>,
+}
+impl Spanning<'_> {
+    /// The span of the node converted last (in the input, including a byte order mark)
+    #[allow(clippy::cast_possible_truncation)]
+    fn span(&self) -> std::ops::Range<usize> {
+        let offset = self.inner.offset as usize;
+        let span = self.stripped_span();
+        span.start + offset..span.end + offset
+    }
+
+    /// The span of the node converted last; i.e. of the element closed last, or of the
+    /// outermost OMATTR directly enclosing it.
+    fn stripped_span(&self) -> std::ops::Range<usize> {
+        let wrapped = self
+            .open
+            .iter()
+            .rev()
+            .take_while(|(_, omattr)| *omattr)
+            .count();
+        if wrapped == 0 {
+            return self.last.clone();
+        }
+        // the end tags of the OMATTRs have not been read yet
+        let end = (0..wrapped).fold(self.last.end, |end, _| {
+            Self::after_end_tag(self.inner.orig, end)
+        });
+        self.open[self.open.len() - wrapped].0..end
+    }
+
+    /// The offset after the next end tag at or after `from`, skipping whitespace and comments
+    fn after_end_tag(input: &[u8], mut from: usize) -> usize {
+        loop {
+            let rest = &input[from..];
+            let trimmed = rest.trim_ascii_start();
+            from += rest.len() - trimmed.len();
+            if let Some(comment) = trimmed.strip_prefix(b"<!--") {
+                from += 4 + comment
+                    .windows(3)
+                    .position(|w| w == b"-->")
+                    .map_or(comment.len(), |i| i + 3);
+            } else {
+                return from
+                    + trimmed
+                        .iter()
+                        .position(|&b| b == b'>')
+                        .map_or(trimmed.len(), |i| i + 1);
+            }
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn close(&mut self) {
+        let end = self.inner.inner.buffer_position() as usize;
+        if let Some((start, _)) = self.open.pop() {
+            self.last = start..end;
+        }
+    }
+}
+impl<'s> Events<'s> for Spanning<'s> {
+    type Input = &'s str;
+    type E<'e>
+        = Ev<'s>
+    where
+        's: 'e;
+
+    fn until(&mut self, tag: quick_xml::name::QName) -> Result<Cow<'s, [u8]>, StructuralError> {
+        let r = self.inner.until(tag)?;
+        self.close();
+        Ok(r)
+    }
+
+    #[inline]
+    fn configure(&mut self, options: &super::Options<'_>) {
+        <FromString<'s> as Events<'s>>::configure(&mut self.inner, options);
+    }
+
+    #[inline]
+    fn attr_filter(&self) -> Option<super::AttrFilter> {
+        self.inner.attr_filter
+    }
+
+    #[inline]
+    fn names(&self) -> super::NameRules {
+        self.inner.names
+    }
+    #[inline]
+    fn float_policy(&self) -> super::FloatPolicy {
+        self.inner.float_policy
+    }
+    #[inline]
+    fn references(&mut self) -> Option<&mut super::References> {
+        self.inner.references.as_mut()
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn next(&mut self) -> Result<Self::E<'_>, StructuralError> {
+        let e = self.inner.next()?;
+        let start = self.inner.position as usize;
+        match e.as_ref() {
+            Event::Start(s) => self
+                .open
+                .push((start, s.local_name().as_ref() == b"OMATTR")),
+            Event::Empty(_) => self.last = start..self.inner.inner.buffer_position() as usize,
+            Event::End(_) => self.close(),
+            _ => (),
+        }
+        Ok(e)
+    }
+
+    #[inline]
+    fn now(&self) -> u64 {
+        self.inner.position + self.inner.offset
+    }
+
+    #[inline]
+    fn new(input: Self::Input) -> Self {
+        Self {
+            inner: FromString::new(input),
+            open: Vec::new(),
+            last: 0..0,
+        }
+    }
+}
+impl<'s, T> Readable<'s, super::Spanned<T>> for Spanning<'s>
+where
+    T: OMDeserializable<'s>,
+{
+    fn convert_node(
+        &mut self,
+        om: OM<'s, super::SpannedRet<T::Ret>>,
+        scope: CdBaseScope<'_>,
+        position: u64,
+        _: usize,
+    ) -> Result<super::SpannedRet<T::Ret>, XmlReadError<T::Err>> {
+        let names = self.inner.names;
+        names
+            .check_om(&om)
+            .map_err(|error| XmlReadError::InvalidName { error, position })?;
+        let symbol = matches!(om, OM::OMS { .. });
+        let mut r = super::References::convert::<super::Spanned<T>>(
+            self.inner.references.as_mut(),
+            om,
+            scope.cdbase,
+        )
+        .map_err(|error| XmlReadError::Reference { error, position })?
+        .map_err(|error| scope.conversion_error(error, symbol, position))?;
+        r.span = self.span();
+        Ok(r)
+    }
+}
+
+/// Wraps a [`Readable`] such that conversion errors are collected and replaced by
+/// [`placeholder`](super::OMDeserializableRecover::placeholder)s rather than being fatal.
+pub(super) struct Collecting<Err: std::fmt::Display, R> {
+    inner: R,
+    errors: Vec<PositionedError<Err>>,
+}
+impl<'s, O, R> Readable<'s, O> for Collecting<O::Err, R>
+where
+    O: super::OMDeserializableRecover<'s>,
+    R: Readable<'s, O>,
+{
+    #[inline]
+    fn recovered(&self) -> usize {
+        self.errors.len()
+    }
+    fn recover(
+        &mut self,
+        error: XmlReadError<O::Err>,
+        position: u64,
+        in_failed_subtree: bool,
+    ) -> Result<O::Ret, XmlReadError<O::Err>> {
+        if !in_failed_subtree {
+            self.errors.push(PositionedError { position, error });
+        }
+        Ok(O::placeholder())
+    }
+}
+impl<'s, Err: std::fmt::Display, R: Events<'s>> Events<'s> for Collecting<Err, R> {
+    type Input = R::Input;
+    type E<'e>
+        = R::E<'e>
+    where
+        's: 'e,
+        Self: 'e;
+
+    #[inline]
+    fn until(&mut self, tag: quick_xml::name::QName) -> Result<Cow<'s, [u8]>, StructuralError> {
+        self.inner.until(tag)
+    }
+    #[inline]
+    fn skip(&mut self, tag: quick_xml::name::QName) -> Result<(), StructuralError> {
+        self.inner.skip(tag)
+    }
+    #[inline]
+    fn configure(&mut self, options: &super::Options<'_>) {
+        self.inner.configure(options);
+    }
+    #[inline]
+    fn attr_filter(&self) -> Option<super::AttrFilter> {
+        self.inner.attr_filter()
+    }
+    #[inline]
+    fn names(&self) -> super::NameRules {
+        self.inner.names()
+    }
+    #[inline]
+    fn float_policy(&self) -> super::FloatPolicy {
+        self.inner.float_policy()
+    }
+    #[inline]
+    fn references(&mut self) -> Option<&mut super::References> {
+        self.inner.references()
+    }
+    #[inline]
+    fn next(&mut self) -> Result<Self::E<'_>, StructuralError> {
+        self.inner.next()
+    }
+    #[inline]
+    fn now(&self) -> u64 {
+        self.inner.now()
+    }
+    #[inline]
+    fn new(input: Self::Input) -> Self {
+        Self {
+            inner: R::new(input),
+            errors: Vec::new(),
+        }
+    }
+}
+impl<Err: std::fmt::Display, R> Collecting<Err, R> {
+    pub(super) fn read_collect<'s, O>(
+        mut self,
+        cdbase: Option<&str>,
+    ) -> (Option<O>, Vec<PositionedError<Err>>)
+    where
+        O: super::OMDeserializableRecover<'s, Err = Err>,
+        Self: Readable<'s, O>,
+    {
+        let scope = CdBaseScope::new(cdbase.unwrap_or(crate::CD_BASE));
+        let result = loop {
+            let now = self.now();
+            match <Self as Readable<'s, O>>::handle_next(&mut self, scope, Attrs::new()) {
+                Ok(ControlFlow::Break(b)) => {
+                    let r = b.try_into().ok();
+                    if r.is_none() && self.errors.is_empty() {
+                        self.errors.push(PositionedError {
+                            position: now,
+                            error: XmlReadError::NotFullyConvertible,
+                        });
+                    }
+                    if let Err(error) = self.finish() {
+                        self.errors.push(PositionedError {
+                            position: self.now(),
+                            error: error.widen(),
+                        });
+                        break None;
+                    }
+                    break r;
+                }
+                Ok(ControlFlow::Continue(_)) => (),
+                Err(error) => {
+                    self.errors.push(PositionedError {
+                        position: self.now(),
+                        error,
+                    });
+                    break None;
+                }
+            }
+        };
+        (result, self.errors)
+    }
+}
@@ -0,0 +1,391 @@
+//! Compact [`Debug`](std::fmt::Debug) implementations for [`OpenMath`] and [`OM`], in the
+//! functional notation of [`openmath_display`](crate::OMSerializable::openmath_display)
+
+use std::fmt::{Debug, Formatter, Result, Write};
+
+use crate::{
+    Attr, BoundVariable, OM, OMMaybeForeign, OMSerializable, OpenMath,
+    de::OMAttr,
+    ser::{DisplayOptions, FloatStyle, write_foreign, write_omb, write_omstr},
+};
+
+/// The number of characters (resp. bytes) after which strings, byte arrays and foreign
+/// objects are truncated
+const MAX_LEN: usize = 32;
+
+/// Prints the [`openmath_display`](OMSerializable::openmath_display) notation, e.g.
+/// `OMA(OMS(arith1#plus),OMI(1),OMV(x))`, with long strings and byte arrays truncated.
+/// [`raw_debug`](OpenMath::raw_debug) prints every field instead.
+impl Debug for OpenMath<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let options = DisplayOptions {
+            max_len: Some(MAX_LEN),
+            ..DisplayOptions::default()
+        };
+        std::fmt::Display::fmt(&self.openmath_display_with(options), f)
+    }
+}
+
+impl OpenMath<'_> {
+    /** The [`Debug`] output of the structure of this object, with every field spelled out
+    (as a derived implementation would), in particular the
+    [`cdbase`](OpenMath::OMS::cdbase) of symbols and whether it was inherited, which the
+    compact [`Debug`] implementation leaves implicit.
+
+    # Examples
+    ```
+    use openmath::OpenMath;
+
+    let om = OpenMath::from_sexpr("(oma (oms arith1 plus) (omi 1) (omv x))").expect("is valid");
+    assert_eq!(
+        format!("{om:?}"),
+        "OMA(OMS(arith1#plus),OMI(1),OMV(x))"
+    );
+    assert!(format!("{:?}", om.raw_debug()).starts_with("OMA { applicant: OMS { cd: \"arith1\""));
+    ```
+    */
+    #[must_use]
+    pub fn raw_debug(&self) -> impl Debug + '_ {
+        Raw(self)
+    }
+}
+
+/// Prints the fields of the object it wraps
+struct Raw<'a, T: ?Sized>(&'a T);
+
+impl Debug for Raw<'_, OpenMath<'_>> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self.0 {
+            OpenMath::OMI { int, attributes } => f
+                .debug_struct("OMI")
+                .field("int", int)
+                .field("attributes", &Raw(&**attributes))
+                .finish(),
+            OpenMath::OMF { float, attributes } => f
+                .debug_struct("OMF")
+                .field("float", float)
+                .field("attributes", &Raw(&**attributes))
+                .finish(),
+            OpenMath::OMSTR { string, attributes } => f
+                .debug_struct("OMSTR")
+                .field("string", string)
+                .field("attributes", &Raw(&**attributes))
+                .finish(),
+            OpenMath::OMB { bytes, attributes } => f
+                .debug_struct("OMB")
+                .field("bytes", bytes)
+                .field("attributes", &Raw(&**attributes))
+                .finish(),
+            OpenMath::OMV { name, attributes } => f
+                .debug_struct("OMV")
+                .field("name", name)
+                .field("attributes", &Raw(&**attributes))
+                .finish(),
+            OpenMath::OMS {
+                cd,
+                name,
+                cdbase,
+                cdbase_inherited,
+                attributes,
+            } => f
+                .debug_struct("OMS")
+                .field("cd", cd)
+                .field("name", name)
+                .field("cdbase", cdbase)
+                .field("cdbase_inherited", cdbase_inherited)
+                .field("attributes", &Raw(&**attributes))
+                .finish(),
+            OpenMath::OMA {
+                applicant,
+                arguments,
+                attributes,
+            } => f
+                .debug_struct("OMA")
+                .field("applicant", &Raw(&**applicant))
+                .field("arguments", &Raw(&**arguments))
+                .field("attributes", &Raw(&**attributes))
+                .finish(),
+            OpenMath::OMBIND {
+                binder,
+                variables,
+                object,
+                attributes,
+            } => f
+                .debug_struct("OMBIND")
+                .field("binder", &Raw(&**binder))
+                .field("variables", &Raw(&**variables))
+                .field("object", &Raw(&**object))
+                .field("attributes", &Raw(&**attributes))
+                .finish(),
+            OpenMath::OME {
+                cdbase,
+                cd,
+                name,
+                arguments,
+                attributes,
+            } => f
+                .debug_struct("OME")
+                .field("cdbase", cdbase)
+                .field("cd", cd)
+                .field("name", name)
+                .field("arguments", &Raw(&**arguments))
+                .field("attributes", &Raw(&**attributes))
+                .finish(),
+        }
+    }
+}
+
+impl Debug for Raw<'_, [OpenMath<'_>]> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.debug_list().entries(self.0.iter().map(Raw)).finish()
+    }
+}
+
+impl Debug for Raw<'_, [BoundVariable<'_>]> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.debug_list().entries(self.0.iter().map(Raw)).finish()
+    }
+}
+
+impl Debug for Raw<'_, BoundVariable<'_>> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.debug_struct("BoundVariable")
+            .field("name", &self.0.name)
+            .field("attributes", &Raw(&*self.0.attributes))
+            .finish()
+    }
+}
+
+impl<'om> Debug for Raw<'_, [Attr<'om, OMMaybeForeign<'om, OpenMath<'om>>>]> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.debug_list().entries(self.0.iter().map(Raw)).finish()
+    }
+}
+
+impl<'om> Debug for Raw<'_, Attr<'om, OMMaybeForeign<'om, OpenMath<'om>>>> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.debug_struct("Attr")
+            .field("key", &self.0.key)
+            .field("value", &Raw(&self.0.value))
+            .finish()
+    }
+}
+
+impl Debug for Raw<'_, [OMMaybeForeign<'_, OpenMath<'_>>]> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.debug_list().entries(self.0.iter().map(Raw)).finish()
+    }
+}
+
+impl Debug for Raw<'_, OMMaybeForeign<'_, OpenMath<'_>>> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self.0 {
+            OMMaybeForeign::OM(o) => f.debug_tuple("OM").field(&Raw(o)).finish(),
+            OMMaybeForeign::Foreign { encoding, value } => f
+                .debug_struct("Foreign")
+                .field("encoding", encoding)
+                .field("value", value)
+                .finish(),
+        }
+    }
+}
+
+/// Prints the same notation as [`OpenMath`]'s [`Debug`] implementation, with the children
+/// in their own [`Debug`] notation, e.g. `OMA(OMS(arith1#plus),1,2)` for an `OM<'_, i64>`.
+impl<I: Debug> Debug for OM<'_, I> {
+    #[allow(clippy::too_many_lines)]
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Self::OMI { int, attrs } => attributed(f, attrs, |f| write!(f, "OMI({int})")),
+            Self::OMF {
+                float,
+                lexical,
+                attrs,
+            } => attributed(f, attrs, |f| {
+                f.write_str("OMF(")?;
+                FloatStyle::default().write(f, *float)?;
+                if let Some(lexical) = lexical {
+                    write!(f, ", {lexical:?}")?;
+                }
+                f.write_char(')')
+            }),
+            Self::OMSTR { string, attrs } => {
+                attributed(f, attrs, |f| write_omstr(f, string, Some(MAX_LEN)))
+            }
+            Self::OMB { bytes, attrs } => attributed(f, attrs, |f| {
+                write_omb(f, bytes.iter().copied(), Some(MAX_LEN))
+            }),
+            Self::OMV { name, attrs } => attributed(f, attrs, |f| write!(f, "OMV({name})")),
+            Self::OMS {
+                cdbase,
+                cd,
+                name,
+                attrs,
+            } => attributed(f, attrs, |f| {
+                f.write_str("OMS(")?;
+                symbol(f, cdbase.as_deref(), cd, name)?;
+                f.write_char(')')
+            }),
+            Self::OMA {
+                applicant,
+                arguments,
+                attrs,
+            } => attributed(f, attrs, |f| {
+                write!(f, "OMA({applicant:?}")?;
+                for a in arguments {
+                    write!(f, ",{a:?}")?;
+                }
+                f.write_char(')')
+            }),
+            Self::OMBIND {
+                binder,
+                variables,
+                object,
+                attrs,
+            } => attributed(f, attrs, |f| {
+                write!(f, "OMBIND({binder:?},[")?;
+                for (i, (name, attrs)) in variables.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    attributed(f, attrs, |f| write!(f, "OMV({name})"))?;
+                }
+                write!(f, "],{object:?})")
+            }),
+            Self::OME {
+                cdbase,
+                cd,
+                name,
+                arguments,
+                attrs,
+            } => attributed(f, attrs, |f| {
+                f.write_str("OME")?;
+                symbol(f, cdbase.as_deref(), cd, name)?;
+                f.write_char('(')?;
+                for (i, a) in arguments.iter().enumerate() {
+                    if i > 0 {
+                        f.write_char(',')?;
+                    }
+                    maybe_foreign(f, a)?;
+                }
+                f.write_char(')')
+            }),
+            Self::OMR { href, attrs } => attributed(f, attrs, |f| write!(f, "OMR({href})")),
+        }
+    }
+}
+
+/// Writes `cdbase/cd#name`
+fn symbol(f: &mut Formatter<'_>, cdbase: Option<&str>, cd: &str, name: &str) -> Result {
+    if let Some(cdbase) = cdbase {
+        write!(f, "{cdbase}/")?;
+    }
+    write!(f, "{cd}#{name}")
+}
+
+/// Writes `inner`, wrapped in `OMATTR(...,[key = value, ...])` if there are any attributes
+fn attributed<I: Debug>(
+    f: &mut Formatter<'_>,
+    attrs: &[OMAttr<'_, I>],
+    inner: impl FnOnce(&mut Formatter<'_>) -> Result,
+) -> Result {
+    if attrs.is_empty() {
+        return inner(f);
+    }
+    f.write_str("OMATTR(")?;
+    inner(f)?;
+    f.write_str(",[")?;
+    for (i, a) in attrs.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        f.write_str("OMS(")?;
+        symbol(f, a.key.cdbase.as_deref(), &a.key.cd, &a.key.name)?;
+        f.write_str(") = ")?;
+        maybe_foreign(f, &a.value)?;
+    }
+    f.write_str("])")
+}
+
+fn maybe_foreign<I: Debug>(f: &mut Formatter<'_>, o: &OMMaybeForeign<'_, I>) -> Result {
+    match o {
+        OMMaybeForeign::OM(o) => o.fmt(f),
+        OMMaybeForeign::Foreign { encoding, value } => {
+            write_foreign(f, encoding.as_deref(), value, Some(MAX_LEN))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{OM, OMMaybeForeign, OpenMath, SymbolRef};
+
+    #[test]
+    fn compact_debug() {
+        let long = "x".repeat(1000);
+        let om = OpenMath::from_sexpr(&format!(
+            r#"(omattr (((oms meta note) (omstr "{long}")))
+                 (oma (oms arith1 plus) (omi 1) (omb "AQID") (ome (oms moreerrors unexpected) (omforeign "{long}"))))"#
+        ))
+        .expect("is valid");
+        let debug = format!("{om:?}");
+        assert_eq!(
+            debug,
+            "OMATTR(OMA(OMS(arith1#plus),OMI(1),OMB(1,2,3),\
+             OMEmoreerrors#unexpected(OMFOREIGN(xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx… (1000 chars)))),\
+             [OMS(meta#note) = OMSTR(\"xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx…\" (1000 chars))])"
+        );
+        // nested in a derived implementation
+        assert!(format!("{:?}", Some(&om)).len() < 300);
+
+        let bytes = OpenMath::OMB {
+            bytes: (0..=255).collect::<Vec<u8>>().into(),
+            attributes: Vec::new(),
+        };
+        assert!(format!("{bytes:?}").ends_with(",31,… (256 bytes))"));
+
+        // the raw output tells inherited and explicit cdbases apart
+        let inherited = OpenMath::from_sexpr("(oms arith1 plus)").expect("is valid");
+        let OpenMath::OMS { cd, name, .. } = &inherited else {
+            panic!("is an OMS")
+        };
+        let explicit = OpenMath::OMS {
+            cd: cd.clone(),
+            name: name.clone(),
+            cdbase: None,
+            cdbase_inherited: false,
+            attributes: Vec::new(),
+        };
+        assert_ne!(
+            format!("{:?}", inherited.raw_debug()),
+            format!("{:?}", explicit.raw_debug())
+        );
+    }
+
+    #[test]
+    fn om_debug() {
+        let om: OM<'_, i64> = OM::OMA {
+            applicant: 1,
+            arguments: [2, 3].into_iter().collect(),
+            attrs: vec![crate::Attr::new(
+                SymbolRef::parse("meta#note").expect("is valid"),
+                OMMaybeForeign::Foreign {
+                    encoding: Some("text".into()),
+                    value: "a".repeat(40).into(),
+                },
+            )],
+        };
+        assert_eq!(
+            format!("{om:?}"),
+            "OMATTR(OMA(1,2,3),[OMS(meta#note) = \
+             OMFOREIGN(encoding=text, aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa… (40 chars))])"
+        );
+        let om: OM<'_, i64> = OM::OMBIND {
+            binder: 0,
+            variables: std::iter::once(("x".into(), Vec::new())).collect(),
+            object: 1,
+            attrs: Vec::new(),
+        };
+        assert_eq!(format!("{om:?}"), "OMBIND(0,[OMV(x)],1)");
+    }
+}
@@ -0,0 +1,383 @@
+/*! Whole <span style="font-variant:small-caps;">OpenMath</span> documents: an object together
+with what is known about the document it came from.
+
+The low-level entry points ([`OMDeserializable::from_openmath_xml`],
+[`de::OMObject`](crate::de::OMObject), [`OMSerializable::xml`], …) only deal with the object
+itself. A [`Document`] additionally keeps its [`Metadata`]: the `version` and `id` of the
+OMOBJ, the cdbase and document URI it was read with, where it came from, and the
+[`Warning`]s about what lenient [`Options`] let pass.
+
+# Examples
+```
+# #[cfg(feature = "xml-read")]
+# {
+use openmath::{Document, OpenMath, de::{AttributePolicy, Options, VersionPolicy}};
+
+let s = r#"<OMOBJ version="3.0" id="x"><OMS cd="arith1" cd="arith2" name="plus"/></OMOBJ>"#;
+assert!(Document::<OpenMath>::parse_xml(s).is_err());
+
+let options = Options::new()
+    .with_version_policy(VersionPolicy::Warn)
+    .with_attribute_policy(AttributePolicy::Lenient);
+let doc = Document::<OpenMath>::parse_xml_with(s, options)
+    .expect("is lenient")
+    .with_source("plus.om");
+assert_eq!(doc.metadata.id.as_deref(), Some("x"));
+assert_eq!(doc.metadata.warnings.len(), 2);
+for warning in &doc.metadata.warnings {
+    eprintln!("{}: {warning}", doc.metadata.source.as_deref().unwrap_or("?"));
+}
+# #[cfg(feature = "xml-write")]
+assert!(doc.to_xml(&openmath::ser::XmlOptions::default()).expect("works").starts_with(r#"<OMOBJ version="3.0" id="x""#));
+# }
+```
+*/
+
+#[cfg(any(feature = "xml-read", all(feature = "serde", feature = "serde_json")))]
+use crate::de::Options;
+use crate::{OMDeserializable, OMSerializable, OpenMath};
+
+/// An <span style="font-variant:small-caps;">OpenMath</span> object with its [`Metadata`];
+/// see the [module documentation](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Document<T = OpenMath<'static>> {
+    /// The object
+    pub object: T,
+    /// What is known about the document
+    pub metadata: Metadata,
+}
+
+/// Document-level data of a [`Document`]. New fields may be added in minor releases, so
+/// start from [`Default`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Metadata {
+    /// The `version` attribute of the OMOBJ (the `openmath` field in JSON), if present
+    pub version: Option<String>,
+    /// The `id` attribute of the OMOBJ, if present (only in XML)
+    pub id: Option<String>,
+    /// The cdbase that symbols without one are resolved against, if not
+    /// [`CD_BASE`](crate::CD_BASE); see [`Options::with_default_cdbase`]
+    pub cdbase: Option<String>,
+    /// The URI of the document, against which relative cdbases are resolved; see
+    /// [`Options::base`]
+    pub base: Option<String>,
+    /// Where the document came from, e.g. a file name; for messages only
+    pub source: Option<String>,
+    /// What lenient [`Options`] accepted while reading the document
+    pub warnings: Vec<Warning>,
+}
+
+/// Something questionable in a [`Document`] that was accepted nonetheless, because the
+/// [`Options`] it was read with are lenient.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum Warning {
+    /// The OMOBJ has a `version` other than [`OPENMATH_VERSION`](crate::OPENMATH_VERSION);
+    /// see [`VersionPolicy::Warn`](crate::de::VersionPolicy::Warn)
+    #[error("unsupported OpenMath version {0}")]
+    Version(String),
+    /// An attribute occurs more than once on the element at byte `position`, and only its
+    /// first occurrence was read; see
+    /// [`AttributePolicy::Lenient`](crate::de::AttributePolicy::Lenient)
+    #[error("duplicate attribute {attribute} at {position}; only the first one was read")]
+    DuplicateAttribute {
+        /// The name of the attribute
+        attribute: String,
+        /// The byte offset of the element
+        position: u64,
+    },
+}
+
+/// Errors when reading a [`Document`] from the
+/// <span style="font-variant:small-caps;">OpenMath</span> JSON encoding.
+#[cfg(all(feature = "serde", feature = "serde_json"))]
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum JsonReadError {
+    /// The input is empty or whitespace only
+    #[error("empty input; expected an OpenMath object")]
+    EmptyInput,
+    /// The input is not a valid OMOBJ in JSON
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+}
+
+impl<T> Document<T> {
+    /// Wraps `object`, without any metadata.
+    #[inline]
+    pub fn new(object: T) -> Self {
+        Self {
+            object,
+            metadata: Metadata::default(),
+        }
+    }
+
+    /// Sets the [`source`](Metadata::source) of the document.
+    #[inline]
+    #[must_use]
+    pub fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.metadata.source = Some(source.into());
+        self
+    }
+
+    /// Returns the object, dropping the metadata.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.object
+    }
+}
+
+impl<'de, T: OMDeserializable<'de> + 'de> Document<T> {
+    /// Collects the metadata of `object`, read with `options`.
+    #[cfg(any(feature = "xml-read", all(feature = "serde", feature = "serde_json")))]
+    fn read(
+        object: crate::de::OMObject<'de, T>,
+        options: &Options<'_>,
+        mut warnings: Vec<Warning>,
+    ) -> Self {
+        if let Some(version) = object.version()
+            && options.version_policy.warns(Some(version))
+        {
+            warnings.insert(0, Warning::Version(version.to_string()));
+        }
+        Self {
+            metadata: Metadata {
+                version: object.version().map(ToString::to_string),
+                id: object.id().map(ToString::to_string),
+                cdbase: options.default_cdbase.map(ToString::to_string),
+                base: options.base.map(ToString::to_string),
+                source: None,
+                warnings,
+            },
+            object: object.into_inner(),
+        }
+    }
+
+    /** Reads an OMOBJ from <span style="font-variant:small-caps;">OpenMath</span> XML, with
+    the default [`Options`]. The object may borrow from `input`; see
+    [`parse_xml_owned`](Self::parse_xml_owned) for one that does not.
+
+    # Errors
+    like [`OMObject::from_openmath_xml_with`](crate::de::OMObject::from_openmath_xml_with)
+    */
+    #[cfg(feature = "xml-read")]
+    #[inline]
+    pub fn parse_xml(input: &'de str) -> Result<Self, crate::de::XmlReadError<T::Err>> {
+        Self::parse_xml_with(input, Options::new())
+    }
+
+    /** Like [`parse_xml`](Self::parse_xml), but with the given [`Options`]; what lenient
+    options accept is recorded in the [`warnings`](Metadata::warnings).
+
+    # Errors
+    like [`OMObject::from_openmath_xml_with`](crate::de::OMObject::from_openmath_xml_with)
+    */
+    #[cfg(feature = "xml-read")]
+    pub fn parse_xml_with<'o>(
+        input: &'de str,
+        options: impl Into<Options<'o>>,
+    ) -> Result<Self, crate::de::XmlReadError<T::Err>> {
+        let options = options.into();
+        let object = crate::de::OMObject::<T>::from_openmath_xml_with(input, options)?;
+        Ok(Self::read(object, &options, xml_warnings(input, &options)))
+    }
+
+    /** Reads an OMOBJ from the <span style="font-variant:small-caps;">OpenMath</span> JSON
+    encoding, with the default [`Options`].
+
+    # Errors
+    [`JsonReadError::EmptyInput`] iff the input is empty or whitespace only; otherwise
+    iff the input is not a valid OMOBJ in JSON, or [`from_openmath`](OMDeserializable::from_openmath)
+    errors
+
+    # Examples
+    ```
+    use openmath::{Document, OpenMath, document::JsonReadError};
+
+    for input in ["", "  \n"] {
+        let error = Document::<OpenMath>::parse_json(input).expect_err("is empty");
+        assert!(matches!(error, JsonReadError::EmptyInput));
+    }
+    ```
+    */
+    #[cfg(all(feature = "serde", feature = "serde_json"))]
+    #[inline]
+    pub fn parse_json(input: &'de str) -> Result<Self, JsonReadError> {
+        Self::parse_json_with(input, Options::new())
+    }
+
+    /** Like [`parse_json`](Self::parse_json), but with the given [`Options`].
+
+    # Errors
+    see [`parse_json`](Self::parse_json)
+    */
+    #[cfg(all(feature = "serde", feature = "serde_json"))]
+    pub fn parse_json_with<'o>(
+        input: &'de str,
+        options: impl Into<Options<'o>>,
+    ) -> Result<Self, JsonReadError> {
+        use serde::de::DeserializeSeed;
+        if input.trim_ascii().is_empty() {
+            return Err(JsonReadError::EmptyInput);
+        }
+        let options = options.into();
+        let mut de = serde_json::Deserializer::from_str(input);
+        let object = crate::de::OMObject::<T>::seed(options).deserialize(&mut de)?;
+        de.end()?;
+        Ok(Self::read(object, &options, Vec::new()))
+    }
+}
+
+impl<T: OMDeserializable<'static> + 'static> Document<T> {
+    /** Like [`parse_xml_with`](Self::parse_xml_with), but copies whatever the object keeps
+    of `input`; e.g. to read a [`Document<OpenMath<'static>>`](Document) from a temporary
+    string.
+
+    # Errors
+    like [`OMObject::from_openmath_xml_with`](crate::de::OMObject::from_openmath_xml_with)
+    */
+    #[cfg(feature = "xml-read")]
+    pub fn parse_xml_owned<'o>(
+        input: &str,
+        options: impl Into<Options<'o>>,
+    ) -> Result<Self, crate::de::XmlReadError<T::Err>> {
+        let options = options.into();
+        let object =
+            crate::de::OMObject::<T>::from_openmath_xml_reader_with(input.as_bytes(), options)?;
+        Ok(Self::read(object, &options, xml_warnings(input, &options)))
+    }
+}
+
+/// The warnings about `input` that reading it with `options` does not report
+#[cfg(feature = "xml-read")]
+fn xml_warnings(input: &str, options: &Options<'_>) -> Vec<Warning> {
+    if options.attribute_policy != crate::de::AttributePolicy::Lenient {
+        return Vec::new();
+    }
+    crate::de::xml::duplicate_attributes(input)
+        .into_iter()
+        .map(|(attribute, position)| Warning::DuplicateAttribute {
+            attribute,
+            position,
+        })
+        .collect()
+}
+
+impl<T: OMSerializable> Document<T> {
+    /// Writes the document as an OMOBJ in <span style="font-variant:small-caps;">OpenMath</span>
+    /// XML. The `version`, `id` and default cdbase of `options` are replaced by those of the
+    /// [`metadata`](Self::metadata), where it has them.
+    ///
+    /// # Errors
+    /// like [`ser::OMObject::try_xml_with`](crate::ser::OMObject::try_xml_with)
+    #[cfg(feature = "xml-write")]
+    pub fn to_xml(
+        &self,
+        options: &crate::ser::XmlOptions<'_>,
+    ) -> Result<String, crate::ser::XmlWriteError> {
+        let metadata = &self.metadata;
+        crate::ser::OMObject(&self.object).try_xml_with(crate::ser::XmlOptions {
+            version: metadata.version.as_deref().or(options.version),
+            id: metadata.id.as_deref().or(options.id),
+            default_cdbase: metadata.cdbase.as_deref().unwrap_or(options.default_cdbase),
+            ..*options
+        })
+    }
+
+    /// Writes the document as an OMOBJ in the
+    /// <span style="font-variant:small-caps;">OpenMath</span> JSON encoding, which has no `id`
+    /// and always states the current version.
+    ///
+    /// # Errors
+    /// iff [`as_openmath`](OMSerializable::as_openmath) errors
+    #[cfg(all(feature = "serde", feature = "serde_json"))]
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(&crate::ser::OMObject(&self.object))
+    }
+}
+
+#[cfg(test)]
+#[cfg(all(feature = "xml-read", feature = "xml-write"))]
+mod tests {
+    use super::{Document, Warning};
+    use crate::{
+        OpenMath,
+        de::{AttributePolicy, Options, VersionPolicy},
+        ser::XmlOptions,
+    };
+
+    #[test]
+    fn xml_roundtrip() {
+        let s = r#"<OMOBJ version="2.0" id="two" xmlns="http://www.openmath.org/OpenMath"><OMA><OMS cd="units" name="metre"/><OMI>2</OMI></OMA></OMOBJ>"#;
+        let options = Options::new().with_default_cdbase("http://example.org/cd");
+        let doc = Document::<OpenMath>::parse_xml_with(s, options).expect("is valid");
+        assert_eq!(doc.metadata.version.as_deref(), Some("2.0"));
+        assert_eq!(
+            doc.metadata.cdbase.as_deref(),
+            Some("http://example.org/cd")
+        );
+        assert!(doc.metadata.warnings.is_empty());
+        assert_eq!(doc.to_xml(&XmlOptions::default()).expect("works"), s);
+
+        let doc = Document::<i64>::parse_xml("<OMOBJ><OMI>2</OMI></OMOBJ>").expect("is valid");
+        assert_eq!((doc.object, doc.metadata.version), (2, None));
+
+        let input = s.replace("metre", "second");
+        let owned: Document = Document::parse_xml_owned(&input, Options::new())
+            .expect("is valid")
+            .with_source("second.om");
+        drop(input);
+        assert_eq!(owned.metadata.id.as_deref(), Some("two"));
+    }
+
+    #[test]
+    fn warnings() {
+        let s = r#"<OMOBJ version="3.0"><OMA><OMS cd="arith1" name="plus" name="minus"/><OMI id="a" id="b">1</OMI></OMA></OMOBJ>"#;
+        // strict options do not need to warn
+        assert!(Document::<OpenMath>::parse_xml(s).is_err());
+        let options = Options::new()
+            .with_version_policy(VersionPolicy::Warn)
+            .with_attribute_policy(AttributePolicy::Lenient);
+        let doc = Document::<OpenMath>::parse_xml_with(s, options).expect("is lenient");
+        let silent = options.with_version_policy(VersionPolicy::Lenient);
+        let silent = Document::<OpenMath>::parse_xml_with(s, silent).expect("is lenient");
+        assert_eq!(silent.metadata.warnings, doc.metadata.warnings[1..]);
+        assert_eq!(
+            doc.metadata.warnings,
+            [
+                Warning::Version("3.0".to_string()),
+                Warning::DuplicateAttribute {
+                    attribute: "name".to_string(),
+                    position: 26,
+                },
+                Warning::DuplicateAttribute {
+                    attribute: "id".to_string(),
+                    position: 69,
+                },
+            ]
+        );
+    }
+
+    #[cfg(all(feature = "serde", feature = "serde_json"))]
+    #[test]
+    fn json_roundtrip() {
+        let s = r#"{"kind":"OMOBJ","openmath":"2.0","object":{"kind":"OMI","integer":2}}"#;
+        let doc = Document::<OpenMath>::parse_json(s).expect("is valid");
+        assert_eq!(doc.metadata.version.as_deref(), Some("2.0"));
+        assert_eq!(doc.to_json().expect("works"), s);
+        assert!(Document::<OpenMath>::parse_json(&format!("{s} 1")).is_err());
+    }
+
+    #[cfg(all(feature = "serde", feature = "serde_json"))]
+    #[test]
+    fn empty_input_json() {
+        use super::JsonReadError;
+        for s in ["", "  \n"] {
+            let e = Document::<OpenMath>::parse_json(s).expect_err(s);
+            assert!(matches!(e, JsonReadError::EmptyInput), "{s:?}: {e:?}");
+        }
+        let e = Document::<OpenMath>::parse_json("  {").expect_err("is truncated");
+        assert!(matches!(&e, JsonReadError::Json(j) if j.is_eof()), "{e:?}");
+    }
+}
@@ -1,26 +1,214 @@
-/*
-use super::{AsOpenMath, MaybeForeign, OMObjectRef, CD_BASE, URIRef};
+/*! <span style="font-variant:small-caps;">OpenMath</span> errors reporting that an object could
+not be read, e.g. for servers that have to answer a malformed request with an error object
+rather than a plain string; see [`EncodingError`].
+
+Such errors are [`OME`](crate::OMKind::OME)s headed by the symbol
+[`moreerrors.encoding_error`](https://openmath.org/cd/moreerrors#encoding_error), which the
+content dictionary describes as signalling an object that was incorrectly encoded. Their
+arguments are an [`OMSTR`](crate::OMKind::OMSTR) with the error message and, if it is known,
+an [`OMI`](crate::OMKind::OMI) with the byte offset in the input the error occurred at:
+
+```xml
+<OME>
+  <OMS cd="moreerrors" name="encoding_error"/>
+  <OMSTR>ill-formed document: expected `&lt;/OMI>`, but `&lt;/OMA>` was found (at offset 41)</OMSTR>
+  <OMI>41</OMI>
+</OME>
+```
+*/
+
 use std::borrow::Cow;
 
-pub const CD_NAME: &'static str = "error";
-lazy_static! {
-    pub static ref UNHANDLED_SYMBOL: URIRef<'static> = URIRef {
-        base_uri: Cow::Borrowed(&CD_BASE),
-        cd_name: Cow::Borrowed(CD_NAME),
-        name: Cow::Borrowed("unhandled_symbol")
-    };
+use crate::{OMMaybeForeign, OpenMath};
+
+/// The content dictionary of [`ENCODING_ERROR`]
+pub const MOREERRORS: &str = "moreerrors";
+/// The name of the symbol heading [`EncodingError`]s
+pub const ENCODING_ERROR: &str = "encoding_error";
+
+/** The contents of a `moreerrors.encoding_error` [`OME`](crate::OMKind::OME); converts into
+[`OpenMath`] and is recovered from it by [`OpenMath::as_encoding_error`].
+
+# Examples
+```rust
+use openmath::{EncodingError, OMDeserializable, OMSerializable, OpenMath};
+
+# #[cfg(all(feature = "xml-read", feature = "xml-write"))]
+# {
+// server side: reading the request fails
+let input = r#"<OMA><OMS cd="arith1" name="plus"/><OMI>1</OMA>"#;
+let error = OpenMath::from_openmath_xml(input).expect_err("is invalid");
+let response = OpenMath::from(&error);
+let xml = response.xml(false).to_string();
+
+// client side
+let om = OpenMath::from_openmath_xml(&xml).expect("is valid");
+let error = om.as_encoding_error().expect("is an encoding error");
+assert_eq!(error.position, Some(41));
+assert!(error.message.starts_with("ill-formed document: expected `</OMI>`"));
+# }
+```
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EncodingError<'om> {
+    pub message: Cow<'om, str>,
+    /// The byte offset in the input the error occurred at
+    pub position: Option<u64>,
 }
-pub struct OMError<'l, T: AsOpenMath> {
-    pub err: URIRef<'l>,
-    pub args: Vec<MaybeForeign<'l, T>>,
+
+impl EncodingError<'_> {
+    /// An error without position from the message of `error`, e.g. one returned by a
+    /// [serde](https://docs.rs/serde) deserializer (whose positions are usually lines and
+    /// columns, and already part of the message).
+    #[must_use]
+    pub fn from_message(error: &impl std::fmt::Display) -> EncodingError<'static> {
+        EncodingError {
+            message: Cow::Owned(error.to_string()),
+            position: None,
+        }
+    }
 }
 
-impl<'l, T: AsOpenMath> OMError<'l, T> {
-    pub fn unhandled_symbol(uri: URIRef<'l>) -> Self {
+#[cfg(feature = "xml-read")]
+impl<E: std::fmt::Display> From<&crate::de::XmlReadError<E>> for EncodingError<'static> {
+    #[inline]
+    fn from(error: &crate::de::XmlReadError<E>) -> Self {
         Self {
-            err: UNHANDLED_SYMBOL.copy(),
-            args: vec![MaybeForeign::OM(OMObjectRef::OMS(uri))],
+            message: Cow::Owned(error.to_string()),
+            position: error.position(),
+        }
+    }
+}
+
+impl<'om> From<EncodingError<'om>> for OpenMath<'om> {
+    fn from(error: EncodingError<'om>) -> Self {
+        let mut arguments = vec![OMMaybeForeign::OM(Self::OMSTR {
+            string: error.message,
+            attributes: Vec::new(),
+        })];
+        if let Some(position) = error.position {
+            arguments.push(OMMaybeForeign::OM(Self::OMI {
+                int: position.into(),
+                attributes: Vec::new(),
+            }));
+        }
+        Self::OME {
+            cd: Cow::Borrowed(MOREERRORS),
+            name: Cow::Borrowed(ENCODING_ERROR),
+            cdbase: None,
+            arguments,
+            attributes: Vec::new(),
+        }
+    }
+}
+
+/// The `moreerrors.encoding_error` for `error`; see [`EncodingError`]
+#[cfg(feature = "xml-read")]
+impl<E: std::fmt::Display> From<&crate::de::XmlReadError<E>> for OpenMath<'static> {
+    #[inline]
+    fn from(error: &crate::de::XmlReadError<E>) -> Self {
+        EncodingError::from(error).into()
+    }
+}
+
+impl OpenMath<'_> {
+    /// The message and position of a `moreerrors.encoding_error` [`OME`](Self::OME) with the
+    /// standard cdbase; `None` for any other object, or if the arguments are not an
+    /// [`OMSTR`](Self::OMSTR) optionally followed by an [`OMI`](Self::OMI) that fits into
+    /// a [`u64`]. See [`EncodingError`].
+    #[must_use]
+    pub fn as_encoding_error(&self) -> Option<EncodingError<'_>> {
+        let Self::OME {
+            cd,
+            name,
+            cdbase,
+            arguments,
+            ..
+        } = self
+        else {
+            return None;
+        };
+        if cd != MOREERRORS
+            || name != ENCODING_ERROR
+            || !cdbase
+                .as_ref()
+                .is_none_or(|c| crate::cdbase::eq_normalized(c, crate::CD_BASE))
+        {
+            return None;
+        }
+        let (message, position) = match &arguments[..] {
+            [OMMaybeForeign::OM(message)] => (message, None),
+            [OMMaybeForeign::OM(message), OMMaybeForeign::OM(position)] => {
+                (message, Some(position))
+            }
+            _ => return None,
+        };
+        let position = match position {
+            Some(p) => Some(u64::try_from(p.as_i64()?).ok()?),
+            None => None,
+        };
+        Some(EncodingError {
+            message: Cow::Borrowed(message.as_str()?),
+            position,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        for position in [None, Some(0), Some(42)] {
+            let error = EncodingError {
+                message: Cow::Borrowed("oops"),
+                position,
+            };
+            let om = OpenMath::from(error.clone());
+            assert_eq!(om.as_encoding_error(), Some(error));
+        }
+        assert_eq!(
+            OpenMath::from(EncodingError::from_message(&"bad json"))
+                .as_encoding_error()
+                .map(|e| (e.message.into_owned(), e.position)),
+            Some(("bad json".to_string(), None))
+        );
+    }
+
+    #[test]
+    fn other_errors() {
+        for s in [
+            r#"(ome (oms moreerrors unexpected) (omstr "oops"))"#,
+            "(ome (oms moreerrors encoding_error) (omi 1))",
+            r#"(ome (oms moreerrors encoding_error) (omstr "oops") (omi -1))"#,
+            r#"(ome (oms moreerrors encoding_error) (omstr "oops") (omi 1) (omi 2))"#,
+            r#"(ome (oms moreerrors encoding_error) (omstr "oops") (omforeign "1"))"#,
+            r#"(cdbase "http://example.org" (ome (oms moreerrors encoding_error) (omstr "oops")))"#,
+            r#"(omstr "oops")"#,
+        ] {
+            let om = OpenMath::from_sexpr(s).expect("is valid");
+            assert_eq!(om.as_encoding_error(), None, "{s}");
+        }
+    }
+
+    #[cfg(all(feature = "xml-read", feature = "xml-write"))]
+    #[test]
+    fn xml_read_errors() {
+        use crate::{OMDeserializable, OMSerializable};
+        for (input, position) in [
+            (r#"<OMA><OMS cd="a" name="b"/><OMI>1</OMA>"#, Some(33)),
+            ("<OMB>%%</OMB>", Some(0)),
+            (r#"<OMA><OMS cd="a"/></OMA>"#, None),
+            ("", None),
+        ] {
+            let error = OpenMath::from_openmath_xml(input).expect_err("is invalid");
+            assert_eq!(error.position(), position, "{input}");
+            let xml = OpenMath::from(&error).xml(false).to_string();
+            let om = OpenMath::from_openmath_xml(&xml).expect("is valid");
+            let recovered = om.as_encoding_error().expect("is an encoding error");
+            assert_eq!(recovered.message, error.to_string(), "{input}");
+            assert_eq!(recovered.position, position, "{input}");
         }
     }
 }
-*/
@@ -0,0 +1,434 @@
+//! Normalizing and comparing floats; see [`OpenMath::canonicalize_floats`] and
+//! [`OpenMath::approx_eq`]; and checking whether decimals are exact floats, for
+//! [`FloatPolicy`](crate::de::FloatPolicy)
+
+use ordered_float::OrderedFloat;
+
+use crate::{Attr, BoundVariable, OMMaybeForeign, OpenMath};
+
+impl OpenMath<'_> {
+    /** Replaces every negative zero in [OMF](Self::OMF)s in this object (including attribute
+    values) by a positive one, and every NaN by [`f64::NAN`], so that equal objects have
+    equal [bits](f64::to_bits) as well; see [`OMF`](Self::OMF) for why that matters.
+
+    # Examples
+    ```rust
+    use openmath::OpenMath;
+
+    let mut om = OpenMath::from_sexpr("(oma (oms arith1 times) (omf -0.0) (omf NaN))")
+        .expect("is valid");
+    om.canonicalize_floats();
+    let OpenMath::OMA { arguments, .. } = &om else { unreachable!() };
+    let OpenMath::OMF { float, .. } = &arguments[0] else { unreachable!() };
+    assert!(float.is_sign_positive());
+    ```
+    */
+    pub fn canonicalize_floats(&mut self) {
+        if let Self::OMF { float, .. } = self {
+            *float = OrderedFloat(canonical(float.0));
+        }
+        self.for_each_child_mut(&mut Self::canonicalize_floats);
+    }
+
+    /** Whether `self` and `other` are [equal](PartialEq), except that corresponding
+    [OMF](Self::OMF)s (including those in attribute values) may differ by at most
+    `epsilon`. As for [`Eq`], NaNs are equal to each other, and `-0.0` is equal to `0.0`;
+    infinities are only equal to themselves. [OMI](Self::OMI)s are never equal to
+    [OMF](Self::OMF)s.
+
+    # Examples
+    ```rust
+    use openmath::OpenMath;
+
+    let a = OpenMath::from_sexpr("(oma (oms arith1 plus) (omf 0.1) (omf 0.2))").expect("is valid");
+    let b = OpenMath::from_sexpr("(oma (oms arith1 plus) (omf 0.1000001) (omf 0.2))").expect("is valid");
+    assert_ne!(a, b);
+    assert!(a.approx_eq(&b, 1e-6));
+    assert!(!a.approx_eq(&b, 1e-8));
+    ```
+    */
+    #[must_use]
+    pub fn approx_eq(&self, other: &Self, epsilon: f64) -> bool {
+        let eq = |a: &Self, b: &Self| a.approx_eq(b, epsilon);
+        let attrs_eq = |a: &[Attr<'_, OMMaybeForeign<'_, Self>>],
+                        b: &[Attr<'_, OMMaybeForeign<'_, Self>>]| {
+            a.len() == b.len()
+                && a.iter()
+                    .zip(b)
+                    .all(|(a, b)| a.key == b.key && maybe_foreign_eq(&a.value, &b.value, epsilon))
+        };
+        if !attrs_eq(self.attributes(), other.attributes()) {
+            return false;
+        }
+        match (self, other) {
+            (Self::OMF { float: a, .. }, Self::OMF { float: b, .. }) => {
+                a == b || (a.is_finite() && b.is_finite() && (a.0 - b.0).abs() <= epsilon)
+            }
+            (
+                Self::OMA {
+                    applicant: fa,
+                    arguments: aa,
+                    ..
+                },
+                Self::OMA {
+                    applicant: fb,
+                    arguments: ab,
+                    ..
+                },
+            ) => eq(fa, fb) && aa.len() == ab.len() && aa.iter().zip(ab).all(|(a, b)| eq(a, b)),
+            (
+                Self::OME {
+                    cdbase: ba,
+                    cd: ca,
+                    name: na,
+                    arguments: aa,
+                    ..
+                },
+                Self::OME {
+                    cdbase: bb,
+                    cd: cb,
+                    name: nb,
+                    arguments: ab,
+                    ..
+                },
+            ) => {
+                (ba, ca, na) == (bb, cb, nb)
+                    && aa.len() == ab.len()
+                    && aa
+                        .iter()
+                        .zip(ab)
+                        .all(|(a, b)| maybe_foreign_eq(a, b, epsilon))
+            }
+            (
+                Self::OMBIND {
+                    binder: ba,
+                    variables: va,
+                    object: oa,
+                    ..
+                },
+                Self::OMBIND {
+                    binder: bb,
+                    variables: vb,
+                    object: ob,
+                    ..
+                },
+            ) => {
+                eq(ba, bb)
+                    && va.len() == vb.len()
+                    && va
+                        .iter()
+                        .zip(vb)
+                        .all(|(a, b)| a.name == b.name && attrs_eq(&a.attributes, &b.attributes))
+                    && eq(oa, ob)
+            }
+            (Self::OMA { .. } | Self::OME { .. } | Self::OMBIND { .. }, _)
+            | (_, Self::OMA { .. } | Self::OME { .. } | Self::OMBIND { .. }) => false,
+            // leaves without floats; their attributes are equal already
+            (a, b) => a.without_attributes() == b.without_attributes(),
+        }
+    }
+
+    /// A copy of this leaf without attributes
+    fn without_attributes(&self) -> Option<Self> {
+        let mut leaf = match self {
+            Self::OMA { .. } | Self::OME { .. } | Self::OMBIND { .. } => return None,
+            _ => self.clone(),
+        };
+        leaf.attributes_mut().clear();
+        Some(leaf)
+    }
+
+    /// Calls `f` on every immediate subterm of this object (including attribute values)
+    fn for_each_child_mut(&mut self, f: &mut impl FnMut(&mut Self)) {
+        fn attrs<'om>(
+            attributes: &mut [Attr<'om, OMMaybeForeign<'om, OpenMath<'om>>>],
+            f: &mut impl FnMut(&mut OpenMath<'om>),
+        ) {
+            for a in attributes {
+                if let OMMaybeForeign::OM(o) = &mut a.value {
+                    f(o);
+                }
+            }
+        }
+        attrs(self.attributes_mut(), f);
+        match self {
+            Self::OMA {
+                applicant,
+                arguments,
+                ..
+            } => {
+                f(applicant);
+                arguments.iter_mut().for_each(f);
+            }
+            Self::OME { arguments, .. } => {
+                for a in arguments {
+                    if let OMMaybeForeign::OM(o) = a {
+                        f(o);
+                    }
+                }
+            }
+            Self::OMBIND {
+                binder,
+                variables,
+                object,
+                ..
+            } => {
+                f(binder);
+                for BoundVariable { attributes, .. } in variables {
+                    attrs(attributes, f);
+                }
+                f(object);
+            }
+            _ => (),
+        }
+    }
+}
+
+fn maybe_foreign_eq(
+    a: &OMMaybeForeign<'_, OpenMath<'_>>,
+    b: &OMMaybeForeign<'_, OpenMath<'_>>,
+    epsilon: f64,
+) -> bool {
+    match (a, b) {
+        (OMMaybeForeign::OM(a), OMMaybeForeign::OM(b)) => a.approx_eq(b, epsilon),
+        _ => a == b,
+    }
+}
+
+/// `f` with a positive zero and [`f64::NAN`] as the only NaN
+pub fn canonical(f: f64) -> f64 {
+    if f.is_nan() { f64::NAN } else { f + 0.0 }
+}
+
+/// Whether the decimal `s` is exactly `float`, the result of [parsing](str::parse) it; the
+/// literals `inf`, `infinity` and `NaN` (in any case) are exact.
+#[cfg(any(feature = "xml-read", feature = "serde"))]
+pub fn is_exact(s: &str, float: f64) -> bool {
+    let unsigned = s.strip_prefix(['+', '-']).unwrap_or(s);
+    if !unsigned.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+        return true;
+    }
+    if !float.is_finite() {
+        return false;
+    }
+    let (mantissa, exponent) = unsigned.split_once(['e', 'E']).unwrap_or((unsigned, "0"));
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{int}{frac}");
+    let digits = digits.trim_start_matches('0');
+    if digits.is_empty() {
+        return true;
+    }
+    let trimmed = digits.trim_end_matches('0');
+    let Ok(exponent) = exponent.parse::<i64>() else {
+        // far out of range, hence 0 or infinite
+        return false;
+    };
+    #[allow(clippy::cast_possible_wrap)]
+    let exponent =
+        exponent.saturating_sub(frac.len() as i64) + (digits.len() - trimmed.len()) as i64;
+    let (exact, exact_exponent) = decimal(float.abs());
+    exact == trimmed && exact_exponent == exponent
+}
+
+/// The digits `d` (without leading or trailing zeros) and exponent `e` of the positive finite
+/// `f` = d × 10<sup>e</sup>; there are at most 767 of them.
+#[cfg(any(feature = "xml-read", feature = "serde"))]
+fn decimal(f: f64) -> (String, i64) {
+    use std::fmt::Write;
+    /// The base of the limbs
+    const BASE: u64 = 1_000_000_000;
+    fn multiply(limbs: &mut Vec<u64>, factor: u64) {
+        let mut carry = 0;
+        for l in limbs.iter_mut() {
+            let v = *l * factor + carry;
+            *l = v % BASE;
+            carry = v / BASE;
+        }
+        while carry > 0 {
+            limbs.push(carry % BASE);
+            carry /= BASE;
+        }
+    }
+
+    let bits = f.to_bits();
+    let biased = (bits >> 52) & 0x7ff;
+    let fraction = bits & ((1 << 52) - 1);
+    // f = mantissa × 2^exponent
+    #[allow(clippy::cast_possible_wrap)]
+    let (mantissa, exponent) = if biased == 0 {
+        (fraction, -1074)
+    } else {
+        (fraction | (1 << 52), biased as i64 - 1075)
+    };
+    if mantissa == 0 {
+        return (String::new(), 0);
+    }
+    let zeros = mantissa.trailing_zeros();
+    let (mantissa, exponent) = (mantissa >> zeros, exponent + i64::from(zeros));
+    // little-endian limbs in base 10^9
+    let mut limbs = vec![mantissa % BASE, mantissa / BASE];
+    // = mantissa × 2^exponent, or mantissa × 5^-exponent × 10^exponent
+    let (factor, chunk, mut remaining) = if exponent >= 0 {
+        (2u64, 29, exponent.unsigned_abs())
+    } else {
+        (5, 13, exponent.unsigned_abs())
+    };
+    while remaining > 0 {
+        let n = remaining.min(chunk);
+        #[allow(clippy::cast_possible_truncation)]
+        multiply(&mut limbs, factor.pow(n as u32));
+        remaining -= n;
+    }
+    while limbs.len() > 1 && limbs.last() == Some(&0) {
+        limbs.pop();
+    }
+    let mut digits = limbs.last().map(u64::to_string).unwrap_or_default();
+    for l in limbs.iter().rev().skip(1) {
+        write!(digits, "{l:09}").expect("writing to a string");
+    }
+    let trimmed = digits.trim_end_matches('0').len();
+    #[allow(clippy::cast_possible_wrap)]
+    let exponent = exponent.min(0) + (digits.len() - trimmed) as i64;
+    digits.truncate(trimmed);
+    (digits, exponent)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::hash::{BuildHasher, RandomState};
+
+    use crate::OpenMath;
+
+    fn omf(f: f64) -> OpenMath<'static> {
+        OpenMath::OMF {
+            float: f.into(),
+            attributes: Vec::new(),
+        }
+    }
+
+    fn float(om: &OpenMath<'_>) -> f64 {
+        let OpenMath::OMF { float, .. } = om else {
+            panic!("is an OMF")
+        };
+        float.0
+    }
+
+    #[cfg(any(all(feature = "xml-read", feature = "xml-write"), feature = "serde"))]
+    const SPECIAL: [f64; 6] = [f64::NAN, f64::INFINITY, f64::NEG_INFINITY, -0.0, 0.0, 1.5];
+
+    #[test]
+    fn eq_and_hash() {
+        let hasher = RandomState::new();
+        let negative_nan = -f64::NAN;
+        let payload_nan = f64::from_bits(f64::NAN.to_bits() | 1);
+        for (a, b) in [
+            (f64::NAN, negative_nan),
+            (f64::NAN, payload_nan),
+            (0.0, -0.0),
+        ] {
+            assert_eq!(omf(a), omf(b), "{a} vs {b}");
+            assert_eq!(
+                hasher.hash_one(omf(a)),
+                hasher.hash_one(omf(b)),
+                "{a} vs {b}"
+            );
+        }
+        assert_ne!(omf(f64::INFINITY), omf(f64::NEG_INFINITY));
+        assert_ne!(omf(f64::NAN), omf(f64::INFINITY));
+
+        let mut om = omf(-0.0);
+        om.canonicalize_floats();
+        assert_eq!(float(&om).to_bits(), 0.0f64.to_bits());
+        for nan in [negative_nan, payload_nan] {
+            let mut om = omf(nan);
+            om.canonicalize_floats();
+            assert_eq!(float(&om).to_bits(), f64::NAN.to_bits());
+        }
+    }
+
+    #[test]
+    fn approx_eq() {
+        assert!(omf(f64::NAN).approx_eq(&omf(-f64::NAN), 0.0));
+        assert!(omf(-0.0).approx_eq(&omf(0.0), 0.0));
+        assert!(omf(f64::INFINITY).approx_eq(&omf(f64::INFINITY), 0.0));
+        assert!(!omf(f64::INFINITY).approx_eq(&omf(f64::NEG_INFINITY), f64::INFINITY));
+        assert!(!omf(f64::NAN).approx_eq(&omf(1.0), f64::INFINITY));
+        assert!(omf(1.0).approx_eq(&omf(1.5), 0.5));
+        assert!(!omf(1.0).approx_eq(&omf(1.5), 0.4));
+    }
+
+    #[cfg(all(feature = "xml-read", feature = "xml-write"))]
+    #[test]
+    fn xml_roundtrip() {
+        use crate::{OMDeserializable, OMSerializable};
+        for f in SPECIAL {
+            let xml = omf(f).xml(false).to_string();
+            let back = OpenMath::from_openmath_xml(&xml).expect("roundtrips");
+            assert_eq!(float(&back).to_bits(), f.to_bits(), "{xml}");
+        }
+        // the forms of the standard
+        assert_eq!(
+            omf(f64::NEG_INFINITY).xml(false).to_string(),
+            r#"<OMF dec="-INF"/>"#
+        );
+        assert_eq!(omf(f64::NAN).xml(false).to_string(), r#"<OMF dec="NaN"/>"#);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_roundtrip() {
+        use crate::{OMSerializable, de::OMFromSerde};
+        for f in SPECIAL {
+            let om = omf(f);
+            for json in [
+                serde_json::to_string(&om.openmath_serde()).expect("works"),
+                serde_json::to_string(&om.openmath_serde_compact()).expect("works"),
+            ] {
+                let back = serde_json::from_str::<OMFromSerde<OpenMath>>(&json)
+                    .expect("roundtrips")
+                    .into_inner();
+                assert_eq!(float(&back).to_bits(), f.to_bits(), "{json}");
+            }
+        }
+        assert_eq!(
+            serde_json::to_string(&omf(f64::NEG_INFINITY).openmath_serde()).expect("works"),
+            r#"{"kind":"OMF","decimal":"-inf"}"#
+        );
+    }
+
+    #[cfg(any(feature = "xml-read", feature = "serde"))]
+    #[test]
+    fn exact_decimals() {
+        for (s, exact) in [
+            ("0.5", true),
+            ("1.25e2", true),
+            ("-0.000", true),
+            ("+.5", true),
+            ("5.", true),
+            ("0e999999999999999999999", true),
+            ("9007199254740992", true),
+            ("9007199254740993", false),
+            ("4.9406564584124654e-324", false),
+            ("1e308", false),
+            ("1e400", false),
+            ("1e-400", false),
+            ("0.1", false),
+            ("0.1000000000000000000000001", false),
+            ("NaN", true),
+            ("-inf", true),
+        ] {
+            let float: f64 = s.parse().expect("is valid");
+            assert_eq!(super::is_exact(s, float), exact, "{s}");
+        }
+        // the exact values of the smallest and largest floats, and all powers of 2
+        let smallest = format!("{:.1074}", f64::from_bits(1));
+        assert!(super::is_exact(&smallest, f64::from_bits(1)));
+        let largest = format!("{:.0}", f64::MAX);
+        assert!(super::is_exact(&largest, f64::MAX));
+        for e in -1074..1024 {
+            let f = 2f64.powi(e);
+            assert!(super::is_exact(&format!("{f:.1074}"), f), "2^{e}");
+            assert!(!super::is_exact(&format!("{f:.1074}1"), f), "2^{e}");
+        }
+    }
+}
@@ -0,0 +1,375 @@
+//! Recorded formatting of an XML document; see [`FormatMap`]
+
+use std::collections::HashMap;
+
+/** The formatting of an <span style="font-variant:small-caps;">OpenMath</span> XML document,
+as recorded while parsing it; i.e. for every element, the whitespace preceding its start and end
+tags, and the order of its attributes; and the `dec` attribute of every
+[OMF](crate::OMKind::OMF), which is written back as long as the float is unchanged.
+
+Elements are identified by their position in the tree (the indices of their ancestors among
+their respective siblings) and their name. When writing XML via
+[`xml_preserving`](crate::ser::OMSerializable::xml_preserving), elements at the same position
+with the same name reuse the recorded formatting; all others (e.g. newly inserted ones) are
+pretty-printed as usual. Attribute order is currently only reproduced for `OMOBJ`s and
+[OMS](crate::OMKind::OMS)s, and the whitespace surrounding the content of an
+[OMFOREIGN](crate::OMKind::OMFOREIGN) is not recorded.
+
+# Examples
+```
+use openmath::{OpenMath, de::OMDeserializable, ser::OMSerializable};
+
+let s = "<OMA>\n\t<OMS cd=\"arith1\" name=\"plus\"/>\n\t<OMI>1</OMI>  <OMI>2</OMI>\n</OMA>";
+# #[cfg(all(feature = "xml-read", feature = "xml-write"))]
+# {
+let (om, format) = OpenMath::from_openmath_xml_preserving(s).expect("is valid");
+assert_eq!(om.xml_preserving(&format).to_string(), s);
+# }
+```
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FormatMap {
+    starts: HashMap<Vec<usize>, Start>,
+    ends: HashMap<Vec<usize>, Tag>,
+    decimals: HashMap<Vec<usize>, Box<str>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Tag {
+    name: Box<str>,
+    whitespace: Box<str>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Start {
+    tag: Tag,
+    attributes: Box<[Box<str>]>,
+}
+
+impl FormatMap {
+    /// The number of elements with recorded formatting
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// Whether no formatting has been recorded
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.starts.is_empty()
+    }
+
+    #[cfg(feature = "xml-read")]
+    pub(crate) fn record_start(
+        &mut self,
+        path: &[usize],
+        name: &str,
+        whitespace: &str,
+        attributes: impl Iterator<Item: Into<Box<str>>>,
+    ) {
+        self.starts.insert(
+            path.to_vec(),
+            Start {
+                tag: Tag {
+                    name: name.into(),
+                    whitespace: whitespace.into(),
+                },
+                attributes: attributes.map(Into::into).collect(),
+            },
+        );
+    }
+
+    #[cfg(feature = "xml-read")]
+    pub(crate) fn record_end(&mut self, path: &[usize], name: &str, whitespace: &str) {
+        self.ends.insert(
+            path.to_vec(),
+            Tag {
+                name: name.into(),
+                whitespace: whitespace.into(),
+            },
+        );
+    }
+
+    #[cfg(feature = "xml-read")]
+    pub(crate) fn record_decimal(&mut self, path: &[usize], decimal: &str) {
+        self.decimals.insert(path.to_vec(), decimal.into());
+    }
+
+    /// The `dec` attribute of the [OMF](crate::OMKind::OMF) at `path`, if it is `float`
+    #[cfg(feature = "xml-write")]
+    pub(crate) fn decimal(&self, path: &[usize], float: f64) -> Option<&str> {
+        self.decimals.get(path).map(|d| &**d).filter(|d| {
+            d.parse::<f64>()
+                .is_ok_and(|f| f.to_bits() == float.to_bits() || (f.is_nan() && float.is_nan()))
+        })
+    }
+
+    /// The whitespace preceding the start tag of the element `name` at `path`
+    #[cfg(feature = "xml-write")]
+    pub(crate) fn start(&self, path: &[usize], name: &str) -> Option<&str> {
+        self.starts
+            .get(path)
+            .filter(|s| &*s.tag.name == name)
+            .map(|s| &*s.tag.whitespace)
+    }
+
+    /// The whitespace preceding the end tag of the element `name` at `path`
+    #[cfg(feature = "xml-write")]
+    pub(crate) fn end(&self, path: &[usize], name: &str) -> Option<&str> {
+        self.ends
+            .get(path)
+            .filter(|s| &*s.name == name)
+            .map(|s| &*s.whitespace)
+    }
+
+    /// The position of `attribute` among the recorded attributes of the element `name` at
+    /// `path`; attributes not recorded come last.
+    #[cfg(feature = "xml-write")]
+    pub(crate) fn attribute_rank(&self, path: &[usize], name: &str, attribute: &str) -> usize {
+        self.starts
+            .get(path)
+            .filter(|s| &*s.tag.name == name)
+            .and_then(|s| s.attributes.iter().position(|a| &**a == attribute))
+            .unwrap_or(usize::MAX)
+    }
+}
+
+/** Parses the <span style="font-variant:small-caps;">OpenMath</span> XML document `input` and
+writes it back, reproducing the input byte for byte wherever the crate records how it was
+written: the whitespace between tags and the attribute order of `OMOBJ`s and
+[OMS](crate::OMKind::OMS)s (see [`FormatMap`]), the `version`, `id` and `xmlns` attributes
+of an `OMOBJ`, and anything
+before and after the root element (e.g. an XML declaration or comments). The root element
+may be an `OMOBJ` or a bare object.
+
+Byte identity is *not* guaranteed for
+- attribute order on elements other than `OMOBJ` and [OMS](crate::OMKind::OMS), and
+  quotes other than `"` around attribute values;
+- whitespace inside tags (e.g. `<OMI >`) and the form of empty elements (`<OMV/>` vs.
+  `<OMV></OMV>`);
+- the lexical form of integers: hexadecimal [OMI](crate::OMKind::OMI)s and leading zeros
+  or `+` signs are written in the canonical form, as are hexadecimal
+  [OMF](crate::OMKind::OMF)s;
+- escaping: only `<` and `&` are written as entities, and character
+  references, CDATA sections, and comments or processing instructions inside the root
+  element are not reproduced;
+- [OMB](crate::OMKind::OMB)s, whose base64 is re-encoded, and whitespace around the
+  content of [OMFOREIGN](crate::OMKind::OMFOREIGN)s;
+- cdbases that are redundant or placed differently than the serializer would place them;
+- [OMR](crate::OMKind::OMR)s, which are rejected as unresolved references (this reads
+  without an [`OMRPolicy`](crate::de::OMRPolicy)).
+
+# Errors
+if `input` is not valid <span style="font-variant:small-caps;">OpenMath</span> XML.
+
+# Examples
+```
+let s = "<?xml version=\"1.0\"?>\n<OMOBJ version=\"2.0\">\n  <OMA><OMS name=\"plus\" cd=\"arith1\"/>\n    <OMV name=\"x\"/></OMA>\n</OMOBJ>\n";
+assert_eq!(openmath::rewrite_verbatim(s).expect("is valid"), s);
+```
+*/
+#[cfg(all(feature = "xml-read", feature = "xml-write"))]
+pub fn rewrite_verbatim(
+    input: &str,
+) -> Result<String, crate::de::XmlReadError<crate::de::ReferenceError>> {
+    use crate::{
+        OMDeserializable, OpenMath,
+        de::{OMObject, VersionPolicy},
+        ser::{OMSerializable, XmlOptions},
+    };
+    let (start, end) = root_span(input);
+    let (prefix, root, suffix) = (&input[..start], &input[start..end], &input[end..]);
+    let output = if is_start_tag(root, "OMOBJ") {
+        let (obj, format) =
+            OMObject::<OpenMath>::from_openmath_xml_preserving(input, VersionPolicy::Lenient)?;
+        let start_tag = &root[..root.find('>').unwrap_or(root.len())];
+        let options = XmlOptions {
+            insert_namespace: start_tag.contains("xmlns="),
+            version: obj.version(),
+            id: obj.id(),
+            ..XmlOptions::default()
+        };
+        format!("{prefix}{}", obj.as_ser().xml_preserving(options, &format))
+    } else {
+        let (om, format) = OpenMath::from_openmath_xml_preserving(input)?;
+        // the whitespace before a bare root element is recorded as its own
+        let prefix = prefix.trim_end_matches(|c: char| c.is_ascii_whitespace());
+        format!("{prefix}{}", om.xml_preserving(&format))
+    };
+    Ok(format!("{output}{suffix}"))
+}
+
+/// Whether `xml` starts with a start tag named `name`
+#[cfg(all(feature = "xml-read", feature = "xml-write"))]
+fn is_start_tag(xml: &str, name: &str) -> bool {
+    xml.strip_prefix('<')
+        .and_then(|s| s.strip_prefix(name))
+        .is_some_and(|s| s.starts_with(|c: char| c.is_ascii_whitespace() || c == '>' || c == '/'))
+}
+
+/// The byte range of the root element of `xml`, skipping the XML declaration, comments,
+/// processing instructions and a doctype before it, and anything after its last tag.
+#[cfg(all(feature = "xml-read", feature = "xml-write"))]
+fn root_span(xml: &str) -> (usize, usize) {
+    let mut start = 0;
+    loop {
+        let Some(i) = xml[start..].find('<').map(|i| start + i) else {
+            return (start, xml.len());
+        };
+        let rest = &xml[i..];
+        let close = if rest.starts_with("<!--") {
+            "-->"
+        } else if rest.starts_with("<?") {
+            "?>"
+        } else if rest.starts_with("<!") {
+            ">"
+        } else {
+            start = i;
+            break;
+        };
+        match rest.find(close) {
+            Some(j) => start = i + j + close.len(),
+            None => return (i, xml.len()),
+        }
+    }
+    let mut end = xml.len();
+    // trailing comments are not part of the root element
+    while let Some(i) = xml[start..end].rfind('>').map(|i| start + i) {
+        let before = &xml[start..=i];
+        if before.ends_with("-->") {
+            match before.rfind("<!--") {
+                Some(j) => end = start + j,
+                None => break,
+            }
+        } else if before.ends_with("?>") && !before.ends_with("/?>") {
+            match before.rfind("<?") {
+                Some(j) => end = start + j,
+                None => break,
+            }
+        } else {
+            end = i + 1;
+            break;
+        }
+    }
+    (start, end)
+}
+
+/// Tracks the position of the current element in the tree, given the depth of each tag
+#[derive(Debug, Default)]
+pub struct Cursor(Vec<usize>);
+impl Cursor {
+    /// The path of an element whose start tag is at `depth`
+    pub fn start(&mut self, depth: usize) -> &[usize] {
+        self.0.truncate(depth + 1);
+        if self.0.len() == depth + 1 {
+            self.0[depth] += 1;
+        } else {
+            self.0.resize(depth + 1, 0);
+        }
+        &self.0
+    }
+
+    /// The path of the current element at `depth`, whose start tag has already been seen;
+    /// e.g. for its end tag
+    pub fn current(&mut self, depth: usize) -> &[usize] {
+        self.0.truncate(depth + 1);
+        &self.0
+    }
+}
+
+#[cfg(all(test, feature = "xml-read", feature = "xml-write"))]
+mod tests {
+    use crate::{
+        OpenMath,
+        de::{OMDeserializable, OMObject, VersionPolicy},
+        ser::{OMSerializable, XmlOptions},
+    };
+    #[test]
+    fn preserve_roundtrip() {
+        let s = r#"<OMOBJ version="2.0" xmlns="http://www.openmath.org/OpenMath">
+	<OMATTR><OMATP>  <OMS name="type" cd="sts"/>
+	      <OMFOREIGN encoding="text/plain">nat</OMFOREIGN>
+	  </OMATP>
+	  <OMBIND>
+	    <OMS cd="fns1" name="lambda"/>
+	    <OMBVAR><OMV name="x"/></OMBVAR>
+	    <OMA><OMS name="plus" cd="arith1"/>   <OMV name="x"/>
+	         <OMI>1</OMI></OMA>
+	  </OMBIND>
+	</OMATTR>
+</OMOBJ>"#;
+        let (obj, format) =
+            OMObject::<OpenMath>::from_openmath_xml_preserving(s, VersionPolicy::Strict)
+                .expect("is valid");
+        assert_eq!(format.len(), 13);
+        let om = obj.into_inner();
+        assert_eq!(
+            om.omobject()
+                .xml_preserving(XmlOptions::default(), &format)
+                .to_string(),
+            s
+        );
+    }
+    #[test]
+    fn rewrite_verbatim() {
+        for s in [
+            "<!-- a comment -->\n<OMA>\n <OMS name=\"sin\" cd=\"transc1\"/><OMV name=\"x\"/></OMA>\n<!-- end -->\n",
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?><OMOBJ xmlns=\"http://www.openmath.org/OpenMath\" id=\"o\" version=\"2.0\"><OMI>1</OMI></OMOBJ>",
+            "\n<OMOBJ>\n\t<OMSTR>a &lt; b</OMSTR>\n</OMOBJ>",
+        ] {
+            assert_eq!(crate::rewrite_verbatim(s).expect("is valid"), s);
+        }
+        assert!(crate::rewrite_verbatim("<OMOBJ><OMI>x</OMI></OMOBJ>").is_err());
+    }
+
+    #[test]
+    fn preserve_decimals() {
+        let s = r#"<OMA><OMS cd="list1" name="list"/><OMF dec="1.50"/><OMF dec="1e-10"/><OMF dec="0.1000000000000000000000001"/><OMF dec="-0"/></OMA>"#;
+        let (mut om, format) = OpenMath::from_openmath_xml_preserving(s).expect("is valid");
+        assert_eq!(om.xml_preserving(&format).to_string(), s);
+        assert_eq!(crate::rewrite_verbatim(s).expect("is valid"), s);
+        // changed floats are written in the canonical form
+        let OpenMath::OMA { arguments, .. } = &mut om else {
+            panic!("is an OMA")
+        };
+        arguments[0] = OpenMath::OMF {
+            float: 2.5.into(),
+            attributes: Vec::new(),
+        };
+        arguments[3] = OpenMath::OMF {
+            float: 0.0.into(),
+            attributes: Vec::new(),
+        };
+        assert_eq!(
+            om.xml_preserving(&format).to_string(),
+            r#"<OMA><OMS cd="list1" name="list"/><OMF dec="2.5"/><OMF dec="1e-10"/><OMF dec="0.1000000000000000000000001"/><OMF dec="0"/></OMA>"#
+        );
+    }
+
+    #[test]
+    fn preserve_changed() {
+        let s = "<OMA>\n\t<OMS name=\"plus\" cd=\"arith1\"/>\n\t<OMI>1</OMI><OMI>2</OMI>\n</OMA>";
+        let (mut om, format) = OpenMath::from_openmath_xml_preserving(s).expect("is valid");
+        let OpenMath::OMA { arguments, .. } = &mut om else {
+            panic!("is an OMA")
+        };
+        arguments[1] = OpenMath::OMSTR {
+            string: "two".into(),
+            attributes: Vec::new(),
+        };
+        arguments.push(OpenMath::OMI {
+            int: 3.into(),
+            attributes: Vec::new(),
+        });
+        assert_eq!(
+            om.xml_preserving(&format).to_string(),
+            "<OMA>\n\t<OMS name=\"plus\" cd=\"arith1\"/>\n\t<OMI>1</OMI>\n  <OMSTR>two</OMSTR>\n  <OMI>3</OMI>\n</OMA>"
+        );
+        assert_eq!(
+            om.xml_preserving(&crate::FormatMap::default()).to_string(),
+            om.xml(true).to_string()
+        );
+    }
+}
@@ -0,0 +1,308 @@
+/*! Entry points for fuzzing the decoders, e.g. with
+[cargo-fuzz](https://github.com/rust-fuzz/cargo-fuzz); the targets in the `fuzz` directory
+of the repository only call these.
+
+Each function panics iff it finds a bug: malformed input has to be rejected with an error,
+and whatever is accepted has to survive a roundtrip. Since decoding normalizes (e.g. cdbases
+are resolved, see [`OpenMath::OMS`]), roundtrips are checked from the *decoded* object
+onwards: encoding and decoding it again has to yield the same object.
+
+Inputs are read with a [`max_depth`](crate::de::Limits::max_depth) of [`MAX_DEPTH`], since
+reading deeply nested XML exhausts the stack otherwise.
+
+[`OpenMath`] implements [`Arbitrary`] with this feature, for structured fuzzing; the
+generated objects are at most [`MAX_DEPTH`] levels deep as well.
+
+```sh
+cd openmath/fuzz
+cargo +nightly fuzz run xml corpus/xml ../tests/corpus
+cargo +nightly fuzz run json corpus/json ../tests/corpus
+cargo +nightly fuzz run roundtrip
+```
+*/
+
+use arbitrary::{Arbitrary, Unstructured};
+
+use crate::{
+    Attr, BoundVariable, Int, OMMaybeForeign, OpenMath, SymbolRef,
+    de::{Limits, OMObject, Options},
+    ser::OMSerializable,
+};
+
+/// The nesting depth beyond which inputs are rejected (or not generated)
+pub const MAX_DEPTH: usize = 32;
+
+const fn options() -> Options<'static> {
+    Options::new().with_limits(Limits::new().with_max_depth(MAX_DEPTH))
+}
+
+/// Decodes `data` as an XML document, both from a string and from a reader, and checks that
+/// the results agree and roundtrip.
+///
+/// # Panics
+/// if they do not
+pub fn xml(data: &[u8]) {
+    let from_reader = OMObject::<OpenMath<'static>>::from_openmath_xml_reader_with(data, options());
+    let Ok(s) = std::str::from_utf8(data) else {
+        return;
+    };
+    let Ok(om) = OMObject::<OpenMath<'_>>::from_openmath_xml_with(s, options()) else {
+        return;
+    };
+    if let Ok(from_reader) = from_reader {
+        assert_eq!(*om, *from_reader, "reading from a reader differs for {s:?}");
+    }
+    roundtrip_xml(&om);
+    roundtrip_json(&om);
+}
+
+/// Decodes `data` as a JSON document and checks that the result roundtrips.
+///
+/// # Panics
+/// if it does not
+pub fn json(data: &[u8]) {
+    let Ok(om) = serde_json::from_slice::<OMObject<OpenMath<'_>>>(data) else {
+        return;
+    };
+    roundtrip_json(&om);
+    roundtrip_xml(&om);
+}
+
+/// Encodes `om` in XML and JSON and checks that the decoded objects roundtrip.
+///
+/// # Panics
+/// if encoding `om` fails or yields something that can not be decoded, or if the decoded
+/// objects do not roundtrip
+pub fn roundtrip(om: &OpenMath<'_>) {
+    let xml = om.omobject().xml(false, false).to_string();
+    let decoded = OMObject::<OpenMath<'_>>::from_openmath_xml(&xml)
+        .unwrap_or_else(|e| panic!("written XML can not be read: {e}\n{xml}"));
+    roundtrip_xml(&decoded);
+
+    let json = serde_json::to_string(&om.omobject())
+        .unwrap_or_else(|e| panic!("can not be written as JSON: {e}\n{om:?}"));
+    let decoded = serde_json::from_str::<OMObject<OpenMath<'_>>>(&json)
+        .unwrap_or_else(|e| panic!("written JSON can not be read: {e}\n{json}"));
+    roundtrip_json(&decoded);
+}
+
+fn roundtrip_xml(om: &OpenMath<'_>) {
+    let xml = om.omobject().xml(false, false).to_string();
+    match OMObject::<OpenMath<'_>>::from_openmath_xml(&xml) {
+        Ok(back) => assert_eq!(*om, back, "XML does not roundtrip:\n{xml}"),
+        Err(e) => panic!("written XML can not be read: {e}\n{xml}"),
+    }
+}
+
+fn roundtrip_json(om: &OpenMath<'_>) {
+    let json = serde_json::to_string(&om.omobject())
+        .unwrap_or_else(|e| panic!("can not be written as JSON: {e}\n{om:?}"));
+    match serde_json::from_str::<OMObject<OpenMath<'_>>>(&json) {
+        Ok(back) => assert_eq!(*om, *back, "JSON does not roundtrip:\n{json}"),
+        Err(e) => panic!("written JSON can not be read: {e}\n{json}"),
+    }
+}
+
+/// Objects of at most [`MAX_DEPTH`] levels. Strings only contain characters that XML can
+/// represent, and foreign values contain no markup.
+impl<'a> Arbitrary<'a> for OpenMath<'a> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        object(u, MAX_DEPTH)
+    }
+}
+
+/// Whether XML can represent `c`; see the `Char` production of the XML specification
+const fn is_xml_char(c: char) -> bool {
+    matches!(c, '\t' | '\n' | '\r' | '\u{20}'..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}' | '\u{10000}'..)
+}
+
+fn string<'a>(u: &mut Unstructured<'a>) -> arbitrary::Result<std::borrow::Cow<'a, str>> {
+    let s = <&'a str>::arbitrary(u)?;
+    Ok(if s.chars().all(is_xml_char) {
+        s.into()
+    } else {
+        s.chars()
+            .filter(|c| is_xml_char(*c))
+            .collect::<String>()
+            .into()
+    })
+}
+
+fn symbol<'a>(u: &mut Unstructured<'a>) -> arbitrary::Result<SymbolRef<'a>> {
+    Ok(SymbolRef {
+        cdbase: if u.arbitrary()? {
+            Some(string(u)?)
+        } else {
+            None
+        },
+        cd: string(u)?,
+        name: string(u)?,
+    })
+}
+
+fn maybe_foreign<'a>(
+    u: &mut Unstructured<'a>,
+    depth: usize,
+) -> arbitrary::Result<OMMaybeForeign<'a, OpenMath<'a>>> {
+    if u.ratio(1, 4)? {
+        let encoding = if u.arbitrary()? {
+            Some(string(u)?)
+        } else {
+            None
+        };
+        let value = string(u)?.replace(['<', '>', '&'], "").into();
+        Ok(OMMaybeForeign::Foreign { encoding, value })
+    } else {
+        object(u, depth).map(OMMaybeForeign::OM)
+    }
+}
+
+fn attrs<'a>(
+    u: &mut Unstructured<'a>,
+    depth: usize,
+) -> arbitrary::Result<Vec<Attr<'a, OMMaybeForeign<'a, OpenMath<'a>>>>> {
+    if depth == 0 || u.ratio(3, 4)? {
+        return Ok(Vec::new());
+    }
+    (0..u.int_in_range(1..=3)?)
+        .map(|_| Ok(Attr::new(symbol(u)?, maybe_foreign(u, depth - 1)?)))
+        .collect()
+}
+
+fn objects<'a>(u: &mut Unstructured<'a>, depth: usize) -> arbitrary::Result<Vec<OpenMath<'a>>> {
+    (0..u.int_in_range(0..=4)?)
+        .map(|_| object(u, depth))
+        .collect()
+}
+
+fn int<'a>(u: &mut Unstructured<'a>) -> arbitrary::Result<Int<'a>> {
+    if u.arbitrary()? {
+        return Ok(Int::from(i64::arbitrary(u)?));
+    }
+    let mut digits = String::from(if u.arbitrary()? { "-" } else { "" });
+    for _ in 0..u.int_in_range(1..=60)? {
+        digits.push(char::from(b'0' + u.int_in_range(0..=9)?));
+    }
+    Int::try_from(digits).map_err(|()| arbitrary::Error::IncorrectFormat)
+}
+
+fn object<'a>(u: &mut Unstructured<'a>, depth: usize) -> arbitrary::Result<OpenMath<'a>> {
+    let attributes = attrs(u, depth)?;
+    let kind = if depth == 0 {
+        u.int_in_range(0..=5)?
+    } else {
+        u.int_in_range(0..=8)?
+    };
+    Ok(match kind {
+        0 => OpenMath::OMI {
+            int: int(u)?,
+            attributes,
+        },
+        1 => OpenMath::OMF {
+            float: f64::arbitrary(u)?.into(),
+            attributes,
+        },
+        2 => OpenMath::OMSTR {
+            string: string(u)?,
+            attributes,
+        },
+        3 => OpenMath::OMB {
+            bytes: <&'a [u8]>::arbitrary(u)?.into(),
+            attributes,
+        },
+        4 => OpenMath::OMV {
+            name: string(u)?,
+            attributes,
+        },
+        5 => {
+            let SymbolRef { cdbase, cd, name } = symbol(u)?;
+            OpenMath::OMS {
+                cd,
+                name,
+                cdbase,
+                cdbase_inherited: false,
+                attributes,
+            }
+        }
+        6 => OpenMath::OMA {
+            applicant: Box::new(object(u, depth - 1)?),
+            arguments: objects(u, depth - 1)?,
+            attributes,
+        },
+        7 => {
+            let SymbolRef { cdbase, cd, name } = symbol(u)?;
+            OpenMath::OME {
+                cd,
+                name,
+                cdbase,
+                arguments: (0..u.int_in_range(0..=3)?)
+                    .map(|_| maybe_foreign(u, depth - 1))
+                    .collect::<arbitrary::Result<_>>()?,
+                attributes,
+            }
+        }
+        _ => OpenMath::OMBIND {
+            binder: Box::new(object(u, depth - 1)?),
+            variables: (0..u.int_in_range(0..=3)?)
+                .map(|_| {
+                    Ok(BoundVariable {
+                        name: string(u)?,
+                        attributes: attrs(u, depth - 1)?,
+                    })
+                })
+                .collect::<arbitrary::Result<_>>()?,
+            object: Box::new(object(u, depth - 1)?),
+            attributes,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use arbitrary::{Arbitrary, Unstructured};
+
+    #[test]
+    fn corpus() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/corpus");
+        for entry in std::fs::read_dir(dir).expect("exists") {
+            let data = std::fs::read(entry.expect("is readable").path()).expect("is readable");
+            super::xml(&data);
+            super::json(&data);
+        }
+    }
+
+    /// Inputs found by fuzzing
+    #[test]
+    fn regressions() {
+        for s in [
+            r#"<OMOBJ cdbase="rel"><OMS cd="c" name="s"/></OMOBJ>"#,
+            r#"<OMOBJ><OMATTR><OMATP><OMS cd="c" name="&quot;s"/><OMSTR/></OMATP><OMV name="x"/></OMATTR></OMOBJ>"#,
+            r#"<OMOBJ><OMV name="x&amp;#0;"/></OMOBJ>"#,
+            "<OMOBJ><OMB></OMB></OMOBJ>",
+        ] {
+            super::xml(s.as_bytes());
+        }
+        super::xml(format!("<OMOBJ>{}</OMOBJ>", "<OMA>".repeat(100_000)).as_bytes());
+        super::json(
+            br#"{"kind":"OMOBJ","cdbase":"rel","object":{"kind":"OMS","cd":"c","name":"s"}}"#,
+        );
+    }
+
+    #[test]
+    fn arbitrary_roundtrips() {
+        let mut state = 0x9E37_79B9_7F4A_7C15_u64;
+        for _ in 0..200 {
+            let data: Vec<u8> = (0..2000)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    state.to_le_bytes()[0]
+                })
+                .collect();
+            if let Ok(om) = crate::OpenMath::arbitrary(&mut Unstructured::new(&data)) {
+                super::roundtrip(&om);
+            }
+        }
+    }
+}
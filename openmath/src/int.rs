@@ -70,7 +70,30 @@ macro_rules! into {
         )*
     }
 }
-into! {u8, i8, u16, i16, u32, i32, u64, i64, usize, isize, i128}
+into! {u8, i8, u16, i16, u32, i32, u64, i64, usize, isize}
+
+impl From<i128> for Int<'_> {
+    #[inline]
+    fn from(value: i128) -> Self {
+        Int::from_i128(value)
+    }
+}
+
+impl From<u128> for Int<'_> {
+    #[inline]
+    fn from(value: u128) -> Self {
+        Int::from_u128(value)
+    }
+}
+
+/// Builds a non-negative [`Int`] from the little-endian base-2³² digits of its value
+/// (e.g. as returned by `num_bigint::BigUint::to_u32_digits`).
+impl From<&[u32]> for Int<'_> {
+    #[inline]
+    fn from(value: &[u32]) -> Self {
+        Int::from_u32_digits(false, value)
+    }
+}
 
 macro_rules! impl_from {
     ($value:ident => $cow:expr;$dropped:expr) => {{
@@ -118,7 +141,163 @@ impl<'l> TryFrom<Cow<'l, str>> for Int<'l> {
     }
 }
 
+/// Errors when [parsing](Int::parse_bytes) an [`Int`] from bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum IntParseError {
+    #[error("no digits in integer")]
+    Empty,
+    #[error("invalid digit in integer at byte {0}")]
+    InvalidDigit(usize),
+}
+
+impl<'l> Int<'l> {
+    /** Wraps the decimal integer `digits` without validating it, e.g. for the output of a
+    bignum library whose rendering is known to be valid. Values that fit in an `i128` are
+    still stored on the stack; longer ones are kept as they are, without looking at them.
+
+    `digits` has to be an optional `-`, followed by ASCII digits without leading zeros
+    (except for `0` itself); e.g. `-1234`, but neither `+1234` nor `01234`. This is only
+    checked with debug assertions enabled. Violating it is not undefined behavior; but the
+    resulting `Int` is written as is, does not equal the valid one of the same value, and
+    its methods may return nonsense or panic.
+
+    # Panics
+    if `digits` is invalid, with debug assertions enabled
+
+    # Examples
+
+    ```rust
+    use openmath::Int;
+
+    let big = "-12345678901234567890123456789012345678901234567890";
+    assert_eq!(Int::from_digits_unchecked(big.into()), Int::new(big).expect("is valid"));
+    assert_eq!(Int::from_digits_unchecked("42".into()).is_i128(), Some(42));
+    ```
+    */
+    #[must_use]
+    pub fn from_digits_unchecked(digits: Cow<'l, str>) -> Self {
+        debug_assert!(
+            is_canonical(&digits),
+            "not a canonical decimal integer: {digits:?}"
+        );
+        let (negative, abs) = digits
+            .strip_prefix('-')
+            .map_or((false, &*digits), |abs| (true, abs));
+        // 38 digits always fit, 39 digits might
+        if abs.len() < 39 {
+            let abs = abs.bytes().fold(0i128, |acc, d| {
+                acc.wrapping_mul(10)
+                    .wrapping_add(i128::from(d.wrapping_sub(b'0')))
+            });
+            return Int(I::Stack(if negative { -abs } else { abs }));
+        }
+        if abs.len() == 39
+            && let Ok(i) = digits.parse()
+        {
+            return Int(I::Stack(i));
+        }
+        Int(I::Heap(digits))
+    }
+
+    /// Parses a decimal integer directly from bytes (e.g. the text of an XML element),
+    /// without allocating: the value is stored on the stack if it fits in `i128`, and
+    /// otherwise borrows the digits from `bytes`.
+    ///
+    /// Leading and trailing ASCII whitespace and a leading sign (`+` or `-`) are allowed.
+    ///
+    /// # Errors
+    /// if there are no digits, or any byte besides them is not a decimal digit.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use openmath::{Int, IntParseError};
+    ///
+    /// assert_eq!(Int::parse_bytes(b" +42\n"), Ok(Int::from(42)));
+    /// let big = Int::parse_bytes(b"-999999999999999999999999999999999999999999").expect("is valid");
+    /// assert_eq!(big.is_big(), Some("-999999999999999999999999999999999999999999"));
+    /// assert_eq!(Int::parse_bytes(b"-"), Err(IntParseError::Empty));
+    /// assert_eq!(Int::parse_bytes(b" 1_000"), Err(IntParseError::InvalidDigit(2)));
+    /// ```
+    pub fn parse_bytes(bytes: &'l [u8]) -> Result<Self, IntParseError> {
+        let start = bytes.len() - bytes.trim_ascii_start().len();
+        let trimmed = bytes.trim_ascii();
+        let (negative, digits) = match trimmed {
+            [b'-', rest @ ..] => (true, rest),
+            [b'+', rest @ ..] => (false, rest),
+            _ => (false, trimmed),
+        };
+        if digits.is_empty() {
+            return Err(IntParseError::Empty);
+        }
+        let sign = trimmed.len() - digits.len();
+        let mut value = Some(0i128);
+        for (i, &d) in digits.iter().enumerate() {
+            if !d.is_ascii_digit() {
+                return Err(IntParseError::InvalidDigit(start + sign + i));
+            }
+            let d = i128::from(d - b'0');
+            value = value.and_then(|v| v.checked_mul(10)).and_then(|v| {
+                if negative {
+                    v.checked_sub(d)
+                } else {
+                    v.checked_add(d)
+                }
+            });
+        }
+        if let Some(value) = value {
+            return Ok(Int(I::Stack(value)));
+        }
+        // like the string conversions, keeps a `-`, but drops a `+`
+        let digits = if negative { trimmed } else { digits };
+        // only ASCII at this point
+        Ok(Int(I::Heap(Cow::Borrowed(
+            std::str::from_utf8(digits).unwrap_or_default(),
+        ))))
+    }
+}
+
 impl Int<'_> {
+    /// Creates a new `Int` from an `i128`, which is always stored on the stack; like
+    /// [`From<i128>`](From), but usable in constants.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use openmath::Int;
+    ///
+    /// const MIN: Int<'static> = Int::from_i128(i128::MIN);
+    /// assert_eq!(MIN.is_i128(), Some(i128::MIN));
+    /// ```
+    #[inline]
+    #[must_use]
+    pub const fn from_i128(value: i128) -> Int<'static> {
+        Int(I::Stack(value))
+    }
+
+    /// Creates a new `Int` from a `u128`; only values beyond [`i128::MAX`] are written as
+    /// decimal strings.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use openmath::Int;
+    ///
+    /// assert_eq!(Int::from_u128(42).is_i128(), Some(42));
+    /// assert_eq!(
+    ///     Int::from_u128(u128::MAX).is_big(),
+    ///     Some("340282366920938463463374607431768211455")
+    /// );
+    /// ```
+    #[inline]
+    #[must_use]
+    pub fn from_u128(value: u128) -> Int<'static> {
+        i128::try_from(value).map_or_else(
+            |_| Int(I::Heap(Cow::Owned(value.to_string()))),
+            |i| Int(I::Stack(i)),
+        )
+    }
+
     /// Returns the value as an `i128` if it fits, otherwise `None`.
     ///
     /// This method allows you to check if the integer is small enough to be
@@ -274,6 +453,151 @@ impl Int<'_> {
         }
     }
 
+    /// Returns the value as a `u128` if it is non-negative and fits, otherwise `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use openmath::Int;
+    ///
+    /// assert_eq!(Int::from(42).is_u128(), Some(42));
+    /// assert_eq!(Int::from(u128::MAX).is_u128(), Some(u128::MAX));
+    /// assert_eq!(Int::from(-1).is_u128(), None);
+    /// ```
+    #[must_use]
+    pub fn is_u128(&self) -> Option<u128> {
+        match &self.0 {
+            I::Stack(v) => u128::try_from(*v).ok(),
+            I::Heap(s) => s.parse().ok(),
+        }
+    }
+
+    /// Returns the nearest `f64` to this integer; (positive or negative) infinity if it
+    /// is out of range.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use openmath::Int;
+    ///
+    /// assert_eq!(Int::from(-42).to_f64_lossy(), -42.0);
+    /// let big = Int::new("1000000000000000000000000000000000000000").expect("should be defined");
+    /// assert_eq!(big.to_f64_lossy(), 1e39);
+    /// ```
+    #[must_use]
+    pub fn to_f64_lossy(&self) -> f64 {
+        match &self.0 {
+            #[allow(clippy::cast_precision_loss)]
+            I::Stack(v) => *v as f64,
+            // decimal digit strings always parse (correctly rounded)
+            I::Heap(s) => s.parse().unwrap_or(f64::NAN),
+        }
+    }
+
+    /// Returns the number of bits required to represent the absolute value of this
+    /// integer; i.e. `0` for zero, and otherwise the position of the most significant
+    /// `1`-bit.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use openmath::Int;
+    ///
+    /// assert_eq!(Int::from(0).bit_length(), 0);
+    /// assert_eq!(Int::from(-8).bit_length(), 4);
+    /// assert_eq!(Int::from(u128::MAX).bit_length(), 128);
+    /// ```
+    #[must_use]
+    pub fn bit_length(&self) -> u32 {
+        match &self.0 {
+            I::Stack(v) => u128::BITS - v.unsigned_abs().leading_zeros(),
+            I::Heap(s) => {
+                let digits = decimal_to_u32_digits(s.trim_start_matches('-').as_bytes());
+                digits.last().map_or(0, |top| {
+                    #[allow(clippy::cast_possible_truncation)]
+                    let len = digits.len() as u32;
+                    (len - 1) * u32::BITS + (u32::BITS - top.leading_zeros())
+                })
+            }
+        }
+    }
+
+    /// Creates a new `Int` from its sign and the little-endian base-2³² digits of its
+    /// absolute value.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use openmath::Int;
+    ///
+    /// assert_eq!(Int::from_u32_digits(true, &[1, 1]), Int::from(-(1i64 << 32) - 1));
+    /// assert_eq!(
+    ///     Int::from_u32_digits(false, &[0, 0, 0, 0, 1]).to_string(),
+    ///     "340282366920938463463374607431768211456"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn from_u32_digits(negative: bool, digits: &[u32]) -> Int<'static> {
+        let len = digits.len() - digits.iter().rev().take_while(|d| **d == 0).count();
+        let digits = &digits[..len];
+        if digits.len() <= 4 {
+            let abs = digits
+                .iter()
+                .rev()
+                .fold(0u128, |acc, d| (acc << u32::BITS) | u128::from(*d));
+            if !negative {
+                return abs.into();
+            }
+            if abs <= i128::MIN.unsigned_abs() {
+                return Int(I::Stack(0i128.wrapping_sub_unsigned(abs)));
+            }
+        }
+        let mut s = u32_digits_to_decimal(digits);
+        if negative {
+            s.insert(0, '-');
+        }
+        Int(I::Heap(Cow::Owned(s)))
+    }
+
+    /// Creates a new `Int` from a hexadecimal string as used in the
+    /// <span style="font-variant:small-caps;">OpenMath</span> XML encoding, i.e. an `x`
+    /// followed by hexadecimal digits, optionally preceded by a `-`. Returns `None` if the
+    /// string is not of that form.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use openmath::Int;
+    ///
+    /// assert_eq!(Int::from_hex("x1F"), Some(Int::from(31)));
+    /// assert_eq!(Int::from_hex("-xff"), Some(Int::from(-255)));
+    /// assert_eq!(
+    ///     Int::from_hex("x100000000000000000000000000000000").map(|i| i.to_string()),
+    ///     Some("340282366920938463463374607431768211456".to_string())
+    /// );
+    /// assert!(Int::from_hex("1F").is_none());
+    /// ```
+    #[must_use]
+    pub fn from_hex(num: &str) -> Option<Int<'static>> {
+        let (negative, num) = num
+            .strip_prefix('-')
+            .map_or((false, num), |num| (true, num));
+        let num = num.strip_prefix('x')?;
+        if num.is_empty() || !num.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        let digits = num
+            .as_bytes()
+            .rchunks(8)
+            .map(|chunk| {
+                chunk.iter().fold(0u32, |acc, b| {
+                    (acc << 4) | char::from(*b).to_digit(16).unwrap_or_default()
+                })
+            })
+            .collect::<Vec<_>>();
+        Some(Int::from_u32_digits(negative, &digits))
+    }
+
     #[must_use]
     pub fn into_owned(self) -> Int<'static> {
         match self.0 {
@@ -284,6 +608,64 @@ impl Int<'_> {
     }
 }
 
+/// Whether `s` is an optional `-`, followed by decimal digits without leading zeros; see
+/// [`Int::from_digits_unchecked`]
+fn is_canonical(s: &str) -> bool {
+    let digits = s.strip_prefix('-').unwrap_or(s);
+    matches!(digits.as_bytes(), [b'0'] | [b'1'..=b'9', ..])
+        && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// little-endian base-2³² digits of a string of decimal digits
+fn decimal_to_u32_digits(decimal: &[u8]) -> Vec<u32> {
+    let mut digits = Vec::new();
+    for chunk in decimal.chunks(9) {
+        let (mut carry, factor) = chunk.iter().fold((0u64, 1u64), |(acc, f), b| {
+            (acc * 10 + u64::from(b - b'0'), f * 10)
+        });
+        for d in &mut digits {
+            let v = u64::from(*d) * factor + carry;
+            #[allow(clippy::cast_possible_truncation)]
+            {
+                *d = v as u32;
+            }
+            carry = v >> u32::BITS;
+        }
+        if carry > 0 {
+            #[allow(clippy::cast_possible_truncation)]
+            digits.push(carry as u32);
+        }
+    }
+    digits
+}
+
+/// decimal representation of little-endian base-2³² digits
+fn u32_digits_to_decimal(digits: &[u32]) -> String {
+    const BASE: u64 = 1_000_000_000;
+    let mut digits = digits.to_vec();
+    let mut chunks = Vec::new();
+    while !digits.is_empty() {
+        let mut rem = 0u64;
+        for d in digits.iter_mut().rev() {
+            let v = (rem << u32::BITS) | u64::from(*d);
+            #[allow(clippy::cast_possible_truncation)]
+            {
+                *d = (v / BASE) as u32;
+            }
+            rem = v % BASE;
+        }
+        chunks.push(rem);
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+    }
+    let mut ret = chunks.pop().unwrap_or_default().to_string();
+    for c in chunks.into_iter().rev() {
+        let _ = std::fmt::Write::write_fmt(&mut ret, format_args!("{c:09}"));
+    }
+    ret
+}
+
 #[cfg(feature = "serde")]
 impl<'de> serde::Deserialize<'de> for Int<'de> {
     #[inline]
@@ -323,7 +705,7 @@ impl<'de> serde::Deserialize<'de> for I<'de> {
             type Value = I<'de>;
 
             fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-                formatter.write_str("an integer or string")
+                formatter.write_str("an integer, or a decimal or hexadecimal string")
             }
 
             fn visit_map<V>(self, mut visitor: V) -> Result<Self::Value, V::Error>
@@ -430,7 +812,7 @@ impl<'de> serde::Deserialize<'de> for I<'de> {
             where
                 E: Error,
             {
-                Ok(I::Heap(Cow::Owned(value.to_string())))
+                Ok(Int::from(value).0)
             }
 
             fn visit_i64<E>(self, value: i64) -> Result<Self::Value, E>
@@ -493,72 +875,34 @@ impl<'de> serde::Deserialize<'de> for I<'de> {
             where
                 E: serde::de::Error,
             {
-                // Try to parse as i128 first
-                if let Ok(n) = value.parse::<i128>() {
-                    Ok(I::Stack(n))
-                } else {
-                    // Validate it's a valid integer string
-                    let mut chars = value.as_bytes();
-                    if chars.is_empty() {
-                        return Err(E::custom("empty string"));
-                    }
-                    if chars[0] == b'+' || chars[0] == b'-' {
-                        chars = &chars[1..];
-                    }
-                    if chars.iter().all(u8::is_ascii_digit) {
-                        Ok(I::Heap(std::borrow::Cow::Borrowed(value)))
-                    } else {
-                        Err(E::custom("invalid integer string"))
-                    }
-                }
+                Int::try_from(value)
+                    .ok()
+                    .or_else(|| Int::from_hex(value))
+                    .map(|i| i.0)
+                    .ok_or_else(|| E::custom("invalid integer string"))
             }
 
             fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
             where
                 E: Error,
             {
-                // Try to parse as i128 first
-                if let Ok(n) = value.parse::<i128>() {
-                    Ok(I::Stack(n))
-                } else {
-                    // Validate it's a valid integer string
-                    let mut chars = value.as_bytes();
-                    if chars.is_empty() {
-                        return Err(E::custom("empty string"));
-                    }
-                    if chars[0] == b'+' || chars[0] == b'-' {
-                        chars = &chars[1..];
-                    }
-                    if chars.iter().all(u8::is_ascii_digit) {
-                        Ok(I::Heap(std::borrow::Cow::Owned(value.to_string())))
-                    } else {
-                        Err(E::custom("invalid integer string"))
-                    }
-                }
+                Int::new(value)
+                    .map(Int::into_owned)
+                    .or_else(|| Int::from_hex(value))
+                    .map(|i| i.0)
+                    .ok_or_else(|| E::custom("invalid integer string"))
             }
 
             fn visit_string<E>(self, value: String) -> Result<Self::Value, E>
             where
                 E: Error,
             {
-                // Try to parse as i128 first
-                if let Ok(n) = value.parse::<i128>() {
-                    Ok(I::Stack(n))
-                } else {
-                    // Validate it's a valid integer string
-                    let mut chars = value.as_bytes();
-                    if chars.is_empty() {
-                        return Err(E::custom("empty string"));
-                    }
-                    if chars[0] == b'+' || chars[0] == b'-' {
-                        chars = &chars[1..];
-                    }
-                    if chars.iter().all(u8::is_ascii_digit) {
-                        Ok(I::Heap(std::borrow::Cow::Owned(value)))
-                    } else {
-                        Err(E::custom("invalid integer string"))
-                    }
+                if let Some(i) = Int::from_hex(&value) {
+                    return Ok(i.0);
                 }
+                Int::try_from(value)
+                    .map(|i| i.0)
+                    .map_err(|()| E::custom("invalid integer string"))
             }
         }
 
@@ -658,6 +1002,77 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_from_digits_unchecked() {
+        let boundary = [
+            i128::MAX.to_string(),
+            (i128::MAX as u128 + 1).to_string(),
+            i128::MIN.to_string(),
+            format!("-{}", i128::MIN.unsigned_abs() + 1),
+            "9".repeat(38),
+            "9".repeat(39),
+            format!("-{}", "9".repeat(39)),
+            format!("1{}", "0".repeat(80)),
+        ];
+        for s in ["0", "-0", "7", "-123"]
+            .into_iter()
+            .map(String::from)
+            .chain(boundary)
+        {
+            let int = Int::from_digits_unchecked(Cow::Borrowed(&s));
+            assert_eq!(int, Int::new(&s).expect("is valid"), "{s}");
+            if s != "-0" {
+                assert_eq!(int.to_string(), s);
+            }
+        }
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    fn test_from_digits_unchecked_invalid() {
+        for s in ["", "-", "+1", "01", "-01", "1_000", "--1", "12a"] {
+            let r = std::panic::catch_unwind(|| Int::from_digits_unchecked(Cow::Borrowed(s)));
+            assert!(r.is_err(), "{s:?} is accepted");
+        }
+    }
+
+    #[test]
+    fn test_parse_bytes() {
+        for s in ["0", "+0", "-0", "123", "+123", "-123", "00042"] {
+            assert_eq!(
+                Int::parse_bytes(s.as_bytes()),
+                Ok(Int::new(s).expect("is valid"))
+            );
+        }
+        for i in [i128::MAX, i128::MIN, i128::MAX - 1, i128::MIN + 1] {
+            let s = i.to_string();
+            assert_eq!(Int::parse_bytes(s.as_bytes()), Ok(Int::from(i)));
+        }
+        for s in [
+            "170141183460469231731687303715884105728",
+            "-170141183460469231731687303715884105729",
+            "+99999999999999999999999999999999999999999999",
+        ] {
+            let parsed = Int::parse_bytes(s.as_bytes()).expect("is valid");
+            assert_eq!(parsed, Int::new(s).expect("is valid"));
+            assert!(matches!(parsed.0, I::Heap(Cow::Borrowed(_))));
+        }
+        assert_eq!(Int::parse_bytes(b"\t 12 \n"), Ok(Int::from(12)));
+        assert_eq!(Int::parse_bytes(b"  "), Err(IntParseError::Empty));
+        assert_eq!(
+            Int::parse_bytes(b"+-1"),
+            Err(IntParseError::InvalidDigit(1))
+        );
+        assert_eq!(
+            Int::parse_bytes(b"1 2"),
+            Err(IntParseError::InvalidDigit(1))
+        );
+        assert_eq!(
+            Int::parse_bytes("1²".as_bytes()),
+            Err(IntParseError::InvalidDigit(1))
+        );
+    }
+
     #[test]
     fn test_valid_string_formats() {
         let valid = ["123", "+123", "-123", "0", "+0", "-0"];
@@ -736,6 +1151,114 @@ mod tests {
         assert_eq!(int.is_big().expect("should be defined"), max_plus_one);
     }
 
+    /// a deterministic spread of `i128`s across all magnitudes
+    fn samples() -> impl Iterator<Item = i128> {
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        (0..u128::BITS).flat_map(move |bits| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let v = ((u128::from(state) << 64) | u128::from(state.rotate_left(17))) >> bits;
+            #[allow(clippy::cast_possible_wrap)]
+            let v = v as i128;
+            [v, v.wrapping_neg(), v >> 1, -(v >> 1)]
+        })
+    }
+
+    #[test]
+    fn test_native_conversions() {
+        for v in samples().chain([0, 1, -1, i128::MAX, i128::MIN]) {
+            let int = Int::from(v);
+            assert_eq!(int.is_i128(), Some(v));
+            assert_eq!(int.is_u128(), u128::try_from(v).ok());
+            #[allow(clippy::cast_precision_loss)]
+            let f = v as f64;
+            assert!(int.to_f64_lossy().total_cmp(&f).is_eq(), "{v}");
+            assert_eq!(
+                int.bit_length(),
+                u128::BITS - v.unsigned_abs().leading_zeros()
+            );
+            assert_eq!(Int::new(&v.to_string()), Some(int.clone()));
+
+            let abs = v.unsigned_abs();
+            #[allow(clippy::cast_possible_truncation)]
+            let digits = [
+                abs as u32,
+                (abs >> 32) as u32,
+                (abs >> 64) as u32,
+                (abs >> 96) as u32,
+                0,
+            ];
+            assert_eq!(Int::from_u32_digits(v < 0, &digits), int);
+            let hex = if v < 0 {
+                format!("-x{abs:X}")
+            } else {
+                format!("x{abs:x}")
+            };
+            assert_eq!(Int::from_hex(&hex), Some(int));
+        }
+        for v in samples() {
+            let u = v.unsigned_abs() << 1 | 1;
+            let int = Int::from(u);
+            assert_eq!(int.is_u128(), Some(u));
+            assert_eq!(int.is_i128(), i128::try_from(u).ok());
+            #[allow(clippy::cast_precision_loss)]
+            let f = u as f64;
+            assert!(int.to_f64_lossy().total_cmp(&f).is_eq(), "{u}");
+            assert_eq!(int.bit_length(), u128::BITS - u.leading_zeros());
+        }
+    }
+
+    #[test]
+    fn test_big_conversions() {
+        let big = "-340282366920938463463374607431768211457"; // -(u128::MAX + 2)
+        let int = Int::new(big).expect("should be defined");
+        assert_eq!(int.is_u128(), None);
+        assert_eq!(int.bit_length(), 129);
+        assert!((int.to_f64_lossy() + 2f64.powi(128)).abs() < 1.0);
+        assert_eq!(Int::from_u32_digits(true, &[1, 0, 0, 0, 1]), int);
+        assert_eq!(
+            Int::from_hex("-x100000000000000000000000000000001"),
+            Some(int)
+        );
+        assert_eq!(Int::from(&[u32::MAX; 8][..]).bit_length(), 8 * u32::BITS);
+        assert_eq!(Int::from(&[][..]), Int::from(0));
+        for invalid in ["", "x", "-x", "xg", "+x1", "x-1", "0x1"] {
+            assert!(
+                Int::from_hex(invalid).is_none(),
+                "Should reject '{invalid}'"
+            );
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_strings() {
+        for (json, expected) in [
+            ("42", Int::from(42)),
+            (r#""42""#, Int::from(42)),
+            (r#""+42""#, Int::from(42)),
+            (r#""-x2A""#, Int::from(-42)),
+            (
+                r#""340282366920938463463374607431768211456""#,
+                Int::from_u32_digits(false, &[0, 0, 0, 0, 1]),
+            ),
+            (
+                r#""x100000000000000000000000000000000""#,
+                Int::from_u32_digits(false, &[0, 0, 0, 0, 1]),
+            ),
+        ] {
+            let int: Int = serde_json::from_str(json).expect("should be defined");
+            assert_eq!(int, expected, "{json}");
+        }
+        for invalid in [r#""""#, r#""4.2""#, r#""0x2A""#, "4.2"] {
+            assert!(
+                serde_json::from_str::<Int>(invalid).is_err(),
+                "Should reject {invalid}"
+            );
+        }
+    }
+
     #[cfg(feature = "serde")]
     #[test]
     fn test_serde_serialization() {
@@ -0,0 +1,515 @@
+//! Checked construction and validation of [`OpenMath`] objects; see [`InvariantError`]
+
+use crate::{
+    Attr, BoundVariable, ErrorArg, OMKind, OMMaybeForeign, OpenMath, Step, visit::Visitor,
+};
+
+/** Violations of the invariants the <span style="font-variant:small-caps;">OpenMath</span>
+standard imposes on objects, which the data model of [`OpenMath`] does not enforce by itself;
+see [`OpenMath::check_invariants`] and the checked constructors [`OpenMath::try_oma`],
+[`OpenMath::try_ome`] and [`OpenMath::try_ombind`].
+
+Symbols (i.e. [OMS](OMKind::OMS)s, the heads of [OME](OMKind::OME)s and attribute keys) need
+cd names and names matching the syntax of
+[Section 2.3](https://openmath.org/standard/om20-2019-07-01/omstd20.html#sec_names) of the
+standard; variable names need only be non-empty.
+
+[OMFOREIGN](OMKind::OMFOREIGN)s can only occur as arguments of [OME](OMKind::OME)s and as
+attribute values, which the data model does enforce (see [`OMMaybeForeign`]); when reading,
+an `OMFOREIGN` anywhere else is rejected (e.g. with
+[`XmlReadError::MisplacedForeign`](crate::de::XmlReadError::MisplacedForeign)).
+*/
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum InvariantError {
+    #[error("empty name in {0}")]
+    EmptyName(OMKind),
+    #[error("invalid name {name:?} in {kind}")]
+    InvalidName { kind: OMKind, name: String },
+    #[error("the head of an OME must be an OMS without attributes, but is an {0}")]
+    NotASymbol(OMKind),
+    #[error("an error (OME) can not occur as {0}")]
+    ErrorAsObject(&'static str),
+}
+
+/// Things that are allowed by the <span style="font-variant:small-caps;">OpenMath</span>
+/// standard, but usually a mistake; see [`OpenMath::lints`]
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Lint {
+    /// The standard does not require the variables of a binding to be distinct; later ones
+    /// shadow earlier ones (see [`OMBIND`](OpenMath::OMBIND))
+    #[error("variable {0:?} is bound more than once in the same OMBIND")]
+    DuplicateBoundVariable(String),
+}
+
+impl InvariantError {
+    /// A short, stable identifier of the kind of violation, e.g. for aggregating reports
+    #[must_use]
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::EmptyName(_) => "empty-name",
+            Self::InvalidName { .. } => "invalid-name",
+            Self::NotASymbol(_) => "not-a-symbol",
+            Self::ErrorAsObject(_) => "error-as-object",
+        }
+    }
+}
+
+impl Lint {
+    /// A short, stable identifier of the kind of lint, e.g. for aggregating reports
+    #[must_use]
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Self::DuplicateBoundVariable(_) => "duplicate-bound-variable",
+        }
+    }
+}
+
+impl<'om> OpenMath<'om> {
+    /// Creates an [OMA](OMKind::OMA), checking that neither `head` nor any of `args` is an
+    /// error ([OME](OMKind::OME)). The arguments may be empty.
+    ///
+    /// Only the direct children are checked; use [`check_invariants`](Self::check_invariants)
+    /// for whole trees.
+    ///
+    /// # Errors
+    /// if `head` or any argument is an [OME](OMKind::OME).
+    pub fn try_oma(
+        head: Self,
+        args: impl IntoIterator<Item = Self>,
+    ) -> Result<Self, InvariantError> {
+        no_error(&head, "the head of an OMA")?;
+        let arguments = args
+            .into_iter()
+            .map(|a| no_error(&a, "an argument of an OMA").map(|()| a))
+            .collect::<Result<_, _>>()?;
+        Ok(Self::OMA {
+            applicant: Box::new(head),
+            arguments,
+            attributes: Vec::new(),
+        })
+    }
+
+    /** Creates an [OME](OMKind::OME) with the given error `symbol`, which needs to be an
+    [OMS](OMKind::OMS) with a valid cd and name, and without attributes. The arguments may
+    be objects or [OMFOREIGN](OMKind::OMFOREIGN)s; see [`ErrorArg`].
+
+    # Errors
+    if `symbol` is not an [OMS](OMKind::OMS) without attributes, or its cd or name are
+    invalid.
+
+    # Examples
+    ```rust
+    use openmath::{ErrorArg, OpenMath};
+
+    let symbol = OpenMath::from_sexpr("(oms moreerrors unexpected)").expect("is valid");
+    let om = OpenMath::try_ome(
+        symbol,
+        [
+            ErrorArg::OM(OpenMath::from_sexpr("(omi 42)").expect("is valid")),
+            ErrorArg::foreign("application/json", r#"{"line":3}"#),
+        ],
+    )
+    .expect("is a valid error");
+    assert_eq!(om.children().count(), 1);
+    ```
+    */
+    pub fn try_ome(
+        symbol: Self,
+        args: impl IntoIterator<Item = ErrorArg<'om>>,
+    ) -> Result<Self, InvariantError> {
+        let Self::OMS {
+            cd,
+            name,
+            cdbase,
+            attributes,
+            ..
+        } = symbol
+        else {
+            return Err(InvariantError::NotASymbol(symbol.kind()));
+        };
+        if !attributes.is_empty() {
+            return Err(InvariantError::NotASymbol(OMKind::OMATTR));
+        }
+        symbol_name(OMKind::OME, &cd)?;
+        symbol_name(OMKind::OME, &name)?;
+        Ok(Self::OME {
+            cd,
+            name,
+            cdbase,
+            arguments: args.into_iter().collect(),
+            attributes: Vec::new(),
+        })
+    }
+
+    /// Creates an [OMBIND](OMKind::OMBIND), checking that the variable names are non-empty
+    /// and neither `binder` nor `body` is an error ([OME](OMKind::OME)).
+    ///
+    /// Variables bound more than once are allowed by the standard and hence not an error;
+    /// see [`lints`](Self::lints).
+    ///
+    /// # Errors
+    /// if a variable name is empty, or `binder` or `body` is an [OME](OMKind::OME).
+    pub fn try_ombind(
+        binder: Self,
+        vars: impl IntoIterator<Item = BoundVariable<'om>>,
+        body: Self,
+    ) -> Result<Self, InvariantError> {
+        no_error(&binder, "the binder of an OMBIND")?;
+        no_error(&body, "the body of an OMBIND")?;
+        let variables = vars
+            .into_iter()
+            .map(|v| variable_name(&v.name).map(|()| v))
+            .collect::<Result<_, _>>()?;
+        Ok(Self::OMBIND {
+            binder: Box::new(binder),
+            variables,
+            object: Box::new(body),
+            attributes: Vec::new(),
+        })
+    }
+
+    /// Checks the invariants of [`InvariantError`] for this object and everything in it,
+    /// e.g. for objects built directly rather than with the checked constructors.
+    ///
+    /// # Errors
+    /// all violations, in document order.
+    pub fn check_invariants(&self) -> Result<(), Vec<InvariantError>> {
+        let mut check = Check(Vec::new());
+        self.walk(&mut check);
+        if check.0.is_empty() {
+            Ok(())
+        } else {
+            Err(check.0)
+        }
+    }
+
+    /// Things in this object that are allowed by the standard, but usually a mistake.
+    #[must_use]
+    pub fn lints(&self) -> Vec<Lint> {
+        let mut lints = Lints::default();
+        self.walk(&mut lints);
+        lints.lints
+    }
+}
+
+/// Collects the violations of [`InvariantError`], in document order
+struct Check(Vec<InvariantError>);
+
+impl Check {
+    fn push(&mut self, r: Result<(), InvariantError>) {
+        if let Err(e) = r {
+            self.0.push(e);
+        }
+    }
+}
+
+impl<'a, 'om: 'a> Visitor<'a, 'om> for Check {
+    fn enter(&mut self, om: &'a OpenMath<'om>, _: Option<Step>) {
+        match om {
+            OpenMath::OMI { .. }
+            | OpenMath::OMF { .. }
+            | OpenMath::OMSTR { .. }
+            | OpenMath::OMB { .. } => (),
+            OpenMath::OMV { name, .. } => self.push(variable_name(name)),
+            OpenMath::OMS { cd, name, .. } => {
+                self.push(symbol_name(OMKind::OMS, cd));
+                self.push(symbol_name(OMKind::OMS, name));
+            }
+            OpenMath::OMA {
+                applicant,
+                arguments,
+                ..
+            } => {
+                self.push(no_error(applicant, "the head of an OMA"));
+                for a in arguments {
+                    self.push(no_error(a, "an argument of an OMA"));
+                }
+            }
+            OpenMath::OME { cd, name, .. } => {
+                self.push(symbol_name(OMKind::OME, cd));
+                self.push(symbol_name(OMKind::OME, name));
+            }
+            OpenMath::OMBIND {
+                binder,
+                variables,
+                object,
+                ..
+            } => {
+                self.push(no_error(binder, "the binder of an OMBIND"));
+                for v in variables {
+                    self.push(variable_name(&v.name));
+                }
+                self.push(no_error(object, "the body of an OMBIND"));
+            }
+        }
+    }
+
+    fn attribute(&mut self, attr: &'a Attr<'om, OMMaybeForeign<'om, OpenMath<'om>>>) {
+        self.push(symbol_name(OMKind::OMATTR, attr.cd()));
+        self.push(symbol_name(OMKind::OMATTR, attr.name()));
+    }
+}
+
+/// Collects the [`Lint`]s, in document order
+#[derive(Default)]
+struct Lints<'a> {
+    lints: Vec<Lint>,
+    /// The names of the variables seen so far of every enclosing [OMBIND](OMKind::OMBIND)
+    bound: Vec<Vec<&'a str>>,
+}
+
+impl<'a, 'om: 'a> Visitor<'a, 'om> for Lints<'a> {
+    fn enter(&mut self, om: &'a OpenMath<'om>, _: Option<Step>) {
+        if om.kind() == OMKind::OMBIND {
+            self.bound.push(Vec::new());
+        }
+    }
+
+    fn leave(&mut self, om: &'a OpenMath<'om>) {
+        if om.kind() == OMKind::OMBIND {
+            self.bound.pop();
+        }
+    }
+
+    fn variable(&mut self, var: &'a BoundVariable<'om>) {
+        let Some(bound) = self.bound.last_mut() else {
+            return;
+        };
+        // report every name once, at its second occurrence
+        if bound.iter().filter(|n| **n == var.name).count() == 1 {
+            self.lints
+                .push(Lint::DuplicateBoundVariable(var.name.to_string()));
+        }
+        bound.push(&var.name);
+    }
+}
+
+const fn no_error(om: &OpenMath<'_>, position: &'static str) -> Result<(), InvariantError> {
+    if matches!(om, OpenMath::OME { .. }) {
+        Err(InvariantError::ErrorAsObject(position))
+    } else {
+        Ok(())
+    }
+}
+
+const fn variable_name(name: &str) -> Result<(), InvariantError> {
+    if name.is_empty() {
+        Err(InvariantError::EmptyName(OMKind::OMV))
+    } else {
+        Ok(())
+    }
+}
+
+/// `(Letter | '_') (Letter | Digit | '.' | '-' | '_')*`, where letters and digits may be
+/// non-ASCII; see Section 2.3 of the standard
+fn symbol_name(kind: OMKind, name: &str) -> Result<(), InvariantError> {
+    let mut chars = name.chars();
+    match chars.next() {
+        None => Err(InvariantError::EmptyName(kind)),
+        Some(c)
+            if (c.is_alphabetic() || c == '_')
+                && chars.all(|c| c.is_alphanumeric() || matches!(c, '.' | '-' | '_')) =>
+        {
+            Ok(())
+        }
+        Some(_) => Err(InvariantError::InvalidName {
+            kind,
+            name: name.to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InvariantError, Lint};
+    use crate::{BoundVariable, ErrorArg, OMKind, OMMaybeForeign, OpenMath};
+
+    fn oms(cd: &'static str, name: &'static str) -> OpenMath<'static> {
+        OpenMath::OMS {
+            cd: cd.into(),
+            name: name.into(),
+            cdbase: None,
+            cdbase_inherited: false,
+            attributes: Vec::new(),
+        }
+    }
+    fn var(name: &'static str) -> BoundVariable<'static> {
+        BoundVariable {
+            name: name.into(),
+            attributes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn checked_constructors() {
+        let error =
+            OpenMath::try_ome(oms("moreerrors", "unexpected"), []).expect("is a valid error");
+        assert!(OpenMath::try_oma(oms("arith1", "plus"), []).is_ok());
+        assert_eq!(
+            OpenMath::try_oma(oms("arith1", "plus"), [error.clone()]),
+            Err(InvariantError::ErrorAsObject("an argument of an OMA"))
+        );
+        assert_eq!(
+            OpenMath::try_ome(oms("more errors", "unexpected"), []),
+            Err(InvariantError::InvalidName {
+                kind: OMKind::OME,
+                name: "more errors".to_string()
+            })
+        );
+        assert_eq!(
+            OpenMath::try_ome(oms("moreerrors", ""), []),
+            Err(InvariantError::EmptyName(OMKind::OME))
+        );
+        assert_eq!(
+            OpenMath::try_ome(OpenMath::from_sexpr("(omv x)").expect("is valid"), []),
+            Err(InvariantError::NotASymbol(OMKind::OMV))
+        );
+        assert!(
+            OpenMath::try_ome(
+                oms("moreerrors", "unexpected"),
+                [OMMaybeForeign::OM(error.clone())]
+            )
+            .is_ok()
+        );
+        assert_eq!(
+            OpenMath::try_ombind(
+                oms("fns1", "lambda"),
+                [var("x"), var("")],
+                oms("logic1", "true")
+            ),
+            Err(InvariantError::EmptyName(OMKind::OMV))
+        );
+        let bind = OpenMath::try_ombind(
+            oms("fns1", "lambda"),
+            [var("x"), var("y"), var("x"), var("x")],
+            oms("logic1", "true"),
+        )
+        .expect("duplicates are allowed");
+        assert_eq!(
+            bind.lints(),
+            [Lint::DuplicateBoundVariable("x".to_string())]
+        );
+    }
+
+    /// Foreign error arguments built with [`ErrorArg::foreign`] keep their encoding when
+    /// written and read back
+    #[test]
+    fn foreign_error_arguments() {
+        let om = OpenMath::try_ome(
+            OpenMath::from_sexpr("(oms moreerrors unexpected)").expect("is valid"),
+            [
+                ErrorArg::foreign("application/json", r#"{"line":3,"note":"a \"b\""}"#),
+                ErrorArg::OM(OpenMath::from_sexpr("(omi 1)").expect("is valid")),
+                OMMaybeForeign::Foreign {
+                    encoding: None,
+                    value: "<x/>".into(),
+                },
+            ],
+        )
+        .expect("is a valid error");
+        let check = |read: &OpenMath<'_>| {
+            assert_eq!(read, &om);
+            let OpenMath::OME { arguments, .. } = read else {
+                panic!("is an OME")
+            };
+            assert!(matches!(
+                &arguments[0],
+                OMMaybeForeign::Foreign { encoding: Some(e), .. } if e == "application/json"
+            ));
+        };
+
+        #[cfg(feature = "xml")]
+        {
+            use crate::{OMDeserializable, OMSerializable};
+            let xml = om.xml(false).to_string();
+            assert!(xml.contains(r#"encoding="application/json""#), "{xml}");
+            check(&OpenMath::from_openmath_xml(&xml).expect("is valid"));
+        }
+        #[cfg(feature = "serde")]
+        {
+            use crate::{OMSerializable, de::OMFromSerde};
+            let json = serde_json::to_string(&om.openmath_serde()).expect("serializes");
+            assert!(json.contains(r#""encoding":"application/json""#), "{json}");
+            check(
+                &serde_json::from_str::<OMFromSerde<OpenMath>>(&json)
+                    .expect("is valid")
+                    .into_inner(),
+            );
+        }
+        check(&om);
+    }
+
+    /// Variables with the same name in one binder are kept in order by every encoding, and
+    /// the last one is the one in scope
+    #[test]
+    fn duplicate_bound_variables() {
+        let om = OpenMath::from_sexpr(
+            "(ombind (oms fns1 lambda)
+                 (bvar x (omattr (((oms sts type) (oms setname1 N))) x) y x)
+                 (oma (oms arith1 plus) (omv x) (omv y)))",
+        )
+        .expect("is valid");
+        let check = |read: &OpenMath<'_>| {
+            // readers differ in whether attribute keys of variables get the default cdbase
+            assert_eq!(read.to_sexpr(), om.to_sexpr());
+            let OpenMath::OMBIND { variables, .. } = read else {
+                panic!("is an OMBIND")
+            };
+            let names = variables.iter().map(|v| &*v.name).collect::<Vec<_>>();
+            assert_eq!(names, ["x", "x", "y", "x"]);
+            assert_eq!(variables[1].attributes.len(), 1);
+            assert_eq!(read.bound_variable("x").map(|(i, _)| i), Some(3));
+            assert_eq!(
+                read.lints(),
+                [Lint::DuplicateBoundVariable("x".to_string())]
+            );
+        };
+
+        #[cfg(feature = "xml")]
+        {
+            use crate::{OMDeserializable, OMSerializable};
+            let xml = om.xml(false).to_string();
+            check(&OpenMath::from_openmath_xml(&xml).expect("is valid"));
+        }
+        #[cfg(feature = "serde")]
+        {
+            use crate::{OMSerializable, de::OMFromSerde};
+            let json = serde_json::to_string(&om.openmath_serde()).expect("serializes");
+            check(
+                &serde_json::from_str::<OMFromSerde<OpenMath>>(&json)
+                    .expect("is valid")
+                    .into_inner(),
+            );
+        }
+        check(&OpenMath::from_sexpr(&om.to_sexpr()).expect("is valid"));
+        check(&om);
+    }
+
+    #[test]
+    fn check_invariants() {
+        let om = OpenMath::from_sexpr(
+            r#"(oma (oms arith1 plus)
+                 (omattr (((oms "" type) (ome (oms moreerrors unexpected)))) (omv ""))
+                 (ome (oms moreerrors "1nvalid")))"#,
+        )
+        .expect("is valid");
+        assert_eq!(
+            om.check_invariants(),
+            Err(vec![
+                InvariantError::ErrorAsObject("an argument of an OMA"),
+                InvariantError::EmptyName(OMKind::OMV),
+                InvariantError::EmptyName(OMKind::OMATTR),
+                InvariantError::InvalidName {
+                    kind: OMKind::OME,
+                    name: "1nvalid".to_string()
+                },
+            ])
+        );
+        assert!(
+            OpenMath::from_sexpr("(ombind (oms fns1 lambda) (bvar x y) (oma (omv x) (omv y)))")
+                .expect("is valid")
+                .check_invariants()
+                .is_ok()
+        );
+    }
+}
@@ -0,0 +1,445 @@
+/*! A mapping between [`serde_json::Value`]s and
+<span style="font-variant:small-caps;">OpenMath</span> objects, for wrapping existing
+(e.g. configuration-style) JSON data as <span style="font-variant:small-caps;">OpenMath</span>
+and for quick-and-dirty consumption of unknown <span style="font-variant:small-caps;">OpenMath</span>.
+
+This is *not* the official <span style="font-variant:small-caps;">OpenMath</span> JSON encoding
+(see [`openmath_serde`](crate::OMSerializable::openmath_serde) for that); instead, JSON values
+map to <span style="font-variant:small-caps;">OpenMath</span> objects as follows:
+
+| JSON | <span style="font-variant:small-caps;">OpenMath</span> |
+|------|---------|
+| `null` | [`JsonMapping::null`] (default: `json.null`) |
+| `true`, `false` | `logic1.true`, `logic1.false` |
+| integers | [`OMI`](crate::OMKind::OMI) |
+| other numbers | [`OMF`](crate::OMKind::OMF) |
+| strings | [`OMSTR`](crate::OMKind::OMSTR) |
+| arrays | [`JsonMapping::array`] applied to the elements (default: `list1.list`) |
+| objects | [`JsonMapping::object`] applied to alternating keys (as [`OMSTR`](crate::OMKind::OMSTR)s) and values (default: `json.object`) |
+
+The `json` content dictionary is not an official one; use [`JsonValue::with_mapping`] to
+choose different symbols.
+
+In the other direction, [`serde_json::Value`] implements [`OMDeserializable`](crate::OMDeserializable)
+using the default mapping. Objects that do not fit the above are represented in the
+official JSON encoding (e.g. `{"kind":"OMV","name":"x"}`), with their children converted
+recursively; attributions are dropped.
+
+# Examples
+```
+use openmath::{OMDeserializable, OMSerializable, ser::from_serde};
+
+let value = serde_json::json!({ "name": "x", "tags": ["a", "b"], "weight": 1.5 });
+# #[cfg(all(feature = "xml-read", feature = "xml-write"))]
+# {
+let xml = from_serde(&value).xml(false).to_string();
+assert!(xml.starts_with(r#"<OMA><OMS cd="json" name="object"/><OMSTR>name</OMSTR>"#));
+assert_eq!(serde_json::Value::from_openmath_xml(&xml).expect("valid"), value);
+# }
+```
+*/
+
+use std::convert::Infallible;
+
+use serde_json::{Map, Number, Value};
+
+use crate::{
+    Int, OM, OMDeserializable, OMMaybeForeign, OMSerializable,
+    ser::{AsOMS, OMSerializer, Uri},
+};
+
+const TRUE: Uri<'static> = Uri {
+    cdbase: Some(crate::CdBase::OPENMATH),
+    cd: "logic1",
+    name: "true",
+};
+const FALSE: Uri<'static> = Uri {
+    cdbase: Some(crate::CdBase::OPENMATH),
+    cd: "logic1",
+    name: "false",
+};
+
+/// Which symbols JSON arrays, objects and `null` map to; see the
+/// [module documentation](crate::json).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct JsonMapping<'m> {
+    /// applied to the elements of an array
+    pub array: Uri<'m>,
+    /// applied to the alternating keys and values of an object
+    pub object: Uri<'m>,
+    /// the symbol for `null`
+    pub null: Uri<'m>,
+}
+impl JsonMapping<'static> {
+    /// The default mapping: `list1.list`, `json.object` and `json.null`
+    pub const DEFAULT: Self = Self {
+        array: Uri {
+            cdbase: Some(crate::CdBase::OPENMATH),
+            cd: "list1",
+            name: "list",
+        },
+        object: Uri {
+            cdbase: None,
+            cd: "json",
+            name: "object",
+        },
+        null: Uri {
+            cdbase: None,
+            cd: "json",
+            name: "null",
+        },
+    };
+}
+impl Default for JsonMapping<'static> {
+    #[inline]
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Wraps a [`serde_json::Value`] as an [`OMSerializable`]; see the
+/// [module documentation](crate::json).
+#[derive(Debug, Clone, Copy)]
+pub struct JsonValue<'v> {
+    value: &'v Value,
+    mapping: &'v JsonMapping<'v>,
+}
+
+static DEFAULT_MAPPING: JsonMapping<'static> = JsonMapping::DEFAULT;
+
+/// Wraps a [`serde_json::Value`] as an [`OMSerializable`] using the
+/// [default mapping](JsonMapping::DEFAULT); see the [module documentation](crate::json).
+#[inline]
+#[must_use]
+pub const fn from_serde(value: &Value) -> JsonValue<'_> {
+    JsonValue {
+        value,
+        mapping: &DEFAULT_MAPPING,
+    }
+}
+
+impl<'v> JsonValue<'v> {
+    /// Uses the given mapping for arrays, objects and `null` instead of the default.
+    #[inline]
+    #[must_use]
+    pub const fn with_mapping(self, mapping: &'v JsonMapping<'v>) -> Self {
+        Self {
+            value: self.value,
+            mapping,
+        }
+    }
+
+    #[inline]
+    const fn child(&self, value: &'v Value) -> Self {
+        Self {
+            value,
+            mapping: self.mapping,
+        }
+    }
+}
+
+impl OMSerializable for JsonValue<'_> {
+    fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        match self.value {
+            Value::Null => self.mapping.null.as_oms().as_openmath(serializer),
+            Value::Bool(true) => TRUE.as_oms().as_openmath(serializer),
+            Value::Bool(false) => FALSE.as_oms().as_openmath(serializer),
+            Value::Number(n) => {
+                if let Some(i) = n.as_i64() {
+                    serializer.omi(&Int::from(i))
+                } else if let Some(u) = n.as_u64() {
+                    serializer.omi(&Int::from(u))
+                } else {
+                    // big integers with serde_json's `arbitrary_precision`
+                    let s = n.to_string();
+                    if let Some(i) = Int::new(&s) {
+                        serializer.omi(&i)
+                    } else {
+                        serializer.omf(n.as_f64().unwrap_or(f64::NAN))
+                    }
+                }
+            }
+            Value::String(s) => serializer.omstr(s),
+            Value::Array(a) => {
+                serializer.oma(self.mapping.array.as_oms(), a.iter().map(|v| self.child(v)))
+            }
+            Value::Object(o) => {
+                // `oma` needs an `ExactSizeIterator`
+                #[allow(clippy::needless_collect)]
+                let args = o
+                    .iter()
+                    .flat_map(|(k, v)| {
+                        [
+                            either::Either::Left(k.as_str()),
+                            either::Either::Right(self.child(v)),
+                        ]
+                    })
+                    .collect::<Vec<_>>();
+                serializer.oma(self.mapping.object.as_oms(), args.into_iter())
+            }
+        }
+    }
+}
+
+/// whether `value` is the (generically represented) `uri`
+fn is_symbol(value: &Value, uri: &Uri<'_>) -> bool {
+    let Value::Object(o) = value else {
+        return false;
+    };
+    o.get("kind").and_then(Value::as_str) == Some("OMS")
+        && o.get("cd").and_then(Value::as_str) == Some(uri.cd)
+        && o.get("name").and_then(Value::as_str) == Some(uri.name)
+        && uri.cdbase.as_ref().is_none_or(|b| {
+            o.get("cdbase")
+                .and_then(Value::as_str)
+                .is_none_or(|c| crate::cdbase::eq_normalized(b, c))
+        })
+}
+
+fn kind(kind: &str) -> Map<String, Value> {
+    let mut map = Map::new();
+    map.insert("kind".to_string(), Value::String(kind.to_string()));
+    map
+}
+
+fn foreign(o: OMMaybeForeign<'_, Value>) -> Value {
+    match o {
+        OMMaybeForeign::OM(v) => v,
+        OMMaybeForeign::Foreign { encoding, value } => {
+            let mut map = kind("OMFOREIGN");
+            if let Some(encoding) = encoding {
+                map.insert("encoding".to_string(), Value::String(encoding.into_owned()));
+            }
+            map.insert("foreign".to_string(), Value::String(value.into_owned()));
+            Value::Object(map)
+        }
+    }
+}
+
+fn symbol(cdbase: Option<&str>, cd: String, name: String) -> Value {
+    let mut map = kind("OMS");
+    if let Some(cdbase) = cdbase.filter(|b| !crate::cdbase::eq_normalized(b, crate::CD_BASE)) {
+        map.insert("cdbase".to_string(), Value::String(cdbase.to_string()));
+    }
+    map.insert("cd".to_string(), Value::String(cd));
+    map.insert("name".to_string(), Value::String(name));
+    Value::Object(map)
+}
+
+impl<'de> OMDeserializable<'de> for Value {
+    type Ret = Self;
+    type Err = Infallible;
+    #[allow(clippy::too_many_lines)]
+    fn from_openmath(om: OM<'de, Self>, cdbase: &str) -> Result<Self, Self::Err>
+    where
+        Self: Sized,
+    {
+        let mapping = JsonMapping::DEFAULT;
+        Ok(match om {
+            OM::OMI { int, .. } => int
+                .to_string()
+                .parse::<Number>()
+                .map_or_else(|_| Self::String(int.to_string()), Self::Number),
+            OM::OMF { float, .. } => Number::from_f64(float).map_or_else(
+                || {
+                    let mut map = kind("OMF");
+                    map.insert("decimal".to_string(), Self::String(float.to_string()));
+                    Self::Object(map)
+                },
+                Self::Number,
+            ),
+            OM::OMSTR { string, .. } => Self::String(string.into_owned()),
+            OM::OMB { bytes, .. } => {
+                use crate::base64::Base64Encodable;
+                let mut map = kind("OMB");
+                map.insert(
+                    "base64".to_string(),
+                    Self::String(bytes.iter().copied().base64().into_string()),
+                );
+                Self::Object(map)
+            }
+            OM::OMV { name, .. } => {
+                let mut map = kind("OMV");
+                map.insert("name".to_string(), Self::String(name.into_owned()));
+                Self::Object(map)
+            }
+            OM::OMR { href, .. } => {
+                let mut map = kind("OMR");
+                map.insert("href".to_string(), Self::String(href.into_owned()));
+                Self::Object(map)
+            }
+            OM::OMS { cd, name, .. } => {
+                let s = symbol(Some(cdbase), cd.into_owned(), name.into_owned());
+                if is_symbol(&s, &TRUE) {
+                    Self::Bool(true)
+                } else if is_symbol(&s, &FALSE) {
+                    Self::Bool(false)
+                } else if is_symbol(&s, &mapping.null) {
+                    Self::Null
+                } else {
+                    s
+                }
+            }
+            OM::OMA {
+                applicant,
+                arguments,
+                ..
+            } if is_symbol(&applicant, &mapping.array) => {
+                Self::Array(arguments.into_iter().collect())
+            }
+            OM::OMA {
+                applicant,
+                arguments,
+                ..
+            } if is_symbol(&applicant, &mapping.object)
+                && arguments.len() % 2 == 0
+                && arguments.iter().step_by(2).all(Self::is_string) =>
+            {
+                let mut map = Map::new();
+                let mut arguments = arguments.into_iter();
+                while let (Some(Self::String(k)), Some(v)) = (arguments.next(), arguments.next()) {
+                    map.insert(k, v);
+                }
+                Self::Object(map)
+            }
+            OM::OMA {
+                applicant,
+                arguments,
+                ..
+            } => {
+                let mut map = kind("OMA");
+                map.insert("applicant".to_string(), applicant);
+                map.insert(
+                    "arguments".to_string(),
+                    Self::Array(arguments.into_iter().collect()),
+                );
+                Self::Object(map)
+            }
+            OM::OMBIND {
+                binder,
+                variables,
+                object,
+                ..
+            } => {
+                let mut map = kind("OMBIND");
+                map.insert("binder".to_string(), binder);
+                map.insert(
+                    "variables".to_string(),
+                    Self::Array(
+                        variables
+                            .into_iter()
+                            .map(|(name, _)| {
+                                let mut map = kind("OMV");
+                                map.insert("name".to_string(), Self::String(name.into_owned()));
+                                Self::Object(map)
+                            })
+                            .collect(),
+                    ),
+                );
+                map.insert("object".to_string(), object);
+                Self::Object(map)
+            }
+            OM::OME {
+                cdbase: ecdbase,
+                cd,
+                name,
+                arguments,
+                ..
+            } => {
+                let mut map = kind("OME");
+                map.insert(
+                    "error".to_string(),
+                    symbol(
+                        Some(ecdbase.as_deref().unwrap_or(cdbase)),
+                        cd.into_owned(),
+                        name.into_owned(),
+                    ),
+                );
+                map.insert(
+                    "arguments".to_string(),
+                    Self::Array(arguments.into_iter().map(foreign).collect()),
+                );
+                Self::Object(map)
+            }
+        })
+    }
+}
+
+#[cfg(all(test, any(feature = "xml-read", feature = "xml-write")))]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[cfg(all(feature = "xml-read", feature = "xml-write"))]
+    #[test]
+    fn roundtrip() {
+        let value = json!({
+            "name": "x",
+            "count": -3,
+            "max": 18_446_744_073_709_551_615_u64,
+            "weight": 1.5,
+            "flags": [true, false, null],
+            "nested": { "empty": [], "none": {} }
+        });
+        let xml = from_serde(&value).xml(true).to_string();
+        assert!(xml.contains(r#"<OMS cd="logic1" name="true"/>"#));
+        assert!(xml.contains(r#"<OMS cd="json" name="null"/>"#));
+        assert!(xml.contains("<OMI>18446744073709551615</OMI>"));
+        assert_eq!(Value::from_openmath_xml(&xml).expect("valid"), value);
+    }
+
+    #[cfg(feature = "xml-write")]
+    #[test]
+    fn custom_mapping() {
+        let value = json!({ "a": null });
+        let mapping = JsonMapping {
+            object: Uri {
+                cdbase: Some(crate::CdBase::new_unchecked("http://example.org")),
+                cd: "records",
+                name: "record",
+            },
+            null: Uri {
+                cdbase: Some(crate::CdBase::OPENMATH),
+                cd: "set1",
+                name: "emptyset",
+            },
+            ..JsonMapping::DEFAULT
+        };
+        let xml = from_serde(&value)
+            .with_mapping(&mapping)
+            .xml(false)
+            .to_string();
+        assert_eq!(
+            xml,
+            r#"<OMA><OMS cdbase="http://example.org" cd="records" name="record"/><OMSTR>a</OMSTR><OMS cd="set1" name="emptyset"/></OMA>"#
+        );
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn unknown_openmath() {
+        let value = Value::from_openmath_xml(
+            r#"<OMBIND><OMS cd="fns1" name="lambda"/><OMBVAR><OMV name="x"/></OMBVAR>
+              <OMA><OMS cd="arith1" name="plus"/><OMV name="x"/><OMI>1</OMI><OMF dec="NaN"/></OMA>
+            </OMBIND>"#,
+        )
+        .expect("valid");
+        assert_eq!(
+            value,
+            json!({
+                "kind": "OMBIND",
+                "binder": { "kind": "OMS", "cd": "fns1", "name": "lambda" },
+                "variables": [{ "kind": "OMV", "name": "x" }],
+                "object": {
+                    "kind": "OMA",
+                    "applicant": { "kind": "OMS", "cd": "arith1", "name": "plus" },
+                    "arguments": [
+                        { "kind": "OMV", "name": "x" },
+                        1,
+                        { "kind": "OMF", "decimal": "NaN" }
+                    ]
+                }
+            })
+        );
+    }
+}
@@ -1,22 +1,89 @@
 #![allow(unexpected_cfgs)]
-#![cfg_attr(all(doc, CHANNEL_NIGHTLY), feature(doc_auto_cfg))]
+#![cfg_attr(all(doc, CHANNEL_NIGHTLY), feature(doc_cfg))]
 #![allow(clippy::doc_markdown)]
 #![doc = include_str!("../README.md")]
 /*! ## Features */
 #![cfg_attr(doc,doc = document_features::document_features!())]
 pub mod ser;
 
-use std::{borrow::Cow, convert::Infallible};
+use std::borrow::Cow;
 
 pub use ser::OMSerializable;
+mod analysis;
+mod annotations;
+pub use annotations::{AnnotationPolicy, TYPE_ANNOTATION};
+pub mod arena;
+#[cfg(feature = "xml-read")]
+pub use analysis::analyze_xml;
+pub use analysis::{Analysis, analyze};
+mod attributes;
+pub use attributes::KeepPolicy;
 pub mod de;
 pub use de::{OM, OMDeserializable};
 pub mod base64;
+#[cfg(feature = "xml-read")]
+pub mod batch;
+pub mod bridge;
+pub mod capabilities;
+pub use capabilities::capabilities;
+mod cdbase;
+mod cdgroup;
+mod convert;
+#[cfg(feature = "xml-read")]
+pub use cdgroup::CdGroupError;
+pub use cdgroup::{CdGroup, CdGroupMember, UnresolvedSymbol};
+pub use convert::ConversionError;
+mod debug;
+pub mod document;
+pub use document::Document;
+mod errors;
+pub use errors::{ENCODING_ERROR, EncodingError, MOREERRORS};
+mod floats;
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing;
+pub use cdbase::{CdBase, CdBaseError};
+#[cfg(any(feature = "xml-read", feature = "xml-write"))]
+mod format_map;
+#[cfg(any(feature = "xml-read", feature = "xml-write"))]
+pub use format_map::FormatMap;
+#[cfg(all(feature = "xml-read", feature = "xml-write"))]
+pub use format_map::rewrite_verbatim;
 mod int;
+mod invariants;
+mod path;
+pub use invariants::{InvariantError, Lint};
+pub use path::{Path, Step, Subterm, SubtermError};
+#[cfg(feature = "serde_json")]
+pub mod json;
+pub mod mathml;
+pub mod numeric;
+#[cfg(feature = "popcorn")]
+pub mod popcorn;
+pub mod schema;
+#[cfg(feature = "scscp")]
+pub mod scscp;
+pub mod sexpr;
+mod shape;
+pub mod stable_hash;
+#[cfg(test)]
+mod test_util;
+pub mod testing;
+pub use shape::{Shape, ShapeError};
+mod symbol_ref;
+pub use symbol_ref::{SymbolRef, SymbolRefError};
+mod symbols;
+pub use symbols::UnexpectedSymbol;
+#[doc(hidden)]
+pub use symbols::find_symbol as __find_symbol;
+pub mod types;
+pub mod view;
+pub mod visit;
+pub mod zipper;
 /// reexported for convenience
 pub use either;
-pub use int::Int;
+pub use int::{Int, IntParseError};
 
+use crate::schema::{SlotKind, SlotView};
 use crate::ser::AsOMS;
 
 /// The base URI of official OᴘᴇɴMᴀᴛʜ dictionaries (`http://www.openmath.org/cd`)
@@ -25,10 +92,13 @@ pub const CD_BASE: &str = "http://www.openmath.org/cd";
 /// XML namespace for OpenMath elements
 pub const XML_NS: &str = "http://www.openmath.org/OpenMath";
 
+/// The version of the <span style="font-variant:small-caps;">OpenMath</span> standard implemented by this crate
+pub const OPENMATH_VERSION: &str = "2.0";
+
 macro_rules! omkinds {
     ($( $(#[$meta:meta])* $id:ident = $v:literal ),* $(,)?) => {
         /// All <span style="font-variant:small-caps;">OpenMath</span> tags/kinds
-        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
         #[repr(u8)]
         #[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
         pub enum OMKind {
@@ -38,6 +108,9 @@ macro_rules! omkinds {
             ),*
         }
         impl OMKind {
+            /// All kinds, in the order of their discriminants
+            pub const ALL: &'static [Self] = &[$(Self::$id),*];
+
             /// as static string
             #[must_use]
             pub const fn as_str(self) -> &'static str {
@@ -45,6 +118,14 @@ macro_rules! omkinds {
                     Self::$id => stringify!($id)
                 ),*}
             }
+            /// convert from a tag name (e.g. `"OMA"`)
+            #[must_use]
+            pub fn from_name(name: &[u8]) -> Option<Self> {
+                match name {
+                    $( n if n == stringify!($id).as_bytes() => Some(Self::$id), )*
+                    _ => None
+                }
+            }
             /// convert from a byte
             #[must_use]
             pub const fn from_u8(u:u8) -> Option<Self> {
@@ -174,11 +255,24 @@ omkinds! {
 /// `OMATTR(OMATTR(OMATTR(...` terms or having to make the grammar significantly
 /// more complicated.
 ///
+/// The attributes of an `OMATTR` always end up on the object it *directly* wraps, never on
+/// its parent or children: `OMATTR(pairs, OMA(f, x))` is an [`OMA`](Self::OMA) whose own
+/// `attributes` are `pairs`, whereas `OMA(OMATTR(pairs, f), x)` is an [`OMA`](Self::OMA)
+/// whose `applicant` carries them; likewise, an `OMATTR` around an `OMBIND` attributes the
+/// binding, and one around a bound variable (inside `OMBVAR`) ends up in that
+/// [`BoundVariable`]'s `attributes`. Nested `OMATTR`s are merged with the pairs of the outer
+/// one first. This holds for both XML and JSON, in both directions.
+///
 ///<div class="openmath">
 /// OᴘᴇɴMᴀᴛʜ objects are built recursively as follows.
 /// </div>
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-#[repr(u8)]
+///
+/// # Equality and hashing
+/// Objects are compared and hashed structurally, except that cdbases are compared modulo
+/// [normalization](CdBase::normalize), and the
+/// [`cdbase_inherited`](Self::OMS::cdbase_inherited) flag of symbols is ignored: a symbol
+/// means the same whether its cdbase was written on it or on an enclosing object.
+#[derive(Clone)]
 pub enum OpenMath<'om> {
     /** <div class="openmath">
     Integers in the mathematical sense, with no predefined range.
@@ -187,15 +281,30 @@ pub enum OpenMath<'om> {
     OMI {
         int: Int<'om>,
         attributes: Vec<Attr<'om, OMMaybeForeign<'om, Self>>>,
-    } = OMKind::OMI as _,
+    },
 
     /** <div class="openmath">
     Double precision floating-point numbers following the IEEE 754-1985 standard.
-    </div> */
+    </div>
+
+    # Equality and hashing
+    Floats are compared and hashed as [`OrderedFloat`](ordered_float::OrderedFloat)s, so
+    that [`Eq`] and [`Hash`] are consistent: all NaNs (regardless of sign and payload) are
+    equal to each other and hash alike, `-0.0` is equal to `0.0` and hashes alike, and
+    infinities are only equal to themselves. Anything keyed on the [bits](f64::to_bits) of
+    a float instead should call [`canonicalize_floats`](Self::canonicalize_floats) first;
+    to compare up to rounding errors, use [`approx_eq`](Self::approx_eq).
+
+    # Serialization
+    XML writes non-finite values as `NaN`, `INF` and `-INF` in the `dec` attribute, and
+    negative zero as `-0`; all of them are read back as they were, except that NaNs lose
+    their sign and payload. Since JSON numbers can not be non-finite, the serde
+    representation uses the `decimal` field (or a string in the compact form) for them
+    instead of `float`. */
     OMF {
         float: ordered_float::OrderedFloat<f64>,
         attributes: Vec<Attr<'om, OMMaybeForeign<'om, Self>>>,
-    } = OMKind::OMF as _,
+    },
 
     /** <div class="openmath">
     A Unicode Character string. This also corresponds to “characters” in XML.
@@ -203,7 +312,7 @@ pub enum OpenMath<'om> {
     OMSTR {
         string: Cow<'om, str>,
         attributes: Vec<Attr<'om, OMMaybeForeign<'om, Self>>>,
-    } = OMKind::OMSTR as _,
+    },
 
     /** <div class="openmath">
     A sequence of bytes.
@@ -211,7 +320,7 @@ pub enum OpenMath<'om> {
     OMB {
         bytes: Cow<'om, [u8]>,
         attributes: Vec<Attr<'om, OMMaybeForeign<'om, Self>>>,
-    } = OMKind::OMB as _,
+    },
 
     ///<div class="openmath">
     ///
@@ -224,7 +333,7 @@ pub enum OpenMath<'om> {
     OMV {
         name: Cow<'om, str>,
         attributes: Vec<Attr<'om, OMMaybeForeign<'om, Self>>>,
-    } = OMKind::OMV as _,
+    },
 
     /** <div class="openmath">
     A Symbol encodes three fields of information, a symbol name, a Content Dictionary name,
@@ -243,8 +352,14 @@ pub enum OpenMath<'om> {
         cd: Cow<'om, str>,
         name: Cow<'om, str>,
         cdbase: Option<Cow<'om, str>>,
+        /// whether `cdbase` was inherited from an enclosing object rather than given
+        /// explicitly on the symbol itself. Serialization places inherited cdbases
+        /// on the outermost object they apply to (rather than repeating them on every
+        /// symbol), so documents round-trip without growing. This flag does not take part in
+        /// comparisons or hashing.
+        cdbase_inherited: bool,
         attributes: Vec<Attr<'om, OMMaybeForeign<'om, Self>>>,
-    } = OMKind::OMS as _,
+    },
 
     /** <div class="openmath">
     If $A_1,...,A_n\;(n>0)$ are <span style="font-variant:small-caps;">OpenMath</span> objects, then
@@ -255,7 +370,7 @@ pub enum OpenMath<'om> {
         applicant: Box<Self>,
         arguments: Vec<Self>,
         attributes: Vec<Attr<'om, OMMaybeForeign<'om, Self>>>,
-    } = OMKind::OMA as _,
+    },
 
     /** <div class="openmath">
     If $S$ is an <span style="font-variant:small-caps;">OpenMath</span> symbol and $A_1,...,A_n\;(n\geq0)$ are <span style="font-variant:small-caps;">OpenMath</span> objects or
@@ -264,10 +379,12 @@ pub enum OpenMath<'om> {
     OME {
         cd: Cow<'om, str>,
         name: Cow<'om, str>,
+        /// the cdbase of the error symbol; like for an [`OMS`](Self::OMS), deserialized
+        /// errors have the one in scope unless it is given explicitly
         cdbase: Option<Cow<'om, str>>,
         arguments: Vec<OMMaybeForeign<'om, Self>>,
         attributes: Vec<Attr<'om, OMMaybeForeign<'om, Self>>>,
-    } = OMKind::OME as _,
+    },
 
     /** <div class="openmath">
     If $B$ and $C$ are <span style="font-variant:small-caps;">OpenMath</span> objects, and $v_1,...,v_n\;(n\geq0)$
@@ -275,13 +392,217 @@ pub enum OpenMath<'om> {
     $\mathrm{binding}(B,v_1,...,v_n,C)$ is an <span style="font-variant:small-caps;">OpenMath</span> binding object.
     $B$ is called the binder, $v_1,...,v_n$ are called variable bindings, and
     $C$ is called the body of the binding object above.
-    </div> */
+    </div>
+
+    The names of the variables need not be distinct, and duplicates are kept (in order) when
+    reading and writing objects. Like in nested bindings, a later variable shadows an earlier
+    one with the same name: an occurrence in the body (or in the attributes of later
+    variables) refers to the last variable with its name, so `λ x x. x` means `λ x. λ x. x`;
+    see [`bound_variable`](Self::bound_variable). Since that is rarely intended,
+    [`lints`](Self::lints) reports such duplicates as [`Lint::DuplicateBoundVariable`]. */
     OMBIND {
         binder: Box<Self>,
+        /// the variables, in order; names may repeat
         variables: Vec<BoundVariable<'om>>,
         object: Box<Self>,
         attributes: Vec<Attr<'om, OMMaybeForeign<'om, Self>>>,
-    } = OMKind::OMBIND as _,
+    },
+}
+
+/// `cdbase` as a [`CdBase`], which compares and hashes modulo normalization
+fn as_cdbase(cdbase: Option<&str>) -> Option<CdBase<'_>> {
+    cdbase.map(CdBase::new_unchecked)
+}
+
+impl PartialEq for OpenMath<'_> {
+    #[allow(clippy::too_many_lines)]
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (
+                Self::OMI { int, attributes },
+                Self::OMI {
+                    int: int2,
+                    attributes: attributes2,
+                },
+            ) => int == int2 && attributes == attributes2,
+            (
+                Self::OMF { float, attributes },
+                Self::OMF {
+                    float: float2,
+                    attributes: attributes2,
+                },
+            ) => float == float2 && attributes == attributes2,
+            (
+                Self::OMSTR { string, attributes },
+                Self::OMSTR {
+                    string: string2,
+                    attributes: attributes2,
+                },
+            ) => string == string2 && attributes == attributes2,
+            (
+                Self::OMB { bytes, attributes },
+                Self::OMB {
+                    bytes: bytes2,
+                    attributes: attributes2,
+                },
+            ) => bytes == bytes2 && attributes == attributes2,
+            (
+                Self::OMV { name, attributes },
+                Self::OMV {
+                    name: name2,
+                    attributes: attributes2,
+                },
+            ) => name == name2 && attributes == attributes2,
+            (
+                Self::OMS {
+                    cd,
+                    name,
+                    cdbase,
+                    cdbase_inherited: _,
+                    attributes,
+                },
+                Self::OMS {
+                    cd: cd2,
+                    name: name2,
+                    cdbase: cdbase2,
+                    cdbase_inherited: _,
+                    attributes: attributes2,
+                },
+            ) => {
+                cd == cd2
+                    && name == name2
+                    && as_cdbase(cdbase.as_deref()) == as_cdbase(cdbase2.as_deref())
+                    && attributes == attributes2
+            }
+            (
+                Self::OMA {
+                    applicant,
+                    arguments,
+                    attributes,
+                },
+                Self::OMA {
+                    applicant: applicant2,
+                    arguments: arguments2,
+                    attributes: attributes2,
+                },
+            ) => applicant == applicant2 && arguments == arguments2 && attributes == attributes2,
+            (
+                Self::OME {
+                    cd,
+                    name,
+                    cdbase,
+                    arguments,
+                    attributes,
+                },
+                Self::OME {
+                    cd: cd2,
+                    name: name2,
+                    cdbase: cdbase2,
+                    arguments: arguments2,
+                    attributes: attributes2,
+                },
+            ) => {
+                cd == cd2
+                    && name == name2
+                    && as_cdbase(cdbase.as_deref()) == as_cdbase(cdbase2.as_deref())
+                    && arguments == arguments2
+                    && attributes == attributes2
+            }
+            (
+                Self::OMBIND {
+                    binder,
+                    variables,
+                    object,
+                    attributes,
+                },
+                Self::OMBIND {
+                    binder: binder2,
+                    variables: variables2,
+                    object: object2,
+                    attributes: attributes2,
+                },
+            ) => {
+                binder == binder2
+                    && variables == variables2
+                    && object == object2
+                    && attributes == attributes2
+            }
+            _ => false,
+        }
+    }
+}
+impl Eq for OpenMath<'_> {}
+impl std::hash::Hash for OpenMath<'_> {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Self::OMI { int, attributes } => {
+                int.hash(state);
+                attributes.hash(state);
+            }
+            Self::OMF { float, attributes } => {
+                float.hash(state);
+                attributes.hash(state);
+            }
+            Self::OMSTR { string, attributes } => {
+                string.hash(state);
+                attributes.hash(state);
+            }
+            Self::OMB { bytes, attributes } => {
+                bytes.hash(state);
+                attributes.hash(state);
+            }
+            Self::OMV { name, attributes } => {
+                name.hash(state);
+                attributes.hash(state);
+            }
+            Self::OMS {
+                cd,
+                name,
+                cdbase,
+                cdbase_inherited: _,
+                attributes,
+            } => {
+                cd.hash(state);
+                name.hash(state);
+                as_cdbase(cdbase.as_deref()).hash(state);
+                attributes.hash(state);
+            }
+            Self::OMA {
+                applicant,
+                arguments,
+                attributes,
+            } => {
+                applicant.hash(state);
+                arguments.hash(state);
+                attributes.hash(state);
+            }
+            Self::OME {
+                cd,
+                name,
+                cdbase,
+                arguments,
+                attributes,
+            } => {
+                cd.hash(state);
+                name.hash(state);
+                as_cdbase(cdbase.as_deref()).hash(state);
+                arguments.hash(state);
+                attributes.hash(state);
+            }
+            Self::OMBIND {
+                binder,
+                variables,
+                object,
+                attributes,
+            } => {
+                binder.hash(state);
+                variables.hash(state);
+                object.hash(state);
+                attributes.hash(state);
+            }
+        }
+    }
 }
 
 /// A bound variable in an [`OMBIND`](OpenMath::OMBIND)
@@ -309,24 +630,87 @@ impl ser::BindVar for &BoundVariable<'_> {
 /// Generic over the attribute value, so it can be used in [OpenMath] and [OM]
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Attr<'o, I> {
-    pub cdbase: Option<Cow<'o, str>>,
-    pub cd: Cow<'o, str>,
-    pub name: Cow<'o, str>,
+    /// The attribute's key symbol
+    pub key: SymbolRef<'o>,
     pub value: I,
 }
+impl<'o, I> Attr<'o, I> {
+    /// The attribution pair `key = value`
+    #[inline]
+    pub const fn new(key: SymbolRef<'o>, value: I) -> Self {
+        Self { key, value }
+    }
+
+    /// The cdbase of the [`key`](Attr::key), if given explicitly
+    #[inline]
+    #[must_use]
+    pub fn cdbase(&self) -> Option<&str> {
+        self.key.cdbase()
+    }
+
+    /// The content dictionary of the [`key`](Attr::key)
+    #[inline]
+    #[must_use]
+    pub fn cd(&self) -> &str {
+        self.key.cd()
+    }
+
+    /// The name of the [`key`](Attr::key)
+    #[inline]
+    #[must_use]
+    pub fn name(&self) -> &str {
+        self.key.name()
+    }
+}
+
+impl<'o, I> Attr<'o, OMMaybeForeign<'o, I>> {
+    /// The content dictionary of the key of [presentation MathML](Self::presentation_mathml)
+    /// attributions
+    pub const ALTENC_CD: &'static str = "altenc";
+    /// The name of the key of [presentation MathML](Self::presentation_mathml) attributions
+    pub const MATHML_PRESENTATION: &'static str = "MathML_Presentation";
+    /// The encoding of the [OMFOREIGN](OMKind::OMFOREIGN) value of
+    /// [presentation MathML](Self::presentation_mathml) attributions
+    pub const MATHML_PRESENTATION_ENCODING: &'static str = "MathML-Presentation";
+
+    /** Attributes an object with its presentation MathML, using the `altenc` content
+    dictionary: the key is the symbol `altenc#MathML_Presentation` (with the standard cdbase),
+    the value an [OMFOREIGN](OMKind::OMFOREIGN) with encoding `MathML-Presentation`
+    containing `mathml` verbatim (e.g. a `<math>` element with its namespace declaration).
+
+    See also [`OpenMath::presentation`].
+    */
+    pub fn presentation_mathml(mathml: impl Into<Cow<'o, str>>) -> Self {
+        Self::new(
+            SymbolRef::new(Self::ALTENC_CD, Self::MATHML_PRESENTATION),
+            OMMaybeForeign::Foreign {
+                encoding: Some(Cow::Borrowed(Self::MATHML_PRESENTATION_ENCODING)),
+                value: mathml.into(),
+            },
+        )
+    }
+
+    /// Whether this is a [presentation MathML](Self::presentation_mathml) attribution, i.e.
+    /// its key is `altenc#MathML_Presentation` with the standard cdbase.
+    #[must_use]
+    pub fn is_presentation_mathml(&self) -> bool {
+        self.cd() == Self::ALTENC_CD
+            && self.name() == Self::MATHML_PRESENTATION
+            && self
+                .cdbase()
+                .is_none_or(|c| cdbase::eq_normalized(c, CD_BASE))
+    }
+}
 impl<I> ser::OMAttr for &Attr<'_, I>
 where
     for<'a> &'a I: ser::OMOrForeign,
 {
     #[inline]
     fn symbol(&self) -> impl AsOMS {
-        ser::Uri {
-            cdbase: self.cdbase.as_deref(),
-            cd: &self.cd,
-            name: &self.name,
-        }
+        self.key.as_uri()
     }
-    fn value(&self) -> impl ser::OMOrForeign {
+    #[inline]
+    fn value(self) -> impl ser::OMOrForeign {
         &self.value
     }
 }
@@ -349,6 +733,90 @@ pub enum OMMaybeForeign<'o, I> {
         value: Cow<'o, str>,
     },
 }
+/// An argument of an [`OME`](OpenMath::OME): an object or an [`OMFOREIGN`](OMKind::OMFOREIGN)
+///
+/// Besides attribute values, these are the only places the standard allows `OMFOREIGN`s;
+/// see [`OpenMath::try_ome`].
+pub type ErrorArg<'om> = OMMaybeForeign<'om, OpenMath<'om>>;
+
+impl<'o, I> OMMaybeForeign<'o, I> {
+    /** An [OMFOREIGN](OMKind::OMFOREIGN) with the given `encoding` (e.g. a media type)
+    containing `value` verbatim; e.g. as an [`ErrorArg`]. XML output contains `value` as
+    is, so it needs to be well-formed XML content (e.g. text with `<` and `&` escaped).
+
+    # Examples
+    ```rust
+    use openmath::{ErrorArg, OMMaybeForeign};
+
+    let arg = ErrorArg::foreign("application/json", r#"{"line":3}"#);
+    assert!(matches!(arg, OMMaybeForeign::Foreign { encoding: Some(e), .. } if e == "application/json"));
+    ```
+    */
+    #[inline]
+    pub fn foreign(encoding: impl Into<Cow<'o, str>>, value: impl Into<Cow<'o, str>>) -> Self {
+        Self::Foreign {
+            encoding: Some(encoding.into()),
+            value: value.into(),
+        }
+    }
+
+    /// The non-OMFOREIGN-case, if this is one
+    #[inline]
+    pub const fn as_om(&self) -> Option<&I> {
+        match self {
+            Self::OM(i) => Some(i),
+            Self::Foreign { .. } => None,
+        }
+    }
+
+    /// The non-OMFOREIGN-case, if this is one (mutable)
+    #[inline]
+    pub const fn as_om_mut(&mut self) -> Option<&mut I> {
+        match self {
+            Self::OM(i) => Some(i),
+            Self::Foreign { .. } => None,
+        }
+    }
+
+    /// Maps the non-OMFOREIGN-case; e.g. to convert deserialized
+    /// [OME](OMKind::OME) arguments or attribute values.
+    #[inline]
+    pub fn map<T>(self, f: impl FnOnce(I) -> T) -> OMMaybeForeign<'o, T> {
+        match self {
+            Self::OM(i) => OMMaybeForeign::OM(f(i)),
+            Self::Foreign { encoding, value } => OMMaybeForeign::Foreign { encoding, value },
+        }
+    }
+
+    /// Copies the encoding and value of an [OMFOREIGN](OMKind::OMFOREIGN), so that it no
+    /// longer borrows from the input.
+    #[must_use]
+    pub fn into_owned(self) -> OMMaybeForeign<'static, I> {
+        match self {
+            Self::OM(i) => OMMaybeForeign::OM(i),
+            Self::Foreign { encoding, value } => OMMaybeForeign::Foreign {
+                encoding: encoding.map(|e| Cow::Owned(e.into_owned())),
+                value: Cow::Owned(value.into_owned()),
+            },
+        }
+    }
+
+    /// Converts the non-OMFOREIGN-case via [`TryInto`]; e.g. to turn a deserialized
+    /// attribute value (an [`OMDeserializable::Ret`](de::OMDeserializable::Ret)) into the
+    /// final type.
+    ///
+    /// # Errors
+    /// if the conversion fails
+    pub fn try_convert<T>(self) -> Result<OMMaybeForeign<'o, T>, I::Error>
+    where
+        I: TryInto<T>,
+    {
+        Ok(match self {
+            Self::OM(i) => OMMaybeForeign::OM(i.try_into()?),
+            Self::Foreign { encoding, value } => OMMaybeForeign::Foreign { encoding, value },
+        })
+    }
+}
 
 impl<I: ser::OMSerializable> ser::OMOrForeign for &OMMaybeForeign<'_, I> {
     /// converts this into an `Either`(crate::either::Either)
@@ -368,14 +836,79 @@ impl<I: ser::OMSerializable> ser::OMOrForeign for &OMMaybeForeign<'_, I> {
 }
 
 impl ser::OMSerializable for OpenMath<'_> {
+    /// The inherited cdbase of a bare [`OMS`](Self::OMS), which it can not pass on to
+    /// itself; so that it goes on the `OMOBJ` (if any) and is inherited again when reading
+    #[inline]
+    fn cdbase(&self) -> Option<&str> {
+        match self {
+            Self::OMS {
+                cdbase: Some(cdbase),
+                cdbase_inherited: true,
+                attributes,
+                ..
+            } if attributes.is_empty() && !cdbase::eq_normalized(cdbase, CD_BASE) => Some(cdbase),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    fn as_openmath<'s, S: ser::OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        WithRefs(self, &()).as_openmath(serializer)
+    }
+}
+
+/// Where [`WithRefs`] serializes subterms as [OMR](OMKind::OMR)s
+pub(crate) trait Refs {
+    /// The `href` to refer to `om` by instead of serializing it, if any
+    fn href(&self, om: &OpenMath<'_>) -> Option<&str>;
+
+    /// The key to [memoize](ser::OMSerializer::memoized) the serialization of `om` under,
+    /// if any
+    #[inline]
+    fn memo_key(&self, om: &OpenMath<'_>) -> Option<usize> {
+        let _ = om;
+        None
+    }
+}
+impl Refs for () {
+    #[inline]
+    fn href(&self, _: &OpenMath<'_>) -> Option<&str> {
+        None
+    }
+}
+
+/// Serializes an [`OpenMath`], replacing the subterms (except bound variables) that `R`
+/// has an `href` for by [OMR](OMKind::OMR)s; see [`ser::Sharder`].
+pub(crate) struct WithRefs<'a, 'o, R: ?Sized>(pub &'a OpenMath<'o>, pub &'a R);
+
+/// The [inherited cdbases](OpenMath::inherited_cdbases) of the subterms of an object, by
+/// address
+type Inherited<'a> = std::collections::HashMap<usize, &'a str>;
+
+impl<R: Refs + ?Sized> ser::OMSerializable for WithRefs<'_, '_, R> {
+    fn as_openmath<'s, S: ser::OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        let Self(om, refs) = *self;
+        // once for the whole object, rather than at every subterm
+        let mut inherited = Inherited::new();
+        om.inherited_cdbases(&mut inherited);
+        Node(om, refs, &inherited).as_openmath(serializer)
+    }
+}
+
+/// A subterm of a [`WithRefs`]
+struct Node<'a, 'o, R: ?Sized>(&'a OpenMath<'o>, &'a R, &'a Inherited<'a>);
+
+impl<R: Refs + ?Sized> ser::OMSerializable for Node<'_, '_, R> {
+    #[allow(clippy::too_many_lines)]
     fn as_openmath<'s, S: ser::OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
-        struct NoAttrs<'s, 'o>(&'s OpenMath<'o>);
-        impl ser::OMSerializable for NoAttrs<'_, '_> {
+        struct NoAttrs<'s, 'o, R: ?Sized>(&'s OpenMath<'o>, &'s R, &'s Inherited<'s>);
+        impl<R: Refs + ?Sized> ser::OMSerializable for NoAttrs<'_, '_, R> {
             fn as_openmath<'s, S: ser::OMSerializer<'s>>(
                 &self,
                 serializer: S,
             ) -> Result<S::Ok, S::Err> {
-                match self.0 {
+                let Self(om, refs, inherited) = *self;
+                match om {
                     OpenMath::OMI { int, .. } => int.as_openmath(serializer),
                     OpenMath::OMF { float, .. } => float.0.as_openmath(serializer),
                     OpenMath::OMSTR { string, .. } => string.as_openmath(serializer),
@@ -384,7 +917,7 @@ impl ser::OMSerializable for OpenMath<'_> {
                     OpenMath::OMS {
                         cd, name, cdbase, ..
                     } => ser::Uri {
-                        cdbase: cdbase.as_deref(),
+                        cdbase: cdbase.as_deref().map(CdBase::new_unchecked),
                         name,
                         cd,
                     }
@@ -394,7 +927,10 @@ impl ser::OMSerializable for OpenMath<'_> {
                         applicant,
                         arguments,
                         ..
-                    } => serializer.oma(&**applicant, arguments.iter()),
+                    } => serializer.oma(
+                        Node(applicant, refs, inherited),
+                        arguments.iter().map(|a| Node(a, refs, inherited)),
+                    ),
                     OpenMath::OME {
                         cd,
                         name,
@@ -403,75 +939,464 @@ impl ser::OMSerializable for OpenMath<'_> {
                         ..
                     } => serializer.ome(
                         &ser::Uri {
-                            cdbase: cdbase.as_deref(),
+                            cdbase: cdbase.as_deref().map(CdBase::new_unchecked),
                             cd,
                             name,
                         },
-                        arguments.iter(),
+                        arguments
+                            .iter()
+                            .map(|a| ForeignWithRefs(a, refs, inherited)),
                     ),
                     OpenMath::OMBIND {
                         binder,
                         variables,
                         object,
                         ..
-                    } => serializer.ombind(&**binder, variables.iter(), &**object),
+                    } => serializer.ombind(
+                        Node(binder, refs, inherited),
+                        variables.iter(),
+                        Node(object, refs, inherited),
+                    ),
+                }
+            }
+        }
+        struct AttrWithRefs<'s, 'o, R: ?Sized>(
+            &'s Attr<'o, OMMaybeForeign<'o, OpenMath<'o>>>,
+            &'s R,
+            &'s Inherited<'s>,
+        );
+        impl<R: Refs + ?Sized> ser::OMAttr for AttrWithRefs<'_, '_, R> {
+            #[inline]
+            fn symbol(&self) -> impl AsOMS {
+                self.0.key.as_uri()
+            }
+            #[inline]
+            fn value(self) -> impl ser::OMOrForeign {
+                ForeignWithRefs(&self.0.value, self.1, self.2)
+            }
+        }
+        struct ForeignWithRefs<'s, 'o, R: ?Sized>(
+            &'s OMMaybeForeign<'o, OpenMath<'o>>,
+            &'s R,
+            &'s Inherited<'s>,
+        );
+        impl<R: Refs + ?Sized> ser::OMOrForeign for ForeignWithRefs<'_, '_, R> {
+            fn om_or_foreign(
+                self,
+            ) -> either::Either<
+                impl OMSerializable,
+                (Option<impl std::fmt::Display>, impl std::fmt::Display),
+            > {
+                match self.0 {
+                    OMMaybeForeign::OM(o) => either::Either::Left(Node(o, self.1, self.2)),
+                    OMMaybeForeign::Foreign { encoding, value } => {
+                        either::Either::Right((encoding.as_deref(), &**value))
+                    }
+                }
+            }
+        }
+        fn with_attrs<'s, S: ser::OMSerializer<'s>, R: Refs + ?Sized>(
+            om: &OpenMath<'_>,
+            refs: &R,
+            inherited: &Inherited<'_>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Err> {
+            match om {
+                OpenMath::OMI { attributes, .. }
+                | OpenMath::OMF { attributes, .. }
+                | OpenMath::OMSTR { attributes, .. }
+                | OpenMath::OMB { attributes, .. }
+                | OpenMath::OMV { attributes, .. }
+                | OpenMath::OMS { attributes, .. }
+                | OpenMath::OMA { attributes, .. }
+                | OpenMath::OME { attributes, .. }
+                | OpenMath::OMBIND { attributes, .. }
+                    if !attributes.is_empty() =>
+                {
+                    serializer.omattr(
+                        attributes.iter().map(|a| AttrWithRefs(a, refs, inherited)),
+                        NoAttrs(om, refs, inherited),
+                    )
+                }
+                _ => NoAttrs(om, refs, inherited).as_openmath(serializer),
+            }
+        }
+        /// `om` itself, without consulting the memo
+        struct Fresh<'s, 'o, R: ?Sized>(&'s OpenMath<'o>, &'s R, &'s Inherited<'s>);
+        impl<R: Refs + ?Sized> ser::OMSerializable for Fresh<'_, '_, R> {
+            fn as_openmath<'s, S: ser::OMSerializer<'s>>(
+                &self,
+                serializer: S,
+            ) -> Result<S::Ok, S::Err> {
+                let Self(om, refs, inherited) = *self;
+                // reinstate inherited cdbases on the outermost object they apply to
+                if !matches!(om, OpenMath::OMS { attributes, .. } if attributes.is_empty())
+                    && !inherited.is_empty()
+                    && let Some(cdbase) = inherited.get(&std::ptr::from_ref(om).addr()).copied()
+                    && !crate::cdbase::eq_normalized(cdbase, serializer.current_cdbase())
+                {
+                    return with_attrs(om, refs, inherited, serializer.with_cdbase(cdbase)?);
                 }
+                with_attrs(om, refs, inherited, serializer)
             }
         }
+        let Self(om, refs, inherited) = *self;
+        if let Some(href) = refs.href(om) {
+            return serializer.omr(href);
+        }
+        if let Some(key) = refs.memo_key(om) {
+            return serializer.memoized(key, Fresh(om, refs, inherited));
+        }
+        Fresh(om, refs, inherited).as_openmath(serializer)
+    }
+}
+
+impl OpenMath<'_> {
+    /// The symbol of an [`OMS`](Self::OMS), or the error symbol of an [`OME`](Self::OME)
+    /// (borrowed)
+    ///
+    /// # Examples
+    /// ```rust
+    /// use openmath::{OpenMath, SymbolRef};
+    ///
+    /// let om = OpenMath::from_sexpr("(oms arith1 plus)").expect("is valid");
+    /// let symbol = om.symbol_ref().expect("is a symbol");
+    /// assert_eq!((symbol.cd(), symbol.name()), ("arith1", "plus"));
+    /// ```
+    #[must_use]
+    pub fn symbol_ref(&self) -> Option<SymbolRef<'_>> {
         match self {
+            Self::OMS {
+                cd, name, cdbase, ..
+            }
+            | Self::OME {
+                cd, name, cdbase, ..
+            } => Some(SymbolRef {
+                cdbase: cdbase.as_deref().map(Cow::Borrowed),
+                cd: Cow::Borrowed(cd),
+                name: Cow::Borrowed(name),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Records the cdbase of the first symbol (in document order) that inherited its cdbase
+    /// (see [`OMS`](Self::OMS)) of this object and every subterm that has one in
+    /// `inherited`, by address; returns that of this object. Every subterm is visited once.
+    fn inherited_cdbases<'a>(&'a self, inherited: &mut Inherited<'a>) -> Option<&'a str> {
+        fn attrs<'a>(
+            attributes: &'a [Attr<'_, OMMaybeForeign<'_, OpenMath<'_>>>],
+            inherited: &mut Inherited<'a>,
+        ) -> Option<&'a str> {
+            foreign(attributes.iter().map(|a| &a.value), inherited)
+        }
+        fn foreign<'a, 'o: 'a>(
+            values: impl Iterator<Item = &'a OMMaybeForeign<'o, OpenMath<'o>>>,
+            inherited: &mut Inherited<'a>,
+        ) -> Option<&'a str> {
+            // not `find_map`: the later ones have to be recorded as well
+            values.fold(None, |first, v| {
+                let this = match v {
+                    OMMaybeForeign::OM(o) => o.inherited_cdbases(inherited),
+                    OMMaybeForeign::Foreign { .. } => None,
+                };
+                first.or(this)
+            })
+        }
+        let first = match self {
             Self::OMI { attributes, .. }
             | Self::OMF { attributes, .. }
             | Self::OMSTR { attributes, .. }
             | Self::OMB { attributes, .. }
-            | Self::OMV { attributes, .. }
-            | Self::OMS { attributes, .. }
-            | Self::OMA { attributes, .. }
-            | Self::OME { attributes, .. }
-            | Self::OMBIND { attributes, .. }
-                if !attributes.is_empty() =>
-            {
-                serializer.omattr(attributes.iter(), NoAttrs(self))
+            | Self::OMV { attributes, .. } => attrs(attributes, inherited),
+            Self::OMS {
+                cdbase,
+                cdbase_inherited,
+                attributes,
+                ..
+            } => attrs(attributes, inherited)
+                .or_else(|| cdbase.as_deref().filter(|_| *cdbase_inherited)),
+            Self::OMA {
+                applicant,
+                arguments,
+                attributes,
+            } => {
+                let mut first = attrs(attributes, inherited);
+                for o in std::iter::once(&**applicant).chain(arguments) {
+                    let this = o.inherited_cdbases(inherited);
+                    first = first.or(this);
+                }
+                first
+            }
+            Self::OME {
+                arguments,
+                attributes,
+                ..
+            } => {
+                let first = attrs(attributes, inherited);
+                let this = foreign(arguments.iter(), inherited);
+                first.or(this)
+            }
+            Self::OMBIND {
+                binder,
+                variables,
+                object,
+                attributes,
+            } => {
+                let mut first = attrs(attributes, inherited);
+                let this = binder.inherited_cdbases(inherited);
+                first = first.or(this);
+                for v in variables {
+                    let this = attrs(&v.attributes, inherited);
+                    first = first.or(this);
+                }
+                let this = object.inherited_cdbases(inherited);
+                first.or(this)
             }
-            _ => NoAttrs(self).as_openmath(serializer),
+        };
+        if let Some(cdbase) = first {
+            inherited.insert(std::ptr::from_ref(self).addr(), cdbase);
+        }
+        first
+    }
+}
+
+type Attrs<'om> = Vec<Attr<'om, OMMaybeForeign<'om, OpenMath<'om>>>>;
+
+impl<'om> OpenMath<'om> {
+    /// The [`OMKind`] of this object; never [`OMR`](OMKind::OMR) or
+    /// [`OMATTR`](OMKind::OMATTR), since references are resolved and attributions are
+    /// [`attributes`](Self::attributes) of the attributed object.
+    #[must_use]
+    pub const fn kind(&self) -> OMKind {
+        match self {
+            Self::OMI { .. } => OMKind::OMI,
+            Self::OMF { .. } => OMKind::OMF,
+            Self::OMSTR { .. } => OMKind::OMSTR,
+            Self::OMB { .. } => OMKind::OMB,
+            Self::OMV { .. } => OMKind::OMV,
+            Self::OMS { .. } => OMKind::OMS,
+            Self::OMA { .. } => OMKind::OMA,
+            Self::OME { .. } => OMKind::OME,
+            Self::OMBIND { .. } => OMKind::OMBIND,
+        }
+    }
+
+    /// The attribution pairs of this object, regardless of its kind; empty if it is not
+    /// attributed.
+    #[must_use]
+    pub const fn attributes(&self) -> &[Attr<'om, OMMaybeForeign<'om, Self>>] {
+        let (Self::OMI { attributes, .. }
+        | Self::OMF { attributes, .. }
+        | Self::OMSTR { attributes, .. }
+        | Self::OMB { attributes, .. }
+        | Self::OMV { attributes, .. }
+        | Self::OMS { attributes, .. }
+        | Self::OMA { attributes, .. }
+        | Self::OME { attributes, .. }
+        | Self::OMBIND { attributes, .. }) = self;
+        attributes.as_slice()
+    }
+
+    /// The attribution pairs of this object, regardless of its kind; e.g. to attribute it
+    /// further.
+    pub const fn attributes_mut(&mut self) -> &mut Attrs<'om> {
+        let (Self::OMI { attributes, .. }
+        | Self::OMF { attributes, .. }
+        | Self::OMSTR { attributes, .. }
+        | Self::OMB { attributes, .. }
+        | Self::OMV { attributes, .. }
+        | Self::OMS { attributes, .. }
+        | Self::OMA { attributes, .. }
+        | Self::OME { attributes, .. }
+        | Self::OMBIND { attributes, .. }) = self;
+        attributes
+    }
+
+    /** The applicant, arguments and attributes of an [`OMA`](Self::OMA), borrowed
+    mutably at the same time; `None` for other kinds.
+
+    # Examples
+    ```rust
+    use openmath::{Attr, OpenMath};
+
+    let mut om = OpenMath::from_sexpr("(oma (oms arith1 plus) (omi 1) (omi 2))").expect("is valid");
+    if let Some((_, arguments, attributes)) = om.oma_parts_mut() {
+        arguments.reverse();
+        attributes.push(Attr::presentation_mathml("<mn>3</mn>"));
+    }
+    assert_eq!(om.children().nth(1), Some(&OpenMath::from_sexpr("(omi 2)").expect("is valid")));
+    assert_eq!(om.take_arguments().len(), 2);
+    assert_eq!(om.children().count(), 1);
+    ```
+    */
+    pub fn oma_parts_mut(&mut self) -> Option<(&mut Self, &mut Vec<Self>, &mut Attrs<'om>)> {
+        match self {
+            Self::OMA {
+                applicant,
+                arguments,
+                attributes,
+            } => Some((applicant, arguments, attributes)),
+            _ => None,
+        }
+    }
+
+    /// The arguments and attributes of an [`OME`](Self::OME), borrowed mutably at the same
+    /// time; `None` for other kinds.
+    pub const fn ome_parts_mut(
+        &mut self,
+    ) -> Option<(&mut Vec<OMMaybeForeign<'om, Self>>, &mut Attrs<'om>)> {
+        match self {
+            Self::OME {
+                arguments,
+                attributes,
+                ..
+            } => Some((arguments, attributes)),
+            _ => None,
+        }
+    }
+
+    /// The binder, variables, body and attributes of an [`OMBIND`](Self::OMBIND), borrowed
+    /// mutably at the same time; `None` for other kinds.
+    pub fn ombind_parts_mut(
+        &mut self,
+    ) -> Option<(
+        &mut Self,
+        &mut Vec<BoundVariable<'om>>,
+        &mut Self,
+        &mut Attrs<'om>,
+    )> {
+        match self {
+            Self::OMBIND {
+                binder,
+                variables,
+                object,
+                attributes,
+            } => Some((binder, variables, object, attributes)),
+            _ => None,
         }
     }
+
+    /// Takes the arguments of an [`OMA`](Self::OMA), leaving it without arguments; returns
+    /// an empty [`Vec`] for other kinds (including [`OME`](Self::OME)s, whose arguments may
+    /// be foreign; see [`ome_parts_mut`](Self::ome_parts_mut)).
+    pub fn take_arguments(&mut self) -> Vec<Self> {
+        self.oma_parts_mut()
+            .map(|(_, arguments, _)| std::mem::take(arguments))
+            .unwrap_or_default()
+    }
+
+    /** The presentation MathML this object is attributed with, i.e. the value of the first
+    attribution pair with key `altenc#MathML_Presentation` whose value is an
+    [`OMFOREIGN`](OMMaybeForeign::Foreign); see [`Attr::presentation_mathml`].
+
+    # Examples
+    ```rust
+    use openmath::{Attr, OpenMath};
+
+    let mut om = OpenMath::from_sexpr("(omv x)").expect("is valid");
+    assert_eq!(om.presentation(), None);
+    om.attributes_mut().push(Attr::presentation_mathml("<mi>x</mi>"));
+    assert_eq!(om.presentation(), Some("<mi>x</mi>"));
+    ```
+    */
+    #[must_use]
+    pub fn presentation(&self) -> Option<&str> {
+        self.attributes().iter().find_map(|a| match &a.value {
+            OMMaybeForeign::Foreign { value, .. } if a.is_presentation_mathml() => Some(&**value),
+            _ => None,
+        })
+    }
+
+    /** The direct subobjects of this object in document order: the applicant and
+    arguments of an [`OMA`](Self::OMA), the (non-foreign) arguments of an
+    [`OME`](Self::OME), or the binder and body of an [`OMBIND`](Self::OMBIND). Attribute
+    values are not included; see [`children_with_attrs`](Self::children_with_attrs).
+
+    # Examples
+    ```rust
+    use openmath::{OMKind, OpenMath};
+
+    let om = OpenMath::from_sexpr(
+        "(omattr (((oms meta note) (omstr \"hi\"))) (oma (oms arith1 plus) (omi 1) (omv x)))",
+    )
+    .expect("is valid");
+    let kinds = om.children().map(OpenMath::kind).collect::<Vec<_>>();
+    assert_eq!(kinds, [OMKind::OMS, OMKind::OMI, OMKind::OMV]);
+    assert_eq!(om.children_with_attrs().next().map(OpenMath::kind), Some(OMKind::OMSTR));
+    ```
+    */
+    pub fn children(&self) -> impl Iterator<Item = &Self> {
+        self.kind()
+            .child_slots()
+            .iter()
+            .filter(|s| matches!(s.kind, SlotKind::Object | SlotKind::MaybeForeign))
+            .filter_map(|s| self.slot(s))
+            .flat_map(SlotView::objects)
+    }
+
+    /// Like [`children`](Self::children), but additionally yields the (non-foreign)
+    /// attribute values of this object and of the variables of an [`OMBIND`](Self::OMBIND),
+    /// in document order (i.e. those of this object first).
+    pub fn children_with_attrs(&self) -> impl Iterator<Item = &Self> {
+        self.kind()
+            .child_slots()
+            .iter()
+            .filter_map(|s| self.slot(s))
+            .flat_map(SlotView::objects)
+    }
+
+    /** The variable an occurrence of `name` in the body of this [`OMBIND`](Self::OMBIND)
+    refers to, with its index: the last one with that name, since later variables shadow
+    earlier ones. `None` if this is not an `OMBIND` or does not bind `name`.
+
+    # Examples
+    ```rust
+    use openmath::OpenMath;
+
+    let om = OpenMath::from_sexpr("(ombind (oms fns1 lambda) (bvar x y x) (omv x))")
+        .expect("is valid");
+    assert_eq!(om.bound_variable("x").map(|(i, _)| i), Some(2));
+    assert_eq!(om.bound_variable("y").map(|(i, _)| i), Some(1));
+    assert!(om.bound_variable("z").is_none());
+    ```
+    */
+    #[must_use]
+    pub fn bound_variable(&self, name: &str) -> Option<(usize, &BoundVariable<'om>)> {
+        let Self::OMBIND { variables, .. } = self else {
+            return None;
+        };
+        variables
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, v)| v.name == name)
+    }
 }
 
 impl<'o> de::OMDeserializable<'o> for OpenMath<'o> {
     type Ret = Self;
-    type Err = Infallible;
+    type Err = de::ReferenceError;
     #[allow(clippy::too_many_lines)]
     fn from_openmath(om: OM<'o, Self>, cdbase: &str) -> Result<Self, Self::Err>
     where
         Self: Sized,
     {
-        /*fn do_attrs<'o>(
-            attrs: Vec<de::OMAttr<'o, OpenMath<'o>>>,
-        ) -> Vec<Attr<'o, OMMaybeForeign<'o, OpenMath<'o>>>> {
-            attrs
-                .into_iter()
-                .map(|a| Attr {
-                    cdbase: a.cdbase,
-                    cd: a.cd,
-                    name: a.name,
-                    value: match a.value {
-                        either::Either::Left(a) => OMMaybeForeign::OM(a),
-                        either::Either::Right(OMMaybeForeign::Foreign { encoding, value }) => {
-                            OMMaybeForeign::Foreign { encoding, value }
-                        }
-                        either::Either::Right(OMMaybeForeign::OM(_)) => {
-                            unreachable!("by construction")
-                        }
-                    },
-                })
-                .collect()
-        }*/
+        // the default cdbase is borrowed, so e.g. views need not copy it; inherited
+        // spellings of it (e.g. with a trailing slash) are not kept
+        let inherited = || {
+            if crate::cdbase::eq_normalized(cdbase, CD_BASE) {
+                Cow::Borrowed(CD_BASE)
+            } else {
+                Cow::Owned(cdbase.to_string())
+            }
+        };
         Ok(match om {
             OM::OMI { int, attrs } => Self::OMI {
                 int,
                 attributes: attrs,
             },
-            OM::OMF { float, attrs } => Self::OMF {
+            OM::OMF { float, attrs, .. } => Self::OMF {
                 float: float.into(),
                 attributes: attrs,
             },
@@ -487,10 +1412,16 @@ impl<'o> de::OMDeserializable<'o> for OpenMath<'o> {
                 name,
                 attributes: attrs,
             },
-            OM::OMS { cd, name, attrs } => Self::OMS {
+            OM::OMS {
+                cdbase: own_cdbase,
                 cd,
                 name,
-                cdbase: Some(Cow::Owned(cdbase.to_string())),
+                attrs,
+            } => Self::OMS {
+                cd,
+                name,
+                cdbase_inherited: own_cdbase.is_none(),
+                cdbase: Some(own_cdbase.unwrap_or_else(inherited)),
                 attributes: attrs,
             },
             OM::OMA {
@@ -528,59 +1459,24 @@ impl<'o> de::OMDeserializable<'o> for OpenMath<'o> {
             } => Self::OME {
                 cd,
                 name,
-                cdbase,
+                cdbase: Some(cdbase.unwrap_or_else(inherited)),
                 arguments,
                 attributes: attrs,
             },
+            OM::OMR { href, .. } => {
+                return Err(de::UnresolvedReference(href.into_owned()).into());
+            }
         })
     }
 }
 
 #[cfg(all(test, feature = "serde"))]
+#[cfg(all(feature = "xml-read", feature = "xml-write"))]
 #[test]
 #[allow(clippy::too_many_lines)]
 fn roundtrip() {
     use OpenMath::*;
-    const XML: &str = r#"<OMOBJ version="2.0" xmlns="http://www.openmath.org/OpenMath">
-      <OMBIND>
-        <OMS cdbase="http://openmath.org/cd" cd="fns1" name="lambda"/>
-        <OMBVAR>
-          <OMV name="x"/>
-          <OMATTR>
-            <OMATP>
-              <OMS cdbase="http://openmath.org/cd" cd="nope" name="type"/>
-              <OMS cdbase="http://openmath.org/cd" cd="arith1" name="real"/>
-            </OMATP>
-          <OMV name="y"/>
-          </OMATTR>
-        </OMBVAR>
-        <OMA>
-          <OMS cdbase="http://my.namespace" cd="utils" name="either"/>
-          <OMA>
-            <OMS cdbase="http://openmath.org/cd" cd="arith1" name="plus"/>
-            <OMI>128</OMI>
-            <OMATTR>
-              <OMATP>
-                <OMS cdbase="http://openmath.org/cd" cd="nope" name="type"/>
-                <OMFOREIGN>
-                  <MOOT>this is an opaque OMFOREIGN</MOOT>
-                </OMFOREIGN>
-              </OMATP>
-            <OMI>-1234567898765432123456789</OMI>
-            </OMATTR>
-            <OMF dec="3.88988"/>
-            <OMSTR>some number</OMSTR>
-            <OMV name="x"/>
-          </OMA>
-          <OME>
-            <OMS cdbase="http://openmath.org" cd="error" name="unhandled_arithmetics"/>
-            <OMFOREIGN encoding="application/nonsense">
-              ERROAR CODE MOO
-            </OMFOREIGN>
-          </OME>
-        </OMA>
-      </OMBIND>
-    </OMOBJ>"#;
+    const XML: &str = include_str!("../doc/roundtrip.xml");
     const JSON: &str = r#"{
       "kind": "OMOBJ",
       "openmath": "2.0",
@@ -675,24 +1571,77 @@ fn roundtrip() {
                 {
                   "kind": "OMV",
                   "name": "x"
+                },
+                {
+                  "kind": "OMATTR",
+                  "attributes": [
+                    [
+                      {
+                        "kind": "OMS",
+                        "cdbase": "http://openmath.org/cd",
+                        "cd": "nope",
+                        "name": "type"
+                      },
+                      {
+                        "kind": "OMSTR",
+                        "string": "function"
+                      }
+                    ]
+                  ],
+                  "object": {
+                    "kind": "OMBIND",
+                    "binder": {
+                      "kind": "OMS",
+                      "cdbase": "http://openmath.org/cd",
+                      "cd": "fns1",
+                      "name": "lambda"
+                    },
+                    "variables": [
+                      {
+                        "kind": "OMV",
+                        "name": "z"
+                      }
+                    ],
+                    "object": {
+                      "kind": "OMV",
+                      "name": "z"
+                    }
+                  }
                 }
               ]
             },
             {
-              "kind": "OME",
-              "error": {
-                "kind": "OMS",
-                "cdbase": "http://openmath.org",
-                "cd": "error",
-                "name": "unhandled_arithmetics"
-              },
-              "arguments": [
-                {
-                  "kind": "OMFOREIGN",
-                  "foreign": "ERROAR CODE MOO",
-                  "encoding": "application/nonsense"
-                }
-              ]
+              "kind": "OMATTR",
+              "attributes": [
+                [
+                  {
+                    "kind": "OMS",
+                    "cdbase": "http://openmath.org/cd",
+                    "cd": "nope",
+                    "name": "type"
+                  },
+                  {
+                    "kind": "OMSTR",
+                    "string": "error"
+                  }
+                ]
+              ],
+              "object": {
+                "kind": "OME",
+                "error": {
+                  "kind": "OMS",
+                  "cdbase": "http://openmath.org",
+                  "cd": "error",
+                  "name": "unhandled_arithmetics"
+                },
+                "arguments": [
+                  {
+                    "kind": "OMFOREIGN",
+                    "foreign": "ERROAR CODE MOO",
+                    "encoding": "application/nonsense"
+                  }
+                ]
+              }
             }
           ]
         }
@@ -704,6 +1653,7 @@ fn roundtrip() {
             cdbase: Some(Cow::Borrowed("http://openmath.org/cd")),
             cd: Cow::Borrowed("fns1"),
             name: Cow::Borrowed("lambda"),
+            cdbase_inherited: false,
             attributes: Vec::new(),
         }),
         variables: vec![
@@ -714,13 +1664,16 @@ fn roundtrip() {
             BoundVariable {
                 name: Cow::Borrowed("y"),
                 attributes: vec![Attr {
-                    cdbase: Some(Cow::Borrowed("http://openmath.org/cd")),
-                    cd: Cow::Borrowed("nope"),
-                    name: Cow::Borrowed("type"),
+                    key: crate::SymbolRef {
+                        cdbase: Some(Cow::Borrowed("http://openmath.org/cd")),
+                        cd: Cow::Borrowed("nope"),
+                        name: Cow::Borrowed("type"),
+                    },
                     value: OMMaybeForeign::OM(OMS {
                         cdbase: Some(Cow::Borrowed("http://openmath.org/cd")),
                         cd: Cow::Borrowed("arith1"),
                         name: Cow::Borrowed("real"),
+                        cdbase_inherited: false,
                         attributes: Vec::new(),
                     }),
                 }],
@@ -731,6 +1684,7 @@ fn roundtrip() {
                 cd: Cow::Borrowed("utils"),
                 name: Cow::Borrowed("either"),
                 cdbase: Some(Cow::Borrowed("http://my.namespace")),
+                cdbase_inherited: false,
                 attributes: Vec::new(),
             }),
             arguments: vec![
@@ -739,6 +1693,7 @@ fn roundtrip() {
                         cdbase: Some(Cow::Borrowed("http://openmath.org/cd")),
                         cd: Cow::Borrowed("arith1"),
                         name: Cow::Borrowed("plus"),
+                        cdbase_inherited: false,
                         attributes: Vec::new(),
                     }),
                     arguments: vec![
@@ -749,9 +1704,11 @@ fn roundtrip() {
                         OMI {
                             int: Int::new("-1234567898765432123456789").expect("works"),
                             attributes: vec![Attr {
-                                cdbase: Some(Cow::Borrowed("http://openmath.org/cd")),
-                                cd: Cow::Borrowed("nope"),
-                                name: Cow::Borrowed("type"),
+                                key: crate::SymbolRef {
+                                    cdbase: Some(Cow::Borrowed("http://openmath.org/cd")),
+                                    cd: Cow::Borrowed("nope"),
+                                    name: Cow::Borrowed("type"),
+                                },
                                 value: OMMaybeForeign::Foreign {
                                     encoding: None,
                                     value: Cow::Borrowed(
@@ -772,6 +1729,34 @@ fn roundtrip() {
                             name: Cow::Borrowed("x"),
                             attributes: Vec::new(),
                         },
+                        OMBIND {
+                            binder: Box::new(OMS {
+                                cdbase: Some(Cow::Borrowed("http://openmath.org/cd")),
+                                cd: Cow::Borrowed("fns1"),
+                                name: Cow::Borrowed("lambda"),
+                                cdbase_inherited: false,
+                                attributes: Vec::new(),
+                            }),
+                            variables: vec![BoundVariable {
+                                name: Cow::Borrowed("z"),
+                                attributes: Vec::new(),
+                            }],
+                            object: Box::new(OMV {
+                                name: Cow::Borrowed("z"),
+                                attributes: Vec::new(),
+                            }),
+                            attributes: vec![Attr {
+                                key: crate::SymbolRef {
+                                    cdbase: Some(Cow::Borrowed("http://openmath.org/cd")),
+                                    cd: Cow::Borrowed("nope"),
+                                    name: Cow::Borrowed("type"),
+                                },
+                                value: OMMaybeForeign::OM(OMSTR {
+                                    string: Cow::Borrowed("function"),
+                                    attributes: Vec::new(),
+                                }),
+                            }],
+                        },
                     ],
                     attributes: Vec::new(),
                 },
@@ -783,7 +1768,17 @@ fn roundtrip() {
                         encoding: Some(Cow::Borrowed("application/nonsense")),
                         value: Cow::Borrowed("ERROAR CODE MOO"),
                     }],
-                    attributes: Vec::new(),
+                    attributes: vec![Attr {
+                        key: crate::SymbolRef {
+                            cdbase: Some(Cow::Borrowed("http://openmath.org/cd")),
+                            cd: Cow::Borrowed("nope"),
+                            name: Cow::Borrowed("type"),
+                        },
+                        value: OMMaybeForeign::OM(OMSTR {
+                            string: Cow::Borrowed("error"),
+                            attributes: Vec::new(),
+                        }),
+                    }],
                 },
             ],
             attributes: Vec::new(),
@@ -808,3 +1803,794 @@ fn roundtrip() {
     let nom = de::OMObject::<OpenMath<'_>>::from_openmath_xml(&xml).expect("works");
     assert_eq!(om, nom);
 }
+
+#[cfg(test)]
+#[cfg(all(feature = "xml-read", feature = "xml-write"))]
+#[test]
+fn inherited_cdbase_roundtrip() {
+    const XML: &str = r#"<OMOBJ version="2.0" xmlns="http://www.openmath.org/OpenMath">
+      <OMA cdbase="http://my.namespace">
+        <OMS cd="utils" name="either"/>
+        <OMS cd="utils" name="left"/>
+        <OMA cdbase="http://www.openmath.org/cd">
+          <OMS cd="arith1" name="plus"/>
+          <OMS cdbase="http://other.namespace" cd="num" name="one"/>
+          <OMATTR>
+            <OMATP>
+              <OMS cdbase="http://my.namespace" cd="utils" name="type"/>
+              <OMS cd="setname1" name="N"/>
+            </OMATP>
+            <OMI>2</OMI>
+          </OMATTR>
+        </OMA>
+        <OMBIND cdbase="http://other.namespace">
+          <OMS cd="fns" name="lambda"/>
+          <OMBVAR><OMV name="x"/></OMBVAR>
+          <OMV name="x"/>
+        </OMBIND>
+      </OMA>
+    </OMOBJ>"#;
+    let om = de::OMObject::<OpenMath<'_>>::from_openmath_xml(XML).expect("works");
+    let OpenMath::OMA { applicant, .. } = &om else {
+        panic!("is an OMA")
+    };
+    assert!(matches!(&**applicant, OpenMath::OMS {
+        cdbase: Some(c),
+        cdbase_inherited: true,
+        ..
+    } if c == "http://my.namespace"));
+
+    let xml = ser::OMObject(&om).xml(false, true).to_string();
+    assert_eq!(
+        xml.replace(|c: char| c.is_ascii_whitespace(), ""),
+        XML.replace(|c: char| c.is_ascii_whitespace(), "")
+    );
+    let nom = de::OMObject::<OpenMath<'_>>::from_openmath_xml(&xml).expect("works");
+    assert_eq!(om, nom);
+
+    let sexpr = om.to_sexpr();
+    assert_eq!(sexpr.matches("cdbase").count(), 5, "{sexpr}");
+    assert_eq!(OpenMath::from_sexpr(&sexpr).expect("works"), om);
+
+    #[cfg(feature = "serde")]
+    {
+        let json = serde_json::to_string(&om.openmath_serde()).expect("works");
+        assert_eq!(json.matches("cdbase").count(), 5, "{json}");
+        let nom = serde_json::from_str::<de::OMFromSerde<OpenMath<'_>>>(&json)
+            .expect("works")
+            .into_inner();
+        assert_eq!(om, nom);
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn cdbase_eq_modulo_inheritance_and_normalization() {
+    use std::hash::BuildHasher;
+    let oms = |cdbase: Option<&'static str>, cdbase_inherited| OpenMath::OMS {
+        cd: "arith1".into(),
+        name: "plus".into(),
+        cdbase: cdbase.map(Into::into),
+        cdbase_inherited,
+        attributes: Vec::new(),
+    };
+    let hasher = std::collections::hash_map::RandomState::new();
+    assert_eq!(oms(Some(CD_BASE), true), oms(Some(CD_BASE), false));
+    assert_eq!(
+        hasher.hash_one(oms(Some(CD_BASE), true)),
+        hasher.hash_one(oms(Some(CD_BASE), false))
+    );
+    assert_ne!(oms(Some(CD_BASE), false), oms(None, false));
+    let spelled = oms(Some("HTTP://WWW.OpenMath.org:80/cd/"), false);
+    assert_eq!(spelled, oms(Some(CD_BASE), true));
+    assert_eq!(
+        hasher.hash_one(&spelled),
+        hasher.hash_one(oms(Some(CD_BASE), true))
+    );
+}
+
+/// Objects of every kind, with every choice of cdbase on each of their symbols (none, the
+/// standard one or another one; given explicitly or inherited), roundtrip through XML and
+/// both JSON forms: all encodings decode to the same object, which roundtrips exactly and
+/// keeps every cdbase that was given.
+#[cfg(test)]
+#[cfg(all(feature = "xml-read", feature = "xml-write", feature = "serde"))]
+#[test]
+#[allow(clippy::too_many_lines)]
+fn cdbase_matrix() {
+    /// A cdbase and whether it is inherited
+    type Base = Option<(&'static str, bool)>;
+    const OTHER: &str = "http://example.org/cd";
+    const BASES: [Base; 5] = [
+        None,
+        Some((CD_BASE, false)),
+        Some((CD_BASE, true)),
+        Some((OTHER, false)),
+        Some((OTHER, true)),
+    ];
+    fn oms(base: Base) -> OpenMath<'static> {
+        OpenMath::OMS {
+            cd: Cow::Borrowed("c"),
+            name: Cow::Borrowed("s"),
+            cdbase: base.map(|(b, _)| Cow::Borrowed(b)),
+            cdbase_inherited: base.is_some_and(|(_, i)| i),
+            attributes: Vec::new(),
+        }
+    }
+    fn attr(
+        key: Base,
+        value: OMMaybeForeign<'static, OpenMath<'static>>,
+    ) -> Attr<'static, OMMaybeForeign<'static, OpenMath<'static>>> {
+        Attr::new(
+            SymbolRef {
+                cdbase: key.map(|(b, _)| Cow::Borrowed(b)),
+                cd: Cow::Borrowed("c"),
+                name: Cow::Borrowed("k"),
+            },
+            value,
+        )
+    }
+    fn ome(base: Base, arguments: Vec<ErrorArg<'static>>) -> OpenMath<'static> {
+        OpenMath::OME {
+            cd: Cow::Borrowed("c"),
+            name: Cow::Borrowed("e"),
+            cdbase: base.map(|(b, _)| Cow::Borrowed(b)),
+            arguments,
+            attributes: Vec::new(),
+        }
+    }
+    fn oma(applicant: OpenMath<'static>, arguments: Vec<OpenMath<'static>>) -> OpenMath<'static> {
+        OpenMath::OMA {
+            applicant: Box::new(applicant),
+            arguments,
+            attributes: Vec::new(),
+        }
+    }
+    fn ombind(
+        binder: OpenMath<'static>,
+        variable: BoundVariable<'static>,
+        object: OpenMath<'static>,
+    ) -> OpenMath<'static> {
+        OpenMath::OMBIND {
+            binder: Box::new(binder),
+            variables: vec![variable],
+            object: Box::new(object),
+            attributes: Vec::new(),
+        }
+    }
+    fn var(
+        attributes: Vec<Attr<'static, OMMaybeForeign<'static, OpenMath<'static>>>>,
+    ) -> OpenMath<'static> {
+        OpenMath::OMV {
+            name: Cow::Borrowed("x"),
+            attributes,
+        }
+    }
+    fn bvar(
+        attributes: Vec<Attr<'static, OMMaybeForeign<'static, OpenMath<'static>>>>,
+    ) -> BoundVariable<'static> {
+        BoundVariable {
+            name: Cow::Borrowed("x"),
+            attributes,
+        }
+    }
+    fn foreign() -> ErrorArg<'static> {
+        OMMaybeForeign::foreign("text/plain", "foreign")
+    }
+    /// Objects with symbols at three positions
+    const CASES: [fn([Base; 3]) -> OpenMath<'static>; 8] = [
+        |[a, b, c]| {
+            let mut om = oms(a);
+            om.attributes_mut()
+                .push(attr(b, OMMaybeForeign::OM(oms(c))));
+            om
+        },
+        |[a, b, c]| oma(oms(a), vec![var(Vec::new()), oms(b), oms(c)]),
+        |[a, b, c]| {
+            ombind(
+                oms(a),
+                bvar(vec![attr(b, OMMaybeForeign::OM(oms(c)))]),
+                var(Vec::new()),
+            )
+        },
+        |[a, b, c]| ombind(oms(a), bvar(Vec::new()), oma(oms(b), vec![oms(c)])),
+        |[a, b, c]| {
+            var(vec![
+                attr(a, OMMaybeForeign::OM(oms(b))),
+                attr(c, foreign()),
+            ])
+        },
+        |[a, b, c]| {
+            ome(
+                a,
+                vec![
+                    OMMaybeForeign::OM(oms(b)),
+                    foreign(),
+                    OMMaybeForeign::OM(oma(oms(c), Vec::new())),
+                ],
+            )
+        },
+        |[a, b, c]| oma(oms(a), vec![ome(b, vec![foreign()]), oms(c)]),
+        |[a, b, c]| {
+            ombind(
+                oms(a),
+                bvar(Vec::new()),
+                ome(b, vec![OMMaybeForeign::OM(ome(c, Vec::new()))]),
+            )
+        },
+    ];
+    /// The cdbases of all symbols and error symbols, in document order; attribute keys only
+    /// have a cdbase if it differs from the one in scope, like symbols in the XML encoding
+    fn cdbases<'a>(om: &'a OpenMath<'_>, out: &mut Vec<Option<&'a str>>) {
+        if let OpenMath::OMS { cdbase, .. } | OpenMath::OME { cdbase, .. } = om {
+            out.push(cdbase.as_deref());
+        }
+        for child in om.children_with_attrs() {
+            cdbases(child, out);
+        }
+    }
+
+    type Encode = fn(&OpenMath<'_>) -> String;
+    fn xml(om: &OpenMath<'_>) -> String {
+        om.xml(false).to_string()
+    }
+    fn json(om: &OpenMath<'_>) -> String {
+        serde_json::to_string(&om.openmath_serde()).expect("works")
+    }
+    fn compact(om: &OpenMath<'_>) -> String {
+        serde_json::to_string(&om.openmath_serde_compact()).expect("works")
+    }
+    fn decode(s: &str) -> OpenMath<'_> {
+        let decoded = if s.starts_with('<') {
+            OpenMath::from_openmath_xml(s).map_err(|e| e.to_string())
+        } else {
+            serde_json::from_str::<de::OMFromSerde<OpenMath<'_>>>(s)
+                .map(de::OMFromSerde::into_inner)
+                .map_err(|e| e.to_string())
+        };
+        decoded.unwrap_or_else(|e| panic!("written object can not be read: {e}\n{s}"))
+    }
+    const ENCODINGS: [(&str, Encode); 3] =
+        [("XML", xml), ("JSON", json), ("compact JSON", compact)];
+
+    let mut count = 0;
+    for case in CASES {
+        for a in BASES {
+            for b in BASES {
+                for c in BASES {
+                    let om = case([a, b, c]);
+                    let written = xml(&om);
+                    let decoded = decode(&written);
+                    for (encoding, encode) in ENCODINGS {
+                        let written = encode(&om);
+                        assert_eq!(
+                            decode(&written),
+                            decoded,
+                            "{encoding} differs from XML for {om:?}"
+                        );
+                        let written = encode(&decoded);
+                        assert_eq!(
+                            decode(&written),
+                            decoded,
+                            "{encoding} does not roundtrip:\n{written}"
+                        );
+                    }
+
+                    let (mut given, mut kept) = (Vec::new(), Vec::new());
+                    cdbases(&om, &mut given);
+                    cdbases(&decoded, &mut kept);
+                    assert_eq!(given.len(), kept.len());
+                    for (given, kept) in given.into_iter().zip(kept) {
+                        if let Some(given) = given {
+                            assert_eq!(kept, Some(given), "cdbase lost in {om:?}:\n{decoded:?}");
+                        }
+                    }
+                    count += 1;
+                }
+            }
+        }
+    }
+    assert_eq!(count, CASES.len() * BASES.len().pow(3));
+}
+
+#[cfg(test)]
+#[cfg(all(feature = "xml-read", feature = "xml-write"))]
+#[test]
+fn attributed_variable_roundtrip() {
+    const XML: &str = r#"<OMOBJ version="2.0" xmlns="http://www.openmath.org/OpenMath">
+      <OMBIND>
+        <OMS cd="fns1" name="lambda"/>
+        <OMBVAR>
+          <OMATTR>
+            <OMATP>
+              <OMS cd="sts" name="type"/>
+              <OMS cd="setname1" name="N"/>
+            </OMATP>
+            <OMV name="x"/>
+          </OMATTR>
+          <OMATTR>
+            <OMATP>
+              <OMS cd="meta" name="note"/>
+              <OMFOREIGN encoding="text/html"><b>y</b></OMFOREIGN>
+            </OMATP>
+            <OMV name="y"/>
+          </OMATTR>
+        </OMBVAR>
+        <OMV name="x"/>
+      </OMBIND>
+    </OMOBJ>"#;
+    let om = de::OMObject::<OpenMath<'_>>::from_openmath_xml(XML).expect("works");
+    let OpenMath::OMBIND { variables, .. } = &om else {
+        panic!("is an OMBIND")
+    };
+    let values = variables
+        .iter()
+        .map(|v| (&*v.name, &v.attributes[0].value))
+        .collect::<Vec<_>>();
+    assert!(matches!(
+        values[..],
+        [
+            ("x", OMMaybeForeign::OM(OpenMath::OMS { name, .. })),
+            ("y", OMMaybeForeign::Foreign { encoding: Some(e), value })
+        ] if name == "N" && e == "text/html" && value == "<b>y</b>"
+    ));
+
+    let xml = ser::OMObject(&om).xml(false, true).to_string();
+    assert_eq!(
+        xml.replace(|c: char| c.is_ascii_whitespace(), ""),
+        XML.replace(|c: char| c.is_ascii_whitespace(), "")
+    );
+    let nom = de::OMObject::<OpenMath<'_>>::from_openmath_xml(&xml).expect("works");
+    assert_eq!(om, nom);
+
+    #[cfg(feature = "serde")]
+    {
+        let json = serde_json::to_string(&om.openmath_serde()).expect("works");
+        assert!(
+            json.contains(r#"{"kind":"OMFOREIGN","foreign":"<b>y</b>","encoding":"text/html"}"#),
+            "{json}"
+        );
+        let nom = serde_json::from_str::<de::OMFromSerde<OpenMath<'_>>>(&json)
+            .expect("works")
+            .into_inner();
+        assert_eq!(om, nom);
+    }
+}
+
+#[cfg(test)]
+#[cfg(all(feature = "xml-read", feature = "xml-write"))]
+const ATTRIBUTED_BIND: &str = r#"<OMOBJ version="2.0" xmlns="http://www.openmath.org/OpenMath">
+  <OMA>
+    <OMS cd="fns1" name="identity"/>
+    <OMATTR>
+      <OMATP>
+        <OMS cd="meta" name="note"/>
+        <OMSTR>outer</OMSTR>
+      </OMATP>
+      <OMATTR>
+        <OMATP>
+          <OMS cd="meta" name="level"/>
+          <OMI>2</OMI>
+        </OMATP>
+        <OMBIND>
+          <OMS cd="fns1" name="lambda"/>
+          <OMBVAR>
+            <OMATTR>
+              <OMATP>
+                <OMS cd="sts" name="type"/>
+                <OMS cd="setname1" name="R"/>
+              </OMATP>
+              <OMV name="x"/>
+            </OMATTR>
+          </OMBVAR>
+          <OMV name="x"/>
+        </OMBIND>
+      </OMATTR>
+    </OMATTR>
+  </OMA>
+</OMOBJ>"#;
+
+#[cfg(test)]
+#[cfg(all(feature = "xml-read", feature = "xml-write"))]
+const ATTRIBUTED_ERROR: &str = r#"<OMOBJ version="2.0" xmlns="http://www.openmath.org/OpenMath">
+  <OMA>
+    <OMS cd="fns1" name="identity"/>
+    <OMATTR>
+      <OMATP>
+        <OMS cd="meta" name="note"/>
+        <OMFOREIGN encoding="text/html"><b>oops</b></OMFOREIGN>
+      </OMATP>
+      <OME>
+        <OMS cd="aritherror" name="DivisionByZero"/>
+        <OMV name="x"/>
+        <OMFOREIGN><x/></OMFOREIGN>
+      </OME>
+    </OMATTR>
+  </OMA>
+</OMOBJ>"#;
+
+#[cfg(test)]
+#[cfg(all(feature = "xml-read", feature = "xml-write"))]
+fn attributed_argument<'a>(om: &'a OpenMath<'a>) -> &'a OpenMath<'a> {
+    let OpenMath::OMA { arguments, .. } = om else {
+        panic!("is an OMA: {om:?}")
+    };
+    &arguments[0]
+}
+
+#[cfg(test)]
+#[cfg(all(feature = "xml-read", feature = "xml-write"))]
+fn check_attributed_bind(om: &OpenMath<'_>) {
+    let OpenMath::OMBIND {
+        variables,
+        attributes,
+        ..
+    } = attributed_argument(om)
+    else {
+        panic!("is an OMBIND: {om:?}")
+    };
+    let keys = attributes.iter().map(Attr::name).collect::<Vec<_>>();
+    assert_eq!(keys, ["note", "level"]);
+    assert_eq!(variables.len(), 1);
+    assert_eq!(variables[0].attributes.len(), 1);
+}
+
+#[cfg(test)]
+#[cfg(all(feature = "xml-read", feature = "xml-write"))]
+fn check_attributed_error(om: &OpenMath<'_>) {
+    let OpenMath::OME {
+        arguments,
+        attributes,
+        ..
+    } = attributed_argument(om)
+    else {
+        panic!("is an OME: {om:?}")
+    };
+    assert!(matches!(
+        &attributes[..],
+        [a @ Attr { value: OMMaybeForeign::Foreign { encoding: Some(e), .. }, .. }]
+            if a.name() == "note" && e == "text/html"
+    ));
+    assert!(matches!(
+        &arguments[..],
+        [
+            OMMaybeForeign::OM(OpenMath::OMV { .. }),
+            OMMaybeForeign::Foreign { encoding: None, .. }
+        ]
+    ));
+}
+
+#[cfg(test)]
+#[cfg(all(feature = "xml-read", feature = "xml-write"))]
+#[test]
+fn attributed_bind_xml_roundtrip() {
+    let om = de::OMObject::<OpenMath<'_>>::from_openmath_xml(ATTRIBUTED_BIND).expect("works");
+    check_attributed_bind(&om);
+    let xml = ser::OMObject(&om).xml(false, true).to_string();
+    let nom = de::OMObject::<OpenMath<'_>>::from_openmath_xml(&xml).expect("works");
+    check_attributed_bind(&nom);
+    assert_eq!(om, nom);
+}
+
+#[cfg(test)]
+#[cfg(all(feature = "xml-read", feature = "xml-write"))]
+#[test]
+fn attributed_error_xml_roundtrip() {
+    let om = de::OMObject::<OpenMath<'_>>::from_openmath_xml(ATTRIBUTED_ERROR).expect("works");
+    check_attributed_error(&om);
+    let xml = ser::OMObject(&om).xml(false, true).to_string();
+    assert_eq!(
+        xml.replace(|c: char| c.is_ascii_whitespace(), ""),
+        ATTRIBUTED_ERROR.replace(|c: char| c.is_ascii_whitespace(), "")
+    );
+    let nom = de::OMObject::<OpenMath<'_>>::from_openmath_xml(&xml).expect("works");
+    assert_eq!(om, nom);
+}
+
+#[cfg(test)]
+#[cfg(all(feature = "xml-read", feature = "xml-write", feature = "serde"))]
+#[test]
+fn attributed_bind_json_roundtrip() {
+    let om = de::OMObject::<OpenMath<'_>>::from_openmath_xml(ATTRIBUTED_BIND).expect("works");
+    let json = serde_json::to_string(&om.openmath_serde()).expect("works");
+    let nom = serde_json::from_str::<de::OMFromSerde<OpenMath<'_>>>(&json)
+        .expect("works")
+        .into_inner();
+    check_attributed_bind(&nom);
+    assert_eq!(om, nom);
+
+    // the attributes may come after the object, and `variables` before the binder
+    let json = r#"{"kind":"OMA","applicant":{"kind":"OMS","cd":"fns1","name":"identity"},
+      "arguments":[{"kind":"OMATTR","object":{"kind":"OMATTR","object":{"kind":"OMBIND",
+      "variables":[{"kind":"OMATTR","object":{"kind":"OMV","name":"x"},"attributes":[[
+        {"kind":"OMS","cd":"sts","name":"type"},{"kind":"OMS","cd":"setname1","name":"R"}]]}],
+      "binder":{"kind":"OMS","cd":"fns1","name":"lambda"},"object":{"kind":"OMV","name":"x"}},
+      "attributes":[[{"kind":"OMS","cd":"meta","name":"level"},{"kind":"OMI","integer":2}]]},
+      "attributes":[[{"kind":"OMS","cd":"meta","name":"note"},{"kind":"OMSTR","string":"outer"}]]}]}"#;
+    let nom = serde_json::from_str::<de::OMFromSerde<OpenMath<'_>>>(json)
+        .expect("works")
+        .into_inner();
+    check_attributed_bind(&nom);
+    assert_eq!(om, nom);
+}
+
+#[cfg(test)]
+#[cfg(all(feature = "xml-read", feature = "xml-write", feature = "serde"))]
+#[test]
+fn attributed_error_json_roundtrip() {
+    let om = de::OMObject::<OpenMath<'_>>::from_openmath_xml(ATTRIBUTED_ERROR).expect("works");
+    let json = serde_json::to_string(&om.openmath_serde()).expect("works");
+    let nom = serde_json::from_str::<de::OMFromSerde<OpenMath<'_>>>(&json)
+        .expect("works")
+        .into_inner();
+    check_attributed_error(&nom);
+    assert_eq!(om, nom);
+
+    let json = r#"{"kind":"OMA","applicant":{"kind":"OMS","cd":"fns1","name":"identity"},
+      "arguments":[{"kind":"OMATTR","object":{"kind":"OME","arguments":[{"kind":"OMV","name":"x"},
+        {"kind":"OMFOREIGN","foreign":"<x/>"}],
+        "error":{"kind":"OMS","cd":"aritherror","name":"DivisionByZero"}},
+      "attributes":[[{"kind":"OMS","cd":"meta","name":"note"},
+        {"kind":"OMFOREIGN","foreign":"<b>oops</b>","encoding":"text/html"}]]}]}"#;
+    let nom = serde_json::from_str::<de::OMFromSerde<OpenMath<'_>>>(json)
+        .expect("works")
+        .into_inner();
+    check_attributed_error(&nom);
+    assert_eq!(om, nom);
+}
+
+#[cfg(test)]
+#[cfg(feature = "xml-read")]
+#[test]
+fn omr_references() {
+    #[derive(Debug, Default, PartialEq, Eq)]
+    struct Refs(Vec<String>);
+    impl<'de> de::OMDeserializable<'de> for Refs {
+        type Ret = Self;
+        type Err = &'static str;
+        fn from_openmath(om: OM<'de, Self>, _: &str) -> Result<Self, Self::Err> {
+            Ok(match om {
+                OM::OMR { href, attrs } => Self(
+                    std::iter::once(href.into_owned())
+                        .chain(attrs.into_iter().map(|a| a.key.to_string()))
+                        .collect(),
+                ),
+                OM::OMA {
+                    applicant,
+                    arguments,
+                    ..
+                } => Self(
+                    std::iter::once(applicant)
+                        .chain(arguments)
+                        .flat_map(|r| r.0)
+                        .collect(),
+                ),
+                _ => Self::default(),
+            })
+        }
+    }
+    let expected = Refs(vec!["#x".into(), "#y".into(), "meta#note".into()]);
+
+    let xml = r##"<OMA>
+      <OMS cd="list1" name="list"/>
+      <OMR href="#x"/>
+      <OMATTR><OMATP><OMS cd="meta" name="note"/><OMSTR/></OMATP><OMR href="#y"/></OMATTR>
+    </OMA>"##;
+    assert_eq!(Refs::from_openmath_xml(xml).expect("is valid"), expected);
+    assert!(matches!(
+        OpenMath::from_openmath_xml(xml),
+        Err(de::XmlReadError::Conversion(de::ReferenceError::Unresolved(de::UnresolvedReference(href)))) if href == "#x"
+    ));
+    let policy = de::OMRPolicy::resolve(|href, _| {
+        Some(OpenMath::OMSTR {
+            string: Cow::Owned(href.to_string()),
+            attributes: Vec::new(),
+        })
+    });
+    let options = de::Options::new().with_omr_policy(&policy);
+    let om = OpenMath::from_openmath_xml_with(xml, options).expect("is resolved");
+    let OpenMath::OMA { arguments, .. } = &om else {
+        panic!("is an OMA")
+    };
+    assert!(
+        matches!(&arguments[1], OpenMath::OMSTR { string, attributes } if string == "#y" && attributes.len() == 1)
+    );
+    // resolved before any type sees them
+    let refs = Refs::from_openmath_xml_with(xml, options).expect("is resolved");
+    assert_eq!(refs, Refs::default());
+    let (arena, root) = arena::OmArena::from_openmath_xml_with(xml, options).expect("is resolved");
+    assert_eq!(arena.to_openmath(root), om);
+    #[cfg(feature = "bump")]
+    {
+        let arena = de::Arena::new();
+        let resolved = arena
+            .from_openmath_xml_with(xml, options)
+            .expect("is resolved");
+        assert_eq!(resolved.to_openmath(), om);
+    }
+
+    #[cfg(feature = "serde")]
+    {
+        use serde::de::DeserializeSeed;
+        const JSON: &str = r##"{ "kind": "OMA",
+          "applicant": { "kind": "OMS", "cd": "list1", "name": "list" },
+          "arguments": [
+            { "kind": "OMR", "href": "#x" },
+            { "kind": "OMATTR",
+              "attributes": [[{ "kind": "OMS", "cd": "meta", "name": "note" }, { "kind": "OMSTR", "string": "" }]],
+              "object": { "kind": "OMR", "href": "#y" } }
+          ] }"##;
+        let refs = serde_json::from_str::<de::OMFromSerde<Refs>>(JSON).expect("is valid");
+        assert_eq!(refs.into_inner(), expected);
+        let r = serde_json::from_str::<de::OMFromSerde<Refs>>(r##"["OMR", null, "#x"]"##)
+            .expect("is valid");
+        assert_eq!(r.into_inner(), Refs(vec!["#x".into()]));
+        let resolved = de::OMFromSerde::<OpenMath>::with_options(options)
+            .deserialize(&mut serde_json::Deserializer::from_str(JSON))
+            .expect("is resolved");
+        assert_eq!(resolved.into_inner(), om);
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn kind_attributes_children() {
+    fn attributed(sexpr: &str) -> String {
+        format!(r#"(omattr (((oms meta note) (omstr "note"))) {sexpr})"#)
+    }
+    let leafs = [
+        ("(omi 1)", OMKind::OMI),
+        ("(omf 1.5)", OMKind::OMF),
+        (r#"(omstr "s")"#, OMKind::OMSTR),
+        (r#"(omb "AQI=")"#, OMKind::OMB),
+        ("(omv x)", OMKind::OMV),
+        ("(oms arith1 plus)", OMKind::OMS),
+    ];
+    for (sexpr, kind) in leafs {
+        let mut om = OpenMath::from_sexpr(sexpr).expect("is valid");
+        assert_eq!(om.kind(), kind);
+        assert!(om.attributes().is_empty());
+        assert_eq!(om.children().count(), 0);
+        assert_eq!(om.children_with_attrs().count(), 0);
+
+        let attributed = OpenMath::from_sexpr(&attributed(sexpr)).expect("is valid");
+        assert_eq!(attributed.kind(), kind);
+        assert_eq!(attributed.attributes().len(), 1);
+        assert_eq!(attributed.children().count(), 0);
+        let values = attributed.children_with_attrs().collect::<Vec<_>>();
+        assert!(matches!(values[..], [OpenMath::OMSTR { .. }]));
+
+        om.attributes_mut()
+            .extend_from_slice(attributed.attributes());
+        assert_eq!(om, attributed);
+    }
+
+    let kinds =
+        |it: &mut dyn Iterator<Item = &OpenMath<'_>>| it.map(OpenMath::kind).collect::<Vec<_>>();
+    let oma =
+        OpenMath::from_sexpr(&attributed("(oma (omv f) (omi 1) (omf 2.0))")).expect("is valid");
+    assert_eq!(oma.kind(), OMKind::OMA);
+    assert_eq!(
+        kinds(&mut oma.children()),
+        [OMKind::OMV, OMKind::OMI, OMKind::OMF]
+    );
+    assert_eq!(
+        kinds(&mut oma.children_with_attrs()),
+        [OMKind::OMSTR, OMKind::OMV, OMKind::OMI, OMKind::OMF]
+    );
+
+    let ome = OpenMath::from_sexpr(&attributed(
+        r#"(ome (oms error unhandled) (omi 1) (omforeign "<x/>") (omv y))"#,
+    ))
+    .expect("is valid");
+    assert_eq!(ome.kind(), OMKind::OME);
+    assert_eq!(kinds(&mut ome.children()), [OMKind::OMI, OMKind::OMV]);
+    assert_eq!(
+        kinds(&mut ome.children_with_attrs()),
+        [OMKind::OMSTR, OMKind::OMI, OMKind::OMV]
+    );
+
+    let ombind = OpenMath::from_sexpr(&attributed(
+        "(ombind (oms fns1 lambda) (bvar (omattr (((oms sts type) (oms setname1 R))) x) y) (omv x))",
+    ))
+    .expect("is valid");
+    assert_eq!(ombind.kind(), OMKind::OMBIND);
+    assert_eq!(kinds(&mut ombind.children()), [OMKind::OMS, OMKind::OMV]);
+    assert_eq!(
+        kinds(&mut ombind.children_with_attrs()),
+        [OMKind::OMSTR, OMKind::OMS, OMKind::OMS, OMKind::OMV]
+    );
+    let mut ombind = ombind;
+    ombind.attributes_mut().clear();
+    assert!(ombind.attributes().is_empty());
+    assert_eq!(ombind.children_with_attrs().count(), 3);
+}
+
+#[cfg(test)]
+#[test]
+fn checked_uris() {
+    use ser::Uri;
+    let check = |cdbase, cd, name| std::panic::catch_unwind(|| Uri::new_checked(cdbase, cd, name));
+    for (cdbase, cd, name) in [
+        (None, "arith1", "plus"),
+        (Some("http://example.org"), "my-cd", "_op.2"),
+        (Some("urn:x"), "dé", "ünïcode"),
+    ] {
+        assert!(check(cdbase, cd, name).is_ok());
+    }
+    for (cdbase, cd, name) in [
+        (None, "", "plus"),
+        (None, "arith1", "1plus"),
+        (None, "arith 1", "plus"),
+        (None, "arith1", "-plus"),
+        (Some(""), "arith1", "plus"),
+        (Some("relative/path"), "arith1", "plus"),
+        (Some("http://exa mple.org"), "arith1", "plus"),
+    ] {
+        assert!(check(cdbase, cd, name).is_err());
+    }
+    assert_eq!(
+        uri!("HTTP://Example.ORG:80/" / "cd" # "x").to_string_full(),
+        "http://example.org/cd#x"
+    );
+}
+
+#[cfg(all(test, feature = "xml"))]
+#[test]
+fn presentation_mathml_roundtrip() {
+    const XML: &str = r#"<OMOBJ version="2.0" xmlns="http://www.openmath.org/OpenMath"><OMATTR><OMATP><OMS cd="altenc" name="MathML_Presentation"/><OMFOREIGN encoding="MathML-Presentation"><math xmlns="http://www.w3.org/1998/Math/MathML" display="inline"><msup><mi>x</mi><mn>2</mn></msup></math></OMFOREIGN></OMATP><OMA><OMS cd="arith1" name="power"/><OMV name="x"/><OMI>2</OMI></OMA></OMATTR></OMOBJ>"#;
+    const MATHML: &str = r#"<math xmlns="http://www.w3.org/1998/Math/MathML" display="inline"><msup><mi>x</mi><mn>2</mn></msup></math>"#;
+
+    let om = de::OMObject::<OpenMath>::from_openmath_xml(XML).expect("is valid");
+    assert_eq!(om.presentation(), Some(MATHML));
+    assert_eq!(om.omobject().xml(false, true).to_string(), XML);
+
+    let mut built =
+        OpenMath::from_sexpr("(oma (oms arith1 power) (omv x) (omi 2))").expect("is valid");
+    built
+        .attributes_mut()
+        .push(Attr::presentation_mathml(MATHML));
+    assert_eq!(om, built);
+
+    #[cfg(feature = "serde_json")]
+    {
+        let json = serde_json::to_string(&om.omobject()).expect("serializable");
+        let back = serde_json::from_str::<de::OMObject<OpenMath>>(&json)
+            .expect("is valid")
+            .into_inner();
+        assert_eq!(back.presentation(), Some(MATHML));
+        assert_eq!(back.omobject().xml(false, true).to_string(), XML);
+        assert_eq!(
+            serde_json::to_string(&back.omobject()).expect("serializable"),
+            json
+        );
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn parts_mut() {
+    let mut ombind =
+        OpenMath::from_sexpr("(ombind (oms fns1 lambda) (bvar x) (omv x))").expect("is valid");
+    assert!(ombind.oma_parts_mut().is_none() && ombind.ome_parts_mut().is_none());
+    let (binder, variables, body, attributes) = ombind.ombind_parts_mut().expect("is an OMBIND");
+    variables[0].name = "y".into();
+    *body = OpenMath::from_sexpr("(omv y)").expect("is valid");
+    attributes.push(Attr::presentation_mathml("<mi>f</mi>"));
+    assert_eq!(binder.kind(), OMKind::OMS);
+    assert_eq!(ombind.presentation(), Some("<mi>f</mi>"));
+    ombind.attributes_mut().clear();
+    assert_eq!(
+        ombind,
+        OpenMath::from_sexpr("(ombind (oms fns1 lambda) (bvar y) (omv y))").expect("is valid")
+    );
+    assert!(ombind.take_arguments().is_empty());
+
+    let mut ome =
+        OpenMath::from_sexpr(r#"(ome (oms moreerrors unexpected) (omi 1) (omforeign "<x/>"))"#)
+            .expect("is valid");
+    let (arguments, _) = ome.ome_parts_mut().expect("is an OME");
+    arguments.pop();
+    assert_eq!(ome.children_with_attrs().count(), 1);
+    assert!(ome.take_arguments().is_empty());
+}
@@ -0,0 +1,9 @@
+/*! Support for converting between <span style="font-variant:small-caps;">OpenMath</span> and
+[MathML](https://www.w3.org/TR/MathML3/); see [`symbols`] for the standard correspondence
+between symbols and Content MathML elements.
+
+Presentation MathML attached to objects is covered by
+[`Attr::presentation_mathml`](crate::Attr::presentation_mathml).
+*/
+
+pub mod symbols;
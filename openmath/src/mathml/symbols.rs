@@ -0,0 +1,501 @@
+/*! The correspondence between the symbols of the
+[MathML CD group](https://openmath.org/cdgroups/mathml.html) and the Content MathML
+elements that stand for them, e.g. `arith1#plus` and `<plus/>`, as specified in
+[chapter 4 of MathML 3](https://www.w3.org/TR/MathML3/chapter4.html).
+
+Some elements stand for several symbols:
+- an attribute selects among them, e.g. `<interval closure="open">` is `interval1#interval_oo`
+  (see [`Mapping::attribute`]);
+- otherwise, the arguments do: `<minus/>` applied to a single argument is
+  `arith1#unary_minus`, `<int/>` with bounds is `calculus1#defint`, and `<selector/>` applied
+  to a matrix is `linalg1#matrix_selector`. [`by_element`] returns the
+  [default](SYMBOLS) among them, for the binary, indefinite or vector case, respectively.
+
+# Examples
+```
+use openmath::{SymbolRef, mathml::symbols};
+
+let plus = symbols::by_element("plus", &[]).expect("is a MathML operator");
+assert_eq!((plus.cd, plus.name), ("arith1", "plus"));
+
+let sin = SymbolRef::new("transc1", "sin");
+assert_eq!(symbols::by_symbol(&sin).map(|m| m.element), Some("sin"));
+
+let open = symbols::by_element("interval", &[("closure", "open")]).expect("is known");
+assert_eq!(open.name, "interval_oo");
+assert_eq!(symbols::by_element("interval", &[]).map(|m| m.name), Some("interval_cc"));
+```
+*/
+
+use crate::{SymbolRef, ser::Uri};
+
+/// An entry of [`SYMBOLS`]: a Content MathML element and the symbol it stands for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Mapping {
+    /// The name of the element, e.g. `plus` for `<plus/>`
+    pub element: &'static str,
+    /// The attribute (name and value) that selects this symbol among those the element
+    /// stands for, e.g. `closure="open"` for `<interval>`; `None` for the default
+    pub attribute: Option<(&'static str, &'static str)>,
+    /// The content dictionary of the symbol
+    pub cd: &'static str,
+    /// The name of the symbol
+    pub name: &'static str,
+}
+impl Mapping {
+    /// The symbol, in the standard cdbase ([`CD_BASE`](crate::CD_BASE))
+    #[inline]
+    #[must_use]
+    pub const fn uri(&self) -> Uri<'static> {
+        Uri {
+            cdbase: Some(crate::CdBase::OPENMATH),
+            cd: self.cd,
+            name: self.name,
+        }
+    }
+}
+
+/// Shorthand for the entries of [`SYMBOLS`]
+const fn m(element: &'static str, cd: &'static str, name: &'static str) -> Mapping {
+    Mapping {
+        element,
+        attribute: None,
+        cd,
+        name,
+    }
+}
+
+/// Shorthand for the entries of [`SYMBOLS`] selected by an attribute
+const fn a(
+    element: &'static str,
+    attribute: (&'static str, &'static str),
+    cd: &'static str,
+    name: &'static str,
+) -> Mapping {
+    Mapping {
+        element,
+        attribute: Some(attribute),
+        cd,
+        name,
+    }
+}
+
+/** All elements and the symbols they stand for, grouped by content dictionary (in the order
+of the MathML CD group). Every symbol occurs once; an element standing for several symbols
+occurs once for each; its default is the first entry without an [`attribute`](Mapping::attribute),
+or the first entry if all have one.
+*/
+pub const SYMBOLS: &[Mapping] = &[
+    // arith1
+    m("abs", "arith1", "abs"),
+    m("divide", "arith1", "divide"),
+    m("gcd", "arith1", "gcd"),
+    m("lcm", "arith1", "lcm"),
+    m("minus", "arith1", "minus"),
+    m("minus", "arith1", "unary_minus"),
+    m("plus", "arith1", "plus"),
+    m("power", "arith1", "power"),
+    m("product", "arith1", "product"),
+    m("root", "arith1", "root"),
+    m("sum", "arith1", "sum"),
+    m("times", "arith1", "times"),
+    // calculus1
+    m("diff", "calculus1", "diff"),
+    m("int", "calculus1", "int"),
+    m("int", "calculus1", "defint"),
+    m("partialdiff", "calculus1", "partialdiff"),
+    // complex1
+    m("arg", "complex1", "argument"),
+    m("conjugate", "complex1", "conjugate"),
+    m("imaginary", "complex1", "imaginary"),
+    m("real", "complex1", "real"),
+    // fns1
+    m("codomain", "fns1", "range"),
+    m("compose", "fns1", "left_compose"),
+    m("domain", "fns1", "domain"),
+    m("ident", "fns1", "identity"),
+    m("image", "fns1", "image"),
+    m("inverse", "fns1", "left_inverse"),
+    m("lambda", "fns1", "lambda"),
+    // integer1
+    m("factorial", "integer1", "factorial"),
+    m("factorof", "integer1", "factorof"),
+    m("quotient", "integer1", "quotient"),
+    m("rem", "integer1", "remainder"),
+    // interval1
+    a(
+        "interval",
+        ("closure", "closed"),
+        "interval1",
+        "interval_cc",
+    ),
+    a("interval", ("closure", "open"), "interval1", "interval_oo"),
+    a(
+        "interval",
+        ("closure", "open-closed"),
+        "interval1",
+        "interval_oc",
+    ),
+    a(
+        "interval",
+        ("closure", "closed-open"),
+        "interval1",
+        "interval_co",
+    ),
+    // limit1
+    m("limit", "limit1", "limit"),
+    // linalg1
+    m("determinant", "linalg1", "determinant"),
+    m("outerproduct", "linalg1", "outerproduct"),
+    m("scalarproduct", "linalg1", "scalarproduct"),
+    m("selector", "linalg1", "vector_selector"),
+    m("selector", "linalg1", "matrix_selector"),
+    m("transpose", "linalg1", "transpose"),
+    m("vectorproduct", "linalg1", "vectorproduct"),
+    // linalg2
+    m("matrix", "linalg2", "matrix"),
+    m("matrixrow", "linalg2", "matrixrow"),
+    m("vector", "linalg2", "vector"),
+    // list1
+    m("list", "list1", "list"),
+    // logic1
+    m("and", "logic1", "and"),
+    m("equivalent", "logic1", "equivalent"),
+    m("false", "logic1", "false"),
+    m("implies", "logic1", "implies"),
+    m("not", "logic1", "not"),
+    m("or", "logic1", "or"),
+    m("true", "logic1", "true"),
+    m("xor", "logic1", "xor"),
+    // minmax1
+    m("max", "minmax1", "max"),
+    m("min", "minmax1", "min"),
+    // multiset1
+    a("set", ("type", "multiset"), "multiset1", "multiset"),
+    // nums1
+    m("eulergamma", "nums1", "gamma"),
+    m("exponentiale", "nums1", "e"),
+    m("imaginaryi", "nums1", "i"),
+    m("infinity", "nums1", "infinity"),
+    m("notanumber", "nums1", "NaN"),
+    m("pi", "nums1", "pi"),
+    // piece1
+    m("otherwise", "piece1", "otherwise"),
+    m("piece", "piece1", "piece"),
+    m("piecewise", "piece1", "piecewise"),
+    // quant1
+    m("exists", "quant1", "exists"),
+    m("forall", "quant1", "forall"),
+    // relation1
+    m("approx", "relation1", "approx"),
+    m("eq", "relation1", "eq"),
+    m("geq", "relation1", "geq"),
+    m("gt", "relation1", "gt"),
+    m("leq", "relation1", "leq"),
+    m("lt", "relation1", "lt"),
+    m("neq", "relation1", "neq"),
+    // rounding1
+    m("ceiling", "rounding1", "ceiling"),
+    m("floor", "rounding1", "floor"),
+    // s_data1
+    m("mean", "s_data1", "mean"),
+    m("median", "s_data1", "median"),
+    m("mode", "s_data1", "mode"),
+    m("moment", "s_data1", "moment"),
+    m("sdev", "s_data1", "sdev"),
+    m("variance", "s_data1", "variance"),
+    // set1
+    m("card", "set1", "size"),
+    m("cartesianproduct", "set1", "cartesian_product"),
+    m("emptyset", "set1", "emptyset"),
+    m("in", "set1", "in"),
+    m("intersect", "set1", "intersect"),
+    m("notin", "set1", "notin"),
+    m("notprsubset", "set1", "notprsubset"),
+    m("notsubset", "set1", "notsubset"),
+    m("prsubset", "set1", "prsubset"),
+    m("set", "set1", "set"),
+    m("setdiff", "set1", "setdiff"),
+    m("subset", "set1", "subset"),
+    m("union", "set1", "union"),
+    // setname1
+    m("complexes", "setname1", "C"),
+    m("integers", "setname1", "Z"),
+    m("naturalnumbers", "setname1", "N"),
+    m("primes", "setname1", "P"),
+    m("rationals", "setname1", "Q"),
+    m("reals", "setname1", "R"),
+    // transc1
+    m("arccos", "transc1", "arccos"),
+    m("arccosh", "transc1", "arccosh"),
+    m("arccot", "transc1", "arccot"),
+    m("arccoth", "transc1", "arccoth"),
+    m("arccsc", "transc1", "arccsc"),
+    m("arccsch", "transc1", "arccsch"),
+    m("arcsec", "transc1", "arcsec"),
+    m("arcsech", "transc1", "arcsech"),
+    m("arcsin", "transc1", "arcsin"),
+    m("arcsinh", "transc1", "arcsinh"),
+    m("arctan", "transc1", "arctan"),
+    m("arctanh", "transc1", "arctanh"),
+    m("cos", "transc1", "cos"),
+    m("cosh", "transc1", "cosh"),
+    m("cot", "transc1", "cot"),
+    m("coth", "transc1", "coth"),
+    m("csc", "transc1", "csc"),
+    m("csch", "transc1", "csch"),
+    m("exp", "transc1", "exp"),
+    m("ln", "transc1", "ln"),
+    m("log", "transc1", "log"),
+    m("sec", "transc1", "sec"),
+    m("sech", "transc1", "sech"),
+    m("sin", "transc1", "sin"),
+    m("sinh", "transc1", "sinh"),
+    m("tan", "transc1", "tan"),
+    m("tanh", "transc1", "tanh"),
+    // veccalc1
+    m("curl", "veccalc1", "curl"),
+    m("divergence", "veccalc1", "divergence"),
+    m("grad", "veccalc1", "grad"),
+    m("laplacian", "veccalc1", "Laplacian"),
+];
+
+/// The symbol that the Content MathML element `element` stands for.
+///
+/// That is the entry selected by one of the `attributes` (names and values), if any, and
+/// the default one otherwise; see the [module documentation](self).
+#[must_use]
+pub fn by_element(element: &str, attributes: &[(&str, &str)]) -> Option<&'static Mapping> {
+    let mut entries = SYMBOLS.iter().filter(|m| m.element == element);
+    let default = entries
+        .clone()
+        .find(|m| m.attribute.is_none())
+        .or_else(|| entries.clone().next());
+    entries
+        .find(|m| m.attribute.is_some_and(|a| attributes.contains(&a)))
+        .or(default)
+}
+
+/// The element that `symbol` corresponds to, if it is in the MathML CD group; `symbol`
+/// has to be in the standard cdbase (or have none).
+#[must_use]
+pub fn by_symbol(symbol: &SymbolRef<'_>) -> Option<&'static Mapping> {
+    if symbol
+        .cdbase()
+        .is_some_and(|b| !crate::cdbase::eq_normalized(b, crate::CD_BASE))
+    {
+        return None;
+    }
+    SYMBOLS
+        .iter()
+        .find(|m| m.cd == symbol.cd() && m.name == symbol.name())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SYMBOLS, by_element, by_symbol};
+    use crate::SymbolRef;
+
+    /// The operator and constant elements of Content MathML 3, by section of chapter 4.4
+    /// ("Content MathML for Specific Operators and Constants"); `interval` and `set` are
+    /// containers there, but are listed here since they stand for symbols as well
+    const SPEC: &[&[&str]] = &[
+        // 4.4.1 Functions and Inverses
+        &[
+            "interval",
+            "inverse",
+            "lambda",
+            "compose",
+            "ident",
+            "domain",
+            "codomain",
+            "image",
+            "piecewise",
+            "piece",
+            "otherwise",
+        ],
+        // 4.4.2 Arithmetic, Algebra and Logic
+        &[
+            "quotient",
+            "factorial",
+            "divide",
+            "max",
+            "min",
+            "minus",
+            "plus",
+            "power",
+            "rem",
+            "times",
+            "root",
+            "gcd",
+            "and",
+            "or",
+            "xor",
+            "not",
+            "implies",
+            "forall",
+            "exists",
+            "abs",
+            "conjugate",
+            "arg",
+            "real",
+            "imaginary",
+            "lcm",
+            "floor",
+            "ceiling",
+        ],
+        // 4.4.3 Relations
+        &[
+            "eq",
+            "neq",
+            "gt",
+            "lt",
+            "geq",
+            "leq",
+            "equivalent",
+            "approx",
+            "factorof",
+        ],
+        // 4.4.4 Calculus and Vector Calculus
+        &[
+            "int",
+            "diff",
+            "partialdiff",
+            "divergence",
+            "grad",
+            "curl",
+            "laplacian",
+        ],
+        // 4.4.6 Theory of Sets
+        &[
+            "set",
+            "list",
+            "union",
+            "intersect",
+            "in",
+            "notin",
+            "subset",
+            "prsubset",
+            "notsubset",
+            "notprsubset",
+            "setdiff",
+            "card",
+            "cartesianproduct",
+        ],
+        // 4.4.7 Sequences and Series
+        &["sum", "product", "limit"],
+        // 4.4.8 Elementary Classical Functions
+        &[
+            "sin", "cos", "tan", "sec", "csc", "cot", "sinh", "cosh", "tanh", "sech", "csch",
+            "coth", "arcsin", "arccos", "arctan", "arccosh", "arccot", "arccoth", "arccsc",
+            "arccsch", "arcsec", "arcsech", "arcsinh", "arctanh", "exp", "ln", "log",
+        ],
+        // 4.4.9 Statistics
+        &["mean", "sdev", "variance", "median", "mode", "moment"],
+        // 4.4.10 Linear Algebra
+        &[
+            "vector",
+            "matrix",
+            "matrixrow",
+            "determinant",
+            "transpose",
+            "selector",
+            "vectorproduct",
+            "scalarproduct",
+            "outerproduct",
+        ],
+        // 4.4.12 Constant and Symbol Elements
+        &[
+            "integers",
+            "reals",
+            "rationals",
+            "naturalnumbers",
+            "complexes",
+            "primes",
+            "exponentiale",
+            "imaginaryi",
+            "notanumber",
+            "true",
+            "false",
+            "emptyset",
+            "pi",
+            "eulergamma",
+            "infinity",
+        ],
+    ];
+
+    #[test]
+    fn complete() {
+        for element in SPEC.iter().copied().flatten() {
+            assert!(by_element(element, &[]).is_some(), "{element} is missing");
+        }
+        // and nothing else
+        for m in SYMBOLS {
+            assert!(
+                SPEC.iter().any(|s| s.contains(&m.element)),
+                "{} is not in the specification",
+                m.element
+            );
+        }
+    }
+
+    #[test]
+    fn bijective() {
+        for (i, m) in SYMBOLS.iter().enumerate() {
+            // every symbol occurs once
+            let symbol = SymbolRef::new(m.cd, m.name);
+            assert_eq!(by_symbol(&symbol), Some(m));
+            assert!(
+                SYMBOLS[i + 1..]
+                    .iter()
+                    .all(|n| (n.cd, n.name) != (m.cd, m.name)),
+                "{}#{} occurs twice",
+                m.cd,
+                m.name
+            );
+            // and can be selected, unless it depends on the arguments
+            let attributes = m.attribute.as_slice();
+            let selected = by_element(m.element, attributes).expect("is known");
+            assert!(
+                selected == m || attributes.is_empty(),
+                "{}#{}",
+                m.cd,
+                m.name
+            );
+        }
+    }
+
+    #[test]
+    fn lookups() {
+        for (element, cd, name) in [
+            ("plus", "arith1", "plus"),
+            ("sin", "transc1", "sin"),
+            ("forall", "quant1", "forall"),
+            ("minus", "arith1", "minus"),
+            ("int", "calculus1", "int"),
+            ("selector", "linalg1", "vector_selector"),
+        ] {
+            let m = by_element(element, &[]).expect("is known");
+            assert_eq!((m.cd, m.name), (cd, name));
+        }
+        assert_eq!(
+            by_symbol(&SymbolRef::new("arith1", "unary_minus")).map(|m| m.element),
+            Some("minus")
+        );
+        assert_eq!(
+            by_element("set", &[("type", "multiset")]).map(|m| m.cd),
+            Some("multiset1")
+        );
+        assert_eq!(
+            by_element("set", &[("type", "normal")]).map(|m| m.cd),
+            Some("set1")
+        );
+        assert_eq!(by_element("mi", &[]), None);
+        assert_eq!(by_symbol(&SymbolRef::new("arith1", "plus_not")), None);
+        // other cdbases are other symbols
+        let symbol = SymbolRef::with_cdbase("http://example.org", "arith1", "plus");
+        assert_eq!(by_symbol(&symbol), None);
+        let symbol = SymbolRef::with_cdbase("HTTP://www.OpenMath.org/cd", "arith1", "plus");
+        assert_eq!(by_symbol(&symbol).map(|m| m.element), Some("plus"));
+    }
+}
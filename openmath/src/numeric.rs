@@ -0,0 +1,550 @@
+/*! Exact numbers as the standard content dictionaries define them: [`Rational`]s
+(`nums1.rational`) and [`Complex`] numbers (`complex1.complex_cartesian`) with rational
+parts.
+
+[`OpenMath::as_rational`] and [`OpenMath::as_complex`] read them from
+[`OMI`](OpenMath::OMI)s, [`OMF`](OpenMath::OMF)s (whose values are dyadic fractions, so they
+convert exactly) and the applications of the respective symbols; [`Rational::to_openmath`]
+and [`Complex::to_openmath`] (or their [`OMSerializable`] implementations) write the
+standard forms back. [`NumericError`] tells objects that are no numbers apart from numbers
+that do not fit into an [`i128`] fraction.
+
+# Examples
+```rust
+use openmath::{OpenMath, numeric::{NumericError, Rational}};
+
+let om = OpenMath::from_sexpr("(oma (oms nums1 rational) (omi 6) (omi -4))").expect("is valid");
+let r = om.as_rational().expect("is a rational");
+assert_eq!((r.numer(), r.denom()), (-3, 2));
+assert_eq!(r.to_openmath(), OpenMath::from_sexpr("(oma (oms nums1 rational) (omi -3) (omi 2))").expect("is valid"));
+
+let om = OpenMath::from_sexpr("(omf 0.375)").expect("is valid");
+assert_eq!(om.as_rational(), Ok(Rational::new(3, 8).expect("is valid")));
+
+let om = OpenMath::from_sexpr(r#"(omstr "3/8")"#).expect("is valid");
+assert!(matches!(om.as_rational(), Err(NumericError::NotNumeric(_))));
+let om = OpenMath::from_sexpr("(omi 1000000000000000000000000000000000000000)").expect("is valid");
+assert!(matches!(om.as_rational(), Err(NumericError::OutOfRange(_))));
+```
+*/
+
+use std::borrow::Cow;
+
+use crate::{
+    Int, OpenMath,
+    ser::{AsOMS, OMSerializable, OMSerializer, Uri},
+};
+
+const RATIONAL: Uri<'static> = Uri {
+    cdbase: Some(crate::CdBase::OPENMATH),
+    cd: "nums1",
+    name: "rational",
+};
+const COMPLEX_CARTESIAN: Uri<'static> = Uri {
+    cdbase: Some(crate::CdBase::OPENMATH),
+    cd: "complex1",
+    name: "complex_cartesian",
+};
+
+/// Errors when reading a [`Rational`] or [`Complex`] number from an [`OpenMath`] object
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum NumericError {
+    /// The object is not a number of a supported form; the value describes the expected forms
+    #[error("not a numeric term; expected {0}")]
+    NotNumeric(&'static str),
+    /// The object is a number, but not one of the target type: an integer beyond an
+    /// [`i128`], a non-finite float, or a fraction whose lowest terms do not fit
+    #[error("{0} out of range")]
+    OutOfRange(String),
+    /// A `nums1.rational` with denominator zero
+    #[error("zero denominator in nums1#rational")]
+    ZeroDenominator,
+}
+
+const EXPECTED_RATIONAL: &str = "an OMI, an OMF or nums1#rational of two OMIs";
+const EXPECTED_COMPLEX: &str = "a rational number or complex1#complex_cartesian of two";
+
+/// A fraction of [`i128`]s in lowest terms, with a positive denominator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rational {
+    numer: i128,
+    denom: i128,
+}
+
+impl Rational {
+    /// Zero
+    pub const ZERO: Self = Self::from_integer(0);
+
+    /// `numer/denom` in lowest terms; `None` if `denom` is zero or the result does not fit
+    /// (only `i128::MIN` over a negative odd number does not)
+    #[must_use]
+    pub fn new(numer: i128, denom: i128) -> Option<Self> {
+        if denom == 0 {
+            return None;
+        }
+        let (n, d) = (numer.unsigned_abs(), denom.unsigned_abs());
+        let mut gcd = (n, d);
+        while gcd.1 != 0 {
+            gcd = (gcd.1, gcd.0 % gcd.1);
+        }
+        let (n, d) = (n / gcd.0, d / gcd.0);
+        let numer = if (numer < 0) == (denom < 0) {
+            i128::try_from(n).ok()?
+        } else {
+            0i128.checked_sub_unsigned(n)?
+        };
+        Some(Self {
+            numer,
+            denom: i128::try_from(d).ok()?,
+        })
+    }
+
+    /// The integer `value`
+    #[inline]
+    #[must_use]
+    pub const fn from_integer(value: i128) -> Self {
+        Self {
+            numer: value,
+            denom: 1,
+        }
+    }
+
+    /** The exact value of `value`; `None` if it is not finite or does not fit: from
+    ±2<sup>127</sup> on, or with a denominator of 2<sup>127</sup> or more.
+
+    # Examples
+    ```rust
+    use openmath::numeric::Rational;
+
+    assert_eq!(Rational::from_f64(-0.75), Rational::new(-3, 4));
+    // the float closest to 0.1
+    assert_eq!(
+        Rational::from_f64(0.1),
+        Rational::new(3_602_879_701_896_397, 36_028_797_018_963_968)
+    );
+    assert_eq!(Rational::from_f64(f64::NAN), None);
+    assert_eq!(Rational::from_f64(1e300), None);
+    ```
+    */
+    #[must_use]
+    pub fn from_f64(value: f64) -> Option<Self> {
+        if !value.is_finite() {
+            return None;
+        }
+        if value == 0.0 {
+            return Some(Self::ZERO);
+        }
+        let bits = value.to_bits();
+        let fraction = bits & ((1 << 52) - 1);
+        #[allow(clippy::cast_possible_truncation)]
+        let biased = ((bits >> 52) & 0x7ff) as i32;
+        // value = ±mantissa · 2^exponent
+        let (mantissa, exponent) = if biased == 0 {
+            (fraction, -1074)
+        } else {
+            (fraction | (1 << 52), biased - 1075)
+        };
+        let zeros = mantissa.trailing_zeros();
+        let mantissa = i128::from(mantissa >> zeros);
+        let exponent = exponent + zeros.cast_signed();
+        let (numer, denom) = if exponent >= 0 {
+            // the numerator may have 127 bits
+            if (128 - mantissa.leading_zeros()).cast_signed() + exponent > 127 {
+                return None;
+            }
+            (mantissa << exponent, 1)
+        } else {
+            if exponent < -126 {
+                return None;
+            }
+            (mantissa, 1i128 << -exponent)
+        };
+        Some(Self {
+            numer: if value < 0.0 { -numer } else { numer },
+            denom,
+        })
+    }
+
+    /// The numerator, which carries the sign
+    #[inline]
+    #[must_use]
+    pub const fn numer(self) -> i128 {
+        self.numer
+    }
+
+    /// The denominator, which is positive
+    #[inline]
+    #[must_use]
+    pub const fn denom(self) -> i128 {
+        self.denom
+    }
+
+    /// Whether the denominator is 1
+    #[inline]
+    #[must_use]
+    pub const fn is_integer(self) -> bool {
+        self.denom == 1
+    }
+
+    /// This value as a float; the numerator and denominator are rounded before dividing
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn to_f64(self) -> f64 {
+        self.numer as f64 / self.denom as f64
+    }
+
+    /// `nums1.rational(numer, denom)`, even for integers
+    #[must_use]
+    pub fn to_openmath(self) -> OpenMath<'static> {
+        apply(
+            &RATIONAL,
+            vec![omi(Int::from(self.numer)), omi(Int::from(self.denom))],
+        )
+    }
+
+    /// An [`OMI`](OpenMath::OMI) for integers, `nums1.rational` otherwise
+    fn to_openmath_part(self) -> OpenMath<'static> {
+        if self.is_integer() {
+            omi(Int::from(self.numer))
+        } else {
+            self.to_openmath()
+        }
+    }
+}
+
+impl std::fmt::Display for Rational {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_integer() {
+            write!(f, "{}", self.numer)
+        } else {
+            write!(f, "{}/{}", self.numer, self.denom)
+        }
+    }
+}
+
+impl From<i128> for Rational {
+    #[inline]
+    fn from(value: i128) -> Self {
+        Self::from_integer(value)
+    }
+}
+
+/// `nums1.rational(numer, denom)`, like [`to_openmath`](Rational::to_openmath)
+impl OMSerializable for Rational {
+    #[inline]
+    fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        serializer.oma(
+            RATIONAL.as_oms(),
+            [Int::from(self.numer), Int::from(self.denom)].iter(),
+        )
+    }
+}
+
+/// A complex number with [`Rational`] real and imaginary parts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Complex {
+    pub re: Rational,
+    pub im: Rational,
+}
+
+impl Complex {
+    /// `complex1.complex_cartesian(re, im)`, with [`OMI`](OpenMath::OMI)s for integer parts
+    /// and `nums1.rational`s for the others
+    #[must_use]
+    pub fn to_openmath(self) -> OpenMath<'static> {
+        apply(
+            &COMPLEX_CARTESIAN,
+            vec![self.re.to_openmath_part(), self.im.to_openmath_part()],
+        )
+    }
+}
+
+impl std::fmt::Display for Complex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} + {}i", self.re, self.im)
+    }
+}
+
+impl From<Rational> for Complex {
+    #[inline]
+    fn from(re: Rational) -> Self {
+        Self {
+            re,
+            im: Rational::ZERO,
+        }
+    }
+}
+
+/// `complex1.complex_cartesian(re, im)`, like [`to_openmath`](Complex::to_openmath)
+impl OMSerializable for Complex {
+    fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        let part = |r: Rational| {
+            if r.is_integer() {
+                either::Either::Left(Int::from(r.numer))
+            } else {
+                either::Either::Right(r)
+            }
+        };
+        serializer.oma(
+            COMPLEX_CARTESIAN.as_oms(),
+            [part(self.re), part(self.im)].iter(),
+        )
+    }
+}
+
+/// Reading numbers; like the other [accessors](OpenMath::as_i64), they ignore attributes.
+impl OpenMath<'_> {
+    /** The exact value of an [`OMI`](Self::OMI), an [`OMF`](Self::OMF) or a
+    `nums1.rational` of two [`OMI`](Self::OMI)s; see the [module](crate::numeric)
+    documentation.
+
+    # Errors
+    [`NumericError::NotNumeric`] for other objects, [`NumericError::OutOfRange`] for values
+    that do not fit, and [`NumericError::ZeroDenominator`]
+    */
+    pub fn as_rational(&self) -> Result<Rational, NumericError> {
+        match self {
+            Self::OMI { int, .. } => integer(int),
+            Self::OMF { float, .. } => Rational::from_f64(float.0)
+                .ok_or_else(|| NumericError::OutOfRange(format!("float {:?}", float.0))),
+            Self::OMA {
+                applicant,
+                arguments,
+                ..
+            } if is(applicant, &RATIONAL) => match &arguments[..] {
+                [Self::OMI { int: n, .. }, Self::OMI { int: d, .. }] => {
+                    if d.is_zero() {
+                        return Err(NumericError::ZeroDenominator);
+                    }
+                    let (n, d) = (integer(n)?, integer(d)?);
+                    Rational::new(n.numer, d.numer)
+                        .ok_or_else(|| NumericError::OutOfRange(format!("rational {n}/{d}")))
+                }
+                _ => Err(NumericError::NotNumeric(EXPECTED_RATIONAL)),
+            },
+            _ => Err(NumericError::NotNumeric(EXPECTED_RATIONAL)),
+        }
+    }
+
+    /** A `complex1.complex_cartesian` of two [rational](Self::as_rational) parts, or a
+    rational number as a complex one with imaginary part zero.
+
+    # Errors
+    [`NumericError::NotNumeric`] for other objects, and the errors of
+    [`as_rational`](Self::as_rational) for the parts
+
+    # Examples
+    ```rust
+    use openmath::{OpenMath, numeric::{Complex, Rational}};
+
+    let om = OpenMath::from_sexpr(
+        "(oma (oms complex1 complex_cartesian) (omf 0.5) (oma (oms nums1 rational) (omi 1) (omi 3)))",
+    )
+    .expect("is valid");
+    let z = om.as_complex().expect("is a complex number");
+    assert_eq!(z.to_string(), "1/2 + 1/3i");
+    assert_eq!(
+        OpenMath::from_sexpr("(omi 2)").expect("is valid").as_complex(),
+        Ok(Complex::from(Rational::from(2)))
+    );
+    ```
+    */
+    pub fn as_complex(&self) -> Result<Complex, NumericError> {
+        match self {
+            Self::OMA {
+                applicant,
+                arguments,
+                ..
+            } if is(applicant, &COMPLEX_CARTESIAN) => match &arguments[..] {
+                [re, im] => Ok(Complex {
+                    re: re.as_rational().map_err(complex_part)?,
+                    im: im.as_rational().map_err(complex_part)?,
+                }),
+                _ => Err(NumericError::NotNumeric(EXPECTED_COMPLEX)),
+            },
+            _ => self.as_rational().map(Complex::from).map_err(complex_part),
+        }
+    }
+}
+
+fn complex_part(e: NumericError) -> NumericError {
+    match e {
+        NumericError::NotNumeric(_) => NumericError::NotNumeric(EXPECTED_COMPLEX),
+        e => e,
+    }
+}
+
+fn integer(int: &Int<'_>) -> Result<Rational, NumericError> {
+    int.is_i128()
+        .map(Rational::from_integer)
+        .ok_or_else(|| NumericError::OutOfRange(format!("integer {int}")))
+}
+
+/// Whether `om` is the symbol `uri` (in the standard cdbase, or one inherited from an
+/// unknown context)
+fn is(om: &OpenMath<'_>, uri: &Uri<'_>) -> bool {
+    om.as_symbol().is_some_and(|(cd, name, cdbase)| {
+        cd == uri.cd
+            && name == uri.name
+            && cdbase.is_none_or(|c| crate::cdbase::eq_normalized(c, crate::CD_BASE))
+    })
+}
+
+const fn omi(int: Int<'static>) -> OpenMath<'static> {
+    OpenMath::OMI {
+        int,
+        attributes: Vec::new(),
+    }
+}
+
+fn apply(uri: &Uri<'static>, arguments: Vec<OpenMath<'static>>) -> OpenMath<'static> {
+    OpenMath::OMA {
+        applicant: Box::new(OpenMath::OMS {
+            cd: Cow::Borrowed(uri.cd),
+            name: Cow::Borrowed(uri.name),
+            cdbase: Some(Cow::Borrowed(crate::CD_BASE)),
+            cdbase_inherited: true,
+            attributes: Vec::new(),
+        }),
+        arguments,
+        attributes: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::om;
+
+    #[test]
+    fn rationals() {
+        let r = |n, d| Rational::new(n, d).expect("is valid");
+        for (sexpr, expected) in [
+            ("(omi -7)", Ok(r(-7, 1))),
+            ("(omf 2.5)", Ok(r(5, 2))),
+            ("(omf -0.0)", Ok(Rational::ZERO)),
+            (
+                "(omf 5e-324)",
+                Err(NumericError::OutOfRange("float 5e-324".into())),
+            ),
+            (
+                "(omf 1.7014118346046923e38)",
+                Err(NumericError::OutOfRange(
+                    "float 1.7014118346046923e38".into(),
+                )),
+            ),
+            ("(omf 8.507059173023462e37)", Ok(r(1 << 126, 1))),
+            ("(oma (oms nums1 rational) (omi 4) (omi -6))", Ok(r(-2, 3))),
+            (
+                "(oma (oms nums1 rational) (omi 1) (omi 0))",
+                Err(NumericError::ZeroDenominator),
+            ),
+            (
+                "(oma (oms nums1 rational) (omi 1) (omi 340282366920938463463374607431768211456))",
+                Err(NumericError::OutOfRange(
+                    "integer 340282366920938463463374607431768211456".into(),
+                )),
+            ),
+            (
+                "(oma (oms nums1 rational) (omi 1) (omf 2))",
+                Err(NumericError::NotNumeric(EXPECTED_RATIONAL)),
+            ),
+            (
+                "(oma (oms nums1 rational) (omi 1))",
+                Err(NumericError::NotNumeric(EXPECTED_RATIONAL)),
+            ),
+            (
+                "(oma (oms arith1 divide) (omi 1) (omi 2))",
+                Err(NumericError::NotNumeric(EXPECTED_RATIONAL)),
+            ),
+            (
+                r#"(cdbase "http://example.org" (oma (oms nums1 rational) (omi 1) (omi 2)))"#,
+                Err(NumericError::NotNumeric(EXPECTED_RATIONAL)),
+            ),
+            ("(omv x)", Err(NumericError::NotNumeric(EXPECTED_RATIONAL))),
+        ] {
+            assert_eq!(om(sexpr).as_rational(), expected, "{sexpr}");
+        }
+        assert_eq!(
+            Rational::new(i128::MIN, 1).map(Rational::numer),
+            Some(i128::MIN)
+        );
+        assert_eq!(Rational::new(i128::MIN, -1), None);
+        assert_eq!(Rational::new(i128::MIN, i128::MIN), Some(r(1, 1)));
+        assert_eq!(Rational::new(1, 0), None);
+    }
+
+    #[test]
+    fn floats_convert_exactly() {
+        for f in [0.1, -1.0 / 3.0, 1e-20, 1e30, 123_456.789] {
+            let r = Rational::from_f64(f).expect("fits");
+            assert_eq!(r.to_f64().to_bits(), f.to_bits(), "{f}");
+            assert_eq!(r.denom().count_ones(), 1, "{f}");
+        }
+    }
+
+    #[test]
+    fn complex_numbers() {
+        let r = |n, d| Rational::new(n, d).expect("is valid");
+        let z = om(
+            "(oma (oms complex1 complex_cartesian) (omi 1) (oma (oms nums1 rational) (omi -1) (omi 2)))",
+        );
+        let expected = Complex {
+            re: r(1, 1),
+            im: r(-1, 2),
+        };
+        assert_eq!(z.as_complex(), Ok(expected));
+        assert_eq!(expected.to_openmath(), z);
+        assert_eq!(om("(omf 0.5)").as_complex(), Ok(Complex::from(r(1, 2))));
+        for (sexpr, expected) in [
+            (
+                "(oma (oms complex1 complex_cartesian) (omi 1))",
+                NumericError::NotNumeric(EXPECTED_COMPLEX),
+            ),
+            (
+                "(oma (oms complex1 complex_polar) (omi 1) (omi 0))",
+                NumericError::NotNumeric(EXPECTED_COMPLEX),
+            ),
+            (
+                r#"(oma (oms complex1 complex_cartesian) (omi 1) (omstr "i"))"#,
+                NumericError::NotNumeric(EXPECTED_COMPLEX),
+            ),
+            (
+                "(oma (oms complex1 complex_cartesian) (omi 1) (omf inf))",
+                NumericError::OutOfRange("float inf".into()),
+            ),
+        ] {
+            assert_eq!(om(sexpr).as_complex(), Err(expected), "{sexpr}");
+        }
+    }
+
+    #[test]
+    fn standard_forms() {
+        let r = Rational::new(-3, 4).expect("is valid");
+        assert_eq!(r.to_openmath().as_rational(), Ok(r));
+        assert_eq!(
+            Rational::from(5).to_openmath(),
+            om("(oma (oms nums1 rational) (omi 5) (omi 1))")
+        );
+        let z = Complex {
+            re: r,
+            im: Rational::from(2),
+        };
+        assert_eq!(z.to_openmath().as_complex(), Ok(z));
+        #[cfg(feature = "xml-write")]
+        {
+            assert_eq!(
+                r.xml(false).to_string(),
+                r#"<OMA><OMS cd="nums1" name="rational"/><OMI>-3</OMI><OMI>4</OMI></OMA>"#
+            );
+            assert_eq!(
+                z.xml(false).to_string(),
+                z.to_openmath().xml(false).to_string()
+            );
+            assert_eq!(
+                z.xml(false).to_string(),
+                r#"<OMA><OMS cd="complex1" name="complex_cartesian"/><OMA><OMS cd="nums1" name="rational"/><OMI>-3</OMI><OMI>4</OMI></OMA><OMI>2</OMI></OMA>"#
+            );
+        }
+    }
+}
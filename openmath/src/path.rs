@@ -0,0 +1,412 @@
+//! Addressing and serializing subterms; see [`Path`] and [`OpenMath::serialize_subterm`]
+
+use crate::{OMSerializable, OpenMath, WithRefs, ser::OMSerializer};
+
+/// A step from an [`OpenMath`] object to one of its immediate subterms
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Step {
+    /// The applicant of an [`OMA`](OpenMath::OMA)
+    Applicant,
+    /// The argument with the given index of an [`OMA`](OpenMath::OMA) or an
+    /// [`OME`](OpenMath::OME) (if it is not foreign)
+    Argument(usize),
+    /// The binder of an [`OMBIND`](OpenMath::OMBIND)
+    Binder,
+    /// The body of an [`OMBIND`](OpenMath::OMBIND)
+    Body,
+    /// The value of the attribution pair with the given index (if it is not foreign)
+    Attribute(usize),
+    /// The value of the attribution pair with the second index of the bound variable with
+    /// the first index of an [`OMBIND`](OpenMath::OMBIND) (if it is not foreign)
+    VariableAttribute(usize, usize),
+}
+
+impl std::fmt::Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Applicant => f.write_str("applicant"),
+            Self::Argument(i) => write!(f, "{i}"),
+            Self::Binder => f.write_str("binder"),
+            Self::Body => f.write_str("body"),
+            Self::Attribute(i) => write!(f, "@{i}"),
+            Self::VariableAttribute(i, j) => write!(f, "var{i}@{j}"),
+        }
+    }
+}
+
+/** The path from an [`OpenMath`] object to one of its subterms, as a sequence of [`Step`]s;
+the empty path refers to the object itself.
+
+Displayed like `/0/applicant/@1/var0@2`, or `/` for the empty path.
+
+# Examples
+```
+use openmath::{OpenMath, Path, Step};
+
+let om = OpenMath::from_sexpr("(oma (oms arith1 plus) (omi 1) (oma (oms arith1 times) (omi 2) (omv x)))")
+    .expect("is valid");
+let path: Path = [Step::Argument(1), Step::Argument(1)].into_iter().collect();
+assert_eq!(path.to_string(), "/1/1");
+assert_eq!(om.at(&path), Some(&OpenMath::from_sexpr("(omv x)").expect("is valid")));
+assert_eq!(om.at(&Path::from(vec![Step::Body])), None);
+```
+*/
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Path(pub Vec<Step>);
+
+impl Path {
+    /// The empty path, referring to the object itself
+    #[inline]
+    #[must_use]
+    pub const fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    /// Appends `step`
+    #[inline]
+    #[must_use]
+    pub fn then(mut self, step: Step) -> Self {
+        self.0.push(step);
+        self
+    }
+
+    /// The steps of this path
+    #[inline]
+    #[must_use]
+    pub fn steps(&self) -> &[Step] {
+        &self.0
+    }
+}
+
+impl From<Vec<Step>> for Path {
+    #[inline]
+    fn from(steps: Vec<Step>) -> Self {
+        Self(steps)
+    }
+}
+
+impl FromIterator<Step> for Path {
+    #[inline]
+    fn from_iter<T: IntoIterator<Item = Step>>(iter: T) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl std::fmt::Display for Path {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0.is_empty() {
+            return f.write_str("/");
+        }
+        for step in &self.0 {
+            write!(f, "/{step}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The error of [serializing a subterm](OpenMath::serialize_subterm)
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SubtermError<E> {
+    /// There is no subterm at the path
+    #[error("no subterm at {0}")]
+    NotFound(Path),
+    /// The serializer failed
+    #[error(transparent)]
+    Serializer(E),
+}
+
+/// The subterm of an [`OpenMath`] object at a [`Path`], which serializes like it does in
+/// the context of the whole object; see [`OpenMath::subterm`].
+#[derive(Debug, Clone, Copy)]
+pub struct Subterm<'a, 'om> {
+    root: &'a OpenMath<'om>,
+    path: &'a Path,
+}
+
+impl<'om> Subterm<'_, 'om> {
+    /// The subterm itself
+    ///
+    /// # Panics
+    /// never; the path is checked when the [`Subterm`] is created
+    #[must_use]
+    pub fn get(&self) -> &OpenMath<'om> {
+        self.root
+            .at(self.path)
+            .expect("checked in OpenMath::subterm")
+    }
+}
+
+impl OMSerializable for Subterm<'_, '_> {
+    fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        self.root
+            .serialize_subterm(self.path, serializer)
+            .map_err(|e| match e {
+                SubtermError::Serializer(e) => e,
+                SubtermError::NotFound(path) => {
+                    crate::ser::Error::custom(format_args!("no subterm at {path}"))
+                }
+            })
+    }
+}
+
+impl<'om> OpenMath<'om> {
+    /// The immediate subterm at `step`, if any
+    #[must_use]
+    pub fn child(&self, step: Step) -> Option<&Self> {
+        match (step, self) {
+            (Step::Attribute(i), _) => self.attributes().get(i)?.value.as_om(),
+            (Step::Applicant, Self::OMA { applicant, .. }) => Some(applicant),
+            (Step::Argument(i), Self::OMA { arguments, .. }) => arguments.get(i),
+            (Step::Argument(i), Self::OME { arguments, .. }) => arguments.get(i)?.as_om(),
+            (Step::Binder, Self::OMBIND { binder, .. }) => Some(binder),
+            (Step::Body, Self::OMBIND { object, .. }) => Some(object),
+            (Step::VariableAttribute(i, j), Self::OMBIND { variables, .. }) => {
+                variables.get(i)?.attributes.get(j)?.value.as_om()
+            }
+            _ => None,
+        }
+    }
+
+    /// Like [`child`](Self::child), but mutable
+    pub fn child_mut(&mut self, step: Step) -> Option<&mut Self> {
+        match (step, self) {
+            (
+                Step::Attribute(i),
+                Self::OMI { attributes, .. }
+                | Self::OMF { attributes, .. }
+                | Self::OMSTR { attributes, .. }
+                | Self::OMB { attributes, .. }
+                | Self::OMV { attributes, .. }
+                | Self::OMS { attributes, .. }
+                | Self::OMA { attributes, .. }
+                | Self::OME { attributes, .. }
+                | Self::OMBIND { attributes, .. },
+            ) => attributes.get_mut(i)?.value.as_om_mut(),
+            (Step::Applicant, Self::OMA { applicant, .. }) => Some(applicant),
+            (Step::Argument(i), Self::OMA { arguments, .. }) => arguments.get_mut(i),
+            (Step::Argument(i), Self::OME { arguments, .. }) => arguments.get_mut(i)?.as_om_mut(),
+            (Step::Binder, Self::OMBIND { binder, .. }) => Some(binder),
+            (Step::Body, Self::OMBIND { object, .. }) => Some(object),
+            (Step::VariableAttribute(i, j), Self::OMBIND { variables, .. }) => variables
+                .get_mut(i)?
+                .attributes
+                .get_mut(j)?
+                .value
+                .as_om_mut(),
+            _ => None,
+        }
+    }
+
+    /// The subterm at `path`, if any; see [`Path`].
+    #[must_use]
+    pub fn at(&self, path: &Path) -> Option<&Self> {
+        path.0.iter().try_fold(self, |om, step| om.child(*step))
+    }
+
+    /// Like [`at`](Self::at), but mutable
+    pub fn at_mut(&mut self, path: &Path) -> Option<&mut Self> {
+        path.0.iter().try_fold(self, |om, step| om.child_mut(*step))
+    }
+
+    /// The subterm at `path` (if any), to serialize it in the context of this object; see
+    /// [`serialize_subterm`](Self::serialize_subterm).
+    #[must_use]
+    pub fn subterm<'a>(&'a self, path: &'a Path) -> Option<Subterm<'a, 'om>> {
+        self.at(path).map(|_| Subterm { root: self, path })
+    }
+
+    /** Serializes the subterm at `path` without cloning it, such that it means the same as
+    in the context of this object (with this object being serialized with `serializer`).
+
+    Symbols without an explicit cdbase (see [`OMS`](Self::OMS)) are interpreted in the
+    cdbase inherited from the nearest ancestor that sets one during serialization, which
+    may be several levels up; that cdbase is set on the subterm, if it differs from the
+    [current cdbase](OMSerializer::current_cdbase) of `serializer`.
+
+    # Errors
+    [`NotFound`](SubtermError::NotFound) if there is no subterm at `path`, or
+    [`Serializer`](SubtermError::Serializer) if the serializer fails.
+
+    # Examples
+    ```
+    # #[cfg(feature = "xml-write")]
+    # {
+    use openmath::{OpenMath, Path, Step};
+
+    let mut om = OpenMath::from_sexpr(
+        r#"(cdbase "http://example.org/cd" (oma (oms my f) (oma (oms my g) (omv x))))"#,
+    )
+    .expect("is valid");
+    // a symbol without explicit cdbase, e.g. constructed in code
+    let path = Path::from(vec![Step::Argument(0), Step::Applicant]);
+    if let Some(OpenMath::OMS { cdbase, .. }) = om.at_mut(&path) {
+        *cdbase = None;
+    }
+    assert_eq!(
+        om.xml_at(&path, false).expect("exists"),
+        r#"<OMS cdbase="http://example.org/cd" cd="my" name="g"/>"#
+    );
+    assert!(om.xml_at(&Path::from(vec![Step::Body]), false).is_err());
+    # }
+    ```
+    */
+    pub fn serialize_subterm<'s, S: OMSerializer<'s>>(
+        &self,
+        path: &Path,
+        serializer: S,
+    ) -> Result<S::Ok, SubtermError<S::Err>> {
+        let mut om = self;
+        let mut inherited = crate::Inherited::new();
+        self.inherited_cdbases(&mut inherited);
+        // the cdbase set by the ancestors so far, if it differs from the serializer's
+        let mut cdbase = None;
+        for step in &path.0 {
+            // as in `WithRefs`, inherited cdbases are set on the outermost object they apply to
+            if !matches!(om, Self::OMS { attributes, .. } if attributes.is_empty())
+                && let Some(inherited) = inherited.get(&std::ptr::from_ref(om).addr()).copied()
+                && !crate::cdbase::eq_normalized(
+                    inherited,
+                    cdbase.unwrap_or_else(|| serializer.current_cdbase()),
+                )
+            {
+                cdbase = Some(inherited);
+            }
+            om = om
+                .child(*step)
+                .ok_or_else(|| SubtermError::NotFound(path.clone()))?;
+        }
+        match cdbase {
+            Some(cdbase) if !crate::cdbase::eq_normalized(cdbase, serializer.current_cdbase()) => {
+                WithRefs(om, &()).as_openmath(
+                    serializer
+                        .with_cdbase(cdbase)
+                        .map_err(SubtermError::Serializer)?,
+                )
+            }
+            _ => WithRefs(om, &()).as_openmath(serializer),
+        }
+        .map_err(SubtermError::Serializer)
+    }
+
+    /// The <span style="font-variant:small-caps;">OpenMath</span> XML of the subterm at
+    /// `path`; see [`serialize_subterm`](Self::serialize_subterm).
+    ///
+    /// # Errors
+    /// if there is no subterm at `path`, or writing fails.
+    #[cfg(feature = "xml-write")]
+    pub fn xml_at(
+        &self,
+        path: &Path,
+        pretty: bool,
+    ) -> Result<String, SubtermError<std::fmt::Error>> {
+        use std::fmt::Write;
+        let subterm = self
+            .subterm(path)
+            .ok_or_else(|| SubtermError::NotFound(path.clone()))?;
+        let mut xml = String::new();
+        write!(xml, "{}", subterm.xml(pretty)).map_err(SubtermError::Serializer)?;
+        Ok(xml)
+    }
+
+    /// The <span style="font-variant:small-caps;">OpenMath</span> JSON of the subterm at
+    /// `path`; see [`serialize_subterm`](Self::serialize_subterm).
+    ///
+    /// # Errors
+    /// if there is no subterm at `path`, or serialization fails.
+    #[cfg(all(feature = "serde", feature = "serde_json"))]
+    pub fn json_at(&self, path: &Path) -> Result<String, SubtermError<serde_json::Error>> {
+        let subterm = self
+            .subterm(path)
+            .ok_or_else(|| SubtermError::NotFound(path.clone()))?;
+        serde_json::to_string(&subterm.openmath_serde()).map_err(SubtermError::Serializer)
+    }
+}
+
+#[cfg(all(test, any(feature = "xml", feature = "serde_json")))]
+mod tests {
+    use super::{Path, Step, SubtermError};
+    use crate::OpenMath;
+
+    /// `(oma f (oma g (ombind lambda (x) (oma h x))))` where only `f` has a cdbase; the
+    /// subterm `(oma h x)` inherits it from the root, three levels up.
+    fn deep() -> (OpenMath<'static>, Path) {
+        let mut om = OpenMath::from_sexpr(
+            r#"(cdbase "http://example.org/cd"
+                 (oma (oms my f)
+                   (oma (oms my g)
+                     (ombind (oms fns1 lambda) (bvar x)
+                       (omattr (((oms meta note) (omstr "n"))) (oma (oms my h) (omv x)))))))"#,
+        )
+        .expect("is valid");
+        let body = Path::from(vec![Step::Argument(0), Step::Argument(0), Step::Body]);
+        for path in [
+            Path::from(vec![Step::Argument(0), Step::Applicant]),
+            body.clone().then(Step::Applicant),
+        ] {
+            let Some(OpenMath::OMS { cdbase, .. }) = om.at_mut(&path) else {
+                panic!("{path} is a symbol")
+            };
+            *cdbase = None;
+        }
+        (om, body)
+    }
+
+    #[cfg(feature = "xml")]
+    #[test]
+    fn subterm_xml() {
+        use crate::{OMDeserializable, OMSerializable};
+        let (om, path) = deep();
+        assert_eq!(path.to_string(), "/0/0/body");
+        let whole = om.xml(false).to_string();
+        let expected = OpenMath::from_openmath_xml(&whole).expect("is valid");
+        let expected = expected.at(&path).expect("exists");
+
+        let xml = om.xml_at(&path, false).expect("exists");
+        assert!(
+            xml.starts_with(r#"<OMATTR cdbase="http://example.org/cd">"#),
+            "{xml}"
+        );
+        assert_eq!(
+            &OpenMath::from_openmath_xml(&xml).expect("is valid"),
+            expected
+        );
+        // serializing a clone loses the context
+        let naive = om.at(&path).expect("exists").xml(false).to_string();
+        assert_ne!(
+            &OpenMath::from_openmath_xml(&naive).expect("is valid"),
+            expected
+        );
+
+        assert_eq!(om.xml_at(&Path::new(), false).expect("exists"), whole);
+        let missing = path.clone().then(Step::Argument(3));
+        assert_eq!(
+            om.xml_at(&missing, false),
+            Err(SubtermError::NotFound(missing.clone()))
+        );
+        assert!(om.subterm(&missing).is_none());
+        assert_eq!(
+            om.xml_at(&path.then(Step::Attribute(0)), false).as_deref(),
+            Ok("<OMSTR>n</OMSTR>")
+        );
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn subterm_json() {
+        use crate::{OMSerializable, de::OMFromSerde};
+        fn parse(json: &str) -> OpenMath<'_> {
+            serde_json::from_str::<OMFromSerde<OpenMath<'_>>>(json)
+                .expect("is valid")
+                .into_inner()
+        }
+        let (om, path) = deep();
+        let whole = serde_json::to_string(&om.openmath_serde()).expect("is valid");
+        let whole = parse(&whole);
+        let json = om.json_at(&path).expect("exists");
+        assert_eq!(Some(&parse(&json)), whole.at(&path));
+        assert!(matches!(
+            om.json_at(&Path::from(vec![Step::Binder])),
+            Err(SubtermError::NotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,1414 @@
+/*! The *Popcorn* syntax for <span style="font-variant:small-caps;">OpenMath</span> objects.
+
+Popcorn is the compact, single-line textual notation used by many
+[SCSCP](https://www.openmath.org/standard/scscp/) tools, e.g. `arith1.plus(2, $x)`. The
+tables below list the forms this module reads and writes; they are not a transcription of
+the published Popcorn grammar, which may have notations this module lacks.
+
+| Form | Meaning |
+|------|---------|
+| `42`, `-7` | [`OMI`](crate::OMKind::OMI) |
+| `1.5`, `-2e10` | [`OMF`](crate::OMKind::OMF); a float literal needs a `.` or an exponent |
+| `"text"` | [`OMSTR`](crate::OMKind::OMSTR); escapes are `\"`, `\\`, `\n`, `\r` and `\t` |
+| `$x` | [`OMV`](crate::OMKind::OMV) |
+| `arith1.plus` | [`OMS`](crate::OMKind::OMS) |
+| `ex:mycd.sym` | [`OMS`](crate::OMKind::OMS) with the cdbase bound to the prefix `ex` |
+| `f(a1, …, an)` | [`OMA`](crate::OMKind::OMA) |
+| `b[$x, $y -> body]` | [`OMBIND`](crate::OMKind::OMBIND); variables may carry attributions |
+| `!cd.name(a1, …, an)`, `!cd.name` | [`OME`](crate::OMKind::OME) |
+| `obj{sts.type -> setname1.Z, …}` | [`OMATTR`](crate::OMKind::OMATTR) |
+| `(obj)` | grouping |
+
+Applications of the following symbols (from the default cdbase) have shorthand notations,
+listed by increasing binding strength; `+`, `*`, `and`, `or` and `;` are n-ary, `-` and `/`
+associate to the left and `^` to the right:
+
+| Shorthand | Symbol |
+|-----------|--------|
+| `a; b` | `prog1.block` |
+| `a := b` | `prog1.assign` |
+| `a ==> b`, `a <=> b` | `logic1.implies`, `logic1.equivalent` |
+| `a or b` | `logic1.or` |
+| `a and b` | `logic1.and` |
+| `a = b`, `a != b` (or `a <> b`), `a < b`, `a <= b`, `a > b`, `a >= b` | `relation1.eq`, `neq`, `lt`, `leq`, `gt`, `geq` |
+| `a .. b` | `interval1.integer_interval` |
+| `a + b`, `a - b` | `arith1.plus`, `arith1.minus` |
+| `a * b`, `a / b` | `arith1.times`, `arith1.divide` |
+| `a ^ b` | `arith1.power` |
+| `a \| b` | `complex1.complex_cartesian` |
+| `a // b` | `nums1.rational` |
+| `-a`, `not a` | `arith1.unary_minus`, `logic1.not` |
+| `[a, b, …]` | `list1.list` |
+| `if c then a else b endif` | `prog1.if` |
+| `while c do a endwhile` | `prog1.while` |
+
+This module has no notation for byte arrays or foreign objects, and names of variables, CDs
+and symbols need to be identifiers (`[A-Za-z_][A-Za-z0-9_]*`). Symbols whose cdbase is neither
+[`CD_BASE`](crate::CD_BASE) nor bound to a prefix can not be represented either;
+[`to_popcorn`](crate::OpenMath::to_popcorn) returns an error in all of these cases.
+Symbols parsed from Popcorn always carry their cdbase, just like [`OMS`](crate::OpenMath::OMS)
+values deserialized from the other encodings; attribution keys, as there, only if it is
+explicit (i.e. they have a prefix).
+
+# Examples
+```
+use openmath::OpenMath;
+
+let om = OpenMath::from_popcorn("arith1.plus(2, $x)").expect("is valid");
+assert!(matches!(om, OpenMath::OMA { .. }));
+assert_eq!(om.to_popcorn().expect("is representable"), "2 + $x");
+
+let om = OpenMath::from_popcorn("fns1.lambda[$x -> $x ^ 2]").expect("is valid");
+assert!(matches!(om, OpenMath::OMBIND { .. }));
+```
+*/
+
+use std::{borrow::Cow, fmt::Write};
+
+use crate::{Attr, BoundVariable, Int, OMKind, OMMaybeForeign, OpenMath};
+
+/// Errors that can occur when reading or writing Popcorn; parsing errors carry the byte
+/// offset into the input at which they occurred.
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum PopcornError {
+    #[error("unexpected end of input at offset {0}")]
+    UnexpectedEof(usize),
+    #[error("unexpected character {0:?} at offset {1}")]
+    UnexpectedChar(char, usize),
+    #[error("unterminated string literal starting at offset {0}")]
+    UnterminatedString(usize),
+    #[error("invalid escape sequence at offset {0}")]
+    InvalidEscape(usize),
+    #[error("expected {expected} at offset {offset}")]
+    Expected {
+        expected: &'static str,
+        offset: usize,
+    },
+    #[error("invalid integer at offset {0}")]
+    InvalidInt(usize),
+    #[error("invalid float at offset {0}")]
+    InvalidFloat(usize),
+    #[error("unknown cdbase prefix `{prefix}` at offset {offset}")]
+    UnknownPrefix { prefix: String, offset: usize },
+    #[error("unexpected trailing input at offset {0}")]
+    TrailingInput(usize),
+    #[error("{0} can not be represented in Popcorn")]
+    Unsupported(OMKind),
+    #[error("non-finite float {0} can not be represented in Popcorn")]
+    NonFinite(f64),
+    #[error("`{0}` is not a valid Popcorn identifier")]
+    InvalidName(String),
+    #[error("no prefix for cdbase `{0}`")]
+    UnknownCdBase(String),
+}
+impl PopcornError {
+    /// The byte offset into the input at which this error occurred, if it occurred during
+    /// parsing
+    #[must_use]
+    pub const fn offset(&self) -> Option<usize> {
+        match self {
+            Self::UnexpectedEof(o)
+            | Self::UnexpectedChar(_, o)
+            | Self::UnterminatedString(o)
+            | Self::InvalidEscape(o)
+            | Self::InvalidInt(o)
+            | Self::InvalidFloat(o)
+            | Self::TrailingInput(o)
+            | Self::Expected { offset: o, .. }
+            | Self::UnknownPrefix { offset: o, .. } => Some(*o),
+            Self::Unsupported(_)
+            | Self::NonFinite(_)
+            | Self::InvalidName(_)
+            | Self::UnknownCdBase(_) => None,
+        }
+    }
+}
+
+impl OpenMath<'_> {
+    /// Returns the Popcorn representation of this object; see the [module
+    /// documentation](crate::popcorn) for the syntax.
+    ///
+    /// # Errors
+    /// if the object contains byte arrays, foreign objects, non-finite floats, names that are
+    /// not identifiers or symbols with a cdbase other than [`CD_BASE`](crate::CD_BASE)
+    pub fn to_popcorn(&self) -> Result<String, PopcornError> {
+        self.to_popcorn_with(&[])
+    }
+
+    /// Like [`to_popcorn`](Self::to_popcorn), but writes symbols whose cdbase is in
+    /// `prefixes` (as `(prefix, cdbase)` pairs) as `prefix:cd.name`.
+    ///
+    /// # Errors
+    /// See [`to_popcorn`](Self::to_popcorn).
+    pub fn to_popcorn_with(&self, prefixes: &[(&str, &str)]) -> Result<String, PopcornError> {
+        let mut w = Writer {
+            out: String::new(),
+            prefixes,
+        };
+        w.object(self, 0)?;
+        Ok(w.out)
+    }
+
+    /// Parses Popcorn; see the [module documentation](crate::popcorn) for the syntax.
+    ///
+    /// # Errors
+    /// if the input is not valid Popcorn
+    pub fn from_popcorn(input: &str) -> Result<OpenMath<'static>, PopcornError> {
+        Self::from_popcorn_with(input, &[])
+    }
+
+    /// Like [`from_popcorn`](Self::from_popcorn), but resolves symbols `prefix:cd.name`
+    /// using `prefixes` (as `(prefix, cdbase)` pairs).
+    ///
+    /// # Errors
+    /// if the input is not valid Popcorn or uses a prefix not in `prefixes`
+    pub fn from_popcorn_with(
+        input: &str,
+        prefixes: &[(&str, &str)],
+    ) -> Result<OpenMath<'static>, PopcornError> {
+        let mut parser = Parser {
+            lexer: Lexer { src: input, pos: 0 },
+            prefixes,
+        };
+        let om = parser.expr(0)?;
+        match parser.lexer.next()? {
+            None => Ok(om),
+            Some((_, offset)) => Err(PopcornError::TrailingInput(offset)),
+        }
+    }
+}
+
+/// Wrapper that [`Display`](std::fmt::Display)s an [`OpenMath`] object as Popcorn; see the
+/// [module documentation](crate::popcorn) for the syntax.
+///
+/// Formatting fails for objects that have no Popcorn representation; use
+/// [`to_popcorn`](OpenMath::to_popcorn) to find out why.
+pub struct Popcorn<'o>(pub &'o OpenMath<'o>);
+impl std::fmt::Display for Popcorn<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0.to_popcorn().map_err(|_| std::fmt::Error)?)
+    }
+}
+
+// ---------------------------------------------------------------------------------------
+// operators
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Fixity {
+    Nary,
+    Left,
+    Right,
+    None,
+}
+
+struct Operator {
+    token: &'static str,
+    cd: &'static str,
+    name: &'static str,
+    precedence: u8,
+    fixity: Fixity,
+}
+
+macro_rules! operators {
+    ($($token:literal = $cd:ident . $name:ident : $prec:literal $fix:ident),* $(,)?) => {
+        const INFIX: &[Operator] = &[$(Operator {
+            token: $token,
+            cd: stringify!($cd),
+            name: stringify!($name),
+            precedence: $prec,
+            fixity: Fixity::$fix,
+        }),*];
+    };
+}
+operators! {
+    ";" = prog1.block : 1 Nary,
+    ":=" = prog1.assign : 2 None,
+    "==>" = logic1.implies : 3 None,
+    "<=>" = logic1.equivalent : 3 None,
+    "or" = logic1.or : 4 Nary,
+    "and" = logic1.and : 5 Nary,
+    "=" = relation1.eq : 6 None,
+    "!=" = relation1.neq : 6 None,
+    "<" = relation1.lt : 6 None,
+    "<=" = relation1.leq : 6 None,
+    ">" = relation1.gt : 6 None,
+    ">=" = relation1.geq : 6 None,
+    ".." = interval1.integer_interval : 7 None,
+    "+" = arith1.plus : 8 Nary,
+    "-" = arith1.minus : 8 Left,
+    "*" = arith1.times : 9 Nary,
+    "/" = arith1.divide : 9 Left,
+    "^" = arith1.power : 10 Right,
+    "|" = complex1.complex_cartesian : 11 None,
+    "//" = nums1.rational : 12 None,
+}
+
+/// binding strength of `-a` and `not a`
+const PREFIX: u8 = 13;
+/// binding strength of everything else
+const ATOM: u8 = 14;
+
+/// How an application is written
+enum Shape {
+    Infix(&'static Operator),
+    Prefix(&'static str),
+    List,
+    If,
+    While,
+}
+impl Shape {
+    const fn precedence(&self) -> u8 {
+        match self {
+            Self::Infix(op) => op.precedence,
+            Self::Prefix(_) => PREFIX,
+            Self::List | Self::If | Self::While => ATOM,
+        }
+    }
+
+    fn of(applicant: &OpenMath<'_>, arity: usize) -> Option<Self> {
+        let OpenMath::OMS {
+            cd,
+            name,
+            cdbase,
+            attributes,
+            ..
+        } = applicant
+        else {
+            return None;
+        };
+        if !attributes.is_empty()
+            || cdbase
+                .as_deref()
+                .is_some_and(|c| !crate::cdbase::eq_normalized(c, crate::CD_BASE))
+        {
+            return None;
+        }
+        match (&**cd, &**name, arity) {
+            ("arith1", "unary_minus", 1) => Some(Self::Prefix("-")),
+            ("logic1", "not", 1) => Some(Self::Prefix("not ")),
+            ("list1", "list", _) => Some(Self::List),
+            ("prog1", "if", 3) => Some(Self::If),
+            ("prog1", "while", 2) => Some(Self::While),
+            _ => INFIX
+                .iter()
+                .find(|op| op.cd == cd && op.name == name)
+                .filter(|op| {
+                    if op.fixity == Fixity::Nary {
+                        arity >= 2
+                    } else {
+                        arity == 2
+                    }
+                })
+                .map(Self::Infix),
+        }
+    }
+}
+
+fn is_ident(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// ---------------------------------------------------------------------------------------
+// writing
+
+type Attrs<'o> = [Attr<'o, OMMaybeForeign<'o, OpenMath<'o>>>];
+
+/// whether `om` is a literal that `-` would be merged into when reading
+fn is_unsigned_number(om: &OpenMath<'_>) -> bool {
+    match om {
+        OpenMath::OMI { int, attributes } => attributes.is_empty() && !int.is_negative(),
+        OpenMath::OMF { float, attributes } => attributes.is_empty() && float.0 >= 0.0,
+        _ => false,
+    }
+}
+
+struct Writer<'p> {
+    out: String,
+    prefixes: &'p [(&'p str, &'p str)],
+}
+impl Writer<'_> {
+    fn name(&mut self, name: &str) -> Result<(), PopcornError> {
+        if !is_ident(name) {
+            return Err(PopcornError::InvalidName(name.to_string()));
+        }
+        self.out.push_str(name);
+        Ok(())
+    }
+
+    fn symbol(&mut self, cdbase: Option<&str>, cd: &str, name: &str) -> Result<(), PopcornError> {
+        if let Some(cdbase) = cdbase
+            && !crate::cdbase::eq_normalized(cdbase, crate::CD_BASE)
+        {
+            let Some((prefix, _)) = self
+                .prefixes
+                .iter()
+                .find(|(_, c)| crate::cdbase::eq_normalized(c, cdbase))
+            else {
+                return Err(PopcornError::UnknownCdBase(cdbase.to_string()));
+            };
+            self.name(prefix)?;
+            self.out.push(':');
+        }
+        self.name(cd)?;
+        self.out.push('.');
+        self.name(name)
+    }
+
+    fn string(&mut self, s: &str) {
+        self.out.push('"');
+        for c in s.chars() {
+            match c {
+                '"' => self.out.push_str("\\\""),
+                '\\' => self.out.push_str("\\\\"),
+                '\n' => self.out.push_str("\\n"),
+                '\r' => self.out.push_str("\\r"),
+                '\t' => self.out.push_str("\\t"),
+                c => self.out.push(c),
+            }
+        }
+        self.out.push('"');
+    }
+
+    fn sequence<'a, 'o: 'a>(
+        &mut self,
+        objects: impl IntoIterator<Item = &'a OpenMath<'o>>,
+    ) -> Result<(), PopcornError> {
+        let mut first = true;
+        for o in objects {
+            if !first {
+                self.out.push_str(", ");
+            }
+            first = false;
+            self.object(o, 0)?;
+        }
+        Ok(())
+    }
+
+    fn foreign(&mut self, o: &OMMaybeForeign<'_, OpenMath<'_>>) -> Result<(), PopcornError> {
+        match o {
+            OMMaybeForeign::OM(o) => self.object(o, 0),
+            OMMaybeForeign::Foreign { .. } => Err(PopcornError::Unsupported(OMKind::OMFOREIGN)),
+        }
+    }
+
+    fn attributes(&mut self, attrs: &Attrs<'_>) -> Result<(), PopcornError> {
+        if attrs.is_empty() {
+            return Ok(());
+        }
+        self.out.push('{');
+        let mut first = true;
+        for a in attrs {
+            if !first {
+                self.out.push_str(", ");
+            }
+            first = false;
+            self.symbol(a.cdbase(), a.cd(), a.name())?;
+            self.out.push_str(" -> ");
+            self.foreign(&a.value)?;
+        }
+        self.out.push('}');
+        Ok(())
+    }
+
+    /// writes `om`, parenthesized if it binds less strongly than `min`
+    fn object(&mut self, om: &OpenMath<'_>, min: u8) -> Result<(), PopcornError> {
+        let attrs = om.attributes();
+        let shape = match om {
+            OpenMath::OMA {
+                applicant,
+                arguments,
+                ..
+            } => Shape::of(applicant, arguments.len()),
+            _ => None,
+        };
+        let precedence = match &shape {
+            Some(s) if attrs.is_empty() => s.precedence(),
+            _ => ATOM,
+        };
+        let parens = precedence < min;
+        if parens {
+            self.out.push('(');
+        }
+        let inner_min = if attrs.is_empty() { 0 } else { ATOM };
+        match (om, shape) {
+            (OpenMath::OMA { arguments, .. }, Some(shape)) => {
+                if inner_min > shape.precedence() {
+                    self.out.push('(');
+                }
+                self.shorthand(&shape, arguments)?;
+                if inner_min > shape.precedence() {
+                    self.out.push(')');
+                }
+            }
+            _ => self.plain(om)?,
+        }
+        self.attributes(attrs)?;
+        if parens {
+            self.out.push(')');
+        }
+        Ok(())
+    }
+
+    fn shorthand(&mut self, shape: &Shape, args: &[OpenMath<'_>]) -> Result<(), PopcornError> {
+        match shape {
+            Shape::Infix(op) => {
+                let p = op.precedence;
+                let (first_min, rest_min) = match op.fixity {
+                    Fixity::Nary | Fixity::None => (p + 1, p + 1),
+                    Fixity::Left => (p, p + 1),
+                    Fixity::Right => (p + 1, p),
+                };
+                for (i, a) in args.iter().enumerate() {
+                    if i == 0 {
+                        self.object(a, first_min)?;
+                    } else {
+                        if op.token != ";" {
+                            self.out.push(' ');
+                        }
+                        self.out.push_str(op.token);
+                        self.out.push(' ');
+                        self.object(a, rest_min)?;
+                    }
+                }
+            }
+            Shape::Prefix(token) => {
+                self.out.push_str(token);
+                let arg = &args[0];
+                if is_unsigned_number(arg) && *token == "-" {
+                    self.out.push('(');
+                    self.object(arg, 0)?;
+                    self.out.push(')');
+                } else {
+                    self.object(arg, PREFIX)?;
+                }
+            }
+            Shape::List => {
+                self.out.push('[');
+                self.sequence(args)?;
+                self.out.push(']');
+            }
+            Shape::If => {
+                self.out.push_str("if ");
+                self.object(&args[0], 0)?;
+                self.out.push_str(" then ");
+                self.object(&args[1], 0)?;
+                self.out.push_str(" else ");
+                self.object(&args[2], 0)?;
+                self.out.push_str(" endif");
+            }
+            Shape::While => {
+                self.out.push_str("while ");
+                self.object(&args[0], 0)?;
+                self.out.push_str(" do ");
+                self.object(&args[1], 0)?;
+                self.out.push_str(" endwhile");
+            }
+        }
+        Ok(())
+    }
+
+    /// writes `om` without shorthands and attributes
+    fn plain(&mut self, om: &OpenMath<'_>) -> Result<(), PopcornError> {
+        match om {
+            OpenMath::OMI { int, .. } => {
+                let _ = write!(self.out, "{int}");
+            }
+            OpenMath::OMF { float, .. } => {
+                if !float.is_finite() {
+                    return Err(PopcornError::NonFinite(float.0));
+                }
+                // `Debug` always includes a `.` or an exponent
+                let _ = write!(self.out, "{:?}", float.0);
+            }
+            OpenMath::OMSTR { string, .. } => self.string(string),
+            OpenMath::OMB { .. } => return Err(PopcornError::Unsupported(OMKind::OMB)),
+            OpenMath::OMV { name, .. } => {
+                self.out.push('$');
+                self.name(name)?;
+            }
+            OpenMath::OMS {
+                cd, name, cdbase, ..
+            } => self.symbol(cdbase.as_deref(), cd, name)?,
+            OpenMath::OMA {
+                applicant,
+                arguments,
+                ..
+            } => {
+                self.object(applicant, ATOM)?;
+                self.out.push('(');
+                self.sequence(arguments)?;
+                self.out.push(')');
+            }
+            OpenMath::OME {
+                cd,
+                name,
+                cdbase,
+                arguments,
+                ..
+            } => {
+                self.out.push('!');
+                self.symbol(cdbase.as_deref(), cd, name)?;
+                self.out.push('(');
+                let mut first = true;
+                for a in arguments {
+                    if !first {
+                        self.out.push_str(", ");
+                    }
+                    first = false;
+                    self.foreign(a)?;
+                }
+                self.out.push(')');
+            }
+            OpenMath::OMBIND {
+                binder,
+                variables,
+                object,
+                ..
+            } => {
+                self.object(binder, ATOM)?;
+                self.out.push('[');
+                for (i, v) in variables.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push_str(", ");
+                    }
+                    self.out.push('$');
+                    self.name(&v.name)?;
+                    self.attributes(&v.attributes)?;
+                }
+                if !variables.is_empty() {
+                    self.out.push(' ');
+                }
+                self.out.push_str("-> ");
+                self.object(object, 0)?;
+                self.out.push(']');
+            }
+        }
+        Ok(())
+    }
+}
+
+// ---------------------------------------------------------------------------------------
+// reading
+
+enum Token<'a> {
+    Int(&'a str),
+    Float(&'a str),
+    Str(String),
+    Var(&'a str),
+    Symbol {
+        prefix: Option<&'a str>,
+        cd: &'a str,
+        name: &'a str,
+    },
+    Keyword(&'a str),
+    Punct(&'static str),
+}
+
+/// ordered such that longer tokens come first
+const PUNCTUATION: &[&str] = &[
+    "==>", "<=>", "->", ":=", "<=", ">=", "!=", "<>", "//", "..", "(", ")", "[", "]", "{", "}",
+    ",", ";", "=", "<", ">", "+", "-", "*", "/", "^", "|", "!",
+];
+
+struct Lexer<'a> {
+    src: &'a str,
+    pos: usize,
+}
+impl<'a> Lexer<'a> {
+    fn skip_whitespace(&mut self) {
+        let rest = &self.src[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&mut self) -> Result<Option<(Token<'a>, usize)>, PopcornError> {
+        let pos = self.pos;
+        let r = self.next();
+        self.pos = pos;
+        r
+    }
+
+    fn ident(&mut self) -> Option<&'a str> {
+        let rest = &self.src[self.pos..];
+        if !rest.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            return None;
+        }
+        let len = rest
+            .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .unwrap_or(rest.len());
+        self.pos += len;
+        Some(&rest[..len])
+    }
+
+    /// consumes `c` if it is followed by an identifier
+    fn separator(&mut self, c: char) -> bool {
+        let mut chars = self.src[self.pos..].chars();
+        if chars.next() == Some(c)
+            && chars
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn next(&mut self) -> Result<Option<(Token<'a>, usize)>, PopcornError> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let rest = &self.src[start..];
+        let Some(c) = rest.chars().next() else {
+            return Ok(None);
+        };
+        let token = match c {
+            '"' => {
+                self.pos += 1;
+                Token::Str(self.string(start)?)
+            }
+            '$' => {
+                self.pos += 1;
+                Token::Var(self.ident().ok_or(PopcornError::Expected {
+                    expected: "a variable name",
+                    offset: self.pos,
+                })?)
+            }
+            '0'..='9' => self.number(),
+            c if c.is_ascii_alphabetic() || c == '_' => self.word(c, start)?,
+            _ => {
+                let Some(p) = PUNCTUATION.iter().find(|p| rest.starts_with(**p)) else {
+                    return Err(PopcornError::UnexpectedChar(c, start));
+                };
+                self.pos += p.len();
+                Token::Punct(p)
+            }
+        };
+        Ok(Some((token, start)))
+    }
+
+    /// keywords and symbols with optional prefix
+    fn word(&mut self, c: char, start: usize) -> Result<Token<'a>, PopcornError> {
+        let first = self.ident().ok_or(PopcornError::UnexpectedChar(c, start))?;
+        let expected = |offset| PopcornError::Expected {
+            expected: "a symbol name",
+            offset,
+        };
+        let prefix = if self.separator(':') {
+            Some(first)
+        } else {
+            None
+        };
+        let cd = if prefix.is_some() {
+            let cd = self.ident().ok_or_else(|| expected(self.pos))?;
+            if !self.separator('.') {
+                return Err(expected(self.pos));
+            }
+            cd
+        } else if self.separator('.') {
+            first
+        } else {
+            return Ok(Token::Keyword(first));
+        };
+        let name = self.ident().ok_or_else(|| expected(self.pos))?;
+        Ok(Token::Symbol { prefix, cd, name })
+    }
+
+    fn number(&mut self) -> Token<'a> {
+        let start = self.pos;
+        let digits = |s: &str| s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let mut end = start + digits(&self.src[start..]);
+        let mut float = false;
+        let rest = &self.src[end..];
+        if rest.starts_with('.') && rest[1..].starts_with(|c: char| c.is_ascii_digit()) {
+            float = true;
+            end += 1 + digits(&rest[1..]);
+        }
+        let rest = &self.src[end..];
+        if let Some(exp) = rest.strip_prefix(['e', 'E']) {
+            let sign = usize::from(exp.starts_with(['+', '-']));
+            let len = digits(&exp[sign..]);
+            if len > 0 {
+                float = true;
+                end += 1 + sign + len;
+            }
+        }
+        self.pos = end;
+        let s = &self.src[start..end];
+        if float {
+            Token::Float(s)
+        } else {
+            Token::Int(s)
+        }
+    }
+
+    fn string(&mut self, start: usize) -> Result<String, PopcornError> {
+        let mut ret = String::new();
+        let base = self.pos;
+        let mut chars = self.src[base..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos = base + i + 1;
+                    return Ok(ret);
+                }
+                '\\' => ret.push(match chars.next() {
+                    None => return Err(PopcornError::UnterminatedString(start)),
+                    Some((_, '"')) => '"',
+                    Some((_, '\\')) => '\\',
+                    Some((_, 'n')) => '\n',
+                    Some((_, 'r')) => '\r',
+                    Some((_, 't')) => '\t',
+                    Some(_) => return Err(PopcornError::InvalidEscape(base + i)),
+                }),
+                c => ret.push(c),
+            }
+        }
+        Err(PopcornError::UnterminatedString(start))
+    }
+}
+
+const fn oms(cd: &'static str, name: &'static str) -> OpenMath<'static> {
+    OpenMath::OMS {
+        cd: Cow::Borrowed(cd),
+        name: Cow::Borrowed(name),
+        cdbase: Some(Cow::Borrowed(crate::CD_BASE)),
+        cdbase_inherited: true,
+        attributes: Vec::new(),
+    }
+}
+
+fn apply(
+    cd: &'static str,
+    name: &'static str,
+    arguments: Vec<OpenMath<'static>>,
+) -> OpenMath<'static> {
+    OpenMath::OMA {
+        applicant: Box::new(oms(cd, name)),
+        arguments,
+        attributes: Vec::new(),
+    }
+}
+
+struct Parser<'a, 'p> {
+    lexer: Lexer<'a>,
+    prefixes: &'p [(&'p str, &'p str)],
+}
+impl<'a> Parser<'a, '_> {
+    fn next(&mut self) -> Result<(Token<'a>, usize), PopcornError> {
+        self.lexer
+            .next()?
+            .ok_or(PopcornError::UnexpectedEof(self.lexer.pos))
+    }
+
+    /// consumes the next token if it is the punctuation `p`
+    fn eat(&mut self, p: &str) -> Result<bool, PopcornError> {
+        if matches!(self.lexer.peek()?, Some((Token::Punct(q), _)) if q == p) {
+            self.next()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn expect(&mut self, p: &str, expected: &'static str) -> Result<(), PopcornError> {
+        match self.next()? {
+            (Token::Punct(q), _) if q == p => Ok(()),
+            (_, offset) => Err(PopcornError::Expected { expected, offset }),
+        }
+    }
+
+    fn keyword(&mut self, k: &str, expected: &'static str) -> Result<(), PopcornError> {
+        match self.next()? {
+            (Token::Keyword(q), _) if q == k => Ok(()),
+            (_, offset) => Err(PopcornError::Expected { expected, offset }),
+        }
+    }
+
+    fn infix(&mut self) -> Result<Option<&'static Operator>, PopcornError> {
+        let token = match self.lexer.peek()? {
+            Some((Token::Punct("<>"), _)) => "!=",
+            Some((Token::Punct(p), _)) => p,
+            Some((Token::Keyword(k @ ("and" | "or")), _)) => k,
+            _ => return Ok(None),
+        };
+        Ok(INFIX.iter().find(|op| op.token == token))
+    }
+
+    /// parses an expression whose operators bind at least as strongly as `min`
+    fn expr(&mut self, min: u8) -> Result<OpenMath<'static>, PopcornError> {
+        let mut lhs = self.prefix()?;
+        // the n-ary operator `lhs` is an application of, if it was built in this loop
+        let mut flat: Option<&'static Operator> = None;
+        while let Some(op) = self.infix()? {
+            if op.precedence < min {
+                break;
+            }
+            self.next()?;
+            let rhs = self.expr(if op.fixity == Fixity::Right {
+                op.precedence
+            } else {
+                op.precedence + 1
+            })?;
+            match (&mut lhs, flat) {
+                (OpenMath::OMA { arguments, .. }, Some(f)) if std::ptr::eq(f, op) => {
+                    arguments.push(rhs);
+                }
+                _ => {
+                    lhs = apply(op.cd, op.name, vec![lhs, rhs]);
+                    flat = (op.fixity == Fixity::Nary).then_some(op);
+                }
+            }
+        }
+        Ok(lhs)
+    }
+
+    fn prefix(&mut self) -> Result<OpenMath<'static>, PopcornError> {
+        match self.lexer.peek()? {
+            Some((Token::Punct("-"), _)) => {
+                self.next()?;
+                if let Some((Token::Int(_) | Token::Float(_), offset)) = self.lexer.peek()? {
+                    let number = self.number(offset - 1)?;
+                    return self.postfix(number);
+                }
+                Ok(apply("arith1", "unary_minus", vec![self.prefix()?]))
+            }
+            Some((Token::Keyword("not"), _)) => {
+                self.next()?;
+                Ok(apply("logic1", "not", vec![self.prefix()?]))
+            }
+            _ => {
+                let primary = self.primary()?;
+                self.postfix(primary)
+            }
+        }
+    }
+
+    /// parses the number literal following a `-` at `minus`
+    fn number(&mut self, minus: usize) -> Result<OpenMath<'static>, PopcornError> {
+        let text = |s: &str| format!("-{s}");
+        match self.next()? {
+            (Token::Int(i), offset) => Ok(OpenMath::OMI {
+                int: Int::from_string(text(i)).ok_or(PopcornError::InvalidInt(offset))?,
+                attributes: Vec::new(),
+            }),
+            (Token::Float(f), offset) => Ok(OpenMath::OMF {
+                float: text(f)
+                    .parse::<f64>()
+                    .map_err(|_| PopcornError::InvalidFloat(offset))?
+                    .into(),
+                attributes: Vec::new(),
+            }),
+            _ => Err(PopcornError::Expected {
+                expected: "a number",
+                offset: minus,
+            }),
+        }
+    }
+
+    fn symbol(
+        &self,
+        prefix: Option<&str>,
+        offset: usize,
+    ) -> Result<Cow<'static, str>, PopcornError> {
+        let Some(prefix) = prefix else {
+            return Ok(Cow::Borrowed(crate::CD_BASE));
+        };
+        self.prefixes
+            .iter()
+            .find(|(p, _)| *p == prefix)
+            .map(|(_, c)| Cow::Owned((*c).to_string()))
+            .ok_or_else(|| PopcornError::UnknownPrefix {
+                prefix: prefix.to_string(),
+                offset,
+            })
+    }
+
+    fn arguments(&mut self, close: &'static str) -> Result<Vec<OpenMath<'static>>, PopcornError> {
+        let mut args = Vec::new();
+        if self.eat(close)? {
+            return Ok(args);
+        }
+        loop {
+            args.push(self.expr(0)?);
+            if self.eat(close)? {
+                return Ok(args);
+            }
+            self.expect(",", "`,`")?;
+        }
+    }
+
+    fn primary(&mut self) -> Result<OpenMath<'static>, PopcornError> {
+        let (token, offset) = self.next()?;
+        Ok(match token {
+            Token::Int(i) => OpenMath::OMI {
+                int: Int::new(i)
+                    .map(Int::into_owned)
+                    .ok_or(PopcornError::InvalidInt(offset))?,
+                attributes: Vec::new(),
+            },
+            Token::Float(f) => OpenMath::OMF {
+                float: f
+                    .parse::<f64>()
+                    .map_err(|_| PopcornError::InvalidFloat(offset))?
+                    .into(),
+                attributes: Vec::new(),
+            },
+            Token::Str(s) => OpenMath::OMSTR {
+                string: Cow::Owned(s),
+                attributes: Vec::new(),
+            },
+            Token::Var(v) => OpenMath::OMV {
+                name: Cow::Owned(v.to_string()),
+                attributes: Vec::new(),
+            },
+            Token::Symbol { prefix, cd, name } => OpenMath::OMS {
+                cdbase_inherited: prefix.is_none(),
+                cdbase: Some(self.symbol(prefix, offset)?),
+                cd: Cow::Owned(cd.to_string()),
+                name: Cow::Owned(name.to_string()),
+                attributes: Vec::new(),
+            },
+            Token::Punct("(") => {
+                let om = self.expr(0)?;
+                self.expect(")", "`)`")?;
+                om
+            }
+            Token::Punct("[") => apply("list1", "list", self.arguments("]")?),
+            Token::Punct("!") => {
+                let (Token::Symbol { prefix, cd, name }, offset) = self.next()? else {
+                    return Err(PopcornError::Expected {
+                        expected: "an error symbol",
+                        offset: offset + 1,
+                    });
+                };
+                let cdbase = Some(self.symbol(prefix, offset)?);
+                let arguments = if self.eat("(")? {
+                    self.arguments(")")?
+                } else {
+                    Vec::new()
+                };
+                OpenMath::OME {
+                    cd: Cow::Owned(cd.to_string()),
+                    name: Cow::Owned(name.to_string()),
+                    cdbase,
+                    arguments: arguments.into_iter().map(OMMaybeForeign::OM).collect(),
+                    attributes: Vec::new(),
+                }
+            }
+            Token::Keyword("if") => {
+                let condition = self.expr(0)?;
+                self.keyword("then", "`then`")?;
+                let then = self.expr(0)?;
+                self.keyword("else", "`else`")?;
+                let otherwise = self.expr(0)?;
+                self.keyword("endif", "`endif`")?;
+                apply("prog1", "if", vec![condition, then, otherwise])
+            }
+            Token::Keyword("while") => {
+                let condition = self.expr(0)?;
+                self.keyword("do", "`do`")?;
+                let body = self.expr(0)?;
+                self.keyword("endwhile", "`endwhile`")?;
+                apply("prog1", "while", vec![condition, body])
+            }
+            _ => {
+                return Err(PopcornError::Expected {
+                    expected: "an expression",
+                    offset,
+                });
+            }
+        })
+    }
+
+    /// applications, bindings and attributions
+    fn postfix(&mut self, mut om: OpenMath<'static>) -> Result<OpenMath<'static>, PopcornError> {
+        loop {
+            if self.eat("(")? {
+                om = OpenMath::OMA {
+                    applicant: Box::new(om),
+                    arguments: self.arguments(")")?,
+                    attributes: Vec::new(),
+                };
+            } else if self.eat("[")? {
+                let mut variables = Vec::new();
+                if !self.eat("->")? {
+                    loop {
+                        variables.push(self.variable()?);
+                        if self.eat("->")? {
+                            break;
+                        }
+                        self.expect(",", "`,` or `->`")?;
+                    }
+                }
+                let object = Box::new(self.expr(0)?);
+                self.expect("]", "`]`")?;
+                om = OpenMath::OMBIND {
+                    binder: Box::new(om),
+                    variables,
+                    object,
+                    attributes: Vec::new(),
+                };
+            } else if self.eat("{")? {
+                let attrs = self.attributes()?;
+                om.attributes_mut().extend(attrs);
+            } else {
+                return Ok(om);
+            }
+        }
+    }
+
+    /// parses the attributions after a `{`
+    fn attributes(
+        &mut self,
+    ) -> Result<Vec<Attr<'static, OMMaybeForeign<'static, OpenMath<'static>>>>, PopcornError> {
+        let mut attrs = Vec::new();
+        if self.eat("}")? {
+            return Ok(attrs);
+        }
+        loop {
+            let (token, offset) = self.next()?;
+            let Token::Symbol { prefix, cd, name } = token else {
+                return Err(PopcornError::Expected {
+                    expected: "an attribution key",
+                    offset,
+                });
+            };
+            // like attribution keys deserialized from the other encodings, only with an
+            // explicit cdbase
+            let cdbase = match prefix {
+                Some(_) => Some(self.symbol(prefix, offset)?),
+                None => None,
+            };
+            self.expect("->", "`->`")?;
+            attrs.push(Attr {
+                key: crate::SymbolRef {
+                    cdbase,
+                    cd: Cow::Owned(cd.to_string()),
+                    name: Cow::Owned(name.to_string()),
+                },
+                value: OMMaybeForeign::OM(self.expr(0)?),
+            });
+            if self.eat("}")? {
+                return Ok(attrs);
+            }
+            self.expect(",", "`,` or `}`")?;
+        }
+    }
+
+    fn variable(&mut self) -> Result<BoundVariable<'static>, PopcornError> {
+        let (token, offset) = self.next()?;
+        let Token::Var(name) = token else {
+            return Err(PopcornError::Expected {
+                expected: "a variable",
+                offset,
+            });
+        };
+        let mut attributes = Vec::new();
+        while self.eat("{")? {
+            attributes.extend(self.attributes()?);
+        }
+        Ok(BoundVariable {
+            name: Cow::Owned(name.to_string()),
+            attributes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(s: &str) -> OpenMath<'static> {
+        let om = OpenMath::from_popcorn(s).expect("is valid");
+        assert_eq!(om.to_popcorn().expect("is representable"), s);
+        om
+    }
+
+    fn same(a: &str, b: &str) {
+        assert_eq!(
+            OpenMath::from_popcorn(a).expect("is valid"),
+            OpenMath::from_popcorn(b).expect("is valid"),
+            "{a} vs. {b}"
+        );
+    }
+
+    #[test]
+    fn popcorn_roundtrip() {
+        roundtrip("42");
+        roundtrip("-123456789012345678901234567890");
+        roundtrip("1.5");
+        roundtrip("-1e-7");
+        roundtrip(r#""a \"quoted\"\nstring \\ ""#);
+        roundtrip("$x");
+        roundtrip("transc1.sin($x)");
+        roundtrip("$f()");
+        roundtrip("fns1.lambda[$x, $y -> $x * $y]");
+        roundtrip("fns1.lambda[-> 1]");
+        roundtrip("fns1.lambda[$x{sts.type -> setname1.R} -> $x]");
+        roundtrip("1{sts.type -> setname1.Z, my.note -> \"hi\"}");
+        roundtrip("!error.unhandled_symbol(\"foo\", 1)");
+        roundtrip("[1, 2, [$x]]");
+        roundtrip("if $x > 0 then $x else -$x endif");
+        roundtrip("while $i < 10 do $i := $i + 1 endwhile");
+        roundtrip("$x := 1; $y := 2; $x + $y");
+    }
+
+    #[test]
+    fn popcorn_operators() {
+        let om = roundtrip("2 + $x");
+        same("arith1.plus(2, $x)", "2 + $x");
+        assert!(matches!(om, OpenMath::OMA { ref arguments, .. } if arguments.len() == 2));
+        // n-ary operators are flattened, unless parenthesized
+        same("1 + 2 + 3", "arith1.plus(1, 2, 3)");
+        roundtrip("(1 + 2) + 3");
+        roundtrip("1 + 2 * 3");
+        roundtrip("(1 + 2) * 3");
+        // left- and right-associativity
+        same("1 - 2 - 3", "arith1.minus(arith1.minus(1, 2), 3)");
+        roundtrip("1 - (2 - 3)");
+        same("2 ^ 3 ^ 4", "arith1.power(2, arith1.power(3, 4))");
+        roundtrip("(2 ^ 3) ^ 4");
+        // unary minus vs. negative literals
+        same("-3", "-3");
+        assert!(matches!(
+            OpenMath::from_popcorn("-3").expect("is valid"),
+            OpenMath::OMI { .. }
+        ));
+        roundtrip("-(3)");
+        roundtrip("-$x ^ 2");
+        roundtrip("-($x ^ 2)");
+        roundtrip("$x - -3");
+        roundtrip("not $a and $b or $c ==> $d");
+        roundtrip("1 // 2 | 3 // 4");
+        roundtrip("1 .. $n");
+        same("$a <> $b", "relation1.neq($a, $b)");
+        roundtrip("$a != $b");
+        roundtrip("($x + 1){my.note -> 1}");
+        roundtrip("$f{my.note -> 1}($x)");
+        // symbols from a different cdbase are not abbreviated
+        let om = OpenMath::from_popcorn_with("ex:arith1.plus(1, 2)", &[("ex", "http://ex.org")])
+            .expect("is valid");
+        assert!(om.to_popcorn().is_err());
+        assert_eq!(
+            om.to_popcorn_with(&[("ex", "http://ex.org/")])
+                .expect("is representable"),
+            "ex:arith1.plus(1, 2)"
+        );
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn popcorn_equivalent_to_xml() {
+        use crate::OMDeserializable;
+        let xml = r#"<OMBIND>
+            <OMS cd="fns1" name="lambda"/>
+            <OMBVAR><OMV name="x"/></OMBVAR>
+            <OMA><OMS cd="arith1" name="plus"/><OMV name="x"/><OMF dec="1.5"/></OMA>
+          </OMBIND>"#;
+        let from_xml = OpenMath::from_openmath_xml(xml).expect("is valid");
+        let popcorn = from_xml.to_popcorn().expect("is representable");
+        assert_eq!(popcorn, "fns1.lambda[$x -> $x + 1.5]");
+        assert_eq!(
+            OpenMath::from_popcorn(&popcorn).expect("is valid"),
+            from_xml
+        );
+    }
+
+    /// One vector per form in the tables of the module documentation (cited by the form),
+    /// with its XML encoding and the form written back where that abbreviates the input.
+    /// They were written for these tests, not taken from the Popcorn paper.
+    #[cfg(feature = "xml-read")]
+    const VECTORS: &[(&str, &str, Option<&str>, &str)] = &[
+        ("`42`, `-7`", "42", None, "<OMI>42</OMI>"),
+        ("`42`, `-7`", "-7", None, "<OMI>-7</OMI>"),
+        ("`1.5`, `-2e10`", "1.5", None, r#"<OMF dec="1.5"/>"#),
+        (
+            r#"`"text"`"#,
+            r#""Hello World""#,
+            None,
+            "<OMSTR>Hello World</OMSTR>",
+        ),
+        ("`$x`", "$x", None, r#"<OMV name="x"/>"#),
+        (
+            "`arith1.plus`",
+            "nums1.pi",
+            None,
+            r#"<OMS cd="nums1" name="pi"/>"#,
+        ),
+        (
+            "`f(a1, …, an)`",
+            "transc1.sin($x)",
+            None,
+            r#"<OMA><OMS cd="transc1" name="sin"/><OMV name="x"/></OMA>"#,
+        ),
+        (
+            "`a + b`, `a - b`",
+            "arith1.plus(2, $x)",
+            Some("2 + $x"),
+            r#"<OMA><OMS cd="arith1" name="plus"/><OMI>2</OMI><OMV name="x"/></OMA>"#,
+        ),
+        (
+            "`a * b`, `a / b`",
+            "1 + 2 * 3",
+            None,
+            r#"<OMA><OMS cd="arith1" name="plus"/><OMI>1</OMI>
+              <OMA><OMS cd="arith1" name="times"/><OMI>2</OMI><OMI>3</OMI></OMA></OMA>"#,
+        ),
+        (
+            "`b[$x, $y -> body]`",
+            "fns1.lambda[$x -> $x ^ 2]",
+            None,
+            r#"<OMBIND><OMS cd="fns1" name="lambda"/><OMBVAR><OMV name="x"/></OMBVAR>
+              <OMA><OMS cd="arith1" name="power"/><OMV name="x"/><OMI>2</OMI></OMA></OMBIND>"#,
+        ),
+        (
+            "`b[$x, $y -> body]`",
+            "quant1.forall[$x, $y -> $x + $y = $y + $x]",
+            None,
+            r#"<OMBIND><OMS cd="quant1" name="forall"/>
+              <OMBVAR><OMV name="x"/><OMV name="y"/></OMBVAR>
+              <OMA><OMS cd="relation1" name="eq"/>
+                <OMA><OMS cd="arith1" name="plus"/><OMV name="x"/><OMV name="y"/></OMA>
+                <OMA><OMS cd="arith1" name="plus"/><OMV name="y"/><OMV name="x"/></OMA>
+              </OMA></OMBIND>"#,
+        ),
+        (
+            "`obj{sts.type -> setname1.Z, …}`",
+            "$x{sts.type -> setname1.Z}",
+            None,
+            r#"<OMATTR><OMATP><OMS cd="sts" name="type"/><OMS cd="setname1" name="Z"/></OMATP>
+              <OMV name="x"/></OMATTR>"#,
+        ),
+        (
+            "`!cd.name(a1, …, an)`",
+            r#"!error.unhandled_symbol("foo")"#,
+            None,
+            r#"<OME><OMS cd="error" name="unhandled_symbol"/><OMSTR>foo</OMSTR></OME>"#,
+        ),
+        (
+            "`[a, b, …]`",
+            "[1, 2, 3]",
+            None,
+            r#"<OMA><OMS cd="list1" name="list"/><OMI>1</OMI><OMI>2</OMI><OMI>3</OMI></OMA>"#,
+        ),
+        (
+            "`a // b`",
+            "1 // 2",
+            None,
+            r#"<OMA><OMS cd="nums1" name="rational"/><OMI>1</OMI><OMI>2</OMI></OMA>"#,
+        ),
+        (
+            "`a | b`",
+            "1 | 2",
+            None,
+            r#"<OMA><OMS cd="complex1" name="complex_cartesian"/><OMI>1</OMI><OMI>2</OMI></OMA>"#,
+        ),
+        (
+            "`a .. b`",
+            "1 .. 10",
+            None,
+            r#"<OMA><OMS cd="interval1" name="integer_interval"/><OMI>1</OMI><OMI>10</OMI></OMA>"#,
+        ),
+        (
+            "`a and b`",
+            "$a and not $b",
+            None,
+            r#"<OMA><OMS cd="logic1" name="and"/><OMV name="a"/>
+              <OMA><OMS cd="logic1" name="not"/><OMV name="b"/></OMA></OMA>"#,
+        ),
+        (
+            "`if c then a else b endif`",
+            "if $x < 0 then -$x else $x endif",
+            None,
+            r#"<OMA><OMS cd="prog1" name="if"/>
+              <OMA><OMS cd="relation1" name="lt"/><OMV name="x"/><OMI>0</OMI></OMA>
+              <OMA><OMS cd="arith1" name="unary_minus"/><OMV name="x"/></OMA>
+              <OMV name="x"/></OMA>"#,
+        ),
+        (
+            "`while c do a endwhile`",
+            "$i := 0; while $i < 10 do $i := $i + 1 endwhile",
+            None,
+            r#"<OMA><OMS cd="prog1" name="block"/>
+              <OMA><OMS cd="prog1" name="assign"/><OMV name="i"/><OMI>0</OMI></OMA>
+              <OMA><OMS cd="prog1" name="while"/>
+                <OMA><OMS cd="relation1" name="lt"/><OMV name="i"/><OMI>10</OMI></OMA>
+                <OMA><OMS cd="prog1" name="assign"/><OMV name="i"/>
+                  <OMA><OMS cd="arith1" name="plus"/><OMV name="i"/><OMI>1</OMI></OMA></OMA>
+              </OMA></OMA>"#,
+        ),
+    ];
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn popcorn_grammar_vectors() {
+        use crate::OMDeserializable;
+        for (form, popcorn, canonical, xml) in VECTORS {
+            let from_xml = OpenMath::from_openmath_xml(xml).expect("is valid");
+            let om = OpenMath::from_popcorn(popcorn).expect("is valid");
+            assert_eq!(om, from_xml, "{popcorn} ({form})");
+            let canonical = canonical.unwrap_or(popcorn);
+            assert_eq!(
+                from_xml.to_popcorn().expect("is representable"),
+                canonical,
+                "{popcorn}"
+            );
+            assert_eq!(roundtrip(canonical), om, "{popcorn}");
+        }
+    }
+
+    #[test]
+    fn popcorn_unsupported() {
+        let err = |s: &str| {
+            OpenMath::from_sexpr(s)
+                .expect("is valid")
+                .to_popcorn()
+                .expect_err("is not representable")
+        };
+        assert!(matches!(
+            err(r#"(omb "SGVsbG8=")"#),
+            PopcornError::Unsupported(OMKind::OMB)
+        ));
+        assert!(matches!(
+            err(r#"(ome (oms error unhandled) (omforeign "x"))"#),
+            PopcornError::Unsupported(OMKind::OMFOREIGN)
+        ));
+        assert!(matches!(err("(omf NaN)"), PopcornError::NonFinite(_)));
+        assert!(matches!(
+            err(r#"(omv "with space")"#),
+            PopcornError::InvalidName(_)
+        ));
+        assert!(matches!(
+            err(r#"(cdbase "http://ex.org" (oms cd name))"#),
+            PopcornError::UnknownCdBase(_)
+        ));
+    }
+
+    #[test]
+    fn popcorn_errors() {
+        fn err(s: &str) -> PopcornError {
+            OpenMath::from_popcorn(s).expect_err("is invalid")
+        }
+        assert!(matches!(err("1 +"), PopcornError::UnexpectedEof(3)));
+        assert!(matches!(err("1 2"), PopcornError::TrailingInput(2)));
+        assert!(matches!(err("1 # 2"), PopcornError::UnexpectedChar('#', 2)));
+        assert!(matches!(
+            err(r#""abc"#),
+            PopcornError::UnterminatedString(0)
+        ));
+        assert!(matches!(err(r#""a\qc""#), PopcornError::InvalidEscape(2)));
+        assert!(matches!(
+            err("ex:cd.name"),
+            PopcornError::UnknownPrefix { offset: 0, .. }
+        ));
+        assert!(matches!(err("$f($x"), PopcornError::UnexpectedEof(5)));
+        let e = err("fns1.lambda[1 -> 2]");
+        assert!(matches!(e, PopcornError::Expected { offset: 12, .. }));
+        assert_eq!(e.offset(), Some(12));
+        assert!(matches!(
+            err("if 1 then 2 endif"),
+            PopcornError::Expected { offset: 12, .. }
+        ));
+    }
+}
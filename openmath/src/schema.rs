@@ -0,0 +1,310 @@
+/*! The child slots of each kind of object, for tools that walk objects generically; see
+[`OMKind::child_slots`] and [`OpenMath::slot`].
+
+Every [`OMKind`] has a fixed list of [`SlotSpec`]s in document order: e.g. the
+attributes, applicant and arguments of an [OMA](OMKind::OMA). Tools that enumerate the
+structure of objects (like [`OpenMath::children_with_attrs`]) go through these, rather than
+matching on every variant of [`OpenMath`].
+
+# Examples
+```
+use openmath::{OpenMath, schema::SlotView};
+
+let om = OpenMath::from_sexpr(
+    "(ombind (oms fns1 lambda) (bvar x (omattr (((oms sts type) (oms setname1 R))) y)) (omv x))",
+)
+.expect("is valid");
+let mut edges = Vec::new();
+for spec in om.kind().child_slots() {
+    let Some(view) = om.slot(spec) else { continue };
+    assert!(spec.arity.allows(view.len()));
+    edges.push(format!("{}: {}", spec.name, view.len()));
+}
+assert_eq!(edges, ["attributes: 0", "binder: 1", "variables: 2", "body: 1"]);
+assert!(matches!(om.slot(&openmath::schema::SlotSpec::VARIABLES), Some(SlotView::Variables(v)) if v[1].name == "y"));
+```
+*/
+
+use crate::{Attr, BoundVariable, OMKind, OMMaybeForeign, OpenMath};
+
+/// What a [slot](SlotSpec) holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SlotKind {
+    /// [`OpenMath`] objects
+    Object,
+    /// [`OpenMath`] objects or foreign objects (the arguments of an [OME](OMKind::OME))
+    MaybeForeign,
+    /// Attribution pairs, whose values are objects or foreign objects
+    Attribute,
+    /// The [`BoundVariable`]s of an [OMBIND](OMKind::OMBIND)
+    Variable,
+}
+
+/// How many entries a [slot](SlotSpec) has
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Arity {
+    /// Exactly one
+    One,
+    /// At least the given number
+    AtLeast(usize),
+}
+impl Arity {
+    /// Whether a slot with this arity may have `len` entries
+    #[inline]
+    #[must_use]
+    pub const fn allows(self, len: usize) -> bool {
+        match self {
+            Self::One => len == 1,
+            Self::AtLeast(min) => len >= min,
+        }
+    }
+}
+
+/// A child slot of an [`OMKind`]; see [`OMKind::child_slots`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SlotSpec {
+    /// The name of the slot (e.g. `"arguments"`), as in [`Step`](crate::Step)s where
+    /// applicable
+    pub name: &'static str,
+    /// What the slot holds
+    pub kind: SlotKind,
+    /// How many entries the slot has
+    pub arity: Arity,
+}
+
+impl SlotSpec {
+    /// The attribution pairs of any object
+    pub const ATTRIBUTES: Self = Self::new("attributes", SlotKind::Attribute, Arity::AtLeast(0));
+    /// The applicant of an [OMA](OMKind::OMA)
+    pub const APPLICANT: Self = Self::new("applicant", SlotKind::Object, Arity::One);
+    /// The arguments of an [OMA](OMKind::OMA)
+    pub const ARGUMENTS: Self = Self::new("arguments", SlotKind::Object, Arity::AtLeast(0));
+    /// The arguments of an [OME](OMKind::OME), which may be foreign
+    pub const ERROR_ARGUMENTS: Self =
+        Self::new("arguments", SlotKind::MaybeForeign, Arity::AtLeast(0));
+    /// The binder of an [OMBIND](OMKind::OMBIND)
+    pub const BINDER: Self = Self::new("binder", SlotKind::Object, Arity::One);
+    /// The bound variables of an [OMBIND](OMKind::OMBIND)
+    pub const VARIABLES: Self = Self::new("variables", SlotKind::Variable, Arity::AtLeast(0));
+    /// The body of an [OMBIND](OMKind::OMBIND)
+    pub const BODY: Self = Self::new("body", SlotKind::Object, Arity::One);
+    /// The attribution pairs of an [OMATTR](OMKind::OMATTR), of which there is at least one
+    pub const PAIRS: Self = Self::new("attributes", SlotKind::Attribute, Arity::AtLeast(1));
+    /// The attributed object of an [OMATTR](OMKind::OMATTR)
+    pub const OBJECT: Self = Self::new("object", SlotKind::Object, Arity::One);
+
+    #[inline]
+    const fn new(name: &'static str, kind: SlotKind, arity: Arity) -> Self {
+        Self { name, kind, arity }
+    }
+}
+
+impl OMKind {
+    /** The child slots of objects of this kind, in document order.
+
+    [`OpenMath`] objects carry their attributes themselves, so every kind but
+    [OMFOREIGN](Self::OMFOREIGN) and [OMR](Self::OMR) starts with
+    [`ATTRIBUTES`](SlotSpec::ATTRIBUTES); the slots of [OMATTR](Self::OMATTR) describe the
+    element of the encodings, which [`OpenMath::slot`] never has.
+    */
+    #[must_use]
+    pub const fn child_slots(self) -> &'static [SlotSpec] {
+        match self {
+            Self::OMI | Self::OMF | Self::OMSTR | Self::OMB | Self::OMV | Self::OMS => {
+                &[SlotSpec::ATTRIBUTES]
+            }
+            Self::OMA => &[
+                SlotSpec::ATTRIBUTES,
+                SlotSpec::APPLICANT,
+                SlotSpec::ARGUMENTS,
+            ],
+            Self::OMBIND => &[
+                SlotSpec::ATTRIBUTES,
+                SlotSpec::BINDER,
+                SlotSpec::VARIABLES,
+                SlotSpec::BODY,
+            ],
+            Self::OME => &[SlotSpec::ATTRIBUTES, SlotSpec::ERROR_ARGUMENTS],
+            Self::OMATTR => &[SlotSpec::PAIRS, SlotSpec::OBJECT],
+            Self::OMFOREIGN | Self::OMR => &[],
+        }
+    }
+}
+
+/// The contents of a child slot of an [`OpenMath`] object; see [`OpenMath::slot`].
+#[derive(Debug, Clone, Copy)]
+pub enum SlotView<'a, 'om> {
+    /// A slot of arity [`One`](Arity::One)
+    One(&'a OpenMath<'om>),
+    /// A slot of kind [`Object`](SlotKind::Object) with any number of entries
+    Objects(&'a [OpenMath<'om>]),
+    /// A slot of kind [`MaybeForeign`](SlotKind::MaybeForeign)
+    MaybeForeign(&'a [OMMaybeForeign<'om, OpenMath<'om>>]),
+    /// A slot of kind [`Attribute`](SlotKind::Attribute)
+    Attributes(&'a [Attr<'om, OMMaybeForeign<'om, OpenMath<'om>>>]),
+    /// A slot of kind [`Variable`](SlotKind::Variable)
+    Variables(&'a [BoundVariable<'om>]),
+}
+
+impl<'a, 'om> SlotView<'a, 'om> {
+    /// The number of entries in the slot
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        match self {
+            Self::One(_) => 1,
+            Self::Objects(s) => s.len(),
+            Self::MaybeForeign(s) => s.len(),
+            Self::Attributes(s) => s.len(),
+            Self::Variables(s) => s.len(),
+        }
+    }
+
+    /// Whether the slot has no entries
+    #[inline]
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The object at index `i` of the slot, or the value of the attribution pair at `i`;
+    /// `None` if there is none, it is foreign, or the slot holds
+    /// [variables](Self::Variables)
+    #[must_use]
+    pub fn get(&self, i: usize) -> Option<&'a OpenMath<'om>> {
+        match *self {
+            Self::One(om) => (i == 0).then_some(om),
+            Self::Objects(s) => s.get(i),
+            Self::MaybeForeign(s) => s.get(i)?.as_om(),
+            Self::Attributes(s) => s.get(i)?.value.as_om(),
+            Self::Variables(_) => None,
+        }
+    }
+
+    /// The (non-foreign) objects in the slot in document order; for
+    /// [variables](Self::Variables), the values of their attribution pairs
+    pub fn objects(self) -> impl Iterator<Item = &'a OpenMath<'om>> {
+        fn values<'a, 'om>(
+            attributes: &'a [Attr<'om, OMMaybeForeign<'om, OpenMath<'om>>>],
+        ) -> impl Iterator<Item = &'a OpenMath<'om>> {
+            attributes.iter().filter_map(|a| a.value.as_om())
+        }
+        let (one, objects, maybe_foreign, attributes, variables): (_, &[_], &[_], &[_], &[_]) =
+            match self {
+                Self::One(om) => (Some(om), &[], &[], &[], &[]),
+                Self::Objects(s) => (None, s, &[], &[], &[]),
+                Self::MaybeForeign(s) => (None, &[], s, &[], &[]),
+                Self::Attributes(s) => (None, &[], &[], s, &[]),
+                Self::Variables(s) => (None, &[], &[], &[], s),
+            };
+        one.into_iter()
+            .chain(objects)
+            .chain(maybe_foreign.iter().filter_map(OMMaybeForeign::as_om))
+            .chain(values(attributes))
+            .chain(variables.iter().flat_map(|v| values(&v.attributes)))
+    }
+}
+
+impl<'om> OpenMath<'om> {
+    /// The contents of the child slot `spec`, or `None` if `spec` is not one of the
+    /// [child slots](OMKind::child_slots) of this object's [kind](Self::kind)
+    #[must_use]
+    pub fn slot<'a>(&'a self, spec: &SlotSpec) -> Option<SlotView<'a, 'om>> {
+        if *spec == SlotSpec::ATTRIBUTES {
+            return Some(SlotView::Attributes(self.attributes()));
+        }
+        Some(match self {
+            Self::OMA { applicant, .. } if *spec == SlotSpec::APPLICANT => SlotView::One(applicant),
+            Self::OMA { arguments, .. } if *spec == SlotSpec::ARGUMENTS => {
+                SlotView::Objects(arguments)
+            }
+            Self::OME { arguments, .. } if *spec == SlotSpec::ERROR_ARGUMENTS => {
+                SlotView::MaybeForeign(arguments)
+            }
+            Self::OMBIND { binder, .. } if *spec == SlotSpec::BINDER => SlotView::One(binder),
+            Self::OMBIND { variables, .. } if *spec == SlotSpec::VARIABLES => {
+                SlotView::Variables(variables)
+            }
+            Self::OMBIND { object, .. } if *spec == SlotSpec::BODY => SlotView::One(object),
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Arity, SlotKind, SlotSpec, SlotView};
+    use crate::{OMKind, OpenMath};
+
+    #[test]
+    fn slots_of_every_variant() {
+        for s in [
+            "(omi 1)",
+            "(omf 1.5)",
+            r#"(omstr "s")"#,
+            "(omv x)",
+            "(oms arith1 plus)",
+            "(omattr (((oms meta note) (omstr \"n\"))) (oma (oms arith1 plus) (omi 1) (omv x)))",
+            "(ombind (oms fns1 lambda) (bvar x) (omv x))",
+            "(ome (oms aritherror DivisionByZero) (omi 1))",
+        ] {
+            let om = OpenMath::from_sexpr(s).expect("is valid");
+            let slots = om.kind().child_slots();
+            assert_eq!(slots[0], SlotSpec::ATTRIBUTES, "{s}");
+            for spec in slots {
+                let view = om.slot(spec).unwrap_or_else(|| panic!("{s} has {spec:?}"));
+                assert!(spec.arity.allows(view.len()), "{s}: {spec:?}");
+                let matches = match view {
+                    SlotView::One(_) => spec.arity == Arity::One,
+                    SlotView::Objects(_) => spec.kind == SlotKind::Object,
+                    SlotView::MaybeForeign(_) => spec.kind == SlotKind::MaybeForeign,
+                    SlotView::Attributes(_) => spec.kind == SlotKind::Attribute,
+                    SlotView::Variables(_) => spec.kind == SlotKind::Variable,
+                };
+                assert!(matches, "{s}: {spec:?}");
+            }
+            // the slots of other kinds are not there
+            for spec in OMKind::ALL.iter().flat_map(|k| k.child_slots()) {
+                assert_eq!(
+                    om.slot(spec).is_some(),
+                    slots.contains(spec),
+                    "{s}: {spec:?}"
+                );
+            }
+            // and the slots make up the children
+            assert!(
+                slots
+                    .iter()
+                    .filter_map(|spec| om.slot(spec))
+                    .flat_map(SlotView::objects)
+                    .eq(om.children_with_attrs()),
+                "{s}"
+            );
+        }
+        assert!(OMKind::OMATTR.child_slots()[0].arity.allows(1));
+        assert!(!OMKind::OMATTR.child_slots()[0].arity.allows(0));
+        assert!(OMKind::OMFOREIGN.child_slots().is_empty());
+    }
+
+    #[test]
+    fn foreign_entries() {
+        let om = OpenMath::OME {
+            cdbase: None,
+            cd: "aritherror".into(),
+            name: "DivisionByZero".into(),
+            arguments: vec![
+                crate::OMMaybeForeign::Foreign {
+                    encoding: None,
+                    value: "<x/>".into(),
+                },
+                crate::OMMaybeForeign::OM(OpenMath::from_sexpr("(omi 1)").expect("is valid")),
+            ],
+            attributes: Vec::new(),
+        };
+        let view = om.slot(&SlotSpec::ERROR_ARGUMENTS).expect("is an OME");
+        assert_eq!(view.len(), 2);
+        assert_eq!(view.get(0), None);
+        assert_eq!(view.get(1).map(OpenMath::kind), Some(OMKind::OMI));
+        assert_eq!(view.objects().count(), 1);
+        assert!(om.slot(&SlotSpec::ARGUMENTS).is_none());
+    }
+}
@@ -0,0 +1,948 @@
+/*! Message envelopes and framing of the
+[Symbolic Computation Software Composability Protocol](https://www.openmath.org/standard/scscp/)
+(SCSCP).
+
+SCSCP messages are <span style="font-variant:small-caps;">OpenMath</span> objects (applications
+of `scscp1` symbols, attributed with a `call_id` and options) transmitted as XML between the
+processing instructions `<?scscp start ?>` and `<?scscp end ?>`:
+
+- [`ProcedureCall`] (`scscp1.procedure_call`) is sent by the client,
+- [`ProcedureResult`] (`scscp1.procedure_completed`) and [`ProcedureTerminated`]
+  (`scscp1.procedure_terminated`) are the server's responses,
+- [`Message`] is any of the above, and converts from and into [`OpenMath`],
+- [`SpecialProcedure`] covers the calls of the `scscp2` procedures every server provides,
+  and [`ServiceDescription`] the answer to one of them,
+- [`read_message`] and [`write_message`] handle the framing over any
+  [`BufRead`](std::io::BufRead)/[`Write`](std::io::Write).
+
+Establishing connections and negotiating versions is out of scope of this module.
+
+# Examples
+```
+use openmath::{OpenMath, de::OMObject, ser::Uri, scscp::*};
+
+let call = ProcedureCall {
+    call_id: "call-1".into(),
+    name: Uri { cdbase: None, cd: "scscp_transient_1", name: "Factorial" },
+    args: vec![OpenMath::OMI { int: 5.into(), attributes: Vec::new() }],
+    options: CallOptions::default(),
+};
+let mut wire = Vec::new();
+write_message(&mut wire, &OpenMath::from(Message::Call(call))).expect("writes");
+
+let received = read_message(&mut wire.as_slice())
+    .expect("reads")
+    .expect("contains a message");
+let om = OMObject::<OpenMath>::from_openmath_xml(&received).expect("is valid");
+let Ok(Message::Call(call)) = Message::try_from(&om) else { panic!() };
+assert_eq!(call.call_id, "call-1");
+assert_eq!(call.name.name, "Factorial");
+```
+*/
+
+use std::borrow::Cow;
+
+use crate::{Attr, Int, OMMaybeForeign, OpenMath, ser::Uri};
+
+/// The processing instruction starting a message
+pub const START: &str = "<?scscp start ?>";
+/// The processing instruction ending a message
+pub const END: &str = "<?scscp end ?>";
+/// The processing instruction aborting the current message
+pub const CANCEL: &str = "<?scscp cancel ?>";
+
+/// The content dictionary of the SCSCP envelope symbols
+pub const SCSCP1: &str = "scscp1";
+/// The content dictionary of the special procedures every server provides
+pub const SCSCP2: &str = "scscp2";
+
+/// Errors when reading, writing or interpreting SCSCP messages
+#[derive(Debug, thiserror::Error)]
+pub enum ScscpError {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+    #[error("connection closed in the middle of a message")]
+    UnexpectedEof,
+    #[error("not an SCSCP procedure call, completion or termination")]
+    NotAMessage,
+    #[error("missing `scscp1.call_id` attribution")]
+    MissingCallId,
+    #[error("malformed `scscp1.{0}`")]
+    Malformed(&'static str),
+}
+
+/// Reads the next message, i.e. everything between a [`START`] and an [`END`]
+/// processing instruction; `None` if `reader` is exhausted before a message starts.
+///
+/// Messages terminated by [`CANCEL`] are skipped, as is everything outside of messages
+/// (e.g. `<?scscp info … ?>` instructions). Processing instructions are expected on lines
+/// of their own.
+///
+/// # Errors
+/// if reading fails or `reader` is exhausted in the middle of a message.
+pub fn read_message(reader: &mut impl std::io::BufRead) -> Result<Option<String>, ScscpError> {
+    let mut line = String::new();
+    let mut message: Option<String> = None;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return if message.is_some() {
+                Err(ScscpError::UnexpectedEof)
+            } else {
+                Ok(None)
+            };
+        }
+        match (line.trim(), &mut message) {
+            (START, _) => message = Some(String::new()),
+            (END, Some(_)) => return Ok(message),
+            (CANCEL, _) => message = None,
+            (_, Some(m)) => m.push_str(&line),
+            (_, None) => (),
+        }
+    }
+}
+
+/// Writes `om` as an OMOBJ between [`START`] and [`END`] and flushes `writer`.
+///
+/// # Errors
+/// if writing fails or `om` can not be serialized.
+pub fn write_message(
+    writer: &mut impl std::io::Write,
+    om: &(impl crate::OMSerializable + ?Sized),
+) -> Result<(), ScscpError> {
+    writeln!(writer, "{START}")?;
+    writeln!(writer, "{}", om.omobject().xml(false, true))?;
+    writeln!(writer, "{END}")?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// How the server should return the result of a [`ProcedureCall`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ReturnMethod {
+    /// `scscp1.option_return_object`
+    #[default]
+    Object,
+    /// `scscp1.option_return_cookie`; the result is stored on the server
+    Cookie,
+    /// `scscp1.option_return_nothing`
+    Nothing,
+}
+
+/// The options of a [`ProcedureCall`]; limits are in bytes and milliseconds respectively.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct CallOptions {
+    pub return_method: ReturnMethod,
+    /// `scscp1.option_min_memory`
+    pub min_memory: Option<u64>,
+    /// `scscp1.option_max_memory`
+    pub max_memory: Option<u64>,
+    /// `scscp1.option_runtime`
+    pub runtime: Option<u64>,
+    /// `scscp1.option_debuglevel`
+    pub debug_level: Option<u64>,
+}
+
+/// A request to call the procedure `name` with arguments `args`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProcedureCall<'om> {
+    pub call_id: Cow<'om, str>,
+    pub name: Uri<'om>,
+    pub args: Vec<OpenMath<'om>>,
+    pub options: CallOptions,
+}
+
+/// Optional information the server attaches to its responses
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ResultInfo<'om> {
+    /// `scscp1.info_runtime`, in milliseconds
+    pub runtime: Option<u64>,
+    /// `scscp1.info_memory`, in bytes
+    pub memory: Option<u64>,
+    /// `scscp1.info_message`
+    pub message: Option<Cow<'om, str>>,
+}
+
+/// The successful response to the [`ProcedureCall`] with the same `call_id`; `result` is
+/// `None` if the call requested [`ReturnMethod::Nothing`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProcedureResult<'om> {
+    pub call_id: Cow<'om, str>,
+    pub result: Option<OpenMath<'om>>,
+    pub info: ResultInfo<'om>,
+}
+
+/// The reason a procedure call was terminated
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ProcedureError<'om> {
+    /// `scscp1.error_memory`
+    Memory(Cow<'om, str>),
+    /// `scscp1.error_runtime`
+    Runtime(Cow<'om, str>),
+    /// `scscp1.error_system_specific`
+    SystemSpecific(Cow<'om, str>),
+    /// any other [`OME`](crate::OMKind::OME)
+    Other(OpenMath<'om>),
+}
+
+/// The unsuccessful response to the [`ProcedureCall`] with the same `call_id`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProcedureTerminated<'om> {
+    pub call_id: Cow<'om, str>,
+    pub error: ProcedureError<'om>,
+    pub info: ResultInfo<'om>,
+}
+
+/// Any SCSCP message; converts from and into [`OpenMath`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Message<'om> {
+    Call(ProcedureCall<'om>),
+    Completed(ProcedureResult<'om>),
+    Terminated(ProcedureTerminated<'om>),
+}
+
+/** A call of one of the special procedures in the `scscp2` CD, which every server provides;
+becomes a [`ProcedureCall`] with [`into_call`](Self::into_call), and is recognized in one with
+[`from_call`](Self::from_call).
+
+The procedures that store objects on the server or refer to them (`store_session`,
+`store_persistent`, `retrieve` and `unbind`) exchange [OMR](crate::OMKind::OMR)s, which an
+[`OpenMath`] can not hold, and are not covered.
+*/
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SpecialProcedure<'om> {
+    /// `scscp2.get_allowed_heads`; answered with a `scscp2.symbol_set`
+    GetAllowedHeads,
+    /// `scscp2.is_allowed_head`; answered with `logic1.true` or `logic1.false`
+    IsAllowedHead(Uri<'om>),
+    /// `scscp2.get_service_description`; answered with a [`ServiceDescription`]
+    GetServiceDescription,
+    /// `scscp2.get_signature`; answered with a `scscp2.signature`
+    GetSignature(Uri<'om>),
+    /// `scscp2.get_transient_cd`, with the name of a transient content dictionary;
+    /// answered with the content dictionary, or a `scscp2.no_such_transient_cd` error
+    GetTransientCd(Cow<'om, str>),
+}
+
+/// The answer to [`SpecialProcedure::GetServiceDescription`] (`scscp2.service_description`);
+/// converts from and into [`OpenMath`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ServiceDescription<'om> {
+    pub name: Cow<'om, str>,
+    pub version: Cow<'om, str>,
+    pub description: Cow<'om, str>,
+}
+
+// ---------------------------------------------------------------------------------------
+// into OpenMath
+
+type Attributes<'om> = Vec<Attr<'om, OMMaybeForeign<'om, OpenMath<'om>>>>;
+
+const fn oms<'om>(name: &'static str) -> OpenMath<'om> {
+    OpenMath::OMS {
+        cd: Cow::Borrowed(SCSCP1),
+        name: Cow::Borrowed(name),
+        cdbase: None,
+        cdbase_inherited: false,
+        attributes: Vec::new(),
+    }
+}
+
+const fn string(string: Cow<'_, str>) -> OpenMath<'_> {
+    OpenMath::OMSTR {
+        string,
+        attributes: Vec::new(),
+    }
+}
+
+fn symbol(uri: Uri<'_>) -> OpenMath<'_> {
+    OpenMath::OMS {
+        cd: Cow::Borrowed(uri.cd),
+        name: Cow::Borrowed(uri.name),
+        cdbase: uri.cdbase.map(crate::CdBase::into_inner),
+        cdbase_inherited: false,
+        attributes: Vec::new(),
+    }
+}
+
+fn push<'om>(attributes: &mut Attributes<'om>, name: &'static str, value: OpenMath<'om>) {
+    attributes.push(Attr::new(
+        crate::SymbolRef::new(SCSCP1, name),
+        OMMaybeForeign::OM(value),
+    ));
+}
+
+fn push_int(attributes: &mut Attributes<'_>, name: &'static str, value: Option<u64>) {
+    if let Some(value) = value {
+        push(
+            attributes,
+            name,
+            OpenMath::OMI {
+                int: Int::from(value),
+                attributes: Vec::new(),
+            },
+        );
+    }
+}
+
+fn push_info<'om>(attributes: &mut Attributes<'om>, info: ResultInfo<'om>) {
+    push_int(attributes, "info_runtime", info.runtime);
+    push_int(attributes, "info_memory", info.memory);
+    if let Some(message) = info.message {
+        push(attributes, "info_message", string(message));
+    }
+}
+
+fn envelope<'om>(
+    head: &'static str,
+    arguments: Vec<OpenMath<'om>>,
+    attributes: Attributes<'om>,
+) -> OpenMath<'om> {
+    OpenMath::OMA {
+        applicant: Box::new(oms(head)),
+        arguments,
+        attributes,
+    }
+}
+
+impl<'om> From<ProcedureCall<'om>> for OpenMath<'om> {
+    fn from(call: ProcedureCall<'om>) -> Self {
+        let mut attributes = Vec::new();
+        push(&mut attributes, "call_id", string(call.call_id));
+        let options = call.options;
+        let return_method = match options.return_method {
+            ReturnMethod::Object => "option_return_object",
+            ReturnMethod::Cookie => "option_return_cookie",
+            ReturnMethod::Nothing => "option_return_nothing",
+        };
+        push(&mut attributes, return_method, string(Cow::Borrowed("")));
+        push_int(&mut attributes, "option_min_memory", options.min_memory);
+        push_int(&mut attributes, "option_max_memory", options.max_memory);
+        push_int(&mut attributes, "option_runtime", options.runtime);
+        push_int(&mut attributes, "option_debuglevel", options.debug_level);
+        let procedure = Self::OMA {
+            applicant: Box::new(symbol(call.name)),
+            arguments: call.args,
+            attributes: Vec::new(),
+        };
+        envelope("procedure_call", vec![procedure], attributes)
+    }
+}
+
+impl<'om> From<ProcedureResult<'om>> for OpenMath<'om> {
+    fn from(result: ProcedureResult<'om>) -> Self {
+        let mut attributes = Vec::new();
+        push(&mut attributes, "call_id", string(result.call_id));
+        push_info(&mut attributes, result.info);
+        envelope(
+            "procedure_completed",
+            result.result.into_iter().collect(),
+            attributes,
+        )
+    }
+}
+
+impl<'om> From<ProcedureTerminated<'om>> for OpenMath<'om> {
+    fn from(terminated: ProcedureTerminated<'om>) -> Self {
+        let mut attributes = Vec::new();
+        push(&mut attributes, "call_id", string(terminated.call_id));
+        push_info(&mut attributes, terminated.info);
+        let (name, message) = match terminated.error {
+            ProcedureError::Memory(m) => ("error_memory", m),
+            ProcedureError::Runtime(m) => ("error_runtime", m),
+            ProcedureError::SystemSpecific(m) => ("error_system_specific", m),
+            ProcedureError::Other(o) => {
+                return envelope("procedure_terminated", vec![o], attributes);
+            }
+        };
+        let error = Self::OME {
+            cd: Cow::Borrowed(SCSCP1),
+            name: Cow::Borrowed(name),
+            cdbase: None,
+            arguments: vec![OMMaybeForeign::OM(string(message))],
+            attributes: Vec::new(),
+        };
+        envelope("procedure_terminated", vec![error], attributes)
+    }
+}
+
+impl<'om> SpecialProcedure<'om> {
+    const fn name(&self) -> &'static str {
+        match self {
+            Self::GetAllowedHeads => "get_allowed_heads",
+            Self::IsAllowedHead(_) => "is_allowed_head",
+            Self::GetServiceDescription => "get_service_description",
+            Self::GetSignature(_) => "get_signature",
+            Self::GetTransientCd(_) => "get_transient_cd",
+        }
+    }
+
+    /// The call of this procedure with the given `call_id` and `options`
+    #[must_use]
+    pub fn into_call(
+        self,
+        call_id: impl Into<Cow<'om, str>>,
+        options: CallOptions,
+    ) -> ProcedureCall<'om> {
+        let name = self.name();
+        let args = match self {
+            Self::GetAllowedHeads | Self::GetServiceDescription => Vec::new(),
+            Self::IsAllowedHead(head) | Self::GetSignature(head) => vec![symbol(head)],
+            Self::GetTransientCd(cd) => vec![OpenMath::OMA {
+                applicant: Box::new(symbol(Uri {
+                    cdbase: None,
+                    cd: "meta",
+                    name: "CDName",
+                })),
+                arguments: vec![string(cd)],
+                attributes: Vec::new(),
+            }],
+        };
+        ProcedureCall {
+            call_id: call_id.into(),
+            name: Uri {
+                cdbase: None,
+                cd: SCSCP2,
+                name,
+            },
+            args,
+            options,
+        }
+    }
+}
+
+impl<'om> From<ServiceDescription<'om>> for OpenMath<'om> {
+    fn from(description: ServiceDescription<'om>) -> Self {
+        Self::OMA {
+            applicant: Box::new(symbol(Uri {
+                cdbase: None,
+                cd: SCSCP2,
+                name: "service_description",
+            })),
+            arguments: vec![
+                string(description.name),
+                string(description.version),
+                string(description.description),
+            ],
+            attributes: Vec::new(),
+        }
+    }
+}
+
+impl<'om> From<Message<'om>> for OpenMath<'om> {
+    #[inline]
+    fn from(message: Message<'om>) -> Self {
+        match message {
+            Message::Call(c) => c.into(),
+            Message::Completed(c) => c.into(),
+            Message::Terminated(t) => t.into(),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------------------
+// from OpenMath
+
+/// returns the name of the symbol, if it is in the `scscp1` CD
+fn scscp1_name<'a>(cdbase: Option<&Cow<'_, str>>, cd: &str, name: &'a str) -> Option<&'a str> {
+    (cd == SCSCP1 && standard(cdbase.map(|c| &**c))).then_some(name)
+}
+
+/// whether `cdbase` is absent or the standard one
+fn standard(cdbase: Option<&str>) -> bool {
+    cdbase.is_none_or(|c| crate::cdbase::eq_normalized(c, crate::CD_BASE))
+}
+
+/// `om` as a [`Uri`], if it is a symbol
+fn as_uri<'a>(om: &'a OpenMath<'_>) -> Option<Uri<'a>> {
+    match om {
+        OpenMath::OMS {
+            cd, name, cdbase, ..
+        } => Some(Uri {
+            cdbase: cdbase.as_deref().map(crate::CdBase::new_unchecked),
+            cd,
+            name,
+        }),
+        _ => None,
+    }
+}
+
+fn as_u64(om: &OMMaybeForeign<'_, OpenMath<'_>>, name: &'static str) -> Result<u64, ScscpError> {
+    match om {
+        OMMaybeForeign::OM(OpenMath::OMI { int, .. }) => int
+            .is_i128()
+            .and_then(|i| u64::try_from(i).ok())
+            .ok_or(ScscpError::Malformed(name)),
+        _ => Err(ScscpError::Malformed(name)),
+    }
+}
+
+fn as_str<'a>(
+    om: &'a OMMaybeForeign<'_, OpenMath<'_>>,
+    name: &'static str,
+) -> Result<Cow<'a, str>, ScscpError> {
+    match om {
+        OMMaybeForeign::OM(OpenMath::OMSTR { string, .. }) => Ok(Cow::Borrowed(string)),
+        // some systems send numeric call ids
+        OMMaybeForeign::OM(OpenMath::OMI { int, .. }) if name == "call_id" => {
+            Ok(Cow::Owned(int.to_string()))
+        }
+        _ => Err(ScscpError::Malformed(name)),
+    }
+}
+
+/// the interpreted `scscp1` attributions of a message; unknown ones are ignored
+#[derive(Default)]
+struct Envelope<'a> {
+    call_id: Option<Cow<'a, str>>,
+    options: CallOptions,
+    info: ResultInfo<'a>,
+}
+impl<'a> Envelope<'a> {
+    fn new(attributes: &'a Attributes<'_>) -> Result<Self, ScscpError> {
+        let mut ret = Self::default();
+        for a in attributes {
+            let Some(name) = scscp1_name(a.key.cdbase.as_ref(), &a.key.cd, &a.key.name) else {
+                continue;
+            };
+            let v = &a.value;
+            match name {
+                "call_id" => ret.call_id = Some(as_str(v, "call_id")?),
+                "option_return_object" => ret.options.return_method = ReturnMethod::Object,
+                "option_return_cookie" => ret.options.return_method = ReturnMethod::Cookie,
+                "option_return_nothing" => ret.options.return_method = ReturnMethod::Nothing,
+                "option_min_memory" => {
+                    ret.options.min_memory = Some(as_u64(v, "option_min_memory")?);
+                }
+                "option_max_memory" => {
+                    ret.options.max_memory = Some(as_u64(v, "option_max_memory")?);
+                }
+                "option_runtime" => ret.options.runtime = Some(as_u64(v, "option_runtime")?),
+                "option_debuglevel" => {
+                    ret.options.debug_level = Some(as_u64(v, "option_debuglevel")?);
+                }
+                "info_runtime" => ret.info.runtime = Some(as_u64(v, "info_runtime")?),
+                "info_memory" => ret.info.memory = Some(as_u64(v, "info_memory")?),
+                "info_message" => ret.info.message = Some(as_str(v, "info_message")?),
+                _ => (),
+            }
+        }
+        Ok(ret)
+    }
+}
+
+impl<'a, 'om: 'a> TryFrom<&'a OpenMath<'om>> for Message<'a> {
+    type Error = ScscpError;
+    fn try_from(om: &'a OpenMath<'om>) -> Result<Self, Self::Error> {
+        let OpenMath::OMA {
+            applicant,
+            arguments,
+            attributes,
+        } = om
+        else {
+            return Err(ScscpError::NotAMessage);
+        };
+        let OpenMath::OMS {
+            cd, name, cdbase, ..
+        } = &**applicant
+        else {
+            return Err(ScscpError::NotAMessage);
+        };
+        let Some(head) = scscp1_name(cdbase.as_ref(), cd, name) else {
+            return Err(ScscpError::NotAMessage);
+        };
+        let envelope = Envelope::new(attributes)?;
+        let call_id = envelope.call_id.ok_or(ScscpError::MissingCallId)?;
+        match (head, arguments.as_slice()) {
+            (
+                "procedure_call",
+                [
+                    OpenMath::OMA {
+                        applicant,
+                        arguments,
+                        ..
+                    },
+                ],
+            ) => {
+                let OpenMath::OMS {
+                    cd, name, cdbase, ..
+                } = &**applicant
+                else {
+                    return Err(ScscpError::Malformed("procedure_call"));
+                };
+                Ok(Self::Call(ProcedureCall {
+                    call_id,
+                    name: Uri {
+                        cdbase: cdbase.as_deref().map(crate::CdBase::new_unchecked),
+                        cd,
+                        name,
+                    },
+                    args: arguments.clone(),
+                    options: envelope.options,
+                }))
+            }
+            ("procedure_completed", [] | [_]) => Ok(Self::Completed(ProcedureResult {
+                call_id,
+                result: arguments.first().cloned(),
+                info: envelope.info,
+            })),
+            ("procedure_terminated", [error @ OpenMath::OME { .. }]) => {
+                Ok(Self::Terminated(ProcedureTerminated {
+                    call_id,
+                    error: procedure_error(error),
+                    info: envelope.info,
+                }))
+            }
+            ("procedure_call", _) => Err(ScscpError::Malformed("procedure_call")),
+            ("procedure_completed", _) => Err(ScscpError::Malformed("procedure_completed")),
+            ("procedure_terminated", _) => Err(ScscpError::Malformed("procedure_terminated")),
+            _ => Err(ScscpError::NotAMessage),
+        }
+    }
+}
+
+impl<'a> SpecialProcedure<'a> {
+    /** The special procedure `call` calls, if it calls one.
+
+    # Errors
+    if `call` calls a procedure in `scscp2` with the wrong arguments
+    */
+    pub fn from_call(call: &'a ProcedureCall<'_>) -> Result<Option<Self>, ScscpError> {
+        if call.name.cd != SCSCP2 || !standard(call.name.cdbase.as_deref()) {
+            return Ok(None);
+        }
+        let (name, args) = (call.name.name, call.args.as_slice());
+        let head = |name| match args {
+            [head] => as_uri(head).ok_or(ScscpError::Malformed(name)),
+            _ => Err(ScscpError::Malformed(name)),
+        };
+        Ok(Some(match name {
+            "get_allowed_heads" if args.is_empty() => Self::GetAllowedHeads,
+            "get_service_description" if args.is_empty() => Self::GetServiceDescription,
+            "get_allowed_heads" => return Err(ScscpError::Malformed("get_allowed_heads")),
+            "get_service_description" => {
+                return Err(ScscpError::Malformed("get_service_description"));
+            }
+            "is_allowed_head" => Self::IsAllowedHead(head("is_allowed_head")?),
+            "get_signature" => Self::GetSignature(head("get_signature")?),
+            "get_transient_cd" => Self::GetTransientCd(
+                transient_cd(args).ok_or(ScscpError::Malformed("get_transient_cd"))?,
+            ),
+            _ => return Ok(None),
+        }))
+    }
+}
+
+/// the name in the argument `meta.CDName(name)` of `scscp2.get_transient_cd`
+fn transient_cd<'a>(args: &'a [OpenMath<'_>]) -> Option<Cow<'a, str>> {
+    let [
+        OpenMath::OMA {
+            applicant,
+            arguments,
+            ..
+        },
+    ] = args
+    else {
+        return None;
+    };
+    let (Some(cd_name), [OpenMath::OMSTR { string, .. }]) =
+        (as_uri(applicant), arguments.as_slice())
+    else {
+        return None;
+    };
+    (cd_name.cd == "meta" && cd_name.name == "CDName" && standard(cd_name.cdbase.as_deref()))
+        .then_some(Cow::Borrowed(&**string))
+}
+
+impl<'a, 'om: 'a> TryFrom<&'a OpenMath<'om>> for ServiceDescription<'a> {
+    type Error = ScscpError;
+    fn try_from(om: &'a OpenMath<'om>) -> Result<Self, Self::Error> {
+        const MALFORMED: ScscpError = ScscpError::Malformed("service_description");
+        let OpenMath::OMA {
+            applicant,
+            arguments,
+            ..
+        } = om
+        else {
+            return Err(MALFORMED);
+        };
+        let head = as_uri(applicant).ok_or(MALFORMED)?;
+        if head.cd != SCSCP2
+            || head.name != "service_description"
+            || !standard(head.cdbase.as_deref())
+        {
+            return Err(MALFORMED);
+        }
+        let [name, version, description] = arguments.as_slice() else {
+            return Err(MALFORMED);
+        };
+        let string = |om: &'a OpenMath<'om>| match om {
+            OpenMath::OMSTR { string, .. } => Ok(Cow::Borrowed(&**string)),
+            _ => Err(MALFORMED),
+        };
+        Ok(Self {
+            name: string(name)?,
+            version: string(version)?,
+            description: string(description)?,
+        })
+    }
+}
+
+fn procedure_error<'a>(error: &'a OpenMath<'_>) -> ProcedureError<'a> {
+    if let OpenMath::OME {
+        cd,
+        name,
+        cdbase,
+        arguments,
+        ..
+    } = error
+        && let [OMMaybeForeign::OM(OpenMath::OMSTR { string, .. })] = arguments.as_slice()
+    {
+        let message = Cow::Borrowed(&**string);
+        match scscp1_name(cdbase.as_ref(), cd, name) {
+            Some("error_memory") => return ProcedureError::Memory(message),
+            Some("error_runtime") => return ProcedureError::Runtime(message),
+            Some("error_system_specific") => return ProcedureError::SystemSpecific(message),
+            _ => (),
+        }
+    }
+    ProcedureError::Other(error.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::de::OMObject;
+
+    /// `om`, written and read back as a message
+    fn over_the_wire(om: &OpenMath<'_>) -> String {
+        let mut wire = Vec::new();
+        write_message(&mut wire, om).expect("writes");
+        read_message(&mut wire.as_slice())
+            .expect("reads")
+            .expect("contains a message")
+    }
+
+    fn roundtrip(message: &Message<'_>) {
+        let xml = over_the_wire(&OpenMath::from(message.clone()));
+        let read = OMObject::<OpenMath>::from_openmath_xml(&xml).expect("is valid");
+        assert_eq!(&Message::try_from(&read).expect("is a message"), message);
+    }
+
+    fn int(i: i32) -> OpenMath<'static> {
+        OpenMath::OMI {
+            int: i.into(),
+            attributes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn scscp_roundtrip() {
+        roundtrip(&Message::Call(ProcedureCall {
+            call_id: "5rqw5".into(),
+            name: Uri {
+                cdbase: Some(crate::CdBase::OPENMATH),
+                cd: "scscp_transient_1",
+                name: "WS_Factorial",
+            },
+            args: vec![int(5)],
+            options: CallOptions {
+                return_method: ReturnMethod::Cookie,
+                runtime: Some(300_000),
+                max_memory: Some(1 << 30),
+                ..CallOptions::default()
+            },
+        }));
+        roundtrip(&Message::Completed(ProcedureResult {
+            call_id: "5rqw5".into(),
+            result: Some(int(120)),
+            info: ResultInfo {
+                runtime: Some(12),
+                memory: None,
+                message: Some("all good".into()),
+            },
+        }));
+        roundtrip(&Message::Completed(ProcedureResult {
+            call_id: "5rqw5".into(),
+            result: None,
+            info: ResultInfo::default(),
+        }));
+        roundtrip(&Message::Terminated(ProcedureTerminated {
+            call_id: "5rqw5".into(),
+            error: ProcedureError::Runtime("timeout".into()),
+            info: ResultInfo::default(),
+        }));
+    }
+    #[test]
+    fn scscp_spec_example() {
+        // procedure call example from the SCSCP specification
+        let xml = r#"<OMOBJ>
+            <OMATTR>
+              <OMATP>
+                <OMS cd="scscp1" name="call_id"/><OMSTR>5rqw5</OMSTR>
+                <OMS cd="scscp1" name="option_return_object"/><OMSTR></OMSTR>
+              </OMATP>
+              <OMA>
+                <OMS cd="scscp1" name="procedure_call"/>
+                <OMA>
+                  <OMS cd="scscp_transient_1" name="WS_Factorial"/>
+                  <OMI>5</OMI>
+                </OMA>
+              </OMA>
+            </OMATTR>
+          </OMOBJ>"#;
+        let om = OMObject::<OpenMath>::from_openmath_xml(xml).expect("is valid");
+        let Message::Call(call) = Message::try_from(&om).expect("is a message") else {
+            panic!("not a procedure call")
+        };
+        assert_eq!(call.call_id, "5rqw5");
+        assert_eq!(
+            (call.name.cd, call.name.name),
+            ("scscp_transient_1", "WS_Factorial")
+        );
+        assert_eq!(call.args, [int(5)]);
+        assert_eq!(call.options, CallOptions::default());
+    }
+
+    #[test]
+    fn scscp2_roundtrip() {
+        // as read back
+        let plus = Uri {
+            cdbase: Some(crate::CdBase::OPENMATH),
+            cd: "arith1",
+            name: "plus",
+        };
+        for special in [
+            SpecialProcedure::GetAllowedHeads,
+            SpecialProcedure::IsAllowedHead(plus.clone()),
+            SpecialProcedure::GetServiceDescription,
+            SpecialProcedure::GetSignature(plus),
+            SpecialProcedure::GetTransientCd("scscp_transient_1".into()),
+        ] {
+            let call = special.clone().into_call("c1", CallOptions::default());
+            assert_eq!(
+                SpecialProcedure::from_call(&call).expect("is well-formed"),
+                Some(special.clone())
+            );
+            let xml = over_the_wire(&Message::Call(call).into());
+            let read = OMObject::<OpenMath>::from_openmath_xml(&xml).expect("is valid");
+            let Ok(Message::Call(call)) = Message::try_from(&read) else {
+                panic!("not a procedure call")
+            };
+            assert_eq!(
+                SpecialProcedure::from_call(&call).expect("is well-formed"),
+                Some(special)
+            );
+        }
+
+        let description = ServiceDescription {
+            name: "MockCAS".into(),
+            version: "1.0".into(),
+            description: "answers everything".into(),
+        };
+        let om = OpenMath::from(description.clone());
+        assert_eq!(
+            ServiceDescription::try_from(&om).expect("is a description"),
+            description
+        );
+        let xml = over_the_wire(&om);
+        let read = OMObject::<OpenMath>::from_openmath_xml(&xml).expect("is valid");
+        assert_eq!(
+            ServiceDescription::try_from(&read).expect("is a description"),
+            description
+        );
+    }
+
+    #[test]
+    fn scscp2_calls() {
+        let call = |xml: &str| {
+            let xml = format!(
+                r#"<OMOBJ><OMATTR><OMATP><OMS cd="scscp1" name="call_id"/><OMSTR>1</OMSTR></OMATP>
+                <OMA><OMS cd="scscp1" name="procedure_call"/>{xml}</OMA></OMATTR></OMOBJ>"#
+            );
+            let om = OMObject::<OpenMath>::from_openmath_xml(&xml).expect("is valid");
+            let Ok(Message::Call(call)) = Message::try_from(&om) else {
+                panic!("not a procedure call")
+            };
+            SpecialProcedure::from_call(&call).map(|s| s.map(|s| format!("{s:?}")))
+        };
+        assert_eq!(
+            call(
+                r#"<OMA><OMS cd="scscp2" name="get_transient_cd"/>
+                  <OMA><OMS cd="meta" name="CDName"/><OMSTR>scscp_transient_1</OMSTR></OMA>
+                </OMA>"#
+            )
+            .expect("is well-formed")
+            .as_deref(),
+            Some(r#"GetTransientCd("scscp_transient_1")"#)
+        );
+        assert_eq!(
+            call(r#"<OMA><OMS cd="scscp_transient_1" name="Factorial"/><OMI>5</OMI></OMA>"#)
+                .expect("is well-formed"),
+            None
+        );
+        assert!(matches!(
+            call(r#"<OMA><OMS cd="scscp2" name="get_signature"/><OMI>5</OMI></OMA>"#),
+            Err(ScscpError::Malformed("get_signature"))
+        ));
+        assert!(matches!(
+            call(r#"<OMA><OMS cd="scscp2" name="get_allowed_heads"/><OMI>5</OMI></OMA>"#),
+            Err(ScscpError::Malformed("get_allowed_heads"))
+        ));
+    }
+
+    #[test]
+    fn scscp_framing() {
+        let wire = format!(
+            "<?scscp version=\"1.3\" ?>\n{START}\n<OMOBJ><OMI>1</OMI></OMOBJ>\n{CANCEL}\n\
+             {START}\n<OMOBJ>\n<OMI>2</OMI>\n</OMOBJ>\n{END}\n  {START}  \n<OMOBJ>"
+        );
+        let mut reader = wire.as_bytes();
+        assert_eq!(
+            read_message(&mut reader).expect("reads").as_deref(),
+            Some("<OMOBJ>\n<OMI>2</OMI>\n</OMOBJ>\n")
+        );
+        assert!(matches!(
+            read_message(&mut reader),
+            Err(ScscpError::UnexpectedEof)
+        ));
+        assert!(matches!(read_message(&mut reader), Ok(None)));
+    }
+
+    #[test]
+    fn scscp_errors() {
+        let om = |s: &str| OpenMath::from_sexpr(s).expect("is valid");
+        let err = |o: &OpenMath<'_>| Message::try_from(o).expect_err("is not a message");
+        assert!(matches!(
+            err(&om("(oma (oms arith1 plus) (omi 1))")),
+            ScscpError::NotAMessage
+        ));
+        assert!(matches!(
+            err(&om("(oma (oms scscp1 procedure_completed) (omi 1))")),
+            ScscpError::MissingCallId
+        ));
+        assert!(matches!(
+            err(&om(
+                r#"(omattr (((oms scscp1 call_id) (omstr "1"))) (oma (oms scscp1 procedure_call) (omi 1)))"#
+            )),
+            ScscpError::Malformed("procedure_call")
+        ));
+        assert!(matches!(
+            err(&om(
+                r#"(omattr (((oms scscp1 call_id) (omstr "1")) ((oms scscp1 option_runtime) (omi -1))) (oma (oms scscp1 procedure_completed)))"#
+            )),
+            ScscpError::Malformed("option_runtime")
+        ));
+        let other = om(
+            r#"(omattr (((oms scscp1 call_id) (omi 7))) (oma (oms scscp1 procedure_terminated) (ome (oms error unhandled_symbol) (omstr "foo"))))"#,
+        );
+        let Ok(Message::Terminated(t)) = Message::try_from(&other) else {
+            panic!("not a termination")
+        };
+        assert_eq!(t.call_id, "7");
+        assert!(matches!(
+            t.error,
+            ProcedureError::Other(OpenMath::OME { .. })
+        ));
+    }
+}
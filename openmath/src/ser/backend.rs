@@ -0,0 +1,203 @@
+/*! Implementing a serializer backend
+
+Output formats are implemented as [`OMSerializer`](super::OMSerializer)s; the crate's own
+backends (XML, JSON via serde, S-expressions, [`OMDisplay`](super::OMDisplay)) use only
+the public API, so third-party backends are first-class citizens. This module documents the
+contract such an implementation has to fulfill; [`check_serializer`](super::testing::check_serializer)
+checks (most of) it automatically.
+
+# The contract
+- Every method consumes `self` and serializes a *complete* node, including all of its
+  children; i.e. children are serialized by calling
+  [`as_openmath`](super::OMSerializable::as_openmath) on them with a new serializer for
+  each child, usually of type [`SubSerializer`](super::OMSerializer::SubSerializer).
+- Every child (heads, arguments, attribute keys and values, bound variables, bodies) is
+  serialized *exactly once* and in order. Iterator lengths reported via
+  [`ExactSizeIterator`] may be used as capacity hints, but not to decide the structure of
+  the output (e.g. whether to write an empty `<OMBVAR/>`): a buggy iterator may misreport
+  its length, which must not result in malformed output. Peek at the first item instead,
+  or fail if the number of items differs from the reported one (e.g. for formats that
+  write the length upfront).
+- [`current_cdbase`](super::OMSerializer::current_cdbase) returns the cdbase of the nearest
+  ancestor (or the node itself) that called [`with_cdbase`](super::OMSerializer::with_cdbase),
+  or the initial cdbase (usually [`CD_BASE`](crate::CD_BASE)) if there is none.
+- A cdbase set via [`with_cdbase`](super::OMSerializer::with_cdbase) applies to the node
+  produced by the returned serializer and all of its descendants, but not to its siblings.
+  Children may in turn call [`with_cdbase`](super::OMSerializer::with_cdbase) themselves.
+  Whether a cdbase equal to the inherited one is emitted again is up to the backend.
+- [`OMAttr::symbol`](super::OMAttr::symbol) is called before
+  [`OMAttr::value`](super::OMAttr::value) consumes the attribute.
+- Entry points (like [`OMSerializable::xml`](super::OMSerializable::xml)) consult
+  [`OMSerializable::cdbase`](super::OMSerializable::cdbase) of the *root* object only;
+  [`OMSerializable`](super::OMSerializable)s below it call
+  [`with_cdbase`](super::OMSerializer::with_cdbase) instead.
+- Failures specific to the backend are reported via [`Error::custom`](super::Error::custom);
+  [`OMSerializable`](super::OMSerializable)s use it for their own errors, too.
+- [`omr`](super::OMSerializer::omr) fails by default; backends for formats that can
+  represent references should override it.
+- [`oma_streamed`](super::OMSerializer::oma_streamed) collects all arguments and calls
+  [`oma`](super::OMSerializer::oma) by default; backends that can write arguments as they
+  are produced should override it.
+- [`omattr_parts`](super::OMSerializer::omattr_parts) adapts its pairs to
+  [`OMAttr`](super::OMAttr)s and calls [`omattr`](super::OMSerializer::omattr) by default;
+  overrides have to call [`into_parts`](super::IntoOMAttr::into_parts) exactly once per
+  pair, too.
+
+[`AsOMS::as_oms`](super::AsOMS::as_oms) is not meant to be overridden; backends handle
+[OMS](crate::OMKind::OMS)s only via [`oms`](super::OMSerializer::oms).
+
+# A reference implementation
+The following backend does not produce any output, but computes the number of nodes, the
+depth and the set of cdbases used by an object:
+```
+use std::{borrow::Cow, collections::BTreeSet};
+use openmath::{
+    OMSerializable, OpenMath,
+    ser::{AsOMS, BindVar, OMAttr, OMOrForeign, OMSerializer, testing::check_serializer},
+};
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Stats {
+    nodes: usize,
+    depth: usize,
+    cdbases: BTreeSet<String>,
+}
+impl Stats {
+    /// Adds a child node
+    fn child(&mut self, child: Self) {
+        self.nodes += child.nodes;
+        self.depth = self.depth.max(child.depth + 1);
+        self.cdbases.extend(child.cdbases);
+    }
+}
+
+#[derive(Debug)]
+struct Failed(String);
+impl openmath::ser::Error for Failed {
+    fn custom(err: impl std::fmt::Display) -> Self {
+        Self(err.to_string())
+    }
+}
+
+struct Metrics<'s>(Cow<'s, str>);
+impl Metrics<'_> {
+    /// The stats of a leaf; every node (not only OMSs) counts its cdbase as used
+    fn leaf(&self) -> Stats {
+        Stats {
+            nodes: 1,
+            depth: 0,
+            cdbases: BTreeSet::from([self.0.to_string()]),
+        }
+    }
+    fn child(&self, o: impl OMSerializable) -> Result<Stats, Failed> {
+        // children inherit the current cdbase; a borrowed one suffices
+        o.as_openmath(Metrics(Cow::Borrowed(&self.0)))
+    }
+    fn foreign(&self, o: impl OMOrForeign) -> Result<Stats, Failed> {
+        match o.om_or_foreign() {
+            openmath::either::Either::Left(o) => self.child(o),
+            openmath::either::Either::Right(_) => Ok(self.leaf()),
+        }
+    }
+    fn attrs(&self, stats: &mut Stats, attrs: impl Iterator<Item: OMAttr>) -> Result<(), Failed> {
+        for a in attrs {
+            stats.child(self.child(a.symbol().as_oms())?);
+            stats.child(self.foreign(a.value())?);
+        }
+        Ok(())
+    }
+}
+
+impl<'s> OMSerializer<'s> for Metrics<'s> {
+    type Ok = Stats;
+    type Err = Failed;
+    type SubSerializer<'ns> = Metrics<'ns> where 's: 'ns;
+    fn current_cdbase(&self) -> &str {
+        &self.0
+    }
+    fn with_cdbase<'ns>(self, cdbase: impl Into<Cow<'ns, str>>) -> Result<Metrics<'ns>, Failed>
+    where
+        's: 'ns,
+    {
+        Ok(Metrics(cdbase.into()))
+    }
+    fn omi(self, _: &openmath::Int) -> Result<Stats, Failed> {
+        Ok(self.leaf())
+    }
+    fn omf(self, _: f64) -> Result<Stats, Failed> {
+        Ok(self.leaf())
+    }
+    fn omstr(self, _: impl std::fmt::Display) -> Result<Stats, Failed> {
+        Ok(self.leaf())
+    }
+    fn omb(self, _: impl ExactSizeIterator<Item = u8>) -> Result<Stats, Failed> {
+        Ok(self.leaf())
+    }
+    fn omv(self, _: impl std::fmt::Display) -> Result<Stats, Failed> {
+        Ok(self.leaf())
+    }
+    fn oms(self, _: impl std::fmt::Display, _: impl std::fmt::Display) -> Result<Stats, Failed> {
+        Ok(self.leaf())
+    }
+    fn oma(
+        self,
+        head: impl OMSerializable,
+        args: impl ExactSizeIterator<Item: OMSerializable>,
+    ) -> Result<Stats, Failed> {
+        let mut stats = self.leaf();
+        stats.child(self.child(head)?);
+        for a in args {
+            stats.child(self.child(a)?);
+        }
+        Ok(stats)
+    }
+    fn omattr(
+        self,
+        attrs: impl ExactSizeIterator<Item: OMAttr>,
+        atp: impl OMSerializable,
+    ) -> Result<Stats, Failed> {
+        let mut stats = self.leaf();
+        self.attrs(&mut stats, attrs)?;
+        stats.child(self.child(atp)?);
+        Ok(stats)
+    }
+    fn ome(
+        self,
+        error: impl AsOMS,
+        args: impl ExactSizeIterator<Item: OMOrForeign>,
+    ) -> Result<Stats, Failed> {
+        let mut stats = self.leaf();
+        stats.child(self.child(error.as_oms())?);
+        for a in args {
+            stats.child(self.foreign(a)?);
+        }
+        Ok(stats)
+    }
+    fn ombind(
+        self,
+        head: impl OMSerializable,
+        vars: impl ExactSizeIterator<Item: BindVar>,
+        body: impl OMSerializable,
+    ) -> Result<Stats, Failed> {
+        let mut stats = self.leaf();
+        stats.child(self.child(head)?);
+        for v in vars {
+            let mut var = self.leaf();
+            self.attrs(&mut var, v.attrs())?;
+            stats.child(var);
+        }
+        stats.child(self.child(body)?);
+        Ok(stats)
+    }
+}
+
+let om = OpenMath::from_sexpr("(oma (oms arith1 plus) (omi 1) (oma (oms arith1 times) (omv x) (omv y)))")
+    .expect("is valid");
+let stats = om.as_openmath(Metrics(Cow::Borrowed(openmath::CD_BASE))).expect("never fails");
+assert_eq!((stats.nodes, stats.depth), (7, 2));
+
+// panics if the contract is violated:
+let outputs = check_serializer(|| Metrics(Cow::Borrowed(openmath::CD_BASE)));
+assert!(outputs.iter().all(|(_, stats)| stats.nodes > 0));
+```
+*/
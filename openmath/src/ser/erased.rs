@@ -0,0 +1,408 @@
+//! A dyn-compatible bridge for [`OMSerializable`]; see [`ErasedOMSerializable`]
+
+use std::borrow::Cow;
+
+use super::{AsOMS, BindVar, OMAttr, OMOrForeign, OMSerializable, OMSerializer};
+use crate::{Attr, BoundVariable, OMMaybeForeign, OpenMath, SymbolRef, either::Either};
+
+/// Errors when [building](ErasedOMSerializable::erased_build) an [`OpenMath`] from an
+/// [`OMSerializable`]; i.e. the [custom](super::Error::custom) errors of the latter.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{0}")]
+pub struct BuildError(pub String);
+impl super::Error for BuildError {
+    #[inline]
+    fn custom(err: impl std::fmt::Display) -> Self {
+        Self(err.to_string())
+    }
+}
+
+/** A dyn-compatible version of [`OMSerializable`], whose
+[`as_openmath`](OMSerializable::as_openmath) is generic over the serializer; e.g. for
+heterogeneous lists of things to serialize, registered at runtime.
+
+Implemented for every [`OMSerializable`], and in turn, `dyn ErasedOMSerializable` (and
+hence <code>[Box]<dyn ErasedOMSerializable></code>) is [`OMSerializable`] by
+[building](Self::erased_build) an [`OpenMath`] first; the other methods serialize directly.
+
+# Examples
+```
+use openmath::{OMSerializable, OpenMath, ser::ErasedOMSerializable};
+
+let terms: Vec<Box<dyn ErasedOMSerializable>> = vec![
+    Box::new(2),
+    Box::new("two"),
+    Box::new(OpenMath::from_sexpr("(oma (oms arith1 plus) (omi 1) (omi 1))").expect("is valid")),
+];
+# #[cfg(feature = "xml-write")]
+assert_eq!(
+    terms.iter().map(|t| t.xml(false).to_string()).collect::<Vec<_>>(),
+    [
+        "<OMI>2</OMI>",
+        "<OMSTR>two</OMSTR>",
+        r#"<OMA><OMS cd="arith1" name="plus"/><OMI>1</OMI><OMI>1</OMI></OMA>"#,
+    ]
+);
+assert_eq!(terms[0].erased_build(), Ok(OpenMath::OMI { int: 2.into(), attributes: Vec::new() }));
+```
+*/
+pub trait ErasedOMSerializable {
+    /// Writes the <span style="font-variant:small-caps;">OpenMath</span> XML of this object
+    /// to `out`, like [`OMSerializable::try_xml_with`].
+    ///
+    /// # Errors
+    /// see [`OMSerializable::try_xml`]
+    #[cfg(feature = "xml-write")]
+    fn erased_xml(
+        &self,
+        out: &mut dyn std::fmt::Write,
+        options: super::Options,
+    ) -> Result<(), super::XmlWriteError>;
+
+    /// Serializes this object into a JSON value, like
+    /// [`OMSerializable::openmath_serde`].
+    ///
+    /// # Errors
+    /// iff [as_openmath](OMSerializable::as_openmath) errors.
+    #[cfg(all(feature = "serde", feature = "serde_json"))]
+    fn erased_json(&self) -> Result<serde_json::Value, serde_json::Error>;
+
+    /// Builds this object as an [`OpenMath`] (relative to [`CD_BASE`](crate::CD_BASE));
+    /// symbols with a cdbase other than that keep it explicitly.
+    ///
+    /// # Errors
+    /// iff [as_openmath](OMSerializable::as_openmath) errors, or uses
+    /// [OMR](crate::OMKind::OMR)s or raw XML, which [`OpenMath`] can not represent.
+    fn erased_build(&self) -> Result<OpenMath<'static>, BuildError>;
+}
+
+impl<T: OMSerializable + ?Sized> ErasedOMSerializable for T {
+    #[cfg(feature = "xml-write")]
+    fn erased_xml(
+        &self,
+        mut out: &mut dyn std::fmt::Write,
+        options: super::Options,
+    ) -> Result<(), super::XmlWriteError> {
+        super::xml::try_write(
+            &super::xml::XmlDisplay {
+                pretty: options.pretty,
+                sort: options.sort_attributes,
+                format: None,
+                o: self,
+            },
+            &mut out,
+        )
+    }
+
+    #[cfg(all(feature = "serde", feature = "serde_json"))]
+    fn erased_json(&self) -> Result<serde_json::Value, serde_json::Error> {
+        serde_json::to_value(self.openmath_serde())
+    }
+
+    fn erased_build(&self) -> Result<OpenMath<'static>, BuildError> {
+        let cdbase = self.cdbase().unwrap_or(crate::CD_BASE);
+        self.as_openmath(Build {
+            cdbase: Cow::Borrowed(cdbase),
+            own: cdbase != crate::CD_BASE,
+        })
+    }
+}
+
+impl OMSerializable for dyn ErasedOMSerializable + '_ {
+    #[inline]
+    fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        self.erased_build()
+            .map_err(super::Error::custom)?
+            .as_openmath(serializer)
+    }
+}
+
+impl OMSerializable for dyn ErasedOMSerializable + Send + Sync + '_ {
+    #[inline]
+    fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        (self as &dyn ErasedOMSerializable).as_openmath(serializer)
+    }
+}
+
+impl<T: OMSerializable + ?Sized> OMSerializable for Box<T> {
+    #[inline]
+    fn cdbase(&self) -> Option<&str> {
+        T::cdbase(self)
+    }
+    #[inline]
+    fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        T::as_openmath(self, serializer)
+    }
+}
+
+/// The key of an attribution pair for `symbol`, with an explicit cdbase only if it differs
+/// from `cdbase`
+pub fn attr_key(symbol: &impl AsOMS, cdbase: &str) -> SymbolRef<'static> {
+    SymbolRef {
+        cdbase: symbol
+            .cdbase_uri(cdbase)
+            .map(|b| Cow::Owned(b.as_str().to_string())),
+        cd: Cow::Owned(symbol.cd().to_string()),
+        name: Cow::Owned(symbol.name().to_string()),
+    }
+}
+
+/// Builds an [`OpenMath`]; `own` is whether the cdbase was set for this node (rather than
+/// inherited from its parent)
+struct Build<'s> {
+    cdbase: Cow<'s, str>,
+    own: bool,
+}
+impl Build<'_> {
+    fn child(&self, o: impl OMSerializable) -> Result<OpenMath<'static>, BuildError> {
+        o.as_openmath(Build {
+            cdbase: Cow::Borrowed(&self.cdbase),
+            own: false,
+        })
+    }
+
+    fn foreign(
+        &self,
+        o: impl OMOrForeign,
+    ) -> Result<OMMaybeForeign<'static, OpenMath<'static>>, BuildError> {
+        Ok(match o.om_or_foreign() {
+            Either::Left(o) => OMMaybeForeign::OM(self.child(o)?),
+            Either::Right((encoding, value)) => OMMaybeForeign::Foreign {
+                encoding: encoding.map(|e| Cow::Owned(e.to_string())),
+                value: Cow::Owned(value.to_string()),
+            },
+        })
+    }
+
+    fn attrs(
+        &self,
+        attrs: impl Iterator<Item: OMAttr>,
+    ) -> Result<Vec<Attr<'static, OMMaybeForeign<'static, OpenMath<'static>>>>, BuildError> {
+        attrs
+            .map(|a| {
+                let key = attr_key(&a.symbol(), &self.cdbase);
+                Ok(Attr::new(key, self.foreign(a.value())?))
+            })
+            .collect()
+    }
+
+    /// The cdbase of a symbol at this node, like after reading it
+    fn symbol_cdbase(&self) -> Cow<'static, str> {
+        if crate::cdbase::eq_normalized(&self.cdbase, crate::CD_BASE) {
+            Cow::Borrowed(crate::CD_BASE)
+        } else {
+            Cow::Owned(self.cdbase.to_string())
+        }
+    }
+}
+
+impl<'s> OMSerializer<'s> for Build<'s> {
+    type Ok = OpenMath<'static>;
+    type Err = BuildError;
+    type SubSerializer<'ns>
+        = Build<'ns>
+    where
+        's: 'ns;
+
+    #[inline]
+    fn current_cdbase(&self) -> &str {
+        &self.cdbase
+    }
+
+    #[inline]
+    fn with_cdbase<'ns>(self, cdbase: impl Into<Cow<'ns, str>>) -> Result<Build<'ns>, BuildError>
+    where
+        's: 'ns,
+    {
+        Ok(Build {
+            cdbase: cdbase.into(),
+            own: true,
+        })
+    }
+
+    fn omi(self, value: &crate::Int) -> Result<Self::Ok, Self::Err> {
+        Ok(OpenMath::OMI {
+            int: value.clone().into_owned(),
+            attributes: Vec::new(),
+        })
+    }
+
+    fn omf(self, value: f64) -> Result<Self::Ok, Self::Err> {
+        Ok(OpenMath::OMF {
+            float: value.into(),
+            attributes: Vec::new(),
+        })
+    }
+
+    fn omstr(self, string: impl std::fmt::Display) -> Result<Self::Ok, Self::Err> {
+        Ok(OpenMath::OMSTR {
+            string: Cow::Owned(string.to_string()),
+            attributes: Vec::new(),
+        })
+    }
+
+    fn omb(self, bytes: impl ExactSizeIterator<Item = u8>) -> Result<Self::Ok, Self::Err> {
+        Ok(OpenMath::OMB {
+            bytes: Cow::Owned(bytes.collect()),
+            attributes: Vec::new(),
+        })
+    }
+
+    fn omv(self, name: impl std::fmt::Display) -> Result<Self::Ok, Self::Err> {
+        Ok(OpenMath::OMV {
+            name: Cow::Owned(name.to_string()),
+            attributes: Vec::new(),
+        })
+    }
+
+    fn oms(
+        self,
+        cd: impl std::fmt::Display,
+        name: impl std::fmt::Display,
+    ) -> Result<Self::Ok, Self::Err> {
+        Ok(OpenMath::OMS {
+            cd: Cow::Owned(cd.to_string()),
+            name: Cow::Owned(name.to_string()),
+            cdbase: Some(self.symbol_cdbase()),
+            cdbase_inherited: !self.own,
+            attributes: Vec::new(),
+        })
+    }
+
+    fn oma(
+        self,
+        head: impl OMSerializable,
+        args: impl ExactSizeIterator<Item: OMSerializable>,
+    ) -> Result<Self::Ok, Self::Err> {
+        let applicant = Box::new(self.child(head)?);
+        let arguments = args.map(|a| self.child(a)).collect::<Result<_, _>>()?;
+        Ok(OpenMath::OMA {
+            applicant,
+            arguments,
+            attributes: Vec::new(),
+        })
+    }
+
+    fn omattr(
+        self,
+        attrs: impl ExactSizeIterator<Item: OMAttr>,
+        atp: impl OMSerializable,
+    ) -> Result<Self::Ok, Self::Err> {
+        let mut attributes = self.attrs(attrs)?;
+        let mut om = self.child(atp)?;
+        attributes.append(om.attributes_mut());
+        *om.attributes_mut() = attributes;
+        Ok(om)
+    }
+
+    fn ome(
+        self,
+        error: impl AsOMS,
+        args: impl ExactSizeIterator<Item: OMOrForeign>,
+    ) -> Result<Self::Ok, Self::Err> {
+        let cdbase = error.cdbase_uri(&self.cdbase).map_or_else(
+            || self.symbol_cdbase(),
+            |b| Cow::Owned(b.as_str().to_string()),
+        );
+        let (cd, name) = (error.cd().to_string(), error.name().to_string());
+        let arguments = args.map(|a| self.foreign(a)).collect::<Result<_, _>>()?;
+        Ok(OpenMath::OME {
+            cd: Cow::Owned(cd),
+            name: Cow::Owned(name),
+            cdbase: Some(cdbase),
+            arguments,
+            attributes: Vec::new(),
+        })
+    }
+
+    fn ombind(
+        self,
+        head: impl OMSerializable,
+        vars: impl ExactSizeIterator<Item: BindVar>,
+        body: impl OMSerializable,
+    ) -> Result<Self::Ok, Self::Err> {
+        let binder = Box::new(self.child(head)?);
+        let variables = vars
+            .map(|v| {
+                Ok(BoundVariable {
+                    name: Cow::Owned(v.name().to_string()),
+                    attributes: self.attrs(v.attrs())?,
+                })
+            })
+            .collect::<Result<_, BuildError>>()?;
+        let object = Box::new(self.child(body)?);
+        Ok(OpenMath::OMBIND {
+            binder,
+            variables,
+            object,
+            attributes: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conformance() {
+        let outputs = crate::ser::testing::check_serializer(|| Build {
+            cdbase: Cow::Borrowed(crate::CD_BASE),
+            own: false,
+        });
+        // rebuilding is lossless, up to rebasing a node to the cdbase already in effect
+        for (name, om) in outputs {
+            let built = om.erased_build().expect("is buildable");
+            assert_eq!(built.erased_build().as_ref(), Ok(&built), "{name}");
+            #[cfg(feature = "xml-write")]
+            assert_eq!(
+                built.xml(false).to_string(),
+                om.xml(false).to_string(),
+                "{name}"
+            );
+        }
+    }
+
+    #[cfg(feature = "xml")]
+    #[test]
+    fn roundtrip() {
+        use crate::de::OMObject;
+        const XML: &str = include_str!("../../doc/roundtrip.xml");
+        let om = OMObject::<OpenMath>::from_openmath_xml(XML).expect("is valid");
+        let built = om.erased_build().expect("is buildable");
+        assert_eq!(built, om);
+
+        let erased: Box<dyn ErasedOMSerializable> = Box::new(om.clone());
+        assert_eq!(erased.xml(true).to_string(), om.xml(true).to_string());
+        let mut out = String::new();
+        erased
+            .erased_xml(&mut out, crate::ser::Options::new().with_pretty(true))
+            .expect("is writable");
+        assert_eq!(out, om.xml(true).to_string());
+        #[cfg(all(feature = "serde", feature = "serde_json"))]
+        assert_eq!(
+            erased.erased_json().expect("is serializable"),
+            serde_json::to_value(om.openmath_serde()).expect("is serializable")
+        );
+    }
+
+    #[test]
+    fn errors() {
+        struct Fails;
+        impl OMSerializable for Fails {
+            fn as_openmath<'s, S: OMSerializer<'s>>(&self, _: S) -> Result<S::Ok, S::Err> {
+                Err(crate::ser::Error::custom("nope"))
+            }
+        }
+        struct Reference;
+        impl OMSerializable for Reference {
+            fn as_openmath<'s, S: OMSerializer<'s>>(&self, s: S) -> Result<S::Ok, S::Err> {
+                s.omr("#x")
+            }
+        }
+        let erased: &dyn ErasedOMSerializable = &Fails;
+        assert_eq!(erased.erased_build(), Err(BuildError("nope".to_string())));
+        let r = Reference.erased_build();
+        assert!(r.is_err(), "{r:?}");
+    }
+}
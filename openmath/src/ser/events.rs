@@ -0,0 +1,451 @@
+use std::borrow::Cow;
+
+use either::Either;
+use quick_xml::{
+    Writer,
+    events::{BytesEnd, BytesStart, BytesText, Event},
+};
+
+use super::{
+    AsOMS, BindVar, FloatStyle, OMAttr, OMOrForeign, OMSerializable, XmlOptions, XmlWriteError,
+};
+
+/** Writes the <span style="font-variant:small-caps;">OpenMath</span> XML of `o` as events into
+`writer`, e.g. to embed it in a host document written with
+[quick-xml](https://docs.rs/quick-xml); without an `OMOBJ` (see
+[`OMObject::write_xml_events_with`](super::OMObject::write_xml_events_with) for that).
+
+Unlike writing the output of [`xml`](OMSerializable::xml) as text, elements are indented as
+configured for `writer`. The content of [OMFOREIGN](crate::OMKind::OMFOREIGN)s and
+[raw XML](super::OMSerializer::raw_xml) is written verbatim.
+
+# Errors
+iff [as_openmath](OMSerializable::as_openmath) errors, or writing to `writer` fails
+([`XmlWriteError::Io`]).
+
+# Examples
+```
+use openmath::{OpenMath, ser::write_xml_events};
+use quick_xml::{Writer, events::{BytesStart, BytesEnd, Event}};
+
+let om = OpenMath::from_sexpr("(oma (oms arith1 plus) (omi 1) (omv x))").expect("is valid");
+let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+writer.write_event(Event::Start(BytesStart::new("term")))?;
+write_xml_events(&om, &mut writer)?;
+writer.write_event(Event::End(BytesEnd::new("term")))?;
+assert_eq!(
+    String::from_utf8(writer.into_inner())?,
+    r#"<term>
+  <OMA>
+    <OMS cd="arith1" name="plus"/>
+    <OMI>1</OMI>
+    <OMV name="x"/>
+  </OMA>
+</term>"#
+);
+# Ok::<(), Box<dyn std::error::Error>>(())
+```
+*/
+pub fn write_xml_events<W: std::io::Write>(
+    o: &(impl OMSerializable + ?Sized),
+    writer: &mut Writer<W>,
+) -> Result<(), XmlWriteError> {
+    o.as_openmath(XmlEvents {
+        w: writer,
+        next_ns: o.cdbase().map(Cow::Borrowed),
+        current_ns: Cow::Borrowed(crate::CD_BASE),
+        floats: FloatStyle::default(),
+        strict: false,
+        wrap_base64: false,
+    })
+}
+
+impl<O: OMSerializable + ?Sized> super::OMObject<'_, O> {
+    /// Like [`write_xml_events`], but wrapped in an `OMOBJ` and with the given `options`
+    /// ([`pretty`](XmlOptions::pretty) is ignored in favor of the configuration of
+    /// `writer`).
+    ///
+    /// # Errors
+    /// iff [as_openmath](OMSerializable::as_openmath) errors, writing to `writer` fails
+    /// ([`XmlWriteError::Io`]), or (if [`strict`](XmlOptions::strict)) the output would be
+    /// invalid.
+    pub fn write_xml_events_with<'s, W: std::io::Write>(
+        &self,
+        options: impl Into<XmlOptions<'s>>,
+        writer: &mut Writer<W>,
+    ) -> Result<(), XmlWriteError> {
+        let options = options.into();
+        let ns = self.0.cdbase();
+        let mut start = BytesStart::new("OMOBJ");
+        for (key, value) in [
+            ("version", options.version),
+            ("id", options.id),
+            (
+                "xmlns",
+                Some(crate::XML_NS).filter(|_| options.insert_namespace),
+            ),
+            ("cdbase", ns),
+        ] {
+            if let Some(value) = value {
+                push_attribute(&mut start, key, value);
+            }
+        }
+        writer.write_event(Event::Start(start))?;
+        let events = XmlEvents {
+            w: &mut *writer,
+            next_ns: None,
+            current_ns: Cow::Borrowed(ns.unwrap_or(options.default_cdbase)),
+            floats: FloatStyle {
+                format: options.float_format,
+                decimal_point: options.float_decimal_point,
+            },
+            strict: options.strict,
+            wrap_base64: options.wrap_base64,
+        };
+        if options.sort_attributes {
+            self.0.as_openmath(super::SortAttrs::new(events))?;
+        } else {
+            self.0.as_openmath(events)?;
+        }
+        writer.write_event(Event::End(BytesEnd::new("OMOBJ")))?;
+        Ok(())
+    }
+}
+
+/// Adds the attribute `key="value"` to `start`, escaped like the `Display` serializer does
+fn push_attribute(start: &mut BytesStart<'_>, key: &str, value: &str) {
+    let value = super::xml::escape_attribute(value);
+    start.push_attribute((key.as_bytes(), value.as_bytes()));
+}
+
+/// The event-producing XML serializer; the counterpart of the `Display` one in `xml`
+struct XmlEvents<'s, W> {
+    w: &'s mut Writer<W>,
+    next_ns: Option<Cow<'s, str>>,
+    current_ns: Cow<'s, str>,
+    floats: FloatStyle,
+    strict: bool,
+    wrap_base64: bool,
+}
+impl<W: std::io::Write> XmlEvents<'_, W> {
+    #[inline]
+    fn clone(&mut self) -> XmlEvents<'_, W> {
+        XmlEvents {
+            w: self.w,
+            next_ns: self.next_ns.as_deref().map(Cow::Borrowed),
+            current_ns: Cow::Borrowed(&self.current_ns),
+            floats: self.floats,
+            strict: self.strict,
+            wrap_base64: self.wrap_base64,
+        }
+    }
+
+    #[inline]
+    fn event<'e>(&mut self, event: impl Into<Event<'e>>) -> Result<(), XmlWriteError> {
+        self.w.write_event(event).map_err(Into::into)
+    }
+
+    /// Writes the start tag of `name`, with the pending cdbase (if any)
+    fn open(&mut self, name: &'static str) -> Result<(), XmlWriteError> {
+        let mut start = BytesStart::new(name);
+        if let Some(ns) = self.next_ns.take() {
+            push_attribute(&mut start, "cdbase", &ns);
+            self.current_ns = ns;
+        }
+        self.event(Event::Start(start))
+    }
+
+    #[inline]
+    fn close(&mut self, name: &'static str) -> Result<(), XmlWriteError> {
+        self.event(Event::End(BytesEnd::new(name)))
+    }
+
+    /// Writes `<name>text</name>`, with `text` already escaped
+    fn text(&mut self, name: &'static str, text: &str) -> Result<(), XmlWriteError> {
+        self.event(Event::Start(BytesStart::new(name)))?;
+        self.event(Event::Text(BytesText::from_escaped(text)))?;
+        self.close(name)
+    }
+
+    /// Writes an empty element `name` with the given `attributes`
+    fn empty(
+        &mut self,
+        name: &'static str,
+        attributes: &[(&str, &str)],
+    ) -> Result<(), XmlWriteError> {
+        let mut start = BytesStart::new(name);
+        for &(key, value) in attributes {
+            push_attribute(&mut start, key, value);
+        }
+        self.event(Event::Empty(start))
+    }
+
+    fn omforeign(&mut self, a: impl OMOrForeign) -> Result<(), XmlWriteError> {
+        match a.om_or_foreign() {
+            Either::Left(o) => o.as_openmath(self.clone()),
+            Either::Right((encoding, value)) => {
+                let value = value.to_string();
+                if self.strict {
+                    super::raw::check_foreign(&value).map_err(XmlWriteError::InvalidForeignXml)?;
+                }
+                let mut start = BytesStart::new("OMFOREIGN");
+                if let Some(encoding) = encoding {
+                    push_attribute(&mut start, "encoding", &encoding.to_string());
+                }
+                self.event(Event::Start(start))?;
+                self.event(Event::Text(BytesText::from_escaped(value)))?;
+                self.close("OMFOREIGN")
+            }
+        }
+    }
+
+    fn oma_iter(
+        mut self,
+        head: impl OMSerializable,
+        args: impl Iterator<Item: OMSerializable>,
+    ) -> Result<(), XmlWriteError> {
+        self.open("OMA")?;
+        head.as_openmath(self.clone())?;
+        for a in args {
+            a.as_openmath(self.clone())?;
+        }
+        self.close("OMA")
+    }
+}
+
+impl<'s, W: std::io::Write> super::OMSerializer<'s> for XmlEvents<'s, W> {
+    type Ok = ();
+    type Err = XmlWriteError;
+    type SubSerializer<'ns>
+        = XmlEvents<'ns, W>
+    where
+        's: 'ns;
+    #[inline]
+    fn current_cdbase(&self) -> &str {
+        self.next_ns.as_deref().unwrap_or(&self.current_ns)
+    }
+    fn with_cdbase<'ns>(
+        self,
+        cdbase: impl Into<Cow<'ns, str>>,
+    ) -> Result<Self::SubSerializer<'ns>, Self::Err>
+    where
+        's: 'ns,
+    {
+        let cdbase = cdbase.into();
+        if crate::cdbase::eq_normalized(&self.current_ns, &cdbase) {
+            Ok(Self {
+                next_ns: None,
+                ..self
+            })
+        } else {
+            Ok(XmlEvents {
+                next_ns: Some(cdbase),
+                ..self
+            })
+        }
+    }
+    fn omi(mut self, value: &crate::Int) -> Result<Self::Ok, Self::Err> {
+        self.text("OMI", &value.to_string())
+    }
+    fn omf(mut self, value: f64) -> Result<Self::Ok, Self::Err> {
+        let mut dec = String::new();
+        self.floats.write(&mut dec, value)?;
+        self.empty("OMF", &[("dec", &dec)])
+    }
+    fn omb(mut self, bytes: impl ExactSizeIterator<Item = u8>) -> Result<Self::Ok, Self::Err> {
+        /// The length of wrapped lines
+        const LINE: usize = 76;
+        let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+        crate::base64::encode_into(&bytes.collect::<Vec<_>>(), &mut encoded);
+        if self.wrap_base64 {
+            // base64 is ASCII, so every split is at a char boundary
+            let lines = encoded
+                .as_bytes()
+                .chunks(LINE)
+                .map(|l| std::str::from_utf8(l).unwrap_or_default())
+                .collect::<Vec<_>>();
+            encoded = lines.join("\n");
+        }
+        self.text("OMB", &encoded)
+    }
+    fn omstr(mut self, string: impl std::fmt::Display) -> Result<Self::Ok, Self::Err> {
+        self.text("OMSTR", &quick_xml::escape::escape(string.to_string()))
+    }
+    fn omv(mut self, name: impl std::fmt::Display) -> Result<Self::Ok, Self::Err> {
+        if self.strict {
+            super::xml::check_name(crate::OMKind::OMV, &name)?;
+        }
+        self.empty("OMV", &[("name", &name.to_string())])
+    }
+    fn omr(mut self, href: impl std::fmt::Display) -> Result<Self::Ok, Self::Err> {
+        self.empty("OMR", &[("href", &href.to_string())])
+    }
+    fn raw_xml(mut self, xml: &str) -> Result<Self::Ok, Self::Err> {
+        if let Some(ns) = self.next_ns {
+            return Err(XmlWriteError::Custom(format!(
+                "can not change the cdbase of raw XML to {ns}"
+            )));
+        }
+        self.event(Event::Text(BytesText::from_escaped(xml.trim())))
+    }
+    fn oms(
+        mut self,
+        cd_name: impl std::fmt::Display,
+        name: impl std::fmt::Display,
+    ) -> Result<Self::Ok, Self::Err> {
+        if self.strict {
+            super::xml::check_name(crate::OMKind::OMS, &cd_name)?;
+            super::xml::check_name(crate::OMKind::OMS, &name)?;
+        }
+        let (cd_name, name) = (cd_name.to_string(), name.to_string());
+        if let Some(ns) = self.next_ns.take() {
+            self.empty("OMS", &[("cdbase", &ns), ("cd", &cd_name), ("name", &name)])
+        } else {
+            self.empty("OMS", &[("cd", &cd_name), ("name", &name)])
+        }
+    }
+    fn ome(
+        mut self,
+        error: impl AsOMS,
+        args: impl ExactSizeIterator<Item: OMOrForeign>,
+    ) -> Result<Self::Ok, Self::Err> {
+        self.open("OME")?;
+        error.as_oms().as_openmath(self.clone())?;
+        for a in args {
+            self.omforeign(a)?;
+        }
+        self.close("OME")
+    }
+
+    #[inline]
+    fn oma(
+        self,
+        head: impl OMSerializable,
+        args: impl ExactSizeIterator<Item: OMSerializable>,
+    ) -> Result<Self::Ok, Self::Err> {
+        self.oma_iter(head, args)
+    }
+
+    /// Writes every argument as soon as it is produced
+    #[inline]
+    fn oma_streamed<I: OMSerializable, F: FnMut() -> Option<I>>(
+        self,
+        head: impl OMSerializable,
+        args: super::StreamedArgs<F>,
+    ) -> Result<Self::Ok, Self::Err> {
+        self.oma_iter(head, args)
+    }
+
+    fn omattr(
+        mut self,
+        attrs: impl ExactSizeIterator<Item: OMAttr>,
+        atp: impl OMSerializable,
+    ) -> Result<Self::Ok, Self::Err> {
+        let mut attrs = attrs.peekable();
+        if attrs.peek().is_none() {
+            return atp.as_openmath(self);
+        }
+        self.open("OMATTR")?;
+        self.open("OMATP")?;
+        for a in attrs {
+            a.symbol().as_oms().as_openmath(self.clone())?;
+            self.omforeign(a.value())?;
+        }
+        self.close("OMATP")?;
+        atp.as_openmath(self.clone())?;
+        self.close("OMATTR")
+    }
+
+    fn ombind(
+        mut self,
+        head: impl OMSerializable,
+        vars: impl ExactSizeIterator<Item: BindVar>,
+        body: impl OMSerializable,
+    ) -> Result<Self::Ok, Self::Err> {
+        self.open("OMBIND")?;
+        head.as_openmath(self.clone())?;
+        let mut vars = vars.peekable();
+        if vars.peek().is_none() {
+            self.empty("OMBVAR", &[])?;
+        } else {
+            self.open("OMBVAR")?;
+            for v in vars {
+                let mut attrs = v.attrs().peekable();
+                if attrs.peek().is_none() {
+                    self.clone().omv(v.name())?;
+                } else {
+                    self.clone().omattr(attrs, super::Omv(v.name()))?;
+                }
+            }
+            self.close("OMBVAR")?;
+        }
+        body.as_openmath(self.clone())?;
+        self.close("OMBIND")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OMDeserializable, OpenMath, ser::OMObject};
+
+    #[test]
+    fn embedded_in_host_document() {
+        let om = OpenMath::from_sexpr(
+            "(ombind (oms fns1 lambda) (bvar (omattr (((oms sts type) (oms setname1 N))) x)) \
+             (omattr (((oms meta note) (omforeign \"text/html\" \"<b>1 &amp; 2</b>\"))) \
+             (oma (cdbase \"http://example.org\" (oms units metre)) \
+             (omstr \"a < b & \\\"c\\\"\") (omf 1.5) (omb \"AQID\") (ome (oms e x)))))",
+        )
+        .expect("is valid");
+        let mut writer = Writer::new_with_indent(Vec::new(), b'\t', 1);
+        writer
+            .write_event(Event::Start(BytesStart::new("omdoc")))
+            .expect("works");
+        writer
+            .write_event(Event::Start(BytesStart::new("term")))
+            .expect("works");
+        write_xml_events(&om, &mut writer).expect("works");
+        writer
+            .write_event(Event::End(BytesEnd::new("term")))
+            .expect("works");
+        OMObject(&om)
+            .write_xml_events_with(XmlOptions::default(), &mut writer)
+            .expect("works");
+        writer
+            .write_event(Event::End(BytesEnd::new("omdoc")))
+            .expect("works");
+        let doc = String::from_utf8(writer.into_inner()).expect("is UTF-8");
+
+        // the host's indentation is used throughout
+        assert!(
+            doc.starts_with("<omdoc>\n\t<term>\n\t\t<OMBIND>\n\t\t\t<OMS cd=\"fns1\""),
+            "{doc}"
+        );
+        assert!(doc.contains("\n\t\t\t\t<OMATTR>"), "{doc}");
+
+        // the document is well-formed, and both copies read back
+        let mut reader = quick_xml::Reader::from_str(&doc);
+        while !matches!(reader.read_event().expect("is well-formed"), Event::Eof) {}
+        let term = &doc[doc.find("<OMBIND").expect("exists")..doc.find("</term>").expect("exists")];
+        let xml = om.xml(false).to_string();
+        let expected = OpenMath::from_openmath_xml(&xml).expect("is valid");
+        assert_eq!(
+            OpenMath::from_openmath_xml(term.trim()).expect("is valid"),
+            expected
+        );
+        let omobj =
+            &doc[doc.find("<OMOBJ").expect("exists")..doc.find("</omdoc>").expect("exists")];
+        assert_eq!(
+            crate::de::OMObject::<OpenMath>::from_openmath_xml(omobj).expect("is valid"),
+            expected
+        );
+        // and agree with the Display backend, up to whitespace and escaping
+        let strip = |s: &str| {
+            s.replace(|c: char| c.is_ascii_whitespace(), "")
+                .replace("&gt;", ">")
+                .replace("&quot;", "\"")
+        };
+        assert_eq!(strip(term), strip(&xml));
+    }
+}
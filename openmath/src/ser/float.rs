@@ -0,0 +1,150 @@
+use std::fmt::Write;
+
+/// How [OMF](crate::OMKind::OMF) values are written by the XML and
+/// [display](super::OMSerializable::openmath_display_with) serializers.
+///
+/// Non-finite values are always written as `INF`, `-INF` and `NaN`, the forms the
+/// <span style="font-variant:small-caps;">OpenMath</span> standard allows in `dec`,
+/// regardless of the chosen format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FloatFormat {
+    /// The shortest positional representation that round-trips exactly (`0.1`, `2`,
+    /// `1000000`); this is what `format!("{value}")` produces.
+    #[default]
+    Shortest,
+    /// The given number of significant digits (at least 1), like C's `%.Ng`: positional
+    /// notation unless the decimal exponent is smaller than -4 or at least the number of
+    /// digits; trailing zeros are dropped (`3.14`, `1.23e7`).
+    Fixed(u8),
+    /// The shortest representation that round-trips exactly, in scientific notation
+    /// (`1e-1`, `2e0`, `1.5e6`).
+    Scientific,
+    /// 17 significant digits, like `Fixed(17)`; enough to distinguish any two `f64`s, and
+    /// matching the output of C's `%.17g` used by many other implementations.
+    Precise17,
+}
+
+/// The float settings of a serializer
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FloatStyle {
+    pub format: FloatFormat,
+    pub decimal_point: bool,
+}
+impl FloatStyle {
+    pub fn write(self, w: &mut impl Write, value: f64) -> std::fmt::Result {
+        if value.is_nan() {
+            return w.write_str("NaN");
+        }
+        if value.is_infinite() {
+            return w.write_str(if value > 0.0 { "INF" } else { "-INF" });
+        }
+        let s = match self.format {
+            FloatFormat::Shortest => value.to_string(),
+            FloatFormat::Scientific => format!("{value:e}"),
+            FloatFormat::Fixed(digits) => general(value, digits.max(1)),
+            FloatFormat::Precise17 => general(value, 17),
+        };
+        if !self.decimal_point || s.contains('.') {
+            return w.write_str(&s);
+        }
+        match s.split_once('e') {
+            Some((mantissa, exp)) => write!(w, "{mantissa}.0e{exp}"),
+            None => write!(w, "{s}.0"),
+        }
+    }
+}
+
+/// `%.{digits}g`
+fn general(value: f64, digits: u8) -> String {
+    let precision = usize::from(digits - 1);
+    let sci = format!("{value:.precision$e}");
+    let (mantissa, exp) = sci.split_once('e').unwrap_or((&sci, "0"));
+    let exp: i32 = exp.parse().unwrap_or_default();
+    if exp < -4 || exp >= i32::from(digits) {
+        return format!("{}e{exp}", strip_zeros(mantissa));
+    }
+    #[allow(clippy::cast_sign_loss)] // exp < digits
+    let decimals = (i32::from(digits) - 1 - exp) as usize;
+    strip_zeros(&format!("{value:.decimals$}")).to_string()
+}
+
+fn strip_zeros(s: &str) -> &str {
+    if s.contains('.') {
+        s.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        s
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fmt(value: f64, format: FloatFormat, decimal_point: bool) -> String {
+        let mut s = String::new();
+        FloatStyle {
+            format,
+            decimal_point,
+        }
+        .write(&mut s, value)
+        .expect("writing to a string does not fail");
+        s
+    }
+
+    #[test]
+    #[allow(clippy::unreadable_literal)]
+    fn float_table() {
+        use FloatFormat::{Fixed, Precise17, Scientific, Shortest};
+        // value, Shortest, Fixed(3), Scientific, Precise17
+        let table: &[(f64, &str, &str, &str, &str)] = &[
+            (2.0, "2", "2", "2e0", "2"),
+            (-0.0, "-0", "-0", "-0e0", "-0"),
+            (0.1, "0.1", "0.1", "1e-1", "0.10000000000000001"),
+            (
+                std::f64::consts::PI,
+                "3.141592653589793",
+                "3.14",
+                "3.141592653589793e0",
+                "3.1415926535897931",
+            ),
+            (1234.5, "1234.5", "1.23e3", "1.2345e3", "1234.5"),
+            (
+                -0.000_012_5,
+                "-0.0000125",
+                "-1.25e-5",
+                "-1.25e-5",
+                "-1.2500000000000001e-5",
+            ),
+            (1e21, "1000000000000000000000", "1e21", "1e21", "1e21"),
+            (0.000_1, "0.0001", "0.0001", "1e-4", "0.0001"),
+            (99.96, "99.96", "100", "9.996e1", "99.959999999999994"),
+            (f64::INFINITY, "INF", "INF", "INF", "INF"),
+            (f64::NEG_INFINITY, "-INF", "-INF", "-INF", "-INF"),
+            (-f64::NAN, "NaN", "NaN", "NaN", "NaN"),
+            (f64::NAN, "NaN", "NaN", "NaN", "NaN"),
+        ];
+        for &(value, shortest, fixed, scientific, precise) in table {
+            assert_eq!(fmt(value, Shortest, false), shortest, "{value}");
+            assert_eq!(fmt(value, Fixed(3), false), fixed, "{value}");
+            assert_eq!(fmt(value, Scientific, false), scientific, "{value}");
+            assert_eq!(fmt(value, Precise17, false), precise, "{value}");
+        }
+        assert_eq!(fmt(2.6, Fixed(0), false), "3");
+        assert_eq!(fmt(2.0, Fixed(4), false), "2");
+
+        // value, Shortest, Scientific, with decimal point
+        let table: &[(f64, &str, &str)] = &[
+            (2.0, "2.0", "2.0e0"),
+            (-0.0, "-0.0", "-0.0e0"),
+            (0.5, "0.5", "5.0e-1"),
+            (1.5e6, "1500000.0", "1.5e6"),
+            (f64::INFINITY, "INF", "INF"),
+        ];
+        for &(value, shortest, scientific) in table {
+            assert_eq!(fmt(value, Shortest, true), shortest, "{value}");
+            assert_eq!(fmt(value, Scientific, true), scientific, "{value}");
+        }
+        assert_eq!(fmt(1e21, Fixed(3), true), "1.0e21");
+        assert_eq!(fmt(1234.0, Precise17, true), "1234.0");
+    }
+}
@@ -0,0 +1,55 @@
+//! Writing OMOBJs to a stream in one piece, e.g. a log file; see [`write_framed`]
+
+use super::{OMObject, OMSerializable, XmlOptions, XmlWriteError};
+
+/** Writes `obj` as an OMOBJ (with the default [`XmlOptions`]), followed by a newline, with a
+single [`write_all`](std::io::Write::write_all) on `w`; e.g. for appending to a log or journal
+file that several threads or processes write to, where
+[`write_xml`](OMSerializable::write_xml) could interleave partial objects.
+
+The object is serialized completely first, so if that fails, nothing is written. Whether a
+single `write_all` is atomic depends on `w`: it is for a file opened in append mode (up to
+the platform's limits) or a writer behind a lock, but not for a [`BufWriter`](std::io::BufWriter)
+that several writers share unsynchronized. See
+[`de::read_framed`](crate::de::read_framed) for reading the objects back.
+
+# Errors
+iff [as_openmath](OMSerializable::as_openmath) errors, or writing fails
+([`XmlWriteError::Io`]).
+
+# Examples
+```
+use openmath::{OpenMath, ser::write_framed};
+
+let mut log = b"12:00 started\n".to_vec();
+write_framed(&mut log, &OpenMath::from_sexpr("(omi 1)").expect("is valid")).expect("writes");
+write_framed(&mut log, &2).expect("writes");
+assert_eq!(
+    std::str::from_utf8(&log).expect("is UTF-8").lines().nth(2),
+    Some(r#"<OMOBJ version="2.0" xmlns="http://www.openmath.org/OpenMath"><OMI>2</OMI></OMOBJ>"#)
+);
+```
+*/
+pub fn write_framed<W: std::io::Write + ?Sized>(
+    w: &mut W,
+    obj: &(impl OMSerializable + ?Sized),
+) -> Result<(), XmlWriteError> {
+    write_framed_with(w, obj, XmlOptions::default())
+}
+
+/// Like [`write_framed`], but with the given [`XmlOptions`]; [`pretty`](XmlOptions::pretty)
+/// output is still written in one piece.
+///
+/// # Errors
+/// see [`write_framed`]; also, if [`strict`](XmlOptions::strict), iff the output would be
+/// invalid.
+pub fn write_framed_with<'s, W: std::io::Write + ?Sized>(
+    w: &mut W,
+    obj: &(impl OMSerializable + ?Sized),
+    options: impl Into<XmlOptions<'s>>,
+) -> Result<(), XmlWriteError> {
+    let mut frame = OMObject(obj).try_xml_with(options)?;
+    frame.push('\n');
+    w.write_all(frame.as_bytes())?;
+    Ok(())
+}
@@ -0,0 +1,470 @@
+use std::{borrow::Cow, cell::RefCell, fmt::Display};
+
+use either::Either;
+
+use super::{AsOMS, BindVar, Error, OMAttr, OMOrForeign, OMSerializable, OMSerializer};
+
+/// Callbacks invoked by [`Inspect`] for every node, *before* the node is handed to the
+/// wrapped [`OMSerializer`]. All methods default to doing nothing.
+///
+/// Symbols are reported via [`oms`](Inspector::oms) regardless of where they occur; i.e.
+/// also for the heads of [OME](crate::OMKind::OME)s and the keys of
+/// [OMATTR](crate::OMKind::OMATTR)s. `cdbase` is always the cdbase in effect for the symbol.
+///
+/// An inspector can stop serialization by returning a message from
+/// [`abort`](Inspector::abort), which is then returned as [`Error::custom`](super::Error::custom).
+#[allow(unused_variables)]
+pub trait Inspector {
+    /// Called for an [OMI](crate::OMKind::OMI) with the given value
+    fn omi(&mut self, value: &crate::Int) {}
+    /// Called for an [OMF](crate::OMKind::OMF) with the given value
+    fn omf(&mut self, value: f64) {}
+    /// Called for an [OMSTR](crate::OMKind::OMSTR) with the given contents
+    fn omstr(&mut self, string: &dyn Display) {}
+    /// Called for an [OMB](crate::OMKind::OMB) with `len` bytes
+    fn omb(&mut self, len: usize) {}
+    /// Called for an [OMV](crate::OMKind::OMV) with the given name; not for the bound
+    /// variables of an [OMBIND](crate::OMKind::OMBIND), only for their occurrences
+    fn omv(&mut self, name: &dyn Display) {}
+    /// Called for an [OMR](crate::OMKind::OMR) with the given `href`
+    fn omr(&mut self, href: &dyn Display) {}
+    /// Called for raw XML (e.g. foreign content) passed on via
+    /// [`OMSerializer::raw_xml`]
+    fn raw_xml(&mut self, xml: &str) {}
+    /// Called for every symbol; see above
+    fn oms(&mut self, cdbase: &str, cd: &dyn Display, name: &dyn Display) {}
+    /// Called for an [OMA](crate::OMKind::OMA) with `num_args` arguments (not counting the
+    /// head), before any of them
+    fn oma(&mut self, num_args: usize) {}
+    /// Called for an [OME](crate::OMKind::OME) with `num_args` arguments (not counting the
+    /// error symbol), before any of them
+    fn ome(&mut self, num_args: usize) {}
+    /// Called for an [OMATTR](crate::OMKind::OMATTR) with `num_attrs` key-value pairs,
+    /// before any of them or the attributed object
+    fn omattr(&mut self, num_attrs: usize) {}
+    /// Called for an [OMBIND](crate::OMKind::OMBIND) with `num_vars` bound variables, before
+    /// the binder, the variables and the body
+    fn ombind(&mut self, num_vars: usize) {}
+    /// Asked after every callback; returning `Some(message)` aborts serialization
+    fn abort(&mut self) -> Option<String> {
+        None
+    }
+}
+impl<I: Inspector + ?Sized> Inspector for &mut I {
+    #[inline]
+    fn omi(&mut self, value: &crate::Int) {
+        I::omi(self, value);
+    }
+    #[inline]
+    fn omf(&mut self, value: f64) {
+        I::omf(self, value);
+    }
+    #[inline]
+    fn omstr(&mut self, string: &dyn Display) {
+        I::omstr(self, string);
+    }
+    #[inline]
+    fn omb(&mut self, len: usize) {
+        I::omb(self, len);
+    }
+    #[inline]
+    fn omv(&mut self, name: &dyn Display) {
+        I::omv(self, name);
+    }
+    #[inline]
+    fn omr(&mut self, href: &dyn Display) {
+        I::omr(self, href);
+    }
+    #[inline]
+    fn raw_xml(&mut self, xml: &str) {
+        I::raw_xml(self, xml);
+    }
+    #[inline]
+    fn oms(&mut self, cdbase: &str, cd: &dyn Display, name: &dyn Display) {
+        I::oms(self, cdbase, cd, name);
+    }
+    #[inline]
+    fn oma(&mut self, num_args: usize) {
+        I::oma(self, num_args);
+    }
+    #[inline]
+    fn ome(&mut self, num_args: usize) {
+        I::ome(self, num_args);
+    }
+    #[inline]
+    fn omattr(&mut self, num_attrs: usize) {
+        I::omattr(self, num_attrs);
+    }
+    #[inline]
+    fn ombind(&mut self, num_vars: usize) {
+        I::ombind(self, num_vars);
+    }
+    #[inline]
+    fn abort(&mut self) -> Option<String> {
+        I::abort(self)
+    }
+}
+
+/// [`OMSerializer`] middleware that reports every node to an [`Inspector`] and then
+/// delegates to the wrapped serializer; see also
+/// [`as_openmath_with`](OMSerializable::as_openmath_with) and [`inspect`].
+pub struct Inspect<'i, S, I: Inspector> {
+    inner: S,
+    inspector: &'i RefCell<I>,
+}
+impl<'i, S, I: Inspector> Inspect<'i, S, I> {
+    /// Wraps `inner`
+    #[inline]
+    pub const fn new(inner: S, inspector: &'i RefCell<I>) -> Self {
+        Self { inner, inspector }
+    }
+}
+impl<'s, S: OMSerializer<'s>, I: Inspector> Inspect<'_, S, I> {
+    fn report(&self, f: impl FnOnce(&mut I)) -> Result<(), S::Err> {
+        let mut inspector = self.inspector.borrow_mut();
+        f(&mut inspector);
+        inspector.abort().map_or(Ok(()), |e| Err(S::Err::custom(e)))
+    }
+}
+fn check<I: Inspector, Ok, Err: Error>(
+    inspector: &RefCell<I>,
+    r: Result<Ok, Err>,
+) -> Result<Ok, Err> {
+    // attribute keys are only reported while the wrapped serializer is running
+    let r = r?;
+    inspector
+        .borrow_mut()
+        .abort()
+        .map_or(Ok(r), |e| Err(Err::custom(e)))
+}
+
+/// Runs `on_symbol(cdbase, cd, name)` for every symbol occuring in `o`, e.g. to find
+/// out which content dictionaries it uses.
+///
+/// Traversal stops at the first error [`as_openmath`](OMSerializable::as_openmath) returns.
+///
+/// # Examples
+/// ```rust
+/// use openmath::{OpenMath, ser::inspect};
+///
+/// let om = OpenMath::from_sexpr("(oma (oms arith1 plus) (omi 2) (oma (oms arith1 times)))")
+///     .expect("is valid");
+/// let mut cds = Vec::new();
+/// inspect(&om, |_, cd, name| cds.push(format!("{cd}#{name}")));
+/// assert_eq!(cds, ["arith1#plus", "arith1#times"]);
+/// ```
+pub fn inspect<O: OMSerializable + ?Sized>(o: &O, on_symbol: impl FnMut(&str, &str, &str)) {
+    struct OnSymbol<F>(F);
+    impl<F: FnMut(&str, &str, &str)> Inspector for OnSymbol<F> {
+        fn oms(&mut self, cdbase: &str, cd: &dyn Display, name: &dyn Display) {
+            (self.0)(cdbase, &cd.to_string(), &name.to_string());
+        }
+    }
+    struct Run<'o, O: ?Sized, F>(&'o O, RefCell<OnSymbol<F>>);
+    impl<O: OMSerializable + ?Sized, F: FnMut(&str, &str, &str)> Display for Run<'_, O, F> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            self.0
+                .as_openmath(Inspect::new(
+                    super::DisplaySerializer {
+                        f,
+                        next_ns: self.0.cdbase().map(Cow::Borrowed),
+                        current_ns: Cow::Borrowed(crate::CD_BASE),
+                        floats: super::FloatStyle::default(),
+                        max_len: None,
+                        wrap: None,
+                    },
+                    &self.1,
+                ))
+                .map_err(Into::into)
+        }
+    }
+    struct Discard;
+    impl std::fmt::Write for Discard {
+        #[inline]
+        fn write_str(&mut self, _: &str) -> std::fmt::Result {
+            Ok(())
+        }
+    }
+    let _ = std::fmt::Write::write_fmt(
+        &mut Discard,
+        format_args!("{}", Run(o, RefCell::new(OnSymbol(on_symbol)))),
+    );
+}
+
+impl<'s, 'i, S: OMSerializer<'s>, I: Inspector> OMSerializer<'s> for Inspect<'i, S, I> {
+    type Ok = S::Ok;
+    type Err = S::Err;
+    type SubSerializer<'ns>
+        = Inspect<'i, S::SubSerializer<'ns>, I>
+    where
+        's: 'ns;
+    #[inline]
+    fn current_cdbase(&self) -> &str {
+        self.inner.current_cdbase()
+    }
+    #[inline]
+    fn with_cdbase<'ns>(
+        self,
+        cdbase: impl Into<Cow<'ns, str>>,
+    ) -> Result<Self::SubSerializer<'ns>, Self::Err>
+    where
+        's: 'ns,
+    {
+        Ok(Inspect {
+            inner: self.inner.with_cdbase(cdbase)?,
+            inspector: self.inspector,
+        })
+    }
+    fn omi(self, value: &crate::Int) -> Result<Self::Ok, Self::Err> {
+        self.report(|i| i.omi(value))?;
+        self.inner.omi(value)
+    }
+    fn omf(self, value: f64) -> Result<Self::Ok, Self::Err> {
+        self.report(|i| i.omf(value))?;
+        self.inner.omf(value)
+    }
+    fn omstr(self, string: impl Display) -> Result<Self::Ok, Self::Err> {
+        self.report(|i| i.omstr(&string))?;
+        self.inner.omstr(string)
+    }
+    fn omb(self, bytes: impl ExactSizeIterator<Item = u8>) -> Result<Self::Ok, Self::Err> {
+        self.report(|i| i.omb(bytes.len()))?;
+        self.inner.omb(bytes)
+    }
+    fn omv(self, name: impl Display) -> Result<Self::Ok, Self::Err> {
+        self.report(|i| i.omv(&name))?;
+        self.inner.omv(name)
+    }
+    fn omr(self, href: impl Display) -> Result<Self::Ok, Self::Err> {
+        self.report(|i| i.omr(&href))?;
+        self.inner.omr(href)
+    }
+    fn raw_xml(self, xml: &str) -> Result<Self::Ok, Self::Err> {
+        self.report(|i| i.raw_xml(xml))?;
+        self.inner.raw_xml(xml)
+    }
+    fn oms(self, cd_name: impl Display, name: impl Display) -> Result<Self::Ok, Self::Err> {
+        self.report(|i| i.oms(self.inner.current_cdbase(), &cd_name, &name))?;
+        self.inner.oms(cd_name, name)
+    }
+    fn oma(
+        self,
+        head: impl OMSerializable,
+        args: impl ExactSizeIterator<Item: OMSerializable>,
+    ) -> Result<Self::Ok, Self::Err> {
+        let inspector = self.inspector;
+        self.report(|i| i.oma(args.len()))?;
+        check(
+            inspector,
+            self.inner.oma(
+                Inspected(head, inspector),
+                args.map(move |a| Inspected(a, inspector)),
+            ),
+        )
+    }
+    fn ome(
+        self,
+        error: impl AsOMS,
+        args: impl ExactSizeIterator<Item: OMOrForeign>,
+    ) -> Result<Self::Ok, Self::Err> {
+        let inspector = self.inspector;
+        self.report(|i| {
+            i.ome(args.len());
+            let current = self.inner.current_cdbase();
+            let cdbase = error.cdbase_uri(current);
+            i.oms(
+                cdbase.as_deref().unwrap_or(current),
+                &error.cd(),
+                &error.name(),
+            );
+        })?;
+        check(
+            inspector,
+            self.inner
+                .ome(error, args.map(move |a| InspectedForeign(a, inspector))),
+        )
+    }
+    fn omattr(
+        self,
+        attrs: impl ExactSizeIterator<Item: OMAttr>,
+        atp: impl OMSerializable,
+    ) -> Result<Self::Ok, Self::Err> {
+        let inspector = self.inspector;
+        self.report(|i| i.omattr(attrs.len()))?;
+        let current = self.inner.current_cdbase().to_string();
+        check(
+            inspector,
+            self.inner.omattr(
+                attrs.map(|a| InspectedAttr(a, inspector, &current)),
+                Inspected(atp, inspector),
+            ),
+        )
+    }
+    fn ombind(
+        self,
+        head: impl OMSerializable,
+        vars: impl ExactSizeIterator<Item: BindVar>,
+        body: impl OMSerializable,
+    ) -> Result<Self::Ok, Self::Err> {
+        let inspector = self.inspector;
+        self.report(|i| i.ombind(vars.len()))?;
+        let current = self.inner.current_cdbase().to_string();
+        check(
+            inspector,
+            self.inner.ombind(
+                Inspected(head, inspector),
+                vars.map(|v| InspectedVar(v, inspector, &current)),
+                Inspected(body, inspector),
+            ),
+        )
+    }
+}
+
+/// An [`OMSerializable`] that reports all its nodes to an [`Inspector`] when serialized.
+///
+/// The counterpart to [`Inspect`] for the built-in serializers (e.g.
+/// [`xml`](OMSerializable::xml)), which can not be wrapped directly.
+pub struct Inspected<'i, T, I: Inspector>(T, &'i RefCell<I>);
+impl<'i, T: OMSerializable, I: Inspector> Inspected<'i, T, I> {
+    /// Wraps `o`
+    #[inline]
+    pub const fn new(o: T, inspector: &'i RefCell<I>) -> Self {
+        Self(o, inspector)
+    }
+}
+impl<T: OMSerializable, I: Inspector> OMSerializable for Inspected<'_, T, I> {
+    #[inline]
+    fn cdbase(&self) -> Option<&str> {
+        self.0.cdbase()
+    }
+    #[inline]
+    fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        self.0.as_openmath(Inspect::new(serializer, self.1))
+    }
+}
+
+struct InspectedForeign<'i, T, I: Inspector>(T, &'i RefCell<I>);
+impl<T: OMOrForeign, I: Inspector> OMOrForeign for InspectedForeign<'_, T, I> {
+    fn om_or_foreign(self) -> Either<impl OMSerializable, (Option<impl Display>, impl Display)> {
+        match self.0.om_or_foreign() {
+            Either::Left(o) => Either::Left(Inspected(o, self.1)),
+            Either::Right(f) => Either::Right(f),
+        }
+    }
+}
+
+struct InspectedAttr<'i, A, I: Inspector>(A, &'i RefCell<I>, &'i str);
+impl<A: OMAttr, I: Inspector> OMAttr for InspectedAttr<'_, A, I> {
+    #[inline]
+    fn symbol(&self) -> impl AsOMS {
+        self.0.symbol()
+    }
+    fn value(self) -> impl OMOrForeign {
+        // reported here rather than in `symbol`, since `value` is called exactly once
+        {
+            let symbol = self.0.symbol();
+            let cdbase = symbol.cdbase_uri(self.2);
+            self.1.borrow_mut().oms(
+                cdbase.as_deref().unwrap_or(self.2),
+                &symbol.cd(),
+                &symbol.name(),
+            );
+        }
+        InspectedForeign(self.0.value(), self.1)
+    }
+}
+
+struct InspectedVar<'i, V, I: Inspector>(V, &'i RefCell<I>, &'i str);
+impl<V: BindVar, I: Inspector> BindVar for InspectedVar<'_, V, I> {
+    #[inline]
+    fn name(&self) -> impl Display {
+        self.0.name()
+    }
+    #[inline]
+    fn attrs(&self) -> impl ExactSizeIterator<Item: OMAttr> {
+        self.0.attrs().map(|a| InspectedAttr(a, self.1, self.2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OpenMath;
+
+    #[test]
+    fn inspect_symbols() {
+        let om = OpenMath::from_sexpr(
+            r#"(ombind (oms fns1 lambda)
+                (bvar (omattr (((oms sts type) (oms setname1 R))) x))
+                (omattr (((cdbase "http://example.org" (oms my note)) (omstr "hi")))
+                  (oma (oms arith1 plus)
+                    (ome (oms error unhandled) (oma (oms arith1 minus)) (omforeign "…"))
+                    (cdbase "http://example.org" (oms my sym)))))"#,
+        )
+        .expect("is valid");
+        let mut symbols = Vec::new();
+        inspect(&om, |cdbase, cd, name| {
+            symbols.push(format!("{cdbase}/{cd}#{name}"));
+        });
+        assert_eq!(
+            symbols,
+            [
+                "http://www.openmath.org/cd/fns1#lambda",
+                "http://www.openmath.org/cd/sts#type",
+                "http://www.openmath.org/cd/setname1#R",
+                "http://www.openmath.org/cd/arith1#plus",
+                "http://www.openmath.org/cd/error#unhandled",
+                "http://www.openmath.org/cd/arith1#minus",
+                "http://example.org/my#sym",
+                "http://example.org/my#note",
+            ]
+        );
+    }
+
+    #[test]
+    fn inspect_passes_through() {
+        #[derive(Default)]
+        struct Counter {
+            leafs: usize,
+            nodes: usize,
+        }
+        impl Inspector for Counter {
+            fn omi(&mut self, _: &crate::Int) {
+                self.leafs += 1;
+            }
+            fn omv(&mut self, _: &dyn Display) {
+                self.leafs += 1;
+            }
+            fn oms(&mut self, _: &str, _: &dyn Display, _: &dyn Display) {
+                self.leafs += 1;
+            }
+            fn oma(&mut self, _: usize) {
+                self.nodes += 1;
+            }
+        }
+        struct WithCounter<'o>(&'o OpenMath<'o>, RefCell<Counter>);
+        impl Display for WithCounter<'_> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                self.0
+                    .as_openmath_with(
+                        super::super::DisplaySerializer {
+                            f,
+                            next_ns: None,
+                            current_ns: Cow::Borrowed(crate::CD_BASE),
+                            floats: super::super::FloatStyle::default(),
+                            max_len: None,
+                            wrap: None,
+                        },
+                        &mut *self.1.borrow_mut(),
+                    )
+                    .map_err(Into::into)
+            }
+        }
+        let om = OpenMath::from_sexpr("(oma (oms arith1 plus) (omi 1) (oma (omv f) (omv x)))")
+            .expect("is valid");
+        let display = WithCounter(&om, RefCell::default());
+        assert_eq!(display.to_string(), om.openmath_display().to_string());
+        let counter = display.1.into_inner();
+        assert_eq!((counter.leafs, counter.nodes), (4, 2));
+    }
+}
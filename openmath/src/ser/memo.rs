@@ -0,0 +1,281 @@
+//! Serializing repeated subterms once; see [`Memoized`]
+
+use std::{
+    collections::HashMap,
+    hash::{BuildHasher, Hash, Hasher, RandomState},
+};
+
+use crate::{Attr, OMKind, OMMaybeForeign, OpenMath, Refs, WithRefs};
+
+use super::{OMSerializable, OMSerializer};
+
+/** An [`OpenMath`] object whose repeated subterms are serialized only once per document,
+e.g. for terms that contain the same unit or annotation tens of thousands of times.
+
+[`new`](Self::new) finds the compound subterms (applications, bindings, errors and
+attributed objects) that occur more than once, comparing them by content. When
+serializing, each occurrence is handed to [`OMSerializer::memoized`] keyed by the first of
+its equal subterms. The XML serializer writes the first occurrence and reuses that output
+for all others. Unlike [`Sharder`](super::Sharder), this does not change the output.
+
+Finding the repeated subterms takes about as long as writing the object once without
+memoization, so this pays off for heavily repetitive objects, or when writing them several
+times (see the `memoized` benchmark).
+
+The cache is keyed by the cdbase in effect as well, so output is never reused under a
+different cdbase. In pretty output, it is also keyed by the indentation level; reindenting
+would change the content of strings that span several lines. Writing with
+[recorded formatting](OMSerializable::xml_preserving) does not reuse output, since the
+formatting depends on the position. Other serializers (e.g. serde's) serialize every
+occurrence, unless they implement [`memoized`](OMSerializer::memoized).
+
+# Examples
+```
+use openmath::{OMSerializable, OpenMath, ser::Memoized};
+
+let om = OpenMath::from_sexpr(
+    "(oma (oms arith1 plus)
+       (oma (oms arith1 times) (omi 2) (oma (oms units_metric1 metre)))
+       (oma (oms arith1 times) (omi 3) (oma (oms units_metric1 metre))))",
+)
+.expect("is valid");
+let memoized = Memoized::new(&om);
+assert_eq!(memoized.repeated(), 1);
+# #[cfg(feature = "xml-write")]
+assert_eq!(memoized.xml(true).to_string(), om.xml(true).to_string());
+```
+*/
+pub struct Memoized<'a> {
+    om: &'a OpenMath<'a>,
+    /// The address of the first of several equal subterms, by the addresses of all of them
+    keys: HashMap<usize, usize>,
+    repeated: usize,
+}
+
+impl<'a> Memoized<'a> {
+    /// Finds the repeated subterms of `om`
+    #[must_use]
+    pub fn new(om: &'a OpenMath<'a>) -> Self {
+        let mut first = First::default();
+        let mut occurrences = Vec::new();
+        visit(om, &mut first, &mut occurrences);
+        let counts = first
+            .subterms
+            .into_values()
+            .flatten()
+            .map(|(_, key, n)| (key, n))
+            .collect::<HashMap<_, _>>();
+        let keys = occurrences
+            .into_iter()
+            .filter(|(_, key)| counts.get(key).is_some_and(|n| *n > 1))
+            .collect::<HashMap<_, _>>();
+        let repeated = counts.values().filter(|n| **n > 1).count();
+        Self { om, keys, repeated }
+    }
+
+    /// The number of distinct subterms that occur more than once
+    #[inline]
+    #[must_use]
+    pub const fn repeated(&self) -> usize {
+        self.repeated
+    }
+}
+
+/// The first occurrences of the distinct subterms seen so far, with their address and
+/// number of occurrences, by their hash
+#[derive(Default)]
+struct First<'a> {
+    hasher: RandomState,
+    #[allow(clippy::type_complexity)]
+    subterms: HashMap<u64, Vec<(&'a OpenMath<'a>, usize, usize)>>,
+}
+
+/// Records the compound subterms of `om` (including itself) in `occurrences`, as pairs of
+/// their address and that of the first equal subterm; returns the hash of `om`.
+///
+/// Equal objects hash alike: the hash covers everything but the subobjects, and the hashes
+/// of those. This way, every node is hashed once, rather than once per ancestor.
+fn visit<'a>(
+    om: &'a OpenMath<'a>,
+    first: &mut First<'a>,
+    occurrences: &mut Vec<(usize, usize)>,
+) -> u64 {
+    fn attrs(attributes: &[Attr<'_, OMMaybeForeign<'_, OpenMath<'_>>>], h: &mut impl Hasher) {
+        attributes.len().hash(h);
+        for a in attributes {
+            a.key.hash(h);
+            foreign(&a.value, h);
+        }
+    }
+    fn foreign(o: &OMMaybeForeign<'_, OpenMath<'_>>, h: &mut impl Hasher) {
+        match o {
+            OMMaybeForeign::OM(_) => 0u8.hash(h),
+            OMMaybeForeign::Foreign { encoding, value } => (encoding, value).hash(h),
+        }
+    }
+
+    let mut h = first.hasher.build_hasher();
+    std::mem::discriminant(om).hash(&mut h);
+    attrs(om.attributes(), &mut h);
+    match om {
+        OpenMath::OMI { int, .. } => int.hash(&mut h),
+        OpenMath::OMF { float, .. } => float.hash(&mut h),
+        OpenMath::OMSTR { string, .. } => string.hash(&mut h),
+        OpenMath::OMB { bytes, .. } => bytes.hash(&mut h),
+        OpenMath::OMV { name, .. } => name.hash(&mut h),
+        OpenMath::OMS {
+            cd,
+            name,
+            cdbase,
+            cdbase_inherited,
+            ..
+        } => (cd, name, cdbase, cdbase_inherited).hash(&mut h),
+        OpenMath::OMA { arguments, .. } => arguments.len().hash(&mut h),
+        OpenMath::OME {
+            cd,
+            name,
+            cdbase,
+            arguments,
+            ..
+        } => {
+            (cd, name, cdbase, arguments.len()).hash(&mut h);
+            for a in arguments {
+                foreign(a, &mut h);
+            }
+        }
+        OpenMath::OMBIND { variables, .. } => {
+            variables.len().hash(&mut h);
+            for v in variables {
+                v.name.hash(&mut h);
+                attrs(&v.attributes, &mut h);
+            }
+        }
+    }
+    for c in om.children_with_attrs() {
+        visit(c, first, occurrences).hash(&mut h);
+    }
+    let hash = h.finish();
+
+    let compound = !om.attributes().is_empty()
+        || matches!(om.kind(), OMKind::OMA | OMKind::OME | OMKind::OMBIND);
+    if compound {
+        let addr = key(om);
+        let equal = first.subterms.entry(hash).or_default();
+        let key = if let Some((_, key, n)) = equal.iter_mut().find(|(o, ..)| *o == om) {
+            *n += 1;
+            *key
+        } else {
+            equal.push((om, addr, 1));
+            addr
+        };
+        occurrences.push((addr, key));
+    }
+    hash
+}
+
+#[inline]
+fn key(om: &OpenMath<'_>) -> usize {
+    std::ptr::from_ref(om).addr()
+}
+
+impl Refs for Memoized<'_> {
+    #[inline]
+    fn href(&self, _: &OpenMath<'_>) -> Option<&str> {
+        None
+    }
+    #[inline]
+    fn memo_key(&self, om: &OpenMath<'_>) -> Option<usize> {
+        if self.keys.is_empty() {
+            return None;
+        }
+        self.keys.get(&key(om)).copied()
+    }
+}
+
+impl OMSerializable for Memoized<'_> {
+    #[inline]
+    fn cdbase(&self) -> Option<&str> {
+        self.om.cdbase()
+    }
+    #[inline]
+    fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        WithRefs(self.om, self).as_openmath(serializer)
+    }
+}
+
+impl std::fmt::Debug for Memoized<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Memoized")
+            .field("om", self.om)
+            .field("repeated", &self.repeated)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(test, feature = "xml-write"))]
+mod tests {
+    use super::Memoized;
+    use crate::{
+        OMSerializable, OpenMath,
+        ser::{Options, XmlOptions},
+    };
+
+    #[test]
+    fn memoized_xml_is_unchanged() {
+        let unit = r#"(omattr (((oms meta note) (omstr "two
+lines"))) (oma (oms units_metric1 metre)))"#;
+        let om = OpenMath::from_sexpr(&format!(
+            r#"(oma (oms arith1 plus)
+                 (oma (oms arith1 times) (omi 2) {unit})
+                 (oma (oms arith1 times) (omi 3) {unit})
+                 (cdbase "http://example.org" (oma (oms arith1 times) (omi 2) {unit}))
+                 (oma (oms arith1 minus) (oma (oms arith1 times) (omi 2) {unit}))
+                 (ombind (oms fns1 lambda) (bvar x) {unit}))"#
+        ))
+        .expect("is valid");
+        let memoized = Memoized::new(&om);
+        // the (attributed) unit, and its product with 2
+        assert_eq!(memoized.repeated(), 2);
+        for options in [
+            Options::new(),
+            Options::new().with_pretty(true),
+            Options::new().with_sort_attributes(true),
+        ] {
+            assert_eq!(
+                memoized.xml_with(options).to_string(),
+                om.xml_with(options).to_string()
+            );
+            assert_eq!(
+                memoized
+                    .omobject()
+                    .xml_with(XmlOptions::from(options))
+                    .to_string(),
+                om.omobject()
+                    .xml_with(XmlOptions::from(options))
+                    .to_string()
+            );
+        }
+        assert_eq!(
+            memoized.openmath_display().to_string(),
+            om.openmath_display().to_string()
+        );
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn memoized_under_other_cdbase() {
+        use crate::OMDeserializable;
+        // the same application, once under the default cdbase and once under that of `g`,
+        // where the cdbase of `f` need not be repeated
+        let f = r#"<OMA><OMS cdbase="http://a.org" cd="c" name="f"/><OMV name="x"/></OMA>"#;
+        let xml = format!(
+            r#"<OMA><OMS cd="list1" name="list"/>{f}<OMA cdbase="http://a.org"><OMS cd="c" name="g"/>{f}</OMA></OMA>"#
+        );
+        let om = OpenMath::from_openmath_xml(&xml).expect("is valid");
+        let memoized = Memoized::new(&om);
+        assert_eq!(memoized.repeated(), 1);
+        let written = memoized.xml(false).to_string();
+        assert_eq!(written, om.xml(false).to_string());
+        assert_eq!(written.matches("cdbase=").count(), 2, "{written}");
+    }
+}
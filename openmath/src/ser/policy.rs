@@ -0,0 +1,421 @@
+use std::fmt::Display;
+
+use super::Inspector;
+
+/// How serious it is to emit a symbol; see [`PolicyChecker`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Severity {
+    /// Recorded as a warning
+    Deprecated,
+    /// Aborts serialization
+    Forbidden,
+}
+
+/// A symbol emitted despite a [`PolicyChecker`]'s rules
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// The (effective) cdbase of the symbol
+    pub cdbase: String,
+    /// The content dictionary of the symbol
+    pub cd: String,
+    /// The name of the symbol
+    pub name: String,
+    pub severity: Severity,
+    /// The position of the symbol in the output, as the number of nodes (including
+    /// symbols) serialized before it. Depends on the backend; e.g. XML emits attributions
+    /// before the attributed object.
+    pub position: usize,
+}
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let what = match self.severity {
+            Severity::Deprecated => "deprecated",
+            Severity::Forbidden => "forbidden",
+        };
+        write!(
+            f,
+            "{what} symbol {}?{}#{} at node {}",
+            self.cdbase, self.cd, self.name, self.position
+        )
+    }
+}
+
+struct Rule {
+    cdbase: String,
+    cd: String,
+    /// `None` for all symbols in the content dictionary
+    name: Option<String>,
+    severity: Severity,
+}
+
+/** An [`Inspector`] that checks every emitted symbol (including the heads of
+[OME](crate::OMKind::OME)s and the keys of [OMATTR](crate::OMKind::OMATTR)s) against sets
+of deprecated and forbidden symbols or content dictionaries. Deprecated symbols are recorded
+as [`Violation`]s accessible after serialization; forbidden ones are recorded as well, and
+abort serialization with [`Error::custom`](super::Error::custom).
+
+Since it is an [`Inspector`], it composes with any [`OMSerializer`](super::OMSerializer)
+via [`Inspect`](super::Inspect) or
+[`as_openmath_with`](super::OMSerializable::as_openmath_with), and with the built-in
+serializers via [`Inspected`](super::Inspected).
+
+# Examples
+```
+# #[cfg(feature = "xml-write")]
+# {
+use openmath::{OpenMath, OMSerializable, ser::{Inspected, PolicyChecker, Severity}};
+use std::{cell::RefCell, fmt::Write};
+
+let checker = RefCell::new(
+    PolicyChecker::new()
+        .deprecate(openmath::CD_BASE, "arith1", "power")
+        .forbid_cd("http://example.org/internal", "secret"),
+);
+let om = OpenMath::from_sexpr("(oma (oms arith1 plus) (omi 2) (oma (oms arith1 power) (omv x) (omi 2)))")
+    .expect("is valid");
+let xml = Inspected::new(&om, &checker).xml(false).to_string();
+# assert!(xml.contains("power"));
+let [violation] = checker.borrow().violations().to_owned().try_into().expect("one violation");
+assert_eq!((violation.name.as_str(), violation.severity), ("power", Severity::Deprecated));
+assert_eq!(violation.position, 4);
+
+checker.borrow_mut().reset();
+let om = OpenMath::from_sexpr(r#"(oma (oms arith1 plus) (cdbase "http://example.org/internal" (oms secret x)))"#)
+    .expect("is valid");
+let mut out = String::new();
+assert!(write!(out, "{}", Inspected::new(&om, &checker).xml(false)).is_err());
+# }
+```
+*/
+#[derive(Default)]
+pub struct PolicyChecker {
+    rules: Vec<Rule>,
+    violations: Vec<Violation>,
+    nodes: usize,
+    abort: Option<String>,
+}
+impl PolicyChecker {
+    /// A checker without any rules
+    #[inline]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn rule(
+        mut self,
+        cdbase: impl Into<String>,
+        cd: impl Into<String>,
+        name: Option<String>,
+        severity: Severity,
+    ) -> Self {
+        self.rules.push(Rule {
+            cdbase: cdbase.into(),
+            cd: cd.into(),
+            name,
+            severity,
+        });
+        self
+    }
+
+    /// Warns about the symbol `cdbase?cd#name`
+    #[must_use]
+    pub fn deprecate(
+        self,
+        cdbase: impl Into<String>,
+        cd: impl Into<String>,
+        name: impl Into<String>,
+    ) -> Self {
+        self.rule(cdbase, cd, Some(name.into()), Severity::Deprecated)
+    }
+
+    /// Fails on the symbol `cdbase?cd#name`
+    #[must_use]
+    pub fn forbid(
+        self,
+        cdbase: impl Into<String>,
+        cd: impl Into<String>,
+        name: impl Into<String>,
+    ) -> Self {
+        self.rule(cdbase, cd, Some(name.into()), Severity::Forbidden)
+    }
+
+    /// Warns about all symbols in the content dictionary `cd` at `cdbase`
+    #[must_use]
+    pub fn deprecate_cd(self, cdbase: impl Into<String>, cd: impl Into<String>) -> Self {
+        self.rule(cdbase, cd, None, Severity::Deprecated)
+    }
+
+    /// Fails on all symbols in the content dictionary `cd` at `cdbase`
+    #[must_use]
+    pub fn forbid_cd(self, cdbase: impl Into<String>, cd: impl Into<String>) -> Self {
+        self.rule(cdbase, cd, None, Severity::Forbidden)
+    }
+
+    /** Adds a rule from the metadata of a content dictionary (in the `.ocd` XML format):
+    if its `<CDStatus>` is `obsolete`, all its symbols are deprecated. The cdbase is taken
+    from `<CDBase>`, defaulting to [`CD_BASE`](crate::CD_BASE).
+
+    # Errors
+    if `ocd` is not well-formed XML or has no `<CDName>`.
+    */
+    #[cfg(feature = "xml-read")]
+    pub fn with_cd_metadata(self, ocd: &str) -> Result<Self, CdMetadataError> {
+        use quick_xml::events::Event;
+        let mut reader = quick_xml::Reader::from_str(ocd);
+        let (mut name, mut cdbase, mut status) = (None, None, None);
+        let mut current: Option<Vec<u8>> = None;
+        loop {
+            match reader.read_event()? {
+                Event::Start(s) => current = Some(s.local_name().as_ref().to_vec()),
+                Event::End(_) => current = None,
+                Event::Text(t) => {
+                    let slot = match current.as_deref() {
+                        Some(b"CDName") => &mut name,
+                        Some(b"CDBase") => &mut cdbase,
+                        Some(b"CDStatus") => &mut status,
+                        _ => continue,
+                    };
+                    *slot = Some(t.decode()?.trim().to_string());
+                }
+                Event::Eof => break,
+                _ => (),
+            }
+        }
+        let name = name.ok_or(CdMetadataError::MissingName)?;
+        Ok(if status.as_deref() == Some("obsolete") {
+            self.deprecate_cd(cdbase.unwrap_or_else(|| crate::CD_BASE.to_string()), name)
+        } else {
+            self
+        })
+    }
+
+    /// The violations found so far, in the order of the output
+    #[inline]
+    #[must_use]
+    pub fn violations(&self) -> &[Violation] {
+        &self.violations
+    }
+
+    /// The violations found
+    #[inline]
+    #[must_use]
+    pub fn into_violations(self) -> Vec<Violation> {
+        self.violations
+    }
+
+    /// Forgets the violations found so far, e.g. to reuse this checker for the next object
+    pub fn reset(&mut self) {
+        self.violations.clear();
+        self.nodes = 0;
+        self.abort = None;
+    }
+
+    const fn node(&mut self) {
+        self.nodes += 1;
+    }
+}
+
+impl Inspector for PolicyChecker {
+    fn omi(&mut self, _: &crate::Int) {
+        self.node();
+    }
+    fn omf(&mut self, _: f64) {
+        self.node();
+    }
+    fn omstr(&mut self, _: &dyn Display) {
+        self.node();
+    }
+    fn omb(&mut self, _: usize) {
+        self.node();
+    }
+    fn omv(&mut self, _: &dyn Display) {
+        self.node();
+    }
+    fn omr(&mut self, _: &dyn Display) {
+        self.node();
+    }
+    fn raw_xml(&mut self, _: &str) {
+        self.node();
+    }
+    fn oma(&mut self, _: usize) {
+        self.node();
+    }
+    fn ome(&mut self, _: usize) {
+        self.node();
+    }
+    fn omattr(&mut self, _: usize) {
+        self.node();
+    }
+    fn ombind(&mut self, _: usize) {
+        self.node();
+    }
+    fn oms(&mut self, cdbase: &str, cd: &dyn Display, name: &dyn Display) {
+        let position = self.nodes;
+        self.node();
+        if self.rules.is_empty() {
+            return;
+        }
+        let (cd, name) = (cd.to_string(), name.to_string());
+        let Some(severity) = self
+            .rules
+            .iter()
+            .filter(|r| {
+                r.cd == cd
+                    && r.name.as_ref().is_none_or(|n| *n == name)
+                    && crate::cdbase::eq_normalized(&r.cdbase, cdbase)
+            })
+            .map(|r| r.severity)
+            .max()
+        else {
+            return;
+        };
+        let violation = Violation {
+            cdbase: cdbase.to_string(),
+            cd,
+            name,
+            severity,
+            position,
+        };
+        if severity == Severity::Forbidden && self.abort.is_none() {
+            self.abort = Some(violation.to_string());
+        }
+        self.violations.push(violation);
+    }
+    fn abort(&mut self) -> Option<String> {
+        self.abort.clone()
+    }
+}
+
+/// Errors when reading content dictionary metadata in
+/// [`PolicyChecker::with_cd_metadata`]
+#[cfg(feature = "xml-read")]
+#[derive(Debug, thiserror::Error)]
+pub enum CdMetadataError {
+    #[error("invalid content dictionary: {0}")]
+    Xml(#[from] quick_xml::Error),
+    #[error("invalid content dictionary: {0}")]
+    Encoding(#[from] quick_xml::encoding::EncodingError),
+    #[error("content dictionary has no CDName")]
+    MissingName,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OMSerializable, OpenMath, ser::Inspected};
+    use std::cell::RefCell;
+
+    const SEXPR: &str = r#"(omattr (((oms my note) (omstr "hi")))
+        (oma (oms arith1 plus)
+          (ome (oms error unhandled) (oma (oms arith1 minus)))
+          (cdbase "http://example.org" (oms my sym))))"#;
+
+    fn rules() -> PolicyChecker {
+        PolicyChecker::new()
+            .deprecate("http://www.openmath.org/cd/", "error", "unhandled")
+            .deprecate_cd("http://example.org", "my")
+            .deprecate(crate::CD_BASE, "my", "note")
+    }
+
+    #[test]
+    fn warnings() {
+        let om = OpenMath::from_sexpr(SEXPR).expect("is valid");
+        let checker = RefCell::new(rules());
+        let display = Inspected::new(&om, &checker).openmath_display().to_string();
+        assert_eq!(display, om.openmath_display().to_string());
+        let found = checker
+            .borrow()
+            .violations()
+            .iter()
+            .map(|v| (v.cd.clone(), v.name.clone(), v.position))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                ("error".to_string(), "unhandled".to_string(), 4),
+                ("my".to_string(), "sym".to_string(), 7),
+                ("my".to_string(), "note".to_string(), 8),
+            ]
+        );
+        assert!(
+            checker
+                .borrow()
+                .violations()
+                .iter()
+                .all(|v| v.severity == Severity::Deprecated)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "xml-write")]
+    fn warnings_xml() {
+        // XML puts attributions before the attributed object
+        let om = OpenMath::from_sexpr(SEXPR).expect("is valid");
+        let checker = RefCell::new(rules());
+        let _ = Inspected::new(&om, &checker).xml(true).to_string();
+        let found = checker
+            .into_inner()
+            .into_violations()
+            .into_iter()
+            .map(|v| (v.name, v.position))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                ("note".to_string(), 1),
+                ("unhandled".to_string(), 6),
+                ("sym".to_string(), 9)
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "xml-write")]
+    fn forbidden() {
+        use std::fmt::Write;
+        let om = OpenMath::from_sexpr(SEXPR).expect("is valid");
+        for (checker, name) in [
+            (
+                rules().forbid(crate::CD_BASE, "error", "unhandled"),
+                "unhandled",
+            ),
+            (rules().forbid_cd("http://example.org/", "my"), "sym"),
+            (rules().forbid(crate::CD_BASE, "my", "note"), "note"),
+        ] {
+            let checker = RefCell::new(checker);
+            let mut out = String::new();
+            assert!(write!(out, "{}", Inspected::new(&om, &checker).xml(false)).is_err());
+            let checker = checker.into_inner();
+            let last = checker.violations().last().expect("found");
+            assert_eq!(
+                (last.name.as_str(), last.severity),
+                (name, Severity::Forbidden)
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "xml-read")]
+    fn cd_metadata() {
+        const OCD: &str = r#"<CD xmlns="http://www.openmath.org/OpenMathCD">
+            <CDName> my </CDName>
+            <CDBase>http://example.org</CDBase>
+            <CDRevision>3</CDRevision>
+            <CDStatus>obsolete</CDStatus>
+        </CD>"#;
+        let checker = PolicyChecker::new()
+            .with_cd_metadata(OCD)
+            .expect("is valid")
+            .with_cd_metadata(&OCD.replace("obsolete", "official"))
+            .expect("is valid");
+        let om = OpenMath::from_sexpr(SEXPR).expect("is valid");
+        let checker = RefCell::new(checker);
+        let _ = Inspected::new(&om, &checker).openmath_display().to_string();
+        assert_eq!(checker.borrow().violations().len(), 1);
+        assert!(matches!(
+            PolicyChecker::new().with_cd_metadata("<CD></CD>"),
+            Err(CdMetadataError::MissingName)
+        ));
+    }
+}
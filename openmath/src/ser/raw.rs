@@ -0,0 +1,413 @@
+use super::{OMSerializable, OMSerializer};
+
+/** A pre-rendered fragment of <span style="font-variant:small-caps;">OpenMath</span> XML
+(e.g. produced by another tool), to be embedded into a larger object without parsing it
+first; an explicit escape hatch for assembling documents in stages.
+
+The XML serializer writes the fragment verbatim (without surrounding whitespace) at the
+current position. Pretty printing puts it on a line of its own at the current indentation,
+but does not reindent the lines *inside* the fragment. The fragment is interpreted in the
+cdbase in effect at its position, so it should carry its own `cdbase` attribute if that
+matters; changing the cdbase via [`with_cdbase`](OMSerializer::with_cdbase) right before
+a fragment is an error.
+
+The [`Display`](std::fmt::Display) serializer renders a placeholder `RAWXML(...)`; other
+serializers (in particular the serde backend) fail, since raw XML can not be embedded
+into, e.g., JSON.
+
+# Examples
+```
+# #[cfg(feature = "xml-write")]
+# {
+use openmath::{OMSerializable, ser::{AsOMS, OMSerializer, RawXml, Uri}};
+
+/// The sum of two pre-rendered objects
+struct Sum<'a>(RawXml<'a>, RawXml<'a>);
+impl OMSerializable for Sum<'_> {
+    fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        let plus = Uri { cdbase: None, cd: "arith1", name: "plus" };
+        serializer.oma(plus.as_oms(), [self.0, self.1].into_iter())
+    }
+}
+
+let a = RawXml::new(r#"<OMA><OMS cd="arith1" name="times"/><OMI>2</OMI><OMV name="x"/></OMA>"#)
+    .expect("is a balanced OpenMath element");
+let b = RawXml::new(r#"<OMI>1</OMI>"#).expect("is a balanced OpenMath element");
+assert_eq!(
+    Sum(a, b).xml(false).to_string(),
+    r#"<OMA><OMS cd="arith1" name="plus"/><OMA><OMS cd="arith1" name="times"/><OMI>2</OMI><OMV name="x"/></OMA><OMI>1</OMI></OMA>"#
+);
+assert_eq!(Sum(a, b).openmath_display().to_string(), format!("OMA(OMS(arith1#plus),RAWXML({}),RAWXML(<OMI>1</OMI>))", a.as_str()));
+
+assert!(RawXml::new("<OMA><OMI>1</OMI>").is_err());
+assert!(RawXml::new("<html/>").is_err());
+# }
+```
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RawXml<'a>(&'a str);
+
+impl<'a> RawXml<'a> {
+    /** Checks that `xml` consists of exactly one balanced element (with arbitrary
+    whitespace, comments and processing instructions around it), which is an
+    <span style="font-variant:small-caps;">OpenMath</span> object element (i.e. `OMI`,
+    `OMA`, etc.; not `OMOBJ` or `OMFOREIGN`) without a namespace prefix and in the
+    <span style="font-variant:small-caps;">OpenMath</span> namespace, if any.
+
+    This is a cheap structural check; it does not validate the content of the fragment.
+
+    # Errors
+    if `xml` is not such a fragment.
+    */
+    pub fn new(xml: &'a str) -> Result<Self, RawXmlError> {
+        check(xml, false)?;
+        Ok(Self(xml))
+    }
+
+    /// Wraps `xml` without any checks; the caller is responsible for it being a
+    /// well-formed <span style="font-variant:small-caps;">OpenMath</span> element.
+    #[inline]
+    #[must_use]
+    pub const fn unchecked(xml: &'a str) -> Self {
+        Self(xml)
+    }
+
+    /// The fragment
+    #[inline]
+    #[must_use]
+    pub const fn as_str(&self) -> &'a str {
+        self.0
+    }
+}
+
+impl OMSerializable for RawXml<'_> {
+    #[inline]
+    fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        serializer.raw_xml(self.0)
+    }
+}
+
+/// Errors when checking a fragment of XML with [`RawXml::new`]
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum RawXmlError {
+    #[error("raw XML must consist of exactly one element")]
+    NotOneElement,
+    #[error("text outside of the element in raw XML at byte {0}")]
+    Text(usize),
+    #[error("malformed markup in raw XML at byte {0}")]
+    Malformed(usize),
+    #[error("element <{0}> in raw XML is never closed")]
+    Unclosed(String),
+    #[error("</{found}> in raw XML at byte {position} does not close <{expected}>")]
+    Mismatched {
+        expected: String,
+        found: String,
+        position: usize,
+    },
+    #[error("<{0}> is not an OpenMath object element")]
+    NotOpenMath(String),
+    #[error("raw XML is in namespace {0} instead of the OpenMath namespace")]
+    Namespace(String),
+}
+
+/// The name of the first element in `xml`, if any; used to record the position of a
+/// fragment when writing with preserved formatting
+pub fn root_name(xml: &str) -> &str {
+    let Some(start) = xml.find('<') else {
+        return "";
+    };
+    let rest = &xml[start + 1..];
+    let end = rest
+        .find(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>')
+        .unwrap_or(rest.len());
+    &rest[..end]
+}
+
+/// Checks that the content of an [OMFOREIGN](crate::OMKind::OMFOREIGN) is balanced markup
+/// (with arbitrary text around and between its elements)
+#[cfg(feature = "xml-write")]
+pub fn check_foreign(xml: &str) -> Result<(), RawXmlError> {
+    check(xml, true)
+}
+
+/// Checks that `xml` is balanced markup; unless `foreign`, also that it consists of exactly
+/// one <span style="font-variant:small-caps;">OpenMath</span> element
+fn check(xml: &str, foreign: bool) -> Result<(), RawXmlError> {
+    let mut stack: Vec<&str> = Vec::new();
+    let mut roots = 0;
+    let mut pos = 0;
+    while pos < xml.len() {
+        let rest = &xml[pos..];
+        let Some(lt) = rest.find('<') else {
+            if !foreign && stack.is_empty() && !rest.trim().is_empty() {
+                return Err(RawXmlError::Text(pos));
+            }
+            break;
+        };
+        if !foreign && stack.is_empty() && !rest[..lt].trim().is_empty() {
+            return Err(RawXmlError::Text(pos));
+        }
+        pos += lt;
+        let tag = &xml[pos..];
+        let skip = |open: &str, close: &str| {
+            tag.strip_prefix(open)
+                .and_then(|t| t.find(close))
+                .map(|i| open.len() + i + close.len())
+        };
+        if tag.starts_with("<!--") {
+            pos += skip("<!--", "-->").ok_or(RawXmlError::Malformed(pos))?;
+        } else if tag.starts_with("<?") {
+            pos += skip("<?", "?>").ok_or(RawXmlError::Malformed(pos))?;
+        } else if tag.starts_with("<![CDATA[") && (foreign || !stack.is_empty()) {
+            pos += skip("<![CDATA[", "]]>").ok_or(RawXmlError::Malformed(pos))?;
+        } else if let Some(end) = tag.strip_prefix("</") {
+            let close = end.find('>').ok_or(RawXmlError::Malformed(pos))?;
+            let found = end[..close].trim_end();
+            match stack.pop() {
+                Some(expected) if expected == found => (),
+                Some(expected) => {
+                    return Err(RawXmlError::Mismatched {
+                        expected: expected.to_string(),
+                        found: found.to_string(),
+                        position: pos,
+                    });
+                }
+                None => return Err(RawXmlError::Malformed(pos)),
+            }
+            pos += 2 + close + 1;
+        } else {
+            let name = root_name(tag);
+            if name.is_empty() || name.starts_with('!') {
+                return Err(RawXmlError::Malformed(pos));
+            }
+            let (len, empty, xmlns) = start_tag(tag, name.len() + 1, pos)?;
+            if !foreign && stack.is_empty() {
+                roots += 1;
+                if roots > 1 {
+                    return Err(RawXmlError::NotOneElement);
+                }
+                if !matches!(
+                    crate::OMKind::from_name(name.as_bytes()),
+                    Some(k) if k != crate::OMKind::OMFOREIGN
+                ) {
+                    return Err(RawXmlError::NotOpenMath(name.to_string()));
+                }
+                if let Some(ns) = xmlns.filter(|ns| *ns != crate::XML_NS) {
+                    return Err(RawXmlError::Namespace(ns.to_string()));
+                }
+            }
+            if !empty {
+                stack.push(name);
+            }
+            pos += len;
+        }
+    }
+    if let Some(open) = stack.pop() {
+        return Err(RawXmlError::Unclosed(open.to_string()));
+    }
+    if !foreign && roots == 0 {
+        return Err(RawXmlError::NotOneElement);
+    }
+    Ok(())
+}
+
+/// Scans the attributes of the start tag `tag`, beginning at `i` (after the name); returns
+/// the length of the tag, whether it is an empty element, and its `xmlns` attribute
+fn start_tag(
+    tag: &str,
+    mut i: usize,
+    pos: usize,
+) -> Result<(usize, bool, Option<&str>), RawXmlError> {
+    let bytes = tag.as_bytes();
+    let mut xmlns = None;
+    loop {
+        while bytes.get(i).is_some_and(u8::is_ascii_whitespace) {
+            i += 1;
+        }
+        match bytes.get(i) {
+            Some(b'>') => return Ok((i + 1, false, xmlns)),
+            Some(b'/') if bytes.get(i + 1) == Some(&b'>') => return Ok((i + 2, true, xmlns)),
+            Some(_) => (),
+            None => return Err(RawXmlError::Malformed(pos)),
+        }
+        let eq = tag[i..].find('=').ok_or(RawXmlError::Malformed(pos))? + i;
+        let key = tag[i..eq].trim();
+        if key.is_empty() || key.contains(|c: char| c.is_ascii_whitespace() || c == '>') {
+            return Err(RawXmlError::Malformed(pos));
+        }
+        i = eq + 1;
+        while bytes.get(i).is_some_and(u8::is_ascii_whitespace) {
+            i += 1;
+        }
+        let quote = match bytes.get(i) {
+            Some(q @ (b'"' | b'\'')) => *q as char,
+            _ => return Err(RawXmlError::Malformed(pos)),
+        };
+        let len = tag[i + 1..]
+            .find(quote)
+            .ok_or(RawXmlError::Malformed(pos))?;
+        let value = &tag[i + 1..i + 1 + len];
+        if value.contains('<') {
+            return Err(RawXmlError::Malformed(pos));
+        }
+        if key == "xmlns" {
+            xmlns = Some(value);
+        }
+        i += len + 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RawXml, RawXmlError};
+    use crate::ser::{AsOMS, OMSerializable, OMSerializer, Uri};
+
+    /// `(arith1#plus first raw)`, with `raw` optionally under a different cdbase
+    struct Plus<'a>(RawXml<'a>, Option<&'a str>);
+    impl OMSerializable for Plus<'_> {
+        fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+            struct Under<'a>(RawXml<'a>, Option<&'a str>);
+            impl OMSerializable for Under<'_> {
+                fn as_openmath<'s, S: OMSerializer<'s>>(
+                    &self,
+                    serializer: S,
+                ) -> Result<S::Ok, S::Err> {
+                    match self.1 {
+                        Some(cdbase) => self.0.as_openmath(serializer.with_cdbase(cdbase)?),
+                        None => self.0.as_openmath(serializer),
+                    }
+                }
+            }
+            let plus = Uri {
+                cdbase: None,
+                cd: "arith1",
+                name: "plus",
+            };
+            serializer.oma(
+                plus.as_oms(),
+                [
+                    either::Either::Left(crate::Int::from(1)),
+                    either::Either::Right(Under(self.0, self.1)),
+                ]
+                .into_iter(),
+            )
+        }
+    }
+
+    #[test]
+    fn check() {
+        for ok in [
+            "<OMI>1</OMI>",
+            "  <OMV name=\"x\"/>\n",
+            "<!-- a comment --><?pi x?>\n<OMS cd='arith1' name=\"plus\" />",
+            r#"<OMSTR xmlns="http://www.openmath.org/OpenMath"><![CDATA[</OMA> <x>]]></OMSTR>"#,
+            r#"<OMA><OMS cd="arith1" name="gt"/><OMSTR>a > b</OMSTR><OMV name="x"/></OMA>"#,
+            r#"<OME><OMS cd="e" name="e"/><OMFOREIGN><b>bold</b></OMFOREIGN></OME>"#,
+        ] {
+            assert!(RawXml::new(ok).is_ok(), "{ok}");
+        }
+        for (bad, err) in [
+            ("", RawXmlError::NotOneElement),
+            ("  ", RawXmlError::NotOneElement),
+            (
+                "<OMA><OMI>1</OMI>",
+                RawXmlError::Unclosed("OMA".to_string()),
+            ),
+            (
+                "<OMA><OMI>1</OMS></OMA>",
+                RawXmlError::Mismatched {
+                    expected: "OMI".to_string(),
+                    found: "OMS".to_string(),
+                    position: 11,
+                },
+            ),
+            ("<OMI>1</OMI></OMA>", RawXmlError::Malformed(12)),
+            ("<OMI>1</OMI><OMI>2</OMI>", RawXmlError::NotOneElement),
+            ("x<OMI>1</OMI>", RawXmlError::Text(0)),
+            ("<OMI>1</OMI>x", RawXmlError::Text(12)),
+            ("<OMV name=\"x/>", RawXmlError::Malformed(0)),
+            ("<OMV name=x/>", RawXmlError::Malformed(0)),
+            ("<OMI>1", RawXmlError::Unclosed("OMI".to_string())),
+            (
+                "<OMOBJ><OMI>1</OMI></OMOBJ>",
+                RawXmlError::NotOpenMath("OMOBJ".to_string()),
+            ),
+            (
+                "<OMFOREIGN>x</OMFOREIGN>",
+                RawXmlError::NotOpenMath("OMFOREIGN".to_string()),
+            ),
+            (
+                "<om:OMI>1</om:OMI>",
+                RawXmlError::NotOpenMath("om:OMI".to_string()),
+            ),
+            (
+                r#"<OMI xmlns="http://example.org">1</OMI>"#,
+                RawXmlError::Namespace("http://example.org".to_string()),
+            ),
+        ] {
+            assert_eq!(RawXml::new(bad), Err(err), "{bad:?}");
+        }
+    }
+
+    #[cfg(feature = "xml-write")]
+    #[test]
+    fn pretty() {
+        let raw = RawXml::new(
+            "\n<OMA><OMS cd=\"arith1\" name=\"times\"/>\n  <OMI>2</OMI><OMV name=\"x\"/></OMA>\n",
+        )
+        .expect("is valid");
+        assert_eq!(
+            Plus(raw, None).xml(true).to_string(),
+            "<OMA>\n  <OMS cd=\"arith1\" name=\"plus\"/>\n  <OMI>1</OMI>\n  \
+             <OMA><OMS cd=\"arith1\" name=\"times\"/>\n  <OMI>2</OMI><OMV name=\"x\"/></OMA>\n</OMA>"
+        );
+        assert_eq!(
+            Plus(raw, None).xml(false).to_string(),
+            "<OMA><OMS cd=\"arith1\" name=\"plus\"/><OMI>1</OMI>\
+             <OMA><OMS cd=\"arith1\" name=\"times\"/>\n  <OMI>2</OMI><OMV name=\"x\"/></OMA></OMA>"
+        );
+        #[cfg(feature = "xml-read")]
+        {
+            use crate::{OMDeserializable, OpenMath};
+            let expected = OpenMath::from_sexpr(
+                "(oma (oms arith1 plus) (omi 1) (oma (oms arith1 times) (omi 2) (omv x)))",
+            )
+            .expect("is valid");
+            for pretty in [true, false] {
+                let xml = Plus(raw, None).xml(pretty).to_string();
+                assert_eq!(
+                    OpenMath::from_openmath_xml(&xml).expect("is valid"),
+                    expected
+                );
+            }
+        }
+
+        // the cdbase can not be changed
+        let mut out = String::new();
+        let r = std::fmt::Write::write_fmt(
+            &mut out,
+            format_args!("{}", Plus(raw, Some("http://example.org")).xml(false)),
+        );
+        assert!(r.is_err());
+    }
+
+    #[test]
+    fn other_serializers() {
+        let raw = RawXml::new("<OMI>12345678901234567890123456789012345678901234567890</OMI>")
+            .expect("is valid");
+        assert_eq!(
+            Plus(raw, None).openmath_display().to_string(),
+            format!("OMA(OMS(arith1#plus),OMI(1),RAWXML({}))", raw.as_str())
+        );
+        #[cfg(feature = "serde")]
+        {
+            let err = serde_json::to_string(&Plus(raw, None).openmath_serde())
+                .expect_err("can not embed raw XML");
+            assert!(
+                err.to_string()
+                    .contains("raw XML can not be embedded in JSON"),
+                "{err}"
+            );
+        }
+    }
+}
@@ -25,6 +25,7 @@ use serde::{
     Serializer,
     ser::{SerializeSeq, SerializeStruct, SerializeTuple},
 };
+use std::borrow::Cow;
 impl<E: serde::ser::Error> super::Error for E {
     #[allow(clippy::inline_always)]
     #[inline(always)]
@@ -42,17 +43,44 @@ impl<O: OMSerializable + ?Sized> serde::Serialize for super::OMObject<'_, O> {
         let mut s =
             serializer.serialize_struct("OMObject", if cdbase.is_some() { 4 } else { 3 })?;
         s.serialize_field("kind", "OMOBJ")?;
-        s.serialize_field("openmath", "2.0")?;
-        if let Some(b) = self.0.cdbase() {
+        s.serialize_field("openmath", crate::OPENMATH_VERSION)?;
+        if let Some(b) = cdbase {
             s.serialize_field("cdbase", b)?;
         } else {
             s.skip_field("cdbase")?;
         }
-        s.serialize_field("object", &self.0.openmath_serde())?;
+        // the object inherits the cdbase of the OMOBJ, rather than repeating it
+        s.serialize_field(
+            "object",
+            &SerdeSerializer(self.0, None, cdbase.unwrap_or(crate::CD_BASE), Form::Object),
+        )?;
         s.end()
     }
 }
 
+/// An OMOBJ in the compact form, `["OMOBJ", version, cdbase, object]`; see
+/// [`OMObject::openmath_serde_compact`](super::OMObject::openmath_serde_compact)
+pub struct CompactObject<'s, O: OMSerializable + ?Sized>(pub(crate) &'s O);
+impl<O: OMSerializable + ?Sized> serde::Serialize for CompactObject<'_, O> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let cdbase = self.0.cdbase();
+        let mut t = serializer.serialize_tuple(4)?;
+        t.serialize_element("OMOBJ")?;
+        t.serialize_element(crate::OPENMATH_VERSION)?;
+        t.serialize_element(&cdbase)?;
+        t.serialize_element(&SerdeSerializer(
+            self.0,
+            None,
+            cdbase.unwrap_or(crate::CD_BASE),
+            Form::Compact,
+        ))?;
+        t.end()
+    }
+}
+
 /// Wrapper type that implements `serde::Serialize` for <span style="font-variant:small-caps;">OpenMath</span> objects.
 ///
 /// This type wraps any `OMSerializable` type and provides a `serde::Serialize`
@@ -76,6 +104,7 @@ pub struct SerdeSerializer<'s, OM>(
     pub(crate) OM,
     pub(crate) Option<&'s str>,
     pub(crate) &'s str,
+    pub(crate) Form,
 )
 where
     OM: crate::OMSerializable;
@@ -89,8 +118,9 @@ impl<OM: crate::OMSerializable> ::serde::Serialize for SerdeSerializer<'_, OM> {
         use serde::ser::Error;
         let serializer = Serder {
             s: serializer,
-            next_ns: self.1,
-            current_ns: self.2,
+            next_ns: self.1.map(Cow::Borrowed),
+            current_ns: Cow::Borrowed(self.2),
+            form: self.3,
         };
         self.0.as_openmath(serializer).map_err(S::Error::custom)
     }
@@ -103,8 +133,114 @@ impl<OM: crate::OMSerializable> ::serde::Serialize for SerdeSerializer<'_, OM> {
 /// serde-compatible format.
 struct Serder<'s, S: ::serde::Serializer> {
     s: S,
-    next_ns: Option<&'s str>,
-    current_ns: &'s str,
+    next_ns: Option<Cow<'s, str>>,
+    current_ns: Cow<'s, str>,
+    form: Form,
+}
+
+/// Whether objects are serialized as structs (the object form of the
+/// <span style="font-variant:small-caps;">OpenMath</span> JSON encoding) or as positional
+/// arrays (the compact form); see [`OMSerializable::openmath_serde_compact`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Form {
+    Object,
+    Compact,
+    /// An [OMS](crate::OMKind::OMS) in the error position of an
+    /// [OME](crate::OMKind::OME) or the key of an attribution, in compact form; i.e.
+    /// without a `kind`
+    CompactSymbol,
+}
+impl Form {
+    /// The form of an [OMS](crate::OMKind::OMS) used as an error or attribute key
+    const fn symbol(self) -> Self {
+        match self {
+            Self::Object => Self::Object,
+            Self::Compact | Self::CompactSymbol => Self::CompactSymbol,
+        }
+    }
+}
+
+/// The fields of an object, written either as a struct or as a tuple
+enum Fields<S: Serializer> {
+    Struct(S::SerializeStruct),
+    Tuple(S::SerializeTuple),
+}
+impl<S: Serializer> Fields<S> {
+    /// `len` is the number of fields actually present in the struct; the tuple always
+    /// contains all `positional` fields, with absent ones as `None`
+    fn new(s: S, form: Form, len: usize, positional: usize) -> Result<Self, S::Error> {
+        if form == Form::Object {
+            s.serialize_struct("OMObject", len).map(Self::Struct)
+        } else {
+            s.serialize_tuple(positional).map(Self::Tuple)
+        }
+    }
+
+    fn field<T: serde::Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), S::Error> {
+        match self {
+            Self::Struct(s) => s.serialize_field(key, value),
+            Self::Tuple(t) => t.serialize_element(value),
+        }
+    }
+
+    fn skip(&mut self, key: &'static str) -> Result<(), S::Error> {
+        match self {
+            Self::Struct(s) => s.skip_field(key),
+            Self::Tuple(t) => t.serialize_element(&None::<()>),
+        }
+    }
+
+    fn end(self) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Struct(s) => s.end(),
+            Self::Tuple(t) => t.end(),
+        }
+    }
+}
+
+impl<S: ::serde::Serializer> Serder<'_, S> {
+    fn oma_iter(
+        mut self,
+        head: impl OMSerializable,
+        args: impl Iterator<Item: OMSerializable>,
+        empty: bool,
+    ) -> Result<S::Ok, S::Error> {
+        let mut num_fields = 2;
+        if !empty {
+            num_fields += 1;
+        }
+        if self.next_ns.is_some() {
+            num_fields += 1;
+        }
+        let mut struc = Fields::new(self.s, self.form, num_fields, 5)?;
+        struc.field("kind", &crate::OMKind::OMA)?;
+        struc.skip("id")?;
+        if let Some(ns) = self.next_ns.take() {
+            struc.field("cdbase", &ns)?;
+            self.current_ns = ns;
+        } else {
+            struc.skip("cdbase")?;
+        }
+        struc.field(
+            "applicant",
+            &SerdeSerializer(head, None, &self.current_ns, self.form),
+        )?;
+        if empty {
+            struc.skip("arguments")?;
+        } else {
+            struc.field(
+                "arguments",
+                &Iter(std::cell::Cell::new(Some(args.map(|e| {
+                    SerdeSerializer(e, None, &self.current_ns, self.form)
+                })))),
+            )?;
+        }
+        struc.end()
+    }
 }
 
 impl<'s, S: ::serde::Serializer> OMSerializer<'s> for Serder<'s, S> {
@@ -117,67 +253,99 @@ impl<'s, S: ::serde::Serializer> OMSerializer<'s> for Serder<'s, S> {
 
     #[inline]
     fn current_cdbase(&self) -> &str {
-        self.next_ns.unwrap_or(self.current_ns)
+        self.next_ns.as_deref().unwrap_or(&self.current_ns)
     }
 
-    fn with_cdbase<'ns>(self, cdbase: &'ns str) -> Result<Self::SubSerializer<'ns>, Self::Err>
+    fn with_cdbase<'ns>(
+        self,
+        cdbase: impl Into<Cow<'ns, str>>,
+    ) -> Result<Self::SubSerializer<'ns>, Self::Err>
     where
         's: 'ns,
     {
-        if self.current_ns == cdbase {
-            Ok(self)
+        let cdbase = cdbase.into();
+        if crate::cdbase::eq_normalized(&self.current_ns, &cdbase) {
+            Ok(Self {
+                next_ns: None,
+                ..self
+            })
         } else {
             Ok(Serder {
                 s: self.s,
                 next_ns: Some(cdbase),
                 current_ns: self.current_ns,
+                form: self.form,
             })
         }
     }
 
     fn omi(self, value: &crate::Int) -> Result<Self::Ok, Self::Err> {
-        let mut struc = self.s.serialize_struct("OMObject", 2)?;
-        struc.serialize_field("kind", &crate::OMKind::OMI)?;
-        struc.skip_field("id")?;
+        let mut struc = Fields::new(self.s, self.form, 2, 3)?;
+        struc.field("kind", &crate::OMKind::OMI)?;
+        struc.skip("id")?;
         if let Some(i) = value.is_i128() {
-            struc.serialize_field("integer", &i)?;
+            struc.field("integer", &i)?;
         } else {
-            struc.serialize_field("decimal", value)?;
+            struc.field("decimal", value)?;
         }
         struc.end()
     }
 
     fn omf(self, value: f64) -> Result<Self::Ok, Self::Err> {
-        let mut struc = self.s.serialize_struct("OMObject", 2)?;
-        struc.serialize_field("kind", &crate::OMKind::OMF)?;
-        struc.skip_field("id")?;
-        struc.serialize_field("float", &value)?;
+        let mut struc = Fields::new(self.s, self.form, 2, 3)?;
+        struc.field("kind", &crate::OMKind::OMF)?;
+        struc.skip("id")?;
+        if value.is_finite() {
+            struc.field("float", &value)?;
+        } else {
+            // not representable as a JSON number; written as `NaN`, `inf` or `-inf`
+            struc.field("decimal", &DWrap(value))?;
+        }
         struc.end()
     }
 
     fn omstr(self, string: impl std::fmt::Display) -> Result<Self::Ok, Self::Err> {
-        let mut struc = self.s.serialize_struct("OMObject", 2)?;
-        struc.serialize_field("kind", &crate::OMKind::OMSTR)?;
-        struc.skip_field("id")?;
-        struc.serialize_field("string", &DWrap(string))?;
+        let mut struc = Fields::new(self.s, self.form, 2, 3)?;
+        struc.field("kind", &crate::OMKind::OMSTR)?;
+        struc.skip("id")?;
+        struc.field("string", &DWrap(string))?;
         struc.end()
     }
 
     fn omb(self, bytes: impl ExactSizeIterator<Item = u8>) -> Result<Self::Ok, Self::Err> {
-        use crate::base64::Base64Encodable;
-        let mut struc = self.s.serialize_struct("OMObject", 2)?;
-        struc.serialize_field("kind", &crate::OMKind::OMB)?;
-        struc.skip_field("id")?;
-        let s = bytes.into_iter().base64().into_string();
-        struc.serialize_field("base64", &s)?;
+        let mut struc = Fields::new(self.s, self.form, 2, 3)?;
+        struc.field("kind", &crate::OMKind::OMB)?;
+        struc.skip("id")?;
+        if self.form == Form::Object {
+            let mut s = String::new();
+            crate::base64::encode_into(&bytes.collect::<Vec<_>>(), &mut s);
+            struc.field("base64", &s)?;
+        } else {
+            struc.field("bytes", &Bytes(bytes.collect()))?;
+        }
         struc.end()
     }
 
     fn omv(self, name: impl std::fmt::Display) -> Result<Self::Ok, Self::Err> {
-        let mut struc = self.s.serialize_struct("OMObject", 2)?;
-        struc.serialize_field("kind", &crate::OMKind::OMV)?;
-        struc.skip_field("id")?;
-        struc.serialize_field("name", &DWrap(name))?;
+        let mut struc = Fields::new(self.s, self.form, 2, 3)?;
+        struc.field("kind", &crate::OMKind::OMV)?;
+        struc.skip("id")?;
+        struc.field("name", &DWrap(name))?;
+        struc.end()
+    }
+
+    fn raw_xml(self, _: &str) -> Result<Self::Ok, Self::Err> {
+        use serde::ser::Error;
+        Err(S::Error::custom(
+            "raw XML can not be embedded in JSON (or other serde formats); parse it into an OpenMath object first",
+        ))
+    }
+
+    fn omr(self, href: impl std::fmt::Display) -> Result<Self::Ok, Self::Err> {
+        let mut struc = Fields::new(self.s, self.form, 2, 3)?;
+        struc.field("kind", &crate::OMKind::OMR)?;
+        struc.skip("id")?;
+        struc.field("href", &DWrap(href))?;
         struc.end()
     }
 
@@ -187,16 +355,19 @@ impl<'s, S: ::serde::Serializer> OMSerializer<'s> for Serder<'s, S> {
         name: impl std::fmt::Display,
     ) -> Result<Self::Ok, Self::Err> {
         let num_fields = if self.next_ns.is_some() { 4 } else { 3 };
-        let mut struc = self.s.serialize_struct("OMObject", num_fields)?;
-        struc.serialize_field("kind", &crate::OMKind::OMS)?;
-        struc.skip_field("id")?;
-        if let Some(ns) = self.next_ns {
-            struc.serialize_field("cdbase", ns)?;
+        let symbol = self.form == Form::CompactSymbol;
+        let mut struc = Fields::new(self.s, self.form, num_fields, if symbol { 4 } else { 5 })?;
+        if !symbol {
+            struc.field("kind", &crate::OMKind::OMS)?;
+        }
+        struc.skip("id")?;
+        if let Some(ns) = &self.next_ns {
+            struc.field("cdbase", ns)?;
         } else {
-            struc.skip_field("cdbase")?;
+            struc.skip("cdbase")?;
         }
-        struc.serialize_field("cd", &DWrap(cd_name))?;
-        struc.serialize_field("name", &DWrap(name))?;
+        struc.field("cd", &DWrap(cd_name))?;
+        struc.field("name", &DWrap(name))?;
         struc.end()
     }
 
@@ -205,81 +376,77 @@ impl<'s, S: ::serde::Serializer> OMSerializer<'s> for Serder<'s, S> {
         error: impl AsOMS,
         args: impl ExactSizeIterator<Item: super::OMOrForeign>,
     ) -> Result<Self::Ok, Self::Err> {
+        let mut args = args.peekable();
+        let nonempty = args.peek().is_some();
         let mut num_fields = 2;
-        if args.len() > 0 {
+        if nonempty {
             num_fields += 1;
         }
         if self.next_ns.is_some() {
             num_fields += 1;
         }
 
-        let mut struc = self.s.serialize_struct("OMObject", num_fields)?;
-        struc.serialize_field("kind", &crate::OMKind::OME)?;
-        struc.skip_field("id")?;
+        let mut struc = Fields::new(self.s, self.form, num_fields, 5)?;
+        struc.field("kind", &crate::OMKind::OME)?;
+        struc.skip("id")?;
         if let Some(ns) = self.next_ns.take() {
+            struc.field("cdbase", &ns)?;
             self.current_ns = ns;
-            struc.serialize_field("cdbase", ns)?;
         } else {
-            struc.skip_field("cdbase")?;
+            struc.skip("cdbase")?;
         }
 
-        struc.serialize_field(
+        struc.field(
             "error",
-            &SerdeSerializer(&error.as_oms(), None, self.current_ns),
+            &SerdeSerializer(&error.as_oms(), None, &self.current_ns, self.form.symbol()),
         )?;
-        if args.len() > 0 {
-            struc.serialize_field(
+        if nonempty {
+            struc.field(
                 "arguments",
                 &Iter(std::cell::Cell::new(Some(args.map(
                     |e| match e.om_or_foreign() {
-                        Either::Left(e) => {
-                            ForeignSerializer::O(SerdeSerializer(e, None, self.current_ns))
-                        }
-                        Either::Right((encoding, value)) => {
-                            ForeignSerializer::F { encoding, value }
-                        }
+                        Either::Left(e) => ForeignSerializer::O(SerdeSerializer(
+                            e,
+                            None,
+                            &self.current_ns,
+                            self.form,
+                        )),
+                        Either::Right((encoding, value)) => ForeignSerializer::F {
+                            encoding,
+                            value,
+                            form: self.form,
+                        },
                     },
                 )))),
             )?;
         } else {
-            struc.skip_field("arguments")?;
+            struc.skip("arguments")?;
         }
         struc.end()
     }
 
+    #[inline]
     fn oma(
-        mut self,
+        self,
         head: impl OMSerializable,
         args: impl ExactSizeIterator<Item: OMSerializable>,
     ) -> Result<Self::Ok, Self::Err> {
-        let mut num_fields = 2;
-        if args.len() != 0 {
-            num_fields += 1;
-        }
-        if self.next_ns.is_some() {
-            num_fields += 1;
-        }
-        let mut struc = self.s.serialize_struct("OMObject", num_fields)?;
-        struc.serialize_field("kind", &crate::OMKind::OMA)?;
-        struc.skip_field("id")?;
-        if let Some(ns) = self.next_ns.take() {
-            self.current_ns = ns;
-            struc.serialize_field("cdbase", ns)?;
-        } else {
-            struc.skip_field("cdbase")?;
-        }
-        struc.serialize_field("applicant", &SerdeSerializer(head, None, self.current_ns))?;
-        if args.len() != 0 {
-            struc.serialize_field(
-                "arguments",
-                &Iter(std::cell::Cell::new(Some(
-                    args.map(|e| SerdeSerializer(e, None, self.current_ns)),
-                ))),
-            )?;
-        } else {
-            struc.skip_field("arguments")?;
-        }
-        struc.end()
+        let mut args = args.peekable();
+        let empty = args.peek().is_none();
+        self.oma_iter(head, args, empty)
+    }
+
+    /// Serializes the arguments as a sequence of unknown length; the first argument is
+    /// produced upfront, to decide whether there are any
+    #[inline]
+    fn oma_streamed<I: OMSerializable, F: FnMut() -> Option<I>>(
+        self,
+        head: impl OMSerializable,
+        args: super::StreamedArgs<F>,
+    ) -> Result<Self::Ok, Self::Err> {
+        let mut args = args.peekable();
+        let empty = args.peek().is_none();
+        self.oma_iter(head, args, empty)
     }
 
     fn ombind(
@@ -293,24 +460,31 @@ impl<'s, S: ::serde::Serializer> OMSerializer<'s> for Serder<'s, S> {
         if self.next_ns.is_some() {
             num_fields += 1;
         }
-        let mut struc = self.s.serialize_struct("OMObject", num_fields)?;
-        struc.serialize_field("kind", &crate::OMKind::OMBIND)?;
-        struc.skip_field("id")?;
+        let mut struc = Fields::new(self.s, self.form, num_fields, 6)?;
+        struc.field("kind", &crate::OMKind::OMBIND)?;
+        struc.skip("id")?;
         if let Some(ns) = self.next_ns.take() {
+            struc.field("cdbase", &ns)?;
             self.current_ns = ns;
-            struc.serialize_field("cdbase", ns)?;
         } else {
-            struc.skip_field("cdbase")?;
+            struc.skip("cdbase")?;
         }
-        struc.serialize_field("binder", &SerdeSerializer(head, None, self.current_ns))?;
-        struc.serialize_field(
+        struc.field(
+            "binder",
+            &SerdeSerializer(head, None, &self.current_ns, self.form),
+        )?;
+        struc.field(
             "variables",
             &Iter(std::cell::Cell::new(Some(vars.map(|v| VWrap {
-                ns: self.current_ns,
+                ns: &self.current_ns,
                 var: v,
+                form: self.form,
             })))),
         )?;
-        struc.serialize_field("object", &SerdeSerializer(body, None, self.current_ns))?;
+        struc.field(
+            "object",
+            &SerdeSerializer(body, None, &self.current_ns, self.form),
+        )?;
         struc.end()
     }
 
@@ -319,39 +493,45 @@ impl<'s, S: ::serde::Serializer> OMSerializer<'s> for Serder<'s, S> {
         attrs: impl ExactSizeIterator<Item: super::OMAttr>,
         atp: impl OMSerializable,
     ) -> Result<Self::Ok, Self::Err> {
-        let i = attrs.into_iter();
-        if i.len() == 0 {
+        let mut i = attrs.peekable();
+        if i.peek().is_none() {
             return atp.as_openmath(self);
         }
 
-        let mut struc = self
-            .s
-            .serialize_struct("OMObject", if self.next_ns.is_some() { 4 } else { 3 })?;
-        struc.serialize_field("kind", &crate::OMKind::OMATTR)?;
-        struc.skip_field("id")?;
+        let num_fields = if self.next_ns.is_some() { 4 } else { 3 };
+        let mut struc = Fields::new(self.s, self.form, num_fields, 5)?;
+        struc.field("kind", &crate::OMKind::OMATTR)?;
+        struc.skip("id")?;
         if let Some(ns) = self.next_ns.take() {
+            struc.field("cdbase", &ns)?;
             self.current_ns = ns;
-            struc.serialize_field("cdbase", ns)?;
         } else {
-            struc.skip_field("cdbase")?;
+            struc.skip("cdbase")?;
         }
-        struc.serialize_field(
+        struc.field(
             "attributes",
             &Iter(std::cell::Cell::new(Some(i.map(|v| OMAttrW {
-                ns: self.current_ns,
-                attr: v,
+                ns: &self.current_ns,
+                attr: std::cell::Cell::new(Some(v)),
+                form: self.form,
             })))),
         )?;
 
-        struc.serialize_field("object", &SerdeSerializer(atp, None, self.current_ns))?;
+        struc.field(
+            "object",
+            &SerdeSerializer(atp, None, &self.current_ns, self.form),
+        )?;
         struc.end()
     }
 }
 
-struct Iter<I: ExactSizeIterator>(std::cell::Cell<Option<I>>)
+/// Serializes the items as a sequence, whose length is given if the iterator knows it
+/// exactly; fails if the iterator then produces a different number of items, since some
+/// formats write the length upfront
+struct Iter<I: Iterator>(std::cell::Cell<Option<I>>)
 where
     I::Item: serde::Serialize;
-impl<I: ExactSizeIterator> serde::Serialize for Iter<I>
+impl<I: Iterator> serde::Serialize for Iter<I>
 where
     I::Item: serde::Serialize,
 {
@@ -363,9 +543,27 @@ where
         let Some(args) = self.0.take() else {
             return Err(S::Error::custom("Error iterating over arguments"));
         };
-        let mut seq = serializer.serialize_seq(Some(args.len()))?;
+        let len = match args.size_hint() {
+            (lower, Some(upper)) if lower == upper => Some(lower),
+            _ => None,
+        };
+        let mut seq = serializer.serialize_seq(len)?;
+        let mut count = 0;
         for s in args {
+            if len == Some(count) {
+                return Err(S::Error::custom(format_args!(
+                    "iterator produced more than the {count} items it reported"
+                )));
+            }
             seq.serialize_element(&s)?;
+            count += 1;
+        }
+        if let Some(len) = len
+            && len != count
+        {
+            return Err(S::Error::custom(format_args!(
+                "iterator reported {len} items, but produced {count}"
+            )));
         }
         seq.end()
     }
@@ -381,28 +579,42 @@ impl<D: std::fmt::Display> serde::Serialize for DWrap<D> {
     }
 }
 
+struct Bytes(Vec<u8>);
+impl serde::Serialize for Bytes {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
 struct VWrap<'d, V: super::BindVar> {
     ns: &'d str,
     var: V,
+    form: Form,
 }
 impl<V: super::BindVar> serde::Serialize for VWrap<'_, V> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        let attrs = self.var.attrs();
-        if attrs.len() == 0 {
+        let mut attrs = self.var.attrs().peekable();
+        if attrs.peek().is_none() {
             Serder {
                 s: serializer,
                 next_ns: None,
-                current_ns: self.ns,
+                current_ns: Cow::Borrowed(self.ns),
+                form: self.form,
             }
             .omv(self.var.name())
         } else {
             Serder {
                 s: serializer,
                 next_ns: None,
-                current_ns: self.ns,
+                current_ns: Cow::Borrowed(self.ns),
+                form: self.form,
             }
             .omattr(attrs, super::Omv(self.var.name()))
         }
@@ -411,7 +623,8 @@ impl<V: super::BindVar> serde::Serialize for VWrap<'_, V> {
 
 struct OMAttrW<'de, A: super::OMAttr> {
     ns: &'de str,
-    attr: A,
+    attr: std::cell::Cell<Option<A>>,
+    form: Form,
 }
 
 impl<A: super::OMAttr> serde::Serialize for OMAttrW<'_, A> {
@@ -420,12 +633,24 @@ impl<A: super::OMAttr> serde::Serialize for OMAttrW<'_, A> {
     where
         S: Serializer,
     {
+        use serde::ser::Error;
+        let Some(attr) = self.attr.take() else {
+            return Err(S::Error::custom("Error serializing attribute"));
+        };
         let mut tup = serializer.serialize_tuple(2)?;
-        let symbol = self.attr.symbol();
-        tup.serialize_element(&SerdeSerializer(&symbol.as_oms(), None, self.ns))?;
-        let v = match self.attr.value().om_or_foreign() {
-            Either::Left(e) => ForeignSerializer::O(SerdeSerializer(e, None, self.ns)),
-            Either::Right((encoding, value)) => ForeignSerializer::F { encoding, value },
+        tup.serialize_element(&SerdeSerializer(
+            &attr.symbol().as_oms(),
+            None,
+            self.ns,
+            self.form.symbol(),
+        ))?;
+        let v = match attr.value().om_or_foreign() {
+            Either::Left(e) => ForeignSerializer::O(SerdeSerializer(e, None, self.ns, self.form)),
+            Either::Right((encoding, value)) => ForeignSerializer::F {
+                encoding,
+                value,
+                form: self.form,
+            },
         };
         tup.serialize_element(&v)?;
         tup.end()
@@ -437,7 +662,11 @@ where
     OM: crate::OMSerializable,
 {
     O(SerdeSerializer<'s, OM>),
-    F { encoding: Option<E>, value: D },
+    F {
+        encoding: Option<E>,
+        value: D,
+        form: Form,
+    },
 }
 impl<OM: crate::OMSerializable, D: std::fmt::Display, E: std::fmt::Display> ::serde::Serialize
     for ForeignSerializer<'_, OM, D, E>
@@ -448,19 +677,180 @@ impl<OM: crate::OMSerializable, D: std::fmt::Display, E: std::fmt::Display> ::se
     {
         match self {
             Self::O(o) => o.serialize(serializer),
-            Self::F { encoding, value } => {
-                let mut struc = serializer
-                    .serialize_struct("OMObject", if encoding.is_some() { 3 } else { 2 })?;
-                struc.serialize_field("kind", &crate::OMKind::OMFOREIGN)?;
-                struc.skip_field("id")?;
-                struc.serialize_field("foreign", &DWrap(value))?;
+            Self::F {
+                encoding,
+                value,
+                form,
+            } => {
+                let num_fields = if encoding.is_some() { 3 } else { 2 };
+                let mut struc = Fields::new(serializer, *form, num_fields, 4)?;
+                struc.field("kind", &crate::OMKind::OMFOREIGN)?;
+                struc.skip("id")?;
+                struc.field("foreign", &DWrap(value))?;
                 if let Some(e) = encoding {
-                    struc.serialize_field("encoding", &DWrap(e))?;
+                    struc.field("encoding", &DWrap(e))?;
                 } else {
-                    struc.skip_field("encoding")?;
+                    struc.skip("encoding")?;
                 }
                 struc.end()
             }
         }
     }
 }
+
+/** Serializes and deserializes a field via its <span style="font-variant:small-caps;">OpenMath</span>
+(object) encoding, for use with `#[serde(with = "openmath::ser::as_openmath")]` on fields
+whose type implements [`OMSerializable`] and/or
+[`OMDeserializable`](crate::OMDeserializable); see also [`OMJson`] for a wrapper type.
+
+# Examples
+```
+use openmath::types::Vector;
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+struct Job {
+    id: u64,
+    #[serde(with = "openmath::ser::as_openmath")]
+    expr: Vector<i64>,
+}
+
+let job = Job { id: 7, expr: Vector(vec![1, 2]) };
+let json = serde_json::to_string(&job).expect("serializable");
+assert!(json.starts_with(r#"{"id":7,"expr":{"kind":"OMA","#));
+assert_eq!(serde_json::from_str::<Job>(&json).expect("deserializable"), job);
+```
+*/
+pub mod as_openmath {
+    use crate::{OMDeserializable, OMSerializable, de::OMFromSerde};
+
+    /// Serializes `value` as an <span style="font-variant:small-caps;">OpenMath</span> object
+    ///
+    /// # Errors
+    /// if [`as_openmath`](OMSerializable::as_openmath) or the serializer errors.
+    #[inline]
+    pub fn serialize<T: OMSerializable + ?Sized, S: serde::Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serde::Serialize::serialize(&value.openmath_serde(), serializer)
+    }
+
+    /// Deserializes an <span style="font-variant:small-caps;">OpenMath</span> object into a `T`
+    ///
+    /// # Errors
+    /// if the input is not a valid <span style="font-variant:small-caps;">OpenMath</span>
+    /// object, or it does not represent a `T`.
+    #[inline]
+    pub fn deserialize<'de, T: OMDeserializable<'de> + 'de, D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        <OMFromSerde<T> as serde::Deserialize<'de>>::deserialize(deserializer)
+            .map(OMFromSerde::into_inner)
+    }
+}
+
+/** A nameable wrapper that [`Serialize`](serde::Serialize)s and
+[`Deserialize`](serde::Deserialize)s its content via its
+<span style="font-variant:small-caps;">OpenMath</span> (object) encoding; e.g. for fields of
+types deriving serde's traits. See also [`as_openmath`] for use with `#[serde(with = ...)]`.
+
+# Examples
+```
+use openmath::{ser::OMJson, types::Vector};
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+struct Job {
+    id: u64,
+    exprs: Vec<OMJson<Vector<f64>>>,
+}
+
+let job = Job { id: 7, exprs: vec![OMJson(Vector(vec![1.5]))] };
+let json = serde_json::to_string(&job).expect("serializable");
+assert_eq!(serde_json::from_str::<Job>(&json).expect("deserializable"), job);
+```
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct OMJson<T>(pub T);
+
+impl<T: OMSerializable> serde::Serialize for OMJson<T> {
+    #[inline]
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        as_openmath::serialize(&self.0, serializer)
+    }
+}
+
+impl<'de, T: crate::OMDeserializable<'de> + 'de> serde::Deserialize<'de> for OMJson<T> {
+    #[inline]
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        as_openmath::deserialize(deserializer).map(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Form, OMJson, Serder};
+    use crate::{OMSerializable, types::Vector};
+    use std::borrow::Cow;
+
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Job {
+        id: u64,
+        #[serde(with = "super::as_openmath")]
+        expr: Vector<i64>,
+        wrapped: Option<OMJson<Vector<f64>>>,
+        name: String,
+    }
+
+    #[test]
+    fn typed_errors() {
+        use crate::ser::{Error, OMSerializer};
+        struct Unsupported;
+        impl OMSerializable for Unsupported {
+            fn as_openmath<'s, S: OMSerializer<'s>>(&self, _: S) -> Result<S::Ok, S::Err> {
+                Err(S::Err::unsupported(crate::OMKind::OMBIND))
+            }
+        }
+        let err = serde_json::to_string(&Unsupported.openmath_serde()).expect_err("fails");
+        assert_eq!(
+            err.to_string(),
+            "OMBIND is not supported by this serializer"
+        );
+    }
+
+    #[test]
+    fn nested() {
+        let job = Job {
+            id: 7,
+            expr: Vector(vec![1, -2]),
+            wrapped: Some(OMJson(Vector(vec![0.5]))),
+            name: "job".to_string(),
+        };
+        let json = serde_json::to_value(&job).expect("serializable");
+        assert_eq!(
+            json["expr"],
+            serde_json::to_value(job.expr.openmath_serde()).expect("serializable")
+        );
+        assert_eq!(json["wrapped"]["kind"], "OMA");
+        let text = json.to_string();
+        assert_eq!(serde_json::from_str::<Job>(&text).expect("roundtrips"), job);
+
+        let err = serde_json::from_str::<Job>(
+            r#"{"id":1,"expr":{"kind":"OMSTR","string":"x"},"wrapped":null,"name":""}"#,
+        )
+        .expect_err("not a vector");
+        assert!(err.to_string().contains("Not an OMI"), "{err}");
+    }
+
+    #[test]
+    fn conformance() {
+        for form in [Form::Object, Form::Compact] {
+            let outputs = crate::ser::testing::check_serializer(|| Serder {
+                s: serde_json::value::Serializer,
+                next_ns: None,
+                current_ns: Cow::Borrowed(crate::CD_BASE),
+                form,
+            });
+            assert_eq!(outputs.len(), 7);
+        }
+    }
+}
@@ -0,0 +1,251 @@
+//! Splitting large objects into several documents that refer to each other; see [`Sharder`]
+
+use std::collections::HashMap;
+
+use crate::{OMMaybeForeign, OpenMath, Refs, WithRefs};
+
+use super::{OMObject, XmlOptions};
+
+/** Splits an [`OpenMath`] object into several XML documents, e.g. to export objects too
+large to handle as a single file: every subterm selected by the predicate is written to a
+document of its own, as an OMOBJ with an `id`, and replaced in the enclosing document by an
+[OMR](crate::OMKind::OMR) referring to it by a relative URI (like `shard3.om#node3`).
+
+Subterms are considered innermost first; the number of nodes passed to the predicate counts
+subterms that have already been split off as a single node, so e.g.
+[`by_size`](Self::by_size) produces documents of bounded size (as long as no single node
+has too many children). The root itself, bound variables and their attributes are never
+split off.
+
+Reading and writing files is left to the caller; the documents are returned as
+`(name, xml)` pairs, in the order they were produced, so every document only refers to
+documents preceding it. [`MultiDocResolver`](crate::de::MultiDocResolver) reads them back.
+
+# Examples
+```
+use openmath::{OpenMath, ser::Sharder};
+
+let om = OpenMath::from_sexpr(
+    "(oma (oms arith1 plus) (oma (oms arith1 times) (omi 2) (omi 3)) (omi 4))",
+)
+.expect("is valid");
+let shards = Sharder::new(|o| matches!(o, OpenMath::OMA { .. })).shard(&om);
+assert_eq!(shards.documents.len(), 1);
+let (name, xml) = &shards.documents[0];
+assert_eq!(name, "shard0.om");
+assert!(xml.starts_with(r#"<OMOBJ version="2.0" id="node0""#));
+assert!(shards.root.contains(r#"<OMR href="shard0.om#node0"/>"#));
+```
+*/
+pub struct Sharder<'s> {
+    #[allow(clippy::type_complexity)]
+    select: Box<dyn Fn(&OpenMath<'_>, usize) -> bool + 's>,
+    prefix: &'s str,
+    options: XmlOptions<'s>,
+}
+
+/// The documents produced by a [`Sharder`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shards {
+    /// The XML of the root object
+    pub root: String,
+    /// The names and XML of the documents split off, in the order they were produced
+    pub documents: Vec<(String, String)>,
+}
+
+impl<'s> Sharder<'s> {
+    /// Splits off every subterm for which `select` returns `true`
+    #[must_use]
+    pub fn new(select: impl Fn(&OpenMath<'_>) -> bool + 's) -> Self {
+        Self::with_size(move |o, _| select(o))
+    }
+
+    /// Splits off every subterm with at least `min_nodes` nodes (counting every object,
+    /// attribute key and bound variable as one node); see [`Sharder`].
+    #[must_use]
+    pub fn by_size(min_nodes: usize) -> Self {
+        Self::with_size(move |_, nodes| nodes >= min_nodes)
+    }
+
+    /// Splits off every subterm for which `select(subterm, nodes)` returns `true`, where
+    /// `nodes` is counted like in [`by_size`](Self::by_size).
+    #[must_use]
+    pub fn with_size(select: impl Fn(&OpenMath<'_>, usize) -> bool + 's) -> Self {
+        Self {
+            select: Box::new(select),
+            prefix: "shard",
+            options: XmlOptions::default(),
+        }
+    }
+
+    /// Names the documents `{prefix}{n}.om` (instead of `shard{n}.om`)
+    #[must_use]
+    pub const fn with_prefix(mut self, prefix: &'s str) -> Self {
+        self.prefix = prefix;
+        self
+    }
+
+    /// The [`XmlOptions`] to write all documents with; the [`id`](XmlOptions::id) is used
+    /// for the root only.
+    #[must_use]
+    pub const fn with_options(mut self, options: XmlOptions<'s>) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Splits `om` into documents
+    #[must_use]
+    pub fn shard(&self, om: &OpenMath<'_>) -> Shards {
+        let mut state = State::default();
+        self.visit(om, &mut state);
+        let root = OMObject(&WithRefs(om, &state.refs))
+            .xml_with(self.options)
+            .to_string();
+        Shards {
+            root,
+            documents: state.documents,
+        }
+    }
+
+    /// Visits the children of `om`, splitting off those selected; returns the number of
+    /// nodes remaining in `om`.
+    fn visit(&self, om: &OpenMath<'_>, state: &mut State) -> usize {
+        let mut nodes = 1;
+        for a in om.attributes() {
+            nodes += 1 + self.foreign(&a.value, state);
+        }
+        match om {
+            OpenMath::OMA {
+                applicant,
+                arguments,
+                ..
+            } => {
+                nodes += self.child(applicant, state);
+                for a in arguments {
+                    nodes += self.child(a, state);
+                }
+            }
+            OpenMath::OME { arguments, .. } => {
+                for a in arguments {
+                    nodes += self.foreign(a, state);
+                }
+            }
+            OpenMath::OMBIND {
+                binder,
+                variables,
+                object,
+                ..
+            } => {
+                nodes += self.child(binder, state) + variables.len();
+                nodes += self.child(object, state);
+            }
+            _ => (),
+        }
+        nodes
+    }
+
+    fn foreign(&self, o: &OMMaybeForeign<'_, OpenMath<'_>>, state: &mut State) -> usize {
+        match o {
+            OMMaybeForeign::OM(o) => self.child(o, state),
+            OMMaybeForeign::Foreign { .. } => 1,
+        }
+    }
+
+    fn child(&self, om: &OpenMath<'_>, state: &mut State) -> usize {
+        let nodes = self.visit(om, state);
+        if !(self.select)(om, nodes) {
+            return nodes;
+        }
+        let n = state.documents.len();
+        let (name, id) = (format!("{}{n}.om", self.prefix), format!("node{n}"));
+        let xml = OMObject(&WithRefs(om, &state.refs))
+            .xml_with(XmlOptions {
+                id: Some(&id),
+                ..self.options
+            })
+            .to_string();
+        state.refs.0.insert(key(om), format!("{name}#{id}"));
+        state.documents.push((name, xml));
+        1
+    }
+}
+
+impl std::fmt::Debug for Sharder<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Sharder")
+            .field("prefix", &self.prefix)
+            .field("options", &self.options)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Default)]
+struct State {
+    refs: Hrefs,
+    documents: Vec<(String, String)>,
+}
+
+/// The `href`s of the subterms split off, by address
+#[derive(Default)]
+struct Hrefs(HashMap<usize, String>);
+impl Refs for Hrefs {
+    #[inline]
+    fn href(&self, om: &OpenMath<'_>) -> Option<&str> {
+        if self.0.is_empty() {
+            return None;
+        }
+        self.0.get(&key(om)).map(String::as_str)
+    }
+}
+
+#[inline]
+fn key(om: &OpenMath<'_>) -> usize {
+    std::ptr::from_ref(om).addr()
+}
+
+#[cfg(all(test, feature = "xml-read", feature = "xml-write"))]
+mod tests {
+    use super::Sharder;
+    use crate::{OMSerializable, OpenMath, de::MultiDocResolver};
+
+    #[test]
+    fn shard_roundtrip() {
+        let om = OpenMath::from_sexpr(
+            r#"(ombind (oms fns1 lambda) (bvar x)
+                 (omattr (((oms meta note) (oma (oms list1 list) (omstr "a") (omstr "b") (omstr "c"))))
+                   (oma (oms arith1 plus)
+                     (oma (oms arith1 times) (omv x) (omi 2) (omi 3))
+                     (ome (oms moreerrors unexpected)
+                       (oma (oms arith1 minus) (omi 1) (omi 2) (omi 3)) (omforeign "…"))
+                     (cdbase "http://example.org" (oma (oms my f) (omi 1) (omi 2) (omi 3))))))"#,
+        )
+        .expect("is valid");
+        let shards = Sharder::by_size(4).with_prefix("part").shard(&om);
+        let names = shards
+            .documents
+            .iter()
+            .map(|(n, _)| n.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            ["part0.om", "part1.om", "part2.om", "part3.om", "part4.om"]
+        );
+        // the body refers to the four arguments split off before
+        for (i, (_, xml)) in shards.documents.iter().enumerate() {
+            for (j, (name, _)) in shards.documents.iter().enumerate() {
+                let href = format!("{name}#node{j}");
+                assert_eq!(xml.contains(&href), i == 4 && j < 4, "{i}: {xml}");
+            }
+        }
+        assert!(!shards.root.contains("<OMA"), "{}", shards.root);
+        assert!(shards.root.contains(r#"<OMR href="part4.om#node4"/>"#));
+
+        let resolver = MultiDocResolver::from_iter(shards.documents);
+        let back = resolver
+            .read(&shards.root)
+            .expect("all references are resolved");
+        assert_eq!(back.xml(false).to_string(), om.xml(false).to_string());
+        assert!(resolver.resolve("part1.om#node0").is_none());
+        assert!(resolver.resolve("part9.om").is_none());
+    }
+}
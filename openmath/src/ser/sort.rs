@@ -0,0 +1,305 @@
+use std::{borrow::Cow, fmt::Display};
+
+use either::Either;
+
+use super::{AsOMS, BindVar, OMAttr, OMOrForeign, OMSerializable, OMSerializer};
+
+/// [`OMSerializer`] middleware that sorts the pairs of [OMATTR](crate::OMKind::OMATTR)s.
+///
+/// The pairs of every OMATTR (and the attributes of bound variables) are handed to the
+/// wrapped serializer sorted by the [resolved URI](crate::SymbolRef::resolved_uri) of their
+/// key; pairs with the same key keep their relative order. The serialized object itself is
+/// not changed.
+///
+/// See [`Options::with_sort_attributes`](super::Options::with_sort_attributes) for the
+/// built-in serializers, and [`SortedAttrs`] to wrap objects rather than serializers.
+pub struct SortAttrs<S>(S);
+impl<S> SortAttrs<S> {
+    /// Wraps `inner`
+    #[inline]
+    pub const fn new(inner: S) -> Self {
+        Self(inner)
+    }
+}
+
+/// An [`OMSerializable`] whose attribute pairs are [sorted](SortAttrs) when serialized;
+/// the counterpart to [`SortAttrs`] for the built-in serializers.
+///
+/// # Examples
+/// ```
+/// use openmath::{OMSerializable, OpenMath, ser::SortedAttrs};
+///
+/// let om = OpenMath::from_sexpr(
+///     "(omattr (((oms meta type) (omstr \"t\")) ((oms meta note) (omstr \"n\"))) (omv x))",
+/// )
+/// .expect("is valid");
+/// # #[cfg(feature = "xml-write")]
+/// assert_eq!(
+///     SortedAttrs(&om).xml(false).to_string(),
+///     r#"<OMATTR><OMATP><OMS cd="meta" name="note"/><OMSTR>n</OMSTR><OMS cd="meta" name="type"/><OMSTR>t</OMSTR></OMATP><OMV name="x"/></OMATTR>"#
+/// );
+/// ```
+pub struct SortedAttrs<T>(pub T);
+impl<T: OMSerializable> OMSerializable for SortedAttrs<T> {
+    #[inline]
+    fn cdbase(&self) -> Option<&str> {
+        self.0.cdbase()
+    }
+    #[inline]
+    fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        self.0.as_openmath(SortAttrs(serializer))
+    }
+}
+
+/// [`SortedAttrs`] if `sort` holds, otherwise just `o`
+#[cfg(feature = "serde")]
+pub struct MaybeSorted<T> {
+    pub o: T,
+    pub sort: bool,
+}
+#[cfg(feature = "serde")]
+impl<T: OMSerializable> OMSerializable for MaybeSorted<T> {
+    #[inline]
+    fn cdbase(&self) -> Option<&str> {
+        self.o.cdbase()
+    }
+    #[inline]
+    fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        if self.sort {
+            self.o.as_openmath(SortAttrs(serializer))
+        } else {
+            self.o.as_openmath(serializer)
+        }
+    }
+}
+
+/// Sorts `attrs` by their resolved key URI (relative to `current`), stably
+fn sorted<A: OMAttr>(attrs: impl Iterator<Item = A>, current: &str) -> Vec<A> {
+    let mut attrs = attrs
+        .map(|a| {
+            let symbol = a.symbol();
+            let cdbase = symbol.cdbase_uri(current);
+            let cdbase = crate::CdBase::new_unchecked(cdbase.as_deref().unwrap_or(current));
+            let key = format!(
+                "{}?{}#{}",
+                cdbase.normalize().as_str(),
+                symbol.cd(),
+                symbol.name()
+            );
+            drop(symbol);
+            (key, a)
+        })
+        .collect::<Vec<_>>();
+    attrs.sort_by(|(a, _), (b, _)| a.cmp(b));
+    attrs.into_iter().map(|(_, a)| a).collect()
+}
+
+impl<'s, S: OMSerializer<'s>> OMSerializer<'s> for SortAttrs<S> {
+    type Ok = S::Ok;
+    type Err = S::Err;
+    type SubSerializer<'ns>
+        = SortAttrs<S::SubSerializer<'ns>>
+    where
+        's: 'ns;
+    #[inline]
+    fn current_cdbase(&self) -> &str {
+        self.0.current_cdbase()
+    }
+    #[inline]
+    fn with_cdbase<'ns>(
+        self,
+        cdbase: impl Into<Cow<'ns, str>>,
+    ) -> Result<Self::SubSerializer<'ns>, Self::Err>
+    where
+        's: 'ns,
+    {
+        self.0.with_cdbase(cdbase).map(SortAttrs)
+    }
+    #[inline]
+    fn omi(self, value: &crate::Int) -> Result<Self::Ok, Self::Err> {
+        self.0.omi(value)
+    }
+    #[inline]
+    fn omf(self, value: f64) -> Result<Self::Ok, Self::Err> {
+        self.0.omf(value)
+    }
+    #[inline]
+    fn omstr(self, string: impl Display) -> Result<Self::Ok, Self::Err> {
+        self.0.omstr(string)
+    }
+    #[inline]
+    fn omb(self, bytes: impl ExactSizeIterator<Item = u8>) -> Result<Self::Ok, Self::Err> {
+        self.0.omb(bytes)
+    }
+    #[inline]
+    fn omv(self, name: impl Display) -> Result<Self::Ok, Self::Err> {
+        self.0.omv(name)
+    }
+    #[inline]
+    fn omr(self, href: impl Display) -> Result<Self::Ok, Self::Err> {
+        self.0.omr(href)
+    }
+    #[inline]
+    fn raw_xml(self, xml: &str) -> Result<Self::Ok, Self::Err> {
+        self.0.raw_xml(xml)
+    }
+    #[inline]
+    fn memoized(self, key: usize, o: impl OMSerializable) -> Result<Self::Ok, Self::Err> {
+        self.0.memoized(key, SortedAttrs(o))
+    }
+    #[inline]
+    fn oms(self, cd_name: impl Display, name: impl Display) -> Result<Self::Ok, Self::Err> {
+        self.0.oms(cd_name, name)
+    }
+    fn oma(
+        self,
+        head: impl OMSerializable,
+        args: impl ExactSizeIterator<Item: OMSerializable>,
+    ) -> Result<Self::Ok, Self::Err> {
+        self.0.oma(SortedAttrs(head), args.map(SortedAttrs))
+    }
+    fn ome(
+        self,
+        error: impl AsOMS,
+        args: impl ExactSizeIterator<Item: OMOrForeign>,
+    ) -> Result<Self::Ok, Self::Err> {
+        self.0.ome(error, args.map(SortedForeign))
+    }
+    fn omattr(
+        self,
+        attrs: impl ExactSizeIterator<Item: OMAttr>,
+        atp: impl OMSerializable,
+    ) -> Result<Self::Ok, Self::Err> {
+        let attrs = sorted(attrs, self.0.current_cdbase());
+        self.0
+            .omattr(attrs.into_iter().map(SortedAttr), SortedAttrs(atp))
+    }
+    fn ombind(
+        self,
+        head: impl OMSerializable,
+        vars: impl ExactSizeIterator<Item: BindVar>,
+        body: impl OMSerializable,
+    ) -> Result<Self::Ok, Self::Err> {
+        let current = self.0.current_cdbase().to_string();
+        self.0.ombind(
+            SortedAttrs(head),
+            vars.map(|v| SortedVar(v, &current)),
+            SortedAttrs(body),
+        )
+    }
+}
+
+struct SortedForeign<T>(T);
+impl<T: OMOrForeign> OMOrForeign for SortedForeign<T> {
+    fn om_or_foreign(self) -> Either<impl OMSerializable, (Option<impl Display>, impl Display)> {
+        match self.0.om_or_foreign() {
+            Either::Left(o) => Either::Left(SortedAttrs(o)),
+            Either::Right(f) => Either::Right(f),
+        }
+    }
+}
+
+struct SortedAttr<A>(A);
+impl<A: OMAttr> OMAttr for SortedAttr<A> {
+    #[inline]
+    fn symbol(&self) -> impl AsOMS {
+        self.0.symbol()
+    }
+    #[inline]
+    fn value(self) -> impl OMOrForeign {
+        SortedForeign(self.0.value())
+    }
+}
+
+struct SortedVar<'c, V>(V, &'c str);
+impl<V: BindVar> BindVar for SortedVar<'_, V> {
+    #[inline]
+    fn name(&self) -> impl Display {
+        self.0.name()
+    }
+    #[inline]
+    fn attrs(&self) -> impl ExactSizeIterator<Item: OMAttr> {
+        sorted(self.0.attrs(), self.1).into_iter().map(SortedAttr)
+    }
+}
+
+#[cfg(all(test, any(feature = "xml-write", feature = "serde_json")))]
+mod tests {
+    use super::*;
+    use crate::{OpenMath, ser::Options};
+
+    /// The same term, with its attributes inserted in the given order
+    fn term(order: [usize; 4]) -> OpenMath<'static> {
+        const PAIRS: [&str; 4] = [
+            "((oms meta type) (omstr \"t\"))",
+            "((oms meta note) (omstr \"first\"))",
+            "((cdbase \"http://example.org\" (oms meta z)) (omi 1))",
+            "((oms meta note) (omstr \"second\"))",
+        ];
+        let pairs = order.map(|i| PAIRS[i]).join(" ");
+        OpenMath::from_sexpr(&format!(
+            "(omattr ({pairs}) (ombind (oms fns1 lambda) (bvar (omattr ({pairs}) x)) \
+             (oma (oms arith1 plus) (omattr ({pairs}) (omv x)))))"
+        ))
+        .expect("is valid")
+    }
+
+    // orders in which "first" precedes "second"; the third one is sorted already
+    const ORDERS: [[usize; 4]; 5] = [
+        [0, 1, 2, 3],
+        [2, 0, 1, 3],
+        [2, 1, 3, 0],
+        [1, 0, 3, 2],
+        [1, 2, 0, 3],
+    ];
+
+    #[cfg(feature = "xml-write")]
+    #[test]
+    fn shuffled_attributes_serialize_identically() {
+        let sorted = Options::new().with_sort_attributes(true);
+        let terms = ORDERS.map(term);
+        let xml = terms
+            .each_ref()
+            .map(|t| t.try_xml_with(sorted).expect("works"));
+        assert!(xml.iter().all(|x| *x == xml[0]), "{xml:#?}");
+        assert!(
+            xml[0].starts_with(
+                r#"<OMATTR><OMATP><OMS cdbase="http://example.org" cd="meta" name="z"/><OMI>1</OMI><OMS cd="meta" name="note"/><OMSTR>first</OMSTR><OMS cd="meta" name="note"/><OMSTR>second</OMSTR><OMS cd="meta" name="type"/>"#
+            ),
+            "{}",
+            xml[0]
+        );
+        let objects = terms.each_ref().map(|t| {
+            t.omobject()
+                .xml_with(sorted.with_insert_namespace(false))
+                .to_string()
+        });
+        assert!(objects.iter().all(|x| *x == objects[0]));
+        assert!(objects[0].contains(&xml[0]));
+
+        // the terms themselves are unchanged
+        let unsorted = terms.each_ref().map(|t| t.xml(false).to_string());
+        assert_ne!(unsorted[0], unsorted[1]);
+        assert_eq!(unsorted[2], xml[0]);
+        assert_eq!(terms[1], term(ORDERS[1]));
+    }
+
+    #[cfg(feature = "serde_json")]
+    #[test]
+    fn shuffled_attributes_serialize_identically_json() {
+        let terms = ORDERS.map(term);
+        let json = terms.each_ref().map(|t| {
+            serde_json::to_string(&t.openmath_serde_with(Options::new().with_sort_attributes(true)))
+                .expect("works")
+        });
+        assert!(json.iter().all(|j| *j == json[0]), "{json:#?}");
+        assert_eq!(
+            json[0],
+            serde_json::to_string(&terms[2].openmath_serde()).expect("works")
+        );
+        assert_ne!(
+            serde_json::to_string(&terms[1].openmath_serde()).expect("works"),
+            json[0]
+        );
+    }
+}
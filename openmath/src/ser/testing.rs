@@ -0,0 +1,390 @@
+/*! Conformance tests for [`OMSerializer`] implementations; see [`check_serializer`] and
+the [backend guide](super::backend).
+*/
+
+use std::{borrow::Cow, cell::Cell};
+
+use super::{AsOMS, BindVar, OMAttr, OMOrForeign, OMSerializable, OMSerializer, Uri};
+use crate::either::Either;
+
+/// Runs a set of fixtures against serializers produced by `make` (a fresh one per fixture)
+/// and panics if the [contract](super::backend#the-contract) is violated; i.e. if
+/// - a fixture fails to serialize,
+/// - a node observes a wrong [`current_cdbase`](OMSerializer::current_cdbase), or
+/// - a child node is serialized more or less than exactly once.
+///
+/// The fixtures cover all node kinds (including edge cases such as huge integers, empty
+/// strings and byte arrays), cdbase inheritance through every kind of child position,
+/// nested [OMATTR](crate::OMKind::OMATTR)s, [OMBIND](crate::OMKind::OMBIND)s with an empty
+/// or attributed variable list, and [OMFOREIGN](crate::OMKind::OMFOREIGN) values in
+/// [OME](crate::OMKind::OME)s and attributions.
+///
+/// Returns the outputs of all fixtures together with their names, e.g. for comparing
+/// them against known-good snapshots.
+///
+/// # Panics
+/// if the serializer violates the contract.
+///
+/// # Examples
+/// See the [backend guide](super::backend#a-reference-implementation).
+#[must_use = "the outputs can be compared against snapshots"]
+pub fn check_serializer<'s, S: OMSerializer<'s>>(
+    make: impl Fn() -> S,
+) -> Vec<(&'static str, S::Ok)> {
+    let initial = make().current_cdbase().to_string();
+    let mut outputs = Vec::new();
+    let mut run = |name: &'static str, fixture: &dyn Fn(S) -> Result<S::Ok, S::Err>| {
+        let Ok(ok) = fixture(make()) else {
+            panic!("conformance fixture `{name}` failed to serialize");
+        };
+        outputs.push((name, ok));
+    };
+
+    run("leaves", &|s| Leaves.as_openmath(s));
+
+    let probes = cdbase_probes(&initial);
+    run("cdbase inheritance", &|s| probes.as_openmath(s));
+    probes.assert_called_once("cdbase inheritance");
+
+    let probes = cdbase_probes(A);
+    run("cdbase inheritance (rebased root)", &|s| {
+        s.with_cdbase(A)?
+            .oma(uri("list1", "list").as_oms(), std::iter::once(&probes))
+    });
+    probes.assert_called_once("cdbase inheritance (rebased root)");
+
+    run("nested OMATTR", &|s| NestedAttr.as_openmath(s));
+    run("empty OMBVAR", &|s| {
+        s.ombind(
+            uri("quant1", "forall").as_oms(),
+            std::iter::empty::<&&str>(),
+            uri("logic1", "true").as_oms(),
+        )
+    });
+    run("attributed OMBVAR", &|s| {
+        s.ombind(
+            uri("fns1", "lambda").as_oms(),
+            [TypedVar("x"), TypedVar("y")].into_iter(),
+            super::Omv("x"),
+        )
+    });
+    run("foreign values", &|s| ForeignValues.as_openmath(s));
+
+    outputs
+}
+
+const fn uri(cd: &'static str, name: &'static str) -> Uri<'static> {
+    Uri {
+        cdbase: None,
+        cd,
+        name,
+    }
+}
+
+const A: &str = "http://example.org/conformance/a";
+const B: &str = "http://example.org/conformance/b";
+static KEY: Uri<'static> = uri("conformance", "key");
+
+/// A node that checks the cdbase it observes and then serializes itself as an
+/// [OMS](crate::OMKind::OMS) (if it has no children) or as the given `shape`.
+struct Probe<'p> {
+    /// the cdbase this node should observe
+    expect: Cow<'p, str>,
+    /// calls [`with_cdbase`](OMSerializer::with_cdbase) before serializing itself
+    rebase: Option<&'static str>,
+    shape: Shape,
+    children: Vec<Self>,
+    calls: Cell<usize>,
+    path: &'static str,
+}
+
+#[derive(Clone, Copy)]
+enum Shape {
+    Oma,
+    Ome,
+    Omattr,
+    Ombind,
+}
+
+impl<'p> Probe<'p> {
+    fn leaf(expect: impl Into<Cow<'p, str>>, path: &'static str) -> Self {
+        Self {
+            expect: expect.into(),
+            rebase: None,
+            shape: Shape::Oma,
+            children: Vec::new(),
+            calls: Cell::new(0),
+            path,
+        }
+    }
+    fn node(
+        expect: impl Into<Cow<'p, str>>,
+        shape: Shape,
+        children: Vec<Self>,
+        path: &'static str,
+    ) -> Self {
+        Self {
+            shape,
+            children,
+            ..Self::leaf(expect, path)
+        }
+    }
+    const fn rebased(mut self, cdbase: &'static str) -> Self {
+        self.rebase = Some(cdbase);
+        self
+    }
+
+    fn assert_called_once(&self, fixture: &str) {
+        assert_eq!(
+            self.calls.get(),
+            1,
+            "conformance fixture `{fixture}`: node `{}` was serialized {} times",
+            self.path,
+            self.calls.get()
+        );
+        for c in &self.children {
+            c.assert_called_once(fixture);
+        }
+    }
+
+    fn check(&self, found: &str, what: &str) {
+        assert!(
+            crate::cdbase::eq_normalized(&self.expect, found),
+            "conformance: node `{}` observed cdbase {found:?} {what}, expected {:?}",
+            self.path,
+            self.expect
+        );
+    }
+
+    fn emit<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        let Some((first, rest)) = self.children.split_first() else {
+            return serializer.oms("conformance", self.path);
+        };
+        match self.shape {
+            Shape::Oma => serializer.oma(first, rest.iter()),
+            Shape::Ome => serializer.ome(uri("moreerrors", "unexpected"), self.children.iter()),
+            Shape::Omattr => serializer.omattr(rest.iter().map(|v| (&KEY, v)), first),
+            Shape::Ombind => {
+                serializer.ombind(first, std::iter::once(&"x"), rest.first().unwrap_or(first))
+            }
+        }
+    }
+}
+
+impl OMSerializable for Probe<'_> {
+    fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        self.calls.set(self.calls.get() + 1);
+        self.check(serializer.current_cdbase(), "");
+        if let Some(cdbase) = self.rebase {
+            let serializer = serializer.with_cdbase(cdbase)?;
+            assert!(
+                crate::cdbase::eq_normalized(serializer.current_cdbase(), cdbase),
+                "conformance: `current_cdbase` does not reflect `with_cdbase({cdbase:?})` at node `{}`",
+                self.path
+            );
+            self.emit(serializer)
+        } else {
+            self.emit(serializer)
+        }
+    }
+}
+
+/// A tree of probes in which every kind of child position (heads, arguments, attribute
+/// values, binders and bodies) occurs below and beside rebased nodes; `inherited` is the
+/// cdbase in effect for the root
+fn cdbase_probes(inherited: &str) -> Probe<'_> {
+    use Shape::{Oma, Omattr, Ombind, Ome};
+    Probe::node(
+        inherited,
+        Oma,
+        vec![
+            Probe::leaf(inherited, "head"),
+            Probe::leaf(inherited, "arg"),
+            // rebased nodes and their children
+            Probe::node(
+                inherited,
+                Oma,
+                vec![
+                    Probe::leaf(B, "rebased.head"),
+                    Probe::leaf(B, "rebased.arg"),
+                ],
+                "rebased",
+            )
+            .rebased(B),
+            // a rebased head
+            Probe::node(
+                inherited,
+                Oma,
+                vec![
+                    Probe::leaf(inherited, "rebased_head.head").rebased(A),
+                    Probe::leaf(inherited, "rebased_head.arg"),
+                ],
+                "rebased_head",
+            ),
+            // nested rebasing, and rebasing to the current cdbase
+            Probe::node(
+                inherited,
+                Ombind,
+                vec![
+                    Probe::leaf(A, "nested.binder"),
+                    Probe::node(
+                        A,
+                        Omattr,
+                        vec![
+                            Probe::leaf(B, "nested.body.object"),
+                            Probe::leaf(B, "nested.body.value"),
+                            Probe::leaf(B, "nested.body.value2").rebased(B),
+                        ],
+                        "nested.body",
+                    )
+                    .rebased(B),
+                ],
+                "nested",
+            )
+            .rebased(A),
+            Probe::node(
+                inherited,
+                Ome,
+                vec![
+                    Probe::leaf(A, "error.arg"),
+                    Probe::leaf(A, "error.arg2").rebased(B),
+                ],
+                "error",
+            )
+            .rebased(A),
+            // siblings are unaffected by rebased nodes
+            Probe::leaf(inherited, "last"),
+        ],
+        "root",
+    )
+}
+
+/// All leaf kinds, including edge cases
+struct Leaves;
+impl OMSerializable for Leaves {
+    fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        enum Leaf {
+            I(crate::Int<'static>),
+            F(f64),
+            Str(&'static str),
+            B(&'static [u8]),
+            V(&'static str),
+            S(Uri<'static>),
+        }
+        impl OMSerializable for Leaf {
+            fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+                match self {
+                    Self::I(i) => serializer.omi(i),
+                    Self::F(f) => serializer.omf(*f),
+                    Self::Str(s) => serializer.omstr(s),
+                    Self::B(b) => serializer.omb(b.iter().copied()),
+                    Self::V(v) => serializer.omv(v),
+                    Self::S(u) => u.as_oms().as_openmath(serializer),
+                }
+            }
+        }
+        let big = crate::Int::new("-123456789012345678901234567890123456789012345678901234567890")
+            .expect("is a valid integer")
+            .into_owned();
+        let leaves = [
+            Leaf::I(0.into()),
+            Leaf::I(i128::MIN.into()),
+            Leaf::I(big),
+            Leaf::F(-0.0),
+            Leaf::F(1e300),
+            Leaf::F(f64::NAN),
+            Leaf::F(f64::NEG_INFINITY),
+            Leaf::Str(""),
+            Leaf::Str("<&>\"'\n\t∀ x ∈ ℕ"),
+            Leaf::B(&[]),
+            Leaf::B(&[0, 1, 0xff]),
+            Leaf::V("x"),
+            Leaf::V("ξ_1"),
+            Leaf::S(uri("arith1", "plus")),
+            Leaf::S(Uri {
+                cdbase: Some(crate::CdBase::new_unchecked(B)),
+                cd: "conformance",
+                name: "symbol",
+            }),
+        ];
+        serializer.oma(uri("list1", "list").as_oms(), leaves.iter())
+    }
+}
+
+/// Attributions with attributed values and objects, and symbols with cdbases as keys
+struct NestedAttr;
+impl OMSerializable for NestedAttr {
+    fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        struct Attributed<O>(&'static [Uri<'static>], O);
+        impl<O: OMSerializable> OMSerializable for Attributed<O> {
+            fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+                serializer.omattr(self.0.iter().map(|k| (k, &1)), &self.1)
+            }
+        }
+        static KEYS: &[Uri<'static>] = &[
+            uri("conformance", "key"),
+            Uri {
+                cdbase: Some(crate::CdBase::new_unchecked(B)),
+                cd: "conformance",
+                name: "key",
+            },
+        ];
+        // an attributed attribute value, and an attribution of an attribution
+        let inner = Attributed(KEYS, Attributed(&KEYS[..1], super::Omv("x")));
+        serializer.omattr(
+            [(&KEYS[0], &inner), (&KEYS[1], &inner)].into_iter(),
+            &Attributed(&KEYS[1..], &inner),
+        )
+    }
+}
+
+/// A bound variable with a type attribution
+struct TypedVar(&'static str);
+impl BindVar for TypedVar {
+    fn name(&self) -> impl std::fmt::Display {
+        self.0
+    }
+    fn attrs(&self) -> impl ExactSizeIterator<Item: OMAttr> {
+        static TYPE: Uri<'static> = uri("sts", "type");
+        [(&TYPE, &"nat")].into_iter()
+    }
+}
+
+/// [OMFOREIGN](crate::OMKind::OMFOREIGN) values with and without encoding
+struct ForeignValues;
+impl OMSerializable for ForeignValues {
+    fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        struct Foreign(Option<&'static str>, &'static str);
+        impl OMOrForeign for &Foreign {
+            fn om_or_foreign(
+                self,
+            ) -> Either<impl OMSerializable, (Option<impl std::fmt::Display>, impl std::fmt::Display)>
+            {
+                Either::Right::<crate::Int<'static>, _>((self.0, self.1))
+            }
+        }
+        struct Error;
+        impl OMSerializable for Error {
+            fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+                serializer.ome(
+                    uri("moreerrors", "unexpected"),
+                    [
+                        &Foreign(Some("text/plain"), "1 < 2 & 3"),
+                        &Foreign(None, "<mi>x</mi>"),
+                    ]
+                    .into_iter(),
+                )
+            }
+        }
+        const A: Uri<'static> = uri("conformance", "a");
+        const B: Uri<'static> = uri("conformance", "b");
+        serializer.omattr(
+            [
+                (&A, &Foreign(Some("application/json"), "{\"a\":[]}")),
+                (&B, &Foreign(None, "")),
+            ]
+            .into_iter(),
+            Error,
+        )
+    }
+}
@@ -1,74 +1,256 @@
-use std::fmt::Write;
+use std::{borrow::Cow, cell::RefCell, collections::HashMap, fmt::Write};
 
 use either::Either;
 
 use crate::{
-    OMSerializable,
-    ser::{AsOMS, BindVar, OMAttr},
+    FormatMap, OMSerializable,
+    format_map::Cursor,
+    ser::{
+        AsOMS, BindVar, OMAttr, OMOrForeign, OMSerializer,
+        float::{FloatFormat, FloatStyle},
+    },
 };
 
+/// Errors when writing <span style="font-variant:small-caps;">OpenMath</span> XML; see e.g.
+/// [`try_xml`](super::OMSerializable::try_xml) and
+/// [`write_xml`](super::OMSerializable::write_xml).
 #[derive(Debug, thiserror::Error)]
 pub enum XmlWriteError {
+    /// see [`Error::custom`](super::Error::custom)
     #[error("error converting OpenMath: {0}")]
     Custom(String),
+    /// see [`Error::unsupported`](super::Error::unsupported)
+    #[error("{0} is not supported in XML")]
+    Unsupported(crate::OMKind),
+    /// see [`Error::invalid_value`](super::Error::invalid_value)
+    #[error("error converting OpenMath: invalid value; expected {0}")]
+    InvalidValue(&'static str),
+    /// the underlying [`io::Write`](std::io::Write) failed
+    #[error("error writing XML: {0}")]
+    Io(#[from] std::io::Error),
+    /// the underlying [`fmt::Write`](std::fmt::Write) failed
     #[error("fmt error")]
     Fmt(#[from] std::fmt::Error),
+    /// the content of an [OMFOREIGN](crate::OMKind::OMFOREIGN) is not well-formed (only with
+    /// [`XmlOptions::strict`])
+    #[error("invalid XML in OMFOREIGN: {0}")]
+    InvalidForeignXml(#[source] super::RawXmlError),
+    /// the name of a symbol, content dictionary or variable is invalid (only with
+    /// [`XmlOptions::strict`])
+    #[error("invalid name {name:?} in {kind}")]
+    InvalidName { kind: crate::OMKind, name: String },
+}
+impl XmlWriteError {
+    /// The cause of this error
+    #[must_use]
+    pub const fn kind(&self) -> super::ErrorKind {
+        match self {
+            Self::Custom(_) => super::ErrorKind::Custom,
+            Self::Unsupported(kind) => super::ErrorKind::Unsupported(*kind),
+            Self::InvalidValue(expected) => super::ErrorKind::InvalidValue(expected),
+            Self::Io(_) | Self::Fmt(_) => super::ErrorKind::Write,
+            Self::InvalidForeignXml(_) => super::ErrorKind::InvalidValue("well-formed XML"),
+            Self::InvalidName { .. } => super::ErrorKind::InvalidValue("a valid name"),
+        }
+    }
 }
 impl super::Error for XmlWriteError {
     fn custom(err: impl std::fmt::Display) -> Self {
         Self::Custom(err.to_string())
     }
+    #[inline]
+    fn unsupported(kind: crate::OMKind) -> Self {
+        Self::Unsupported(kind)
+    }
+    #[inline]
+    fn invalid_value(expected: &'static str) -> Self {
+        Self::InvalidValue(expected)
+    }
 }
 
 pub struct XmlDisplay<'s, O: super::OMSerializable + ?Sized> {
     pub pretty: bool,
+    pub sort: bool,
+    pub format: Option<&'s FormatMap>,
     pub o: &'s O,
 }
 impl<O: super::OMSerializable + ?Sized> std::fmt::Display for XmlDisplay<'_, O> {
+    #[inline]
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.try_fmt(f).map_err(|_| std::fmt::Error)
+    }
+}
+impl<O: super::OMSerializable + ?Sized> TryDisplay for XmlDisplay<'_, O> {
+    fn try_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), XmlWriteError> {
+        let preserve = self.format.map(Preserve::new);
+        let memo = Memo::default();
         let displayer = XmlDisplayer {
-            indent: if self.pretty { Some((false, 0)) } else { None },
+            indent: if self.pretty || preserve.is_some() {
+                Some((false, 0))
+            } else {
+                None
+            },
             w: f,
-            next_ns: self.o.cdbase(),
-            current_ns: crate::CD_BASE,
+            next_ns: self.o.cdbase().map(Cow::Borrowed),
+            current_ns: Cow::Borrowed(crate::CD_BASE),
+            floats: FloatStyle::default(),
+            preserve: preserve.as_ref(),
+            strict: false,
+            wrap_base64: false,
+            memo: &memo,
+            max_width: None,
         };
-        self.o.as_openmath(displayer).map_err(|_| std::fmt::Error)
+        if self.sort {
+            self.o.as_openmath(super::SortAttrs::new(displayer))
+        } else {
+            self.o.as_openmath(displayer)
+        }
     }
 }
 
-pub struct XmlObjDisplay<'s, O: super::OMSerializable + ?Sized> {
+/// Options for writing an OMOBJ as XML; see [`OMObject::xml_with`](super::OMObject::xml_with).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct XmlOptions<'s> {
+    /// Whether to indent the output
     pub pretty: bool,
+    /// Whether to add the `xmlns` attribute for the <span style="font-variant:small-caps;">OpenMath</span> namespace
     pub insert_namespace: bool,
+    /// The value of the `version` attribute; `None` omits the attribute
+    pub version: Option<&'s str>,
+    /// How to write the `dec` attribute of [OMF](crate::OMKind::OMF)s
+    pub float_format: FloatFormat,
+    /// Whether to always include a decimal point in floats (`2.0` instead of `2`)
+    pub float_decimal_point: bool,
+    /// The value of the `id` attribute, by which [OMR](crate::OMKind::OMR)s in other
+    /// documents can refer to the object; `None` omits the attribute
+    pub id: Option<&'s str>,
+    /// The cdbase that readers of the output assume for symbols without one, i.e. the
+    /// initial [`current_cdbase`](super::OMSerializer::current_cdbase); usually
+    /// [`CD_BASE`](crate::CD_BASE)
+    pub default_cdbase: &'s str,
+    /// Whether to fail on output that would not be valid
+    /// <span style="font-variant:small-caps;">OpenMath</span> XML, rather than writing it
+    /// anyway: invalid names and malformed [OMFOREIGN](crate::OMKind::OMFOREIGN) content
+    /// (see [`XmlWriteError`]). Non-finite floats are valid either way, as `INF`, `-INF` and
+    /// `NaN`
+    pub strict: bool,
+    /// Whether to wrap the base64 content of [OMB](crate::OMKind::OMB)s into lines of 76
+    /// characters, like MIME; readers skip whitespace in base64
+    pub wrap_base64: bool,
+    /// Whether to write the pairs of [OMATTR](crate::OMKind::OMATTR)s sorted by their key;
+    /// see [`Options::sort_attributes`](super::Options::sort_attributes)
+    pub sort_attributes: bool,
+    /// When [`pretty`](Self::pretty), the width (in characters, including the
+    /// indentation) to fit lines into: elements that fit are written on a single line, and
+    /// the children of those that do not are packed onto shared lines, e.g. the arguments of
+    /// an [OMA](crate::OMKind::OMA), the pairs of an `OMATP` or the variables of an
+    /// `OMBVAR`. Children that are too long on their own are broken the same way. `None`
+    /// writes every element on its own line.
+    pub max_width: Option<usize>,
+}
+impl Default for XmlOptions<'_> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            pretty: false,
+            insert_namespace: true,
+            version: Some(crate::OPENMATH_VERSION),
+            float_format: FloatFormat::Shortest,
+            float_decimal_point: false,
+            id: None,
+            default_cdbase: crate::CD_BASE,
+            strict: false,
+            wrap_base64: false,
+            sort_attributes: false,
+            max_width: None,
+        }
+    }
+}
+
+impl From<super::Options> for XmlOptions<'_> {
+    #[inline]
+    fn from(options: super::Options) -> Self {
+        Self {
+            pretty: options.pretty,
+            insert_namespace: options.insert_namespace,
+            sort_attributes: options.sort_attributes,
+            ..Self::default()
+        }
+    }
+}
+
+pub struct XmlObjDisplay<'s, O: super::OMSerializable + ?Sized> {
+    pub options: XmlOptions<'s>,
+    pub format: Option<&'s FormatMap>,
     pub o: &'s O,
 }
 impl<O: super::OMSerializable + ?Sized> std::fmt::Display for XmlObjDisplay<'_, O> {
+    #[inline]
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str("<OMOBJ version=\"2.0\"")?;
-        if self.insert_namespace {
-            f.write_str(" xmlns=\"")?;
-            f.write_str(crate::XML_NS)?;
-            f.write_char('\"')?;
-        }
-        let ns = if let Some(ns) = self.o.cdbase() {
-            f.write_str("cdbase=\"")?;
-            write!(DisplayEscaper(f), "{ns}")?;
-            f.write_str("\"")?;
-            ns
-        } else {
-            crate::CD_BASE
-        };
+        self.try_fmt(f).map_err(|_| std::fmt::Error)
+    }
+}
+impl<O: super::OMSerializable + ?Sized> TryDisplay for XmlObjDisplay<'_, O> {
+    fn try_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), XmlWriteError> {
+        let preserve = self.format.map(Preserve::new);
+        if let Some(p) = &preserve {
+            p.cursor.borrow_mut().start(0);
+        }
+        f.write_str("<OMOBJ")?;
+        let ns = self.o.cdbase();
+        let mut attributes = [
+            ("version", self.options.version),
+            ("id", self.options.id),
+            (
+                "xmlns",
+                Some(crate::XML_NS).filter(|_| self.options.insert_namespace),
+            ),
+            ("cdbase", ns),
+        ];
+        if let Some(p) = &preserve {
+            attributes.sort_by_key(|(a, _)| p.map.attribute_rank(&[0], "OMOBJ", a));
+        }
+        for (attribute, value) in attributes {
+            if let Some(value) = value {
+                write!(f, " {attribute}=\"")?;
+                write!(AttrEscaper(f), "{value}")?;
+                f.write_char('"')?;
+            }
+        }
+        let ns = ns.unwrap_or(self.options.default_cdbase);
         f.write_char('>')?;
 
-        self.o
-            .as_openmath(XmlDisplayer {
-                indent: if self.pretty { Some((true, 1)) } else { None },
-                w: f,
-                next_ns: None,
-                current_ns: ns,
-            })
-            .map_err(|_| std::fmt::Error)?;
+        let memo = Memo::default();
+        let displayer = XmlDisplayer {
+            indent: if self.options.pretty || preserve.is_some() {
+                Some((true, 1))
+            } else {
+                None
+            },
+            w: f,
+            next_ns: None,
+            current_ns: Cow::Borrowed(ns),
+            floats: FloatStyle {
+                format: self.options.float_format,
+                decimal_point: self.options.float_decimal_point,
+            },
+            preserve: preserve.as_ref(),
+            strict: self.options.strict,
+            wrap_base64: self.options.wrap_base64,
+            memo: &memo,
+            max_width: self.options.max_width,
+        };
+        if self.options.sort_attributes {
+            self.o.as_openmath(super::SortAttrs::new(displayer))?;
+        } else {
+            self.o.as_openmath(displayer)?;
+        }
 
-        if self.pretty {
+        if let Some(ws) = preserve.as_ref().and_then(|p| p.map.end(&[0], "OMOBJ")) {
+            f.write_str(ws)?;
+            f.write_str("</OMOBJ>")?;
+        } else if self.options.pretty || preserve.is_some() {
             f.write_str("\n</OMOBJ>")?;
         } else {
             f.write_str("</OMOBJ>")?;
@@ -77,14 +259,199 @@ impl<O: super::OMSerializable + ?Sized> std::fmt::Display for XmlObjDisplay<'_,
     }
 }
 
+/// Something that [`Display`](std::fmt::Display)s as XML, discarding the [`XmlWriteError`]
+pub(super) trait TryDisplay {
+    fn try_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), XmlWriteError>;
+}
+
+/// Writes `xml` to `w`, keeping the [`XmlWriteError`] that `Display` would discard
+pub(super) fn try_write(
+    xml: &impl TryDisplay,
+    w: &mut impl std::fmt::Write,
+) -> Result<(), XmlWriteError> {
+    struct Capture<'x, X>(&'x X, RefCell<Option<XmlWriteError>>);
+    impl<X: TryDisplay> std::fmt::Display for Capture<'_, X> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            self.0.try_fmt(f).map_err(|e| {
+                *self.1.borrow_mut() = Some(e);
+                std::fmt::Error
+            })
+        }
+    }
+    let capture = Capture(xml, RefCell::new(None));
+    let result = write!(w, "{capture}");
+    capture
+        .1
+        .into_inner()
+        .map_or_else(|| result.map_err(Into::into), Err)
+}
+
+/// Like [`try_write`], but to an [`io::Write`](std::io::Write); failures of `w` are
+/// [`XmlWriteError::Io`]
+pub(super) fn try_write_io(
+    xml: &impl TryDisplay,
+    w: impl std::io::Write,
+) -> Result<(), XmlWriteError> {
+    struct Io<W> {
+        inner: W,
+        error: Option<std::io::Error>,
+    }
+    impl<W: std::io::Write> std::fmt::Write for Io<W> {
+        fn write_str(&mut self, s: &str) -> std::fmt::Result {
+            self.inner.write_all(s.as_bytes()).map_err(|e| {
+                self.error = Some(e);
+                std::fmt::Error
+            })
+        }
+    }
+    let mut io = Io {
+        inner: w,
+        error: None,
+    };
+    let result = try_write(xml, &mut io);
+    io.error.map_or(result, |e| Err(e.into()))
+}
+
+/// Fails unless `name` is a valid symbol or content dictionary name; for
+/// [OMV](crate::OMKind::OMV)s, unless it is non-empty
+pub(super) fn check_name(
+    kind: crate::OMKind,
+    name: &impl std::fmt::Display,
+) -> Result<(), XmlWriteError> {
+    let name = name.to_string();
+    let valid = if kind == crate::OMKind::OMV {
+        !name.is_empty()
+    } else {
+        super::valid_name(&name)
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(XmlWriteError::InvalidName { kind, name })
+    }
+}
+
+/// The state for writing XML with recorded formatting
+struct Preserve<'m> {
+    map: &'m FormatMap,
+    cursor: RefCell<Cursor>,
+}
+impl<'m> Preserve<'m> {
+    fn new(map: &'m FormatMap) -> Self {
+        Self {
+            map,
+            cursor: RefCell::default(),
+        }
+    }
+}
+
+/// The XML of the [memoized](super::OMSerializer::memoized) objects written so far, by
+/// their key and indentation level (if any), and the cdbase in effect
+#[derive(Default)]
+#[allow(clippy::type_complexity)]
+struct Memo(RefCell<HashMap<(usize, Option<usize>), Vec<(Box<str>, Box<str>)>>>);
+
+/// A memoized object, written on its own at the indentation level `depth`
+struct Fragment<'s, O> {
+    o: O,
+    depth: Option<usize>,
+    cdbase: &'s str,
+    floats: FloatStyle,
+    strict: bool,
+    wrap_base64: bool,
+    memo: &'s Memo,
+    max_width: Option<usize>,
+}
+impl<O: OMSerializable> TryDisplay for Fragment<'_, O> {
+    fn try_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), XmlWriteError> {
+        self.o.as_openmath(XmlDisplayer {
+            indent: self.depth.map(|depth| (false, depth)),
+            w: f,
+            next_ns: None,
+            current_ns: Cow::Borrowed(self.cdbase),
+            floats: self.floats,
+            preserve: None,
+            strict: self.strict,
+            wrap_base64: self.wrap_base64,
+            memo: self.memo,
+            max_width: self.max_width,
+        })
+    }
+}
+
+/// A child of an element written by [`XmlDisplayer::element`]; it may be written twice,
+/// once without indentation to measure it, and again if that does not fit on a line
+type Unit<'u> = Box<dyn Fn(XmlDisplayer<'_, '_>) -> Result<(), XmlWriteError> + 'u>;
+
+#[inline]
+fn unit<'u>(o: impl OMSerializable + 'u) -> Unit<'u> {
+    Box::new(move |d| o.as_openmath(d))
+}
+
+/// A [`Unit`] written without indentation, to measure its width
+struct Compact<'s, 'u> {
+    unit: &'s Unit<'u>,
+    cdbase: &'s str,
+    floats: FloatStyle,
+    strict: bool,
+    wrap_base64: bool,
+    memo: &'s Memo,
+}
+impl TryDisplay for Compact<'_, '_> {
+    fn try_fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), XmlWriteError> {
+        (self.unit)(XmlDisplayer {
+            indent: None,
+            w: f,
+            next_ns: None,
+            current_ns: Cow::Borrowed(self.cdbase),
+            floats: self.floats,
+            preserve: None,
+            strict: self.strict,
+            wrap_base64: self.wrap_base64,
+            memo: self.memo,
+            max_width: None,
+        })
+    }
+}
+
 struct XmlDisplayer<'s, 'f: 's> {
     indent: Option<(bool, usize)>,
     w: &'s mut std::fmt::Formatter<'f>,
-    next_ns: Option<&'s str>,
-    current_ns: &'s str,
+    next_ns: Option<Cow<'s, str>>,
+    current_ns: Cow<'s, str>,
+    floats: FloatStyle,
+    preserve: Option<&'s Preserve<'s>>,
+    strict: bool,
+    wrap_base64: bool,
+    memo: &'s Memo,
+    max_width: Option<usize>,
 }
 impl<'f> XmlDisplayer<'_, 'f> {
-    fn indent(&mut self) -> std::fmt::Result {
+    /// Writes the whitespace preceding the tag `tag` (`/NAME` for end tags); returns
+    /// whether recorded formatting was used.
+    fn indent(&mut self, tag: &str) -> Result<bool, std::fmt::Error> {
+        let Some((_, indent)) = self.indent else {
+            return Ok(false);
+        };
+        if let Some(p) = self.preserve {
+            let end = tag.strip_prefix('/');
+            let mut cursor = p.cursor.borrow_mut();
+            let ws = if let Some(name) = end {
+                p.map.end(cursor.current(indent), name)
+            } else {
+                p.map.start(cursor.start(indent), tag)
+            };
+            if let Some(ws) = ws {
+                self.w.write_str(ws)?;
+                self.indent = Some((true, indent));
+                return Ok(true);
+            }
+        }
+        self.newline()?;
+        Ok(false)
+    }
+
+    fn newline(&mut self) -> std::fmt::Result {
         let Some((had_content, indent)) = self.indent else {
             return Ok(());
         };
@@ -113,34 +480,167 @@ impl<'f> XmlDisplayer<'_, 'f> {
     }
 
     #[inline]
-    const fn clone(&mut self) -> XmlDisplayer<'_, 'f> {
+    fn clone(&mut self) -> XmlDisplayer<'_, 'f> {
         XmlDisplayer {
             indent: self.indent,
             w: self.w,
-            next_ns: self.next_ns,
-            current_ns: self.current_ns,
+            next_ns: self.next_ns.as_deref().map(Cow::Borrowed),
+            current_ns: Cow::Borrowed(&self.current_ns),
+            floats: self.floats,
+            preserve: self.preserve,
+            strict: self.strict,
+            wrap_base64: self.wrap_base64,
+            memo: self.memo,
+            max_width: self.max_width,
         }
     }
 
+    /// [`XmlOptions::max_width`], if lines are wrapped; recorded formatting takes precedence
+    fn max_width(&self) -> Option<usize> {
+        self.max_width
+            .filter(|_| self.indent.is_some() && self.preserve.is_none())
+    }
+
+    /// The start tag of the element `name` without its closing `>`, including the pending
+    /// cdbase change
+    fn start_tag(&mut self, name: &str) -> String {
+        if let Some(ns) = self.next_ns.take() {
+            let tag = format!("<{name} cdbase=\"{}\"", escape_attribute(&ns));
+            self.current_ns = ns;
+            tag
+        } else {
+            format!("<{name}")
+        }
+    }
+
+    /// `unit` as written without indentation
+    fn compact(&self, unit: &Unit<'_>) -> Result<String, XmlWriteError> {
+        let mut line = String::new();
+        try_write(
+            &Compact {
+                unit,
+                cdbase: &self.current_ns,
+                floats: self.floats,
+                strict: self.strict,
+                wrap_base64: self.wrap_base64,
+                memo: self.memo,
+            },
+            &mut line,
+        )?;
+        Ok(line)
+    }
+
+    /// Writes the element `name` starting with `start` (see [`start_tag`](Self::start_tag))
+    /// and containing `units`: on a single line if it fits into the
+    /// [`max_width`](Self::max_width), otherwise with the children packed onto the lines
+    /// between its tags.
+    fn element(
+        &mut self,
+        start: &str,
+        name: &str,
+        units: &[Unit<'_>],
+    ) -> Result<(), XmlWriteError> {
+        let max = self.max_width.unwrap_or(usize::MAX);
+        let column = self.indent.map_or(0, |(_, depth)| 2 * depth);
+        self.indent(name)?;
+        self.w.write_str(start)?;
+        if units.is_empty() {
+            self.w.write_str("/>")?;
+            return Ok(());
+        }
+        self.w.write_char('>')?;
+        let lines = units
+            .iter()
+            .map(|u| self.compact(u))
+            .collect::<Result<Vec<_>, _>>()?;
+        // `start`, `>`, the children and `</name>`
+        let width = start.chars().count()
+            + 1
+            + lines.iter().map(|l| l.chars().count()).sum::<usize>()
+            + name.len()
+            + 3;
+        if column + width <= max && lines.iter().all(|l| !l.contains('\n')) {
+            for l in &lines {
+                self.w.write_str(l)?;
+            }
+        } else {
+            self.indented(|nslf| nslf.pack(units, &lines))?;
+            self.indent(&format!("/{name}"))?;
+        }
+        write!(self.w, "</{name}>")?;
+        Ok(())
+    }
+
+    /// Writes `units`, whose compact forms are `lines`, as many on a line as fit; those that
+    /// do not fit on a line of their own are written indented, and end their line.
+    fn pack(&mut self, units: &[Unit<'_>], lines: &[String]) -> Result<(), XmlWriteError> {
+        let max = self.max_width.unwrap_or(usize::MAX);
+        let column = self.indent.map_or(0, |(_, depth)| 2 * depth);
+        // the width of the current line, if more may be appended to it
+        let mut line = None;
+        for (unit, l) in units.iter().zip(lines) {
+            let width = l.chars().count();
+            let single = !l.contains('\n');
+            match line {
+                Some(len) if single && len + 1 + width <= max => {
+                    self.w.write_char(' ')?;
+                    self.w.write_str(l)?;
+                    line = Some(len + 1 + width);
+                }
+                _ if single && column + width <= max => {
+                    self.newline()?;
+                    self.w.write_str(l)?;
+                    line = Some(column + width);
+                }
+                _ => {
+                    unit(self.clone())?;
+                    line = None;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the bound variable `v` of an `OMBVAR`
+    fn var(self, v: &impl BindVar) -> Result<(), XmlWriteError> {
+        let mut attrs = v.attrs().peekable();
+        if attrs.peek().is_none() {
+            self.omv(v.name())
+        } else {
+            self.omattr(attrs, super::Omv(v.name()))
+        }
+    }
+
+    #[inline]
     fn omforeign(&mut self, a: impl super::OMOrForeign) -> Result<(), XmlWriteError> {
-        match a.om_or_foreign() {
+        self.foreign(&a.om_or_foreign())
+    }
+
+    fn foreign(
+        &mut self,
+        a: &Either<impl OMSerializable, (Option<impl std::fmt::Display>, impl std::fmt::Display)>,
+    ) -> Result<(), XmlWriteError> {
+        match a {
             Either::Left(o) => o.as_openmath(self.clone())?,
             Either::Right((encoding, value)) => {
-                let ind = self.indent.is_some();
-                if ind {
-                    self.indent()?;
+                if self.strict {
+                    super::raw::check_foreign(&value.to_string())
+                        .map_err(XmlWriteError::InvalidForeignXml)?;
                 }
+                // the whitespace around the content is not recorded, so content of
+                // preserved OMFOREIGNs is written as is
+                let ind = self.indent.is_some() && !self.indent("OMFOREIGN")?;
                 if let Some(enc) = encoding {
                     self.w.write_str("<OMFOREIGN encoding=\"")?;
-                    write!(DisplayEscaper(self.w), "{enc}")?;
+                    write!(AttrEscaper(self.w), "{enc}")?;
                     self.w.write_str("\">")?;
                 } else {
                     self.w.write_str("<OMFOREIGN>")?;
                 }
                 if ind {
-                    self.indent()?;
+                    self.newline()?;
                     write!(self.w, "  {value}")?;
-                    self.indent()?;
+                    self.newline()?;
                 } else {
                     write!(self.w, "{value}")?;
                 }
@@ -149,6 +649,74 @@ impl<'f> XmlDisplayer<'_, 'f> {
         }
         Ok(())
     }
+
+    fn oma_iter(
+        mut self,
+        head: impl OMSerializable,
+        args: impl Iterator<Item: OMSerializable>,
+    ) -> Result<(), XmlWriteError> {
+        if self.max_width().is_some() {
+            let start = self.start_tag("OMA");
+            let args = args.collect::<Vec<_>>();
+            let units = std::iter::once(unit(&head))
+                .chain(args.iter().map(unit))
+                .collect::<Vec<_>>();
+            return self.element(&start, "OMA", &units);
+        }
+        self.indent("OMA")?;
+        if let Some(ns) = self.next_ns.take() {
+            self.w.write_str("<OMA cdbase=\"")?;
+            write!(AttrEscaper(self.w), "{ns}")?;
+            self.w.write_str("\">")?;
+            self.current_ns = ns;
+        } else {
+            self.w.write_str("<OMA>")?;
+        }
+        self.indented(|nslf| {
+            head.as_openmath(nslf.clone())?;
+            for a in args {
+                a.as_openmath(nslf.clone())?;
+            }
+            Ok(())
+        })?;
+        self.indent("/OMA")?;
+        self.w.write_str("</OMA>")?;
+        Ok(())
+    }
+
+    /// Writes an OMS with the given cdbase attribute
+    fn write_oms(
+        mut self,
+        cdbase: Option<&str>,
+        cd_name: impl std::fmt::Display,
+        name: impl std::fmt::Display,
+    ) -> Result<(), XmlWriteError> {
+        if self.strict {
+            check_name(crate::OMKind::OMS, &cd_name)?;
+            check_name(crate::OMKind::OMS, &name)?;
+        }
+        self.indent("OMS")?;
+        let mut attributes: [(&str, Option<&dyn std::fmt::Display>); 3] = [
+            ("cdbase", cdbase.as_ref().map(|n| n as _)),
+            ("cd", Some(&cd_name)),
+            ("name", Some(&name)),
+        ];
+        if let (Some(p), Some((_, depth))) = (self.preserve, self.indent) {
+            let mut cursor = p.cursor.borrow_mut();
+            let path = cursor.current(depth);
+            attributes.sort_by_key(|(a, _)| p.map.attribute_rank(path, "OMS", a));
+        }
+        self.w.write_str("<OMS")?;
+        for (key, value) in attributes {
+            if let Some(value) = value {
+                write!(self.w, " {key}=\"")?;
+                write!(AttrEscaper(self.w), "{value}")?;
+                self.w.write_char('"')?;
+            }
+        }
+        self.w.write_str("/>")?;
+        Ok(())
+    }
 }
 
 impl<'s, 'f> super::OMSerializer<'s> for XmlDisplayer<'s, 'f> {
@@ -160,88 +728,217 @@ impl<'s, 'f> super::OMSerializer<'s> for XmlDisplayer<'s, 'f> {
         's: 'ns;
     #[inline]
     fn current_cdbase(&self) -> &str {
-        self.next_ns.unwrap_or(self.current_ns)
+        self.next_ns.as_deref().unwrap_or(&self.current_ns)
     }
-    fn with_cdbase<'ns>(self, cdbase: &'ns str) -> Result<Self::SubSerializer<'ns>, Self::Err>
+    fn with_cdbase<'ns>(
+        self,
+        cdbase: impl Into<Cow<'ns, str>>,
+    ) -> Result<Self::SubSerializer<'ns>, Self::Err>
     where
         's: 'ns,
     {
-        if self.current_ns == cdbase {
-            Ok(self)
+        let cdbase = cdbase.into();
+        if crate::cdbase::eq_normalized(&self.current_ns, &cdbase) {
+            // a cdbase set further out but not yet written is overridden
+            Ok(Self {
+                next_ns: None,
+                ..self
+            })
         } else {
             Ok(XmlDisplayer {
                 indent: self.indent,
                 w: self.w,
                 next_ns: Some(cdbase),
                 current_ns: self.current_ns,
+                floats: self.floats,
+                preserve: self.preserve,
+                strict: self.strict,
+                wrap_base64: self.wrap_base64,
+                memo: self.memo,
+                max_width: self.max_width,
             })
         }
     }
     fn omi(mut self, value: &crate::Int) -> Result<Self::Ok, Self::Err> {
-        self.indent()?;
+        self.indent("OMI")?;
         write!(self.w, "<OMI>{value}</OMI>")?;
         Ok(())
     }
     fn omf(mut self, value: f64) -> Result<Self::Ok, Self::Err> {
-        self.indent()?;
-        write!(self.w, "<OMF dec=\"{value}\"/>")?;
+        self.indent("OMF")?;
+        self.w.write_str("<OMF dec=\"")?;
+        let recorded = match (self.preserve, self.indent) {
+            (Some(p), Some((_, depth))) => {
+                p.map.decimal(p.cursor.borrow_mut().current(depth), value)
+            }
+            _ => None,
+        };
+        match recorded {
+            // parses as a float, so it needs no escaping
+            Some(recorded) => self.w.write_str(recorded)?,
+            None => self.floats.write(self.w, value)?,
+        }
+        self.w.write_str("\"/>")?;
         Ok(())
     }
     fn omb(mut self, bytes: impl ExactSizeIterator<Item = u8>) -> Result<Self::Ok, Self::Err> {
-        use crate::base64::Base64Encodable;
-        self.indent()?;
+        /// The length of wrapped lines
+        const LINE: usize = 76;
+        // encoded in blocks of whole lines (of 57 bytes each), so only the last one is
+        // padded or shorter
+        const BLOCK: usize = LINE / 4 * 3 * 54;
+        self.indent("OMB")?;
         self.w.write_str("<OMB>")?;
-        for [a, b, c, d] in bytes.into_iter().base64() {
-            self.w.write_char(a.get() as _)?;
-            self.w.write_char(b.get() as _)?;
-            self.w.write_char(c.get() as _)?;
-            self.w.write_char(d.get() as _)?;
+        let mut bytes = bytes.into_iter();
+        let mut block = Vec::with_capacity(bytes.len().min(BLOCK));
+        let mut encoded = String::with_capacity(block.capacity() / 3 * 4 + 4);
+        let mut first = true;
+        loop {
+            block.clear();
+            block.extend(bytes.by_ref().take(BLOCK));
+            if block.is_empty() {
+                break;
+            }
+            encoded.clear();
+            crate::base64::encode_into(&block, &mut encoded);
+            if !self.wrap_base64 {
+                self.w.write_str(&encoded)?;
+                continue;
+            }
+            // base64 is ASCII, so every split is at a char boundary
+            let mut rest = encoded.as_str();
+            while !rest.is_empty() {
+                let (line, next) = rest.split_at(rest.len().min(LINE));
+                if !std::mem::take(&mut first) {
+                    self.w.write_char('\n')?;
+                }
+                self.w.write_str(line)?;
+                rest = next;
+            }
         }
         self.w.write_str("</OMB>")?;
         Ok(())
     }
     fn omstr(mut self, string: impl std::fmt::Display) -> Result<Self::Ok, Self::Err> {
-        self.indent()?;
+        self.indent("OMSTR")?;
         self.w.write_str("<OMSTR>")?;
         write!(DisplayEscaper(self.w), "{string}")?;
         self.w.write_str("</OMSTR>")?;
         Ok(())
     }
     fn omv(mut self, name: impl std::fmt::Display) -> Result<Self::Ok, Self::Err> {
-        self.indent()?;
+        if self.strict {
+            check_name(crate::OMKind::OMV, &name)?;
+        }
+        self.indent("OMV")?;
         self.w.write_str("<OMV name=\"")?;
-        write!(DisplayEscaper(self.w), "{name}")?;
+        write!(AttrEscaper(self.w), "{name}")?;
+        self.w.write_str("\"/>")?;
+        Ok(())
+    }
+    fn omr(mut self, href: impl std::fmt::Display) -> Result<Self::Ok, Self::Err> {
+        self.indent("OMR")?;
+        self.w.write_str("<OMR href=\"")?;
+        write!(AttrEscaper(self.w), "{href}")?;
         self.w.write_str("\"/>")?;
         Ok(())
     }
+    fn raw_xml(mut self, xml: &str) -> Result<Self::Ok, Self::Err> {
+        if let Some(ns) = self.next_ns {
+            return Err(XmlWriteError::Custom(format!(
+                "can not change the cdbase of raw XML to {ns}"
+            )));
+        }
+        let xml = xml.trim();
+        self.indent(super::raw::root_name(xml))?;
+        self.w.write_str(xml)?;
+        Ok(())
+    }
+    fn memoized(mut self, key: usize, o: impl OMSerializable) -> Result<Self::Ok, Self::Err> {
+        // recorded formatting depends on the position, and a pending cdbase change is
+        // written on the object itself
+        if self.preserve.is_some() || self.next_ns.is_some() {
+            return o.as_openmath(self);
+        }
+        let depth = self.indent.map(|(_, depth)| depth);
+        let slot = (key, depth);
+        let cached = self.memo.0.borrow().get(&slot).and_then(|v| {
+            v.iter()
+                .find(|(cdbase, _)| **cdbase == *self.current_ns)
+                .map(|(_, xml)| xml.clone())
+        });
+        let xml = if let Some(xml) = cached {
+            xml
+        } else {
+            let mut xml = String::new();
+            try_write(
+                &Fragment {
+                    o,
+                    depth,
+                    cdbase: &self.current_ns,
+                    floats: self.floats,
+                    strict: self.strict,
+                    wrap_base64: self.wrap_base64,
+                    memo: self.memo,
+                    max_width: self.max_width,
+                },
+                &mut xml,
+            )?;
+            // the indentation of the first line depends on what precedes it
+            let xml: Box<str> = xml.trim_start().into();
+            self.memo
+                .0
+                .borrow_mut()
+                .entry(slot)
+                .or_default()
+                .push((self.current_ns.as_ref().into(), xml.clone()));
+            xml
+        };
+        self.newline()?;
+        self.w.write_str(&xml)?;
+        Ok(())
+    }
     fn oms(
         mut self,
         cd_name: impl std::fmt::Display,
         name: impl std::fmt::Display,
     ) -> Result<Self::Ok, Self::Err> {
-        self.indent()?;
-        self.w.write_str("<OMS ")?;
-        if let Some(cdbase) = self.next_ns {
-            self.w.write_str("cdbase=\"")?;
-            write!(DisplayEscaper(self.w), "{cdbase}")?;
-            self.w.write_str("\" ")?;
-        }
-        self.w.write_str("cd=\"")?;
-        write!(DisplayEscaper(self.w), "{cd_name}")?;
-        self.w.write_str("\" name=\"")?;
-        write!(DisplayEscaper(self.w), "{name}")?;
-        self.w.write_str("\"/>")?;
-        Ok(())
+        let cdbase = self.next_ns.take();
+        self.write_oms(cdbase.as_deref(), cd_name, name)
+    }
+    fn oms_uri(mut self, uri: &crate::SymbolRef<'_>) -> Result<Self::Ok, Self::Err> {
+        // an OMS has no children, so only the cdbase it ends up with matters
+        let pending = self.next_ns.take();
+        let cdbase = match uri.cdbase() {
+            Some(cdbase) if !crate::cdbase::eq_normalized(cdbase, &self.current_ns) => Some(cdbase),
+            Some(_) => None,
+            None => pending.as_deref(),
+        };
+        self.write_oms(cdbase, uri.cd(), uri.name())
     }
     fn ome(
         mut self,
         error: impl AsOMS,
         args: impl ExactSizeIterator<Item: super::OMOrForeign>,
     ) -> Result<Self::Ok, Self::Err> {
-        self.indent()?;
+        if self.max_width().is_some() {
+            let start = self.start_tag("OME");
+            let args = args.map(OMOrForeign::om_or_foreign).collect::<Vec<_>>();
+            let units =
+                std::iter::once(
+                    Box::new(|d: XmlDisplayer<'_, '_>| error.as_oms().as_openmath(d)) as Unit,
+                )
+                .chain(
+                    args.iter()
+                        .map(|a| Box::new(move |mut d: XmlDisplayer<'_, '_>| d.foreign(a)) as Unit),
+                )
+                .collect::<Vec<_>>();
+            return self.element(&start, "OME", &units);
+        }
+        self.indent("OME")?;
         if let Some(ns) = self.next_ns.take() {
             self.w.write_str("<OME cdbase=\"")?;
-            write!(DisplayEscaper(self.w), "{ns}")?;
+            write!(AttrEscaper(self.w), "{ns}")?;
             self.w.write_str("\">")?;
             self.current_ns = ns;
         } else {
@@ -254,35 +951,28 @@ impl<'s, 'f> super::OMSerializer<'s> for XmlDisplayer<'s, 'f> {
             }
             Ok(())
         })?;
-        self.indent()?;
+        self.indent("/OME")?;
         self.w.write_str("</OME>")?;
         Ok(())
     }
 
+    #[inline]
     fn oma(
-        mut self,
+        self,
         head: impl OMSerializable,
         args: impl ExactSizeIterator<Item: OMSerializable>,
     ) -> Result<Self::Ok, Self::Err> {
-        self.indent()?;
-        if let Some(ns) = self.next_ns.take() {
-            self.w.write_str("<OMA cdbase=\"")?;
-            write!(DisplayEscaper(self.w), "{ns}")?;
-            self.w.write_str("\">")?;
-            self.current_ns = ns;
-        } else {
-            self.w.write_str("<OMA>")?;
-        }
-        self.indented(|nslf| {
-            head.as_openmath(nslf.clone())?;
-            for a in args {
-                a.as_openmath(nslf.clone())?;
-            }
-            Ok(())
-        })?;
-        self.indent()?;
-        self.w.write_str("</OMA>")?;
-        Ok(())
+        self.oma_iter(head, args)
+    }
+
+    /// Writes every argument as soon as it is produced
+    #[inline]
+    fn oma_streamed<I: OMSerializable, F: FnMut() -> Option<I>>(
+        self,
+        head: impl OMSerializable,
+        args: super::StreamedArgs<F>,
+    ) -> Result<Self::Ok, Self::Err> {
+        self.oma_iter(head, args)
     }
 
     fn omattr(
@@ -290,15 +980,43 @@ impl<'s, 'f> super::OMSerializer<'s> for XmlDisplayer<'s, 'f> {
         attrs: impl ExactSizeIterator<Item: super::OMAttr>,
         atp: impl OMSerializable,
     ) -> Result<Self::Ok, Self::Err> {
-        let attrs = attrs.into_iter();
-        if attrs.len() == 0 {
+        let mut attrs = attrs.peekable();
+        if attrs.peek().is_none() {
             return atp.as_openmath(self.clone());
         }
+        if self.max_width().is_some() {
+            let start = self.start_tag("OMATTR");
+            // the symbols are leaves, so they are the same in the compact form
+            let pairs = attrs
+                .map(|a| {
+                    let key = self.compact(&unit(a.symbol().as_oms()))?;
+                    Ok((key, a.value().om_or_foreign()))
+                })
+                .collect::<Result<Vec<_>, XmlWriteError>>()?;
+            let pairs = pairs
+                .iter()
+                .map(|(key, value)| {
+                    Box::new(move |mut d: XmlDisplayer<'_, '_>| {
+                        d.indent("OMS")?;
+                        d.w.write_str(key)?;
+                        d.foreign(value)
+                    }) as Unit
+                })
+                .collect::<Vec<_>>();
+            let units = [
+                Box::new(|mut d: XmlDisplayer<'_, '_>| {
+                    let start = d.start_tag("OMATP");
+                    d.element(&start, "OMATP", &pairs)
+                }) as Unit,
+                unit(&atp),
+            ];
+            return self.element(&start, "OMATTR", &units);
+        }
 
-        self.indent()?;
+        self.indent("OMATTR")?;
         if let Some(ns) = self.next_ns.take() {
             self.w.write_str("<OMATTR cdbase=\"")?;
-            write!(DisplayEscaper(self.w), "{ns}")?;
+            write!(AttrEscaper(self.w), "{ns}")?;
             self.w.write_str("\">")?;
             self.current_ns = ns;
         } else {
@@ -306,7 +1024,7 @@ impl<'s, 'f> super::OMSerializer<'s> for XmlDisplayer<'s, 'f> {
         }
 
         self.indented(move |nslf| {
-            nslf.indent()?;
+            nslf.indent("OMATP")?;
             nslf.w.write_str("<OMATP>")?;
             nslf.indented(move |nslf| {
                 for a in attrs {
@@ -315,12 +1033,12 @@ impl<'s, 'f> super::OMSerializer<'s> for XmlDisplayer<'s, 'f> {
                 }
                 Ok(())
             })?;
-            nslf.indent()?;
+            nslf.indent("/OMATP")?;
             nslf.w.write_str("</OMATP>")?;
             atp.as_openmath(nslf.clone())
         })?;
 
-        self.indent()?;
+        self.indent("/OMATTR")?;
         self.w.write_str("</OMATTR>")?;
         Ok(())
     }
@@ -331,10 +1049,27 @@ impl<'s, 'f> super::OMSerializer<'s> for XmlDisplayer<'s, 'f> {
         vars: impl ExactSizeIterator<Item: super::BindVar>,
         body: impl OMSerializable,
     ) -> Result<Self::Ok, Self::Err> {
-        self.indent()?;
+        if self.max_width().is_some() {
+            let start = self.start_tag("OMBIND");
+            let vars = vars.collect::<Vec<_>>();
+            let vars = vars
+                .iter()
+                .map(|v| Box::new(move |d: XmlDisplayer<'_, '_>| d.var(v)) as Unit)
+                .collect::<Vec<_>>();
+            let units = [
+                unit(&head),
+                Box::new(|mut d: XmlDisplayer<'_, '_>| {
+                    let start = d.start_tag("OMBVAR");
+                    d.element(&start, "OMBVAR", &vars)
+                }) as Unit,
+                unit(&body),
+            ];
+            return self.element(&start, "OMBIND", &units);
+        }
+        self.indent("OMBIND")?;
         if let Some(ns) = self.next_ns.take() {
             self.w.write_str("<OMBIND cdbase=\"")?;
-            write!(DisplayEscaper(self.w), "{ns}")?;
+            write!(AttrEscaper(self.w), "{ns}")?;
             self.w.write_str("\">")?;
             self.current_ns = ns;
         } else {
@@ -343,7 +1078,7 @@ impl<'s, 'f> super::OMSerializer<'s> for XmlDisplayer<'s, 'f> {
 
         self.indented(|nslf| {
             head.as_openmath(nslf.clone())?;
-            nslf.indent()?;
+            nslf.indent("OMBVAR")?;
             nslf.w.write_str("<OMBVAR")?;
             let mut was_empty = true;
 
@@ -353,55 +1088,507 @@ impl<'s, 'f> super::OMSerializer<'s> for XmlDisplayer<'s, 'f> {
                         nslf.w.write_char('>')?;
                     }
                     was_empty = false;
-                    let attrs = v.attrs();
-                    if attrs.len() == 0 {
-                        nslf.clone().omv(v.name())?;
-                    } else {
-                        nslf.clone().omattr(attrs, super::Omv(v.name()))?;
-                    }
+                    nslf.clone().var(&v)?;
                 }
                 Ok(())
             })?;
             if was_empty {
                 nslf.w.write_str("/>")?;
             } else {
-                nslf.indent()?;
+                nslf.indent("/OMBVAR")?;
                 nslf.w.write_str("</OMBVAR>")?;
             }
             body.as_openmath(nslf.clone())
         })?;
 
-        self.indent()?;
+        self.indent("/OMBIND")?;
         self.w.write_str("</OMBIND>")?;
         Ok(())
     }
 }
 
+/// Escapes `&`, `<` and `"`, for text
 struct DisplayEscaper<'a, 'f>(&'a mut std::fmt::Formatter<'f>);
 impl std::fmt::Write for DisplayEscaper<'_, '_> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        write_escaped(self.0, s, &['&', '<', '"'])
+    }
+}
+
+/// Escapes `&`, `<` and `"`, and tabs and line breaks (which readers would normalize to
+/// spaces), for (double-quoted) attribute values
+struct AttrEscaper<'a, 'f>(&'a mut std::fmt::Formatter<'f>);
+impl std::fmt::Write for AttrEscaper<'_, '_> {
+    #[inline]
     fn write_str(&mut self, s: &str) -> std::fmt::Result {
-        let mut is_first = true;
-        for seq in s.split('&') {
-            if !is_first {
-                self.0.write_str("&amp;")?;
+        write_escaped(self.0, s, ATTRIBUTE_SPECIALS)
+    }
+}
+
+const ATTRIBUTE_SPECIALS: &[char] = &['&', '<', '"', '\t', '\n', '\r'];
+
+/// `value` escaped for a (double-quoted) attribute, like [`AttrEscaper`] does
+pub(super) fn escape_attribute(value: &str) -> Cow<'_, str> {
+    if !value.contains(ATTRIBUTE_SPECIALS) {
+        return Cow::Borrowed(value);
+    }
+    let mut escaped = String::with_capacity(value.len() + 8);
+    let _ = write_escaped(&mut escaped, value, ATTRIBUTE_SPECIALS);
+    Cow::Owned(escaped)
+}
+
+/// Writes `s`, with the characters in `specials` (all of them ASCII) escaped
+fn write_escaped(w: &mut impl std::fmt::Write, s: &str, specials: &[char]) -> std::fmt::Result {
+    let mut rest = s;
+    while let Some(i) = rest.find(specials) {
+        w.write_str(&rest[..i])?;
+        w.write_str(match rest.as_bytes()[i] {
+            b'&' => "&amp;",
+            b'<' => "&lt;",
+            b'"' => "&quot;",
+            b'\t' => "&#9;",
+            b'\n' => "&#10;",
+            _ => "&#13;",
+        })?;
+        rest = &rest[i + 1..];
+    }
+    w.write_str(rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::XmlWriteError;
+    use crate::{
+        OMKind, OMSerializable,
+        ser::{Error, ErrorKind, OMObject, OMSerializer, XmlOptions},
+    };
+
+    fn xml_error(o: &impl OMSerializable) -> ErrorKind {
+        o.try_xml(false).expect_err("fails").kind()
+    }
+
+    #[test]
+    fn error_kinds() {
+        struct Fails(fn() -> XmlWriteError);
+        impl OMSerializable for Fails {
+            fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+                let _ = serializer;
+                // only ever serialized to XML here
+                let e = (self.0)();
+                Err(match e {
+                    XmlWriteError::Unsupported(k) => S::Err::unsupported(k),
+                    XmlWriteError::InvalidValue(v) => S::Err::invalid_value(v),
+                    e => S::Err::custom(e),
+                })
             }
-            is_first = false;
-            let mut is_first = true;
-            for seq in seq.split('<') {
-                if !is_first {
-                    self.0.write_str("&lt;")?;
+        }
+        assert_eq!(
+            xml_error(&Fails(|| XmlWriteError::Unsupported(OMKind::OMBIND))),
+            ErrorKind::Unsupported(OMKind::OMBIND)
+        );
+        assert_eq!(
+            xml_error(&Fails(|| XmlWriteError::InvalidValue("a prime"))),
+            ErrorKind::InvalidValue("a prime")
+        );
+        assert_eq!(
+            xml_error(&Fails(|| XmlWriteError::Custom("x".into()))),
+            ErrorKind::Custom
+        );
+
+        let mut arena = crate::arena::OmArena::new();
+        let foreign = arena.foreign(None, "<x/>");
+        assert_eq!(
+            xml_error(&arena.object(foreign)),
+            ErrorKind::InvalidValue(
+                "OMFOREIGN only as an argument of an OME or an attribute value"
+            )
+        );
+    }
+
+    /// The error `foo#bar` with a variable, a float and foreign content, each as given
+    struct Obj<'a> {
+        cd: &'a str,
+        var: &'a str,
+        float: f64,
+        foreign: &'a str,
+    }
+    impl OMSerializable for Obj<'_> {
+        fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+            use crate::{OMMaybeForeign, OpenMath};
+            OpenMath::OME {
+                cd: self.cd.into(),
+                name: "bar".into(),
+                cdbase: None,
+                arguments: vec![
+                    OMMaybeForeign::OM(OpenMath::OMV {
+                        name: self.var.into(),
+                        attributes: Vec::new(),
+                    }),
+                    OMMaybeForeign::OM(OpenMath::OMF {
+                        float: self.float.into(),
+                        attributes: Vec::new(),
+                    }),
+                    OMMaybeForeign::Foreign {
+                        encoding: None,
+                        value: self.foreign.into(),
+                    },
+                ],
+                attributes: Vec::new(),
+            }
+            .as_openmath(serializer)
+        }
+    }
+
+    /// Fails after `n` bytes
+    struct Full(usize);
+    impl std::io::Write for Full {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            if buf.len() > self.0 {
+                return Err(std::io::Error::other("disk full"));
+            }
+            self.0 -= buf.len();
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    impl std::fmt::Write for Full {
+        fn write_str(&mut self, s: &str) -> std::fmt::Result {
+            std::io::Write::write_all(self, s.as_bytes()).map_err(|_| std::fmt::Error)
+        }
+    }
+
+    /// Valid even in strict mode
+    const VALID: Obj = Obj {
+        cd: "foo",
+        var: "x",
+        float: 1.5,
+        foreign: "<b>bold</b> text <br/>",
+    };
+
+    #[test]
+    fn error_variants() {
+        let strict = |o: &Obj| {
+            OMObject(o).try_xml_with(XmlOptions {
+                insert_namespace: false,
+                version: None,
+                strict: true,
+                ..XmlOptions::default()
+            })
+        };
+        assert_eq!(
+            strict(&VALID).expect("is valid"),
+            r#"<OMOBJ><OME><OMS cd="foo" name="bar"/><OMV name="x"/><OMF dec="1.5"/><OMFOREIGN><b>bold</b> text <br/></OMFOREIGN></OME></OMOBJ>"#
+        );
+
+        // non-finite floats have a representation in the standard
+        for (float, dec) in [
+            (f64::INFINITY, r#"dec="INF""#),
+            (f64::NEG_INFINITY, r#"dec="-INF""#),
+            (f64::NAN, r#"dec="NaN""#),
+        ] {
+            let xml = strict(&Obj { float, ..VALID }).expect("is valid");
+            assert!(xml.contains(dec), "{xml}");
+        }
+
+        let e = strict(&Obj {
+            foreign: "<b>bold",
+            ..VALID
+        })
+        .expect_err("is unclosed");
+        assert!(matches!(e, XmlWriteError::InvalidForeignXml(_)), "{e}");
+        assert!(std::error::Error::source(&e).is_some());
+
+        let e = strict(&Obj { cd: "f o", ..VALID }).expect_err("invalid cd");
+        assert!(
+            matches!(&e, XmlWriteError::InvalidName { kind: OMKind::OMS, name } if name == "f o"),
+            "{e}"
+        );
+        let e = strict(&Obj { var: "", ..VALID }).expect_err("empty variable");
+        assert!(
+            matches!(
+                &e,
+                XmlWriteError::InvalidName {
+                    kind: OMKind::OMV,
+                    ..
                 }
-                is_first = false;
-                self.0.write_str(seq)?;
+            ),
+            "{e}"
+        );
+        assert_eq!(e.kind(), ErrorKind::InvalidValue("a valid name"));
+        let e = strict(&Obj { cd: "", ..VALID }).expect_err("empty cd");
+        assert!(
+            matches!(&e, XmlWriteError::InvalidName { kind: OMKind::OMS, name } if name.is_empty()),
+            "{e}"
+        );
+    }
+
+    #[test]
+    fn write_errors() {
+        let e = VALID.write_xml(false, Full(10)).expect_err("is full");
+        assert!(
+            matches!(&e, XmlWriteError::Io(io) if io.to_string() == "disk full"),
+            "{e}"
+        );
+        assert!(std::error::Error::source(&e).is_some());
+        assert!(VALID.write_xml(false, Full(1000)).is_ok());
+
+        let e = super::try_write(
+            &super::XmlDisplay {
+                pretty: false,
+                sort: false,
+                format: None,
+                o: &VALID,
+            },
+            &mut Full(10),
+        )
+        .expect_err("is full");
+        assert!(matches!(e, XmlWriteError::Fmt(_)), "{e}");
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn escaped_names() {
+        use crate::{Attr, BoundVariable, OMDeserializable, OMMaybeForeign, OpenMath, SymbolRef};
+        let names = [
+            "a&b",
+            "a\"b",
+            "a'b",
+            "a<b",
+            "a>b",
+            "a\u{1D538}b",
+            "&amp;<\"'>",
+            "a\nb\tc\r",
+        ];
+        for name in names {
+            let oms = |cd: &str| OpenMath::OMS {
+                cd: cd.to_string().into(),
+                name: name.into(),
+                cdbase: None,
+                cdbase_inherited: false,
+                attributes: Vec::new(),
+            };
+            let omv = || OpenMath::OMV {
+                name: name.into(),
+                attributes: Vec::new(),
+            };
+            let om = OpenMath::OMBIND {
+                binder: Box::new(oms("fns1")),
+                variables: vec![BoundVariable {
+                    name: name.into(),
+                    attributes: vec![Attr::new(
+                        SymbolRef {
+                            cdbase: None,
+                            cd: name.into(),
+                            name: name.into(),
+                        },
+                        OMMaybeForeign::OM(omv()),
+                    )],
+                }],
+                object: Box::new(OpenMath::OMA {
+                    applicant: Box::new(oms(name)),
+                    arguments: vec![omv()],
+                    attributes: Vec::new(),
+                }),
+                attributes: Vec::new(),
+            };
+            let xml = om.xml(false).to_string();
+            let mut events = quick_xml::Writer::new(Vec::new());
+            crate::ser::write_xml_events(&om, &mut events).expect("works");
+            let written = [
+                xml.clone(),
+                om.xml(true).to_string(),
+                String::from_utf8(events.into_inner()).expect("is UTF-8"),
+            ];
+            #[cfg_attr(not(feature = "serde"), allow(unused_mut))]
+            let mut read = written
+                .iter()
+                .map(|s| OpenMath::from_openmath_xml(s).expect("is valid"))
+                .collect::<Vec<_>>();
+            #[cfg(feature = "serde")]
+            let json = serde_json::to_string(&om.openmath_serde()).expect("works");
+            #[cfg(feature = "serde")]
+            read.push(
+                serde_json::from_str::<crate::de::OMFromSerde<OpenMath>>(&json)
+                    .map(crate::de::OMFromSerde::into_inner)
+                    .expect("is valid"),
+            );
+            // readers normalize literal tabs and line breaks in attributes to spaces
+            assert!(!xml.contains(['\t', '\n', '\r']), "{xml}");
+            assert_eq!(written[2], xml);
+            for back in &read {
+                assert_eq!(back, &read[0], "{name}");
+                assert_eq!(back.xml(false).to_string(), xml, "{name}");
             }
+            let OpenMath::OMBIND { variables, .. } = &read[0] else {
+                panic!("is an OMBIND")
+            };
+            assert_eq!(variables[0].name, name);
+            assert_eq!(variables[0].attributes[0].key.cd, name);
         }
-        Ok(())
     }
-    fn write_char(&mut self, c: char) -> std::fmt::Result {
-        match c {
-            '&' => self.0.write_str("&amp;"),
-            '<' => self.0.write_str("&lt;"),
-            _ => self.0.write_char(c),
+    /// A term with short and long arguments, nested applications, bound variables and an
+    /// attribution
+    fn wrapped_term() -> crate::OpenMath<'static> {
+        crate::OpenMath::from_sexpr(
+            "(ombind (oms fns1 lambda) (bvar x y (omattr (((oms sts type) (oms setname1 R))) z)) \
+             (oma (oms arith1 plus) (oma (oms arith1 times) (omv x) (omi 2)) (omi 1) (omi 22) (omi 333) \
+             (omstr \"a somewhat longer string argument\") (oma (oms arith1 power) (omv y) (omi 3)) (omv z)))",
+        )
+        .expect("is valid")
+    }
+
+    #[test]
+    fn max_width() {
+        #[cfg(feature = "xml-read")]
+        fn read(xml: &str) -> crate::OpenMath<'_> {
+            crate::de::OMObject::<crate::OpenMath>::from_openmath_xml(xml).expect("is valid")
+        }
+        let om = wrapped_term();
+        #[cfg(feature = "xml-read")]
+        let unwrapped = OMObject(&om).xml(true, true).to_string();
+        for (width, expected) in [
+            (
+                40,
+                r#"<OMOBJ version="2.0" xmlns="http://www.openmath.org/OpenMath">
+  <OMBIND>
+    <OMS cd="fns1" name="lambda"/>
+    <OMBVAR>
+      <OMV name="x"/> <OMV name="y"/>
+      <OMATTR>
+        <OMATP>
+          <OMS cd="sts" name="type"/>
+          <OMS cd="setname1" name="R"/>
+        </OMATP>
+        <OMV name="z"/>
+      </OMATTR>
+    </OMBVAR>
+    <OMA>
+      <OMS cd="arith1" name="plus"/>
+      <OMA>
+        <OMS cd="arith1" name="times"/>
+        <OMV name="x"/> <OMI>2</OMI>
+      </OMA>
+      <OMI>1</OMI> <OMI>22</OMI>
+      <OMI>333</OMI>
+      <OMSTR>a somewhat longer string argument</OMSTR>
+      <OMA>
+        <OMS cd="arith1" name="power"/>
+        <OMV name="y"/> <OMI>3</OMI>
+      </OMA>
+      <OMV name="z"/>
+    </OMA>
+  </OMBIND>
+</OMOBJ>"#,
+            ),
+            (
+                80,
+                r#"<OMOBJ version="2.0" xmlns="http://www.openmath.org/OpenMath">
+  <OMBIND>
+    <OMS cd="fns1" name="lambda"/>
+    <OMBVAR>
+      <OMV name="x"/> <OMV name="y"/>
+      <OMATTR>
+        <OMATP><OMS cd="sts" name="type"/><OMS cd="setname1" name="R"/></OMATP>
+        <OMV name="z"/>
+      </OMATTR>
+    </OMBVAR>
+    <OMA>
+      <OMS cd="arith1" name="plus"/>
+      <OMA><OMS cd="arith1" name="times"/><OMV name="x"/><OMI>2</OMI></OMA>
+      <OMI>1</OMI> <OMI>22</OMI> <OMI>333</OMI>
+      <OMSTR>a somewhat longer string argument</OMSTR>
+      <OMA><OMS cd="arith1" name="power"/><OMV name="y"/><OMI>3</OMI></OMA>
+      <OMV name="z"/>
+    </OMA>
+  </OMBIND>
+</OMOBJ>"#,
+            ),
+            (
+                120,
+                r#"<OMOBJ version="2.0" xmlns="http://www.openmath.org/OpenMath">
+  <OMBIND>
+    <OMS cd="fns1" name="lambda"/>
+    <OMBVAR>
+      <OMV name="x"/> <OMV name="y"/>
+      <OMATTR><OMATP><OMS cd="sts" name="type"/><OMS cd="setname1" name="R"/></OMATP><OMV name="z"/></OMATTR>
+    </OMBVAR>
+    <OMA>
+      <OMS cd="arith1" name="plus"/> <OMA><OMS cd="arith1" name="times"/><OMV name="x"/><OMI>2</OMI></OMA> <OMI>1</OMI>
+      <OMI>22</OMI> <OMI>333</OMI> <OMSTR>a somewhat longer string argument</OMSTR>
+      <OMA><OMS cd="arith1" name="power"/><OMV name="y"/><OMI>3</OMI></OMA> <OMV name="z"/>
+    </OMA>
+  </OMBIND>
+</OMOBJ>"#,
+            ),
+        ] {
+            let options = XmlOptions {
+                pretty: true,
+                max_width: Some(width),
+                ..XmlOptions::default()
+            };
+            let xml = OMObject(&om).xml_with(options).to_string();
+            assert_eq!(xml, expected, "{width}");
+            // only the start tag of the OMOBJ and the string are too long for a line of
+            // their own
+            assert!(
+                xml.lines().all(|l| l.chars().count() <= width
+                    || l.starts_with("<OMOBJ")
+                    || l.contains("OMSTR")),
+                "{width}"
+            );
+            #[cfg(feature = "xml-read")]
+            assert_eq!(read(&xml), read(&unwrapped), "{width}");
+        }
+    }
+
+    #[test]
+    fn oms_uri() {
+        use crate::{SymbolRef, ser::AsOMS};
+        struct Sym(SymbolRef<'static>, bool);
+        impl OMSerializable for Sym {
+            fn as_openmath<'s, S: OMSerializer<'s>>(&self, s: S) -> Result<S::Ok, S::Err> {
+                if self.1 {
+                    s.oms_uri(&self.0)
+                } else {
+                    self.0.as_oms().as_openmath(s)
+                }
+            }
+        }
+        struct In(Option<&'static str>, Sym, bool);
+        impl OMSerializable for In {
+            fn as_openmath<'s, S: OMSerializer<'s>>(&self, s: S) -> Result<S::Ok, S::Err> {
+                match (self.0, self.2) {
+                    (None, _) => self.1.as_openmath(s),
+                    // the cdbase is still pending when the OMS is written
+                    (Some(cdbase), false) => self.1.as_openmath(s.with_cdbase(cdbase)?),
+                    (Some(cdbase), true) => s.with_cdbase(cdbase)?.oma(
+                        Sym(SymbolRef::new("fns1", "lambda"), self.1.1),
+                        std::iter::once(&self.1),
+                    ),
+                }
+            }
+        }
+        let uris = [
+            "arith1#plus",
+            "http://www.openmath.org/cd?arith1#plus",
+            "http://www.openmath.org/cd/?arith1#plus",
+            "http://example.org/cd?arith1#plus",
+        ];
+        for uri in uris {
+            let uri: SymbolRef<'static> = uri.parse().expect("is valid");
+            for cdbase in [None, Some(crate::CD_BASE), Some("http://example.org/cd")] {
+                for nested in [false, true] {
+                    let xml = |direct| {
+                        In(cdbase, Sym(uri.clone(), direct), nested)
+                            .xml(false)
+                            .to_string()
+                    };
+                    assert_eq!(xml(true), xml(false), "{uri} in {cdbase:?}");
+                }
+            }
         }
     }
 }
@@ -0,0 +1,978 @@
+/*! A compact s-expression syntax for <span style="font-variant:small-caps;">OpenMath</span> objects.
+
+This is *not* an official <span style="font-variant:small-caps;">OpenMath</span> encoding; it is
+meant for unit tests, REPLs and debugging output, where XML or JSON literals are unwieldy.
+The syntax is stable:
+
+| Form | Meaning |
+|------|---------|
+| `(omi 42)` | [`OMI`](crate::OMKind::OMI) |
+| `(omf 1.5)` | [`OMF`](crate::OMKind::OMF) (also `NaN`, `inf`, `-inf`) |
+| `(omstr "text")` | [`OMSTR`](crate::OMKind::OMSTR) |
+| `(omb "SGVsbG8=")` | [`OMB`](crate::OMKind::OMB) (base64) |
+| `(omv x)` | [`OMV`](crate::OMKind::OMV) |
+| `(oms arith1 plus)` | [`OMS`](crate::OMKind::OMS) |
+| `(oma f a1 … an)` | [`OMA`](crate::OMKind::OMA) |
+| `(ombind b (bvar x y …) body)` | [`OMBIND`](crate::OMKind::OMBIND) |
+| `(ome (oms cd name) a1 … an)` | [`OME`](crate::OMKind::OME) |
+| `(omattr ((key value) …) obj)` | [`OMATTR`](crate::OMKind::OMATTR); keys are `(oms …)` forms |
+| `(omforeign "enc" "value")` | [`OMFOREIGN`](crate::OMKind::OMFOREIGN); the encoding is optional |
+| `(cdbase "uri" obj)` | sets the cdbase for `obj` and everything in it |
+
+Names (of variables, CDs and symbols) are written as bare symbols, or as string literals
+if they contain whitespace, parentheses, `"` or `;`, or would otherwise be read as numbers.
+Bound variables are names, `(omv x)` or `(omattr (…) x)`. String literals support the escapes
+`\"`, `\\`, `\n`, `\r`, `\t`, `\0` and `\u{…}`; `;` starts a comment that extends to the end
+of the line.
+
+Symbols parsed from s-expressions always carry the cdbase in scope (by default
+[`CD_BASE`](crate::CD_BASE)), just like [`OMS`](crate::OpenMath::OMS) values deserialized
+from the other encodings.
+
+# Examples
+```
+use openmath::OpenMath;
+
+let om = OpenMath::from_sexpr("(oma (oms arith1 plus) (omi 2) (omi 2))").expect("is valid");
+assert!(matches!(om, OpenMath::OMA { .. }));
+assert_eq!(om.to_sexpr(), "(oma (oms arith1 plus) (omi 2) (omi 2))");
+```
+*/
+
+use std::{borrow::Cow, fmt::Write};
+
+use either::Either;
+
+use crate::{
+    Attr, BoundVariable, Int, OMMaybeForeign, OMSerializable, OpenMath,
+    ser::{AsOMS, BindVar, OMAttr, OMOrForeign, OMSerializer},
+};
+
+/// Errors that can occur when parsing s-expressions; every variant carries the byte offset
+/// into the input at which the error occurred.
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum SexprError {
+    #[error("unexpected end of input at offset {0}")]
+    UnexpectedEof(usize),
+    #[error("unterminated string literal starting at offset {0}")]
+    UnterminatedString(usize),
+    #[error("invalid escape sequence at offset {0}")]
+    InvalidEscape(usize),
+    #[error("unexpected `)` at offset {0}")]
+    UnexpectedClose(usize),
+    #[error("expected {expected} at offset {offset}")]
+    Expected {
+        expected: &'static str,
+        offset: usize,
+    },
+    #[error("unknown form `{name}` at offset {offset}")]
+    UnknownForm { name: String, offset: usize },
+    #[error("invalid integer at offset {0}")]
+    InvalidInt(usize),
+    #[error("invalid float at offset {0}")]
+    InvalidFloat(usize),
+    #[error("invalid base64 at offset {0}: {1}")]
+    Base64(usize, crate::base64::Error),
+    #[error("unexpected trailing input at offset {0}")]
+    TrailingInput(usize),
+}
+impl SexprError {
+    /// The byte offset into the input at which this error occurred
+    #[must_use]
+    pub const fn offset(&self) -> usize {
+        match self {
+            Self::UnexpectedEof(o)
+            | Self::UnterminatedString(o)
+            | Self::InvalidEscape(o)
+            | Self::UnexpectedClose(o)
+            | Self::InvalidInt(o)
+            | Self::InvalidFloat(o)
+            | Self::Base64(o, _)
+            | Self::TrailingInput(o)
+            | Self::Expected { offset: o, .. }
+            | Self::UnknownForm { offset: o, .. } => *o,
+        }
+    }
+}
+
+impl OpenMath<'_> {
+    /// Returns the s-expression representation of this object; see the [module
+    /// documentation](crate::sexpr) for the syntax.
+    #[must_use]
+    pub fn to_sexpr(&self) -> String {
+        Sexpr(self).to_string()
+    }
+
+    /// Parses an s-expression; see the [module documentation](crate::sexpr) for the syntax.
+    ///
+    /// # Errors
+    /// if the input is not a valid s-expression representing an
+    /// <span style="font-variant:small-caps;">OpenMath</span> object
+    pub fn from_sexpr(input: &str) -> Result<OpenMath<'static>, SexprError> {
+        let mut parser = Parser {
+            lexer: Lexer { src: input, pos: 0 },
+        };
+        let om = parser.object(crate::CD_BASE)?;
+        match parser.lexer.next()? {
+            None => Ok(om),
+            Some((_, offset)) => Err(SexprError::TrailingInput(offset)),
+        }
+    }
+}
+
+/// Wrapper that [`Display`](std::fmt::Display)s any [`OMSerializable`] as an s-expression;
+/// see the [module documentation](crate::sexpr) for the syntax.
+pub struct Sexpr<'o, O: OMSerializable + ?Sized>(pub &'o O);
+impl<O: OMSerializable + ?Sized> std::fmt::Display for Sexpr<'_, O> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0
+            .as_openmath(SexprSerializer {
+                w: f,
+                next_ns: self.0.cdbase().map(Cow::Borrowed),
+                current_ns: Cow::Borrowed(crate::CD_BASE),
+            })
+            .map_err(|_| std::fmt::Error)
+    }
+}
+
+// ---------------------------------------------------------------------------------------
+// writing
+
+struct WriteErr;
+impl From<std::fmt::Error> for WriteErr {
+    #[inline]
+    fn from(_: std::fmt::Error) -> Self {
+        Self
+    }
+}
+impl crate::ser::Error for WriteErr {
+    #[inline]
+    fn custom(_: impl std::fmt::Display) -> Self {
+        Self
+    }
+}
+
+struct SexprSerializer<'s, 'f: 's> {
+    w: &'s mut std::fmt::Formatter<'f>,
+    next_ns: Option<Cow<'s, str>>,
+    current_ns: Cow<'s, str>,
+}
+impl<'f> SexprSerializer<'_, 'f> {
+    #[inline]
+    fn clone(&mut self) -> SexprSerializer<'_, 'f> {
+        SexprSerializer {
+            w: self.w,
+            next_ns: None,
+            current_ns: Cow::Borrowed(&self.current_ns),
+        }
+    }
+
+    /// writes the opening `(cdbase "…" ` if necessary and returns whether a closing `)`
+    /// is needed
+    fn open_cdbase(&mut self) -> Result<bool, WriteErr> {
+        let Some(ns) = self.next_ns.take() else {
+            return Ok(false);
+        };
+        self.w.write_str("(cdbase ")?;
+        write_string(self.w, &ns)?;
+        self.current_ns = ns;
+        self.w.write_char(' ')?;
+        Ok(true)
+    }
+
+    fn close_cdbase(&mut self, close: bool) -> Result<(), WriteErr> {
+        if close {
+            self.w.write_char(')')?;
+        }
+        Ok(())
+    }
+
+    fn leaf(mut self, f: impl FnOnce(&mut Self) -> Result<(), WriteErr>) -> Result<(), WriteErr> {
+        let close = self.open_cdbase()?;
+        f(&mut self)?;
+        self.close_cdbase(close)
+    }
+
+    fn foreign(&mut self, o: impl OMOrForeign) -> Result<(), WriteErr> {
+        match o.om_or_foreign() {
+            Either::Left(o) => o.as_openmath(self.clone()),
+            Either::Right((encoding, value)) => {
+                self.w.write_str("(omforeign ")?;
+                if let Some(encoding) = encoding {
+                    write_string(self.w, encoding)?;
+                    self.w.write_char(' ')?;
+                }
+                write_string(self.w, value)?;
+                self.w.write_char(')')?;
+                Ok(())
+            }
+        }
+    }
+
+    fn attrs(&mut self, attrs: impl ExactSizeIterator<Item: OMAttr>) -> Result<(), WriteErr> {
+        self.w.write_char('(')?;
+        let mut first = true;
+        for a in attrs {
+            if !first {
+                self.w.write_char(' ')?;
+            }
+            first = false;
+            self.w.write_char('(')?;
+            a.symbol().as_oms().as_openmath(self.clone())?;
+            self.w.write_char(' ')?;
+            self.foreign(a.value())?;
+            self.w.write_char(')')?;
+        }
+        self.w.write_char(')')?;
+        Ok(())
+    }
+}
+
+impl<'s, 'f> OMSerializer<'s> for SexprSerializer<'s, 'f> {
+    type Ok = ();
+    type Err = WriteErr;
+    type SubSerializer<'ns>
+        = SexprSerializer<'ns, 'f>
+    where
+        's: 'ns;
+    #[inline]
+    fn current_cdbase(&self) -> &str {
+        self.next_ns.as_deref().unwrap_or(&self.current_ns)
+    }
+    fn with_cdbase<'ns>(
+        self,
+        cdbase: impl Into<Cow<'ns, str>>,
+    ) -> Result<Self::SubSerializer<'ns>, Self::Err>
+    where
+        's: 'ns,
+    {
+        let cdbase = cdbase.into();
+        if crate::cdbase::eq_normalized(&self.current_ns, &cdbase) {
+            Ok(Self {
+                next_ns: None,
+                ..self
+            })
+        } else {
+            Ok(SexprSerializer {
+                w: self.w,
+                next_ns: Some(cdbase),
+                current_ns: self.current_ns,
+            })
+        }
+    }
+    fn omi(self, value: &Int) -> Result<Self::Ok, Self::Err> {
+        self.leaf(|s| Ok(write!(s.w, "(omi {value})")?))
+    }
+    fn omf(self, value: f64) -> Result<Self::Ok, Self::Err> {
+        self.leaf(|s| Ok(write!(s.w, "(omf {value})")?))
+    }
+    fn omstr(self, string: impl std::fmt::Display) -> Result<Self::Ok, Self::Err> {
+        self.leaf(|s| {
+            s.w.write_str("(omstr ")?;
+            write_string(s.w, string)?;
+            Ok(s.w.write_char(')')?)
+        })
+    }
+    fn omb(self, bytes: impl ExactSizeIterator<Item = u8>) -> Result<Self::Ok, Self::Err> {
+        use crate::base64::Base64Encodable;
+        self.leaf(|s| {
+            s.w.write_str("(omb \"")?;
+            for c in bytes.base64().chars() {
+                s.w.write_char(c)?;
+            }
+            Ok(s.w.write_str("\")")?)
+        })
+    }
+    fn omv(self, name: impl std::fmt::Display) -> Result<Self::Ok, Self::Err> {
+        self.leaf(|s| {
+            s.w.write_str("(omv ")?;
+            write_name(s.w, name)?;
+            Ok(s.w.write_char(')')?)
+        })
+    }
+    fn oms(
+        self,
+        cd_name: impl std::fmt::Display,
+        name: impl std::fmt::Display,
+    ) -> Result<Self::Ok, Self::Err> {
+        self.leaf(|s| {
+            s.w.write_str("(oms ")?;
+            write_name(s.w, cd_name)?;
+            s.w.write_char(' ')?;
+            write_name(s.w, name)?;
+            Ok(s.w.write_char(')')?)
+        })
+    }
+    fn oma(
+        self,
+        head: impl OMSerializable,
+        args: impl ExactSizeIterator<Item: OMSerializable>,
+    ) -> Result<Self::Ok, Self::Err> {
+        self.leaf(|s| {
+            s.w.write_str("(oma ")?;
+            head.as_openmath(s.clone())?;
+            for a in args {
+                s.w.write_char(' ')?;
+                a.as_openmath(s.clone())?;
+            }
+            Ok(s.w.write_char(')')?)
+        })
+    }
+    fn ome(
+        self,
+        error: impl AsOMS,
+        args: impl ExactSizeIterator<Item: OMOrForeign>,
+    ) -> Result<Self::Ok, Self::Err> {
+        self.leaf(|s| {
+            s.w.write_str("(ome ")?;
+            error.as_oms().as_openmath(s.clone())?;
+            for a in args {
+                s.w.write_char(' ')?;
+                s.foreign(a)?;
+            }
+            Ok(s.w.write_char(')')?)
+        })
+    }
+    fn omattr(
+        mut self,
+        attrs: impl ExactSizeIterator<Item: OMAttr>,
+        atp: impl OMSerializable,
+    ) -> Result<Self::Ok, Self::Err> {
+        let mut attrs = attrs.peekable();
+        if attrs.peek().is_none() {
+            return atp.as_openmath(self);
+        }
+        let close = self.open_cdbase()?;
+        self.w.write_str("(omattr ")?;
+        self.attrs(attrs)?;
+        self.w.write_char(' ')?;
+        atp.as_openmath(self.clone())?;
+        self.w.write_char(')')?;
+        self.close_cdbase(close)
+    }
+    fn ombind(
+        self,
+        head: impl OMSerializable,
+        vars: impl ExactSizeIterator<Item: BindVar>,
+        body: impl OMSerializable,
+    ) -> Result<Self::Ok, Self::Err> {
+        self.leaf(|s| {
+            s.w.write_str("(ombind ")?;
+            head.as_openmath(s.clone())?;
+            s.w.write_str(" (bvar")?;
+            for v in vars {
+                s.w.write_char(' ')?;
+                let mut attrs = v.attrs().peekable();
+                if attrs.peek().is_none() {
+                    write_name(s.w, v.name())?;
+                } else {
+                    s.w.write_str("(omattr ")?;
+                    s.attrs(attrs)?;
+                    s.w.write_char(' ')?;
+                    write_name(s.w, v.name())?;
+                    s.w.write_char(')')?;
+                }
+            }
+            s.w.write_str(") ")?;
+            body.as_openmath(s.clone())?;
+            Ok(s.w.write_char(')')?)
+        })
+    }
+}
+
+const fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '(' | ')' | '"' | ';')
+}
+
+fn write_string(w: &mut std::fmt::Formatter<'_>, s: impl std::fmt::Display) -> std::fmt::Result {
+    struct Escaper<'a, 'f>(&'a mut std::fmt::Formatter<'f>);
+    impl Write for Escaper<'_, '_> {
+        fn write_str(&mut self, s: &str) -> std::fmt::Result {
+            for c in s.chars() {
+                self.write_char(c)?;
+            }
+            Ok(())
+        }
+        fn write_char(&mut self, c: char) -> std::fmt::Result {
+            match c {
+                '"' => self.0.write_str("\\\""),
+                '\\' => self.0.write_str("\\\\"),
+                '\n' => self.0.write_str("\\n"),
+                '\r' => self.0.write_str("\\r"),
+                '\t' => self.0.write_str("\\t"),
+                '\0' => self.0.write_str("\\0"),
+                c if c.is_control() => write!(self.0, "\\u{{{:x}}}", c as u32),
+                c => self.0.write_char(c),
+            }
+        }
+    }
+    w.write_char('"')?;
+    write!(Escaper(w), "{s}")?;
+    w.write_char('"')
+}
+
+fn write_name(w: &mut std::fmt::Formatter<'_>, name: impl std::fmt::Display) -> std::fmt::Result {
+    let name = name.to_string();
+    let bare = name
+        .chars()
+        .next()
+        .is_some_and(|c| !c.is_ascii_digit() && !matches!(c, '+' | '-' | '.'))
+        && !name.chars().any(|c| is_delimiter(c) || c.is_control());
+    if bare {
+        w.write_str(&name)
+    } else {
+        write_string(w, name)
+    }
+}
+
+// ---------------------------------------------------------------------------------------
+// reading
+
+enum Token<'a> {
+    Open,
+    Close,
+    Str(String),
+    Atom(&'a str),
+}
+
+struct Lexer<'a> {
+    src: &'a str,
+    pos: usize,
+}
+impl<'a> Lexer<'a> {
+    fn skip_trivia(&mut self) {
+        loop {
+            let rest = &self.src[self.pos..];
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            if trimmed.starts_with(';') {
+                self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn peek(&mut self) -> Result<Option<(Token<'a>, usize)>, SexprError> {
+        let pos = self.pos;
+        let r = self.next();
+        self.pos = pos;
+        r
+    }
+
+    fn next(&mut self) -> Result<Option<(Token<'a>, usize)>, SexprError> {
+        self.skip_trivia();
+        let start = self.pos;
+        let Some(c) = self.src[start..].chars().next() else {
+            return Ok(None);
+        };
+        let token = match c {
+            '(' => {
+                self.pos += 1;
+                Token::Open
+            }
+            ')' => {
+                self.pos += 1;
+                Token::Close
+            }
+            '"' => {
+                self.pos += 1;
+                Token::Str(self.string(start)?)
+            }
+            _ => {
+                let rest = &self.src[start..];
+                let len = rest.find(is_delimiter).unwrap_or(rest.len());
+                self.pos += len;
+                Token::Atom(&rest[..len])
+            }
+        };
+        Ok(Some((token, start)))
+    }
+
+    fn string(&mut self, start: usize) -> Result<String, SexprError> {
+        let mut ret = String::new();
+        let mut chars = self.src[self.pos..].char_indices();
+        let base = self.pos;
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos = base + i + 1;
+                    return Ok(ret);
+                }
+                '\\' => {
+                    let esc = base + i;
+                    let c = match chars.next() {
+                        None => return Err(SexprError::UnterminatedString(start)),
+                        Some((_, '"')) => '"',
+                        Some((_, '\\')) => '\\',
+                        Some((_, 'n')) => '\n',
+                        Some((_, 'r')) => '\r',
+                        Some((_, 't')) => '\t',
+                        Some((_, '0')) => '\0',
+                        Some((j, 'u')) => {
+                            let after = &self.src[base + j + 1..];
+                            let Some(hex) = after
+                                .strip_prefix('{')
+                                .and_then(|r| r.split_once('}'))
+                                .map(|(h, _)| h)
+                            else {
+                                return Err(SexprError::InvalidEscape(esc));
+                            };
+                            let c = u32::from_str_radix(hex, 16)
+                                .ok()
+                                .and_then(char::from_u32)
+                                .ok_or(SexprError::InvalidEscape(esc))?;
+                            // skip `{hex}`
+                            for _ in 0..hex.len() + 2 {
+                                chars.next();
+                            }
+                            c
+                        }
+                        Some(_) => return Err(SexprError::InvalidEscape(esc)),
+                    };
+                    ret.push(c);
+                }
+                c => ret.push(c),
+            }
+        }
+        Err(SexprError::UnterminatedString(start))
+    }
+}
+
+struct Parser<'a> {
+    lexer: Lexer<'a>,
+}
+impl<'a> Parser<'a> {
+    fn next(&mut self) -> Result<(Token<'a>, usize), SexprError> {
+        self.lexer
+            .next()?
+            .ok_or(SexprError::UnexpectedEof(self.lexer.pos))
+    }
+
+    fn open(&mut self) -> Result<usize, SexprError> {
+        match self.next()? {
+            (Token::Open, offset) => Ok(offset),
+            (Token::Close, offset) => Err(SexprError::UnexpectedClose(offset)),
+            (_, offset) => Err(SexprError::Expected {
+                expected: "`(`",
+                offset,
+            }),
+        }
+    }
+
+    fn close(&mut self) -> Result<(), SexprError> {
+        match self.next()? {
+            (Token::Close, _) => Ok(()),
+            (_, offset) => Err(SexprError::Expected {
+                expected: "`)`",
+                offset,
+            }),
+        }
+    }
+
+    /// returns `true` (and consumes the token) if the next token is `)`
+    fn at_close(&mut self) -> Result<bool, SexprError> {
+        match self.lexer.peek()? {
+            Some((Token::Close, _)) => {
+                self.next()?;
+                Ok(true)
+            }
+            Some(_) => Ok(false),
+            None => Err(SexprError::UnexpectedEof(self.lexer.pos)),
+        }
+    }
+
+    fn head(&mut self) -> Result<(&'a str, usize), SexprError> {
+        match self.next()? {
+            (Token::Atom(a), offset) => Ok((a, offset)),
+            (_, offset) => Err(SexprError::Expected {
+                expected: "a form name",
+                offset,
+            }),
+        }
+    }
+
+    fn string(&mut self) -> Result<(String, usize), SexprError> {
+        match self.next()? {
+            (Token::Str(s), offset) => Ok((s, offset)),
+            (_, offset) => Err(SexprError::Expected {
+                expected: "a string literal",
+                offset,
+            }),
+        }
+    }
+
+    fn atom(&mut self, expected: &'static str) -> Result<(&'a str, usize), SexprError> {
+        match self.next()? {
+            (Token::Atom(a), offset) => Ok((a, offset)),
+            (_, offset) => Err(SexprError::Expected { expected, offset }),
+        }
+    }
+
+    fn name(&mut self) -> Result<Cow<'static, str>, SexprError> {
+        match self.next()? {
+            (Token::Atom(a), _) => Ok(Cow::Owned(a.to_string())),
+            (Token::Str(s), _) => Ok(Cow::Owned(s)),
+            (_, offset) => Err(SexprError::Expected {
+                expected: "a name",
+                offset,
+            }),
+        }
+    }
+
+    #[allow(clippy::too_many_lines)]
+    fn object(&mut self, cdbase: &str) -> Result<OpenMath<'static>, SexprError> {
+        let offset = self.open()?;
+        let (head, head_offset) = self.head()?;
+        let om = match head {
+            "omi" => {
+                let (i, offset) = self.atom("an integer")?;
+                let int = Int::new(i)
+                    .map(Int::into_owned)
+                    .ok_or(SexprError::InvalidInt(offset))?;
+                OpenMath::OMI {
+                    int,
+                    attributes: Vec::new(),
+                }
+            }
+            "omf" => {
+                let (f, offset) = self.atom("a float")?;
+                let float = f
+                    .parse::<f64>()
+                    .map_err(|_| SexprError::InvalidFloat(offset))?;
+                OpenMath::OMF {
+                    float: float.into(),
+                    attributes: Vec::new(),
+                }
+            }
+            "omstr" => OpenMath::OMSTR {
+                string: Cow::Owned(self.string()?.0),
+                attributes: Vec::new(),
+            },
+            "omb" => {
+                use crate::base64::Base64Decodable;
+                let (s, offset) = self.string()?;
+                let bytes: Result<Vec<u8>, _> = s.bytes().decode_base64().flat().collect();
+                OpenMath::OMB {
+                    bytes: Cow::Owned(bytes.map_err(|e| SexprError::Base64(offset, e))?),
+                    attributes: Vec::new(),
+                }
+            }
+            "omv" => OpenMath::OMV {
+                name: self.name()?,
+                attributes: Vec::new(),
+            },
+            "oms" => OpenMath::OMS {
+                cd: self.name()?,
+                name: self.name()?,
+                cdbase: Some(Cow::Owned(cdbase.to_string())),
+                cdbase_inherited: true,
+                attributes: Vec::new(),
+            },
+            "oma" => {
+                let applicant = Box::new(self.object(cdbase)?);
+                let mut arguments = Vec::new();
+                while !self.at_close()? {
+                    arguments.push(self.object(cdbase)?);
+                }
+                return Ok(OpenMath::OMA {
+                    applicant,
+                    arguments,
+                    attributes: Vec::new(),
+                });
+            }
+            "ome" => {
+                let (cdbase_o, cd, name) = self.symbol(cdbase)?;
+                let mut arguments = Vec::new();
+                while !self.at_close()? {
+                    arguments.push(self.maybe_foreign(cdbase)?);
+                }
+                return Ok(OpenMath::OME {
+                    cd,
+                    name,
+                    cdbase: cdbase_o,
+                    arguments,
+                    attributes: Vec::new(),
+                });
+            }
+            "ombind" => {
+                let binder = Box::new(self.object(cdbase)?);
+                self.open()?;
+                let (bvar, offset) = self.head()?;
+                if bvar != "bvar" {
+                    return Err(SexprError::Expected {
+                        expected: "`(bvar …)`",
+                        offset,
+                    });
+                }
+                let mut variables = Vec::new();
+                while !self.at_close()? {
+                    variables.push(self.variable(cdbase)?);
+                }
+                OpenMath::OMBIND {
+                    binder,
+                    variables,
+                    object: Box::new(self.object(cdbase)?),
+                    attributes: Vec::new(),
+                }
+            }
+            "omattr" => {
+                let mut attrs = self.attributes(cdbase)?;
+                let mut om = self.object(cdbase)?;
+                let (OpenMath::OMI { attributes, .. }
+                | OpenMath::OMF { attributes, .. }
+                | OpenMath::OMSTR { attributes, .. }
+                | OpenMath::OMB { attributes, .. }
+                | OpenMath::OMV { attributes, .. }
+                | OpenMath::OMS { attributes, .. }
+                | OpenMath::OMA { attributes, .. }
+                | OpenMath::OME { attributes, .. }
+                | OpenMath::OMBIND { attributes, .. }) = &mut om;
+                attrs.append(attributes);
+                *attributes = attrs;
+                om
+            }
+            "cdbase" => {
+                let (cdbase, _) = self.string()?;
+                let mut om = self.object(&cdbase)?;
+                // a cdbase directly on a symbol is its own
+                if let OpenMath::OMS {
+                    cdbase_inherited, ..
+                } = &mut om
+                {
+                    *cdbase_inherited = false;
+                }
+                om
+            }
+            "omforeign" => {
+                return Err(SexprError::Expected {
+                    expected: "an OpenMath object",
+                    offset,
+                });
+            }
+            _ => {
+                return Err(SexprError::UnknownForm {
+                    name: head.to_string(),
+                    offset: head_offset,
+                });
+            }
+        };
+        self.close()?;
+        Ok(om)
+    }
+
+    /// parses a `(oms …)` form (possibly wrapped in `(cdbase …)`)
+    #[allow(clippy::type_complexity)]
+    fn symbol(
+        &mut self,
+        cdbase: &str,
+    ) -> Result<
+        (
+            Option<Cow<'static, str>>,
+            Cow<'static, str>,
+            Cow<'static, str>,
+        ),
+        SexprError,
+    > {
+        self.lexer.skip_trivia();
+        let offset = self.lexer.pos;
+        match self.object(cdbase)? {
+            OpenMath::OMS {
+                cd,
+                name,
+                cdbase,
+                attributes,
+                ..
+            } if attributes.is_empty() => Ok((cdbase, cd, name)),
+            _ => Err(SexprError::Expected {
+                expected: "an `(oms …)` form",
+                offset,
+            }),
+        }
+    }
+
+    fn maybe_foreign(
+        &mut self,
+        cdbase: &str,
+    ) -> Result<OMMaybeForeign<'static, OpenMath<'static>>, SexprError> {
+        let pos = self.lexer.pos;
+        self.open()?;
+        let (head, _) = self.head()?;
+        if head != "omforeign" {
+            self.lexer.pos = pos;
+            return self.object(cdbase).map(OMMaybeForeign::OM);
+        }
+        let (first, _) = self.string()?;
+        let (encoding, value) = if self.at_close()? {
+            (None, first)
+        } else {
+            let (value, _) = self.string()?;
+            self.close()?;
+            (Some(Cow::Owned(first)), value)
+        };
+        Ok(OMMaybeForeign::Foreign {
+            encoding,
+            value: Cow::Owned(value),
+        })
+    }
+
+    fn attributes(
+        &mut self,
+        cdbase: &str,
+    ) -> Result<Vec<Attr<'static, OMMaybeForeign<'static, OpenMath<'static>>>>, SexprError> {
+        self.open()?;
+        let mut attrs = Vec::new();
+        while !self.at_close()? {
+            self.open()?;
+            let (cdbase_o, cd, name) = self.symbol(cdbase)?;
+            let value = self.maybe_foreign(cdbase)?;
+            self.close()?;
+            attrs.push(Attr {
+                key: crate::SymbolRef {
+                    cdbase: cdbase_o,
+                    cd,
+                    name,
+                },
+                value,
+            });
+        }
+        Ok(attrs)
+    }
+
+    fn variable(&mut self, cdbase: &str) -> Result<BoundVariable<'static>, SexprError> {
+        if !matches!(self.lexer.peek()?, Some((Token::Open, _))) {
+            return Ok(BoundVariable {
+                name: self.name()?,
+                attributes: Vec::new(),
+            });
+        }
+        self.open()?;
+        let (head, offset) = self.head()?;
+        let var = match head {
+            "omv" => BoundVariable {
+                name: self.name()?,
+                attributes: Vec::new(),
+            },
+            "omattr" => {
+                let mut attributes = self.attributes(cdbase)?;
+                let mut var = self.variable(cdbase)?;
+                attributes.append(&mut var.attributes);
+                var.attributes = attributes;
+                var
+            }
+            _ => {
+                return Err(SexprError::Expected {
+                    expected: "a variable",
+                    offset,
+                });
+            }
+        };
+        self.close()?;
+        Ok(var)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(s: &str) -> OpenMath<'static> {
+        let om = OpenMath::from_sexpr(s).expect("is valid");
+        assert_eq!(om.to_sexpr(), s);
+        om
+    }
+
+    #[test]
+    fn sexpr_roundtrip() {
+        let om = roundtrip("(oma (oms arith1 plus) (omi 2) (omf 2.5))");
+        assert!(matches!(om, OpenMath::OMA { ref arguments, .. } if arguments.len() == 2));
+        roundtrip("(omi -123456789012345678901234567890)");
+        roundtrip(r#"(omstr "a \"quoted\"\nstring \\ ")"#);
+        roundtrip(r#"(omb "SGVsbG8=")"#);
+        roundtrip(r#"(omstr "bell: \u{7}")"#);
+        roundtrip(r#"(omv "with space")"#);
+        roundtrip(r#"(omv "42")"#);
+        roundtrip("(ombind (oms fns1 lambda) (bvar x y) (oma (omv x) (omv y)))");
+        roundtrip("(ombind (oms fns1 lambda) (bvar) (omi 1))");
+        roundtrip(
+            "(ombind (oms fns1 lambda) (bvar (omattr (((oms sts type) (oms setname1 R))) x)) (omv x))",
+        );
+        roundtrip(
+            r#"(omattr (((oms sts type) (oms setname1 Z)) ((oms my note) (omforeign "text/plain" "hi"))) (omi 1))"#,
+        );
+        roundtrip(r#"(ome (oms error unhandled_symbol) (omstr "foo") (omforeign "bar"))"#);
+        roundtrip(
+            r#"(oma (oms arith1 plus) (cdbase "http://example.org" (oms utils either)) (omi 1))"#,
+        );
+    }
+
+    #[test]
+    fn sexpr_cdbase() {
+        // the cdbase is stored on the symbols, but inherited ones are written where they were
+        let om = roundtrip(
+            r#"(cdbase "http://example.org" (oma (oms utils either) (oms arith1 plus)))"#,
+        );
+        assert!(matches!(om, OpenMath::OMA { ref applicant, .. }
+            if matches!(&**applicant, OpenMath::OMS { cdbase: Some(c), .. } if c == "http://example.org")));
+
+        let om = OpenMath::from_sexpr(r#"(cdbase "http://example.org" (oms cd name))"#)
+            .expect("is valid");
+        assert!(matches!(om, OpenMath::OMS { cdbase: Some(c), .. } if c == "http://example.org"));
+        let om = OpenMath::from_sexpr("(oms cd name)").expect("is valid");
+        assert!(matches!(om, OpenMath::OMS { cdbase: Some(c), .. } if c == crate::CD_BASE));
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn sexpr_equivalent_to_xml() {
+        use crate::OMDeserializable;
+        let xml = r#"<OMBIND>
+            <OMS cd="fns1" name="lambda"/>
+            <OMBVAR><OMV name="x"/></OMBVAR>
+            <OMATTR>
+              <OMATP><OMS cd="sts" name="type"/><OMFOREIGN encoding="enc">foo</OMFOREIGN></OMATP>
+              <OMA><OMS cd="arith1" name="plus"/><OMV name="x"/><OMF dec="1.5"/></OMA>
+            </OMATTR>
+          </OMBIND>"#;
+        let from_xml = OpenMath::from_openmath_xml(xml).expect("is valid");
+        let from_sexpr = OpenMath::from_sexpr(&from_xml.to_sexpr()).expect("is valid");
+        assert_eq!(from_xml.to_sexpr(), from_sexpr.to_sexpr());
+    }
+
+    #[test]
+    fn sexpr_comments_and_whitespace() {
+        let om = OpenMath::from_sexpr(
+            "  ; two plus two\n (oma\n\t(oms arith1 plus) ; the head\n (omi 2) (omi 2)) ",
+        )
+        .expect("is valid");
+        assert_eq!(om.to_sexpr(), "(oma (oms arith1 plus) (omi 2) (omi 2))");
+    }
+
+    #[test]
+    fn sexpr_errors() {
+        fn err(s: &str) -> SexprError {
+            OpenMath::from_sexpr(s).expect_err("is invalid")
+        }
+        assert!(matches!(err("(omi 2"), SexprError::UnexpectedEof(6)));
+        assert!(matches!(err("(omi x)"), SexprError::InvalidInt(5)));
+        assert!(matches!(err("(omf x)"), SexprError::InvalidFloat(5)));
+        assert!(matches!(
+            err(r#"(omstr "abc)"#),
+            SexprError::UnterminatedString(7)
+        ));
+        assert!(matches!(
+            err(r#"(omstr "a\qc")"#),
+            SexprError::InvalidEscape(9)
+        ));
+        assert!(matches!(err("(omi 2))"), SexprError::TrailingInput(7)));
+        assert!(matches!(err(")"), SexprError::UnexpectedClose(0)));
+        let e = err("(oma (foo 1))");
+        assert!(matches!(&e, SexprError::UnknownForm { name, offset: 6 } if name == "foo"));
+        assert_eq!(e.offset(), 6);
+        assert!(matches!(
+            err("(ome (omi 1))"),
+            SexprError::Expected { offset: 5, .. }
+        ));
+        assert!(matches!(err(r#"(omb "a")"#), SexprError::Base64(5, _)));
+    }
+}
@@ -0,0 +1,654 @@
+//! Declaring and checking the expected shape of an object; see [`Shape`]
+
+use std::fmt::Write;
+
+use crate::{Attr, OMKind, OMMaybeForeign, OpenMath, Path, Step};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Repr {
+    Any,
+    Kind(OMKind),
+    Symbol {
+        cdbase: Option<String>,
+        cd: String,
+        name: String,
+    },
+    Oma {
+        head: Box<Shape>,
+        args: Vec<Shape>,
+    },
+    Ome {
+        cd: String,
+        name: String,
+        args: Vec<Shape>,
+    },
+    Bind {
+        binder: Box<Shape>,
+        body: Box<Shape>,
+    },
+    OneOf(Vec<Shape>),
+    Many(Box<Shape>),
+    Attributed {
+        shape: Box<Shape>,
+        attrs: Vec<AttrShape>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct AttrShape {
+    cd: String,
+    name: String,
+    value: Shape,
+    required: bool,
+}
+
+/** A description of the expected shape of an [`OpenMath`] object, for validating input with
+readable errors; see [`check`](Shape::check).
+
+Shapes are built from leaf kinds ([`int`](Shape::int), [`symbol`](Shape::symbol), …),
+applications, errors and bindings, [alternatives](Shape::one_of), and
+[repeated tails](Shape::many) of argument lists. Attributes not mentioned in a shape are
+ignored; see [`with_attr`](Shape::with_attr).
+
+# Examples
+```
+use openmath::{OpenMath, Shape};
+
+let shape = Shape::oma(Shape::symbol("arith1", "plus"), [Shape::int(), Shape::int()]);
+let om = OpenMath::from_sexpr("(oma (oms arith1 plus) (omi 1) (omi 2))").expect("is valid");
+assert!(shape.check(&om).is_ok());
+
+let om = OpenMath::from_sexpr(r#"(oma (oms arith1 plus) (omi 1) (omstr "two"))"#).expect("is valid");
+let err = shape.check(&om).expect_err("is a string");
+assert_eq!(err.to_string(), r#"at /1: expected OMI, found OMSTR "two""#);
+```
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shape(Repr);
+
+impl Shape {
+    /// Matches every object
+    #[inline]
+    #[must_use]
+    pub const fn any() -> Self {
+        Self(Repr::Any)
+    }
+
+    /// Matches every [`OMI`](OpenMath::OMI)
+    #[inline]
+    #[must_use]
+    pub const fn int() -> Self {
+        Self(Repr::Kind(OMKind::OMI))
+    }
+
+    /// Matches every [`OMF`](OpenMath::OMF)
+    #[inline]
+    #[must_use]
+    pub const fn float() -> Self {
+        Self(Repr::Kind(OMKind::OMF))
+    }
+
+    /// Matches every [`OMSTR`](OpenMath::OMSTR)
+    #[inline]
+    #[must_use]
+    pub const fn string() -> Self {
+        Self(Repr::Kind(OMKind::OMSTR))
+    }
+
+    /// Matches every [`OMB`](OpenMath::OMB)
+    #[inline]
+    #[must_use]
+    pub const fn bytes() -> Self {
+        Self(Repr::Kind(OMKind::OMB))
+    }
+
+    /// Matches every [`OMV`](OpenMath::OMV)
+    #[inline]
+    #[must_use]
+    pub const fn var() -> Self {
+        Self(Repr::Kind(OMKind::OMV))
+    }
+
+    /// Matches the [`OMS`](OpenMath::OMS) `cd#name`, regardless of its cdbase
+    #[must_use]
+    pub fn symbol(cd: impl Into<String>, name: impl Into<String>) -> Self {
+        Self(Repr::Symbol {
+            cdbase: None,
+            cd: cd.into(),
+            name: name.into(),
+        })
+    }
+
+    /// Matches the [`OMS`](OpenMath::OMS) `cdbase/cd#name`; symbols without a cdbase are
+    /// assumed to be in [`CD_BASE`](crate::CD_BASE).
+    #[must_use]
+    pub fn symbol_in(
+        cdbase: impl Into<String>,
+        cd: impl Into<String>,
+        name: impl Into<String>,
+    ) -> Self {
+        Self(Repr::Symbol {
+            cdbase: Some(cdbase.into()),
+            cd: cd.into(),
+            name: name.into(),
+        })
+    }
+
+    /// Matches an [`OMA`](OpenMath::OMA) whose applicant matches `head` and whose arguments
+    /// match `args`; the last of which may be [`many`](Self::many).
+    #[must_use]
+    pub fn oma(head: Self, args: impl IntoIterator<Item = Self>) -> Self {
+        Self(Repr::Oma {
+            head: Box::new(head),
+            args: args.into_iter().collect(),
+        })
+    }
+
+    /// Matches an [`OME`](OpenMath::OME) with the error symbol `cd#name` whose arguments
+    /// match `args`; foreign arguments only match [`any`](Self::any).
+    #[must_use]
+    pub fn ome(
+        cd: impl Into<String>,
+        name: impl Into<String>,
+        args: impl IntoIterator<Item = Self>,
+    ) -> Self {
+        Self(Repr::Ome {
+            cd: cd.into(),
+            name: name.into(),
+            args: args.into_iter().collect(),
+        })
+    }
+
+    /// Matches an [`OMBIND`](OpenMath::OMBIND) (with any number of variables) whose binder
+    /// matches `binder` and whose body matches `body`
+    #[must_use]
+    pub fn bind(binder: Self, body: Self) -> Self {
+        Self(Repr::Bind {
+            binder: Box::new(binder),
+            body: Box::new(body),
+        })
+    }
+
+    /// Matches everything that matches any of `alternatives`
+    #[must_use]
+    pub fn one_of(alternatives: impl IntoIterator<Item = Self>) -> Self {
+        Self(Repr::OneOf(alternatives.into_iter().collect()))
+    }
+
+    /// As the last argument shape of an [`oma`](Self::oma) or [`ome`](Self::ome), matches
+    /// any number (including zero) of remaining arguments that match `shape`; anywhere
+    /// else, it is equivalent to `shape`.
+    #[must_use]
+    pub fn many(shape: Self) -> Self {
+        Self(Repr::Many(Box::new(shape)))
+    }
+
+    /// Additionally requires an attribute with key `cd#name` whose value matches `value`
+    #[must_use]
+    pub fn with_attr(self, cd: impl Into<String>, name: impl Into<String>, value: Self) -> Self {
+        self.attr(cd.into(), name.into(), value, true)
+    }
+
+    /// Additionally requires that an attribute with key `cd#name`, if present, has a value
+    /// matching `value`
+    #[must_use]
+    pub fn with_optional_attr(
+        self,
+        cd: impl Into<String>,
+        name: impl Into<String>,
+        value: Self,
+    ) -> Self {
+        self.attr(cd.into(), name.into(), value, false)
+    }
+
+    fn attr(self, cd: String, name: String, value: Self, required: bool) -> Self {
+        let attr = AttrShape {
+            cd,
+            name,
+            value,
+            required,
+        };
+        match self.0 {
+            Repr::Attributed { shape, mut attrs } => {
+                attrs.push(attr);
+                Self(Repr::Attributed { shape, attrs })
+            }
+            repr => Self(Repr::Attributed {
+                shape: Box::new(Self(repr)),
+                attrs: vec![attr],
+            }),
+        }
+    }
+
+    /// Checks whether `term` has this shape.
+    ///
+    /// # Errors
+    /// at the (first) subterm that does not match; for [alternatives](Self::one_of), the
+    /// error of the alternative that matched the furthest is reported.
+    pub fn check(&self, term: &OpenMath<'_>) -> Result<(), ShapeError> {
+        self.check_at(term, &mut Vec::new())
+    }
+
+    fn check_at(&self, term: &OpenMath<'_>, path: &mut Vec<Step>) -> Result<(), ShapeError> {
+        let mismatch = |path: &[Step]| Err(ShapeError::new(path, self, term));
+        match (&self.0, term) {
+            (Repr::Any, _) => Ok(()),
+            (Repr::Many(shape), _) => shape.check_at(term, path),
+            (Repr::Kind(kind), _) if *kind == term.kind() => Ok(()),
+            (
+                Repr::Symbol { cdbase, cd, name },
+                OpenMath::OMS {
+                    cd: c,
+                    name: n,
+                    cdbase: b,
+                    ..
+                },
+            ) if cd == c
+                && name == n
+                && cdbase.as_ref().is_none_or(|cdbase| {
+                    crate::cdbase::eq_normalized(cdbase, b.as_deref().unwrap_or(crate::CD_BASE))
+                }) =>
+            {
+                Ok(())
+            }
+            (
+                Repr::Oma { head, args },
+                OpenMath::OMA {
+                    applicant,
+                    arguments,
+                    ..
+                },
+            ) => {
+                path.push(Step::Applicant);
+                head.check_at(applicant, path)?;
+                path.pop();
+                check_args(self, term, args, arguments.iter().map(Some), path)
+            }
+            (
+                Repr::Ome { cd, name, args },
+                OpenMath::OME {
+                    cd: c,
+                    name: n,
+                    arguments,
+                    ..
+                },
+            ) if cd == c && name == n => check_args(
+                self,
+                term,
+                args,
+                arguments.iter().map(OMMaybeForeign::as_om),
+                path,
+            ),
+            (
+                Repr::Bind { binder, body },
+                OpenMath::OMBIND {
+                    binder: b, object, ..
+                },
+            ) => {
+                path.push(Step::Binder);
+                binder.check_at(b, path)?;
+                path.pop();
+                path.push(Step::Body);
+                body.check_at(object, path)?;
+                path.pop();
+                Ok(())
+            }
+            (Repr::OneOf(alternatives), _) => {
+                let mut furthest: Option<ShapeError> = None;
+                for a in alternatives {
+                    match a.check_at(term, &mut path.clone()) {
+                        Ok(()) => return Ok(()),
+                        Err(e) if e.path.0.len() > path.len() => {
+                            if furthest
+                                .as_ref()
+                                .is_none_or(|f| f.path.0.len() < e.path.0.len())
+                            {
+                                furthest = Some(e);
+                            }
+                        }
+                        Err(_) => (),
+                    }
+                }
+                furthest.map_or_else(|| mismatch(path), Err)
+            }
+            (Repr::Attributed { shape, attrs }, _) => {
+                shape.check_at(term, path)?;
+                for a in attrs {
+                    check_attr(a, term.attributes(), path)?;
+                }
+                Ok(())
+            }
+            _ => mismatch(path),
+        }
+    }
+}
+
+fn check_args<'a, 'om: 'a>(
+    shape: &Shape,
+    term: &OpenMath<'_>,
+    args: &[Shape],
+    arguments: impl ExactSizeIterator<Item = Option<&'a OpenMath<'om>>>,
+    path: &mut Vec<Step>,
+) -> Result<(), ShapeError> {
+    let (fixed, rest) = match args.split_last() {
+        Some((Shape(Repr::Many(rest)), fixed)) => (fixed, Some(&**rest)),
+        _ => (args, None),
+    };
+    let len = arguments.len();
+    if len < fixed.len() || (rest.is_none() && len > fixed.len()) {
+        return Err(ShapeError {
+            path: path.iter().copied().collect(),
+            expected: format!(
+                "{} with {}{} arguments",
+                shape.kind_str(),
+                if rest.is_some() { "at least " } else { "" },
+                fixed.len()
+            ),
+            found: format!("{} with {len} arguments", term.kind()),
+        });
+    }
+    for (i, arg) in arguments.enumerate() {
+        let shape = fixed.get(i).or(rest).unwrap_or(&Shape(Repr::Any));
+        path.push(Step::Argument(i));
+        match arg {
+            Some(arg) => shape.check_at(arg, path)?,
+            None if shape.0 == Repr::Any => (),
+            None => {
+                return Err(ShapeError {
+                    path: path.iter().copied().collect(),
+                    expected: shape.to_string(),
+                    found: "OMFOREIGN".to_string(),
+                });
+            }
+        }
+        path.pop();
+    }
+    Ok(())
+}
+
+fn check_attr(
+    shape: &AttrShape,
+    attributes: &[Attr<'_, OMMaybeForeign<'_, OpenMath<'_>>>],
+    path: &mut Vec<Step>,
+) -> Result<(), ShapeError> {
+    let Some(i) = attributes
+        .iter()
+        .rposition(|a| a.cd() == shape.cd && a.name() == shape.name)
+    else {
+        return if shape.required {
+            Err(ShapeError {
+                path: path.iter().copied().collect(),
+                expected: format!("attribute {}#{}", shape.cd, shape.name),
+                found: "none".to_string(),
+            })
+        } else {
+            Ok(())
+        };
+    };
+    path.push(Step::Attribute(i));
+    match &attributes[i].value {
+        OMMaybeForeign::OM(value) => shape.value.check_at(value, path)?,
+        OMMaybeForeign::Foreign { .. } if shape.value.0 == Repr::Any => (),
+        OMMaybeForeign::Foreign { .. } => {
+            return Err(ShapeError {
+                path: path.iter().copied().collect(),
+                expected: shape.value.to_string(),
+                found: "OMFOREIGN".to_string(),
+            });
+        }
+    }
+    path.pop();
+    Ok(())
+}
+
+impl Shape {
+    const fn kind_str(&self) -> &'static str {
+        match &self.0 {
+            Repr::Oma { .. } => "OMA",
+            Repr::Ome { .. } => "OME",
+            _ => "object",
+        }
+    }
+}
+
+impl std::fmt::Display for Shape {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn list(f: &mut std::fmt::Formatter<'_>, shapes: &[Shape]) -> std::fmt::Result {
+            for s in shapes {
+                write!(f, ", {s}")?;
+            }
+            Ok(())
+        }
+        match &self.0 {
+            Repr::Any => f.write_str("anything"),
+            Repr::Kind(kind) => kind.fmt(f),
+            Repr::Symbol {
+                cdbase: Some(cdbase),
+                cd,
+                name,
+            } => write!(f, "OMS {cdbase}/{cd}#{name}"),
+            Repr::Symbol { cd, name, .. } => write!(f, "OMS {cd}#{name}"),
+            Repr::Oma { head, args } => {
+                write!(f, "OMA({head}")?;
+                list(f, args)?;
+                f.write_char(')')
+            }
+            Repr::Ome { cd, name, args } => {
+                write!(f, "OME({cd}#{name}")?;
+                list(f, args)?;
+                f.write_char(')')
+            }
+            Repr::Bind { binder, body } => write!(f, "OMBIND({binder}, …, {body})"),
+            Repr::OneOf(alternatives) => {
+                let mut alternatives = alternatives.iter();
+                match alternatives.next() {
+                    None => f.write_str("nothing"),
+                    Some(first) => {
+                        write!(f, "one of {first}")?;
+                        for a in alternatives {
+                            write!(f, " | {a}")?;
+                        }
+                        Ok(())
+                    }
+                }
+            }
+            Repr::Many(shape) => write!(f, "{shape}*"),
+            Repr::Attributed { shape, .. } => shape.fmt(f),
+        }
+    }
+}
+
+/// A mismatch between an object and a [`Shape`]; see [`Shape::check`]
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("at {path}: expected {expected}, found {found}")]
+pub struct ShapeError {
+    /// The path to the offending subterm
+    pub path: Path,
+    /// A description of the expected shape
+    pub expected: String,
+    /// A description of the offending subterm
+    pub found: String,
+}
+
+impl ShapeError {
+    fn new(path: &[Step], expected: &Shape, found: &OpenMath<'_>) -> Self {
+        Self {
+            path: path.iter().copied().collect(),
+            expected: expected.to_string(),
+            found: describe(found),
+        }
+    }
+}
+
+/// The kind and (abbreviated) contents of `om`
+fn describe(om: &OpenMath<'_>) -> String {
+    const MAX_LEN: usize = 32;
+    match om {
+        OpenMath::OMI { int, .. } => format!("OMI {int}"),
+        OpenMath::OMF { float, .. } => format!("OMF {float}"),
+        OpenMath::OMSTR { string, .. } if string.chars().count() > MAX_LEN => {
+            let prefix = string.chars().take(MAX_LEN).collect::<String>();
+            format!("OMSTR {prefix:?}…")
+        }
+        OpenMath::OMSTR { string, .. } => format!("OMSTR {string:?}"),
+        OpenMath::OMB { bytes, .. } => format!("OMB of {} bytes", bytes.len()),
+        OpenMath::OMV { name, .. } => format!("OMV {name}"),
+        OpenMath::OMS {
+            cd,
+            name,
+            cdbase: Some(cdbase),
+            ..
+        } if cdbase != crate::CD_BASE => format!("OMS {cdbase}/{cd}#{name}"),
+        OpenMath::OMS { cd, name, .. } => format!("OMS {cd}#{name}"),
+        OpenMath::OME { cd, name, .. } => format!("OME {cd}#{name}"),
+        OpenMath::OMA { applicant, .. } => match &**applicant {
+            OpenMath::OMS { cd, name, .. } => format!("OMA of {cd}#{name}"),
+            _ => "OMA".to_string(),
+        },
+        OpenMath::OMBIND { binder, .. } => match &**binder {
+            OpenMath::OMS { cd, name, .. } => format!("OMBIND of {cd}#{name}"),
+            _ => "OMBIND".to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::om;
+
+    fn error(shape: &Shape, sexpr: &str) -> String {
+        shape.check(&om(sexpr)).expect_err("mismatch").to_string()
+    }
+
+    #[test]
+    fn leafs_and_applications() {
+        let plus = Shape::oma(
+            Shape::symbol("arith1", "plus"),
+            [Shape::int(), Shape::int()],
+        );
+        assert!(
+            plus.check(&om("(oma (oms arith1 plus) (omi 1) (omi 2))"))
+                .is_ok()
+        );
+        assert_eq!(
+            error(&plus, "(oma (oms arith1 times) (omi 1) (omi 2))"),
+            "at /applicant: expected OMS arith1#plus, found OMS arith1#times"
+        );
+        assert_eq!(
+            error(&plus, "(oma (oms arith1 plus) (omi 1))"),
+            "at /: expected OMA with 2 arguments, found OMA with 1 arguments"
+        );
+        assert_eq!(
+            error(&plus, "(omf 1.5)"),
+            "at /: expected OMA(OMS arith1#plus, OMI, OMI), found OMF 1.5"
+        );
+        assert_eq!(
+            error(
+                &plus,
+                "(oma (oms arith1 plus) (omi 1) (oma (oms arith1 plus) (omi 2)))"
+            ),
+            "at /1: expected OMI, found OMA of arith1#plus"
+        );
+
+        let sum = Shape::oma(
+            Shape::symbol_in(crate::CD_BASE, "arith1", "plus"),
+            [Shape::many(Shape::one_of([Shape::int(), Shape::float()]))],
+        );
+        assert!(sum.check(&om("(oma (oms arith1 plus))")).is_ok());
+        assert!(
+            sum.check(&om("(oma (oms arith1 plus) (omi 1) (omf 2.5) (omi 3))"))
+                .is_ok()
+        );
+        assert_eq!(
+            error(&sum, "(oma (oms arith1 plus) (omi 1) (omv x))"),
+            "at /1: expected one of OMI | OMF, found OMV x"
+        );
+        assert_eq!(
+            error(
+                &sum,
+                r#"(oma (cdbase "http://example.org" (oms arith1 plus)))"#
+            ),
+            "at /applicant: expected OMS http://www.openmath.org/cd/arith1#plus, \
+             found OMS http://example.org/arith1#plus"
+        );
+
+        let list = Shape::ome("error", "unhandled", [Shape::string(), Shape::any()]);
+        assert!(
+            list.check(&om(
+                r#"(ome (oms error unhandled) (omstr "s") (omforeign "<x/>"))"#
+            ))
+            .is_ok()
+        );
+        assert_eq!(
+            error(
+                &list,
+                r#"(ome (oms error unhandled) (omforeign "<x/>") (omi 1))"#
+            ),
+            "at /0: expected OMSTR, found OMFOREIGN"
+        );
+    }
+
+    #[test]
+    fn alternatives_report_furthest() {
+        let shape = Shape::one_of([
+            Shape::int(),
+            Shape::oma(
+                Shape::symbol("arith1", "minus"),
+                [Shape::int(), Shape::int()],
+            ),
+        ]);
+        assert!(shape.check(&om("(omi 1)")).is_ok());
+        assert_eq!(
+            error(&shape, r#"(oma (oms arith1 minus) (omi 1) (omstr "two"))"#),
+            r#"at /1: expected OMI, found OMSTR "two""#
+        );
+        assert_eq!(
+            error(&shape, "(omv x)"),
+            "at /: expected one of OMI | OMA(OMS arith1#minus, OMI, OMI), found OMV x"
+        );
+    }
+
+    #[test]
+    fn attributes_and_binders() {
+        let lambda = Shape::bind(
+            Shape::symbol("fns1", "lambda"),
+            Shape::bind(
+                Shape::symbol("fns1", "lambda"),
+                Shape::var().with_optional_attr("sts", "type", Shape::symbol("setname1", "R")),
+            ),
+        )
+        .with_attr("meta", "note", Shape::string());
+        assert!(
+            lambda
+                .check(&om(
+                    r#"(omattr (((oms meta note) (omstr "curried")))
+                        (ombind (oms fns1 lambda) (bvar x) (ombind (oms fns1 lambda) (bvar y) (omv x))))"#
+                ))
+                .is_ok()
+        );
+        assert_eq!(
+            error(
+                &lambda,
+                "(ombind (oms fns1 lambda) (bvar x) (ombind (oms fns1 lambda) (bvar y) (omv x)))"
+            ),
+            "at /: expected attribute meta#note, found none"
+        );
+        assert_eq!(
+            error(
+                &lambda,
+                r#"(omattr (((oms meta note) (omstr "curried")))
+                    (ombind (oms fns1 lambda) (bvar x)
+                      (ombind (oms fns1 lambda) (bvar y) (omattr (((oms sts type) (oms setname1 N))) (omv x)))))"#
+            ),
+            "at /body/body/@0: expected OMS setname1#R, found OMS setname1#N"
+        );
+        assert_eq!(
+            error(
+                &lambda,
+                r#"(omattr (((oms meta note) (omstr "curried"))) (ombind (oms fns1 lambda) (bvar x) (omi 1)))"#
+            ),
+            "at /body: expected OMBIND(OMS fns1#lambda, …, OMV), found OMI 1"
+        );
+    }
+}
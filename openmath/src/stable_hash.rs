@@ -0,0 +1,415 @@
+/*! Hashing <span style="font-variant:small-caps;">OpenMath</span> objects, e.g. for using them as
+map keys, and digests that are stable across versions of this crate.
+
+# [`Hash`] and [`Eq`]
+[`OpenMath`] implements [`Hash`] consistently with [`Eq`]: equal objects hash equal within
+one process, regardless of whether their strings are borrowed or owned, so they can be used
+as keys of [`HashMap`](std::collections::HashMap)s and looked up with objects borrowing from
+some other input. Floats are compared as [`OrderedFloat`](ordered_float::OrderedFloat)s (see
+[`OMF`](crate::OpenMath::OMF)), so all NaNs are equal and `-0.0 == 0.0`; everything else is
+compared structurally, including the order of attributes, but ignoring the
+[`cdbase_inherited`](crate::OpenMath::OMS) flag of symbols.
+
+The values of [`Hash`] are *not* stable: they depend on the [`Hasher`](std::hash::Hasher)
+and on the layout of the types in this crate, which may change in any release. Anything that
+is persisted or exchanged (caches, content addressing, deduplication across processes)
+should use [`canonical_bytes`](crate::OpenMath::canonical_bytes) or
+[`stable_hash`](crate::OpenMath::stable_hash) (feature `stable-hash`) instead.
+
+# The canonical form
+[`canonical_bytes`](crate::OpenMath::canonical_bytes) encodes an object such that two
+objects are [equal](Eq) if and only if their encodings are. The encoding never changes
+without changing its version header as well. Version 1 is the header `OM1` followed by the
+object, where
+
+- lengths and counts are 64-bit little-endian integers;
+- strings and byte arrays are their length followed by their (UTF-8) bytes;
+- optional values are a `0` byte if absent, or a `1` byte followed by the value;
+- cdbases are [normalized](crate::CdBase::normalize) strings; where the source document
+  wrote them (on the symbol or on an enclosing object) is not encoded;
+- objects are their [`OMKind`](crate::OMKind) as a byte, then their fields, then their
+  attributes (see below):
+
+| Kind | Fields |
+|------|--------|
+| [`OMI`](crate::OMKind::OMI) | the integer in decimal, as a string |
+| [`OMF`](crate::OMKind::OMF) | the [bits](f64::to_bits) of the float, little-endian, after replacing every NaN by [`f64::NAN`] and `-0.0` by `0.0` |
+| [`OMSTR`](crate::OMKind::OMSTR) | the string |
+| [`OMB`](crate::OMKind::OMB) | the bytes |
+| [`OMV`](crate::OMKind::OMV) | the name |
+| [`OMS`](crate::OMKind::OMS) | the cd, the name and the optional cdbase |
+| [`OMA`](crate::OMKind::OMA) | the applicant, the number of arguments and the arguments |
+| [`OME`](crate::OMKind::OME) | the cd, the name, the optional cdbase, the number of arguments and the arguments, each a `0` byte followed by an object or a `1` byte followed by the optional encoding and the value of an [`OMFOREIGN`](crate::OMKind::OMFOREIGN) |
+| [`OMBIND`](crate::OMKind::OMBIND) | the binder, the number of variables, each variable's name and attributes, and the body |
+
+- attributes are their number followed by, for each, its key (the optional cdbase, the cd
+  and the name) and its value, like an argument of an `OME`.
+
+# Examples
+```rust
+use std::collections::HashMap;
+use openmath::OpenMath;
+
+let mut seen = HashMap::new();
+let om = OpenMath::from_sexpr("(oma (oms arith1 plus) (omi 1) (omf -0.0))").expect("is valid");
+seen.insert(om, "first");
+let again = OpenMath::from_sexpr("(oma (oms arith1 plus) (omi 1) (omf 0.0))").expect("is valid");
+assert_eq!(seen.get(&again), Some(&"first"));
+assert!(again.canonical_bytes().starts_with(b"OM1"));
+```
+*/
+
+use crate::{Attr, CdBase, OMKind, OMMaybeForeign, OpenMath, floats};
+
+/// The header of [`OpenMath::canonical_bytes`], including the version of the encoding
+const HEADER: &[u8] = b"OM1";
+
+impl OpenMath<'_> {
+    /// Encodes this object in the canonical form described in the [module
+    /// documentation](crate::stable_hash): two objects are equal if and only if their
+    /// canonical bytes are, and these bytes do not change between releases unless their
+    /// version header does.
+    #[must_use]
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        let mut out = HEADER.to_vec();
+        self.encode(&mut out);
+        out
+    }
+
+    /** The SHA-256 digest of the [canonical bytes](Self::canonical_bytes) of this object.
+    Unlike [`Hash`], this is stable across processes, platforms and (unless the version of the
+    canonical form changes) releases, so it can be persisted or used for content addressing.
+
+    # Examples
+    ```rust
+    use openmath::OpenMath;
+
+    let om = OpenMath::from_sexpr("(omi 42)").expect("is valid");
+    let built = OpenMath::OMI { int: 42.into(), attributes: Vec::new() };
+    assert_eq!(om.stable_hash(), built.stable_hash());
+    ```
+    */
+    #[cfg(feature = "stable-hash")]
+    #[must_use]
+    pub fn stable_hash(&self) -> [u8; 32] {
+        use sha2::Digest;
+        let mut hasher = sha2::Sha256::new();
+        hasher.put(HEADER);
+        self.encode(&mut hasher);
+        hasher.finalize().into()
+    }
+
+    fn encode(&self, out: &mut impl Sink) {
+        match self {
+            Self::OMI { int, attributes } => {
+                out.put(&[OMKind::OMI as u8]);
+                out.string(&int.to_string());
+                encode_attributes(attributes, out);
+            }
+            Self::OMF { float, attributes } => {
+                out.put(&[OMKind::OMF as u8]);
+                out.put(&floats::canonical(float.0).to_bits().to_le_bytes());
+                encode_attributes(attributes, out);
+            }
+            Self::OMSTR { string, attributes } => {
+                out.put(&[OMKind::OMSTR as u8]);
+                out.string(string);
+                encode_attributes(attributes, out);
+            }
+            Self::OMB { bytes, attributes } => {
+                out.put(&[OMKind::OMB as u8]);
+                out.bytes(bytes);
+                encode_attributes(attributes, out);
+            }
+            Self::OMV { name, attributes } => {
+                out.put(&[OMKind::OMV as u8]);
+                out.string(name);
+                encode_attributes(attributes, out);
+            }
+            Self::OMS {
+                cd,
+                name,
+                cdbase,
+                cdbase_inherited: _,
+                attributes,
+            } => {
+                out.put(&[OMKind::OMS as u8]);
+                out.string(cd);
+                out.string(name);
+                out.cdbase(cdbase.as_deref());
+                encode_attributes(attributes, out);
+            }
+            Self::OMA {
+                applicant,
+                arguments,
+                attributes,
+            } => {
+                out.put(&[OMKind::OMA as u8]);
+                applicant.encode(out);
+                out.len(arguments.len());
+                for a in arguments {
+                    a.encode(out);
+                }
+                encode_attributes(attributes, out);
+            }
+            Self::OME {
+                cd,
+                name,
+                cdbase,
+                arguments,
+                attributes,
+            } => {
+                out.put(&[OMKind::OME as u8]);
+                out.string(cd);
+                out.string(name);
+                out.cdbase(cdbase.as_deref());
+                out.len(arguments.len());
+                for a in arguments {
+                    encode_maybe_foreign(a, out);
+                }
+                encode_attributes(attributes, out);
+            }
+            Self::OMBIND {
+                binder,
+                variables,
+                object,
+                attributes,
+            } => {
+                out.put(&[OMKind::OMBIND as u8]);
+                binder.encode(out);
+                out.len(variables.len());
+                for v in variables {
+                    out.string(&v.name);
+                    encode_attributes(&v.attributes, out);
+                }
+                object.encode(out);
+                encode_attributes(attributes, out);
+            }
+        }
+    }
+}
+
+fn encode_maybe_foreign(value: &OMMaybeForeign<'_, OpenMath<'_>>, out: &mut impl Sink) {
+    match value {
+        OMMaybeForeign::OM(om) => {
+            out.put(&[0]);
+            om.encode(out);
+        }
+        OMMaybeForeign::Foreign { encoding, value } => {
+            out.put(&[1]);
+            out.optional(encoding.as_deref());
+            out.string(value);
+        }
+    }
+}
+
+fn encode_attributes(
+    attributes: &[Attr<'_, OMMaybeForeign<'_, OpenMath<'_>>>],
+    out: &mut impl Sink,
+) {
+    out.len(attributes.len());
+    for Attr { key, value } in attributes {
+        out.cdbase(key.cdbase.as_deref());
+        out.string(&key.cd);
+        out.string(&key.name);
+        encode_maybe_foreign(value, out);
+    }
+}
+
+/// Where the canonical form is written to: a buffer, or directly into a digest
+trait Sink {
+    fn put(&mut self, bytes: &[u8]);
+
+    fn len(&mut self, len: usize) {
+        self.put(&(len as u64).to_le_bytes());
+    }
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.len(bytes.len());
+        self.put(bytes);
+    }
+    fn string(&mut self, s: &str) {
+        self.bytes(s.as_bytes());
+    }
+    fn optional(&mut self, s: Option<&str>) {
+        match s {
+            None => self.put(&[0]),
+            Some(s) => {
+                self.put(&[1]);
+                self.string(s);
+            }
+        }
+    }
+    fn cdbase(&mut self, cdbase: Option<&str>) {
+        let cdbase = cdbase.map(CdBase::new_unchecked);
+        self.optional(cdbase.as_ref().map(CdBase::normalize).as_deref());
+    }
+}
+
+impl Sink for Vec<u8> {
+    #[inline]
+    fn put(&mut self, bytes: &[u8]) {
+        self.extend_from_slice(bytes);
+    }
+}
+
+#[cfg(feature = "stable-hash")]
+impl Sink for sha2::Sha256 {
+    #[inline]
+    fn put(&mut self, bytes: &[u8]) {
+        sha2::Digest::update(self, bytes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::om;
+    use std::{
+        borrow::Cow,
+        collections::{HashMap, HashSet},
+    };
+
+    use crate::OpenMath;
+
+    const OBJECTS: &[&str] = &[
+        "(omi 42)",
+        "(omi -170141183460469231731687303715884105729)",
+        "(omf 1.5)",
+        "(omf NaN)",
+        "(omstr \"text\")",
+        "(omb \"SGVsbG8=\")",
+        "(omv x)",
+        "(oms arith1 plus)",
+        "(oma (oms arith1 plus) (omi 1) (omv x))",
+        "(ombind (oms fns1 lambda) (bvar x (omattr (((oms ecc type) (oms setname1 Z))) y)) (omv x))",
+        "(ome (oms aritherror division_by_zero) (omi 1) (omforeign \"text/plain\" \"1/0\"))",
+        "(omattr (((oms ecc type) (omforeign \"x\"))) (omi 1))",
+    ];
+
+    #[test]
+    fn equal_iff_canonical_bytes_equal() {
+        let objects: Vec<_> = OBJECTS.iter().map(|s| om(s)).collect();
+        for (i, a) in objects.iter().enumerate() {
+            for (j, b) in objects.iter().enumerate() {
+                assert_eq!(
+                    i == j,
+                    a.canonical_bytes() == b.canonical_bytes(),
+                    "{i} vs {j}"
+                );
+            }
+        }
+        assert_eq!(
+            om("(omf NaN)").canonical_bytes(),
+            om("(omf -NaN)").canonical_bytes()
+        );
+        assert_eq!(
+            om("(omf 0.0)").canonical_bytes(),
+            om("(omf -0.0)").canonical_bytes()
+        );
+    }
+
+    /// Where a cdbase was written, and how it is spelled, do not change the canonical form
+    #[test]
+    fn cdbase_provenance() {
+        let oms = |cdbase: Option<&'static str>, cdbase_inherited| OpenMath::OMS {
+            cd: "arith1".into(),
+            name: "plus".into(),
+            cdbase: cdbase.map(Into::into),
+            cdbase_inherited,
+            attributes: Vec::new(),
+        };
+        let inherited = oms(Some(crate::CD_BASE), true);
+        for other in [
+            oms(Some(crate::CD_BASE), false),
+            oms(Some("HTTP://www.OpenMath.org:80/cd/"), false),
+            om("(oms arith1 plus)"),
+        ] {
+            assert_eq!(other, inherited);
+            assert_eq!(other.canonical_bytes(), inherited.canonical_bytes());
+            #[cfg(feature = "stable-hash")]
+            assert_eq!(other.stable_hash(), inherited.stable_hash());
+        }
+        for other in [oms(None, false), oms(Some("http://example.org/cd"), true)] {
+            assert_ne!(other, inherited);
+            assert_ne!(other.canonical_bytes(), inherited.canonical_bytes());
+        }
+    }
+
+    #[test]
+    fn canonical_bytes_vector() {
+        let mut expected = b"OM1".to_vec();
+        expected.push(0); // OMI
+        expected.extend_from_slice(&2u64.to_le_bytes());
+        expected.extend_from_slice(b"42");
+        expected.extend_from_slice(&0u64.to_le_bytes()); // no attributes
+        assert_eq!(om("(omi 42)").canonical_bytes(), expected);
+    }
+
+    #[test]
+    fn map_keys() {
+        let owned = om("(oma (oms arith1 plus) (omstr \"a\") (omv x))");
+        let mut map = HashMap::new();
+        map.insert(owned.clone(), 1);
+        let source = String::from("a");
+        let OpenMath::OMA {
+            applicant,
+            mut arguments,
+            attributes,
+        } = owned
+        else {
+            unreachable!()
+        };
+        arguments[0] = OpenMath::OMSTR {
+            string: Cow::Borrowed(&source),
+            attributes: Vec::new(),
+        };
+        let borrowed = OpenMath::OMA {
+            applicant,
+            arguments,
+            attributes,
+        };
+        assert_eq!(map.get(&borrowed), Some(&1));
+
+        let floats: HashSet<_> = ["(omf NaN)", "(omf -NaN)", "(omf 0.0)", "(omf -0.0)"]
+            .into_iter()
+            .map(om)
+            .collect();
+        assert_eq!(floats.len(), 2);
+    }
+
+    /// SHA-256 digests that must not change unless the version of the canonical form does
+    #[cfg(feature = "stable-hash")]
+    #[test]
+    fn stable_hash_vectors() {
+        use std::fmt::Write;
+        const VECTORS: &[(&str, &str)] = &[
+            (
+                "(omi 42)",
+                "f5ef641ba4a529c069acd5b30381089bc5b35a6a17efef780fcc02f1c1b2d9e4",
+            ),
+            (
+                "(omf NaN)",
+                "c6cdcd9f23305376ee816abf3f0e17924811a16db78068cab1bff08a7bdd5294",
+            ),
+            (
+                "(oma (oms arith1 plus) (omi 1) (omv x))",
+                "f6c8a5e98ae22f82e73ddfdd35b3de80b13e6d7ea24d2bca149ed5f54598c7d1",
+            ),
+            (
+                "(ombind (oms fns1 lambda) (bvar x (omattr (((oms ecc type) (oms setname1 Z))) y)) (omv x))",
+                "a212f427f0d5cbae826c4b8c66984cabb24a24c9e272c9a0602cf307e8e67612",
+            ),
+            (
+                "(ome (oms aritherror division_by_zero) (omi 1) (omforeign \"text/plain\" \"1/0\"))",
+                "d9c789d3bcb31b3a24d9fd0856f10ca438562d81b8f99f00d5e07380e278b71a",
+            ),
+        ];
+        for (s, hex) in VECTORS {
+            let digest = om(s).stable_hash();
+            let digest = digest.iter().fold(String::new(), |mut s, b| {
+                write!(s, "{b:02x}").expect("writing to a String does not fail");
+                s
+            });
+            assert_eq!(digest, *hex, "{s}");
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use std::borrow::Cow;
+
+use crate::ser::AsOMS;
+
+/// A reference to a symbol: its (optional) cdbase, content dictionary and name; e.g. the
+/// key of an [`Attr`](crate::Attr).
+///
+/// Displayed (and [parsed](SymbolRef::parse)) as `cdbase?cd#name`, or `cd#name` if there is
+/// no cdbase; parsing additionally accepts the short form `cd.name`.
+///
+/// # Examples
+/// ```rust
+/// use openmath::SymbolRef;
+///
+/// let s: SymbolRef = "http://www.openmath.org/cd?arith1#plus".parse().expect("is valid");
+/// assert_eq!(s, SymbolRef::with_cdbase(openmath::CD_BASE, "arith1", "plus"));
+/// assert_eq!(SymbolRef::parse("arith1.plus"), Ok(SymbolRef::new("arith1", "plus")));
+/// assert_eq!(s.to_string(), "http://www.openmath.org/cd?arith1#plus");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SymbolRef<'o> {
+    /// The content dictionary base (optional; inherited if `None`)
+    pub cdbase: Option<Cow<'o, str>>,
+    /// The name of the content dictionary
+    pub cd: Cow<'o, str>,
+    /// The name of the symbol
+    pub name: Cow<'o, str>,
+}
+
+/// Errors when [parsing](SymbolRef::parse) a [`SymbolRef`]
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SymbolRefError {
+    #[error("expected `cd#name` or `cd.name`, found `{0}`")]
+    MissingSeparator(String),
+    #[error("empty content dictionary or symbol name in `{0}`")]
+    Empty(String),
+}
+
+impl<'o> SymbolRef<'o> {
+    /// A symbol without cdbase, i.e. one that inherits its cdbase
+    #[inline]
+    pub fn new(cd: impl Into<Cow<'o, str>>, name: impl Into<Cow<'o, str>>) -> Self {
+        Self {
+            cdbase: None,
+            cd: cd.into(),
+            name: name.into(),
+        }
+    }
+
+    /// A symbol with an explicit cdbase
+    #[inline]
+    pub fn with_cdbase(
+        cdbase: impl Into<Cow<'o, str>>,
+        cd: impl Into<Cow<'o, str>>,
+        name: impl Into<Cow<'o, str>>,
+    ) -> Self {
+        Self {
+            cdbase: Some(cdbase.into()),
+            cd: cd.into(),
+            name: name.into(),
+        }
+    }
+
+    /// Parses `cdbase?cd#name`, `cd#name` or `cd.name`, borrowing from `s`.
+    ///
+    /// # Errors
+    /// if there is no separator between cd and name, or either of them is empty.
+    pub fn parse(s: &'o str) -> Result<Self, SymbolRefError> {
+        let (cdbase, cd, name) = if let Some((prefix, name)) = s.rsplit_once('#') {
+            match prefix.rsplit_once('?') {
+                Some((cdbase, cd)) => (Some(cdbase), cd, name),
+                None => (None, prefix, name),
+            }
+        } else if let Some((cd, name)) = s.split_once('.') {
+            (None, cd, name)
+        } else {
+            return Err(SymbolRefError::MissingSeparator(s.to_string()));
+        };
+        if cd.is_empty() || name.is_empty() || cdbase.is_some_and(str::is_empty) {
+            return Err(SymbolRefError::Empty(s.to_string()));
+        }
+        Ok(Self {
+            cdbase: cdbase.map(Cow::Borrowed),
+            cd: Cow::Borrowed(cd),
+            name: Cow::Borrowed(name),
+        })
+    }
+
+    /// The cdbase, if given explicitly
+    #[inline]
+    #[must_use]
+    pub fn cdbase(&self) -> Option<&str> {
+        self.cdbase.as_deref()
+    }
+
+    /// The name of the content dictionary
+    #[inline]
+    #[must_use]
+    pub fn cd(&self) -> &str {
+        &self.cd
+    }
+
+    /// The name of the symbol
+    #[inline]
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Borrows this as a [`Uri`](crate::ser::Uri), e.g. for serialization
+    #[inline]
+    #[must_use]
+    pub fn as_uri(&self) -> crate::ser::Uri<'_> {
+        crate::ser::Uri {
+            cdbase: self.cdbase.as_deref().map(crate::CdBase::new_unchecked),
+            cd: &self.cd,
+            name: &self.name,
+        }
+    }
+
+    /// The URI `cdbase?cd#name` of this symbol, with its cdbase (or `current_cdbase`, if it
+    /// has none) [normalized](crate::CdBase::normalize); i.e. equal for all references to
+    /// the same symbol.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use openmath::SymbolRef;
+    ///
+    /// let s = SymbolRef::with_cdbase("HTTP://www.openmath.org/cd/", "arith1", "plus");
+    /// assert_eq!(
+    ///     s.resolved_uri("http://example.org"),
+    ///     SymbolRef::new("arith1", "plus").resolved_uri(openmath::CD_BASE)
+    /// );
+    /// ```
+    #[must_use]
+    pub fn resolved_uri(&self, current_cdbase: &str) -> String {
+        let cdbase = crate::CdBase::new_unchecked(self.cdbase.as_deref().unwrap_or(current_cdbase));
+        format!("{}?{}#{}", cdbase.normalize().as_str(), self.cd, self.name)
+    }
+
+    /// Clones all borrowed data
+    #[must_use]
+    pub fn into_owned(self) -> SymbolRef<'static> {
+        SymbolRef {
+            cdbase: self.cdbase.map(|c| Cow::Owned(c.into_owned())),
+            cd: Cow::Owned(self.cd.into_owned()),
+            name: Cow::Owned(self.name.into_owned()),
+        }
+    }
+}
+
+impl std::str::FromStr for SymbolRef<'static> {
+    type Err = SymbolRefError;
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SymbolRef::parse(s).map(SymbolRef::into_owned)
+    }
+}
+
+impl std::fmt::Display for SymbolRef<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(cdbase) = &self.cdbase {
+            write!(f, "{cdbase}?")?;
+        }
+        write!(f, "{}#{}", self.cd, self.name)
+    }
+}
+
+impl AsOMS for SymbolRef<'_> {
+    #[inline]
+    fn cdbase_uri(&self, current_cdbase: &str) -> Option<crate::CdBase<'_>> {
+        self.cdbase
+            .as_deref()
+            .filter(|s| !crate::cdbase::eq_normalized(s, current_cdbase))
+            .map(crate::CdBase::new_unchecked)
+    }
+    #[inline]
+    fn cd(&self) -> impl std::fmt::Display {
+        &*self.cd
+    }
+    #[inline]
+    fn name(&self) -> impl std::fmt::Display {
+        &*self.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SymbolRef, SymbolRefError};
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            SymbolRef::parse("http://example.org/cd?x=1?arith1#plus"),
+            Ok(SymbolRef::with_cdbase(
+                "http://example.org/cd?x=1",
+                "arith1",
+                "plus"
+            ))
+        );
+        assert_eq!(
+            SymbolRef::parse("arith1#plus"),
+            Ok(SymbolRef::new("arith1", "plus"))
+        );
+        assert_eq!(
+            SymbolRef::parse("set1.map.x"),
+            Ok(SymbolRef::new("set1", "map.x"))
+        );
+        assert!(matches!(
+            SymbolRef::parse("plus"),
+            Err(SymbolRefError::MissingSeparator(_))
+        ));
+        for empty in ["#plus", "arith1#", "?arith1#plus", ".plus"] {
+            assert!(
+                matches!(SymbolRef::parse(empty), Err(SymbolRefError::Empty(_))),
+                "{empty}"
+            );
+        }
+        for s in ["arith1#plus", "http://www.openmath.org/cd?arith1#plus"] {
+            assert_eq!(s.parse::<SymbolRef>().expect("is valid").to_string(), s);
+        }
+    }
+}
@@ -0,0 +1,258 @@
+//! Enums of expected symbols; see [`om_symbols!`](crate::om_symbols)
+
+use crate::{OM, ser::Uri};
+
+/// Errors when deserializing an enum generated by [`om_symbols!`](crate::om_symbols) from
+/// anything but one of its symbols
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("expected one of {}, found {found}", expected.join(", "))]
+pub struct UnexpectedSymbol {
+    /// The symbol found (as `cdbase?cd#name`), or the kind of object if it is not a symbol
+    pub found: String,
+    /// The accepted symbols, as `cdbase?cd#name`
+    pub expected: Vec<String>,
+}
+
+/** Generates `enum`s whose variants stand for <span style="font-variant:small-caps;">OpenMath</span>
+symbols, e.g. to deserialize the head of an [OMA](crate::OMKind::OMA) that is expected to be
+one of a few operators.
+
+Every variant is given as `Variant = ("cd", "name")` or `Variant = ("cd", "name", "cdbase")`;
+the cdbase defaults to [`CD_BASE`](crate::CD_BASE). The generated enum derives `Debug`,
+`Clone`, `Copy`, `PartialEq`, `Eq` and `Hash`, and has
+- an associated constant `ALL` with all variants, in the order declared,
+- a method `uri(self)` returning the symbol of a variant as a [`Uri`](crate::ser::Uri),
+- an implementation of [`OMSerializable`](crate::OMSerializable) writing that symbol, and
+- an implementation of [`OMDeserializable`](crate::OMDeserializable) accepting exactly these
+  symbols (by cd, name and effective cdbase); anything else fails with an
+  [`UnexpectedSymbol`](crate::UnexpectedSymbol) listing the accepted ones. Attributes of the
+  symbol are discarded, but since their values are deserialized as the same enum, they have
+  to be accepted symbols as well.
+
+# Examples
+```
+use openmath::{OMDeserializable, OMSerializable, om_symbols};
+
+om_symbols! {
+    /// Some arithmetic operators
+    pub enum Op {
+        Plus = ("arith1", "plus"),
+        Times = ("arith1", "times"),
+        /// a symbol from a different cdbase
+        Custom = ("my", "op", "http://example.org"),
+    }
+}
+
+# #[cfg(all(feature = "xml-read", feature = "xml-write"))]
+# {
+assert_eq!(Op::Times.xml(false).to_string(), r#"<OMS cd="arith1" name="times"/>"#);
+let op = Op::from_openmath_xml(r#"<OMS cd="arith1" name="plus"/>"#).expect("is a known symbol");
+assert_eq!(op, Op::Plus);
+let op = Op::from_openmath_xml(r#"<OMS cdbase="http://example.org" cd="my" name="op"/>"#)
+    .expect("is a known symbol");
+assert_eq!(op, Op::Custom);
+
+let err = Op::from_openmath_xml(r#"<OMS cd="arith1" name="minus"/>"#).expect_err("is unknown");
+assert!(err.to_string().contains(
+    "expected one of http://www.openmath.org/cd?arith1#plus, http://www.openmath.org/cd?arith1#times, \
+     http://example.org?my#op, found http://www.openmath.org/cd?arith1#minus"
+));
+# }
+```
+*/
+#[macro_export]
+macro_rules! om_symbols {
+    (@cdbase) => {
+        $crate::CD_BASE
+    };
+    (@cdbase $cdbase:literal) => {
+        $cdbase
+    };
+    ($(
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident {
+            $(
+                $(#[$vmeta:meta])*
+                $variant:ident = ($cd:literal, $symbol:literal $(, $cdbase:literal)?)
+            ),* $(,)?
+        }
+    )*) => {$(
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        $vis enum $name {
+            $(
+                $(#[$vmeta])*
+                $variant,
+            )*
+        }
+
+        impl $name {
+            /// All symbols, in the order declared
+            pub const ALL: &'static [Self] = &[$(Self::$variant),*];
+
+            /// The symbol this value stands for
+            #[must_use]
+            pub const fn uri(self) -> $crate::ser::Uri<'static> {
+                match self {
+                    $(Self::$variant => $crate::ser::Uri {
+                        cdbase: Some($crate::CdBase::new_unchecked($crate::om_symbols!(@cdbase $($cdbase)?))),
+                        cd: $cd,
+                        name: $symbol,
+                    },)*
+                }
+            }
+        }
+
+        impl $crate::OMSerializable for $name {
+            #[inline]
+            fn as_openmath<'s, S: $crate::ser::OMSerializer<'s>>(
+                &self,
+                serializer: S,
+            ) -> Result<S::Ok, S::Err> {
+                $crate::ser::AsOMS::as_oms(&self.uri()).as_openmath(serializer)
+            }
+        }
+
+        impl<'de> $crate::OMDeserializable<'de> for $name {
+            type Ret = Self;
+            type Err = $crate::UnexpectedSymbol;
+            #[inline]
+            fn from_openmath(om: $crate::OM<'de, Self>, cdbase: &str) -> Result<Self, Self::Err> {
+                $crate::__find_symbol(Self::ALL, Self::uri, &om, cdbase)
+            }
+        }
+    )*};
+}
+
+/// The symbol in `all` that `om` is, if any; used by [`om_symbols!`](crate::om_symbols)
+#[doc(hidden)]
+pub fn find_symbol<T: Copy, I>(
+    all: &[T],
+    uri: fn(T) -> Uri<'static>,
+    om: &OM<'_, I>,
+    cdbase: &str,
+) -> Result<T, UnexpectedSymbol> {
+    let found = if let OM::OMS { cd, name, .. } = om {
+        if let Some(s) = all.iter().copied().find(|s| {
+            let u = uri(*s);
+            u.cd == cd
+                && u.name == name
+                && crate::cdbase::eq_normalized(
+                    u.cdbase.as_deref().unwrap_or(crate::CD_BASE),
+                    cdbase,
+                )
+        }) {
+            return Ok(s);
+        }
+        format!("{cdbase}?{cd}#{name}")
+    } else {
+        format!("{:?}", om.kind())
+    };
+    Err(UnexpectedSymbol {
+        found,
+        expected: all
+            .iter()
+            .map(|s| {
+                let u = uri(*s);
+                format!(
+                    "{}?{}#{}",
+                    u.cdbase.as_deref().unwrap_or(crate::CD_BASE),
+                    u.cd,
+                    u.name
+                )
+            })
+            .collect(),
+    })
+}
+
+#[cfg(all(test, feature = "xml-read", feature = "xml-write"))]
+mod tests {
+    use super::UnexpectedSymbol;
+    use crate::{OMDeserializable, OMSerializable, de::xml::XmlReadError};
+
+    crate::om_symbols! {
+        enum Op {
+            Plus = ("arith1", "plus"),
+            Times = ("arith1", "times"),
+            F = ("my", "f", "http://example.org"),
+        }
+    }
+
+    #[test]
+    fn symbols() {
+        for op in Op::ALL {
+            let xml = op.xml(false).to_string();
+            assert_eq!(
+                Op::from_openmath_xml(&xml).expect("roundtrips"),
+                *op,
+                "{xml}"
+            );
+        }
+        assert_eq!(
+            Op::F.xml(false).to_string(),
+            r#"<OMS cdbase="http://example.org" cd="my" name="f"/>"#
+        );
+        // the cdbase may be inherited, and attributes are discarded
+        assert_eq!(
+            Op::from_openmath_xml(
+                r#"<OMATTR cdbase="http://example.org"><OMATP><OMS cd="meta" name="note"/><OMS cd="my" name="f"/></OMATP><OMS cd="my" name="f"/></OMATTR>"#
+            )
+            .expect("is a known symbol"),
+            Op::F
+        );
+
+        // right cd and name, but the wrong cdbase, set by the OMS itself
+        let Err(XmlReadError::SymbolConversion {
+            error: e,
+            position: 0,
+            cdbase_origin: Some(0),
+        }) = Op::from_openmath_xml(r#"<OMS cdbase="http://example.org" cd="arith1" name="plus"/>"#)
+        else {
+            panic!("the cdbase differs")
+        };
+        assert_eq!(
+            e,
+            UnexpectedSymbol {
+                found: "http://example.org?arith1#plus".to_string(),
+                expected: vec![
+                    "http://www.openmath.org/cd?arith1#plus".to_string(),
+                    "http://www.openmath.org/cd?arith1#times".to_string(),
+                    "http://example.org?my#f".to_string(),
+                ],
+            }
+        );
+        // ... or inherited from an ancestor
+        let xml = r#"<OMATTR cdbase="http://example.org"><OMATP><OMS cd="meta" name="note"/><OMS cd="my" name="f"/></OMATP><OMS cd="my" name="g"/></OMATTR>"#;
+        let Err(XmlReadError::SymbolConversion {
+            position,
+            cdbase_origin: Some(0),
+            ..
+        }) = Op::from_openmath_xml(xml)
+        else {
+            panic!("the name differs")
+        };
+        assert_eq!(
+            &xml[usize::try_from(position).expect("fits")..][..4],
+            "<OMS"
+        );
+        assert!(matches!(
+            Op::from_openmath_xml(r#"<OMS cd="my" name="f"/>"#),
+            Err(XmlReadError::SymbolConversion {
+                cdbase_origin: None,
+                ..
+            })
+        ));
+        let Err(XmlReadError::Conversion(e)) = Op::from_openmath_xml("<OMI>1</OMI>") else {
+            panic!("is not a symbol")
+        };
+        assert_eq!(e.found, "OMI");
+    }
+
+    #[test]
+    fn unit() {
+        <()>::from_openmath_xml(
+            r#"<OMA><OMS cd="arith1" name="plus"/><OMI>1</OMI><OMSTR>a</OMSTR></OMA>"#,
+        )
+        .expect("anything is accepted");
+    }
+}
@@ -0,0 +1,9 @@
+//! Helpers shared by the unit tests
+
+use crate::OpenMath;
+
+/// Reads the s-expression `sexpr`, which the test knows to be valid; see
+/// [`OpenMath::from_sexpr`].
+pub fn om(sexpr: &str) -> OpenMath<'static> {
+    OpenMath::from_sexpr(sexpr).expect("is valid")
+}
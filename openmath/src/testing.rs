@@ -0,0 +1,360 @@
+/*! Test support for checking that deserializing borrows from the input; see
+[`OpenMath::borrow_stats`]. (For testing serializers, see [`ser::testing`](crate::ser::testing).)
+
+Reading from a `&str` only has to copy strings that contain escapes (or that are otherwise
+not verbatim in the input); everything else should borrow from it. [`BorrowStats`] counts the
+borrowed and owned strings of an object by what they are, so that tests can assert that e.g.
+symbol names are never copied:
+
+```rust
+# #[cfg(feature = "xml-read")]
+# {
+use openmath::{OMDeserializable, OpenMath};
+
+let xml = r#"<OMA><OMS cd="arith1" name="plus"/><OMV name="x"/><OMSTR>a &amp; b</OMSTR></OMA>"#;
+let stats = OpenMath::from_openmath_xml(xml).expect("is valid").borrow_stats();
+assert_eq!(stats.names.owned, 0);
+assert_eq!(stats.names.borrowed, 2);
+assert_eq!(stats.cds.borrowed, 1);
+// unescaped
+assert_eq!(stats.strings.owned, 1);
+# }
+```
+
+For measuring how much (de)serializing allocates in benchmarks and tests, [`CountingAlloc`]
+counts the allocations of the whole program.
+*/
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    borrow::Cow,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{Attr, OMMaybeForeign, OpenMath, int::I};
+
+/// The numbers of borrowed and owned strings of some category; see [`BorrowStats`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Counts {
+    pub borrowed: usize,
+    pub owned: usize,
+}
+
+impl Counts {
+    // whether the `Cow` is borrowed is the point
+    #[allow(clippy::ptr_arg)]
+    const fn count<B: ToOwned + ?Sized>(&mut self, cow: &Cow<'_, B>) {
+        match cow {
+            Cow::Borrowed(_) => self.borrowed += 1,
+            Cow::Owned(_) => self.owned += 1,
+        }
+    }
+}
+
+impl std::ops::Add for Counts {
+    type Output = Self;
+    #[inline]
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            borrowed: self.borrowed + rhs.borrowed,
+            owned: self.owned + rhs.owned,
+        }
+    }
+}
+
+/// How many of the strings of an object are borrowed, and how many owned; see
+/// [`OpenMath::borrow_stats`] and the [module documentation](self).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct BorrowStats {
+    /// The content dictionaries of [OMS](crate::OMKind::OMS)s, the symbols of
+    /// [OME](crate::OMKind::OME)s and attribute keys
+    pub cds: Counts,
+    /// The names of symbols (as for [`cds`](Self::cds)) and of (bound) variables
+    pub names: Counts,
+    /// Explicit or inherited cdbases of symbols (as for [`cds`](Self::cds))
+    pub cdbases: Counts,
+    /// The contents of [OMSTR](crate::OMKind::OMSTR)s
+    pub strings: Counts,
+    /// The contents of [OMB](crate::OMKind::OMB)s
+    pub bytes: Counts,
+    /// The digits of [OMI](crate::OMKind::OMI)s too large for an [`i128`]
+    pub integers: Counts,
+    /// The encodings and contents of [OMFOREIGN](crate::OMKind::OMFOREIGN)s
+    pub foreign: Counts,
+}
+
+impl BorrowStats {
+    /// The counts of all categories together
+    #[must_use]
+    pub fn total(&self) -> Counts {
+        self.cds
+            + self.names
+            + self.cdbases
+            + self.strings
+            + self.bytes
+            + self.integers
+            + self.foreign
+    }
+
+    #[allow(clippy::ptr_arg)]
+    const fn symbol(
+        &mut self,
+        cd: &Cow<'_, str>,
+        name: &Cow<'_, str>,
+        cdbase: Option<&Cow<'_, str>>,
+    ) {
+        self.cds.count(cd);
+        self.names.count(name);
+        if let Some(cdbase) = cdbase {
+            self.cdbases.count(cdbase);
+        }
+    }
+
+    fn attributes(&mut self, attributes: &[Attr<'_, OMMaybeForeign<'_, OpenMath<'_>>>]) {
+        for a in attributes {
+            self.symbol(&a.key.cd, &a.key.name, a.key.cdbase.as_ref());
+            self.maybe_foreign(&a.value);
+        }
+    }
+
+    fn maybe_foreign(&mut self, om: &OMMaybeForeign<'_, OpenMath<'_>>) {
+        match om {
+            OMMaybeForeign::OM(om) => self.object(om),
+            OMMaybeForeign::Foreign { encoding, value } => {
+                if let Some(encoding) = encoding {
+                    self.foreign.count(encoding);
+                }
+                self.foreign.count(value);
+            }
+        }
+    }
+
+    fn object(&mut self, om: &OpenMath<'_>) {
+        self.attributes(om.attributes());
+        match om {
+            OpenMath::OMI { int, .. } => {
+                if let I::Heap(digits) = &int.0 {
+                    self.integers.count(digits);
+                }
+            }
+            OpenMath::OMF { .. } => (),
+            OpenMath::OMSTR { string, .. } => self.strings.count(string),
+            OpenMath::OMB { bytes, .. } => self.bytes.count(bytes),
+            OpenMath::OMV { name, .. } => self.names.count(name),
+            OpenMath::OMS {
+                cd, name, cdbase, ..
+            } => self.symbol(cd, name, cdbase.as_ref()),
+            OpenMath::OMA {
+                applicant,
+                arguments,
+                ..
+            } => {
+                self.object(applicant);
+                for a in arguments {
+                    self.object(a);
+                }
+            }
+            OpenMath::OME {
+                cd,
+                name,
+                cdbase,
+                arguments,
+                ..
+            } => {
+                self.symbol(cd, name, cdbase.as_ref());
+                for a in arguments {
+                    self.maybe_foreign(a);
+                }
+            }
+            OpenMath::OMBIND {
+                binder,
+                variables,
+                object,
+                ..
+            } => {
+                self.object(binder);
+                for v in variables {
+                    self.names.count(&v.name);
+                    self.attributes(&v.attributes);
+                }
+                self.object(object);
+            }
+        }
+    }
+}
+
+impl OpenMath<'_> {
+    /// Counts the borrowed and owned strings in this object (including its attributes),
+    /// by what they are; see [`BorrowStats`].
+    #[must_use]
+    pub fn borrow_stats(&self) -> BorrowStats {
+        let mut stats = BorrowStats::default();
+        stats.object(self);
+        stats
+    }
+}
+
+/** A [`GlobalAlloc`] that forwards to the [`System`] allocator, counting allocations and
+the bytes allocated; install it as the `#[global_allocator]`, and [`measure`](Self::measure)
+what some code allocates.
+
+The counts are global, so allocations made by other threads meanwhile are included.
+
+```rust
+use openmath::testing::CountingAlloc;
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc::new();
+
+fn main() {
+    let (allocations, v) = GLOBAL.measure(|| vec![0u8; 1000]);
+    assert_eq!(allocations.count, 1);
+    assert_eq!(allocations.live, 1000);
+    drop(v);
+    let (allocations, ()) = GLOBAL.measure(|| drop(vec![0u8; 1000]));
+    assert_eq!((allocations.live, allocations.peak), (0, 1000));
+}
+```
+*/
+#[derive(Debug, Default)]
+pub struct CountingAlloc {
+    allocations: AtomicUsize,
+    allocated: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+/// What some code allocated; see [`CountingAlloc::measure`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Allocations {
+    /// The number of allocations (including reallocations)
+    pub count: usize,
+    /// The bytes allocated and still live afterwards (or 0, if more were freed)
+    pub live: usize,
+    /// The most bytes live at any point, in addition to those live before
+    pub peak: usize,
+}
+
+impl CountingAlloc {
+    /// A new allocator, with all counts 0
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            allocations: AtomicUsize::new(0),
+            allocated: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+        }
+    }
+
+    /// Runs `f`, and returns what it allocated along with its result
+    pub fn measure<R>(&self, f: impl FnOnce() -> R) -> (Allocations, R) {
+        let allocations = self.allocations.load(Ordering::Relaxed);
+        let before = self.allocated.load(Ordering::Relaxed);
+        self.peak.store(before, Ordering::Relaxed);
+        let r = f();
+        let counted = Allocations {
+            count: self.allocations.load(Ordering::Relaxed) - allocations,
+            live: self
+                .allocated
+                .load(Ordering::Relaxed)
+                .saturating_sub(before),
+            peak: self.peak.load(Ordering::Relaxed).saturating_sub(before),
+        };
+        (counted, r)
+    }
+
+    fn grow(&self, by: usize) {
+        self.allocations.fetch_add(1, Ordering::Relaxed);
+        let now = self.allocated.fetch_add(by, Ordering::Relaxed) + by;
+        self.peak.fetch_max(now, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.grow(layout.size());
+        unsafe { System.alloc(layout) }
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.allocated.fetch_sub(layout.size(), Ordering::Relaxed);
+        unsafe { System.dealloc(ptr, layout) }
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        self.grow(new_size);
+        self.allocated.fetch_sub(layout.size(), Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "xml-read")]
+mod tests {
+    use super::*;
+    use crate::de::OMObject;
+
+    const XML: &str = include_str!("../doc/roundtrip.xml");
+
+    const fn borrowed(borrowed: usize) -> Counts {
+        Counts { borrowed, owned: 0 }
+    }
+
+    /// Guards against changes that silently copy strings that could be borrowed
+    #[test]
+    fn zero_copy_xml() {
+        let stats = OMObject::<OpenMath>::from_openmath_xml(XML)
+            .expect("is valid")
+            .borrow_stats();
+        assert_eq!(
+            stats,
+            BorrowStats {
+                cds: borrowed(10),
+                names: borrowed(15),
+                cdbases: borrowed(10),
+                strings: borrowed(3),
+                bytes: borrowed(0),
+                integers: borrowed(0),
+                foreign: borrowed(3),
+            }
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn zero_copy_json() {
+        use crate::{OMSerializable, de::OMFromSerde};
+        let om = OMObject::<OpenMath>::from_openmath_xml(XML).expect("is valid");
+        let json = serde_json::to_string(&om.openmath_serde()).expect("works");
+        let stats = serde_json::from_str::<OMFromSerde<OpenMath>>(&json)
+            .expect("is valid")
+            .into_inner()
+            .borrow_stats();
+        // the attributes of an OMATTR without cdbase are buffered (and hence copied) in
+        // case a cdbase follows them: the attribute of the bound variable `y` (two symbols,
+        // and `y` itself), and the key and OMFOREIGN value of the one of the big integer;
+        // once they are buffered, so is the object, i.e. the attributed OMBIND and OME
+        let copied = |borrowed, owned| Counts { borrowed, owned };
+        assert_eq!(
+            stats,
+            BorrowStats {
+                cds: copied(3, 7),
+                names: copied(5, 10),
+                cdbases: copied(3, 7),
+                strings: copied(1, 2),
+                bytes: borrowed(0),
+                integers: borrowed(0),
+                foreign: copied(0, 3),
+            }
+        );
+
+        // a reader can not be borrowed from
+        let mut reader = serde_json::Deserializer::from_reader(json.as_bytes());
+        let owned = <OMFromSerde<OpenMath> as serde::Deserialize>::deserialize(&mut reader)
+            .expect("is valid")
+            .into_inner()
+            .borrow_stats();
+        assert_eq!(owned.total().borrowed, 0);
+        assert_eq!(
+            owned.total().owned,
+            stats.total().borrowed + stats.total().owned
+        );
+    }
+}
@@ -0,0 +1,67 @@
+use crate::{OM, OMDeserializable, OMSerializable, ser::OMSerializer};
+
+/// Errors when deserializing a [`Uuid`](uuid::Uuid)
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum UuidError {
+    #[error("invalid UUID {value:?}: {reason}")]
+    Invalid { value: String, reason: String },
+    #[error("expected an OMSTR containing a UUID")]
+    Expected,
+}
+
+/// [`Uuid`](uuid::Uuid)s are represented as [`OMSTR`](crate::OMKind::OMSTR)s in their
+/// lowercase hyphenated form (e.g. `67e55044-10b1-426f-9247-bb680e5fe0c8`). When
+/// deserializing, all formats accepted by [`Uuid::parse_str`](uuid::Uuid::parse_str) are
+/// allowed.
+impl OMSerializable for uuid::Uuid {
+    #[inline]
+    fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        serializer.omstr(self.hyphenated())
+    }
+}
+
+impl<'de> OMDeserializable<'de> for uuid::Uuid {
+    type Ret = Self;
+    type Err = UuidError;
+    fn from_openmath(om: OM<'de, Self>, _: &str) -> Result<Self, Self::Err>
+    where
+        Self: Sized,
+    {
+        let OM::OMSTR { string, .. } = om else {
+            return Err(UuidError::Expected);
+        };
+        Self::parse_str(&string).map_err(|e| UuidError::Invalid {
+            value: string.into_owned(),
+            reason: e.to_string(),
+        })
+    }
+}
+
+#[cfg(all(test, feature = "xml-read", feature = "xml-write"))]
+mod tests {
+    use super::*;
+    #[test]
+    fn uuid() {
+        let id = uuid::Uuid::from_u128(0x67e5_5044_10b1_426f_9247_bb68_0e5f_e0c8);
+        let xml = id.xml(false).to_string();
+        assert_eq!(xml, "<OMSTR>67e55044-10b1-426f-9247-bb680e5fe0c8</OMSTR>");
+        assert_eq!(uuid::Uuid::from_openmath_xml(&xml).expect("roundtrips"), id);
+        assert_eq!(
+            uuid::Uuid::from_openmath_xml("<OMSTR>67E5504410B1426F9247BB680E5FE0C8</OMSTR>")
+                .expect("is valid"),
+            id
+        );
+        assert!(
+            uuid::Uuid::from_openmath_xml("<OMSTR>not-a-uuid</OMSTR>")
+                .expect_err("is invalid")
+                .to_string()
+                .contains(r#"invalid UUID "not-a-uuid""#)
+        );
+        assert!(matches!(
+            uuid::Uuid::from_openmath_xml("<OMI>1</OMI>"),
+            Err(crate::de::xml::XmlReadError::Conversion(
+                UuidError::Expected
+            ))
+        ));
+    }
+}
@@ -0,0 +1,550 @@
+use std::borrow::Cow;
+
+use crate::{
+    OM, OMDeserializable, OMSerializable,
+    ser::{AsOMS, OMSerializer, Uri},
+};
+
+/// The content dictionary of matrices and vectors
+pub const LINALG2: &str = "linalg2";
+
+const MATRIX: Uri<'static> = Uri {
+    cdbase: Some(crate::CdBase::OPENMATH),
+    cd: LINALG2,
+    name: "matrix",
+};
+const MATRIXROW: Uri<'static> = Uri {
+    cdbase: Some(crate::CdBase::OPENMATH),
+    cd: LINALG2,
+    name: "matrixrow",
+};
+const VECTOR: Uri<'static> = Uri {
+    cdbase: Some(crate::CdBase::OPENMATH),
+    cd: LINALG2,
+    name: "vector",
+};
+
+/// Errors when constructing or deserializing a [`Matrix`] or [`Vector`]
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum Linalg2Error {
+    #[error("row {row} has {found} entries, but row 0 has {expected}")]
+    RaggedRow {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+    #[error("{found} entries do not make up a {rows}×{cols} matrix")]
+    Dimensions {
+        rows: usize,
+        cols: usize,
+        found: usize,
+    },
+    #[error("unexpected head symbol {cd}#{name}")]
+    UnexpectedHead { cd: String, name: String },
+    #[error("unexpected symbol {cd}#{name}")]
+    UnexpectedSymbol { cd: String, name: String },
+    #[error("argument {index} of linalg2#matrix is not a linalg2#matrixrow")]
+    NotARow { index: usize },
+    #[error("linalg2#{0} is not allowed here")]
+    Misplaced(&'static str),
+    #[error("expected a linalg2#{0}")]
+    Expected(&'static str),
+    #[error("invalid entry: {0}")]
+    Entry(String),
+}
+
+/// A rectangular matrix, represented as `linalg2.matrix(linalg2.matrixrow(…), …)`.
+///
+/// Entries are stored in row-major order; the constructors guarantee that all rows have
+/// the same length.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Matrix<T> {
+    rows: usize,
+    cols: usize,
+    entries: Vec<T>,
+}
+impl<T> Matrix<T> {
+    /// Builds a matrix from its rows.
+    ///
+    /// # Errors
+    /// [`RaggedRow`](Linalg2Error::RaggedRow) if not all rows have the same length.
+    pub fn from_rows(rows: impl IntoIterator<Item = Vec<T>>) -> Result<Self, Linalg2Error> {
+        let mut cols = None;
+        let mut num_rows = 0;
+        let mut entries = Vec::new();
+        for (row, entries_of_row) in rows.into_iter().enumerate() {
+            let expected = *cols.get_or_insert(entries_of_row.len());
+            if entries_of_row.len() != expected {
+                return Err(Linalg2Error::RaggedRow {
+                    row,
+                    expected,
+                    found: entries_of_row.len(),
+                });
+            }
+            entries.extend(entries_of_row);
+            num_rows += 1;
+        }
+        Ok(Self {
+            rows: num_rows,
+            cols: cols.unwrap_or_default(),
+            entries,
+        })
+    }
+
+    /// Builds a `rows`×`cols` matrix from its entries in row-major order.
+    ///
+    /// # Errors
+    /// [`Dimensions`](Linalg2Error::Dimensions) if there are not exactly `rows * cols` entries.
+    pub fn from_row_major(rows: usize, cols: usize, entries: Vec<T>) -> Result<Self, Linalg2Error> {
+        if rows.checked_mul(cols) == Some(entries.len()) {
+            Ok(Self {
+                rows,
+                cols,
+                entries,
+            })
+        } else {
+            Err(Linalg2Error::Dimensions {
+                rows,
+                cols,
+                found: entries.len(),
+            })
+        }
+    }
+
+    /// The number of rows
+    #[inline]
+    #[must_use]
+    pub const fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// The number of columns
+    #[inline]
+    #[must_use]
+    pub const fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// The entry in the given row and column, if in range
+    #[must_use]
+    pub fn get(&self, row: usize, col: usize) -> Option<&T> {
+        if row < self.rows && col < self.cols {
+            self.entries.get(row * self.cols + col)
+        } else {
+            None
+        }
+    }
+
+    /// The given row, if in range
+    #[must_use]
+    pub fn row(&self, row: usize) -> Option<&[T]> {
+        if row < self.rows {
+            self.entries.get(row * self.cols..(row + 1) * self.cols)
+        } else {
+            None
+        }
+    }
+
+    /// Iterates over all rows
+    #[must_use]
+    pub fn iter_rows(&self) -> impl ExactSizeIterator<Item = &[T]> {
+        (0..self.rows).map(|row| &self.entries[row * self.cols..(row + 1) * self.cols])
+    }
+
+    /// All entries in row-major order
+    #[inline]
+    #[must_use]
+    pub fn entries(&self) -> &[T] {
+        &self.entries
+    }
+
+    /// Returns all entries in row-major order
+    #[inline]
+    #[must_use]
+    pub fn into_entries(self) -> Vec<T> {
+        self.entries
+    }
+}
+
+/// A vector, represented as `linalg2.vector(…)`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Vector<T>(pub Vec<T>);
+impl<T> From<Vec<T>> for Vector<T> {
+    #[inline]
+    fn from(value: Vec<T>) -> Self {
+        Self(value)
+    }
+}
+impl<T> FromIterator<T> for Vector<T> {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl<T: OMSerializable> OMSerializable for Matrix<T> {
+    fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        struct Row<'m, T>(&'m [T]);
+        impl<T: OMSerializable> OMSerializable for Row<'_, T> {
+            #[inline]
+            fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+                serializer.oma(MATRIXROW.as_oms(), self.0.iter())
+            }
+        }
+        serializer.oma(MATRIX.as_oms(), self.iter_rows().map(Row))
+    }
+}
+
+impl<T: OMSerializable> OMSerializable for Vector<T> {
+    #[inline]
+    fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        serializer.oma(VECTOR.as_oms(), self.0.iter())
+    }
+}
+
+/// The intermediate [`Ret`](OMDeserializable::Ret)-type when deserializing a [`Matrix`]
+/// or [`Vector`]; either (part of) an entry, or (part of) a `linalg2` object.
+pub struct Linalg2Partial<'de, T: OMDeserializable<'de>>(Partial<'de, T>);
+
+enum Partial<'de, T: OMDeserializable<'de>> {
+    Entry(T::Ret),
+    /// a symbol that is neither a `linalg2` constructor nor (part of) an entry
+    Symbol {
+        cd: Cow<'de, str>,
+        name: Cow<'de, str>,
+    },
+    Constructor(&'static str),
+    Row(Vec<T>),
+    Matrix(Matrix<T>),
+    Vector(Vector<T>),
+}
+
+impl<'de, T: OMDeserializable<'de>> Partial<'de, T> {
+    fn from_openmath(
+        om: OM<'de, Linalg2Partial<'de, T>>,
+        cdbase: &str,
+    ) -> Result<Self, Linalg2Error> {
+        match om {
+            OM::OMS { cd, name, .. }
+                if cd == LINALG2
+                    && matches!(&*name, "matrix" | "matrixrow" | "vector")
+                    && crate::cdbase::eq_normalized(cdbase, crate::CD_BASE) =>
+            {
+                Ok(Self::Constructor(match &*name {
+                    "matrix" => MATRIX.name,
+                    "matrixrow" => MATRIXROW.name,
+                    _ => VECTOR.name,
+                }))
+            }
+            OM::OMS {
+                cdbase: own_cdbase,
+                cd,
+                name,
+                attrs,
+            } => Ok(Self::entry(
+                OM::OMS {
+                    cdbase: own_cdbase,
+                    cd: cd.clone(),
+                    name: name.clone(),
+                    attrs,
+                },
+                cdbase,
+            )
+            .unwrap_or(Self::Symbol { cd, name })),
+            OM::OMA {
+                applicant: Linalg2Partial(Self::Constructor(c)),
+                arguments,
+                ..
+            } => match c {
+                "matrixrow" => Ok(Self::Row(Self::entries(arguments)?)),
+                "vector" => Ok(Self::Vector(Vector(Self::entries(arguments)?))),
+                _ => Matrix::from_rows(
+                    arguments
+                        .into_iter()
+                        .enumerate()
+                        .map(|(index, a)| match a.0 {
+                            Self::Row(r) => Ok(r),
+                            _ => Err(Linalg2Error::NotARow { index }),
+                        })
+                        .collect::<Result<Vec<_>, _>>()?,
+                )
+                .map(Self::Matrix),
+            },
+            OM::OMA {
+                applicant: Linalg2Partial(Self::Symbol { cd, name }),
+                ..
+            } => Err(Linalg2Error::UnexpectedHead {
+                cd: cd.into_owned(),
+                name: name.into_owned(),
+            }),
+            om => Self::entry(om, cdbase),
+        }
+    }
+
+    fn entry(om: OM<'de, Linalg2Partial<'de, T>>, cdbase: &str) -> Result<Self, Linalg2Error> {
+        let om = om.try_map(|p| match p.0 {
+            Self::Entry(e) => Ok(e),
+            other => Err(other.misplaced()),
+        })?;
+        T::from_openmath(om, cdbase)
+            .map(Self::Entry)
+            .map_err(|e| Linalg2Error::Entry(e.to_string()))
+    }
+
+    fn entries(
+        arguments: impl IntoIterator<Item = Linalg2Partial<'de, T>>,
+    ) -> Result<Vec<T>, Linalg2Error> {
+        arguments
+            .into_iter()
+            .map(|a| match a.0 {
+                Self::Entry(e) => e
+                    .try_into()
+                    .map_err(|e| Linalg2Error::Entry(format!("{e:?}"))),
+                other => Err(other.misplaced()),
+            })
+            .collect()
+    }
+
+    fn misplaced(self) -> Linalg2Error {
+        match self {
+            Self::Symbol { cd, name } => Linalg2Error::UnexpectedSymbol {
+                cd: cd.into_owned(),
+                name: name.into_owned(),
+            },
+            Self::Constructor(c) => Linalg2Error::Misplaced(c),
+            Self::Row(_) => Linalg2Error::Misplaced(MATRIXROW.name),
+            Self::Matrix(_) => Linalg2Error::Misplaced(MATRIX.name),
+            Self::Vector(_) => Linalg2Error::Misplaced(VECTOR.name),
+            Self::Entry(_) => Linalg2Error::Entry("unexpected entry".to_string()),
+        }
+    }
+}
+
+impl<'de, T: OMDeserializable<'de>> TryFrom<Linalg2Partial<'de, T>> for Matrix<T> {
+    type Error = Linalg2Error;
+    fn try_from(value: Linalg2Partial<'de, T>) -> Result<Self, Self::Error> {
+        match value.0 {
+            Partial::Matrix(m) => Ok(m),
+            Partial::Symbol { cd, name } => Err(Linalg2Error::UnexpectedSymbol {
+                cd: cd.into_owned(),
+                name: name.into_owned(),
+            }),
+            _ => Err(Linalg2Error::Expected(MATRIX.name)),
+        }
+    }
+}
+
+impl<'de, T: OMDeserializable<'de>> TryFrom<Linalg2Partial<'de, T>> for Vector<T> {
+    type Error = Linalg2Error;
+    fn try_from(value: Linalg2Partial<'de, T>) -> Result<Self, Self::Error> {
+        match value.0 {
+            Partial::Vector(v) => Ok(v),
+            Partial::Symbol { cd, name } => Err(Linalg2Error::UnexpectedSymbol {
+                cd: cd.into_owned(),
+                name: name.into_owned(),
+            }),
+            _ => Err(Linalg2Error::Expected(VECTOR.name)),
+        }
+    }
+}
+
+impl<'de, T: OMDeserializable<'de>> OMDeserializable<'de> for Matrix<T> {
+    type Ret = Linalg2Partial<'de, T>;
+    type Err = Linalg2Error;
+    #[inline]
+    fn from_openmath(om: OM<'de, Self::Ret>, cdbase: &str) -> Result<Self::Ret, Self::Err>
+    where
+        Self: Sized,
+    {
+        Partial::from_openmath(om, cdbase).map(Linalg2Partial)
+    }
+}
+
+impl<'de, T: OMDeserializable<'de>> OMDeserializable<'de> for Vector<T> {
+    type Ret = Linalg2Partial<'de, T>;
+    type Err = Linalg2Error;
+    #[inline]
+    fn from_openmath(om: OM<'de, Self::Ret>, cdbase: &str) -> Result<Self::Ret, Self::Err>
+    where
+        Self: Sized,
+    {
+        Partial::from_openmath(om, cdbase).map(Linalg2Partial)
+    }
+}
+
+#[cfg(feature = "ndarray")]
+impl<T> From<ndarray::Array2<T>> for Matrix<T> {
+    fn from(value: ndarray::Array2<T>) -> Self {
+        let (rows, cols) = value.dim();
+        Self {
+            rows,
+            cols,
+            entries: value.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(feature = "ndarray")]
+impl<T> From<Matrix<T>> for ndarray::Array2<T> {
+    fn from(value: Matrix<T>) -> Self {
+        Self::from_shape_vec((value.rows, value.cols), value.entries)
+            .unwrap_or_else(|_| unreachable!("matrices are rectangular"))
+    }
+}
+
+#[cfg(feature = "ndarray")]
+impl<T> From<ndarray::Array1<T>> for Vector<T> {
+    #[inline]
+    fn from(value: ndarray::Array1<T>) -> Self {
+        Self(value.into_iter().collect())
+    }
+}
+
+#[cfg(feature = "ndarray")]
+impl<T> From<Vector<T>> for ndarray::Array1<T> {
+    #[inline]
+    fn from(value: Vector<T>) -> Self {
+        Self::from_vec(value.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Int;
+
+    #[cfg_attr(not(feature = "xml"), allow(dead_code))]
+    fn floats() -> Matrix<f64> {
+        Matrix::from_rows((0..3).map(|r| (0..4).map(|c| f64::from(r * 4 + c) + 0.5).collect()))
+            .expect("is rectangular")
+    }
+
+    #[cfg_attr(not(feature = "xml"), allow(dead_code))]
+    fn big_ints() -> Matrix<Int<'static>> {
+        Matrix::from_rows((0..3).map(|r| {
+            (0..4)
+                .map(|c| {
+                    Int::from_string(format!("{r}{c}123456789012345678901234567890"))
+                        .expect("is an integer")
+                })
+                .collect()
+        }))
+        .expect("is rectangular")
+    }
+
+    #[cfg(all(feature = "xml-read", feature = "xml-write"))]
+    #[test]
+    fn matrix_xml() {
+        let m = floats();
+        assert_eq!((m.rows(), m.cols()), (3, 4));
+        assert_eq!(m.get(1, 2), Some(&6.5));
+        let xml = m.xml(false).to_string();
+        assert!(xml.starts_with(
+            r#"<OMA><OMS cd="linalg2" name="matrix"/><OMA><OMS cd="linalg2" name="matrixrow"/><OMF dec="0.5"/>"#
+        ));
+        assert_eq!(
+            Matrix::<f64>::from_openmath_xml(&xml).expect("roundtrips"),
+            m
+        );
+
+        let m = big_ints();
+        let xml = m.xml(true).to_string();
+        assert_eq!(
+            Matrix::<Int>::from_openmath_xml(&xml).expect("roundtrips"),
+            m
+        );
+
+        let v: Vector<i32> = (1..=3).collect();
+        let xml = v.xml(false).to_string();
+        assert_eq!(
+            Vector::<i32>::from_openmath_xml(&xml).expect("roundtrips"),
+            v
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn matrix_json() {
+        use crate::de::OMFromSerde;
+        let m = floats();
+        let json = serde_json::to_string(&m.openmath_serde()).expect("serializes");
+        let r = serde_json::from_str::<OMFromSerde<Matrix<f64>>>(&json).expect("roundtrips");
+        assert_eq!(r.into_inner(), m);
+
+        let m = big_ints();
+        let json = serde_json::to_string(&m.openmath_serde()).expect("serializes");
+        let r = serde_json::from_str::<OMFromSerde<Matrix<Int>>>(&json).expect("roundtrips");
+        assert_eq!(r.into_inner(), m);
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn errors() {
+        assert_eq!(
+            Matrix::from_rows([vec![1, 2], vec![3]]),
+            Err(Linalg2Error::RaggedRow {
+                row: 1,
+                expected: 2,
+                found: 1
+            })
+        );
+        assert_eq!(
+            Matrix::from_row_major(2, 2, vec![1, 2, 3]),
+            Err(Linalg2Error::Dimensions {
+                rows: 2,
+                cols: 2,
+                found: 3
+            })
+        );
+
+        let ragged = r#"<OMA><OMS cd="linalg2" name="matrix"/>
+            <OMA><OMS cd="linalg2" name="matrixrow"/><OMF dec="1"/><OMF dec="2"/></OMA>
+            <OMA><OMS cd="linalg2" name="matrixrow"/><OMF dec="3"/></OMA>
+        </OMA>"#;
+        assert!(
+            Matrix::<f64>::from_openmath_xml(ragged)
+                .expect_err("is ragged")
+                .to_string()
+                .contains("row 1 has 1 entries, but row 0 has 2")
+        );
+
+        let head = r#"<OMA><OMS cd="linalg1" name="matrix"/>
+            <OMA><OMS cd="linalg2" name="matrixrow"/><OMF dec="1"/></OMA>
+        </OMA>"#;
+        assert!(
+            Matrix::<f64>::from_openmath_xml(head)
+                .expect_err("wrong head")
+                .to_string()
+                .contains("unexpected head symbol linalg1#matrix")
+        );
+
+        let not_a_row = r#"<OMA><OMS cd="linalg2" name="matrix"/><OMF dec="1"/></OMA>"#;
+        assert!(
+            Matrix::<f64>::from_openmath_xml(not_a_row)
+                .expect_err("not a row")
+                .to_string()
+                .contains("argument 0 of linalg2#matrix is not a linalg2#matrixrow")
+        );
+
+        let vector = r#"<OMA><OMS cd="linalg2" name="vector"/><OMF dec="1"/></OMA>"#;
+        assert!(matches!(
+            Matrix::<f64>::from_openmath_xml(vector),
+            Err(crate::de::xml::XmlReadError::NotFullyConvertible)
+        ));
+        assert_eq!(
+            Vector::<f64>::from_openmath_xml(vector).expect("is a vector"),
+            Vector(vec![1.0])
+        );
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn ndarray() {
+        let m = floats();
+        let a = ndarray::Array2::from(m.clone());
+        assert_eq!(a.dim(), (3, 4));
+        assert!((a[[1, 2]] - 6.5).abs() < f64::EPSILON);
+        assert_eq!(Matrix::from(a.t().to_owned()).get(2, 1), Some(&6.5));
+        assert_eq!(Matrix::from(a), m);
+    }
+}
@@ -0,0 +1,29 @@
+/*! Typed Rust counterparts of objects from official
+<span style="font-variant:small-caps;">OpenMath</span> content dictionaries, implementing
+[`OMSerializable`](crate::OMSerializable) and [`OMDeserializable`](crate::OMDeserializable).
+
+- [`Matrix`] and [`Vector`] follow the [`linalg2`](https://openmath.org/cd/linalg2.xhtml)
+  content dictionary; with the `ndarray` feature, they convert from and into
+  [`Array2`](https://docs.rs/ndarray/latest/ndarray/type.Array2.html) and
+  [`Array1`](https://docs.rs/ndarray/latest/ndarray/type.Array1.html).
+- [`Duration`](std::time::Duration)s are `arith1.times(n, units_time1.second)`, where `n`
+  is an [`OMI`](crate::OMKind::OMI) for whole seconds and an exact `nums1.rational`
+  otherwise.
+- (with the `chrono` feature) `chrono::DateTime<Utc>`s are [RFC 3339](https://www.rfc-editor.org/rfc/rfc3339)
+  [`OMSTR`](crate::OMKind::OMSTR)s, attributed with `sts.type` ↦ [`rfc3339.datetime`](DATETIME).
+- (with the `uuid` feature) `uuid::Uuid`s are [`OMSTR`](crate::OMKind::OMSTR)s in their
+  lowercase hyphenated form.
+
+These are the mappings used throughout this crate, so that different services interoperate
+by default; the [`Duration`](std::time::Duration) and date-time ones share the
+[`Ret`](crate::OMDeserializable::Ret)-type [`TimePartial`].
+*/
+
+#[cfg(feature = "uuid")]
+mod id;
+mod linalg2;
+mod time;
+#[cfg(feature = "uuid")]
+pub use id::UuidError;
+pub use linalg2::{LINALG2, Linalg2Error, Linalg2Partial, Matrix, Vector};
+pub use time::{DATETIME, TimeError, TimePartial};
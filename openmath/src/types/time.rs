@@ -0,0 +1,421 @@
+use std::{borrow::Cow, time::Duration};
+
+use crate::{
+    Int, OM, OMDeserializable, OMSerializable,
+    ser::{AsOMS, OMSerializer, Uri},
+};
+
+const TIMES: Uri<'static> = Uri {
+    cdbase: Some(crate::CdBase::OPENMATH),
+    cd: "arith1",
+    name: "times",
+};
+const RATIONAL: Uri<'static> = Uri {
+    cdbase: Some(crate::CdBase::OPENMATH),
+    cd: "nums1",
+    name: "rational",
+};
+const SECOND: Uri<'static> = Uri {
+    cdbase: Some(crate::CdBase::OPENMATH),
+    cd: "units_time1",
+    name: "second",
+};
+#[cfg(feature = "chrono")]
+const TYPE: Uri<'static> = Uri {
+    cdbase: Some(crate::CdBase::OPENMATH),
+    cd: "sts",
+    name: "type",
+};
+
+/// The type of date-times (`rfc3339.datetime`).
+///
+/// An [`OMSTR`](crate::OMKind::OMSTR) attributed with `sts.type` ↦ `rfc3339.datetime`
+/// contains an [RFC 3339](https://www.rfc-editor.org/rfc/rfc3339) date-time. The symbol
+/// inherits the cdbase, which is ignored when deserializing.
+pub const DATETIME: Uri<'static> = Uri {
+    cdbase: None,
+    cd: "rfc3339",
+    name: "datetime",
+};
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+/// Errors when deserializing a [`Duration`] or date-time
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TimeError {
+    #[error("invalid RFC 3339 date-time {value:?}: {reason}")]
+    InvalidDateTime { value: String, reason: String },
+    #[error("unexpected type {cd}#{name}; expected rfc3339#datetime")]
+    UnexpectedType { cd: String, name: String },
+    #[error("unexpected head symbol {cd}#{name}")]
+    UnexpectedHead { cd: String, name: String },
+    #[error("unexpected symbol {cd}#{name}")]
+    UnexpectedSymbol { cd: String, name: String },
+    #[error("invalid duration: {0}")]
+    InvalidDuration(&'static str),
+    #[error("expected {0}")]
+    Expected(&'static str),
+}
+
+/// `a/b` in lowest terms
+struct Rational(u128, u128);
+impl OMSerializable for Rational {
+    #[inline]
+    fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        serializer.oma(
+            RATIONAL.as_oms(),
+            [Int::from(self.0), Int::from(self.1)].iter(),
+        )
+    }
+}
+
+/// [`Duration`]s are represented as `arith1.times(n, units_time1.second)`, where `n` is an
+/// [`OMI`](crate::OMKind::OMI) for whole seconds and an (exact) `nums1.rational` otherwise.
+/// When deserializing, an [`OMF`](crate::OMKind::OMF) is accepted for `n` as well.
+impl OMSerializable for Duration {
+    fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        let amount = if self.subsec_nanos() == 0 {
+            either::Either::Left(Int::from(self.as_secs()))
+        } else {
+            let nanos = self.as_nanos();
+            let mut gcd = (nanos, NANOS_PER_SEC);
+            while gcd.1 != 0 {
+                gcd = (gcd.1, gcd.0 % gcd.1);
+            }
+            either::Either::Right(Rational(nanos / gcd.0, NANOS_PER_SEC / gcd.0))
+        };
+        serializer.oma(
+            TIMES.as_oms(),
+            [
+                either::Either::Left(amount),
+                either::Either::Right(SECOND.as_oms()),
+            ]
+            .iter(),
+        )
+    }
+}
+
+/// The intermediate [`Ret`](OMDeserializable::Ret)-type when deserializing a [`Duration`]
+/// or date-time.
+pub struct TimePartial<'de>(Partial<'de>);
+
+enum Partial<'de> {
+    Int(Int<'de>),
+    Float(f64),
+    Rational(Int<'de>, Int<'de>),
+    Times,
+    RationalHead,
+    Second,
+    Symbol {
+        cd: Cow<'de, str>,
+        name: Cow<'de, str>,
+    },
+    Duration(Duration),
+    #[cfg(feature = "chrono")]
+    DateTime(chrono::DateTime<chrono::Utc>),
+}
+
+impl<'de> Partial<'de> {
+    fn symbol(cd: Cow<'de, str>, name: Cow<'de, str>, cdbase: &str) -> Self {
+        if !crate::cdbase::eq_normalized(cdbase, crate::CD_BASE) {
+            return Self::Symbol { cd, name };
+        }
+        match (&*cd, &*name) {
+            ("arith1", "times") => Self::Times,
+            ("nums1", "rational") => Self::RationalHead,
+            ("units_time1", "second") => Self::Second,
+            _ => Self::Symbol { cd, name },
+        }
+    }
+
+    fn duration(om: OM<'de, TimePartial<'de>>, cdbase: &str) -> Result<Self, TimeError> {
+        match om {
+            OM::OMI { int, .. } => Ok(Self::Int(int)),
+            OM::OMF { float, .. } => Ok(Self::Float(float)),
+            OM::OMS { cd, name, .. } => Ok(Self::symbol(cd, name, cdbase)),
+            OM::OMA {
+                applicant: TimePartial(Self::RationalHead),
+                arguments,
+                ..
+            } => match <[_; 2]>::try_from(arguments.into_iter().collect::<Vec<_>>()) {
+                Ok([TimePartial(Self::Int(a)), TimePartial(Self::Int(b))]) => {
+                    Ok(Self::Rational(a, b))
+                }
+                _ => Err(TimeError::Expected("two OMIs in nums1#rational")),
+            },
+            OM::OMA {
+                applicant: TimePartial(Self::Times),
+                arguments,
+                ..
+            } => match <[_; 2]>::try_from(arguments.into_iter().collect::<Vec<_>>()) {
+                Ok([TimePartial(amount), TimePartial(Self::Second)]) => {
+                    amount.seconds().map(Self::Duration)
+                }
+                _ => Err(TimeError::Expected(
+                    "an amount and units_time1#second in arith1#times",
+                )),
+            },
+            OM::OMA {
+                applicant: TimePartial(Self::Symbol { cd, name }),
+                ..
+            } => Err(TimeError::UnexpectedHead {
+                cd: cd.into_owned(),
+                name: name.into_owned(),
+            }),
+            _ => Err(TimeError::Expected("arith1#times(…, units_time1#second)")),
+        }
+    }
+
+    fn seconds(self) -> Result<Duration, TimeError> {
+        const TOO_LARGE: TimeError = TimeError::InvalidDuration("negative or too large");
+        match self {
+            Self::Int(i) => i
+                .is_u128()
+                .and_then(|i| u64::try_from(i).ok())
+                .map(Duration::from_secs)
+                .ok_or(TOO_LARGE),
+            Self::Rational(a, b) => {
+                let (Some(a), Some(b)) = (a.is_u128(), b.is_u128()) else {
+                    return Err(TOO_LARGE);
+                };
+                if b == 0 {
+                    return Err(TimeError::InvalidDuration("zero denominator"));
+                }
+                let nanos = a.checked_mul(NANOS_PER_SEC).ok_or(TOO_LARGE)?;
+                if nanos % b != 0 {
+                    return Err(TimeError::InvalidDuration(
+                        "not a whole number of nanoseconds",
+                    ));
+                }
+                let nanos = nanos / b;
+                let secs = u64::try_from(nanos / NANOS_PER_SEC).map_err(|_| TOO_LARGE)?;
+                #[allow(clippy::cast_possible_truncation)]
+                Ok(Duration::new(secs, (nanos % NANOS_PER_SEC) as u32))
+            }
+            Self::Float(f) => Duration::try_from_secs_f64(f).map_err(|_| TOO_LARGE),
+            other => Err(other.misplaced()),
+        }
+    }
+
+    fn misplaced(self) -> TimeError {
+        match self {
+            Self::Symbol { cd, name } => TimeError::UnexpectedSymbol {
+                cd: cd.into_owned(),
+                name: name.into_owned(),
+            },
+            Self::Times => TimeError::UnexpectedSymbol {
+                cd: TIMES.cd.to_string(),
+                name: TIMES.name.to_string(),
+            },
+            Self::RationalHead => TimeError::UnexpectedSymbol {
+                cd: RATIONAL.cd.to_string(),
+                name: RATIONAL.name.to_string(),
+            },
+            Self::Second => TimeError::UnexpectedSymbol {
+                cd: SECOND.cd.to_string(),
+                name: SECOND.name.to_string(),
+            },
+            _ => TimeError::Expected("an amount of seconds"),
+        }
+    }
+}
+
+impl<'de> TryFrom<TimePartial<'de>> for Duration {
+    type Error = TimeError;
+    fn try_from(value: TimePartial<'de>) -> Result<Self, Self::Error> {
+        match value.0 {
+            Partial::Duration(d) => Ok(d),
+            Partial::Symbol { cd, name } => Err(TimeError::UnexpectedSymbol {
+                cd: cd.into_owned(),
+                name: name.into_owned(),
+            }),
+            _ => Err(TimeError::Expected("arith1#times(…, units_time1#second)")),
+        }
+    }
+}
+
+impl<'de> OMDeserializable<'de> for Duration {
+    type Ret = TimePartial<'de>;
+    type Err = TimeError;
+    #[inline]
+    fn from_openmath(om: OM<'de, Self::Ret>, cdbase: &str) -> Result<Self::Ret, Self::Err>
+    where
+        Self: Sized,
+    {
+        Partial::duration(om, cdbase).map(TimePartial)
+    }
+}
+
+/// Date-times are represented as [`OMSTR`](crate::OMKind::OMSTR)s in
+/// [RFC 3339](https://www.rfc-editor.org/rfc/rfc3339) format (e.g. `2024-02-29T12:00:00Z`),
+/// attributed with `sts.type` ↦ [`rfc3339.datetime`](DATETIME). When deserializing,
+/// the attribution is optional, and arbitrary offsets are converted to UTC.
+#[cfg(feature = "chrono")]
+impl OMSerializable for chrono::DateTime<chrono::Utc> {
+    fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+        struct DateTimeType;
+        impl OMSerializable for DateTimeType {
+            #[inline]
+            fn as_openmath<'s, S: OMSerializer<'s>>(&self, serializer: S) -> Result<S::Ok, S::Err> {
+                DATETIME.as_oms().as_openmath(serializer)
+            }
+        }
+        serializer.omattr(
+            std::iter::once((&TYPE, &DateTimeType)),
+            self.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true),
+        )
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl<'de> TryFrom<TimePartial<'de>> for chrono::DateTime<chrono::Utc> {
+    type Error = TimeError;
+    fn try_from(value: TimePartial<'de>) -> Result<Self, Self::Error> {
+        match value.0 {
+            Partial::DateTime(d) => Ok(d),
+            _ => Err(TimeError::Expected("an RFC 3339 date-time")),
+        }
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl<'de> OMDeserializable<'de> for chrono::DateTime<chrono::Utc> {
+    type Ret = TimePartial<'de>;
+    type Err = TimeError;
+    fn from_openmath(om: OM<'de, Self::Ret>, cdbase: &str) -> Result<Self::Ret, Self::Err>
+    where
+        Self: Sized,
+    {
+        match om {
+            OM::OMS { cd, name, .. } => Ok(TimePartial(Partial::Symbol { cd, name })),
+            OM::OMSTR { string, attrs } => {
+                for attr in attrs {
+                    if attr.cd() != TYPE.cd
+                        || attr.name() != TYPE.name
+                        || !crate::cdbase::eq_normalized(
+                            attr.cdbase().unwrap_or(cdbase),
+                            crate::CD_BASE,
+                        )
+                    {
+                        continue;
+                    }
+                    match attr.value {
+                        crate::OMMaybeForeign::OM(TimePartial(Partial::Symbol { cd, name }))
+                            if cd == DATETIME.cd && name == DATETIME.name => {}
+                        crate::OMMaybeForeign::OM(TimePartial(Partial::Symbol { cd, name })) => {
+                            return Err(TimeError::UnexpectedType {
+                                cd: cd.into_owned(),
+                                name: name.into_owned(),
+                            });
+                        }
+                        _ => return Err(TimeError::Expected("a symbol as sts#type")),
+                    }
+                }
+                chrono::DateTime::parse_from_rfc3339(&string)
+                    .map(|d| TimePartial(Partial::DateTime(d.to_utc())))
+                    .map_err(|e| TimeError::InvalidDateTime {
+                        value: string.into_owned(),
+                        reason: e.to_string(),
+                    })
+            }
+            _ => Err(TimeError::Expected(
+                "an OMSTR containing an RFC 3339 date-time",
+            )),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "xml-read", feature = "xml-write"))]
+mod tests {
+    use super::*;
+    #[test]
+    fn duration() {
+        for (d, xml) in [
+            (
+                Duration::from_secs(90),
+                r#"<OMA><OMS cd="arith1" name="times"/><OMI>90</OMI><OMS cd="units_time1" name="second"/></OMA>"#,
+            ),
+            (
+                Duration::from_millis(1500),
+                r#"<OMA><OMS cd="arith1" name="times"/><OMA><OMS cd="nums1" name="rational"/><OMI>3</OMI><OMI>2</OMI></OMA><OMS cd="units_time1" name="second"/></OMA>"#,
+            ),
+            (
+                Duration::new(u64::MAX, 999_999_999),
+                r#"<OMA><OMS cd="arith1" name="times"/><OMA><OMS cd="nums1" name="rational"/><OMI>18446744073709551615999999999</OMI><OMI>1000000000</OMI></OMA><OMS cd="units_time1" name="second"/></OMA>"#,
+            ),
+        ] {
+            assert_eq!(d.xml(false).to_string(), xml);
+            assert_eq!(Duration::from_openmath_xml(xml).expect("roundtrips"), d);
+        }
+        let float = r#"<OMA><OMS cd="arith1" name="times"/><OMF dec="0.25"/><OMS cd="units_time1" name="second"/></OMA>"#;
+        assert_eq!(
+            Duration::from_openmath_xml(float).expect("is valid"),
+            Duration::from_millis(250)
+        );
+    }
+    #[test]
+    fn duration_errors() {
+        let err = |xml: &str| {
+            Duration::from_openmath_xml(xml)
+                .expect_err("is invalid")
+                .to_string()
+        };
+        assert!(
+            err(r#"<OMA><OMS cd="arith1" name="plus"/><OMI>1</OMI><OMS cd="units_time1" name="second"/></OMA>"#)
+                .contains("unexpected head symbol arith1#plus")
+        );
+        assert!(
+            err(r#"<OMA><OMS cd="arith1" name="times"/><OMI>-1</OMI><OMS cd="units_time1" name="second"/></OMA>"#)
+                .contains("negative or too large")
+        );
+        assert!(
+            err(r#"<OMA><OMS cd="arith1" name="times"/><OMI>1</OMI><OMS cd="units_time1" name="minute"/></OMA>"#)
+                .contains("an amount and units_time1#second")
+        );
+        assert!(
+            err(r#"<OMA><OMS cd="arith1" name="times"/><OMA><OMS cd="nums1" name="rational"/><OMI>1</OMI><OMI>3</OMI></OMA><OMS cd="units_time1" name="second"/></OMA>"#)
+                .contains("not a whole number of nanoseconds")
+        );
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn datetime() {
+        use chrono::{DateTime, TimeZone, Utc};
+        let d = Utc
+            .with_ymd_and_hms(2024, 2, 29, 12, 30, 0)
+            .single()
+            .expect("is valid");
+        let xml = d.xml(false).to_string();
+        assert_eq!(
+            xml,
+            r#"<OMATTR><OMATP><OMS cd="sts" name="type"/><OMS cd="rfc3339" name="datetime"/></OMATP><OMSTR>2024-02-29T12:30:00Z</OMSTR></OMATTR>"#
+        );
+        assert_eq!(
+            DateTime::<Utc>::from_openmath_xml(&xml).expect("roundtrips"),
+            d
+        );
+        assert_eq!(
+            DateTime::<Utc>::from_openmath_xml("<OMSTR>2024-02-29T14:30:00+02:00</OMSTR>")
+                .expect("is valid"),
+            d
+        );
+
+        let err = DateTime::<Utc>::from_openmath_xml("<OMSTR>29.02.2024</OMSTR>")
+            .expect_err("is invalid")
+            .to_string();
+        assert!(
+            err.contains(r#"invalid RFC 3339 date-time "29.02.2024""#),
+            "{err}"
+        );
+        let err = DateTime::<Utc>::from_openmath_xml(
+            r#"<OMATTR><OMATP><OMS cd="sts" name="type"/><OMS cd="setname1" name="N"/></OMATP><OMSTR>2024-02-29T12:30:00Z</OMSTR></OMATTR>"#,
+        )
+        .expect_err("wrong type")
+        .to_string();
+        assert!(
+            err.contains("unexpected type setname1#N; expected rfc3339#datetime"),
+            "{err}"
+        );
+    }
+}
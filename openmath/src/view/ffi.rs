@@ -0,0 +1,112 @@
+/*! `extern "C"` accessors for [`OMView`](super::OMView)s.
+
+C code receives an [`OMViewRaw`] (via [`OMView::as_raw`](super::OMView::as_raw)) and can
+either read its arrays directly (the layout of all involved types is `#[repr(C)]`) or use the
+bounds-checked accessors in this module. A C header can be generated with
+[cbindgen](https://docs.rs/cbindgen) from the sources of the [`view`](super) module.
+*/
+
+use super::{OMViewAttr, OMViewNode, OMViewRaw};
+
+/// Returned by [`openmath_view_child`] if there is no such child
+pub const OPENMATH_VIEW_NONE: u32 = u32::MAX;
+
+/// # Safety
+/// `view` must be null or point to an [`OMViewRaw`] obtained from a live
+/// [`OMView`](super::OMView).
+unsafe fn node_at(view: *const OMViewRaw<'_>, index: u32) -> Option<&OMViewNode<'_>> {
+    // SAFETY: by the caller
+    let view = unsafe { view.as_ref() }?;
+    (index < view.nodes_len).then(|| {
+        // SAFETY: `index` is in bounds
+        unsafe { &*view.nodes.add(index as usize) }
+    })
+}
+
+/// The node with index `index`, or null if there is none; the root node has index 0.
+///
+/// # Safety
+/// `view` must be null or point to an [`OMViewRaw`] obtained from a live
+/// [`OMView`](super::OMView).
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn openmath_view_node(
+    view: *const OMViewRaw<'_>,
+    index: u32,
+) -> *const OMViewNode<'_> {
+    // SAFETY: by the caller
+    unsafe { node_at(view, index) }.map_or(std::ptr::null(), std::ptr::from_ref)
+}
+
+/// The index of the `i`th child of the node with index `node`, or [`OPENMATH_VIEW_NONE`].
+///
+/// # Safety
+/// `view` must be null or point to an [`OMViewRaw`] obtained from a live
+/// [`OMView`](super::OMView).
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn openmath_view_child(view: *const OMViewRaw<'_>, node: u32, i: u32) -> u32 {
+    // SAFETY: by the caller
+    let Some(node) = (unsafe { node_at(view, node) }) else {
+        return OPENMATH_VIEW_NONE;
+    };
+    if i >= node.children.len {
+        return OPENMATH_VIEW_NONE;
+    }
+    // SAFETY: `view` is not null, and the children of a node are in bounds
+    unsafe { *(*view).edges.add((node.children.start + i) as usize) }
+}
+
+/// The `i`th attribute of the node with index `node`, or null if there is none; its value
+/// is the node with index [`value`](OMViewAttr::value).
+///
+/// # Safety
+/// `view` must be null or point to an [`OMViewRaw`] obtained from a live
+/// [`OMView`](super::OMView).
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn openmath_view_attribute(
+    view: *const OMViewRaw<'_>,
+    node: u32,
+    i: u32,
+) -> *const OMViewAttr<'_> {
+    // SAFETY: by the caller
+    let Some(node) = (unsafe { node_at(view, node) }) else {
+        return std::ptr::null();
+    };
+    if i >= node.attributes.len {
+        return std::ptr::null();
+    }
+    // SAFETY: `view` is not null, and the attributes of a node are in bounds
+    unsafe { (*view).attributes.add((node.attributes.start + i) as usize) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OMKind, OpenMath, view::ViewArena};
+
+    #[test]
+    fn accessors() {
+        let om = OpenMath::from_sexpr("(omattr (((oms sts type) (omforeign \"text/plain\" \"nat\"))) (oma (oms arith1 plus) (omv x)))")
+            .expect("is valid");
+        let arena = ViewArena::new();
+        let view = om.as_view_in(&arena).expect("fits");
+        let raw = view.as_raw();
+        let v = std::ptr::from_ref(&raw);
+        // SAFETY: `raw` is obtained from `view`
+        unsafe {
+            let root = &*openmath_view_node(v, 0);
+            assert_eq!(root.kind(), OMKind::OMA);
+            let x = &*openmath_view_node(v, openmath_view_child(v, 0, 1));
+            assert_eq!(x.text(), Some("x"));
+            assert_eq!(openmath_view_child(v, 0, 2), OPENMATH_VIEW_NONE);
+            assert!(openmath_view_node(v, raw.nodes_len).is_null());
+            let attr = &*openmath_view_attribute(v, 0, 0);
+            assert_eq!(attr.name(), "type");
+            assert_eq!(
+                (*openmath_view_node(v, attr.value)).cd(),
+                Some("text/plain")
+            );
+            assert!(openmath_view_attribute(v, 0, 1).is_null());
+            assert!(openmath_view_node(std::ptr::null(), 0).is_null());
+        }
+    }
+}
@@ -0,0 +1,650 @@
+/*! Read-only views of [`OpenMath`] objects with a stable, C-compatible layout.
+
+An [`OMView`] flattens an object into three arrays: its nodes (in pre-order, the root
+being the first one), the indices of their children, and their attributes. All strings and
+byte arrays are represented as pointers into the original data (usually the buffer the object
+was deserialized from), so a view can be handed across an FFI boundary without copying; see
+[`OMView::as_raw`] and (with the `ffi` feature) the accessors in [`ffi`].
+
+Data owned by the object (e.g. strings containing XML entities, or the decoded bytes of an
+[OMB](crate::OMKind::OMB)) can not be borrowed for the lifetime of the original data;
+[`OpenMath::as_view`] fails in that case, whereas [`OpenMath::as_view_in`] copies it into a
+[`ViewArena`].
+
+| node kind                              | [`text`](OMViewNode::text)     | [`cd`](OMViewNode::cd) | children |
+|----------------------------------------|--------------------------------|------------------------|----------|
+| [OMI](crate::OMKind::OMI)              | the decimal digits, if it does not fit in an `i128` (see [`int`](OMViewNode::int)) | | |
+| [OMF](crate::OMKind::OMF)              | | | |
+| [OMSTR](crate::OMKind::OMSTR)          | the string | | |
+| [OMB](crate::OMKind::OMB)              | the bytes | | |
+| [OMV](crate::OMKind::OMV)              | the name | | |
+| [OMS](crate::OMKind::OMS)              | the name | the cd | |
+| [OMA](crate::OMKind::OMA)              | | | the head, followed by the arguments |
+| [OMBIND](crate::OMKind::OMBIND)        | | | the binder, the variables (as [OMV](crate::OMKind::OMV)s), the body |
+| [OME](crate::OMKind::OME)              | the name of the error symbol | its cd | the arguments |
+| [OMFOREIGN](crate::OMKind::OMFOREIGN)  | the value | the encoding, if any | |
+
+Attribute values are nodes, too (possibly [OMFOREIGN](crate::OMKind::OMFOREIGN)s).
+
+# Examples
+```
+use openmath::{OMKind, OpenMath, de::OMDeserializable};
+
+# #[cfg(feature = "xml-read")]
+# {
+let xml = r#"<OMA><OMS cd="arith1" name="plus"/><OMV name="x"/><OMI>2</OMI></OMA>"#;
+let om = OpenMath::from_openmath_xml(xml).expect("is valid");
+let view = om.as_view().expect("all data is borrowed");
+let root = view.root();
+assert_eq!(root.kind(), OMKind::OMA);
+let [head, x, two] = view.children(root).collect::<Vec<_>>()[..] else {
+    panic!("has three children")
+};
+assert_eq!((head.cd(), head.text()), (Some("arith1"), Some("plus")));
+// strings point into the input
+assert!(xml.as_bytes().as_ptr_range().contains(&x.text_bytes().expect("has a name").as_ptr()));
+assert_eq!(two.int(), Some(2));
+# }
+```
+*/
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+use std::{borrow::Cow, cell::RefCell, marker::PhantomData};
+
+use crate::{Attr, BoundVariable, OMKind, OMMaybeForeign, OpenMath, int::I};
+
+/// Errors when creating an [`OMView`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum ViewError {
+    #[error("{0} contains owned data; use `as_view_in` to copy it into a `ViewArena`")]
+    Owned(OMKind),
+    #[error("object has too many nodes or attributes for a view")]
+    TooLarge,
+}
+
+/// A borrowed string or byte array; `ptr` is null for absent values (e.g. a missing cdbase).
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct OMSlice<'de> {
+    ptr: *const u8,
+    len: usize,
+    lifetime: PhantomData<&'de [u8]>,
+}
+impl<'de> OMSlice<'de> {
+    const NONE: Self = Self {
+        ptr: std::ptr::null(),
+        len: 0,
+        lifetime: PhantomData,
+    };
+    const fn new(bytes: &'de [u8]) -> Self {
+        Self {
+            ptr: bytes.as_ptr(),
+            len: bytes.len(),
+            lifetime: PhantomData,
+        }
+    }
+
+    /// The referenced bytes, if any
+    #[must_use]
+    pub const fn as_bytes(&self) -> Option<&'de [u8]> {
+        if self.ptr.is_null() {
+            None
+        } else {
+            // SAFETY: constructed from a `&'de [u8]` in `new`
+            Some(unsafe { std::slice::from_raw_parts(self.ptr, self.len) })
+        }
+    }
+}
+
+/// A range of indices into one of the arrays of an [`OMView`]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OMRange {
+    pub start: u32,
+    pub len: u32,
+}
+impl OMRange {
+    const fn to_range(self) -> std::ops::Range<usize> {
+        self.start as usize..self.start as usize + self.len as usize
+    }
+}
+
+/// A node in an [`OMView`]; see the [module documentation](self) for which fields are set
+/// for which kind.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct OMViewNode<'de> {
+    /// an [`OMKind`]
+    kind: u8,
+    /// whether the cdbase of an [OMS](crate::OMKind::OMS) was inherited
+    cdbase_inherited: bool,
+    /// whether [`int_low`](Self::int_low) and [`int_high`](Self::int_high) hold the value of
+    /// an [OMI](crate::OMKind::OMI)
+    small_int: bool,
+    int_low: u64,
+    int_high: i64,
+    float: f64,
+    text: OMSlice<'de>,
+    cd: OMSlice<'de>,
+    cdbase: OMSlice<'de>,
+    children: OMRange,
+    attributes: OMRange,
+}
+impl<'de> OMViewNode<'de> {
+    const fn new(kind: OMKind) -> Self {
+        Self {
+            kind: kind as u8,
+            cdbase_inherited: false,
+            small_int: false,
+            int_low: 0,
+            int_high: 0,
+            float: 0.0,
+            text: OMSlice::NONE,
+            cd: OMSlice::NONE,
+            cdbase: OMSlice::NONE,
+            children: OMRange { start: 0, len: 0 },
+            attributes: OMRange { start: 0, len: 0 },
+        }
+    }
+
+    /// The kind of this node
+    #[must_use]
+    pub const fn kind(&self) -> OMKind {
+        match OMKind::from_u8(self.kind) {
+            Some(k) => k,
+            None => unreachable!(),
+        }
+    }
+
+    /// The value of an [OMI](crate::OMKind::OMI), if it fits in an `i128`; otherwise,
+    /// its digits are in [`text`](Self::text)
+    #[must_use]
+    #[allow(clippy::cast_possible_wrap, clippy::cast_sign_loss)]
+    pub const fn int(&self) -> Option<i128> {
+        if self.small_int {
+            Some(((self.int_high as i128) << 64) | self.int_low as i128)
+        } else {
+            None
+        }
+    }
+
+    /// The value of an [OMF](crate::OMKind::OMF)
+    #[must_use]
+    pub const fn float(&self) -> f64 {
+        self.float
+    }
+
+    /// The text of this node, unless it is an [OMB](crate::OMKind::OMB)
+    #[must_use]
+    pub fn text(&self) -> Option<&'de str> {
+        if self.kind == OMKind::OMB as u8 {
+            return None;
+        }
+        str(self.text)
+    }
+
+    /// The text of this node as bytes; e.g. the contents of an [OMB](crate::OMKind::OMB)
+    #[must_use]
+    pub const fn text_bytes(&self) -> Option<&'de [u8]> {
+        self.text.as_bytes()
+    }
+
+    /// The cd of an [OMS](crate::OMKind::OMS) or [OME](crate::OMKind::OME), or the encoding
+    /// of an [OMFOREIGN](crate::OMKind::OMFOREIGN)
+    #[must_use]
+    pub fn cd(&self) -> Option<&'de str> {
+        str(self.cd)
+    }
+
+    /// The cdbase of an [OMS](crate::OMKind::OMS) or [OME](crate::OMKind::OME), if given
+    #[must_use]
+    pub fn cdbase(&self) -> Option<&'de str> {
+        str(self.cdbase)
+    }
+
+    /// Whether the [`cdbase`](Self::cdbase) of an [OMS](crate::OMKind::OMS) was inherited;
+    /// see [`OpenMath::OMS`]
+    #[must_use]
+    pub const fn cdbase_inherited(&self) -> bool {
+        self.cdbase_inherited
+    }
+}
+
+/// An attribute of a node in an [`OMView`]
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct OMViewAttr<'de> {
+    cdbase: OMSlice<'de>,
+    cd: OMSlice<'de>,
+    name: OMSlice<'de>,
+    /// the index of the value node
+    value: u32,
+}
+impl<'de> OMViewAttr<'de> {
+    /// The cdbase of the key, if given
+    #[must_use]
+    pub fn cdbase(&self) -> Option<&'de str> {
+        str(self.cdbase)
+    }
+    /// The cd of the key
+    #[must_use]
+    pub fn cd(&self) -> &'de str {
+        str(self.cd).unwrap_or_default()
+    }
+    /// The name of the key
+    #[must_use]
+    pub fn name(&self) -> &'de str {
+        str(self.name).unwrap_or_default()
+    }
+}
+
+fn str(s: OMSlice<'_>) -> Option<&str> {
+    s.as_bytes().map(|b| {
+        debug_assert!(
+            std::str::from_utf8(b).is_ok(),
+            "OMSlice of a name or text is not UTF-8"
+        );
+        // SAFETY: all slices but the bytes of an OMB are constructed from a `&str`
+        unsafe { std::str::from_utf8_unchecked(b) }
+    })
+}
+
+/** A flattened, read-only view of an [`OpenMath`] object; see the
+[module documentation](self).
+
+Created via [`OpenMath::as_view`] or [`OpenMath::as_view_in`].
+*/
+#[derive(Debug, Clone)]
+pub struct OMView<'de> {
+    nodes: Vec<OMViewNode<'de>>,
+    edges: Vec<u32>,
+    attributes: Vec<OMViewAttr<'de>>,
+}
+
+/// The arrays of an [`OMView`], as handed across an FFI boundary; valid as long as the view
+/// they were obtained from is neither dropped nor modified.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct OMViewRaw<'de> {
+    pub nodes: *const OMViewNode<'de>,
+    pub nodes_len: u32,
+    /// the children of a node are `edges[node.children.start..][..node.children.len]`
+    pub edges: *const u32,
+    pub edges_len: u32,
+    pub attributes: *const OMViewAttr<'de>,
+    pub attributes_len: u32,
+}
+
+impl<'de> OMView<'de> {
+    /// The root node
+    #[must_use]
+    pub fn root(&self) -> &OMViewNode<'de> {
+        &self.nodes[0]
+    }
+
+    /// The node with the given index
+    #[must_use]
+    pub fn node(&self, index: u32) -> Option<&OMViewNode<'de>> {
+        self.nodes.get(index as usize)
+    }
+
+    /// All nodes, in pre-order
+    #[must_use]
+    pub fn nodes(&self) -> &[OMViewNode<'de>] {
+        &self.nodes
+    }
+
+    /// The children of `node`
+    #[must_use]
+    pub fn children(
+        &self,
+        node: &OMViewNode<'de>,
+    ) -> impl ExactSizeIterator<Item = &OMViewNode<'de>> {
+        self.edges[node.children.to_range()]
+            .iter()
+            .map(|i| &self.nodes[*i as usize])
+    }
+
+    /// The attributes of `node`
+    #[must_use]
+    pub fn attributes(&self, node: &OMViewNode<'de>) -> &[OMViewAttr<'de>] {
+        &self.attributes[node.attributes.to_range()]
+    }
+
+    /// The value of `attribute`
+    #[must_use]
+    pub fn value(&self, attribute: &OMViewAttr<'de>) -> &OMViewNode<'de> {
+        &self.nodes[attribute.value as usize]
+    }
+
+    /// The arrays of this view, e.g. for handing them to C
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub const fn as_raw(&self) -> OMViewRaw<'de> {
+        // lengths are checked when building the view
+        OMViewRaw {
+            nodes: self.nodes.as_ptr(),
+            nodes_len: self.nodes.len() as u32,
+            edges: self.edges.as_ptr(),
+            edges_len: self.edges.len() as u32,
+            attributes: self.attributes.as_ptr(),
+            attributes_len: self.attributes.len() as u32,
+        }
+    }
+}
+
+/// Storage for data that [`OpenMath::as_view_in`] can not borrow from the original input
+#[derive(Debug, Default)]
+pub struct ViewArena(RefCell<Vec<Box<[u8]>>>);
+impl ViewArena {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn alloc(&self, bytes: &[u8]) -> &[u8] {
+        let bytes: Box<[u8]> = bytes.into();
+        let (ptr, len) = (bytes.as_ptr(), bytes.len());
+        self.0.borrow_mut().push(bytes);
+        // SAFETY: the box is never dropped or modified before `self` is, and moving it
+        // does not move its contents
+        unsafe { std::slice::from_raw_parts(ptr, len) }
+    }
+}
+
+impl<'om> OpenMath<'om> {
+    /// Creates a [view](OMView) of this object, borrowing all strings and byte arrays
+    /// for `'om`; see the [module documentation](crate::view).
+    ///
+    /// # Errors
+    /// if this object contains owned data (use [`as_view_in`](Self::as_view_in) instead), or
+    /// has more than [`u32::MAX`] nodes or attributes.
+    pub fn as_view(&self) -> Result<OMView<'om>, ViewError> {
+        Builder::new(None).build(self)
+    }
+
+    /// Like [`as_view`](Self::as_view), but copies owned data into `arena`.
+    ///
+    /// # Errors
+    /// if this object has more than [`u32::MAX`] nodes or attributes.
+    pub fn as_view_in<'a>(&self, arena: &'a ViewArena) -> Result<OMView<'a>, ViewError>
+    where
+        'om: 'a,
+    {
+        Builder::new(Some(arena)).build(self)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Child<'x, 'om> {
+    OM(&'x OpenMath<'om>),
+    Foreign(&'x OMMaybeForeign<'om, OpenMath<'om>>),
+    Var(&'x BoundVariable<'om>),
+}
+
+struct Builder<'a> {
+    arena: Option<&'a ViewArena>,
+    view: OMView<'a>,
+}
+impl<'a> Builder<'a> {
+    const fn new(arena: Option<&'a ViewArena>) -> Self {
+        Self {
+            arena,
+            view: OMView {
+                nodes: Vec::new(),
+                edges: Vec::new(),
+                attributes: Vec::new(),
+            },
+        }
+    }
+
+    fn build<'om: 'a>(mut self, om: &OpenMath<'om>) -> Result<OMView<'a>, ViewError> {
+        self.node(Child::OM(om))?;
+        Ok(self.view)
+    }
+
+    #[allow(clippy::ptr_arg)] // distinguishes borrowed from owned data
+    fn slice<'om: 'a, T: ?Sized + ToOwned + AsRef<[u8]>>(
+        &self,
+        cow: &Cow<'om, T>,
+        kind: OMKind,
+    ) -> Result<OMSlice<'a>, ViewError> {
+        match (cow, self.arena) {
+            (Cow::Borrowed(b), _) => Ok(OMSlice::new((*b).as_ref())),
+            (Cow::Owned(_), Some(arena)) => Ok(OMSlice::new(arena.alloc((**cow).as_ref()))),
+            (Cow::Owned(_), None) => Err(ViewError::Owned(kind)),
+        }
+    }
+
+    fn opt_slice<'om: 'a>(
+        &self,
+        cow: Option<&Cow<'om, str>>,
+        kind: OMKind,
+    ) -> Result<OMSlice<'a>, ViewError> {
+        cow.map_or(Ok(OMSlice::NONE), |c| self.slice(c, kind))
+    }
+
+    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+    fn node<'om: 'a>(&mut self, child: Child<'_, 'om>) -> Result<u32, ViewError> {
+        let index = index(self.view.nodes.len())?;
+        let (kind, attributes) = match child {
+            Child::OM(om) => kind_and_attributes(om),
+            Child::Var(v) => (OMKind::OMV, &*v.attributes),
+            Child::Foreign(OMMaybeForeign::OM(om)) => return self.node(Child::OM(om)),
+            Child::Foreign(OMMaybeForeign::Foreign { encoding, value }) => {
+                let mut node = OMViewNode::new(OMKind::OMFOREIGN);
+                node.text = self.slice(value, OMKind::OMFOREIGN)?;
+                node.cd = self.opt_slice(encoding.as_ref(), OMKind::OMFOREIGN)?;
+                self.view.nodes.push(node);
+                return Ok(index);
+            }
+        };
+        let mut node = OMViewNode::new(kind);
+        let mut children = Vec::new();
+        match child {
+            Child::Var(v) => node.text = self.slice(&v.name, kind)?,
+            Child::OM(OpenMath::OMI { int, .. }) => match &int.0 {
+                I::Stack(i) => {
+                    node.small_int = true;
+                    node.int_low = *i as u64;
+                    node.int_high = (*i >> 64) as i64;
+                }
+                I::Heap(digits) => node.text = self.slice(digits, kind)?,
+            },
+            Child::OM(OpenMath::OMF { float, .. }) => node.float = float.0,
+            Child::OM(OpenMath::OMSTR { string: text, .. } | OpenMath::OMV { name: text, .. }) => {
+                node.text = self.slice(text, kind)?;
+            }
+            Child::OM(OpenMath::OMB { bytes, .. }) => node.text = self.slice(bytes, kind)?,
+            Child::OM(OpenMath::OMS {
+                cd,
+                name,
+                cdbase,
+                cdbase_inherited,
+                ..
+            }) => {
+                node.text = self.slice(name, kind)?;
+                node.cd = self.slice(cd, kind)?;
+                node.cdbase = self.opt_slice(cdbase.as_ref(), kind)?;
+                node.cdbase_inherited = *cdbase_inherited;
+            }
+            Child::OM(OpenMath::OMA {
+                applicant,
+                arguments,
+                ..
+            }) => {
+                children.push(Child::OM(applicant));
+                children.extend(arguments.iter().map(Child::OM));
+            }
+            Child::OM(OpenMath::OME {
+                cd,
+                name,
+                cdbase,
+                arguments,
+                ..
+            }) => {
+                node.text = self.slice(name, kind)?;
+                node.cd = self.slice(cd, kind)?;
+                node.cdbase = self.opt_slice(cdbase.as_ref(), kind)?;
+                children.extend(arguments.iter().map(Child::Foreign));
+            }
+            Child::OM(OpenMath::OMBIND {
+                binder,
+                variables,
+                object,
+                ..
+            }) => {
+                children.push(Child::OM(binder));
+                children.extend(variables.iter().map(Child::Var));
+                children.push(Child::OM(object));
+            }
+            Child::Foreign(_) => unreachable!(),
+        }
+        self.view.nodes.push(node);
+
+        let start = self.view.edges.len();
+        self.view.edges.resize(start + children.len(), 0);
+        let range = range(start, children.len())?;
+        for (i, c) in children.into_iter().enumerate() {
+            self.view.edges[start + i] = self.node(c)?;
+        }
+        self.view.nodes[index as usize].children = range;
+        self.attributes(index, attributes).map(|()| index)
+    }
+
+    fn attributes<'om: 'a>(
+        &mut self,
+        index: u32,
+        attributes: &[Attr<'om, OMMaybeForeign<'om, OpenMath<'om>>>],
+    ) -> Result<(), ViewError> {
+        let start = self.view.attributes.len();
+        let range = range(start, attributes.len())?;
+        for a in attributes {
+            let attr = OMViewAttr {
+                cdbase: self.opt_slice(a.key.cdbase.as_ref(), OMKind::OMATTR)?,
+                cd: self.slice(&a.key.cd, OMKind::OMATTR)?,
+                name: self.slice(&a.key.name, OMKind::OMATTR)?,
+                value: 0,
+            };
+            self.view.attributes.push(attr);
+        }
+        for (i, a) in attributes.iter().enumerate() {
+            self.view.attributes[start + i].value = self.node(Child::Foreign(&a.value))?;
+        }
+        self.view.nodes[index as usize].attributes = range;
+        Ok(())
+    }
+}
+
+fn kind_and_attributes<'x, 'om>(
+    om: &'x OpenMath<'om>,
+) -> (OMKind, &'x [Attr<'om, OMMaybeForeign<'om, OpenMath<'om>>>]) {
+    match om {
+        OpenMath::OMI { attributes, .. } => (OMKind::OMI, attributes),
+        OpenMath::OMF { attributes, .. } => (OMKind::OMF, attributes),
+        OpenMath::OMSTR { attributes, .. } => (OMKind::OMSTR, attributes),
+        OpenMath::OMB { attributes, .. } => (OMKind::OMB, attributes),
+        OpenMath::OMV { attributes, .. } => (OMKind::OMV, attributes),
+        OpenMath::OMS { attributes, .. } => (OMKind::OMS, attributes),
+        OpenMath::OMA { attributes, .. } => (OMKind::OMA, attributes),
+        OpenMath::OME { attributes, .. } => (OMKind::OME, attributes),
+        OpenMath::OMBIND { attributes, .. } => (OMKind::OMBIND, attributes),
+    }
+}
+
+fn index(i: usize) -> Result<u32, ViewError> {
+    u32::try_from(i).map_err(|_| ViewError::TooLarge)
+}
+fn range(start: usize, len: usize) -> Result<OMRange, ViewError> {
+    index(start + len)?;
+    Ok(OMRange {
+        start: index(start)?,
+        len: index(len)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ViewArena, ViewError};
+    use crate::{OMKind, OpenMath};
+
+    #[test]
+    fn view() {
+        let om = OpenMath::from_sexpr(
+            r#"(ombind (oms fns1 lambda) (bvar (omattr (((oms sts type) (oms setname1 N))) x))
+                 (oma (oms arith1 plus) (omv x) (omi 123456789012345678901234567890123456789012)
+                   (omf 1.5) (omstr "a") (omb "AQL/") (omi -2)))"#,
+        )
+        .expect("is valid");
+        // the sexpr parser owns all data
+        assert_eq!(
+            om.as_view().expect_err("data is owned"),
+            ViewError::Owned(OMKind::OMS)
+        );
+        let arena = ViewArena::new();
+        let view = om.as_view_in(&arena).expect("fits");
+        assert_eq!(view.nodes().len(), 12);
+        let root = view.root();
+        assert_eq!(root.kind(), OMKind::OMBIND);
+        let [binder, x, body] = view.children(root).collect::<Vec<_>>()[..] else {
+            panic!("has three children")
+        };
+        assert_eq!((binder.cd(), binder.text()), (Some("fns1"), Some("lambda")));
+        assert_eq!(x.text(), Some("x"));
+        let [attr] = view.attributes(x) else {
+            panic!("has one attribute")
+        };
+        assert_eq!((attr.cd(), attr.name()), ("sts", "type"));
+        assert_eq!(view.value(attr).text(), Some("N"));
+        let args = view.children(body).collect::<Vec<_>>();
+        assert_eq!(args.len(), 7);
+        assert_eq!(args[2].int(), None);
+        assert_eq!(
+            args[2].text(),
+            Some("123456789012345678901234567890123456789012")
+        );
+        assert_eq!(args[3].float().to_bits(), 1.5f64.to_bits());
+        assert_eq!(args[4].text(), Some("a"));
+        assert_eq!(args[5].text(), None);
+        assert_eq!(args[5].text_bytes(), Some(&[1, 2, 255][..]));
+        assert_eq!(args[6].int(), Some(-2));
+    }
+
+    #[cfg(feature = "xml-read")]
+    #[test]
+    fn borrowed_view() {
+        use crate::de::OMDeserializable;
+        let xml = r#"<OME><OMS cdbase="http://example.org" cd="moreerrors" name="unexpected"/><OMSTR>a</OMSTR><OMFOREIGN encoding="text/plain">b</OMFOREIGN></OME>"#;
+        let om = OpenMath::from_openmath_xml(xml).expect("is valid");
+        let view = om.as_view().expect("all data is borrowed");
+        let root = view.root();
+        assert_eq!(root.kind(), OMKind::OME);
+        assert_eq!(root.cdbase(), Some("http://example.org"));
+        let [a, b] = view.children(root).collect::<Vec<_>>()[..] else {
+            panic!("has two children")
+        };
+        assert_eq!((a.kind(), a.text()), (OMKind::OMSTR, Some("a")));
+        assert_eq!(
+            (b.kind(), b.text(), b.cd()),
+            (OMKind::OMFOREIGN, Some("b"), Some("text/plain"))
+        );
+        assert!(
+            xml.as_bytes()
+                .as_ptr_range()
+                .contains(&b.text_bytes().expect("is set").as_ptr())
+        );
+
+        let om = OpenMath::from_openmath_xml("<OMSTR>a &amp; b</OMSTR>").expect("is valid");
+        assert_eq!(
+            om.as_view().expect_err("is owned"),
+            ViewError::Owned(OMKind::OMSTR)
+        );
+        let arena = ViewArena::new();
+        assert_eq!(
+            om.as_view_in(&arena).expect("fits").root().text(),
+            Some("a & b")
+        );
+    }
+}
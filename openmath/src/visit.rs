@@ -0,0 +1,458 @@
+/*! Walking everything in an [`OpenMath`] object; see [`OpenMath::walk`],
+[`OpenMath::try_walk`] and [`OpenMath::walk_until`].
+
+A walk visits, in pre-order and document order (i.e. the order of the XML encoding):
+1. the object itself ([`enter`](TryVisitor::enter)),
+2. its attributes, each key ([`attribute`](TryVisitor::attribute)) followed by its value,
+3. its children in the order of its [child slots](crate::OMKind::child_slots): the
+   applicant and arguments of an [OMA](crate::OMKind::OMA), the arguments of an
+   [OME](crate::OMKind::OME), and the binder, the bound variables
+   ([`variable`](TryVisitor::variable), each followed by its attributes) and the body of
+   an [OMBIND](crate::OMKind::OMBIND),
+4. the object again ([`leave`](TryVisitor::leave)).
+
+Values that are [OMFOREIGN](crate::OMKind::OMFOREIGN)s are reported with
+[`foreign`](TryVisitor::foreign) instead. Walks keep their own stack rather than recursing,
+so arbitrarily deep objects can be walked.
+
+# Examples
+```
+use std::ops::ControlFlow;
+use openmath::{OpenMath, visit::Visitor};
+
+let om = OpenMath::from_sexpr(
+    "(omattr (((oms meta note) (omstr \"n\"))) (oma (oms arith1 plus) (omi 1) (omv x)))",
+)
+.expect("is valid");
+
+struct Kinds(Vec<String>);
+impl Visitor<'_, '_> for Kinds {
+    fn enter(&mut self, om: &OpenMath<'_>, _: Option<openmath::Step>) {
+        self.0.push(om.kind().to_string());
+    }
+}
+let mut kinds = Kinds(Vec::new());
+om.walk(&mut kinds);
+assert_eq!(kinds.0, ["OMA", "OMSTR", "OMS", "OMI", "OMV"]);
+
+let first_int = om.walk_until(|o| match o.as_i64() {
+    Some(i) => ControlFlow::Break(i),
+    None => ControlFlow::Continue(()),
+});
+assert_eq!(first_int, Some(1));
+```
+*/
+use std::{convert::Infallible, ops::ControlFlow};
+
+use crate::{
+    Attr, BoundVariable, OMMaybeForeign, OpenMath, Step,
+    schema::{SlotSpec, SlotView},
+};
+
+/// Callbacks for [`OpenMath::try_walk`], which stops at the first error; every method
+/// does nothing by default. See the [module documentation](self) for the order of calls.
+pub trait TryVisitor<'a, 'om> {
+    /// The error aborting the walk
+    type Error;
+
+    /// An object, before its attributes and children; `step` is how it is reached from its
+    /// parent (`None` for the object walked)
+    ///
+    /// # Errors
+    /// Any error stops the walk.
+    #[inline]
+    fn enter(&mut self, om: &'a OpenMath<'om>, step: Option<Step>) -> Result<(), Self::Error> {
+        let _ = (om, step);
+        Ok(())
+    }
+
+    /// An object, after its attributes and children
+    ///
+    /// # Errors
+    /// Any error stops the walk.
+    #[inline]
+    fn leave(&mut self, om: &'a OpenMath<'om>) -> Result<(), Self::Error> {
+        let _ = om;
+        Ok(())
+    }
+
+    /// An attribute of an object or bound variable, before its value
+    ///
+    /// # Errors
+    /// Any error stops the walk.
+    #[inline]
+    fn attribute(
+        &mut self,
+        attr: &'a Attr<'om, OMMaybeForeign<'om, OpenMath<'om>>>,
+    ) -> Result<(), Self::Error> {
+        let _ = attr;
+        Ok(())
+    }
+
+    /// A bound variable of an [OMBIND](crate::OMKind::OMBIND), before its attributes
+    ///
+    /// # Errors
+    /// Any error stops the walk.
+    #[inline]
+    fn variable(&mut self, var: &'a BoundVariable<'om>) -> Result<(), Self::Error> {
+        let _ = var;
+        Ok(())
+    }
+
+    /// An [OMFOREIGN](crate::OMKind::OMFOREIGN) attribute value or argument of an
+    /// [OME](crate::OMKind::OME)
+    ///
+    /// # Errors
+    /// Any error stops the walk.
+    #[inline]
+    fn foreign(&mut self, encoding: Option<&'a str>, value: &'a str) -> Result<(), Self::Error> {
+        let _ = (encoding, value);
+        Ok(())
+    }
+}
+
+/// Callbacks for [`OpenMath::walk`]; like [`TryVisitor`], but infallible.
+pub trait Visitor<'a, 'om> {
+    /// See [`TryVisitor::enter`]
+    #[inline]
+    fn enter(&mut self, om: &'a OpenMath<'om>, step: Option<Step>) {
+        let _ = (om, step);
+    }
+    /// See [`TryVisitor::leave`]
+    #[inline]
+    fn leave(&mut self, om: &'a OpenMath<'om>) {
+        let _ = om;
+    }
+    /// See [`TryVisitor::attribute`]
+    #[inline]
+    fn attribute(&mut self, attr: &'a Attr<'om, OMMaybeForeign<'om, OpenMath<'om>>>) {
+        let _ = attr;
+    }
+    /// See [`TryVisitor::variable`]
+    #[inline]
+    fn variable(&mut self, var: &'a BoundVariable<'om>) {
+        let _ = var;
+    }
+    /// See [`TryVisitor::foreign`]
+    #[inline]
+    fn foreign(&mut self, encoding: Option<&'a str>, value: &'a str) {
+        let _ = (encoding, value);
+    }
+}
+
+impl<'a, 'om: 'a, V: Visitor<'a, 'om>> TryVisitor<'a, 'om> for V {
+    type Error = Infallible;
+    #[inline]
+    fn enter(&mut self, om: &'a OpenMath<'om>, step: Option<Step>) -> Result<(), Infallible> {
+        Visitor::enter(self, om, step);
+        Ok(())
+    }
+    #[inline]
+    fn leave(&mut self, om: &'a OpenMath<'om>) -> Result<(), Infallible> {
+        Visitor::leave(self, om);
+        Ok(())
+    }
+    #[inline]
+    fn attribute(
+        &mut self,
+        attr: &'a Attr<'om, OMMaybeForeign<'om, OpenMath<'om>>>,
+    ) -> Result<(), Infallible> {
+        Visitor::attribute(self, attr);
+        Ok(())
+    }
+    #[inline]
+    fn variable(&mut self, var: &'a BoundVariable<'om>) -> Result<(), Infallible> {
+        Visitor::variable(self, var);
+        Ok(())
+    }
+    #[inline]
+    fn foreign(&mut self, encoding: Option<&'a str>, value: &'a str) -> Result<(), Infallible> {
+        Visitor::foreign(self, encoding, value);
+        Ok(())
+    }
+}
+
+/// What is left to do in a walk
+enum Pending<'a, 'om> {
+    Enter(&'a OpenMath<'om>, Option<Step>),
+    Leave(&'a OpenMath<'om>),
+    Attribute(&'a Attr<'om, OMMaybeForeign<'om, OpenMath<'om>>>, Step),
+    Variable(&'a BoundVariable<'om>, usize),
+    MaybeForeign(&'a OMMaybeForeign<'om, OpenMath<'om>>, Step),
+}
+
+impl<'a, 'om: 'a> Pending<'a, 'om> {
+    /// Pushes what is in `om` onto `stack`, to be popped in document order
+    fn push_parts(om: &'a OpenMath<'om>, stack: &mut Vec<Self>) {
+        let start = stack.len();
+        for spec in om.kind().child_slots() {
+            match om.slot(spec) {
+                Some(SlotView::One(o)) => {
+                    let step = if *spec == SlotSpec::APPLICANT {
+                        Step::Applicant
+                    } else if *spec == SlotSpec::BINDER {
+                        Step::Binder
+                    } else {
+                        Step::Body
+                    };
+                    stack.push(Self::Enter(o, Some(step)));
+                }
+                Some(SlotView::Objects(os)) => stack.extend(
+                    os.iter()
+                        .enumerate()
+                        .map(|(i, o)| Self::Enter(o, Some(Step::Argument(i)))),
+                ),
+                Some(SlotView::MaybeForeign(os)) => stack.extend(
+                    os.iter()
+                        .enumerate()
+                        .map(|(i, o)| Self::MaybeForeign(o, Step::Argument(i))),
+                ),
+                Some(SlotView::Attributes(attrs)) => stack.extend(
+                    attrs
+                        .iter()
+                        .enumerate()
+                        .map(|(i, a)| Self::Attribute(a, Step::Attribute(i))),
+                ),
+                Some(SlotView::Variables(vars)) => {
+                    stack.extend(vars.iter().enumerate().map(|(i, v)| Self::Variable(v, i)));
+                }
+                None => (),
+            }
+        }
+        stack[start..].reverse();
+    }
+}
+
+impl<'om> OpenMath<'om> {
+    /// Calls the methods of `visitor` on everything in this object, in the order described
+    /// in the [`visit`](crate::visit) module, until one of them fails.
+    ///
+    /// # Errors
+    /// The first error of `visitor`.
+    pub fn try_walk<'a, V: TryVisitor<'a, 'om>>(&'a self, visitor: &mut V) -> Result<(), V::Error> {
+        let mut stack = vec![Pending::Enter(self, None)];
+        while let Some(next) = stack.pop() {
+            match next {
+                Pending::Enter(om, step) => {
+                    visitor.enter(om, step)?;
+                    stack.push(Pending::Leave(om));
+                    Pending::push_parts(om, &mut stack);
+                }
+                Pending::Leave(om) => visitor.leave(om)?,
+                Pending::Attribute(attr, step) => {
+                    visitor.attribute(attr)?;
+                    stack.push(Pending::MaybeForeign(&attr.value, step));
+                }
+                Pending::Variable(var, i) => {
+                    visitor.variable(var)?;
+                    stack.extend(
+                        var.attributes
+                            .iter()
+                            .enumerate()
+                            .rev()
+                            .map(|(j, a)| Pending::Attribute(a, Step::VariableAttribute(i, j))),
+                    );
+                }
+                Pending::MaybeForeign(OMMaybeForeign::OM(om), step) => {
+                    stack.push(Pending::Enter(om, Some(step)));
+                }
+                Pending::MaybeForeign(OMMaybeForeign::Foreign { encoding, value }, _) => {
+                    visitor.foreign(encoding.as_deref(), value)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Calls the methods of `visitor` on everything in this object, in the order described
+    /// in the [`visit`](crate::visit) module.
+    #[inline]
+    pub fn walk<'a>(&'a self, visitor: &mut impl Visitor<'a, 'om>) {
+        let Ok(()) = self.try_walk(visitor);
+    }
+
+    /// Calls `f` on this object and every object in it (including attribute values and the
+    /// arguments of [OME](crate::OMKind::OME)s) in pre-order and document order, until it breaks
+    /// with some value, which is returned; `None` if it never does.
+    pub fn walk_until<'a, B>(&'a self, f: impl FnMut(&'a Self) -> ControlFlow<B>) -> Option<B> {
+        struct Until<F>(F);
+        impl<'a, 'om: 'a, B, F: FnMut(&'a OpenMath<'om>) -> ControlFlow<B>> TryVisitor<'a, 'om>
+            for Until<F>
+        {
+            type Error = B;
+            #[inline]
+            fn enter(&mut self, om: &'a OpenMath<'om>, _: Option<Step>) -> Result<(), B> {
+                match (self.0)(om) {
+                    ControlFlow::Continue(()) => Ok(()),
+                    ControlFlow::Break(b) => Err(b),
+                }
+            }
+        }
+        self.try_walk(&mut Until(f)).err()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
+
+    use super::{TryVisitor, Visitor};
+    use crate::{Attr, BoundVariable, OMMaybeForeign, OpenMath, Step};
+
+    /// Records every call
+    #[derive(Default)]
+    struct Log(Vec<String>);
+    impl<'a, 'om: 'a> Visitor<'a, 'om> for Log {
+        fn enter(&mut self, om: &'a OpenMath<'om>, step: Option<Step>) {
+            let step = step.map_or_else(String::new, |s| format!(" at {s}"));
+            self.0.push(format!("enter {}{step}", om.kind()));
+        }
+        fn leave(&mut self, om: &'a OpenMath<'om>) {
+            self.0.push(format!("leave {}", om.kind()));
+        }
+        fn attribute(&mut self, attr: &'a Attr<'om, OMMaybeForeign<'om, OpenMath<'om>>>) {
+            self.0.push(format!("attribute {}", attr.name()));
+        }
+        fn variable(&mut self, var: &'a BoundVariable<'om>) {
+            self.0.push(format!("variable {}", var.name));
+        }
+        fn foreign(&mut self, encoding: Option<&'a str>, value: &'a str) {
+            self.0.push(format!("foreign {encoding:?} {value}"));
+        }
+    }
+
+    /// Fails at the first variable named `x`, counting the calls before
+    struct NoX(usize);
+    impl<'a, 'om: 'a> TryVisitor<'a, 'om> for NoX {
+        type Error = (usize, &'a str);
+        fn enter(&mut self, _: &'a OpenMath<'om>, _: Option<Step>) -> Result<(), Self::Error> {
+            self.0 += 1;
+            Ok(())
+        }
+        fn variable(&mut self, var: &'a BoundVariable<'om>) -> Result<(), Self::Error> {
+            if var.name == "x" {
+                return Err((self.0, &var.name));
+            }
+            Ok(())
+        }
+    }
+
+    fn fixture() -> OpenMath<'static> {
+        let mut om = OpenMath::from_sexpr(
+            "(omattr (((oms meta note) (omstr \"n\")))
+               (oma (oms arith1 plus)
+                 (ome (oms aritherror DivisionByZero) (omi 1))
+                 (ombind (oms fns1 lambda)
+                   (bvar x (omattr (((oms sts type) (oms setname1 R))) y))
+                   (omv x))))",
+        )
+        .expect("is valid");
+        let OpenMath::OMA { arguments, .. } = &mut om else {
+            panic!("is an OMA")
+        };
+        let OpenMath::OME { arguments, .. } = &mut arguments[0] else {
+            panic!("is an OME")
+        };
+        arguments.push(OMMaybeForeign::Foreign {
+            encoding: Some("text/plain".into()),
+            value: "f".into(),
+        });
+        om
+    }
+
+    #[test]
+    fn order() {
+        let om = fixture();
+        let mut log = Log::default();
+        om.walk(&mut log);
+        assert_eq!(
+            log.0,
+            [
+                "enter OMA",
+                "attribute note",
+                "enter OMSTR at @0",
+                "leave OMSTR",
+                "enter OMS at applicant",
+                "leave OMS",
+                "enter OME at 0",
+                "enter OMI at 0",
+                "leave OMI",
+                "foreign Some(\"text/plain\") f",
+                "leave OME",
+                "enter OMBIND at 1",
+                "enter OMS at binder",
+                "leave OMS",
+                "variable x",
+                "variable y",
+                "attribute type",
+                "enter OMS at var1@0",
+                "leave OMS",
+                "enter OMV at body",
+                "leave OMV",
+                "leave OMBIND",
+                "leave OMA",
+            ]
+        );
+        // the objects entered are the subterms in pre-order
+        let mut entered = Vec::new();
+        assert_eq!(
+            om.walk_until(|o| {
+                entered.push(o);
+                ControlFlow::<()>::Continue(())
+            }),
+            None
+        );
+        assert_eq!(entered.len(), 9);
+        assert_eq!(entered[0], &om);
+        assert!(
+            entered[1..]
+                .iter()
+                .copied()
+                .eq(om.children_with_attrs().flat_map(|c| {
+                    let mut all = Vec::new();
+                    c.walk_until(|o| {
+                        all.push(o);
+                        ControlFlow::<()>::Continue(())
+                    });
+                    all
+                }))
+        );
+    }
+
+    #[test]
+    fn early_exit() {
+        let om = fixture();
+        // borrowed results
+        let found = om.walk_until(|o| match o {
+            OpenMath::OMS { name, .. } if name.starts_with('D') || name == "lambda" => {
+                ControlFlow::Break(name.as_ref())
+            }
+            _ => ControlFlow::Continue(()),
+        });
+        assert_eq!(found, Some("lambda"));
+
+        assert_eq!(om.try_walk(&mut NoX(0)), Err((7, "x")));
+        let y = OpenMath::from_sexpr("(ombind (oms fns1 lambda) (bvar y) (omv y))").expect("ok");
+        assert_eq!(y.try_walk(&mut NoX(0)), Ok(()));
+    }
+
+    #[test]
+    fn deep() {
+        let mut om = OpenMath::from_sexpr("(omi 0)").expect("is valid");
+        for _ in 0..100_000 {
+            om = OpenMath::OMA {
+                applicant: Box::new(om),
+                arguments: Vec::new(),
+                attributes: Vec::new(),
+            };
+        }
+        let mut log = Log::default();
+        om.walk(&mut log);
+        assert_eq!(log.0.len(), 200_002);
+        assert_eq!(log.0[100_000], "enter OMI at applicant");
+        // dropping recurses, so take it apart first
+        while let OpenMath::OMA { applicant, .. } = om {
+            om = *applicant;
+        }
+    }
+}
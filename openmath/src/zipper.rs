@@ -0,0 +1,423 @@
+/*! Navigating and editing [`OpenMath`] objects node by node; see [`Zipper`] and
+[`OwnedZipper`].
+
+Both zippers move along the [`Step`]s of a [`Path`], so they reach every subterm that
+[`OpenMath::child`] does: applicants, arguments (of [OMA](OpenMath::OMA)s and
+[OME](OpenMath::OME)s), binders, bodies, and the values of attribution pairs (including those
+of bound variables), except foreign objects.
+
+# Examples
+```
+use openmath::{OpenMath, zipper::OwnedZipper};
+
+let om = OpenMath::from_sexpr(
+    "(oma (oms arith1 plus) (omi 1) (ombind (oms fns1 lambda) (bvar x) (omv x)))",
+)
+.expect("is valid");
+let z = om.zipper().down_argument(1).and_then(|z| z.down_body()).expect("exists");
+assert_eq!(z.focus(), &OpenMath::from_sexpr("(omv x)").expect("is valid"));
+assert_eq!(z.path().to_string(), "/1/body");
+
+let replaced = OwnedZipper::new(om)
+    .down_argument(1)
+    .and_then(OwnedZipper::down_body)
+    .expect("exists")
+    .replace(OpenMath::from_sexpr("(omi 2)").expect("is valid"))
+    .root();
+assert_eq!(
+    replaced,
+    OpenMath::from_sexpr("(oma (oms arith1 plus) (omi 1) (ombind (oms fns1 lambda) (bvar x) (omi 2)))")
+        .expect("is valid")
+);
+```
+*/
+
+use crate::{Int, OpenMath, Path, Step};
+
+/// A position in an [`OpenMath`] object, borrowing it; moving is cheap, and the
+/// [`focus`](Self::focus) borrows from the object rather than the zipper.
+///
+/// Navigation returns `None` if there is no subterm in the given direction; since a
+/// [`Zipper`] is cheap to clone, clone it first to keep the current position.
+#[derive(Debug, Clone)]
+pub struct Zipper<'a, 'om> {
+    focus: &'a OpenMath<'om>,
+    /// the ancestors of the focus, outermost first
+    ancestors: Vec<&'a OpenMath<'om>>,
+    path: Path,
+}
+
+impl<'a, 'om> Zipper<'a, 'om> {
+    /// A zipper focused on `root`
+    #[inline]
+    #[must_use]
+    pub const fn new(root: &'a OpenMath<'om>) -> Self {
+        Self {
+            focus: root,
+            ancestors: Vec::new(),
+            path: Path::new(),
+        }
+    }
+
+    /// The subterm in focus
+    #[inline]
+    #[must_use]
+    pub const fn focus(&self) -> &'a OpenMath<'om> {
+        self.focus
+    }
+
+    /// The path from the root to the [`focus`](Self::focus)
+    #[inline]
+    #[must_use]
+    pub const fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the focus is the root
+    #[inline]
+    #[must_use]
+    pub const fn is_root(&self) -> bool {
+        self.ancestors.is_empty()
+    }
+
+    /// The object this zipper navigates
+    #[must_use]
+    pub fn root(&self) -> &'a OpenMath<'om> {
+        self.ancestors.first().copied().unwrap_or(self.focus)
+    }
+
+    /// Moves to the immediate subterm at `step`, if any
+    #[must_use]
+    pub fn down(mut self, step: Step) -> Option<Self> {
+        let child = self.focus.child(step)?;
+        self.ancestors
+            .push(std::mem::replace(&mut self.focus, child));
+        self.path.0.push(step);
+        Some(self)
+    }
+
+    /// Moves to the parent of the focus, if any
+    #[must_use]
+    pub fn up(mut self) -> Option<Self> {
+        self.focus = self.ancestors.pop()?;
+        self.path.0.pop();
+        Some(self)
+    }
+
+    /// Moves to the subterm at `path`, relative to the focus
+    #[must_use]
+    pub fn down_path(self, path: &Path) -> Option<Self> {
+        path.0.iter().try_fold(self, |z, step| z.down(*step))
+    }
+
+    /// A zipper owning a copy of the object, at the same position; see [`OwnedZipper`]
+    ///
+    /// # Panics
+    /// never; the path leads to the focus in the copy as well
+    #[must_use]
+    pub fn to_owned_zipper(&self) -> OwnedZipper<'om> {
+        OwnedZipper::new(self.root().clone())
+            .down_path(&self.path)
+            .unwrap_or_else(|_| unreachable!("the path exists in the copy"))
+    }
+}
+
+/// A position in an [`OpenMath`] object, owning it; the focus can be
+/// [`replace`](Self::replace)d or [`modify`](Self::modify)d, and [`root`](Self::root)
+/// puts the object back together.
+///
+/// Moving down takes the subterm out of its parent, and moving up puts it back, so only the
+/// nodes on the path to the focus are touched; nothing is cloned. Navigation returns the
+/// zipper unchanged as the `Err` case if there is no subterm in the given direction.
+#[derive(Debug, Clone)]
+pub struct OwnedZipper<'om> {
+    focus: OpenMath<'om>,
+    /// the ancestors of the focus, outermost first, each with the step to the next one
+    /// (which is missing from it)
+    ancestors: Vec<(OpenMath<'om>, Step)>,
+}
+
+// the zipper is returned unchanged on failure, so nothing is lost
+#[allow(clippy::result_large_err)]
+impl<'om> OwnedZipper<'om> {
+    /// A zipper focused on `root`
+    #[inline]
+    #[must_use]
+    pub const fn new(root: OpenMath<'om>) -> Self {
+        Self {
+            focus: root,
+            ancestors: Vec::new(),
+        }
+    }
+
+    /// The subterm in focus
+    #[inline]
+    #[must_use]
+    pub const fn focus(&self) -> &OpenMath<'om> {
+        &self.focus
+    }
+
+    /// The subterm in focus, mutably
+    #[inline]
+    pub const fn focus_mut(&mut self) -> &mut OpenMath<'om> {
+        &mut self.focus
+    }
+
+    /// The path from the root to the [`focus`](Self::focus)
+    #[must_use]
+    pub fn path(&self) -> Path {
+        self.ancestors.iter().map(|(_, step)| *step).collect()
+    }
+
+    /// Whether the focus is the root
+    #[inline]
+    #[must_use]
+    pub const fn is_root(&self) -> bool {
+        self.ancestors.is_empty()
+    }
+
+    /// Replaces the focus by `om`
+    #[inline]
+    #[must_use]
+    pub fn replace(mut self, om: OpenMath<'om>) -> Self {
+        self.focus = om;
+        self
+    }
+
+    /// Replaces the focus by the result of `f` on it
+    #[inline]
+    #[must_use]
+    pub fn modify(mut self, f: impl FnOnce(OpenMath<'om>) -> OpenMath<'om>) -> Self {
+        self.focus = f(self.focus);
+        self
+    }
+
+    /// Moves to the immediate subterm at `step`
+    ///
+    /// # Errors
+    /// if there is no subterm at `step`, returning the zipper unchanged
+    pub fn down(mut self, step: Step) -> Result<Self, Self> {
+        let Some(child) = self.focus.child_mut(step) else {
+            return Err(self);
+        };
+        let child = std::mem::replace(child, hole());
+        let parent = std::mem::replace(&mut self.focus, child);
+        self.ancestors.push((parent, step));
+        Ok(self)
+    }
+
+    /// Moves to the parent of the focus
+    ///
+    /// # Errors
+    /// if the focus is the root, returning the zipper unchanged
+    #[allow(clippy::missing_panics_doc)]
+    pub fn up(mut self) -> Result<Self, Self> {
+        let Some((parent, step)) = self.ancestors.pop() else {
+            return Err(self);
+        };
+        let child = std::mem::replace(&mut self.focus, parent);
+        *self
+            .focus
+            .child_mut(step)
+            .expect("the child was taken out of this position") = child;
+        Ok(self)
+    }
+
+    /// Moves to the subterm at `path`, relative to the focus
+    ///
+    /// # Errors
+    /// if there is no subterm at `path`, returning the zipper unchanged
+    pub fn down_path(self, path: &Path) -> Result<Self, Self> {
+        let mut z = self;
+        for (i, step) in path.0.iter().enumerate() {
+            z = match z.down(*step) {
+                Ok(z) => z,
+                Err(mut z) => {
+                    for _ in 0..i {
+                        z = z.up().unwrap_or_else(|_| unreachable!("moved down before"));
+                    }
+                    return Err(z);
+                }
+            };
+        }
+        Ok(z)
+    }
+
+    /// Puts the object back together and returns it
+    #[must_use]
+    pub fn root(mut self) -> OpenMath<'om> {
+        loop {
+            match self.up() {
+                Ok(z) => self = z,
+                Err(z) => return z.focus,
+            }
+        }
+    }
+}
+
+/// What a subterm is replaced by while the [`OwnedZipper`] is below it; does not allocate.
+const fn hole() -> OpenMath<'static> {
+    OpenMath::OMI {
+        int: Int(crate::int::I::Stack(0)),
+        attributes: Vec::new(),
+    }
+}
+
+macro_rules! moves {
+    ($($(#[$meta:meta])* $name:ident($($arg:ident),*) => $step:expr;)*) => {
+        impl<'om> Zipper<'_, 'om> {
+            $(
+                $(#[$meta])*
+                #[inline]
+                #[must_use]
+                pub fn $name(self $(, $arg: usize)*) -> Option<Self> {
+                    self.down($step)
+                }
+            )*
+        }
+        #[allow(clippy::result_large_err)]
+        impl<'om> OwnedZipper<'om> {
+            $(
+                $(#[$meta])*
+                ///
+                /// # Errors
+                /// if there is no such subterm, returning the zipper unchanged
+                #[inline]
+                pub fn $name(self $(, $arg: usize)*) -> Result<Self, Self> {
+                    self.down($step)
+                }
+            )*
+        }
+    };
+}
+moves! {
+    /// Moves to the applicant of an [OMA](OpenMath::OMA)
+    down_applicant() => Step::Applicant;
+    /// Moves to the argument with index `i` of an [OMA](OpenMath::OMA) or
+    /// [OME](OpenMath::OME)
+    down_argument(i) => Step::Argument(i);
+    /// Moves to the binder of an [OMBIND](OpenMath::OMBIND)
+    down_binder() => Step::Binder;
+    /// Moves to the body of an [OMBIND](OpenMath::OMBIND)
+    down_body() => Step::Body;
+    /// Moves to the value of the attribution pair with index `i`
+    down_attribute(i) => Step::Attribute(i);
+    /// Moves to the value of the attribution pair with index `j` of the bound variable with
+    /// index `i` of an [OMBIND](OpenMath::OMBIND)
+    down_variable_attribute(i, j) => Step::VariableAttribute(i, j);
+}
+
+impl<'om> OpenMath<'om> {
+    /// A [`Zipper`] focused on this object
+    #[inline]
+    #[must_use]
+    pub const fn zipper(&self) -> Zipper<'_, 'om> {
+        Zipper::new(self)
+    }
+
+    /// An [`OwnedZipper`] focused on this object
+    #[inline]
+    #[must_use]
+    pub const fn into_zipper(self) -> OwnedZipper<'om> {
+        OwnedZipper::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OwnedZipper;
+    use crate::test_util::om as parse;
+    use crate::{OpenMath, Path, Step};
+
+    const TERM: &str = r#"(omattr (((oms meta note) (omstr "n")))
+        (oma (oms arith1 plus)
+          (ombind (oms fns1 lambda) (bvar (omattr (((oms sts type) (oms setname1 N))) x))
+            (oma (oms arith1 times) (omv x) (omi 2)))
+          (ome (oms moreerrors unexpected) (omforeign "…") (omi 3))))"#;
+
+    #[test]
+    fn every_position() {
+        let om = parse(TERM);
+        let positions = [
+            (vec![Step::Attribute(0)], r#"(omstr "n")"#),
+            (vec![Step::Applicant], "(oms arith1 plus)"),
+            (vec![Step::Argument(0), Step::Binder], "(oms fns1 lambda)"),
+            (
+                vec![Step::Argument(0), Step::VariableAttribute(0, 0)],
+                "(oms setname1 N)",
+            ),
+            (
+                vec![Step::Argument(0), Step::Body, Step::Argument(1)],
+                "(omi 2)",
+            ),
+            (vec![Step::Argument(1), Step::Argument(1)], "(omi 3)"),
+        ];
+        for (steps, expected) in positions {
+            let path = Path::from(steps);
+            let z = om.zipper().down_path(&path).expect("exists");
+            assert_eq!(z.focus(), &parse(expected), "{path}");
+            assert_eq!(z.path(), &path);
+            assert_eq!(z.root(), &om);
+            assert_eq!(z.to_owned_zipper().focus(), z.focus());
+
+            // replacing the subterm changes exactly that subterm
+            let new = parse("(omv new)");
+            let replaced = om
+                .clone()
+                .into_zipper()
+                .down_path(&path)
+                .expect("exists")
+                .replace(new.clone())
+                .root();
+            let mut expected = om.clone();
+            *expected.at_mut(&path).expect("exists") = new;
+            assert_eq!(replaced, expected, "{path}");
+        }
+
+        // foreign objects and missing positions are not reachable
+        assert!(
+            om.zipper()
+                .down_argument(1)
+                .and_then(|z| z.down_argument(0))
+                .is_none()
+        );
+        assert!(om.zipper().down_body().is_none());
+        let z = om.clone().into_zipper();
+        let Err(z) = z.down_path(&Path::from(vec![Step::Argument(0), Step::Argument(5)])) else {
+            panic!("there is no such argument")
+        };
+        assert!(z.is_root());
+        assert_eq!(z.root(), om);
+    }
+
+    #[test]
+    fn edit_and_move() {
+        let z = OwnedZipper::new(parse(TERM))
+            .down_argument(0)
+            .and_then(OwnedZipper::down_body)
+            .expect("exists")
+            .modify(|times| OpenMath::OMA {
+                applicant: Box::new(parse("(oms arith1 minus)")),
+                arguments: vec![times],
+                attributes: Vec::new(),
+            });
+        assert_eq!(z.path().to_string(), "/0/body");
+        let mut z = z
+            .up()
+            .and_then(OwnedZipper::up)
+            .and_then(OwnedZipper::down_applicant)
+            .expect("exists");
+        *z.focus_mut() = parse("(oms arith1 minus)");
+        let om = z.root();
+        assert_eq!(
+            om,
+            parse(
+                r#"(omattr (((oms meta note) (omstr "n")))
+                     (oma (oms arith1 minus)
+                       (ombind (oms fns1 lambda) (bvar (omattr (((oms sts type) (oms setname1 N))) x))
+                         (oma (oms arith1 minus) (oma (oms arith1 times) (omv x) (omi 2))))
+                       (ome (oms moreerrors unexpected) (omforeign "…") (omi 3))))"#
+            )
+        );
+    }
+}
@@ -0,0 +1,84 @@
+//! Checks that attribute pairs skipped by an [attribute filter](openmath::de::Options::attr_filter)
+//! are never materialized, by measuring the peak allocation while reading an object with a
+//! 1 MB annotation
+#![cfg(all(feature = "xml-read", feature = "serde"))]
+
+use openmath::{
+    OpenMath,
+    de::{AttrAction, OMFromSerde, OMObject, Options},
+    testing::CountingAlloc,
+};
+use serde::de::DeserializeSeed;
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc::new();
+
+/// The size of the annotation
+const MB: usize = 1 << 20;
+/// The most that reading the object may allocate at once if the annotation is skipped
+const SMALL: usize = 64 << 10;
+
+/// The peak of bytes allocated by `f` in addition to those allocated before
+fn peak<R>(f: impl FnOnce() -> R) -> (usize, R) {
+    let (allocations, r) = GLOBAL.measure(f);
+    (allocations.peak, r)
+}
+
+fn skip_provenance(_: &str, cd: &str, name: &str) -> AttrAction {
+    if (cd, name) == ("meta", "provenance") {
+        AttrAction::Skip
+    } else {
+        AttrAction::Keep
+    }
+}
+
+// a single test, so that no other test allocates concurrently
+#[test]
+fn skipped_attributes_are_not_materialized() {
+    let options = Options::new().with_attr_filter(skip_provenance);
+
+    // XML, read incrementally: the foreign content would be reassembled
+    let entry = "<entry>provenance</entry>";
+    let xml = format!(
+        r#"<OMOBJ><OMATTR><OMATP>
+            <OMS cd="meta" name="provenance"/><OMFOREIGN><log>{}</log></OMFOREIGN>
+            <OMS cd="meta" name="note"/><OMSTR>n</OMSTR>
+        </OMATP><OMV name="x"/></OMATTR></OMOBJ>"#,
+        entry.repeat(MB / entry.len())
+    );
+    let read = |options: Options<'static>| {
+        OMObject::<OpenMath<'static>>::from_openmath_xml_reader_with(xml.as_bytes(), options)
+            .expect("is valid")
+    };
+    let (kept, om) = peak(|| read(Options::new()));
+    assert!(kept > MB, "{kept}");
+    assert_eq!(om.attributes().len(), 2);
+    drop(om);
+    let (skipped, om) = peak(|| read(options));
+    assert!(skipped < SMALL, "{skipped}");
+    assert_eq!(om.attributes().len(), 1);
+    drop(om);
+
+    // JSON, with escapes (so that strings can not be borrowed) and without a cdbase (so that
+    // the attributes are buffered until the end of the OMATTR)
+    let json = format!(
+        r#"{{"kind":"OMATTR","attributes":[
+            [{{"kind":"OMS","cd":"meta","name":"provenance"}},{{"kind":"OMFOREIGN","foreign":"{}"}}],
+            [{{"kind":"OMS","cd":"meta","name":"note"}},{{"kind":"OMSTR","string":"n"}}]
+        ],"object":{{"kind":"OMV","name":"x"}}}}"#,
+        r"entry\n".repeat(MB / 7)
+    );
+    let parse = |options: Options<'static>| {
+        OMFromSerde::<OpenMath>::with_options(options)
+            .deserialize(&mut serde_json::Deserializer::from_str(&json))
+            .expect("is valid")
+            .into_inner()
+    };
+    let (kept, om) = peak(|| parse(Options::new()));
+    assert!(kept > MB / 2, "{kept}");
+    assert_eq!(om.attributes().len(), 2);
+    drop(om);
+    let (skipped, om) = peak(|| parse(options));
+    assert!(skipped < SMALL, "{skipped}");
+    assert_eq!(om.attributes().len(), 1);
+}
@@ -0,0 +1,11 @@
+//! Checks the JSON in `tests/compat`, reconstructing the quirks of other implementations,
+//! against the equivalent XML; see [`openmath::batch::check_corpus`]
+#![cfg(all(feature = "xml", feature = "serde", feature = "serde_json"))]
+
+#[test]
+fn compat() {
+    let report =
+        openmath::batch::check_corpus(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/compat"));
+    assert!(report.is_ok(), "{report}");
+    assert_eq!(report.checked, 16);
+}
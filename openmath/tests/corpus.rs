@@ -0,0 +1,113 @@
+//! Runs the conformance corpus in `tests/corpus`; see [`openmath::batch::check_corpus`]
+#![cfg(feature = "xml")]
+
+#[test]
+fn corpus() {
+    let report =
+        openmath::batch::check_corpus(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/corpus"));
+    assert!(report.is_ok(), "{report}");
+    let json = if cfg!(all(feature = "serde", feature = "serde_json")) {
+        18
+    } else {
+        0
+    };
+    assert_eq!(report.checked, 12 + json);
+}
+
+/// Corpus files that [`openmath::rewrite_verbatim`] can not reproduce byte for byte (or
+/// rejects), and why; all others have to be reproduced exactly.
+const DIVERGENT: [(&str, &str); 5] = [
+    ("cdbase.xml", "redundant OMOBJ cdbase is dropped"),
+    ("float-hex.xml", "hexadecimal floats are not supported"),
+    ("int-hex.xml", "hexadecimal integers are written in decimal"),
+    ("numbers.xml", "`&gt;` is normalized"),
+    ("sharing.xml", "OMRs are resolved"),
+];
+
+#[test]
+fn verbatim() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/corpus");
+    let mut checked = 0;
+    for entry in std::fs::read_dir(dir).expect("exists") {
+        let path = entry.expect("is readable").path();
+        if path.extension().is_none_or(|e| e != "xml") {
+            continue;
+        }
+        let name = path.file_name().and_then(|n| n.to_str()).expect("is UTF-8");
+        let input = std::fs::read_to_string(&path).expect("is readable");
+        let output = openmath::rewrite_verbatim(&input);
+        if let Some((_, why)) = DIVERGENT.iter().find(|(n, _)| *n == name) {
+            assert!(
+                output.as_ref().map_or(true, |o| *o != input),
+                "{name} is reproduced now ({why}); remove it from DIVERGENT"
+            );
+        } else {
+            let output = output.unwrap_or_else(|e| panic!("{name}: {e}"));
+            assert_eq!(output, input, "{name}");
+            checked += 1;
+        }
+    }
+    assert_eq!(checked, 7);
+}
+
+/// `*.array.json` files that are not exactly what [`openmath::ser::OMObject::openmath_serde_compact`]
+/// writes for them, and why; all others are golden vectors for the array form.
+#[cfg(all(feature = "serde", feature = "serde_json"))]
+const NOT_GOLDEN: [(&str, &str); 2] = [
+    (
+        "cdbase.array.json",
+        "the default cdbase of the OMOBJ is written as null",
+    ),
+    (
+        "sin.array.json",
+        "the omitted cdbase of the OMOBJ is written as null",
+    ),
+];
+
+/// Removes the whitespace outside of string literals from JSON text
+#[cfg(all(feature = "serde", feature = "serde_json"))]
+fn minify(json: &str) -> String {
+    let mut out = String::with_capacity(json.len());
+    let (mut in_string, mut escaped) = (false, false);
+    for c in json.chars() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+        } else if c == '"' {
+            in_string = true;
+        } else if c.is_whitespace() {
+            continue;
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(all(feature = "serde", feature = "serde_json"))]
+#[test]
+fn array_golden() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/corpus");
+    let mut checked = 0;
+    for entry in std::fs::read_dir(dir).expect("exists") {
+        let path = entry.expect("is readable").path();
+        let name = path.file_name().and_then(|n| n.to_str()).expect("is UTF-8");
+        if !name.ends_with(".array.json") || NOT_GOLDEN.iter().any(|(n, _)| *n == name) {
+            continue;
+        }
+        let input = std::fs::read_to_string(&path).expect("is readable");
+        let om: openmath::de::OMObject<openmath::OpenMath> =
+            serde_json::from_str(&input).unwrap_or_else(|e| panic!("{name}: {e}"));
+        let output = serde_json::to_string(
+            &openmath::ser::OMObject(&om.into_inner()).openmath_serde_compact(),
+        )
+        .unwrap_or_else(|e| panic!("{name}: {e}"));
+        assert_eq!(output, minify(&input), "{name}");
+        checked += 1;
+    }
+    assert_eq!(checked, 7);
+}
@@ -0,0 +1,28 @@
+//! Generates a C header for the [`openmath::view`] module with cbindgen; kept out of the
+//! library's unit tests, since cbindgen's dependencies make some `as_ref` calls ambiguous
+#![cfg(feature = "ffi")]
+
+#[test]
+fn header() {
+    let mut header = Vec::new();
+    cbindgen::Builder::new()
+        .with_src(concat!(env!("CARGO_MANIFEST_DIR"), "/src/view/mod.rs"))
+        .with_src(concat!(env!("CARGO_MANIFEST_DIR"), "/src/view/ffi.rs"))
+        .with_language(cbindgen::Language::C)
+        .with_include_guard("OPENMATH_VIEW_H")
+        .generate()
+        .expect("header can be generated")
+        .write(&mut header);
+    let header = String::from_utf8(header).expect("is UTF-8");
+    for item in [
+        "#define OPENMATH_VIEW_NONE",
+        "typedef struct OMSlice {",
+        "typedef struct OMViewNode {",
+        "typedef struct OMViewAttr {",
+        "typedef struct OMViewRaw {",
+        "const struct OMViewNode *openmath_view_node(const struct OMViewRaw *view, uint32_t index);",
+        "uint32_t openmath_view_child(const struct OMViewRaw *view, uint32_t node, uint32_t i);",
+    ] {
+        assert!(header.contains(item), "missing `{item}` in\n{header}");
+    }
+}
@@ -0,0 +1,37 @@
+//! Reads the documents in `tests/om1`, written in the style of version 1.0 of the standard,
+//! and compares them with their version 2.0 counterparts
+#![cfg(feature = "xml-read")]
+
+use openmath::{
+    Document, OpenMath,
+    de::{Options, VersionPolicy},
+};
+
+fn fixture(name: &str) -> String {
+    let path = format!("{}/tests/om1/{name}", env!("CARGO_MANIFEST_DIR"));
+    std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{path}: {e}"))
+}
+
+/// Reads `name.xml` and `name.om2.xml`, which have to hold the same object
+fn check(name: &str) {
+    let om1 = fixture(&format!("{name}.xml"));
+    let om2 = fixture(&format!("{name}.om2.xml"));
+    // OpenMath 1 documents have no version, which the strict policy accepts
+    let options = Options::new().with_version_policy(VersionPolicy::Strict);
+    let before = Document::<OpenMath>::parse_xml_with(&om1, options).expect("is valid");
+    let after = Document::<OpenMath>::parse_xml_with(&om2, options).expect("is valid");
+    assert_eq!(before.object, after.object, "{name}");
+    assert_eq!(before.metadata.version, None, "{name}");
+    assert_eq!(after.metadata.version.as_deref(), Some("2.0"), "{name}");
+    assert!(before.metadata.warnings.is_empty(), "{name}");
+}
+
+#[test]
+fn lambda() {
+    check("lambda");
+}
+
+#[test]
+fn equation() {
+    check("equation");
+}
@@ -0,0 +1,124 @@
+//! Runs the `om-tool` binary on the conformance corpus in `tests/corpus`
+#![cfg(feature = "cli")]
+
+use std::path::PathBuf;
+
+use assert_cmd::{Command, cargo::cargo_bin_cmd};
+use openmath::batch::Expectation;
+
+fn om_tool() -> Command {
+    cargo_bin_cmd!("om-tool")
+}
+
+/// The corpus files, with whether they are valid
+fn corpus() -> Vec<(PathBuf, bool)> {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/corpus");
+    let mut files = std::fs::read_dir(dir)
+        .expect("exists")
+        .map(|e| e.expect("is readable").path())
+        .filter(|p| p.extension().is_some_and(|e| e == "xml" || e == "json"))
+        .map(|p| {
+            let input = std::fs::read_to_string(&p).expect("is readable");
+            let valid = Expectation::of(&p, &input).expect("is valid") == Expectation::Valid;
+            (p, valid)
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
+
+fn stdout(cmd: &mut Command) -> String {
+    String::from_utf8(cmd.assert().success().get_output().stdout.clone()).expect("is UTF-8")
+}
+
+/// The stdout and stderr of `cmd`, which has to fail with exit code 1
+fn failure(cmd: &mut Command) -> (String, String) {
+    let output = cmd.assert().code(1).get_output().clone();
+    (
+        String::from_utf8(output.stdout).expect("is UTF-8"),
+        String::from_utf8(output.stderr).expect("is UTF-8"),
+    )
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn validate() {
+    let files = corpus();
+    assert_eq!(files.len(), 30);
+    for (path, valid) in &files {
+        let assert = om_tool().arg("validate").arg(path).assert();
+        if *valid {
+            assert.success().stdout("");
+        } else {
+            assert.code(1);
+        }
+    }
+    // all at once, from stdin, and with lints
+    om_tool()
+        .arg("validate")
+        .args(files.iter().map(|(p, _)| p))
+        .assert()
+        .code(1);
+    let xml = r#"<OMOBJ><OMBIND><OMS cd="fns1" name="lambda"/><OMBVAR><OMV name="x"/><OMV name="x"/></OMBVAR><OMV name="x"/></OMBIND></OMOBJ>"#;
+    om_tool()
+        .arg("validate")
+        .write_stdin(xml)
+        .assert()
+        .success()
+        .stdout("");
+    let (out, _) = failure(om_tool().args(["validate", "--lint"]).write_stdin(xml));
+    assert!(
+        out.starts_with("<stdin>: [duplicate-bound-variable]"),
+        "{out}"
+    );
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn convert_roundtrips() {
+    for (path, _) in corpus().into_iter().filter(|(_, valid)| *valid) {
+        let canonical = stdout(
+            om_tool()
+                .args(["fmt", "--compact", "--to", "xml"])
+                .arg(&path),
+        );
+        let json = stdout(om_tool().args(["convert", "--to", "json"]).arg(&path));
+        assert!(json.starts_with(r#"{"kind":"OMOBJ""#), "{json}");
+        // the format of stdin is guessed
+        let xml = stdout(om_tool().arg("convert").write_stdin(json));
+        assert_eq!(xml, canonical, "{}", path.display());
+    }
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn fmt_and_stats() {
+    let sin = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/corpus/sin.json");
+    assert_eq!(
+        stdout(om_tool().args(["fmt", "--to", "xml", sin])),
+        "<OMOBJ version=\"2.0\" xmlns=\"http://www.openmath.org/OpenMath\">\n  <OMA>\n    <OMS cd=\"transc1\" name=\"sin\"/>\n    <OMV name=\"x\"/>\n  </OMA>\n</OMOBJ>\n"
+    );
+    let stats: openmath::Analysis =
+        serde_json::from_str(&stdout(om_tool().args(["stats", sin]))).expect("is an analysis");
+    assert_eq!(stats.symbols["http://www.openmath.org/cd?transc1#sin"], 1);
+    assert_eq!(stats.max_depth, 2);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn errors() {
+    om_tool().assert().code(2);
+    om_tool().args(["convert", "--to", "yaml"]).assert().code(2);
+    assert!(stdout(om_tool().arg("help")).starts_with("Usage: om-tool"));
+    let (out, err) = failure(
+        om_tool()
+            .args(["stats", "--from", "xml"])
+            .write_stdin("<OMOBJ><OMI>one</OMI></OMOBJ>"),
+    );
+    assert_eq!(out, "");
+    assert!(err.starts_with("<stdin>: [xml]"), "{err}");
+    om_tool()
+        .args(["fmt", "does-not-exist.xml"])
+        .assert()
+        .code(1);
+}